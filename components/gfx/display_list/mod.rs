@@ -931,10 +931,12 @@ impl DisplayItemMetadata {
                -> DisplayItemMetadata {
         DisplayItemMetadata {
             node: node,
-            pointing: match (style.get_pointing().pointer_events, style.get_pointing().cursor) {
+            // FIXME: The cursor's images aren't drawn, only the keyword they fall back to.
+            pointing: match (style.get_pointing().pointer_events, style.get_pointing().cursor.keyword) {
                 (pointer_events::T::none, _) => None,
-                (pointer_events::T::auto, cursor::T::AutoCursor) => Some(default_cursor),
-                (pointer_events::T::auto, cursor::T::SpecifiedCursor(cursor)) => Some(cursor),
+                // The other values only tell SVG elements apart.
+                (_, cursor::Keyword::AutoCursor) => Some(default_cursor),
+                (_, cursor::Keyword::SpecifiedCursor(cursor)) => Some(cursor),
             },
         }
     }
@@ -27,6 +27,8 @@ use std::sync::{Arc, Mutex, RwLock};
 use style::context::{LocalStyleContext, StyleContext};
 use style::matching::{ApplicableDeclarationsCache, StyleSharingCandidateCache};
 use style::properties::ServoComputedValues;
+use style::rule_tree::RuleNodeStyleCache;
+use style::scratch::ScratchArena;
 use style::selector_impl::ServoSelectorImpl;
 use style::servo::SharedStyleContext;
 use url::Url;
@@ -60,6 +62,7 @@ fn create_or_get_local_context(shared_layout_context: &SharedLayoutContext)
         if let Some(context) = r.clone() {
             if shared_layout_context.style_context.screen_size_changed {
                 context.style_context.applicable_declarations_cache.borrow_mut().evict_all();
+                context.style_context.rule_node_style_cache.borrow_mut().evict_all();
             }
             context
         } else {
@@ -68,6 +71,8 @@ fn create_or_get_local_context(shared_layout_context: &SharedLayoutContext)
                 style_context: LocalStyleContext {
                     applicable_declarations_cache: RefCell::new(ApplicableDeclarationsCache::new()),
                     style_sharing_candidate_cache: RefCell::new(StyleSharingCandidateCache::new()),
+                    rule_node_style_cache: RefCell::new(RuleNodeStyleCache::new()),
+                    scratch: RefCell::new(ScratchArena::new()),
                 },
                 font_context: RefCell::new(FontContext::new(font_cache_thread)),
             });
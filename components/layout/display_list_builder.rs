@@ -45,11 +45,11 @@ use std::sync::Arc;
 use std::{cmp, f32};
 use style::computed_values::filter::Filter;
 use style::computed_values::{_servo_overflow_clip_box as overflow_clip_box};
-use style::computed_values::{background_attachment, background_clip, background_origin};
-use style::computed_values::{background_repeat, background_size};
+use style::computed_values::{background_attachment, background_clip, background_origin, background_repeat};
 use style::computed_values::{border_style, image_rendering, overflow_x, position};
 use style::computed_values::{transform, transform_style, visibility};
 use style::logical_geometry::{LogicalPoint, LogicalRect, LogicalSize, WritingMode};
+use style::properties::longhands::background_size::single_value::computed_value::{ExplicitSize, T as BackgroundSize};
 use style::properties::style_structs::Border;
 use style::properties::{self, ComputedValues, ServoComputedValues};
 use style::values::RGBA;
@@ -131,19 +131,21 @@ pub trait FragmentDisplayListBuilding {
                                                        absolute_bounds: &Rect<Au>,
                                                        clip: &ClippingRegion);
 
-    /// Computes the background size for an image with the given background area according to the
-    /// rules in CSS-BACKGROUNDS § 3.9.
+    /// Computes the background size for an image of the background layer at `index` with the
+    /// given background area according to the rules in CSS-BACKGROUNDS § 3.9.
     fn compute_background_image_size(&self,
                                      style: &ServoComputedValues,
+                                     index: usize,
                                      bounds: &Rect<Au>,
                                      image: &WebRenderImageInfo)
                                      -> Size2D<Au>;
 
-    /// Adds the display items necessary to paint the background image of this fragment to the
-    /// appropriate section of the display list.
+    /// Adds the display items necessary to paint the image of the background layer at `index` of
+    /// this fragment to the appropriate section of the display list.
     fn build_display_list_for_background_image(&self,
                                                state: &mut DisplayListBuildState,
                                                style: &ServoComputedValues,
+                                               index: usize,
                                                display_list_section: DisplayListSection,
                                                absolute_bounds: &Rect<Au>,
                                                clip: &ClippingRegion,
@@ -342,29 +344,35 @@ impl FragmentDisplayListBuilding for Fragment {
         // needed. We could use display list optimization to clean this up, but it still seems
         // inefficient. What we really want is something like "nearest ancestor element that
         // doesn't have a fragment".
-        let background_color = style.resolve_color(style.get_background().background_color);
+        let background = style.get_background();
+        let background_color = style.resolve_color(background.background_color);
 
-        // 'background-clip' determines the area within which the background is painted.
+        // 'background-clip' determines the area within which each layer is painted, and the color
+        // goes with the bottom one.
         // http://dev.w3.org/csswg/css-backgrounds-3/#the-background-clip
-        let mut bounds = *absolute_bounds;
-
-        match style.get_background().background_clip {
-            background_clip::T::border_box => {}
-            background_clip::T::padding_box => {
-                let border = style.logical_border_width().to_physical(style.writing_mode);
-                bounds.origin.x = bounds.origin.x + border.left;
-                bounds.origin.y = bounds.origin.y + border.top;
-                bounds.size.width = bounds.size.width - border.horizontal();
-                bounds.size.height = bounds.size.height - border.vertical();
-            }
-            background_clip::T::content_box => {
-                let border_padding = self.border_padding.to_physical(style.writing_mode);
-                bounds.origin.x = bounds.origin.x + border_padding.left;
-                bounds.origin.y = bounds.origin.y + border_padding.top;
-                bounds.size.width = bounds.size.width - border_padding.horizontal();
-                bounds.size.height = bounds.size.height - border_padding.vertical();
+        let clip_bounds = |index: usize| {
+            let mut bounds = *absolute_bounds;
+            match *background.background_clip.layer(index) {
+                background_clip::SingleComputedValue::border_box => {}
+                background_clip::SingleComputedValue::padding_box => {
+                    let border = style.logical_border_width().to_physical(style.writing_mode);
+                    bounds.origin.x = bounds.origin.x + border.left;
+                    bounds.origin.y = bounds.origin.y + border.top;
+                    bounds.size.width = bounds.size.width - border.horizontal();
+                    bounds.size.height = bounds.size.height - border.vertical();
+                }
+                background_clip::SingleComputedValue::content_box => {
+                    let border_padding = self.border_padding.to_physical(style.writing_mode);
+                    bounds.origin.x = bounds.origin.x + border_padding.left;
+                    bounds.origin.y = bounds.origin.y + border_padding.top;
+                    bounds.size.width = bounds.size.width - border_padding.horizontal();
+                    bounds.size.height = bounds.size.height - border_padding.vertical();
+                }
             }
-        }
+            bounds
+        };
+        let bottom_layer = background.background_image.0.len() - 1;
+        let bounds = clip_bounds(bottom_layer);
 
         state.add_display_item(
             DisplayItem::SolidColorClass(box SolidColorDisplayItem {
@@ -379,30 +387,38 @@ impl FragmentDisplayListBuilding for Fragment {
         // The background image is painted on top of the background color.
         // Implements background image, per spec:
         // http://www.w3.org/TR/CSS21/colors.html#background
-        let background = style.get_background();
-        match background.background_image.0 {
-            None => {}
-            Some(computed::Image::LinearGradient(ref gradient)) => {
-                self.build_display_list_for_background_linear_gradient(state,
-                                                                       display_list_section,
-                                                                       &bounds,
-                                                                       &clip,
-                                                                       gradient,
-                                                                       style);
-            }
-            Some(computed::Image::Url(ref image_url)) => {
-                self.build_display_list_for_background_image(state,
-                                                             style,
-                                                             display_list_section,
-                                                             &bounds,
-                                                             &clip,
-                                                             image_url);
+        //
+        // The first layer is the top one, so the layers are painted last to first.
+        for (index, image) in background.background_image.0.iter().enumerate().rev() {
+            let bounds = clip_bounds(index);
+            match *image {
+                None => {}
+                Some(computed::Image::LinearGradient(ref gradient)) => {
+                    self.build_display_list_for_background_linear_gradient(state,
+                                                                           display_list_section,
+                                                                           &bounds,
+                                                                           &clip,
+                                                                           gradient,
+                                                                           style);
+                }
+                // FIXME: Paint radial gradients.
+                Some(computed::Image::RadialGradient(_)) => {}
+                Some(computed::Image::Url(ref image_url)) => {
+                    self.build_display_list_for_background_image(state,
+                                                                 style,
+                                                                 index,
+                                                                 display_list_section,
+                                                                 &bounds,
+                                                                 &clip,
+                                                                 image_url.url());
+                }
             }
         }
     }
 
     fn compute_background_image_size(&self,
                                      style: &ServoComputedValues,
+                                     index: usize,
                                      bounds: &Rect<Au>,
                                      image: &WebRenderImageInfo)
                                      -> Size2D<Au> {
@@ -412,19 +428,19 @@ impl FragmentDisplayListBuilding for Fragment {
         let bounds_aspect_ratio = bounds.size.width.to_f64_px() / bounds.size.height.to_f64_px();
         let intrinsic_size = Size2D::new(Au::from_px(image.width as i32),
                                          Au::from_px(image.height as i32));
-        match (style.get_background().background_size.clone(),
+        match (style.get_background().background_size.layer(index).clone(),
                image_aspect_ratio < bounds_aspect_ratio) {
-            (background_size::T::Contain, false) | (background_size::T::Cover, true) => {
+            (BackgroundSize::Contain, false) | (BackgroundSize::Cover, true) => {
                 Size2D::new(bounds.size.width,
                             Au::from_f64_px(bounds.size.width.to_f64_px() / image_aspect_ratio))
             }
 
-            (background_size::T::Contain, true) | (background_size::T::Cover, false) => {
+            (BackgroundSize::Contain, true) | (BackgroundSize::Cover, false) => {
                 Size2D::new(Au::from_f64_px(bounds.size.height.to_f64_px() * image_aspect_ratio),
                             bounds.size.height)
             }
 
-            (background_size::T::Explicit(background_size::ExplicitSize {
+            (BackgroundSize::Explicit(ExplicitSize {
                 width,
                 height: LengthOrPercentageOrAuto::Auto,
             }), _) => {
@@ -433,7 +449,7 @@ impl FragmentDisplayListBuilding for Fragment {
                 Size2D::new(width, Au::from_f64_px(width.to_f64_px() / image_aspect_ratio))
             }
 
-            (background_size::T::Explicit(background_size::ExplicitSize {
+            (BackgroundSize::Explicit(ExplicitSize {
                 width: LengthOrPercentageOrAuto::Auto,
                 height
             }), _) => {
@@ -442,7 +458,7 @@ impl FragmentDisplayListBuilding for Fragment {
                 Size2D::new(Au::from_f64_px(height.to_f64_px() * image_aspect_ratio), height)
             }
 
-            (background_size::T::Explicit(background_size::ExplicitSize {
+            (BackgroundSize::Explicit(ExplicitSize {
                 width,
                 height
             }), _) => {
@@ -457,6 +473,7 @@ impl FragmentDisplayListBuilding for Fragment {
     fn build_display_list_for_background_image(&self,
                                                state: &mut DisplayListBuildState,
                                                style: &ServoComputedValues,
+                                               index: usize,
                                                display_list_section: DisplayListSection,
                                                absolute_bounds: &Rect<Au>,
                                                clip: &ClippingRegion,
@@ -472,7 +489,7 @@ impl FragmentDisplayListBuilding for Fragment {
 
             // Use `background-size` to get the size.
             let mut bounds = *absolute_bounds;
-            let image_size = self.compute_background_image_size(style, &bounds, &webrender_image);
+            let image_size = self.compute_background_image_size(style, index, &bounds, &webrender_image);
 
             // Clip.
             //
@@ -484,25 +501,25 @@ impl FragmentDisplayListBuilding for Fragment {
             let border = style.logical_border_width().to_physical(style.writing_mode);
 
             // Use 'background-origin' to get the origin value.
-            let (mut origin_x, mut origin_y) = match background.background_origin {
-                background_origin::T::padding_box => {
+            let (mut origin_x, mut origin_y) = match *background.background_origin.layer(index) {
+                background_origin::SingleComputedValue::padding_box => {
                     (Au(0), Au(0))
                 }
-                background_origin::T::border_box => {
+                background_origin::SingleComputedValue::border_box => {
                     (-border.left, -border.top)
                 }
-                background_origin::T::content_box => {
+                background_origin::SingleComputedValue::content_box => {
                     let border_padding = self.border_padding.to_physical(self.style.writing_mode);
                     (border_padding.left - border.left, border_padding.top - border.top)
                 }
             };
 
             // Use `background-attachment` to get the initial virtual origin
-            let (virtual_origin_x, virtual_origin_y) = match background.background_attachment {
-                background_attachment::T::scroll => {
+            let (virtual_origin_x, virtual_origin_y) = match *background.background_attachment.layer(index) {
+                background_attachment::SingleComputedValue::scroll => {
                     (absolute_bounds.origin.x, absolute_bounds.origin.y)
                 }
-                background_attachment::T::fixed => {
+                background_attachment::SingleComputedValue::fixed => {
                     // If the ‘background-attachment’ value for this image is ‘fixed’, then
                     // 'background-origin' has no effect.
                     origin_x = Au(0);
@@ -512,23 +529,24 @@ impl FragmentDisplayListBuilding for Fragment {
             };
 
             // Use `background-position` to get the offset.
-            let horizontal_position = model::specified(background.background_position.horizontal,
+            let position = background.background_position.layer(index);
+            let horizontal_position = model::specified(position.horizontal,
                                                        bounds.size.width - image_size.width);
-            let vertical_position = model::specified(background.background_position.vertical,
+            let vertical_position = model::specified(position.vertical,
                                                      bounds.size.height - image_size.height);
 
             let abs_x = border.left + virtual_origin_x + horizontal_position + origin_x;
             let abs_y = border.top + virtual_origin_y + vertical_position + origin_y;
 
             // Adjust origin and size based on background-repeat
-            match background.background_repeat {
-                background_repeat::T::no_repeat => {
+            match *background.background_repeat.layer(index) {
+                background_repeat::SingleComputedValue::no_repeat => {
                     bounds.origin.x = abs_x;
                     bounds.origin.y = abs_y;
                     bounds.size.width = image_size.width;
                     bounds.size.height = image_size.height;
                 }
-                background_repeat::T::repeat_x => {
+                background_repeat::SingleComputedValue::repeat_x => {
                     bounds.origin.y = abs_y;
                     bounds.size.height = image_size.height;
                     ImageFragmentInfo::tile_image(&mut bounds.origin.x,
@@ -536,7 +554,7 @@ impl FragmentDisplayListBuilding for Fragment {
                                                   abs_x,
                                                   image_size.width.to_nearest_px() as u32);
                 }
-                background_repeat::T::repeat_y => {
+                background_repeat::SingleComputedValue::repeat_y => {
                     bounds.origin.x = abs_x;
                     bounds.size.width = image_size.width;
                     ImageFragmentInfo::tile_image(&mut bounds.origin.y,
@@ -544,7 +562,7 @@ impl FragmentDisplayListBuilding for Fragment {
                                                   abs_y,
                                                   image_size.height.to_nearest_px() as u32);
                 }
-                background_repeat::T::repeat => {
+                background_repeat::SingleComputedValue::repeat => {
                     ImageFragmentInfo::tile_image(&mut bounds.origin.x,
                                                   &mut bounds.size.width,
                                                   abs_x,
@@ -615,64 +633,13 @@ impl FragmentDisplayListBuilding for Fragment {
         let length = Au::from_f32_px(
             (delta.x.to_f32_px() * 2.0).hypot(delta.y.to_f32_px() * 2.0));
 
-        // Determine the position of each stop per CSS-IMAGES § 3.4.
-        //
-        // FIXME(#3908, pcwalton): Make sure later stops can't be behind earlier stops.
-        let (mut stops, mut stop_run) = (Vec::new(), None);
-        for (i, stop) in gradient.stops.iter().enumerate() {
-            let offset = match stop.position {
-                None => {
-                    if stop_run.is_none() {
-                        // Initialize a new stop run.
-                        let start_offset = if i == 0 {
-                            0.0
-                        } else {
-                            // `unwrap()` here should never fail because this is the beginning of
-                            // a stop run, which is always bounded by a length or percentage.
-                            position_to_offset(gradient.stops[i - 1].position.unwrap(), length)
-                        };
-                        let (end_index, end_offset) =
-                            match gradient.stops[i..]
-                                          .iter()
-                                          .enumerate()
-                                          .find(|&(_, ref stop)| stop.position.is_some()) {
-                                None => (gradient.stops.len() - 1, 1.0),
-                                Some((end_index, end_stop)) => {
-                                    // `unwrap()` here should never fail because this is the end of
-                                    // a stop run, which is always bounded by a length or
-                                    // percentage.
-                                    (end_index,
-                                     position_to_offset(end_stop.position.unwrap(), length))
-                                }
-                            };
-                        stop_run = Some(StopRun {
-                            start_offset: start_offset,
-                            end_offset: end_offset,
-                            start_index: i,
-                            stop_count: end_index - i,
-                        })
-                    }
-
-                    let stop_run = stop_run.unwrap();
-                    let stop_run_length = stop_run.end_offset - stop_run.start_offset;
-                    if stop_run.stop_count == 0 {
-                        stop_run.end_offset
-                    } else {
-                        stop_run.start_offset +
-                            stop_run_length * (i - stop_run.start_index) as f32 /
-                                (stop_run.stop_count as f32)
-                    }
-                }
-                Some(position) => {
-                    stop_run = None;
-                    position_to_offset(position, length)
-                }
-            };
-            stops.push(GradientStop {
-                offset: offset,
+        // The stops' positions were filled in per CSS-IMAGES § 3.4 when they were computed.
+        let stops = gradient.stops.iter().map(|stop| {
+            GradientStop {
+                offset: position_to_offset(stop.position, length),
                 color: style.resolve_color(stop.color).to_gfx_color()
-            })
-        }
+            }
+        }).collect();
 
         let center = Point2D::new(absolute_bounds.origin.x + absolute_bounds.size.width / 2,
                                   absolute_bounds.origin.y + absolute_bounds.size.height / 2);
@@ -1122,7 +1089,7 @@ impl FragmentDisplayListBuilding for Fragment {
                     self.style().get_color().color
                 };
 
-                for text_shadow in self.style.get_effects().text_shadow.0.iter().rev() {
+                for text_shadow in self.style.get_inheritedtext().text_shadow.0.iter().rev() {
                     let offset = &Point2D::new(text_shadow.offset_x, text_shadow.offset_y);
                     let color = self.style().resolve_color(text_shadow.color);
                     self.build_display_list_for_text_fragment(state,
@@ -1951,15 +1918,6 @@ impl BaseFlowDisplayListBuilding for BaseFlow {
     }
 }
 
-// A helper data structure for gradients.
-#[derive(Copy, Clone)]
-struct StopRun {
-    start_offset: f32,
-    end_offset: f32,
-    start_index: usize,
-    stop_count: usize,
-}
-
 fn position_to_offset(position: LengthOrPercentage, Au(total_length): Au) -> f32 {
     match position {
         LengthOrPercentage::Length(Au(length)) => {
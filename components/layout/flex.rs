@@ -93,7 +93,7 @@ impl FlexFlow {
     // Currently, this is the core of BlockFlow::bubble_inline_sizes() with all float logic
     // stripped out, and max replaced with union_nonbreaking_inline.
     fn inline_mode_bubble_inline_sizes(&mut self) {
-        let fixed_width = match self.block_flow.fragment.style().get_box().width {
+        let fixed_width = match self.block_flow.fragment.style().get_box().width.to_length_or_percentage_or_auto() {
             LengthOrPercentageOrAuto::Length(_) => true,
             _ => false,
         };
@@ -115,7 +115,7 @@ impl FlexFlow {
     // Currently, this is the core of BlockFlow::bubble_inline_sizes() with all float logic
     // stripped out.
     fn block_mode_bubble_inline_sizes(&mut self) {
-        let fixed_width = match self.block_flow.fragment.style().get_box().width {
+        let fixed_width = match self.block_flow.fragment.style().get_box().width.to_length_or_percentage_or_auto() {
             LengthOrPercentageOrAuto::Length(_) => true,
             _ => false,
         };
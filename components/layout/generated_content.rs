@@ -181,9 +181,13 @@ impl<'a,'b> ResolveGeneratedContentFragmentMutator<'a,'b> {
                                                                RenderingMode::Suffix(".\u{00a0}"))
                 }
                 GeneratedContentInfo::Empty |
-                GeneratedContentInfo::ContentItem(ContentItem::String(_)) => {
+                GeneratedContentInfo::ContentItem(ContentItem::String(_)) |
+                GeneratedContentInfo::ContentItem(ContentItem::Attr(_)) => {
                     // Nothing to do here.
                 }
+                GeneratedContentInfo::ContentItem(ContentItem::Url(_)) => {
+                    // FIXME: Render images in generated content.
+                }
                 GeneratedContentInfo::ContentItem(ContentItem::Counter(ref counter_name,
                                                                        counter_style)) => {
                     let temporary_counter = Counter::new();
@@ -187,10 +187,12 @@ pub fn compute_damage(old: Option<&Arc<ServoComputedValues>>, new: &ServoCompute
         get_inheritedtext.overflow_wrap, get_inheritedtext.text_justify,
         get_inheritedtext.white_space, get_inheritedtext.word_break, get_inheritedtext.text_overflow,
         get_font.font_family, get_font.font_style, get_font.font_variant, get_font.font_weight,
-        get_font.font_size, get_font.font_stretch,
+        get_font.font_size, get_font.font_stretch, get_font.font_size_adjust,
+        get_font.font_feature_settings, get_font.font_variation_settings,
+        get_font.font_language_override, get_font._x_lang,
         get_inheritedbox.direction, get_inheritedbox.writing_mode,
         get_inheritedbox.text_orientation,
-        get_text.text_decoration, get_text.unicode_bidi,
+        get_text.text_decoration_line, get_text.unicode_bidi,
         get_inheritedtable.empty_cells, get_inheritedtable.caption_side,
         get_column.column_width, get_column.column_count
     ]) || add_if_not_equal!(old, new, damage,
@@ -204,6 +206,8 @@ pub fn compute_damage(old: Option<&Arc<ServoComputedValues>>, new: &ServoCompute
         get_box.width, get_box.height,
         get_inheritedbox.line_height,
         get_inheritedtext.text_align, get_inheritedtext.text_indent,
+        // Emphasis marks take up room in the line.
+        get_inheritedtext.text_emphasis_style, get_inheritedtext.text_emphasis_position,
         get_table.table_layout,
         get_inheritedtable.border_collapse,
         get_inheritedtable.border_spacing,
@@ -226,10 +230,14 @@ pub fn compute_damage(old: Option<&Arc<ServoComputedValues>>, new: &ServoCompute
         get_border.border_bottom_style, get_border.border_left_style,
         get_border.border_top_left_radius, get_border.border_top_right_radius,
         get_border.border_bottom_left_radius, get_border.border_bottom_right_radius,
+        get_border.border_image_source, get_border.border_image_slice, get_border.border_image_width,
+        get_border.border_image_outset, get_border.border_image_repeat,
         get_box.z_index, get_box._servo_overflow_clip_box,
-        get_inheritedtext._servo_text_decorations_in_effect,
+        get_inheritedtext._servo_text_decorations_in_effect, get_inheritedtext.text_shadow,
+        get_inheritedtext.text_emphasis_color,
+        get_text.text_decoration_style, get_text.text_decoration_color,
         get_pointing.cursor, get_pointing.pointer_events,
-        get_effects.box_shadow, get_effects.clip, get_effects.text_shadow, get_effects.filter,
+        get_effects.box_shadow, get_effects.clip, get_effects.filter,
         get_effects.transform, get_effects.backface_visibility, get_effects.transform_style,
         get_effects.transform_origin, get_effects.perspective, get_effects.perspective_origin,
         get_effects.mix_blend_mode, get_effects.image_rendering,
@@ -668,16 +668,20 @@ impl LineBreaker {
         }
 
         // Determine if an ellipsis will be necessary to account for `text-overflow`.
-        let mut need_ellipsis = false;
+        //
+        // FIXME: A `<string>` is drawn as an ellipsis too.
         let available_inline_size = self.pending_line.green_zone.inline -
             self.pending_line.bounds.size.inline - indentation;
-        match (fragment.style().get_inheritedtext().text_overflow,
-               fragment.style().get_box().overflow_x) {
-            (text_overflow::T::clip, _) | (_, overflow_x::T::visible) => {}
-            (text_overflow::T::ellipsis, _) => {
-                need_ellipsis = fragment.margin_box_inline_size() > available_inline_size;
+        let need_ellipsis = {
+            let style = fragment.style();
+            let rtl = !style.writing_mode.is_bidi_ltr();
+            match (style.get_inheritedtext().text_overflow.line_end(rtl), style.get_box().overflow_x) {
+                (&text_overflow::Side::Clip, _) | (_, overflow_x::T::visible) => false,
+                (&text_overflow::Side::Ellipsis, _) | (&text_overflow::Side::String(_), _) => {
+                    fragment.margin_box_inline_size() > available_inline_size
+                }
             }
-        }
+        };
 
         if !need_ellipsis {
             self.push_fragment_to_line_ignoring_text_overflow(fragment, layout_context);
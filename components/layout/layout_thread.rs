@@ -76,6 +76,7 @@ use style::properties::ComputedValues;
 use style::selector_impl::ServoSelectorImpl;
 use style::selector_matching::USER_OR_USER_AGENT_STYLESHEETS;
 use style::servo::{SharedStyleContext, Stylesheet, Stylist};
+use style::shared_lock::SharedRwLock;
 use style::stylesheets::CSSRuleIteratorExt;
 use traversal::RecalcStyleAndConstructFlows;
 use url::Url;
@@ -232,6 +233,10 @@ pub struct LayoutThread {
     /// The CSS error reporter for all CSS loaded in this layout thread
     error_reporter: CSSErrorReporter,
 
+    /// The lock the style traversal holds for reading. Style attributes live in the DOM
+    /// here, so nothing is behind it yet.
+    shared_lock: SharedRwLock,
+
     webrender_image_cache: Arc<RwLock<HashMap<(Url, UsePlaceholder),
                                               WebRenderImageInfo,
                                               BuildHasherDefault<FnvHasher>>>>,
@@ -473,6 +478,7 @@ impl LayoutThread {
                   pipelineid: id,
                   script_chan: Arc::new(Mutex::new(script_chan)),
               },
+              shared_lock: SharedRwLock::new(),
               webrender_image_cache:
                   Arc::new(RwLock::new(HashMap::with_hasher(Default::default()))),
         }
@@ -504,11 +510,14 @@ impl LayoutThread {
                 screen_size_changed: screen_size_changed,
                 stylist: StylistWrapper::<ServoSelectorImpl>(&*rw_data.stylist),
                 generation: self.generation,
+                style_generation: 0,
                 goal: goal,
                 new_animations_sender: Mutex::new(self.new_animations_sender.clone()),
                 running_animations: self.running_animations.clone(),
                 expired_animations: self.expired_animations.clone(),
                 error_reporter: self.error_reporter.clone(),
+                rule_tree: None,
+                guard: self.shared_lock.read(),
             },
             image_cache_thread: self.image_cache_thread.clone(),
             image_cache_sender: Mutex::new(self.image_cache_sender.clone()),
@@ -1073,7 +1082,7 @@ impl LayoutThread {
                         self.id, constraints)).unwrap();
             }
             // FIXME (#10104): Only dirty nodes affected by vh/vw/vmin/vmax styles.
-            if data.document_stylesheets.iter().any(|sheet| sheet.dirty_on_viewport_size_change) {
+            if data.document_stylesheets.iter().any(|sheet| sheet.dirty_on_viewport_size_change()) {
                 needs_dirtying = true;
             }
         }
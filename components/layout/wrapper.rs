@@ -69,9 +69,10 @@ use style::dom::{TDocument, TElement, TNode, UnsafeNode};
 use style::element_state::*;
 use style::properties::{ComputedValues, ServoComputedValues};
 use style::properties::{PropertyDeclaration, PropertyDeclarationBlock};
-use style::restyle_hints::ElementSnapshot;
+use style::restyle_hints::ServoElementSnapshot;
 use style::selector_impl::{NonTSPseudoClass, PseudoElement, ServoSelectorImpl};
 use style::servo::PrivateStyleData;
+use style::shared_lock::SharedRwLockReadGuard;
 use url::Url;
 use util::str::{is_whitespace, search_index};
 
@@ -378,7 +379,7 @@ impl<'ld> TDocument for ServoLayoutDocument<'ld> {
         self.as_node().children().find(ServoLayoutNode::is_element)
     }
 
-    fn drain_modified_elements(&self) -> Vec<(ServoLayoutElement<'ld>, ElementSnapshot)> {
+    fn drain_modified_elements(&self) -> Vec<(ServoLayoutElement<'ld>, ServoElementSnapshot)> {
         let elements =  unsafe { self.document.drain_modified_elements() };
         elements.into_iter().map(|(el, snapshot)| (ServoLayoutElement::from_layout_js(el), snapshot)).collect()
     }
@@ -408,7 +409,7 @@ impl<'le> TElement for ServoLayoutElement<'le> {
         ServoLayoutNode::from_layout_js(self.element.upcast())
     }
 
-    fn style_attribute(&self) -> &Option<PropertyDeclarationBlock> {
+    fn style_attribute<'a>(&'a self, _: &'a SharedRwLockReadGuard) -> &'a Option<PropertyDeclarationBlock> {
         unsafe {
             &*self.element.style_attribute()
         }
@@ -418,7 +419,7 @@ impl<'le> TElement for ServoLayoutElement<'le> {
         self.element.get_state_for_layout()
     }
 
-    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, hints: &mut V)
+    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, _: &SharedRwLockReadGuard, hints: &mut V)
         where V: VecLike<DeclarationBlock<Vec<PropertyDeclaration>>>
     {
         unsafe {
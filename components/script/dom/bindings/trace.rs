@@ -84,7 +84,7 @@ use string_cache::{Atom, Namespace, QualName};
 use style::attr::{AttrIdentifier, AttrValue};
 use style::element_state::*;
 use style::properties::PropertyDeclarationBlock;
-use style::restyle_hints::ElementSnapshot;
+use style::restyle_hints::ServoElementSnapshot;
 use style::selector_impl::PseudoElement;
 use style::values::specified::Length;
 use url::Url;
@@ -317,7 +317,7 @@ no_jsmanaged_fields!(DOMString);
 no_jsmanaged_fields!(Mime);
 no_jsmanaged_fields!(AttrIdentifier);
 no_jsmanaged_fields!(AttrValue);
-no_jsmanaged_fields!(ElementSnapshot);
+no_jsmanaged_fields!(ServoElementSnapshot);
 no_jsmanaged_fields!(HttpsState);
 
 impl JSTraceable for ConstellationChan<ScriptMsg> {
@@ -89,6 +89,7 @@ use style::properties::DeclaredValue;
 use style::properties::longhands::{self, background_image, border_spacing, font_family, font_size};
 use style::properties::{PropertyDeclaration, PropertyDeclarationBlock, parse_style_attribute};
 use style::selector_impl::{NonTSPseudoClass, ServoSelectorImpl};
+use style::url_value::{UrlExtraData, UrlValue};
 use style::values::CSSFloat;
 use style::values::specified::{self, CSSColor, CSSRGBA, LengthOrPercentage};
 use util::str::{DOMString, LengthOrPercentageOrAuto};
@@ -284,7 +285,7 @@ impl LayoutElementHelpers for LayoutJS<Element> {
         if let Some(color) = bgcolor {
             hints.push(from_declaration(
                 PropertyDeclaration::BackgroundColor(DeclaredValue::Value(
-                    CSSColor { parsed: Color::RGBA(color), authored: None }))));
+                    CSSColor { parsed: Color::RGBA(color), authored: None, system: None }))));
         }
 
         let background = if let Some(this) = self.downcast::<HTMLBodyElement>() {
@@ -296,7 +297,8 @@ impl LayoutElementHelpers for LayoutJS<Element> {
         if let Some(url) = background {
             hints.push(from_declaration(
                 PropertyDeclaration::BackgroundImage(DeclaredValue::Value(
-                    background_image::SpecifiedValue(Some(specified::Image::Url(url)))))));
+                    background_image::SpecifiedValue(vec![Some(specified::Image::Url(
+                        Arc::new(UrlValue::new(url, UrlExtraData::none())))))])))));
         }
 
         let color = if let Some(this) = self.downcast::<HTMLFontElement>() {
@@ -316,6 +318,7 @@ impl LayoutElementHelpers for LayoutJS<Element> {
                 PropertyDeclaration::Color(DeclaredValue::Value(CSSRGBA {
                     parsed: color,
                     authored: None,
+                    system: None,
                 }))));
         }
 
@@ -344,7 +347,7 @@ impl LayoutElementHelpers for LayoutJS<Element> {
             hints.push(from_declaration(
                 PropertyDeclaration::FontSize(
                     DeclaredValue::Value(
-                        font_size::SpecifiedValue(
+                        font_size::SpecifiedValue::Length(
                             LengthOrPercentage::Length(font_size))))))
         }
 
@@ -390,8 +393,8 @@ impl LayoutElementHelpers for LayoutJS<Element> {
         if let Some(size) = size {
             let value = specified::Length::ServoCharacterWidth(specified::CharacterWidth(size));
             hints.push(from_declaration(
-                PropertyDeclaration::Width(DeclaredValue::Value(
-                    specified::LengthOrPercentageOrAuto::Length(value)))));
+                PropertyDeclaration::Width(DeclaredValue::Value(specified::MozLength::LengthOrPercentageOrAuto(
+                    specified::LengthOrPercentageOrAuto::Length(value))))));
         }
 
 
@@ -415,14 +418,14 @@ impl LayoutElementHelpers for LayoutJS<Element> {
             LengthOrPercentageOrAuto::Percentage(percentage) => {
                 let width_value =
                     specified::LengthOrPercentageOrAuto::Percentage(specified::Percentage(percentage));
-                hints.push(from_declaration(
-                    PropertyDeclaration::Width(DeclaredValue::Value(width_value))));
+                hints.push(from_declaration(PropertyDeclaration::Width(DeclaredValue::Value(
+                    specified::MozLength::LengthOrPercentageOrAuto(width_value)))));
             }
             LengthOrPercentageOrAuto::Length(length) => {
                 let width_value = specified::LengthOrPercentageOrAuto::Length(
                     specified::Length::Absolute(length));
-                hints.push(from_declaration(
-                    PropertyDeclaration::Width(DeclaredValue::Value(width_value))));
+                hints.push(from_declaration(PropertyDeclaration::Width(DeclaredValue::Value(
+                    specified::MozLength::LengthOrPercentageOrAuto(width_value)))));
             }
         }
 
@@ -440,14 +443,14 @@ impl LayoutElementHelpers for LayoutJS<Element> {
             LengthOrPercentageOrAuto::Percentage(percentage) => {
                 let height_value =
                     specified::LengthOrPercentageOrAuto::Percentage(specified::Percentage(percentage));
-                hints.push(from_declaration(
-                    PropertyDeclaration::Height(DeclaredValue::Value(height_value))));
+                hints.push(from_declaration(PropertyDeclaration::Height(DeclaredValue::Value(
+                    specified::MozLength::LengthOrPercentageOrAuto(height_value)))));
             }
             LengthOrPercentageOrAuto::Length(length) => {
                 let height_value = specified::LengthOrPercentageOrAuto::Length(
                     specified::Length::Absolute(length));
-                hints.push(from_declaration(
-                    PropertyDeclaration::Height(DeclaredValue::Value(height_value))));
+                hints.push(from_declaration(PropertyDeclaration::Height(DeclaredValue::Value(
+                    specified::MozLength::LengthOrPercentageOrAuto(height_value)))));
             }
         }
 
@@ -469,8 +472,8 @@ impl LayoutElementHelpers for LayoutJS<Element> {
             // https://html.spec.whatwg.org/multipage/#textarea-effective-width
             let value = specified::Length::ServoCharacterWidth(specified::CharacterWidth(cols));
             hints.push(from_declaration(
-                PropertyDeclaration::Width(DeclaredValue::Value(
-                    specified::LengthOrPercentageOrAuto::Length(value)))));
+                PropertyDeclaration::Width(DeclaredValue::Value(specified::MozLength::LengthOrPercentageOrAuto(
+                    specified::LengthOrPercentageOrAuto::Length(value))))));
         }
 
 
@@ -489,8 +492,8 @@ impl LayoutElementHelpers for LayoutJS<Element> {
             // https://html.spec.whatwg.org/multipage/#textarea-effective-height
             let value = specified::Length::FontRelative(specified::FontRelativeLength::Em(rows as CSSFloat));
             hints.push(from_declaration(
-                PropertyDeclaration::Height(DeclaredValue::Value(
-                        specified::LengthOrPercentageOrAuto::Length(value)))));
+                PropertyDeclaration::Height(DeclaredValue::Value(specified::MozLength::LengthOrPercentageOrAuto(
+                        specified::LengthOrPercentageOrAuto::Length(value))))));
         }
 
 
@@ -721,6 +724,7 @@ impl Element {
         *inline_declarations = Some(PropertyDeclarationBlock {
             important: Arc::new(important),
             normal: Arc::new(normal),
+            shorthands: vec![],
         });
     }
 
@@ -286,9 +286,9 @@ impl AsyncResponseListener for StylesheetContext {
         let elem = self.elem.root();
         let win = window_from_node(&*elem);
 
-        let mut sheet = Stylesheet::from_bytes(&data, final_url, protocol_encoding_label,
-                                               Some(environment_encoding), Origin::Author,
-                                               win.css_error_reporter());
+        let sheet = Stylesheet::from_bytes(&data, final_url, protocol_encoding_label,
+                                           Some(environment_encoding), Origin::Author,
+                                           win.css_error_reporter());
         let media = self.media.take().unwrap();
         sheet.set_media(Some(media));
         let sheet = Arc::new(sheet);
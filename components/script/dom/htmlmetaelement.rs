@@ -13,10 +13,13 @@ use dom::htmlelement::HTMLElement;
 use dom::node::{Node, document_from_node};
 use dom::virtualmethods::VirtualMethods;
 use std::ascii::AsciiExt;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use string_cache::Atom;
+use style::parser::QuirksMode;
 use style::servo::Stylesheet;
 use style::stylesheets::{CSSRule, Origin};
+use style::url_value::UrlExtraData;
 use style::viewport::ViewportRule;
 use util::str::{DOMString, HTML_SPACE_CHARACTERS};
 
@@ -69,15 +72,22 @@ impl HTMLMetaElement {
             let content = content.value();
             if !content.is_empty() {
                 if let Some(translated_rule) = ViewportRule::from_meta(&**content) {
+                    let doc = document_from_node(self);
                     *self.stylesheet.borrow_mut() = Some(Arc::new(Stylesheet {
-                        rules: vec![CSSRule::Viewport(translated_rule)],
+                        rules: RwLock::new(Arc::new(vec![CSSRule::Viewport(translated_rule)])),
                         origin: Origin::Author,
-                        media: None,
+                        media: RwLock::new(None),
                         // Viewport constraints are always recomputed on resize; they don't need to
                         // force all styles to be recomputed.
-                        dirty_on_viewport_size_change: false,
+                        dirty_on_viewport_size_change: AtomicBool::new(false),
+                        disabled: AtomicBool::new(false),
+                        rules_generation: AtomicUsize::new(0),
+                        frozen: AtomicBool::new(false),
+                        title: RwLock::new(String::new()),
+                        url_extra_data: RwLock::new(UrlExtraData::none()),
+                        base_url: doc.url().clone(),
+                        quirks_mode: QuirksMode::NoQuirks,
                     }));
-                    let doc = document_from_node(self);
                     doc.invalidate_stylesheets();
                 }
             }
@@ -3,10 +3,11 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
-use bezier::Bezier;
-use cssparser::{Color, RGBA};
+use cssparser::{Color, RGBA, ToCss};
 use dom::{OpaqueNode, TRestyleDamage};
-use euclid::point::Point2D;
+use error_reporting::StdoutErrorReporter;
+use euclid::size::Size2D;
+use properties::longhands::background_image::computed_value::T as BackgroundImage;
 use properties::longhands::background_position::computed_value::T as BackgroundPosition;
 use properties::longhands::border_spacing::computed_value::T as BorderSpacing;
 use properties::longhands::clip::computed_value::ClipRect;
@@ -18,22 +19,28 @@ use properties::longhands::transform::computed_value::ComputedMatrix;
 use properties::longhands::transform::computed_value::ComputedOperation as TransformOperation;
 use properties::longhands::transform::computed_value::T as TransformList;
 use properties::longhands::transition_property;
+use properties::longhands::{transition_delay, transition_duration, transition_timing_function};
 use properties::longhands::transition_property::computed_value::TransitionProperty;
-use properties::longhands::transition_timing_function::computed_value::{StartEnd};
 use properties::longhands::transition_timing_function::computed_value::{TransitionTimingFunction};
 use properties::longhands::vertical_align::computed_value::T as VerticalAlign;
 use properties::longhands::visibility::computed_value::T as Visibility;
 use properties::longhands::z_index::computed_value::T as ZIndex;
 use properties::style_struct_traits::TAnimation;
-use properties::{ComputedValues, ServoComputedValues};
+use properties::{ComputedValues, PropertyDeclaration, ServoComputedValues, cascade, parse_property_value};
+use selectors::matching::DeclarationBlock;
 use std::cmp::Ordering;
+use std::f64::consts::PI;
 use std::iter::repeat;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 use time;
+use url::Url;
 use values::CSSFloat;
 use values::computed::{Angle, LengthOrPercentageOrAuto, LengthOrPercentageOrNone};
 use values::computed::{CalcLengthOrPercentage, Length, LengthOrPercentage, Time};
+use values::computed::{MaxLength, MinLength, MozLength};
+use values::computed::{ColorStop, GradientSize, Image, LinearGradient, Position, RadialGradient};
+use values::specified::AngleOrCorner;
 
 /// State relating to an animation.
 #[derive(Clone)]
@@ -106,12 +113,190 @@ impl PropertyAnimation {
                                 new_style: &mut ServoComputedValues)
                                 -> Option<PropertyAnimation> {
         let animation_style = new_style.get_animation();
+        let property_animation = PropertyAnimation {
+            property: AnimatedProperty::from_styles(transition_property, old_style, new_style),
+            timing_function:
+                *animation_style.transition_timing_function.0.get_mod(transition_index),
+            duration: *animation_style.transition_duration.0.get_mod(transition_index),
+        };
+        if property_animation.does_not_animate() {
+            None
+        } else {
+            Some(property_animation)
+        }
+    }
+
+    pub fn update(&self, style: &mut ServoComputedValues, time: f64) {
+        // See `WebCore::AnimationBase::solveEpsilon(double)` in WebKit.
+        let epsilon = 1.0 / (200.0 * (self.duration.seconds() as f64));
+        let progress = self.timing_function.calculate_output(time, false, epsilon);
+
+        self.property.update(style, progress);
+    }
+
+    #[inline]
+    fn does_not_animate(&self) -> bool {
+        self.property.does_not_animate() || self.duration == Time(0.0)
+    }
+}
+
+/// The computed value of a single animatable property, used by embedders that run
+/// animations themselves and only need Servo to interpolate between keyframe values.
+#[derive(Clone)]
+pub struct AnimationValue {
+    property: TransitionProperty,
+    /// A style holding the value. Only `property` is meaningful in it.
+    style: Arc<ServoComputedValues>,
+}
+
+impl AnimationValue {
+    /// Computes the value `property` gets from `declarations`, or returns `None` if the
+    /// declarations don't set it.
+    ///
+    /// FIXME: The declarations are cascaded on top of the initial values, so font-relative
+    /// units resolve against the initial font size.
+    pub fn from_declarations(property: TransitionProperty,
+                             declarations: Arc<Vec<PropertyDeclaration>>,
+                             viewport_size: Size2D<Au>)
+                             -> Option<AnimationValue> {
+        if property == TransitionProperty::All {
+            return None
+        }
+        let name = property.to_css_string();
+        if !declarations.iter().any(|declaration| declaration.matches(&name)) {
+            return None
+        }
+        let (style, _) = cascade::<ServoComputedValues>(viewport_size,
+                                                        &[DeclarationBlock::from_declarations(declarations)],
+                                                        false, None, None, Box::new(StdoutErrorReporter));
+        Some(AnimationValue {
+            property: property,
+            style: Arc::new(style),
+        })
+    }
+
+    /// Computes the value `property` gets from `css`, written as it would be in a declaration
+    /// of the property, or returns `None` if it isn't a valid value for it.
+    pub fn from_css(property: TransitionProperty,
+                    css: &str,
+                    base_url: &Url,
+                    viewport_size: Size2D<Au>)
+                    -> Option<AnimationValue> {
+        if property == TransitionProperty::All {
+            return None
+        }
+        match parse_property_value(&property.to_css_string(), css, base_url, Box::new(StdoutErrorReporter)) {
+            Ok(declarations) => AnimationValue::from_declarations(property, Arc::new(declarations), viewport_size),
+            Err(()) => None,
+        }
+    }
+
+    pub fn property(&self) -> TransitionProperty {
+        self.property
+    }
+
+    /// Whether there are values between `self` and `other`, rather than one flipping to the
+    /// other halfway through.
+    pub fn can_interpolate(&self, other: &AnimationValue) -> bool {
+        self.property == other.property &&
+        AnimatedProperty::from_styles(self.property, &self.style, &other.style)
+            .update(&mut (*self.style).clone(), 0.5)
+    }
+
+    /// Returns the value `progress` of the way from `self` to `other`, or `None` if they're
+    /// values of different properties. Values that can't be interpolated flip from one to the
+    /// other halfway through.
+    pub fn interpolate(&self, other: &AnimationValue, progress: f64) -> Option<AnimationValue> {
+        if self.property != other.property {
+            return None
+        }
+        let animated_property = AnimatedProperty::from_styles(self.property, &self.style, &other.style);
+        let mut style = (*self.style).clone();
+        if animated_property.update(&mut style, progress) {
+            return Some(AnimationValue {
+                property: self.property,
+                style: Arc::new(style),
+            })
+        }
+        Some(if progress < 0.5 { self.clone() } else { other.clone() })
+    }
+
+    /// Serializes the value the way `getComputedStyle` would.
+    pub fn serialize(&self) -> String {
+        self.style.computed_value_to_string(&self.property.to_css_string())
+                  .expect("Animatable properties should all be longhands")
+    }
+}
+
+impl PartialEq for AnimationValue {
+    fn eq(&self, other: &AnimationValue) -> bool {
+        self.property == other.property &&
+        AnimatedProperty::from_styles(self.property, &self.style, &other.style).does_not_animate()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum AnimatedProperty {
+    BackgroundColor(Color, Color),
+    BackgroundImage(BackgroundImage, BackgroundImage),
+    BackgroundPosition(BackgroundPosition, BackgroundPosition),
+    BorderBottomColor(Color, Color),
+    BorderBottomWidth(Length, Length),
+    BorderLeftColor(Color, Color),
+    BorderLeftWidth(Length, Length),
+    BorderRightColor(Color, Color),
+    BorderRightWidth(Length, Length),
+    BorderSpacing(BorderSpacing, BorderSpacing),
+    BorderTopColor(Color, Color),
+    BorderTopWidth(Length, Length),
+    Bottom(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    Color(RGBA, RGBA),
+    Clip(Option<ClipRect>, Option<ClipRect>),
+    FontSize(Length, Length),
+    FontWeight(FontWeight, FontWeight),
+    Height(MozLength, MozLength),
+    Left(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    LetterSpacing(Option<Au>, Option<Au>),
+    LineHeight(LineHeight, LineHeight),
+    MarginBottom(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    MarginLeft(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    MarginRight(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    MarginTop(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    MaxHeight(MaxLength, MaxLength),
+    MaxWidth(MaxLength, MaxLength),
+    MinHeight(MinLength, MinLength),
+    MinWidth(MinLength, MinLength),
+    Opacity(CSSFloat, CSSFloat),
+    OutlineColor(Color, Color),
+    OutlineWidth(Length, Length),
+    PaddingBottom(LengthOrPercentage, LengthOrPercentage),
+    PaddingLeft(LengthOrPercentage, LengthOrPercentage),
+    PaddingRight(LengthOrPercentage, LengthOrPercentage),
+    PaddingTop(LengthOrPercentage, LengthOrPercentage),
+    Right(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    TextIndent(LengthOrPercentage, LengthOrPercentage),
+    TextShadow(TextShadowList, TextShadowList),
+    Top(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
+    Transform(TransformList, TransformList),
+    VerticalAlign(VerticalAlign, VerticalAlign),
+    Visibility(Visibility, Visibility),
+    Width(MozLength, MozLength),
+    WordSpacing(Option<Au>, Option<Au>),
+    ZIndex(ZIndex, ZIndex),
+}
+
+impl AnimatedProperty {
+    /// Pairs up the values `transition_property` has in the two styles.
+    fn from_styles(transition_property: TransitionProperty,
+                   old_style: &ServoComputedValues,
+                   new_style: &ServoComputedValues)
+                   -> AnimatedProperty {
         macro_rules! match_transition {
                 ( $( [$name:ident; $structname:ident; $field:ident] ),* ) => {
                     match transition_property {
                         TransitionProperty::All => {
                             panic!("Don't use `TransitionProperty::All` with \
-                                   `PropertyAnimation::from_transition_property`!")
+                                   `AnimatedProperty::from_styles`!")
                         }
                         $(
                             TransitionProperty::$name => {
@@ -119,6 +304,10 @@ impl PropertyAnimation {
                                                         new_style.$structname().$field)
                             }
                         )*
+                        TransitionProperty::BackgroundImage => {
+                            AnimatedProperty::BackgroundImage(old_style.get_background().background_image.clone(),
+                                                              new_style.get_background().background_image.clone())
+                        }
                         TransitionProperty::Clip => {
                             AnimatedProperty::Clip(old_style.get_effects().clip.0,
                                                    new_style.get_effects().clip.0)
@@ -128,8 +317,8 @@ impl PropertyAnimation {
                                                             new_style.get_inheritedtext().letter_spacing.0)
                         }
                         TransitionProperty::TextShadow => {
-                            AnimatedProperty::TextShadow(old_style.get_effects().text_shadow.clone(),
-                                                         new_style.get_effects().text_shadow.clone())
+                            AnimatedProperty::TextShadow(old_style.get_inheritedtext().text_shadow.clone(),
+                                                         new_style.get_inheritedtext().text_shadow.clone())
                         }
                         TransitionProperty::Transform => {
                             AnimatedProperty::Transform(old_style.get_effects().transform.clone(),
@@ -142,7 +331,7 @@ impl PropertyAnimation {
                     }
                 }
         }
-        let animated_property = match_transition!(
+        match_transition!(
             [BackgroundColor; get_background; background_color],
             [BackgroundPosition; get_background; background_position],
             [BorderBottomColor; get_border; border_bottom_color],
@@ -182,66 +371,59 @@ impl PropertyAnimation {
             [VerticalAlign; get_box; vertical_align],
             [Visibility; get_inheritedbox; visibility],
             [Width; get_box; width],
-            [ZIndex; get_box; z_index]);
-
-        let property_animation = PropertyAnimation {
-            property: animated_property,
-            timing_function:
-                *animation_style.transition_timing_function.0.get_mod(transition_index),
-            duration: *animation_style.transition_duration.0.get_mod(transition_index),
-        };
-        if property_animation.does_not_animate() {
-            None
-        } else {
-            Some(property_animation)
-        }
+            [ZIndex; get_box; z_index])
     }
 
-    pub fn update(&self, style: &mut ServoComputedValues, time: f64) {
-        let progress = match self.timing_function {
-            TransitionTimingFunction::CubicBezier(p1, p2) => {
-                // See `WebCore::AnimationBase::solveEpsilon(double)` in WebKit.
-                let epsilon = 1.0 / (200.0 * (self.duration.seconds() as f64));
-                Bezier::new(Point2D::new(p1.x as f64, p1.y as f64),
-                            Point2D::new(p2.x as f64, p2.y as f64)).solve(time, epsilon)
-            }
-            TransitionTimingFunction::Steps(steps, StartEnd::Start) => {
-                (time * (steps as f64)).ceil() / (steps as f64)
-            }
-            TransitionTimingFunction::Steps(steps, StartEnd::End) => {
-                (time * (steps as f64)).floor() / (steps as f64)
-            }
-        };
-
+    /// Sets the property in `style` to its value `progress` of the way between the start and
+    /// end values. Returns false, leaving `style` untouched, if the values can't be
+    /// interpolated.
+    fn update(&self, style: &mut ServoComputedValues, progress: f64) -> bool {
         macro_rules! match_property(
             ( $( [$name:ident; $structname:ident; $field:ident] ),* ) => {
                 match self.property {
                     $(
                         AnimatedProperty::$name(ref start, ref end) => {
-                            if let Some(value) = start.interpolate(end, progress) {
-                                style.$structname().$field = value
+                            match start.interpolate(end, progress) {
+                                Some(value) => {
+                                    style.$structname().$field = value;
+                                    true
+                                }
+                                None => false,
                             }
                         }
                     )*
                     AnimatedProperty::Clip(ref start, ref end) => {
-                        if let Some(value) = start.interpolate(end, progress) {
-                            style.mutate_effects().clip.0 = value
+                        match start.interpolate(end, progress) {
+                            Some(value) => {
+                                style.mutate_effects().clip.0 = value;
+                                true
+                            }
+                            None => false,
                         }
                     }
                     AnimatedProperty::LetterSpacing(ref start, ref end) => {
-                        if let Some(value) = start.interpolate(end, progress) {
-                            style.mutate_inheritedtext().letter_spacing.0 = value
+                        match start.interpolate(end, progress) {
+                            Some(value) => {
+                                style.mutate_inheritedtext().letter_spacing.0 = value;
+                                true
+                            }
+                            None => false,
                         }
                     }
                     AnimatedProperty::WordSpacing(ref start, ref end) => {
-                        if let Some(value) = start.interpolate(end, progress) {
-                            style.mutate_inheritedtext().word_spacing.0 = value
+                        match start.interpolate(end, progress) {
+                            Some(value) => {
+                                style.mutate_inheritedtext().word_spacing.0 = value;
+                                true
+                            }
+                            None => false,
                         }
                     }
-                 }
+                }
             });
         match_property!(
             [BackgroundColor; mutate_background; background_color],
+            [BackgroundImage; mutate_background; background_image],
             [BackgroundPosition; mutate_background; background_position],
             [BorderBottomColor; mutate_border; border_bottom_color],
             [BorderBottomWidth; mutate_border; border_bottom_width],
@@ -276,71 +458,15 @@ impl PropertyAnimation {
             [PaddingTop; mutate_padding; padding_top],
             [Right; mutate_positionoffsets; right],
             [TextIndent; mutate_inheritedtext; text_indent],
-            [TextShadow; mutate_effects; text_shadow],
+            [TextShadow; mutate_inheritedtext; text_shadow],
             [Top; mutate_positionoffsets; top],
             [Transform; mutate_effects; transform],
             [VerticalAlign; mutate_box; vertical_align],
             [Visibility; mutate_inheritedbox; visibility],
             [Width; mutate_box; width],
-            [ZIndex; mutate_box; z_index]);
-    }
-
-    #[inline]
-    fn does_not_animate(&self) -> bool {
-        self.property.does_not_animate() || self.duration == Time(0.0)
+            [ZIndex; mutate_box; z_index])
     }
-}
-
-#[derive(Clone, Debug)]
-enum AnimatedProperty {
-    BackgroundColor(Color, Color),
-    BackgroundPosition(BackgroundPosition, BackgroundPosition),
-    BorderBottomColor(Color, Color),
-    BorderBottomWidth(Length, Length),
-    BorderLeftColor(Color, Color),
-    BorderLeftWidth(Length, Length),
-    BorderRightColor(Color, Color),
-    BorderRightWidth(Length, Length),
-    BorderSpacing(BorderSpacing, BorderSpacing),
-    BorderTopColor(Color, Color),
-    BorderTopWidth(Length, Length),
-    Bottom(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    Color(RGBA, RGBA),
-    Clip(Option<ClipRect>, Option<ClipRect>),
-    FontSize(Length, Length),
-    FontWeight(FontWeight, FontWeight),
-    Height(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    Left(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    LetterSpacing(Option<Au>, Option<Au>),
-    LineHeight(LineHeight, LineHeight),
-    MarginBottom(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    MarginLeft(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    MarginRight(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    MarginTop(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    MaxHeight(LengthOrPercentageOrNone, LengthOrPercentageOrNone),
-    MaxWidth(LengthOrPercentageOrNone, LengthOrPercentageOrNone),
-    MinHeight(LengthOrPercentage, LengthOrPercentage),
-    MinWidth(LengthOrPercentage, LengthOrPercentage),
-    Opacity(CSSFloat, CSSFloat),
-    OutlineColor(Color, Color),
-    OutlineWidth(Length, Length),
-    PaddingBottom(LengthOrPercentage, LengthOrPercentage),
-    PaddingLeft(LengthOrPercentage, LengthOrPercentage),
-    PaddingRight(LengthOrPercentage, LengthOrPercentage),
-    PaddingTop(LengthOrPercentage, LengthOrPercentage),
-    Right(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    TextIndent(LengthOrPercentage, LengthOrPercentage),
-    TextShadow(TextShadowList, TextShadowList),
-    Top(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    Transform(TransformList, TransformList),
-    VerticalAlign(VerticalAlign, VerticalAlign),
-    Visibility(Visibility, Visibility),
-    Width(LengthOrPercentageOrAuto, LengthOrPercentageOrAuto),
-    WordSpacing(Option<Au>, Option<Au>),
-    ZIndex(ZIndex, ZIndex),
-}
 
-impl AnimatedProperty {
     #[inline]
     fn does_not_animate(&self) -> bool {
         match *self {
@@ -351,13 +477,13 @@ impl AnimatedProperty {
             AnimatedProperty::MarginTop(ref a, ref b) |
             AnimatedProperty::MarginRight(ref a, ref b) |
             AnimatedProperty::MarginBottom(ref a, ref b) |
-            AnimatedProperty::MarginLeft(ref a, ref b) |
+            AnimatedProperty::MarginLeft(ref a, ref b) => a == b,
             AnimatedProperty::Width(ref a, ref b) |
             AnimatedProperty::Height(ref a, ref b) => a == b,
             AnimatedProperty::MaxWidth(ref a, ref b) |
             AnimatedProperty::MaxHeight(ref a, ref b) => a == b,
             AnimatedProperty::MinWidth(ref a, ref b) |
-            AnimatedProperty::MinHeight(ref a, ref b) |
+            AnimatedProperty::MinHeight(ref a, ref b) => a == b,
             AnimatedProperty::TextIndent(ref a, ref b) => a == b,
             AnimatedProperty::FontSize(ref a, ref b) |
             AnimatedProperty::BorderTopWidth(ref a, ref b) |
@@ -376,6 +502,7 @@ impl AnimatedProperty {
             AnimatedProperty::PaddingLeft(ref a, ref b) => a == b,
             AnimatedProperty::LineHeight(ref a, ref b) => a == b,
             AnimatedProperty::LetterSpacing(ref a, ref b) => a == b,
+            AnimatedProperty::BackgroundImage(ref a, ref b) => a == b,
             AnimatedProperty::BackgroundPosition(ref a, ref b) => a == b,
             AnimatedProperty::BorderSpacing(ref a, ref b) => a == b,
             AnimatedProperty::Clip(ref a, ref b) => a == b,
@@ -511,17 +638,31 @@ impl Interpolate for BorderSpacing {
     }
 }
 
+/// https://drafts.csswg.org/css-transitions/#animtype-color
+///
+/// Colors are interpolated in premultiplied space, so that fading from or to a transparent
+/// color doesn't pass through that color's RGB components.
 impl Interpolate for RGBA {
     #[inline]
     fn interpolate(&self, other: &RGBA, time: f64) -> Option<RGBA> {
-        match (self.red.interpolate(&other.red, time),
-               self.green.interpolate(&other.green, time),
-               self.blue.interpolate(&other.blue, time),
-               self.alpha.interpolate(&other.alpha, time)) {
-            (Some(red), Some(green), Some(blue), Some(alpha)) => {
+        let alpha = match self.alpha.interpolate(&other.alpha, time) {
+            Some(alpha) => alpha.max(0.).min(1.),
+            None => return None,
+        };
+        if alpha == 0. {
+            return Some(RGBA { red: 0., green: 0., blue: 0., alpha: 0. })
+        }
+        let premultiplied = |this: f32, other_value: f32| {
+            (this * self.alpha).interpolate(&(other_value * other.alpha), time)
+                               .map(|value| (value / alpha).max(0.).min(1.))
+        };
+        match (premultiplied(self.red, other.red),
+               premultiplied(self.green, other.green),
+               premultiplied(self.blue, other.blue)) {
+            (Some(red), Some(green), Some(blue)) => {
                 Some(RGBA { red: red, green: green, blue: blue, alpha: alpha })
             }
-            (_, _, _, _) => None
+            (_, _, _) => None
         }
     }
 }
@@ -635,6 +776,53 @@ impl Interpolate for LengthOrPercentageOrNone {
     }
 }
 
+/// The intrinsic size keywords only go to themselves; there's nothing in between.
+impl Interpolate for MozLength {
+    #[inline]
+    fn interpolate(&self, other: &MozLength, time: f64) -> Option<MozLength> {
+        match (*self, *other) {
+            (MozLength::LengthOrPercentageOrAuto(ref this),
+             MozLength::LengthOrPercentageOrAuto(ref other)) => {
+                this.interpolate(other, time).map(MozLength::LengthOrPercentageOrAuto)
+            }
+            (MozLength::ExtremumLength(this), MozLength::ExtremumLength(other)) if this == other => {
+                Some(MozLength::ExtremumLength(this))
+            }
+            (_, _) => None,
+        }
+    }
+}
+
+impl Interpolate for MinLength {
+    #[inline]
+    fn interpolate(&self, other: &MinLength, time: f64) -> Option<MinLength> {
+        match (*self, *other) {
+            (MinLength::LengthOrPercentage(ref this), MinLength::LengthOrPercentage(ref other)) => {
+                this.interpolate(other, time).map(MinLength::LengthOrPercentage)
+            }
+            (MinLength::ExtremumLength(this), MinLength::ExtremumLength(other)) if this == other => {
+                Some(MinLength::ExtremumLength(this))
+            }
+            (_, _) => None,
+        }
+    }
+}
+
+impl Interpolate for MaxLength {
+    #[inline]
+    fn interpolate(&self, other: &MaxLength, time: f64) -> Option<MaxLength> {
+        match (*self, *other) {
+            (MaxLength::LengthOrPercentageOrNone(ref this), MaxLength::LengthOrPercentageOrNone(ref other)) => {
+                this.interpolate(other, time).map(MaxLength::LengthOrPercentageOrNone)
+            }
+            (MaxLength::ExtremumLength(this), MaxLength::ExtremumLength(other)) if this == other => {
+                Some(MaxLength::ExtremumLength(this))
+            }
+            (_, _) => None,
+        }
+    }
+}
+
 impl Interpolate for LineHeight {
     #[inline]
     fn interpolate(&self, other: &LineHeight, time: f64)
@@ -706,20 +894,171 @@ impl Interpolate for ClipRect {
     }
 }
 
-impl Interpolate for BackgroundPosition {
+impl Interpolate for Position {
     #[inline]
-    fn interpolate(&self, other: &BackgroundPosition, time: f64)
-                   -> Option<BackgroundPosition> {
+    fn interpolate(&self, other: &Position, time: f64)
+                   -> Option<Position> {
         match (self.horizontal.interpolate(&other.horizontal, time),
                self.vertical.interpolate(&other.vertical, time)) {
             (Some(horizontal), Some(vertical)) => {
-                Some(BackgroundPosition { horizontal: horizontal, vertical: vertical })
+                Some(Position { horizontal: horizontal, vertical: vertical })
             },
             (_, _) => None,
         }
     }
 }
 
+/// Only positions with the same number of layers interpolate, each layer with the one it covers.
+impl Interpolate for BackgroundPosition {
+    #[inline]
+    fn interpolate(&self, other: &BackgroundPosition, time: f64) -> Option<BackgroundPosition> {
+        if self.0.len() != other.0.len() {
+            return None
+        }
+        let mut layers = Vec::with_capacity(self.0.len());
+        for (this, other) in self.0.iter().zip(other.0.iter()) {
+            match this.interpolate(other, time) {
+                Some(position) => layers.push(position),
+                None => return None,
+            }
+        }
+        Some(BackgroundPosition(layers))
+    }
+}
+
+/// https://drafts.csswg.org/css-images/#interpolating-gradients
+///
+/// Only images with the same number of layers interpolate, each layer with the one it covers.
+impl Interpolate for BackgroundImage {
+    #[inline]
+    fn interpolate(&self, other: &BackgroundImage, time: f64) -> Option<BackgroundImage> {
+        if self.0.len() != other.0.len() {
+            return None
+        }
+        let mut layers = Vec::with_capacity(self.0.len());
+        for (this, other) in self.0.iter().zip(other.0.iter()) {
+            match (this, other) {
+                (&Some(ref this), &Some(ref other)) => {
+                    match this.interpolate(other, time) {
+                        Some(image) => layers.push(Some(image)),
+                        None => return None,
+                    }
+                }
+                (&None, &None) => layers.push(None),
+                (_, _) => return None,
+            }
+        }
+        Some(BackgroundImage(layers))
+    }
+}
+
+/// Gradients interpolate when they're of the same kind and have as many stops, the same
+/// shape and the same kind of size; images only "interpolate" with themselves.
+impl Interpolate for Image {
+    #[inline]
+    fn interpolate(&self, other: &Image, time: f64) -> Option<Image> {
+        match (self, other) {
+            (&Image::LinearGradient(ref this), &Image::LinearGradient(ref other)) => {
+                this.interpolate(other, time).map(Image::LinearGradient)
+            }
+            (&Image::RadialGradient(ref this), &Image::RadialGradient(ref other)) => {
+                this.interpolate(other, time).map(Image::RadialGradient)
+            }
+            (this, other) if this == other => Some(this.clone()),
+            (_, _) => None,
+        }
+    }
+}
+
+impl Interpolate for AngleOrCorner {
+    #[inline]
+    fn interpolate(&self, other: &AngleOrCorner, time: f64) -> Option<AngleOrCorner> {
+        match (*self, *other) {
+            (AngleOrCorner::Angle(ref this), AngleOrCorner::Angle(ref other)) => {
+                this.interpolate(other, time).map(AngleOrCorner::Angle)
+            }
+            (this, other) if this == other => Some(this),
+            (_, _) => None,
+        }
+    }
+}
+
+fn interpolate_color_stops(this: &[ColorStop], other: &[ColorStop], time: f64) -> Option<Vec<ColorStop>> {
+    if this.len() != other.len() {
+        return None
+    }
+    let mut stops = Vec::with_capacity(this.len());
+    for (this, other) in this.iter().zip(other.iter()) {
+        // `currentcolor` doesn't interpolate with anything but itself.
+        let color = if this.color == other.color {
+            this.color
+        } else {
+            match this.color.interpolate(&other.color, time) {
+                Some(color) => color,
+                None => return None,
+            }
+        };
+        match this.position.interpolate(&other.position, time) {
+            Some(position) => {
+                stops.push(ColorStop {
+                    color: color,
+                    position: position,
+                    implied_position: this.implied_position && other.implied_position,
+                })
+            }
+            None => return None,
+        }
+    }
+    Some(stops)
+}
+
+impl Interpolate for LinearGradient {
+    #[inline]
+    fn interpolate(&self, other: &LinearGradient, time: f64) -> Option<LinearGradient> {
+        match (self.angle_or_corner.interpolate(&other.angle_or_corner, time),
+               interpolate_color_stops(&self.stops, &other.stops, time)) {
+            (Some(angle_or_corner), Some(stops)) => {
+                Some(LinearGradient { angle_or_corner: angle_or_corner, stops: stops })
+            }
+            (_, _) => None,
+        }
+    }
+}
+
+impl Interpolate for GradientSize {
+    #[inline]
+    fn interpolate(&self, other: &GradientSize, time: f64) -> Option<GradientSize> {
+        match (*self, *other) {
+            (GradientSize::Explicit(ref this_width, ref this_height),
+             GradientSize::Explicit(ref other_width, ref other_height)) => {
+                match (this_width.interpolate(other_width, time), this_height.interpolate(other_height, time)) {
+                    (Some(width), Some(height)) => Some(GradientSize::Explicit(width, height)),
+                    (_, _) => None,
+                }
+            }
+            (this, other) if this == other => Some(this),
+            (_, _) => None,
+        }
+    }
+}
+
+impl Interpolate for RadialGradient {
+    #[inline]
+    fn interpolate(&self, other: &RadialGradient, time: f64) -> Option<RadialGradient> {
+        if self.shape != other.shape {
+            return None
+        }
+        match (self.size.interpolate(&other.size, time),
+               self.position.interpolate(&other.position, time),
+               interpolate_color_stops(&self.stops, &other.stops, time)) {
+            (Some(size), Some(position), Some(stops)) => {
+                Some(RadialGradient { shape: self.shape, size: size, position: position, stops: stops })
+            }
+            (_, _, _) => None,
+        }
+    }
+}
+
 impl Interpolate for TextShadow {
     #[inline]
     fn interpolate(&self, other: &TextShadow, time: f64)
@@ -749,14 +1088,15 @@ impl Interpolate for TextShadowList {
             })
         };
 
-        let interpolate_each = |(a, b): (&TextShadow, &TextShadow)| {
-            a.interpolate(b, time).unwrap()
-        };
+        let interpolate_each = |(a, b): (&TextShadow, &TextShadow)| a.interpolate(b, time);
 
-        Some(TextShadowList(match self.0.len().cmp(&other.0.len()) {
-            Ordering::Less => other.0.iter().chain(repeat(&zero)).zip(other.0.iter()).map(interpolate_each).collect(),
+        // The shorter list is padded out with transparent shadows. Shadows whose colors can't be
+        // interpolated, like currentColor ones, make the whole list flip over instead.
+        let shadows: Option<Vec<_>> = match self.0.len().cmp(&other.0.len()) {
+            Ordering::Less => self.0.iter().chain(repeat(&zero)).zip(other.0.iter()).map(interpolate_each).collect(),
             _ => self.0.iter().zip(other.0.iter().chain(repeat(&zero))).map(interpolate_each).collect(),
-        }))
+        };
+        shadows.map(TextShadowList)
     }
 }
 
@@ -788,63 +1128,244 @@ fn can_interpolate_list(from_list: &[TransformOperation],
     true
 }
 
-/// Interpolate two transform lists.
+/// Interpolate two transform lists. Returns `None` if they can't be interpolated, in which
+/// case the animation is discrete.
 /// http://dev.w3.org/csswg/css-transforms/#interpolation-of-transforms
 fn interpolate_transform_list(from_list: &[TransformOperation],
                               to_list: &[TransformOperation],
-                              time: f64) -> TransformList {
-    let mut result = vec!();
+                              time: f64) -> Option<TransformList> {
+    if !can_interpolate_list(from_list, to_list) {
+        return interpolate_transform_list_as_matrix(from_list, to_list, time)
+    }
 
-    if can_interpolate_list(from_list, to_list) {
-        for (from, to) in from_list.iter().zip(to_list) {
-            match (from, to) {
-                (&TransformOperation::Matrix(from),
-                 &TransformOperation::Matrix(_to)) => {
-                    // TODO(gw): Implement matrix decomposition and interpolation
-                    result.push(TransformOperation::Matrix(from));
+    let mut result = vec!();
+    for (from, to) in from_list.iter().zip(to_list) {
+        match (from, to) {
+            (&TransformOperation::Matrix(from),
+             &TransformOperation::Matrix(to)) => {
+                match (DecomposedMatrix2D::from_matrix(&from), DecomposedMatrix2D::from_matrix(&to)) {
+                    (Some(from), Some(to)) => {
+                        result.push(TransformOperation::Matrix(from.interpolate(&to, time).to_matrix()));
+                    }
+                    _ => return None,
                 }
-                (&TransformOperation::Skew(fx, fy),
-                 &TransformOperation::Skew(tx, ty)) => {
-                    let ix = fx.interpolate(&tx, time).unwrap();
-                    let iy = fy.interpolate(&ty, time).unwrap();
-                    result.push(TransformOperation::Skew(ix, iy));
+            }
+            (&TransformOperation::Skew(fx, fy),
+             &TransformOperation::Skew(tx, ty)) => {
+                let ix = fx.interpolate(&tx, time).unwrap();
+                let iy = fy.interpolate(&ty, time).unwrap();
+                result.push(TransformOperation::Skew(ix, iy));
+            }
+            (&TransformOperation::Translate(fx, fy, fz),
+             &TransformOperation::Translate(tx, ty, tz)) => {
+                let ix = fx.interpolate(&tx, time).unwrap();
+                let iy = fy.interpolate(&ty, time).unwrap();
+                let iz = fz.interpolate(&tz, time).unwrap();
+                result.push(TransformOperation::Translate(ix, iy, iz));
+            }
+            (&TransformOperation::Scale(fx, fy, fz),
+             &TransformOperation::Scale(tx, ty, tz)) => {
+                let ix = fx.interpolate(&tx, time).unwrap();
+                let iy = fy.interpolate(&ty, time).unwrap();
+                let iz = fz.interpolate(&tz, time).unwrap();
+                result.push(TransformOperation::Scale(ix, iy, iz));
+            }
+            (&TransformOperation::Rotate(fx, fy, fz, fa),
+             &TransformOperation::Rotate(tx, ty, tz, ta)) => {
+                if (fx, fy, fz) != (tx, ty, tz) {
+                    // TODO(gw): Implement 3D matrix decomposition and interpolation
+                    return None
                 }
-                (&TransformOperation::Translate(fx, fy, fz),
-                 &TransformOperation::Translate(tx, ty, tz)) => {
-                    let ix = fx.interpolate(&tx, time).unwrap();
-                    let iy = fy.interpolate(&ty, time).unwrap();
-                    let iz = fz.interpolate(&tz, time).unwrap();
-                    result.push(TransformOperation::Translate(ix, iy, iz));
+                let ia = fa.interpolate(&ta, time).unwrap();
+                result.push(TransformOperation::Rotate(fx, fy, fz, ia));
+            }
+            (&TransformOperation::Perspective(fd),
+             &TransformOperation::Perspective(_td)) => {
+                // TODO(gw): Implement matrix decomposition and interpolation
+                result.push(TransformOperation::Perspective(fd));
+            }
+            _ => {
+                // This should be unreachable due to the can_interpolate_list() call.
+                unreachable!();
+            }
+        }
+    }
+
+    Some(TransformList(Some(result)))
+}
+
+/// Interpolates two lists that don't match up pairwise by multiplying each one out into
+/// a single matrix and interpolating the decomposed matrices.
+/// http://dev.w3.org/csswg/css-transforms/#matrix-interpolation
+///
+/// FIXME: Only 2D transforms without percentages can be turned into matrices for now;
+/// anything else animates discretely.
+fn interpolate_transform_list_as_matrix(from_list: &[TransformOperation],
+                                        to_list: &[TransformOperation],
+                                        time: f64) -> Option<TransformList> {
+    let from = match Matrix2D::from_transform_list(from_list).and_then(|m| m.decompose()) {
+        Some(from) => from,
+        None => return None,
+    };
+    let to = match Matrix2D::from_transform_list(to_list).and_then(|m| m.decompose()) {
+        Some(to) => to,
+        None => return None,
+    };
+    let matrix = from.interpolate(&to, time).recompose();
+    Some(TransformList(Some(vec![TransformOperation::Matrix(matrix.to_computed_matrix())])))
+}
+
+/// A 2D affine transform, as in `matrix(a, b, c, d, e, f)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Matrix2D {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Matrix2D {
+    fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Matrix2D {
+        Matrix2D { a: a, b: b, c: c, d: d, e: e, f: f }
+    }
+
+    fn identity() -> Matrix2D {
+        Matrix2D::new(1., 0., 0., 1., 0., 0.)
+    }
+
+    /// Returns the transform that applies `other` first and then `self`.
+    fn multiply(&self, other: &Matrix2D) -> Matrix2D {
+        Matrix2D::new(self.a * other.a + self.c * other.b,
+                      self.b * other.a + self.d * other.b,
+                      self.a * other.c + self.c * other.d,
+                      self.b * other.c + self.d * other.d,
+                      self.a * other.e + self.c * other.f + self.e,
+                      self.b * other.e + self.d * other.f + self.f)
+    }
+
+    /// Multiplies out a transform list, or returns `None` if it has 3D or
+    /// percentage-based components.
+    fn from_transform_list(list: &[TransformOperation]) -> Option<Matrix2D> {
+        let mut result = Matrix2D::identity();
+        for operation in list {
+            let matrix = match *operation {
+                TransformOperation::Matrix(ref m) => {
+                    if !m.is_2d() {
+                        return None
+                    }
+                    Matrix2D::new(m.m11 as f64, m.m12 as f64, m.m21 as f64, m.m22 as f64,
+                                  m.m41 as f64, m.m42 as f64)
                 }
-                (&TransformOperation::Scale(fx, fy, fz),
-                 &TransformOperation::Scale(tx, ty, tz)) => {
-                    let ix = fx.interpolate(&tx, time).unwrap();
-                    let iy = fy.interpolate(&ty, time).unwrap();
-                    let iz = fz.interpolate(&tz, time).unwrap();
-                    result.push(TransformOperation::Scale(ix, iy, iz));
+                TransformOperation::Skew(ax, ay) => {
+                    Matrix2D::new(1., (ay.radians() as f64).tan(), (ax.radians() as f64).tan(), 1., 0., 0.)
                 }
-                (&TransformOperation::Rotate(fx, fy, fz, fa),
-                 &TransformOperation::Rotate(_tx, _ty, _tz, _ta)) => {
-                    // TODO(gw): Implement matrix decomposition and interpolation
-                    result.push(TransformOperation::Rotate(fx, fy, fz, fa));
+                TransformOperation::Translate(LengthOrPercentage::Length(tx),
+                                              LengthOrPercentage::Length(ty),
+                                              Au(0)) => {
+                    Matrix2D::new(1., 0., 0., 1., tx.to_f64_px(), ty.to_f64_px())
                 }
-                (&TransformOperation::Perspective(fd),
-                 &TransformOperation::Perspective(_td)) => {
-                    // TODO(gw): Implement matrix decomposition and interpolation
-                    result.push(TransformOperation::Perspective(fd));
+                TransformOperation::Scale(sx, sy, sz) if sz == 1. => {
+                    Matrix2D::new(sx as f64, 0., 0., sy as f64, 0., 0.)
                 }
-                _ => {
-                    // This should be unreachable due to the can_interpolate_list() call.
-                    unreachable!();
+                TransformOperation::Rotate(x, y, z, angle) if x == 0. && y == 0. && z > 0. => {
+                    let (sin, cos) = (angle.radians() as f64).sin_cos();
+                    Matrix2D::new(cos, sin, -sin, cos, 0., 0.)
                 }
+                _ => return None,
+            };
+            result = result.multiply(&matrix);
+        }
+        Some(result)
+    }
+
+    /// Splits the matrix into a translation, a rotation and an upper triangular matrix
+    /// holding the scale and skew, or returns `None` if it isn't invertible.
+    fn decompose(&self) -> Option<DecomposedMatrix2D> {
+        let scale_x = self.a.hypot(self.b);
+        if scale_x == 0. {
+            return None
+        }
+        let angle = self.b.atan2(self.a);
+        let (sin, cos) = angle.sin_cos();
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant == 0. {
+            return None
+        }
+        Some(DecomposedMatrix2D {
+            translate_x: self.e,
+            translate_y: self.f,
+            angle: angle,
+            scale_x: scale_x,
+            skew: cos * self.c + sin * self.d,
+            scale_y: determinant / scale_x,
+        })
+    }
+
+    fn to_computed_matrix(&self) -> ComputedMatrix {
+        let mut matrix = ComputedMatrix::identity();
+        matrix.m11 = self.a as CSSFloat;
+        matrix.m12 = self.b as CSSFloat;
+        matrix.m21 = self.c as CSSFloat;
+        matrix.m22 = self.d as CSSFloat;
+        matrix.m41 = self.e as CSSFloat;
+        matrix.m42 = self.f as CSSFloat;
+        matrix
+    }
+}
+
+/// A 2D matrix decomposed into `translate(translate_x, translate_y) rotate(angle)
+/// matrix(scale_x, 0, skew, scale_y, 0, 0)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DecomposedMatrix2D {
+    translate_x: f64,
+    translate_y: f64,
+    /// In radians, between -π and π.
+    angle: f64,
+    scale_x: f64,
+    skew: f64,
+    scale_y: f64,
+}
+
+impl DecomposedMatrix2D {
+    fn from_matrix(matrix: &ComputedMatrix) -> Option<DecomposedMatrix2D> {
+        Matrix2D::from_transform_list(&[TransformOperation::Matrix(*matrix)]).and_then(|m| m.decompose())
+    }
+
+    fn interpolate(&self, other: &DecomposedMatrix2D, time: f64) -> DecomposedMatrix2D {
+        // Don't rotate the long way around.
+        let (mut from_angle, mut to_angle) = (self.angle, other.angle);
+        if (from_angle - to_angle).abs() > PI {
+            if from_angle < to_angle {
+                from_angle += 2. * PI;
+            } else {
+                to_angle += 2. * PI;
             }
         }
-    } else {
-        // TODO(gw): Implement matrix decomposition and interpolation
-        result.extend_from_slice(from_list);
+        DecomposedMatrix2D {
+            translate_x: self.translate_x.interpolate(&other.translate_x, time).unwrap(),
+            translate_y: self.translate_y.interpolate(&other.translate_y, time).unwrap(),
+            angle: from_angle.interpolate(&to_angle, time).unwrap(),
+            scale_x: self.scale_x.interpolate(&other.scale_x, time).unwrap(),
+            skew: self.skew.interpolate(&other.skew, time).unwrap(),
+            scale_y: self.scale_y.interpolate(&other.scale_y, time).unwrap(),
+        }
+    }
+
+    fn recompose(&self) -> Matrix2D {
+        let (sin, cos) = self.angle.sin_cos();
+        Matrix2D::new(cos * self.scale_x,
+                      sin * self.scale_x,
+                      cos * self.skew - sin * self.scale_y,
+                      sin * self.skew + cos * self.scale_y,
+                      self.translate_x,
+                      self.translate_y)
     }
 
-    TransformList(Some(result))
+    fn to_matrix(&self) -> ComputedMatrix {
+        self.recompose().to_computed_matrix()
+    }
 }
 
 /// Build an equivalent 'identity transform function list' based
@@ -888,7 +1409,7 @@ impl Interpolate for TransformList {
     #[inline]
     fn interpolate(&self, other: &TransformList, time: f64) -> Option<TransformList> {
         // http://dev.w3.org/csswg/css-transforms/#interpolation-of-transforms
-        let result = match (&self.0, &other.0) {
+        match (&self.0, &other.0) {
             (&Some(ref from_list), &Some(ref to_list)) => {
                 // Two lists of transforms
                 interpolate_transform_list(from_list, &to_list, time)
@@ -905,11 +1426,9 @@ impl Interpolate for TransformList {
             }
             _ => {
                 // http://dev.w3.org/csswg/css-transforms/#none-none-animation
-                TransformList(None)
+                Some(TransformList(None))
             }
-        };
-
-        Some(result)
+        }
     }
 }
 
@@ -927,6 +1446,51 @@ impl<T> GetMod for Vec<T> {
     }
 }
 
+/// The duration, delay and timing function a longhand transitions with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SingleTransition {
+    pub property: TransitionProperty,
+    pub duration: Time,
+    pub delay: Time,
+    pub timing_function: TransitionTimingFunction,
+}
+
+/// The transitions the `transition-*` longhands ask for, one per longhand, with `all`
+/// expanded and the lists of durations, delays and timing functions repeated to match
+/// `transition-property`. A longhand named more than once transitions the way its last
+/// mention says. Transitions whose combined duration isn't positive never run, so they're
+/// left out.
+///
+/// https://drafts.csswg.org/css-transitions/#transitions
+pub fn single_transitions(properties: &transition_property::computed_value::T,
+                          durations: &transition_duration::computed_value::T,
+                          delays: &transition_delay::computed_value::T,
+                          timing_functions: &transition_timing_function::computed_value::T)
+                          -> Vec<SingleTransition> {
+    let mut transitions: Vec<SingleTransition> = vec![];
+    for (i, &property) in properties.0.iter().enumerate() {
+        let longhands = if property == TransitionProperty::All {
+            transition_property::computed_value::ALL_TRANSITION_PROPERTIES.to_vec()
+        } else {
+            vec![property]
+        };
+        for longhand in longhands {
+            let transition = SingleTransition {
+                property: longhand,
+                duration: *durations.0.get_mod(i),
+                delay: *delays.0.get_mod(i),
+                timing_function: *timing_functions.0.get_mod(i),
+            };
+            match transitions.iter().position(|existing| existing.property == longhand) {
+                Some(index) => transitions[index] = transition,
+                None => transitions.push(transition),
+            }
+        }
+    }
+    transitions.retain(|transition| transition.duration.seconds().max(0.) + transition.delay.seconds() > 0.);
+    transitions
+}
+
 /// Inserts transitions into the queue of running animations as applicable for the given style
 /// difference. This is called from the layout worker threads. Returns true if any animations were
 /// kicked off and false otherwise.
@@ -9,8 +9,11 @@ use error_reporting::ParseErrorReporter;
 use euclid::Size2D;
 use matching::{ApplicableDeclarationsCache, StyleSharingCandidateCache};
 use properties::ComputedValues;
+use rule_tree::{RuleNodeStyleCache, RuleTree};
+use scratch::ScratchArena;
 use selector_impl::SelectorImplExt;
 use selector_matching::Stylist;
+use shared_lock::SharedRwLockReadGuard;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::mpsc::Sender;
@@ -38,6 +41,10 @@ pub struct SharedStyleContext<Impl: SelectorImplExt> {
     /// This can be used to easily check for invalid stale data.
     pub generation: u32,
 
+    /// What the styles the traversal computes are stamped with, for telling them apart from
+    /// the ones older rules or another device gave. Servo's layout doesn't look at it.
+    pub style_generation: usize,
+
     /// A channel on which new animations that have been triggered by style recalculation can be
     /// sent.
     pub new_animations_sender: Mutex<Sender<Animation>>,
@@ -53,11 +60,22 @@ pub struct SharedStyleContext<Impl: SelectorImplExt> {
 
     ///The CSS error reporter for all CSS loaded in this layout thread
     pub error_reporter: Box<ParseErrorReporter + Sync>,
+
+    /// The rule tree elements' matched rules are interned into, or None to cascade every
+    /// element that can't share a sibling's style.
+    pub rule_tree: Option<Arc<RuleTree>>,
+
+    /// Holds the lock of the style attributes and other declaration blocks elements bring
+    /// along for reading, for as long as the traversal runs.
+    pub guard: SharedRwLockReadGuard,
 }
 
 pub struct LocalStyleContext<C: ComputedValues> {
     pub applicable_declarations_cache: RefCell<ApplicableDeclarationsCache<C>>,
     pub style_sharing_candidate_cache: RefCell<StyleSharingCandidateCache<C>>,
+    pub rule_node_style_cache: RefCell<RuleNodeStyleCache<C>>,
+    /// The buffers elements match into, reused from one element to the next.
+    pub scratch: RefCell<ScratchArena>,
 }
 
 pub trait StyleContext<'a, Impl: SelectorImplExt, C: ComputedValues> {
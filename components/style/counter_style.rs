@@ -0,0 +1,337 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `@counter-style` rules, which define the counter styles `list-style-type` and
+//! `counter()` can use by name.
+//!
+//! Images aren't supported as symbols yet.
+//!
+//! https://drafts.csswg.org/css-counter-styles/
+
+use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Parser, Token};
+use media_queries::Device;
+use parser::{ParserContext, log_css_error};
+use selectors::parser::SelectorImpl;
+use std::ascii::AsciiExt;
+use std::sync::Arc;
+use string_cache::Atom;
+use stylesheets::{CSSRuleIteratorExt, Origin, Stylesheet};
+use values::specified::parse_integer;
+
+/// How a counter style turns a counter value into a representation.
+///
+/// https://drafts.csswg.org/css-counter-styles/#counter-style-system
+#[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum System {
+    Cyclic,
+    Numeric,
+    Alphabetic,
+    Symbolic,
+    Additive,
+    /// `fixed`, with the value of the first symbol.
+    Fixed(i32),
+    /// `extends`, with the name of the counter style whose algorithm is used.
+    Extends(Atom),
+}
+
+/// A symbol, which is a string or an identifier that stands for itself.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum Symbol {
+    String(String),
+    Ident(String),
+}
+
+impl Symbol {
+    /// The text the symbol stands for.
+    pub fn text(&self) -> &str {
+        match *self {
+            Symbol::String(ref text) | Symbol::Ident(ref text) => text,
+        }
+    }
+}
+
+/// The value of the `speak-as` descriptor.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum SpeakAs {
+    Auto,
+    Bullets,
+    Numbers,
+    Words,
+    SpellOut,
+    /// Speak the counter as the named counter style would.
+    CounterStyle(Atom),
+}
+
+/// An `@counter-style` rule. Descriptors that weren't given, or whose value was invalid,
+/// are None or empty.
+#[derive(Debug, HeapSizeOf, PartialEq)]
+pub struct CounterStyleRule {
+    pub name: Atom,
+    /// `symbolic` if the rule didn't give one.
+    pub system: System,
+    /// The symbols to put before and after the representation of negative values.
+    pub negative: Option<(Symbol, Option<Symbol>)>,
+    pub prefix: Option<Symbol>,
+    pub suffix: Option<Symbol>,
+    /// The ranges of values the counter style applies to, with None for an infinite bound,
+    /// or no ranges for `auto`.
+    pub range: Vec<(Option<i32>, Option<i32>)>,
+    /// The width representations are padded to, and the symbol they're padded with.
+    pub pad: Option<(u32, Symbol)>,
+    pub fallback: Option<Atom>,
+    pub symbols: Vec<Symbol>,
+    /// The weights and symbols of an `additive` system, by decreasing weight.
+    pub additive_symbols: Vec<(u32, Symbol)>,
+    pub speak_as: Option<SpeakAs>,
+}
+
+/// Finds the `@counter-style` rule defining the counter style called `name`, given every
+/// sheet in document order. Author rules win over user rules, which win over user agent
+/// rules, and later rules win within an origin.
+pub fn find_counter_style_rule<'a, Impl>(stylesheets: &'a [Arc<Stylesheet<Impl>>], device: &'a Device,
+                                         name: &Atom) -> Option<&'a Arc<CounterStyleRule>>
+                                         where Impl: SelectorImpl + 'a {
+    fn origin_rank(origin: Origin) -> u8 {
+        match origin {
+            Origin::UserAgent => 0,
+            Origin::User => 1,
+            Origin::Author => 2,
+        }
+    }
+
+    let mut winner: Option<(&Arc<CounterStyleRule>, u8)> = None;
+    for stylesheet in stylesheets {
+        if stylesheet.disabled() || !stylesheet.is_effective_for_device(device) {
+            continue
+        }
+        let rank = origin_rank(stylesheet.origin);
+        for rule in stylesheet.effective_rules(device).counter_style() {
+            if rule.name == *name && winner.map_or(true, |(_, winning_rank)| rank >= winning_rank) {
+                winner = Some((rule, rank));
+            }
+        }
+    }
+    winner.map(|(rule, _)| rule)
+}
+
+/// The counter styles only user agent sheets may define.
+///
+/// https://drafts.csswg.org/css-counter-styles/#the-counter-style-rule
+const PREDEFINED_NAMES: &'static [&'static str] = &[
+    "decimal", "disc", "square", "circle", "disclosure-open", "disclosure-closed",
+];
+
+/// Parses the name in the prelude of an `@counter-style` rule.
+pub fn parse_counter_style_name_definition(context: &ParserContext, input: &mut Parser) -> Result<Atom, ()> {
+    let name = try!(parse_counter_style_name(input));
+    if context.stylesheet_origin != Origin::UserAgent &&
+       PREDEFINED_NAMES.iter().any(|predefined| name.eq_ignore_ascii_case(predefined)) {
+        return Err(())
+    }
+    Ok(name)
+}
+
+/// `<counter-style-name>`, which is any identifier but `none`.
+fn parse_counter_style_name(input: &mut Parser) -> Result<Atom, ()> {
+    let name = try!(input.expect_ident());
+    if name.eq_ignore_ascii_case("none") {
+        return Err(())
+    }
+    Ok(Atom::from(&*name))
+}
+
+fn parse_symbol(input: &mut Parser) -> Result<Symbol, ()> {
+    match try!(input.next()) {
+        Token::QuotedString(value) => Ok(Symbol::String(value.into_owned())),
+        Token::Ident(value) => Ok(Symbol::Ident(value.into_owned())),
+        _ => Err(())
+    }
+}
+
+fn parse_non_negative_integer(input: &mut Parser) -> Result<u32, ()> {
+    let value = try!(parse_integer(input));
+    if value < 0 {
+        return Err(())
+    }
+    Ok(value as u32)
+}
+
+fn parse_system(input: &mut Parser) -> Result<System, ()> {
+    let ident = try!(input.expect_ident());
+    match_ignore_ascii_case! { ident,
+        "cyclic" => Ok(System::Cyclic),
+        "numeric" => Ok(System::Numeric),
+        "alphabetic" => Ok(System::Alphabetic),
+        "symbolic" => Ok(System::Symbolic),
+        "additive" => Ok(System::Additive),
+        "fixed" => Ok(System::Fixed(input.try(parse_integer).unwrap_or(1))),
+        "extends" => parse_counter_style_name(input).map(System::Extends),
+        _ => Err(())
+    }
+}
+
+/// `[ <integer> | infinite ]{2}`, with the lower bound no greater than the upper one.
+fn parse_range_bounds(input: &mut Parser) -> Result<(Option<i32>, Option<i32>), ()> {
+    fn parse_bound(input: &mut Parser) -> Result<Option<i32>, ()> {
+        if input.try(|input| input.expect_ident_matching("infinite")).is_ok() {
+            return Ok(None)
+        }
+        parse_integer(input).map(Some)
+    }
+    let lower = try!(parse_bound(input));
+    let upper = try!(parse_bound(input));
+    if let (Some(lower), Some(upper)) = (lower, upper) {
+        if lower > upper {
+            return Err(())
+        }
+    }
+    Ok((lower, upper))
+}
+
+/// `<integer [0,∞]> && <symbol>`, for `pad` and `additive-symbols`.
+fn parse_weighted_symbol(input: &mut Parser) -> Result<(u32, Symbol), ()> {
+    if let Ok(width) = input.try(parse_non_negative_integer) {
+        return parse_symbol(input).map(|symbol| (width, symbol))
+    }
+    let symbol = try!(parse_symbol(input));
+    parse_non_negative_integer(input).map(|width| (width, symbol))
+}
+
+fn parse_speak_as(input: &mut Parser) -> Result<SpeakAs, ()> {
+    let keyword = input.try(|input| {
+        let ident = try!(input.expect_ident());
+        match_ignore_ascii_case! { ident,
+            "auto" => Ok(SpeakAs::Auto),
+            "bullets" => Ok(SpeakAs::Bullets),
+            "numbers" => Ok(SpeakAs::Numbers),
+            "words" => Ok(SpeakAs::Words),
+            "spell-out" => Ok(SpeakAs::SpellOut),
+            _ => Err(())
+        }
+    });
+    keyword.or_else(|()| parse_counter_style_name(input).map(SpeakAs::CounterStyle))
+}
+
+enum CounterStyleDescriptor {
+    System(System),
+    Negative(Symbol, Option<Symbol>),
+    Prefix(Symbol),
+    Suffix(Symbol),
+    Range(Vec<(Option<i32>, Option<i32>)>),
+    Pad(u32, Symbol),
+    Fallback(Atom),
+    Symbols(Vec<Symbol>),
+    AdditiveSymbols(Vec<(u32, Symbol)>),
+    SpeakAs(SpeakAs),
+}
+
+struct CounterStyleDescriptorParser;
+
+/// Default methods reject all at rules.
+impl AtRuleParser for CounterStyleDescriptorParser {
+    type Prelude = ();
+    type AtRule = CounterStyleDescriptor;
+}
+
+impl DeclarationParser for CounterStyleDescriptorParser {
+    type Declaration = CounterStyleDescriptor;
+
+    fn parse_value(&self, name: &str, input: &mut Parser) -> Result<CounterStyleDescriptor, ()> {
+        match_ignore_ascii_case! { name,
+            "system" => parse_system(input).map(CounterStyleDescriptor::System),
+            "negative" => {
+                let before = try!(parse_symbol(input));
+                let after = input.try(parse_symbol).ok();
+                Ok(CounterStyleDescriptor::Negative(before, after))
+            },
+            "prefix" => parse_symbol(input).map(CounterStyleDescriptor::Prefix),
+            "suffix" => parse_symbol(input).map(CounterStyleDescriptor::Suffix),
+            "range" => {
+                if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
+                    return Ok(CounterStyleDescriptor::Range(vec![]))
+                }
+                input.parse_comma_separated(parse_range_bounds).map(CounterStyleDescriptor::Range)
+            },
+            "pad" => parse_weighted_symbol(input).map(|(width, symbol)| CounterStyleDescriptor::Pad(width, symbol)),
+            "fallback" => parse_counter_style_name(input).map(CounterStyleDescriptor::Fallback),
+            "symbols" => {
+                let mut symbols = vec![try!(parse_symbol(input))];
+                while let Ok(symbol) = input.try(parse_symbol) {
+                    symbols.push(symbol);
+                }
+                Ok(CounterStyleDescriptor::Symbols(symbols))
+            },
+            "additive-symbols" => {
+                let symbols = try!(input.parse_comma_separated(parse_weighted_symbol));
+                // Weights have to be strictly decreasing.
+                if symbols.windows(2).any(|pair| pair[0].0 <= pair[1].0) {
+                    return Err(())
+                }
+                Ok(CounterStyleDescriptor::AdditiveSymbols(symbols))
+            },
+            "speak-as" => parse_speak_as(input).map(CounterStyleDescriptor::SpeakAs),
+            _ => Err(())
+        }
+    }
+}
+
+/// Parses the block of an `@counter-style` rule called `name`. Invalid descriptors are
+/// dropped, but the whole rule is invalid if its system lacks the symbols it needs, or if
+/// it extends another counter style and gives symbols of its own.
+pub fn parse_counter_style_block(context: &ParserContext, input: &mut Parser, name: Atom)
+                                 -> Result<CounterStyleRule, ()> {
+    let mut rule = CounterStyleRule {
+        name: name,
+        system: System::Symbolic,
+        negative: None,
+        prefix: None,
+        suffix: None,
+        range: vec![],
+        pad: None,
+        fallback: None,
+        symbols: vec![],
+        additive_symbols: vec![],
+        speak_as: None,
+    };
+    let (mut has_symbols, mut has_additive_symbols) = (false, false);
+    let mut iter = DeclarationListParser::new(input, CounterStyleDescriptorParser);
+    while let Some(descriptor) = iter.next() {
+        match descriptor {
+            Ok(CounterStyleDescriptor::System(system)) => rule.system = system,
+            Ok(CounterStyleDescriptor::Negative(before, after)) => rule.negative = Some((before, after)),
+            Ok(CounterStyleDescriptor::Prefix(prefix)) => rule.prefix = Some(prefix),
+            Ok(CounterStyleDescriptor::Suffix(suffix)) => rule.suffix = Some(suffix),
+            Ok(CounterStyleDescriptor::Range(range)) => rule.range = range,
+            Ok(CounterStyleDescriptor::Pad(width, symbol)) => rule.pad = Some((width, symbol)),
+            Ok(CounterStyleDescriptor::Fallback(fallback)) => rule.fallback = Some(fallback),
+            Ok(CounterStyleDescriptor::Symbols(symbols)) => {
+                rule.symbols = symbols;
+                has_symbols = true;
+            }
+            Ok(CounterStyleDescriptor::AdditiveSymbols(symbols)) => {
+                rule.additive_symbols = symbols;
+                has_additive_symbols = true;
+            }
+            Ok(CounterStyleDescriptor::SpeakAs(speak_as)) => rule.speak_as = Some(speak_as),
+            Err(range) => {
+                let pos = range.start;
+                let message = format!("Unsupported @counter-style descriptor declaration: '{}'",
+                                      iter.input.slice(range));
+                log_css_error(iter.input, pos, &*message, context);
+            }
+        }
+    }
+
+    // https://drafts.csswg.org/css-counter-styles/#counter-style-symbols
+    let valid = match rule.system {
+        System::Cyclic | System::Fixed(_) | System::Symbolic => !rule.symbols.is_empty(),
+        System::Alphabetic | System::Numeric => rule.symbols.len() >= 2,
+        System::Additive => !rule.additive_symbols.is_empty(),
+        System::Extends(_) => !has_symbols && !has_additive_symbols,
+    };
+    if !valid {
+        return Err(())
+    }
+    Ok(rule)
+}
@@ -41,7 +41,7 @@ pub struct BorrowedSpecifiedValue<'a> {
     references: Option<&'a HashSet<Name>>,
 }
 
-#[derive(Clone, HeapSizeOf, Debug)]
+#[derive(Clone, PartialEq, HeapSizeOf, Debug)]
 pub struct ComputedValue {
     css: String,
     first_token_type: TokenSerializationType,
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use properties::ComputedValues;
+use rule_tree::RuleNode;
 use selectors::parser::SelectorImpl;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
@@ -17,6 +18,10 @@ pub struct PrivateStyleData<Impl: SelectorImpl, ConcreteComputedValues: Computed
     pub per_pseudo: HashMap<Impl::PseudoElement, Arc<ConcreteComputedValues>,
                             BuildHasherDefault<::fnv::FnvHasher>>,
 
+    /// The rule node for the rules the node matched, if the rule tree is in use and the node
+    /// didn't share its style with a sibling.
+    pub rule_node: Option<Arc<RuleNode>>,
+
     /// Information needed during parallel traversals.
     pub parallel: DomParallelInfo,
 }
@@ -27,6 +32,7 @@ impl<Impl, ConcreteComputedValues> PrivateStyleData<Impl, ConcreteComputedValues
         PrivateStyleData {
             style: None,
             per_pseudo: HashMap::with_hasher(Default::default()),
+            rule_node: None,
             parallel: DomParallelInfo::new(),
         }
     }
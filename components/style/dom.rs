@@ -7,10 +7,12 @@
 use data::PrivateStyleData;
 use element_state::ElementState;
 use properties::{ComputedValues, PropertyDeclaration, PropertyDeclarationBlock};
-use restyle_hints::{ElementSnapshot, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS, RESTYLE_SELF, RestyleHint};
+use restyle_hints::{ServoElementSnapshot, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS, RESTYLE_SELF, RestyleHint};
 use selector_impl::ElementExt;
+use selector_matching::CascadeLevel;
 use selectors::Element;
 use selectors::matching::DeclarationBlock;
+use shared_lock::SharedRwLockReadGuard;
 use smallvec::VecLike;
 use std::cell::{Ref, RefMut};
 use std::ops::BitOr;
@@ -76,6 +78,15 @@ pub trait TNode : Sized + Copy + Clone {
         }
     }
 
+    /// Calls `f` with each child of this node, in order, for the parallel traversal.
+    /// Implementations can override this to fetch children in batches, since the tree
+    /// isn't changed while the traversal runs.
+    fn each_child<F>(&self, mut f: F) where F: FnMut(Self) {
+        for kid in self.children() {
+            f(kid);
+        }
+    }
+
     fn rev_children(self) -> ReverseChildrenIterator<Self> {
         ReverseChildrenIterator {
             current: self.last_child(),
@@ -188,7 +199,7 @@ pub trait TDocument : Sized + Copy + Clone {
 
     fn root_node(&self) -> Option<Self::ConcreteNode>;
 
-    fn drain_modified_elements(&self) -> Vec<(Self::ConcreteElement, ElementSnapshot)>;
+    fn drain_modified_elements(&self) -> Vec<(Self::ConcreteElement, ServoElementSnapshot)>;
 }
 
 pub trait TElement : Sized + Copy + Clone + ElementExt {
@@ -197,13 +208,27 @@ pub trait TElement : Sized + Copy + Clone + ElementExt {
 
     fn as_node(&self) -> Self::ConcreteNode;
 
-    fn style_attribute(&self) -> &Option<PropertyDeclarationBlock>;
+    /// The declarations of the element's style attribute. The embedder may keep them behind
+    /// a `SharedRwLock`, which `guard` holds, as it does for the blocks the next two methods
+    /// push.
+    fn style_attribute<'a>(&'a self, guard: &'a SharedRwLockReadGuard) -> &'a Option<PropertyDeclarationBlock>;
 
     fn get_state(&self) -> ElementState;
 
-    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, &mut V)
+    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, &SharedRwLockReadGuard, &mut V)
         where V: VecLike<DeclarationBlock<Vec<PropertyDeclaration>>>;
 
+    /// Pushes the current values of the element's animations that cascade at `level`, one of
+    /// `CascadeLevel::animations()`. Embedders that animate styles some other way push none.
+    fn get_animation_declarations<V>(&self, _level: CascadeLevel, _: &SharedRwLockReadGuard, _: &mut V)
+        where V: VecLike<DeclarationBlock<Vec<PropertyDeclaration>>> {}
+
+    /// Whether the element is content the embedder made up to render something, like the
+    /// parts of a scrollbar, which only user agent rules apply to.
+    fn is_native_anonymous(&self) -> bool {
+        false
+    }
+
     fn get_attr<'a>(&'a self, namespace: &Namespace, attr: &Atom) -> Option<&'a str>;
     fn get_attrs<'a>(&'a self, attr: &Atom) -> Vec<&'a str>;
 
@@ -5,7 +5,7 @@
 bitflags! {
     #[doc = "Event-based element states."]
     #[derive(HeapSizeOf)]
-    flags ElementState: u8 {
+    flags ElementState: u16 {
         #[doc = "The mouse is down on this element. \
                  https://html.spec.whatwg.org/multipage/#selector-active \
                  FIXME(#7333): set/unset this when appropriate"]
@@ -27,5 +27,11 @@ bitflags! {
         const IN_CHECKED_STATE = 0x20,
         #[doc = "https://html.spec.whatwg.org/multipage/#selector-indeterminate"]
         const IN_INDETERMINATE_STATE = 0x40,
+        #[doc = "This element or one of its descendants has focus. \
+                 https://drafts.csswg.org/selectors-4/#the-focus-within-pseudo"]
+        const IN_FOCUS_WITHIN_STATE = 0x80,
+        #[doc = "This element is showing its placeholder text. \
+                 https://html.spec.whatwg.org/multipage/#selector-placeholder-shown"]
+        const IN_PLACEHOLDER_SHOWN_STATE = 0x100,
     }
 }
@@ -4,9 +4,25 @@
 
 use cssparser::{Parser, SourcePosition};
 use log;
+use selector_impl::InvalidSelector;
+
+/// More than a message about a parse error, for reporters that can point at what went wrong.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorDetails<'a> {
+    /// A style rule was dropped because of one of its selectors.
+    InvalidSelector(InvalidSelector<'a>),
+}
 
 pub trait ParseErrorReporter {
     fn report_error(&self, input: &mut Parser, position: SourcePosition, message: &str);
+
+    /// Reports an error that `details` say more about. Reporters that have no use for them
+    /// get `message`, like any other error.
+    fn report_error_with_details(&self, input: &mut Parser, position: SourcePosition, message: &str,
+                                 _details: &ParseErrorDetails) {
+        self.report_error(input, position, message)
+    }
+
     fn clone(&self) -> Box<ParseErrorReporter + Send + Sync>;
 }
 
@@ -3,10 +3,19 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use computed_values::font_family::FontFamily;
-use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Parser};
+use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Parser, Token};
+use media_queries::Device;
 use parser::{ParserContext, log_css_error};
 use properties::longhands::font_family::parse_one_family;
+use properties::longhands::font_stretch::computed_value::T as FontStretch;
+use properties::longhands::font_style::computed_value::T as FontStyle;
+use properties::longhands::font_weight::computed_value::T as FontWeight;
+use properties::longhands::{font_stretch, font_style, font_weight};
+use selectors::parser::SelectorImpl;
 use std::ascii::AsciiExt;
+use std::cmp;
+use std::sync::Arc;
+use stylesheets::{CSSRule, Origin, Stylesheet};
 use url::Url;
 
 #[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq, Deserialize, Serialize)]
@@ -21,16 +30,35 @@ pub struct UrlSource {
     pub format_hints: Vec<String>,
 }
 
-#[derive(Debug, HeapSizeOf, PartialEq, Eq)]
+/// A range of code points, as in `unicode-range: U+0-7F`. Both ends are inclusive.
+#[derive(Clone, Copy, Debug, HeapSizeOf, PartialEq, Eq)]
+pub struct UnicodeRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// The highest code point, which `U+` ranges are clamped to.
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+#[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq)]
 pub struct FontFaceRule {
     pub family: FontFamily,
     pub sources: Vec<Source>,
+    /// The code points the font covers. All of them if `unicode-range` wasn't given.
+    pub unicode_range: Vec<UnicodeRange>,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+    pub stretch: FontStretch,
 }
 
 pub fn parse_font_face_block(context: &ParserContext, input: &mut Parser)
                              -> Result<FontFaceRule, ()> {
     let mut family = None;
     let mut src = None;
+    let mut unicode_range = vec![UnicodeRange { start: 0, end: MAX_CODE_POINT }];
+    let mut weight = FontWeight::Weight400;
+    let mut style = FontStyle::normal;
+    let mut stretch = FontStretch::normal;
     let mut iter = DeclarationListParser::new(input, FontFaceRuleParser { context: context });
     while let Some(declaration) = iter.next() {
         match declaration {
@@ -46,6 +74,18 @@ pub fn parse_font_face_block(context: &ParserContext, input: &mut Parser)
             Ok(FontFaceDescriptorDeclaration::Src(value)) => {
                 src = Some(value);
             }
+            Ok(FontFaceDescriptorDeclaration::UnicodeRange(value)) => {
+                unicode_range = value;
+            }
+            Ok(FontFaceDescriptorDeclaration::Weight(value)) => {
+                weight = value;
+            }
+            Ok(FontFaceDescriptorDeclaration::Style(value)) => {
+                style = value;
+            }
+            Ok(FontFaceDescriptorDeclaration::Stretch(value)) => {
+                stretch = value;
+            }
         }
     }
     match (family, src) {
@@ -53,15 +93,67 @@ pub fn parse_font_face_block(context: &ParserContext, input: &mut Parser)
             Ok(FontFaceRule {
                 family: family,
                 sources: src,
+                unicode_range: unicode_range,
+                weight: weight,
+                style: style,
+                stretch: stretch,
             })
         }
         _ => Err(())
     }
 }
 
+/// Collects the `@font-face` rules that apply to `device` from every sheet, in cascade
+/// order, along with the sheet each one comes from. Rules in `@import`ed sheets are
+/// attributed to the imported sheet. Families aren't deduplicated: if several rules
+/// define the same face, it's up to the font loader which one wins.
+///
+/// The rules are copied, so that the CSSOM can change the sheets while the result is
+/// alive.
+pub fn effective_font_face_rules<Impl>(stylesheets: &[Arc<Stylesheet<Impl>>], device: &Device)
+                                       -> Vec<(Arc<Stylesheet<Impl>>, FontFaceRule)>
+                                       where Impl: SelectorImpl {
+    fn collect<Impl>(stylesheet: &Arc<Stylesheet<Impl>>, rules: &[CSSRule<Impl>], device: &Device,
+                     result: &mut Vec<(Arc<Stylesheet<Impl>>, FontFaceRule)>)
+                     where Impl: SelectorImpl {
+        for rule in rules {
+            match *rule {
+                CSSRule::FontFace(ref font_face) => result.push((stylesheet.clone(), font_face.clone())),
+                CSSRule::Media(ref media) if media.evaluate(device) => {
+                    collect(stylesheet, &media.rules, device, result)
+                }
+                CSSRule::Supports(ref supports) if supports.enabled => {
+                    collect(stylesheet, &supports.rules, device, result)
+                }
+                CSSRule::Import(ref import) if import.evaluate(device) => {
+                    if let Some(imported) = import.stylesheet() {
+                        collect(&imported, &imported.read_rules(), device, result)
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut result = vec![];
+    for origin in &[Origin::UserAgent, Origin::User, Origin::Author] {
+        for stylesheet in stylesheets {
+            if stylesheet.origin != *origin || stylesheet.disabled() || !stylesheet.is_effective_for_device(device) {
+                continue
+            }
+            collect(stylesheet, &stylesheet.read_rules(), device, &mut result);
+        }
+    }
+    result
+}
+
 enum FontFaceDescriptorDeclaration {
     Family(FontFamily),
     Src(Vec<Source>),
+    UnicodeRange(Vec<UnicodeRange>),
+    Weight(FontWeight),
+    Style(FontStyle),
+    Stretch(FontStretch),
 }
 
 
@@ -91,11 +183,54 @@ impl<'a, 'b> DeclarationParser for FontFaceRuleParser<'a, 'b> {
                     parse_one_src(self.context, input)
                 }))))
             },
+            "unicode-range" => {
+                Ok(FontFaceDescriptorDeclaration::UnicodeRange(try!(
+                            input.parse_comma_separated(parse_one_unicode_range))))
+            },
+            "font-weight" => {
+                Ok(FontFaceDescriptorDeclaration::Weight(try!(parse_font_weight(self.context, input))))
+            },
+            "font-style" => {
+                Ok(FontFaceDescriptorDeclaration::Style(try!(font_style::parse(self.context, input))))
+            },
+            "font-stretch" => {
+                Ok(FontFaceDescriptorDeclaration::Stretch(try!(font_stretch::parse(self.context, input))))
+            },
             _ => Err(())
         }
     }
 }
 
+/// Unlike the property, the `font-weight` descriptor only takes absolute weights.
+/// https://drafts.csswg.org/css-fonts/#descdef-font-weight
+fn parse_font_weight(context: &ParserContext, input: &mut Parser) -> Result<FontWeight, ()> {
+    match try!(font_weight::parse(context, input)) {
+        font_weight::SpecifiedValue::Bolder | font_weight::SpecifiedValue::Lighter => Err(()),
+        font_weight::SpecifiedValue::Weight100 => Ok(FontWeight::Weight100),
+        font_weight::SpecifiedValue::Weight200 => Ok(FontWeight::Weight200),
+        font_weight::SpecifiedValue::Weight300 => Ok(FontWeight::Weight300),
+        font_weight::SpecifiedValue::Weight400 => Ok(FontWeight::Weight400),
+        font_weight::SpecifiedValue::Weight500 => Ok(FontWeight::Weight500),
+        font_weight::SpecifiedValue::Weight600 => Ok(FontWeight::Weight600),
+        font_weight::SpecifiedValue::Weight700 => Ok(FontWeight::Weight700),
+        font_weight::SpecifiedValue::Weight800 => Ok(FontWeight::Weight800),
+        font_weight::SpecifiedValue::Weight900 => Ok(FontWeight::Weight900),
+    }
+}
+
+/// https://drafts.csswg.org/css-fonts/#descdef-unicode-range
+fn parse_one_unicode_range(input: &mut Parser) -> Result<UnicodeRange, ()> {
+    match try!(input.next()) {
+        Token::UnicodeRange(start, end) if start <= end && start <= MAX_CODE_POINT => {
+            Ok(UnicodeRange {
+                start: start,
+                end: cmp::min(end, MAX_CODE_POINT),
+            })
+        }
+        _ => Err(())
+    }
+}
+
 fn parse_one_src(context: &ParserContext, input: &mut Parser) -> Result<Source, ()> {
     if input.try(|input| input.expect_function_matching("local")).is_ok() {
         return Ok(Source::Local(try!(input.parse_nested_block(parse_one_family))))
@@ -0,0 +1,208 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Delimiter, Parser};
+use cssparser::{QualifiedRuleParser, RuleListParser, Token, parse_important};
+use media_queries::Device;
+use parser::{ParserContext, log_css_error};
+use properties::longhands::transition_timing_function;
+use properties::longhands::transition_timing_function::computed_value::TransitionTimingFunction;
+use properties::{PropertyDeclaration, PropertyDeclarationParseResult, deduplicate_property_declarations};
+use selectors::parser::SelectorImpl;
+use std::ascii::AsciiExt;
+use std::sync::Arc;
+use string_cache::Atom;
+use stylesheets::{CSSRuleIteratorExt, Origin, Stylesheet};
+
+/// A `@keyframes` rule.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub struct KeyframesRule {
+    pub name: Atom,
+    /// Whether this is a vendor-prefixed rule like `@-webkit-keyframes`, which only
+    /// applies if there's no unprefixed rule with the same name.
+    pub vendor_prefixed: bool,
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// A block inside a `@keyframes` rule, like `from, 50% { ... }`.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub struct Keyframe {
+    /// The offsets the keyframe applies at, between 0 and 1.
+    pub offsets: Vec<f32>,
+    /// The `animation-timing-function` used from this keyframe to the next, if any.
+    pub timing_function: Option<TransitionTimingFunction>,
+    #[ignore_heap_size_of = "#7038"]
+    pub declarations: Arc<Vec<PropertyDeclaration>>,
+}
+
+/// The declarations that apply at a single offset of an animation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyframesStep {
+    pub offset: f32,
+    pub timing_function: Option<TransitionTimingFunction>,
+    pub declarations: Arc<Vec<PropertyDeclaration>>,
+}
+
+impl KeyframesRule {
+    /// Returns one step per offset the rule mentions, sorted by offset. If several
+    /// keyframes have the same offset, the last one wins.
+    pub fn steps(&self) -> Vec<KeyframesStep> {
+        let mut steps: Vec<KeyframesStep> = vec![];
+        for keyframe in &self.keyframes {
+            for &offset in &keyframe.offsets {
+                let step = KeyframesStep {
+                    offset: offset,
+                    timing_function: keyframe.timing_function,
+                    declarations: keyframe.declarations.clone(),
+                };
+                match steps.iter().position(|existing| existing.offset == offset) {
+                    Some(index) => steps[index] = step,
+                    None => steps.push(step),
+                }
+            }
+        }
+        // Offsets are always between 0 and 1, so they can't be NaN.
+        steps.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap());
+        steps
+    }
+}
+
+/// Finds the `@keyframes` rule defining the animation called `name`, given every sheet
+/// in document order. Author rules win over user rules, which win over user agent
+/// rules, and later rules win within an origin. Vendor-prefixed rules are only used
+/// if there's no unprefixed one.
+pub fn find_keyframes_rule<'a, Impl>(stylesheets: &'a [Arc<Stylesheet<Impl>>], device: &'a Device,
+                                     name: &Atom) -> Option<&'a KeyframesRule>
+                                     where Impl: SelectorImpl + 'a {
+    fn origin_rank(origin: Origin) -> u8 {
+        match origin {
+            Origin::UserAgent => 0,
+            Origin::User => 1,
+            Origin::Author => 2,
+        }
+    }
+
+    let mut winner: Option<(&KeyframesRule, (bool, u8))> = None;
+    for stylesheet in stylesheets {
+        if stylesheet.disabled() || !stylesheet.is_effective_for_device(device) {
+            continue
+        }
+        for rule in stylesheet.effective_rules(device).keyframes() {
+            if rule.name != *name {
+                continue
+            }
+            let rank = (!rule.vendor_prefixed, origin_rank(stylesheet.origin));
+            if winner.map_or(true, |(_, winning_rank)| rank >= winning_rank) {
+                winner = Some((rule, rank));
+            }
+        }
+    }
+    winner.map(|(rule, _)| rule)
+}
+
+pub fn parse_keyframe_list(context: &ParserContext, input: &mut Parser) -> Vec<Keyframe> {
+    let mut iter = RuleListParser::new_for_nested_rule(input, KeyframeListParser { context: context });
+    let mut keyframes = vec![];
+    while let Some(result) = iter.next() {
+        match result {
+            Ok(keyframe) => keyframes.push(keyframe),
+            Err(range) => {
+                let pos = range.start;
+                let message = format!("Invalid keyframe: '{}'", iter.input.slice(range));
+                log_css_error(iter.input, pos, &*message, context);
+            }
+        }
+    }
+    keyframes
+}
+
+struct KeyframeListParser<'a, 'b: 'a> {
+    context: &'a ParserContext<'b>,
+}
+
+/// Default methods reject all at rules.
+impl<'a, 'b> AtRuleParser for KeyframeListParser<'a, 'b> {
+    type Prelude = ();
+    type AtRule = Keyframe;
+}
+
+impl<'a, 'b> QualifiedRuleParser for KeyframeListParser<'a, 'b> {
+    type Prelude = Vec<f32>;
+    type QualifiedRule = Keyframe;
+
+    fn parse_prelude(&self, input: &mut Parser) -> Result<Vec<f32>, ()> {
+        input.parse_comma_separated(|input| {
+            match try!(input.next()) {
+                Token::Ident(ref ident) if ident.eq_ignore_ascii_case("from") => Ok(0.),
+                Token::Ident(ref ident) if ident.eq_ignore_ascii_case("to") => Ok(1.),
+                Token::Percentage(ref value) if value.unit_value >= 0. && value.unit_value <= 1. => {
+                    Ok(value.unit_value)
+                }
+                _ => Err(())
+            }
+        })
+    }
+
+    fn parse_block(&self, offsets: Vec<f32>, input: &mut Parser) -> Result<Keyframe, ()> {
+        let mut timing_function = None;
+        let mut declarations = vec![];
+        let mut iter = DeclarationListParser::new(input, KeyframeDeclarationParser { context: self.context });
+        while let Some(declaration) = iter.next() {
+            match declaration {
+                Ok(KeyframeDeclaration::TimingFunction(value)) => timing_function = Some(value),
+                Ok(KeyframeDeclaration::Properties(results)) => declarations.extend(results),
+                Err(range) => {
+                    let pos = range.start;
+                    let message = format!("Unsupported keyframe property declaration: '{}'",
+                                          iter.input.slice(range));
+                    log_css_error(iter.input, pos, &*message, self.context);
+                }
+            }
+        }
+        Ok(Keyframe {
+            offsets: offsets,
+            timing_function: timing_function,
+            declarations: Arc::new(deduplicate_property_declarations(declarations)),
+        })
+    }
+}
+
+enum KeyframeDeclaration {
+    TimingFunction(TransitionTimingFunction),
+    Properties(Vec<PropertyDeclaration>),
+}
+
+struct KeyframeDeclarationParser<'a, 'b: 'a> {
+    context: &'a ParserContext<'b>,
+}
+
+/// Default methods reject all at rules.
+impl<'a, 'b> AtRuleParser for KeyframeDeclarationParser<'a, 'b> {
+    type Prelude = ();
+    type AtRule = KeyframeDeclaration;
+}
+
+impl<'a, 'b> DeclarationParser for KeyframeDeclarationParser<'a, 'b> {
+    type Declaration = KeyframeDeclaration;
+
+    fn parse_value(&self, name: &str, input: &mut Parser) -> Result<KeyframeDeclaration, ()> {
+        let declaration = try!(input.parse_until_before(Delimiter::Bang, |input| {
+            if name.eq_ignore_ascii_case("animation-timing-function") {
+                return transition_timing_function::parse_one(input).map(KeyframeDeclaration::TimingFunction)
+            }
+            let mut results = vec![];
+            match PropertyDeclaration::parse(name, self.context, input, &mut results) {
+                PropertyDeclarationParseResult::ValidOrIgnoredDeclaration => {
+                    Ok(KeyframeDeclaration::Properties(results))
+                }
+                _ => Err(())
+            }
+        }));
+        // !important declarations are ignored inside keyframes.
+        if input.try(parse_important).is_ok() {
+            return Err(())
+        }
+        Ok(declaration)
+    }
+}
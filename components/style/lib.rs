@@ -50,24 +50,36 @@ pub mod animation;
 pub mod attr;
 pub mod bezier;
 pub mod context;
+pub mod counter_style;
 pub mod custom_properties;
 pub mod data;
 pub mod dom;
 pub mod element_state;
 pub mod error_reporting;
 pub mod font_face;
+pub mod keyframes;
 pub mod logical_geometry;
+pub mod malloc_size_of;
 pub mod matching;
 pub mod media_queries;
+pub mod nth_index;
+pub mod page;
 pub mod parallel;
 pub mod parser;
 pub mod restyle_hints;
+pub mod rule_tree;
+pub mod scratch;
 pub mod selector_impl;
 pub mod selector_matching;
 pub mod sequential;
 pub mod servo;
+pub mod shared_lock;
 pub mod stylesheets;
+pub mod supports;
+pub mod system_values;
 pub mod traversal;
+pub mod traversal_stats;
+pub mod url_value;
 #[macro_use]
 #[allow(non_camel_case_types)]
 pub mod values;
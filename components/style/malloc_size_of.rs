@@ -0,0 +1,203 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Measuring heap usage with a size function the embedder provides, like Gecko's
+//! `moz_malloc_size_of`, for memory reporters.
+//!
+//! Unlike `heapsize`, which skips what's behind an `Arc` (#7038), this measures it, but
+//! only the first time a report comes across it: `MallocSizeOfOps` remembers what it has
+//! seen. The values inside rules, which don't share anything, are still measured with
+//! `heapsize`.
+
+#![allow(unsafe_code)]
+
+use counter_style::CounterStyleRule;
+use custom_properties::ComputedValuesMap;
+use font_face::FontFaceRule;
+use heapsize::HeapSizeOf;
+use keyframes::{Keyframe, KeyframesRule};
+use media_queries::MediaQueryList;
+use page::PageRule;
+use properties::{PropertyDeclaration, PropertyDeclarationBlock};
+use selectors::parser::{Selector, SelectorImpl};
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::sync::Arc;
+use stylesheets::{CSSRule, ImportRule, MediaRule, StyleRule, Stylesheet, SupportsRule, UnsupportedRule};
+use viewport::ViewportRule;
+
+/// Returns the size of the heap block that starts at `ptr`.
+pub type MallocSizeOfFn = unsafe extern "C" fn(ptr: *const c_void) -> usize;
+
+/// What a single memory report needs to measure things.
+pub struct MallocSizeOfOps {
+    size_of_op: MallocSizeOfFn,
+    /// The addresses of the `Arc` contents measured so far.
+    seen: HashSet<usize>,
+}
+
+impl MallocSizeOfOps {
+    pub fn new(size_of_op: MallocSizeOfFn) -> MallocSizeOfOps {
+        MallocSizeOfOps {
+            size_of_op: size_of_op,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// The size of the heap block that starts at `ptr`.
+    pub unsafe fn malloc_size_of<T>(&self, ptr: *const T) -> usize {
+        (self.size_of_op)(ptr as *const c_void)
+    }
+
+    /// The size of the buffer of `vec`, without what its items own.
+    pub fn vec_buffer_size_of<T>(&self, vec: &Vec<T>) -> usize {
+        // Empty vectors and vectors of zero-sized types don't allocate.
+        if vec.capacity() == 0 || size_of::<T>() == 0 {
+            return 0;
+        }
+        unsafe { self.malloc_size_of(vec.as_ptr()) }
+    }
+
+    /// Whether `ptr` has already been measured in this report. Marks it as measured.
+    pub fn have_seen_ptr<T>(&mut self, ptr: *const T) -> bool {
+        !self.seen.insert(ptr as usize)
+    }
+}
+
+/// Types that can measure the heap blocks they own, not counting their own size.
+pub trait MallocSizeOf {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize;
+}
+
+impl<T: MallocSizeOf> MallocSizeOf for Vec<T> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        let buffer = ops.vec_buffer_size_of(self);
+        self.iter().fold(buffer, |size, item| size + item.malloc_size_of_children(ops))
+    }
+}
+
+impl<T: MallocSizeOf> MallocSizeOf for Option<T> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.as_ref().map_or(0, |value| value.malloc_size_of_children(ops))
+    }
+}
+
+impl<T: MallocSizeOf> MallocSizeOf for Arc<T> {
+    /// Measures the allocation and what's in it, unless the report measured another `Arc`
+    /// to the same value already.
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        let value = &**self as *const T;
+        if ops.have_seen_ptr(value) {
+            return 0;
+        }
+        // The allocation starts with the strong and weak counts.
+        // FIXME: This relies on the layout of std's ArcInner, and on T not needing more
+        // than a usize of alignment.
+        let allocation = unsafe { (value as *const usize).offset(-2) };
+        unsafe { ops.malloc_size_of(allocation) + (**self).malloc_size_of_children(ops) }
+    }
+}
+
+/// Measures the types that don't hold an `Arc` with `heapsize`.
+macro_rules! malloc_size_of_is_heap_size_of {
+    ($($ty:ty),+) => {
+        $(
+            impl MallocSizeOf for $ty {
+                fn malloc_size_of_children(&self, _: &mut MallocSizeOfOps) -> usize {
+                    self.heap_size_of_children()
+                }
+            }
+        )+
+    }
+}
+
+malloc_size_of_is_heap_size_of!(ComputedValuesMap, CounterStyleRule, FontFaceRule, MediaQueryList, PropertyDeclaration,
+                                String, UnsupportedRule, ViewportRule);
+
+impl MallocSizeOf for PropertyDeclarationBlock {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.important.malloc_size_of_children(ops) + self.normal.malloc_size_of_children(ops)
+    }
+}
+
+impl MallocSizeOf for Keyframe {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        ops.vec_buffer_size_of(&self.offsets) + self.declarations.malloc_size_of_children(ops)
+    }
+}
+
+impl MallocSizeOf for KeyframesRule {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.keyframes.malloc_size_of_children(ops)
+    }
+}
+
+impl MallocSizeOf for PageRule {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.selectors.heap_size_of_children() + self.declarations.malloc_size_of_children(ops)
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for Stylesheet<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        // Clones of the sheet that share its rules only count them once.
+        self.rules.read().unwrap().malloc_size_of_children(ops) +
+            self.media.read().unwrap().malloc_size_of_children(ops)
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for CSSRule<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        match *self {
+            CSSRule::Charset(ref encoding) => encoding.malloc_size_of_children(ops),
+            CSSRule::Import(ref import) => import.malloc_size_of_children(ops),
+            CSSRule::Namespace(ref prefix, ref url) => {
+                prefix.malloc_size_of_children(ops) + url.heap_size_of_children()
+            }
+            CSSRule::Style(ref style) => style.malloc_size_of_children(ops),
+            CSSRule::Media(ref media) => media.malloc_size_of_children(ops),
+            CSSRule::Supports(ref supports) => supports.malloc_size_of_children(ops),
+            CSSRule::FontFace(ref font_face) => font_face.malloc_size_of_children(ops),
+            CSSRule::Viewport(ref viewport) => viewport.malloc_size_of_children(ops),
+            CSSRule::Keyframes(ref keyframes) => keyframes.malloc_size_of_children(ops),
+            CSSRule::Page(ref page) => page.malloc_size_of_children(ops),
+            CSSRule::CounterStyle(ref counter_style) => counter_style.malloc_size_of_children(ops),
+            CSSRule::Unsupported(ref unsupported) => unsupported.malloc_size_of_children(ops),
+        }
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for ImportRule<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.url.heap_size_of_children() + self.media_queries.malloc_size_of_children(ops) +
+            self.stylesheet.read().unwrap().malloc_size_of_children(ops)
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for Selector<Impl> {
+    // FIXME: The compound selectors are behind an Arc, which heapsize skips, and the
+    // selectors crate doesn't let us get at it.
+    fn malloc_size_of_children(&self, _: &mut MallocSizeOfOps) -> usize {
+        self.heap_size_of_children()
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for StyleRule<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.selectors.malloc_size_of_children(ops) + self.declarations.malloc_size_of_children(ops)
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for MediaRule<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.media_queries.malloc_size_of_children(ops) + self.rules.malloc_size_of_children(ops)
+    }
+}
+
+impl<Impl: SelectorImpl + HeapSizeOf> MallocSizeOf for SupportsRule<Impl> {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.condition.heap_size_of_children() + self.rules.malloc_size_of_children(ops)
+    }
+}
@@ -8,16 +8,21 @@ use animation::{self, Animation};
 use context::SharedStyleContext;
 use data::PrivateStyleData;
 use dom::{TElement, TNode, TRestyleDamage};
-use properties::{ComputedValues, PropertyDeclaration, cascade};
+use element_state::ElementState;
+use properties::style_struct_traits::TBox;
+use properties::{ComputedValues, PropertyDeclaration, cascade, restrict_declarations, visited_style_declarations};
+use rule_tree::{RuleNode, RuleNodeStyleCache};
+use scratch::ScratchArena;
 use selector_impl::{ElementExt, SelectorImplExt};
-use selector_matching::{DeclarationBlock, Stylist};
+use selector_matching::{CascadeLevel, DeclarationBlock, Stylist};
 use selectors::Element;
 use selectors::bloom::BloomFilter;
 use selectors::matching::{CommonStyleAffectingAttributeMode, CommonStyleAffectingAttributes};
 use selectors::matching::{common_style_affecting_attributes, rare_style_affecting_attributes};
+use shared_lock::SharedRwLockReadGuard;
 use smallvec::SmallVec;
-use std::collections::HashMap;
-use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::slice::Iter;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
@@ -52,28 +57,57 @@ fn create_common_style_affecting_attributes_from_element<E: TElement>(element: &
     flags
 }
 
+/// What an element matched, in buffers taken from the scratch arena of the thread styling it,
+/// which `give_back` returns them to once the element is cascaded.
 pub struct ApplicableDeclarations<Impl: SelectorImplExt> {
     pub normal: SmallVec<[DeclarationBlock; 16]>,
-    pub per_pseudo: HashMap<Impl::PseudoElement, Vec<DeclarationBlock>, BuildHasherDefault<::fnv::FnvHasher>>,
+    /// The declarations of each eagerly cascaded pseudo-element, which there are few enough
+    /// of to keep inline.
+    pub per_pseudo: SmallVec<[(Impl::PseudoElement, Vec<DeclarationBlock>); 4]>,
 
     /// Whether the `normal` declarations are shareable with other nodes.
     pub normal_shareable: bool,
+
+    /// Which of the `normal` declarations are at each cascade level, for the levels that have
+    /// some.
+    pub levels: Vec<(CascadeLevel, Range<usize>)>,
 }
 
 impl<Impl: SelectorImplExt> ApplicableDeclarations<Impl> {
-    pub fn new() -> ApplicableDeclarations<Impl> {
+    pub fn new(scratch: &mut ScratchArena) -> ApplicableDeclarations<Impl> {
         let mut applicable_declarations = ApplicableDeclarations {
             normal: SmallVec::new(),
-            per_pseudo: HashMap::with_hasher(Default::default()),
+            per_pseudo: SmallVec::new(),
             normal_shareable: false,
+            levels: scratch.take_levels(),
         };
 
         Impl::each_eagerly_cascaded_pseudo_element(|pseudo| {
-            applicable_declarations.per_pseudo.insert(pseudo, vec![]);
+            applicable_declarations.per_pseudo.push((pseudo, scratch.take_declarations()));
         });
 
         applicable_declarations
     }
+
+    /// The declarations `pseudo` matched, if it's eagerly cascaded.
+    pub fn pseudo_declarations(&self, pseudo: &Impl::PseudoElement) -> Option<&Vec<DeclarationBlock>> {
+        self.per_pseudo.iter().find(|&&(ref p, _)| p == pseudo).map(|&(_, ref declarations)| declarations)
+    }
+
+    fn pseudo_declarations_mut(&mut self, pseudo: &Impl::PseudoElement) -> &mut Vec<DeclarationBlock> {
+        let index = self.per_pseudo.iter().position(|&(ref p, _)| p == pseudo)
+                                   .expect("Only eagerly cascaded pseudo-elements are matched with the element");
+        &mut self.per_pseudo[index].1
+    }
+
+    /// Hands the buffers back to the arena they came from.
+    pub fn give_back(self, scratch: &mut ScratchArena) {
+        let ApplicableDeclarations { per_pseudo, levels, .. } = self;
+        scratch.give_back_levels(levels);
+        for (_, declarations) in per_pseudo.into_iter() {
+            scratch.give_back_declarations(declarations);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -162,6 +196,14 @@ impl<C: ComputedValues> ApplicableDeclarationsCache<C> {
         }
     }
 
+    /// A cache whose hashing is seeded with `k0` and `k1`, for traversals that have to hit
+    /// and miss the same way every time they run.
+    pub fn with_hash_keys(k0: u64, k1: u64) -> Self {
+        ApplicableDeclarationsCache {
+            cache: SimpleHashCache::with_keys(APPLICABLE_DECLARATIONS_CACHE_SIZE, k0, k1),
+        }
+    }
+
     pub fn find(&self, declarations: &[DeclarationBlock]) -> Option<Arc<C>> {
         match self.cache.find(&ApplicableDeclarationsCacheQuery::new(declarations)) {
             None => None,
@@ -193,6 +235,7 @@ pub struct StyleSharingCandidate<C: ComputedValues> {
     pub namespace: Namespace,
     pub common_style_affecting_attributes: CommonStyleAffectingAttributes,
     pub link: bool,
+    pub state: ElementState,
 }
 
 impl<C: ComputedValues> PartialEq for StyleSharingCandidate<C> {
@@ -203,7 +246,8 @@ impl<C: ComputedValues> PartialEq for StyleSharingCandidate<C> {
             self.class == other.class &&
             self.link == other.link &&
             self.namespace == other.namespace &&
-            self.common_style_affecting_attributes == other.common_style_affecting_attributes
+            self.common_style_affecting_attributes == other.common_style_affecting_attributes &&
+            self.state == other.state
     }
 }
 
@@ -212,7 +256,8 @@ impl<C: ComputedValues> StyleSharingCandidate<C> {
     /// the style sharing candidate or `None` if this node is ineligible for
     /// style sharing.
     #[allow(unsafe_code)]
-    fn new<N: TNode<ConcreteComputedValues=C>>(element: &N::ConcreteElement) -> Option<Self> {
+    fn new<N: TNode<ConcreteComputedValues=C>>(element: &N::ConcreteElement, guard: &SharedRwLockReadGuard)
+                                               -> Option<Self> {
         let parent_element = match element.parent_element() {
             None => return None,
             Some(parent_element) => parent_element,
@@ -241,7 +286,17 @@ impl<C: ComputedValues> StyleSharingCandidate<C> {
             }
         };
 
-        if element.style_attribute().is_some() {
+        if element.style_attribute(guard).is_some() {
+            return None
+        }
+
+        // An id selector matching this element wouldn't necessarily match another.
+        if element.get_id().is_some() {
+            return None
+        }
+
+        // The style has the values of the element's own attributes in it.
+        if style.get_box().content_reads_attributes() {
             return None
         }
 
@@ -254,11 +309,12 @@ impl<C: ComputedValues> StyleSharingCandidate<C> {
             link: element.is_link(),
             namespace: (*element.get_namespace()).clone(),
             common_style_affecting_attributes:
-                   create_common_style_affecting_attributes_from_element::<N::ConcreteElement>(&element)
+                   create_common_style_affecting_attributes_from_element::<N::ConcreteElement>(&element),
+            state: element.get_state(),
         })
     }
 
-    pub fn can_share_style_with<E: TElement>(&self, element: &E) -> bool {
+    pub fn can_share_style_with<E: TElement>(&self, element: &E, guard: &SharedRwLockReadGuard) -> bool {
         if *element.get_local_name() != self.local_name {
             return false
         }
@@ -277,11 +333,26 @@ impl<C: ComputedValues> StyleSharingCandidate<C> {
             return false
         }
 
+        if element.style_attribute(guard).is_some() || element.get_id().is_some() {
+            return false
+        }
+
+        // Differing state bits could make a state pseudo-class match one but not the other.
+        if element.get_state() != self.state {
+            return false
+        }
+
         let mut matching_rules = ForgetfulSink::new();
-        element.synthesize_presentational_hints_for_legacy_attributes(&mut matching_rules);
+        element.synthesize_presentational_hints_for_legacy_attributes(guard, &mut matching_rules);
         if !matching_rules.is_empty() {
             return false;
         }
+        for &level in CascadeLevel::animations() {
+            element.get_animation_declarations(level, guard, &mut matching_rules);
+            if !matching_rules.is_empty() {
+                return false;
+            }
+        }
 
         // FIXME(pcwalton): It's probably faster to iterate over all the element's attributes and
         // use the {common, rare}-style-affecting-attributes tables as lookup tables.
@@ -323,8 +394,8 @@ impl<C: ComputedValues> StyleSharingCandidate<C> {
             return false
         }
 
-        // TODO(pcwalton): We don't support visited links yet, but when we do there will need to
-        // be some logic here.
+        // Whether the link is visited doesn't matter: the style has what it looks like both when
+        // it is and when it isn't.
 
         true
     }
@@ -343,8 +414,9 @@ impl<C: ComputedValues> StyleSharingCandidateCache<C> {
         self.cache.iter()
     }
 
-    pub fn insert_if_possible<N: TNode<ConcreteComputedValues=C>>(&mut self, element: &N::ConcreteElement) {
-        match StyleSharingCandidate::new::<N>(element) {
+    pub fn insert_if_possible<N: TNode<ConcreteComputedValues=C>>(&mut self, element: &N::ConcreteElement,
+                                                                   guard: &SharedRwLockReadGuard) {
+        match StyleSharingCandidate::new::<N>(element, guard) {
             None => {}
             Some(candidate) => self.cache.insert(candidate, ())
         }
@@ -364,28 +436,104 @@ pub enum StyleSharingResult<ConcreteRestyleDamage: TRestyleDamage> {
     StyleWasShared(usize, ConcreteRestyleDamage),
 }
 
+/// Whether `display: contents` on `element` computes to `display: none`, which it does on the
+/// replaced elements and form controls CSS Display Appendix B lists.
+//
+// FIXME: So it does on most SVG elements, and on MathML ones.
+fn display_contents_computes_to_none<E: Element>(element: &E) -> bool {
+    *element.get_namespace() == ns!(html) &&
+        matches!(&**element.get_local_name(),
+                 "br" | "wbr" | "meter" | "progress" | "canvas" | "embed" | "object" | "audio" |
+                 "iframe" | "img" | "video" | "frame" | "frameset" | "input" | "textarea" | "select")
+}
+
+/// The blocks of `declarations`, and the ranges of `levels` into them, that apply when the link
+/// they were matched through is `visited` or not: the blocks of rules that only match links in
+/// the other state are left out.
+fn leave_out_link_rules<Impl: SelectorImplExt>(stylist: &Stylist<Impl>,
+                                               declarations: &[DeclarationBlock],
+                                               levels: &[(CascadeLevel, Range<usize>)],
+                                               visited: bool)
+                                               -> (Vec<DeclarationBlock>, Vec<(CascadeLevel, Range<usize>)>) {
+    let mut kept = Vec::with_capacity(declarations.len());
+    let mut kept_levels: Vec<(CascadeLevel, Range<usize>)> = Vec::with_capacity(levels.len());
+    for (i, block) in declarations.iter().enumerate() {
+        if stylist.link_visitedness(block) == Some(!visited) {
+            continue
+        }
+        let index = kept.len();
+        kept.push(block.clone());
+        let level = match levels.iter().find(|&&(_, ref range)| range.start <= i && i < range.end) {
+            Some(&(level, _)) => level,
+            None => continue,
+        };
+        if let Some(&mut (last_level, ref mut range)) = kept_levels.last_mut() {
+            if last_level == level && range.end == index {
+                range.end += 1;
+                continue
+            }
+        }
+        kept_levels.push((level, index..index + 1));
+    }
+    (kept, kept_levels)
+}
+
 trait PrivateMatchMethods: TNode
     where <Self::ConcreteElement as Element>::Impl: SelectorImplExt {
     fn cascade_node_pseudo_element(&self,
                                    context: &SharedStyleContext<<Self::ConcreteElement as Element>::Impl>,
                                    parent_style: Option<&Arc<Self::ConcreteComputedValues>>,
                                    applicable_declarations: &[DeclarationBlock],
+                                   visited_declarations: Option<&[DeclarationBlock]>,
                                    mut style: Option<&mut Arc<Self::ConcreteComputedValues>>,
                                    applicable_declarations_cache:
                                     &mut ApplicableDeclarationsCache<Self::ConcreteComputedValues>,
+                                   rule_node: Option<&Arc<RuleNode>>,
+                                   rule_node_style_cache:
+                                    &mut RuleNodeStyleCache<Self::ConcreteComputedValues>,
                                    new_animations_sender: &Mutex<Sender<Animation>>,
                                    shareable: bool,
-                                   animate_properties: bool)
+                                   animate_properties: bool,
+                                   is_pseudo_element: bool)
                                    -> (Self::ConcreteRestyleDamage, Arc<Self::ConcreteComputedValues>) {
-        let mut cacheable = true;
+        // Neither cache tells styles apart by what links would look like if they were visited.
+        let mut cacheable = visited_declarations.is_none();
         if animate_properties {
             cacheable = !self.update_animations_for_cascade(context, &mut style) && cacheable;
         }
 
+        // Elements that matched the same rules under the same parent style get the same style.
+        if let (true, Some(node)) = (cacheable, rule_node) {
+            if let Some(mut this_style) = rule_node_style_cache.find(node, parent_style, shareable) {
+                if animate_properties {
+                    if let Some(ref style) = style {
+                        let mut animated_style = (*this_style).clone();
+                        let animations_started =
+                            animation::start_transitions_if_applicable::<Self::ConcreteComputedValues>(
+                                new_animations_sender,
+                                self.opaque(),
+                                &**style,
+                                &mut animated_style);
+                        if animations_started {
+                            this_style = Arc::new(animated_style);
+                        }
+                    }
+                }
+                let this_style = self.resolve_content_attributes(this_style);
+                let this_style = self.fix_up_display_contents(this_style, is_pseudo_element);
+                let damage = Self::ConcreteRestyleDamage::compute(style.map(|s| &*s), &*this_style);
+                return (damage, this_style);
+            }
+        }
+
         let mut this_style;
         match parent_style {
             Some(ref parent_style) => {
-                let cache_entry = applicable_declarations_cache.find(applicable_declarations);
+                let cache_entry = if cacheable {
+                    applicable_declarations_cache.find(applicable_declarations)
+                } else {
+                    None
+                };
                 let cached_computed_values = match cache_entry {
                     None => None,
                     Some(ref style) => Some(&**style),
@@ -425,20 +573,83 @@ trait PrivateMatchMethods: TNode
             }
         }
 
-        // Calculate style difference.
-        let this_style = Arc::new(this_style);
-        let damage = Self::ConcreteRestyleDamage::compute(style.map(|s| &*s), &*this_style);
+        if let Some(node) = rule_node {
+            this_style.set_rule_node_id(node.id());
+        }
+
+        // The visited style inherits from that of the parent, when it has one, as the text inside
+        // a visited link takes the colors it has when visited.
+        if let Some(visited_declarations) = visited_declarations {
+            let visited_parent_style = parent_style.map(|parent_style| {
+                parent_style.visited_style().unwrap_or(parent_style)
+            });
+            let (visited_style, _) = cascade(context.viewport_size,
+                                             visited_declarations,
+                                             false,
+                                             visited_parent_style.map(|style| &**style),
+                                             None,
+                                             context.error_reporter.clone());
+            this_style.set_visited_style(Arc::new(visited_style));
+        }
 
         // Cache the resolved style if it was cacheable.
+        let this_style = Arc::new(this_style);
         if cacheable {
             applicable_declarations_cache.insert(applicable_declarations.to_vec(),
                                                  this_style.clone());
+            if let Some(node) = rule_node {
+                rule_node_style_cache.insert(node, parent_style, shareable, this_style.clone());
+            }
         }
 
+        // Calculate style difference.
+        let this_style = self.resolve_content_attributes(this_style);
+        let this_style = self.fix_up_display_contents(this_style, is_pseudo_element);
+        let damage = Self::ConcreteRestyleDamage::compute(style.map(|s| &*s), &*this_style);
+
         // Return the final style and the damage done to our caller.
         (damage, this_style)
     }
 
+    /// `style` with the `attr()` items of its `content` replaced by the values of this
+    /// node's attributes. The caches hand the same style to every element with the same
+    /// rules, so this is done to each element's copy after it leaves them.
+    fn resolve_content_attributes(&self, style: Arc<Self::ConcreteComputedValues>)
+                                  -> Arc<Self::ConcreteComputedValues> {
+        let element = match self.as_element() {
+            Some(element) if style.get_box().content_reads_attributes() => element,
+            _ => return style,
+        };
+        let content = style.get_box().clone_content().with_attributes_resolved(|name| {
+            element.get_attr(&ns!(), &Atom::from(name)).map(|value| value.to_owned())
+        });
+        let mut style = style;
+        Arc::make_mut(&mut style).mutate_box().set_content(content);
+        style
+    }
+
+    /// `style` with `display: contents` made `display: none` if this node is one of the
+    /// elements that can't do without their box. The caches share styles between elements of
+    /// any kind, so like resolve_content_attributes this is done after they're left.
+    fn fix_up_display_contents(&self, style: Arc<Self::ConcreteComputedValues>, is_pseudo_element: bool)
+                               -> Arc<Self::ConcreteComputedValues> {
+        use properties::longhands::display::computed_value::T as display;
+        if is_pseudo_element || style.get_box().clone_display() != display::contents {
+            return style
+        }
+        match self.as_element() {
+            Some(ref element) if display_contents_computes_to_none(element) => {}
+            _ => return style,
+        }
+        let mut style = style;
+        {
+            let box_ = Arc::make_mut(&mut style).mutate_box();
+            box_.set_display(display::none);
+            box_.set__servo_display_for_hypothetical_box(display::none);
+        }
+        style
+    }
+
     fn update_animations_for_cascade(&self,
                                      context: &SharedStyleContext<<Self::ConcreteElement as Element>::Impl>,
                                      style: &mut Option<&mut Arc<Self::ConcreteComputedValues>>)
@@ -492,7 +703,8 @@ trait PrivateElementMatchMethods: TElement {
     fn share_style_with_candidate_if_possible(&self,
                                               parent_node: Option<Self::ConcreteNode>,
                                               candidate: &StyleSharingCandidate<<Self::ConcreteNode as
-                                                                                 TNode>::ConcreteComputedValues>)
+                                                                                 TNode>::ConcreteComputedValues>,
+                                              guard: &SharedRwLockReadGuard)
                                               -> Option<Arc<<Self::ConcreteNode as TNode>::ConcreteComputedValues>> {
         let parent_node = match parent_node {
             Some(ref parent_node) if parent_node.as_element().is_some() => parent_node,
@@ -510,7 +722,7 @@ trait PrivateElementMatchMethods: TElement {
                 return None
             }
             // Check tag names, classes, etc.
-            if !candidate.can_share_style_with(self) {
+            if !candidate.can_share_style_with(self, guard) {
                 return None
             }
             return Some(candidate.style.clone())
@@ -525,27 +737,35 @@ pub trait ElementMatchMethods : TElement
     where Self::Impl: SelectorImplExt {
     fn match_element(&self,
                      stylist: &Stylist<Self::Impl>,
+                     guard: &SharedRwLockReadGuard,
                      parent_bf: Option<&BloomFilter>,
                      applicable_declarations: &mut ApplicableDeclarations<Self::Impl>)
                      -> bool {
-        let style_attribute = self.style_attribute().as_ref();
-
-        applicable_declarations.normal_shareable =
-            stylist.push_applicable_declarations(self,
-                                                 parent_bf,
-                                                 style_attribute,
-                                                 None,
-                                                 &mut applicable_declarations.normal);
+        let style_attribute = self.style_attribute(guard).as_ref();
+
+        let shareable =
+            stylist.push_applicable_declarations_and_levels(self,
+                                                            guard,
+                                                            parent_bf,
+                                                            style_attribute,
+                                                            None,
+                                                            &mut applicable_declarations.normal,
+                                                            &mut applicable_declarations.levels);
+        applicable_declarations.normal_shareable = shareable;
         Self::Impl::each_eagerly_cascaded_pseudo_element(|pseudo| {
+            if !stylist.has_rules_for_pseudo(&pseudo) {
+                return;
+            }
             stylist.push_applicable_declarations(self,
+                                                 guard,
                                                  parent_bf,
                                                  None,
                                                  Some(pseudo.clone()),
-                                                 applicable_declarations.per_pseudo.entry(pseudo).or_insert(vec![]));
+                                                 applicable_declarations.pseudo_declarations_mut(&pseudo));
         });
 
         applicable_declarations.normal_shareable &&
-        applicable_declarations.per_pseudo.values().all(|v| v.is_empty())
+        applicable_declarations.per_pseudo.iter().all(|&(_, ref v)| v.is_empty())
     }
 
     /// Attempts to share a style with another node. This method is unsafe because it depends on
@@ -555,13 +775,14 @@ pub trait ElementMatchMethods : TElement
                                       style_sharing_candidate_cache:
                                         &mut StyleSharingCandidateCache<<Self::ConcreteNode as
                                                                          TNode>::ConcreteComputedValues>,
-                                      parent: Option<Self::ConcreteNode>)
+                                      parent: Option<Self::ConcreteNode>,
+                                      guard: &SharedRwLockReadGuard)
                                       -> StyleSharingResult<<Self::ConcreteNode as TNode>::ConcreteRestyleDamage> {
         if opts::get().disable_share_style_cache {
             return StyleSharingResult::CannotShare
         }
 
-        if self.style_attribute().is_some() {
+        if self.style_attribute(guard).is_some() {
             return StyleSharingResult::CannotShare
         }
         if self.get_attr(&ns!(), &atom!("id")).is_some() {
@@ -569,14 +790,22 @@ pub trait ElementMatchMethods : TElement
         }
 
         for (i, &(ref candidate, ())) in style_sharing_candidate_cache.iter().enumerate() {
-            match self.share_style_with_candidate_if_possible(parent.clone(), candidate) {
+            match self.share_style_with_candidate_if_possible(parent.clone(), candidate, guard) {
                 Some(shared_style) => {
                     // Yay, cache hit. Share the style.
                     let node = self.as_node();
-                    let style = &mut node.mutate_data().unwrap().style;
-                    let damage = <<Self as TElement>::ConcreteNode as TNode>
-                                     ::ConcreteRestyleDamage::compute((*style).as_ref(), &*shared_style);
-                    *style = Some(shared_style);
+                    let mut data = node.mutate_data().unwrap();
+                    let mut damage = <<Self as TElement>::ConcreteNode as TNode>
+                                         ::ConcreteRestyleDamage::compute(data.style.as_ref(), &*shared_style);
+                    // Candidates never have pseudo-element styles, so the ones this element
+                    // had no longer apply.
+                    if !data.per_pseudo.is_empty() {
+                        data.per_pseudo.clear();
+                        damage = damage | <<Self as TElement>::ConcreteNode as TNode>
+                                              ::ConcreteRestyleDamage::rebuild_and_reflow();
+                    }
+                    data.style = Some(shared_style);
+                    data.rule_node = None;
                     return StyleSharingResult::StyleWasShared(i, damage)
                 }
                 None => {}
@@ -645,6 +874,7 @@ pub trait MatchMethods : TNode {
                            applicable_declarations: &ApplicableDeclarations<<Self::ConcreteElement as Element>::Impl>,
                            applicable_declarations_cache:
                              &mut ApplicableDeclarationsCache<Self::ConcreteComputedValues>,
+                           rule_node_style_cache: &mut RuleNodeStyleCache<Self::ConcreteComputedValues>,
                            new_animations_sender: &Mutex<Sender<Animation>>)
                            where <Self::ConcreteElement as Element>::Impl: SelectorImplExt {
         // Get our parent's style. This must be unsafe so that we don't touch the parent's
@@ -676,36 +906,102 @@ pub trait MatchMethods : TNode {
             damage = {
                 let mut data_ref = self.mutate_data().unwrap();
                 let mut data = &mut *data_ref;
+
+                // Links, and what's inside them, get a second style from the rules they matched
+                // for when the link is visited, when some rules tell visited links apart. The
+                // rules that only match unvisited links are left out of it, and those that only
+                // match visited ones out of the style itself.
+                let stylist = &*context.stylist.0;
+                let link_dependent = stylist.has_link_dependent_rules() &&
+                    (self.as_element().map_or(false, |element| element.is_link()) ||
+                     parent_style.map_or(false, |parent_style| parent_style.visited_style().is_some()));
+                let unvisited;
+                let visited_declarations;
+                let (normal, levels) = if link_dependent {
+                    unvisited = leave_out_link_rules(stylist,
+                                                     &applicable_declarations.normal,
+                                                     &applicable_declarations.levels,
+                                                     false);
+                    let visited = leave_out_link_rules(stylist,
+                                                       &applicable_declarations.normal,
+                                                       &applicable_declarations.levels,
+                                                       true);
+                    visited_declarations = Some(visited_style_declarations(&visited.0));
+                    (&*unvisited.0, &*unvisited.1)
+                } else {
+                    visited_declarations = None;
+                    (&*applicable_declarations.normal, &*applicable_declarations.levels)
+                };
+
+                let rule_node = context.rule_tree.as_ref().map(|rule_tree| {
+                    rule_tree.insert_ordered_rules_at_levels(normal, levels)
+                });
                 let (mut damage, final_style) = self.cascade_node_pseudo_element(
                     context,
                     parent_style,
-                    &applicable_declarations.normal,
+                    normal,
+                    visited_declarations.as_ref().map(|declarations| &**declarations),
                     data.style.as_mut(),
                     applicable_declarations_cache,
+                    rule_node.as_ref(),
+                    rule_node_style_cache,
                     new_animations_sender,
                     applicable_declarations.normal_shareable,
-                    true);
+                    true,
+                    false);
 
                 data.style = Some(final_style);
+                data.rule_node = rule_node;
 
                 <Self::ConcreteElement as Element>::Impl::each_eagerly_cascaded_pseudo_element(|pseudo| {
                     let applicable_declarations_for_this_pseudo =
-                        applicable_declarations.per_pseudo.get(&pseudo).unwrap();
+                        applicable_declarations.pseudo_declarations(&pseudo).unwrap();
 
 
                     if !applicable_declarations_for_this_pseudo.is_empty() {
+                        // Declarations of properties that don't apply to the pseudo-element
+                        // are left out. The rule tree knows nothing of that, so its styles
+                        // can't be used for it.
+                        let restriction =
+                            <Self::ConcreteElement as Element>::Impl::pseudo_element_property_restriction(&pseudo);
+                        let restricted;
+                        let (declarations, pseudo_rule_node) = match restriction {
+                            Some(restriction) => {
+                                restricted = restrict_declarations(applicable_declarations_for_this_pseudo,
+                                                                   restriction);
+                                (&*restricted, None)
+                            }
+                            None => {
+                                let rule_node = context.rule_tree.as_ref().map(|rule_tree| {
+                                    rule_tree.insert_ordered_rules(&applicable_declarations_for_this_pseudo)
+                                });
+                                (&**applicable_declarations_for_this_pseudo, rule_node)
+                            }
+                        };
+                        let parent_style =
+                            <Self::ConcreteElement as Element>::Impl::pseudo_element_inherits_from(&pseudo)
+                            .and_then(|parent_pseudo| data.per_pseudo.get(&parent_pseudo).cloned())
+                            .unwrap_or_else(|| data.style.clone().unwrap());
                         let (new_damage, style) = self.cascade_node_pseudo_element(
                             context,
-                            Some(data.style.as_ref().unwrap()),
-                            &*applicable_declarations_for_this_pseudo,
+                            Some(&parent_style),
+                            declarations,
+                            None,
                             data.per_pseudo.get_mut(&pseudo),
                             applicable_declarations_cache,
+                            pseudo_rule_node.as_ref(),
+                            rule_node_style_cache,
                             new_animations_sender,
                             false,
-                            false);
+                            false,
+                            true);
                         data.per_pseudo.insert(pseudo, style);
 
                         damage = damage | new_damage;
+                    } else if data.per_pseudo.remove(&pseudo).is_some() {
+                        // No rule applies to the pseudo-element anymore, so whatever was
+                        // generated for it has to go.
+                        damage = damage | Self::ConcreteRestyleDamage::rebuild_and_reflow();
                     }
                 });
 
@@ -724,6 +1020,43 @@ pub trait MatchMethods : TNode {
         // scope first.
         self.set_restyle_damage(damage);
     }
+
+    /// Cascades an element that was styled before again, with the blocks on the way to
+    /// `rule_node` and under the current style of `parent`, without matching: for when the
+    /// rules it matched got new declarations but kept their selectors, or when what it
+    /// inherits changed. The styles of its pseudo-elements are left as they were. Returns the
+    /// damage, which is left for the caller to record.
+    unsafe fn cascade_rule_node(&self,
+                                context: &SharedStyleContext<<Self::ConcreteElement as Element>::Impl>,
+                                parent: Option<Self>,
+                                rule_node: Arc<RuleNode>,
+                                applicable_declarations_cache:
+                                  &mut ApplicableDeclarationsCache<Self::ConcreteComputedValues>,
+                                rule_node_style_cache: &mut RuleNodeStyleCache<Self::ConcreteComputedValues>,
+                                new_animations_sender: &Mutex<Sender<Animation>>)
+                                -> Self::ConcreteRestyleDamage
+                                where <Self::ConcreteElement as Element>::Impl: SelectorImplExt {
+        let parent_style = parent.map(|parent_node| {
+            (*parent_node.borrow_data_unchecked().unwrap()).style.clone().unwrap()
+        });
+        let mut data_ref = self.mutate_data().unwrap();
+        let data = &mut *data_ref;
+        let (damage, style) = self.cascade_node_pseudo_element(context,
+                                                               parent_style.as_ref(),
+                                                               &rule_node.path(),
+                                                               None,
+                                                               data.style.as_mut(),
+                                                               applicable_declarations_cache,
+                                                               Some(&rule_node),
+                                                               rule_node_style_cache,
+                                                               new_animations_sender,
+                                                               false,
+                                                               true,
+                                                               false);
+        data.style = Some(style);
+        data.rule_node = Some(rule_node);
+        damage
+    }
 }
 
 impl<N: TNode> MatchMethods for N {}
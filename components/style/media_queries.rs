@@ -3,15 +3,16 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
-use cssparser::{Delimiter, Parser, Token};
+use cssparser::{Delimiter, Parser, ToCss, Token};
 use euclid::size::{Size2D, TypedSize2D};
 use properties::longhands;
 use std::ascii::AsciiExt;
+use std::fmt;
 use util::geometry::ViewportPx;
 use values::specified;
 
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub struct MediaQueryList {
     pub media_queries: Vec<MediaQuery>
 }
@@ -72,7 +73,7 @@ pub enum Qualifier {
     Not,
 }
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub struct MediaQuery {
     pub qualifier: Option<Qualifier>,
     pub media_type: MediaQueryType,
@@ -179,6 +180,60 @@ impl MediaQuery {
     }
 }
 
+impl ToCss for Expression {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        let (name, length) = match *self {
+            Expression::Width(Range::Min(ref length)) => ("min-width", length),
+            Expression::Width(Range::Max(ref length)) => ("max-width", length),
+        };
+        try!(write!(dest, "({}: ", name));
+        try!(length.to_css(dest));
+        dest.write_str(")")
+    }
+}
+
+// https://drafts.csswg.org/cssom/#serialize-a-media-query
+impl ToCss for MediaQuery {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        match self.qualifier {
+            Some(Qualifier::Only) => try!(dest.write_str("only ")),
+            Some(Qualifier::Not) => try!(dest.write_str("not ")),
+            None => {}
+        }
+        let mut expressions = self.expressions.iter();
+        // `all` goes without saying when there's something else to say.
+        if self.qualifier.is_some() || self.media_type != MediaQueryType::All || self.expressions.is_empty() {
+            try!(dest.write_str(match self.media_type {
+                MediaQueryType::All => "all",
+                MediaQueryType::MediaType(MediaType::Screen) => "screen",
+                MediaQueryType::MediaType(MediaType::Print) => "print",
+                // FIXME: Keep the names of unknown media types, so that they round-trip.
+                MediaQueryType::MediaType(MediaType::Unknown) => "unknown",
+            }));
+        } else if let Some(first) = expressions.next() {
+            try!(first.to_css(dest));
+        }
+        for expression in expressions {
+            try!(dest.write_str(" and "));
+            try!(expression.to_css(dest));
+        }
+        Ok(())
+    }
+}
+
+// https://drafts.csswg.org/cssom/#serialize-a-media-query-list
+impl ToCss for MediaQueryList {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        for (i, query) in self.media_queries.iter().enumerate() {
+            if i != 0 {
+                try!(dest.write_str(", "));
+            }
+            try!(query.to_css(dest));
+        }
+        Ok(())
+    }
+}
+
 pub fn parse_media_query_list(input: &mut Parser) -> MediaQueryList {
     let queries = if input.is_exhausted() {
         vec![MediaQuery::new(None, MediaQueryType::All, vec!())]
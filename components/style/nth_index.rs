@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The index of an element among its siblings, for `:nth-child()` and the other structural
+//! pseudo-classes that take an `an+b` argument.
+//!
+//! Finding an index by walking the previous siblings is linear in the number of siblings,
+//! which makes matching a long list of them quadratic. `NthIndexCache` remembers every index
+//! it comes across while walking, so each sibling is only walked over about once. A cache
+//! belongs to a single thread and mustn't be used across a change to the DOM.
+//!
+//! The selectors crate walks the siblings itself, so implementations with a cache have the
+//! stylist replace the `:nth-*` selectors with a pseudo-class of their own, through
+//! `SelectorImplExt::nth_index_pseudo_class`, and match that with `NthIndexCache::index`.
+
+use selectors::Element;
+use selectors::parser::{SelectorImpl, SimpleSelector};
+use std::collections::HashMap;
+
+pub trait NthIndexElement: Element {
+    /// Identifies the element among the ones alive while a cache is in use.
+    fn nth_index_key(&self) -> usize;
+
+    /// The 1-based index of the element among the element children of its parent, if the
+    /// DOM already knows it.
+    fn child_index_hint(&self) -> Option<i32> {
+        None
+    }
+}
+
+/// Which siblings count, and from which end.
+#[derive(Clone, Copy, Debug, Eq, Hash, HeapSizeOf, PartialEq)]
+pub struct NthIndexKind {
+    /// Only count siblings with the same local name and namespace.
+    pub of_type: bool,
+    /// Count from the last sibling rather than from the first.
+    pub from_end: bool,
+}
+
+impl NthIndexKind {
+    /// The name of the pseudo-class that counts siblings this way, without the leading colon.
+    pub fn pseudo_class_name(&self) -> &'static str {
+        match (self.of_type, self.from_end) {
+            (false, false) => "nth-child",
+            (false, true) => "nth-last-child",
+            (true, false) => "nth-of-type",
+            (true, true) => "nth-last-of-type",
+        }
+    }
+}
+
+/// The indices found so far, by element and kind.
+#[derive(Default)]
+pub struct NthIndexCache {
+    indices: HashMap<(usize, NthIndexKind), i32>,
+}
+
+impl NthIndexCache {
+    pub fn new() -> NthIndexCache {
+        Default::default()
+    }
+
+    /// Forgets every index, for when the DOM may have changed.
+    pub fn clear(&mut self) {
+        self.indices.clear();
+    }
+
+    /// The 1-based index of `element` among the siblings `kind` counts.
+    pub fn index<E>(&mut self, element: &E, kind: NthIndexKind) -> i32 where E: NthIndexElement {
+        if !kind.of_type && !kind.from_end {
+            if let Some(index) = element.child_index_hint() {
+                return index;
+            }
+        }
+        if let Some(&index) = self.indices.get(&(element.nth_index_key(), kind)) {
+            return index;
+        }
+
+        // Walk towards the end we count from until we reach a sibling we know the index of,
+        // or run out of siblings, keeping the ones that count so they can be cached too.
+        let mut counted = vec![];
+        let mut known = 0;
+        let mut sibling = step(element, kind);
+        while let Some(current) = sibling {
+            if !kind.of_type || same_type(element, &current) {
+                if let Some(&index) = self.indices.get(&(current.nth_index_key(), kind)) {
+                    known = index;
+                    break;
+                }
+                counted.push(current.nth_index_key());
+            }
+            sibling = step(&current, kind);
+        }
+
+        // The siblings were found nearest first, so the farthest one comes right after the
+        // known index.
+        let index = known + counted.len() as i32 + 1;
+        for (position, key) in counted.into_iter().rev().enumerate() {
+            self.indices.insert((key, kind), known + position as i32 + 1);
+        }
+        self.indices.insert((element.nth_index_key(), kind), index);
+        index
+    }
+}
+
+fn step<E>(element: &E, kind: NthIndexKind) -> Option<E> where E: Element {
+    if kind.from_end {
+        element.next_sibling_element()
+    } else {
+        element.prev_sibling_element()
+    }
+}
+
+fn same_type<E>(a: &E, b: &E) -> bool where E: Element {
+    a.get_local_name() == b.get_local_name() && a.get_namespace() == b.get_namespace()
+}
+
+/// Whether `index` is `a*n + b` for some n >= 0.
+pub fn matches_nth(a: i32, b: i32, index: i32) -> bool {
+    if a == 0 {
+        index == b
+    } else {
+        let n = index - b;
+        n / a >= 0 && n % a == 0
+    }
+}
+
+/// The `a` and `b` of `selector` and the siblings it counts, if it's one of the nth
+/// pseudo-classes, or None otherwise.
+pub fn nth_arguments<Impl>(selector: &SimpleSelector<Impl>) -> Option<(i32, i32, NthIndexKind)>
+                           where Impl: SelectorImpl {
+    let (a, b, of_type, from_end) = match *selector {
+        SimpleSelector::NthChild(a, b) => (a, b, false, false),
+        SimpleSelector::NthLastChild(a, b) => (a, b, false, true),
+        SimpleSelector::NthOfType(a, b) => (a, b, true, false),
+        SimpleSelector::NthLastOfType(a, b) => (a, b, true, true),
+        _ => return None,
+    };
+    Some((a, b, NthIndexKind { of_type: of_type, from_end: from_end }))
+}
@@ -0,0 +1,365 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `@page` rules, which set the size and margins of the pages a document is printed on.
+//!
+//! Only the page's own declarations are supported: margin boxes like `@top-left` are
+//! dropped.
+//!
+//! https://drafts.csswg.org/css-page/
+
+use app_units::Au;
+use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Delimiter, Parser, Token};
+use cssparser::parse_important;
+use media_queries::Device;
+use parser::{ParserContext, log_css_error};
+use properties::{PropertyDeclaration, PropertyDeclarationBlock, PropertyDeclarationParseResult};
+use properties::deduplicate_property_declarations;
+use selector_matching::CascadeLevel;
+use selectors::parser::SelectorImpl;
+use std::ascii::AsciiExt;
+use std::sync::Arc;
+use string_cache::Atom;
+use stylesheets::{CSSRuleIteratorExt, Stylesheet};
+use values::specified::Length;
+
+#[derive(Clone, Copy, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum PagePseudoClass {
+    First,
+    Left,
+    Right,
+    Blank,
+}
+
+/// A selector of an `@page` rule, like `chapter:first`.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq, Eq)]
+pub struct PageSelector {
+    /// The name of the pages the selector is for, or None for pages of any name.
+    pub name: Option<Atom>,
+    pub pseudo_classes: Vec<PagePseudoClass>,
+}
+
+/// A page to match `@page` rules against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    /// The page's name, from the `page` property of the content on it, if any.
+    pub name: Option<Atom>,
+    pub first: bool,
+    /// Whether it's a left page. Pages that aren't are right pages.
+    pub left: bool,
+    pub blank: bool,
+}
+
+impl PageSelector {
+    pub fn matches(&self, page: &Page) -> bool {
+        if self.name.is_some() && self.name != page.name {
+            return false
+        }
+        self.pseudo_classes.iter().all(|pseudo_class| match *pseudo_class {
+            PagePseudoClass::First => page.first,
+            PagePseudoClass::Left => page.left,
+            PagePseudoClass::Right => !page.left,
+            PagePseudoClass::Blank => page.blank,
+        })
+    }
+
+    /// The page name, then :first and :blank, then :left and :right, packed like the
+    /// specificity of a selector.
+    ///
+    /// https://drafts.csswg.org/css-page/#cascading-and-page-context
+    pub fn specificity(&self) -> u32 {
+        let (mut first_or_blank, mut left_or_right) = (0, 0);
+        for pseudo_class in &self.pseudo_classes {
+            match *pseudo_class {
+                PagePseudoClass::First | PagePseudoClass::Blank => first_or_blank += 1,
+                PagePseudoClass::Left | PagePseudoClass::Right => left_or_right += 1,
+            }
+        }
+        let name = if self.name.is_some() { 1 } else { 0 };
+        name << 20 | first_or_blank << 10 | left_or_right
+    }
+}
+
+#[derive(Clone, Copy, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape,
+}
+
+/// The value of the `size` descriptor.
+#[derive(Clone, Copy, Debug, HeapSizeOf, PartialEq, Eq)]
+pub enum PageSize {
+    /// The size of the paper the user picked.
+    Auto,
+    /// The size of the paper the user picked, turned to the given orientation.
+    Orientation(PageOrientation),
+    /// A width and a height.
+    Explicit(Au, Au),
+}
+
+/// An `@page` rule.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub struct PageRule {
+    /// The rule applies to the pages any of these match, or to every page if there are none.
+    pub selectors: Vec<PageSelector>,
+    /// The margin declarations of the rule. Other properties don't apply to pages yet.
+    pub declarations: PropertyDeclarationBlock,
+    /// The `size` descriptor, if given, and whether it was `!important`.
+    pub size: Option<(PageSize, bool)>,
+}
+
+impl PageRule {
+    /// The specificity of the most specific of the rule's selectors that matches `page`, or
+    /// None if the rule doesn't apply to it.
+    pub fn matching_specificity(&self, page: &Page) -> Option<u32> {
+        if self.selectors.is_empty() {
+            return Some(0)
+        }
+        self.selectors.iter().filter(|selector| selector.matches(page)).map(PageSelector::specificity).max()
+    }
+}
+
+/// What the `@page` rules that apply to a page give it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageStyle {
+    /// The margin declarations that win the cascade, one per property.
+    pub declarations: Vec<PropertyDeclaration>,
+    pub size: PageSize,
+}
+
+/// Cascades the `@page` rules of `stylesheets`, which come in document order, for `page`.
+/// Rules cascade like style rules: by origin and importance, then by the specificity of
+/// their selectors, then by the order they come in.
+pub fn resolve_page_style<Impl>(stylesheets: &[Arc<Stylesheet<Impl>>], device: &Device, page: &Page)
+                                -> PageStyle where Impl: SelectorImpl {
+    let mut applicable = vec![];
+    for stylesheet in stylesheets {
+        if stylesheet.disabled() || !stylesheet.is_effective_for_device(device) {
+            continue
+        }
+        for rule in stylesheet.effective_rules(device).page() {
+            if let Some(specificity) = rule.matching_specificity(page) {
+                let source_order = applicable.len();
+                // Each rule takes part once with its normal declarations, and once with its
+                // important ones.
+                for &important in &[false, true] {
+                    let level = CascadeLevel::for_rules(stylesheet.origin, important);
+                    applicable.push(((level, specificity, source_order), important, rule));
+                }
+            }
+        }
+    }
+    applicable.sort_by_key(|&(key, _, _)| key);
+
+    let mut style = PageStyle {
+        declarations: vec![],
+        size: PageSize::Auto,
+    };
+    for (_, important, rule) in applicable {
+        let declarations = if important { &rule.declarations.important } else { &rule.declarations.normal };
+        for declaration in declarations.iter() {
+            style.declarations.retain(|existing| existing.name() != declaration.name());
+            style.declarations.push(declaration.clone());
+        }
+        if let Some((size, size_important)) = rule.size {
+            if size_important == important {
+                style.size = size;
+            }
+        }
+    }
+    style
+}
+
+/// Parses the prelude of an `@page` rule: a comma-separated list of page selectors, which
+/// may be empty.
+pub fn parse_page_selectors(input: &mut Parser) -> Result<Vec<PageSelector>, ()> {
+    if input.is_exhausted() {
+        return Ok(vec![])
+    }
+    input.parse_comma_separated(parse_page_selector)
+}
+
+fn parse_page_selector(input: &mut Parser) -> Result<PageSelector, ()> {
+    let name = input.try(|input| input.expect_ident().map(|name| Atom::from(&*name))).ok();
+    let mut pseudo_classes = vec![];
+    // No whitespace is allowed after the name or between the pseudo-classes.
+    loop {
+        let starts_selector = name.is_none() && pseudo_classes.is_empty();
+        match input.try(|input| parse_pseudo_page(input, starts_selector)) {
+            Ok(pseudo_class) => pseudo_classes.push(pseudo_class),
+            Err(()) => break,
+        }
+    }
+    if name.is_none() && pseudo_classes.is_empty() {
+        return Err(())
+    }
+    Ok(PageSelector {
+        name: name,
+        pseudo_classes: pseudo_classes,
+    })
+}
+
+/// Whitespace may only come before the colon if the pseudo-class starts the selector.
+fn parse_pseudo_page(input: &mut Parser, starts_selector: bool) -> Result<PagePseudoClass, ()> {
+    let colon = if starts_selector { input.next() } else { input.next_including_whitespace() };
+    match try!(colon) {
+        Token::Colon => {}
+        _ => return Err(())
+    }
+    match try!(input.next_including_whitespace()) {
+        Token::Ident(ref name) => match_ignore_ascii_case! { name,
+            "first" => Ok(PagePseudoClass::First),
+            "left" => Ok(PagePseudoClass::Left),
+            "right" => Ok(PagePseudoClass::Right),
+            "blank" => Ok(PagePseudoClass::Blank),
+            _ => Err(())
+        },
+        _ => Err(())
+    }
+}
+
+/// The sizes of the paper `size` can name, in millimeters, portrait.
+///
+/// https://drafts.csswg.org/css-page/#typedef-page-size-page-size
+fn named_page_size(name: &str) -> Option<(f32, f32)> {
+    match_ignore_ascii_case! { name,
+        "a5" => Some((148., 210.)),
+        "a4" => Some((210., 297.)),
+        "a3" => Some((297., 420.)),
+        "b5" => Some((176., 250.)),
+        "b4" => Some((250., 353.)),
+        "jis-b5" => Some((182., 257.)),
+        "jis-b4" => Some((257., 364.)),
+        "letter" => Some((215.9, 279.4)),
+        "legal" => Some((215.9, 355.6)),
+        "ledger" => Some((279.4, 431.8)),
+        _ => None
+    }
+}
+
+fn parse_orientation(input: &mut Parser) -> Result<PageOrientation, ()> {
+    let ident = try!(input.expect_ident());
+    match_ignore_ascii_case! { ident,
+        "portrait" => Ok(PageOrientation::Portrait),
+        "landscape" => Ok(PageOrientation::Landscape),
+        _ => Err(())
+    }
+}
+
+fn parse_absolute_length(input: &mut Parser) -> Result<Au, ()> {
+    match try!(Length::parse_non_negative(input)) {
+        Length::Absolute(length) => Ok(length),
+        // Pages have no font or viewport for other lengths to be relative to.
+        _ => Err(())
+    }
+}
+
+/// `auto | <length>{1,2} | [ <page-size> || [ portrait | landscape ] ]`
+pub fn parse_page_size(input: &mut Parser) -> Result<PageSize, ()> {
+    if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
+        return Ok(PageSize::Auto)
+    }
+    if let Ok(width) = input.try(parse_absolute_length) {
+        let height = input.try(parse_absolute_length).unwrap_or(width);
+        return Ok(PageSize::Explicit(width, height))
+    }
+
+    let mut orientation = input.try(parse_orientation).ok();
+    let named = input.try(|input| {
+        let name = try!(input.expect_ident());
+        named_page_size(&name).ok_or(())
+    }).ok();
+    if orientation.is_none() {
+        orientation = input.try(parse_orientation).ok();
+    }
+    match (named, orientation) {
+        (None, None) => Err(()),
+        (None, Some(orientation)) => Ok(PageSize::Orientation(orientation)),
+        (Some((width, height)), orientation) => {
+            let (width, height) = (millimeters(width), millimeters(height));
+            Ok(match orientation {
+                Some(PageOrientation::Landscape) => PageSize::Explicit(height, width),
+                _ => PageSize::Explicit(width, height),
+            })
+        }
+    }
+}
+
+fn millimeters(value: f32) -> Au {
+    Au::from_f32_px(value * 96. / 25.4)
+}
+
+enum PageDeclaration {
+    Margins(Vec<PropertyDeclaration>),
+    Size(PageSize),
+}
+
+struct PageDeclarationParser<'a, 'b: 'a> {
+    context: &'a ParserContext<'b>,
+}
+
+/// Margin boxes aren't supported yet, so the default methods reject all at rules.
+impl<'a, 'b> AtRuleParser for PageDeclarationParser<'a, 'b> {
+    type Prelude = ();
+    type AtRule = (PageDeclaration, bool);
+}
+
+impl<'a, 'b> DeclarationParser for PageDeclarationParser<'a, 'b> {
+    type Declaration = (PageDeclaration, bool);
+
+    fn parse_value(&self, name: &str, input: &mut Parser) -> Result<(PageDeclaration, bool), ()> {
+        let declaration = try!(input.parse_until_before(Delimiter::Bang, |input| {
+            if name.eq_ignore_ascii_case("size") {
+                return parse_page_size(input).map(PageDeclaration::Size)
+            }
+            let is_margin = ["margin", "margin-top", "margin-right", "margin-bottom", "margin-left"]
+                                .iter().any(|margin| name.eq_ignore_ascii_case(margin));
+            if !is_margin {
+                return Err(())
+            }
+            let mut results = vec![];
+            match PropertyDeclaration::parse(name, self.context, input, &mut results) {
+                PropertyDeclarationParseResult::ValidOrIgnoredDeclaration => Ok(PageDeclaration::Margins(results)),
+                _ => Err(())
+            }
+        }));
+        let important = input.try(parse_important).is_ok();
+        Ok((declaration, important))
+    }
+}
+
+/// Parses the block of an `@page` rule with the given selectors.
+pub fn parse_page_block(context: &ParserContext, input: &mut Parser, selectors: Vec<PageSelector>) -> PageRule {
+    let mut normal = vec![];
+    let mut important = vec![];
+    let mut size = None;
+    let mut iter = DeclarationListParser::new(input, PageDeclarationParser { context: context });
+    while let Some(declaration) = iter.next() {
+        match declaration {
+            Ok((PageDeclaration::Margins(declarations), true)) => important.extend(declarations),
+            Ok((PageDeclaration::Margins(declarations), false)) => normal.extend(declarations),
+            Ok((PageDeclaration::Size(value), is_important)) => {
+                // A later normal declaration doesn't override an earlier important one.
+                if is_important || size.map_or(true, |(_, was_important)| !was_important) {
+                    size = Some((value, is_important));
+                }
+            }
+            Err(range) => {
+                let pos = range.start;
+                let message = format!("Unsupported @page descriptor declaration: '{}'",
+                                      iter.input.slice(range));
+                log_css_error(iter.input, pos, &*message, context);
+            }
+        }
+    }
+    PageRule {
+        selectors: selectors,
+        declarations: PropertyDeclarationBlock {
+            important: Arc::new(deduplicate_property_declarations(important)),
+            normal: Arc::new(deduplicate_property_declarations(normal)),
+            shorthands: vec![],
+        },
+        size: size,
+    }
+}
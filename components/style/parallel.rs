@@ -66,7 +66,15 @@ fn top_down_dom<N, C>(unsafe_nodes: UnsafeNodeList,
         // Perform the appropriate traversal.
         context.process_preorder(node);
 
-        let child_count = node.children_count();
+        // Gather the children to traverse. They're only enqueued once every node in
+        // this work unit is done, after their parent's count is set.
+        let previously_discovered = discovered_child_nodes.len();
+        node.each_child(|kid| {
+            if context.should_traverse_child(kid) {
+                discovered_child_nodes.push(kid.to_unsafe())
+            }
+        });
+        let child_count = discovered_child_nodes.len() - previously_discovered;
 
         // Reset the count of children.
         {
@@ -75,12 +83,7 @@ fn top_down_dom<N, C>(unsafe_nodes: UnsafeNodeList,
                                                Ordering::Relaxed);
         }
 
-        // Possibly enqueue the children.
-        if child_count != 0 {
-            for kid in node.children() {
-                discovered_child_nodes.push(kid.to_unsafe())
-            }
-        } else {
+        if child_count == 0 {
             // If there were no more children, start walking back up.
             bottom_up_dom::<N, C>(unsafe_nodes.1, unsafe_node, proxy)
         }
@@ -126,10 +129,13 @@ fn bottom_up_dom<N, C>(root: OpaqueNode,
             &*parent.borrow_data_unchecked().unwrap()
         };
 
+        // The children of a node can finish on different threads. Releasing here and
+        // acquiring in the last child to finish makes what all of them wrote in their
+        // bottom-up steps visible to the thread that goes on to the parent.
         if parent_data
             .parallel
             .children_count
-            .fetch_sub(1, Ordering::Relaxed) != 1 {
+            .fetch_sub(1, Ordering::AcqRel) != 1 {
             // Get out of here and find another node to work on.
             break
         }
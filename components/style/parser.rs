@@ -4,16 +4,31 @@
 
 
 use cssparser::{Parser, SourcePosition};
-use error_reporting::ParseErrorReporter;
+use error_reporting::{ParseErrorDetails, ParseErrorReporter};
 use selectors::parser::ParserContext as SelectorParserContext;
 use stylesheets::Origin;
 use url::Url;
+use url_value::{UrlExtraData, UrlValue};
+
+/// The compatibility mode of the document a stylesheet is parsed for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, HeapSizeOf)]
+pub enum QuirksMode {
+    /// Full quirks mode, which accepts some legacy syntax like hashless hex colors.
+    Quirks,
+    /// Almost standards mode, whose quirks only affect layout.
+    LimitedQuirks,
+    /// Standards mode.
+    NoQuirks,
+}
 
 pub struct ParserContext<'a> {
     pub stylesheet_origin: Origin,
     pub base_url: &'a Url,
     pub selector_context: SelectorParserContext,
     pub error_reporter: Box<ParseErrorReporter + Send>,
+    pub quirks_mode: QuirksMode,
+    /// What the embedder wants `url()` values in the sheet to carry.
+    pub url_extra_data: UrlExtraData,
 }
 
 impl<'a> ParserContext<'a> {
@@ -26,6 +41,8 @@ impl<'a> ParserContext<'a> {
             base_url: base_url,
             selector_context: selector_context,
             error_reporter: error_reporter,
+            quirks_mode: QuirksMode::NoQuirks,
+            url_extra_data: UrlExtraData::none(),
         }
     }
 }
@@ -36,6 +53,17 @@ impl<'a> ParserContext<'a> {
         self.base_url.join(input)
             .unwrap_or_else(|_| url!("about:invalid"))
     }
+
+    /// A `url()` value the embedder can load on behalf of the sheet being parsed.
+    pub fn parse_url_value(&self, input: &str) -> UrlValue {
+        UrlValue::new(self.parse_url(input), self.url_extra_data.clone())
+    }
+
+    /// Whether legacy quirks like hashless hex colors should be accepted. User agent
+    /// sheets are always parsed in standards mode.
+    pub fn in_quirks_mode(&self) -> bool {
+        self.quirks_mode == QuirksMode::Quirks && self.stylesheet_origin != Origin::UserAgent
+    }
 }
 
 
@@ -45,3 +73,9 @@ impl<'a> ParserContext<'a> {
 pub fn log_css_error(input: &mut Parser, position: SourcePosition, message: &str, parsercontext: &ParserContext) {
     parsercontext.error_reporter.report_error(input, position, message);
 }
+
+/// Like `log_css_error`, for errors that reporters can say more about.
+pub fn log_css_error_with_details(input: &mut Parser, position: SourcePosition, message: &str,
+                                  details: &ParseErrorDetails, parsercontext: &ParserContext) {
+    parsercontext.error_reporter.report_error_with_details(input, position, message, details);
+}
@@ -9,11 +9,13 @@
 // can be escaped. In the above example, Vec<<&Foo> achieves the desired result of Vec<&Foo>.
 
 use std::ascii::AsciiExt;
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::intrinsics;
 use std::mem;
 use std::sync::Arc;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
 
 use app_units::Au;
 use cssparser::{Parser, Color, RGBA, AtRuleParser, DeclarationParser, Delimiter,
@@ -28,6 +30,8 @@ use logical_geometry::{LogicalMargin, PhysicalSide, WritingMode};
 use parser::{ParserContext, log_css_error};
 use selectors::matching::DeclarationBlock;
 use stylesheets::Origin;
+use system_values::{SystemColor, SystemFont, SystemFontValues};
+use url_value::{ImageRequest, UrlExtraData};
 use values::AuExtensionMethods;
 use values::computed::{self, TContext, ToComputedValue};
 use values::specified::BorderStyle;
@@ -49,7 +53,7 @@ def to_camel_case(ident):
 
 class Longhand(object):
     def __init__(self, name, derived_from=None, custom_cascade=False, experimental=False,
-                 internal=False):
+                 internal=False, logical_side=None, physical_pattern=None):
         self.name = name
         self.ident = to_rust_ident(name)
         self.camel_case = to_camel_case(self.ident)
@@ -57,6 +61,10 @@ class Longhand(object):
         self.experimental = ("layout.%s.enabled" % name) if experimental else None
         self.custom_cascade = custom_cascade
         self.internal = internal
+        # A logical longhand has no slot of its own: it sets the physical longhand named by
+        # physical_pattern for whichever side logical_side is on in the element's writing mode.
+        self.logical_side = logical_side
+        self.physical_pattern = physical_pattern
         if derived_from is None:
             self.derived_from = None
         else:
@@ -108,6 +116,76 @@ LONGHANDS_BY_NAME = {}
 DERIVED_LONGHANDS = {}
 SHORTHANDS = []
 
+# Other names properties are parsed under, which serialize under the property's own name.
+ALIASES = {
+    "-moz-user-select": "user-select",
+}
+
+# The longhands of the Box struct Gecko builds scroll frames from.
+SCROLL_FRAME_LONGHANDS = ["overflow_x", "overflow_y", "resize", "scroll_snap_type_x", "scroll_snap_type_y",
+                          "scroll_snap_points_x", "scroll_snap_points_y", "scroll_snap_destination",
+                          "scroll_snap_coordinate"]
+
+# The longhands of the Margin and Padding structs.
+MARGIN_LONGHANDS = ["margin_" + side for side in ["top", "right", "bottom", "left"]]
+PADDING_LONGHANDS = ["padding_" + side for side in ["top", "right", "bottom", "left"]]
+
+# The longhands of the Box struct Gecko's nsStyleDisplay and nsStylePosition are filled in from,
+# besides the ones other callers clone too.
+BOX_STYLE_STRUCT_LONGHANDS = ["_servo_display_for_hypothetical_box", "clear", "min_width", "max_width",
+                              "min_height", "max_height", "box_sizing", "touch_action", "user_select"]
+
+# The longhands of the Effects struct Gecko's nsStyleDisplay and nsStyleVisibility are filled in
+# from.
+EFFECTS_STYLE_STRUCT_LONGHANDS = ["opacity", "mix_blend_mode", "backface_visibility", "transform_style",
+                                  "image_rendering"]
+
+# The longhands of the InheritedText struct Gecko lays lines out with.
+LINE_LAYOUT_LONGHANDS = ["white_space", "text_transform", "word_break", "overflow_wrap", "letter_spacing",
+                         "word_spacing", "tab_size", "text_align"]
+
+# The longhands of the InheritedText struct emphasis marks are drawn from.
+TEXT_EMPHASIS_LONGHANDS = ["text_emphasis_style", "text_emphasis_color", "text_emphasis_position"]
+
+# The longhands of the Text struct decoration lines are drawn from.
+TEXT_DECORATION_LONGHANDS = ["text_decoration_line", "text_decoration_style", "text_decoration_color"]
+
+# The longhands of the Border struct borders are drawn from, besides their radii.
+BORDER_SIDE_LONGHANDS = ["border_%s_%s" % (side, prop) for side in ["top", "right", "bottom", "left"]
+                         for prop in ["color", "style", "width"]]
+BORDER_IMAGE_LONGHANDS = ["border_image_" + name for name in ["source", "slice", "width", "outset", "repeat"]]
+
+# The longhands of the Outline struct.
+OUTLINE_LONGHANDS = ["outline_color", "outline_style", "outline_width", "outline_offset"]
+
+# The longhands of the Column struct multi-column elements are laid out with.
+COLUMN_LONGHANDS = ["column_width", "column_count", "column_gap", "column_rule_width", "column_rule_style",
+                    "column_rule_color"]
+
+# The longhands of the Flex struct flex containers and their items are laid out with.
+FLEX_LONGHANDS = ["flex_direction", "flex_wrap", "justify_content", "align_items", "align_content",
+                  "align_self", "flex_grow", "flex_shrink", "flex_basis", "order"]
+
+# The longhands of the Background struct that take a value for each layer.
+BACKGROUND_LAYER_LONGHANDS = ["background_image", "background_position", "background_repeat",
+                              "background_attachment", "background_clip", "background_origin", "background_size"]
+
+# The longhands of the Counters struct generated content and list numbering go by.
+COUNTER_LONGHANDS = ["counter_increment", "counter_reset", "counter_set"]
+
+# The longhands of the Font struct.
+FONT_LONGHANDS = ["font_family", "font_style", "font_variant", "font_weight", "font_size", "font_stretch",
+                  "font_size_adjust", "font_feature_settings", "font_variation_settings",
+                  "font_language_override", "_x_lang", "_servo_system_font"]
+
+# The sides the logical longhands are for.
+LOGICAL_SIDES = ["block-start", "block-end", "inline-start", "inline-end"]
+PHYSICAL_SIDES = ["top", "right", "bottom", "left"]
+
+# The logical longhands, which go in the struct of the physical longhands they map to, but have
+# no slot in it.
+LOGICAL_LONGHANDS = []
+
 def new_style_struct(name, is_inherited, gecko_name=None, additional_methods=None):
     global THIS_STYLE_STRUCT
 
@@ -132,7 +210,7 @@ pub mod longhands {
     use values::specified;
 
     <%def name="raw_longhand(name, derived_from=None, custom_cascade=False, experimental=False,
-                             internal=False)">
+                             internal=False, logical_side=None, physical_pattern=None)">
     <%
         if derived_from is not None:
             derived_from = derived_from.split()
@@ -141,9 +219,14 @@ pub mod longhands {
                             derived_from=derived_from,
                             custom_cascade=custom_cascade,
                             experimental=experimental,
-                            internal=internal)
+                            internal=internal,
+                            logical_side=logical_side,
+                            physical_pattern=physical_pattern)
         property.style_struct = THIS_STYLE_STRUCT
-        THIS_STYLE_STRUCT.longhands.append(property)
+        if logical_side is None:
+            THIS_STYLE_STRUCT.longhands.append(property)
+        else:
+            LOGICAL_LONGHANDS.append(property)
         LONGHANDS.append(property)
         LONGHANDS_BY_NAME[name] = property
 
@@ -184,7 +267,34 @@ pub mod longhands {
                     }
                     _ => panic!("entered the wrong cascade_property() implementation"),
                 };
-                % if property.derived_from is None:
+                % if property.logical_side:
+                    // The physical longhand is cascaded in the declaration's place, so that
+                    // whichever of the two comes last wins. Its side depends on the writing mode,
+                    // which may be the parent's, so the style can't be reused for another parent.
+                    *cacheable = false;
+                    let mut substituted = None;
+                    {
+                        let custom_props = context.style().custom_properties();
+                        ::properties::substitute_variables_${property.ident}(
+                            declared_value, &custom_props, |value| substituted = Some(value.clone()),
+                            error_reporter);
+                    }
+                    let value = match substituted {
+                        Some(value) => value,
+                        None => return,
+                    };
+                    let mode = context.style().writing_mode();
+                    match mode.${to_rust_ident(logical_side)}_physical_side() {
+                        % for side in PHYSICAL_SIDES:
+                        <% physical = to_rust_ident(physical_pattern % side) %>
+                        ::logical_geometry::PhysicalSide::${side.capitalize()} => {
+                            longhands::${physical}::cascade_property(
+                                &PropertyDeclaration::${to_camel_case(physical)}(value), inherited_style,
+                                context, seen, cacheable, error_reporter)
+                        }
+                        % endfor
+                    }
+                % elif property.derived_from is None:
                     if seen.get_${property.ident}() {
                         return
                     }
@@ -331,17 +441,137 @@ pub mod longhands {
         </%self:longhand>
     </%def>
 
+    // A longhand that takes a value for each background layer, separated by commas. The body
+    // defines the value of a single layer, as it would for a longhand of its own.
+    <%def name="layered_longhand(name)">
+        <%self:longhand name="${name}">
+            use cssparser::ToCss;
+            use std::fmt;
+
+            pub mod single_value {
+                #![allow(unused_imports)]
+                use cssparser::Parser;
+                use parser::ParserContext;
+                use values::computed::{TContext, ToComputedValue};
+                use values::{computed, specified};
+                ${caller.body()}
+            }
+
+            pub mod computed_value {
+                pub use super::single_value::computed_value::T as SingleComputedValue;
+
+                /// The value of each layer, from the top one down. There's at least one.
+                #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
+                pub struct T(pub Vec<SingleComputedValue>);
+
+                impl T {
+                    /// The value of the layer at `index`. The layers are as many as background-image
+                    /// has images, and a shorter list is repeated as many times as it takes to cover
+                    /// them all.
+                    pub fn layer(&self, index: usize) -> &SingleComputedValue {
+                        &self.0[index % self.0.len()]
+                    }
+                }
+            }
+
+            #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
+            pub struct SpecifiedValue(pub Vec<single_value::SpecifiedValue>);
+
+            impl ToCss for SpecifiedValue {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    for (index, value) in self.0.iter().enumerate() {
+                        if index != 0 {
+                            try!(dest.write_str(", "));
+                        }
+                        try!(value.to_css(dest));
+                    }
+                    Ok(())
+                }
+            }
+
+            impl ToCss for computed_value::T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    for (index, value) in self.0.iter().enumerate() {
+                        if index != 0 {
+                            try!(dest.write_str(", "));
+                        }
+                        try!(value.to_css(dest));
+                    }
+                    Ok(())
+                }
+            }
+
+            impl ToComputedValue for SpecifiedValue {
+                type ComputedValue = computed_value::T;
+
+                #[inline]
+                fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                    computed_value::T(self.0.iter().map(|value| value.to_computed_value(context)).collect())
+                }
+            }
+
+            #[inline]
+            pub fn get_initial_value() -> computed_value::T {
+                computed_value::T(vec![single_value::get_initial_value()])
+            }
+
+            pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+                input.parse_comma_separated(|input| single_value::parse(context, input)).map(SpecifiedValue)
+            }
+        </%self:longhand>
+    </%def>
+
+    <%def name="layered_single_keyword(name, values)">
+        <%self:layered_longhand name="${name}">
+            use values::computed::ComputedValueAsSpecified;
+
+            pub use self::computed_value::T as SpecifiedValue;
+            impl ComputedValueAsSpecified for SpecifiedValue {}
+
+            pub mod computed_value {
+                define_css_keyword_enum! { T:
+                    % for value in values.split():
+                        "${value}" => ${to_rust_ident(value)},
+                    % endfor
+                }
+            }
+            #[inline] pub fn get_initial_value() -> computed_value::T {
+                computed_value::T::${to_rust_ident(values.split()[0])}
+            }
+            pub fn parse(_context: &ParserContext, input: &mut Parser)
+                         -> Result<SpecifiedValue, ()> {
+                computed_value::T::parse(input)
+            }
+        </%self:layered_longhand>
+    </%def>
+
+    <%def name="logical_longhands(logical_pattern, physical_pattern)">
+        % for side in LOGICAL_SIDES:
+            <%self:raw_longhand name="${logical_pattern % side}" logical_side="${side}"
+                                physical_pattern="${physical_pattern}">
+                pub use super::${to_rust_ident(physical_pattern % "top")}::{SpecifiedValue, computed_value};
+                pub use super::${to_rust_ident(physical_pattern % "top")}::{get_initial_value, parse, parse_specified};
+            </%self:raw_longhand>
+        % endfor
+    </%def>
+
 
     // CSS 2.1, Section 8 - Box model
 
-    ${new_style_struct("Margin", is_inherited=False, gecko_name="nsStyleMargin")}
+    ${new_style_struct("Margin", is_inherited=False, gecko_name="nsStyleMargin",
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in MARGIN_LONGHANDS])}
 
     % for side in ["top", "right", "bottom", "left"]:
         ${predefined_type("margin-" + side, "LengthOrPercentageOrAuto",
                           "computed::LengthOrPercentageOrAuto::Length(Au(0))")}
     % endfor
 
-    ${new_style_struct("Padding", is_inherited=False, gecko_name="nsStylePadding")}
+    ${logical_longhands("margin-%s", "margin-%s")}
+
+    ${new_style_struct("Padding", is_inherited=False, gecko_name="nsStylePadding",
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in PADDING_LONGHANDS])}
 
     % for side in ["top", "right", "bottom", "left"]:
         ${predefined_type("padding-" + side, "LengthOrPercentage",
@@ -349,9 +579,13 @@ pub mod longhands {
                           "parse_non_negative")}
     % endfor
 
+    ${logical_longhands("padding-%s", "padding-%s")}
+
     ${new_style_struct("Border", is_inherited=False, gecko_name="nsStyleBorder",
                        additional_methods=[Method("border_" + side + "_is_none_or_hidden_and_has_nonzero_width",
-                                                  "bool") for side in ["top", "right", "bottom", "left"]])}
+                                                  "bool") for side in ["top", "right", "bottom", "left"]] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in BORDER_SIDE_LONGHANDS + BORDER_IMAGE_LONGHANDS])}
 
     % for side in ["top", "right", "bottom", "left"]:
         ${predefined_type("border-%s-color" % side, "CSSColor", "::cssparser::Color::CurrentColor")}
@@ -406,467 +640,440 @@ pub mod longhands {
                           "parse")}
     % endfor
 
-    ${new_style_struct("Outline", is_inherited=False, gecko_name="nsStyleOutline",
-                       additional_methods=[Method("outline_is_none_or_hidden_and_has_nonzero_width", "bool")])}
-
-    // TODO(pcwalton): `invert`
-    ${predefined_type("outline-color", "CSSColor", "::cssparser::Color::CurrentColor")}
+    <%self:longhand name="border-image-source">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::specified::Image;
 
-    <%self:longhand name="outline-style">
-        pub use values::specified::BorderStyle as SpecifiedValue;
-        pub fn get_initial_value() -> SpecifiedValue { SpecifiedValue::none }
         pub mod computed_value {
-            pub use values::specified::BorderStyle as T;
+            use values::computed;
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<computed::Image>);
         }
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            match SpecifiedValue::parse(input) {
-                Ok(SpecifiedValue::hidden) => Err(()),
-                result => result
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    None => dest.write_str("none"),
+                    Some(ref image) => image.to_css(dest),
+                }
             }
         }
-    </%self:longhand>
 
-    <%self:longhand name="outline-width">
-        use app_units::Au;
-        use cssparser::ToCss;
-        use std::fmt;
-        use values::AuExtensionMethods;
+        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub Option<Image>);
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                self.0.to_css(dest)
+                match *self {
+                    SpecifiedValue(Some(ref image)) => image.to_css(dest),
+                    SpecifiedValue(None) => dest.write_str("none"),
+                }
             }
         }
 
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            specified::parse_border_width(input).map(SpecifiedValue)
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
         }
-        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-        pub struct SpecifiedValue(pub specified::Length);
-        pub mod computed_value {
-            use app_units::Au;
-            pub type T = Au;
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                Ok(SpecifiedValue(None))
+            } else {
+                Ok(SpecifiedValue(Some(try!(Image::parse(context, input)))))
+            }
         }
-        pub use super::border_top_width::get_initial_value;
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                self.0.to_computed_value(context)
+                computed_value::T(self.0.as_ref().map(|image| image.to_computed_value(context)))
             }
         }
     </%self:longhand>
 
-    ${predefined_type("outline-offset", "Length", "Au(0)")}
-
-    ${new_style_struct("PositionOffsets", is_inherited=False, gecko_name="nsStylePosition")}
+    <%self:longhand name="border-image-slice">
+        use cssparser::{ToCss, Token};
+        use properties::shorthands::parse_four_sides;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+        use values::{CSSFloat, four_sides_to_css};
 
-    % for side in ["top", "right", "bottom", "left"]:
-        ${predefined_type(side, "LengthOrPercentageOrAuto",
-                          "computed::LengthOrPercentageOrAuto::Auto")}
-    % endfor
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub enum SliceOffset {
+            /// A number of the image's pixels, or of its coordinates if it's a vector image.
+            Number(CSSFloat),
+            Percentage(specified::Percentage),
+        }
 
-    // CSS 2.1, Section 9 - Visual formatting model
+        impl ToCss for SliceOffset {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SliceOffset::Number(number) => write!(dest, "{}", number),
+                    SliceOffset::Percentage(percentage) => percentage.to_css(dest),
+                }
+            }
+        }
 
-    ${new_style_struct("Box", is_inherited=False, gecko_name="nsStyleDisplay",
-                       additional_methods=[Method("clone_display",
-                                                  "longhands::display::computed_value::T"),
-                                           Method("clone_position",
-                                                  "longhands::position::computed_value::T"),
-                                           Method("is_floated", "bool"),
-                                           Method("overflow_x_is_visible", "bool"),
-                                           Method("overflow_y_is_visible", "bool")])}
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue {
+            /// How far in from the top, right, bottom and left edges of the image to slice it.
+            pub offsets: [SliceOffset; 4],
+            /// Whether the middle of the image is drawn behind the element's background.
+            pub fill: bool,
+        }
 
-    // TODO(SimonSapin): don't parse `inline-table`, since we don't support it
-    <%self:longhand name="display" custom_cascade="True">
-        <%
-            values = """inline block inline-block
-                table inline-table table-row-group table-header-group table-footer-group
-                table-row table-column-group table-column table-cell table-caption
-                list-item flex
-                none
-            """.split()
-            experimental_values = set("flex".split())
-        %>
-        pub use self::computed_value::T as SpecifiedValue;
-        use values::computed::{Context, ComputedValueAsSpecified};
-        use properties::style_struct_traits::TInheritedText;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
 
         pub mod computed_value {
-            #[allow(non_camel_case_types)]
-            #[derive(Clone, Eq, PartialEq, Copy, Hash, RustcEncodable, Debug, HeapSizeOf)]
-            #[derive(Deserialize, Serialize)]
-            pub enum T {
-                % for value in values:
-                    ${to_rust_ident(value)},
-                % endfor
-            }
+            pub type T = super::SpecifiedValue;
+        }
 
-            impl ::cssparser::ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> ::std::fmt::Result
-                where W: ::std::fmt::Write {
-                    match *self {
-                        % for value in values:
-                            T::${to_rust_ident(value)} => dest.write_str("${value}"),
-                        % endfor
-                    }
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(four_sides_to_css(&self.offsets, dest));
+                if self.fill {
+                    try!(dest.write_str(" fill"));
                 }
+                Ok(())
             }
         }
-        #[inline] pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::${to_rust_ident(values[0])}
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            SpecifiedValue {
+                offsets: [SliceOffset::Percentage(specified::Percentage(1.)); 4],
+                fill: false,
+            }
         }
-        pub fn parse(_context: &ParserContext, input: &mut Parser)
-                     -> Result<SpecifiedValue, ()> {
-            match_ignore_ascii_case! { try!(input.expect_ident()),
-                % for value in values:
-                    "${value}" => {
-                        % if value in experimental_values:
-                            if !::util::prefs::get_pref("layout.${value}.enabled")
-                                .as_boolean().unwrap_or(false) {
-                                return Err(())
-                            }
-                        % endif
-                        Ok(computed_value::T::${to_rust_ident(value)})
-                    },
-                % endfor
+
+        fn parse_offset(input: &mut Parser) -> Result<SliceOffset, ()> {
+            match try!(input.next()) {
+                Token::Number(ref value) if value.value >= 0. => Ok(SliceOffset::Number(value.value)),
+                Token::Percentage(ref value) if value.unit_value >= 0. => {
+                    Ok(SliceOffset::Percentage(specified::Percentage(value.unit_value)))
+                }
                 _ => Err(())
             }
         }
 
-        impl ComputedValueAsSpecified for SpecifiedValue {}
+        /// [ <number> | <percentage> ]{1,4} && fill?
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            let mut fill = input.try(|input| input.expect_ident_matching("fill")).is_ok();
+            let (top, right, bottom, left) = try!(parse_four_sides(input, parse_offset));
+            if !fill {
+                fill = input.try(|input| input.expect_ident_matching("fill")).is_ok();
+            }
+            Ok(SpecifiedValue {
+                offsets: [top, right, bottom, left],
+                fill: fill,
+            })
+        }
+    </%self:longhand>
 
-        fn cascade_property_custom<C: ComputedValues>(
-                                   _declaration: &PropertyDeclaration,
-                                   _inherited_style: &C,
-                                   context: &mut computed::Context<C>,
-                                   _seen: &mut PropertyBitField,
-                                   _cacheable: &mut bool,
-                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
-            longhands::_servo_display_for_hypothetical_box::derive_from_display(context);
-            longhands::_servo_text_decorations_in_effect::derive_from_display(context);
-        }
-    </%self:longhand>
-
-    ${single_keyword("position", "static absolute relative fixed")}
-
-    <%self:single_keyword_computed name="float" values="none left right">
-        impl ToComputedValue for SpecifiedValue {
-            type ComputedValue = computed_value::T;
+    <%self:longhand name="border-image-width">
+        use cssparser::{ToCss, Token};
+        use properties::shorthands::parse_four_sides;
+        use std::fmt;
+        use values::{CSSFloat, four_sides_to_css};
 
-            #[inline]
-            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                let positioned = matches!(context.style().get_box().clone_position(),
-                    longhands::position::SpecifiedValue::absolute |
-                    longhands::position::SpecifiedValue::fixed);
-                if positioned {
-                    SpecifiedValue::none
-                } else {
-                    *self
-                }
-            }
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub enum SpecifiedSide {
+            LengthOrPercentage(specified::LengthOrPercentage),
+            /// A multiple of the border's width on that side.
+            Number(CSSFloat),
+            /// The image's own width or height on that side, once sliced.
+            Auto,
         }
 
-    </%self:single_keyword_computed>
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue(pub [SpecifiedSide; 4]);
 
-    ${single_keyword("clear", "none left right both")}
+        pub mod computed_value {
+            use values::{CSSFloat, computed};
 
-    <%self:longhand name="-servo-display-for-hypothetical-box" derived_from="display">
-        pub use super::display::{SpecifiedValue, get_initial_value};
-        pub use super::display::{parse};
+            #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+            pub enum Side {
+                LengthOrPercentage(computed::LengthOrPercentage),
+                Number(CSSFloat),
+                Auto,
+            }
 
-        pub mod computed_value {
-            pub type T = super::SpecifiedValue;
+            #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+            pub struct T(pub [Side; 4]);
         }
 
-        #[inline]
-        pub fn derive_from_display<Cx: TContext>(context: &mut Cx) {
-            let d = context.style().get_box().clone_display();
-            context.mutate_style().mutate_box().set__servo_display_for_hypothetical_box(d);
+        impl ToCss for SpecifiedSide {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedSide::LengthOrPercentage(ref length) => length.to_css(dest),
+                    SpecifiedSide::Number(number) => write!(dest, "{}", number),
+                    SpecifiedSide::Auto => dest.write_str("auto"),
+                }
+            }
         }
 
-    </%self:longhand>
-
-    <%self:longhand name="z-index">
-        use values::computed::ComputedValueAsSpecified;
-
-        impl ComputedValueAsSpecified for SpecifiedValue {}
-        pub type SpecifiedValue = computed_value::T;
-        pub mod computed_value {
-            use cssparser::ToCss;
-            use std::fmt;
-
-            #[derive(PartialEq, Clone, Eq, Copy, Debug, HeapSizeOf)]
-            pub enum T {
-                Auto,
-                Number(i32),
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                four_sides_to_css(&self.0, dest)
             }
+        }
 
-            impl ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    match *self {
-                        T::Auto => dest.write_str("auto"),
-                        T::Number(number) => write!(dest, "{}", number),
-                    }
+        impl ToCss for computed_value::Side {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::Side::LengthOrPercentage(ref length) => length.to_css(dest),
+                    computed_value::Side::Number(number) => write!(dest, "{}", number),
+                    computed_value::Side::Auto => dest.write_str("auto"),
                 }
             }
+        }
 
-            impl T {
-                pub fn number_or_zero(self) -> i32 {
-                    match self {
-                        T::Auto => 0,
-                        T::Number(value) => value,
-                    }
-                }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                four_sides_to_css(&self.0, dest)
             }
         }
+
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::Auto
-        }
-        fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
-                Ok(computed_value::T::Auto)
-            } else {
-                specified::parse_integer(input).map(computed_value::T::Number)
-            }
+            computed_value::T([computed_value::Side::Number(1.); 4])
         }
-    </%self:longhand>
-
-    ${new_style_struct("InheritedBox", is_inherited=True,
-                       additional_methods=[Method("clone_direction",
-                                                  "longhands::direction::computed_value::T"),
-                                           Method("clone_writing_mode",
-                                                  "longhands::writing_mode::computed_value::T"),
-                                           Method("clone_text_orientation",
-                                                  "longhands::text_orientation::computed_value::T")])}
 
-    ${single_keyword("direction", "ltr rtl")}
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
 
-    // CSS 2.1, Section 10 - Visual formatting model details
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                let mut sides = [computed_value::Side::Auto; 4];
+                for (side, specified) in sides.iter_mut().zip(self.0.iter()) {
+                    *side = match *specified {
+                        SpecifiedSide::LengthOrPercentage(ref length) => {
+                            computed_value::Side::LengthOrPercentage(length.to_computed_value(context))
+                        }
+                        SpecifiedSide::Number(number) => computed_value::Side::Number(number),
+                        SpecifiedSide::Auto => computed_value::Side::Auto,
+                    };
+                }
+                computed_value::T(sides)
+            }
+        }
 
-    ${switch_to_style_struct("Box")}
+        fn parse_side(input: &mut Parser) -> Result<SpecifiedSide, ()> {
+            // A unitless zero is the number, which comes to the same thing.
+            if let Ok(number) = input.try(|input| match try!(input.next()) {
+                Token::Number(ref value) if value.value >= 0. => Ok(value.value),
+                _ => Err(()),
+            }) {
+                return Ok(SpecifiedSide::Number(number))
+            }
+            if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
+                return Ok(SpecifiedSide::Auto)
+            }
+            specified::LengthOrPercentage::parse_non_negative(input).map(SpecifiedSide::LengthOrPercentage)
+        }
 
-    ${predefined_type("width", "LengthOrPercentageOrAuto",
-                      "computed::LengthOrPercentageOrAuto::Auto",
-                      "parse_non_negative")}
+        /// [ <length-percentage> | <number> | auto ]{1,4}
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            let (top, right, bottom, left) = try!(parse_four_sides(input, parse_side));
+            Ok(SpecifiedValue([top, right, bottom, left]))
+        }
+    </%self:longhand>
 
-    ${predefined_type("height", "LengthOrPercentageOrAuto",
-                      "computed::LengthOrPercentageOrAuto::Auto",
-                      "parse_non_negative")}
+    <%self:longhand name="border-image-outset">
+        use cssparser::{ToCss, Token};
+        use properties::shorthands::parse_four_sides;
+        use std::fmt;
+        use values::{AuExtensionMethods, CSSFloat, four_sides_to_css};
 
-    ${predefined_type("min-width", "LengthOrPercentage",
-                      "computed::LengthOrPercentage::Length(Au(0))",
-                      "parse_non_negative")}
-    ${predefined_type("max-width", "LengthOrPercentageOrNone",
-                      "computed::LengthOrPercentageOrNone::None",
-                      "parse_non_negative")}
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub enum SpecifiedSide {
+            Length(specified::Length),
+            /// A multiple of the border's width on that side.
+            Number(CSSFloat),
+        }
 
-    ${predefined_type("min-height", "LengthOrPercentage",
-                      "computed::LengthOrPercentage::Length(Au(0))",
-                      "parse_non_negative")}
-    ${predefined_type("max-height", "LengthOrPercentageOrNone",
-                      "computed::LengthOrPercentageOrNone::None",
-                      "parse_non_negative")}
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue(pub [SpecifiedSide; 4]);
 
-    ${switch_to_style_struct("InheritedBox")}
+        pub mod computed_value {
+            use app_units::Au;
+            use values::CSSFloat;
 
-    <%self:longhand name="line-height">
-        use cssparser::ToCss;
-        use std::fmt;
-        use values::AuExtensionMethods;
-        use values::CSSFloat;
+            #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+            pub enum Side {
+                Length(Au),
+                Number(CSSFloat),
+            }
 
-        #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
-        pub enum SpecifiedValue {
-            Normal,
-            Number(CSSFloat),
-            LengthOrPercentage(specified::LengthOrPercentage),
+            #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+            pub struct T(pub [Side; 4]);
         }
 
-        impl ToCss for SpecifiedValue {
+        impl ToCss for SpecifiedSide {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    SpecifiedValue::Normal => dest.write_str("normal"),
-                    SpecifiedValue::LengthOrPercentage(value) => value.to_css(dest),
-                    SpecifiedValue::Number(number) => write!(dest, "{}", number),
+                    SpecifiedSide::Length(ref length) => length.to_css(dest),
+                    SpecifiedSide::Number(number) => write!(dest, "{}", number),
                 }
             }
         }
-        /// normal | <number> | <length> | <percentage>
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            use cssparser::Token;
-            use std::ascii::AsciiExt;
-            input.try(specified::LengthOrPercentage::parse_non_negative)
-            .map(SpecifiedValue::LengthOrPercentage)
-            .or_else(|()| {
-                match try!(input.next()) {
-                    Token::Number(ref value) if value.value >= 0. => {
-                        Ok(SpecifiedValue::Number(value.value))
-                    }
-                    Token::Ident(ref value) if value.eq_ignore_ascii_case("normal") => {
-                        Ok(SpecifiedValue::Normal)
-                    }
-                    _ => Err(()),
-                }
-            })
-        }
-        pub mod computed_value {
-            use app_units::Au;
-            use std::fmt;
-            use values::CSSFloat;
-            #[derive(PartialEq, Copy, Clone, HeapSizeOf, Debug)]
-            pub enum T {
-                Normal,
-                Length(Au),
-                Number(CSSFloat),
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                four_sides_to_css(&self.0, dest)
             }
         }
-        impl ToCss for computed_value::T {
+
+        impl ToCss for computed_value::Side {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    computed_value::T::Normal => dest.write_str("normal"),
-                    computed_value::T::Length(length) => length.to_css(dest),
-                    computed_value::T::Number(number) => write!(dest, "{}", number),
+                    computed_value::Side::Length(length) => length.to_css(dest),
+                    computed_value::Side::Number(number) => write!(dest, "{}", number),
                 }
             }
         }
-         #[inline]
-        pub fn get_initial_value() -> computed_value::T { computed_value::T::Normal }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                four_sides_to_css(&self.0, dest)
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T([computed_value::Side::Number(0.); 4])
+        }
 
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                match *self {
-                    SpecifiedValue::Normal => computed_value::T::Normal,
-                    SpecifiedValue::Number(value) => computed_value::T::Number(value),
-                    SpecifiedValue::LengthOrPercentage(value) => {
-                        match value {
-                            specified::LengthOrPercentage::Length(value) =>
-                                computed_value::T::Length(value.to_computed_value(context)),
-                            specified::LengthOrPercentage::Percentage(specified::Percentage(value)) => {
-                                let fr = specified::Length::FontRelative(specified::FontRelativeLength::Em(value));
-                                computed_value::T::Length(fr.to_computed_value(context))
-                            },
-                            specified::LengthOrPercentage::Calc(calc) => {
-                                let calc = calc.to_computed_value(context);
-                                let fr = specified::FontRelativeLength::Em(calc.percentage());
-                                let fr = specified::Length::FontRelative(fr);
-                                computed_value::T::Length(calc.length() + fr.to_computed_value(context))
-                            }
+                let mut sides = [computed_value::Side::Number(0.); 4];
+                for (side, specified) in sides.iter_mut().zip(self.0.iter()) {
+                    *side = match *specified {
+                        SpecifiedSide::Length(ref length) => {
+                            computed_value::Side::Length(length.to_computed_value(context))
                         }
-                    }
+                        SpecifiedSide::Number(number) => computed_value::Side::Number(number),
+                    };
                 }
+                computed_value::T(sides)
             }
         }
-    </%self:longhand>
 
-    ${switch_to_style_struct("Box")}
+        fn parse_side(input: &mut Parser) -> Result<SpecifiedSide, ()> {
+            if let Ok(number) = input.try(|input| match try!(input.next()) {
+                Token::Number(ref value) if value.value >= 0. => Ok(value.value),
+                _ => Err(()),
+            }) {
+                return Ok(SpecifiedSide::Number(number))
+            }
+            specified::Length::parse_non_negative(input).map(SpecifiedSide::Length)
+        }
 
-    <%self:longhand name="vertical-align">
+        /// [ <length> | <number> ]{1,4}
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            let (top, right, bottom, left) = try!(parse_four_sides(input, parse_side));
+            Ok(SpecifiedValue([top, right, bottom, left]))
+        }
+    </%self:longhand>
+
+    <%self:longhand name="border-image-repeat">
         use cssparser::ToCss;
         use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
 
-        <% vertical_align_keywords = (
-            "baseline sub super top text-top middle bottom text-bottom".split()) %>
-        #[allow(non_camel_case_types)]
-        #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
-        pub enum SpecifiedValue {
-            % for keyword in vertical_align_keywords:
-                ${to_rust_ident(keyword)},
-            % endfor
-            LengthOrPercentage(specified::LengthOrPercentage),
+        define_css_keyword_enum!(RepeatKeyword:
+                                 "stretch" => Stretch,
+                                 "repeat" => Repeat,
+                                 "round" => Round,
+                                 "space" => Space);
+
+        /// How the edges of the image are fitted to the horizontal and vertical sides.
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue(pub RepeatKeyword, pub RepeatKeyword);
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            pub type T = super::SpecifiedValue;
         }
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match *self {
-                    % for keyword in vertical_align_keywords:
-                        SpecifiedValue::${to_rust_ident(keyword)} => dest.write_str("${keyword}"),
-                    % endfor
-                    SpecifiedValue::LengthOrPercentage(value) => value.to_css(dest),
+                try!(self.0.to_css(dest));
+                if self.1 != self.0 {
+                    try!(dest.write_str(" "));
+                    try!(self.1.to_css(dest));
                 }
+                Ok(())
             }
         }
-        /// baseline | sub | super | top | text-top | middle | bottom | text-bottom
-        /// | <percentage> | <length>
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            SpecifiedValue(RepeatKeyword::Stretch, RepeatKeyword::Stretch)
+        }
+
+        /// [ stretch | repeat | round | space ]{1,2}
         pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            input.try(specified::LengthOrPercentage::parse)
-            .map(SpecifiedValue::LengthOrPercentage)
-            .or_else(|()| {
-                match_ignore_ascii_case! { try!(input.expect_ident()),
-                    % for keyword in vertical_align_keywords:
-                        "${keyword}" => Ok(SpecifiedValue::${to_rust_ident(keyword)}),
-                    % endfor
-                    _ => Err(())
-                }
-            })
+            let horizontal = try!(RepeatKeyword::parse(input));
+            let vertical = input.try(RepeatKeyword::parse).unwrap_or(horizontal);
+            Ok(SpecifiedValue(horizontal, vertical))
         }
+    </%self:longhand>
+
+    ${new_style_struct("Outline", is_inherited=False, gecko_name="nsStyleOutline",
+                       additional_methods=[Method("outline_is_none_or_hidden_and_has_nonzero_width", "bool")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in OUTLINE_LONGHANDS])}
+
+    <%self:longhand name="outline-color">
+        pub type SpecifiedValue = specified::CSSColor;
         pub mod computed_value {
-            use app_units::Au;
-            use std::fmt;
-            use values::AuExtensionMethods;
-            use values::{CSSFloat, computed};
-            #[allow(non_camel_case_types)]
-            #[derive(PartialEq, Copy, Clone, HeapSizeOf, Debug)]
-            pub enum T {
-                % for keyword in vertical_align_keywords:
-                    ${to_rust_ident(keyword)},
-                % endfor
-                LengthOrPercentage(computed::LengthOrPercentage),
-            }
-            impl ::cssparser::ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    match *self {
-                        % for keyword in vertical_align_keywords:
-                            T::${to_rust_ident(keyword)} => dest.write_str("${keyword}"),
-                        % endfor
-                        T::LengthOrPercentage(value) => value.to_css(dest),
-                    }
-                }
-            }
+            pub use values::computed::CSSColor as T;
         }
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T { computed_value::T::baseline }
+        #[inline] pub fn get_initial_value() -> computed_value::T { ::cssparser::Color::CurrentColor }
 
-        impl ToComputedValue for SpecifiedValue {
-            type ComputedValue = computed_value::T;
-
-            #[inline]
-            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                match *self {
-                    % for keyword in vertical_align_keywords:
-                        SpecifiedValue::${to_rust_ident(keyword)} => {
-                            computed_value::T::${to_rust_ident(keyword)}
-                        }
-                    % endfor
-                    SpecifiedValue::LengthOrPercentage(value) =>
-                        computed_value::T::LengthOrPercentage(value.to_computed_value(context)),
-                }
+        /// <color> | invert
+        ///
+        /// Nothing can paint an inverted outline, so `invert` is taken to mean `currentColor`,
+        /// which is what the spec allows when it isn't supported. It keeps its name when the
+        /// declaration is serialized.
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("invert")).is_ok() {
+                return Ok(specified::CSSColor {
+                    parsed: ::cssparser::Color::CurrentColor,
+                    authored: Some("invert".to_owned()),
+                    system: None,
+                })
             }
+            specified::CSSColor::parse(input)
         }
     </%self:longhand>
 
+    <%self:longhand name="outline-style">
+        pub use values::specified::BorderStyle as SpecifiedValue;
+        pub fn get_initial_value() -> SpecifiedValue { SpecifiedValue::none }
+        pub mod computed_value {
+            pub use values::specified::BorderStyle as T;
+        }
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            match SpecifiedValue::parse(input) {
+                Ok(SpecifiedValue::hidden) => Err(()),
+                result => result
+            }
+        }
+    </%self:longhand>
 
-    // CSS 2.1, Section 11 - Visual effects
-
-    // Non-standard, see https://developer.mozilla.org/en-US/docs/Web/CSS/overflow-clip-box#Specifications
-    ${single_keyword("-servo-overflow-clip-box", "padding-box content-box", internal=True)}
-
-    // FIXME(pcwalton, #2742): Implement scrolling for `scroll` and `auto`.
-    ${single_keyword("overflow-x", "visible hidden scroll auto")}
-
-    // FIXME(pcwalton, #2742): Implement scrolling for `scroll` and `auto`.
-    <%self:longhand name="overflow-y">
-        use super::overflow_x;
-
+    <%self:longhand name="outline-width">
+        use app_units::Au;
         use cssparser::ToCss;
         use std::fmt;
-
-        pub use self::computed_value::T as SpecifiedValue;
+        use values::AuExtensionMethods;
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
@@ -874,688 +1081,696 @@ pub mod longhands {
             }
         }
 
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            specified::parse_border_width(input).map(SpecifiedValue)
+        }
+        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub specified::Length);
         pub mod computed_value {
-            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
-            pub struct T(pub super::super::overflow_x::computed_value::T);
+            use app_units::Au;
+            pub type T = Au;
         }
-
+        pub use super::border_top_width::get_initial_value;
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                computed_value::T(self.0.to_computed_value(context))
+                self.0.to_computed_value(context)
             }
         }
-
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(overflow_x::get_initial_value())
-        }
-
-        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            overflow_x::parse(context, input).map(SpecifiedValue)
-        }
     </%self:longhand>
 
-    ${switch_to_style_struct("InheritedBox")}
+    ${predefined_type("outline-offset", "Length", "Au(0)")}
 
-    // TODO: collapse. Well, do tables first.
-    ${single_keyword("visibility", "visible hidden")}
+    ${new_style_struct("PositionOffsets", is_inherited=False, gecko_name="nsStylePosition",
+                       additional_methods=[Method("clone_" + side, "longhands::%s::computed_value::T" % side)
+                                           for side in ["top", "right", "bottom", "left"]])}
 
-    // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
+    % for side in ["top", "right", "bottom", "left"]:
+        ${predefined_type(side, "LengthOrPercentageOrAuto",
+                          "computed::LengthOrPercentageOrAuto::Auto")}
+    % endfor
 
-    ${switch_to_style_struct("Box")}
+    ${logical_longhands("inset-%s", "%s")}
 
-    <%self:longhand name="content">
-        use cssparser::Token;
-        use std::ascii::AsciiExt;
-        use values::computed::ComputedValueAsSpecified;
+    // CSS 2.1, Section 9 - Visual formatting model
 
-        use super::list_style_type;
+    ${new_style_struct("Box", is_inherited=False, gecko_name="nsStyleDisplay",
+                       additional_methods=[Method("clone_display",
+                                                  "longhands::display::computed_value::T"),
+                                           Method("clone_position",
+                                                  "longhands::position::computed_value::T"),
+                                           Method("clone_float",
+                                                  "longhands::float::computed_value::T"),
+                                           Method("clone_z_index",
+                                                  "longhands::z_index::computed_value::T"),
+                                           Method("clone_width",
+                                                  "longhands::width::computed_value::T"),
+                                           Method("clone_height",
+                                                  "longhands::height::computed_value::T"),
+                                           Method("clone_content",
+                                                  "longhands::content::computed_value::T"),
+                                           Method("content_reads_attributes", "bool"),
+                                           Method("clone_will_change",
+                                                  "longhands::will_change::computed_value::T"),
+                                           Method("clone_contain",
+                                                  "longhands::contain::computed_value::T"),
+                                           Method("is_floated", "bool"),
+                                           Method("overflow_x_is_visible", "bool"),
+                                           Method("overflow_y_is_visible", "bool")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in SCROLL_FRAME_LONGHANDS + BOX_STYLE_STRUCT_LONGHANDS])}
 
+    // TODO(SimonSapin): don't parse `inline-table`, since we don't support it
+    <%self:longhand name="display" custom_cascade="True">
+        <%
+            values = """inline block inline-block
+                table inline-table table-row-group table-header-group table-footer-group
+                table-row table-column-group table-column table-cell table-caption
+                list-item flex
+                none inline-flex contents
+            """.split()
+            experimental_values = set("flex inline-flex contents".split())
+        %>
         pub use self::computed_value::T as SpecifiedValue;
-        pub use self::computed_value::ContentItem;
-
-        impl ComputedValueAsSpecified for SpecifiedValue {}
+        use values::computed::{Context, ComputedValueAsSpecified};
+        use properties::style_struct_traits::TInheritedText;
 
         pub mod computed_value {
-            use super::super::list_style_type;
-
-            use cssparser::{self, ToCss};
-            use std::fmt;
-
-            #[derive(Debug, PartialEq, Eq, Clone, HeapSizeOf)]
-            pub enum ContentItem {
-                /// Literal string content.
-                String(String),
-                /// `counter(name, style)`.
-                Counter(String, list_style_type::computed_value::T),
-                /// `counters(name, separator, style)`.
-                Counters(String, String, list_style_type::computed_value::T),
-                /// `open-quote`.
-                OpenQuote,
-                /// `close-quote`.
-                CloseQuote,
-                /// `no-open-quote`.
-                NoOpenQuote,
-                /// `no-close-quote`.
-                NoCloseQuote,
+            #[allow(non_camel_case_types)]
+            #[derive(Clone, Eq, PartialEq, Copy, Hash, RustcEncodable, Debug, HeapSizeOf)]
+            #[derive(Deserialize, Serialize)]
+            pub enum T {
+                % for value in values:
+                    ${to_rust_ident(value)},
+                % endfor
             }
 
-            impl ToCss for ContentItem {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            impl ::cssparser::ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> ::std::fmt::Result
+                where W: ::std::fmt::Write {
                     match *self {
-                        ContentItem::String(ref s) => {
-                            cssparser::serialize_string(&**s, dest)
-                        }
-                        ContentItem::Counter(ref s, ref list_style_type) => {
-                            try!(dest.write_str("counter("));
-                            try!(cssparser::serialize_identifier(&**s, dest));
-                            try!(dest.write_str(", "));
-                            try!(list_style_type.to_css(dest));
-                            dest.write_str(")")
-                        }
-                        ContentItem::Counters(ref s, ref separator, ref list_style_type) => {
-                            try!(dest.write_str("counter("));
-                            try!(cssparser::serialize_identifier(&**s, dest));
-                            try!(dest.write_str(", "));
-                            try!(cssparser::serialize_string(&**separator, dest));
-                            try!(dest.write_str(", "));
-                            try!(list_style_type.to_css(dest));
-                            dest.write_str(")")
-                        }
-                        ContentItem::OpenQuote => dest.write_str("open-quote"),
-                        ContentItem::CloseQuote => dest.write_str("close-quote"),
-                        ContentItem::NoOpenQuote => dest.write_str("no-open-quote"),
-                        ContentItem::NoCloseQuote => dest.write_str("no-close-quote"),
+                        % for value in values:
+                            T::${to_rust_ident(value)} => dest.write_str("${value}"),
+                        % endfor
                     }
                 }
             }
 
-            #[allow(non_camel_case_types)]
-            #[derive(Debug, PartialEq, Eq, Clone, HeapSizeOf)]
-            pub enum T {
-                normal,
-                none,
-                Content(Vec<ContentItem>),
-            }
-
-            impl ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    match *self {
-                        T::normal => dest.write_str("normal"),
-                        T::none => dest.write_str("none"),
-                        T::Content(ref content) => {
-                            let mut iter = content.iter();
-                            try!(iter.next().unwrap().to_css(dest));
-                            for c in iter {
-                                try!(dest.write_str(" "));
-                                try!(c.to_css(dest));
-                            }
-                            Ok(())
-                        }
+            impl T {
+                /// The display a box that has to be block-level, like a float or the root's,
+                /// computes to, from the table in CSS Display § 2.7.
+                pub fn blockified(self, is_root_element: bool) -> T {
+                    match self {
+                        T::inline_table => T::table,
+                        T::inline_flex => T::flex,
+                        T::inline | T::inline_block |
+                        T::table_row_group | T::table_column |
+                        T::table_column_group | T::table_header_group |
+                        T::table_footer_group | T::table_row | T::table_cell |
+                        T::table_caption => T::block,
+                        // Only the root has to have a box.
+                        T::contents if is_root_element => T::block,
+                        other => other,
                     }
                 }
             }
         }
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T  {
-            computed_value::T::normal
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::${to_rust_ident(values[0])}
+        }
+        pub fn parse(_context: &ParserContext, input: &mut Parser)
+                     -> Result<SpecifiedValue, ()> {
+            match_ignore_ascii_case! { try!(input.expect_ident()),
+                % for value in values:
+                    "${value}" => {
+                        % if value in experimental_values:
+                            if !::util::prefs::get_pref("layout.${value}.enabled")
+                                .as_boolean().unwrap_or(false) {
+                                return Err(())
+                            }
+                        % endif
+                        Ok(computed_value::T::${to_rust_ident(value)})
+                    },
+                % endfor
+                _ => Err(())
+            }
         }
 
-        pub fn counter_name_is_illegal(name: &str) -> bool {
-            name.eq_ignore_ascii_case("none") || name.eq_ignore_ascii_case("inherit") ||
-                name.eq_ignore_ascii_case("initial")
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        fn cascade_property_custom<C: ComputedValues>(
+                                   _declaration: &PropertyDeclaration,
+                                   _inherited_style: &C,
+                                   context: &mut computed::Context<C>,
+                                   _seen: &mut PropertyBitField,
+                                   _cacheable: &mut bool,
+                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            longhands::_servo_display_for_hypothetical_box::derive_from_display(context);
+            longhands::_servo_text_decorations_in_effect::derive_from_display(context);
         }
+    </%self:longhand>
 
-        // normal | none | [ <string> | <counter> | open-quote | close-quote | no-open-quote |
-        // no-close-quote ]+
-        // TODO: <uri>, attr(<identifier>)
-        pub fn parse(context: &ParserContext, input: &mut Parser)
-                     -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
-                return Ok(SpecifiedValue::normal)
-            }
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                return Ok(SpecifiedValue::none)
-            }
-            let mut content = vec![];
-            loop {
-                match input.next() {
-                    Ok(Token::QuotedString(value)) => {
-                        content.push(ContentItem::String(value.into_owned()))
-                    }
-                    Ok(Token::Function(name)) => {
-                        content.push(try!(match_ignore_ascii_case! { name,
-                            "counter" => input.parse_nested_block(|input| {
-                                let name = try!(input.expect_ident()).into_owned();
-                                let style = input.try(|input| {
-                                    try!(input.expect_comma());
-                                    list_style_type::parse(context, input)
-                                }).unwrap_or(list_style_type::computed_value::T::decimal);
-                                Ok(ContentItem::Counter(name, style))
-                            }),
-                            "counters" => input.parse_nested_block(|input| {
-                                let name = try!(input.expect_ident()).into_owned();
-                                try!(input.expect_comma());
-                                let separator = try!(input.expect_string()).into_owned();
-                                let style = input.try(|input| {
-                                    try!(input.expect_comma());
-                                    list_style_type::parse(context, input)
-                                }).unwrap_or(list_style_type::computed_value::T::decimal);
-                                Ok(ContentItem::Counters(name, separator, style))
-                            }),
-                            _ => return Err(())
-                        }));
-                    }
-                    Ok(Token::Ident(ident)) => {
-                        match_ignore_ascii_case! { ident,
-                            "open-quote" => content.push(ContentItem::OpenQuote),
-                            "close-quote" => content.push(ContentItem::CloseQuote),
-                            "no-open-quote" => content.push(ContentItem::NoOpenQuote),
-                            "no-close-quote" => content.push(ContentItem::NoCloseQuote),
-                            _ => return Err(())
-                        }
-                    }
-                    Err(_) => break,
-                    _ => return Err(())
-                }
-            }
-            if !content.is_empty() {
-                Ok(SpecifiedValue::Content(content))
-            } else {
-                Err(())
-            }
-        }
-    </%self:longhand>
-
-    ${new_style_struct("List", is_inherited=True, gecko_name="nsStyleList")}
-
-    ${single_keyword("list-style-position", "outside inside")}
+    ${single_keyword("position", "static absolute relative fixed")}
 
-    // TODO(pcwalton): Implement the full set of counter styles per CSS-COUNTER-STYLES [1] 6.1:
-    //
-    //     decimal-leading-zero, armenian, upper-armenian, lower-armenian, georgian, lower-roman,
-    //     upper-roman
-    //
-    // [1]: http://dev.w3.org/csswg/css-counter-styles/
-    ${single_keyword("list-style-type", """
-        disc none circle square decimal arabic-indic bengali cambodian cjk-decimal devanagari
-        gujarati gurmukhi kannada khmer lao malayalam mongolian myanmar oriya persian telugu thai
-        tibetan lower-alpha upper-alpha cjk-earthly-branch cjk-heavenly-stem lower-greek hiragana
-        hiragana-iroha katakana katakana-iroha disclosure-open disclosure-closed
-    """)}
+    // float and clear take the logical keywords of CSS Logical Properties § 2.1 too, which
+    // compute to the physical side they're on in the element's direction, so that layout only
+    // ever sees physical sides. They're cascaded after direction for it.
+    % for (name, values) in [("float", "none left right"), ("clear", "none left right both")]:
+    <%self:longhand name="${name}" custom_cascade="True">
+        use properties::style_struct_traits::TInheritedBox;
 
-    <%self:longhand name="list-style-image">
-        use cssparser::{ToCss, Token};
-        use std::fmt;
-        use url::Url;
-        use values::LocalToCss;
+        pub mod computed_value {
+            define_css_keyword_enum! { T:
+                % for value in values.split():
+                    "${value}" => ${to_rust_ident(value)},
+                % endfor
+            }
+        }
 
-        #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
-        pub enum SpecifiedValue {
-            None,
-            Url(Url),
+        define_css_keyword_enum! { SpecifiedValue:
+            % for value in values.split() + ["inline-start", "inline-end"]:
+                "${value}" => ${to_rust_ident(value)},
+            % endfor
         }
 
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match *self {
-                    SpecifiedValue::None => dest.write_str("none"),
-                    SpecifiedValue::Url(ref url) => url.to_css(dest),
-                }
+        impl SpecifiedValue {
+            fn is_logical(&self) -> bool {
+                matches!(*self, SpecifiedValue::inline_start | SpecifiedValue::inline_end)
             }
         }
 
-        pub mod computed_value {
-            use cssparser::{ToCss, Token};
-            use std::fmt;
-            use url::Url;
-            use values::LocalToCss;
-
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Option<Url>);
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::none
+        }
 
-            impl ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    match self.0 {
-                        None => dest.write_str("none"),
-                        Some(ref url) => url.to_css(dest),
-                    }
-                }
-            }
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            SpecifiedValue::parse(input)
         }
 
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
             #[inline]
-            fn to_computed_value<Cx: TContext>(&self, _context: &Cx) -> computed_value::T {
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                use computed_values::direction;
+                let ltr = context.style().get_inheritedbox().clone_direction() == direction::T::ltr;
                 match *self {
-                    SpecifiedValue::None => computed_value::T(None),
-                    SpecifiedValue::Url(ref url) => computed_value::T(Some(url.clone())),
+                    % for value in values.split():
+                    SpecifiedValue::${to_rust_ident(value)} => computed_value::T::${to_rust_ident(value)},
+                    % endfor
+                    SpecifiedValue::inline_start if ltr => computed_value::T::left,
+                    SpecifiedValue::inline_end if ltr => computed_value::T::right,
+                    SpecifiedValue::inline_start => computed_value::T::right,
+                    SpecifiedValue::inline_end => computed_value::T::left,
                 }
             }
         }
 
-        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                Ok(SpecifiedValue::None)
-            } else {
-                Ok(SpecifiedValue::Url(context.parse_url(&*try!(input.expect_url()))))
+        fn cascade_property_custom<C: ComputedValues>(
+                                   declaration: &PropertyDeclaration,
+                                   _inherited_style: &C,
+                                   _context: &mut computed::Context<C>,
+                                   _seen: &mut PropertyBitField,
+                                   cacheable: &mut bool,
+                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            // The side a logical keyword is on depends on the direction, which may be the
+            // parent's, so the style can't be reused for another parent. Nor can one that
+            // takes the parent's value, or a value from variables that may be logical.
+            match *declaration {
+                PropertyDeclaration::${to_camel_case(name)}(DeclaredValue::Value(ref value))
+                    if !value.is_logical() => {}
+                PropertyDeclaration::${to_camel_case(name)}(DeclaredValue::Initial) => {}
+                _ => *cacheable = false,
             }
         }
+    </%self:longhand>
+    % endfor
+
+    <%self:longhand name="-servo-display-for-hypothetical-box" derived_from="display">
+        pub use super::display::{SpecifiedValue, get_initial_value};
+        pub use super::display::{parse};
+
+        pub mod computed_value {
+            pub type T = super::SpecifiedValue;
+        }
+
         #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(None)
+        pub fn derive_from_display<Cx: TContext>(context: &mut Cx) {
+            let d = context.style().get_box().clone_display();
+            context.mutate_style().mutate_box().set__servo_display_for_hypothetical_box(d);
         }
+
     </%self:longhand>
 
-    <%self:longhand name="quotes">
-        use std::borrow::Cow;
-        use std::fmt;
+    <%self:longhand name="z-index">
         use values::computed::ComputedValueAsSpecified;
 
-        use cssparser::{ToCss, Token};
-
-        pub use self::computed_value::T as SpecifiedValue;
-
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+        pub type SpecifiedValue = computed_value::T;
         pub mod computed_value {
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Vec<(String,String)>);
-        }
+            use cssparser::ToCss;
+            use std::fmt;
 
-        impl ComputedValueAsSpecified for SpecifiedValue {}
+            #[derive(PartialEq, Clone, Eq, Copy, Debug, HeapSizeOf)]
+            pub enum T {
+                Auto,
+                Number(i32),
+            }
 
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                let mut first = true;
-                for pair in &self.0 {
-                    if !first {
-                        try!(dest.write_str(" "));
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match *self {
+                        T::Auto => dest.write_str("auto"),
+                        T::Number(number) => write!(dest, "{}", number),
                     }
-                    first = false;
-                    try!(Token::QuotedString(Cow::from(&*pair.0)).to_css(dest));
-                    try!(dest.write_str(" "));
-                    try!(Token::QuotedString(Cow::from(&*pair.1)).to_css(dest));
                 }
-                Ok(())
             }
-        }
 
+            impl T {
+                pub fn number_or_zero(self) -> i32 {
+                    match self {
+                        T::Auto => 0,
+                        T::Number(value) => value,
+                    }
+                }
+            }
+        }
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(vec![
-                ("\u{201c}".to_owned(), "\u{201d}".to_owned()),
-                ("\u{2018}".to_owned(), "\u{2019}".to_owned()),
-            ])
+            computed_value::T::Auto
         }
-
-        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                return Ok(SpecifiedValue(Vec::new()))
-            }
-
-            let mut quotes = Vec::new();
-            loop {
-                let first = match input.next() {
-                    Ok(Token::QuotedString(value)) => value.into_owned(),
-                    Ok(_) => return Err(()),
-                    Err(()) => break,
-                };
-                let second = match input.next() {
-                    Ok(Token::QuotedString(value)) => value.into_owned(),
-                    _ => return Err(()),
-                };
-                quotes.push((first, second))
-            }
-            if !quotes.is_empty() {
-                Ok(SpecifiedValue(quotes))
+        fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
+                Ok(computed_value::T::Auto)
             } else {
-                Err(())
+                specified::parse_integer(input).map(computed_value::T::Number)
             }
         }
     </%self:longhand>
 
-    ${new_style_struct("Counters", is_inherited=False)}
+    ${new_style_struct("InheritedBox", is_inherited=True,
+                       additional_methods=[Method("clone_direction",
+                                                  "longhands::direction::computed_value::T"),
+                                           Method("clone_writing_mode",
+                                                  "longhands::writing_mode::computed_value::T"),
+                                           Method("clone_text_orientation",
+                                                  "longhands::text_orientation::computed_value::T"),
+                                           Method("clone_visibility",
+                                                  "longhands::visibility::computed_value::T")])}
 
-    <%self:longhand name="counter-increment">
-        use std::fmt;
-        use super::content;
-        use values::computed::ComputedValueAsSpecified;
+    ${single_keyword("direction", "ltr rtl")}
 
-        use cssparser::{ToCss, Token, serialize_identifier};
-        use std::borrow::{Cow, ToOwned};
+    // CSS 2.1, Section 10 - Visual formatting model details
 
-        pub use self::computed_value::T as SpecifiedValue;
+    ${switch_to_style_struct("Box")}
 
-        pub mod computed_value {
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Vec<(String,i32)>);
-        }
+    // The sizes also take the -moz- intrinsic size keywords, which Servo's layout takes as
+    // their initial values.
+    ${predefined_type("width", "MozLength",
+                      "computed::MozLength::LengthOrPercentageOrAuto(computed::LengthOrPercentageOrAuto::Auto)",
+                      "parse_non_negative")}
 
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(Vec::new())
-        }
+    ${predefined_type("height", "MozLength",
+                      "computed::MozLength::LengthOrPercentageOrAuto(computed::LengthOrPercentageOrAuto::Auto)",
+                      "parse_non_negative")}
 
-        impl ComputedValueAsSpecified for SpecifiedValue {}
+    ${predefined_type("min-width", "MinLength",
+                      "computed::MinLength::LengthOrPercentage(computed::LengthOrPercentage::Length(Au(0)))",
+                      "parse_non_negative")}
+    ${predefined_type("max-width", "MaxLength",
+                      "computed::MaxLength::LengthOrPercentageOrNone(computed::LengthOrPercentageOrNone::None)",
+                      "parse_non_negative")}
 
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                let mut first = true;
-                for pair in &self.0 {
-                    if !first {
-                        try!(dest.write_str(" "));
-                    }
-                    first = false;
-                    try!(serialize_identifier(&pair.0, dest));
-                    try!(write!(dest, " {}", pair.1));
-                }
-                Ok(())
-            }
-        }
+    ${predefined_type("min-height", "MinLength",
+                      "computed::MinLength::LengthOrPercentage(computed::LengthOrPercentage::Length(Au(0)))",
+                      "parse_non_negative")}
+    ${predefined_type("max-height", "MaxLength",
+                      "computed::MaxLength::LengthOrPercentageOrNone(computed::LengthOrPercentageOrNone::None)",
+                      "parse_non_negative")}
 
-        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            parse_common(1, input)
+    ${switch_to_style_struct("InheritedBox")}
+
+    <%self:longhand name="line-height">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::AuExtensionMethods;
+        use values::CSSFloat;
+
+        #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Normal,
+            Number(CSSFloat),
+            LengthOrPercentage(specified::LengthOrPercentage),
         }
 
-        pub fn parse_common(default_value: i32, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                return Ok(SpecifiedValue(Vec::new()))
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Normal => dest.write_str("normal"),
+                    SpecifiedValue::LengthOrPercentage(value) => value.to_css(dest),
+                    SpecifiedValue::Number(number) => write!(dest, "{}", number),
+                }
             }
-
-            let mut counters = Vec::new();
-            loop {
-                let counter_name = match input.next() {
-                    Ok(Token::Ident(ident)) => (*ident).to_owned(),
-                    Ok(_) => return Err(()),
-                    Err(_) => break,
-                };
-                if content::counter_name_is_illegal(&counter_name) {
-                    return Err(())
+        }
+        /// normal | <number> | <length> | <percentage>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            use cssparser::Token;
+            use std::ascii::AsciiExt;
+            input.try(specified::LengthOrPercentage::parse_non_negative)
+            .map(SpecifiedValue::LengthOrPercentage)
+            .or_else(|()| {
+                match try!(input.next()) {
+                    Token::Number(ref value) if value.value >= 0. => {
+                        Ok(SpecifiedValue::Number(value.value))
+                    }
+                    Token::Ident(ref value) if value.eq_ignore_ascii_case("normal") => {
+                        Ok(SpecifiedValue::Normal)
+                    }
+                    _ => Err(()),
                 }
-                let counter_delta =
-                    input.try(|input| specified::parse_integer(input)).unwrap_or(default_value);
-                counters.push((counter_name, counter_delta))
+            })
+        }
+        pub mod computed_value {
+            use app_units::Au;
+            use std::fmt;
+            use values::CSSFloat;
+            #[derive(PartialEq, Copy, Clone, HeapSizeOf, Debug)]
+            pub enum T {
+                Normal,
+                Length(Au),
+                Number(CSSFloat),
             }
-
-            if !counters.is_empty() {
-                Ok(SpecifiedValue(counters))
-            } else {
-                Err(())
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Normal => dest.write_str("normal"),
+                    computed_value::T::Length(length) => length.to_css(dest),
+                    computed_value::T::Number(number) => write!(dest, "{}", number),
+                }
             }
         }
-    </%self:longhand>
+         #[inline]
+        pub fn get_initial_value() -> computed_value::T { computed_value::T::Normal }
 
-    <%self:longhand name="counter-reset">
-        pub use super::counter_increment::{SpecifiedValue, computed_value, get_initial_value};
-        use super::counter_increment::{parse_common};
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
 
-        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            parse_common(0, input)
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::Normal => computed_value::T::Normal,
+                    SpecifiedValue::Number(value) => computed_value::T::Number(value),
+                    SpecifiedValue::LengthOrPercentage(value) => {
+                        match value {
+                            specified::LengthOrPercentage::Length(value) =>
+                                computed_value::T::Length(value.to_computed_value(context)),
+                            specified::LengthOrPercentage::Percentage(specified::Percentage(value)) => {
+                                let fr = specified::Length::FontRelative(specified::FontRelativeLength::Em(value));
+                                computed_value::T::Length(fr.to_computed_value(context))
+                            },
+                            specified::LengthOrPercentage::Calc(calc) => {
+                                let calc = calc.to_computed_value(context);
+                                let fr = specified::FontRelativeLength::Em(calc.percentage());
+                                let fr = specified::Length::FontRelative(fr);
+                                computed_value::T::Length(calc.length() + fr.to_computed_value(context))
+                            }
+                        }
+                    }
+                }
+            }
         }
     </%self:longhand>
 
-    // CSS 2.1, Section 13 - Paged media
-
-    // CSS 2.1, Section 14 - Colors and Backgrounds
-
-    ${new_style_struct("Background", is_inherited=False, gecko_name="nsStyleBackground")}
-    ${predefined_type(
-        "background-color", "CSSColor",
-        "::cssparser::Color::RGBA(::cssparser::RGBA { red: 0., green: 0., blue: 0., alpha: 0. }) /* transparent */")}
+    ${switch_to_style_struct("Box")}
 
-    <%self:longhand name="background-image">
+    <%self:longhand name="vertical-align">
         use cssparser::ToCss;
         use std::fmt;
-        use values::specified::Image;
-        use values::LocalToCss;
-
-        pub mod computed_value {
-            use values::computed;
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Option<computed::Image>);
-        }
 
-        impl ToCss for computed_value::T {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match self.0 {
-                    None => dest.write_str("none"),
-                    Some(computed::Image::Url(ref url)) => url.to_css(dest),
-                    Some(computed::Image::LinearGradient(ref gradient)) =>
-                        gradient.to_css(dest)
-                }
-            }
+        <% vertical_align_keywords = (
+            "baseline sub super top text-top middle bottom text-bottom".split()) %>
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            % for keyword in vertical_align_keywords:
+                ${to_rust_ident(keyword)},
+            % endfor
+            LengthOrPercentage(specified::LengthOrPercentage),
         }
 
-        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-        pub struct SpecifiedValue(pub Option<Image>);
-
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    SpecifiedValue(Some(ref image)) => image.to_css(dest),
-                    SpecifiedValue(None) => dest.write_str("none"),
+                    % for keyword in vertical_align_keywords:
+                        SpecifiedValue::${to_rust_ident(keyword)} => dest.write_str("${keyword}"),
+                    % endfor
+                    SpecifiedValue::LengthOrPercentage(value) => value.to_css(dest),
                 }
             }
         }
-
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(None)
+        /// baseline | sub | super | top | text-top | middle | bottom | text-bottom
+        /// | <percentage> | <length>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            input.try(specified::LengthOrPercentage::parse)
+            .map(SpecifiedValue::LengthOrPercentage)
+            .or_else(|()| {
+                match_ignore_ascii_case! { try!(input.expect_ident()),
+                    % for keyword in vertical_align_keywords:
+                        "${keyword}" => Ok(SpecifiedValue::${to_rust_ident(keyword)}),
+                    % endfor
+                    _ => Err(())
+                }
+            })
         }
-        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                Ok(SpecifiedValue(None))
-            } else {
-                Ok(SpecifiedValue(Some(try!(Image::parse(context, input)))))
+        pub mod computed_value {
+            use app_units::Au;
+            use std::fmt;
+            use values::AuExtensionMethods;
+            use values::{CSSFloat, computed};
+            #[allow(non_camel_case_types)]
+            #[derive(PartialEq, Copy, Clone, HeapSizeOf, Debug)]
+            pub enum T {
+                % for keyword in vertical_align_keywords:
+                    ${to_rust_ident(keyword)},
+                % endfor
+                LengthOrPercentage(computed::LengthOrPercentage),
+            }
+            impl ::cssparser::ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match *self {
+                        % for keyword in vertical_align_keywords:
+                            T::${to_rust_ident(keyword)} => dest.write_str("${keyword}"),
+                        % endfor
+                        T::LengthOrPercentage(value) => value.to_css(dest),
+                    }
+                }
             }
         }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T { computed_value::T::baseline }
+
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
                 match *self {
-                    SpecifiedValue(None) => computed_value::T(None),
-                    SpecifiedValue(Some(ref image)) =>
-                        computed_value::T(Some(image.to_computed_value(context))),
+                    % for keyword in vertical_align_keywords:
+                        SpecifiedValue::${to_rust_ident(keyword)} => {
+                            computed_value::T::${to_rust_ident(keyword)}
+                        }
+                    % endfor
+                    SpecifiedValue::LengthOrPercentage(value) =>
+                        computed_value::T::LengthOrPercentage(value.to_computed_value(context)),
                 }
             }
         }
     </%self:longhand>
 
-    <%self:longhand name="background-position">
-            use cssparser::ToCss;
-            use std::fmt;
-            use values::AuExtensionMethods;
 
-            pub mod computed_value {
-                use values::computed::LengthOrPercentage;
+    // CSS 2.1, Section 11 - Visual effects
 
-                #[derive(PartialEq, Copy, Clone, Debug, HeapSizeOf)]
-                pub struct T {
-                    pub horizontal: LengthOrPercentage,
-                    pub vertical: LengthOrPercentage,
-                }
-            }
+    // Non-standard, see https://developer.mozilla.org/en-US/docs/Web/CSS/overflow-clip-box#Specifications
+    ${single_keyword("-servo-overflow-clip-box", "padding-box content-box", internal=True)}
 
-            #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
-            pub struct SpecifiedValue {
-                pub horizontal: specified::LengthOrPercentage,
-                pub vertical: specified::LengthOrPercentage,
-            }
+    // FIXME(pcwalton, #2742): Implement scrolling for `scroll` and `auto`.
+    ${single_keyword("overflow-x", "visible hidden scroll auto")}
 
-            impl ToCss for SpecifiedValue {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    try!(self.horizontal.to_css(dest));
-                    try!(dest.write_str(" "));
-                    try!(self.vertical.to_css(dest));
-                    Ok(())
-                }
-            }
+    // FIXME(pcwalton, #2742): Implement scrolling for `scroll` and `auto`.
+    <%self:longhand name="overflow-y">
+        use super::overflow_x;
 
-            impl ToCss for computed_value::T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    try!(self.horizontal.to_css(dest));
-                    try!(dest.write_str(" "));
-                    try!(self.vertical.to_css(dest));
-                    Ok(())
-                }
-            }
+        use cssparser::ToCss;
+        use std::fmt;
 
-            impl SpecifiedValue {
-                fn new(first: specified::PositionComponent, second: specified::PositionComponent)
-                        -> Result<SpecifiedValue, ()> {
-                    let (horiz, vert) = match (category(first), category(second)) {
-                        // Don't allow two vertical keywords or two horizontal keywords.
-                        (PositionCategory::HorizontalKeyword, PositionCategory::HorizontalKeyword) |
-                        (PositionCategory::VerticalKeyword, PositionCategory::VerticalKeyword) => return Err(()),
+        pub use self::computed_value::T as SpecifiedValue;
 
-                        // Swap if both are keywords and vertical precedes horizontal.
-                        (PositionCategory::VerticalKeyword, PositionCategory::HorizontalKeyword) |
-                        (PositionCategory::VerticalKeyword, PositionCategory::OtherKeyword) |
-                        (PositionCategory::OtherKeyword, PositionCategory::HorizontalKeyword) => (second, first),
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                self.0.to_css(dest)
+            }
+        }
 
-                        // By default, horizontal is first.
-                        _ => (first, second),
-                    };
-                    Ok(SpecifiedValue {
-                        horizontal: horiz.to_length_or_percentage(),
-                        vertical: vert.to_length_or_percentage(),
-                    })
-                }
-            }
-
-            // Collapse `Position` into a few categories to simplify the above `match` expression.
-            enum PositionCategory {
-                HorizontalKeyword,
-                VerticalKeyword,
-                OtherKeyword,
-                LengthOrPercentage,
-            }
-            fn category(p: specified::PositionComponent) -> PositionCategory {
-                match p {
-                    specified::PositionComponent::Left |
-                    specified::PositionComponent::Right =>
-                        PositionCategory::HorizontalKeyword,
-                    specified::PositionComponent::Top |
-                    specified::PositionComponent::Bottom =>
-                        PositionCategory::VerticalKeyword,
-                    specified::PositionComponent::Center =>
-                        PositionCategory::OtherKeyword,
-                    specified::PositionComponent::LengthOrPercentage(_) =>
-                        PositionCategory::LengthOrPercentage,
-                }
-            }
-
-            impl ToComputedValue for SpecifiedValue {
-                type ComputedValue = computed_value::T;
+        pub mod computed_value {
+            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+            pub struct T(pub super::super::overflow_x::computed_value::T);
+        }
 
-                #[inline]
-                fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                    computed_value::T {
-                        horizontal: self.horizontal.to_computed_value(context),
-                        vertical: self.vertical.to_computed_value(context),
-                    }
-                }
-            }
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
 
             #[inline]
-            pub fn get_initial_value() -> computed_value::T {
-                computed_value::T {
-                    horizontal: computed::LengthOrPercentage::Percentage(0.0),
-                    vertical: computed::LengthOrPercentage::Percentage(0.0),
-                }
-            }
-
-            pub fn parse(_context: &ParserContext, input: &mut Parser)
-                         -> Result<SpecifiedValue, ()> {
-                let first = try!(specified::PositionComponent::parse(input));
-                let second = input.try(specified::PositionComponent::parse)
-                    .unwrap_or(specified::PositionComponent::Center);
-                SpecifiedValue::new(first, second)
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                computed_value::T(self.0.to_computed_value(context))
             }
-    </%self:longhand>
+        }
 
-    ${single_keyword("background-repeat", "repeat repeat-x repeat-y no-repeat")}
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(overflow_x::get_initial_value())
+        }
 
-    ${single_keyword("background-attachment", "scroll fixed")}
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            overflow_x::parse(context, input).map(SpecifiedValue)
+        }
+    </%self:longhand>
 
-    ${single_keyword("background-clip", "border-box padding-box content-box")}
+    ${single_keyword("resize", "none both horizontal vertical")}
 
-    ${single_keyword("background-origin", "padding-box border-box content-box")}
+    // https://drafts.csswg.org/css-scroll-snap-1/, as of the 2015 draft Gecko implements.
+    % for axis in ["x", "y"]:
+    ${single_keyword("scroll-snap-type-" + axis, "none mandatory proximity")}
 
-    <%self:longhand name="background-size">
-        use cssparser::{ToCss, Token};
-        use std::ascii::AsciiExt;
+    <%self:longhand name="scroll-snap-points-${axis}">
+        use cssparser::ToCss;
         use std::fmt;
 
         pub mod computed_value {
-            use values::computed::LengthOrPercentageOrAuto;
+            use values::computed::LengthOrPercentage;
 
-            #[derive(PartialEq, Clone, Debug, HeapSizeOf)]
-            pub struct ExplicitSize {
-                pub width: LengthOrPercentageOrAuto,
-                pub height: LengthOrPercentageOrAuto,
-            }
+            /// The distance between the snap points of `repeat()`, or `None` for `none`.
+            #[derive(Clone, Copy, Debug, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<LengthOrPercentage>);
+        }
 
-            #[derive(PartialEq, Clone, Debug, HeapSizeOf)]
-            pub enum T {
-                Explicit(ExplicitSize),
-                Cover,
-                Contain,
+        #[derive(Clone, Copy, Debug, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub Option<specified::LengthOrPercentage>);
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    None => dest.write_str("none"),
+                    Some(ref interval) => {
+                        try!(dest.write_str("repeat("));
+                        try!(interval.to_css(dest));
+                        dest.write_str(")")
+                    }
+                }
             }
         }
 
         impl ToCss for computed_value::T {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match *self {
-                    computed_value::T::Explicit(ref size) => size.to_css(dest),
-                    computed_value::T::Cover => dest.write_str("cover"),
-                    computed_value::T::Contain => dest.write_str("contain"),
+                match self.0 {
+                    None => dest.write_str("none"),
+                    Some(ref interval) => {
+                        try!(dest.write_str("repeat("));
+                        try!(interval.to_css(dest));
+                        dest.write_str(")")
+                    }
                 }
             }
         }
 
-        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-        pub struct SpecifiedExplicitSize {
-            pub width: specified::LengthOrPercentageOrAuto,
-            pub height: specified::LengthOrPercentageOrAuto,
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                computed_value::T(self.0.map(|interval| interval.to_computed_value(context)))
+            }
         }
 
-        impl ToCss for SpecifiedExplicitSize {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                try!(self.width.to_css(dest));
-                try!(dest.write_str(" "));
-                self.height.to_css(dest)
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+
+        // none | repeat(<length-percentage>)
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            use std::ascii::AsciiExt;
+
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue(None))
+            }
+            if !try!(input.expect_function()).eq_ignore_ascii_case("repeat") {
+                return Err(())
             }
+            input.parse_nested_block(specified::LengthOrPercentage::parse_non_negative)
+                 .map(|interval| SpecifiedValue(Some(interval)))
         }
+    </%self:longhand>
+    % endfor
 
-        impl ToCss for computed_value::ExplicitSize {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                try!(self.width.to_css(dest));
-                try!(dest.write_str(" "));
-                self.height.to_css(dest)
+    <%self:longhand name="scroll-snap-destination">
+        use app_units::Au;
+
+        pub use super::background_position::single_value::{SpecifiedValue, computed_value, parse};
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::LengthOrPercentage::Length(Au(0)),
+                vertical: computed::LengthOrPercentage::Length(Au(0)),
             }
         }
+    </%self:longhand>
 
+    <%self:longhand name="scroll-snap-coordinate">
+        use cssparser::ToCss;
+        use std::fmt;
+        use super::background_position::single_value as background_position;
 
-        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-        pub enum SpecifiedValue {
-            Explicit(SpecifiedExplicitSize),
-            Cover,
-            Contain,
+        pub mod computed_value {
+            use super::super::background_position::single_value as background_position;
+
+            /// The positions of the element's snap points, empty for `none`.
+            #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
+            pub struct T(pub Vec<background_position::computed_value::T>);
         }
 
+        #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub Vec<background_position::SpecifiedValue>);
+
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match *self {
-                    SpecifiedValue::Explicit(ref size) => size.to_css(dest),
-                    SpecifiedValue::Cover => dest.write_str("cover"),
-                    SpecifiedValue::Contain => dest.write_str("contain"),
+                if self.0.is_empty() {
+                    return dest.write_str("none")
+                }
+                for (index, position) in self.0.iter().enumerate() {
+                    if index != 0 {
+                        try!(dest.write_str(", "));
+                    }
+                    try!(position.to_css(dest));
+                }
+                Ok(())
+            }
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                if self.0.is_empty() {
+                    return dest.write_str("none")
+                }
+                for (index, position) in self.0.iter().enumerate() {
+                    if index != 0 {
+                        try!(dest.write_str(", "));
+                    }
+                    try!(position.to_css(dest));
                 }
+                Ok(())
             }
         }
 
@@ -1564,306 +1779,2114 @@ pub mod longhands {
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                match *self {
-                    SpecifiedValue::Explicit(ref size) => {
-                        computed_value::T::Explicit(computed_value::ExplicitSize {
-                            width: size.width.to_computed_value(context),
-                            height: size.height.to_computed_value(context),
-                        })
-                    }
-                    SpecifiedValue::Cover => computed_value::T::Cover,
-                    SpecifiedValue::Contain => computed_value::T::Contain,
-                }
+                computed_value::T(self.0.iter().map(|position| position.to_computed_value(context)).collect())
             }
         }
 
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::Explicit(computed_value::ExplicitSize {
-                width: computed::LengthOrPercentageOrAuto::Auto,
-                height: computed::LengthOrPercentageOrAuto::Auto,
-            })
+            computed_value::T(vec![])
         }
 
-        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            let width;
-            if let Ok(value) = input.try(|input| {
-                match input.next() {
-                    Err(_) => Err(()),
-                    Ok(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("cover") => {
-                        Ok(SpecifiedValue::Cover)
-                    }
-                    Ok(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("contain") => {
-                        Ok(SpecifiedValue::Contain)
-                    }
-                    Ok(_) => Err(()),
-                }
-            }) {
-                return Ok(value)
-            } else {
-                width = try!(specified::LengthOrPercentageOrAuto::parse(input))
-            }
-
-            let height;
-            if let Ok(value) = input.try(|input| {
-                match input.next() {
-                    Err(_) => Ok(specified::LengthOrPercentageOrAuto::Auto),
-                    Ok(_) => Err(()),
-                }
-            }) {
-                height = value
-            } else {
-                height = try!(specified::LengthOrPercentageOrAuto::parse(input));
+        // none | <position>#
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue(vec![]))
             }
-
-            Ok(SpecifiedValue::Explicit(SpecifiedExplicitSize {
-                width: width,
-                height: height,
-            }))
+            input.parse_comma_separated(|input| background_position::parse(context, input)).map(SpecifiedValue)
         }
     </%self:longhand>
 
-    ${new_style_struct("Color", is_inherited=True, gecko_name="nsStyleColor",
-                       additional_methods=[Method("clone_color",
-                                                  "longhands::color::computed_value::T")])}
-
-    <%self:raw_longhand name="color">
-        use cssparser::{Color, RGBA};
-        use values::specified::{CSSColor, CSSRGBA};
+    ${switch_to_style_struct("InheritedBox")}
 
-        impl ToComputedValue for SpecifiedValue {
-            type ComputedValue = computed_value::T;
+    // TODO: collapse. Well, do tables first.
+    ${single_keyword("visibility", "visible hidden")}
 
-            #[inline]
-            fn to_computed_value<Cx: TContext>(&self, _context: &Cx) -> computed_value::T {
-                self.parsed
-            }
-        }
+    // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
 
-        pub type SpecifiedValue = CSSRGBA;
-        pub mod computed_value {
-            use cssparser;
-            pub type T = cssparser::RGBA;
-        }
-        #[inline] pub fn get_initial_value() -> computed_value::T {
-            RGBA { red: 0., green: 0., blue: 0., alpha: 1. }  /* black */
-        }
-        pub fn parse_specified(_context: &ParserContext, input: &mut Parser)
-                               -> Result<DeclaredValue<SpecifiedValue>, ()> {
-            let value = try!(CSSColor::parse(input));
-            let rgba = match value.parsed {
-                Color::RGBA(rgba) => rgba,
-                Color::CurrentColor => return Ok(DeclaredValue::Inherit)
-            };
-            Ok(DeclaredValue::Value(CSSRGBA {
-                parsed: rgba,
-                authored: value.authored,
-            }))
-        }
-    </%self:raw_longhand>
+    ${switch_to_style_struct("Box")}
 
-    // CSS 2.1, Section 15 - Fonts
+    <%self:longhand name="content">
+        use cssparser::Token;
+        use std::ascii::AsciiExt;
+        use values::computed::ComputedValueAsSpecified;
 
-    ${new_style_struct("Font", is_inherited=True, gecko_name="nsStyleFont",
-                       additional_methods=[Method("clone_font_size",
-                                                  "longhands::font_size::computed_value::T"),
-                                           Method("clone_font_weight",
-                                                  "longhands::font_weight::computed_value::T"),
-                                           Method("compute_font_hash", is_mut=True)])}
+        use super::list_style_type;
 
-    <%self:longhand name="font-family">
-        use self::computed_value::FontFamily;
-        use values::computed::ComputedValueAsSpecified;
         pub use self::computed_value::T as SpecifiedValue;
-
-        const SERIF: &'static str = "serif";
-        const SANS_SERIF: &'static str = "sans-serif";
-        const CURSIVE: &'static str = "cursive";
-        const FANTASY: &'static str = "fantasy";
-        const MONOSPACE: &'static str = "monospace";
+        pub use self::computed_value::ContentItem;
 
         impl ComputedValueAsSpecified for SpecifiedValue {}
+
         pub mod computed_value {
-            use cssparser::ToCss;
+            use super::super::list_style_type;
+
+            use cssparser::{self, ToCss};
             use std::fmt;
-            use string_cache::Atom;
+            use url::Url;
+            use values::LocalToCss;
 
-            #[derive(Debug, PartialEq, Eq, Clone, Hash, HeapSizeOf, Deserialize, Serialize)]
-            pub enum FontFamily {
-                FamilyName(Atom),
-                // Generic,
-                Serif,
-                SansSerif,
-                Cursive,
-                Fantasy,
-                Monospace,
+            #[derive(Debug, PartialEq, Eq, Clone, HeapSizeOf)]
+            pub enum ContentItem {
+                /// Literal string content.
+                String(String),
+                /// `attr(name)`, until the cascade replaces it with the value of the element's
+                /// attribute of that local name.
+                Attr(String),
+                /// An image, `url(...)`.
+                Url(Url),
+                /// `counter(name, style)`.
+                Counter(String, list_style_type::computed_value::T),
+                /// `counters(name, separator, style)`.
+                Counters(String, String, list_style_type::computed_value::T),
+                /// `open-quote`.
+                OpenQuote,
+                /// `close-quote`.
+                CloseQuote,
+                /// `no-open-quote`.
+                NoOpenQuote,
+                /// `no-close-quote`.
+                NoCloseQuote,
             }
-            impl FontFamily {
-                #[inline]
-                pub fn name(&self) -> &str {
+
+            impl ToCss for ContentItem {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                     match *self {
-                        FontFamily::FamilyName(ref name) => &*name,
-                        FontFamily::Serif => super::SERIF,
-                        FontFamily::SansSerif => super::SANS_SERIF,
-                        FontFamily::Cursive => super::CURSIVE,
-                        FontFamily::Fantasy => super::FANTASY,
-                        FontFamily::Monospace => super::MONOSPACE
+                        ContentItem::String(ref s) => {
+                            cssparser::serialize_string(&**s, dest)
+                        }
+                        ContentItem::Attr(ref name) => {
+                            try!(dest.write_str("attr("));
+                            try!(cssparser::serialize_identifier(&**name, dest));
+                            dest.write_str(")")
+                        }
+                        ContentItem::Url(ref url) => url.to_css(dest),
+                        ContentItem::Counter(ref s, ref list_style_type) => {
+                            try!(dest.write_str("counter("));
+                            try!(cssparser::serialize_identifier(&**s, dest));
+                            try!(dest.write_str(", "));
+                            try!(list_style_type.to_css(dest));
+                            dest.write_str(")")
+                        }
+                        ContentItem::Counters(ref s, ref separator, ref list_style_type) => {
+                            try!(dest.write_str("counters("));
+                            try!(cssparser::serialize_identifier(&**s, dest));
+                            try!(dest.write_str(", "));
+                            try!(cssparser::serialize_string(&**separator, dest));
+                            try!(dest.write_str(", "));
+                            try!(list_style_type.to_css(dest));
+                            dest.write_str(")")
+                        }
+                        ContentItem::OpenQuote => dest.write_str("open-quote"),
+                        ContentItem::CloseQuote => dest.write_str("close-quote"),
+                        ContentItem::NoOpenQuote => dest.write_str("no-open-quote"),
+                        ContentItem::NoCloseQuote => dest.write_str("no-close-quote"),
                     }
                 }
+            }
 
-                pub fn from_atom(input: Atom) -> FontFamily {
-                    let option = match_ignore_ascii_case! { &input,
-                        super::SERIF => Some(FontFamily::Serif),
-                        super::SANS_SERIF => Some(FontFamily::SansSerif),
-                        super::CURSIVE => Some(FontFamily::Cursive),
-                        super::FANTASY => Some(FontFamily::Fantasy),
-                        super::MONOSPACE => Some(FontFamily::Monospace),
-                        _ => None
-                    };
+            #[allow(non_camel_case_types)]
+            #[derive(Debug, PartialEq, Eq, Clone, HeapSizeOf)]
+            pub enum T {
+                normal,
+                none,
+                Content(Vec<ContentItem>),
+            }
 
-                    match option {
-                        Some(family) => family,
-                        None => FontFamily::FamilyName(input)
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match *self {
+                        T::normal => dest.write_str("normal"),
+                        T::none => dest.write_str("none"),
+                        T::Content(ref content) => {
+                            let mut iter = content.iter();
+                            try!(iter.next().unwrap().to_css(dest));
+                            for c in iter {
+                                try!(dest.write_str(" "));
+                                try!(c.to_css(dest));
+                            }
+                            Ok(())
+                        }
                     }
                 }
             }
-            impl ToCss for FontFamily {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    dest.write_str(self.name())
+
+            impl T {
+                /// The local names of the attributes `attr()` items read.
+                pub fn attribute_names(&self) -> Vec<&str> {
+                    match *self {
+                        T::Content(ref content) => content.iter().filter_map(|item| match *item {
+                            ContentItem::Attr(ref name) => Some(&**name),
+                            _ => None,
+                        }).collect(),
+                        _ => vec![],
+                    }
                 }
-            }
-            impl ToCss for T {
-                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                    let mut iter = self.0.iter();
-                    try!(iter.next().unwrap().to_css(dest));
-                    for family in iter {
-                        try!(dest.write_str(", "));
-                        try!(family.to_css(dest));
+
+                /// The value with each `attr()` item replaced by the string `attribute` gives
+                /// for its name, which is empty for attributes the element doesn't have.
+                pub fn with_attributes_resolved<F>(&self, mut attribute: F) -> T
+                    where F: FnMut(&str) -> Option<String> {
+                    match *self {
+                        T::Content(ref content) => T::Content(content.iter().map(|item| match *item {
+                            ContentItem::Attr(ref name) => {
+                                ContentItem::String(attribute(name).unwrap_or(String::new()))
+                            }
+                            ref item => item.clone(),
+                        }).collect()),
+                        ref other => other.clone(),
                     }
-                    Ok(())
                 }
             }
-            #[derive(Debug, Clone, PartialEq, Eq, Hash, HeapSizeOf)]
-            pub struct T(pub Vec<FontFamily>);
         }
-
         #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(vec![FontFamily::Serif])
+        pub fn get_initial_value() -> computed_value::T  {
+            computed_value::T::normal
         }
-        /// <family-name>#
-        /// <family-name> = <string> | [ <ident>+ ]
-        /// TODO: <generic-family>
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            input.parse_comma_separated(parse_one_family).map(SpecifiedValue)
+
+        pub fn counter_name_is_illegal(name: &str) -> bool {
+            name.eq_ignore_ascii_case("none") || name.eq_ignore_ascii_case("inherit") ||
+                name.eq_ignore_ascii_case("initial")
         }
-        pub fn parse_one_family(input: &mut Parser) -> Result<FontFamily, ()> {
-            if let Ok(value) = input.try(|input| input.expect_string()) {
-                return Ok(FontFamily::FamilyName(Atom::from(&*value)))
-            }
-            let first_ident = try!(input.expect_ident());
 
-            match_ignore_ascii_case! { first_ident,
-                SERIF => return Ok(FontFamily::Serif),
-                SANS_SERIF => return Ok(FontFamily::SansSerif),
-                CURSIVE => return Ok(FontFamily::Cursive),
-                FANTASY => return Ok(FontFamily::Fantasy),
-                MONOSPACE => return Ok(FontFamily::Monospace),
-                _ => {}
+        // normal | none | [ <string> | <uri> | <counter> | attr(<identifier>) | open-quote |
+        // close-quote | no-open-quote | no-close-quote ]+
+        pub fn parse(context: &ParserContext, input: &mut Parser)
+                     -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                return Ok(SpecifiedValue::normal)
             }
-            let mut value = first_ident.into_owned();
-            while let Ok(ident) = input.try(|input| input.expect_ident()) {
-                value.push_str(" ");
-                value.push_str(&ident);
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue::none)
+            }
+            let mut content = vec![];
+            loop {
+                match input.next() {
+                    Ok(Token::QuotedString(value)) => {
+                        content.push(ContentItem::String(value.into_owned()))
+                    }
+                    Ok(Token::Url(url)) => {
+                        content.push(ContentItem::Url(context.parse_url(&url)))
+                    }
+                    Ok(Token::Function(name)) => {
+                        content.push(try!(match_ignore_ascii_case! { name,
+                            "counter" => input.parse_nested_block(|input| {
+                                let name = try!(input.expect_ident()).into_owned();
+                                let style = input.try(|input| {
+                                    try!(input.expect_comma());
+                                    list_style_type::parse(context, input)
+                                }).unwrap_or(list_style_type::computed_value::T::decimal);
+                                Ok(ContentItem::Counter(name, style))
+                            }),
+                            "counters" => input.parse_nested_block(|input| {
+                                let name = try!(input.expect_ident()).into_owned();
+                                try!(input.expect_comma());
+                                let separator = try!(input.expect_string()).into_owned();
+                                let style = input.try(|input| {
+                                    try!(input.expect_comma());
+                                    list_style_type::parse(context, input)
+                                }).unwrap_or(list_style_type::computed_value::T::decimal);
+                                Ok(ContentItem::Counters(name, separator, style))
+                            }),
+                            "attr" => input.parse_nested_block(|input| {
+                                Ok(ContentItem::Attr(try!(input.expect_ident()).into_owned()))
+                            }),
+                            "url" => input.parse_nested_block(|input| {
+                                Ok(ContentItem::Url(context.parse_url(&try!(input.expect_string()))))
+                            }),
+                            _ => return Err(())
+                        }));
+                    }
+                    Ok(Token::Ident(ident)) => {
+                        match_ignore_ascii_case! { ident,
+                            "open-quote" => content.push(ContentItem::OpenQuote),
+                            "close-quote" => content.push(ContentItem::CloseQuote),
+                            "no-open-quote" => content.push(ContentItem::NoOpenQuote),
+                            "no-close-quote" => content.push(ContentItem::NoCloseQuote),
+                            _ => return Err(())
+                        }
+                    }
+                    Err(_) => break,
+                    _ => return Err(())
+                }
+            }
+            if !content.is_empty() {
+                Ok(SpecifiedValue::Content(content))
+            } else {
+                Err(())
             }
-            Ok(FontFamily::FamilyName(Atom::from(value)))
         }
     </%self:longhand>
 
+    ${new_style_struct("List", is_inherited=True, gecko_name="nsStyleList")}
 
-    ${single_keyword("font-style", "normal italic oblique")}
-    ${single_keyword("font-variant", "normal small-caps")}
+    ${single_keyword("list-style-position", "outside inside")}
 
-    <%self:longhand name="font-weight">
-        use cssparser::ToCss;
+    // TODO(pcwalton): Implement the full set of counter styles per CSS-COUNTER-STYLES [1] 6.1:
+    //
+    //     decimal-leading-zero, armenian, upper-armenian, lower-armenian, georgian, lower-roman,
+    //     upper-roman
+    //
+    // [1]: http://dev.w3.org/csswg/css-counter-styles/
+    ${single_keyword("list-style-type", """
+        disc none circle square decimal arabic-indic bengali cambodian cjk-decimal devanagari
+        gujarati gurmukhi kannada khmer lao malayalam mongolian myanmar oriya persian telugu thai
+        tibetan lower-alpha upper-alpha cjk-earthly-branch cjk-heavenly-stem lower-greek hiragana
+        hiragana-iroha katakana katakana-iroha disclosure-open disclosure-closed
+    """)}
+
+    <%self:longhand name="list-style-image">
+        use cssparser::{ToCss, Token};
         use std::fmt;
+        use url::Url;
+        use values::LocalToCss;
 
-        #[derive(Debug, Clone, PartialEq, Eq, Copy, HeapSizeOf)]
+        #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
         pub enum SpecifiedValue {
-            Bolder,
-            Lighter,
-            % for weight in range(100, 901, 100):
-                Weight${weight},
-            % endfor
+            None,
+            Url(Url),
         }
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    SpecifiedValue::Bolder => dest.write_str("bolder"),
-                    SpecifiedValue::Lighter => dest.write_str("lighter"),
-                    % for weight in range(100, 901, 100):
-                        SpecifiedValue::Weight${weight} => dest.write_str("${weight}"),
-                    % endfor
+                    SpecifiedValue::None => dest.write_str("none"),
+                    SpecifiedValue::Url(ref url) => url.to_css(dest),
                 }
             }
         }
-        /// normal | bold | bolder | lighter | 100 | 200 | 300 | 400 | 500 | 600 | 700 | 800 | 900
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            input.try(|input| {
-                match_ignore_ascii_case! { try!(input.expect_ident()),
-                    "bold" => Ok(SpecifiedValue::Weight700),
-                    "normal" => Ok(SpecifiedValue::Weight400),
-                    "bolder" => Ok(SpecifiedValue::Bolder),
-                    "lighter" => Ok(SpecifiedValue::Lighter),
-                    _ => Err(())
-                }
-            }).or_else(|()| {
-                match try!(input.expect_integer()) {
-                    100 => Ok(SpecifiedValue::Weight100),
-                    200 => Ok(SpecifiedValue::Weight200),
-                    300 => Ok(SpecifiedValue::Weight300),
-                    400 => Ok(SpecifiedValue::Weight400),
-                    500 => Ok(SpecifiedValue::Weight500),
+
+        pub mod computed_value {
+            use cssparser::{ToCss, Token};
+            use std::fmt;
+            use url::Url;
+            use values::LocalToCss;
+
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<Url>);
+
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match self.0 {
+                        None => dest.write_str("none"),
+                        Some(ref url) => url.to_css(dest),
+                    }
+                }
+            }
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, _context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::None => computed_value::T(None),
+                    SpecifiedValue::Url(ref url) => computed_value::T(Some(url.clone())),
+                }
+            }
+        }
+
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                Ok(SpecifiedValue::None)
+            } else {
+                Ok(SpecifiedValue::Url(context.parse_url(&*try!(input.expect_url()))))
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="quotes">
+        use std::borrow::Cow;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        use cssparser::{ToCss, Token};
+
+        pub use self::computed_value::T as SpecifiedValue;
+
+        pub mod computed_value {
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Vec<(String,String)>);
+        }
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                let mut first = true;
+                for pair in &self.0 {
+                    if !first {
+                        try!(dest.write_str(" "));
+                    }
+                    first = false;
+                    try!(Token::QuotedString(Cow::from(&*pair.0)).to_css(dest));
+                    try!(dest.write_str(" "));
+                    try!(Token::QuotedString(Cow::from(&*pair.1)).to_css(dest));
+                }
+                Ok(())
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(vec![
+                ("\u{201c}".to_owned(), "\u{201d}".to_owned()),
+                ("\u{2018}".to_owned(), "\u{2019}".to_owned()),
+            ])
+        }
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue(Vec::new()))
+            }
+
+            let mut quotes = Vec::new();
+            loop {
+                let first = match input.next() {
+                    Ok(Token::QuotedString(value)) => value.into_owned(),
+                    Ok(_) => return Err(()),
+                    Err(()) => break,
+                };
+                let second = match input.next() {
+                    Ok(Token::QuotedString(value)) => value.into_owned(),
+                    _ => return Err(()),
+                };
+                quotes.push((first, second))
+            }
+            if !quotes.is_empty() {
+                Ok(SpecifiedValue(quotes))
+            } else {
+                Err(())
+            }
+        }
+    </%self:longhand>
+
+    ${new_style_struct("Counters", is_inherited=False,
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in COUNTER_LONGHANDS])}
+
+    <%self:longhand name="counter-increment">
+        use std::fmt;
+        use super::content;
+        use values::computed::ComputedValueAsSpecified;
+
+        use cssparser::{ToCss, Token, serialize_identifier};
+        use std::borrow::{Cow, ToOwned};
+
+        pub use self::computed_value::T as SpecifiedValue;
+
+        pub mod computed_value {
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Vec<(String,i32)>);
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(Vec::new())
+        }
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                if self.0.is_empty() {
+                    return dest.write_str("none")
+                }
+                let mut first = true;
+                for pair in &self.0 {
+                    if !first {
+                        try!(dest.write_str(" "));
+                    }
+                    first = false;
+                    try!(serialize_identifier(&pair.0, dest));
+                    try!(write!(dest, " {}", pair.1));
+                }
+                Ok(())
+            }
+        }
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            parse_common(1, input)
+        }
+
+        pub fn parse_common(default_value: i32, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue(Vec::new()))
+            }
+
+            let mut counters = Vec::new();
+            loop {
+                let counter_name = match input.next() {
+                    Ok(Token::Ident(ident)) => (*ident).to_owned(),
+                    Ok(_) => return Err(()),
+                    Err(_) => break,
+                };
+                if content::counter_name_is_illegal(&counter_name) {
+                    return Err(())
+                }
+                let counter_delta =
+                    input.try(|input| specified::parse_integer(input)).unwrap_or(default_value);
+                counters.push((counter_name, counter_delta))
+            }
+
+            if !counters.is_empty() {
+                Ok(SpecifiedValue(counters))
+            } else {
+                Err(())
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="counter-reset">
+        pub use super::counter_increment::{SpecifiedValue, computed_value, get_initial_value};
+        use super::counter_increment::{parse_common};
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            parse_common(0, input)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="counter-set">
+        pub use super::counter_increment::{SpecifiedValue, computed_value, get_initial_value};
+        use super::counter_increment::{parse_common};
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            parse_common(0, input)
+        }
+    </%self:longhand>
+
+    // CSS 2.1, Section 13 - Paged media
+
+    // CSS 2.1, Section 14 - Colors and Backgrounds
+
+    ${new_style_struct("Background", is_inherited=False, gecko_name="nsStyleBackground",
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in BACKGROUND_LAYER_LONGHANDS])}
+    ${predefined_type(
+        "background-color", "CSSColor",
+        "::cssparser::Color::RGBA(::cssparser::RGBA { red: 0., green: 0., blue: 0., alpha: 0. }) /* transparent */")}
+
+    <%self:longhand name="background-image">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::specified::Image;
+
+        pub mod computed_value {
+            use values::computed;
+
+            /// The image of each layer, from the top one down, or None if a layer has no image.
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Vec<SingleComputedValue>);
+
+            pub type SingleComputedValue = Option<computed::Image>;
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                for (i, image) in self.0.iter().enumerate() {
+                    if i != 0 {
+                        try!(dest.write_str(", "));
+                    }
+                    match *image {
+                        None => try!(dest.write_str("none")),
+                        Some(ref image) => try!(image.to_css(dest)),
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        pub type SingleSpecifiedValue = Option<Image>;
+
+        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub Vec<SingleSpecifiedValue>);
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                for (i, image) in self.0.iter().enumerate() {
+                    if i != 0 {
+                        try!(dest.write_str(", "));
+                    }
+                    match *image {
+                        None => try!(dest.write_str("none")),
+                        Some(ref image) => try!(image.to_css(dest)),
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(vec![get_initial_single_value()])
+        }
+
+        #[inline]
+        pub fn get_initial_single_value() -> computed_value::SingleComputedValue {
+            None
+        }
+
+        pub fn parse_one(context: &ParserContext, input: &mut Parser) -> Result<SingleSpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                Ok(None)
+            } else {
+                Ok(Some(try!(Image::parse(context, input))))
+            }
+        }
+
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            Ok(SpecifiedValue(try!(input.parse_comma_separated(|input| parse_one(context, input)))))
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                computed_value::T(self.0.iter().map(|image| {
+                    image.as_ref().map(|image| image.to_computed_value(context))
+                }).collect())
+            }
+        }
+    </%self:longhand>
+
+    <%self:layered_longhand name="background-position">
+        pub use values::specified::Position as SpecifiedValue;
+
+        pub mod computed_value {
+            pub use values::computed::Position as T;
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::LengthOrPercentage::Percentage(0.0),
+                vertical: computed::LengthOrPercentage::Percentage(0.0),
+            }
+        }
+
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            SpecifiedValue::parse(input)
+        }
+    </%self:layered_longhand>
+
+    ${layered_single_keyword("background-repeat", "repeat repeat-x repeat-y no-repeat")}
+
+    ${layered_single_keyword("background-attachment", "scroll fixed")}
+
+    ${layered_single_keyword("background-clip", "border-box padding-box content-box")}
+
+    ${layered_single_keyword("background-origin", "padding-box border-box content-box")}
+
+    <%self:layered_longhand name="background-size">
+        use cssparser::{ToCss, Token};
+        use std::ascii::AsciiExt;
+        use std::fmt;
+
+        pub mod computed_value {
+            use values::computed::LengthOrPercentageOrAuto;
+
+            #[derive(PartialEq, Clone, Debug, HeapSizeOf)]
+            pub struct ExplicitSize {
+                pub width: LengthOrPercentageOrAuto,
+                pub height: LengthOrPercentageOrAuto,
+            }
+
+            #[derive(PartialEq, Clone, Debug, HeapSizeOf)]
+            pub enum T {
+                Explicit(ExplicitSize),
+                Cover,
+                Contain,
+            }
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Explicit(ref size) => size.to_css(dest),
+                    computed_value::T::Cover => dest.write_str("cover"),
+                    computed_value::T::Contain => dest.write_str("contain"),
+                }
+            }
+        }
+
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedExplicitSize {
+            pub width: specified::LengthOrPercentageOrAuto,
+            pub height: specified::LengthOrPercentageOrAuto,
+        }
+
+        impl ToCss for SpecifiedExplicitSize {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.width.to_css(dest));
+                try!(dest.write_str(" "));
+                self.height.to_css(dest)
+            }
+        }
+
+        impl ToCss for computed_value::ExplicitSize {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.width.to_css(dest));
+                try!(dest.write_str(" "));
+                self.height.to_css(dest)
+            }
+        }
+
+
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Explicit(SpecifiedExplicitSize),
+            Cover,
+            Contain,
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Explicit(ref size) => size.to_css(dest),
+                    SpecifiedValue::Cover => dest.write_str("cover"),
+                    SpecifiedValue::Contain => dest.write_str("contain"),
+                }
+            }
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::Explicit(ref size) => {
+                        computed_value::T::Explicit(computed_value::ExplicitSize {
+                            width: size.width.to_computed_value(context),
+                            height: size.height.to_computed_value(context),
+                        })
+                    }
+                    SpecifiedValue::Cover => computed_value::T::Cover,
+                    SpecifiedValue::Contain => computed_value::T::Contain,
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Explicit(computed_value::ExplicitSize {
+                width: computed::LengthOrPercentageOrAuto::Auto,
+                height: computed::LengthOrPercentageOrAuto::Auto,
+            })
+        }
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            let width;
+            if let Ok(value) = input.try(|input| {
+                match input.next() {
+                    Err(_) => Err(()),
+                    Ok(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("cover") => {
+                        Ok(SpecifiedValue::Cover)
+                    }
+                    Ok(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("contain") => {
+                        Ok(SpecifiedValue::Contain)
+                    }
+                    Ok(_) => Err(()),
+                }
+            }) {
+                return Ok(value)
+            } else {
+                width = try!(specified::LengthOrPercentageOrAuto::parse(input))
+            }
+
+            // The height can be left out, and something else of the background shorthand may
+            // come after it.
+            let height = input.try(specified::LengthOrPercentageOrAuto::parse)
+                              .unwrap_or(specified::LengthOrPercentageOrAuto::Auto);
+
+            Ok(SpecifiedValue::Explicit(SpecifiedExplicitSize {
+                width: width,
+                height: height,
+            }))
+        }
+    </%self:layered_longhand>
+
+    ${new_style_struct("Color", is_inherited=True, gecko_name="nsStyleColor",
+                       additional_methods=[Method("clone_color",
+                                                  "longhands::color::computed_value::T")])}
+
+    <%self:raw_longhand name="color">
+        use cssparser::{Color, RGBA};
+        use values::specified::{CSSColor, CSSRGBA};
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match self.system {
+                    Some(system) => context.system_color(system),
+                    None => self.parsed,
+                }
+            }
+        }
+
+        pub type SpecifiedValue = CSSRGBA;
+        pub mod computed_value {
+            use cssparser;
+            pub type T = cssparser::RGBA;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            RGBA { red: 0., green: 0., blue: 0., alpha: 1. }  /* black */
+        }
+        pub fn parse_specified(context: &ParserContext, input: &mut Parser)
+                               -> Result<DeclaredValue<SpecifiedValue>, ()> {
+            let value = try!(CSSColor::parse_quirky(context, input));
+            let rgba = match value.parsed {
+                Color::RGBA(rgba) => rgba,
+                Color::CurrentColor => return Ok(DeclaredValue::Inherit)
+            };
+            Ok(DeclaredValue::Value(CSSRGBA {
+                parsed: rgba,
+                authored: value.authored,
+                system: value.system,
+            }))
+        }
+    </%self:raw_longhand>
+
+    // CSS 2.1, Section 15 - Fonts
+
+    ${new_style_struct("Font", is_inherited=True, gecko_name="nsStyleFont",
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in FONT_LONGHANDS] +
+                                          [Method("compute_font_hash", is_mut=True),
+                                           Method("font_metrics",
+                                                  "Option<::values::computed::FontMetrics>")])}
+
+    <%self:longhand name="font-family">
+        use self::computed_value::FontFamily;
+        use values::computed::ComputedValueAsSpecified;
+        pub use self::computed_value::T as SpecifiedValue;
+
+        const SERIF: &'static str = "serif";
+        const SANS_SERIF: &'static str = "sans-serif";
+        const CURSIVE: &'static str = "cursive";
+        const FANTASY: &'static str = "fantasy";
+        const MONOSPACE: &'static str = "monospace";
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+        pub mod computed_value {
+            use cssparser::{ToCss, serialize_identifier, serialize_string};
+            use std::fmt;
+            use string_cache::Atom;
+
+            #[derive(Debug, PartialEq, Eq, Clone, Hash, HeapSizeOf, Deserialize, Serialize)]
+            pub enum FontFamily {
+                FamilyName(Atom),
+                // Generic,
+                Serif,
+                SansSerif,
+                Cursive,
+                Fantasy,
+                Monospace,
+            }
+            impl FontFamily {
+                #[inline]
+                pub fn name(&self) -> &str {
+                    match *self {
+                        FontFamily::FamilyName(ref name) => &*name,
+                        FontFamily::Serif => super::SERIF,
+                        FontFamily::SansSerif => super::SANS_SERIF,
+                        FontFamily::Cursive => super::CURSIVE,
+                        FontFamily::Fantasy => super::FANTASY,
+                        FontFamily::Monospace => super::MONOSPACE
+                    }
+                }
+
+                pub fn from_atom(input: Atom) -> FontFamily {
+                    let option = match_ignore_ascii_case! { &input,
+                        super::SERIF => Some(FontFamily::Serif),
+                        super::SANS_SERIF => Some(FontFamily::SansSerif),
+                        super::CURSIVE => Some(FontFamily::Cursive),
+                        super::FANTASY => Some(FontFamily::Fantasy),
+                        super::MONOSPACE => Some(FontFamily::Monospace),
+                        _ => None
+                    };
+
+                    match option {
+                        Some(family) => family,
+                        None => FontFamily::FamilyName(input)
+                    }
+                }
+            }
+            impl ToCss for FontFamily {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    let name = match *self {
+                        FontFamily::FamilyName(ref name) => &**name,
+                        _ => return dest.write_str(self.name()),
+                    };
+                    if !serializes_as_identifiers(name) {
+                        return serialize_string(name, dest)
+                    }
+                    for (i, word) in name.split(' ').enumerate() {
+                        if i != 0 {
+                            try!(dest.write_str(" "));
+                        }
+                        try!(serialize_identifier(word, dest));
+                    }
+                    Ok(())
+                }
+            }
+            /// Whether a family name reads back the same written as a sequence of identifiers:
+            /// rather than being quoted, it has to be words separated by single spaces, and not
+            /// start with a generic family or be a keyword the whole declaration would mean.
+            fn serializes_as_identifiers(name: &str) -> bool {
+                if name.is_empty() || name.split(' ').any(|word| word.is_empty()) {
+                    return false
+                }
+                let first_word = name.split(' ').next().unwrap();
+                match_ignore_ascii_case! { first_word,
+                    super::SERIF => return false,
+                    super::SANS_SERIF => return false,
+                    super::CURSIVE => return false,
+                    super::FANTASY => return false,
+                    super::MONOSPACE => return false,
+                    _ => {}
+                }
+                match_ignore_ascii_case! { name,
+                    "inherit" => false,
+                    "initial" => false,
+                    "unset" => false,
+                    "default" => false,
+                    _ => true
+                }
+            }
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    let mut iter = self.0.iter();
+                    try!(iter.next().unwrap().to_css(dest));
+                    for family in iter {
+                        try!(dest.write_str(", "));
+                        try!(family.to_css(dest));
+                    }
+                    Ok(())
+                }
+            }
+            #[derive(Debug, Clone, PartialEq, Eq, Hash, HeapSizeOf)]
+            pub struct T(pub Vec<FontFamily>);
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(vec![FontFamily::Serif])
+        }
+        /// <family-name>#
+        /// <family-name> = <string> | [ <ident>+ ]
+        /// TODO: <generic-family>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            input.parse_comma_separated(parse_one_family).map(SpecifiedValue)
+        }
+        pub fn parse_one_family(input: &mut Parser) -> Result<FontFamily, ()> {
+            if let Ok(value) = input.try(|input| input.expect_string()) {
+                return Ok(FontFamily::FamilyName(Atom::from(&*value)))
+            }
+            let first_ident = try!(input.expect_ident());
+
+            match_ignore_ascii_case! { first_ident,
+                SERIF => return Ok(FontFamily::Serif),
+                SANS_SERIF => return Ok(FontFamily::SansSerif),
+                CURSIVE => return Ok(FontFamily::Cursive),
+                FANTASY => return Ok(FontFamily::Fantasy),
+                MONOSPACE => return Ok(FontFamily::Monospace),
+                _ => {}
+            }
+            let mut value = first_ident.into_owned();
+            while let Ok(ident) = input.try(|input| input.expect_ident()) {
+                value.push_str(" ");
+                value.push_str(&ident);
+            }
+            Ok(FontFamily::FamilyName(Atom::from(value)))
+        }
+    </%self:longhand>
+
+
+    ${single_keyword("font-style", "normal italic oblique")}
+    ${single_keyword("font-variant", "normal small-caps")}
+
+    <%self:longhand name="font-weight">
+        use cssparser::ToCss;
+        use std::fmt;
+
+        #[derive(Debug, Clone, PartialEq, Eq, Copy, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Bolder,
+            Lighter,
+            % for weight in range(100, 901, 100):
+                Weight${weight},
+            % endfor
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Bolder => dest.write_str("bolder"),
+                    SpecifiedValue::Lighter => dest.write_str("lighter"),
+                    % for weight in range(100, 901, 100):
+                        SpecifiedValue::Weight${weight} => dest.write_str("${weight}"),
+                    % endfor
+                }
+            }
+        }
+        /// normal | bold | bolder | lighter | 100 | 200 | 300 | 400 | 500 | 600 | 700 | 800 | 900
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            input.try(|input| {
+                match_ignore_ascii_case! { try!(input.expect_ident()),
+                    "bold" => Ok(SpecifiedValue::Weight700),
+                    "normal" => Ok(SpecifiedValue::Weight400),
+                    "bolder" => Ok(SpecifiedValue::Bolder),
+                    "lighter" => Ok(SpecifiedValue::Lighter),
+                    _ => Err(())
+                }
+            }).or_else(|()| {
+                match try!(input.expect_integer()) {
+                    100 => Ok(SpecifiedValue::Weight100),
+                    200 => Ok(SpecifiedValue::Weight200),
+                    300 => Ok(SpecifiedValue::Weight300),
+                    400 => Ok(SpecifiedValue::Weight400),
+                    500 => Ok(SpecifiedValue::Weight500),
                     600 => Ok(SpecifiedValue::Weight600),
                     700 => Ok(SpecifiedValue::Weight700),
                     800 => Ok(SpecifiedValue::Weight800),
                     900 => Ok(SpecifiedValue::Weight900),
                     _ => Err(())
                 }
-            })
+            })
+        }
+        pub mod computed_value {
+            use std::fmt;
+            #[derive(PartialEq, Eq, Copy, Clone, Hash, Deserialize, Serialize, HeapSizeOf, Debug)]
+            pub enum T {
+                % for weight in range(100, 901, 100):
+                    Weight${weight} = ${weight},
+                % endfor
+            }
+            impl T {
+                #[inline]
+                pub fn is_bold(self) -> bool {
+                    match self {
+                        T::Weight900 | T::Weight800 |
+                        T::Weight700 | T::Weight600 => true,
+                        _ => false
+                    }
+                }
+            }
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    % for weight in range(100, 901, 100):
+                        computed_value::T::Weight${weight} => dest.write_str("${weight}"),
+                    % endfor
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Weight400  // normal
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    % for weight in range(100, 901, 100):
+                        SpecifiedValue::Weight${weight} => computed_value::T::Weight${weight},
+                    % endfor
+                    SpecifiedValue::Bolder => match context.inherited_style().get_font().clone_font_weight() {
+                        computed_value::T::Weight100 => computed_value::T::Weight400,
+                        computed_value::T::Weight200 => computed_value::T::Weight400,
+                        computed_value::T::Weight300 => computed_value::T::Weight400,
+                        computed_value::T::Weight400 => computed_value::T::Weight700,
+                        computed_value::T::Weight500 => computed_value::T::Weight700,
+                        computed_value::T::Weight600 => computed_value::T::Weight900,
+                        computed_value::T::Weight700 => computed_value::T::Weight900,
+                        computed_value::T::Weight800 => computed_value::T::Weight900,
+                        computed_value::T::Weight900 => computed_value::T::Weight900,
+                    },
+                    SpecifiedValue::Lighter => match context.inherited_style().get_font().clone_font_weight() {
+                        computed_value::T::Weight100 => computed_value::T::Weight100,
+                        computed_value::T::Weight200 => computed_value::T::Weight100,
+                        computed_value::T::Weight300 => computed_value::T::Weight100,
+                        computed_value::T::Weight400 => computed_value::T::Weight100,
+                        computed_value::T::Weight500 => computed_value::T::Weight100,
+                        computed_value::T::Weight600 => computed_value::T::Weight400,
+                        computed_value::T::Weight700 => computed_value::T::Weight400,
+                        computed_value::T::Weight800 => computed_value::T::Weight700,
+                        computed_value::T::Weight900 => computed_value::T::Weight700,
+                    },
+                }
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="font-size" custom_cascade="True">
+        use app_units::Au;
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::FONT_MEDIUM_PX;
+        use values::specified::{KeywordSize, LengthOrPercentage, Length, Percentage};
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Length(ref length) => length.to_css(dest),
+                    SpecifiedValue::Keyword(keyword) => keyword.to_css(dest),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Length(specified::LengthOrPercentage),
+            /// An <absolute-size>, which the embedder decides the size of.
+            Keyword(KeywordSize),
+        }
+        pub mod computed_value {
+            use app_units::Au;
+            pub type T = Au;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            Au::from_px(FONT_MEDIUM_PX)
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                let length = match *self {
+                    SpecifiedValue::Length(ref length) => length,
+                    SpecifiedValue::Keyword(keyword) => {
+                        let font = context.style().get_font();
+                        return keyword.to_size(<Cx::ConcreteComputedValues as ComputedValues>::medium_font_size(font))
+                    }
+                };
+                match *length {
+                    LengthOrPercentage::Length(Length::FontRelative(value)) => {
+                        value.to_computed_value(context.inherited_style().get_font().clone_font_size(),
+                                                context.style().root_font_size())
+                    }
+                    LengthOrPercentage::Length(Length::ServoCharacterWidth(value)) => {
+                        value.to_computed_value(context.inherited_style().get_font().clone_font_size())
+                    }
+                    LengthOrPercentage::Length(l) => {
+                        l.to_computed_value(context)
+                    }
+                    LengthOrPercentage::Percentage(Percentage(value)) => {
+                        context.inherited_style().get_font().clone_font_size().scale_by(value)
+                    }
+                    LengthOrPercentage::Calc(calc) => {
+                        let calc = calc.to_computed_value(context);
+                        calc.length() + context.inherited_style().get_font().clone_font_size()
+                                               .scale_by(calc.percentage())
+                    }
+                }
+            }
+        }
+        /// <length> | <percentage> | <absolute-size> | <relative-size>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if let Ok(length) = input.try(specified::LengthOrPercentage::parse_non_negative) {
+                return Ok(SpecifiedValue::Length(length))
+            }
+            let ident = try!(input.expect_ident());
+            if let Some(keyword) = KeywordSize::from_str(&ident) {
+                return Ok(SpecifiedValue::Keyword(keyword))
+            }
+            specified::Length::from_str(&ident)
+                .ok_or(())
+                .map(|length| SpecifiedValue::Length(specified::LengthOrPercentage::Length(length)))
+        }
+
+        /// Remembers whether the size came from a keyword, for the cascade to work it out
+        /// again once the font family and language it depends on are known.
+        pub fn cascade_property_custom<C: ComputedValues>(
+                                       declaration: &PropertyDeclaration,
+                                       _inherited_style: &C,
+                                       context: &mut computed::Context<C>,
+                                       _seen: &mut PropertyBitField,
+                                       _cacheable: &mut bool,
+                                       error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            let declared_value = match *declaration {
+                PropertyDeclaration::FontSize(ref declared_value) => declared_value,
+                _ => return,
+            };
+            let mut keyword = None;
+            let custom_properties = context.style().custom_properties();
+            ::properties::substitute_variables_font_size(declared_value, &custom_properties, |value| {
+                if let DeclaredValue::Value(SpecifiedValue::Keyword(size)) = *value {
+                    keyword = Some(size);
+                }
+            }, error_reporter);
+            context.font_size_keyword = keyword;
+        }
+    </%self:longhand>
+
+    ${single_keyword("font-stretch",
+                     "normal ultra-condensed extra-condensed condensed semi-condensed semi-expanded \
+                     expanded extra-expanded ultra-expanded")}
+
+    <%self:longhand name="font-size-adjust">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use values::CSSFloat;
+
+            /// The aspect value to scale the font's size by, or None for `none`.
+            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<CSSFloat>);
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    Some(value) => write!(dest, "{}", value),
+                    None => dest.write_str("none"),
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+        /// none | <number>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(computed_value::T(None))
+            }
+            match try!(specified::parse_number(input)) {
+                value if value >= 0. => Ok(computed_value::T(Some(value))),
+                _ => Err(()),
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="font-feature-settings">
+        use cssparser::{self, ToCss};
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+        use self::computed_value::FeatureTagValue;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            /// A font feature, named by its OpenType tag, and the value to turn it on with. Most
+            /// features are just on, with 1, or off, with 0, but some pick among alternates.
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub struct FeatureTagValue {
+                pub tag: String,
+                pub value: u32,
+            }
+
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub enum T {
+                Normal,
+                Tag(Vec<FeatureTagValue>),
+            }
+        }
+        impl ToCss for FeatureTagValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(cssparser::serialize_string(&self.tag, dest));
+                match self.value {
+                    1 => Ok(()),
+                    0 => dest.write_str(" off"),
+                    value => write!(dest, " {}", value),
+                }
+            }
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Normal => dest.write_str("normal"),
+                    computed_value::T::Tag(ref features) => {
+                        try!(features[0].to_css(dest));
+                        for feature in &features[1..] {
+                            try!(dest.write_str(", "));
+                            try!(feature.to_css(dest));
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Normal
+        }
+
+        /// Parses the OpenType tag of a font feature or variation axis: a string of four
+        /// printable ASCII characters.
+        pub fn parse_tag(input: &mut Parser) -> Result<String, ()> {
+            let tag = try!(input.expect_string());
+            if tag.len() != 4 || !tag.bytes().all(|byte| byte >= 0x20 && byte <= 0x7E) {
+                return Err(())
+            }
+            Ok(tag.into_owned())
+        }
+
+        /// normal | <feature-tag-value>#
+        /// <feature-tag-value> = <string> [ <integer> | on | off ]?
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                return Ok(computed_value::T::Normal)
+            }
+            input.parse_comma_separated(|input| {
+                let tag = try!(parse_tag(input));
+                let value = if input.try(|input| input.expect_ident_matching("on")).is_ok() {
+                    1
+                } else if input.try(|input| input.expect_ident_matching("off")).is_ok() {
+                    0
+                } else {
+                    match input.try(|input| input.expect_integer()) {
+                        Ok(value) if value >= 0 => value as u32,
+                        Ok(_) => return Err(()),
+                        Err(()) => 1,
+                    }
+                };
+                Ok(FeatureTagValue {
+                    tag: tag,
+                    value: value,
+                })
+            }).map(computed_value::T::Tag)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="font-variation-settings">
+        use cssparser::{self, ToCss};
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+        use self::computed_value::VariationValue;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use values::CSSFloat;
+
+            /// A variation axis of the font, named by its OpenType tag, and where along it to
+            /// take the font from.
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct VariationValue {
+                pub tag: String,
+                pub value: CSSFloat,
+            }
+
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub enum T {
+                Normal,
+                Axes(Vec<VariationValue>),
+            }
+        }
+        impl ToCss for VariationValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(cssparser::serialize_string(&self.tag, dest));
+                write!(dest, " {}", self.value)
+            }
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Normal => dest.write_str("normal"),
+                    computed_value::T::Axes(ref axes) => {
+                        try!(axes[0].to_css(dest));
+                        for axis in &axes[1..] {
+                            try!(dest.write_str(", "));
+                            try!(axis.to_css(dest));
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Normal
+        }
+        /// normal | [ <string> <number> ]#
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                return Ok(computed_value::T::Normal)
+            }
+            input.parse_comma_separated(|input| {
+                let tag = try!(longhands::font_feature_settings::parse_tag(input));
+                Ok(VariationValue {
+                    tag: tag,
+                    value: try!(specified::parse_number(input)),
+                })
+            }).map(computed_value::T::Axes)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="font-language-override">
+        use cssparser::{self, ToCss};
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            /// The OpenType language system to shape text with instead of the one its
+            /// language implies.
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub enum T {
+                Normal,
+                Override(String),
+            }
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Normal => dest.write_str("normal"),
+                    computed_value::T::Override(ref language) => cssparser::serialize_string(language, dest),
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Normal
+        }
+        /// normal | <string>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                return Ok(computed_value::T::Normal)
+            }
+            Ok(computed_value::T::Override(try!(input.expect_string()).into_owned()))
+        }
+    </%self:longhand>
+
+    // The language of the element's text, which Gecko maps the lang attribute to. It's
+    // inherited, like the attribute, and picks among the platform's font preferences, so
+    // font-size keywords can come to a different size in a different language.
+    <%self:longhand name="-x-lang" internal="True">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use string_cache::Atom;
+
+            /// The language, or None when nothing says, in which case the document's is used.
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub struct T(pub Option<Atom>);
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    Some(ref language) => dest.write_str(language),
+                    None => dest.write_str("none"),
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+        /// none | <ident>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(computed_value::T(None))
+            }
+            Ok(computed_value::T(Some(Atom::from(&*try!(input.expect_ident())))))
+        }
+    </%self:longhand>
+
+    // The system font a `font` declaration named. The font longhands that no more important
+    // declaration sets take their values from it.
+    <%self:longhand name="-servo-system-font" custom_cascade="True" internal="True">
+        use cssparser::ToCss;
+        use std::fmt;
+        use system_values::SystemFont;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use system_values::SystemFont;
+
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, HeapSizeOf)]
+            pub struct T(pub Option<SystemFont>);
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    Some(font) => font.to_css(dest),
+                    None => dest.write_str("none"),
+                }
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+        /// none | caption | icon | menu | message-box | small-caption | status-bar
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(computed_value::T(None))
+            }
+            SystemFont::parse(input).map(|font| computed_value::T(Some(font)))
+        }
+
+        /// Gives the font longhands that haven't been seen yet the values of the system font.
+        /// This runs before the `font` declaration's other longhands are cascaded, since it's
+        /// the last of them, and those are then skipped.
+        pub fn cascade_property_custom<C: ComputedValues>(
+                                       declaration: &PropertyDeclaration,
+                                       _inherited_style: &C,
+                                       context: &mut computed::Context<C>,
+                                       seen: &mut PropertyBitField,
+                                       _cacheable: &mut bool,
+                                       _error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            let font = match *declaration {
+                PropertyDeclaration::ServoSystemFont(DeclaredValue::Value(computed_value::T(Some(font)))) => font,
+                _ => return,
+            };
+            let values = match C::system_font(font) {
+                Some(values) => values,
+                None => return,
+            };
+            let style = context.mutate_style().mutate_font();
+            if !seen.get_font_family() {
+                seen.set_font_family();
+                style.set_font_family(values.family);
+            }
+            if !seen.get_font_size() {
+                seen.set_font_size();
+                style.set_font_size(values.size);
+            }
+            if !seen.get_font_weight() {
+                seen.set_font_weight();
+                style.set_font_weight(values.weight);
+            }
+            if !seen.get_font_style() {
+                seen.set_font_style();
+                style.set_font_style(values.style);
+            }
+        }
+    </%self:longhand>
+
+    // CSS 2.1, Section 16 - Text
+
+    ${new_style_struct("InheritedText", is_inherited=True, gecko_name="nsStyleText",
+                       additional_methods=[Method("clone__servo_text_decorations_in_effect",
+                                                  "longhands::_servo_text_decorations_in_effect::computed_value::T")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in LINE_LAYOUT_LONGHANDS + TEXT_EMPHASIS_LONGHANDS +
+                                                        ["text_shadow", "text_overflow"]])}
+
+    <%self:longhand name="text-align">
+        pub use self::computed_value::T as SpecifiedValue;
+        use values::computed::ComputedValueAsSpecified;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+        pub mod computed_value {
+            macro_rules! define_text_align {
+                ( $( $name: ident ( $string: expr ) => $discriminant: expr, )+ ) => {
+                    define_css_keyword_enum! { T:
+                        $(
+                            $string => $name,
+                        )+
+                    }
+                    impl T {
+                        pub fn to_u32(self) -> u32 {
+                            match self {
+                                $(
+                                    T::$name => $discriminant,
+                                )+
+                            }
+                        }
+                        pub fn from_u32(discriminant: u32) -> Option<T> {
+                            match discriminant {
+                                $(
+                                    $discriminant => Some(T::$name),
+                                )+
+                                _ => None
+                            }
+                        }
+                    }
+                }
+            }
+            define_text_align! {
+                start("start") => 0,
+                end("end") => 1,
+                left("left") => 2,
+                right("right") => 3,
+                center("center") => 4,
+                justify("justify") => 5,
+                servo_center("-servo-center") => 6,
+                servo_left("-servo-left") => 7,
+                servo_right("-servo-right") => 8,
+            }
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::start
+        }
+        pub fn parse(_context: &ParserContext, input: &mut Parser)
+                     -> Result<SpecifiedValue, ()> {
+            computed_value::T::parse(input)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="letter-spacing">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::AuExtensionMethods;
+
+        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Normal,
+            Specified(specified::Length),
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Normal => dest.write_str("normal"),
+                    SpecifiedValue::Specified(l) => l.to_css(dest),
+                }
+            }
+        }
+
+        pub mod computed_value {
+            use app_units::Au;
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<Au>);
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    None => dest.write_str("normal"),
+                    Some(l) => l.to_css(dest),
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::Normal => computed_value::T(None),
+                    SpecifiedValue::Specified(l) =>
+                        computed_value::T(Some(l.to_computed_value(context)))
+                }
+            }
+        }
+
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                Ok(SpecifiedValue::Normal)
+            } else {
+                specified::Length::parse_non_negative(input).map(SpecifiedValue::Specified)
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="word-spacing">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::AuExtensionMethods;
+
+        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Normal,
+            Specified(specified::Length),  // FIXME(SimonSapin) support percentages
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Normal => dest.write_str("normal"),
+                    SpecifiedValue::Specified(l) => l.to_css(dest),
+                }
+            }
+        }
+
+        pub mod computed_value {
+            use app_units::Au;
+            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+            pub struct T(pub Option<Au>);
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.0 {
+                    None => dest.write_str("normal"),
+                    Some(l) => l.to_css(dest),
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(None)
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::Normal => computed_value::T(None),
+                    SpecifiedValue::Specified(l) =>
+                        computed_value::T(Some(l.to_computed_value(context)))
+                }
+            }
+        }
+
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
+                Ok(SpecifiedValue::Normal)
+            } else {
+                specified::Length::parse_non_negative(input).map(SpecifiedValue::Specified)
+            }
+        }
+    </%self:longhand>
+
+    ${predefined_type("text-indent", "LengthOrPercentage", "computed::LengthOrPercentage::Length(Au(0))")}
+
+    // Also known as "word-wrap" (which is more popular because of IE), but this is the preferred
+    // name per CSS-TEXT 6.2.
+    ${single_keyword("overflow-wrap", "normal break-word")}
+
+    // TODO(pcwalton): Support `word-break: keep-all` once we have better CJK support.
+    ${single_keyword("word-break", "normal break-all")}
+
+    <%self:longhand name="text-overflow">
+        use cssparser::{self, ToCss};
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            /// What's shown where the text of a line is cut off.
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub enum Side {
+                Clip,
+                Ellipsis,
+                String(String),
+            }
+
+            /// A single value is for the end of the line, with the start clipped. Two are for
+            /// the left and right of the line, whatever its direction.
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub struct T {
+                pub first: Side,
+                pub second: Option<Side>,
+            }
+
+            impl T {
+                /// What's shown at the end of a line, which is on the left when |rtl|.
+                pub fn line_end(&self, rtl: bool) -> &Side {
+                    match self.second {
+                        Some(ref right) if !rtl => right,
+                        _ => &self.first,
+                    }
+                }
+            }
+        }
+        impl ToCss for computed_value::Side {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::Side::Clip => dest.write_str("clip"),
+                    computed_value::Side::Ellipsis => dest.write_str("ellipsis"),
+                    computed_value::Side::String(ref string) => cssparser::serialize_string(string, dest),
+                }
+            }
+        }
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.first.to_css(dest));
+                if let Some(ref second) = self.second {
+                    try!(dest.write_str(" "));
+                    try!(second.to_css(dest));
+                }
+                Ok(())
+            }
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                first: computed_value::Side::Clip,
+                second: None,
+            }
+        }
+        fn parse_side(input: &mut Parser) -> Result<computed_value::Side, ()> {
+            if let Ok(string) = input.try(|input| input.expect_string()) {
+                return Ok(computed_value::Side::String(string.into_owned()))
+            }
+            match_ignore_ascii_case! { try!(input.expect_ident()),
+                "clip" => Ok(computed_value::Side::Clip),
+                "ellipsis" => Ok(computed_value::Side::Ellipsis),
+                _ => Err(())
+            }
+        }
+        /// [ clip | ellipsis | <string> ]{1,2}
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            let first = try!(parse_side(input));
+            Ok(computed_value::T {
+                first: first,
+                second: input.try(parse_side).ok(),
+            })
+        }
+    </%self:longhand>
+
+    // TODO(pcwalton): Support `text-justify: distribute`.
+    ${single_keyword("text-justify", "auto none inter-word")}
+
+    ${new_style_struct("Text", is_inherited=False, gecko_name="nsStyleTextReset",
+                       additional_methods=[Method("has_underline", "bool"),
+                                           Method("has_overline", "bool"),
+                                           Method("has_line_through", "bool")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in TEXT_DECORATION_LONGHANDS + ["unicode_bidi"]])}
+
+    ${single_keyword("unicode-bidi", "normal embed isolate bidi-override isolate-override plaintext")}
+
+    <%self:longhand name="text-decoration-line" custom_cascade="True">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+        use properties::style_struct_traits::TInheritedText;
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        #[derive(PartialEq, Eq, Copy, Clone, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue {
+            pub underline: bool,
+            pub overline: bool,
+            pub line_through: bool,
+            // 'blink' is accepted in the parser but ignored.
+            // Just not blinking the text is a conforming implementation per CSS 2.1.
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                if *self == computed_value::none {
+                    return dest.write_str("none")
+                }
+                let mut space = false;
+                if self.underline {
+                    try!(dest.write_str("underline"));
+                    space = true;
+                }
+                if self.overline {
+                    if space {
+                        try!(dest.write_str(" "));
+                    }
+                    try!(dest.write_str("overline"));
+                    space = true;
+                }
+                if self.line_through {
+                    if space {
+                        try!(dest.write_str(" "));
+                    }
+                    try!(dest.write_str("line-through"));
+                }
+                Ok(())
+            }
         }
         pub mod computed_value {
-            use std::fmt;
-            #[derive(PartialEq, Eq, Copy, Clone, Hash, Deserialize, Serialize, HeapSizeOf, Debug)]
-            pub enum T {
-                % for weight in range(100, 901, 100):
-                    Weight${weight} = ${weight},
-                % endfor
+            pub type T = super::SpecifiedValue;
+            #[allow(non_upper_case_globals)]
+            pub const none: T = super::SpecifiedValue {
+                underline: false, overline: false, line_through: false
+            };
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            computed_value::none
+        }
+        /// none | [ underline || overline || line-through || blink ]
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            let mut result = SpecifiedValue {
+                underline: false, overline: false, line_through: false,
+            };
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(result)
             }
-            impl T {
-                #[inline]
-                pub fn is_bold(self) -> bool {
-                    match self {
-                        T::Weight900 | T::Weight800 |
-                        T::Weight700 | T::Weight600 => true,
-                        _ => false
+            let mut blink = false;
+            let mut empty = true;
+            loop {
+                // Leave whatever follows the keywords for the text-decoration shorthand to parse.
+                let start = input.position();
+                let ident = match input.expect_ident() {
+                    Ok(ident) => ident,
+                    Err(()) => {
+                        input.reset(start);
+                        break
+                    }
+                };
+                match_ignore_ascii_case! { ident,
+                    "underline" => if result.underline { return Err(()) }
+                                  else { empty = false; result.underline = true },
+                    "overline" => if result.overline { return Err(()) }
+                                  else { empty = false; result.overline = true },
+                    "line-through" => if result.line_through { return Err(()) }
+                                      else { empty = false; result.line_through = true },
+                    "blink" => if blink { return Err(()) }
+                               else { empty = false; blink = true },
+                    _ => {
+                        input.reset(start);
+                        break
+                    }
+                }
+            }
+            if !empty { Ok(result) } else { Err(()) }
+        }
+
+        fn cascade_property_custom<C: ComputedValues>(
+                                   _declaration: &PropertyDeclaration,
+                                   _inherited_style: &C,
+                                   context: &mut computed::Context<C>,
+                                   _seen: &mut PropertyBitField,
+                                   _cacheable: &mut bool,
+                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            longhands::_servo_text_decorations_in_effect::derive_from_text_decoration_line(context);
+        }
+    </%self:longhand>
+
+    ${single_keyword("text-decoration-style", "solid double dotted dashed wavy")}
+
+    // Kept as currentColor at computed-value time, so that it follows the color of whatever
+    // the decoration is drawn over; see -servo-text-decorations-in-effect.
+    <%self:longhand name="text-decoration-color" custom_cascade="True">
+        pub type SpecifiedValue = specified::CSSColor;
+
+        pub mod computed_value {
+            pub use values::computed::CSSColor as T;
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            ::cssparser::Color::CurrentColor
+        }
+
+        #[inline]
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            specified::CSSColor::parse(input)
+        }
+
+        fn cascade_property_custom<C: ComputedValues>(
+                                   _declaration: &PropertyDeclaration,
+                                   _inherited_style: &C,
+                                   context: &mut computed::Context<C>,
+                                   _seen: &mut PropertyBitField,
+                                   _cacheable: &mut bool,
+                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
+            longhands::_servo_text_decorations_in_effect::derive_from_text_decoration_color(context);
+        }
+    </%self:longhand>
+
+    ${switch_to_style_struct("InheritedText")}
+
+    <%self:longhand name="-servo-text-decorations-in-effect"
+                    derived_from="display text-decoration-line text-decoration-color">
+        use cssparser::{Color, RGBA, ToCss};
+        use std::fmt;
+
+        use values::computed::ComputedValueAsSpecified;
+        use properties::style_struct_traits::{TBox, TColor, TText};
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue {
+            pub underline: Option<RGBA>,
+            pub overline: Option<RGBA>,
+            pub line_through: Option<RGBA>,
+        }
+
+        pub mod computed_value {
+            pub type T = super::SpecifiedValue;
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, _: &mut W) -> fmt::Result where W: fmt::Write {
+                // Web compat doesn't matter here.
+                Ok(())
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            SpecifiedValue {
+                underline: None,
+                overline: None,
+                line_through: None,
+            }
+        }
+
+        fn maybe<Cx: TContext>(flag: bool, context: &Cx) -> Option<RGBA> {
+            if !flag {
+                return None
+            }
+            // The decoration color is only resolved here, where the decoration is drawn from.
+            match context.style().get_text().clone_text_decoration_color() {
+                Color::RGBA(rgba) => Some(rgba),
+                Color::CurrentColor => Some(context.style().get_color().clone_color()),
+            }
+        }
+
+        fn derive<Cx: TContext>(context: &Cx) -> computed_value::T {
+            // Start with no declarations if this is a block; otherwise, start with the
+            // declarations in effect and add in the text decorations that this inline specifies.
+            let mut result = match context.style().get_box().clone_display() {
+                super::display::computed_value::T::inline => {
+                    context.inherited_style().get_inheritedtext().clone__servo_text_decorations_in_effect()
+                }
+                _ => {
+                    SpecifiedValue {
+                        underline: None,
+                        overline: None,
+                        line_through: None,
                     }
                 }
+            };
+
+            if result.underline.is_none() {
+                result.underline = maybe(context.style().get_text().has_underline(), context)
+            }
+            if result.overline.is_none() {
+                result.overline = maybe(context.style().get_text().has_overline(), context)
+            }
+            if result.line_through.is_none() {
+                result.line_through = maybe(context.style().get_text().has_line_through(), context)
+            }
+
+            result
+        }
+
+        #[inline]
+        pub fn derive_from_text_decoration_line<Cx: TContext>(context: &mut Cx) {
+            let derived = derive(context);
+            context.mutate_style().mutate_inheritedtext().set__servo_text_decorations_in_effect(derived);
+        }
+
+        #[inline]
+        pub fn derive_from_text_decoration_color<Cx: TContext>(context: &mut Cx) {
+            let derived = derive(context);
+            context.mutate_style().mutate_inheritedtext().set__servo_text_decorations_in_effect(derived);
+        }
+
+        #[inline]
+        pub fn derive_from_display<Cx: TContext>(context: &mut Cx) {
+            let derived = derive(context);
+            context.mutate_style().mutate_inheritedtext().set__servo_text_decorations_in_effect(derived);
+        }
+    </%self:longhand>
+
+    <%self:single_keyword_computed name="white-space" values="normal pre nowrap pre-wrap pre-line">
+        use values::computed::ComputedValueAsSpecified;
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        impl SpecifiedValue {
+            pub fn allow_wrap(&self) -> bool {
+                match *self {
+                    SpecifiedValue::nowrap |
+                    SpecifiedValue::pre => false,
+                    SpecifiedValue::normal |
+                    SpecifiedValue::pre_wrap |
+                    SpecifiedValue::pre_line => true,
+                }
+            }
+
+            pub fn preserve_newlines(&self) -> bool {
+                match *self {
+                    SpecifiedValue::normal |
+                    SpecifiedValue::nowrap => false,
+                    SpecifiedValue::pre |
+                    SpecifiedValue::pre_wrap |
+                    SpecifiedValue::pre_line => true,
+                }
+            }
+
+            pub fn preserve_spaces(&self) -> bool {
+                match *self {
+                    SpecifiedValue::normal |
+                    SpecifiedValue::nowrap |
+                    SpecifiedValue::pre_line => false,
+                    SpecifiedValue::pre |
+                    SpecifiedValue::pre_wrap => true,
+                }
+            }
+        }
+    </%self:single_keyword_computed>
+
+    <%self:longhand name="tab-size">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::AuExtensionMethods;
+        use values::CSSFloat;
+
+        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Number(CSSFloat),
+            Length(specified::Length),
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Number(number) => write!(dest, "{}", number),
+                    SpecifiedValue::Length(length) => length.to_css(dest),
+                }
+            }
+        }
+
+        pub mod computed_value {
+            use app_units::Au;
+            use values::CSSFloat;
+
+            /// A number of spaces, or a length.
+            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+            pub enum T {
+                Number(CSSFloat),
+                Length(Au),
             }
         }
+
         impl ToCss for computed_value::T {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    % for weight in range(100, 901, 100):
-                        computed_value::T::Weight${weight} => dest.write_str("${weight}"),
-                    % endfor
+                    computed_value::T::Number(number) => write!(dest, "{}", number),
+                    computed_value::T::Length(length) => length.to_css(dest),
                 }
             }
         }
+
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::Weight400  // normal
+            computed_value::T::Number(8.0)
         }
 
         impl ToComputedValue for SpecifiedValue {
@@ -1872,263 +3895,279 @@ pub mod longhands {
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
                 match *self {
-                    % for weight in range(100, 901, 100):
-                        SpecifiedValue::Weight${weight} => computed_value::T::Weight${weight},
-                    % endfor
-                    SpecifiedValue::Bolder => match context.inherited_style().get_font().clone_font_weight() {
-                        computed_value::T::Weight100 => computed_value::T::Weight400,
-                        computed_value::T::Weight200 => computed_value::T::Weight400,
-                        computed_value::T::Weight300 => computed_value::T::Weight400,
-                        computed_value::T::Weight400 => computed_value::T::Weight700,
-                        computed_value::T::Weight500 => computed_value::T::Weight700,
-                        computed_value::T::Weight600 => computed_value::T::Weight900,
-                        computed_value::T::Weight700 => computed_value::T::Weight900,
-                        computed_value::T::Weight800 => computed_value::T::Weight900,
-                        computed_value::T::Weight900 => computed_value::T::Weight900,
-                    },
-                    SpecifiedValue::Lighter => match context.inherited_style().get_font().clone_font_weight() {
-                        computed_value::T::Weight100 => computed_value::T::Weight100,
-                        computed_value::T::Weight200 => computed_value::T::Weight100,
-                        computed_value::T::Weight300 => computed_value::T::Weight100,
-                        computed_value::T::Weight400 => computed_value::T::Weight100,
-                        computed_value::T::Weight500 => computed_value::T::Weight100,
-                        computed_value::T::Weight600 => computed_value::T::Weight400,
-                        computed_value::T::Weight700 => computed_value::T::Weight400,
-                        computed_value::T::Weight800 => computed_value::T::Weight700,
-                        computed_value::T::Weight900 => computed_value::T::Weight700,
-                    },
+                    SpecifiedValue::Number(number) => computed_value::T::Number(number),
+                    SpecifiedValue::Length(length) =>
+                        computed_value::T::Length(length.to_computed_value(context)),
+                }
+            }
+        }
+
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if let Ok(number) = input.try(specified::parse_number) {
+                if number < 0.0 {
+                    return Err(())
                 }
+                return Ok(SpecifiedValue::Number(number))
             }
+            specified::Length::parse_non_negative(input).map(SpecifiedValue::Length)
         }
     </%self:longhand>
 
-    <%self:longhand name="font-size">
-        use app_units::Au;
-        use cssparser::ToCss;
+    <%self:longhand name="text-shadow">
+        use cssparser::{self, ToCss};
         use std::fmt;
-        use values::FONT_MEDIUM_PX;
-        use values::specified::{LengthOrPercentage, Length, Percentage};
+        use values::AuExtensionMethods;
 
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                self.0.to_css(dest)
-            }
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue(Vec<SpecifiedTextShadow>);
+
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedTextShadow {
+            pub offset_x: specified::Length,
+            pub offset_y: specified::Length,
+            pub blur_radius: specified::Length,
+            pub color: Option<specified::CSSColor>,
         }
 
-        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-        pub struct SpecifiedValue(pub specified::LengthOrPercentage);
         pub mod computed_value {
             use app_units::Au;
-            pub type T = Au;
-        }
-        #[inline] pub fn get_initial_value() -> computed_value::T {
-            Au::from_px(FONT_MEDIUM_PX)
-        }
+            use cssparser::Color;
 
-        impl ToComputedValue for SpecifiedValue {
-            type ComputedValue = computed_value::T;
+            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+            pub struct T(pub Vec<TextShadow>);
 
-            #[inline]
-            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                match self.0 {
-                    LengthOrPercentage::Length(Length::FontRelative(value)) => {
-                        value.to_computed_value(context.inherited_style().get_font().clone_font_size(),
-                                                context.style().root_font_size())
-                    }
-                    LengthOrPercentage::Length(Length::ServoCharacterWidth(value)) => {
-                        value.to_computed_value(context.inherited_style().get_font().clone_font_size())
-                    }
-                    LengthOrPercentage::Length(l) => {
-                        l.to_computed_value(context)
-                    }
-                    LengthOrPercentage::Percentage(Percentage(value)) => {
-                        context.inherited_style().get_font().clone_font_size().scale_by(value)
-                    }
-                    LengthOrPercentage::Calc(calc) => {
-                        let calc = calc.to_computed_value(context);
-                        calc.length() + context.inherited_style().get_font().clone_font_size()
-                                               .scale_by(calc.percentage())
-                    }
-                }
+            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+            pub struct TextShadow {
+                pub offset_x: Au,
+                pub offset_y: Au,
+                pub blur_radius: Au,
+                pub color: Color,
             }
         }
-        /// <length> | <percentage> | <absolute-size> | <relative-size>
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            use values::specified::{Length, LengthOrPercentage};
 
-            input.try(specified::LengthOrPercentage::parse_non_negative)
-            .or_else(|()| {
-                let ident = try!(input.expect_ident());
-                specified::Length::from_str(&ident as &str)
-                    .ok_or(())
-                    .map(specified::LengthOrPercentage::Length)
-            })
-            .map(SpecifiedValue)
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                let mut iter = self.0.iter();
+                if let Some(shadow) = iter.next() {
+                    try!(shadow.to_css(dest));
+                } else {
+                    try!(dest.write_str("none"));
+                    return Ok(())
+                }
+                for shadow in iter {
+                    try!(dest.write_str(", "));
+                    try!(shadow.to_css(dest));
+                }
+                Ok(())
+            }
         }
-    </%self:longhand>
 
-    ${single_keyword("font-stretch",
-                     "normal ultra-condensed extra-condensed condensed semi-condensed semi-expanded \
-                     expanded extra-expanded ultra-expanded")}
+        impl ToCss for computed_value::TextShadow {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.offset_x.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.offset_y.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.blur_radius.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.color.to_css(dest));
+                Ok(())
+            }
+        }
 
-    // CSS 2.1, Section 16 - Text
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                let mut iter = self.0.iter();
+                if let Some(shadow) = iter.next() {
+                    try!(shadow.to_css(dest));
+                } else {
+                    try!(dest.write_str("none"));
+                    return Ok(())
+                }
+                for shadow in iter {
+                    try!(dest.write_str(", "));
+                    try!(shadow.to_css(dest));
+                }
+                Ok(())
+            }
+        }
 
-    ${new_style_struct("InheritedText", is_inherited=True, gecko_name="nsStyleText",
-                       additional_methods=[Method("clone__servo_text_decorations_in_effect",
-                                                  "longhands::_servo_text_decorations_in_effect::computed_value::T")])}
+        impl ToCss for SpecifiedTextShadow {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.offset_x.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.offset_y.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.blur_radius.to_css(dest));
 
-    <%self:longhand name="text-align">
-        pub use self::computed_value::T as SpecifiedValue;
-        use values::computed::ComputedValueAsSpecified;
-        impl ComputedValueAsSpecified for SpecifiedValue {}
-        pub mod computed_value {
-            macro_rules! define_text_align {
-                ( $( $name: ident ( $string: expr ) => $discriminant: expr, )+ ) => {
-                    define_css_keyword_enum! { T:
-                        $(
-                            $string => $name,
-                        )+
-                    }
-                    impl T {
-                        pub fn to_u32(self) -> u32 {
-                            match self {
-                                $(
-                                    T::$name => $discriminant,
-                                )+
-                            }
-                        }
-                        pub fn from_u32(discriminant: u32) -> Option<T> {
-                            match discriminant {
-                                $(
-                                    $discriminant => Some(T::$name),
-                                )+
-                                _ => None
-                            }
-                        }
-                    }
+                if let Some(ref color) = self.color {
+                    try!(dest.write_str(" "));
+                    try!(color.to_css(dest));
                 }
-            }
-            define_text_align! {
-                start("start") => 0,
-                end("end") => 1,
-                left("left") => 2,
-                right("right") => 3,
-                center("center") => 4,
-                justify("justify") => 5,
-                servo_center("-servo-center") => 6,
-                servo_left("-servo-left") => 7,
-                servo_right("-servo-right") => 8,
+                Ok(())
             }
         }
-        #[inline] pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::start
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(Vec::new())
         }
-        pub fn parse(_context: &ParserContext, input: &mut Parser)
-                     -> Result<SpecifiedValue, ()> {
-            computed_value::T::parse(input)
+
+        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                Ok(SpecifiedValue(Vec::new()))
+            } else {
+                input.parse_comma_separated(parse_one_text_shadow).map(SpecifiedValue)
+            }
         }
-    </%self:longhand>
 
-    <%self:longhand name="letter-spacing">
-        use cssparser::ToCss;
-        use std::fmt;
-        use values::AuExtensionMethods;
+        fn parse_one_text_shadow(input: &mut Parser) -> Result<SpecifiedTextShadow,()> {
+            use app_units::Au;
+            let mut lengths = [specified::Length::Absolute(Au(0)); 3];
+            let mut lengths_parsed = false;
+            let mut color = None;
 
-        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
-        pub enum SpecifiedValue {
-            Normal,
-            Specified(specified::Length),
-        }
+            loop {
+                if !lengths_parsed {
+                    if let Ok(value) = input.try(specified::Length::parse) {
+                        lengths[0] = value;
+                        let mut length_parsed_count = 1;
+                        while length_parsed_count < 3 {
+                            // The blur radius can't be negative.
+                            let parse_length: fn(&mut Parser) -> Result<specified::Length, ()> =
+                                if length_parsed_count == 2 {
+                                    specified::Length::parse_non_negative
+                                } else {
+                                    specified::Length::parse
+                                };
+                            if let Ok(value) = input.try(parse_length) {
+                                lengths[length_parsed_count] = value
+                            } else {
+                                break
+                            }
+                            length_parsed_count += 1;
+                        }
 
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match *self {
-                    SpecifiedValue::Normal => dest.write_str("normal"),
-                    SpecifiedValue::Specified(l) => l.to_css(dest),
+                        // The first two lengths must be specified.
+                        if length_parsed_count < 2 {
+                            return Err(())
+                        }
+
+                        lengths_parsed = true;
+                        continue
+                    }
+                }
+                if color.is_none() {
+                    if let Ok(value) = input.try(specified::CSSColor::parse) {
+                        color = Some(value);
+                        continue
+                    }
                 }
+                break
             }
-        }
-
-        pub mod computed_value {
-            use app_units::Au;
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Option<Au>);
-        }
 
-        impl ToCss for computed_value::T {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match self.0 {
-                    None => dest.write_str("normal"),
-                    Some(l) => l.to_css(dest),
-                }
+            // Lengths must be specified.
+            if !lengths_parsed {
+                return Err(())
             }
-        }
 
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(None)
+            Ok(SpecifiedTextShadow {
+                offset_x: lengths[0],
+                offset_y: lengths[1],
+                blur_radius: lengths[2],
+                color: color,
+            })
         }
 
         impl ToComputedValue for SpecifiedValue {
             type ComputedValue = computed_value::T;
 
-            #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                match *self {
-                    SpecifiedValue::Normal => computed_value::T(None),
-                    SpecifiedValue::Specified(l) =>
-                        computed_value::T(Some(l.to_computed_value(context)))
-                }
-            }
-        }
-
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
-                Ok(SpecifiedValue::Normal)
-            } else {
-                specified::Length::parse_non_negative(input).map(SpecifiedValue::Specified)
+                computed_value::T(self.0.iter().map(|value| {
+                    computed_value::TextShadow {
+                        offset_x: value.offset_x.to_computed_value(context),
+                        offset_y: value.offset_y.to_computed_value(context),
+                        blur_radius: value.blur_radius.to_computed_value(context),
+                        color: value.color
+                                    .as_ref()
+                                    .map(|color| color.to_computed_value(context))
+                                    .unwrap_or(cssparser::Color::CurrentColor),
+                    }
+                }).collect())
             }
         }
     </%self:longhand>
 
-    <%self:longhand name="word-spacing">
-        use cssparser::ToCss;
+    <%self:longhand name="text-emphasis-style">
+        use cssparser::{self, ToCss};
         use std::fmt;
-        use values::AuExtensionMethods;
+        use properties::style_struct_traits::TInheritedBox;
 
-        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+        define_css_keyword_enum!(FillMode: "filled" => Filled, "open" => Open);
+        define_css_keyword_enum!(ShapeKeyword:
+                                 "dot" => Dot,
+                                 "circle" => Circle,
+                                 "double-circle" => DoubleCircle,
+                                 "triangle" => Triangle,
+                                 "sesame" => Sesame);
+
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
         pub enum SpecifiedValue {
-            Normal,
-            Specified(specified::Length),  // FIXME(SimonSapin) support percentages
+            None,
+            /// At least one of the two is given. The other is filled in at computed-value time.
+            Keyword(Option<FillMode>, Option<ShapeKeyword>),
+            String(String),
         }
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    SpecifiedValue::Normal => dest.write_str("normal"),
-                    SpecifiedValue::Specified(l) => l.to_css(dest),
+                    SpecifiedValue::None => dest.write_str("none"),
+                    SpecifiedValue::Keyword(fill, shape) => {
+                        if let Some(fill) = fill {
+                            try!(fill.to_css(dest));
+                            if shape.is_some() {
+                                try!(dest.write_str(" "));
+                            }
+                        }
+                        if let Some(shape) = shape {
+                            try!(shape.to_css(dest));
+                        }
+                        Ok(())
+                    }
+                    SpecifiedValue::String(ref string) => cssparser::serialize_string(string, dest),
                 }
             }
         }
 
         pub mod computed_value {
-            use app_units::Au;
-            #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
-            pub struct T(pub Option<Au>);
+            pub use super::{FillMode, ShapeKeyword};
+
+            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+            pub enum T {
+                None,
+                Keyword(FillMode, ShapeKeyword),
+                String(String),
+            }
         }
 
         impl ToCss for computed_value::T {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                match self.0 {
-                    None => dest.write_str("normal"),
-                    Some(l) => l.to_css(dest),
+                match *self {
+                    computed_value::T::None => dest.write_str("none"),
+                    computed_value::T::Keyword(fill, shape) => {
+                        try!(fill.to_css(dest));
+                        try!(dest.write_str(" "));
+                        shape.to_css(dest)
+                    }
+                    computed_value::T::String(ref string) => cssparser::serialize_string(string, dest),
                 }
             }
         }
 
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(None)
+            computed_value::T::None
         }
 
         impl ToComputedValue for SpecifiedValue {
@@ -2136,256 +4175,99 @@ pub mod longhands {
 
             #[inline]
             fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                use properties::longhands::writing_mode::computed_value::T as WritingMode;
                 match *self {
-                    SpecifiedValue::Normal => computed_value::T(None),
-                    SpecifiedValue::Specified(l) =>
-                        computed_value::T(Some(l.to_computed_value(context)))
-                }
-            }
-        }
-
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
-                Ok(SpecifiedValue::Normal)
-            } else {
-                specified::Length::parse_non_negative(input).map(SpecifiedValue::Specified)
-            }
-        }
-    </%self:longhand>
-
-    ${predefined_type("text-indent", "LengthOrPercentage", "computed::LengthOrPercentage::Length(Au(0))")}
-
-    // Also known as "word-wrap" (which is more popular because of IE), but this is the preferred
-    // name per CSS-TEXT 6.2.
-    ${single_keyword("overflow-wrap", "normal break-word")}
-
-    // TODO(pcwalton): Support `word-break: keep-all` once we have better CJK support.
-    ${single_keyword("word-break", "normal break-all")}
-
-    ${single_keyword("text-overflow", "clip ellipsis")}
-
-    // TODO(pcwalton): Support `text-justify: distribute`.
-    ${single_keyword("text-justify", "auto none inter-word")}
-
-    ${new_style_struct("Text", is_inherited=False, gecko_name="nsStyleTextReset",
-                       additional_methods=[Method("has_underline", "bool"),
-                                           Method("has_overline", "bool"),
-                                           Method("has_line_through", "bool")])}
-
-    ${single_keyword("unicode-bidi", "normal embed isolate bidi-override isolate-override plaintext")}
-
-    <%self:longhand name="text-decoration" custom_cascade="True">
-        use cssparser::ToCss;
-        use std::fmt;
-        use values::computed::ComputedValueAsSpecified;
-        use properties::style_struct_traits::TInheritedText;
-
-        impl ComputedValueAsSpecified for SpecifiedValue {}
-
-        #[derive(PartialEq, Eq, Copy, Clone, Debug, HeapSizeOf)]
-        pub struct SpecifiedValue {
-            pub underline: bool,
-            pub overline: bool,
-            pub line_through: bool,
-            // 'blink' is accepted in the parser but ignored.
-            // Just not blinking the text is a conforming implementation per CSS 2.1.
-        }
-
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                let mut space = false;
-                if self.underline {
-                    try!(dest.write_str("underline"));
-                    space = true;
-                }
-                if self.overline {
-                    if space {
-                        try!(dest.write_str(" "));
-                    }
-                    try!(dest.write_str("overline"));
-                    space = true;
-                }
-                if self.line_through {
-                    if space {
-                        try!(dest.write_str(" "));
+                    SpecifiedValue::None => computed_value::T::None,
+                    SpecifiedValue::Keyword(fill, shape) => {
+                        // https://drafts.csswg.org/css-text-decor-3/#text-emphasis-style-property
+                        let shape = shape.unwrap_or_else(|| {
+                            match context.style().get_inheritedbox().clone_writing_mode() {
+                                WritingMode::horizontal_tb => ShapeKeyword::Circle,
+                                _ => ShapeKeyword::Sesame,
+                            }
+                        });
+                        computed_value::T::Keyword(fill.unwrap_or(FillMode::Filled), shape)
                     }
-                    try!(dest.write_str("line-through"));
-                }
-                Ok(())
-            }
-        }
-        pub mod computed_value {
-            pub type T = super::SpecifiedValue;
-            #[allow(non_upper_case_globals)]
-            pub const none: T = super::SpecifiedValue {
-                underline: false, overline: false, line_through: false
-            };
-        }
-        #[inline] pub fn get_initial_value() -> computed_value::T {
-            computed_value::none
-        }
-        /// none | [ underline || overline || line-through || blink ]
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
-            let mut result = SpecifiedValue {
-                underline: false, overline: false, line_through: false,
-            };
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                return Ok(result)
-            }
-            let mut blink = false;
-            let mut empty = true;
-            while let Ok(ident) = input.expect_ident() {
-                match_ignore_ascii_case! { ident,
-                    "underline" => if result.underline { return Err(()) }
-                                  else { empty = false; result.underline = true },
-                    "overline" => if result.overline { return Err(()) }
-                                  else { empty = false; result.overline = true },
-                    "line-through" => if result.line_through { return Err(()) }
-                                      else { empty = false; result.line_through = true },
-                    "blink" => if blink { return Err(()) }
-                               else { empty = false; blink = true },
-                    _ => break
+                    SpecifiedValue::String(ref string) => computed_value::T::String(string.clone()),
                 }
             }
-            if !empty { Ok(result) } else { Err(()) }
-        }
-
-        fn cascade_property_custom<C: ComputedValues>(
-                                   _declaration: &PropertyDeclaration,
-                                   _inherited_style: &C,
-                                   context: &mut computed::Context<C>,
-                                   _seen: &mut PropertyBitField,
-                                   _cacheable: &mut bool,
-                                   _error_reporter: &mut Box<ParseErrorReporter + Send>) {
-            longhands::_servo_text_decorations_in_effect::derive_from_text_decoration(context);
-        }
-    </%self:longhand>
-
-    ${switch_to_style_struct("InheritedText")}
-
-    <%self:longhand name="-servo-text-decorations-in-effect"
-                    derived_from="display text-decoration">
-        use cssparser::{RGBA, ToCss};
-        use std::fmt;
-
-        use values::computed::ComputedValueAsSpecified;
-        use properties::style_struct_traits::{TBox, TColor, TText};
-
-        impl ComputedValueAsSpecified for SpecifiedValue {}
-
-        #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
-        pub struct SpecifiedValue {
-            pub underline: Option<RGBA>,
-            pub overline: Option<RGBA>,
-            pub line_through: Option<RGBA>,
-        }
-
-        pub mod computed_value {
-            pub type T = super::SpecifiedValue;
-        }
-
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, _: &mut W) -> fmt::Result where W: fmt::Write {
-                // Web compat doesn't matter here.
-                Ok(())
-            }
-        }
-
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            SpecifiedValue {
-                underline: None,
-                overline: None,
-                line_through: None,
-            }
         }
 
-        fn maybe<Cx: TContext>(flag: bool, context: &Cx) -> Option<RGBA> {
-            if flag {
-                Some(context.style().get_color().clone_color())
-            } else {
-                None
+        /// none | [ [ filled | open ] || [ dot | circle | double-circle | triangle | sesame ] ] | <string>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SpecifiedValue::None)
             }
-        }
-
-        fn derive<Cx: TContext>(context: &Cx) -> computed_value::T {
-            // Start with no declarations if this is a block; otherwise, start with the
-            // declarations in effect and add in the text decorations that this inline specifies.
-            let mut result = match context.style().get_box().clone_display() {
-                super::display::computed_value::T::inline => {
-                    context.inherited_style().get_inheritedtext().clone__servo_text_decorations_in_effect()
+            if let Ok(string) = input.try(|input| input.expect_string()) {
+                return Ok(SpecifiedValue::String(string.into_owned()))
+            }
+            let mut fill = None;
+            let mut shape = None;
+            loop {
+                if fill.is_none() {
+                    if let Ok(value) = input.try(FillMode::parse) {
+                        fill = Some(value);
+                        continue
+                    }
                 }
-                _ => {
-                    SpecifiedValue {
-                        underline: None,
-                        overline: None,
-                        line_through: None,
+                if shape.is_none() {
+                    if let Ok(value) = input.try(ShapeKeyword::parse) {
+                        shape = Some(value);
+                        continue
                     }
                 }
-            };
-
-            if result.underline.is_none() {
-                result.underline = maybe(context.style().get_text().has_underline(), context)
-            }
-            if result.overline.is_none() {
-                result.overline = maybe(context.style().get_text().has_overline(), context)
+                break
             }
-            if result.line_through.is_none() {
-                result.line_through = maybe(context.style().get_text().has_line_through(), context)
+            if fill.is_none() && shape.is_none() {
+                return Err(())
             }
-
-            result
-        }
-
-        #[inline]
-        pub fn derive_from_text_decoration<Cx: TContext>(context: &mut Cx) {
-            let derived = derive(context);
-            context.mutate_style().mutate_inheritedtext().set__servo_text_decorations_in_effect(derived);
-        }
-
-        #[inline]
-        pub fn derive_from_display<Cx: TContext>(context: &mut Cx) {
-            let derived = derive(context);
-            context.mutate_style().mutate_inheritedtext().set__servo_text_decorations_in_effect(derived);
+            Ok(SpecifiedValue::Keyword(fill, shape))
         }
     </%self:longhand>
 
-    <%self:single_keyword_computed name="white-space" values="normal pre nowrap pre-wrap pre-line">
+    ${predefined_type("text-emphasis-color", "CSSColor", "::cssparser::Color::CurrentColor")}
+
+    <%self:longhand name="text-emphasis-position">
+        use cssparser::ToCss;
+        use std::fmt;
         use values::computed::ComputedValueAsSpecified;
+
+        define_css_keyword_enum!(VerticalPosition: "over" => Over, "under" => Under);
+        define_css_keyword_enum!(HorizontalPosition: "right" => Right, "left" => Left);
+
+        #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue(pub VerticalPosition, pub HorizontalPosition);
+
         impl ComputedValueAsSpecified for SpecifiedValue {}
 
-        impl SpecifiedValue {
-            pub fn allow_wrap(&self) -> bool {
-                match *self {
-                    SpecifiedValue::nowrap |
-                    SpecifiedValue::pre => false,
-                    SpecifiedValue::normal |
-                    SpecifiedValue::pre_wrap |
-                    SpecifiedValue::pre_line => true,
-                }
-            }
+        pub mod computed_value {
+            pub type T = super::SpecifiedValue;
+        }
 
-            pub fn preserve_newlines(&self) -> bool {
-                match *self {
-                    SpecifiedValue::normal |
-                    SpecifiedValue::nowrap => false,
-                    SpecifiedValue::pre |
-                    SpecifiedValue::pre_wrap |
-                    SpecifiedValue::pre_line => true,
-                }
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                try!(self.0.to_css(dest));
+                try!(dest.write_str(" "));
+                self.1.to_css(dest)
             }
+        }
 
-            pub fn preserve_spaces(&self) -> bool {
-                match *self {
-                    SpecifiedValue::normal |
-                    SpecifiedValue::nowrap |
-                    SpecifiedValue::pre_line => false,
-                    SpecifiedValue::pre |
-                    SpecifiedValue::pre_wrap => true,
-                }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            SpecifiedValue(VerticalPosition::Over, HorizontalPosition::Right)
+        }
+
+        /// [ over | under ] && [ right | left ]
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if let Ok(vertical) = input.try(VerticalPosition::parse) {
+                let horizontal = try!(HorizontalPosition::parse(input));
+                return Ok(SpecifiedValue(vertical, horizontal))
             }
+            let horizontal = try!(HorizontalPosition::parse(input));
+            let vertical = try!(VerticalPosition::parse(input));
+            Ok(SpecifiedValue(vertical, horizontal))
         }
-    </%self:single_keyword_computed>
+    </%self:longhand>
 
     // TODO(pcwalton): `full-width`
     ${single_keyword("text-transform", "none capitalize uppercase lowercase")}
@@ -2513,59 +4395,400 @@ pub mod longhands {
 
     ${single_keyword("box-sizing", "content-box border-box")}
 
-    ${new_style_struct("Pointing", is_inherited=True)}
+    // https://drafts.csswg.org/css-will-change/#will-change
+    <%self:longhand name="will-change">
+        use std::ascii::AsciiExt;
+        use values::computed::ComputedValueAsSpecified;
+
+        pub use self::computed_value::T as SpecifiedValue;
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use cssparser::{self, ToCss};
+            use std::fmt;
+
+            #[derive(Debug, Clone, PartialEq, Eq, HeapSizeOf)]
+            pub enum T {
+                Auto,
+                /// What the author expects to change, as written: `scroll-position`,
+                /// `contents`, or the names of properties, which needn't be known ones.
+                AnimateableFeatures(Vec<String>),
+            }
+
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match *self {
+                        T::Auto => dest.write_str("auto"),
+                        T::AnimateableFeatures(ref features) => {
+                            for (i, feature) in features.iter().enumerate() {
+                                if i != 0 {
+                                    try!(dest.write_str(", "));
+                                }
+                                try!(cssparser::serialize_identifier(feature, dest));
+                            }
+                            Ok(())
+                        }
+                    }
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Auto
+        }
+
+        /// Identifiers that can't name an animateable feature.
+        const EXCLUDED_IDENTS: &'static [&'static str] = &[
+            "will-change", "none", "all", "auto", "initial", "inherit", "unset", "default",
+        ];
+
+        // auto | [ scroll-position | contents | <custom-ident> ]#
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("auto")).is_ok() {
+                return Ok(SpecifiedValue::Auto)
+            }
+            input.parse_comma_separated(|input| {
+                let ident = try!(input.expect_ident());
+                if EXCLUDED_IDENTS.iter().any(|excluded| ident.eq_ignore_ascii_case(excluded)) {
+                    return Err(())
+                }
+                Ok(ident.into_owned())
+            }).map(SpecifiedValue::AnimateableFeatures)
+        }
+    </%self:longhand>
+
+    // https://drafts.csswg.org/css-contain/#contain-property
+    <%self:longhand name="contain">
+        use std::ascii::AsciiExt;
+        use values::computed::ComputedValueAsSpecified;
 
-    <%self:longhand name="cursor">
         pub use self::computed_value::T as SpecifiedValue;
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        pub mod computed_value {
+            use cssparser::ToCss;
+            use std::fmt;
+
+            pub const SIZE: u8 = 1 << 0;
+            pub const LAYOUT: u8 = 1 << 1;
+            pub const STYLE: u8 = 1 << 2;
+            pub const PAINT: u8 = 1 << 3;
+            /// What `strict` stands for.
+            pub const STRICT: u8 = SIZE | LAYOUT | STYLE | PAINT;
+            /// What `content` stands for.
+            pub const CONTENT: u8 = LAYOUT | STYLE | PAINT;
+
+            /// The kinds of containment that apply, as bits.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, HeapSizeOf)]
+            pub struct T(pub u8);
+
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match self.0 {
+                        0 => return dest.write_str("none"),
+                        STRICT => return dest.write_str("strict"),
+                        CONTENT => return dest.write_str("content"),
+                        _ => {}
+                    }
+                    let mut first = true;
+                    for &(bit, name) in &[(SIZE, "size"), (LAYOUT, "layout"), (STYLE, "style"), (PAINT, "paint")] {
+                        if self.0 & bit != 0 {
+                            if !first {
+                                try!(dest.write_str(" "));
+                            }
+                            first = false;
+                            try!(dest.write_str(name));
+                        }
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(0)
+        }
+
+        // none | strict | content | [ size || layout || style || paint ]
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            use self::computed_value::{CONTENT, LAYOUT, PAINT, SIZE, STRICT, STYLE};
+            let first = try!(input.expect_ident());
+            match_ignore_ascii_case! { first,
+                "none" => return Ok(SpecifiedValue(0)),
+                "strict" => return Ok(SpecifiedValue(STRICT)),
+                "content" => return Ok(SpecifiedValue(CONTENT)),
+                _ => {}
+            }
+            let mut bits = 0;
+            let mut ident = first;
+            loop {
+                let bit = match_ignore_ascii_case! { ident,
+                    "size" => SIZE,
+                    "layout" => LAYOUT,
+                    "style" => STYLE,
+                    "paint" => PAINT,
+                    _ => return Err(())
+                };
+                if bits & bit != 0 {
+                    return Err(())
+                }
+                bits |= bit;
+                ident = match input.try(|input| input.expect_ident()) {
+                    Ok(ident) => ident,
+                    Err(()) => break,
+                };
+            }
+            Ok(SpecifiedValue(bits))
+        }
+    </%self:longhand>
+
+    <%self:longhand name="touch-action">
+        use std::ascii::AsciiExt;
         use values::computed::ComputedValueAsSpecified;
 
+        pub use self::computed_value::T as SpecifiedValue;
+
         impl ComputedValueAsSpecified for SpecifiedValue {}
 
+        pub mod computed_value {
+            use cssparser::ToCss;
+            use std::fmt;
+
+            pub const AUTO: u8 = 1 << 0;
+            pub const NONE: u8 = 1 << 1;
+            pub const PAN_X: u8 = 1 << 2;
+            pub const PAN_Y: u8 = 1 << 3;
+            pub const MANIPULATION: u8 = 1 << 4;
+
+            /// The touch behaviors the browser may handle itself, as bits. Either exactly one of
+            /// `AUTO`, `NONE` and `MANIPULATION` is set, or some of `PAN_X` and `PAN_Y`.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, HeapSizeOf)]
+            pub struct T(pub u8);
+
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    match self.0 {
+                        AUTO => dest.write_str("auto"),
+                        NONE => dest.write_str("none"),
+                        MANIPULATION => dest.write_str("manipulation"),
+                        PAN_X => dest.write_str("pan-x"),
+                        PAN_Y => dest.write_str("pan-y"),
+                        _ => dest.write_str("pan-x pan-y"),
+                    }
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T(computed_value::AUTO)
+        }
+
+        // auto | none | [ pan-x || pan-y ] | manipulation
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            use self::computed_value::{AUTO, MANIPULATION, NONE, PAN_X, PAN_Y};
+            let first = try!(input.expect_ident());
+            let pan = match_ignore_ascii_case! { first,
+                "auto" => return Ok(SpecifiedValue(AUTO)),
+                "none" => return Ok(SpecifiedValue(NONE)),
+                "manipulation" => return Ok(SpecifiedValue(MANIPULATION)),
+                "pan-x" => (PAN_X, PAN_Y),
+                "pan-y" => (PAN_Y, PAN_X),
+                _ => return Err(())
+            };
+            let (bits, other) = pan;
+            let both = input.try(|input| {
+                let second = try!(input.expect_ident());
+                let matches = if other == PAN_X { "pan-x" } else { "pan-y" };
+                if second.eq_ignore_ascii_case(matches) { Ok(()) } else { Err(()) }
+            });
+            Ok(SpecifiedValue(if both.is_ok() { bits | other } else { bits }))
+        }
+    </%self:longhand>
+
+    // The -moz- values are Gecko's own, which its UA sheets use.
+    ${single_keyword("user-select",
+                     "auto text none element elements all toggle tri-state -moz-all -moz-none -moz-text")}
+
+    ${new_style_struct("Pointing", is_inherited=True,
+                       additional_methods=[Method("clone_pointer_events",
+                                                  "longhands::pointer_events::computed_value::T"),
+                                           Method("clone_cursor", "longhands::cursor::computed_value::T"),
+                                           Method("clone_fill", "longhands::fill::computed_value::T"),
+                                           Method("clone_stroke", "longhands::stroke::computed_value::T")])}
+
+    <%self:longhand name="cursor">
+        use cssparser::ToCss;
+        use std::fmt;
+        use url_value::{ComputedUrl, UrlValue};
+        use values::LocalToCss;
+
+        /// An image a cursor may be drawn with, along with where the point of the cursor is
+        /// in it if it says.
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedImage {
+            #[ignore_heap_size_of = "Arc"]
+            pub url: Arc<UrlValue>,
+            pub hotspot: Option<(f32, f32)>,
+        }
+
+        /// The images to try to draw the cursor with, in order, and the keyword for the cursor
+        /// to fall back to when none of them can be.
+        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+        pub struct SpecifiedValue {
+            pub images: Vec<SpecifiedImage>,
+            pub keyword: computed_value::Keyword,
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                for image in &self.images {
+                    try!(image.url.url().to_css(dest));
+                    try!(computed_value::hotspot_to_css(image.hotspot, dest));
+                    try!(dest.write_str(", "));
+                }
+                self.keyword.to_css(dest)
+            }
+        }
+
         pub mod computed_value {
             use cssparser::ToCss;
             use std::fmt;
             use style_traits::cursor::Cursor;
+            use url_value::ComputedUrl;
+            use values::LocalToCss;
 
             #[derive(Clone, PartialEq, Eq, Copy, Debug, HeapSizeOf)]
-            pub enum T {
+            pub enum Keyword {
                 AutoCursor,
                 SpecifiedCursor(Cursor),
             }
 
-            impl ToCss for T {
+            impl ToCss for Keyword {
                 fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                     match *self {
-                        T::AutoCursor => dest.write_str("auto"),
-                        T::SpecifiedCursor(c) => c.to_css(dest),
+                        Keyword::AutoCursor => dest.write_str("auto"),
+                        Keyword::SpecifiedCursor(c) => c.to_css(dest),
+                    }
+                }
+            }
+
+            /// Holds on to the embedder's request for the image, if it made one.
+            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+            pub struct Image {
+                #[ignore_heap_size_of = "Arc"]
+                pub url: ComputedUrl,
+                pub hotspot: Option<(f32, f32)>,
+            }
+
+            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+            pub struct T {
+                pub images: Vec<Image>,
+                pub keyword: Keyword,
+            }
+
+            pub fn hotspot_to_css<W>(hotspot: Option<(f32, f32)>, dest: &mut W) -> fmt::Result
+                                    where W: fmt::Write {
+                if let Some((x, y)) = hotspot {
+                    try!(dest.write_str(" "));
+                    try!(x.to_css(dest));
+                    try!(dest.write_str(" "));
+                    try!(y.to_css(dest));
+                }
+                Ok(())
+            }
+
+            impl ToCss for T {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    for image in &self.images {
+                        try!(image.url.url().to_css(dest));
+                        try!(hotspot_to_css(image.hotspot, dest));
+                        try!(dest.write_str(", "));
                     }
+                    self.keyword.to_css(dest)
+                }
+            }
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, _context: &Cx) -> computed_value::T {
+                computed_value::T {
+                    images: self.images.iter().map(|image| {
+                        let load = <Cx::ConcreteComputedValues as ComputedValues>::load_image;
+                        let request = image.url.image_request(load);
+                        computed_value::Image {
+                            url: ComputedUrl::new(image.url.clone(), request),
+                            hotspot: image.hotspot,
+                        }
+                    }).collect(),
+                    keyword: self.keyword,
                 }
             }
         }
 
         #[inline]
         pub fn get_initial_value() -> computed_value::T {
-            computed_value::T::AutoCursor
+            computed_value::T {
+                images: vec![],
+                keyword: computed_value::Keyword::AutoCursor,
+            }
         }
-        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+
+        /// Parses `[<url> [<x> <y>]?,]* <keyword>`. Unlike most lists, one bad entry makes the
+        /// whole of it invalid.
+        pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
             use std::ascii::AsciiExt;
             use style_traits::cursor::Cursor;
+            let mut images = vec![];
+            while let Ok(url) = input.try(|input| input.expect_url()) {
+                let hotspot = input.try(|input| -> Result<(f32, f32), ()> {
+                    Ok((try!(input.expect_number()), try!(input.expect_number())))
+                }).ok();
+                try!(input.expect_comma());
+                images.push(SpecifiedImage {
+                    url: Arc::new(context.parse_url_value(&url)),
+                    hotspot: hotspot,
+                });
+            }
             let ident = try!(input.expect_ident());
-            if ident.eq_ignore_ascii_case("auto") {
-                Ok(SpecifiedValue::AutoCursor)
+            let keyword = if ident.eq_ignore_ascii_case("auto") {
+                computed_value::Keyword::AutoCursor
             } else {
-                Cursor::from_css_keyword(&ident)
-                .map(SpecifiedValue::SpecifiedCursor)
-            }
+                computed_value::Keyword::SpecifiedCursor(try!(Cursor::from_css_keyword(&ident)))
+            };
+            Ok(SpecifiedValue {
+                images: images,
+                keyword: keyword,
+            })
         }
     </%self:longhand>
 
     // NB: `pointer-events: auto` (and use of `pointer-events` in anything that isn't SVG, in fact)
     // is nonstandard, slated for CSS4-UI.
-    // TODO(pcwalton): SVG-only values.
-    ${single_keyword("pointer-events", "auto none")}
+    //
+    // The SVG values parse on any element, but only tell SVG elements apart: everything else
+    // takes any of them for `auto`.
+    ${single_keyword("pointer-events",
+                     "auto none visiblePainted visibleFill visibleStroke visible painted fill stroke all")}
+
+    // Only SVG shapes are painted with these.
+    ${predefined_type("fill", "SVGPaint",
+                      "computed_value::T::Color(::cssparser::Color::RGBA(" +
+                      "::cssparser::RGBA { red: 0., green: 0., blue: 0., alpha: 1. })) /* black */")}
+    ${predefined_type("stroke", "SVGPaint", "computed_value::T::None")}
 
 
-    ${new_style_struct("Column", is_inherited=False, gecko_name="nsStyleColumn")}
+    ${new_style_struct("Column", is_inherited=False, gecko_name="nsStyleColumn",
+                       additional_methods=[Method("column_rule_is_none_or_hidden_and_has_nonzero_width", "bool")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in COLUMN_LONGHANDS])}
 
     <%self:longhand name="column-width" experimental="True">
         use cssparser::ToCss;
@@ -2756,8 +4979,71 @@ pub mod longhands {
         }
     </%self:longhand>
 
+    // Column rules are drawn like borders, so their width is made zero when they have no style.
+    <%self:longhand name="column-rule-width" experimental="True">
+        use app_units::Au;
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::AuExtensionMethods;
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                self.0.to_css(dest)
+            }
+        }
+
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            specified::parse_border_width(input).map(SpecifiedValue)
+        }
+        #[derive(Debug, Clone, PartialEq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub specified::Length);
+        pub mod computed_value {
+            use app_units::Au;
+            pub type T = Au;
+        }
+        pub use super::border_top_width::get_initial_value;
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                self.0.to_computed_value(context)
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="column-rule-style" experimental="True">
+        pub use values::specified::BorderStyle as SpecifiedValue;
+        pub fn get_initial_value() -> SpecifiedValue { SpecifiedValue::none }
+        pub mod computed_value {
+            pub use values::specified::BorderStyle as T;
+        }
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            SpecifiedValue::parse(input)
+        }
+    </%self:longhand>
+
+    <%self:longhand name="column-rule-color" experimental="True">
+        pub type SpecifiedValue = specified::CSSColor;
+        pub mod computed_value {
+            pub use values::computed::CSSColor as T;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T { ::cssparser::Color::CurrentColor }
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            specified::CSSColor::parse(input)
+        }
+    </%self:longhand>
+
     // Box-shadow, etc.
-    ${new_style_struct("Effects", is_inherited=False)}
+    ${new_style_struct("Effects", is_inherited=False,
+                       additional_methods=[Method("clone_transform",
+                                                  "longhands::transform::computed_value::T"),
+                                           Method("clone_isolation",
+                                                  "longhands::isolation::computed_value::T"),
+                                           Method("clone_box_shadow",
+                                                  "longhands::box_shadow::computed_value::T")] +
+                                          [Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in EFFECTS_STYLE_STRUCT_LONGHANDS])}
 
     <%self:longhand name="opacity">
         use cssparser::ToCss;
@@ -2840,13 +5126,13 @@ pub mod longhands {
                 if self.inset {
                     try!(dest.write_str("inset "));
                 }
-                try!(self.blur_radius.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.spread_radius.to_css(dest));
-                try!(dest.write_str(" "));
                 try!(self.offset_x.to_css(dest));
                 try!(dest.write_str(" "));
                 try!(self.offset_y.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.blur_radius.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.spread_radius.to_css(dest));
 
                 if let Some(ref color) = self.color {
                     try!(dest.write_str(" "));
@@ -2897,14 +5183,14 @@ pub mod longhands {
                 if self.inset {
                     try!(dest.write_str("inset "));
                 }
-                try!(self.blur_radius.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.spread_radius.to_css(dest));
-                try!(dest.write_str(" "));
                 try!(self.offset_x.to_css(dest));
                 try!(dest.write_str(" "));
                 try!(self.offset_y.to_css(dest));
                 try!(dest.write_str(" "));
+                try!(self.blur_radius.to_css(dest));
+                try!(dest.write_str(" "));
+                try!(self.spread_radius.to_css(dest));
+                try!(dest.write_str(" "));
                 try!(self.color.to_css(dest));
                 Ok(())
             }
@@ -2941,7 +5227,7 @@ pub mod longhands {
                 spread_radius: value.spread_radius.to_computed_value(context),
                 color: value.color
                             .as_ref()
-                            .map(|color| color.parsed)
+                            .map(|color| color.to_computed_value(context))
                             .unwrap_or(cssparser::Color::CurrentColor),
                 inset: value.inset,
             }
@@ -2966,7 +5252,14 @@ pub mod longhands {
                         lengths[0] = value;
                         let mut length_parsed_count = 1;
                         while length_parsed_count < 4 {
-                            if let Ok(value) = input.try(specified::Length::parse) {
+                            // The blur radius can't be negative, though the spread radius can.
+                            let parse_length: fn(&mut Parser) -> Result<specified::Length, ()> =
+                                if length_parsed_count == 2 {
+                                    specified::Length::parse_non_negative
+                                } else {
+                                    specified::Length::parse
+                                };
+                            if let Ok(value) = input.try(parse_length) {
                                 lengths[length_parsed_count] = value
                             } else {
                                 break
@@ -3161,184 +5454,6 @@ pub mod longhands {
         }
     </%self:longhand>
 
-    <%self:longhand name="text-shadow">
-        use cssparser::{self, ToCss};
-        use std::fmt;
-        use values::AuExtensionMethods;
-
-        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-        pub struct SpecifiedValue(Vec<SpecifiedTextShadow>);
-
-        #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-        pub struct SpecifiedTextShadow {
-            pub offset_x: specified::Length,
-            pub offset_y: specified::Length,
-            pub blur_radius: specified::Length,
-            pub color: Option<specified::CSSColor>,
-        }
-
-        pub mod computed_value {
-            use app_units::Au;
-            use cssparser::Color;
-
-            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-            pub struct T(pub Vec<TextShadow>);
-
-            #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
-            pub struct TextShadow {
-                pub offset_x: Au,
-                pub offset_y: Au,
-                pub blur_radius: Au,
-                pub color: Color,
-            }
-        }
-
-        impl ToCss for computed_value::T {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                let mut iter = self.0.iter();
-                if let Some(shadow) = iter.next() {
-                    try!(shadow.to_css(dest));
-                } else {
-                    try!(dest.write_str("none"));
-                    return Ok(())
-                }
-                for shadow in iter {
-                    try!(dest.write_str(", "));
-                    try!(shadow.to_css(dest));
-                }
-                Ok(())
-            }
-        }
-
-        impl ToCss for computed_value::TextShadow {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                try!(self.offset_x.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.offset_y.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.blur_radius.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.color.to_css(dest));
-                Ok(())
-            }
-        }
-
-        impl ToCss for SpecifiedValue {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                let mut iter = self.0.iter();
-                if let Some(shadow) = iter.next() {
-                    try!(shadow.to_css(dest));
-                } else {
-                    try!(dest.write_str("none"));
-                    return Ok(())
-                }
-                for shadow in iter {
-                    try!(dest.write_str(", "));
-                    try!(shadow.to_css(dest));
-                }
-                Ok(())
-            }
-        }
-
-        impl ToCss for SpecifiedTextShadow {
-            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
-                try!(self.offset_x.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.offset_y.to_css(dest));
-                try!(dest.write_str(" "));
-                try!(self.blur_radius.to_css(dest));
-
-                if let Some(ref color) = self.color {
-                    try!(dest.write_str(" "));
-                    try!(color.to_css(dest));
-                }
-                Ok(())
-            }
-        }
-
-        #[inline]
-        pub fn get_initial_value() -> computed_value::T {
-            computed_value::T(Vec::new())
-        }
-
-        pub fn parse(_: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue,()> {
-            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
-                Ok(SpecifiedValue(Vec::new()))
-            } else {
-                input.parse_comma_separated(parse_one_text_shadow).map(SpecifiedValue)
-            }
-        }
-
-        fn parse_one_text_shadow(input: &mut Parser) -> Result<SpecifiedTextShadow,()> {
-            use app_units::Au;
-            let mut lengths = [specified::Length::Absolute(Au(0)); 3];
-            let mut lengths_parsed = false;
-            let mut color = None;
-
-            loop {
-                if !lengths_parsed {
-                    if let Ok(value) = input.try(specified::Length::parse) {
-                        lengths[0] = value;
-                        let mut length_parsed_count = 1;
-                        while length_parsed_count < 3 {
-                            if let Ok(value) = input.try(specified::Length::parse) {
-                                lengths[length_parsed_count] = value
-                            } else {
-                                break
-                            }
-                            length_parsed_count += 1;
-                        }
-
-                        // The first two lengths must be specified.
-                        if length_parsed_count < 2 {
-                            return Err(())
-                        }
-
-                        lengths_parsed = true;
-                        continue
-                    }
-                }
-                if color.is_none() {
-                    if let Ok(value) = input.try(specified::CSSColor::parse) {
-                        color = Some(value);
-                        continue
-                    }
-                }
-                break
-            }
-
-            // Lengths must be specified.
-            if !lengths_parsed {
-                return Err(())
-            }
-
-            Ok(SpecifiedTextShadow {
-                offset_x: lengths[0],
-                offset_y: lengths[1],
-                blur_radius: lengths[2],
-                color: color,
-            })
-        }
-
-        impl ToComputedValue for SpecifiedValue {
-            type ComputedValue = computed_value::T;
-
-            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
-                computed_value::T(self.0.iter().map(|value| {
-                    computed_value::TextShadow {
-                        offset_x: value.offset_x.to_computed_value(context),
-                        offset_y: value.offset_y.to_computed_value(context),
-                        blur_radius: value.blur_radius.to_computed_value(context),
-                        color: value.color
-                                    .as_ref()
-                                    .map(|color| color.parsed)
-                                    .unwrap_or(cssparser::Color::CurrentColor),
-                    }
-                }).collect())
-            }
-        }
-    </%self:longhand>
-
     <%self:longhand name="filter">
         //pub use self::computed_value::T as SpecifiedValue;
         use cssparser::ToCss;
@@ -3576,12 +5691,19 @@ pub mod longhands {
         use values::CSSFloat;
 
         use cssparser::ToCss;
+        use euclid::size::Size2D;
         use std::fmt;
 
         pub mod computed_value {
+            use app_units::Au;
+            use cssparser::ToCss;
+            use euclid::size::Size2D;
+            use std::fmt;
             use values::CSSFloat;
             use values::computed;
 
+            /// A matrix in the order `matrix3d()` lists its values in, which transforms row
+            /// vectors: the translation is in m41, m42 and m43.
             #[derive(Clone, Copy, Debug, PartialEq, HeapSizeOf)]
             pub struct ComputedMatrix {
                 pub m11: CSSFloat, pub m12: CSSFloat, pub m13: CSSFloat, pub m14: CSSFloat,
@@ -3599,6 +5721,56 @@ pub mod longhands {
                         m41: 0.0, m42: 0.0, m43: 0.0, m44: 1.0
                     }
                 }
+
+                /// Whether `matrix()` can say what this says.
+                pub fn is_2d(&self) -> bool {
+                    self.m13 == 0. && self.m14 == 0. && self.m23 == 0. && self.m24 == 0. &&
+                    self.m31 == 0. && self.m32 == 0. && self.m33 == 1. && self.m34 == 0. &&
+                    self.m43 == 0. && self.m44 == 1.
+                }
+
+                fn from_rows(rows: [[CSSFloat; 4]; 4]) -> ComputedMatrix {
+                    ComputedMatrix {
+                        m11: rows[0][0], m12: rows[0][1], m13: rows[0][2], m14: rows[0][3],
+                        m21: rows[1][0], m22: rows[1][1], m23: rows[1][2], m24: rows[1][3],
+                        m31: rows[2][0], m32: rows[2][1], m33: rows[2][2], m34: rows[2][3],
+                        m41: rows[3][0], m42: rows[3][1], m43: rows[3][2], m44: rows[3][3],
+                    }
+                }
+
+                fn rows(&self) -> [[CSSFloat; 4]; 4] {
+                    [[self.m11, self.m12, self.m13, self.m14],
+                     [self.m21, self.m22, self.m23, self.m24],
+                     [self.m31, self.m32, self.m33, self.m34],
+                     [self.m41, self.m42, self.m43, self.m44]]
+                }
+
+                /// The transform that applies `self` first and then `other`.
+                pub fn then(&self, other: &ComputedMatrix) -> ComputedMatrix {
+                    let (a, b) = (self.rows(), other.rows());
+                    let mut result = [[0.; 4]; 4];
+                    for i in 0..4 {
+                        for j in 0..4 {
+                            for k in 0..4 {
+                                result[i][j] += a[i][k] * b[k][j];
+                            }
+                        }
+                    }
+                    ComputedMatrix::from_rows(result)
+                }
+            }
+
+            impl ToCss for ComputedMatrix {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    let m = self;
+                    if m.is_2d() {
+                        write!(dest, "matrix({}, {}, {}, {}, {}, {})", m.m11, m.m12, m.m21, m.m22, m.m41, m.m42)
+                    } else {
+                        write!(dest, "matrix3d({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
+                               m.m11, m.m12, m.m13, m.m14, m.m21, m.m22, m.m23, m.m24,
+                               m.m31, m.m32, m.m33, m.m34, m.m41, m.m42, m.m43, m.m44)
+                    }
+                }
             }
 
             #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
@@ -3613,8 +5785,78 @@ pub mod longhands {
                 Perspective(computed::Length),
             }
 
+            impl ComputedOperation {
+                /// The matrix the operation amounts to on a box of the given size, which
+                /// percentages in translations are relative to.
+                pub fn to_matrix(&self, reference_box: Size2D<Au>) -> ComputedMatrix {
+                    let mut matrix = ComputedMatrix::identity();
+                    match *self {
+                        ComputedOperation::Matrix(m) => matrix = m,
+                        ComputedOperation::Skew(ax, ay) => {
+                            matrix.m12 = ay.radians().tan();
+                            matrix.m21 = ax.radians().tan();
+                        }
+                        ComputedOperation::Translate(tx, ty, tz) => {
+                            matrix.m41 = resolve(tx, reference_box.width).to_f32_px();
+                            matrix.m42 = resolve(ty, reference_box.height).to_f32_px();
+                            matrix.m43 = tz.to_f32_px();
+                        }
+                        ComputedOperation::Scale(sx, sy, sz) => {
+                            matrix.m11 = sx;
+                            matrix.m22 = sy;
+                            matrix.m33 = sz;
+                        }
+                        ComputedOperation::Rotate(ax, ay, az, theta) => {
+                            // https://drafts.csswg.org/css-transforms/#Rotate3dDefined
+                            let length = (ax * ax + ay * ay + az * az).sqrt();
+                            if length == 0. {
+                                return matrix
+                            }
+                            let (x, y, z) = (ax / length, ay / length, az / length);
+                            let half_angle = theta.radians() / 2.;
+                            let sc = half_angle.sin() * half_angle.cos();
+                            let sq = half_angle.sin() * half_angle.sin();
+                            matrix.m11 = 1. - 2. * (y * y + z * z) * sq;
+                            matrix.m12 = 2. * (x * y * sq + z * sc);
+                            matrix.m13 = 2. * (x * z * sq - y * sc);
+                            matrix.m21 = 2. * (x * y * sq - z * sc);
+                            matrix.m22 = 1. - 2. * (x * x + z * z) * sq;
+                            matrix.m23 = 2. * (y * z * sq + x * sc);
+                            matrix.m31 = 2. * (x * z * sq + y * sc);
+                            matrix.m32 = 2. * (y * z * sq - x * sc);
+                            matrix.m33 = 1. - 2. * (x * x + y * y) * sq;
+                        }
+                        ComputedOperation::Perspective(d) => {
+                            // A depth of zero or less does nothing.
+                            if d > Au(0) {
+                                matrix.m34 = -1. / d.to_f32_px();
+                            }
+                        }
+                    }
+                    matrix
+                }
+            }
+
+            /// A translation, with the percentage of the box it adds, if any, resolved.
+            fn resolve(length: computed::LengthOrPercentage, basis: Au) -> Au {
+                let calc = computed::CalcLengthOrPercentage::from(length);
+                calc.length() + basis.scale_by(calc.percentage())
+            }
+
             #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
             pub struct T(pub Option<Vec<ComputedOperation>>);
+
+            impl T {
+                /// The operations multiplied out for a box of the given size, or None for `none`.
+                pub fn to_matrix(&self, reference_box: Size2D<Au>) -> Option<ComputedMatrix> {
+                    self.0.as_ref().map(|operations| {
+                        // Each operation applies before the ones to its left.
+                        operations.iter().rev().fold(ComputedMatrix::identity(), |matrix, operation| {
+                            matrix.then(&operation.to_matrix(reference_box))
+                        })
+                    })
+                }
+            }
         }
 
         pub use self::computed_value::ComputedMatrix as SpecifiedMatrix;
@@ -3670,23 +5912,28 @@ pub mod longhands {
             Perspective(specified::Length),
         }
 
+        /// Serializes as the matrix the operations amount to, for getComputedStyle. Computed
+        /// values don't know the size of the box percentages are relative to, so those resolve
+        /// against an empty one, as for elements without a box.
         impl ToCss for computed_value::T {
-            fn to_css<W>(&self, _: &mut W) -> fmt::Result where W: fmt::Write {
-                // TODO(pcwalton)
-                Ok(())
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match self.to_matrix(Size2D::new(Au(0), Au(0))) {
+                    Some(matrix) => matrix.to_css(dest),
+                    None => dest.write_str("none"),
+                }
             }
         }
 
         impl ToCss for SpecifiedOperation {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                 match *self {
-                    // todo(gw): implement serialization for transform
-                    // types other than translate.
-                    SpecifiedOperation::Matrix(_m) => {
-                        Ok(())
-                    }
-                    SpecifiedOperation::Skew(_sx, _sy) => {
-                        Ok(())
+                    SpecifiedOperation::Matrix(ref m) => m.to_css(dest),
+                    SpecifiedOperation::Skew(sx, sy) => {
+                        try!(dest.write_str("skew("));
+                        try!(sx.to_css(dest));
+                        try!(dest.write_str(", "));
+                        try!(sy.to_css(dest));
+                        dest.write_str(")")
                     }
                     SpecifiedOperation::Translate(kind, tx, ty, tz) => {
                         match kind {
@@ -3723,14 +5970,26 @@ pub mod longhands {
                             }
                         }
                     }
-                    SpecifiedOperation::Scale(_sx, _sy, _sz) => {
-                        Ok(())
+                    SpecifiedOperation::Scale(sx, sy, sz) => {
+                        if sz == 1. {
+                            write!(dest, "scale({}, {})", sx, sy)
+                        } else {
+                            write!(dest, "scale3d({}, {}, {})", sx, sy, sz)
+                        }
                     }
-                    SpecifiedOperation::Rotate(_ax, _ay, _az, _angle) => {
-                        Ok(())
+                    SpecifiedOperation::Rotate(ax, ay, az, theta) => {
+                        if (ax, ay, az) == (0., 0., 1.) {
+                            try!(dest.write_str("rotate("));
+                        } else {
+                            try!(write!(dest, "rotate3d({}, {}, {}, ", ax, ay, az));
+                        }
+                        try!(theta.to_css(dest));
+                        dest.write_str(")")
                     }
-                    SpecifiedOperation::Perspective(_p) => {
-                        Ok(())
+                    SpecifiedOperation::Perspective(d) => {
+                        try!(dest.write_str("perspective("));
+                        try!(d.to_css(dest));
+                        dest.write_str(")")
                     }
                 }
             }
@@ -3741,6 +6000,9 @@ pub mod longhands {
 
         impl ToCss for SpecifiedValue {
             fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                if self.0.is_empty() {
+                    return dest.write_str("none")
+                }
                 let mut first = true;
                 for operation in &self.0 {
                     if !first {
@@ -4270,6 +6532,9 @@ pub mod longhands {
                         color-burn hard-light soft-light difference exclusion hue
                         saturation color luminosity""")}
 
+    // https://drafts.fxtf.org/compositing/#isolation
+    ${single_keyword("isolation", "auto isolate")}
+
     <%self:longhand name="image-rendering">
 
         pub mod computed_value {
@@ -4326,7 +6591,15 @@ pub mod longhands {
     </%self:longhand>
 
     ${new_style_struct("Animation", is_inherited=False,
-                       additional_methods=[Method("transition_count", return_type="usize")])}
+                       additional_methods=[Method("transition_count", return_type="usize"),
+                                           Method("clone_transition_property",
+                                                  "longhands::transition_property::computed_value::T"),
+                                           Method("clone_transition_duration",
+                                                  "longhands::transition_duration::computed_value::T"),
+                                           Method("clone_transition_delay",
+                                                  "longhands::transition_delay::computed_value::T"),
+                                           Method("clone_transition_timing_function",
+                                                  "longhands::transition_timing_function::computed_value::T")])}
 
     // TODO(pcwalton): Multiple transitions.
     <%self:longhand name="transition-duration">
@@ -4393,7 +6666,7 @@ pub mod longhands {
     // TODO(pcwalton): Lots more timing functions.
     // TODO(pcwalton): Multiple transitions.
     <%self:longhand name="transition-timing-function">
-        use self::computed_value::{StartEnd, TransitionTimingFunction};
+        use self::computed_value::{StepPosition, TransitionTimingFunction};
 
         use euclid::point::Point2D;
 
@@ -4407,13 +6680,6 @@ pub mod longhands {
             x: 0.25,
             y: 1.0,
         });
-        static LINEAR: TransitionTimingFunction = TransitionTimingFunction::CubicBezier(Point2D {
-            x: 0.0,
-            y: 0.0,
-        }, Point2D {
-            x: 1.0,
-            y: 1.0,
-        });
         static EASE_IN: TransitionTimingFunction = TransitionTimingFunction::CubicBezier(Point2D {
             x: 0.42,
             y: 0.0,
@@ -4437,11 +6703,12 @@ pub mod longhands {
                 y: 1.0,
             });
         static STEP_START: TransitionTimingFunction =
-            TransitionTimingFunction::Steps(1, StartEnd::Start);
+            TransitionTimingFunction::Steps(1, StepPosition::Start);
         static STEP_END: TransitionTimingFunction =
-            TransitionTimingFunction::Steps(1, StartEnd::End);
+            TransitionTimingFunction::Steps(1, StepPosition::End);
 
         pub mod computed_value {
+            use bezier::Bezier;
             use cssparser::ToCss;
             use euclid::point::Point2D;
             use std::fmt;
@@ -4450,13 +6717,78 @@ pub mod longhands {
 
             #[derive(Copy, Clone, Debug, PartialEq, HeapSizeOf)]
             pub enum TransitionTimingFunction {
+                Linear,
                 CubicBezier(Point2D<f32>, Point2D<f32>),
-                Steps(u32, StartEnd),
+                Steps(u32, StepPosition),
+            }
+
+            impl TransitionTimingFunction {
+                /// The output progress for `input` progress, as CSS Easing § 2 computes it.
+                /// `before_flag` says whether the animation is in its before phase, which
+                /// matters to steps that jump at the start. The x of a point on a curve is
+                /// solved for to within `epsilon`.
+                pub fn calculate_output(&self, input: f64, before_flag: bool, epsilon: f64) -> f64 {
+                    match *self {
+                        TransitionTimingFunction::Linear => input,
+                        TransitionTimingFunction::CubicBezier(p1, p2) => {
+                            let (x1, y1, x2, y2) = (p1.x as f64, p1.y as f64, p2.x as f64, p2.y as f64);
+                            // Past either end, the curve goes on along its tangent there. A
+                            // control point with the end's x gives no slope, so the other one
+                            // is tried, and the output is flat if neither does.
+                            if input < 0. {
+                                let slope = if x1 > 0. {
+                                    y1 / x1
+                                } else if y1 == 0. && x2 > 0. {
+                                    y2 / x2
+                                } else {
+                                    0.
+                                };
+                                return input * slope
+                            }
+                            if input > 1. {
+                                let slope = if x2 < 1. {
+                                    (y2 - 1.) / (x2 - 1.)
+                                } else if y2 == 1. && x1 < 1. {
+                                    (y1 - 1.) / (x1 - 1.)
+                                } else {
+                                    0.
+                                };
+                                return 1. + (input - 1.) * slope
+                            }
+                            Bezier::new(Point2D::new(x1, y1), Point2D::new(x2, y2)).solve(input, epsilon)
+                        }
+                        TransitionTimingFunction::Steps(steps, position) => {
+                            let steps = steps as f64;
+                            let mut current_step = (input * steps).floor();
+                            if position == StepPosition::Start || position == StepPosition::JumpBoth {
+                                current_step += 1.;
+                            }
+                            // Right on a jump while going backwards, the step before it is
+                            // still the one in effect.
+                            if before_flag && (input * steps).fract() == 0. {
+                                current_step -= 1.;
+                            }
+                            if input >= 0. && current_step < 0. {
+                                current_step = 0.;
+                            }
+                            let jumps = match position {
+                                StepPosition::JumpBoth => steps + 1.,
+                                StepPosition::JumpNone => steps - 1.,
+                                StepPosition::Start | StepPosition::End => steps,
+                            };
+                            if input <= 1. && current_step > jumps {
+                                current_step = jumps;
+                            }
+                            current_step / jumps
+                        }
+                    }
+                }
             }
 
             impl ToCss for TransitionTimingFunction {
                 fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                     match *self {
+                        TransitionTimingFunction::Linear => dest.write_str("linear"),
                         TransitionTimingFunction::CubicBezier(p1, p2) => {
                             try!(dest.write_str("cubic-bezier("));
                             try!(p1.x.to_css(dest));
@@ -4468,28 +6800,34 @@ pub mod longhands {
                             try!(p2.y.to_css(dest));
                             dest.write_str(")")
                         }
-                        TransitionTimingFunction::Steps(steps, start_end) => {
+                        TransitionTimingFunction::Steps(steps, position) => {
                             try!(dest.write_str("steps("));
                             try!(steps.to_css(dest));
                             try!(dest.write_str(", "));
-                            try!(start_end.to_css(dest));
+                            try!(position.to_css(dest));
                             dest.write_str(")")
                         }
                     }
                 }
             }
 
+            /// Where the jumps of a `steps()` function are. `start` and `end` are the same as
+            /// `jump-start` and `jump-end`.
             #[derive(Copy, Clone, Debug, PartialEq, HeapSizeOf)]
-            pub enum StartEnd {
+            pub enum StepPosition {
                 Start,
                 End,
+                JumpNone,
+                JumpBoth,
             }
 
-            impl ToCss for StartEnd {
+            impl ToCss for StepPosition {
                 fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
                     match *self {
-                        StartEnd::Start => dest.write_str("start"),
-                        StartEnd::End => dest.write_str("end"),
+                        StepPosition::Start => dest.write_str("start"),
+                        StepPosition::End => dest.write_str("end"),
+                        StepPosition::JumpNone => dest.write_str("jump-none"),
+                        StepPosition::JumpBoth => dest.write_str("jump-both"),
                     }
                 }
             }
@@ -4548,23 +6886,37 @@ pub mod longhands {
                             p2y = try!(specified::parse_number(input));
                             Ok(())
                         }));
+                        // The curve has to be a function of time, so its x can't leave [0, 1].
+                        if p1x < 0. || p1x > 1. || p2x < 0. || p2x > 1. {
+                            return Err(())
+                        }
                         let (p1, p2) = (Point2D::new(p1x, p1y), Point2D::new(p2x, p2y));
                         Ok(TransitionTimingFunction::CubicBezier(p1, p2))
                     },
                     "steps" => {
-                        let (mut step_count, mut start_end) = (0, computed_value::StartEnd::Start);
+                        let (mut step_count, mut position) = (0, StepPosition::End);
                         try!(input.parse_nested_block(|input| {
                             step_count = try!(specified::parse_integer(input));
-                            try!(input.expect_comma());
-                            start_end = try!(match_ignore_ascii_case! {
-                                try!(input.expect_ident()),
-                                "start" => Ok(computed_value::StartEnd::Start),
-                                "end" => Ok(computed_value::StartEnd::End),
-                                _ => Err(())
-                            });
+                            if input.try(|input| input.expect_comma()).is_ok() {
+                                position = try!(match_ignore_ascii_case! {
+                                    try!(input.expect_ident()),
+                                    "start" => Ok(StepPosition::Start),
+                                    "end" => Ok(StepPosition::End),
+                                    "jump-start" => Ok(StepPosition::Start),
+                                    "jump-end" => Ok(StepPosition::End),
+                                    "jump-none" => Ok(StepPosition::JumpNone),
+                                    "jump-both" => Ok(StepPosition::JumpBoth),
+                                    _ => Err(())
+                                });
+                            }
                             Ok(())
                         }));
-                        Ok(TransitionTimingFunction::Steps(step_count as u32, start_end))
+                        // Without a jump at either end, a single step would never move.
+                        let min_steps = if position == StepPosition::JumpNone { 2 } else { 1 };
+                        if step_count < min_steps {
+                            return Err(())
+                        }
+                        Ok(TransitionTimingFunction::Steps(step_count as u32, position))
                     },
                     _ => Err(())
                 }
@@ -4572,7 +6924,7 @@ pub mod longhands {
             match_ignore_ascii_case! {
                 try!(input.expect_ident()),
                 "ease" => Ok(EASE),
-                "linear" => Ok(LINEAR),
+                "linear" => Ok(TransitionTimingFunction::Linear),
                 "ease-in" => Ok(EASE_IN),
                 "ease-out" => Ok(EASE_OUT),
                 "ease-in-out" => Ok(EASE_IN_OUT),
@@ -4604,6 +6956,7 @@ pub mod longhands {
             pub enum TransitionProperty {
                 All,
                 BackgroundColor,
+                BackgroundImage,
                 BackgroundPosition,
                 BorderBottomColor,
                 BorderBottomWidth,
@@ -4650,8 +7003,9 @@ pub mod longhands {
                 ZIndex,
             }
 
-            pub static ALL_TRANSITION_PROPERTIES: [TransitionProperty; 45] = [
+            pub static ALL_TRANSITION_PROPERTIES: [TransitionProperty; 46] = [
                 TransitionProperty::BackgroundColor,
+                TransitionProperty::BackgroundImage,
                 TransitionProperty::BackgroundPosition,
                 TransitionProperty::BorderBottomColor,
                 TransitionProperty::BorderBottomWidth,
@@ -4703,6 +7057,7 @@ pub mod longhands {
                     match *self {
                         TransitionProperty::All => dest.write_str("all"),
                         TransitionProperty::BackgroundColor => dest.write_str("background-color"),
+                        TransitionProperty::BackgroundImage => dest.write_str("background-image"),
                         TransitionProperty::BackgroundPosition => dest.write_str("background-position"),
                         TransitionProperty::BorderBottomColor => dest.write_str("border-bottom-color"),
                         TransitionProperty::BorderBottomWidth => dest.write_str("border-bottom-width"),
@@ -4780,6 +7135,7 @@ pub mod longhands {
                 try!(input.expect_ident()),
                 "all" => Ok(TransitionProperty::All),
                 "background-color" => Ok(TransitionProperty::BackgroundColor),
+                "background-image" => Ok(TransitionProperty::BackgroundImage),
                 "background-position" => Ok(TransitionProperty::BackgroundPosition),
                 "border-bottom-color" => Ok(TransitionProperty::BorderBottomColor),
                 "border-bottom-width" => Ok(TransitionProperty::BorderBottomWidth),
@@ -4852,10 +7208,157 @@ pub mod longhands {
     // CSS Flexible Box Layout Module Level 1
     // http://www.w3.org/TR/css3-flexbox/
 
-    ${new_style_struct("Flex", is_inherited=False)}
+    ${new_style_struct("Flex", is_inherited=False,
+                       additional_methods=[Method("clone_" + ident, "longhands::%s::computed_value::T" % ident)
+                                           for ident in FLEX_LONGHANDS])}
 
     // Flex container properties
     ${single_keyword("flex-direction", "row row-reverse column column-reverse", experimental=True)}
+
+    ${single_keyword("flex-wrap", "nowrap wrap wrap-reverse", experimental=True)}
+
+    // The alignment properties only take the keywords of css3-flexbox, not the rest of
+    // css-align's, which is what Gecko supports so far.
+    ${single_keyword("justify-content", "flex-start flex-end center space-between space-around",
+                     experimental=True)}
+
+    ${single_keyword("align-items", "stretch flex-start flex-end center baseline", experimental=True)}
+
+    ${single_keyword("align-content", "stretch flex-start flex-end center space-between space-around",
+                     experimental=True)}
+
+    // Flex item properties
+    ${single_keyword("align-self", "auto stretch flex-start flex-end center baseline", experimental=True)}
+
+    % for (name, initial) in [("flex-grow", "0.0"), ("flex-shrink", "1.0")]:
+        <%self:longhand name="${name}" experimental="True">
+            use cssparser::ToCss;
+            use std::fmt;
+            use values::CSSFloat;
+            use values::computed::ComputedValueAsSpecified;
+
+            impl ComputedValueAsSpecified for SpecifiedValue {}
+
+            impl ToCss for SpecifiedValue {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                    self.0.to_css(dest)
+                }
+            }
+
+            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+            pub struct SpecifiedValue(pub CSSFloat);
+            pub mod computed_value {
+                pub use super::SpecifiedValue as T;
+            }
+            #[inline]
+            pub fn get_initial_value() -> computed_value::T {
+                SpecifiedValue(${initial})
+            }
+            /// A non-negative <number>.
+            pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+                match try!(specified::parse_number(input)) {
+                    factor if factor < 0. => Err(()),
+                    factor => Ok(SpecifiedValue(factor)),
+                }
+            }
+        </%self:longhand>
+    % endfor
+
+    <%self:longhand name="flex-basis" experimental="True">
+        use cssparser::ToCss;
+        use std::fmt;
+
+        #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+        pub enum SpecifiedValue {
+            Content,
+            Specified(specified::LengthOrPercentageOrAuto),
+        }
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    SpecifiedValue::Content => dest.write_str("content"),
+                    SpecifiedValue::Specified(ref basis) => basis.to_css(dest),
+                }
+            }
+        }
+
+        pub mod computed_value {
+            use values::computed;
+
+            /// `auto` takes the flex base size from the item's main size property, which is
+            /// `width` or `height` depending on the container's direction, while `content`
+            /// sizes the item from its contents whatever that property says.
+            #[derive(Debug, Clone, Copy, PartialEq, HeapSizeOf)]
+            pub enum T {
+                Content,
+                Specified(computed::LengthOrPercentageOrAuto),
+            }
+        }
+
+        impl ToCss for computed_value::T {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                match *self {
+                    computed_value::T::Content => dest.write_str("content"),
+                    computed_value::T::Specified(ref basis) => basis.to_css(dest),
+                }
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T::Specified(computed::LengthOrPercentageOrAuto::Auto)
+        }
+
+        impl ToComputedValue for SpecifiedValue {
+            type ComputedValue = computed_value::T;
+
+            #[inline]
+            fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> computed_value::T {
+                match *self {
+                    SpecifiedValue::Content => computed_value::T::Content,
+                    SpecifiedValue::Specified(ref basis) =>
+                        computed_value::T::Specified(basis.to_computed_value(context)),
+                }
+            }
+        }
+
+        /// content | <'width'>
+        pub fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            if input.try(|input| input.expect_ident_matching("content")).is_ok() {
+                Ok(SpecifiedValue::Content)
+            } else {
+                specified::LengthOrPercentageOrAuto::parse_non_negative(input).map(SpecifiedValue::Specified)
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="order" experimental="True">
+        use cssparser::ToCss;
+        use std::fmt;
+        use values::computed::ComputedValueAsSpecified;
+
+        impl ComputedValueAsSpecified for SpecifiedValue {}
+
+        impl ToCss for SpecifiedValue {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+                write!(dest, "{}", self.0)
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, HeapSizeOf)]
+        pub struct SpecifiedValue(pub i32);
+        pub mod computed_value {
+            pub use super::SpecifiedValue as T;
+        }
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            SpecifiedValue(0)
+        }
+        fn parse(_context: &ParserContext, input: &mut Parser) -> Result<SpecifiedValue, ()> {
+            specified::parse_integer(input).map(SpecifiedValue)
+        }
+    </%self:longhand>
 }
 
 
@@ -4928,7 +7431,7 @@ pub mod shorthands {
         }
     </%def>
 
-    fn parse_four_sides<F, T>(input: &mut Parser, parse_one: F) -> Result<(T, T, T, T), ()>
+    pub fn parse_four_sides<F, T>(input: &mut Parser, parse_one: F) -> Result<(T, T, T, T), ()>
     where F: Fn(&mut Parser) -> Result<T, ()>, F: Copy, T: Clone {
         // zero or more than four values is invalid.
         // one value sets them all
@@ -4987,101 +7490,177 @@ pub mod shorthands {
         </%self:shorthand>
     </%def>
 
-    // TODO: other background-* properties
     <%self:shorthand name="background"
                      sub_properties="background-color background-position background-repeat background-attachment
                                      background-image background-size background-origin background-clip">
         use properties::longhands::{background_color, background_position, background_repeat, background_attachment};
         use properties::longhands::{background_image, background_size, background_origin, background_clip};
+        use properties::longhands::background_size::single_value::{SpecifiedExplicitSize, SpecifiedValue as Size};
+        use values::specified;
 
-        let mut color = None;
-        let mut image = None;
-        let mut position = None;
-        let mut repeat = None;
-        let mut size = None;
-        let mut attachment = None;
-        let mut any = false;
-        let mut origin = None;
-        let mut clip = None;
-
-        loop {
-            if position.is_none() {
-                if let Ok(value) = input.try(|input| background_position::parse(context, input)) {
-                    position = Some(value);
-                    any = true;
+        /// What a layer of the shorthand sets, leaving the rest to their initial values.
+        struct Layer {
+            color: Option<background_color::SpecifiedValue>,
+            image: Option<background_image::SingleSpecifiedValue>,
+            position: Option<background_position::single_value::SpecifiedValue>,
+            size: Option<background_size::single_value::SpecifiedValue>,
+            repeat: Option<background_repeat::single_value::SpecifiedValue>,
+            attachment: Option<background_attachment::single_value::SpecifiedValue>,
+            origin: Option<background_origin::single_value::SpecifiedValue>,
+            clip: Option<background_clip::single_value::SpecifiedValue>,
+        }
+
+        fn parse_layer(context: &ParserContext, input: &mut Parser) -> Result<Layer, ()> {
+            let mut layer = Layer {
+                color: None,
+                image: None,
+                position: None,
+                size: None,
+                repeat: None,
+                attachment: None,
+                origin: None,
+                clip: None,
+            };
+            let mut any = false;
+            loop {
+                if layer.position.is_none() {
+                    if let Ok(value) = input.try(|input| background_position::single_value::parse(context, input)) {
+                        layer.position = Some(value);
+                        any = true;
 
-                    // Parse background size, if applicable.
-                    size = input.try(|input| {
-                        try!(input.expect_delim('/'));
-                        background_size::parse(context, input)
-                    }).ok();
+                        // Parse background size, if applicable.
+                        layer.size = input.try(|input| {
+                            try!(input.expect_delim('/'));
+                            background_size::single_value::parse(context, input)
+                        }).ok();
 
-                    continue
+                        continue
+                    }
                 }
-            }
-            if color.is_none() {
-                if let Ok(value) = input.try(|input| background_color::parse(context, input)) {
-                    color = Some(value);
-                    any = true;
-                    continue
+                if layer.color.is_none() {
+                    if let Ok(value) = input.try(|input| background_color::parse(context, input)) {
+                        layer.color = Some(value);
+                        any = true;
+                        continue
+                    }
                 }
-            }
-            if image.is_none() {
-                if let Ok(value) = input.try(|input| background_image::parse(context, input)) {
-                    image = Some(value);
-                    any = true;
-                    continue
+                if layer.image.is_none() {
+                    if let Ok(value) = input.try(|input| background_image::parse_one(context, input)) {
+                        layer.image = Some(value);
+                        any = true;
+                        continue
+                    }
                 }
-            }
-            if repeat.is_none() {
-                if let Ok(value) = input.try(|input| background_repeat::parse(context, input)) {
-                    repeat = Some(value);
-                    any = true;
-                    continue
+                if layer.repeat.is_none() {
+                    if let Ok(value) = input.try(|input| background_repeat::single_value::parse(context, input)) {
+                        layer.repeat = Some(value);
+                        any = true;
+                        continue
+                    }
                 }
-            }
-            if attachment.is_none() {
-                if let Ok(value) = input.try(|input| background_attachment::parse(context, input)) {
-                    attachment = Some(value);
-                    any = true;
-                    continue
+                if layer.attachment.is_none() {
+                    if let Ok(value) = input.try(|input| background_attachment::single_value::parse(context, input)) {
+                        layer.attachment = Some(value);
+                        any = true;
+                        continue
+                    }
                 }
-            }
-            if origin.is_none() {
-                if let Ok(value) = input.try(|input| background_origin::parse(context, input)) {
-                    origin = Some(value);
-                    any = true;
-                    continue
+                // The first box is the origin, and the second one the clip.
+                if layer.origin.is_none() {
+                    if let Ok(value) = input.try(|input| background_origin::single_value::parse(context, input)) {
+                        layer.origin = Some(value);
+                        any = true;
+                        continue
+                    }
                 }
-            }
-            if clip.is_none() {
-                if let Ok(value) = input.try(|input| background_clip::parse(context, input)) {
-                    clip = Some(value);
-                    any = true;
-                    continue
+                if layer.origin.is_some() && layer.clip.is_none() {
+                    if let Ok(value) = input.try(|input| background_clip::single_value::parse(context, input)) {
+                        layer.clip = Some(value);
+                        continue
+                    }
                 }
+                break
             }
-            break
+            if !any {
+                return Err(())
+            }
+            // A single box is both.
+            if layer.clip.is_none() {
+                layer.clip = layer.origin.map(|origin| match origin {
+                    background_origin::single_value::T::padding_box => background_clip::single_value::T::padding_box,
+                    background_origin::single_value::T::border_box => background_clip::single_value::T::border_box,
+                    background_origin::single_value::T::content_box => background_clip::single_value::T::content_box,
+                });
+            }
+            Ok(layer)
         }
 
-        if any {
-            Ok(Longhands {
-                background_color: color,
-                background_image: image,
-                background_position: position,
-                background_repeat: repeat,
-                background_attachment: attachment,
-                background_size: size,
-                background_origin: origin,
-                background_clip: clip,
-            })
-        } else {
-            Err(())
+        let mut layers = try!(input.parse_comma_separated(|input| parse_layer(context, input)));
+        // Only the bottom layer, which comes last, can have a color.
+        let color = layers.last_mut().unwrap().color.take();
+        if layers.iter().any(|layer| layer.color.is_some()) {
+            return Err(())
+        }
+
+        // A single layer leaves what it doesn't set to `initial`, and more of them give every
+        // layered longhand a value for each.
+        fn layered<T: Clone, F>(layers: &[Layer], field: F, initial: T) -> Option<Vec<T>>
+                                where F: Fn(&Layer) -> &Option<T> {
+            if layers.len() == 1 && field(&layers[0]).is_none() {
+                return None
+            }
+            Some(layers.iter().map(|layer| field(layer).clone().unwrap_or_else(|| initial.clone())).collect())
         }
+        let zero = specified::LengthOrPercentage::Percentage(specified::Percentage(0.));
+        let auto = specified::LengthOrPercentageOrAuto::Auto;
+        Ok(Longhands {
+            background_color: color,
+            background_image: layered(&layers, |layer| &layer.image, None).map(background_image::SpecifiedValue),
+            background_position: layered(&layers, |layer| &layer.position, specified::Position {
+                horizontal: zero,
+                vertical: zero,
+            }).map(background_position::SpecifiedValue),
+            background_repeat: layered(&layers, |layer| &layer.repeat,
+                                       background_repeat::single_value::get_initial_value())
+                .map(background_repeat::SpecifiedValue),
+            background_attachment: layered(&layers, |layer| &layer.attachment,
+                                           background_attachment::single_value::get_initial_value())
+                .map(background_attachment::SpecifiedValue),
+            background_size: layered(&layers, |layer| &layer.size, Size::Explicit(SpecifiedExplicitSize {
+                width: auto,
+                height: auto,
+            })).map(background_size::SpecifiedValue),
+            background_origin: layered(&layers, |layer| &layer.origin,
+                                       background_origin::single_value::get_initial_value())
+                .map(background_origin::SpecifiedValue),
+            background_clip: layered(&layers, |layer| &layer.clip, background_clip::single_value::get_initial_value())
+                .map(background_clip::SpecifiedValue),
+        })
     </%self:shorthand>
 
-    ${four_sides_shorthand("margin", "margin-%s", "specified::LengthOrPercentageOrAuto::parse")}
-    ${four_sides_shorthand("padding", "padding-%s", "specified::LengthOrPercentage::parse")}
+    ${four_sides_shorthand("margin", "margin-%s", "specified::LengthOrPercentageOrAuto::parse")}
+    ${four_sides_shorthand("padding", "padding-%s", "specified::LengthOrPercentage::parse")}
+
+    <%def name="logical_shorthand(name, sub_property_pattern)">
+        <%self:shorthand name="${name}"
+                         sub_properties="${sub_property_pattern % 'start'} ${sub_property_pattern % 'end'}">
+            <% start_ident, end_ident = [to_rust_ident(sub_property_pattern % edge) for edge in ["start", "end"]] %>
+            // One value sets both sides.
+            let start = try!(longhands::${start_ident}::parse(context, input));
+            let end = input.try(|input| longhands::${end_ident}::parse(context, input))
+                           .unwrap_or_else(|()| start.clone());
+            Ok(Longhands {
+                ${start_ident}: Some(start),
+                ${end_ident}: Some(end),
+            })
+        </%self:shorthand>
+    </%def>
+
+    % for axis in ["block", "inline"]:
+        ${logical_shorthand("margin-" + axis, "margin-" + axis + "-%s")}
+        ${logical_shorthand("padding-" + axis, "padding-" + axis + "-%s")}
+        ${logical_shorthand("inset-" + axis, "inset-" + axis + "-%s")}
+    % endfor
 
     ${four_sides_shorthand("border-color", "border-%s-color", "specified::CSSColor::parse")}
     ${four_sides_shorthand("border-style", "border-%s-style",
@@ -5155,10 +7734,12 @@ pub mod shorthands {
         </%self:shorthand>
     % endfor
 
+    // `border` can't set the border image, but it does reset it.
     <%self:shorthand name="border" sub_properties="${' '.join(
-        'border-%s-%s' % (side, prop)
-        for side in ['top', 'right', 'bottom', 'left']
-        for prop in ['color', 'style', 'width']
+        ['border-%s-%s' % (side, prop)
+         for side in ['top', 'right', 'bottom', 'left']
+         for prop in ['width', 'style', 'color']] +
+        [ident.replace('_', '-') for ident in BORDER_IMAGE_LONGHANDS]
     )}">
         let (color, style, width) = try!(super::parse_border(context, input));
         Ok(Longhands {
@@ -5168,6 +7749,61 @@ pub mod shorthands {
                 border_${side}_width:
                     width.map(longhands::${to_rust_ident('border-%s-width' % side)}::SpecifiedValue),
             % endfor
+            % for ident in BORDER_IMAGE_LONGHANDS:
+                ${ident}: None,
+            % endfor
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="border-image" sub_properties="${' '.join(ident.replace('_', '-')
+                                                                     for ident in BORDER_IMAGE_LONGHANDS)}">
+        use properties::longhands::{border_image_outset, border_image_repeat, border_image_slice};
+        use properties::longhands::{border_image_source, border_image_width};
+
+        let mut source = None;
+        let mut slice = None;
+        let mut width = None;
+        let mut outset = None;
+        let mut repeat = None;
+        loop {
+            if source.is_none() {
+                if let Ok(value) = input.try(|input| border_image_source::parse(context, input)) {
+                    source = Some(value);
+                    continue
+                }
+            }
+            if slice.is_none() {
+                if let Ok(value) = input.try(|input| border_image_slice::parse(context, input)) {
+                    slice = Some(value);
+                    // The width and the outset can only follow the slice, after a slash each.
+                    if input.try(|input| input.expect_delim('/')).is_ok() {
+                        width = input.try(|input| border_image_width::parse(context, input)).ok();
+                        if input.try(|input| input.expect_delim('/')).is_ok() {
+                            outset = Some(try!(border_image_outset::parse(context, input)));
+                        } else if width.is_none() {
+                            return Err(())
+                        }
+                    }
+                    continue
+                }
+            }
+            if repeat.is_none() {
+                if let Ok(value) = input.try(|input| border_image_repeat::parse(context, input)) {
+                    repeat = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if source.is_none() && slice.is_none() && repeat.is_none() {
+            return Err(())
+        }
+        Ok(Longhands {
+            border_image_source: source,
+            border_image_slice: slice,
+            border_image_width: width,
+            border_image_outset: outset,
+            border_image_repeat: repeat,
         })
     </%self:shorthand>
 
@@ -5227,8 +7863,7 @@ pub mod shorthands {
     </%self:shorthand>
 
     <%self:shorthand name="outline" sub_properties="outline-color outline-style outline-width">
-        use properties::longhands::outline_width;
-        use values::specified;
+        use properties::longhands::{outline_color, outline_style, outline_width};
 
         let _unused = context;
         let mut color = None;
@@ -5237,14 +7872,14 @@ pub mod shorthands {
         let mut any = false;
         loop {
             if color.is_none() {
-                if let Ok(value) = input.try(specified::CSSColor::parse) {
+                if let Ok(value) = input.try(|input| outline_color::parse(context, input)) {
                     color = Some(value);
                     any = true;
                     continue
                 }
             }
             if style.is_none() {
-                if let Ok(value) = input.try(specified::BorderStyle::parse) {
+                if let Ok(value) = input.try(|input| outline_style::parse(context, input)) {
                     style = Some(value);
                     any = true;
                     continue
@@ -5270,18 +7905,40 @@ pub mod shorthands {
         }
     </%self:shorthand>
 
-    <%self:shorthand name="font" sub_properties="font-style font-variant font-weight
-                                                 font-size line-height font-family">
-        use properties::longhands::{font_style, font_variant, font_weight, font_size,
-                                    line_height, font_family};
+    <%self:shorthand name="font" sub_properties="font-style font-variant font-weight font-stretch
+                                                 font-size line-height font-family
+                                                 font-size-adjust font-feature-settings
+                                                 font-variation-settings font-language-override
+                                                 -servo-system-font">
+        use properties::longhands::{font_style, font_variant, font_weight, font_stretch, font_size,
+                                    line_height, font_family, _servo_system_font};
+        use system_values::SystemFont;
+        if let Ok(font) = input.try(SystemFont::parse) {
+            // Everything else is reset, and then set from the system font by the cascade.
+            return Ok(Longhands {
+                font_style: None,
+                font_variant: None,
+                font_weight: None,
+                font_stretch: None,
+                font_size: None,
+                line_height: None,
+                font_family: None,
+                font_size_adjust: None,
+                font_feature_settings: None,
+                font_variation_settings: None,
+                font_language_override: None,
+                _servo_system_font: Some(_servo_system_font::SpecifiedValue(Some(font))),
+            })
+        }
         let mut nb_normals = 0;
         let mut style = None;
         let mut variant = None;
         let mut weight = None;
+        let mut stretch = None;
         let size;
         loop {
             // Special-case 'normal' because it is valid in each of
-            // font-style, font-weight and font-variant.
+            // font-style, font-weight, font-variant and font-stretch.
             // Leaves the values to None, 'normal' is the initial value for each of them.
             if input.try(|input| input.expect_ident_matching("normal")).is_ok() {
                 nb_normals += 1;
@@ -5305,6 +7962,12 @@ pub mod shorthands {
                     continue
                 }
             }
+            if stretch.is_none() {
+                if let Ok(value) = input.try(|input| font_stretch::parse(context, input)) {
+                    stretch = Some(value);
+                    continue
+                }
+            }
             size = Some(try!(font_size::parse(context, input)));
             break
         }
@@ -5312,7 +7975,7 @@ pub mod shorthands {
         fn count<T>(opt: &Option<T>) -> u8 {
             if opt.is_some() { 1 } else { 0 }
         }
-        if size.is_none() || (count(&style) + count(&weight) + count(&variant) + nb_normals) > 3 {
+        if size.is_none() || (count(&style) + count(&weight) + count(&variant) + count(&stretch) + nb_normals) > 4 {
             return Err(())
         }
         let line_height = if input.try(|input| input.expect_delim('/')).is_ok() {
@@ -5321,13 +7984,20 @@ pub mod shorthands {
             None
         };
         let family = try!(input.parse_comma_separated(font_family::parse_one_family));
+        // The font longhands the shorthand can't set are reset.
         Ok(Longhands {
             font_style: style,
             font_variant: variant,
             font_weight: weight,
+            font_stretch: stretch,
             font_size: size,
             line_height: line_height,
-            font_family: Some(font_family::SpecifiedValue(family))
+            font_family: Some(font_family::SpecifiedValue(family)),
+            font_size_adjust: None,
+            font_feature_settings: None,
+            font_variation_settings: None,
+            font_language_override: None,
+            _servo_system_font: None,
         })
     </%self:shorthand>
 
@@ -5340,6 +8010,75 @@ pub mod shorthands {
         })
     </%self:shorthand>
 
+    <%self:shorthand name="text-decoration"
+                     sub_properties="text-decoration-line text-decoration-style text-decoration-color">
+        use properties::longhands::{text_decoration_line, text_decoration_style};
+        use values::specified;
+
+        let mut line = None;
+        let mut style = None;
+        let mut color = None;
+        loop {
+            if line.is_none() {
+                if let Ok(value) = input.try(|input| text_decoration_line::parse(context, input)) {
+                    line = Some(value);
+                    continue
+                }
+            }
+            if style.is_none() {
+                if let Ok(value) = input.try(|input| text_decoration_style::parse(context, input)) {
+                    style = Some(value);
+                    continue
+                }
+            }
+            if color.is_none() {
+                if let Ok(value) = input.try(specified::CSSColor::parse) {
+                    color = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if line.is_none() && style.is_none() && color.is_none() {
+            return Err(())
+        }
+        Ok(Longhands {
+            text_decoration_line: line,
+            text_decoration_style: style,
+            text_decoration_color: color,
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="text-emphasis" sub_properties="text-emphasis-style text-emphasis-color">
+        use properties::longhands::text_emphasis_style;
+        use values::specified;
+
+        let mut style = None;
+        let mut color = None;
+        loop {
+            if style.is_none() {
+                if let Ok(value) = input.try(|input| text_emphasis_style::parse(context, input)) {
+                    style = Some(value);
+                    continue
+                }
+            }
+            if color.is_none() {
+                if let Ok(value) = input.try(specified::CSSColor::parse) {
+                    color = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if style.is_none() && color.is_none() {
+            return Err(())
+        }
+        Ok(Longhands {
+            text_emphasis_style: style,
+            text_emphasis_color: color,
+        })
+    </%self:shorthand>
+
     <%self:shorthand name="list-style"
                      sub_properties="list-style-image list-style-position list-style-type">
         use properties::longhands::{list_style_image, list_style_position, list_style_type};
@@ -5468,6 +8207,44 @@ pub mod shorthands {
         }
     </%self:shorthand>
 
+    <%self:shorthand name="column-rule" sub_properties="column-rule-width column-rule-style column-rule-color"
+                     experimental="True">
+        use properties::longhands::{column_rule_color, column_rule_style, column_rule_width};
+
+        let mut width = None;
+        let mut style = None;
+        let mut color = None;
+        loop {
+            if width.is_none() {
+                if let Ok(value) = input.try(|input| column_rule_width::parse(context, input)) {
+                    width = Some(value);
+                    continue
+                }
+            }
+            if style.is_none() {
+                if let Ok(value) = input.try(|input| column_rule_style::parse(context, input)) {
+                    style = Some(value);
+                    continue
+                }
+            }
+            if color.is_none() {
+                if let Ok(value) = input.try(|input| column_rule_color::parse(context, input)) {
+                    color = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if width.is_none() && style.is_none() && color.is_none() {
+            return Err(())
+        }
+        Ok(Longhands {
+            column_rule_width: width,
+            column_rule_style: style,
+            column_rule_color: color,
+        })
+    </%self:shorthand>
+
     <%self:shorthand name="overflow" sub_properties="overflow-x overflow-y">
         use properties::longhands::{overflow_x, overflow_y};
 
@@ -5478,6 +8255,21 @@ pub mod shorthands {
         })
     </%self:shorthand>
 
+    <%self:shorthand name="scroll-snap-type" sub_properties="scroll-snap-type-x scroll-snap-type-y">
+        use properties::longhands::scroll_snap_type_x::{self, SpecifiedValue as X};
+        use properties::longhands::scroll_snap_type_y::SpecifiedValue as Y;
+
+        let snap_type = try!(scroll_snap_type_x::parse(context, input));
+        Ok(Longhands {
+            scroll_snap_type_x: Some(snap_type),
+            scroll_snap_type_y: Some(match snap_type {
+                X::none => Y::none,
+                X::mandatory => Y::mandatory,
+                X::proximity => Y::proximity,
+            }),
+        })
+    </%self:shorthand>
+
     <%self:shorthand name="transition"
                      sub_properties="transition-property transition-duration transition-timing-function
                                      transition-delay">
@@ -5571,6 +8363,84 @@ pub mod shorthands {
             transition_delay: Some(transition_delay::SpecifiedValue(delays)),
         })
     </%self:shorthand>
+
+    <%self:shorthand name="flex-flow" sub_properties="flex-direction flex-wrap" experimental="True">
+        use properties::longhands::{flex_direction, flex_wrap};
+
+        let mut direction = None;
+        let mut wrap = None;
+        loop {
+            if direction.is_none() {
+                if let Ok(value) = input.try(|input| flex_direction::parse(context, input)) {
+                    direction = Some(value);
+                    continue
+                }
+            }
+            if wrap.is_none() {
+                if let Ok(value) = input.try(|input| flex_wrap::parse(context, input)) {
+                    wrap = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if direction.is_none() && wrap.is_none() {
+            return Err(())
+        }
+        Ok(Longhands {
+            flex_direction: direction,
+            flex_wrap: wrap,
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="flex" sub_properties="flex-grow flex-shrink flex-basis" experimental="True">
+        use properties::longhands::{flex_basis, flex_grow, flex_shrink};
+        use values::specified::{LengthOrPercentageOrAuto, Percentage};
+
+        if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+            return Ok(Longhands {
+                flex_grow: Some(flex_grow::SpecifiedValue(0.)),
+                flex_shrink: Some(flex_shrink::SpecifiedValue(0.)),
+                flex_basis: Some(flex_basis::SpecifiedValue::Specified(LengthOrPercentageOrAuto::Auto)),
+            })
+        }
+
+        // [ <'flex-grow'> <'flex-shrink'>? ] || <'flex-basis'>
+        //
+        // The factors are tried first, so a unitless zero is a flex factor unless both factors
+        // have been given already: `flex: 0 0` has two factors, and `flex: 0 0 0` a zero basis.
+        let mut factors = None;
+        let mut basis = None;
+        loop {
+            if factors.is_none() {
+                if let Ok(grow) = input.try(|input| flex_grow::parse(context, input)) {
+                    let shrink = input.try(|input| flex_shrink::parse(context, input)).ok();
+                    factors = Some((grow, shrink));
+                    continue
+                }
+            }
+            if basis.is_none() {
+                if let Ok(value) = input.try(|input| flex_basis::parse(context, input)) {
+                    basis = Some(value);
+                    continue
+                }
+            }
+            break
+        }
+        if factors.is_none() && basis.is_none() {
+            return Err(())
+        }
+
+        // The factors default to one when they're left out, and the basis to zero, rather
+        // than to their initial values.
+        let (grow, shrink) = factors.unwrap_or((flex_grow::SpecifiedValue(1.), None));
+        Ok(Longhands {
+            flex_grow: Some(grow),
+            flex_shrink: Some(shrink.unwrap_or(flex_shrink::SpecifiedValue(1.))),
+            flex_basis: Some(basis.unwrap_or(
+                flex_basis::SpecifiedValue::Specified(LengthOrPercentageOrAuto::Percentage(Percentage(0.))))),
+        })
+    </%self:shorthand>
 }
 
 
@@ -5684,17 +8554,423 @@ mod property_bit_field {
                 )
             );
         }
-    % endif
-% endfor
+    % endif
+% endfor
+
+/// Declarations are stored in reverse order.
+/// Overridden declarations are skipped.
+#[derive(Clone, Debug, PartialEq, HeapSizeOf)]
+pub struct PropertyDeclarationBlock {
+    #[ignore_heap_size_of = "#7038"]
+    pub important: Arc<Vec<PropertyDeclaration>>,
+    #[ignore_heap_size_of = "#7038"]
+    pub normal: Arc<Vec<PropertyDeclaration>>,
+    /// The shorthands that were written as such, and whether they were important, for which
+    /// the block still has the longhands they set. Serializing the block gives those back
+    /// rather than whichever shorthands would cover the most longhands. Since the values
+    /// always come from the longhands, a shorthand left here after changing one of them
+    /// only makes it the preferred way to write them.
+    pub shorthands: Vec<(Shorthand, bool)>,
+}
+
+impl PropertyDeclarationBlock {
+    /// Forgets that the shorthands setting the longhand called `name` were written, for when
+    /// its value is changed or removed from the block by itself.
+    pub fn forget_written_shorthands(&mut self, name: &str) {
+        self.shorthands.retain(|&(shorthand, _)| !shorthand.longhands().iter().any(|l| l.eq_ignore_ascii_case(name)));
+    }
+
+    /// Whether any of the declarations is important.
+    pub fn has_important(&self) -> bool {
+        !self.important.is_empty()
+    }
+}
+
+impl ToCss for PropertyDeclarationBlock {
+    // https://drafts.csswg.org/cssom/#serialize-a-css-declaration-block
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        let mut is_first = true;
+        let mut already_serialized = Vec::new();
+        for &(declarations, important) in &[(&self.normal, false), (&self.important, true)] {
+            // Declarations are stored in reverse order.
+            for declaration in declarations.iter().rev() {
+                let name = declaration.name();
+                if name == PropertyDeclarationName::Internal || already_serialized.contains(&name) {
+                    continue
+                }
+
+                let mut serialized_as_shorthand = false;
+                let shorthands = declaration.shorthands();
+                let written = shorthands.iter().filter(|&&shorthand| self.shorthands.contains(&(shorthand, important)));
+                for &shorthand in written.chain(shorthands) {
+                    let longhands = shorthand.longhands().iter().map(|longhand| {
+                        declarations.iter().find(|d| d.matches(longhand))
+                    }).collect::<Option<Vec<_>>>();
+                    let longhands = match longhands {
+                        Some(longhands) => longhands,
+                        None => continue,
+                    };
+                    if longhands.iter().any(|d| already_serialized.contains(&d.name())) {
+                        continue
+                    }
+                    let value = serialize_shorthand(shorthand, &longhands);
+                    if value.is_empty() {
+                        continue
+                    }
+                    try!(write_declaration(dest, &mut is_first, shorthand.name(), &value, important));
+                    already_serialized.extend(longhands.iter().map(|d| d.name()));
+                    serialized_as_shorthand = true;
+                    break
+                }
+
+                if !serialized_as_shorthand {
+                    try!(write_declaration(dest, &mut is_first, &name.to_string(),
+                                           &declaration.value(), important));
+                    already_serialized.push(name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_declaration<W>(dest: &mut W, is_first: &mut bool, name: &str, value: &str, important: bool)
+                        -> fmt::Result where W: fmt::Write {
+    if !*is_first {
+        try!(dest.write_str(" "));
+    }
+    *is_first = false;
+    try!(write!(dest, "{}: {}", name, value));
+    if important {
+        try!(dest.write_str(" !important"));
+    }
+    dest.write_str(";")
+}
+
+/// Serializes the value of a shorthand from the declarations of all of its longhands.
+pub fn serialize_shorthand(shorthand: Shorthand, declarations: &[&PropertyDeclaration]) -> String {
+    // https://drafts.csswg.org/css-variables/#variables-in-shorthands
+    if let Some(css) = declarations[0].with_variables_from_shorthand(shorthand) {
+        if declarations[1..]
+               .iter()
+               .all(|d| d.with_variables_from_shorthand(shorthand) == Some(css)) {
+            css.to_owned()
+        } else {
+            String::new()
+        }
+    } else {
+        if declarations.iter().any(|d| d.with_variables()) {
+            String::new()
+        } else if let Some(keyword) = shared_keyword(declarations, &["inherit", "unset"]) {
+            keyword
+        } else {
+            match shorthand {
+                Shorthand::TextDecoration |
+                Shorthand::TextEmphasis |
+                Shorthand::ColumnRule |
+                Shorthand::FlexFlow |
+                Shorthand::Outline |
+                Shorthand::Columns |
+                Shorthand::BorderTop | Shorthand::BorderRight |
+                Shorthand::BorderBottom | Shorthand::BorderLeft => serialize_leaving_out_initial_values(declarations),
+                Shorthand::ListStyle => serialize_list_style(declarations),
+                Shorthand::Border => serialize_border(declarations),
+                Shorthand::BorderImage => serialize_border_image(declarations),
+                Shorthand::Font => serialize_font(declarations),
+                Shorthand::Background => serialize_background(declarations),
+                Shorthand::Transition => serialize_transition(declarations),
+                Shorthand::BorderRadius => serialize_border_radius(declarations),
+                Shorthand::Overflow | Shorthand::ScrollSnapType => serialize_one_value_for_all(declarations),
+                Shorthand::MarginBlock | Shorthand::MarginInline |
+                Shorthand::PaddingBlock | Shorthand::PaddingInline |
+                Shorthand::InsetBlock | Shorthand::InsetInline => serialize_start_and_end(declarations),
+                _ => {
+                    if let Some(keyword) = shared_keyword(declarations, &["initial"]) {
+                        return keyword
+                    }
+                    let values = declarations.iter().map(|d| d.value()).collect::<Vec<_>>();
+                    // FIXME: this needs property-specific code
+                    // "as appropriate according to the grammar of shorthand "
+                    // https://drafts.csswg.org/cssom/#serialize-a-css-value
+                    values.join(" ")
+                }
+            }
+        }
+    }
+}
+
+/// A shorthand can't be written with one of the CSS-wide `keywords` in among its other values,
+/// only on its own when every longhand is set to it. Returns that keyword, or an empty string
+/// if only some of the longhands are set to one, and None if none of them are.
+fn shared_keyword(declarations: &[&PropertyDeclaration], keywords: &[&str]) -> Option<String> {
+    let values = declarations.iter().map(|d| d.value()).collect::<Vec<_>>();
+    if !values.iter().any(|value| keywords.contains(&&**value)) {
+        return None
+    }
+    if values.iter().all(|value| *value == values[0]) {
+        Some(values[0].clone())
+    } else {
+        Some(String::new())
+    }
+}
+
+/// Serializes a shorthand whose longhands can each be left out, leaving out those set to their
+/// initial values, or all but the first if every one of them is.
+///
+/// https://drafts.csswg.org/cssom/#serialize-a-css-value
+fn serialize_leaving_out_initial_values(declarations: &[&PropertyDeclaration]) -> String {
+    let values = declarations.iter()
+                             .filter(|d| !d.sets_initial_value())
+                             .map(|d| d.value())
+                             .collect::<Vec<_>>();
+    if values.is_empty() {
+        declarations[0].initial_value()
+    } else {
+        values.join(" ")
+    }
+}
+
+/// `list-style: none` sets the type to `none` as well as the image, so when every longhand is
+/// initial it's the type that's written out.
+fn serialize_list_style(declarations: &[&PropertyDeclaration]) -> String {
+    if declarations.iter().all(|d| d.sets_initial_value()) {
+        return declarations[2].initial_value()
+    }
+    serialize_leaving_out_initial_values(declarations)
+}
+
+/// A shorthand that sets all of its longhands to the one value it's given can only be written
+/// when they're all the same.
+fn serialize_one_value_for_all(declarations: &[&PropertyDeclaration]) -> String {
+    let value = declarations[0].value();
+    if declarations[1..].iter().all(|d| d.value() == value) {
+        value
+    } else {
+        String::new()
+    }
+}
+
+/// The logical shorthands for the start and end of an axis leave out the end when it's the same
+/// as the start.
+fn serialize_start_and_end(declarations: &[&PropertyDeclaration]) -> String {
+    let (start, end) = (declarations[0].value(), declarations[1].value());
+    if start == end {
+        start
+    } else {
+        format!("{} {}", start, end)
+    }
+}
+
+/// `border` sets the four sides to the same thing, and can only reset the border image, so
+/// there's nothing to serialize if the sides differ or the border image isn't the initial one.
+fn serialize_border(declarations: &[&PropertyDeclaration]) -> String {
+    let (sides, image) = declarations.split_at(12);
+    let top = &sides[..3];
+    let same_sides = sides.chunks(3).all(|side| side.iter().zip(top).all(|(a, b)| a.value() == b.value()));
+    if !same_sides || !image.iter().all(|d| d.sets_initial_value()) {
+        return String::new()
+    }
+    let values = top.iter()
+                    .filter(|d| !d.sets_initial_value())
+                    .map(|d| d.value())
+                    .collect::<Vec<_>>();
+    if values.is_empty() {
+        // The style, which is `none`.
+        top[1].initial_value()
+    } else {
+        values.join(" ")
+    }
+}
+
+/// The width and the outset of `border-image` are tied to the slice before them by slashes, so
+/// those are spelled out, initial or not, whenever something after them isn't initial.
+fn serialize_border_image(declarations: &[&PropertyDeclaration]) -> String {
+    let value = |d: &PropertyDeclaration| if d.value() == "initial" { d.initial_value() } else { d.value() };
+    let (source, slice, width, outset, repeat) =
+        (declarations[0], declarations[1], declarations[2], declarations[3], declarations[4]);
+    let has_outset = !outset.sets_initial_value();
+    let has_width = has_outset || !width.sets_initial_value();
+    let mut values = vec![];
+    if !source.sets_initial_value() {
+        values.push(value(source));
+    }
+    if has_width || !slice.sets_initial_value() {
+        values.push(value(slice));
+    }
+    if has_width {
+        values.push(format!("/ {}", value(width)));
+    }
+    if has_outset {
+        values.push(format!("/ {}", value(outset)));
+    }
+    if !repeat.sets_initial_value() {
+        values.push(value(repeat));
+    }
+    if values.is_empty() {
+        value(source)
+    } else {
+        values.join(" ")
+    }
+}
+
+/// `font` can only reset the longhands after font-family, so there's nothing to serialize if
+/// one of those isn't initial. A system font is written out on its own, unless something more
+/// important than the `font` declaration that named it set another longhand since.
+fn serialize_font(declarations: &[&PropertyDeclaration]) -> String {
+    let value = |d: &PropertyDeclaration| if d.value() == "initial" { d.initial_value() } else { d.value() };
+    let (settable, rest) = declarations.split_at(7);
+    let (reset_only, system_font) = rest.split_at(rest.len() - 1);
+    if !reset_only.iter().all(|d| d.sets_initial_value()) {
+        return String::new()
+    }
+    if !system_font[0].sets_initial_value() {
+        return if settable.iter().all(|d| d.value() == "initial") {
+            system_font[0].value()
+        } else {
+            String::new()
+        }
+    }
+    let (size, line_height, family) = (settable[4], settable[5], settable[6]);
+    let mut values = settable[..4].iter()
+                                  .filter(|d| !d.sets_initial_value())
+                                  .map(|d| value(d))
+                                  .collect::<Vec<_>>();
+    if line_height.sets_initial_value() {
+        values.push(value(size));
+    } else {
+        values.push(format!("{}/{}", value(size), value(line_height)));
+    }
+    values.push(value(family));
+    values.join(" ")
+}
+
+/// The serialization of each layer a layered background longhand is declared with, or None if
+/// it's declared with a keyword like `inherit`, which `background` can't write out for it.
+fn background_layers(declaration: &PropertyDeclaration) -> Option<Vec<String>> {
+    match *declaration {
+        % for ident in BACKGROUND_LAYER_LONGHANDS:
+        PropertyDeclaration::${to_camel_case(ident)}(DeclaredValue::Value(ref value)) => {
+            Some(value.0.iter().map(|layer| {
+                longhands::${ident}::SpecifiedValue(vec![layer.clone()]).to_css_string()
+            }).collect())
+        }
+        PropertyDeclaration::${to_camel_case(ident)}(DeclaredValue::Initial) => {
+            Some(vec![longhands::${ident}::get_initial_value().to_css_string()])
+        }
+        % endfor
+        _ => None,
+    }
+}
+
+/// `background` can only be written out when every layered longhand has a value for each image,
+/// as it sets them all for each layer. Each layer leaves out what's initial, writing `none` if
+/// that's everything, and the bottom one has the color.
+fn serialize_background(declarations: &[&PropertyDeclaration]) -> String {
+    let color = declarations[0];
+    if color.value() == "inherit" {
+        return String::new()
+    }
+    // In the order of the sub-properties after the color.
+    let layered = match declarations[1..].iter().map(|d| background_layers(d)).collect::<Option<Vec<_>>>() {
+        Some(layered) => layered,
+        None => return String::new(),
+    };
+    let (position, repeat, attachment, image, size, origin, clip) =
+        (&layered[0], &layered[1], &layered[2], &layered[3], &layered[4], &layered[5], &layered[6]);
+    if layered.iter().any(|layers| layers.len() != image.len()) {
+        return String::new()
+    }
+    let initial = declarations[1..].iter().map(|d| d.initial_value()).collect::<Vec<_>>();
+    let is_initial = |longhand: usize, value: &str| value.eq_ignore_ascii_case(&initial[longhand]);
+    let mut layers = vec![];
+    for index in 0..image.len() {
+        let mut values = vec![];
+        if !is_initial(3, &image[index]) {
+            values.push(image[index].clone());
+        }
+        if !is_initial(4, &size[index]) {
+            values.push(format!("{} / {}", position[index], size[index]));
+        } else if !is_initial(0, &position[index]) {
+            values.push(position[index].clone());
+        }
+        for &(longhand, value) in &[(1, &repeat[index]), (2, &attachment[index])] {
+            if !is_initial(longhand, value) {
+                values.push(value.clone());
+            }
+        }
+        // A single box sets both the origin and the clip.
+        if origin[index] == clip[index] {
+            values.push(origin[index].clone());
+        } else if !is_initial(5, &origin[index]) || !is_initial(6, &clip[index]) {
+            values.push(format!("{} {}", origin[index], clip[index]));
+        }
+        if index == image.len() - 1 && !color.sets_initial_value() {
+            values.push(color.value());
+        }
+        if values.is_empty() {
+            values.push(initial[3].clone());
+        }
+        layers.push(values.join(" "));
+    }
+    layers.join(", ")
+}
 
-/// Declarations are stored in reverse order.
-/// Overridden declarations are skipped.
-#[derive(Debug, PartialEq, HeapSizeOf)]
-pub struct PropertyDeclarationBlock {
-    #[ignore_heap_size_of = "#7038"]
-    pub important: Arc<Vec<PropertyDeclaration>>,
-    #[ignore_heap_size_of = "#7038"]
-    pub normal: Arc<Vec<PropertyDeclaration>>,
+/// `border-radius` writes the widths of the corners, and then their heights after a slash if
+/// any of them differ.
+fn serialize_border_radius(declarations: &[&PropertyDeclaration]) -> String {
+    if let Some(keyword) = shared_keyword(declarations, &["initial"]) {
+        return keyword
+    }
+    let (mut widths, mut heights) = (vec![], vec![]);
+    for declaration in declarations {
+        let size = match **declaration {
+            % for corner in ["TopLeft", "TopRight", "BottomRight", "BottomLeft"]:
+            PropertyDeclaration::Border${corner}Radius(DeclaredValue::Value(ref size)) => size,
+            % endfor
+            _ => return String::new(),
+        };
+        widths.push(size.0.width.to_css_string());
+        heights.push(size.0.height.to_css_string());
+    }
+    if widths == heights {
+        widths.join(" ")
+    } else {
+        format!("{} / {}", widths.join(" "), heights.join(" "))
+    }
+}
+
+/// The serialization of each transition a transition longhand is declared with, or None if it's
+/// declared with a keyword.
+fn transition_items(declaration: &PropertyDeclaration) -> Option<Vec<String>> {
+    match *declaration {
+        % for ident in ["transition_property", "transition_duration", "transition_timing_function", "transition_delay"]:
+        PropertyDeclaration::${to_camel_case(ident)}(DeclaredValue::Value(ref value)) => {
+            Some(value.0.iter().map(|item| {
+                longhands::${ident}::SpecifiedValue(vec![item.clone()]).to_css_string()
+            }).collect())
+        }
+        % endfor
+        _ => None,
+    }
+}
+
+/// `transition` writes each transition out in full, so it can only be when every longhand has
+/// a value for each of them.
+fn serialize_transition(declarations: &[&PropertyDeclaration]) -> String {
+    if let Some(keyword) = shared_keyword(declarations, &["initial"]) {
+        return keyword
+    }
+    let items = match declarations.iter().map(|d| transition_items(d)).collect::<Option<Vec<_>>>() {
+        Some(items) => items,
+        None => return String::new(),
+    };
+    let count = items[0].len();
+    if count == 0 || items.iter().any(|longhand| longhand.len() != count) {
+        return String::new()
+    }
+    (0..count).map(|index| {
+        items.iter().map(|longhand| &*longhand[index]).collect::<Vec<_>>().join(" ")
+    }).collect::<Vec<_>>().join(", ")
 }
 
 pub fn parse_style_attribute(input: &str, base_url: &Url, error_reporter: Box<ParseErrorReporter + Send>)
@@ -5713,6 +8989,21 @@ pub fn parse_one_declaration(name: &str, input: &str, base_url: &Url, error_repo
     }
 }
 
+/// Parses `input` as the whole value of the property called `name`, expanding shorthands into
+/// their longhands. Unlike `parse_one_declaration`, anything after the value is an error, and
+/// so is `!important`. Quirks never apply.
+pub fn parse_property_value(name: &str, input: &str, base_url: &Url, error_reporter: Box<ParseErrorReporter + Send>)
+                            -> Result<Vec<PropertyDeclaration>, ()> {
+    let context = ParserContext::new(Origin::Author, base_url, error_reporter);
+    Parser::new(input).parse_entirely(|input| {
+        let mut results = vec![];
+        match PropertyDeclaration::parse(name, &context, input, &mut results) {
+            PropertyDeclarationParseResult::ValidOrIgnoredDeclaration => Ok(results),
+            _ => Err(())
+        }
+    })
+}
+
 struct PropertyDeclarationParser<'a, 'b: 'a> {
     context: &'a ParserContext<'b>,
 }
@@ -5721,14 +9012,16 @@ struct PropertyDeclarationParser<'a, 'b: 'a> {
 /// Default methods reject all at rules.
 impl<'a, 'b> AtRuleParser for PropertyDeclarationParser<'a, 'b> {
     type Prelude = ();
-    type AtRule = (Vec<PropertyDeclaration>, bool);
+    type AtRule = (Vec<PropertyDeclaration>, bool, Option<Shorthand>);
 }
 
 
 impl<'a, 'b> DeclarationParser for PropertyDeclarationParser<'a, 'b> {
-    type Declaration = (Vec<PropertyDeclaration>, bool);
+    /// The longhands set, whether they're important, and the shorthand that set them if any.
+    type Declaration = (Vec<PropertyDeclaration>, bool, Option<Shorthand>);
 
-    fn parse_value(&self, name: &str, input: &mut Parser) -> Result<(Vec<PropertyDeclaration>, bool), ()> {
+    fn parse_value(&self, name: &str, input: &mut Parser)
+                   -> Result<(Vec<PropertyDeclaration>, bool, Option<Shorthand>), ()> {
         let mut results = vec![];
         try!(input.parse_until_before(Delimiter::Bang, |input| {
             match PropertyDeclaration::parse(name, self.context, input, &mut results) {
@@ -5737,7 +9030,7 @@ impl<'a, 'b> DeclarationParser for PropertyDeclarationParser<'a, 'b> {
             }
         }));
         let important = input.try(parse_important).is_ok();
-        Ok((results, important))
+        Ok((results, important, Shorthand::from_name(name)))
     }
 }
 
@@ -5746,13 +9039,22 @@ pub fn parse_property_declaration_list(context: &ParserContext, input: &mut Pars
                                        -> PropertyDeclarationBlock {
     let mut important_declarations = Vec::new();
     let mut normal_declarations = Vec::new();
+    let mut shorthands: Vec<(Shorthand, bool)> = Vec::new();
     let parser = PropertyDeclarationParser {
         context: context,
     };
     let mut iter = DeclarationListParser::new(input, parser);
     while let Some(declaration) = iter.next() {
         match declaration {
-            Ok((results, important)) => {
+            Ok((results, important, shorthand)) => {
+                // Only the declarations of the same importance override those of a shorthand.
+                shorthands.retain(|&(written, written_important)| {
+                    written_important != important ||
+                        !written.longhands().iter().any(|longhand| results.iter().any(|d| d.matches(longhand)))
+                });
+                if let Some(shorthand) = shorthand {
+                    shorthands.push((shorthand, important));
+                }
                 if important {
                     important_declarations.extend(results);
                 } else {
@@ -5770,14 +9072,15 @@ pub fn parse_property_declaration_list(context: &ParserContext, input: &mut Pars
     PropertyDeclarationBlock {
         important: Arc::new(deduplicate_property_declarations(important_declarations)),
         normal: Arc::new(deduplicate_property_declarations(normal_declarations)),
+        shorthands: shorthands,
     }
 }
 
 
 /// Only keep the last declaration for any given property.
 /// The input is in source order, output in reverse source order.
-fn deduplicate_property_declarations(declarations: Vec<PropertyDeclaration>)
-                                     -> Vec<PropertyDeclaration> {
+pub fn deduplicate_property_declarations(declarations: Vec<PropertyDeclaration>)
+                                         -> Vec<PropertyDeclaration> {
     let mut deduplicated = vec![];
     let mut seen = PropertyBitField::new();
     let mut seen_custom = Vec::new();
@@ -5843,6 +9146,14 @@ impl Shorthand {
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        match *self {
+            % for property in SHORTHANDS:
+                Shorthand::${property.camel_case} => "${property.name}",
+            % endfor
+        }
+    }
+
     pub fn longhands(&self) -> &'static [&'static str] {
         % for property in SHORTHANDS:
             static ${property.ident.upper()}: &'static [&'static str] = &[
@@ -5899,6 +9210,59 @@ pub enum PropertyDeclaration {
 }
 
 
+<%
+    ENABLEABLE_PROPERTIES = [p for p in LONGHANDS if p.derived_from is None] + SHORTHANDS
+    PROPERTY_INDICES = dict((p.name, i) for i, p in enumerate(ENABLEABLE_PROPERTIES))
+%>
+/// The bits of the entries of PROPERTIES_DISABLED.
+const DISABLED_FOR_CONTENT: usize = 1;
+const DISABLED_FOR_UA: usize = 2;
+
+/// The origins each property has been disabled for with `set_property_enabled`, in the order
+/// `property_index` numbers them. Everything starts out enabled.
+static PROPERTIES_DISABLED: [AtomicUsize; ${len(ENABLEABLE_PROPERTIES)}] = [
+    % for property in ENABLEABLE_PROPERTIES:
+        ATOMIC_USIZE_INIT,
+    % endfor
+];
+
+fn property_index(name: &str) -> Option<usize> {
+    match_ignore_ascii_case! { name,
+        % for property in ENABLEABLE_PROPERTIES:
+            "${property.name}" => Some(${PROPERTY_INDICES[property.name]}),
+        % endfor
+        _ => None
+    }
+}
+
+/// Makes the property called `name` available to user agent sheets or not, and to those of
+/// the other origins or not, for embedders that decide that at runtime, as Gecko does with
+/// its prefs and its chrome-only properties. Sheets that can't use a property parse its
+/// declarations as those of an unknown one. Returns false if there's no such property.
+///
+/// Only what gets parsed afterwards is affected: declarations already parsed stay as they
+/// are, so sheets have to be parsed again to pick up the change.
+pub fn set_property_enabled(name: &str, enabled_for_content: bool, enabled_for_ua: bool) -> bool {
+    let index = match property_index(name) {
+        Some(index) => index,
+        None => return false,
+    };
+    let mut disabled = 0;
+    if !enabled_for_content {
+        disabled |= DISABLED_FOR_CONTENT;
+    }
+    if !enabled_for_ua {
+        disabled |= DISABLED_FOR_UA;
+    }
+    PROPERTIES_DISABLED[index].store(disabled, Ordering::Relaxed);
+    true
+}
+
+fn is_property_enabled(index: usize, origin: Origin) -> bool {
+    let bit = if origin == Origin::UserAgent { DISABLED_FOR_UA } else { DISABLED_FOR_CONTENT };
+    PROPERTIES_DISABLED[index].load(Ordering::Relaxed) & bit == 0
+}
+
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub enum PropertyDeclarationParseResult {
     UnknownProperty,
@@ -5939,7 +9303,67 @@ impl fmt::Display for PropertyDeclarationName {
     }
 }
 
+/// A pseudo-element that only some properties apply to. Declarations of the others are
+/// ignored when cascading it, rather than being invalid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PropertyRestriction {
+    /// https://drafts.csswg.org/css-pseudo/#first-letter-styling
+    FirstLetter,
+    /// https://drafts.csswg.org/css-pseudo/#first-line-styling
+    FirstLine,
+}
+
+<%
+    FIRST_LINE_STYLE_STRUCTS = ["Background", "Color", "Font"]
+    FIRST_LINE_PROPERTIES = ["letter-spacing", "line-height", "text-decoration-line", "text-decoration-style",
+                             "text-decoration-color", "text-shadow", "text-transform", "vertical-align",
+                             "word-spacing"]
+    FIRST_LETTER_STYLE_STRUCTS = FIRST_LINE_STYLE_STRUCTS + ["Border", "Margin", "Padding"]
+    FIRST_LETTER_PROPERTIES = FIRST_LINE_PROPERTIES + ["float"]
+
+    def applies_to_first_line(property):
+        return (property.style_struct.name in FIRST_LINE_STYLE_STRUCTS or
+                property.name in FIRST_LINE_PROPERTIES)
+
+    def applies_to_first_letter(property):
+        return (property.style_struct.name in FIRST_LETTER_STYLE_STRUCTS or
+                property.name in FIRST_LETTER_PROPERTIES)
+
+    # The properties :visited rules may set, which are all colors.
+    VISITED_DEPENDENT_PROPERTIES = ["color", "background-color", "border-top-color", "border-right-color",
+                                    "border-bottom-color", "border-left-color", "outline-color", "fill", "stroke"]
+%>
+
 impl PropertyDeclaration {
+    /// Whether the declaration applies to a pseudo-element that `restriction` limits.
+    /// Custom properties always do.
+    pub fn applies_with(&self, restriction: PropertyRestriction) -> bool {
+        match *self {
+            % for property in LONGHANDS:
+                % if applies_to_first_line(property):
+                    PropertyDeclaration::${property.camel_case}(..) => true,
+                % elif applies_to_first_letter(property):
+                    PropertyDeclaration::${property.camel_case}(..) => {
+                        restriction == PropertyRestriction::FirstLetter
+                    }
+                % endif
+            % endfor
+            PropertyDeclaration::Custom(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the declaration may apply to the style a link would have if it were visited.
+    /// Only colors may, so that the layout of a page can't reveal which links were visited.
+    pub fn applies_to_visited_style(&self) -> bool {
+        match *self {
+            % for name in VISITED_DEPENDENT_PROPERTIES:
+                PropertyDeclaration::${LONGHANDS_BY_NAME[name].camel_case}(..) => true,
+            % endfor
+            _ => false,
+        }
+    }
+
     pub fn name(&self) -> PropertyDeclarationName {
         match *self {
             % for property in LONGHANDS:
@@ -5969,6 +9393,27 @@ impl PropertyDeclaration {
         }
     }
 
+    /// The initial value of the declared longhand, serialized.
+    pub fn initial_value(&self) -> String {
+        match *self {
+            % for property in LONGHANDS:
+                % if property.derived_from is None:
+                    PropertyDeclaration::${property.camel_case}(_) =>
+                        longhands::${property.ident}::get_initial_value().to_css_string(),
+                % endif
+            % endfor
+            ref decl => panic!("unsupported property declaration: {}", decl.name()),
+        }
+    }
+
+    /// Whether this sets a longhand to its initial value, either with `initial` or by
+    /// spelling the value out.
+    pub fn sets_initial_value(&self) -> bool {
+        // Keywords keep the case they were written in.
+        let value = self.value();
+        value == "initial" || value.eq_ignore_ascii_case(&self.initial_value())
+    }
+
     /// If this is a pending-substitution value from the given shorthand, return that value
     // Extra space here because < seems to be removed by Mako when immediately followed by &.
     //                                                                          ↓
@@ -6004,6 +9449,26 @@ impl PropertyDeclaration {
         }
     }
 
+    /// The shorthands this declaration's property belongs to, with the ones covering the
+    /// most longhands first.
+    pub fn shorthands(&self) -> &'static [Shorthand] {
+        % for property in LONGHANDS:
+            <% shorthands = sorted([s for s in SHORTHANDS if property in s.sub_properties],
+                                   key=lambda s: -len(s.sub_properties)) %>
+            static ${property.ident.upper()}: &'static [Shorthand] = &[
+                % for shorthand in shorthands:
+                    Shorthand::${shorthand.camel_case},
+                % endfor
+            ];
+        % endfor
+        match *self {
+            % for property in LONGHANDS:
+                PropertyDeclaration::${property.camel_case}(_) => ${property.ident.upper()},
+            % endfor
+            PropertyDeclaration::Custom(..) => &[],
+        }
+    }
+
     pub fn matches(&self, name: &str) -> bool {
         match *self {
             % for property in LONGHANDS:
@@ -6036,6 +9501,9 @@ impl PropertyDeclaration {
             return PropertyDeclarationParseResult::ValidOrIgnoredDeclaration;
         }
         match_ignore_ascii_case! { name,
+            % for alias, property in sorted(ALIASES.items()):
+                "${alias}" => PropertyDeclaration::parse("${property}", context, input, result_list),
+            % endfor
             % for property in LONGHANDS:
                 % if property.derived_from is None:
                     "${property.name}" => {
@@ -6044,6 +9512,9 @@ impl PropertyDeclaration {
                                 return PropertyDeclarationParseResult::UnknownProperty
                             }
                         % endif
+                        if !is_property_enabled(${PROPERTY_INDICES[property.name]}, context.stylesheet_origin) {
+                            return PropertyDeclarationParseResult::UnknownProperty
+                        }
                         % if property.experimental:
                             if !::util::prefs::get_pref("${property.experimental}")
                                 .as_boolean().unwrap_or(false) {
@@ -6069,6 +9540,9 @@ impl PropertyDeclaration {
                             return PropertyDeclarationParseResult::UnknownProperty
                         }
                     % endif
+                    if !is_property_enabled(${PROPERTY_INDICES[shorthand.name]}, context.stylesheet_origin) {
+                        return PropertyDeclarationParseResult::UnknownProperty
+                    }
                     % if shorthand.experimental:
                         if !::util::prefs::get_pref("${shorthand.experimental}")
                             .as_boolean().unwrap_or(false) {
@@ -6176,6 +9650,17 @@ pub mod style_structs {
                 fn transition_count(&self) -> usize {
                     self.transition_property.0.len()
                 }
+                % for name in ["property", "duration", "delay", "timing_function"]:
+                fn clone_transition_${name}(&self) -> longhands::transition_${name}::computed_value::T {
+                    self.transition_${name}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "Background":
+                % for ident in BACKGROUND_LAYER_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
             % elif style_struct.name == "Border":
                 % for side in ["top", "right", "bottom", "left"]:
                 fn border_${side}_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
@@ -6183,6 +9668,23 @@ pub mod style_structs {
                     self.border_${side}_width != ::app_units::Au(0)
                 }
                 % endfor
+                % for ident in BORDER_SIDE_LONGHANDS + BORDER_IMAGE_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name in ["Margin", "Padding"]:
+                % for ident in MARGIN_LONGHANDS if style_struct.name == "Margin" else PADDING_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "PositionOffsets":
+                % for side in ["top", "right", "bottom", "left"]:
+                fn clone_${side}(&self) -> longhands::${side}::computed_value::T {
+                    self.${side}.clone()
+                }
+                % endfor
             % elif style_struct.name == "Box":
                 fn clone_display(&self) -> longhands::display::computed_value::T {
                     self.display.clone()
@@ -6190,8 +9692,32 @@ pub mod style_structs {
                 fn clone_position(&self) -> longhands::position::computed_value::T {
                     self.position.clone()
                 }
+                fn clone_float(&self) -> longhands::float::computed_value::T {
+                    self.float.clone()
+                }
+                fn clone_z_index(&self) -> longhands::z_index::computed_value::T {
+                    self.z_index.clone()
+                }
+                fn clone_width(&self) -> longhands::width::computed_value::T {
+                    self.width.clone()
+                }
+                fn clone_height(&self) -> longhands::height::computed_value::T {
+                    self.height.clone()
+                }
+                fn clone_content(&self) -> longhands::content::computed_value::T {
+                    self.content.clone()
+                }
+                fn content_reads_attributes(&self) -> bool {
+                    !self.content.attribute_names().is_empty()
+                }
+                fn clone_will_change(&self) -> longhands::will_change::computed_value::T {
+                    self.will_change.clone()
+                }
+                fn clone_contain(&self) -> longhands::contain::computed_value::T {
+                    self.contain.clone()
+                }
                 fn is_floated(&self) -> bool {
-                    self.float != longhands::float::SpecifiedValue::none
+                    self.float != longhands::float::computed_value::T::none
                 }
                 fn overflow_x_is_visible(&self) -> bool {
                     self.overflow_x == longhands::overflow_x::computed_value::T::visible
@@ -6199,17 +9725,57 @@ pub mod style_structs {
                 fn overflow_y_is_visible(&self) -> bool {
                     self.overflow_y.0 == longhands::overflow_x::computed_value::T::visible
                 }
+                % for ident in SCROLL_FRAME_LONGHANDS + BOX_STYLE_STRUCT_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "Column":
+                fn column_rule_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
+                    self.column_rule_style.none_or_hidden() && self.column_rule_width != ::app_units::Au(0)
+                }
+                % for ident in COLUMN_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "Flex":
+                % for ident in FLEX_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "Counters":
+                % for ident in COUNTER_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
             % elif style_struct.name == "Color":
                 fn clone_color(&self) -> longhands::color::computed_value::T {
                     self.color.clone()
                 }
-            % elif style_struct.name == "Font":
-                fn clone_font_size(&self) -> longhands::font_size::computed_value::T {
-                    self.font_size.clone()
+            % elif style_struct.name == "Effects":
+                fn clone_transform(&self) -> longhands::transform::computed_value::T {
+                    self.transform.clone()
+                }
+                fn clone_isolation(&self) -> longhands::isolation::computed_value::T {
+                    self.isolation.clone()
                 }
-                fn clone_font_weight(&self) -> longhands::font_weight::computed_value::T {
-                    self.font_weight.clone()
+                fn clone_box_shadow(&self) -> longhands::box_shadow::computed_value::T {
+                    self.box_shadow.clone()
+                }
+                % for ident in EFFECTS_STYLE_STRUCT_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
+            % elif style_struct.name == "Font":
+                % for ident in FONT_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
                 }
+                % endfor
                 fn compute_font_hash(&mut self) {
                     // Corresponds to the fields in `gfx::font_template::FontTemplateDescriptor`.
                     let mut hasher: FnvHasher = Default::default();
@@ -6218,6 +9784,10 @@ pub mod style_structs {
                     self.font_family.hash(&mut hasher);
                     self.hash = hasher.finish()
                 }
+                fn font_metrics(&self) -> Option<::values::computed::FontMetrics> {
+                    // Fonts are only resolved during layout, after the cascade.
+                    None
+                }
             % elif style_struct.name == "InheritedBox":
                 fn clone_direction(&self) -> longhands::direction::computed_value::T {
                     self.direction.clone()
@@ -6228,25 +9798,56 @@ pub mod style_structs {
                 fn clone_text_orientation(&self) -> longhands::text_orientation::computed_value::T {
                     self.text_orientation.clone()
                 }
+                fn clone_visibility(&self) -> longhands::visibility::computed_value::T {
+                    self.visibility.clone()
+                }
+            % elif style_struct.name == "Pointing":
+                fn clone_pointer_events(&self) -> longhands::pointer_events::computed_value::T {
+                    self.pointer_events.clone()
+                }
+                fn clone_cursor(&self) -> longhands::cursor::computed_value::T {
+                    self.cursor.clone()
+                }
+                fn clone_fill(&self) -> longhands::fill::computed_value::T {
+                    self.fill.clone()
+                }
+                fn clone_stroke(&self) -> longhands::stroke::computed_value::T {
+                    self.stroke.clone()
+                }
             % elif style_struct.name == "InheritedText":
                 fn clone__servo_text_decorations_in_effect(&self) ->
                     longhands::_servo_text_decorations_in_effect::computed_value::T {
                     self._servo_text_decorations_in_effect.clone()
                 }
+                % for ident in LINE_LAYOUT_LONGHANDS + TEXT_EMPHASIS_LONGHANDS + ["text_shadow", "text_overflow"]:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
             % elif style_struct.name == "Outline":
                 fn outline_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
                     self.outline_style.none_or_hidden() && self.outline_width != ::app_units::Au(0)
                 }
+                % for ident in OUTLINE_LONGHANDS:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
+                }
+                % endfor
             % elif style_struct.name == "Text":
                 fn has_underline(&self) -> bool {
-                    self.text_decoration.underline
+                    self.text_decoration_line.underline
                 }
                 fn has_overline(&self) -> bool {
-                    self.text_decoration.overline
+                    self.text_decoration_line.overline
                 }
                 fn has_line_through(&self) -> bool {
-                    self.text_decoration.line_through
+                    self.text_decoration_line.line_through
+                }
+                % for ident in TEXT_DECORATION_LONGHANDS + ["unicode_bidi"]:
+                fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+                    self.${ident}.clone()
                 }
+                % endfor
             % endif
         }
 
@@ -6287,8 +9888,35 @@ pub trait ComputedValues : Clone + Send + Sync + 'static {
     fn custom_properties(&self) -> Option<Arc<::custom_properties::ComputedValuesMap>>;
     fn root_font_size(&self) -> Au;
     fn set_root_font_size(&mut self, size: Au);
+    fn writing_mode(&self) -> WritingMode;
     fn set_writing_mode(&mut self, mode: WritingMode);
     fn is_multicol(&self) -> bool;
+
+    /// Remembers the id of the rule node the values were cascaded for. Implementations that
+    /// have no use for it can ignore it.
+    fn set_rule_node_id(&mut self, _id: usize) {}
+
+    /// The style the element would have if the link it's in were visited, for implementations
+    /// whose selectors tell visited links apart. Implementations that have no use for it
+    /// never keep one.
+    fn visited_style(&self) -> Option<&Arc<Self>> { None }
+    fn set_visited_style(&mut self, _style: Arc<Self>) {}
+
+    /// What the platform makes of a system color. A cascade only asks about each color once.
+    fn system_color(color: SystemColor) -> RGBA;
+
+    /// What the platform makes of a system font, or None if it doesn't know it, in which case
+    /// `font: <system font>` leaves the font longhands at their initial values.
+    fn system_font(font: SystemFont) -> Option<SystemFontValues>;
+
+    /// How big the platform makes `font-size: medium` for the generic family and language of
+    /// `font`, which the other font-size keywords are relative to.
+    fn medium_font_size(font: &Self::ConcreteFont) -> Au;
+
+    /// Starts loading the image at `url` for the sheet `extra_data` describes, the first
+    /// time a computed value needs it. Embedders that load images themselves later on
+    /// return None.
+    fn load_image(url: &Url, extra_data: &UrlExtraData) -> Option<Box<ImageRequest>>;
 }
 
 #[derive(Clone, HeapSizeOf)]
@@ -6358,6 +9986,7 @@ impl ComputedValues for ServoComputedValues {
 
     fn root_font_size(&self) -> Au { self.root_font_size }
     fn set_root_font_size(&mut self, size: Au) { self.root_font_size = size }
+    fn writing_mode(&self) -> WritingMode { self.writing_mode }
     fn set_writing_mode(&mut self, mode: WritingMode) { self.writing_mode = mode; }
 
     #[inline]
@@ -6365,6 +9994,14 @@ impl ComputedValues for ServoComputedValues {
         let style = self.get_column();
         style.column_count.0.is_some() || style.column_width.0.is_some()
     }
+
+    // Servo doesn't know the platform's colors and fonts.
+    fn system_color(color: SystemColor) -> RGBA { color.standin() }
+    fn system_font(_font: SystemFont) -> Option<SystemFontValues> { None }
+    fn medium_font_size(_font: &style_structs::Font) -> Au { Au::from_px(::values::FONT_MEDIUM_PX) }
+
+    // Layout fetches images itself through the image cache.
+    fn load_image(_url: &Url, _extra_data: &UrlExtraData) -> Option<Box<ImageRequest>> { None }
 }
 
 impl ServoComputedValues {
@@ -6386,40 +10023,45 @@ impl ServoComputedValues {
     pub fn content_inline_size(&self) -> computed::LengthOrPercentageOrAuto {
         let box_style = self.get_box();
         if self.writing_mode.is_vertical() {
-            box_style.height
+            box_style.height.to_length_or_percentage_or_auto()
         } else {
-            box_style.width
+            box_style.width.to_length_or_percentage_or_auto()
         }
     }
 
     #[inline]
     pub fn content_block_size(&self) -> computed::LengthOrPercentageOrAuto {
         let box_style = self.get_box();
-        if self.writing_mode.is_vertical() { box_style.width } else { box_style.height }
+        let size = if self.writing_mode.is_vertical() { box_style.width } else { box_style.height };
+        size.to_length_or_percentage_or_auto()
     }
 
     #[inline]
     pub fn min_inline_size(&self) -> computed::LengthOrPercentage {
         let box_style = self.get_box();
-        if self.writing_mode.is_vertical() { box_style.min_height } else { box_style.min_width }
+        let size = if self.writing_mode.is_vertical() { box_style.min_height } else { box_style.min_width };
+        size.to_length_or_percentage()
     }
 
     #[inline]
     pub fn min_block_size(&self) -> computed::LengthOrPercentage {
         let box_style = self.get_box();
-        if self.writing_mode.is_vertical() { box_style.min_width } else { box_style.min_height }
+        let size = if self.writing_mode.is_vertical() { box_style.min_width } else { box_style.min_height };
+        size.to_length_or_percentage()
     }
 
     #[inline]
     pub fn max_inline_size(&self) -> computed::LengthOrPercentageOrNone {
         let box_style = self.get_box();
-        if self.writing_mode.is_vertical() { box_style.max_height } else { box_style.max_width }
+        let size = if self.writing_mode.is_vertical() { box_style.max_height } else { box_style.max_width };
+        size.to_length_or_percentage_or_none()
     }
 
     #[inline]
     pub fn max_block_size(&self) -> computed::LengthOrPercentageOrNone {
         let box_style = self.get_box();
-        if self.writing_mode.is_vertical() { box_style.max_width } else { box_style.max_height }
+        let size = if self.writing_mode.is_vertical() { box_style.max_width } else { box_style.max_height };
+        size.to_length_or_percentage_or_none()
     }
 
     #[inline]
@@ -6547,9 +10189,47 @@ impl ServoComputedValues {
             }
         }
     }
+
+    /// The resolved value of a longhand or custom property, as `getComputedStyle` reports
+    /// it: the computed value, except that `currentColor` is resolved to the `color`.
+    ///
+    /// https://drafts.csswg.org/cssom/#resolved-values
+    pub fn resolved_value_to_string(&self, name: &str) -> Result<String, ()> {
+        if let Some(physical) = physical_longhand_name(name, self.writing_mode) {
+            return self.resolved_value_to_string(physical)
+        }
+        match name {
+            % for side in ["top", "right", "bottom", "left"]:
+            "border-${side}-color" => Ok(self.resolve_color(self.get_border().border_${side}_color).to_css_string()),
+            % endfor
+            "outline-color" => Ok(self.resolve_color(self.get_outline().outline_color).to_css_string()),
+            "column-rule-color" => Ok(self.resolve_color(self.get_column().column_rule_color).to_css_string()),
+            "background-color" => Ok(self.resolve_color(self.get_background().background_color).to_css_string()),
+            "text-decoration-color" => Ok(self.resolve_color(self.get_text().text_decoration_color).to_css_string()),
+            "text-emphasis-color" => {
+                Ok(self.resolve_color(self.get_inheritedtext().text_emphasis_color).to_css_string())
+            }
+            _ => self.computed_value_to_string(name),
+        }
+    }
 }
 
 
+/// The physical longhand that the logical longhand called `name` sets in `mode`, or None if
+/// `name` isn't a logical longhand.
+pub fn physical_longhand_name(name: &str, mode: WritingMode) -> Option<&'static str> {
+    match name {
+        % for property in LOGICAL_LONGHANDS:
+        "${property.name}" => Some(match mode.${to_rust_ident(property.logical_side)}_physical_side() {
+            % for side in PHYSICAL_SIDES:
+            PhysicalSide::${side.capitalize()} => "${property.physical_pattern % side}",
+            % endfor
+        }),
+        % endfor
+        _ => None,
+    }
+}
+
 /// Return a WritingMode bitflags from the relevant CSS properties.
 pub fn get_writing_mode<S: style_struct_traits::TInheritedBox>(inheritedbox_style: &S) -> WritingMode {
     use logical_geometry;
@@ -6606,6 +10286,55 @@ lazy_static! {
 }
 
 
+/// Makes an absolutely positioned box's float compute to none, as CSS 2.1 § 9.7 has it,
+/// whichever of `position` and `float` was cascaded first and whether the float was
+/// inherited.
+fn fix_up_float<C: ComputedValues>(style: &mut C) {
+    use properties::style_struct_traits::TBox;
+
+    let positioned = matches!(style.get_box().clone_position(),
+        longhands::position::SpecifiedValue::absolute |
+        longhands::position::SpecifiedValue::fixed);
+    if positioned && style.get_box().is_floated() {
+        style.mutate_box().set_float(longhands::float::computed_value::T::none);
+    }
+}
+
+/// Gives `style` the display `specified_display` computes to once the fixups of CSS Display
+/// § 2.7 are applied: the root, floats, absolutely positioned boxes and flex items are
+/// blockified. `display` and `position` and `float` must have been cascaded already.
+fn fix_up_display<C: ComputedValues>(style: &mut C,
+                                     specified_display: longhands::display::computed_value::T,
+                                     parent_style: &C,
+                                     is_root_element: bool) {
+    use computed_values::display::T;
+    use properties::style_struct_traits::TBox;
+
+    let positioned = matches!(style.get_box().clone_position(),
+        longhands::position::SpecifiedValue::absolute |
+        longhands::position::SpecifiedValue::fixed);
+    let floated = style.get_box().is_floated();
+    // The children of flex containers are flex items, which are blockified too.
+    let is_flex_item = !is_root_element && matches!(parent_style.get_box().clone_display(),
+        T::flex | T::inline_flex);
+    let computed_display = if positioned || floated || is_root_element || is_flex_item {
+        specified_display.blockified(is_root_element)
+    } else {
+        specified_display
+    };
+    let hypothetical_display = if is_root_element {
+        computed_display
+    } else {
+        specified_display
+    };
+    if style.get_box().clone_display() != computed_display ||
+            style.get_box().clone__servo_display_for_hypothetical_box() != hypothetical_display {
+        let box_ = style.mutate_box();
+        box_.set_display(computed_display);
+        box_.set__servo_display_for_hypothetical_box(hypothetical_display);
+    }
+}
+
 /// Fast path for the function below. Only computes new inherited styles.
 #[allow(unused_mut, unused_imports)]
 fn cascade_with_cached_declarations<C: ComputedValues>(
@@ -6617,10 +10346,14 @@ fn cascade_with_cached_declarations<C: ComputedValues>(
         custom_properties: Option<Arc<::custom_properties::ComputedValuesMap>>,
         mut error_reporter: Box<ParseErrorReporter + Send>)
         -> C {
+    use properties::style_struct_traits::TBox;
     let mut context = computed::Context {
         is_root_element: false,
         viewport_size: viewport_size,
         inherited_style: parent_style,
+        font_metrics: Cell::new(None),
+        system_colors: RefCell::new(HashMap::new()),
+        font_size_keyword: None,
         style: C::new(
             custom_properties,
             shareable,
@@ -6637,72 +10370,93 @@ fn cascade_with_cached_declarations<C: ComputedValues>(
         ),
     };
     let mut seen = PropertyBitField::new();
-    // Declaration blocks are stored in increasing precedence order,
-    // we want them in decreasing order here.
-    for sub_list in applicable_declarations.iter().rev() {
-        // Declarations are already stored in reverse order.
-        for declaration in sub_list.declarations.iter() {
-            match *declaration {
-                % for style_struct in STYLE_STRUCTS:
-                    % for property in style_struct.longhands:
-                        % if property.derived_from is None:
-                            PropertyDeclaration::${property.camel_case}(ref
-                                    ${'_' if not style_struct.inherited else ''}declared_value)
-                                    => {
-                                    use properties::style_struct_traits::T${style_struct.name};
-                                % if style_struct.inherited:
-                                    if seen.get_${property.ident}() {
-                                        continue
-                                    }
-                                    seen.set_${property.ident}();
-                                    let custom_props = context.style().custom_properties();
-                                    substitute_variables_${property.ident}(
-                                        declared_value, &custom_props,
-                                        |value| match *value {
-                                            DeclaredValue::Value(ref specified_value)
-                                            => {
-                                                let computed = specified_value.to_computed_value(&context);
-                                                context.mutate_style().mutate_${style_struct.name.lower()}()
-                                                       .set_${property.ident}(computed);
-                                            },
-                                            DeclaredValue::Initial
-                                            => {
-                                                // FIXME(bholley): We may want set_X_to_initial_value() here.
-                                                let initial = longhands::${property.ident}::get_initial_value();
-                                                context.mutate_style().mutate_${style_struct.name.lower()}()
-                                                       .set_${property.ident}(initial);
-                                            },
-                                            DeclaredValue::Inherit => {
-                                                // This is a bit slow, but this is rare so it shouldn't
-                                                // matter.
-                                                //
-                                                // FIXME: is it still?
-                                                let inherited_struct = parent_style.get_${style_struct.ident}();
-                                                context.mutate_style().mutate_${style_struct.name.lower()}()
-                                                       .copy_${property.ident}_from(inherited_struct);
-                                            }
-                                            DeclaredValue::WithVariables { .. } => unreachable!()
-                                        }, &mut error_reporter
-                                    );
-                                % endif
-
-                                % if property.name in DERIVED_LONGHANDS:
-                                    % for derived in DERIVED_LONGHANDS[property.name]:
-                                            longhands::${derived.ident}
-                                                     ::derive_from_${property.ident}(&mut context);
-                                    % endfor
-                                % endif
-                            }
-                        % else:
-                            PropertyDeclaration::${property.camel_case}(_) => {
-                                // Do not allow stylesheets to set derived properties.
-                            }
-                        % endif
+    // Like in `cascade`, the properties others depend on are cascaded first. Declaration
+    // blocks are stored in increasing precedence order, we want them in decreasing order here.
+    for &early in &[true, false] {
+        for sub_list in applicable_declarations.iter().rev() {
+            // Declarations are already stored in reverse order.
+            for declaration in sub_list.declarations.iter() {
+                if is_early_property(declaration) != early {
+                    continue
+                }
+                match *declaration {
+                    % for style_struct in STYLE_STRUCTS:
+                        % for property in style_struct.longhands:
+                            % if property.derived_from is None:
+                                PropertyDeclaration::${property.camel_case}(ref
+                                        ${'_' if not style_struct.inherited else ''}declared_value)
+                                        => {
+                                        use properties::style_struct_traits::T${style_struct.name};
+                                    % if style_struct.inherited:
+                                        if seen.get_${property.ident}() {
+                                            continue
+                                        }
+                                        seen.set_${property.ident}();
+                                        let custom_props = context.style().custom_properties();
+                                        substitute_variables_${property.ident}(
+                                            declared_value, &custom_props,
+                                            |value| match *value {
+                                                DeclaredValue::Value(ref specified_value)
+                                                => {
+                                                    let computed = specified_value.to_computed_value(&context);
+                                                    context.mutate_style().mutate_${style_struct.name.lower()}()
+                                                           .set_${property.ident}(computed);
+                                                },
+                                                DeclaredValue::Initial
+                                                => {
+                                                    // FIXME(bholley): We may want set_X_to_initial_value() here.
+                                                    let initial = longhands::${property.ident}::get_initial_value();
+                                                    context.mutate_style().mutate_${style_struct.name.lower()}()
+                                                           .set_${property.ident}(initial);
+                                                },
+                                                DeclaredValue::Inherit => {
+                                                    // This is a bit slow, but this is rare so it shouldn't
+                                                    // matter.
+                                                    //
+                                                    // FIXME: is it still?
+                                                    let inherited_struct = parent_style.get_${style_struct.ident}();
+                                                    context.mutate_style().mutate_${style_struct.name.lower()}()
+                                                           .copy_${property.ident}_from(inherited_struct);
+                                                }
+                                                DeclaredValue::WithVariables { .. } => unreachable!()
+                                            }, &mut error_reporter
+                                        );
+                                        % if property.custom_cascade:
+                                            let mut cacheable = true;
+                                            longhands::${property.ident}::cascade_property_custom(
+                                                declaration, parent_style, &mut context, &mut seen,
+                                                &mut cacheable, &mut error_reporter);
+                                        % endif
+                                    % endif
+
+                                    % if property.name in DERIVED_LONGHANDS:
+                                        % for derived in DERIVED_LONGHANDS[property.name]:
+                                                longhands::${derived.ident}
+                                                         ::derive_from_${property.ident}(&mut context);
+                                        % endfor
+                                    % endif
+                                }
+                            % else:
+                                PropertyDeclaration::${property.camel_case}(_) => {
+                                    // Do not allow stylesheets to set derived properties.
+                                }
+                            % endif
+                        % endfor
                     % endfor
-                % endfor
-                PropertyDeclaration::Custom(..) => {}
+                    % for property in LOGICAL_LONGHANDS:
+                        // The longhands they map to aren't inherited.
+                        PropertyDeclaration::${property.camel_case}(_) => {}
+                    % endfor
+                    PropertyDeclaration::Custom(..) => {}
+                }
             }
         }
+        if early {
+            compute_font_size_keyword(&mut context);
+            context.font_metrics.set(None);
+            let mode = get_writing_mode(context.style.get_inheritedbox());
+            context.style.set_writing_mode(mode);
+        }
     }
 
     if seen.get_font_style() || seen.get_font_weight() || seen.get_font_stretch() ||
@@ -6711,7 +10465,48 @@ fn cascade_with_cached_declarations<C: ComputedValues>(
         context.mutate_style().mutate_font().compute_font_hash();
     }
 
-    context.style
+    // The cached style's display was fixed up under its own parent, which needn't agree with
+    // this one on being a flex container. The display of the hypothetical box is the
+    // specified one for any element but the root, and the root's style is never cached.
+    let mut style = context.style;
+    let specified_display = style.get_box().clone__servo_display_for_hypothetical_box();
+    fix_up_display(&mut style, specified_display, parent_style, false);
+    style
+}
+
+/// Whether the cascade sets the property `declaration` is for before the others. These are
+/// the properties that the computed values of others depend on. The font properties are all
+/// early so that em lengths see the final font size, and ex and ch lengths the final font
+/// when looking up its metrics. The properties making up the writing mode are early so that
+/// the logical properties can be mapped onto physical sides.
+fn is_early_property(declaration: &PropertyDeclaration) -> bool {
+    matches!(*declaration,
+        PropertyDeclaration::FontSize(_) |
+        PropertyDeclaration::FontSizeAdjust(_) |
+        PropertyDeclaration::FontFamily(_) |
+        PropertyDeclaration::FontStyle(_) |
+        PropertyDeclaration::FontWeight(_) |
+        PropertyDeclaration::FontStretch(_) |
+        PropertyDeclaration::XLang(_) |
+        PropertyDeclaration::ServoSystemFont(_) |
+        PropertyDeclaration::Color(_) |
+        PropertyDeclaration::Position(_) |
+        PropertyDeclaration::TextDecorationLine(_) |
+        PropertyDeclaration::TextDecorationColor(_) |
+        PropertyDeclaration::WritingMode(_) |
+        PropertyDeclaration::Direction(_) |
+        PropertyDeclaration::TextOrientation(_)
+    )
+}
+
+/// Works out what the font-size keyword the early properties left the style with comes to,
+/// now that the font family and language it depends on are final.
+fn compute_font_size_keyword<C: ComputedValues>(context: &mut computed::Context<C>) {
+    use properties::style_struct_traits::TFont;
+    if let Some(keyword) = context.font_size_keyword {
+        let size = keyword.to_size(C::medium_font_size(context.style.get_font()));
+        context.style.mutate_font().set_font_size(size);
+    }
 }
 
 pub type CascadePropertyFn<C /*: ComputedValues */> =
@@ -6726,23 +10521,78 @@ pub type CascadePropertyFn<C /*: ComputedValues */> =
 // properties.
 thread_local!(static CASCADE_PROPERTY: Vec<Option<CascadePropertyFn<ServoComputedValues>>> = {
     let mut result: Vec<Option<CascadePropertyFn<ServoComputedValues>>> = Vec::new();
-    % for style_struct in STYLE_STRUCTS:
-        % for property in style_struct.longhands:
-            let discriminant;
-            unsafe {
-                let variant = PropertyDeclaration::${property.camel_case}(intrinsics::uninit());
-                discriminant = intrinsics::discriminant_value(&variant) as usize;
-                mem::forget(variant);
-            }
-            while result.len() < discriminant + 1 {
-                result.push(None)
-            }
-            result[discriminant] = Some(longhands::${property.ident}::cascade_property);
-        % endfor
+    % for property in LONGHANDS:
+        let discriminant;
+        unsafe {
+            let variant = PropertyDeclaration::${property.camel_case}(intrinsics::uninit());
+            discriminant = intrinsics::discriminant_value(&variant) as usize;
+            mem::forget(variant);
+        }
+        while result.len() < discriminant + 1 {
+            result.push(None)
+        }
+        result[discriminant] = Some(longhands::${property.ident}::cascade_property);
     % endfor
     result
 });
 
+/// Computes the style of a node that no rule applies to, like a text node: inherited
+/// properties take their values from `parent` and the others their initial values. No
+/// style struct is copied; each one is shared with `parent` or with the initial values.
+pub fn inherit_from<C: ComputedValues>(parent: &C) -> C {
+    let initial_values = C::initial_values();
+    C::new(parent.custom_properties(),
+           false,
+           get_writing_mode(parent.get_inheritedbox()),
+           parent.root_font_size(),
+           % for style_struct in STYLE_STRUCTS:
+               % if style_struct.inherited:
+           parent.clone_${style_struct.name.lower()}(),
+               % else:
+           initial_values.clone_${style_struct.name.lower()}(),
+               % endif
+           % endfor
+    )
+}
+
+/// The declarations of `applicable_declarations` that apply to a pseudo-element that
+/// `restriction` limits, for cascading it. Blocks left without any are dropped.
+pub fn restrict_declarations(applicable_declarations: &[DeclarationBlock<Vec<PropertyDeclaration>>],
+                             restriction: PropertyRestriction)
+                             -> Vec<DeclarationBlock<Vec<PropertyDeclaration>>> {
+    filter_declarations(applicable_declarations, |declaration| declaration.applies_with(restriction))
+}
+
+/// The declarations of `applicable_declarations` that may apply to the visited style of a
+/// link, for cascading it. Blocks left without any are dropped.
+pub fn visited_style_declarations(applicable_declarations: &[DeclarationBlock<Vec<PropertyDeclaration>>])
+                                  -> Vec<DeclarationBlock<Vec<PropertyDeclaration>>> {
+    filter_declarations(applicable_declarations, PropertyDeclaration::applies_to_visited_style)
+}
+
+fn filter_declarations<F>(applicable_declarations: &[DeclarationBlock<Vec<PropertyDeclaration>>],
+                          applies: F)
+                          -> Vec<DeclarationBlock<Vec<PropertyDeclaration>>>
+                          where F: Fn(&PropertyDeclaration) -> bool {
+    applicable_declarations.iter().filter_map(|block| {
+        if block.declarations.iter().all(|declaration| applies(declaration)) {
+            return Some(block.clone());
+        }
+        let declarations: Vec<_> = block.declarations.iter()
+                                        .filter(|declaration| applies(declaration))
+                                        .cloned()
+                                        .collect();
+        if declarations.is_empty() {
+            return None;
+        }
+        Some(DeclarationBlock {
+            declarations: Arc::new(declarations),
+            source_order: block.source_order,
+            specificity: block.specificity,
+        })
+    }).collect()
+}
+
 /// Performs the CSS cascade, computing new styles for an element from its parent style and
 /// optionally a cached related style. The arguments are:
 ///
@@ -6769,7 +10619,7 @@ pub fn cascade<C: ComputedValues>(
                cached_style: Option<<&C>,
                mut error_reporter: Box<ParseErrorReporter + Send>)
                -> (C, bool) {
-    use properties::style_struct_traits::{TBorder, TBox, TColor, TFont, TOutline};
+    use properties::style_struct_traits::{TBorder, TBox, TColor, TColumn, TFont, TOutline};
     let initial_values = C::initial_values();
     let (is_root_element, inherited_style) = match parent_style {
         Some(parent_style) => (false, parent_style),
@@ -6810,6 +10660,9 @@ pub fn cascade<C: ComputedValues>(
         is_root_element: is_root_element,
         viewport_size: viewport_size,
         inherited_style: inherited_style,
+        font_metrics: Cell::new(None),
+        system_colors: RefCell::new(HashMap::new()),
+        font_size_keyword: None,
         style: C::new(
             custom_properties,
             shareable,
@@ -6850,18 +10703,11 @@ pub fn cascade<C: ComputedValues>(
                     // We iterate applicable_declarations twice, first cascading "early" properties
                     // then "other".
                     // Unfortunately, it’s not easy to check that this classification is correct.
-                    let is_early_property = matches!(*declaration,
-                        PropertyDeclaration::FontSize(_) |
-                        PropertyDeclaration::Color(_) |
-                        PropertyDeclaration::Position(_) |
-                        PropertyDeclaration::Float(_) |
-                        PropertyDeclaration::TextDecoration(_)
-                    );
                     if
                         % if category_to_cascade_now == "early":
                             !
                         % endif
-                        is_early_property
+                        is_early_property(declaration)
                     {
                         continue
                     }
@@ -6876,42 +10722,22 @@ pub fn cascade<C: ComputedValues>(
                                                               &mut error_reporter);
                 }
             }
+            % if category_to_cascade_now == "early":
+                compute_font_size_keyword(&mut context);
+                // Anything looked up while the font was still changing is stale.
+                context.font_metrics.set(None);
+                // The logical properties map onto physical sides by the writing mode.
+                let mode = get_writing_mode(context.style.get_inheritedbox());
+                context.style.set_writing_mode(mode);
+            % endif
         % endfor
     });
 
     let mut style = context.style;
 
-    let positioned = matches!(style.get_box().clone_position(),
-        longhands::position::SpecifiedValue::absolute |
-        longhands::position::SpecifiedValue::fixed);
-    let floated = style.get_box().is_floated();
-    if positioned || floated || is_root_element {
-        use computed_values::display::T;
-
-        let specified_display = style.get_box().clone_display();
-        let computed_display = match specified_display {
-            T::inline_table => {
-                Some(T::table)
-            }
-            T::inline | T::inline_block |
-            T::table_row_group | T::table_column |
-            T::table_column_group | T::table_header_group |
-            T::table_footer_group | T::table_row | T::table_cell |
-            T::table_caption => {
-                Some(T::block)
-            }
-            _ => None
-        };
-        if let Some(computed_display) = computed_display {
-            let box_ = style.mutate_box();
-            box_.set_display(computed_display);
-            box_.set__servo_display_for_hypothetical_box(if is_root_element {
-                computed_display
-            } else {
-                specified_display
-            });
-        }
-    }
+    fix_up_float(&mut style);
+    let specified_display = style.get_box().clone_display();
+    fix_up_display(&mut style, specified_display, inherited_style, is_root_element);
 
     {
         use computed_values::overflow_x::T as overflow;
@@ -6941,6 +10767,11 @@ pub fn cascade<C: ComputedValues>(
         style.mutate_outline().set_outline_width(Au(0));
     }
 
+    // So may the column rule width's.
+    if style.get_column().column_rule_is_none_or_hidden_and_has_nonzero_width() {
+        style.mutate_column().set_column_rule_width(Au(0));
+    }
+
     if is_root_element {
         let s = style.get_font().clone_font_size();
         style.set_root_font_size(s);
@@ -6954,7 +10785,8 @@ pub fn cascade<C: ComputedValues>(
 
     let mode = get_writing_mode(style.get_inheritedbox());
     style.set_writing_mode(mode);
-    (style, cacheable)
+    // The root's display is fixed up from one the cached path couldn't get back.
+    (style, cacheable && !is_root_element)
 }
 
 /// Alters the given style to accommodate replaced content. This is called in flow construction. It
@@ -7124,6 +10956,9 @@ pub fn is_supported_property(property: &str) -> bool {
         % for property in SHORTHANDS + LONGHANDS:
             "${property.name}" => true,
         % endfor
+        % for alias in sorted(ALIASES):
+            "${alias}" => true,
+        % endfor
         _ => property.starts_with("--")
     }
 }
@@ -3,14 +3,18 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use attr::{AttrIdentifier, AttrValue};
+use dom::TElement;
 use element_state::*;
 use selector_impl::SelectorImplExt;
 use selectors::Element;
 use selectors::matching::matches_compound_selector;
 use selectors::parser::{AttrSelector, Combinator, CompoundSelector, NamespaceConstraint, SelectorImpl, SimpleSelector};
 use std::clone::Clone;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
 use std::sync::Arc;
 use string_cache::{Atom, Namespace};
+use stylesheets::Origin;
 
 /// When the ElementState of an element (like IN_HOVER_STATE) changes, certain
 /// pseudo-classes (like :hover) may require us to restyle that element, its
@@ -20,6 +24,7 @@ use string_cache::{Atom, Namespace};
 /// short-circuit work we know is unnecessary.
 
 bitflags! {
+    #[derive(HeapSizeOf)]
     flags RestyleHint: u8 {
         #[doc = "Rerun selector matching on the element."]
         const RESTYLE_SELF = 0x01,
@@ -50,14 +55,45 @@ bitflags! {
 /// now to reduce complexity, but it's worth measuring the performance impact (if any) of the
 /// mStateMask approach.
 
+/// The information about an element's past state that restyle hint computation needs.
+///
+/// Servo records this as a copy of the element's state and attributes (see
+/// ServoElementSnapshot), while embedders like Gecko can keep a cheaper representation
+/// on their side of the FFI boundary.
+pub trait ElementSnapshot {
+    /// The state of the element at the time of the snapshot, if its state has changed
+    /// since.
+    fn state(&self) -> Option<ElementState>;
+
+    /// Whether the snapshot captured the element's attributes, i.e. whether any of them
+    /// have changed since.
+    fn has_attrs(&self) -> bool;
+
+    /// The value of the id attribute. Only called if has_attrs() is true.
+    fn id_attr(&self) -> Option<Atom>;
+
+    /// Whether the class attribute contained the given class. Only called if has_attrs()
+    /// is true.
+    fn has_class(&self, name: &Atom) -> bool;
+
+    /// Calls the callback for each class in the class attribute. Only called if
+    /// has_attrs() is true.
+    fn each_class<F>(&self, callback: F) where F: FnMut(&Atom);
+
+    /// Matches the attribute selector against the old attribute values. Only called if
+    /// has_attrs() is true.
+    fn match_attr<F>(&self, attr: &AttrSelector, is_html: bool, test: F) -> bool
+                    where F: Fn(&str) -> bool;
+}
+
 #[derive(HeapSizeOf, Clone)]
-pub struct ElementSnapshot {
+pub struct ServoElementSnapshot {
     pub state: Option<ElementState>,
     pub attrs: Option<Vec<(AttrIdentifier, AttrValue)>>,
 }
 
-impl ElementSnapshot {
-    pub fn new() -> ElementSnapshot {
+impl ServoElementSnapshot {
+    pub fn new() -> ServoElementSnapshot {
         EMPTY_SNAPSHOT.clone()
     }
 
@@ -76,30 +112,72 @@ impl ElementSnapshot {
     }
 }
 
-static EMPTY_SNAPSHOT: ElementSnapshot = ElementSnapshot { state: None, attrs: None };
+impl ElementSnapshot for ServoElementSnapshot {
+    fn state(&self) -> Option<ElementState> {
+        self.state
+    }
+
+    fn has_attrs(&self) -> bool {
+        self.attrs.is_some()
+    }
+
+    fn id_attr(&self) -> Option<Atom> {
+        self.get_attr(&ns!(), &atom!("id")).map(|value| value.as_atom().clone())
+    }
+
+    fn has_class(&self, name: &Atom) -> bool {
+        self.get_attr(&ns!(), &atom!("class"))
+            .map_or(false, |v| { v.as_tokens().iter().any(|atom| atom == name) })
+    }
 
-struct ElementWrapper<'a, E>
+    fn each_class<F>(&self, mut callback: F) where F: FnMut(&Atom) {
+        if let Some(v) = self.get_attr(&ns!(), &atom!("class")) {
+            for c in v.as_tokens() { callback(c) }
+        }
+    }
+
+    fn match_attr<F>(&self, attr: &AttrSelector, is_html: bool, test: F) -> bool
+                    where F: Fn(&str) -> bool {
+        let local_name = if is_html { &attr.lower_name } else { &attr.name };
+        match attr.namespace {
+            NamespaceConstraint::Specific(ref ns) => self.get_attr(ns, local_name),
+            NamespaceConstraint::Any => self.get_attr_ignore_ns(local_name),
+        }.map_or(false, |v| test(v))
+    }
+}
+
+static EMPTY_SNAPSHOT: ServoElementSnapshot = ServoElementSnapshot { state: None, attrs: None };
+
+struct ElementWrapper<'a, E, S>
     where E: Element,
-          E::Impl: SelectorImplExt {
+          E::Impl: SelectorImplExt,
+          S: ElementSnapshot + 'a {
     element: E,
-    snapshot: &'a ElementSnapshot,
+    snapshot: Option<&'a S>,
 }
 
-impl<'a, E> ElementWrapper<'a, E>
+impl<'a, E, S> ElementWrapper<'a, E, S>
     where E: Element,
-          E::Impl: SelectorImplExt {
-    pub fn new(el: E) -> ElementWrapper<'a, E> {
-        ElementWrapper { element: el, snapshot: &EMPTY_SNAPSHOT }
+          E::Impl: SelectorImplExt,
+          S: ElementSnapshot + 'a {
+    pub fn new(el: E) -> ElementWrapper<'a, E, S> {
+        ElementWrapper { element: el, snapshot: None }
+    }
+
+    pub fn new_with_snapshot(el: E, snapshot: &'a S) -> ElementWrapper<'a, E, S> {
+        ElementWrapper { element: el, snapshot: Some(snapshot) }
     }
 
-    pub fn new_with_snapshot(el: E, snapshot: &'a ElementSnapshot) -> ElementWrapper<'a, E> {
-        ElementWrapper { element: el, snapshot: snapshot }
+    /// The snapshot, if it has captured the element's old attributes.
+    fn attr_snapshot(&self) -> Option<&'a S> {
+        self.snapshot.and_then(|s| if s.has_attrs() { Some(s) } else { None })
     }
 }
 
-impl<'a, E> Element for ElementWrapper<'a, E>
+impl<'a, E, S> Element for ElementWrapper<'a, E, S>
     where E: Element,
-          E::Impl: SelectorImplExt {
+          E::Impl: SelectorImplExt,
+          S: ElementSnapshot + 'a {
     type Impl = E::Impl;
 
     fn match_non_ts_pseudo_class(&self,
@@ -108,7 +186,7 @@ impl<'a, E> Element for ElementWrapper<'a, E>
         if flag == ElementState::empty() {
             self.element.match_non_ts_pseudo_class(pseudo_class)
         } else {
-            match self.snapshot.state {
+            match self.snapshot.and_then(|s| s.state()) {
                 Some(s) => s.contains(flag),
                 None => self.element.match_non_ts_pseudo_class(pseudo_class)
             }
@@ -140,29 +218,21 @@ impl<'a, E> Element for ElementWrapper<'a, E>
         self.element.get_namespace()
     }
     fn get_id(&self) -> Option<Atom> {
-        match self.snapshot.attrs {
-            Some(_) => self.snapshot.get_attr(&ns!(), &atom!("id")).map(|value| value.as_atom().clone()),
+        match self.attr_snapshot() {
+            Some(s) => s.id_attr(),
             None => self.element.get_id(),
         }
     }
     fn has_class(&self, name: &Atom) -> bool {
-        match self.snapshot.attrs {
-            Some(_) => self.snapshot.get_attr(&ns!(), &atom!("class"))
-                                    .map_or(false, |v| { v.as_tokens().iter().any(|atom| atom == name) }),
+        match self.attr_snapshot() {
+            Some(s) => s.has_class(name),
             None => self.element.has_class(name),
         }
     }
     fn match_attr<F>(&self, attr: &AttrSelector, test: F) -> bool
                     where F: Fn(&str) -> bool {
-        match self.snapshot.attrs {
-            Some(_) => {
-                let html = self.is_html_element_in_html_document();
-                let local_name = if html { &attr.lower_name } else { &attr.name };
-                match attr.namespace {
-                    NamespaceConstraint::Specific(ref ns) => self.snapshot.get_attr(ns, local_name),
-                    NamespaceConstraint::Any => self.snapshot.get_attr_ignore_ns(local_name),
-                }.map_or(false, |v| test(v))
-            },
+        match self.attr_snapshot() {
+            Some(s) => s.match_attr(attr, self.is_html_element_in_html_document(), test),
             None => self.element.match_attr(attr, test)
         }
     }
@@ -172,13 +242,9 @@ impl<'a, E> Element for ElementWrapper<'a, E>
     fn is_root(&self) -> bool {
         self.element.is_root()
     }
-    fn each_class<F>(&self, mut callback: F) where F: FnMut(&Atom) {
-        match self.snapshot.attrs {
-            Some(_) => {
-                if let Some(v) = self.snapshot.get_attr(&ns!(), &atom!("class")) {
-                    for c in v.as_tokens() { callback(c) }
-                }
-            }
+    fn each_class<F>(&self, callback: F) where F: FnMut(&Atom) {
+        match self.attr_snapshot() {
+            Some(s) => s.each_class(callback),
             None => self.element.each_class(callback),
         }
     }
@@ -191,21 +257,73 @@ fn selector_to_state<Impl: SelectorImplExt>(sel: &SimpleSelector<Impl>) -> Eleme
     }
 }
 
-fn is_attr_selector<Impl: SelectorImpl>(sel: &SimpleSelector<Impl>) -> bool {
+fn selector_to_document_state<Impl: SelectorImplExt>(sel: &SimpleSelector<Impl>) -> u64 {
     match *sel {
-        SimpleSelector::ID(_) |
-        SimpleSelector::Class(_) |
-        SimpleSelector::AttrExists(_) |
-        SimpleSelector::AttrEqual(_, _, _) |
-        SimpleSelector::AttrIncludes(_, _) |
-        SimpleSelector::AttrDashMatch(_, _, _) |
-        SimpleSelector::AttrPrefixMatch(_, _) |
-        SimpleSelector::AttrSubstringMatch(_, _) |
-        SimpleSelector::AttrSuffixMatch(_, _) => true,
-        _ => false,
+        SimpleSelector::NonTSPseudoClass(ref pc) => Impl::pseudo_class_document_state_flag(pc),
+        SimpleSelector::Negation(ref selectors) => {
+            selectors.iter().fold(0, |states, sel| states | selector_to_document_state(sel))
+        }
+        _ => 0,
     }
 }
 
+fn selector_to_element_state_bits<Impl: SelectorImplExt>(sel: &SimpleSelector<Impl>) -> u64 {
+    match *sel {
+        SimpleSelector::NonTSPseudoClass(ref pc) => Impl::pseudo_class_element_state_bits(pc),
+        SimpleSelector::Negation(ref selectors) => {
+            selectors.iter().fold(0, |states, sel| states | selector_to_element_state_bits(sel))
+        }
+        _ => 0,
+    }
+}
+
+/// Adds `bits` to those of `origin` in `origin_bits`.
+fn note_origin_bits(origin_bits: &mut Vec<(Origin, u64)>, origin: Origin, bits: u64) {
+    match origin_bits.iter_mut().find(|&&mut (bits_origin, _)| bits_origin == origin) {
+        Some(&mut (_, ref mut existing)) => *existing |= bits,
+        None => origin_bits.push((origin, bits)),
+    }
+}
+
+fn union_of_origin_bits(origin_bits: &[(Origin, u64)]) -> u64 {
+    origin_bits.iter().fold(0, |union, &(_, bits)| union | bits)
+}
+
+/// Notes the attributes and classes `sel` depends on in `sensitivities`. ID selectors
+/// depend on the id attribute, and class selectors on the classes they test for.
+fn note_attr_dependencies<Impl: SelectorImpl>(sel: &SimpleSelector<Impl>, sensitivities: &mut Sensitivities) {
+    let attr = match *sel {
+        SimpleSelector::ID(_) => {
+            sensitivities.note_attr_name(&atom!("id"));
+            return;
+        }
+        SimpleSelector::Class(ref class) => {
+            if !sensitivities.classes.contains(class) {
+                sensitivities.classes.push(class.clone());
+            }
+            return;
+        }
+        SimpleSelector::Negation(ref selectors) => {
+            for sel in selectors {
+                note_attr_dependencies(sel, sensitivities);
+            }
+            return;
+        }
+        SimpleSelector::AttrExists(ref attr) |
+        SimpleSelector::AttrEqual(ref attr, _, _) |
+        SimpleSelector::AttrIncludes(ref attr, _) |
+        SimpleSelector::AttrDashMatch(ref attr, _, _) |
+        SimpleSelector::AttrPrefixMatch(ref attr, _) |
+        SimpleSelector::AttrSubstringMatch(ref attr, _) |
+        SimpleSelector::AttrSuffixMatch(ref attr, _) => attr,
+        _ => return,
+    };
+    // HTML elements in HTML documents match the lowercase name, and other elements the
+    // name as written.
+    sensitivities.note_attr_name(&attr.name);
+    sensitivities.note_attr_name(&attr.lower_name);
+}
+
 fn combinator_to_restyle_hint(combinator: Option<Combinator>) -> RestyleHint {
     match combinator {
         None => RESTYLE_SELF,
@@ -221,22 +339,150 @@ fn combinator_to_restyle_hint(combinator: Option<Combinator>) -> RestyleHint {
 #[derive(Debug, HeapSizeOf)]
 struct Sensitivities {
     pub states: ElementState,
-    pub attrs: bool,
+    /// The local names of the attributes the selector depends on.
+    pub attr_names: Vec<Atom>,
+    /// The classes the selector tests for.
+    pub classes: Vec<Atom>,
 }
 
 impl Sensitivities {
     fn is_empty(&self) -> bool {
-        self.states.is_empty() && !self.attrs
+        self.states.is_empty() && !self.attrs()
     }
 
     fn new() -> Sensitivities {
         Sensitivities {
             states: ElementState::empty(),
-            attrs: false,
+            attr_names: vec![],
+            classes: vec![],
+        }
+    }
+
+    fn attrs(&self) -> bool {
+        !self.attr_names.is_empty() || !self.classes.is_empty()
+    }
+
+    fn note_attr_name(&mut self, name: &Atom) {
+        if !self.attr_names.contains(name) {
+            self.attr_names.push(name.clone());
+        }
+    }
+}
+
+type AtomMap<V> = HashMap<Atom, V, BuildHasherDefault<::fnv::FnvHasher>>;
+
+/// What the selectors of a DependencySet depend on, each along with the elements a change to
+/// it can restyle, which follows from the combinators to the right of the selectors that
+/// depend on it.
+#[derive(Debug, HeapSizeOf)]
+struct DependencySummary {
+    /// By the local name of the attribute. Class selectors count as depending on `class`,
+    /// and ID selectors on `id`.
+    attributes: AtomMap<RestyleHint>,
+    /// By the class class selectors test for.
+    classes: AtomMap<RestyleHint>,
+    /// The states that restyle the element itself, its descendants and its later siblings.
+    #[ignore_heap_size_of = "Plain bits"]
+    states: [ElementState; 3],
+}
+
+/// The restyles `DependencySummary::states` is split by, in order.
+const STATE_HINTS: [RestyleHint; 3] = [RESTYLE_SELF, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS];
+
+/// The states ancestors have because of a state of their descendants, as pairs of the state of
+/// the descendant and the one of its ancestors that follows it. Selectors that depend on the
+/// latter, like :focus-within, depend on the states of descendants.
+const ANCESTOR_STATES: [(ElementState, ElementState); 1] = [(IN_FOCUS_STATE, IN_FOCUS_WITHIN_STATE)];
+
+impl DependencySummary {
+    fn new() -> DependencySummary {
+        DependencySummary {
+            attributes: HashMap::with_hasher(Default::default()),
+            classes: HashMap::with_hasher(Default::default()),
+            states: [ElementState::empty(); 3],
+        }
+    }
+
+    fn note(&mut self, sensitivities: &Sensitivities, hint: RestyleHint) {
+        for name in &sensitivities.attr_names {
+            self.attributes.entry(name.clone()).or_insert(RestyleHint::empty()).insert(hint);
+        }
+        if !sensitivities.classes.is_empty() {
+            self.attributes.entry(atom!("class")).or_insert(RestyleHint::empty()).insert(hint);
+        }
+        for class in &sensitivities.classes {
+            self.classes.entry(class.clone()).or_insert(RestyleHint::empty()).insert(hint);
+        }
+        for (states, &state_hint) in self.states.iter_mut().zip(STATE_HINTS.iter()) {
+            if hint.contains(state_hint) {
+                states.insert(sensitivities.states);
+            }
         }
     }
 }
 
+/// Which of the attributes and classes selectors depend on changed since a snapshot,
+/// worked out as they're asked about.
+struct AttributeChanges<'a, E: 'a, S: 'a> {
+    element: &'a E,
+    snapshot: &'a S,
+    is_html: bool,
+    names: Vec<(Atom, bool)>,
+    classes: Option<Vec<Atom>>,
+}
+
+impl<'a, E: Element, S: ElementSnapshot> AttributeChanges<'a, E, S> {
+    fn new(element: &'a E, snapshot: &'a S) -> AttributeChanges<'a, E, S> {
+        AttributeChanges {
+            element: element,
+            snapshot: snapshot,
+            is_html: element.is_html_element_in_html_document(),
+            names: vec![],
+            classes: None,
+        }
+    }
+
+    /// Whether an attribute or class the selector with `sensitivities` depends on changed.
+    fn affect(&mut self, sensitivities: &Sensitivities) -> bool {
+        sensitivities.attr_names.iter().any(|name| self.attr_changed(name)) ||
+        sensitivities.classes.iter().any(|class| self.class_changed(class))
+    }
+
+    fn attr_changed(&mut self, name: &Atom) -> bool {
+        if let Some(&(_, changed)) = self.names.iter().find(|&&(ref n, _)| n == name) {
+            return changed;
+        }
+        let attr = AttrSelector {
+            name: name.clone(),
+            lower_name: name.clone(),
+            namespace: NamespaceConstraint::Any,
+        };
+        let had = self.snapshot.match_attr(&attr, self.is_html, |_| true);
+        let has = self.element.match_attr(&attr, |_| true);
+        let element = self.element;
+        let changed = had != has ||
+                      (had && !self.snapshot.match_attr(&attr, self.is_html, |old| {
+                          element.match_attr(&attr, |new| new == old)
+                      }));
+        self.names.push((name.clone(), changed));
+        changed
+    }
+
+    fn class_changed(&mut self, class: &Atom) -> bool {
+        if self.classes.is_none() {
+            // The classes the element gained or lost.
+            let mut old = vec![];
+            self.snapshot.each_class(|class| old.push(class.clone()));
+            let mut new = vec![];
+            self.element.each_class(|class| new.push(class.clone()));
+            let mut changed: Vec<Atom> = old.iter().filter(|class| !new.contains(class)).cloned().collect();
+            changed.extend(new.into_iter().filter(|class| !old.contains(class)));
+            self.classes = Some(changed);
+        }
+        self.classes.as_ref().unwrap().contains(class)
+    }
+}
+
 // Mapping between (partial) CompoundSelectors (and the combinator to their right)
 // and the states and attributes they depend on.
 //
@@ -260,27 +506,84 @@ struct Dependency<Impl: SelectorImplExt> {
     selector: Arc<CompoundSelector<Impl>>,
     combinator: Option<Combinator>,
     sensitivities: Sensitivities,
+    origin: Origin,
 }
 
 #[derive(Debug, HeapSizeOf)]
 pub struct DependencySet<Impl: SelectorImplExt> {
     deps: Vec<Dependency<Impl>>,
+    /// What the selectors of all the dependencies depend on, so that changes nothing
+    /// depends on are told apart without going through every dependency.
+    summary: DependencySummary,
+    /// The document state bits the selectors of each origin depend on, for the origins with
+    /// any. Document state applies to every element at once, so it's tracked per origin
+    /// rather than per selector.
+    document_states: Vec<(Origin, u64)>,
+    /// The bits of the selector implementation's element state the selectors of each origin
+    /// depend on, for the origins with any. These cover pseudo-classes whose changes restyle
+    /// hints don't track too.
+    element_states: Vec<(Origin, u64)>,
+    /// The unions of the bits in `document_states` and `element_states`, for telling whether
+    /// anything depends on a state change with a single test.
+    document_state_mask: u64,
+    element_state_mask: u64,
+    /// The local names of the attributes `content: attr(...)` declarations read, by the
+    /// origin of the sheet they're in. The element itself needs restyling when one changes,
+    /// whatever the selector of the rule.
+    content_attributes: Vec<(Origin, Atom)>,
 }
 
 impl<Impl: SelectorImplExt> DependencySet<Impl> {
     pub fn new() -> DependencySet<Impl> {
-        DependencySet { deps: Vec::new() }
+        DependencySet {
+            deps: Vec::new(),
+            summary: DependencySummary::new(),
+            document_states: Vec::new(),
+            element_states: Vec::new(),
+            document_state_mask: 0,
+            element_state_mask: 0,
+            content_attributes: Vec::new(),
+        }
     }
 
-    pub fn compute_hint<E>(&self, el: &E, snapshot: &ElementSnapshot, current_state: ElementState)
-                          -> RestyleHint
-                          where E: Element<Impl=Impl> + Clone {
-        let state_changes = snapshot.state.map_or(ElementState::empty(), |old_state| current_state ^ old_state);
-        let attrs_changed = snapshot.attrs.is_some();
+    /// Whether some selector depends on the attribute with local name `name`, so that
+    /// changes to it may need a restyle.
+    pub fn might_have_attribute_dependency(&self, name: &Atom) -> bool {
+        self.summary.attributes.contains_key(name)
+    }
+
+    /// The elements a change to the attribute with local name `name` may restyle.
+    pub fn attribute_dependency_hint(&self, name: &Atom) -> RestyleHint {
+        self.summary.attributes.get(name).cloned().unwrap_or(RestyleHint::empty())
+    }
+
+    /// The elements an element gaining or losing `class` may restyle.
+    pub fn class_dependency_hint(&self, class: &Atom) -> RestyleHint {
+        self.summary.classes.get(class).cloned().unwrap_or(RestyleHint::empty())
+    }
+
+    /// The elements a change to the states in `states` may restyle.
+    pub fn state_dependency_hint(&self, states: ElementState) -> RestyleHint {
+        self.summary.states.iter().zip(STATE_HINTS.iter())
+                    .filter(|&(dependencies, _)| dependencies.intersects(states))
+                    .fold(RestyleHint::empty(), |hint, (_, &state_hint)| hint | state_hint)
+    }
+
+    pub fn compute_hint<E, S>(&self, el: &E, snapshot: &S, current_state: ElementState)
+                             -> RestyleHint
+                             where E: Element<Impl=Impl> + Clone,
+                                   S: ElementSnapshot {
+        let state_changes = snapshot.state().map_or(ElementState::empty(), |old_state| current_state ^ old_state);
+        let attrs_changed = snapshot.has_attrs() && !self.summary.attributes.is_empty();
+        if !attrs_changed && self.state_dependency_hint(state_changes).is_empty() {
+            return RestyleHint::empty();
+        }
+        let mut attr_changes = AttributeChanges::new(el, snapshot);
         let mut hint = RestyleHint::empty();
         for dep in &self.deps {
-            if state_changes.intersects(dep.sensitivities.states) || (attrs_changed && dep.sensitivities.attrs) {
-                let old_el: ElementWrapper<E> = ElementWrapper::new_with_snapshot(el.clone(), snapshot);
+            if state_changes.intersects(dep.sensitivities.states) ||
+               (attrs_changed && dep.sensitivities.attrs() && attr_changes.affect(&dep.sensitivities)) {
+                let old_el: ElementWrapper<E, S> = ElementWrapper::new_with_snapshot(el.clone(), snapshot);
                 let matched_then = matches_compound_selector(&*dep.selector, &old_el, None, &mut false);
                 let matches_now = matches_compound_selector(&*dep.selector, el, None, &mut false);
                 if matched_then != matches_now {
@@ -291,25 +594,104 @@ impl<Impl: SelectorImplExt> DependencySet<Impl> {
                 }
             }
         }
+        if attrs_changed && !hint.contains(RESTYLE_SELF) &&
+           self.content_attributes.iter().any(|&(_, ref name)| attr_changes.attr_changed(name)) {
+            hint.insert(RESTYLE_SELF);
+        }
         hint
     }
 
-    pub fn note_selector(&mut self, selector: Arc<CompoundSelector<Impl>>) {
+    /// The restyle hints that the changes to the states of `el` since `snapshot` give its
+    /// ancestors, through the states ancestors have because of those of their descendants.
+    /// Only the ancestors that some selector matches differently with those states flipped
+    /// get one.
+    ///
+    /// Embedders update the ancestors' states along with the element's but needn't snapshot
+    /// them, so each ancestor is compared against itself with the states flipped, whichever
+    /// way they changed.
+    pub fn compute_ancestor_hints<E, S>(&self, el: &E, snapshot: &S, current_state: ElementState)
+                                       -> Vec<(E, RestyleHint)>
+                                       where E: TElement + Element<Impl=Impl>,
+                                             S: ElementSnapshot {
+        let state_changes = snapshot.state().map_or(ElementState::empty(), |old_state| current_state ^ old_state);
+        let ancestor_changes = ANCESTOR_STATES.iter()
+                                              .filter(|&&(state, _)| state_changes.intersects(state))
+                                              .fold(ElementState::empty(), |changes, &(_, state)| changes | state);
+        let mut hints = vec![];
+        if self.state_dependency_hint(ancestor_changes).is_empty() {
+            return hints;
+        }
+        let mut ancestor = el.parent_element();
+        while let Some(element) = ancestor {
+            let flipped = ServoElementSnapshot { state: Some(element.get_state() ^ ancestor_changes), attrs: None };
+            let mut hint = RestyleHint::empty();
+            for dep in &self.deps {
+                if ancestor_changes.intersects(dep.sensitivities.states) {
+                    let old_el: ElementWrapper<E, _> = ElementWrapper::new_with_snapshot(element, &flipped);
+                    let matched_then = matches_compound_selector(&*dep.selector, &old_el, None, &mut false);
+                    let matches_now = matches_compound_selector(&*dep.selector, &element, None, &mut false);
+                    if matched_then != matches_now {
+                        hint.insert(combinator_to_restyle_hint(dep.combinator));
+                    }
+                }
+            }
+            if !hint.is_empty() {
+                hints.push((element, hint));
+            }
+            ancestor = element.parent_element();
+        }
+        hints
+    }
+
+    /// Whether any selector depends on one of the document state bits in `states`.
+    pub fn depends_on_document_state(&self, states: u64) -> bool {
+        self.document_state_mask & states != 0
+    }
+
+    /// Whether any selector depends on one of the bits of element state in `states`, which
+    /// are those of SelectorImplExt::pseudo_class_element_state_bits. If none does, a change
+    /// to them never needs a restyle.
+    pub fn depends_on_element_state(&self, states: u64) -> bool {
+        self.element_state_mask & states != 0
+    }
+
+    /// Calls `callback` with the local name of each attribute some selector or `content`
+    /// declaration depends on.
+    pub fn each_attribute_dependency<F>(&self, mut callback: F) where F: FnMut(&Atom) {
+        for name in self.summary.attributes.keys() {
+            callback(name)
+        }
+    }
+
+    pub fn note_selector(&mut self, selector: Arc<CompoundSelector<Impl>>, origin: Origin) {
         let mut cur = selector;
         let mut combinator: Option<Combinator> = None;
         loop {
+            let document_states = cur.simple_selectors.iter()
+                                     .fold(0, |states, s| states | selector_to_document_state(s));
+            if document_states != 0 {
+                note_origin_bits(&mut self.document_states, origin, document_states);
+                self.document_state_mask |= document_states;
+            }
+            let element_states = cur.simple_selectors.iter()
+                                    .fold(0, |states, s| states | selector_to_element_state_bits(s));
+            if element_states != 0 {
+                note_origin_bits(&mut self.element_states, origin, element_states);
+                self.element_state_mask |= element_states;
+            }
+
             let mut sensitivities = Sensitivities::new();
             for s in &cur.simple_selectors {
                 sensitivities.states.insert(selector_to_state(s));
-                if !sensitivities.attrs {
-                    sensitivities.attrs = is_attr_selector(s);
-                }
+                note_attr_dependencies(s, &mut sensitivities);
             }
             if !sensitivities.is_empty() {
+                self.summary.note(&sensitivities, combinator_to_restyle_hint(combinator));
                 self.deps.push(Dependency {
                     selector: cur.clone(),
                     combinator: combinator,
                     sensitivities: sensitivities,
+                    origin: origin,
                 });
             }
 
@@ -323,7 +705,42 @@ impl<Impl: SelectorImplExt> DependencySet<Impl> {
         }
     }
 
+    /// Notes that a `content` declaration from a sheet of `origin` reads the attribute with
+    /// local name `name`.
+    pub fn note_content_attribute(&mut self, name: Atom, origin: Origin) {
+        self.summary.attributes.entry(name.clone()).or_insert(RestyleHint::empty()).insert(RESTYLE_SELF);
+        let noted = self.content_attributes.iter()
+                        .any(|&(dep_origin, ref dep_name)| dep_origin == origin && *dep_name == name);
+        if !noted {
+            self.content_attributes.push((origin, name));
+        }
+    }
+
     pub fn clear(&mut self) {
         self.deps.clear();
+        self.summary = DependencySummary::new();
+        self.document_states.clear();
+        self.element_states.clear();
+        self.document_state_mask = 0;
+        self.element_state_mask = 0;
+        self.content_attributes.clear();
+    }
+
+    /// Forgets the dependencies of the selectors that came from stylesheets of the given origin.
+    pub fn clear_origin(&mut self, origin: Origin) {
+        self.deps.retain(|dep| dep.origin != origin);
+        self.document_states.retain(|&(dep_origin, _)| dep_origin != origin);
+        self.element_states.retain(|&(dep_origin, _)| dep_origin != origin);
+        self.document_state_mask = union_of_origin_bits(&self.document_states);
+        self.element_state_mask = union_of_origin_bits(&self.element_states);
+        self.content_attributes.retain(|&(dep_origin, _)| dep_origin != origin);
+        let mut summary = DependencySummary::new();
+        for dep in &self.deps {
+            summary.note(&dep.sensitivities, combinator_to_restyle_hint(dep.combinator));
+        }
+        for &(_, ref name) in &self.content_attributes {
+            summary.attributes.entry(name.clone()).or_insert(RestyleHint::empty()).insert(RESTYLE_SELF);
+        }
+        self.summary = summary;
     }
 }
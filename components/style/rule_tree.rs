@@ -0,0 +1,325 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The rule tree: the lists of declaration blocks elements matched, in cascade order, with
+//! the lists that start the same way sharing nodes.
+//!
+//! Matching the same blocks always ends at the same node, so elements that matched the same
+//! rules and have the same parent style can get the same computed style without cascading
+//! again. A node is kept alive by the elements that matched it and by its children, and
+//! `RuleTree::gc` forgets the children nothing uses anymore.
+//!
+//! When the declarations of a rule change without its selectors changing, the elements that
+//! matched it still match the same rules, so `RuleTree::replace_declarations` lets them swap
+//! the old declarations for the new ones in their node instead of matching again.
+//!
+//! The nodes of elements are tagged with the cascade level of their block, so that
+//! `RuleTree::replace_levels` can swap the values of an element's animations for newer ones,
+//! and put them where they go if it had none, without matching again either.
+
+use properties::{ComputedValues, PropertyDeclaration};
+use selector_matching::{CascadeLevel, DeclarationBlock};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use util::arc_ptr_eq;
+use util::cache::LRUCache;
+
+pub struct RuleNode {
+    parent: Option<Arc<RuleNode>>,
+
+    /// The block this node adds to its parent's, or None for the root.
+    source: Option<DeclarationBlock>,
+
+    /// The cascade level of the block, if it was inserted with one.
+    level: Option<CascadeLevel>,
+
+    /// The children, by the address of their block's declarations and their level. Each
+    /// child keeps its block alive, so an address is only reused once the child is gone.
+    children: Mutex<HashMap<(usize, Option<CascadeLevel>), Weak<RuleNode>>>,
+
+    /// The generation of the tree the node was last found current in by `RuleTree::refresh`.
+    generation: AtomicUsize,
+}
+
+impl RuleNode {
+    fn new(parent: Option<Arc<RuleNode>>, source: Option<DeclarationBlock>, level: Option<CascadeLevel>)
+           -> RuleNode {
+        RuleNode {
+            parent: parent,
+            source: source,
+            level: level,
+            children: Mutex::new(HashMap::new()),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Tells the node apart from every other node alive at the same time.
+    pub fn id(&self) -> usize {
+        self as *const RuleNode as usize
+    }
+
+    pub fn parent(&self) -> Option<&Arc<RuleNode>> {
+        self.parent.as_ref()
+    }
+
+    pub fn source(&self) -> Option<&DeclarationBlock> {
+        self.source.as_ref()
+    }
+
+    pub fn level(&self) -> Option<CascadeLevel> {
+        self.level
+    }
+
+    /// The blocks on the way from the root to the node, in the order they cascade in.
+    pub fn path(&self) -> Vec<DeclarationBlock> {
+        self.path_with_levels().into_iter().map(|(_, block)| block).collect()
+    }
+
+    /// Like `path`, with the level each block was inserted with.
+    pub fn path_with_levels(&self) -> Vec<(Option<CascadeLevel>, DeclarationBlock)> {
+        let mut path = vec![];
+        let mut current = Some(self);
+        while let Some(node) = current {
+            if let Some(ref source) = node.source {
+                path.push((node.level, source.clone()));
+            }
+            current = node.parent.as_ref().map(|parent| &**parent);
+        }
+        path.reverse();
+        path
+    }
+
+    fn ensure_child(node: &Arc<RuleNode>, source: &DeclarationBlock, level: Option<CascadeLevel>)
+                    -> Arc<RuleNode> {
+        let key = (declarations_key(&source.declarations), level);
+        let mut children = node.children.lock().unwrap();
+        if let Some(child) = children.get(&key).and_then(|child| child.upgrade()) {
+            return child;
+        }
+        let child = Arc::new(RuleNode::new(Some(node.clone()), Some(source.clone()), level));
+        children.insert(key, Arc::downgrade(&child));
+        child
+    }
+}
+
+/// Declarations a rule had, and the ones it has now.
+type Replacement = (Arc<Vec<PropertyDeclaration>>, Arc<Vec<PropertyDeclaration>>);
+
+pub struct RuleTree {
+    root: Arc<RuleNode>,
+
+    /// Ticked whenever declarations are replaced, so that the nodes found current before are
+    /// looked at again.
+    generation: AtomicUsize,
+
+    /// The declarations replaced since `forget_replacements`, by the address of the old ones,
+    /// which are kept alive so that the address isn't reused.
+    replacements: Mutex<HashMap<usize, Replacement>>,
+}
+
+impl RuleTree {
+    pub fn new() -> RuleTree {
+        RuleTree {
+            root: Arc::new(RuleNode::new(None, None, None)),
+            generation: AtomicUsize::new(0),
+            replacements: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn root(&self) -> &Arc<RuleNode> {
+        &self.root
+    }
+
+    /// The node for `declarations`, which are in the order they cascade in. It's safe to
+    /// call from several threads at once.
+    pub fn insert_ordered_rules(&self, declarations: &[DeclarationBlock]) -> Arc<RuleNode> {
+        let mut current = self.root.clone();
+        for declaration in declarations {
+            current = RuleNode::ensure_child(&current, declaration, None);
+        }
+        current
+    }
+
+    /// Like `insert_ordered_rules`, with the nodes tagged with the level of their block.
+    /// `levels` says which of `declarations` are at each level, as the stylist gives them.
+    pub fn insert_ordered_rules_at_levels(&self, declarations: &[DeclarationBlock],
+                                          levels: &[(CascadeLevel, Range<usize>)]) -> Arc<RuleNode> {
+        let mut current = self.root.clone();
+        for (i, declaration) in declarations.iter().enumerate() {
+            let level = levels.iter()
+                              .find(|&&(_, ref range)| range.start <= i && i < range.end)
+                              .map(|&(level, _)| level);
+            current = RuleNode::ensure_child(&current, declaration, level);
+        }
+        current
+    }
+
+    /// The node for the blocks on the way to `node`, with the ones at `range`, counting from
+    /// the root, swapped for `replacements`, which have no level. The ones before `range` are
+    /// shared with `node`.
+    pub fn replace_rules(&self, node: &Arc<RuleNode>, range: Range<usize>,
+                         replacements: &[DeclarationBlock]) -> Arc<RuleNode> {
+        let path = node.path_with_levels();
+        let replacements: Vec<_> = replacements.iter().map(|block| (None, block.clone())).collect();
+        let mut current = self.root.clone();
+        for &(level, ref declaration) in path[..range.start].iter().chain(&replacements).chain(&path[range.end..]) {
+            current = RuleNode::ensure_child(&current, declaration, level);
+        }
+        current
+    }
+
+    /// The node for the blocks on the way to `node`, with the ones at each level of
+    /// `replacements` swapped for that level's blocks, which go where blocks of the level
+    /// would have been if there were none. Only the nodes after the first changed one are
+    /// new. Untagged nodes are taken to lose to every level.
+    pub fn replace_levels(&self, node: &Arc<RuleNode>, replacements: &[(CascadeLevel, Vec<DeclarationBlock>)])
+                          -> Arc<RuleNode> {
+        let replaced = |level: Option<CascadeLevel>| {
+            replacements.iter().any(|&(replaced, _)| level == Some(replaced))
+        };
+        let mut path: Vec<_> = node.path_with_levels().into_iter().filter(|&(level, _)| !replaced(level)).collect();
+        for &(level, ref blocks) in replacements {
+            path.extend(blocks.iter().map(|block| (Some(level), block.clone())));
+        }
+        // The sort is stable, which keeps the blocks of each level in their order.
+        path.sort_by_key(|&(level, _)| level);
+
+        let mut current = self.root.clone();
+        for &(level, ref declaration) in &path {
+            current = RuleNode::ensure_child(&current, declaration, level);
+        }
+        current
+    }
+
+    /// Notes that a rule whose selectors stayed the same now has the declarations `new` in
+    /// place of `old`, which makes the nodes with `old` on their way from the root stale.
+    pub fn replace_declarations(&self, old: &Arc<Vec<PropertyDeclaration>>, new: &Arc<Vec<PropertyDeclaration>>) {
+        if arc_ptr_eq(old, new) {
+            return;
+        }
+        let mut replacements = self.replacements.lock().unwrap();
+        // Declarations that were replaced before go straight to the latest ones.
+        for &mut (_, ref mut latest) in replacements.values_mut() {
+            if arc_ptr_eq(latest, old) {
+                *latest = new.clone();
+            }
+        }
+        replacements.insert(declarations_key(old), (old.clone(), new.clone()));
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether any declarations were replaced since `forget_replacements`.
+    pub fn has_replacements(&self) -> bool {
+        !self.replacements.lock().unwrap().is_empty()
+    }
+
+    /// The node with the replaced declarations on the way to `node` swapped for the new ones,
+    /// or None if there are none. Each node's path is only gone through once for every change.
+    pub fn refresh(&self, node: &Arc<RuleNode>) -> Option<Arc<RuleNode>> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if node.generation.load(Ordering::Relaxed) == generation {
+            return None;
+        }
+        let replacements = self.replacements.lock().unwrap();
+        let path = node.path_with_levels();
+        if !path.iter().any(|&(_, ref block)| replacements.contains_key(&declarations_key(&block.declarations))) {
+            node.generation.store(generation, Ordering::Relaxed);
+            return None;
+        }
+        let mut current = self.root.clone();
+        for &(level, ref block) in &path {
+            current = match replacements.get(&declarations_key(&block.declarations)) {
+                Some(&(_, ref new)) => RuleNode::ensure_child(&current, &DeclarationBlock {
+                    declarations: new.clone(),
+                    specificity: block.specificity,
+                    source_order: block.source_order,
+                }, level),
+                None => RuleNode::ensure_child(&current, block, level),
+            };
+        }
+        Some(current)
+    }
+
+    /// Forgets the replaced declarations, once no element is left with a node that has them.
+    pub fn forget_replacements(&self) {
+        self.replacements.lock().unwrap().clear();
+    }
+
+    /// Forgets the children no element or other node uses anymore.
+    pub fn gc(&self) {
+        gc_children(&self.root);
+    }
+
+    /// How many nodes the tree has, counting the root, for testing.
+    pub fn node_count(&self) -> usize {
+        fn count(node: &Arc<RuleNode>) -> usize {
+            let children: Vec<_> = node.children.lock().unwrap()
+                                       .values().filter_map(Weak::upgrade).collect();
+            1 + children.iter().map(count).fold(0, |sum, count| sum + count)
+        }
+        count(&self.root)
+    }
+}
+
+fn gc_children(node: &Arc<RuleNode>) {
+    let live_children: Vec<_> = {
+        let mut children = node.children.lock().unwrap();
+        let dead: Vec<_> = children.iter()
+                                   .filter(|&(_, child)| child.upgrade().is_none())
+                                   .map(|(&key, _)| key)
+                                   .collect();
+        for key in dead {
+            children.remove(&key);
+        }
+        children.values().filter_map(Weak::upgrade).collect()
+    };
+    for child in &live_children {
+        gc_children(child);
+    }
+}
+
+/// What tells blocks of declarations apart: their address, which is the same for every block
+/// made from the same declarations.
+pub fn declarations_key(declarations: &Arc<Vec<PropertyDeclaration>>) -> usize {
+    &**declarations as *const _ as usize
+}
+
+const RULE_NODE_STYLE_CACHE_SIZE: usize = 32;
+
+/// The styles cascaded for rule nodes, by node, parent style, and whether they may be
+/// shared with siblings. The entries keep the node and the parent style alive, so that
+/// their addresses aren't reused while they're in the cache.
+pub struct RuleNodeStyleCache<C: ComputedValues> {
+    cache: LRUCache<(usize, usize, bool), (Arc<RuleNode>, Option<Arc<C>>, Arc<C>)>,
+}
+
+impl<C: ComputedValues> RuleNodeStyleCache<C> {
+    pub fn new() -> Self {
+        RuleNodeStyleCache {
+            cache: LRUCache::new(RULE_NODE_STYLE_CACHE_SIZE),
+        }
+    }
+
+    pub fn find(&mut self, node: &Arc<RuleNode>, parent_style: Option<&Arc<C>>, shareable: bool)
+                -> Option<Arc<C>> {
+        self.cache.find(&cache_key(node, parent_style, shareable)).map(|(_, _, style)| style)
+    }
+
+    pub fn insert(&mut self, node: &Arc<RuleNode>, parent_style: Option<&Arc<C>>, shareable: bool,
+                  style: Arc<C>) {
+        self.cache.insert(cache_key(node, parent_style, shareable),
+                          (node.clone(), parent_style.cloned(), style))
+    }
+
+    pub fn evict_all(&mut self) {
+        self.cache.evict_all();
+    }
+}
+
+fn cache_key<C>(node: &Arc<RuleNode>, parent_style: Option<&Arc<C>>, shareable: bool)
+                -> (usize, usize, bool) {
+    (node.id(), parent_style.map_or(0, |style| &**style as *const C as usize), shareable)
+}
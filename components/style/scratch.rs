@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Scratch space for the temporaries of styling an element: the declarations it matched,
+//! for itself and its pseudo-elements, and the cascade levels they're at.
+//!
+//! Each thread of a traversal keeps a `ScratchArena` in its local style context. Styling
+//! an element takes buffers out of it and gives them back, cleared but with their capacity,
+//! once the element is cascaded, so the traversal allocates them once per thread rather
+//! than once per element. Nothing taken out of the arena may be kept past the element:
+//! node data and the rule tree get copies. `reset`, which the traversal calls after each
+//! element, checks in debug builds that every buffer came back.
+
+use selector_matching::{CascadeLevel, DeclarationBlock};
+use std::ops::Range;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, AtomicBool, Ordering};
+
+/// Buffers that grew past this many items go back to the allocator rather than to the
+/// arena, so that one element matching thousands of rules doesn't hold on to the memory
+/// for the rest of the traversal.
+const MAX_KEPT_CAPACITY: usize = 128;
+
+/// How many free buffers of each kind the arena keeps.
+const MAX_KEPT_BUFFERS: usize = 8;
+
+static REUSE_DISABLED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Turns reusing buffers on or off; it's on unless turned off. With it off, the arena hands
+/// out new buffers and frees the ones it gets back, the way every element allocated its own
+/// before, which is only useful for measuring what the arena saves.
+pub fn set_reuse_enabled(enabled: bool) {
+    REUSE_DISABLED.store(!enabled, Ordering::Relaxed);
+}
+
+struct BufferPool<T> {
+    free: Vec<Vec<T>>,
+}
+
+impl<T> BufferPool<T> {
+    fn new() -> BufferPool<T> {
+        BufferPool {
+            free: vec![],
+        }
+    }
+
+    fn take(&mut self) -> Vec<T> {
+        self.free.pop().unwrap_or_else(Vec::new)
+    }
+
+    fn give_back(&mut self, mut buffer: Vec<T>) {
+        buffer.clear();
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_KEPT_CAPACITY ||
+           self.free.len() == MAX_KEPT_BUFFERS || REUSE_DISABLED.load(Ordering::Relaxed) {
+            return;
+        }
+        self.free.push(buffer);
+    }
+}
+
+pub struct ScratchArena {
+    declarations: BufferPool<DeclarationBlock>,
+    levels: BufferPool<(CascadeLevel, Range<usize>)>,
+    /// How many buffers are out, for checking that they all come back.
+    lent: usize,
+}
+
+impl ScratchArena {
+    pub fn new() -> ScratchArena {
+        ScratchArena {
+            declarations: BufferPool::new(),
+            levels: BufferPool::new(),
+            lent: 0,
+        }
+    }
+
+    /// An empty buffer for matched declarations, which has to be given back with
+    /// `give_back_declarations`.
+    pub fn take_declarations(&mut self) -> Vec<DeclarationBlock> {
+        self.lent += 1;
+        self.declarations.take()
+    }
+
+    pub fn give_back_declarations(&mut self, buffer: Vec<DeclarationBlock>) {
+        debug_assert!(self.lent > 0, "Giving back a buffer the scratch arena didn't lend");
+        self.lent -= 1;
+        self.declarations.give_back(buffer);
+    }
+
+    /// An empty buffer for the ranges of matched declarations at each cascade level, which
+    /// has to be given back with `give_back_levels`.
+    pub fn take_levels(&mut self) -> Vec<(CascadeLevel, Range<usize>)> {
+        self.lent += 1;
+        self.levels.take()
+    }
+
+    pub fn give_back_levels(&mut self, buffer: Vec<(CascadeLevel, Range<usize>)>) {
+        debug_assert!(self.lent > 0, "Giving back a buffer the scratch arena didn't lend");
+        self.lent -= 1;
+        self.levels.give_back(buffer);
+    }
+
+    /// Marks the end of an element. Every buffer it took has to be back by now: one that
+    /// isn't was kept somewhere that outlives the element.
+    pub fn reset(&mut self) {
+        debug_assert!(self.lent == 0, "{} scratch buffers outlived their element", self.lent);
+        self.lent = 0;
+    }
+
+    /// How many buffers are out.
+    pub fn lent(&self) -> usize {
+        self.lent
+    }
+}
@@ -1,25 +1,106 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use cssparser::{Delimiter, Parser, Token, serialize_identifier, serialize_string};
 use element_state::ElementState;
+use nth_index::{NthIndexKind, nth_arguments};
+use properties::PropertyRestriction;
 use selector_matching::{USER_OR_USER_AGENT_STYLESHEETS, QUIRKS_MODE_STYLESHEET};
 use selectors::Element;
-use selectors::parser::{ParserContext, SelectorImpl};
+use selectors::parser::{AttrSelector, CaseSensitivity, Combinator, CompoundSelector, NamespaceConstraint};
+use selectors::parser::{ParserContext, Selector, SelectorImpl, SimpleSelector, parse_selector_list};
+use std::ascii::AsciiExt;
+use std::fmt;
+use std::sync::Arc;
+use string_cache::{Atom, Namespace};
 use stylesheets::Stylesheet;
 
 pub trait ElementExt: Element {
     fn is_link(&self) -> bool;
 }
 
-pub trait SelectorImplExt : SelectorImpl + Sized {
+pub trait SelectorImplExt : SelectorImpl + Clone + Sized {
     fn each_eagerly_cascaded_pseudo_element<F>(mut fun: F)
         where F: FnMut(<Self as SelectorImpl>::PseudoElement);
 
     fn pseudo_class_state_flag(pc: &Self::NonTSPseudoClass) -> ElementState;
 
+    /// The bits of document-wide state a pseudo-class depends on, like whether the window is
+    /// active. What each bit means is up to the implementation. Servo has none.
+    fn pseudo_class_document_state_flag(_pc: &Self::NonTSPseudoClass) -> u64 {
+        0
+    }
+
+    /// The bits of the implementation's own element state a pseudo-class depends on, which
+    /// may be more than the ElementState ones restyle hints track. By default, those.
+    fn pseudo_class_element_state_bits(pc: &Self::NonTSPseudoClass) -> u64 {
+        Self::pseudo_class_state_flag(pc).bits() as u64
+    }
+
+    /// Whether `pc` only matches links as they'd be styled if they were visited (`Some(true)`),
+    /// or only as they'd be styled if they weren't (`Some(false)`). Implementations that style
+    /// links both ways match both kinds of pseudo-class on the link they style, and the stylist
+    /// keeps the rules that needed one kind out of the other kind's style. Servo only styles
+    /// links one way.
+    fn pseudo_class_link_visitedness(_pc: &Self::NonTSPseudoClass) -> Option<bool> {
+        None
+    }
+
     fn get_user_or_user_agent_stylesheets() -> &'static [Stylesheet<Self>];
 
     fn get_quirks_mode_stylesheet() -> Option<&'static Stylesheet<Self>>;
+
+    /// The name of a pseudo-class, as it appears after the colon in a selector.
+    fn pseudo_class_name(pc: &Self::NonTSPseudoClass) -> &str;
+
+    /// Writes out a pseudo-class without the colon, along with its arguments if it takes
+    /// any.
+    fn serialize_pseudo_class<W>(pc: &Self::NonTSPseudoClass, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        dest.write_str(Self::pseudo_class_name(pc))
+    }
+
+    /// The name of a pseudo-element, as it appears after the double colon in a selector.
+    fn pseudo_element_name(pseudo: &Self::PseudoElement) -> &'static str;
+
+    /// Which properties apply to `pseudo`, if not all of them do.
+    fn pseudo_element_property_restriction(_pseudo: &Self::PseudoElement) -> Option<PropertyRestriction> {
+        None
+    }
+
+    /// The eagerly cascaded pseudo-element whose style `pseudo` inherits from when the
+    /// element has one, instead of from the element's style. It has to be cascaded first.
+    fn pseudo_element_inherits_from(_pseudo: &Self::PseudoElement) -> Option<Self::PseudoElement> {
+        None
+    }
+
+    /// Whether `pseudo` styles a box that no element generates, and that every user agent
+    /// rule for it applies to.
+    fn pseudo_element_is_anonymous_box(_pseudo: &Self::PseudoElement) -> bool {
+        false
+    }
+
+    /// Whether the elements of quirks mode documents give their ids and classes with their
+    /// ASCII letters lowercased, so that the stylist lowercases those of the author and user
+    /// rules too and they match ASCII case-insensitively. Servo's elements don't.
+    fn lowercases_ids_and_classes_in_quirks_mode() -> bool {
+        false
+    }
+
+    /// The pseudo-class the stylist matches the `an+b` nth selectors counting siblings the
+    /// way `kind` says with instead, for implementations whose elements keep an
+    /// `NthIndexCache`. The selectors crate would otherwise walk the previous or next
+    /// siblings of every element it matches them against. Servo has no cache.
+    fn nth_index_pseudo_class(_a: i32, _b: i32, _kind: NthIndexKind) -> Option<Self::NonTSPseudoClass> {
+        None
+    }
+
+    /// The pseudo-class the stylist matches the attribute selector `selector` with instead,
+    /// for implementations that compare attributes against interned values. The selectors
+    /// crate would otherwise have `Element::match_attr` test the value as a string. Servo
+    /// keeps its attribute values as strings.
+    fn attr_pseudo_class(_selector: &SimpleSelector<Self>) -> Option<Self::NonTSPseudoClass> {
+        None
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
@@ -31,6 +112,20 @@ pub enum PseudoElement {
     DetailsContent,
 }
 
+impl PseudoElement {
+    /// The name of the pseudo-element, without the leading colons.
+    pub fn as_str(&self) -> &'static str {
+        use self::PseudoElement::*;
+        match *self {
+            Before => "before",
+            After => "after",
+            Selection => "selection",
+            DetailsSummary => "-servo-details-summary",
+            DetailsContent => "-servo-details-content",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
 pub enum NonTSPseudoClass {
     AnyLink,
@@ -65,6 +160,24 @@ impl NonTSPseudoClass {
             ServoNonZeroBorder => ElementState::empty(),
         }
     }
+
+    /// The name of the pseudo-class, without the leading colon.
+    pub fn as_str(&self) -> &'static str {
+        use self::NonTSPseudoClass::*;
+        match *self {
+            AnyLink => "any-link",
+            Link => "link",
+            Visited => "visited",
+            Active => "active",
+            Focus => "focus",
+            Hover => "hover",
+            Enabled => "enabled",
+            Disabled => "disabled",
+            Checked => "checked",
+            Indeterminate => "indeterminate",
+            ServoNonZeroBorder => "-servo-nonzero-border",
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, HeapSizeOf)]
@@ -155,4 +268,418 @@ impl SelectorImplExt for ServoSelectorImpl {
     fn get_quirks_mode_stylesheet() -> Option<&'static Stylesheet<Self>> {
         Some(&*QUIRKS_MODE_STYLESHEET)
     }
+
+    #[inline]
+    fn pseudo_class_name(pc: &NonTSPseudoClass) -> &str {
+        pc.as_str()
+    }
+
+    #[inline]
+    fn pseudo_element_name(pseudo: &PseudoElement) -> &'static str {
+        pseudo.as_str()
+    }
+}
+
+/// Parses the argument of `:lang()`: a comma-separated list of language ranges, each an
+/// identifier or a string.
+pub fn parse_lang_ranges(input: &mut Parser) -> Result<Vec<String>, ()> {
+    input.parse_comma_separated(|input| {
+        match try!(input.next()) {
+            Token::Ident(range) | Token::QuotedString(range) => Ok(range.into_owned()),
+            _ => Err(()),
+        }
+    })
+}
+
+/// Whether `language`, the content language of an element, is in the language `range`, as
+/// the extended filtering of RFC 4647 has it. Subtags compare ASCII case-insensitively, `*`
+/// stands for any subtag, and an empty range matches nothing.
+pub fn lang_matches(range: &str, language: &str) -> bool {
+    if range.is_empty() || language.is_empty() {
+        return false;
+    }
+    let mut range = range.split('-');
+    let mut language = language.split('-');
+
+    // The primary subtags have to match, unless the range starts with a wildcard.
+    match (range.next(), language.next()) {
+        (Some(r), Some(l)) if r == "*" || r.eq_ignore_ascii_case(l) => {}
+        _ => return false,
+    }
+    for subtag in range {
+        if subtag == "*" {
+            continue;
+        }
+        // Skip over the subtags the range leaves out, but not past a singleton, which
+        // starts an extension.
+        loop {
+            match language.next() {
+                Some(l) if l.eq_ignore_ascii_case(subtag) => break,
+                Some(l) if l.len() > 1 => continue,
+                _ => return false,
+            }
+        }
+    }
+    true
+}
+
+/// The attributes whose values HTML elements in HTML documents compare ASCII
+/// case-insensitively in selectors, sorted.
+///
+/// https://html.spec.whatwg.org/multipage/#selectors
+static HTML_CASE_INSENSITIVE_ATTRIBUTES: &'static [&'static str] = &[
+    "accept", "accept-charset", "align", "alink", "axis", "bgcolor", "charset", "checked", "clear",
+    "codetype", "color", "compact", "declare", "defer", "dir", "direction", "disabled", "enctype",
+    "face", "frame", "hreflang", "http-equiv", "lang", "language", "link", "media", "method",
+    "multiple", "nohref", "noresize", "noshade", "nowrap", "readonly", "rel", "rev", "rules", "scope",
+    "scrolling", "selected", "shape", "target", "text", "type", "valign", "valuetype", "vlink",
+];
+
+/// Whether an attribute selector compares values ASCII case-insensitively on an element.
+/// An explicit `i` flag always does; otherwise the attributes HTML lists do, in no
+/// namespace, on HTML elements in HTML documents only.
+pub fn attr_value_ignores_case(attr: &AttrSelector, case_sensitivity: CaseSensitivity,
+                               is_html_element_in_html_document: bool) -> bool {
+    if case_sensitivity == CaseSensitivity::CaseInsensitive {
+        return true;
+    }
+    if !is_html_element_in_html_document {
+        return false;
+    }
+    let in_no_namespace = match attr.namespace {
+        NamespaceConstraint::Specific(ref namespace) => *namespace == ns!(),
+        NamespaceConstraint::Any => true,
+    };
+    in_no_namespace && HTML_CASE_INSENSITIVE_ATTRIBUTES.binary_search(&&*attr.lower_name).is_ok()
+}
+
+/// Matches `[attr=value]` on `element`, with the case-sensitivity the selector has there.
+///
+/// FIXME: The selectors crate compares AttrEqual values going by the `i` flag alone (and
+/// rejects any other flag, `s` included, as an invalid selector), so the HTML attributes
+/// only compare case-insensitively on the paths that match attributes through here.
+pub fn matches_attr_equal<E: Element>(element: &E, attr: &AttrSelector, value: &str,
+                                      case_sensitivity: CaseSensitivity) -> bool {
+    if attr_value_ignores_case(attr, case_sensitivity, element.is_html_element_in_html_document()) {
+        element.match_attr(attr, |attr_value| attr_value.eq_ignore_ascii_case(value))
+    } else {
+        element.match_attr(attr, |attr_value| attr_value == value)
+    }
+}
+
+/// Parses a selector list for `Element.matches()` and the like, which must take up all
+/// of `input`. Type selectors without a namespace prefix match elements in
+/// `default_namespace`. Selectors with pseudo-elements never match an element, so they
+/// make the list invalid.
+///
+/// https://drafts.csswg.org/selectors-4/#parse-a-selector
+pub fn parse_selector_list_for_matching<Impl: SelectorImpl>(input: &str, default_namespace: Option<Namespace>)
+                                                            -> Result<Vec<Selector<Impl>>, ()> {
+    let mut context = ParserContext::new();
+    context.default_namespace = default_namespace;
+    let selectors = try!(Parser::new(input).parse_entirely(|input| parse_selector_list(&context, input)));
+    if selectors.iter().any(|selector| selector.pseudo_element.is_some()) {
+        return Err(());
+    }
+    Ok(selectors)
+}
+
+/// `selector` with the ASCII letters of its ids and classes lowercased, for matching against
+/// the elements of a quirks mode document. Returns `selector` itself if there's nothing to
+/// lowercase.
+pub fn ascii_lowercase_ids_and_classes<Impl>(selector: &Arc<CompoundSelector<Impl>>) -> Arc<CompoundSelector<Impl>>
+                                             where Impl: SelectorImpl + Clone {
+    fn has_uppercase(name: &Atom) -> bool {
+        name.bytes().any(|byte| b'A' <= byte && byte <= b'Z')
+    }
+    rewrite_simple_selectors(selector, &|simple: &SimpleSelector<Impl>| {
+        match *simple {
+            SimpleSelector::ID(ref id) if has_uppercase(id) => {
+                Some(SimpleSelector::ID(Atom::from(&*id.to_ascii_lowercase())))
+            }
+            SimpleSelector::Class(ref class) if has_uppercase(class) => {
+                Some(SimpleSelector::Class(Atom::from(&*class.to_ascii_lowercase())))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// `selector` with its nth and attribute selectors replaced by the pseudo-classes `Impl`
+/// matches them with, if it has any. Returns `selector` itself otherwise.
+pub fn with_matching_pseudo_classes<Impl>(selector: &Arc<CompoundSelector<Impl>>) -> Arc<CompoundSelector<Impl>>
+                                          where Impl: SelectorImplExt {
+    rewrite_simple_selectors(selector, &|simple: &SimpleSelector<Impl>| {
+        let pseudo_class = match nth_arguments(simple) {
+            Some((a, b, kind)) => Impl::nth_index_pseudo_class(a, b, kind),
+            None => Impl::attr_pseudo_class(simple),
+        };
+        pseudo_class.map(SimpleSelector::NonTSPseudoClass)
+    })
+}
+
+/// Like `ascii_lowercase_ids_and_classes`, for each selector of a list.
+pub fn ascii_lowercase_selector_list<Impl>(selectors: &[Selector<Impl>]) -> Vec<Selector<Impl>>
+                                           where Impl: SelectorImpl + Clone {
+    selectors.iter().map(|selector| Selector {
+        compound_selectors: ascii_lowercase_ids_and_classes(&selector.compound_selectors),
+        pseudo_element: selector.pseudo_element.clone(),
+        specificity: selector.specificity,
+    }).collect()
+}
+
+/// `selector` with each of its simple selectors, including those inside `:not()`, replaced
+/// by what `rewrite` gives for it, if anything. Returns `selector` itself if `rewrite` gives
+/// nothing for any of them.
+fn rewrite_simple_selectors<Impl, F>(selector: &Arc<CompoundSelector<Impl>>, rewrite: &F)
+                                     -> Arc<CompoundSelector<Impl>>
+                                     where Impl: SelectorImpl + Clone,
+                                           F: Fn(&SimpleSelector<Impl>) -> Option<SimpleSelector<Impl>> {
+    let simple_selectors = rewrite_each(&selector.simple_selectors, rewrite);
+    let next = selector.next.as_ref().map(|&(ref next, combinator)| {
+        (rewrite_simple_selectors(next, rewrite), combinator)
+    });
+    let next_rewritten = match (&next, &selector.next) {
+        (&Some((ref new_next, _)), &Some((ref old_next, _))) => {
+            &**new_next as *const CompoundSelector<Impl> != &**old_next as *const CompoundSelector<Impl>
+        }
+        _ => false,
+    };
+    match simple_selectors {
+        None if !next_rewritten => selector.clone(),
+        simple_selectors => Arc::new(CompoundSelector {
+            simple_selectors: simple_selectors.unwrap_or_else(|| selector.simple_selectors.clone()),
+            next: next,
+        }),
+    }
+}
+
+/// `selectors` with those `rewrite` gives something for replaced, or None if it gives
+/// nothing for any of them.
+fn rewrite_each<Impl, F>(selectors: &[SimpleSelector<Impl>], rewrite: &F) -> Option<Vec<SimpleSelector<Impl>>>
+                         where Impl: SelectorImpl + Clone,
+                               F: Fn(&SimpleSelector<Impl>) -> Option<SimpleSelector<Impl>> {
+    let rewritten: Vec<_> = selectors.iter().map(|selector| {
+        match *selector {
+            SimpleSelector::Negation(ref negated) => rewrite_each(negated, rewrite).map(SimpleSelector::Negation),
+            _ => rewrite(selector),
+        }
+    }).collect();
+    if rewritten.iter().all(Option::is_none) {
+        return None;
+    }
+    Some(rewritten.into_iter().zip(selectors).map(|(new, old)| new.unwrap_or_else(|| old.clone())).collect())
+}
+
+/// A selector in a list that doesn't parse, and where in it things go wrong.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidSelector<'a> {
+    /// The selector, without the whitespace around it.
+    pub selector: &'a str,
+    /// The byte offset of the selector in the list.
+    pub start: usize,
+    /// The byte offset in the selector of its first invalid token, which is the one after
+    /// the longest run of tokens it starts with that parses as a selector.
+    pub offset: usize,
+}
+
+/// Finds the first selector in the list `selectors` that doesn't parse in `context`, so
+/// that an error can point at it. With `for_matching`, selectors with pseudo-elements are
+/// invalid, as they are for `Element.matches()`. Returns None if every selector parses.
+pub fn find_invalid_selector<'a, Impl: SelectorImpl>(context: &ParserContext, selectors: &'a str,
+                                                     for_matching: bool) -> Option<InvalidSelector<'a>> {
+    let parses = |selector: &str| {
+        match Parser::new(selector).parse_entirely(|input| parse_selector_list::<Impl>(context, input)) {
+            Ok(list) => !for_matching || list.iter().all(|selector| selector.pseudo_element.is_none()),
+            Err(()) => false,
+        }
+    };
+    let mut input = Parser::new(selectors);
+    let list_start = input.position();
+    loop {
+        let invalid = input.parse_until_before(Delimiter::Comma, |input| {
+            let start = input.position();
+            let mut token_ends = vec![];
+            while input.next_including_whitespace().is_ok() {
+                token_ends.push(input.slice(start..input.position()).len());
+            }
+            let text = input.slice_from(start);
+            let selector = text.trim_left();
+            let leading_whitespace = text.len() - selector.len();
+            let selector = selector.trim_right();
+            if parses(selector) {
+                return Ok(None)
+            }
+            let valid_length = token_ends.iter().rev().cloned()
+                                         .find(|&end| end > leading_whitespace && parses(&text[..end]))
+                                         .unwrap_or(leading_whitespace);
+            let rest = &text[valid_length..];
+            Ok(Some(InvalidSelector {
+                selector: selector,
+                start: input.slice(list_start..start).len() + leading_whitespace,
+                offset: valid_length + (rest.len() - rest.trim_left().len()) - leading_whitespace,
+            }))
+        });
+        if let Ok(Some(invalid)) = invalid {
+            return Some(invalid)
+        }
+        if input.next().is_err() {
+            return None
+        }
+    }
+}
+
+/// Serializes a selector list the way CSSOM's `selectorText` does.
+///
+/// https://drafts.csswg.org/cssom/#serialize-a-group-of-selectors
+pub fn serialize_selector_list<Impl, W>(selectors: &[Selector<Impl>], dest: &mut W) -> fmt::Result
+                                        where Impl: SelectorImplExt, W: fmt::Write {
+    for (i, selector) in selectors.iter().enumerate() {
+        if i > 0 {
+            try!(dest.write_str(", "));
+        }
+        try!(serialize_selector(selector, dest));
+    }
+    Ok(())
+}
+
+/// https://drafts.csswg.org/cssom/#serialize-a-selector
+pub fn serialize_selector<Impl, W>(selector: &Selector<Impl>, dest: &mut W) -> fmt::Result
+                                   where Impl: SelectorImplExt, W: fmt::Write {
+    try!(serialize_compound_selector(&selector.compound_selectors, selector.pseudo_element.is_some(), dest));
+    if let Some(ref pseudo) = selector.pseudo_element {
+        try!(dest.write_str("::"));
+        try!(dest.write_str(Impl::pseudo_element_name(pseudo)));
+    }
+    Ok(())
+}
+
+fn serialize_compound_selector<Impl, W>(compound: &CompoundSelector<Impl>, before_pseudo_element: bool,
+                                        dest: &mut W) -> fmt::Result
+                                        where Impl: SelectorImplExt, W: fmt::Write {
+    if let Some((ref next, combinator)) = compound.next {
+        try!(serialize_compound_selector(next, false, dest));
+        try!(dest.write_str(match combinator {
+            Combinator::Child => " > ",
+            Combinator::Descendant => " ",
+            Combinator::NextSibling => " + ",
+            Combinator::LaterSibling => " ~ ",
+        }));
+    }
+
+    // FIXME: Namespace prefixes aren't kept after parsing, so namespace constraints
+    // can't be written back out.
+    let simple_selectors: Vec<_> = compound.simple_selectors.iter().filter(|s| match **s {
+        SimpleSelector::Namespace(_) => false,
+        _ => true,
+    }).collect();
+    // The universal selector is only written out if nothing else would be.
+    if simple_selectors.is_empty() && !before_pseudo_element {
+        return dest.write_str("*")
+    }
+    for selector in simple_selectors {
+        try!(serialize_simple_selector(selector, dest));
+    }
+    Ok(())
+}
+
+fn serialize_simple_selector<Impl, W>(selector: &SimpleSelector<Impl>, dest: &mut W) -> fmt::Result
+                                      where Impl: SelectorImplExt, W: fmt::Write {
+    match *selector {
+        SimpleSelector::ID(ref id) => {
+            try!(dest.write_str("#"));
+            serialize_identifier(&**id, dest)
+        }
+        SimpleSelector::Class(ref class) => {
+            try!(dest.write_str("."));
+            serialize_identifier(&**class, dest)
+        }
+        SimpleSelector::LocalName(ref name) => serialize_identifier(&*name.name, dest),
+        SimpleSelector::Namespace(_) => Ok(()),
+        SimpleSelector::AttrExists(ref attr) => serialize_attr_selector(attr, None, false, dest),
+        SimpleSelector::AttrEqual(ref attr, ref value, case_sensitivity) => {
+            serialize_attr_selector(attr, Some(("=", value)),
+                                    case_sensitivity == CaseSensitivity::CaseInsensitive, dest)
+        }
+        SimpleSelector::AttrIncludes(ref attr, ref value) => {
+            serialize_attr_selector(attr, Some(("~=", value)), false, dest)
+        }
+        SimpleSelector::AttrDashMatch(ref attr, ref value, _) => {
+            serialize_attr_selector(attr, Some(("|=", value)), false, dest)
+        }
+        SimpleSelector::AttrPrefixMatch(ref attr, ref value) => {
+            serialize_attr_selector(attr, Some(("^=", value)), false, dest)
+        }
+        SimpleSelector::AttrSubstringMatch(ref attr, ref value) => {
+            serialize_attr_selector(attr, Some(("*=", value)), false, dest)
+        }
+        SimpleSelector::AttrSuffixMatch(ref attr, ref value) => {
+            serialize_attr_selector(attr, Some(("$=", value)), false, dest)
+        }
+        SimpleSelector::Negation(ref selectors) => {
+            try!(dest.write_str(":not("));
+            let mut wrote_any = false;
+            for selector in selectors {
+                if let SimpleSelector::Namespace(_) = *selector {
+                    continue
+                }
+                try!(serialize_simple_selector(selector, dest));
+                wrote_any = true;
+            }
+            if !wrote_any {
+                try!(dest.write_str("*"));
+            }
+            dest.write_str(")")
+        }
+        SimpleSelector::FirstChild => dest.write_str(":first-child"),
+        SimpleSelector::LastChild => dest.write_str(":last-child"),
+        SimpleSelector::OnlyChild => dest.write_str(":only-child"),
+        SimpleSelector::Root => dest.write_str(":root"),
+        SimpleSelector::Empty => dest.write_str(":empty"),
+        SimpleSelector::NthChild(a, b) => serialize_nth(":nth-child(", a, b, dest),
+        SimpleSelector::NthLastChild(a, b) => serialize_nth(":nth-last-child(", a, b, dest),
+        SimpleSelector::NthOfType(a, b) => serialize_nth(":nth-of-type(", a, b, dest),
+        SimpleSelector::NthLastOfType(a, b) => serialize_nth(":nth-last-of-type(", a, b, dest),
+        SimpleSelector::FirstOfType => dest.write_str(":first-of-type"),
+        SimpleSelector::LastOfType => dest.write_str(":last-of-type"),
+        SimpleSelector::OnlyOfType => dest.write_str(":only-of-type"),
+        SimpleSelector::NonTSPseudoClass(ref pc) => {
+            try!(dest.write_str(":"));
+            Impl::serialize_pseudo_class(pc, dest)
+        }
+    }
+}
+
+fn serialize_attr_selector<W>(attr: &AttrSelector, operator_and_value: Option<(&str, &String)>,
+                              case_insensitive: bool, dest: &mut W) -> fmt::Result where W: fmt::Write {
+    try!(dest.write_str("["));
+    if let NamespaceConstraint::Any = attr.namespace {
+        try!(dest.write_str("*|"));
+    }
+    try!(serialize_identifier(&*attr.name, dest));
+    if let Some((operator, value)) = operator_and_value {
+        try!(dest.write_str(operator));
+        try!(serialize_string(value, dest));
+        if case_insensitive {
+            try!(dest.write_str(" i"));
+        }
+    }
+    dest.write_str("]")
+}
+
+/// Writes out `prefix` and an `an+b` argument, followed by the closing parenthesis.
+///
+/// https://drafts.csswg.org/css-syntax/#serializing-anb
+pub fn serialize_nth<W>(prefix: &str, a: i32, b: i32, dest: &mut W) -> fmt::Result where W: fmt::Write {
+    try!(dest.write_str(prefix));
+    match a {
+        0 => try!(write!(dest, "{}", b)),
+        1 => try!(dest.write_str("n")),
+        -1 => try!(dest.write_str("-n")),
+        _ => try!(write!(dest, "{}n", a)),
+    }
+    if a != 0 && b != 0 {
+        try!(write!(dest, "{:+}", b));
+    }
+    dest.write_str(")")
 }
@@ -5,26 +5,33 @@
 // For lazy_static
 #![allow(unsafe_code)]
 
-use dom::TElement;
+use dom::{OpaqueNode, TElement, TNode};
 use element_state::*;
 use error_reporting::{ParseErrorReporter, StdoutErrorReporter};
+use euclid::size::Size2D;
+use heapsize::HeapSizeOf;
 use media_queries::{Device, MediaType};
-use properties::{PropertyDeclaration, PropertyDeclarationBlock};
+use properties::{DeclaredValue, PropertyDeclaration, PropertyDeclarationBlock};
 use restyle_hints::{ElementSnapshot, RestyleHint, DependencySet};
-use selector_impl::{SelectorImplExt, ServoSelectorImpl};
+use rule_tree::declarations_key;
+use selector_impl::{SelectorImplExt, ServoSelectorImpl, ascii_lowercase_ids_and_classes, with_matching_pseudo_classes};
 use selectors::Element;
 use selectors::bloom::BloomFilter;
 use selectors::matching::DeclarationBlock as GenericDeclarationBlock;
 use selectors::matching::{Rule, SelectorMap};
-use selectors::parser::SelectorImpl;
+use selectors::parser::{CompoundSelector, SelectorImpl, SimpleSelector};
+use shared_lock::SharedRwLockReadGuard;
 use smallvec::VecLike;
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
+use std::ops::Range;
 use std::process;
 use std::sync::Arc;
+use string_cache::Atom;
 use style_traits::viewport::ViewportConstraints;
 use stylesheets::{CSSRuleIteratorExt, Origin, Stylesheet};
 use url::Url;
+use util::arc_ptr_eq;
 use util::opts;
 use util::resource_files::read_resource_file;
 use viewport::{MaybeNew, ViewportRuleCascade};
@@ -97,12 +104,35 @@ pub struct Stylist<Impl: SelectorImplExt> {
     // If true, the device has changed, and the stylist needs to be updated.
     is_device_dirty: bool,
 
+    // If true, neither author sheets nor style attributes apply, as when the user turns
+    // off the page's styles.
+    author_style_disabled: bool,
+
     // The current selector maps, after evaluating media
-    // rules against the current device.
-    element_map: PerPseudoElementSelectorMap<Impl>,
-    pseudos_map: HashMap<Impl::PseudoElement, PerPseudoElementSelectorMap<Impl>, BuildHasherDefault<::fnv::FnvHasher>>,
+    // rules against the current device. The rules of each user agent
+    // stylesheet have maps of their own, which may be shared.
+    user_agent_rules: Vec<UserAgentRules<Impl>>,
+    user_rules: OriginRuleMaps<Impl>,
+    author_rules: OriginRuleMaps<Impl>,
     rules_source_order: usize,
 
+    // The rules of the author sheets that only apply under an element, by that element's
+    // node, in the order their first sheet came in.
+    scoped_rules: Vec<(OpaqueNode, OriginRuleMaps<Impl>)>,
+
+    // The author sheets scoped to the subtree of an element, and that element's node.
+    #[ignore_heap_size_of = "Measured with the rest of the sheets"]
+    stylesheet_scopes: Vec<(Arc<Stylesheet<Impl>>, OpaqueNode)>,
+
+    // The pseudo-elements to match rules for, and whether a rule was ever added
+    // for each of them.
+    pseudos: HashMap<Impl::PseudoElement, bool, BuildHasherDefault<::fnv::FnvHasher>>,
+
+    // The shared stylesheets whose rule maps this stylist uses when it's given
+    // their sheet.
+    #[ignore_heap_size_of = "Measured by whoever shares them"]
+    shared_stylesheets: Vec<Arc<SharedStylesheet<Impl>>>,
+
     // Selector dependencies used to compute restyle hints.
     state_deps: DependencySet<Impl>,
 }
@@ -115,15 +145,21 @@ impl<Impl: SelectorImplExt> Stylist<Impl> {
             device: device,
             is_device_dirty: true,
             quirks_mode: false,
+            author_style_disabled: false,
 
-            element_map: PerPseudoElementSelectorMap::new(),
-            pseudos_map: HashMap::with_hasher(Default::default()),
+            user_agent_rules: Vec::new(),
+            user_rules: OriginRuleMaps::new(),
+            author_rules: OriginRuleMaps::new(),
             rules_source_order: 0,
+            scoped_rules: Vec::new(),
+            stylesheet_scopes: Vec::new(),
+            pseudos: HashMap::with_hasher(Default::default()),
+            shared_stylesheets: Vec::new(),
             state_deps: DependencySet::new(),
         };
 
         Impl::each_eagerly_cascaded_pseudo_element(|pseudo| {
-            stylist.pseudos_map.insert(pseudo, PerPseudoElementSelectorMap::new());
+            stylist.pseudos.insert(pseudo, false);
         });
 
         // FIXME: Add iso-8859-9.css when the document’s encoding is ISO-8859-8.
@@ -137,10 +173,15 @@ impl<Impl: SelectorImplExt> Stylist<Impl> {
         if !(self.is_device_dirty || stylesheets_changed) {
             return false;
         }
-        self.element_map = PerPseudoElementSelectorMap::new();
-        self.pseudos_map = HashMap::with_hasher(Default::default());
+        self.user_agent_rules.clear();
+        self.user_rules = OriginRuleMaps::new();
+        self.author_rules = OriginRuleMaps::new();
+        self.scoped_rules.clear();
         self.rules_source_order = 0;
+        self.pseudos = HashMap::with_hasher(Default::default());
         self.state_deps.clear();
+        self.forget_unused_shared_stylesheets(doc_stylesheets);
+        self.forget_unused_scopes(doc_stylesheets);
 
         for ref stylesheet in Impl::get_user_or_user_agent_stylesheets().iter() {
             self.add_stylesheet(&stylesheet);
@@ -160,79 +201,265 @@ impl<Impl: SelectorImplExt> Stylist<Impl> {
         true
     }
 
-    fn add_stylesheet(&mut self, stylesheet: &Stylesheet<Impl>) {
-        let device = &self.device;
-        if !stylesheet.is_effective_for_device(device) {
-            return;
+    pub fn is_device_dirty(&self) -> bool {
+        self.is_device_dirty
+    }
+
+    /// Like update, but only rebuilds the rules that come from stylesheets of the given
+    /// origins, keeping the rest. Does a full update instead if the device changed.
+    ///
+    /// Returns true if anything was rebuilt.
+    pub fn update_origins(&mut self, doc_stylesheets: &[Arc<Stylesheet<Impl>>],
+                          origins: &[Origin]) -> bool
+                          where Impl: 'static {
+        if self.is_device_dirty {
+            return self.update(doc_stylesheets, true);
+        }
+        if origins.is_empty() {
+            return false;
         }
-        let mut rules_source_order = self.rules_source_order;
 
-        // Take apart the StyleRule into individual Rules and insert
-        // them into the SelectorMap of that priority.
-        macro_rules! append(
-            ($style_rule: ident, $priority: ident) => {
-                if $style_rule.declarations.$priority.len() > 0 {
-                    for selector in &$style_rule.selectors {
-                        let map = if let Some(ref pseudo) = selector.pseudo_element {
-                            self.pseudos_map.entry(pseudo.clone())
-                                            .or_insert_with(PerPseudoElementSelectorMap::new)
-                                            .borrow_for_origin(&stylesheet.origin)
-                        } else {
-                            self.element_map.borrow_for_origin(&stylesheet.origin)
-                        };
+        for origin in origins {
+            match *origin {
+                Origin::UserAgent => {
+                    self.user_agent_rules.clear();
+                    self.forget_unused_shared_stylesheets(doc_stylesheets);
+                }
+                Origin::User => self.user_rules = OriginRuleMaps::new(),
+                Origin::Author => {
+                    self.author_rules = OriginRuleMaps::new();
+                    self.scoped_rules.clear();
+                    self.forget_unused_scopes(doc_stylesheets);
+                }
+            }
+            self.state_deps.clear_origin(*origin);
+        }
 
-                        map.$priority.insert(Rule {
-                                selector: selector.compound_selectors.clone(),
-                                declarations: DeclarationBlock {
-                                    specificity: selector.specificity,
-                                    declarations: $style_rule.declarations.$priority.clone(),
-                                    source_order: rules_source_order,
-                                },
-                        });
-                    }
+        // Rule source order only matters within an origin, so the rebuilt rules can
+        // simply continue numbering after everything we already have.
+        for ref stylesheet in Impl::get_user_or_user_agent_stylesheets().iter() {
+            if origins.contains(&stylesheet.origin) {
+                self.add_stylesheet(&stylesheet);
+            }
+        }
+
+        if self.quirks_mode {
+            if let Some(s) = Impl::get_quirks_mode_stylesheet() {
+                if origins.contains(&s.origin) {
+                    self.add_stylesheet(s);
                 }
-            };
-        );
+            }
+        }
 
+        for ref stylesheet in doc_stylesheets.iter() {
+            if origins.contains(&stylesheet.origin) {
+                self.add_stylesheet(stylesheet);
+            }
+        }
+
+        true
+    }
+
+    /// Lets the stylist use the rule maps of `shared` rather than build its own whenever
+    /// it's given the sheet `shared` was made from. The sheet still has to be passed to
+    /// `update` like any other, and its origin rebuilt.
+    pub fn add_shared_stylesheet(&mut self, shared: Arc<SharedStylesheet<Impl>>) {
+        if !self.shared_stylesheets.iter().any(|x| arc_ptr_eq(x, &shared)) {
+            self.shared_stylesheets.push(shared);
+        }
+    }
+
+    /// Makes the author sheet `sheet` only apply to the descendants of the element whose
+    /// node is `scope`, like the sheets of an XBL binding or a shadow root. The sheet still
+    /// has to be passed to `update` like any other, and the author origin rebuilt. Its rules
+    /// cascade after those of the sheets that apply everywhere.
+    pub fn set_stylesheet_scope(&mut self, sheet: &Arc<Stylesheet<Impl>>, scope: OpaqueNode) {
+        self.stylesheet_scopes.retain(|&(ref x, _)| !arc_ptr_eq(x, sheet));
+        self.stylesheet_scopes.push((sheet.clone(), scope));
+    }
+
+    /// Forgets the scope of every sheet scoped to `scope`, once the element is gone, and
+    /// returns those sheets, which are for the caller to remove.
+    pub fn forget_scope(&mut self, scope: OpaqueNode) -> Vec<Arc<Stylesheet<Impl>>> {
+        let sheets = self.stylesheet_scopes.iter()
+                                           .filter(|&&(_, x)| x == scope)
+                                           .map(|&(ref sheet, _)| sheet.clone())
+                                           .collect();
+        self.stylesheet_scopes.retain(|&(_, x)| x != scope);
+        sheets
+    }
+
+    /// Whether some sheet is scoped to the subtree of `element`.
+    pub fn is_scope_root<E: TElement>(&self, element: &E) -> bool {
+        let node = element.as_node().opaque();
+        self.stylesheet_scopes.iter().any(|&(_, scope)| scope == node)
+    }
+
+    fn forget_unused_scopes(&mut self, doc_stylesheets: &[Arc<Stylesheet<Impl>>]) {
+        self.stylesheet_scopes.retain(|&(ref sheet, _)| doc_stylesheets.iter().any(|x| arc_ptr_eq(x, sheet)));
+    }
+
+    fn forget_unused_shared_stylesheets(&mut self, doc_stylesheets: &[Arc<Stylesheet<Impl>>]) {
+        self.shared_stylesheets.retain(|shared| doc_stylesheets.iter().any(|x| arc_ptr_eq(x, &shared.sheet)));
+    }
+
+    fn add_stylesheet(&mut self, stylesheet: &Stylesheet<Impl>) {
+        if stylesheet.disabled() || !stylesheet.is_effective_for_device(&self.device) {
+            return;
+        }
+        if stylesheet.origin == Origin::Author && self.author_style_disabled {
+            return;
+        }
+
+        let lowercase_ids_and_classes = self.lowercases_ids_and_classes(stylesheet.origin);
         for style_rule in stylesheet.effective_rules(&self.device).style() {
-            append!(style_rule, normal);
-            append!(style_rule, important);
-            rules_source_order += 1;
             for selector in &style_rule.selectors {
-                self.state_deps.note_selector(selector.compound_selectors.clone());
+                let compound_selectors = if lowercase_ids_and_classes {
+                    ascii_lowercase_ids_and_classes(&selector.compound_selectors)
+                } else {
+                    selector.compound_selectors.clone()
+                };
+                self.state_deps.note_selector(compound_selectors, stylesheet.origin);
+            }
+            let declarations = &style_rule.declarations;
+            for declaration in declarations.normal.iter().chain(declarations.important.iter()) {
+                if let PropertyDeclaration::Content(DeclaredValue::Value(ref content)) = *declaration {
+                    for name in content.attribute_names() {
+                        self.state_deps.note_content_attribute(Atom::from(name), stylesheet.origin);
+                    }
+                }
             }
         }
-        self.rules_source_order = rules_source_order;
+
+        let maps = match stylesheet.origin {
+            Origin::UserAgent => {
+                let shared_rules = self.shared_stylesheets.iter()
+                                       .find(|shared| &*shared.sheet as *const Stylesheet<Impl> ==
+                                                      stylesheet as *const Stylesheet<Impl>)
+                                       .and_then(|shared| shared.rules.clone());
+                let rules = match shared_rules {
+                    Some(rules) => UserAgentRules::Shared(rules),
+                    None => {
+                        let mut maps = OriginRuleMaps::new();
+                        maps.add_stylesheet(stylesheet, &self.device, 0, false);
+                        UserAgentRules::Own(maps)
+                    }
+                };
+                self.user_agent_rules.push(rules);
+                self.user_agent_rules.last().unwrap().maps()
+            }
+            Origin::User => {
+                self.rules_source_order = self.user_rules.add_stylesheet(stylesheet, &self.device,
+                                                                         self.rules_source_order,
+                                                                         lowercase_ids_and_classes);
+                &self.user_rules
+            }
+            Origin::Author => {
+                let scope = self.stylesheet_scopes.iter()
+                                .find(|&&(ref sheet, _)| &**sheet as *const Stylesheet<Impl> ==
+                                                         stylesheet as *const Stylesheet<Impl>)
+                                .map(|&(_, scope)| scope);
+                match scope {
+                    Some(scope) => {
+                        let index = match self.scoped_rules.iter().position(|&(x, _)| x == scope) {
+                            Some(index) => index,
+                            None => {
+                                self.scoped_rules.push((scope, OriginRuleMaps::new()));
+                                self.scoped_rules.len() - 1
+                            }
+                        };
+                        let maps = &mut self.scoped_rules[index].1;
+                        self.rules_source_order = maps.add_stylesheet(stylesheet, &self.device,
+                                                                      self.rules_source_order,
+                                                                      lowercase_ids_and_classes);
+                        &*maps
+                    }
+                    None => {
+                        self.rules_source_order = self.author_rules.add_stylesheet(stylesheet, &self.device,
+                                                                                   self.rules_source_order,
+                                                                                   lowercase_ids_and_classes);
+                        &self.author_rules
+                    }
+                }
+            }
+        };
+        for pseudo in maps.pseudos_map.keys() {
+            self.pseudos.insert(pseudo.clone(), true);
+        }
+    }
+
+    /// Whether a change to the document state bits in `changed_states` can change the style
+    /// of any element.
+    pub fn depends_on_document_state(&self, changed_states: u64) -> bool {
+        self.state_deps.depends_on_document_state(changed_states)
+    }
+
+    /// Whether some selector depends on the attribute with local name `name`. If none does,
+    /// changes to the attribute never need a restyle.
+    pub fn might_have_attribute_dependency(&self, name: &Atom) -> bool {
+        self.state_deps.might_have_attribute_dependency(name)
+    }
+
+    /// Whether any selector depends on the bits of element state in `states`. See
+    /// DependencySet::depends_on_element_state.
+    pub fn depends_on_element_state(&self, states: u64) -> bool {
+        self.state_deps.depends_on_element_state(states)
+    }
+
+    /// Calls `callback` with the local name of each attribute the styles depend on.
+    pub fn each_attribute_dependency<F>(&self, callback: F) where F: FnMut(&Atom) {
+        self.state_deps.each_attribute_dependency(callback)
     }
 
-    pub fn compute_restyle_hint<E>(&self, element: &E,
-                                   snapshot: &ElementSnapshot,
-                                   // NB: We need to pass current_state as an argument because
-                                   // selectors::Element doesn't provide access to ElementState
-                                   // directly, and computing it from the ElementState would be
-                                   // more expensive than getting it directly from the caller.
-                                   current_state: ElementState)
-                                   -> RestyleHint
-                                   where E: Element<Impl=Impl> + Clone {
+    pub fn compute_restyle_hint<E, S>(&self, element: &E,
+                                      snapshot: &S,
+                                      // NB: We need to pass current_state as an argument because
+                                      // selectors::Element doesn't provide access to ElementState
+                                      // directly, and computing it from the ElementState would be
+                                      // more expensive than getting it directly from the caller.
+                                      current_state: ElementState)
+                                      -> RestyleHint
+                                      where E: Element<Impl=Impl> + Clone,
+                                            S: ElementSnapshot {
         self.state_deps.compute_hint(element, snapshot, current_state)
     }
 
-    pub fn set_device(&mut self, mut device: Device, stylesheets: &[Arc<Stylesheet<Impl>>]) {
+    /// The restyle hints of the ancestors of `element` that a change to its state since
+    /// `snapshot` gives them, for selectors like :focus-within. See
+    /// DependencySet::compute_ancestor_hints.
+    pub fn compute_ancestor_restyle_hints<E, S>(&self, element: &E, snapshot: &S, current_state: ElementState)
+                                                -> Vec<(E, RestyleHint)>
+                                                where E: TElement + Element<Impl=Impl>,
+                                                      S: ElementSnapshot {
+        self.state_deps.compute_ancestor_hints(element, snapshot, current_state)
+    }
+
+    /// Switches to a new device, and marks the stylist dirty if that changes which
+    /// stylesheets or @media rules apply.
+    ///
+    /// Returns whether anything's applicability changed.
+    pub fn set_device(&mut self, mut device: Device, stylesheets: &[Arc<Stylesheet<Impl>>]) -> bool {
         let cascaded_rule = stylesheets.iter()
             .flat_map(|s| s.effective_rules(&self.device).viewport())
             .cascade();
 
         self.viewport_constraints = ViewportConstraints::maybe_new(device.viewport_size, &cascaded_rule);
         if let Some(ref constraints) = self.viewport_constraints {
-            device = Device::new(MediaType::Screen, constraints.size);
+            device = Device::new(device.media_type, constraints.size);
         }
 
-        self.is_device_dirty |= stylesheets.iter().any(|stylesheet| {
+        let applicability_changed = stylesheets.iter().any(|stylesheet| {
+                stylesheet.is_effective_for_device(&self.device) !=
+                    stylesheet.is_effective_for_device(&device) ||
                 stylesheet.rules().media().any(|media_rule|
-                    media_rule.evaluate(&self.device) != media_rule.evaluate(&device))
+                    media_rule.evaluate(&self.device) != media_rule.evaluate(&device)) ||
+                stylesheet.rules().import().any(|import_rule|
+                    import_rule.evaluate(&self.device) != import_rule.evaluate(&device))
         });
+        self.is_device_dirty |= applicability_changed;
 
         self.device = device;
+        applicability_changed
     }
 
     pub fn viewport_constraints(&self) -> &Option<ViewportConstraints> {
@@ -243,98 +470,370 @@ impl<Impl: SelectorImplExt> Stylist<Impl> {
         self.quirks_mode = enabled;
     }
 
+    /// Whether the ids and classes in the selectors of `origin`'s rules are lowercased, as
+    /// those of quirks mode documents are for implementations whose elements lowercase theirs.
+    /// User agent sheets are shared across documents, and only use lowercase ones.
+    fn lowercases_ids_and_classes(&self, origin: Origin) -> bool {
+        self.quirks_mode && origin != Origin::UserAgent && Impl::lowercases_ids_and_classes_in_quirks_mode()
+    }
+
+    /// Turns author style off or back on. Style attributes follow straight away, but the
+    /// author sheets only once the author origin is updated.
+    pub fn set_author_style_disabled(&mut self, disabled: bool) {
+        self.author_style_disabled = disabled;
+    }
+
+    pub fn author_style_disabled(&self) -> bool {
+        self.author_style_disabled
+    }
+
+    /// Whether any stylesheet has a rule for `pseudo`, so that elements in documents without
+    /// any don't need to be matched for it. Once an update of some origins removes the last
+    /// rule, this stays true until the next full update.
+    pub fn has_rules_for_pseudo(&self, pseudo: &Impl::PseudoElement) -> bool {
+        self.pseudos.get(pseudo).map_or(false, |&has_rules| has_rules)
+    }
+
+    /// Whether `block` comes from a rule that only matches through a visited link
+    /// (`Some(true)`) or only through an unvisited one (`Some(false)`).
+    pub fn link_visitedness(&self, block: &DeclarationBlock) -> Option<bool> {
+        let key = declarations_key(&block.declarations);
+        let mut visitedness = None;
+        self.any_origin_maps(|maps| {
+            visitedness = maps.link_visitedness.get(&key).cloned();
+            visitedness.is_some()
+        });
+        visitedness
+    }
+
+    /// Whether some rule only matches through a visited link or only through an unvisited
+    /// one, so that links and what's inside them get a visited style of their own.
+    pub fn has_link_dependent_rules(&self) -> bool {
+        self.any_origin_maps(|maps| !maps.link_visitedness.is_empty())
+    }
+
+    /// The declarations of the user agent rules for the `pseudo` anonymous box, in cascade
+    /// order. There's no element to match their selectors against, so they all apply.
+    pub fn anonymous_box_declarations(&self, pseudo: &Impl::PseudoElement) -> Vec<DeclarationBlock> {
+        let mut declarations = vec![];
+        for &important in &[false, true] {
+            let start = declarations.len();
+            for rules in &self.user_agent_rules {
+                if let Some(anonymous_box) = rules.maps().anonymous_boxes.get(pseudo) {
+                    declarations.extend(anonymous_box.declarations(important).iter().cloned());
+                }
+            }
+            declarations[start..].sort_by_key(|block| (block.specificity, block.source_order));
+        }
+        declarations
+    }
+
+    /// Whether `f` returns true for the rule maps of any origin or scope.
+    fn any_origin_maps<F>(&self, mut f: F) -> bool where F: FnMut(&OriginRuleMaps<Impl>) -> bool {
+        for rules in &self.user_agent_rules {
+            if f(rules.maps()) {
+                return true;
+            }
+        }
+        if f(&self.user_rules) || f(&self.author_rules) {
+            return true;
+        }
+        self.scoped_rules.iter().any(|&(_, ref maps)| f(maps))
+    }
+
     /// Returns the applicable CSS declarations for the given element. This corresponds to
     /// `ElementRuleCollector` in WebKit.
     ///
     /// The returned boolean indicates whether the style is *shareable*; that is, whether the
     /// matched selectors are simple enough to allow the matching logic to be reduced to the logic
     /// in `css::matching::PrivateMatchMethods::candidate_element_allows_for_style_sharing`.
+    ///
+    /// `guard` is for reading the presentational hints and animation values of the element.
     pub fn push_applicable_declarations<E, V>(
                                         &self,
                                         element: &E,
+                                        guard: &SharedRwLockReadGuard,
+                                        parent_bf: Option<&BloomFilter>,
+                                        style_attribute: Option<&PropertyDeclarationBlock>,
+                                        pseudo_element: Option<Impl::PseudoElement>,
+                                        applicable_declarations: &mut V)
+                                        -> bool
+                                        where E: Element<Impl=Impl> + TElement,
+                                              V: VecLike<DeclarationBlock> {
+        self.push_applicable_declarations_with_extra_blocks(element, guard, parent_bf, style_attribute,
+                                                            pseudo_element, &[], applicable_declarations)
+    }
+
+    /// Like `push_applicable_declarations`, but also cascades each of `extra_blocks` at its
+    /// level, after everything else there, as if it were the last rule of that level.
+    pub fn push_applicable_declarations_with_extra_blocks<E, V>(
+                                        &self,
+                                        element: &E,
+                                        guard: &SharedRwLockReadGuard,
                                         parent_bf: Option<&BloomFilter>,
                                         style_attribute: Option<&PropertyDeclarationBlock>,
                                         pseudo_element: Option<Impl::PseudoElement>,
+                                        extra_blocks: &[(CascadeLevel, DeclarationBlock)],
                                         applicable_declarations: &mut V)
                                         -> bool
                                         where E: Element<Impl=Impl> + TElement,
                                               V: VecLike<DeclarationBlock> {
+        self.push_applicable_declarations_by_level(element, guard, parent_bf, style_attribute, pseudo_element,
+                                                   extra_blocks, applicable_declarations, None)
+    }
+
+    /// Like `push_applicable_declarations`, but also pushes onto `levels` which of the pushed
+    /// declarations are at each level, for the levels that have some, in order. The rule tree tags its
+    /// nodes with them, so that the values of the element's animations can be swapped for
+    /// newer ones later on without matching again.
+    pub fn push_applicable_declarations_and_levels<E, V>(
+                                        &self,
+                                        element: &E,
+                                        guard: &SharedRwLockReadGuard,
+                                        parent_bf: Option<&BloomFilter>,
+                                        style_attribute: Option<&PropertyDeclarationBlock>,
+                                        pseudo_element: Option<Impl::PseudoElement>,
+                                        applicable_declarations: &mut V,
+                                        levels: &mut Vec<(CascadeLevel, Range<usize>)>)
+                                        -> bool
+                                        where E: Element<Impl=Impl> + TElement,
+                                              V: VecLike<DeclarationBlock> {
+        self.push_applicable_declarations_by_level(element, guard, parent_bf, style_attribute, pseudo_element,
+                                                   &[], applicable_declarations, Some(levels))
+    }
+
+    fn push_applicable_declarations_by_level<E, V>(
+                                        &self,
+                                        element: &E,
+                                        guard: &SharedRwLockReadGuard,
+                                        parent_bf: Option<&BloomFilter>,
+                                        style_attribute: Option<&PropertyDeclarationBlock>,
+                                        pseudo_element: Option<Impl::PseudoElement>,
+                                        extra_blocks: &[(CascadeLevel, DeclarationBlock)],
+                                        applicable_declarations: &mut V,
+                                        mut levels: Option<&mut Vec<(CascadeLevel, Range<usize>)>>)
+                                        -> bool
+                                        where E: Element<Impl=Impl> + TElement,
+                                              V: VecLike<DeclarationBlock> {
         assert!(!self.is_device_dirty);
         assert!(style_attribute.is_none() || pseudo_element.is_none(),
                 "Style attributes do not apply to pseudo-elements");
+        // Style attributes are author style, and go along with the author sheets.
+        let style_attribute = if self.author_style_disabled { None } else { style_attribute };
 
-        let map = match pseudo_element {
-            Some(ref pseudo) => match self.pseudos_map.get(pseudo) {
-                Some(map) => map,
+        if let Some(ref pseudo) = pseudo_element {
+            if !self.pseudos.contains_key(pseudo) {
                 // TODO(emilio): get non eagerly-cascaded pseudo-element rules here.
                 // Actually assume there are no rules applicable.
-                None => return true,
-            },
-            None => &self.element_map,
-        };
+                return true;
+            }
+        }
+        let pseudo = pseudo_element.as_ref();
 
-        let mut shareable = true;
+        let mut shareable = extra_blocks.is_empty();
+        let native_anonymous = element.is_native_anonymous();
 
+        // Which scopes the element is in, worked out once for both importances. Scope
+        // roots never share their style, so that nothing in a scope can share the style of
+        // something outside it through their parents.
+        let in_scopes: Vec<bool> = self.scoped_rules.iter().map(|&(scope, _)| {
+            is_descendant_of(element, scope)
+        }).collect();
+        if !self.scoped_rules.is_empty() && self.is_scope_root(element) {
+            shareable = false;
+        }
 
-        // Step 1: Normal user-agent rules.
-        map.user_agent.normal.get_all_matching_rules(element,
-                                                     parent_bf,
-                                                     applicable_declarations,
-                                                     &mut shareable);
+        for &level in CascadeLevel::all() {
+            // Nothing pages or users write applies to native anonymous content.
+            if native_anonymous {
+                match level {
+                    CascadeLevel::UserAgentNormal | CascadeLevel::UserAgentImportant => {}
+                    _ if level.is_animation() => {}
+                    _ => continue,
+                }
+            }
 
-        // Step 2: Presentational hints.
-        let length = applicable_declarations.len();
-        element.synthesize_presentational_hints_for_legacy_attributes(applicable_declarations);
-        if applicable_declarations.len() != length {
-            // Never share style for elements with preshints
-            shareable = false;
+            let level_start = applicable_declarations.len();
+            match level {
+                CascadeLevel::PresentationalHints => {
+                    let length = applicable_declarations.len();
+                    element.synthesize_presentational_hints_for_legacy_attributes(guard, applicable_declarations);
+                    if applicable_declarations.len() != length {
+                        // Never share style for elements with preshints
+                        shareable = false;
+                    }
+                }
+                CascadeLevel::StyleAttributeNormal | CascadeLevel::StyleAttributeImportant => {
+                    if let Some(sa) = style_attribute {
+                        shareable = false;
+                        let declarations = if level == CascadeLevel::StyleAttributeNormal {
+                            &sa.normal
+                        } else {
+                            &sa.important
+                        };
+                        applicable_declarations.push(
+                            GenericDeclarationBlock::from_declarations(declarations.clone()))
+                    }
+                }
+                CascadeLevel::SMILOverride | CascadeLevel::Animations | CascadeLevel::Transitions => {
+                    // Animations run on elements, not on their pseudo-elements.
+                    if pseudo.is_none() {
+                        element.get_animation_declarations(level, guard, applicable_declarations);
+                        if applicable_declarations.len() != level_start {
+                            shareable = false;
+                        }
+                    }
+                }
+                CascadeLevel::UserAgentNormal | CascadeLevel::UserAgentImportant => {
+                    let important = level == CascadeLevel::UserAgentImportant;
+                    let length = applicable_declarations.len();
+                    for rules in &self.user_agent_rules {
+                        if let Some(map) = rules.maps().map_for(pseudo) {
+                            map.rules(important).get_all_matching_rules(element,
+                                                                        parent_bf,
+                                                                        applicable_declarations,
+                                                                        &mut shareable);
+                        }
+                    }
+                    // The rules of each sheet come sorted by specificity and source order, and
+                    // a stable sort by specificity keeps those of later sheets after the ones
+                    // of earlier sheets they tie with.
+                    if self.user_agent_rules.len() > 1 {
+                        applicable_declarations[length..].sort_by_key(|block| block.specificity);
+                    }
+                }
+                CascadeLevel::UserNormal | CascadeLevel::UserImportant |
+                CascadeLevel::AuthorNormal | CascadeLevel::AuthorImportant => {
+                    let rules = match level {
+                        CascadeLevel::UserNormal | CascadeLevel::UserImportant => &self.user_rules,
+                        _ => &self.author_rules,
+                    };
+                    let important = level == CascadeLevel::UserImportant || level == CascadeLevel::AuthorImportant;
+                    if let Some(map) = rules.map_for(pseudo) {
+                        map.rules(important).get_all_matching_rules(element,
+                                                                    parent_bf,
+                                                                    applicable_declarations,
+                                                                    &mut shareable);
+                    }
+                    if level == CascadeLevel::AuthorNormal || level == CascadeLevel::AuthorImportant {
+                        let scoped = self.scoped_rules.iter().zip(&in_scopes).filter(|&(_, &in_scope)| in_scope);
+                        for (&(_, ref maps), _) in scoped {
+                            if let Some(map) = maps.map_for(pseudo) {
+                                map.rules(important).get_all_matching_rules(element,
+                                                                            parent_bf,
+                                                                            applicable_declarations,
+                                                                            &mut shareable);
+                            }
+                        }
+                    }
+                }
+            }
+            for &(extra_level, ref block) in extra_blocks {
+                if extra_level == level {
+                    applicable_declarations.push(block.clone());
+                }
+            }
+            if let Some(ref mut levels) = levels {
+                if applicable_declarations.len() != level_start {
+                    levels.push((level, level_start..applicable_declarations.len()));
+                }
+            }
         }
 
-        // Step 3: User and author normal rules.
-        map.user.normal.get_all_matching_rules(element,
-                                               parent_bf,
-                                               applicable_declarations,
-                                               &mut shareable);
-        map.author.normal.get_all_matching_rules(element,
-                                                 parent_bf,
-                                                 applicable_declarations,
-                                                 &mut shareable);
+        shareable
+    }
 
-        // Step 4: Normal style attributes.
-        style_attribute.map(|sa| {
-            shareable = false;
-            applicable_declarations.push(
-                GenericDeclarationBlock::from_declarations(sa.normal.clone()))
-        });
+    pub fn is_device_dirty(&self) -> bool {
+        self.is_device_dirty
+    }
+}
 
-        // Step 5: Author-supplied `!important` rules.
-        map.author.important.get_all_matching_rules(element,
-                                                    parent_bf,
-                                                    applicable_declarations,
-                                                    &mut shareable);
+/// Whether `element` is in the subtree of the element whose node is `scope`, not counting
+/// that element itself.
+fn is_descendant_of<E: Element + TElement>(element: &E, scope: OpaqueNode) -> bool {
+    let mut current = element.parent_element();
+    while let Some(ancestor) = current {
+        if ancestor.as_node().opaque() == scope {
+            return true;
+        }
+        current = ancestor.parent_element();
+    }
+    false
+}
 
-        // Step 6: `!important` style attributes.
-        style_attribute.map(|sa| {
-            shareable = false;
-            applicable_declarations.push(
-                GenericDeclarationBlock::from_declarations(sa.important.clone()))
-        });
+/// Where declarations come from, in the order they cascade in: a declaration from a later
+/// level wins over one from an earlier level, whatever their specificity. Importance
+/// reverses the order of the origins.
+///
+/// https://drafts.csswg.org/css-cascade/#cascade-origin
+///
+/// The levels animations cascade at are where Gecko puts them: SMIL animations override
+/// every normal declaration, the animations Web Animations and CSS animations run win over
+/// those, and transitions win over everything but important user agent declarations.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CascadeLevel {
+    UserAgentNormal,
+    PresentationalHints,
+    UserNormal,
+    AuthorNormal,
+    StyleAttributeNormal,
+    /// The values SMIL animates the element's properties to.
+    SMILOverride,
+    /// The current values of the element's running animations.
+    Animations,
+    AuthorImportant,
+    StyleAttributeImportant,
+    UserImportant,
+    /// The current values of the element's running transitions.
+    Transitions,
+    UserAgentImportant,
+}
 
-        // Step 7: User and UA `!important` rules.
-        map.user.important.get_all_matching_rules(element,
-                                                  parent_bf,
-                                                  applicable_declarations,
-                                                  &mut shareable);
-        map.user_agent.important.get_all_matching_rules(element,
-                                                        parent_bf,
-                                                        applicable_declarations,
-                                                        &mut shareable);
+impl CascadeLevel {
+    /// Every level, from the one that loses to the one that wins.
+    pub fn all() -> &'static [CascadeLevel] {
+        static ALL: [CascadeLevel; 12] = [
+            CascadeLevel::UserAgentNormal,
+            CascadeLevel::PresentationalHints,
+            CascadeLevel::UserNormal,
+            CascadeLevel::AuthorNormal,
+            CascadeLevel::StyleAttributeNormal,
+            CascadeLevel::SMILOverride,
+            CascadeLevel::Animations,
+            CascadeLevel::AuthorImportant,
+            CascadeLevel::StyleAttributeImportant,
+            CascadeLevel::UserImportant,
+            CascadeLevel::Transitions,
+            CascadeLevel::UserAgentImportant,
+        ];
+        &ALL
+    }
 
-        shareable
+    /// The levels whose blocks hold animated values, which the animation-only restyle swaps
+    /// for newer ones.
+    pub fn animations() -> &'static [CascadeLevel] {
+        static ANIMATIONS: [CascadeLevel; 3] = [
+            CascadeLevel::SMILOverride,
+            CascadeLevel::Animations,
+            CascadeLevel::Transitions,
+        ];
+        &ANIMATIONS
     }
 
-    pub fn is_device_dirty(&self) -> bool {
-        self.is_device_dirty
+    pub fn is_animation(&self) -> bool {
+        CascadeLevel::animations().contains(self)
+    }
+
+    /// The level of the rules of a sheet with the given origin.
+    pub fn for_rules(origin: Origin, important: bool) -> CascadeLevel {
+        match (origin, important) {
+            (Origin::UserAgent, false) => CascadeLevel::UserAgentNormal,
+            (Origin::User, false) => CascadeLevel::UserNormal,
+            (Origin::Author, false) => CascadeLevel::AuthorNormal,
+            (Origin::Author, true) => CascadeLevel::AuthorImportant,
+            (Origin::User, true) => CascadeLevel::UserImportant,
+            (Origin::UserAgent, true) => CascadeLevel::UserAgentImportant,
+        }
     }
 }
 
@@ -352,31 +851,204 @@ impl<Impl: SelectorImpl> PerOriginSelectorMap<Impl> {
             important: SelectorMap::new(),
         }
     }
+
+    fn rules(&self, important: bool) -> &SelectorMap<Vec<PropertyDeclaration>, Impl> {
+        if important {
+            &self.important
+        } else {
+            &self.normal
+        }
+    }
 }
 
+/// The declarations of rules that apply without matching their selectors, by importance.
+#[derive(Default)]
+struct PerOriginDeclarations {
+    normal: Vec<DeclarationBlock>,
+    important: Vec<DeclarationBlock>,
+}
+
+impl PerOriginDeclarations {
+    fn declarations(&self, important: bool) -> &[DeclarationBlock] {
+        if important {
+            &self.important
+        } else {
+            &self.normal
+        }
+    }
+}
+
+/// The rules of the stylesheets of a single origin, by pseudo-element.
 #[derive(HeapSizeOf)]
-struct PerPseudoElementSelectorMap<Impl: SelectorImpl> {
-    user_agent: PerOriginSelectorMap<Impl>,
-    author: PerOriginSelectorMap<Impl>,
-    user: PerOriginSelectorMap<Impl>,
+struct OriginRuleMaps<Impl: SelectorImplExt> {
+    element_map: PerOriginSelectorMap<Impl>,
+    /// The rules for each pseudo-element with any.
+    pseudos_map: HashMap<Impl::PseudoElement, PerOriginSelectorMap<Impl>, BuildHasherDefault<::fnv::FnvHasher>>,
+    /// Whether the rules whose declarations these are, by `declarations_key`, only match
+    /// through a visited link or only through an unvisited one. Those rules get a copy of
+    /// their declarations, so that they aren't mistaken for the other selectors of their
+    /// style rule.
+    link_visitedness: HashMap<usize, bool, BuildHasherDefault<::fnv::FnvHasher>>,
+    /// The declarations of the rules for each anonymous box, in the order of the rules.
+    #[ignore_heap_size_of = "The declarations are measured through pseudos_map"]
+    anonymous_boxes: HashMap<Impl::PseudoElement, PerOriginDeclarations, BuildHasherDefault<::fnv::FnvHasher>>,
 }
 
-impl<Impl: SelectorImpl> PerPseudoElementSelectorMap<Impl> {
-    #[inline]
-    fn new() -> PerPseudoElementSelectorMap<Impl> {
-        PerPseudoElementSelectorMap {
-            user_agent: PerOriginSelectorMap::new(),
-            author: PerOriginSelectorMap::new(),
-            user: PerOriginSelectorMap::new(),
+impl<Impl: SelectorImplExt> OriginRuleMaps<Impl> {
+    fn new() -> OriginRuleMaps<Impl> {
+        OriginRuleMaps {
+            element_map: PerOriginSelectorMap::new(),
+            pseudos_map: HashMap::with_hasher(Default::default()),
+            link_visitedness: HashMap::with_hasher(Default::default()),
+            anonymous_boxes: HashMap::with_hasher(Default::default()),
         }
     }
 
-    #[inline]
-    fn borrow_for_origin(&mut self, origin: &Origin) -> &mut PerOriginSelectorMap<Impl> {
-        match *origin {
-            Origin::UserAgent => &mut self.user_agent,
-            Origin::Author => &mut self.author,
-            Origin::User => &mut self.user,
+    /// Adds the style rules of `stylesheet` that apply on `device`, numbering them from
+    /// `rules_source_order`, with the ids and classes of their selectors lowercased if
+    /// `lowercase_ids_and_classes`, and their nth and attribute selectors matched with the
+    /// pseudo-classes `Impl` has for them, if any. Returns the number the next rule gets.
+    fn add_stylesheet(&mut self, stylesheet: &Stylesheet<Impl>, device: &Device,
+                      mut rules_source_order: usize, lowercase_ids_and_classes: bool) -> usize {
+        // Take apart the StyleRule into individual Rules and insert
+        // them into the SelectorMap of that priority.
+        macro_rules! append(
+            ($style_rule: ident, $priority: ident) => {
+                if $style_rule.declarations.$priority.len() > 0 {
+                    for selector in &$style_rule.selectors {
+                        let map = if let Some(ref pseudo) = selector.pseudo_element {
+                            self.pseudos_map.entry(pseudo.clone()).or_insert_with(PerOriginSelectorMap::new)
+                        } else {
+                            &mut self.element_map
+                        };
+
+                        let declarations = match link_visitedness(&selector.compound_selectors) {
+                            Some(visited) => {
+                                let declarations = Arc::new((*$style_rule.declarations.$priority).clone());
+                                self.link_visitedness.insert(declarations_key(&declarations), visited);
+                                declarations
+                            }
+                            None => $style_rule.declarations.$priority.clone(),
+                        };
+                        let block = DeclarationBlock {
+                            specificity: selector.specificity,
+                            declarations: declarations,
+                            source_order: rules_source_order,
+                        };
+                        if let Some(ref pseudo) = selector.pseudo_element {
+                            if Impl::pseudo_element_is_anonymous_box(pseudo) {
+                                self.anonymous_boxes.entry(pseudo.clone())
+                                                    .or_insert_with(Default::default)
+                                                    .$priority.push(block.clone());
+                            }
+                        }
+                        let compound_selectors = if lowercase_ids_and_classes {
+                            ascii_lowercase_ids_and_classes(&selector.compound_selectors)
+                        } else {
+                            selector.compound_selectors.clone()
+                        };
+                        let compound_selectors = with_matching_pseudo_classes(&compound_selectors);
+                        map.$priority.insert(Rule {
+                                selector: compound_selectors,
+                                declarations: block,
+                        });
+                    }
+                }
+            };
+        );
+
+        for style_rule in stylesheet.effective_rules(device).style() {
+            append!(style_rule, normal);
+            append!(style_rule, important);
+            rules_source_order += 1;
+        }
+        rules_source_order
+    }
+
+    /// The rules for `pseudo`, or for elements themselves if it's None.
+    fn map_for(&self, pseudo: Option<&Impl::PseudoElement>) -> Option<&PerOriginSelectorMap<Impl>> {
+        match pseudo {
+            Some(pseudo) => self.pseudos_map.get(pseudo),
+            None => Some(&self.element_map),
+        }
+    }
+}
+
+/// Whether `selector` only matches through a link that's visited (`Some(true)`) or only
+/// through one that isn't (`Some(false)`), for implementations that style links both ways.
+/// Those only let one link match `:visited`, so a selector that also needs `:link` goes
+/// with the visited one.
+fn link_visitedness<Impl: SelectorImplExt>(selector: &CompoundSelector<Impl>) -> Option<bool> {
+    let mut visitedness = None;
+    let mut compound = Some(selector);
+    while let Some(current) = compound {
+        for simple in &current.simple_selectors {
+            if let SimpleSelector::NonTSPseudoClass(ref pc) = *simple {
+                match Impl::pseudo_class_link_visitedness(pc) {
+                    Some(true) => return Some(true),
+                    Some(false) => visitedness = Some(false),
+                    None => {}
+                }
+            }
+        }
+        compound = current.next.as_ref().map(|&(ref next, _)| &**next);
+    }
+    visitedness
+}
+
+/// The rule maps of a user agent stylesheet: made by the stylist itself, or shared
+/// with other stylists through a `SharedStylesheet`.
+#[derive(HeapSizeOf)]
+enum UserAgentRules<Impl: SelectorImplExt> {
+    Own(OriginRuleMaps<Impl>),
+    Shared(#[ignore_heap_size_of = "Measured by whoever shares them"] Arc<OriginRuleMaps<Impl>>),
+}
+
+impl<Impl: SelectorImplExt> UserAgentRules<Impl> {
+    fn maps(&self) -> &OriginRuleMaps<Impl> {
+        match *self {
+            UserAgentRules::Own(ref maps) => maps,
+            UserAgentRules::Shared(ref maps) => maps,
+        }
+    }
+}
+
+/// A stylesheet frozen so that the stylists of several documents can use it at once. For
+/// user agent sheets whose rules apply whatever the device, the rule maps are built once
+/// here too, and stylists told about the sheet with `Stylist::add_shared_stylesheet` use
+/// them instead of building their own.
+pub struct SharedStylesheet<Impl: SelectorImplExt> {
+    pub sheet: Arc<Stylesheet<Impl>>,
+    rules: Option<Arc<OriginRuleMaps<Impl>>>,
+}
+
+impl<Impl: SelectorImplExt> SharedStylesheet<Impl> {
+    /// Freezes `sheet` and builds its rule maps, if they can be shared.
+    pub fn new(sheet: Arc<Stylesheet<Impl>>) -> SharedStylesheet<Impl> {
+        sheet.freeze();
+        let rules = if sheet.origin == Origin::UserAgent && !sheet.depends_on_device() {
+            // Nothing in the sheet depends on the device, so any will do.
+            let device = Device::new(MediaType::Screen, Size2D::typed(0.0, 0.0));
+            let mut maps = OriginRuleMaps::new();
+            maps.add_stylesheet(&sheet, &device, 0, false);
+            Some(Arc::new(maps))
+        } else {
+            None
+        };
+        SharedStylesheet {
+            sheet: sheet,
+            rules: rules,
         }
     }
+
+    /// Whether stylists share the rule maps of the sheet as well as its rules.
+    pub fn shares_rule_maps(&self) -> bool {
+        self.rules.is_some()
+    }
+
+    /// Measures the rule maps stylists share, which they leave out of their own size. The
+    /// sheet is measured on its own.
+    pub fn heap_size_of_rule_maps(&self) -> usize {
+        self.rules.as_ref().map_or(0, |rules| rules.heap_size_of_children())
+    }
 }
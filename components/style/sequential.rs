@@ -16,7 +16,9 @@ pub fn traverse_dom<N, C>(root: N,
         context.process_preorder(node);
 
         for kid in node.children() {
-            doit::<N, C>(context, kid);
+            if context.should_traverse_child(kid) {
+                doit::<N, C>(context, kid);
+            }
         }
 
         context.process_postorder(node);
@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A read/write lock that many pieces of style data share, so that a restyle can lock all
+//! of them for reading at once, instead of taking a lock for every block it looks at.
+
+#![allow(unsafe_code)]
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use util::arc_ptr_eq;
+
+/// The lock itself. Clones are handles to the same lock.
+#[derive(Clone)]
+pub struct SharedRwLock {
+    cell: Arc<RwLock<()>>,
+}
+
+impl fmt::Debug for SharedRwLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SharedRwLock")
+    }
+}
+
+impl SharedRwLock {
+    pub fn new() -> SharedRwLock {
+        SharedRwLock { cell: Arc::new(RwLock::new(())) }
+    }
+
+    /// Blocks until nobody holds the lock for writing, then holds it for reading until the
+    /// guard is dropped.
+    pub fn read(&self) -> SharedRwLockReadGuard {
+        let guard = self.cell.read().unwrap();
+        SharedRwLockReadGuard {
+            // The guard only borrows the lock, which the guard keeps alive.
+            _guard: unsafe { mem::transmute::<RwLockReadGuard<()>, RwLockReadGuard<'static, ()>>(guard) },
+            cell: self.cell.clone(),
+        }
+    }
+
+    /// Blocks until nobody holds the lock, then holds it for writing until the guard is
+    /// dropped.
+    pub fn write(&self) -> SharedRwLockWriteGuard {
+        let guard = self.cell.write().unwrap();
+        SharedRwLockWriteGuard {
+            _guard: unsafe { mem::transmute::<RwLockWriteGuard<()>, RwLockWriteGuard<'static, ()>>(guard) },
+            cell: self.cell.clone(),
+        }
+    }
+}
+
+/// Proof that a `SharedRwLock` is held for reading. It doesn't borrow the lock, so that it
+/// can be kept in a traversal's shared context.
+pub struct SharedRwLockReadGuard {
+    // Declared before `cell` so that it's dropped first.
+    _guard: RwLockReadGuard<'static, ()>,
+    cell: Arc<RwLock<()>>,
+}
+
+/// Proof that a `SharedRwLock` is held for writing.
+pub struct SharedRwLockWriteGuard {
+    _guard: RwLockWriteGuard<'static, ()>,
+    cell: Arc<RwLock<()>>,
+}
+
+/// Data only reachable with a guard of the lock it was made with.
+pub struct Locked<T> {
+    shared_lock: SharedRwLock,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Locked<T> {}
+unsafe impl<T: Send + Sync> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+    pub fn new(data: T, shared_lock: &SharedRwLock) -> Locked<T> {
+        Locked {
+            shared_lock: shared_lock.clone(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// The lock that guards the data.
+    pub fn shared_lock(&self) -> &SharedRwLock {
+        &self.shared_lock
+    }
+
+    /// Panics if `guard` is a guard of some other lock.
+    pub fn read_with<'a>(&'a self, guard: &'a SharedRwLockReadGuard) -> &'a T {
+        assert!(arc_ptr_eq(&self.shared_lock.cell, &guard.cell),
+                "Locked::read_with called with a guard of an unrelated lock");
+        unsafe { &*self.data.get() }
+    }
+
+    /// Panics if `guard` is a guard of some other lock. The guard is borrowed mutably for as
+    /// long as the data is, so that nothing else can be looking at it meanwhile.
+    pub fn write_with<'a>(&'a self, guard: &'a mut SharedRwLockWriteGuard) -> &'a mut T {
+        assert!(arc_ptr_eq(&self.shared_lock.cell, &guard.cell),
+                "Locked::write_with called with a guard of an unrelated lock");
+        unsafe { &mut *self.data.get() }
+    }
+}
@@ -2,23 +2,36 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use cssparser::{AtRuleParser, Parser, QualifiedRuleParser, decode_stylesheet_bytes};
-use cssparser::{AtRuleType, RuleListParser};
+use cssparser::{AtRuleParser, Delimiter, Parser, QualifiedRuleParser, SourcePosition, decode_stylesheet_bytes};
+use cssparser::{AtRuleType, RuleListParser, ToCss, Token, serialize_string};
+use counter_style::{CounterStyleRule, parse_counter_style_block, parse_counter_style_name_definition};
 use encoding::EncodingRef;
-use error_reporting::ParseErrorReporter;
+use error_reporting::{ParseErrorDetails, ParseErrorReporter};
 use font_face::{FontFaceRule, parse_font_face_block};
+use keyframes::{KeyframesRule, parse_keyframe_list};
 use media_queries::{Device, MediaQueryList, parse_media_query_list};
-use parser::{ParserContext, log_css_error};
+use page::{PageRule, PageSelector, parse_page_block, parse_page_selectors};
+use parser::{ParserContext, QuirksMode, log_css_error, log_css_error_with_details};
 use properties::{PropertyDeclarationBlock, parse_property_declaration_list};
+use selector_impl::{SelectorImplExt, find_invalid_selector, serialize_selector_list};
 use selectors::parser::{Selector, SelectorImpl, parse_selector_list};
 use smallvec::SmallVec;
 use std::ascii::AsciiExt;
+use std::borrow::Cow;
 use std::cell::Cell;
+use std::fmt;
 use std::iter::Iterator;
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, Range};
 use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 use string_cache::{Atom, Namespace};
+use supports::SupportsCondition;
 use url::Url;
+use url_value::UrlExtraData;
+use util::arc_ptr_eq;
 use viewport::ViewportRule;
 
 
@@ -38,29 +51,210 @@ pub enum Origin {
 }
 
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+#[derive(Debug, HeapSizeOf)]
 pub struct Stylesheet<Impl: SelectorImpl> {
     /// List of rules in the order they were found (important for
-    /// cascading order)
-    pub rules: Vec<CSSRule<Impl>>,
-    /// List of media associated with the Stylesheet, if any.
-    pub media: Option<MediaQueryList>,
+    /// cascading order). Locked for writing while the CSSOM changes them
+    /// through `with_rules_mut`, and for reading by whoever walks them.
+    ///
+    /// Clones of the sheet share the list until one of them changes it.
+    #[ignore_heap_size_of = "Arc"]
+    pub rules: RwLock<Arc<Vec<CSSRule<Impl>>>>,
+    /// List of media associated with the Stylesheet, if any. Behind a lock since the
+    /// embedder changes it on a sheet that restyles may be reading.
+    #[ignore_heap_size_of = "Behind a lock"]
+    pub media: RwLock<Option<MediaQueryList>>,
     pub origin: Origin,
-    pub dirty_on_viewport_size_change: bool,
+    /// Whether the rules use viewport units, so that the sheet's styles have to be
+    /// recomputed when the viewport size changes. Atomic since reparsing the
+    /// sheet changes it.
+    #[ignore_heap_size_of = "Just a flag"]
+    pub dirty_on_viewport_size_change: AtomicBool,
+    /// Whether the style-sheet is disabled, in which case none of its rules
+    /// apply. Atomic since sheets are shared across threads.
+    #[ignore_heap_size_of = "Just a flag"]
+    pub disabled: AtomicBool,
+    /// Bumped every time the CSSOM changes `rules`, so that whoever holds the
+    /// sheet can tell its rules need to be collected again.
+    #[ignore_heap_size_of = "Just a counter"]
+    pub rules_generation: AtomicUsize,
+    /// Whether the sheet was frozen so that several documents can share it, after
+    /// which the CSSOM can't change it anymore.
+    #[ignore_heap_size_of = "Just a flag"]
+    pub frozen: AtomicBool,
+    /// The title the author gave the sheet, or an empty string. Sheets with the same
+    /// title make up an alternate style set.
+    #[ignore_heap_size_of = "Behind a lock"]
+    pub title: RwLock<String>,
+    /// What the `url()` values parsed for the sheet carry, so that the embedder loads them
+    /// on its behalf.
+    #[ignore_heap_size_of = "Owned by the embedder"]
+    pub url_extra_data: RwLock<UrlExtraData>,
+    /// The URL relative `url()` values in the sheet resolve against, those of rules parsed
+    /// for it later included.
+    pub base_url: Url,
+    /// The compatibility mode the sheet was parsed in, which rules parsed for it later are
+    /// parsed in too.
+    pub quirks_mode: QuirksMode,
 }
 
+impl<Impl: SelectorImpl> PartialEq for Stylesheet<Impl> {
+    fn eq(&self, other: &Stylesheet<Impl>) -> bool {
+        // The rules aren't compared with both locks held, since the sheets may be the same.
+        let rules = self.rules.read().unwrap().clone();
+        let other_rules = other.rules.read().unwrap().clone();
+        rules == other_rules &&
+        self.media() == other.media() &&
+        self.origin == other.origin &&
+        self.dirty_on_viewport_size_change() == other.dirty_on_viewport_size_change() &&
+        self.disabled() == other.disabled() &&
+        self.title() == other.title()
+    }
+}
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+impl<Impl: SelectorImpl> Clone for Stylesheet<Impl> {
+    /// Returns a sheet with the same rules, media list, disabled flag, title, url extra
+    /// data, base URL and quirks mode, which shares the rules with this one until either of them changes its own. The
+    /// sheets `@import` rules refer to are still shared. The copy isn't frozen,
+    /// even if this sheet is.
+    fn clone(&self) -> Stylesheet<Impl> {
+        Stylesheet {
+            rules: RwLock::new(self.rules.read().unwrap().clone()),
+            media: RwLock::new(self.media()),
+            origin: self.origin,
+            dirty_on_viewport_size_change: AtomicBool::new(self.dirty_on_viewport_size_change()),
+            disabled: AtomicBool::new(self.disabled()),
+            rules_generation: AtomicUsize::new(0),
+            frozen: AtomicBool::new(false),
+            title: RwLock::new(self.title()),
+            url_extra_data: RwLock::new(self.url_extra_data()),
+            base_url: self.base_url.clone(),
+            quirks_mode: self.quirks_mode,
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub enum CSSRule<Impl: SelectorImpl> {
     Charset(String),
+    Import(#[ignore_heap_size_of = "Arc"] Arc<ImportRule<Impl>>),
     Namespace(Option<String>, Namespace),
     Style(StyleRule<Impl>),
     Media(MediaRule<Impl>),
+    Supports(SupportsRule<Impl>),
     FontFace(FontFaceRule),
     Viewport(ViewportRule),
+    Keyframes(KeyframesRule),
+    Page(PageRule),
+    CounterStyle(#[ignore_heap_size_of = "Arc"] Arc<CounterStyleRule>),
+    Unsupported(UnsupportedRule),
+}
+
+/// An at-rule Servo doesn't implement, like one another engine adds. It applies nothing, but
+/// it's kept as it was written, so that the CSSOM serializes the sheet back to the same text.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub struct UnsupportedRule {
+    /// The name of the rule, lowercased and without the `@`.
+    pub name: String,
+    /// The whole rule, from the `@` to its `;` or closing brace.
+    pub text: String,
+}
+
+impl UnsupportedRule {
+    /// The rule named `name`, with the text between its name and its block or end, `prelude`,
+    /// and the contents of its block if it has one.
+    fn new(name: &str, prelude: &str, block: Option<&str>) -> UnsupportedRule {
+        let text = match block {
+            Some(block) => format!("@{}{}{{{}}}", name, prelude, block),
+            None => format!("@{}{};", name, prelude),
+        };
+        UnsupportedRule {
+            name: name.to_ascii_lowercase(),
+            text: text,
+        }
+    }
+}
+
+impl ToCss for UnsupportedRule {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        dest.write_str(&self.text)
+    }
+}
+
+/// An `@import` rule. The imported sheet is loaded by a `StylesheetLoader`,
+/// possibly after the parent sheet finished parsing, and its rules cascade as if
+/// they appeared where the rule is.
+#[derive(Debug, HeapSizeOf)]
+pub struct ImportRule<Impl: SelectorImpl> {
+    pub url: Url,
+    pub media_queries: MediaQueryList,
+    /// The imported sheet, or None if it hasn't loaded (yet). Behind a lock since the
+    /// load completes while restyles may be reading the parent sheet.
+    #[ignore_heap_size_of = "Arc"]
+    pub stylesheet: RwLock<Option<Arc<Stylesheet<Impl>>>>,
+    /// Whether the sheet holding the rule was frozen, after which the imported sheet
+    /// can't be replaced.
+    #[ignore_heap_size_of = "Just a flag"]
+    pub frozen: AtomicBool,
 }
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+impl<Impl: SelectorImpl> PartialEq for ImportRule<Impl> {
+    fn eq(&self, other: &ImportRule<Impl>) -> bool {
+        self.url == other.url &&
+        self.media_queries == other.media_queries &&
+        self.stylesheet() == other.stylesheet()
+    }
+}
+
+impl<Impl: SelectorImpl> ImportRule<Impl> {
+    pub fn new(url: Url, media_queries: MediaQueryList, stylesheet: Option<Arc<Stylesheet<Impl>>>)
+               -> ImportRule<Impl> {
+        ImportRule {
+            url: url,
+            media_queries: media_queries,
+            stylesheet: RwLock::new(stylesheet),
+            frozen: AtomicBool::new(false),
+        }
+    }
+
+    #[inline]
+    pub fn evaluate(&self, device: &Device) -> bool {
+        self.media_queries.evaluate(device)
+    }
+
+    /// The imported sheet, or None if it hasn't loaded (yet).
+    pub fn stylesheet(&self) -> Option<Arc<Stylesheet<Impl>>> {
+        self.stylesheet.read().unwrap().clone()
+    }
+
+    /// Attaches the sheet the loader fetched for the rule. Fails if the sheet holding
+    /// the rule is frozen, since the documents sharing it may not have loaded the same
+    /// sheet.
+    pub fn set_stylesheet(&self, stylesheet: Arc<Stylesheet<Impl>>) -> Result<(), RulesMutateError> {
+        if self.frozen.load(Ordering::SeqCst) {
+            return Err(RulesMutateError::NoModificationAllowed)
+        }
+        *self.stylesheet.write().unwrap() = Some(stylesheet);
+        Ok(())
+    }
+}
+
+/// Something that can fetch the sheets `@import` rules refer to.
+pub trait StylesheetLoader<Impl: SelectorImpl> {
+    /// Called for each `@import` rule as it is parsed, along with the source
+    /// text of its media list. The rule's stylesheet is filled in once the
+    /// load completes.
+    fn request_stylesheet(&self, import: &Arc<ImportRule<Impl>>, media: &str);
+
+    /// What `url()` values in the sheet should carry so that the embedder can load them on
+    /// its behalf.
+    fn url_extra_data(&self) -> UrlExtraData {
+        UrlExtraData::none()
+    }
+}
+
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub struct MediaRule<Impl: SelectorImpl> {
     pub media_queries: MediaQueryList,
     pub rules: Vec<CSSRule<Impl>>,
@@ -73,10 +267,68 @@ impl<Impl: SelectorImpl> MediaRule<Impl> {
     }
 }
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+/// A `@supports` rule. Its condition is evaluated once, at parse time. The rules
+/// inside are kept even if it doesn't hold, so they can still be serialized.
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub struct SupportsRule<Impl: SelectorImpl> {
+    pub condition: SupportsCondition,
+    /// Whether the condition holds.
+    pub enabled: bool,
+    pub rules: Vec<CSSRule<Impl>>,
+}
+
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub struct StyleRule<Impl: SelectorImpl> {
     pub selectors: Vec<Selector<Impl>>,
     pub declarations: PropertyDeclarationBlock,
+    pub source_location: SourceLocation,
+}
+
+/// Where a rule starts in the source of its sheet: the start of its selectors, for
+/// a style rule. Lines and columns count from 1. Rules that weren't parsed as part
+/// of a sheet, like the ones added by `insertRule`, are at line 0, column 0.
+#[derive(Clone, Copy, Debug, Default, HeapSizeOf, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl SourceLocation {
+    fn current(input: &Parser) -> SourceLocation {
+        let location = input.source_location(input.position());
+        SourceLocation {
+            line: location.line as u32,
+            column: location.column as u32,
+        }
+    }
+}
+
+/// Resets the source location of `rule`, and of the rules nested in it.
+fn clear_source_locations<Impl: SelectorImpl>(rule: &mut CSSRule<Impl>) {
+    match *rule {
+        CSSRule::Style(ref mut rule) => rule.source_location = SourceLocation::default(),
+        CSSRule::Media(ref mut media) => for rule in &mut media.rules {
+            clear_source_locations(rule)
+        },
+        CSSRule::Supports(ref mut supports) => for rule in &mut supports.rules {
+            clear_source_locations(rule)
+        },
+        _ => {}
+    }
+}
+
+impl<Impl: SelectorImplExt> ToCss for StyleRule<Impl> {
+    // https://drafts.csswg.org/cssom/#serialize-a-css-rule
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        try!(serialize_selector_list(&self.selectors, dest));
+        try!(dest.write_str(" { "));
+        let declarations = self.declarations.to_css_string();
+        if !declarations.is_empty() {
+            try!(dest.write_str(&declarations));
+            try!(dest.write_str(" "));
+        }
+        dest.write_str("}")
+    }
 }
 
 
@@ -108,9 +360,30 @@ impl<Impl: SelectorImpl> Stylesheet<Impl> {
 
     pub fn from_str(css: &str, base_url: Url, origin: Origin,
                     error_reporter: Box<ParseErrorReporter + Send>) -> Stylesheet<Impl> {
+        Stylesheet::from_str_with_loader(css, base_url, origin, error_reporter, None, QuirksMode::NoQuirks)
+    }
+
+    /// Like `from_str`, but hands `@import` rules to `loader` so that it can
+    /// fetch the sheets they refer to, and parses the sheet in the compatibility
+    /// mode of the document it's for. Without a loader, imports never apply.
+    pub fn from_str_with_loader(css: &str, base_url: Url, origin: Origin,
+                                error_reporter: Box<ParseErrorReporter + Send>,
+                                loader: Option<&StylesheetLoader<Impl>>,
+                                quirks_mode: QuirksMode) -> Stylesheet<Impl> {
+        let url_extra_data = loader.map_or_else(UrlExtraData::none, |loader| loader.url_extra_data());
+        Stylesheet::parse(css, base_url, origin, error_reporter, loader, url_extra_data, quirks_mode)
+    }
+
+    fn parse(css: &str, base_url: Url, origin: Origin, error_reporter: Box<ParseErrorReporter + Send>,
+             loader: Option<&StylesheetLoader<Impl>>, url_extra_data: UrlExtraData,
+             quirks_mode: QuirksMode) -> Stylesheet<Impl> {
+        let mut context = ParserContext::new(origin, &base_url, error_reporter);
+        context.quirks_mode = quirks_mode;
+        context.url_extra_data = url_extra_data.clone();
         let rule_parser = TopLevelRuleParser {
-            context: ParserContext::new(origin, &base_url, error_reporter.clone()),
+            context: context,
             state: Cell::new(State::Start),
+            loader: loader,
             _impl: PhantomData,
         };
         let mut input = Parser::new(css);
@@ -134,25 +407,191 @@ impl<Impl: SelectorImpl> Stylesheet<Impl> {
                         rules.push(rule);
                     }
                     Err(range) => {
-                        let pos = range.start;
-                        let message = format!("Invalid rule: '{}'", iter.input.slice(range));
-                        let context = ParserContext::new(origin, &base_url, error_reporter.clone());
-                        log_css_error(iter.input, pos, &*message, &context);
+                        let message = format!("Invalid rule: '{}'", iter.input.slice(range.clone()));
+                        log_invalid_rule::<Impl>(iter.input, range, &*message, &iter.parser.context);
                     }
                 }
             }
         }
         Stylesheet {
             origin: origin,
-            rules: rules,
-            media: None,
-            dirty_on_viewport_size_change: input.seen_viewport_percentages(),
+            rules: RwLock::new(Arc::new(rules)),
+            media: RwLock::new(None),
+            dirty_on_viewport_size_change: AtomicBool::new(input.seen_viewport_percentages()),
+            disabled: AtomicBool::new(false),
+            rules_generation: AtomicUsize::new(0),
+            frozen: AtomicBool::new(false),
+            title: RwLock::new(String::new()),
+            url_extra_data: RwLock::new(url_extra_data),
+            base_url: base_url.clone(),
+            quirks_mode: quirks_mode,
         }
     }
 
+    /// The MediaQueryList associated with the style-sheet, if any.
+    pub fn media(&self) -> Option<MediaQueryList> {
+        self.media.read().unwrap().clone()
+    }
+
     /// Set the MediaQueryList associated with the style-sheet.
-    pub fn set_media(&mut self, media: Option<MediaQueryList>) {
-        self.media = media;
+    pub fn set_media(&self, media: Option<MediaQueryList>) {
+        *self.media.write().unwrap() = media;
+    }
+
+    /// Whether the styles of the sheet have to be recomputed when the viewport size
+    /// changes.
+    pub fn dirty_on_viewport_size_change(&self) -> bool {
+        self.dirty_on_viewport_size_change.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the style-sheet is disabled.
+    pub fn disabled(&self) -> bool {
+        self.disabled.load(Ordering::SeqCst)
+    }
+
+    /// Enables or disables the style-sheet. Whoever holds it in a stylist is
+    /// responsible for noticing the change and rebuilding.
+    pub fn set_disabled(&self, disabled: bool) {
+        self.disabled.store(disabled, Ordering::SeqCst)
+    }
+
+    /// The title of the sheet, or an empty string if it has none.
+    pub fn title(&self) -> String {
+        self.title.read().unwrap().clone()
+    }
+
+    /// Gives the sheet a title. Fails if the sheet is frozen, since the documents
+    /// sharing it may not agree on one.
+    pub fn set_title(&self, title: &str) -> Result<(), RulesMutateError> {
+        if self.is_frozen() {
+            return Err(RulesMutateError::NoModificationAllowed)
+        }
+        *self.title.write().unwrap() = title.to_owned();
+        Ok(())
+    }
+
+    /// What `url()` values parsed for the sheet carry: the ones its loader gave it, unless
+    /// `set_url_extra_data` changed them since.
+    pub fn url_extra_data(&self) -> UrlExtraData {
+        self.url_extra_data.read().unwrap().clone()
+    }
+
+    /// Changes what the `url()` values parsed for the sheet from now on carry, as when
+    /// the embedder reuses it for a document with a different principal. The values
+    /// already parsed keep what they had.
+    pub fn set_url_extra_data(&self, url_extra_data: UrlExtraData) {
+        *self.url_extra_data.write().unwrap() = url_extra_data;
+    }
+
+    /// A description of the sheet for logs: its origin, whether it's disabled or
+    /// frozen, its title and its media list, as in
+    /// `author, enabled, title "Dark", media "screen and (min-width: 400px)"`. A sheet
+    /// without a media list has an empty one.
+    pub fn debug_info(&self) -> String {
+        let mut info = String::new();
+        info.push_str(match self.origin {
+            Origin::UserAgent => "user-agent",
+            Origin::User => "user",
+            Origin::Author => "author",
+        });
+        info.push_str(if self.disabled() { ", disabled" } else { ", enabled" });
+        if self.is_frozen() {
+            info.push_str(", frozen");
+        }
+        info.push_str(", title ");
+        serialize_string(&self.title(), &mut info).unwrap();
+        info.push_str(", media ");
+        let media = self.media().map_or(String::new(), |media| media.to_css_string());
+        serialize_string(&media, &mut info).unwrap();
+        info
+    }
+
+    /// Locks the rules for reading. Iterating with `rules` or `effective_rules`
+    /// holds the lock already.
+    pub fn read_rules(&self) -> RulesReadGuard<Impl> {
+        RulesReadGuard { guard: self.rules.read().unwrap() }
+    }
+
+    /// Calls `f` to change the rules of a sheet that may be shared, with the
+    /// rules lock held for writing. If clones of the sheet still share its
+    /// rules, the sheet gets a copy of its own for `f` to change first, so
+    /// the clones are left alone.
+    ///
+    /// Taking the lock again from `f`, including by iterating over the rules,
+    /// deadlocks.
+    pub fn with_rules_mut<F, R>(&self, f: F) -> R
+                                      where F: FnOnce(&mut Vec<CSSRule<Impl>>) -> R, Impl: Clone {
+        self.with_shared_rules_mut(|rules| f(Arc::make_mut(rules)))
+    }
+
+    /// Like `with_rules_mut`, for changes the CSSOM asks for: fails without calling
+    /// `f` if the sheet is frozen.
+    pub fn try_with_rules_mut<F, R>(&self, f: F) -> Result<R, RulesMutateError>
+                                          where F: FnOnce(&mut Vec<CSSRule<Impl>>) -> R, Impl: Clone {
+        if self.is_frozen() {
+            return Err(RulesMutateError::NoModificationAllowed)
+        }
+        Ok(self.with_rules_mut(f))
+    }
+
+    /// Freezes the sheet, so that documents can share it without seeing each
+    /// other's changes to it. Once it's frozen, it's up to whoever holds it not to
+    /// reparse it, disable it, or change its rules other than through
+    /// `try_with_rules_mut`, which refuses to. Its `@import` rules, and those of the
+    /// sheets they import, refuse to change the sheet they import too.
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::SeqCst);
+        for import in self.rules().import() {
+            import.frozen.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Whether the sheet was frozen with `freeze`.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::SeqCst)
+    }
+
+    /// Like `with_rules_mut`, but hands `f` the shared list itself, for
+    /// changes that replace it rather than edit it.
+    fn with_shared_rules_mut<F, R>(&self, f: F) -> R where F: FnOnce(&mut Arc<Vec<CSSRule<Impl>>>) -> R {
+        let mut rules = self.rules.write().unwrap();
+        self.rules_generation.fetch_add(1, Ordering::SeqCst);
+        f(&mut *rules)
+    }
+
+    /// How many times the rules of this sheet, or of any sheet it imports, have
+    /// been changed by the CSSOM.
+    pub fn rules_generation(&self) -> usize {
+        let own = self.rules_generation.load(Ordering::SeqCst);
+        self.rules().import().filter_map(|import| import.stylesheet())
+                    .fold(own, |generation, imported| {
+                        generation.wrapping_add(imported.rules_generation.load(Ordering::SeqCst))
+                    })
+    }
+
+    /// Replaces the rules of a sheet that may be shared with the ones parsed from
+    /// `css`, as when the text of a `<style>` element changes. The sheet keeps its
+    /// media list, disabled flag, base URL and quirks mode, and whoever takes the rules
+    /// lock sees either all of the old rules or all of the new ones. The new rules' `url()`
+    /// values carry the loader's extra data, or the sheet's own without a loader.
+    ///
+    /// Returns a sheet that nothing else holds with the rules this one had.
+    ///
+    /// Like `with_rules_mut`, this deadlocks if the caller holds the rules lock.
+    pub fn reparse(&self, css: &str, error_reporter: Box<ParseErrorReporter + Send>,
+                   loader: Option<&StylesheetLoader<Impl>>) -> Stylesheet<Impl> {
+        let url_extra_data = loader.map_or_else(|| self.url_extra_data(), |loader| loader.url_extra_data());
+        let mut old = Stylesheet::parse(css, self.base_url.clone(), self.origin, error_reporter, loader,
+                                        url_extra_data, self.quirks_mode);
+        self.with_shared_rules_mut(|rules| {
+            mem::swap(rules, old.rules.get_mut().unwrap());
+            mem::swap(&mut *self.url_extra_data.write().unwrap(), old.url_extra_data.get_mut().unwrap());
+            let dirty_on_viewport_size_change = old.dirty_on_viewport_size_change();
+            old.dirty_on_viewport_size_change.store(self.dirty_on_viewport_size_change(), Ordering::SeqCst);
+            self.dirty_on_viewport_size_change.store(dirty_on_viewport_size_change, Ordering::SeqCst);
+        });
+        *old.media.get_mut().unwrap() = self.media();
+        old
     }
 
     /// Returns whether the style-sheet applies for the current device depending
@@ -160,13 +599,33 @@ impl<Impl: SelectorImpl> Stylesheet<Impl> {
     ///
     /// Always true if no associated MediaQueryList exists.
     pub fn is_effective_for_device(&self, device: &Device) -> bool {
-        self.media.as_ref().map_or(true, |ref media| media.evaluate(device))
+        self.media.read().unwrap().as_ref().map_or(true, |media| media.evaluate(device))
+    }
+
+    /// Whether which of the rules of this sheet apply can depend on the device, because
+    /// of its media list or of `@media` or `@import` rules in it or in the sheets it
+    /// imports.
+    pub fn depends_on_device(&self) -> bool {
+        self.media.read().unwrap().is_some() || self.rules().any(|rule| match *rule {
+            CSSRule::Media(_) | CSSRule::Import(_) => true,
+            _ => false,
+        })
+    }
+
+    /// Returns whether `rule` is one of the `@import` rules of this style-sheet
+    /// or of the ones it imports, however deep. Attaching a sheet to an import
+    /// rule it contains would make it import itself.
+    pub fn contains_import_rule(&self, rule: &Arc<ImportRule<Impl>>) -> bool {
+        self.rules().any(|r| match *r {
+            CSSRule::Import(ref import) => arc_ptr_eq(import, rule),
+            _ => false,
+        })
     }
 
     /// Return an iterator over all the rules within the style-sheet.
     #[inline]
     pub fn rules(&self) -> Rules<Impl> {
-        Rules::new(self.rules.iter(), None)
+        Rules::new(self, None)
     }
 
     /// Return an iterator over the effective rules within the style-sheet, as
@@ -177,24 +636,81 @@ impl<Impl: SelectorImpl> Stylesheet<Impl> {
     /// examined.
     #[inline]
     pub fn effective_rules<'a>(&'a self, device: &'a Device) -> Rules<'a, Impl> {
-        Rules::new(self.rules.iter(), Some(device))
+        Rules::new(self, Some(device))
+    }
+}
+
+/// Decodes bytes that are meant to be UTF-8 but may not be, replacing each invalid
+/// sequence, truncated or overlong ones included, with U+FFFD. Valid input isn't
+/// copied. The flag says whether anything had to be replaced.
+pub fn decode_utf8_lossy(bytes: &[u8]) -> (Cow<str>, bool) {
+    let css = String::from_utf8_lossy(bytes);
+    let was_lossy = match css {
+        Cow::Borrowed(_) => false,
+        Cow::Owned(_) => true,
+    };
+    (css, was_lossy)
+}
+
+/// The rules of a stylesheet, locked for reading.
+pub struct RulesReadGuard<'a, Impl: SelectorImpl + 'a> {
+    guard: RwLockReadGuard<'a, Arc<Vec<CSSRule<Impl>>>>,
+}
+
+impl<'a, Impl: SelectorImpl + 'a> Deref for RulesReadGuard<'a, Impl> {
+    type Target = Vec<CSSRule<Impl>>;
+
+    fn deref(&self) -> &Vec<CSSRule<Impl>> {
+        &**self.guard
     }
 }
 
 /// `CSSRule` iterator.
 ///
 /// The iteration order is pre-order. Specifically, this implies that a
-/// conditional group rule will come before its nested rules.
+/// conditional group rule will come before its nested rules, and an `@import`
+/// rule before the rules of the sheet it imports.
+///
+/// The rules of each sheet are locked for reading while they are iterated over.
 pub struct Rules<'a, Impl: SelectorImpl + 'a> {
     // 2 because normal case is likely to be just one level of nesting (@media)
-    stack: SmallVec<[slice::Iter<'a, CSSRule<Impl>>; 2]>,
+    stack: SmallVec<[RuleList<'a, Impl>; 2]>,
     device: Option<&'a Device>
 }
 
+/// A list of rules `Rules` is going through, and what keeps it from changing meanwhile.
+struct RuleList<'a, Impl: SelectorImpl + 'a> {
+    iter: slice::Iter<'a, CSSRule<Impl>>,
+    /// The rules lock, for the rules of a sheet rather than the ones nested in a rule.
+    _guard: Option<RwLockReadGuard<'a, Arc<Vec<CSSRule<Impl>>>>>,
+    /// The sheet an `@import` rule imports, which the rule may let go of meanwhile.
+    /// Declared after the guard so that it outlives it.
+    _imported: Option<Arc<Stylesheet<Impl>>>,
+}
+
+impl<'a, Impl: SelectorImpl + 'a> RuleList<'a, Impl> {
+    #[allow(unsafe_code)]
+    fn of_sheet(stylesheet: &'a Stylesheet<Impl>, imported: Option<Arc<Stylesheet<Impl>>>) -> RuleList<'a, Impl> {
+        let guard = stylesheet.rules.read().unwrap();
+        // The rules are behind the Arc, which the guard keeps from being replaced, so
+        // they stay where they are when the guard moves into the list.
+        let rules = unsafe { &*(&**guard as *const Vec<CSSRule<Impl>>) };
+        RuleList {
+            iter: rules.iter(),
+            _guard: Some(guard),
+            _imported: imported,
+        }
+    }
+
+    fn nested(rules: &'a [CSSRule<Impl>]) -> RuleList<'a, Impl> {
+        RuleList { iter: rules.iter(), _guard: None, _imported: None }
+    }
+}
+
 impl<'a, Impl: SelectorImpl + 'a> Rules<'a, Impl> {
-    fn new(iter: slice::Iter<'a, CSSRule<Impl>>, device: Option<&'a Device>) -> Rules<'a, Impl> {
-        let mut stack: SmallVec<[slice::Iter<'a, CSSRule<Impl>>; 2]> = SmallVec::new();
-        stack.push(iter);
+    fn new(stylesheet: &'a Stylesheet<Impl>, device: Option<&'a Device>) -> Rules<'a, Impl> {
+        let mut stack = SmallVec::new();
+        stack.push(RuleList::of_sheet(stylesheet, None));
 
         Rules { stack: stack, device: device }
     }
@@ -203,20 +719,40 @@ impl<'a, Impl: SelectorImpl + 'a> Rules<'a, Impl> {
 impl<'a, Impl: SelectorImpl + 'a> Iterator for Rules<'a, Impl> {
     type Item = &'a CSSRule<Impl>;
 
+    #[allow(unsafe_code)]
     fn next(&mut self) -> Option<&'a CSSRule<Impl>> {
         while !self.stack.is_empty() {
             let top = self.stack.len() - 1;
-            while let Some(rule) = self.stack[top].next() {
+            while let Some(rule) = self.stack[top].iter.next() {
                 // handle conditional group rules
                 if let &CSSRule::Media(ref rule) = rule {
                     if let Some(device) = self.device {
                         if rule.evaluate(device) {
-                            self.stack.push(rule.rules.iter());
+                            self.stack.push(RuleList::nested(&rule.rules));
                         } else {
                             continue
                         }
                     } else {
-                        self.stack.push(rule.rules.iter());
+                        self.stack.push(RuleList::nested(&rule.rules));
+                    }
+                }
+
+                if let &CSSRule::Supports(ref rule) = rule {
+                    if self.device.is_none() || rule.enabled {
+                        self.stack.push(RuleList::nested(&rule.rules));
+                    } else {
+                        continue
+                    }
+                }
+
+                if let &CSSRule::Import(ref import) = rule {
+                    if let Some(imported) = import.stylesheet() {
+                        if self.device.map_or(true, |device| import.evaluate(device)) {
+                            // The list keeps the imported sheet alive for as long as it
+                            // borrows from it.
+                            let stylesheet = unsafe { &*(&*imported as *const Stylesheet<Impl>) };
+                            self.stack.push(RuleList::of_sheet(stylesheet, Some(imported)));
+                        }
                     }
                 }
 
@@ -241,8 +777,12 @@ pub mod rule_filter {
     use selectors::parser::SelectorImpl;
     use std::marker::PhantomData;
     use super::super::font_face::FontFaceRule;
+    use super::super::counter_style::CounterStyleRule;
+    use super::super::keyframes::KeyframesRule;
+    use super::super::page::PageRule;
     use super::super::viewport::ViewportRule;
-    use super::{CSSRule, MediaRule, StyleRule};
+    use std::sync::Arc;
+    use super::{CSSRule, ImportRule, MediaRule, StyleRule, SupportsRule, UnsupportedRule};
 
     macro_rules! rule_filter {
         ($variant:ident -> $value:ty) => {
@@ -285,10 +825,16 @@ pub mod rule_filter {
         }
     }
 
+    rule_filter!(Import -> Arc<ImportRule<Impl>>);
     rule_filter!(Media -> MediaRule<Impl>);
+    rule_filter!(Supports -> SupportsRule<Impl>);
     rule_filter!(Style -> StyleRule<Impl>);
     rule_filter!(FontFace -> FontFaceRule);
     rule_filter!(Viewport -> ViewportRule);
+    rule_filter!(Keyframes -> KeyframesRule);
+    rule_filter!(Page -> PageRule);
+    rule_filter!(CounterStyle -> Arc<CounterStyleRule>);
+    rule_filter!(Unsupported -> UnsupportedRule);
 }
 
 /// Extension methods for `CSSRule` iterators.
@@ -296,14 +842,32 @@ pub trait CSSRuleIteratorExt<'a, Impl: SelectorImpl + 'a>: Iterator<Item=&'a CSS
     /// Yield only @font-face rules.
     fn font_face(self) -> rule_filter::FontFace<'a, Self>;
 
+    /// Yield only @import rules.
+    fn import(self) -> rule_filter::Import<'a, Self>;
+
     /// Yield only @media rules.
     fn media(self) -> rule_filter::Media<'a, Self>;
 
+    /// Yield only @supports rules.
+    fn supports(self) -> rule_filter::Supports<'a, Self>;
+
     /// Yield only style rules.
     fn style(self) -> rule_filter::Style<'a, Self>;
 
     /// Yield only @viewport rules.
     fn viewport(self) -> rule_filter::Viewport<'a, Self>;
+
+    /// Yield only @keyframes rules.
+    fn keyframes(self) -> rule_filter::Keyframes<'a, Self>;
+
+    /// Yield only @page rules.
+    fn page(self) -> rule_filter::Page<'a, Self>;
+
+    /// Yield only @counter-style rules.
+    fn counter_style(self) -> rule_filter::CounterStyle<'a, Self>;
+
+    /// Yield only unsupported rules.
+    fn unsupported(self) -> rule_filter::Unsupported<'a, Self>;
 }
 
 impl<'a, I, Impl: SelectorImpl + 'a> CSSRuleIteratorExt<'a, Impl> for I where I: Iterator<Item=&'a CSSRule<Impl>> {
@@ -312,11 +876,21 @@ impl<'a, I, Impl: SelectorImpl + 'a> CSSRuleIteratorExt<'a, Impl> for I where I:
         rule_filter::FontFace::new(self)
     }
 
+    #[inline]
+    fn import(self) -> rule_filter::Import<'a, I> {
+        rule_filter::Import::new(self)
+    }
+
     #[inline]
     fn media(self) -> rule_filter::Media<'a, I> {
         rule_filter::Media::new(self)
     }
 
+    #[inline]
+    fn supports(self) -> rule_filter::Supports<'a, I> {
+        rule_filter::Supports::new(self)
+    }
+
     #[inline]
     fn style(self) -> rule_filter::Style<'a, I> {
         rule_filter::Style::new(self)
@@ -326,6 +900,167 @@ impl<'a, I, Impl: SelectorImpl + 'a> CSSRuleIteratorExt<'a, Impl> for I where I:
     fn viewport(self) -> rule_filter::Viewport<'a, I> {
         rule_filter::Viewport::new(self)
     }
+
+    #[inline]
+    fn keyframes(self) -> rule_filter::Keyframes<'a, I> {
+        rule_filter::Keyframes::new(self)
+    }
+
+    #[inline]
+    fn page(self) -> rule_filter::Page<'a, I> {
+        rule_filter::Page::new(self)
+    }
+
+    #[inline]
+    fn counter_style(self) -> rule_filter::CounterStyle<'a, I> {
+        rule_filter::CounterStyle::new(self)
+    }
+
+    #[inline]
+    fn unsupported(self) -> rule_filter::Unsupported<'a, I> {
+        rule_filter::Unsupported::new(self)
+    }
+}
+
+/// Why the CSSOM refused to insert or delete a rule, which decides the exception
+/// it throws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RulesMutateError {
+    /// The text isn't exactly one valid rule.
+    Syntax,
+    /// The index is past the end of the list.
+    IndexSize,
+    /// The rule isn't allowed at that position, like an `@import` after a style rule.
+    HierarchyRequest,
+    /// An `@namespace` rule can't be added or removed once there are other rules.
+    InvalidState,
+    /// The sheet is frozen, since other documents share it.
+    NoModificationAllowed,
+}
+
+/// The `@charset` rule isn't part of the CSSOM, so CSSOM indices start after it.
+fn cssom_offset<Impl: SelectorImpl>(rules: &[CSSRule<Impl>]) -> usize {
+    match rules.first() {
+        Some(&CSSRule::Charset(_)) => 1,
+        _ => 0,
+    }
+}
+
+/// Unsupported rules don't count: they're invalid as far as the order of rules goes.
+fn only_imports_and_namespaces<Impl: SelectorImpl>(rules: &[CSSRule<Impl>]) -> bool {
+    rules.iter().all(|rule| match *rule {
+        CSSRule::Import(_) | CSSRule::Namespace(..) | CSSRule::Unsupported(_) => true,
+        _ => false,
+    })
+}
+
+/// Makes the prefixes declared by the `@namespace` rules in `rules` available
+/// to selectors parsed with `context`.
+pub fn add_namespace_prefixes<Impl: SelectorImpl>(context: &mut ParserContext, rules: &[CSSRule<Impl>]) {
+    for rule in rules {
+        if let CSSRule::Namespace(ref prefix, ref namespace) = *rule {
+            match *prefix {
+                Some(ref prefix) => {
+                    context.selector_context.namespace_prefixes.insert(prefix.clone(), namespace.clone());
+                }
+                None => context.selector_context.default_namespace = Some(namespace.clone()),
+            }
+        }
+    }
+}
+
+/// Parses `css` as a rule and inserts it in `rules` before the rule at CSSOM
+/// index `index`, as `insertRule` does. `nested` says whether `rules` is the
+/// list of a grouping rule like `@media`, rather than that of a sheet. An
+/// `@import` rule is handed to `loader` once it's in.
+///
+/// https://drafts.csswg.org/cssom/#insert-a-css-rule
+pub fn insert_rule<Impl: SelectorImpl>(rules: &mut Vec<CSSRule<Impl>>, index: usize, nested: bool,
+                                       css: &str, context: ParserContext,
+                                       loader: Option<&StylesheetLoader<Impl>>) -> Result<(), RulesMutateError> {
+    let offset = cssom_offset(rules);
+    if index > rules.len() - offset {
+        return Err(RulesMutateError::IndexSize)
+    }
+
+    let rule = {
+        let parser = TopLevelRuleParser {
+            context: context,
+            state: Cell::new(State::Start),
+            loader: None,
+            _impl: PhantomData,
+        };
+        let mut input = Parser::new(css);
+        let mut iter = RuleListParser::new_for_stylesheet(&mut input, parser);
+        let mut rule = match iter.next() {
+            Some(Ok(rule)) => rule,
+            _ => return Err(RulesMutateError::Syntax),
+        };
+        // The locations are relative to `css`, not to the sheet.
+        clear_source_locations(&mut rule);
+        if iter.next().is_some() {
+            return Err(RulesMutateError::Syntax)
+        }
+        rule
+    };
+
+    // @import rules have to come first, then @namespace rules, then everything else. The
+    // unsupported rules a sheet was parsed with can be anywhere, but insertRule only takes
+    // rules it knows, as Gecko does.
+    let allowed = {
+        let (before, after) = rules[offset..].split_at(index);
+        match rule {
+            CSSRule::Charset(_) | CSSRule::Unsupported(_) => return Err(RulesMutateError::Syntax),
+            CSSRule::Import(_) => {
+                !nested && before.iter().all(|rule| match *rule {
+                    CSSRule::Import(_) | CSSRule::Unsupported(_) => true,
+                    _ => false,
+                })
+            }
+            CSSRule::Namespace(..) => {
+                !nested && only_imports_and_namespaces(before) && after.iter().all(|rule| match *rule {
+                    CSSRule::Import(_) => false,
+                    _ => true,
+                })
+            }
+            _ => after.iter().all(|rule| match *rule {
+                CSSRule::Import(_) | CSSRule::Namespace(..) => false,
+                _ => true,
+            }),
+        }
+    };
+    if !allowed {
+        return Err(RulesMutateError::HierarchyRequest)
+    }
+    if let CSSRule::Namespace(..) = rule {
+        if !only_imports_and_namespaces(&rules[offset..]) {
+            return Err(RulesMutateError::InvalidState)
+        }
+    }
+
+    if let (&CSSRule::Import(ref import), Some(loader)) = (&rule, loader) {
+        loader.request_stylesheet(import, &import.media_queries.to_css_string());
+    }
+    rules.insert(offset + index, rule);
+    Ok(())
+}
+
+/// Removes the rule at CSSOM index `index` from `rules`, as `deleteRule` does.
+///
+/// https://drafts.csswg.org/cssom/#remove-a-css-rule
+pub fn delete_rule<Impl: SelectorImpl>(rules: &mut Vec<CSSRule<Impl>>, index: usize)
+                                       -> Result<(), RulesMutateError> {
+    let offset = cssom_offset(rules);
+    if index >= rules.len() - offset {
+        return Err(RulesMutateError::IndexSize)
+    }
+    if let CSSRule::Namespace(..) = rules[offset + index] {
+        if !only_imports_and_namespaces(&rules[offset..]) {
+            return Err(RulesMutateError::InvalidState)
+        }
+    }
+    rules.remove(offset + index);
+    Ok(())
 }
 
 fn parse_nested_rules<Impl: SelectorImpl>(context: &ParserContext, input: &mut Parser) -> Vec<CSSRule<Impl>> {
@@ -339,19 +1074,42 @@ fn parse_nested_rules<Impl: SelectorImpl>(context: &ParserContext, input: &mut P
         match result {
             Ok(rule) => rules.push(rule),
             Err(range) => {
-                let pos = range.start;
-                let message = format!("Unsupported rule: '{}'", iter.input.slice(range));
-                log_css_error(iter.input, pos, &*message, &context);
+                let message = format!("Unsupported rule: '{}'", iter.input.slice(range.clone()));
+                log_invalid_rule::<Impl>(iter.input, range, &*message, &context);
             }
         }
     }
     rules
 }
 
+/// Reports the rule in `range`, which didn't parse. If it's a style rule whose selectors
+/// are why, the report says which selector, and where in it things go wrong.
+fn log_invalid_rule<Impl: SelectorImpl>(input: &mut Parser, range: Range<SourcePosition>, message: &str,
+                                        context: &ParserContext) {
+    let rule = input.slice(range.clone());
+    if !rule.starts_with('@') {
+        let prelude = Parser::new(rule).parse_until_before(Delimiter::CurlyBracketBlock, |input| {
+            let start = input.position();
+            while input.next().is_ok() {}
+            Ok(input.slice_from(start))
+        });
+        let invalid = prelude.ok().and_then(|prelude| {
+            find_invalid_selector::<Impl>(&context.selector_context, prelude, false)
+        });
+        if let Some(invalid) = invalid {
+            let message = format!("Invalid selector: '{}'", invalid.selector);
+            let details = ParseErrorDetails::InvalidSelector(invalid);
+            return log_css_error_with_details(input, range.start, &*message, &details, context)
+        }
+    }
+    log_css_error(input, range.start, message, context)
+}
+
 
-struct TopLevelRuleParser<'a, Impl: SelectorImpl> {
+struct TopLevelRuleParser<'a, Impl: SelectorImpl + 'a> {
     context: ParserContext<'a>,
     state: Cell<State>,
+    loader: Option<&'a StylesheetLoader<Impl>>,
     _impl: PhantomData<Impl>
 }
 
@@ -367,11 +1125,18 @@ enum State {
 enum AtRulePrelude {
     FontFace,
     Media(MediaQueryList),
+    Supports(SupportsCondition),
     Viewport,
+    /// The animation name, and whether the rule was vendor-prefixed.
+    Keyframes(Atom, bool),
+    Page(Vec<PageSelector>),
+    CounterStyle(Atom),
+    /// An at-rule Servo doesn't know: its name as written, and the text after the name.
+    Unsupported(String, String),
 }
 
 
-impl<'a, Impl: SelectorImpl> AtRuleParser for TopLevelRuleParser<'a, Impl> {
+impl<'a, Impl: SelectorImpl + 'a> AtRuleParser for TopLevelRuleParser<'a, Impl> {
     type Prelude = AtRulePrelude;
     type AtRule = CSSRule<Impl>;
 
@@ -391,8 +1156,13 @@ impl<'a, Impl: SelectorImpl> AtRuleParser for TopLevelRuleParser<'a, Impl> {
             "import" => {
                 if self.state.get() <= State::Imports {
                     self.state.set(State::Imports);
-                    // TODO: support @import
-                    return Err(())  // "@import is not supported yet"
+                    let url = self.context.parse_url(&*try!(input.expect_url_or_string()));
+                    let media_start = input.position();
+                    let import = Arc::new(ImportRule::new(url, parse_media_query_list(input), None));
+                    if let Some(loader) = self.loader {
+                        loader.request_stylesheet(&import, input.slice_from(media_start).trim());
+                    }
+                    return Ok(AtRuleType::WithoutBlock(CSSRule::Import(import)))
                 } else {
                     return Err(())  // "@import must be before any rule but @charset"
                 }
@@ -411,29 +1181,41 @@ impl<'a, Impl: SelectorImpl> AtRuleParser for TopLevelRuleParser<'a, Impl> {
             _ => {}
         }
 
-        self.state.set(State::Body);
-        AtRuleParser::parse_prelude(&NestedRuleParser { context: &self.context, _impl: PhantomData }, name, input)
+        let result = AtRuleParser::parse_prelude(&NestedRuleParser { context: &self.context, _impl: PhantomData },
+                                                 name, input);
+        match result {
+            // Unknown at-rules are invalid, so an @import after one still applies.
+            Ok(AtRuleType::OptionalBlock(AtRulePrelude::Unsupported(..))) => {}
+            _ => self.state.set(State::Body),
+        }
+        result
     }
 
     #[inline]
     fn parse_block(&self, prelude: AtRulePrelude, input: &mut Parser) -> Result<CSSRule<Impl>, ()> {
         AtRuleParser::parse_block(&NestedRuleParser { context: &self.context, _impl: PhantomData }, prelude, input)
     }
+
+    #[inline]
+    fn rule_without_block(&self, prelude: AtRulePrelude) -> CSSRule<Impl> {
+        AtRuleParser::rule_without_block(&NestedRuleParser { context: &self.context, _impl: PhantomData }, prelude)
+    }
 }
 
 
-impl<'a, Impl: SelectorImpl> QualifiedRuleParser for TopLevelRuleParser<'a, Impl> {
-    type Prelude = Vec<Selector<Impl>>;
+impl<'a, Impl: SelectorImpl + 'a> QualifiedRuleParser for TopLevelRuleParser<'a, Impl> {
+    type Prelude = (Vec<Selector<Impl>>, SourceLocation);
     type QualifiedRule = CSSRule<Impl>;
 
     #[inline]
-    fn parse_prelude(&self, input: &mut Parser) -> Result<Vec<Selector<Impl>>, ()> {
+    fn parse_prelude(&self, input: &mut Parser) -> Result<(Vec<Selector<Impl>>, SourceLocation), ()> {
         self.state.set(State::Body);
         QualifiedRuleParser::parse_prelude(&NestedRuleParser { context: &self.context, _impl: PhantomData }, input)
     }
 
     #[inline]
-    fn parse_block(&self, prelude: Vec<Selector<Impl>>, input: &mut Parser) -> Result<CSSRule<Impl>, ()> {
+    fn parse_block(&self, prelude: (Vec<Selector<Impl>>, SourceLocation), input: &mut Parser)
+                   -> Result<CSSRule<Impl>, ()> {
         QualifiedRuleParser::parse_block(&NestedRuleParser { context: &self.context, _impl: PhantomData },
                                          prelude, input)
     }
@@ -457,6 +1239,9 @@ impl<'a, 'b, Impl: SelectorImpl> AtRuleParser for NestedRuleParser<'a, 'b, Impl>
                 let media_queries = parse_media_query_list(input);
                 Ok(AtRuleType::WithBlock(AtRulePrelude::Media(media_queries)))
             },
+            "supports" => {
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Supports(try!(SupportsCondition::parse(input)))))
+            },
             "font-face" => {
                 Ok(AtRuleType::WithBlock(AtRulePrelude::FontFace))
             },
@@ -467,7 +1252,23 @@ impl<'a, 'b, Impl: SelectorImpl> AtRuleParser for NestedRuleParser<'a, 'b, Impl>
                     Err(())
                 }
             },
-            _ => Err(())
+            "keyframes" => {
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Keyframes(try!(parse_keyframes_name(input)), false)))
+            },
+            "-webkit-keyframes" => {
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Keyframes(try!(parse_keyframes_name(input)), true)))
+            },
+            "-moz-keyframes" => {
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Keyframes(try!(parse_keyframes_name(input)), true)))
+            },
+            "page" => {
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Page(try!(parse_page_selectors(input)))))
+            },
+            "counter-style" => {
+                let name = try!(parse_counter_style_name_definition(self.context, input));
+                Ok(AtRuleType::WithBlock(AtRulePrelude::CounterStyle(name)))
+            },
+            _ => parse_unsupported_prelude(name, input)
         }
     }
 
@@ -482,26 +1283,90 @@ impl<'a, 'b, Impl: SelectorImpl> AtRuleParser for NestedRuleParser<'a, 'b, Impl>
                     rules: parse_nested_rules(self.context, input),
                 }))
             }
+            AtRulePrelude::Supports(condition) => {
+                Ok(CSSRule::Supports(SupportsRule {
+                    enabled: condition.eval(self.context),
+                    condition: condition,
+                    rules: parse_nested_rules(self.context, input),
+                }))
+            }
             AtRulePrelude::Viewport => {
                 ViewportRule::parse(input, self.context).map(CSSRule::Viewport)
             }
+            AtRulePrelude::Keyframes(name, vendor_prefixed) => {
+                Ok(CSSRule::Keyframes(KeyframesRule {
+                    name: name,
+                    vendor_prefixed: vendor_prefixed,
+                    keyframes: parse_keyframe_list(self.context, input),
+                }))
+            }
+            AtRulePrelude::Page(selectors) => {
+                Ok(CSSRule::Page(parse_page_block(self.context, input, selectors)))
+            }
+            AtRulePrelude::CounterStyle(name) => {
+                parse_counter_style_block(self.context, input, name).map(|rule| CSSRule::CounterStyle(Arc::new(rule)))
+            }
+            AtRulePrelude::Unsupported(name, prelude) => {
+                let block = consume_remaining(input);
+                Ok(CSSRule::Unsupported(UnsupportedRule::new(&name, &prelude, Some(&block))))
+            }
         }
     }
+
+    fn rule_without_block(&self, prelude: AtRulePrelude) -> CSSRule<Impl> {
+        match prelude {
+            AtRulePrelude::Unsupported(name, prelude) => {
+                CSSRule::Unsupported(UnsupportedRule::new(&name, &prelude, None))
+            }
+            _ => unreachable!("Only unsupported rules may do without a block"),
+        }
+    }
+}
+
+/// The prelude of an at-rule named `name` that no parser knows, which is kept as an
+/// unsupported rule, with or without a block. @charset, @import and @namespace are only known
+/// at the start of a sheet: anywhere else they're invalid, and dropped.
+fn parse_unsupported_prelude<Impl: SelectorImpl>(name: &str, input: &mut Parser)
+                                                 -> Result<AtRuleType<AtRulePrelude, CSSRule<Impl>>, ()> {
+    if ["charset", "import", "namespace"].iter().any(|known| name.eq_ignore_ascii_case(known)) {
+        return Err(())
+    }
+    let prelude = consume_remaining(input);
+    Ok(AtRuleType::OptionalBlock(AtRulePrelude::Unsupported(name.to_owned(), prelude)))
+}
+
+/// Skips to the end of `input`, returning the text it went past.
+fn consume_remaining(input: &mut Parser) -> String {
+    let start = input.position();
+    while input.next().is_ok() {}
+    input.slice_from(start).to_owned()
+}
+
+fn parse_keyframes_name(input: &mut Parser) -> Result<Atom, ()> {
+    match try!(input.next()) {
+        Token::Ident(ref name) if !name.eq_ignore_ascii_case("none") => Ok(Atom::from(&**name)),
+        Token::QuotedString(ref name) => Ok(Atom::from(&**name)),
+        _ => Err(())
+    }
 }
 
 
 impl<'a, 'b, Impl: SelectorImpl> QualifiedRuleParser for NestedRuleParser<'a, 'b, Impl> {
-    type Prelude = Vec<Selector<Impl>>;
+    type Prelude = (Vec<Selector<Impl>>, SourceLocation);
     type QualifiedRule = CSSRule<Impl>;
 
-    fn parse_prelude(&self, input: &mut Parser) -> Result<Vec<Selector<Impl>>, ()> {
-        parse_selector_list(&self.context.selector_context, input)
+    fn parse_prelude(&self, input: &mut Parser) -> Result<(Vec<Selector<Impl>>, SourceLocation), ()> {
+        let location = SourceLocation::current(input);
+        parse_selector_list(&self.context.selector_context, input).map(|selectors| (selectors, location))
     }
 
-    fn parse_block(&self, prelude: Vec<Selector<Impl>>, input: &mut Parser) -> Result<CSSRule<Impl>, ()> {
+    fn parse_block(&self, prelude: (Vec<Selector<Impl>>, SourceLocation), input: &mut Parser)
+                   -> Result<CSSRule<Impl>, ()> {
+        let (selectors, location) = prelude;
         Ok(CSSRule::Style(StyleRule {
-            selectors: prelude,
-            declarations: parse_property_declaration_list(self.context, input)
+            selectors: selectors,
+            declarations: parse_property_declaration_list(self.context, input),
+            source_location: location,
         }))
     }
 }
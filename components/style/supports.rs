@@ -0,0 +1,172 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `@supports` conditions.
+//!
+//! https://drafts.csswg.org/css-conditional-3/#at-supports
+
+use cssparser::{Delimiter, Parser, ToCss, Token, parse_important};
+use parser::ParserContext;
+use properties::{PropertyDeclaration, PropertyDeclarationParseResult};
+use std::ascii::AsciiExt;
+use std::fmt;
+
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
+pub enum SupportsCondition {
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// A `(property: value)` test, kept as source text.
+    Declaration(String, String),
+    /// A `<general-enclosed>` production this engine doesn't understand, which is false
+    /// but doesn't make the condition around it invalid.
+    FutureSyntax(String),
+}
+
+impl SupportsCondition {
+    /// Parses a `<supports-condition>`, which must take up all of `input`.
+    pub fn parse(input: &mut Parser) -> Result<SupportsCondition, ()> {
+        input.parse_entirely(parse_condition)
+    }
+
+    /// Whether the condition holds, given which properties and values the engine parses.
+    pub fn eval(&self, context: &ParserContext) -> bool {
+        match *self {
+            SupportsCondition::Not(ref condition) => !condition.eval(context),
+            SupportsCondition::And(ref conditions) => conditions.iter().all(|c| c.eval(context)),
+            SupportsCondition::Or(ref conditions) => conditions.iter().any(|c| c.eval(context)),
+            SupportsCondition::Declaration(ref name, ref value) => supports_declaration(name, value, context),
+            SupportsCondition::FutureSyntax(_) => false,
+        }
+    }
+}
+
+fn parse_condition(input: &mut Parser) -> Result<SupportsCondition, ()> {
+    if input.try(|input| input.expect_ident_matching("not")).is_ok() {
+        return parse_condition_in_parens(input).map(|condition| SupportsCondition::Not(Box::new(condition)))
+    }
+
+    let mut conditions = vec![try!(parse_condition_in_parens(input))];
+    let is_and = match input.next() {
+        Err(()) => return Ok(conditions.pop().unwrap()),
+        Ok(Token::Ident(ref keyword)) if keyword.eq_ignore_ascii_case("and") => true,
+        Ok(Token::Ident(ref keyword)) if keyword.eq_ignore_ascii_case("or") => false,
+        Ok(_) => return Err(()),
+    };
+    // `and` and `or` can't be mixed without parentheses.
+    let keyword = if is_and { "and" } else { "or" };
+    loop {
+        conditions.push(try!(parse_condition_in_parens(input)));
+        if input.is_exhausted() {
+            break
+        }
+        try!(input.expect_ident_matching(keyword));
+    }
+    Ok(if is_and { SupportsCondition::And(conditions) } else { SupportsCondition::Or(conditions) })
+}
+
+/// Parses a `<supports-in-parens>`.
+fn parse_condition_in_parens(input: &mut Parser) -> Result<SupportsCondition, ()> {
+    let start = input.position();
+    let condition = match try!(input.next()) {
+        Token::ParenthesisBlock => {
+            try!(input.parse_nested_block(|input| {
+                if let Ok(condition) = input.try(|input| input.parse_entirely(parse_condition)) {
+                    return Ok(Some(condition))
+                }
+                if let Ok(declaration) = input.try(parse_declaration) {
+                    return Ok(Some(declaration))
+                }
+                consume_all(input);
+                Ok(None)
+            }))
+        }
+        Token::Function(_) => {
+            try!(input.parse_nested_block(|input| Ok(consume_all(input))));
+            None
+        }
+        _ => return Err(())
+    };
+    Ok(condition.unwrap_or_else(|| SupportsCondition::FutureSyntax(input.slice_from(start).to_owned())))
+}
+
+/// Parses the inside of a `(property: value)` test.
+fn parse_declaration(input: &mut Parser) -> Result<SupportsCondition, ()> {
+    let name = try!(input.expect_ident()).into_owned();
+    try!(input.expect_colon());
+    let start = input.position();
+    consume_all(input);
+    let value = input.slice_from(start).trim();
+    if value.is_empty() {
+        return Err(())
+    }
+    Ok(SupportsCondition::Declaration(name, value.to_owned()))
+}
+
+fn consume_all(input: &mut Parser) {
+    while let Ok(_) = input.next() {}
+}
+
+/// Whether `name: value` would be a valid declaration, as for the two-argument form of
+/// `CSS.supports()`.
+pub fn supports_declaration(name: &str, value: &str, context: &ParserContext) -> bool {
+    Parser::new(value).parse_entirely(|input| {
+        let mut results = vec![];
+        try!(input.parse_until_before(Delimiter::Bang, |input| {
+            match PropertyDeclaration::parse(name, context, input, &mut results) {
+                PropertyDeclarationParseResult::ValidOrIgnoredDeclaration if input.is_exhausted() => Ok(()),
+                _ => Err(())
+            }
+        }));
+        let _ = input.try(parse_important);
+        Ok(())
+    }).is_ok()
+}
+
+/// Whether the one-argument form of `CSS.supports()` holds for `condition`. A bare
+/// declaration is accepted as if it were wrapped in parentheses.
+/// https://drafts.csswg.org/css-conditional-3/#dom-css-supports
+pub fn supports_condition(condition: &str, context: &ParserContext) -> bool {
+    let parsed = SupportsCondition::parse(&mut Parser::new(condition)).or_else(|()| {
+        SupportsCondition::parse(&mut Parser::new(&format!("({})", condition)))
+    });
+    parsed.map_or(false, |condition| condition.eval(context))
+}
+
+impl ToCss for SupportsCondition {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        fn write_in_parens<W: fmt::Write>(condition: &SupportsCondition, dest: &mut W) -> fmt::Result {
+            match *condition {
+                SupportsCondition::Declaration(..) | SupportsCondition::FutureSyntax(_) => condition.to_css(dest),
+                _ => {
+                    try!(dest.write_str("("));
+                    try!(condition.to_css(dest));
+                    dest.write_str(")")
+                }
+            }
+        }
+
+        fn write_list<W: fmt::Write>(conditions: &[SupportsCondition], keyword: &str, dest: &mut W)
+                                     -> fmt::Result {
+            for (i, condition) in conditions.iter().enumerate() {
+                if i > 0 {
+                    try!(write!(dest, " {} ", keyword));
+                }
+                try!(write_in_parens(condition, dest));
+            }
+            Ok(())
+        }
+
+        match *self {
+            SupportsCondition::Not(ref condition) => {
+                try!(dest.write_str("not "));
+                write_in_parens(condition, dest)
+            }
+            SupportsCondition::And(ref conditions) => write_list(conditions, "and", dest),
+            SupportsCondition::Or(ref conditions) => write_list(conditions, "or", dest),
+            SupportsCondition::Declaration(ref name, ref value) => write!(dest, "({}: {})", name, value),
+            SupportsCondition::FutureSyntax(ref text) => dest.write_str(text),
+        }
+    }
+}
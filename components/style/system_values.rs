@@ -0,0 +1,108 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The colors and fonts of the platform's user interface, which keywords like `GrayText`
+//! and `font: menu` refer to. Only the embedder knows what they are, so they're looked up
+//! through `ComputedValues::system_color` and `ComputedValues::system_font` when values
+//! are computed.
+
+use app_units::Au;
+use cssparser::RGBA;
+use properties::longhands::{font_family, font_style, font_weight};
+
+// https://drafts.csswg.org/css-color/#css-system-colors
+define_css_keyword_enum! { SystemColor:
+    "activeborder" => ActiveBorder,
+    "activecaption" => ActiveCaption,
+    "appworkspace" => AppWorkspace,
+    "background" => Background,
+    "buttonface" => ButtonFace,
+    "buttonhighlight" => ButtonHighlight,
+    "buttonshadow" => ButtonShadow,
+    "buttontext" => ButtonText,
+    "captiontext" => CaptionText,
+    "graytext" => GrayText,
+    "highlight" => Highlight,
+    "highlighttext" => HighlightText,
+    "inactiveborder" => InactiveBorder,
+    "inactivecaption" => InactiveCaption,
+    "inactivecaptiontext" => InactiveCaptionText,
+    "infobackground" => InfoBackground,
+    "infotext" => InfoText,
+    "menu" => Menu,
+    "menutext" => MenuText,
+    "scrollbar" => Scrollbar,
+    "threeddarkshadow" => ThreeDDarkShadow,
+    "threedface" => ThreeDFace,
+    "threedhighlight" => ThreeDHighlight,
+    "threedlightshadow" => ThreeDLightShadow,
+    "threedshadow" => ThreeDShadow,
+    "window" => Window,
+    "windowframe" => WindowFrame,
+    "windowtext" => WindowText,
+}
+
+impl SystemColor {
+    /// The color to use instead of the platform's, which is the same everywhere and so can't
+    /// be used to tell users apart. Content documents only ever get these, and so does Servo,
+    /// which knows no platform colors.
+    pub fn standin(self) -> RGBA {
+        let (red, green, blue) = match self {
+            SystemColor::ActiveBorder => (0xB4, 0xB4, 0xB4),
+            SystemColor::ActiveCaption => (0x99, 0xB4, 0xD1),
+            SystemColor::AppWorkspace => (0xAB, 0xAB, 0xAB),
+            SystemColor::Background => (0x00, 0x00, 0x00),
+            SystemColor::ButtonFace => (0xF0, 0xF0, 0xF0),
+            SystemColor::ButtonHighlight => (0xFF, 0xFF, 0xFF),
+            SystemColor::ButtonShadow => (0xA0, 0xA0, 0xA0),
+            SystemColor::ButtonText => (0x00, 0x00, 0x00),
+            SystemColor::CaptionText => (0x00, 0x00, 0x00),
+            SystemColor::GrayText => (0x6D, 0x6D, 0x6D),
+            SystemColor::Highlight => (0x33, 0x99, 0xFF),
+            SystemColor::HighlightText => (0xFF, 0xFF, 0xFF),
+            SystemColor::InactiveBorder => (0xF4, 0xF7, 0xFC),
+            SystemColor::InactiveCaption => (0xBF, 0xCD, 0xDB),
+            SystemColor::InactiveCaptionText => (0x43, 0x4E, 0x54),
+            SystemColor::InfoBackground => (0xFF, 0xFF, 0xE1),
+            SystemColor::InfoText => (0x00, 0x00, 0x00),
+            SystemColor::Menu => (0xF0, 0xF0, 0xF0),
+            SystemColor::MenuText => (0x00, 0x00, 0x00),
+            SystemColor::Scrollbar => (0xC8, 0xC8, 0xC8),
+            SystemColor::ThreeDDarkShadow => (0x69, 0x69, 0x69),
+            SystemColor::ThreeDFace => (0xF0, 0xF0, 0xF0),
+            SystemColor::ThreeDHighlight => (0xFF, 0xFF, 0xFF),
+            SystemColor::ThreeDLightShadow => (0xE3, 0xE3, 0xE3),
+            SystemColor::ThreeDShadow => (0xA0, 0xA0, 0xA0),
+            SystemColor::Window => (0xFF, 0xFF, 0xFF),
+            SystemColor::WindowFrame => (0x64, 0x64, 0x64),
+            SystemColor::WindowText => (0x00, 0x00, 0x00),
+        };
+        RGBA {
+            red: red as f32 / 255.,
+            green: green as f32 / 255.,
+            blue: blue as f32 / 255.,
+            alpha: 1.,
+        }
+    }
+}
+
+// https://drafts.csswg.org/css-fonts/#font-prop
+define_css_keyword_enum! { SystemFont:
+    "caption" => Caption,
+    "icon" => Icon,
+    "menu" => Menu,
+    "message-box" => MessageBox,
+    "small-caption" => SmallCaption,
+    "status-bar" => StatusBar,
+}
+
+/// What a system font sets the longhands of the `font` shorthand to. The ones it doesn't
+/// mention are reset to their initial values, as by any other `font` declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemFontValues {
+    pub family: font_family::computed_value::T,
+    pub size: Au,
+    pub weight: font_weight::computed_value::T,
+    pub style: font_style::computed_value::T,
+}
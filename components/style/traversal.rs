@@ -9,6 +9,7 @@ use selector_impl::SelectorImplExt;
 use selectors::Element;
 use selectors::bloom::BloomFilter;
 use std::cell::RefCell;
+use traversal_stats;
 use util::opts;
 use util::tid::tid;
 
@@ -89,6 +90,43 @@ pub fn put_thread_local_bloom_filter<Impl: SelectorImplExt>(bf: Box<BloomFilter>
     })
 }
 
+/// Removes `node` from the thread-local bloom filter on the way back up the tree,
+/// and hands the filter over to its parent.
+///
+/// If the cached filter doesn't belong to `node` (because the parallel traversal
+/// moved work onto another thread in between), it is simply thrown away, and the
+/// next preorder step on this thread rebuilds it from the ancestor chain.
+pub fn pop_thread_local_bloom_filter<'a, N, C>(context: &'a C, root: OpaqueNode, node: N)
+    where N: TNode,
+          C: StyleContext<'a, <N::ConcreteElement as Element>::Impl, N::ConcreteComputedValues>,
+          <N::ConcreteElement as Element>::Impl: SelectorImplExt + 'a {
+    let unsafe_layout_node = node.to_unsafe();
+
+    let (mut bf, old_node, old_generation) =
+        match STYLE_BLOOM.with(|style_bloom| style_bloom.borrow_mut().take()) {
+            Some(cached) => cached,
+            None => return,
+        };
+
+    if old_node != unsafe_layout_node || old_generation != context.shared_context().generation {
+        debug!("[{}] - {:X}, but the bloom filter is stale. Deleting BF.", tid(), unsafe_layout_node.0);
+        return;
+    }
+
+    match node.layout_parent_node(root) {
+        None => {
+            debug!("[{}] - {:X}, and deleting BF.", tid(), unsafe_layout_node.0);
+            // If this is the reflow root, eat the thread-local bloom filter.
+        }
+        Some(parent) => {
+            // Otherwise, put it back, but remove this node.
+            node.remove_from_bloom_filter(&mut *bf);
+            let unsafe_parent = parent.to_unsafe();
+            put_thread_local_bloom_filter(bf, &unsafe_parent, &context.shared_context());
+        },
+    };
+}
+
 /// "Ancestors" in this context is inclusive of ourselves.
 fn insert_ancestors_into_bloom_filter<N>(bf: &mut Box<BloomFilter>,
                                          mut n: N,
@@ -113,6 +151,12 @@ pub trait DomTraversalContext<N: TNode>  {
     fn new<'a>(&'a Self::SharedContext, OpaqueNode) -> Self;
     fn process_preorder(&self, node: N);
     fn process_postorder(&self, node: N);
+
+    /// Whether the traversal should go into `node`, a child of a node it processed.
+    /// Contexts that know which subtrees have nothing to do can leave them out.
+    fn should_traverse_child(&self, _node: N) -> bool {
+        true
+    }
 }
 
 /// The recalc-style-for-node traversal, which styles each node and must run before
@@ -137,6 +181,10 @@ pub fn recalc_style_at<'a, N, C>(context: &'a C,
     // Get the style bloom filter.
     let mut bf = take_thread_local_bloom_filter(parent_opt, root, context.shared_context());
 
+    if node.as_element().is_some() {
+        traversal_stats::record(|stats| stats.elements_traversed += 1);
+    }
+
     let nonincremental_layout = opts::get().nonincremental_layout;
     if nonincremental_layout || node.is_dirty() {
         // Remove existing CSS styles from nodes whose content has changed (e.g. text changed),
@@ -149,26 +197,31 @@ pub fn recalc_style_at<'a, N, C>(context: &'a C,
         let style_sharing_candidate_cache =
             &mut context.local_context().style_sharing_candidate_cache.borrow_mut();
 
+        let stylist = unsafe { &*context.shared_context().stylist.0 };
         let sharing_result = match node.as_element() {
-            Some(element) => {
+            // What's inside a scope mustn't end up with the style of something outside it
+            // through their parents, so scope roots don't take anyone else's style.
+            Some(element) if !stylist.is_scope_root(&element) => {
                 unsafe {
                     element.share_style_if_possible(style_sharing_candidate_cache,
-                                                    parent_opt.clone())
+                                                    parent_opt.clone(),
+                                                    &context.shared_context().guard)
                 }
             },
-            None => StyleSharingResult::CannotShare,
+            _ => StyleSharingResult::CannotShare,
         };
 
         // Otherwise, match and cascade selectors.
         match sharing_result {
             StyleSharingResult::CannotShare => {
-                let mut applicable_declarations = ApplicableDeclarations::new();
+                let mut applicable_declarations =
+                    ApplicableDeclarations::new(&mut context.local_context().scratch.borrow_mut());
 
                 let shareable_element = match node.as_element() {
                     Some(element) => {
                         // Perform the CSS selector matching.
-                        let stylist = unsafe { &*context.shared_context().stylist.0 };
                         if element.match_element(stylist,
+                                                 &context.shared_context().guard,
                                                  Some(&*bf),
                                                  &mut applicable_declarations) {
                             Some(element)
@@ -184,25 +237,46 @@ pub fn recalc_style_at<'a, N, C>(context: &'a C,
                     },
                 };
 
+                if node.as_element().is_some() {
+                    traversal_stats::record(|stats| {
+                        let pseudo_blocks = applicable_declarations.per_pseudo.iter()
+                            .flat_map(|&(_, ref blocks)| blocks.iter());
+                        let blocks = applicable_declarations.normal.iter().chain(pseudo_blocks);
+                        for block in blocks {
+                            stats.selectors_matched += 1;
+                            stats.declarations_cascaded += block.declarations.len() as u32;
+                        }
+                        stats.elements_styled += 1;
+                    });
+                }
+
                 // Perform the CSS cascade.
                 unsafe {
                     node.cascade_node(&context.shared_context(),
                                       parent_opt,
                                       &applicable_declarations,
                                       &mut context.local_context().applicable_declarations_cache.borrow_mut(),
+                                      &mut context.local_context().rule_node_style_cache.borrow_mut(),
                                       &context.shared_context().new_animations_sender);
                 }
+                applicable_declarations.give_back(&mut context.local_context().scratch.borrow_mut());
 
                 // Add ourselves to the LRU cache.
                 if let Some(element) = shareable_element {
-                    style_sharing_candidate_cache.insert_if_possible::<'ln, N>(&element);
+                    style_sharing_candidate_cache.insert_if_possible::<'ln, N>(&element,
+                                                                               &context.shared_context().guard);
                 }
             }
             StyleSharingResult::StyleWasShared(index, damage) => {
                 style_sharing_candidate_cache.touch(index);
                 node.set_restyle_damage(damage);
+                traversal_stats::record(|stats| {
+                    stats.elements_styled += 1;
+                    stats.styles_shared += 1;
+                });
             }
         }
+        context.local_context().scratch.borrow_mut().reset();
     }
 
     let unsafe_layout_node = node.to_unsafe();
@@ -0,0 +1,96 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Counts of what the styling traversal did, for finding out why a restyle was slow.
+//!
+//! Counting is off unless an embedder turns it on, and costs a single relaxed load per
+//! node when it's off. Each thread counts into its own thread-local totals, so the
+//! parallel traversal never contends on them; the embedder collects them from every
+//! thread once the traversal is done.
+
+use std::cell::Cell;
+use std::ops::AddAssign;
+use std::sync::Mutex;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, AtomicBool, Ordering};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraversalStatistics {
+    /// The elements the traversal went through, whether or not they needed styling.
+    pub elements_traversed: u32,
+    /// The elements that got a new style, by matching or by sharing.
+    pub elements_styled: u32,
+    /// The elements that shared the style of a sibling or cousin instead of matching.
+    pub styles_shared: u32,
+    /// The selectors that matched, one for each rule applying to an element that matched.
+    pub selectors_matched: u32,
+    /// The declarations of the rules that matched, which the cascade went through.
+    pub declarations_cascaded: u32,
+    /// The times the embedder's DOM wrapper was asked about a node's parent, next sibling,
+    /// flags, state or element-ness, for embedders that cache the answers.
+    pub node_queries: u32,
+    /// The calls into the embedder's DOM that answering those took.
+    pub node_ffi_calls: u32,
+}
+
+impl AddAssign for TraversalStatistics {
+    fn add_assign(&mut self, other: TraversalStatistics) {
+        self.elements_traversed += other.elements_traversed;
+        self.elements_styled += other.elements_styled;
+        self.styles_shared += other.styles_shared;
+        self.selectors_matched += other.selectors_matched;
+        self.declarations_cascaded += other.declarations_cascaded;
+        self.node_queries += other.node_queries;
+        self.node_ffi_calls += other.node_ffi_calls;
+    }
+}
+
+static ENABLED: AtomicBool = ATOMIC_BOOL_INIT;
+
+thread_local!(static THREAD_STATISTICS: Cell<TraversalStatistics> = Cell::new(Default::default()));
+
+lazy_static! {
+    static ref COLLECTED_STATISTICS: Mutex<TraversalStatistics> = Mutex::new(Default::default());
+}
+
+/// Turns counting on or off.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Has `f` update the counts of the current thread, if counting is on.
+#[inline]
+pub fn record<F>(f: F) where F: FnOnce(&mut TraversalStatistics) {
+    if !enabled() {
+        return;
+    }
+    THREAD_STATISTICS.with(|statistics| {
+        let mut counts = statistics.get();
+        f(&mut counts);
+        statistics.set(counts);
+    })
+}
+
+/// Moves the counts of the current thread into the collected ones. This needs to run on
+/// every thread that took part in the traversal, once it's over.
+pub fn collect_thread_statistics() {
+    let counts = THREAD_STATISTICS.with(|statistics| {
+        let counts = statistics.get();
+        statistics.set(Default::default());
+        counts
+    });
+    *COLLECTED_STATISTICS.lock().unwrap() += counts;
+}
+
+/// Returns the counts collected since the last call, and starts over.
+pub fn take_collected_statistics() -> TraversalStatistics {
+    let mut collected = COLLECTED_STATISTICS.lock().unwrap();
+    let counts = *collected;
+    *collected = Default::default();
+    counts
+}
@@ -0,0 +1,162 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `url()` values that the embedder loads itself, like Gecko does images. Each value
+//! remembers the sheet it came from so the load happens on the sheet's behalf, and holds on
+//! to the load for as long as a computed style uses it.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+use url::Url;
+
+/// The embedder's reference to what it knows about a sheet, e.g. the principal of the
+/// document that owns it. Dropping it lets go of the reference.
+pub trait UrlExtraDataRef: Send + Sync {
+    /// The address of the data, which is what tells the data of two sheets apart.
+    fn as_ptr(&self) -> usize;
+}
+
+/// What the embedder needs to know about a sheet to load the urls in it. Opaque to the style
+/// system, shared by the sheet and every value parsed from it, and empty where a value didn't
+/// come from a sheet the embedder knows about.
+#[derive(Clone)]
+pub struct UrlExtraData(Option<Arc<Box<UrlExtraDataRef>>>);
+
+impl UrlExtraData {
+    pub fn new(data: Box<UrlExtraDataRef>) -> UrlExtraData {
+        UrlExtraData(Some(Arc::new(data)))
+    }
+
+    pub fn none() -> UrlExtraData {
+        UrlExtraData(None)
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// The address of the embedder's data, or 0 if there isn't any.
+    pub fn as_ptr(&self) -> usize {
+        self.0.as_ref().map_or(0, |data| data.as_ptr())
+    }
+}
+
+impl PartialEq for UrlExtraData {
+    fn eq(&self, other: &UrlExtraData) -> bool {
+        self.as_ptr() == other.as_ptr()
+    }
+}
+
+impl Eq for UrlExtraData {}
+
+impl fmt::Debug for UrlExtraData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UrlExtraData({:#x})", self.as_ptr())
+    }
+}
+
+/// The embedder's handle on the load of an image. Dropping it lets go of the load.
+pub trait ImageRequest: Send + Sync {
+    /// The address of the embedder's request, for handing it back.
+    fn as_ptr(&self) -> usize;
+}
+
+/// An image request shared by all the computed values the url is used in.
+pub type SharedImageRequest = Arc<Box<ImageRequest>>;
+
+/// A parsed `url()` value, shared between a declaration and everything computed from it.
+pub struct UrlValue {
+    url: Url,
+    extra_data: UrlExtraData,
+    /// The load computed values of this url started, which lives until the last of them
+    /// is dropped.
+    request: Mutex<Weak<Box<ImageRequest>>>,
+}
+
+impl UrlValue {
+    pub fn new(url: Url, extra_data: UrlExtraData) -> UrlValue {
+        UrlValue {
+            url: url,
+            extra_data: extra_data,
+            request: Mutex::new(Weak::new()),
+        }
+    }
+
+    /// The absolute URL the value resolved to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn extra_data(&self) -> &UrlExtraData {
+        &self.extra_data
+    }
+
+    /// The request for the image at this url, which `load` is asked to start unless a
+    /// computed value still holds on to an earlier one.
+    pub fn image_request<F>(&self, load: F) -> Option<SharedImageRequest>
+                            where F: FnOnce(&Url, &UrlExtraData) -> Option<Box<ImageRequest>> {
+        let mut request = self.request.lock().unwrap();
+        if let Some(request) = request.upgrade() {
+            return Some(request)
+        }
+        let new_request = load(&self.url, &self.extra_data).map(Arc::new);
+        if let Some(ref new_request) = new_request {
+            *request = Arc::downgrade(new_request);
+        }
+        new_request
+    }
+}
+
+impl PartialEq for UrlValue {
+    fn eq(&self, other: &UrlValue) -> bool {
+        self.url == other.url && self.extra_data == other.extra_data
+    }
+}
+
+impl fmt::Debug for UrlValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "url(\"{}\")", self.url)
+    }
+}
+
+/// The computed value of a `url()`: the url, along with the request for it if the embedder
+/// started one.
+#[derive(Clone)]
+pub struct ComputedUrl {
+    value: Arc<UrlValue>,
+    request: Option<SharedImageRequest>,
+}
+
+impl ComputedUrl {
+    pub fn new(value: Arc<UrlValue>, request: Option<SharedImageRequest>) -> ComputedUrl {
+        ComputedUrl {
+            value: value,
+            request: request,
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        self.value.url()
+    }
+
+    pub fn extra_data(&self) -> &UrlExtraData {
+        self.value.extra_data()
+    }
+
+    pub fn image_request(&self) -> Option<&SharedImageRequest> {
+        self.request.as_ref()
+    }
+}
+
+impl PartialEq for ComputedUrl {
+    fn eq(&self, other: &ComputedUrl) -> bool {
+        self.value == other.value
+    }
+}
+
+impl fmt::Debug for ComputedUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
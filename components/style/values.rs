@@ -87,6 +87,28 @@ pub type CSSFloat = f32;
 
 pub const FONT_MEDIUM_PX: i32 = 16;
 
+/// Serializes the values of the top, right, bottom and left sides of a box, leaving out those
+/// that parsing would fill in from the others.
+pub fn four_sides_to_css<T, W>(sides: &[T; 4], dest: &mut W) -> fmt::Result
+where T: ::cssparser::ToCss + PartialEq, W: fmt::Write {
+    let count = if sides[3] != sides[1] {
+        4
+    } else if sides[2] != sides[0] {
+        3
+    } else if sides[1] != sides[0] {
+        2
+    } else {
+        1
+    };
+    for (i, side) in sides[..count].iter().enumerate() {
+        if i > 0 {
+            try!(dest.write_str(" "));
+        }
+        try!(side.to_css(dest));
+    }
+    Ok(())
+}
+
 
 pub mod specified {
     use app_units::Au;
@@ -98,16 +120,21 @@ pub mod specified {
     use std::f32::consts::PI;
     use std::fmt::{self, Write};
     use std::ops::Mul;
+    use std::sync::Arc;
     use style_traits::values::specified::AllowedNumericType;
     use super::AuExtensionMethods;
     use super::computed::{TContext, ToComputedValue};
     use super::{CSSFloat, FONT_MEDIUM_PX};
-    use url::Url;
+    use system_values::SystemColor;
+    use url_value::UrlValue;
 
     #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
     pub struct CSSColor {
         pub parsed: cssparser::Color,
         pub authored: Option<String>,
+        /// The system color `authored` names, if any, in which case `parsed` is its
+        /// stand-in and the real color is only known once the value is computed.
+        pub system: Option<SystemColor>,
     }
     impl CSSColor {
         pub fn parse(input: &mut Parser) -> Result<CSSColor, ()> {
@@ -117,9 +144,51 @@ pub mod specified {
                 _ => None,
             };
             input.reset(start_position);
+            if let Ok(system) = input.try(SystemColor::parse) {
+                return Ok(CSSColor {
+                    parsed: cssparser::Color::RGBA(system.standin()),
+                    authored: authored,
+                    system: Some(system),
+                })
+            }
             Ok(CSSColor {
                 parsed: try!(cssparser::Color::parse(input)),
                 authored: authored,
+                system: None,
+            })
+        }
+
+        /// Like `parse`, but also accepts the hashless hex colors that quirks mode
+        /// documents allow, like `color: ff0000`.
+        ///
+        /// https://quirks.spec.whatwg.org/#the-hashless-hex-color-quirk
+        pub fn parse_quirky(context: &ParserContext, input: &mut Parser) -> Result<CSSColor, ()> {
+            if let Ok(color) = input.try(CSSColor::parse) {
+                return Ok(color)
+            }
+            if !context.in_quirks_mode() {
+                return Err(())
+            }
+            let digits = match try!(input.next()) {
+                Token::Ident(s) => s.into_owned(),
+                Token::Number(ref value) => match value.int_value {
+                    Some(int) if int >= 0 => format!("{:06}", int),
+                    _ => return Err(()),
+                },
+                Token::Dimension(ref value, ref unit) => match value.int_value {
+                    Some(int) if int >= 0 => format!("{:0>6}", format!("{}{}", int, unit)),
+                    _ => return Err(()),
+                },
+                _ => return Err(()),
+            };
+            if !(digits.len() == 3 || digits.len() == 6) || !digits.chars().all(|c| c.is_digit(16)) {
+                return Err(())
+            }
+            let hex = format!("#{}", digits);
+            Ok(CSSColor {
+                parsed: try!(cssparser::Color::parse(&mut Parser::new(&hex))),
+                authored: None,
+                system: None,
             })
         }
     }
@@ -133,10 +202,38 @@ pub mod specified {
         }
     }
 
+    /// What fills or strokes an SVG shape: `none | <color>`. Paint servers, which need
+    /// `url()` values, aren't supported yet.
+    #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+    pub enum SVGPaint {
+        None,
+        Color(CSSColor),
+    }
+
+    impl SVGPaint {
+        pub fn parse(input: &mut Parser) -> Result<SVGPaint, ()> {
+            if input.try(|input| input.expect_ident_matching("none")).is_ok() {
+                return Ok(SVGPaint::None)
+            }
+            CSSColor::parse(input).map(SVGPaint::Color)
+        }
+    }
+
+    impl ToCss for SVGPaint {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                SVGPaint::None => dest.write_str("none"),
+                SVGPaint::Color(ref color) => color.to_css(dest),
+            }
+        }
+    }
+
     #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
     pub struct CSSRGBA {
         pub parsed: cssparser::RGBA,
         pub authored: Option<String>,
+        /// As for `CSSColor`.
+        pub system: Option<SystemColor>,
     }
 
     impl ToCss for CSSRGBA {
@@ -319,18 +416,71 @@ pub mod specified {
     const AU_PER_Q: CSSFloat = AU_PER_MM / 4.;
     const AU_PER_PT: CSSFloat = AU_PER_IN / 72.;
     const AU_PER_PC: CSSFloat = AU_PER_PT * 12.;
+    /// An absolute-size keyword of font-size, which picks a size relative to `medium`. How
+    /// big `medium` is is up to the platform, and can depend on the font's generic family and
+    /// language.
+    ///
+    /// https://drafts.csswg.org/css-fonts-3/#absolute-size-value
+    #[derive(Clone, Copy, PartialEq, Debug, HeapSizeOf)]
+    pub enum KeywordSize {
+        XXSmall,
+        XSmall,
+        Small,
+        Medium,
+        Large,
+        XLarge,
+        XXLarge,
+    }
+
+    impl KeywordSize {
+        pub fn from_str(s: &str) -> Option<KeywordSize> {
+            Some(match_ignore_ascii_case! { s,
+                "xx-small" => KeywordSize::XXSmall,
+                "x-small" => KeywordSize::XSmall,
+                "small" => KeywordSize::Small,
+                "medium" => KeywordSize::Medium,
+                "large" => KeywordSize::Large,
+                "x-large" => KeywordSize::XLarge,
+                "xx-large" => KeywordSize::XXLarge,
+                _ => return None
+            })
+        }
+
+        /// The size the keyword stands for when `medium` is `medium`.
+        pub fn to_size(self, medium: Au) -> Au {
+            match self {
+                KeywordSize::XXSmall => medium * 3 / 5,
+                KeywordSize::XSmall => medium * 3 / 4,
+                KeywordSize::Small => medium * 8 / 9,
+                KeywordSize::Medium => medium,
+                KeywordSize::Large => medium * 6 / 5,
+                KeywordSize::XLarge => medium * 3 / 2,
+                KeywordSize::XXLarge => medium * 2,
+            }
+        }
+    }
+
+    impl ToCss for KeywordSize {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            dest.write_str(match *self {
+                KeywordSize::XXSmall => "xx-small",
+                KeywordSize::XSmall => "x-small",
+                KeywordSize::Small => "small",
+                KeywordSize::Medium => "medium",
+                KeywordSize::Large => "large",
+                KeywordSize::XLarge => "x-large",
+                KeywordSize::XXLarge => "xx-large",
+            })
+        }
+    }
+
     impl Length {
         // https://drafts.csswg.org/css-fonts-3/#font-size-prop
         pub fn from_str(s: &str) -> Option<Length> {
+            if let Some(keyword) = KeywordSize::from_str(s) {
+                return Some(Length::Absolute(keyword.to_size(Au::from_px(FONT_MEDIUM_PX))))
+            }
             Some(match_ignore_ascii_case! { s,
-                "xx-small" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 3 / 5),
-                "x-small" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 3 / 4),
-                "small" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 8 / 9),
-                "medium" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX)),
-                "large" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 6 / 5),
-                "x-large" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 3 / 2),
-                "xx-large" => Length::Absolute(Au::from_px(FONT_MEDIUM_PX) * 2),
-
                 // https://github.com/servo/servo/issues/3423#issuecomment-56321664
                 "smaller" => Length::FontRelative(FontRelativeLength::Em(0.85)),
                 "larger" => Length::FontRelative(FontRelativeLength::Em(1.2)),
@@ -378,9 +528,11 @@ pub mod specified {
                 _ => Err(())
             }
         }
+        /// Rounds to the nearest app unit: the pixels a length serializes to are only close to
+        /// its app units, and truncating them could lose one each time it's parsed back.
         #[inline]
         pub fn from_px(px_value: CSSFloat) -> Length {
-            Length::Absolute(Au((px_value * AU_PER_PX) as i32))
+            Length::Absolute(Au((px_value * AU_PER_PX).round() as i32))
         }
     }
 
@@ -591,6 +743,11 @@ pub mod specified {
                     input.parse_nested_block(|i| CalcLengthOrPercentage::parse_sum(i, expected_unit))
                          .map(|result| CalcValueNode::Sum(box result))
                 },
+                // A nested calc() is the same as parentheses.
+                (Token::Function(ref name), _) if name.eq_ignore_ascii_case("calc") => {
+                    input.parse_nested_block(|i| CalcLengthOrPercentage::parse_sum(i, expected_unit))
+                         .map(|result| CalcValueNode::Sum(box result))
+                },
                 _ => Err(())
             }
         }
@@ -732,6 +889,15 @@ pub mod specified {
                 }
             }
 
+            // A number can't be added to a length, and a sum of numbers isn't a length.
+            if number.is_some() {
+                return Err(())
+            }
+            if absolute.is_none() && vw.is_none() && vh.is_none() && vmax.is_none() && vmin.is_none() &&
+               em.is_none() && ex.is_none() && ch.is_none() && rem.is_none() && percentage.is_none() {
+                return Err(())
+            }
+
             Ok(CalcLengthOrPercentage {
                 absolute: absolute.map(Au),
                 vw: vw.map(ViewportPercentageLength::Vw),
@@ -1050,6 +1216,115 @@ pub mod specified {
         }
     }
 
+    /// The intrinsic size keywords, which Gecko's UA sheets use with their -moz- prefixes.
+    ///
+    /// https://drafts.csswg.org/css-sizing/#size-keywords
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, HeapSizeOf)]
+    pub enum ExtremumLength {
+        MaxContent,
+        MinContent,
+        FitContent,
+        FillAvailable,
+    }
+
+    impl ExtremumLength {
+        pub fn parse(input: &mut Parser) -> Result<ExtremumLength, ()> {
+            match_ignore_ascii_case! { try!(input.expect_ident()),
+                "-moz-max-content" => Ok(ExtremumLength::MaxContent),
+                "-moz-min-content" => Ok(ExtremumLength::MinContent),
+                "-moz-fit-content" => Ok(ExtremumLength::FitContent),
+                "-moz-available" => Ok(ExtremumLength::FillAvailable),
+                _ => Err(())
+            }
+        }
+    }
+
+    impl ToCss for ExtremumLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            dest.write_str(match *self {
+                ExtremumLength::MaxContent => "-moz-max-content",
+                ExtremumLength::MinContent => "-moz-min-content",
+                ExtremumLength::FitContent => "-moz-fit-content",
+                ExtremumLength::FillAvailable => "-moz-available",
+            })
+        }
+    }
+
+    /// A `width` or `height`.
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum MozLength {
+        LengthOrPercentageOrAuto(LengthOrPercentageOrAuto),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl ToCss for MozLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MozLength::LengthOrPercentageOrAuto(length) => length.to_css(dest),
+                MozLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
+    impl MozLength {
+        #[inline]
+        pub fn parse_non_negative(input: &mut Parser) -> Result<MozLength, ()> {
+            input.try(ExtremumLength::parse).map(MozLength::ExtremumLength).or_else(|()| {
+                LengthOrPercentageOrAuto::parse_non_negative(input).map(MozLength::LengthOrPercentageOrAuto)
+            })
+        }
+    }
+
+    /// A `min-width` or `min-height`.
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum MinLength {
+        LengthOrPercentage(LengthOrPercentage),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl ToCss for MinLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MinLength::LengthOrPercentage(length) => length.to_css(dest),
+                MinLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
+    impl MinLength {
+        #[inline]
+        pub fn parse_non_negative(input: &mut Parser) -> Result<MinLength, ()> {
+            input.try(ExtremumLength::parse).map(MinLength::ExtremumLength).or_else(|()| {
+                LengthOrPercentage::parse_non_negative(input).map(MinLength::LengthOrPercentage)
+            })
+        }
+    }
+
+    /// A `max-width` or `max-height`.
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum MaxLength {
+        LengthOrPercentageOrNone(LengthOrPercentageOrNone),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl ToCss for MaxLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MaxLength::LengthOrPercentageOrNone(length) => length.to_css(dest),
+                MaxLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
+    impl MaxLength {
+        #[inline]
+        pub fn parse_non_negative(input: &mut Parser) -> Result<MaxLength, ()> {
+            input.try(ExtremumLength::parse).map(MaxLength::ExtremumLength).or_else(|()| {
+                LengthOrPercentageOrNone::parse_non_negative(input).map(MaxLength::LengthOrPercentageOrNone)
+            })
+        }
+    }
+
     #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
     pub struct BorderRadiusSize(pub Size2D<LengthOrPercentage>);
 
@@ -1127,6 +1402,80 @@ pub mod specified {
         }
     }
 
+    /// A position, as in `background-position` or a radial gradient's center.
+    #[derive(Debug, Clone, PartialEq, Copy, HeapSizeOf)]
+    pub struct Position {
+        pub horizontal: LengthOrPercentage,
+        pub vertical: LengthOrPercentage,
+    }
+
+    impl ToCss for Position {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            try!(self.horizontal.to_css(dest));
+            try!(dest.write_str(" "));
+            try!(self.vertical.to_css(dest));
+            Ok(())
+        }
+    }
+
+    impl Position {
+        pub fn new(first: PositionComponent, second: PositionComponent) -> Result<Position, ()> {
+            let (horiz, vert) = match (category(first), category(second)) {
+                // Don't allow two vertical keywords or two horizontal keywords.
+                (PositionCategory::HorizontalKeyword, PositionCategory::HorizontalKeyword) |
+                (PositionCategory::VerticalKeyword, PositionCategory::VerticalKeyword) => return Err(()),
+
+                // Swap if both are keywords and vertical precedes horizontal.
+                (PositionCategory::VerticalKeyword, PositionCategory::HorizontalKeyword) |
+                (PositionCategory::VerticalKeyword, PositionCategory::OtherKeyword) |
+                (PositionCategory::OtherKeyword, PositionCategory::HorizontalKeyword) => (second, first),
+
+                // By default, horizontal is first.
+                _ => (first, second),
+            };
+            Ok(Position {
+                horizontal: horiz.to_length_or_percentage(),
+                vertical: vert.to_length_or_percentage(),
+            })
+        }
+
+        pub fn parse(input: &mut Parser) -> Result<Position, ()> {
+            let first = try!(PositionComponent::parse(input));
+            let second = input.try(PositionComponent::parse).unwrap_or(PositionComponent::Center);
+            Position::new(first, second)
+        }
+
+        #[inline]
+        pub fn center() -> Position {
+            Position {
+                horizontal: LengthOrPercentage::Percentage(Percentage(0.5)),
+                vertical: LengthOrPercentage::Percentage(Percentage(0.5)),
+            }
+        }
+    }
+
+    // Collapse `PositionComponent` into a few categories to simplify the above `match` expression.
+    enum PositionCategory {
+        HorizontalKeyword,
+        VerticalKeyword,
+        OtherKeyword,
+        LengthOrPercentage,
+    }
+    fn category(p: PositionComponent) -> PositionCategory {
+        match p {
+            PositionComponent::Left |
+            PositionComponent::Right =>
+                PositionCategory::HorizontalKeyword,
+            PositionComponent::Top |
+            PositionComponent::Bottom =>
+                PositionCategory::VerticalKeyword,
+            PositionComponent::Center =>
+                PositionCategory::OtherKeyword,
+            PositionComponent::LengthOrPercentage(_) =>
+                PositionCategory::LengthOrPercentage,
+        }
+    }
+
     #[derive(Clone, PartialEq, PartialOrd, Copy, Debug, HeapSizeOf, Deserialize, Serialize)]
     pub struct Angle(pub CSSFloat);
 
@@ -1174,8 +1523,9 @@ pub mod specified {
     /// Specified values for an image according to CSS-IMAGES.
     #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
     pub enum Image {
-        Url(Url),
+        Url(#[ignore_heap_size_of = "Arc"] Arc<UrlValue>),
         LinearGradient(LinearGradient),
+        RadialGradient(RadialGradient),
     }
 
     impl ToCss for Image {
@@ -1183,25 +1533,40 @@ pub mod specified {
             match *self {
                 Image::Url(ref url) => {
                     try!(dest.write_str("url(\""));
-                    try!(write!(&mut CssStringWriter::new(dest), "{}", url));
+                    try!(write!(&mut CssStringWriter::new(dest), "{}", url.url()));
                     try!(dest.write_str("\")"));
                     Ok(())
                 }
-                Image::LinearGradient(ref gradient) => gradient.to_css(dest)
+                Image::LinearGradient(ref gradient) => gradient.to_css(dest),
+                Image::RadialGradient(ref gradient) => gradient.to_css(dest),
             }
         }
     }
 
     impl Image {
+        /// Parses an image. The `-moz-` gradients Gecko's UA sheets still use are parsed into
+        /// the modern gradients they stand for, and serialize as those.
         pub fn parse(context: &ParserContext, input: &mut Parser) -> Result<Image, ()> {
             if let Ok(url) = input.try(|input| input.expect_url()) {
-                Ok(Image::Url(context.parse_url(&url)))
+                Ok(Image::Url(Arc::new(context.parse_url_value(&url))))
             } else {
                 match_ignore_ascii_case! { try!(input.expect_function()),
                     "linear-gradient" => {
                         Ok(Image::LinearGradient(try!(
                             input.parse_nested_block(LinearGradient::parse_function))))
                     },
+                    "-moz-linear-gradient" => {
+                        Ok(Image::LinearGradient(try!(
+                            input.parse_nested_block(LinearGradient::parse_legacy_function))))
+                    },
+                    "radial-gradient" => {
+                        Ok(Image::RadialGradient(try!(
+                            input.parse_nested_block(RadialGradient::parse_function))))
+                    },
+                    "-moz-radial-gradient" => {
+                        Ok(Image::RadialGradient(try!(
+                            input.parse_nested_block(RadialGradient::parse_legacy_function))))
+                    },
                     _ => Err(())
                 }
             }
@@ -1221,17 +1586,18 @@ pub mod specified {
     impl ToCss for LinearGradient {
         fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
             try!(dest.write_str("linear-gradient("));
-            try!(self.angle_or_corner.to_css(dest));
-            for stop in &self.stops {
+            // Going to the bottom is the default, so it goes without saying.
+            if self.angle_or_corner != AngleOrCorner::Angle(Angle(PI)) {
+                try!(self.angle_or_corner.to_css(dest));
                 try!(dest.write_str(", "));
-                try!(stop.to_css(dest));
             }
-            try!(dest.write_str(")"));
-            Ok(())
+            try!(serialize_color_stops(&self.stops, dest));
+            dest.write_str(")")
         }
     }
 
-    /// Specified values for an angle or a corner in a linear gradient.
+    /// Specified values for an angle or a corner in a linear gradient. Going to a side is an
+    /// angle, and serializes as the side.
     #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
     pub enum AngleOrCorner {
         Angle(Angle),
@@ -1241,6 +1607,10 @@ pub mod specified {
     impl ToCss for AngleOrCorner {
         fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
             match *self {
+                AngleOrCorner::Angle(Angle(radians)) if radians == 0. => dest.write_str("to top"),
+                AngleOrCorner::Angle(Angle(radians)) if radians == PI * 0.5 => dest.write_str("to right"),
+                AngleOrCorner::Angle(Angle(radians)) if radians == PI => dest.write_str("to bottom"),
+                AngleOrCorner::Angle(Angle(radians)) if radians == PI * 1.5 => dest.write_str("to left"),
                 AngleOrCorner::Angle(angle) => angle.to_css(dest),
                 AngleOrCorner::Corner(horizontal, vertical) => {
                     try!(dest.write_str("to "));
@@ -1253,7 +1623,21 @@ pub mod specified {
         }
     }
 
-    /// Specified values for one color stop in a linear gradient.
+    impl AngleOrCorner {
+        /// The direction of a gradient going to a side or corner.
+        fn to_side_or_corner(horizontal: Option<HorizontalDirection>, vertical: Option<VerticalDirection>)
+                             -> AngleOrCorner {
+            match (horizontal, vertical) {
+                (None, Some(VerticalDirection::Top)) => AngleOrCorner::Angle(Angle(0.0)),
+                (Some(HorizontalDirection::Right), None) => AngleOrCorner::Angle(Angle(PI * 0.5)),
+                (None, Some(VerticalDirection::Bottom)) | (None, None) => AngleOrCorner::Angle(Angle(PI)),
+                (Some(HorizontalDirection::Left), None) => AngleOrCorner::Angle(Angle(PI * 1.5)),
+                (Some(horizontal), Some(vertical)) => AngleOrCorner::Corner(horizontal, vertical),
+            }
+        }
+    }
+
+    /// Specified values for one color stop in a gradient.
     #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
     pub struct ColorStop {
         /// The color of this stop.
@@ -1275,9 +1659,37 @@ pub mod specified {
         }
     }
 
+    fn serialize_color_stops<W>(stops: &[ColorStop], dest: &mut W) -> fmt::Result where W: fmt::Write {
+        for (i, stop) in stops.iter().enumerate() {
+            if i != 0 {
+                try!(dest.write_str(", "));
+            }
+            try!(stop.to_css(dest));
+        }
+        Ok(())
+    }
+
     define_css_keyword_enum!(HorizontalDirection: "left" => Left, "right" => Right);
     define_css_keyword_enum!(VerticalDirection: "top" => Top, "bottom" => Bottom);
 
+    impl HorizontalDirection {
+        fn opposite(self) -> HorizontalDirection {
+            match self {
+                HorizontalDirection::Left => HorizontalDirection::Right,
+                HorizontalDirection::Right => HorizontalDirection::Left,
+            }
+        }
+    }
+
+    impl VerticalDirection {
+        fn opposite(self) -> VerticalDirection {
+            match self {
+                VerticalDirection::Top => VerticalDirection::Bottom,
+                VerticalDirection::Bottom => VerticalDirection::Top,
+            }
+        }
+    }
+
     fn parse_one_color_stop(input: &mut Parser) -> Result<ColorStop, ()> {
         Ok(ColorStop {
             color: try!(CSSColor::parse(input)),
@@ -1285,6 +1697,45 @@ pub mod specified {
         })
     }
 
+    fn parse_color_stops(input: &mut Parser) -> Result<Vec<ColorStop>, ()> {
+        let stops = try!(input.parse_comma_separated(parse_one_color_stop));
+        if stops.len() < 2 {
+            return Err(())
+        }
+        Ok(stops)
+    }
+
+    /// Parses the side or corner a `-moz-` linear gradient starts from: one or two of the
+    /// side keywords and `center`, in either order.
+    fn parse_legacy_start(input: &mut Parser)
+                          -> Result<(Option<HorizontalDirection>, Option<VerticalDirection>), ()> {
+        let (mut horizontal, mut vertical, mut keywords) = (None, None, 0);
+        while keywords < 2 {
+            if input.try(|input| input.expect_ident_matching("center")).is_err() {
+                if horizontal.is_none() {
+                    if let Ok(value) = input.try(HorizontalDirection::parse) {
+                        horizontal = Some(value);
+                        keywords += 1;
+                        continue
+                    }
+                }
+                if vertical.is_none() {
+                    if let Ok(value) = input.try(VerticalDirection::parse) {
+                        vertical = Some(value);
+                        keywords += 1;
+                        continue
+                    }
+                }
+                break
+            }
+            keywords += 1;
+        }
+        if keywords == 0 {
+            return Err(())
+        }
+        Ok((horizontal, vertical))
+    }
+
     impl LinearGradient {
         /// Parses a linear gradient from the given arguments.
         pub fn parse_function(input: &mut Parser) -> Result<LinearGradient, ()> {
@@ -1297,38 +1748,223 @@ pub mod specified {
                     (input.try(HorizontalDirection::parse).ok(), Some(value))
                 };
                 try!(input.expect_comma());
-                match (horizontal, vertical) {
-                    (None, Some(VerticalDirection::Top)) => {
-                        AngleOrCorner::Angle(Angle(0.0))
-                    },
-                    (Some(HorizontalDirection::Right), None) => {
-                        AngleOrCorner::Angle(Angle(PI * 0.5))
-                    },
-                    (None, Some(VerticalDirection::Bottom)) => {
-                        AngleOrCorner::Angle(Angle(PI))
-                    },
-                    (Some(HorizontalDirection::Left), None) => {
-                        AngleOrCorner::Angle(Angle(PI * 1.5))
-                    },
-                    (Some(horizontal), Some(vertical)) => {
-                        AngleOrCorner::Corner(horizontal, vertical)
-                    }
-                    (None, None) => unreachable!(),
-                }
+                AngleOrCorner::to_side_or_corner(horizontal, vertical)
             } else if let Ok(angle) = input.try(Angle::parse) {
                 try!(input.expect_comma());
                 AngleOrCorner::Angle(angle)
             } else {
                 AngleOrCorner::Angle(Angle(PI))
             };
-            // Parse the color stops.
-            let stops = try!(input.parse_comma_separated(parse_one_color_stop));
-            if stops.len() < 2 {
-                return Err(())
-            }
             Ok(LinearGradient {
                 angle_or_corner: angle_or_corner,
-                stops: stops,
+                stops: try!(parse_color_stops(input)),
+            })
+        }
+
+        /// Parses the arguments of a `-moz-linear-gradient`, which names the side or corner
+        /// the gradient starts from rather than the one it goes to, and measures its angles
+        /// counterclockwise from the right rather than clockwise from the top.
+        pub fn parse_legacy_function(input: &mut Parser) -> Result<LinearGradient, ()> {
+            let angle_or_corner = if let Ok(angle) = input.try(Angle::parse) {
+                try!(input.expect_comma());
+                AngleOrCorner::Angle(Angle(PI * 0.5 - angle.radians()))
+            } else if let Ok((horizontal, vertical)) = input.try(parse_legacy_start) {
+                try!(input.expect_comma());
+                match (horizontal, vertical) {
+                    (None, None) => AngleOrCorner::Angle(Angle(PI)),
+                    (horizontal, vertical) => {
+                        AngleOrCorner::to_side_or_corner(horizontal.map(HorizontalDirection::opposite),
+                                                         vertical.map(VerticalDirection::opposite))
+                    }
+                }
+            } else {
+                AngleOrCorner::Angle(Angle(PI))
+            };
+            Ok(LinearGradient {
+                angle_or_corner: angle_or_corner,
+                stops: try!(parse_color_stops(input)),
+            })
+        }
+    }
+
+    define_css_keyword_enum!(GradientShape: "circle" => Circle, "ellipse" => Ellipse);
+    define_css_keyword_enum!(SizeKeyword: "closest-side" => ClosestSide, "farthest-side" => FarthestSide,
+                             "closest-corner" => ClosestCorner, "farthest-corner" => FarthestCorner);
+
+    /// Specified values for the size of a radial gradient's ending shape. A circle's explicit
+    /// size is its radius, and is always a length; it goes in both fields.
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum GradientSize {
+        Keyword(SizeKeyword),
+        Explicit(LengthOrPercentage, LengthOrPercentage),
+    }
+
+    /// Specified values for a CSS radial gradient.
+    #[derive(Clone, PartialEq, Debug, HeapSizeOf)]
+    pub struct RadialGradient {
+        pub shape: GradientShape,
+        pub size: GradientSize,
+
+        /// The center of the gradient.
+        pub position: Position,
+
+        /// The color stops.
+        pub stops: Vec<ColorStop>,
+    }
+
+    impl ToCss for RadialGradient {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            try!(dest.write_str("radial-gradient("));
+            // An ellipse reaching the farthest corner is the default, and an explicit size says
+            // which shape it's for.
+            let wrote_shape = match (self.shape, self.size) {
+                (GradientShape::Ellipse, GradientSize::Keyword(SizeKeyword::FarthestCorner)) => false,
+                (GradientShape::Circle, GradientSize::Keyword(SizeKeyword::FarthestCorner)) => {
+                    try!(dest.write_str("circle"));
+                    true
+                }
+                (GradientShape::Circle, GradientSize::Keyword(keyword)) => {
+                    try!(dest.write_str("circle "));
+                    try!(keyword.to_css(dest));
+                    true
+                }
+                (GradientShape::Ellipse, GradientSize::Keyword(keyword)) => {
+                    try!(keyword.to_css(dest));
+                    true
+                }
+                (GradientShape::Circle, GradientSize::Explicit(radius, _)) => {
+                    try!(radius.to_css(dest));
+                    true
+                }
+                (GradientShape::Ellipse, GradientSize::Explicit(width, height)) => {
+                    try!(width.to_css(dest));
+                    try!(dest.write_str(" "));
+                    try!(height.to_css(dest));
+                    true
+                }
+            };
+            let centered = self.position == Position::center();
+            if !centered {
+                try!(dest.write_str(if wrote_shape { " at " } else { "at " }));
+                try!(self.position.to_css(dest));
+            }
+            if wrote_shape || !centered {
+                try!(dest.write_str(", "));
+            }
+            try!(serialize_color_stops(&self.stops, dest));
+            dest.write_str(")")
+        }
+    }
+
+    /// A radial gradient's size as written, before it's known which shape it's for.
+    enum ParsedGradientSize {
+        Keyword(SizeKeyword),
+        Explicit(LengthOrPercentage, Option<LengthOrPercentage>),
+    }
+
+    fn parse_size_keyword(input: &mut Parser, legacy: bool) -> Result<SizeKeyword, ()> {
+        if let Ok(keyword) = input.try(SizeKeyword::parse) {
+            return Ok(keyword)
+        }
+        if !legacy {
+            return Err(())
+        }
+        match_ignore_ascii_case! { try!(input.expect_ident()),
+            "contain" => Ok(SizeKeyword::ClosestSide),
+            "cover" => Ok(SizeKeyword::FarthestCorner),
+            _ => Err(())
+        }
+    }
+
+    /// Parses the shape and size of a radial gradient, in either order, or neither. `-moz-`
+    /// gradients also have `contain` and `cover` for sizes, but no explicit ones.
+    fn parse_shape_and_size(input: &mut Parser, legacy: bool)
+                            -> Result<Option<(GradientShape, GradientSize)>, ()> {
+        let (mut shape, mut size) = (None, None);
+        loop {
+            if shape.is_none() {
+                if let Ok(value) = input.try(GradientShape::parse) {
+                    shape = Some(value);
+                    continue
+                }
+            }
+            if size.is_none() {
+                if let Ok(keyword) = input.try(|input| parse_size_keyword(input, legacy)) {
+                    size = Some(ParsedGradientSize::Keyword(keyword));
+                    continue
+                }
+                if !legacy {
+                    if let Ok(width) = input.try(LengthOrPercentage::parse) {
+                        size = Some(ParsedGradientSize::Explicit(width, input.try(LengthOrPercentage::parse).ok()));
+                        continue
+                    }
+                }
+            }
+            break
+        }
+        Ok(Some(match (shape, size) {
+            (None, None) => return Ok(None),
+            (shape, None) => {
+                (shape.unwrap_or(GradientShape::Ellipse), GradientSize::Keyword(SizeKeyword::FarthestCorner))
+            }
+            (shape, Some(ParsedGradientSize::Keyword(keyword))) => {
+                (shape.unwrap_or(GradientShape::Ellipse), GradientSize::Keyword(keyword))
+            }
+            (Some(GradientShape::Circle), Some(ParsedGradientSize::Explicit(radius, None))) |
+            (None, Some(ParsedGradientSize::Explicit(radius, None))) => {
+                match radius {
+                    LengthOrPercentage::Length(_) => (GradientShape::Circle, GradientSize::Explicit(radius, radius)),
+                    _ => return Err(()),
+                }
+            }
+            (Some(GradientShape::Ellipse), Some(ParsedGradientSize::Explicit(width, Some(height)))) |
+            (None, Some(ParsedGradientSize::Explicit(width, Some(height)))) => {
+                (GradientShape::Ellipse, GradientSize::Explicit(width, height))
+            }
+            _ => return Err(()),
+        }))
+    }
+
+    impl RadialGradient {
+        /// Parses a radial gradient from the given arguments.
+        pub fn parse_function(input: &mut Parser) -> Result<RadialGradient, ()> {
+            let shape_and_size = try!(parse_shape_and_size(input, false));
+            let position = if input.try(|input| input.expect_ident_matching("at")).is_ok() {
+                Some(try!(Position::parse(input)))
+            } else {
+                None
+            };
+            if shape_and_size.is_some() || position.is_some() {
+                try!(input.expect_comma());
+            }
+            let (shape, size) =
+                shape_and_size.unwrap_or((GradientShape::Ellipse, GradientSize::Keyword(SizeKeyword::FarthestCorner)));
+            Ok(RadialGradient {
+                shape: shape,
+                size: size,
+                position: position.unwrap_or(Position::center()),
+                stops: try!(parse_color_stops(input)),
+            })
+        }
+
+        /// Parses the arguments of a `-moz-radial-gradient`, which puts the center first and
+        /// the shape and size after it, each followed by a comma.
+        pub fn parse_legacy_function(input: &mut Parser) -> Result<RadialGradient, ()> {
+            let position = input.try(Position::parse).ok();
+            if position.is_some() {
+                try!(input.expect_comma());
+            }
+            let shape_and_size = try!(parse_shape_and_size(input, true));
+            if shape_and_size.is_some() {
+                try!(input.expect_comma());
+            }
+            let (shape, size) =
+                shape_and_size.unwrap_or((GradientShape::Ellipse, GradientSize::Keyword(SizeKeyword::FarthestCorner)));
+            Ok(RadialGradient {
+                shape: shape,
+                size: size,
+                position: position.unwrap_or(Position::center()),
+                stops: try!(parse_color_stops(input)),
             })
         }
     }
@@ -1435,16 +2071,29 @@ pub mod specified {
 
 pub mod computed {
     use app_units::Au;
+    use cssparser::{RGBA, ToCss};
     use euclid::size::Size2D;
+    use std::cell::{Cell, RefCell};
     use properties::ComputedValues;
     use properties::style_struct_traits::TFont;
+    use std::collections::HashMap;
+    use std::f32::consts::PI;
     use std::fmt;
     use super::AuExtensionMethods;
-    use super::specified::AngleOrCorner;
-    use super::{CSSFloat, specified};
-    use url::Url;
+    use super::specified::{AngleOrCorner, GradientShape, SizeKeyword};
+    use super::{CSSFloat, LocalToCss, specified};
+    use system_values::SystemColor;
+    use url_value::ComputedUrl;
     pub use cssparser::Color as CSSColor;
-    pub use super::specified::{Angle, BorderStyle, Time};
+    pub use super::specified::{Angle, BorderStyle, ExtremumLength, Time};
+
+    /// The metrics of a font that ex and ch lengths are relative to.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct FontMetrics {
+        pub x_height: Au,
+        /// The advance of the "0" glyph.
+        pub zero_advance_measure: Au,
+    }
 
     pub trait TContext {
         type ConcreteComputedValues: ComputedValues;
@@ -1453,6 +2102,11 @@ pub mod computed {
         fn inherited_style(&self) -> &Self::ConcreteComputedValues;
         fn style(&self) -> &Self::ConcreteComputedValues;
         fn mutate_style(&mut self) -> &mut Self::ConcreteComputedValues;
+        /// The metrics of the first available font of the style, or None if they
+        /// aren't known (yet).
+        fn font_metrics(&self) -> Option<FontMetrics>;
+        /// What the platform makes of a system color keyword.
+        fn system_color(&self, color: SystemColor) -> RGBA;
     }
 
     pub struct Context<'a, C: ComputedValues> {
@@ -1463,6 +2117,19 @@ pub mod computed {
         /// Values access through this need to be in the properties "computed early":
         /// color, text-decoration, font-size, display, position, float, border-*-style, outline-style
         pub style: C,
+
+        /// The font metrics of `style`, looked up the first time an ex or ch length
+        /// needs them, since that can be expensive. None until then.
+        pub font_metrics: Cell<Option<Option<FontMetrics>>>,
+
+        /// The system colors looked up so far, so that a cascade asks the embedder about
+        /// each of them once however many properties use it.
+        pub system_colors: RefCell<HashMap<SystemColor, RGBA>>,
+
+        /// The keyword `style`'s font-size came from, if it came from one. What the keyword
+        /// comes to depends on the font family and language, which can be cascaded after the
+        /// size, so the cascade works it out again once they're known.
+        pub font_size_keyword: Option<specified::KeywordSize>,
     }
 
     impl<'a, C: ComputedValues> TContext for Context<'a, C> {
@@ -1472,6 +2139,36 @@ pub mod computed {
         fn inherited_style(&self) -> &C { &self.inherited_style }
         fn style(&self) -> &C { &self.style }
         fn mutate_style(&mut self) -> &mut C { &mut self.style }
+        fn font_metrics(&self) -> Option<FontMetrics> {
+            if let Some(metrics) = self.font_metrics.get() {
+                return metrics
+            }
+            let metrics = self.style.get_font().font_metrics();
+            self.font_metrics.set(Some(metrics));
+            metrics
+        }
+        fn system_color(&self, color: SystemColor) -> RGBA {
+            *self.system_colors.borrow_mut().entry(color).or_insert_with(|| C::system_color(color))
+        }
+    }
+
+    /// Resolves a font-relative length against the style's font, using the font's
+    /// metrics for ex and ch when they're available.
+    fn font_relative_to_computed_value<Cx: TContext>(length: specified::FontRelativeLength,
+                                                     context: &Cx) -> Au {
+        let metrics = match length {
+            specified::FontRelativeLength::Ex(_) |
+            specified::FontRelativeLength::Ch(_) => context.font_metrics(),
+            _ => None,
+        };
+        match (length, metrics) {
+            (specified::FontRelativeLength::Ex(length), Some(metrics)) =>
+                metrics.x_height.scale_by(length),
+            (specified::FontRelativeLength::Ch(length), Some(metrics)) =>
+                metrics.zero_advance_measure.scale_by(length),
+            _ => length.to_computed_value(context.style().get_font().clone_font_size(),
+                                          context.style().root_font_size()),
+        }
     }
 
     pub trait ToComputedValue {
@@ -1496,8 +2193,38 @@ pub mod computed {
         type ComputedValue = CSSColor;
 
         #[inline]
-        fn to_computed_value<Cx: TContext>(&self, _context: &Cx) -> CSSColor {
-            self.parsed
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> CSSColor {
+            match self.system {
+                Some(system) => CSSColor::RGBA(context.system_color(system)),
+                None => self.parsed,
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum SVGPaint {
+        None,
+        Color(CSSColor),
+    }
+
+    impl ToComputedValue for specified::SVGPaint {
+        type ComputedValue = SVGPaint;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> SVGPaint {
+            match *self {
+                specified::SVGPaint::None => SVGPaint::None,
+                specified::SVGPaint::Color(ref color) => SVGPaint::Color(color.to_computed_value(context)),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for SVGPaint {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                SVGPaint::None => dest.write_str("none"),
+                SVGPaint::Color(color) => color.to_css(dest),
+            }
         }
     }
 
@@ -1512,8 +2239,7 @@ pub mod computed {
                 specified::Length::Absolute(length) => length,
                 specified::Length::Calc(calc) => calc.to_computed_value(context).length(),
                 specified::Length::FontRelative(length) =>
-                    length.to_computed_value(context.style().get_font().clone_font_size(),
-                                             context.style().root_font_size()),
+                    font_relative_to_computed_value(length, context),
                 specified::Length::ViewportPercentage(length) =>
                     length.to_computed_value(context.viewport_size()),
                 specified::Length::ServoCharacterWidth(length) =>
@@ -1589,11 +2315,15 @@ pub mod computed {
 
     impl ::cssparser::ToCss for CalcLengthOrPercentage {
         fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            // https://drafts.csswg.org/css-values/#calc-serialize
+            // A calc() that was only made of lengths, or only of percentages, serializes as
+            // the one length or percentage it sums up to. Otherwise the length comes first,
+            // and a negative part is added rather than subtracted.
             match (self.length, self.percentage) {
                 (None, Some(p)) => write!(dest, "{}%", p * 100.),
-                (Some(l), None) => write!(dest, "{}px", Au::to_px(l)),
-                (Some(l), Some(p)) => write!(dest, "calc({}px + {}%)", Au::to_px(l), p * 100.),
-                _ => unreachable!()
+                (Some(l), None) => write!(dest, "{}px", l.to_f32_px()),
+                (Some(l), Some(p)) => write!(dest, "calc({}px + {}%)", l.to_f32_px(), p * 100.),
+                (None, None) => dest.write_str("0px"),
             }
         }
     }
@@ -1616,8 +2346,7 @@ pub mod computed {
             }
             for val in &[self.ch, self.em, self.ex, self.rem] {
                 if let Some(val) = *val {
-                    length = Some(length.unwrap_or(Au(0)) + val.to_computed_value(
-                        context.style().get_font().clone_font_size(), context.style().root_font_size()));
+                    length = Some(length.unwrap_or(Au(0)) + font_relative_to_computed_value(val, context));
                 }
             }
 
@@ -1852,16 +2581,141 @@ pub mod computed {
         }
     }
 
+    /// Servo's layout doesn't size boxes by the intrinsic size keywords, and takes them as
+    /// the initial value of the property instead, as it would take a value it can't parse.
+    #[derive(PartialEq, Clone, Copy, Debug, HeapSizeOf)]
+    pub enum MozLength {
+        LengthOrPercentageOrAuto(LengthOrPercentageOrAuto),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl MozLength {
+        #[inline]
+        pub fn to_length_or_percentage_or_auto(&self) -> LengthOrPercentageOrAuto {
+            match *self {
+                MozLength::LengthOrPercentageOrAuto(length) => length,
+                MozLength::ExtremumLength(_) => LengthOrPercentageOrAuto::Auto,
+            }
+        }
+    }
+
+    impl ToComputedValue for specified::MozLength {
+        type ComputedValue = MozLength;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> MozLength {
+            match *self {
+                specified::MozLength::LengthOrPercentageOrAuto(length) => {
+                    MozLength::LengthOrPercentageOrAuto(length.to_computed_value(context))
+                }
+                specified::MozLength::ExtremumLength(keyword) => MozLength::ExtremumLength(keyword),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for MozLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MozLength::LengthOrPercentageOrAuto(length) => length.to_css(dest),
+                MozLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Clone, Copy, Debug, HeapSizeOf)]
+    pub enum MinLength {
+        LengthOrPercentage(LengthOrPercentage),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl MinLength {
+        #[inline]
+        pub fn to_length_or_percentage(&self) -> LengthOrPercentage {
+            match *self {
+                MinLength::LengthOrPercentage(length) => length,
+                MinLength::ExtremumLength(_) => LengthOrPercentage::Length(Au(0)),
+            }
+        }
+    }
+
+    impl ToComputedValue for specified::MinLength {
+        type ComputedValue = MinLength;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> MinLength {
+            match *self {
+                specified::MinLength::LengthOrPercentage(length) => {
+                    MinLength::LengthOrPercentage(length.to_computed_value(context))
+                }
+                specified::MinLength::ExtremumLength(keyword) => MinLength::ExtremumLength(keyword),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for MinLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MinLength::LengthOrPercentage(length) => length.to_css(dest),
+                MinLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Clone, Copy, Debug, HeapSizeOf)]
+    pub enum MaxLength {
+        LengthOrPercentageOrNone(LengthOrPercentageOrNone),
+        ExtremumLength(ExtremumLength),
+    }
+
+    impl MaxLength {
+        #[inline]
+        pub fn to_length_or_percentage_or_none(&self) -> LengthOrPercentageOrNone {
+            match *self {
+                MaxLength::LengthOrPercentageOrNone(length) => length,
+                MaxLength::ExtremumLength(_) => LengthOrPercentageOrNone::None,
+            }
+        }
+    }
+
+    impl ToComputedValue for specified::MaxLength {
+        type ComputedValue = MaxLength;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> MaxLength {
+            match *self {
+                specified::MaxLength::LengthOrPercentageOrNone(length) => {
+                    MaxLength::LengthOrPercentageOrNone(length.to_computed_value(context))
+                }
+                specified::MaxLength::ExtremumLength(keyword) => MaxLength::ExtremumLength(keyword),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for MaxLength {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                MaxLength::LengthOrPercentageOrNone(length) => length.to_css(dest),
+                MaxLength::ExtremumLength(keyword) => keyword.to_css(dest),
+            }
+        }
+    }
+
     impl ToComputedValue for specified::Image {
         type ComputedValue = Image;
 
         #[inline]
         fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> Image {
             match *self {
-                specified::Image::Url(ref url) => Image::Url(url.clone()),
+                specified::Image::Url(ref url) => {
+                    let request = url.image_request(<Cx::ConcreteComputedValues as ComputedValues>::load_image);
+                    Image::Url(ComputedUrl::new(url.clone(), request))
+                }
                 specified::Image::LinearGradient(ref linear_gradient) => {
                     Image::LinearGradient(linear_gradient.to_computed_value(context))
                 }
+                specified::Image::RadialGradient(ref radial_gradient) => {
+                    Image::RadialGradient(radial_gradient.to_computed_value(context))
+                }
             }
         }
     }
@@ -1870,15 +2724,29 @@ pub mod computed {
     /// Computed values for an image according to CSS-IMAGES.
     #[derive(Clone, PartialEq, HeapSizeOf)]
     pub enum Image {
-        Url(Url),
+        /// Holds on to the embedder's request for the image, if it made one, so that it's
+        /// only loaded once however many times the styles using it are recomputed.
+        Url(#[ignore_heap_size_of = "Arc"] ComputedUrl),
         LinearGradient(LinearGradient),
+        RadialGradient(RadialGradient),
     }
 
     impl fmt::Debug for Image {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             match *self {
-                Image::Url(ref url) => write!(f, "url(\"{}\")", url),
+                Image::Url(ref url) => url.fmt(f),
                 Image::LinearGradient(ref grad) => write!(f, "linear-gradient({:?})", grad),
+                Image::RadialGradient(ref grad) => write!(f, "radial-gradient({:?})", grad),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for Image {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            match *self {
+                Image::Url(ref url) => url.url().to_css(dest),
+                Image::LinearGradient(ref gradient) => gradient.to_css(dest),
+                Image::RadialGradient(ref gradient) => gradient.to_css(dest),
             }
         }
     }
@@ -1896,13 +2764,12 @@ pub mod computed {
     impl ::cssparser::ToCss for LinearGradient {
         fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
             try!(dest.write_str("linear-gradient("));
-            try!(self.angle_or_corner.to_css(dest));
-            for stop in &self.stops {
+            if self.angle_or_corner != AngleOrCorner::Angle(specified::Angle(PI)) {
+                try!(self.angle_or_corner.to_css(dest));
                 try!(dest.write_str(", "));
-                try!(stop.to_css(dest));
             }
-            try!(dest.write_str(")"));
-            Ok(())
+            try!(serialize_color_stops(&self.stops, dest));
+            dest.write_str(")")
         }
     }
 
@@ -1916,23 +2783,100 @@ pub mod computed {
         }
     }
 
-    /// Computed values for one color stop in a linear gradient.
+    /// Computed values for a CSS radial gradient.
+    #[derive(Clone, PartialEq, HeapSizeOf)]
+    pub struct RadialGradient {
+        pub shape: GradientShape,
+        pub size: GradientSize,
+
+        /// The center of the gradient.
+        pub position: Position,
+
+        /// The color stops.
+        pub stops: Vec<ColorStop>,
+    }
+
+    /// Computed values for the size of a radial gradient's ending shape.
+    #[derive(Clone, PartialEq, Copy, Debug, HeapSizeOf)]
+    pub enum GradientSize {
+        Keyword(SizeKeyword),
+        Explicit(LengthOrPercentage, LengthOrPercentage),
+    }
+
+    impl ::cssparser::ToCss for RadialGradient {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            try!(dest.write_str("radial-gradient("));
+            let wrote_shape = match (self.shape, self.size) {
+                (GradientShape::Ellipse, GradientSize::Keyword(SizeKeyword::FarthestCorner)) => false,
+                (GradientShape::Circle, GradientSize::Keyword(SizeKeyword::FarthestCorner)) => {
+                    try!(dest.write_str("circle"));
+                    true
+                }
+                (GradientShape::Circle, GradientSize::Keyword(keyword)) => {
+                    try!(dest.write_str("circle "));
+                    try!(keyword.to_css(dest));
+                    true
+                }
+                (GradientShape::Ellipse, GradientSize::Keyword(keyword)) => {
+                    try!(keyword.to_css(dest));
+                    true
+                }
+                (GradientShape::Circle, GradientSize::Explicit(radius, _)) => {
+                    try!(radius.to_css(dest));
+                    true
+                }
+                (GradientShape::Ellipse, GradientSize::Explicit(width, height)) => {
+                    try!(width.to_css(dest));
+                    try!(dest.write_str(" "));
+                    try!(height.to_css(dest));
+                    true
+                }
+            };
+            let centered = self.position == Position::center();
+            if !centered {
+                try!(dest.write_str(if wrote_shape { " at " } else { "at " }));
+                try!(self.position.to_css(dest));
+            }
+            if wrote_shape || !centered {
+                try!(dest.write_str(", "));
+            }
+            try!(serialize_color_stops(&self.stops, dest));
+            dest.write_str(")")
+        }
+    }
+
+    impl fmt::Debug for RadialGradient {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let _ = write!(f, "{:?} {:?} at {:?}", self.shape, self.size, self.position);
+            for stop in &self.stops {
+                let _ = write!(f, ", {:?}", stop);
+            }
+            Ok(())
+        }
+    }
+
+    /// Computed values for one color stop in a gradient. Every stop has a position once
+    /// computed, placed per CSS-IMAGES § 3.4.3 when the stop didn't say where it goes.
     #[derive(Clone, PartialEq, Copy, HeapSizeOf)]
     pub struct ColorStop {
-        /// The color of this stop.
+        /// The color of this stop. `currentcolor` stays as it is, so that the gradient
+        /// follows the color of whatever element it's painted for.
         pub color: CSSColor,
 
-        /// The position of this stop. If not specified, this stop is placed halfway between the
-        /// point that precedes it and the point that follows it per CSS-IMAGES § 3.4.
-        pub position: Option<LengthOrPercentage>,
+        /// The position of this stop.
+        pub position: LengthOrPercentage,
+
+        /// Whether the position was filled in rather than specified, in which case it isn't
+        /// serialized.
+        pub implied_position: bool,
     }
 
     impl ::cssparser::ToCss for ColorStop {
         fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
             try!(self.color.to_css(dest));
-            if let Some(position) = self.position {
+            if !self.implied_position {
                 try!(dest.write_str(" "));
-                try!(position.to_css(dest));
+                try!(self.position.to_css(dest));
             }
             Ok(())
         }
@@ -1940,34 +2884,174 @@ pub mod computed {
 
     impl fmt::Debug for ColorStop {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            let _ = write!(f, "{:?}", self.color);
-            self.position.map(|pos| {
-                let _ = write!(f, " {:?}", pos);
-            });
-            Ok(())
+            write!(f, "{:?} {:?}", self.color, self.position)
         }
     }
 
+    fn serialize_color_stops<W>(stops: &[ColorStop], dest: &mut W) -> fmt::Result where W: fmt::Write {
+        for (i, stop) in stops.iter().enumerate() {
+            if i != 0 {
+                try!(dest.write_str(", "));
+            }
+            try!(stop.to_css(dest));
+        }
+        Ok(())
+    }
+
+    /// Places the stop `fraction` of the way from `start` to `end`, in calc() if they're in
+    /// different units.
+    fn position_between(start: LengthOrPercentage, end: LengthOrPercentage, fraction: CSSFloat)
+                        -> LengthOrPercentage {
+        match (start, end) {
+            (LengthOrPercentage::Length(start), LengthOrPercentage::Length(end)) => {
+                LengthOrPercentage::Length(start + (end - start).scale_by(fraction))
+            }
+            (LengthOrPercentage::Percentage(start), LengthOrPercentage::Percentage(end)) => {
+                LengthOrPercentage::Percentage(start + (end - start) * fraction)
+            }
+            (start, end) => {
+                let start = CalcLengthOrPercentage::from(start);
+                let end = CalcLengthOrPercentage::from(end);
+                LengthOrPercentage::Calc(CalcLengthOrPercentage {
+                    length: Some(start.length().scale_by(1. - fraction) + end.length().scale_by(fraction)),
+                    percentage: Some(start.percentage() * (1. - fraction) + end.percentage() * fraction),
+                })
+            }
+        }
+    }
+
+    /// Computes the color stops of a gradient, filling in the positions of the stops that
+    /// don't have one per CSS-IMAGES § 3.4.3: the first stop goes at 0% and the last at 100%,
+    /// a stop that would come before the one preceding it is moved up to it, and a run of
+    /// stops without positions is spread evenly between the stops on either side of it.
+    ///
+    /// A stop can only be moved up when the two positions are in the same unit, since which
+    /// one comes first otherwise depends on the size of the gradient.
+    pub fn compute_color_stops<Cx: TContext>(stops: &[specified::ColorStop], context: &Cx) -> Vec<ColorStop> {
+        let mut computed: Vec<ColorStop> = stops.iter().map(|stop| {
+            ColorStop {
+                color: stop.color.to_computed_value(context),
+                position: stop.position.map_or(LengthOrPercentage::Percentage(0.), |position| {
+                    position.to_computed_value(context)
+                }),
+                implied_position: stop.position.is_none(),
+            }
+        }).collect();
+        let last = match computed.len() {
+            0 => return computed,
+            len => len - 1,
+        };
+        if computed[last].implied_position {
+            computed[last].position = LengthOrPercentage::Percentage(1.);
+        }
+
+        let mut largest = computed[0].position;
+        for (index, stop) in computed.iter_mut().enumerate().skip(1) {
+            if stop.implied_position && index != last {
+                continue
+            }
+            match (largest, stop.position) {
+                (LengthOrPercentage::Length(largest), LengthOrPercentage::Length(position)) if position < largest => {
+                    stop.position = LengthOrPercentage::Length(largest);
+                }
+                (LengthOrPercentage::Percentage(largest), LengthOrPercentage::Percentage(position))
+                        if position < largest => {
+                    stop.position = LengthOrPercentage::Percentage(largest);
+                }
+                _ => {}
+            }
+            largest = stop.position;
+        }
+
+        let mut run_start = 0;
+        for index in 1..computed.len() {
+            if computed[index].implied_position && index != last {
+                continue
+            }
+            let (start, end) = (computed[run_start].position, computed[index].position);
+            let steps = (index - run_start) as CSSFloat;
+            for (step, stop) in computed[run_start + 1..index].iter_mut().enumerate() {
+                stop.position = position_between(start, end, (step + 1) as CSSFloat / steps);
+            }
+            run_start = index;
+        }
+        computed
+    }
+
     impl ToComputedValue for specified::LinearGradient {
         type ComputedValue = LinearGradient;
 
         #[inline]
         fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> LinearGradient {
-            let specified::LinearGradient {
-                angle_or_corner,
-                ref stops
-            } = *self;
             LinearGradient {
-                angle_or_corner: angle_or_corner,
-                stops: stops.iter().map(|stop| {
-                    ColorStop {
-                        color: stop.color.parsed,
-                        position: match stop.position {
-                            None => None,
-                            Some(value) => Some(value.to_computed_value(context)),
-                        },
-                    }
-                }).collect()
+                angle_or_corner: self.angle_or_corner,
+                stops: compute_color_stops(&self.stops, context),
+            }
+        }
+    }
+
+    impl ToComputedValue for specified::GradientSize {
+        type ComputedValue = GradientSize;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> GradientSize {
+            match *self {
+                specified::GradientSize::Keyword(keyword) => GradientSize::Keyword(keyword),
+                specified::GradientSize::Explicit(width, height) => {
+                    GradientSize::Explicit(width.to_computed_value(context), height.to_computed_value(context))
+                }
+            }
+        }
+    }
+
+    impl ToComputedValue for specified::RadialGradient {
+        type ComputedValue = RadialGradient;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> RadialGradient {
+            RadialGradient {
+                shape: self.shape,
+                size: self.size.to_computed_value(context),
+                position: self.position.to_computed_value(context),
+                stops: compute_color_stops(&self.stops, context),
+            }
+        }
+    }
+
+    /// Computed values for a position, as in `background-position`.
+    #[derive(PartialEq, Copy, Clone, Debug, HeapSizeOf)]
+    pub struct Position {
+        pub horizontal: LengthOrPercentage,
+        pub vertical: LengthOrPercentage,
+    }
+
+    impl Position {
+        #[inline]
+        pub fn center() -> Position {
+            Position {
+                horizontal: LengthOrPercentage::Percentage(0.5),
+                vertical: LengthOrPercentage::Percentage(0.5),
+            }
+        }
+    }
+
+    impl ::cssparser::ToCss for Position {
+        fn to_css<W>(&self, dest: &mut W) -> fmt::Result where W: fmt::Write {
+            try!(self.horizontal.to_css(dest));
+            try!(dest.write_str(" "));
+            try!(self.vertical.to_css(dest));
+            Ok(())
+        }
+    }
+
+    impl ToComputedValue for specified::Position {
+        type ComputedValue = Position;
+
+        #[inline]
+        fn to_computed_value<Cx: TContext>(&self, context: &Cx) -> Position {
+            Position {
+                horizontal: self.horizontal.to_computed_value(context),
+                vertical: self.vertical.to_computed_value(context),
             }
         }
     }
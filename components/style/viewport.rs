@@ -10,6 +10,7 @@ use euclid::size::{Size2D, TypedSize2D};
 use parser::{ParserContext, log_css_error};
 use properties::{ComputedValues, ServoComputedValues};
 use std::ascii::AsciiExt;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::{Entry, HashMap};
 use std::fmt;
 use std::intrinsics;
@@ -228,7 +229,7 @@ impl<'a, 'b> DeclarationParser for ViewportRuleParser<'a, 'b> {
     }
 }
 
-#[derive(Debug, HeapSizeOf, PartialEq)]
+#[derive(Clone, Debug, HeapSizeOf, PartialEq)]
 pub struct ViewportRule {
     pub declarations: Vec<ViewportDescriptorDeclaration>
 }
@@ -596,6 +597,9 @@ impl MaybeNew for ViewportConstraints {
             viewport_size: initial_viewport,
             inherited_style: ServoComputedValues::initial_values(),
             style: ServoComputedValues::initial_values().clone(),
+            font_metrics: Cell::new(None),
+            system_colors: RefCell::new(HashMap::new()),
+            font_size_keyword: None,
         };
 
         // DEVICE-ADAPT § 9.3 Resolving 'extend-to-zoom'
@@ -122,10 +122,16 @@ pub struct SimpleHashCache<K, V> {
 impl<K: Clone + Eq + Hash, V: Clone> SimpleHashCache<K, V> {
     pub fn new(cache_size: usize) -> SimpleHashCache<K, V> {
         let mut r = rand::thread_rng();
+        SimpleHashCache::with_keys(cache_size, r.gen(), r.gen())
+    }
+
+    /// A cache that hashes with the given keys instead of random ones, so that which entries
+    /// evict each other is the same from run to run.
+    pub fn with_keys(cache_size: usize, k0: u64, k1: u64) -> SimpleHashCache<K, V> {
         SimpleHashCache {
             entries: vec![None; cache_size],
-            k0: r.gen(),
-            k1: r.gen(),
+            k0: k0,
+            k1: k1,
         }
     }
 
@@ -40,6 +40,8 @@ enum WorkerMsg<QueueData: 'static, WorkData: 'static + Send> {
     Stop,
     /// Tells the worker to measure the heap size of its TLS using the supplied function.
     HeapSizeOfTLS(fn() -> usize),
+    /// Tells the worker to run the supplied function, for instance to gather what it left in its TLS.
+    RunOnWorker(fn()),
     /// Tells the worker thread to terminate.
     Exit,
 }
@@ -50,6 +52,7 @@ unsafe impl<QueueData: 'static, WorkData: 'static + Send> Send for WorkerMsg<Que
 enum SupervisorMsg<QueueData: 'static, WorkData: 'static + Send> {
     Finished,
     HeapSizeOfTLS(usize),
+    RanOnWorker,
     ReturnDeque(usize, Worker<WorkUnit<QueueData, WorkData>>),
 }
 
@@ -125,6 +128,11 @@ impl<QueueData: Sync, WorkData: Send> WorkerThread<QueueData, WorkData> {
                     self.chan.send(SupervisorMsg::HeapSizeOfTLS(f())).unwrap();
                     continue;
                 }
+                WorkerMsg::RunOnWorker(f) => {
+                    f();
+                    self.chan.send(SupervisorMsg::RanOnWorker).unwrap();
+                    continue;
+                }
             };
 
             let mut back_off_sleep = 0 as u32;
@@ -340,6 +348,7 @@ impl<QueueData: Sync, WorkData: Send> WorkQueue<QueueData, WorkData> {
             match self.port.recv().unwrap() {
                 SupervisorMsg::ReturnDeque(index, deque) => self.workers[index].deque = Some(deque),
                 SupervisorMsg::HeapSizeOfTLS(_) => panic!("unexpected HeapSizeOfTLS message"),
+                SupervisorMsg::RanOnWorker => panic!("unexpected RanOnWorker message"),
                 SupervisorMsg::Finished => panic!("unexpected finished message!"),
             }
         }
@@ -365,6 +374,20 @@ impl<QueueData: Sync, WorkData: Send> WorkQueue<QueueData, WorkData> {
         sizes
     }
 
+    /// Synchronously runs `f` once on each worker thread.
+    pub fn run_on_each_worker(&self, f: fn()) {
+        for worker in &self.workers {
+            worker.chan.send(WorkerMsg::RunOnWorker(f)).unwrap()
+        }
+
+        for _ in 0..self.workers.len() {
+            match self.port.recv().unwrap() {
+                SupervisorMsg::RanOnWorker => {}
+                _ => panic!("unexpected message!"),
+            }
+        }
+    }
+
     pub fn shutdown(&mut self) {
         for worker in &self.workers {
             worker.chan.send(WorkerMsg::Exit).unwrap()
@@ -0,0 +1,331 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![allow(unsafe_code)]
+
+use bindings::{Gecko_AddRefAtom, Gecko_AtomEqualsUTF8, Gecko_AtomEqualsUTF8IgnoreCase, Gecko_AtomIsStatic};
+use bindings::{Gecko_Atomize, Gecko_GetAtomAsUTF16, Gecko_ReleaseAtom};
+use bindings::nsIAtom;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::slice;
+use std::sync::RwLock;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+use string_cache::{Atom, Namespace};
+use util::cache::SimpleHashCache;
+
+/// The bit set in a GeckoAtom's pointer when the atom is static. Atoms are aligned to more
+/// than a byte, so the bit is free.
+const STATIC_ATOM_TAG: usize = 1;
+
+/// A reference to a Gecko atom that doesn't keep it alive, to compare and hash atoms by
+/// address while something else holds them.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WeakAtom(*mut nsIAtom);
+
+// Gecko's atoms never change once made, so the address is all there is to share.
+unsafe impl Send for WeakAtom {}
+unsafe impl Sync for WeakAtom {}
+
+impl WeakAtom {
+    /// |atom| mustn't be null, and has to outlive the WeakAtom.
+    pub unsafe fn new(atom: *mut nsIAtom) -> WeakAtom {
+        debug_assert!(!atom.is_null());
+        WeakAtom(atom)
+    }
+
+    pub fn as_ptr(&self) -> *mut nsIAtom {
+        self.0
+    }
+
+    pub fn eq_str(&self, string: &str) -> bool {
+        unsafe { Gecko_AtomEqualsUTF8(self.0, string.as_ptr() as *const _, string.len() as u32) }
+    }
+
+    pub fn eq_str_ignore_ascii_case(&self, string: &str) -> bool {
+        unsafe { Gecko_AtomEqualsUTF8IgnoreCase(self.0, string.as_ptr() as *const _, string.len() as u32) }
+    }
+
+    /// Holds a reference to the atom, so that it outlives the borrow.
+    pub fn upgrade(&self) -> GeckoAtom {
+        unsafe { GeckoAtom::from_raw(self.0) }
+    }
+}
+
+impl fmt::Display for WeakAtom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&unsafe { string_from_gecko_atom(self.0) })
+    }
+}
+
+impl fmt::Debug for WeakAtom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakAtom({:?}, {:p})", self.to_string(), self.0)
+    }
+}
+
+/// A Gecko atom that holds a reference to it, unless it's static, in which case it doesn't
+/// need one and Gecko is never called to take or drop it. Atoms are compared and hashed by
+/// address, which is what makes two of them the same.
+pub struct GeckoAtom(usize);
+
+// Gecko's atoms are refcounted atomically, and never change once made.
+unsafe impl Send for GeckoAtom {}
+unsafe impl Sync for GeckoAtom {}
+
+impl GeckoAtom {
+    /// Takes a new reference to the (non-null) |atom|.
+    pub unsafe fn from_raw(atom: *mut nsIAtom) -> GeckoAtom {
+        let atom = GeckoAtom::from_addrefed(atom);
+        if !atom.is_static() {
+            Gecko_AddRefAtom(atom.as_ptr());
+        }
+        atom
+    }
+
+    /// Takes over a reference to the (non-null) |atom| that the caller owned, like the one
+    /// Gecko_Atomize returns.
+    pub unsafe fn from_addrefed(atom: *mut nsIAtom) -> GeckoAtom {
+        debug_assert!(!atom.is_null());
+        debug_assert!(atom as usize & STATIC_ATOM_TAG == 0);
+        if Gecko_AtomIsStatic(atom) {
+            GeckoAtom(atom as usize | STATIC_ATOM_TAG)
+        } else {
+            GeckoAtom(atom as usize)
+        }
+    }
+
+    pub fn from_str(string: &str) -> GeckoAtom {
+        unsafe { GeckoAtom::from_addrefed(Gecko_Atomize(string.as_ptr() as *const _, string.len() as u32)) }
+    }
+
+    pub fn as_ptr(&self) -> *mut nsIAtom {
+        (self.0 & !STATIC_ATOM_TAG) as *mut nsIAtom
+    }
+
+    /// Gives up the reference to the atom, for something on the Gecko side that drops it
+    /// itself, like an nsCOMPtr.
+    pub fn into_raw(self) -> *mut nsIAtom {
+        let atom = self.as_ptr();
+        mem::forget(self);
+        atom
+    }
+
+    pub fn as_weak(&self) -> WeakAtom {
+        WeakAtom(self.as_ptr())
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.0 & STATIC_ATOM_TAG != 0
+    }
+}
+
+impl Clone for GeckoAtom {
+    fn clone(&self) -> GeckoAtom {
+        if !self.is_static() {
+            unsafe { Gecko_AddRefAtom(self.as_ptr()) };
+        }
+        GeckoAtom(self.0)
+    }
+}
+
+impl Drop for GeckoAtom {
+    fn drop(&mut self) {
+        if !self.is_static() {
+            unsafe { Gecko_ReleaseAtom(self.as_ptr()) };
+        }
+    }
+}
+
+impl PartialEq for GeckoAtom {
+    fn eq(&self, other: &GeckoAtom) -> bool {
+        self.as_ptr() == other.as_ptr()
+    }
+}
+
+impl Eq for GeckoAtom {}
+
+impl Hash for GeckoAtom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_weak().hash(state)
+    }
+}
+
+impl fmt::Display for GeckoAtom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_weak().fmt(f)
+    }
+}
+
+impl fmt::Debug for GeckoAtom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GeckoAtom({:?}, {:p})", self.to_string(), self.as_ptr())
+    }
+}
+
+impl PartialEq<GeckoAtom> for WeakAtom {
+    fn eq(&self, other: &GeckoAtom) -> bool {
+        *self == other.as_weak()
+    }
+}
+
+/// How many Gecko atoms each thread remembers the string_cache equivalent of.
+const THREAD_ATOM_CACHE_SIZE: usize = 1024;
+
+/// How many element names, namespaces and string_cache atoms each thread remembers where
+/// the global maps keep them.
+const THREAD_NAME_CACHE_SIZE: usize = 256;
+
+/// What a thread looked up lately, so that most lookups take no lock. The caches are
+/// direct-mapped, so they never grow past their size, and the one for atoms holds a
+/// reference to each Gecko atom in it, so that its address can't be reused for a different
+/// one while the entry is there.
+struct ThreadAtomCaches {
+    /// The value of CACHE_GENERATION the caches were filled in.
+    generation: usize,
+    atoms: SimpleHashCache<GeckoAtom, Atom>,
+    local_names: SimpleHashCache<WeakAtom, &'static Atom>,
+    namespaces: SimpleHashCache<WeakAtom, &'static Namespace>,
+    gecko_atoms: SimpleHashCache<Atom, *mut nsIAtom>,
+}
+
+impl ThreadAtomCaches {
+    fn new() -> ThreadAtomCaches {
+        ThreadAtomCaches {
+            generation: CACHE_GENERATION.load(Ordering::Relaxed),
+            atoms: SimpleHashCache::new(THREAD_ATOM_CACHE_SIZE),
+            local_names: SimpleHashCache::new(THREAD_NAME_CACHE_SIZE),
+            namespaces: SimpleHashCache::new(THREAD_NAME_CACHE_SIZE),
+            gecko_atoms: SimpleHashCache::new(THREAD_NAME_CACHE_SIZE),
+        }
+    }
+
+    fn evict_all(&mut self) {
+        self.atoms.evict_all();
+        self.local_names.evict_all();
+        self.namespaces.evict_all();
+        self.gecko_atoms.evict_all();
+    }
+}
+
+thread_local!(static THREAD_CACHES: RefCell<ThreadAtomCaches> = RefCell::new(ThreadAtomCaches::new()));
+
+/// Bumped by drop_atom_caches, so that every thread empties its caches the next time it
+/// uses them rather than handing out what the global maps freed.
+static CACHE_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn with_thread_caches<F, R>(callback: F) -> R where F: FnOnce(&mut ThreadAtomCaches) -> R {
+    THREAD_CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        let generation = CACHE_GENERATION.load(Ordering::Relaxed);
+        if caches.generation != generation {
+            caches.evict_all();
+            caches.generation = generation;
+        }
+        callback(&mut caches)
+    })
+}
+
+// The element names and namespaces we've handed out, and the Gecko atoms for the
+// string_cache atoms we were asked about. Both are leaked until drop_atom_caches, which is
+// what lets callers keep the names in node data and hand the Gecko atoms to Gecko without a
+// reference. That's fine since there are only so many of either: element names are mostly
+// the ones the HTML, SVG and MathML specs define, and we only make Gecko atoms for the names
+// and values the rules of the loaded sheets use. The ids, classes and attribute values of
+// the DOM, which have no such bound, only ever go in the thread caches.
+//
+// We hold a reference to each Gecko atom in the maps so that its address can't be reused
+// for a different one, and they're keyed on the address so that looking an atom up doesn't
+// need a reference.
+lazy_static! {
+    static ref LOCAL_NAMES: RwLock<HashMap<WeakAtom, (GeckoAtom, &'static Atom)>> = RwLock::new(HashMap::new());
+    static ref NAMESPACES: RwLock<HashMap<WeakAtom, (GeckoAtom, &'static Namespace)>> =
+        RwLock::new(HashMap::new());
+    static ref GECKO_ATOMS: RwLock<HashMap<Atom, GeckoAtom>> = RwLock::new(HashMap::new());
+}
+
+unsafe fn string_from_gecko_atom(atom: *mut nsIAtom) -> String {
+    let mut length: u32 = 0;
+    let chars = Gecko_GetAtomAsUTF16(atom, &mut length);
+    String::from_utf16_lossy(slice::from_raw_parts(chars, length as usize))
+}
+
+/// Returns the string_cache atom for a (non-null) Gecko atom.
+pub unsafe fn atom_from_gecko(atom: *mut nsIAtom) -> Atom {
+    let weak = WeakAtom::new(atom);
+    if let Some(existing) = with_thread_caches(|caches| caches.atoms.find(&weak)) {
+        debug_assert!(weak.eq_str(&existing), "Gecko atom no longer matches the string_cache atom we made for it");
+        return existing;
+    }
+    let converted = Atom::from(&*string_from_gecko_atom(atom));
+    with_thread_caches(|caches| caches.atoms.insert(GeckoAtom::from_raw(atom), converted.clone()));
+    converted
+}
+
+/// Returns the string_cache atom for a (non-null) Gecko atom that's the local name of an
+/// element, for as long as the node data that keeps it needs it.
+pub unsafe fn local_name_from_gecko(atom: *mut nsIAtom) -> &'static Atom {
+    let weak = WeakAtom::new(atom);
+    if let Some(existing) = with_thread_caches(|caches| caches.local_names.find(&weak)) {
+        return existing;
+    }
+    let name = LOCAL_NAMES.read().unwrap().get(&weak).map(|&(_, existing)| existing);
+    let name = name.unwrap_or_else(|| {
+        LOCAL_NAMES.write().unwrap().entry(weak).or_insert_with(|| {
+            (GeckoAtom::from_raw(atom), &*Box::into_raw(Box::new(Atom::from(&*string_from_gecko_atom(atom)))))
+        }).1
+    });
+    with_thread_caches(|caches| caches.local_names.insert(weak, name));
+    name
+}
+
+/// Returns the namespace named by a (non-null) Gecko atom.
+pub unsafe fn namespace_from_gecko(atom: *mut nsIAtom) -> &'static Namespace {
+    let weak = WeakAtom::new(atom);
+    if let Some(existing) = with_thread_caches(|caches| caches.namespaces.find(&weak)) {
+        return existing;
+    }
+    let namespace = NAMESPACES.read().unwrap().get(&weak).map(|&(_, existing)| existing);
+    let namespace = namespace.unwrap_or_else(|| {
+        NAMESPACES.write().unwrap().entry(weak).or_insert_with(|| {
+            (GeckoAtom::from_raw(atom), &*Box::into_raw(Box::new(Namespace(atom_from_gecko(atom)))))
+        }).1
+    });
+    with_thread_caches(|caches| caches.namespaces.insert(weak, namespace));
+    namespace
+}
+
+/// Returns the Gecko atom for a string_cache atom, atomizing it on the Gecko side the
+/// first time it's seen. The Gecko atom is kept alive until drop_atom_caches, so the
+/// returned pointer doesn't dangle before then.
+pub fn atom_to_gecko(atom: &Atom) -> *mut nsIAtom {
+    if let Some(existing) = with_thread_caches(|caches| caches.gecko_atoms.find(atom)) {
+        return existing;
+    }
+    let existing = GECKO_ATOMS.read().unwrap().get(atom).map(|existing| existing.as_ptr());
+    let gecko_atom = existing.unwrap_or_else(|| {
+        GECKO_ATOMS.write().unwrap().entry(atom.clone()).or_insert_with(|| GeckoAtom::from_str(atom)).as_ptr()
+    });
+    with_thread_caches(|caches| caches.gecko_atoms.insert(atom.clone(), gecko_atom));
+    gecko_atom
+}
+
+/// Forgets every atom handed out so far, dropping our references to the Gecko atoms and
+/// freeing the string_cache ones. Only Servo_Shutdown may call this, since nothing can be
+/// borrowing them by then. The other threads' caches are emptied the next time they're
+/// used, or when the thread exits.
+pub unsafe fn drop_atom_caches() {
+    CACHE_GENERATION.fetch_add(1, Ordering::Relaxed);
+    with_thread_caches(|_| {});
+    for (_, (_, atom)) in LOCAL_NAMES.write().unwrap().drain() {
+        let _ = Box::from_raw(atom as *const Atom as *mut Atom);
+    }
+    for (_, (_, namespace)) in NAMESPACES.write().unwrap().drain() {
+        let _ = Box::from_raw(namespace as *const Namespace as *mut Namespace);
+    }
+    GECKO_ATOMS.write().unwrap().clear();
+}
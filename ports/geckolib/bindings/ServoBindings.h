@@ -0,0 +1,1271 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Servo's copy of the declarations in Gecko's layout/style/ServoBindings.h,
+// which tests/check_bindings.rs checks bindings/mod.rs against. Changes to the
+// bindings go in both, and then in Gecko's header.
+
+#ifndef mozilla_ServoBindings_h
+#define mozilla_ServoBindings_h
+
+#include <stddef.h>
+#include <stdint.h>
+
+struct nsIAtom;
+struct nsINode;
+struct Element;
+struct nsIDocument;
+struct ServoNodeData;
+struct ServoComputedValues;
+struct RawServoStyleSheet;
+struct RawServoStyleSet;
+struct RawServoPerDocumentData;
+struct RawServoDeclarationBlock;
+struct RawServoMediaList;
+struct RawServoImportRule;
+struct RawServoAnimationValue;
+struct RawServoCssRules;
+struct RawServoStyleRule;
+struct RawServoCounterStyleRule;
+struct RawServoAttributeSnapshot;
+struct RawServoSelectorList;
+struct RawServoSharedStyleSheet;
+struct nsString;
+struct ServoElementSnapshot;
+struct nsStyleFont;
+struct RawGeckoKeyframeList;
+struct RawGeckoFontFaceRuleList;
+struct RawGeckoFontFaceRule;
+struct RawGeckoContentList;
+struct RawGeckoPropertyList;
+struct RawGeckoRuleList;
+struct RawGeckoTransformList;
+struct RawGeckoShadowArray;
+struct RawGeckoFontFeatureArray;
+struct RawGeckoContentItemList;
+struct RawGeckoGradientStopArray;
+struct RawGeckoCursorImageArray;
+struct RawGeckoCounterItemArray;
+struct RawGeckoURLExtraData;
+typedef nsINode RawGeckoNode;
+typedef Element RawGeckoElement;
+typedef nsIDocument RawGeckoDocument;
+typedef uint32_t nsChangeHint;
+enum nsCSSProperty : int32_t;
+typedef size_t (*MallocSizeOfFn)(const void*);
+typedef void (*ServoReleaseFn)(void*);
+struct ServoComputedValuesStrong { ServoComputedValues* mPtr; };
+struct RawServoStyleSheetStrong { RawServoStyleSheet* mPtr; };
+struct RawServoDeclarationBlockStrong { RawServoDeclarationBlock* mPtr; };
+struct RawServoMediaListStrong { RawServoMediaList* mPtr; };
+struct RawServoImportRuleStrong { RawServoImportRule* mPtr; };
+struct RawServoAnimationValueStrong { RawServoAnimationValue* mPtr; };
+struct RawServoCssRulesStrong { RawServoCssRules* mPtr; };
+struct RawServoStyleRuleStrong { RawServoStyleRule* mPtr; };
+struct RawServoCounterStyleRuleStrong { RawServoCounterStyleRule* mPtr; };
+struct RawServoSharedStyleSheetStrong { RawServoSharedStyleSheet* mPtr; };
+typedef uint32_t nsresult;
+// Keep these in sync with ServoRestyleHint in ports/geckolib/wrapper.rs.
+enum ServoRestyleHint : uint8_t {
+  SERVO_RESTYLE_SELF = 1 << 0,
+  SERVO_RESTYLE_DESCENDANTS = 1 << 1,
+  SERVO_RESTYLE_LATER_SIBLINGS = 1 << 2,
+};
+enum class StyleSheetOrigin : uint32_t {
+  UserAgent = 0,
+  User = 1,
+  Author = 2,
+};
+enum class ServoStyleDisplay : uint8_t {
+  Inline = 0,
+  Block = 1,
+  InlineBlock = 2,
+  Table = 3,
+  InlineTable = 4,
+  TableRowGroup = 5,
+  TableHeaderGroup = 6,
+  TableFooterGroup = 7,
+  TableRow = 8,
+  TableColumnGroup = 9,
+  TableColumn = 10,
+  TableCell = 11,
+  TableCaption = 12,
+  ListItem = 13,
+  Flex = 14,
+  None = 15,
+  InlineFlex = 16,
+  Contents = 17,
+};
+enum class ServoStylePosition : uint8_t {
+  Static = 0,
+  Absolute = 1,
+  Relative = 2,
+  Fixed = 3,
+};
+enum class ServoStyleFloat : uint8_t {
+  None = 0,
+  Left = 1,
+  Right = 2,
+};
+enum class ServoStyleClear : uint8_t {
+  None = 0,
+  Left = 1,
+  Right = 2,
+  Both = 3,
+};
+enum class ServoStyleIsolation : uint8_t {
+  Auto = 0,
+  Isolate = 1,
+};
+enum class ServoLengthUnit : uint8_t {
+  Length = 0,
+  Percent = 1,
+  Auto = 2,
+  Calc = 3,
+  None = 4,
+  Keyword = 5,
+};
+enum class ServoTimingFunctionType : uint8_t {
+  CubicBezier = 0,
+  Steps = 1,
+  Linear = 2,
+};
+enum class ServoStepPosition : uint8_t {
+  Start = 0,
+  End = 1,
+  JumpNone = 2,
+  JumpBoth = 3,
+};
+enum class ServoPageSizeKind : uint8_t {
+  Auto = 0,
+  Portrait = 1,
+  Landscape = 2,
+  Explicit = 3,
+};
+enum class ServoTextSpacingKind : uint8_t {
+  Normal = 0,
+  Length = 1,
+};
+enum class ServoTabSizeKind : uint8_t {
+  Number = 0,
+  Length = 1,
+};
+enum class ServoStyleStructId : uint8_t {
+  Margin = 0,
+  Padding = 1,
+  Border = 2,
+  Outline = 3,
+  PositionOffsets = 4,
+  Box = 5,
+  InheritedBox = 6,
+  List = 7,
+  Counters = 8,
+  Background = 9,
+  Color = 10,
+  Font = 11,
+  InheritedText = 12,
+  Text = 13,
+  Table = 14,
+  InheritedTable = 15,
+  Pointing = 16,
+  Column = 17,
+  Effects = 18,
+  Animation = 19,
+  Flex = 20,
+};
+enum class ServoTransformFunction : uint8_t {
+  Matrix = 0,
+  Skew = 1,
+  Translate = 2,
+  Scale = 3,
+  Rotate = 4,
+  Perspective = 5,
+};
+// The None unit is only for a max-width or max-height of none, and the Keyword unit is only for
+// the intrinsic size keywords of the sizes, with the NS_STYLE_WIDTH_* keyword in mLength.
+struct ServoLengthOrPercentageOrAuto {
+  ServoLengthUnit mUnit;
+  int32_t mLength;
+  float mPercent;
+  bool mHasPercent;
+};
+// The keywords are the NS_STYLE_OVERFLOW_*, NS_STYLE_RESIZE_* and NS_STYLE_SCROLL_SNAP_TYPE_*
+// values, and a `none` snap point interval has the Auto unit.
+struct ServoScrollFrameInfo {
+  uint8_t mOverflowX;
+  uint8_t mOverflowY;
+  uint8_t mResize;
+  uint8_t mScrollSnapTypeX;
+  uint8_t mScrollSnapTypeY;
+  ServoLengthOrPercentageOrAuto mScrollSnapPointsX;
+  ServoLengthOrPercentageOrAuto mScrollSnapPointsY;
+  ServoLengthOrPercentageOrAuto mScrollSnapDestinationX;
+  ServoLengthOrPercentageOrAuto mScrollSnapDestinationY;
+  uint32_t mScrollSnapCoordinateCount;
+};
+// The keywords are the NS_STYLE_WHITESPACE_*, NS_STYLE_TEXT_TRANSFORM_*, NS_STYLE_WORDBREAK_*,
+// NS_STYLE_WORDWRAP_* and NS_STYLE_TEXT_ALIGN_* values, and the lengths are in app units. A
+// spacing is only meaningful with the Length kind, and tab-size is either a number of spaces or a
+// length. The layout, which build.rs has the Rust side checked against:
+//
+//    0  mWhiteSpace          uint8_t
+//    1  mTextTransform       uint8_t
+//    2  mWordBreak           uint8_t
+//    3  mOverflowWrap        uint8_t
+//    4  mTextAlign           uint8_t
+//    5  mLetterSpacingKind   ServoTextSpacingKind
+//    6  mWordSpacingKind     ServoTextSpacingKind
+//    7  mTabSizeKind         ServoTabSizeKind
+//    8  mLetterSpacing       int32_t
+//   12  mWordSpacing         int32_t
+//   16  mTabSizeLength       int32_t
+//   20  mTabSizeNumber       float
+//   24  (size, aligned to 4)
+struct ServoTextStyleFFI {
+  uint8_t mWhiteSpace;
+  uint8_t mTextTransform;
+  uint8_t mWordBreak;
+  uint8_t mOverflowWrap;
+  uint8_t mTextAlign;
+  ServoTextSpacingKind mLetterSpacingKind;
+  ServoTextSpacingKind mWordSpacingKind;
+  ServoTabSizeKind mTabSizeKind;
+  int32_t mLetterSpacing;
+  int32_t mWordSpacing;
+  int32_t mTabSizeLength;
+  float mTabSizeNumber;
+};
+struct ServoTimingFunction {
+  ServoTimingFunctionType mType;
+  float mX1;
+  float mY1;
+  float mX2;
+  float mY2;
+  uint32_t mSteps;
+  ServoStepPosition mStepPosition;
+};
+struct ServoPageSize {
+  ServoPageSizeKind mKind;
+  int32_t mWidth;
+  int32_t mHeight;
+};
+struct ServoTraversalStatistics {
+  uint32_t mElementsTraversed;
+  uint32_t mElementsStyled;
+  uint32_t mStylesShared;
+  uint32_t mSelectorsMatched;
+  uint32_t mDeclarationsCascaded;
+  uint32_t mNodeQueries;
+  uint32_t mNodeFFICalls;
+  double mTraversalTimeMs;
+};
+struct ServoTransformOperation {
+  ServoTransformFunction mFunction;
+  float mValues[16];
+  float mPercentages[2];
+};
+enum class ServoContentKind : uint8_t {
+  Normal = 0,
+  None = 1,
+  Items = 2,
+};
+enum class ServoContentItemType : uint8_t {
+  String = 0,
+  Image = 1,
+  Attr = 2,
+  Counter = 3,
+  Counters = 4,
+  OpenQuote = 5,
+  CloseQuote = 6,
+  NoOpenQuote = 7,
+  NoCloseQuote = 8,
+};
+// The strings point into the computed values, and only live as long as the call that
+// hands the item over. mCounterName is the atom of the counter a Counter or Counters item
+// refers to, the same one Servo_GetComputedCounterData hands out for it, and null for the
+// other items.
+struct ServoContentItem {
+  ServoContentItemType mType;
+  const uint8_t* mString;
+  uint32_t mStringLength;
+  const uint8_t* mSeparator;
+  uint32_t mSeparatorLength;
+  const uint8_t* mCounterStyle;
+  uint32_t mCounterStyleLength;
+  nsIAtom* mCounterName;
+};
+// Laid out like nsCSSShadowItem, with the lengths in app units. mColor is only meaningful
+// when mHasColor is set; otherwise the shadow takes the element's color.
+struct ServoShadowItem {
+  int32_t mXOffset;
+  int32_t mYOffset;
+  int32_t mRadius;
+  int32_t mSpread;
+  uint32_t mColor;  // An nscolor.
+  bool mHasColor;
+  bool mInset;
+};
+// Laid out like gfxFontFeature. mTag has the four characters of the OpenType tag packed
+// into it big-endian, as HB_TAG does.
+struct ServoFontFeature {
+  uint32_t mTag;
+  uint32_t mValue;
+};
+enum class ServoBorderImageKind : uint8_t {
+  None = 0,
+  Url = 1,
+  Gradient = 2,
+};
+enum class ServoBorderImageUnit : uint8_t {
+  Auto = 0,
+  Number = 1,
+  Percent = 2,
+  Coord = 3,
+  Calc = 4,
+};
+enum class ServoBorderImageRepeat : uint8_t {
+  Stretch = 0,
+  Repeat = 1,
+  Round = 2,
+  Space = 3,
+};
+// A side of border-image-slice, -width or -outset. mFactor holds a Number, or a Percent with
+// 1.0 for 100%, and mCoord a Coord in app units. A Calc has both.
+struct ServoBorderImageSide {
+  ServoBorderImageUnit mUnit;
+  float mFactor;
+  int32_t mCoord;
+};
+// What the borders are drawn from. The sides go top, right, bottom, left. The widths are in
+// app units, and already zero for the sides whose style is none or hidden. The styles are
+// NS_STYLE_BORDER_STYLE_* values, and each color is only meaningful when its mHasColors is
+// set; otherwise the side takes the element's color. If mBoxShadow points at an array when
+// Servo_GetComputedBorderInfo is called, it's filled with the box-shadow, and mHasBoxShadow
+// says whether there was one. The layout, which build.rs has the Rust side checked against:
+//
+//    0  mBoxShadow           RawGeckoShadowArray*
+//    8  mWidths              int32_t[4]
+//   24  mColors              uint32_t[4]
+//   40  mStyles              uint8_t[4]
+//   44  mHasColors           bool[4]
+//   48  mImageKind           ServoBorderImageKind
+//   49  mImageFill           bool
+//   50  mImageRepeatH        ServoBorderImageRepeat
+//   51  mImageRepeatV        ServoBorderImageRepeat
+//   52  mHasBoxShadow        bool
+//   56  mImageSlice          ServoBorderImageSide[4]
+//  104  mImageWidth          ServoBorderImageSide[4]
+//  152  mImageOutset         ServoBorderImageSide[4]
+//  200  (size, aligned to 8)
+struct ServoBorderInfo {
+  RawGeckoShadowArray* mBoxShadow;
+  int32_t mWidths[4];
+  uint32_t mColors[4];  // nscolors.
+  uint8_t mStyles[4];
+  bool mHasColors[4];
+  ServoBorderImageKind mImageKind;
+  bool mImageFill;
+  ServoBorderImageRepeat mImageRepeatH;
+  ServoBorderImageRepeat mImageRepeatV;
+  bool mHasBoxShadow;
+  ServoBorderImageSide mImageSlice[4];
+  ServoBorderImageSide mImageWidth[4];
+  ServoBorderImageSide mImageOutset[4];
+};
+enum class ServoImageKind : uint8_t {
+  None = 0,
+  Url = 1,
+  LinearGradient = 2,
+  RadialGradient = 3,
+};
+// A color stop, whose position is always filled in. mColor is only meaningful when
+// mHasColor is set; otherwise the stop takes the element's color.
+struct ServoGradientStop {
+  ServoLengthOrPercentageOrAuto mPosition;
+  uint32_t mColor;  // An nscolor.
+  bool mHasColor;
+};
+// An image of a background layer. If mURL points at a string, a Url's url is written to it, and
+// if mStops points at an array, it's filled with a gradient's stops. A gradient has its mShape
+// and mSize as NS_STYLE_GRADIENT_SHAPE_* and NS_STYLE_GRADIENT_SIZE_* values, a linear one
+// being LINEAR with no size. A linear gradient going at an angle has mHasAngle set and mAngle
+// in radians, clockwise from going to the top; one going to a corner has the corner as its
+// position, at 0% or 100% on each axis. A radial gradient has its center as the position,
+// and for an EXPLICIT_SIZE its radii. The layout, which build.rs has the Rust side checked
+// against:
+//
+//    0  mStops               RawGeckoGradientStopArray*
+//    8  mURL                 nsString*
+//   16  mKind                ServoImageKind
+//   17  mShape               uint8_t
+//   18  mSize                uint8_t
+//   19  mHasAngle            bool
+//   20  mAngle               float
+//   24  mPositionX           ServoLengthOrPercentageOrAuto
+//   40  mPositionY           ServoLengthOrPercentageOrAuto
+//   56  mRadiusX             ServoLengthOrPercentageOrAuto
+//   72  mRadiusY             ServoLengthOrPercentageOrAuto
+//   88  (size, aligned to 8)
+struct ServoImageInfo {
+  RawGeckoGradientStopArray* mStops;
+  nsString* mURL;
+  ServoImageKind mKind;
+  uint8_t mShape;
+  uint8_t mSize;
+  bool mHasAngle;
+  float mAngle;
+  ServoLengthOrPercentageOrAuto mPositionX;
+  ServoLengthOrPercentageOrAuto mPositionY;
+  ServoLengthOrPercentageOrAuto mRadiusX;
+  ServoLengthOrPercentageOrAuto mRadiusY;
+};
+// A background layer, but for its image. mRepeat, mAttachment, mClip and mOrigin are the
+// NS_STYLE_IMAGELAYER_* values. The size is mWidth by mHeight if mHasExplicitSize is set,
+// either of which may be auto, and NS_STYLE_IMAGELAYER_SIZE_CONTAIN or COVER in mSize otherwise.
+struct ServoBackgroundLayer {
+  ServoLengthOrPercentageOrAuto mPositionX;
+  ServoLengthOrPercentageOrAuto mPositionY;
+  ServoLengthOrPercentageOrAuto mWidth;
+  ServoLengthOrPercentageOrAuto mHeight;
+  bool mHasExplicitSize;
+  uint8_t mSize;
+  uint8_t mRepeat;
+  uint8_t mAttachment;
+  uint8_t mClip;
+  uint8_t mOrigin;
+};
+// An image of the cursor property: the request Gecko_LoadImage started for it, or null if it
+// didn't start one, and the point of the cursor in it if mHaveHotspot is set.
+struct ServoCursorImage {
+  void* mRequest;
+  bool mHaveHotspot;
+  float mHotspotX;
+  float mHotspotY;
+};
+// An entry of counter-reset, counter-increment or counter-set: the counter, and the value to
+// reset or set it to, or to add to it. Servo keeps mName alive until it shuts down, so Gecko
+// only needs its own reference to hold on to it past then.
+struct ServoCounterItem {
+  nsIAtom* mName;
+  int32_t mValue;
+};
+enum class ServoCSSErrorKind : uint8_t {
+  InvalidSelector = 0,
+};
+// Why a selector list doesn't parse: the selector at fault, as the byte offset and length of
+// it in the list, and the byte offset in it of its first invalid token. The layout, which
+// build.rs has the Rust side checked against:
+//
+//    0  mKind                ServoCSSErrorKind
+//    4  mSelectorStart       uint32_t
+//    8  mSelectorLength      uint32_t
+//   12  mOffset              uint32_t
+//   16  (size, aligned to 4)
+struct ServoSelectorParseError {
+  ServoCSSErrorKind mKind;
+  uint32_t mSelectorStart;
+  uint32_t mSelectorLength;
+  uint32_t mOffset;
+};
+// What flex containers and their items are laid out from. The keywords are the
+// NS_STYLE_FLEX_DIRECTION_*, NS_STYLE_FLEX_WRAP_* and NS_STYLE_ALIGN_* values, and a `content`
+// flex basis sets mFlexBasisIsContent and leaves mFlexBasis with the Auto unit, which otherwise
+// means the main size property is used. The layout, which build.rs has the Rust side checked
+// against:
+//
+//    0  mFlexDirection       uint8_t
+//    1  mFlexWrap            uint8_t
+//    2  mJustifyContent      uint8_t
+//    3  mAlignItems          uint8_t
+//    4  mAlignContent        uint8_t
+//    5  mAlignSelf           uint8_t
+//    6  mFlexBasisIsContent  bool
+//    8  mOrder               int32_t
+//   12  mFlexGrow            float
+//   16  mFlexShrink          float
+//   20  mFlexBasis           ServoLengthOrPercentageOrAuto
+//   36  (size, aligned to 4)
+struct ServoFlexInfo {
+  uint8_t mFlexDirection;
+  uint8_t mFlexWrap;
+  uint8_t mJustifyContent;
+  uint8_t mAlignItems;
+  uint8_t mAlignContent;
+  uint8_t mAlignSelf;
+  bool mFlexBasisIsContent;
+  int32_t mOrder;
+  float mFlexGrow;
+  float mFlexShrink;
+  ServoLengthOrPercentageOrAuto mFlexBasis;
+};
+// What multi-column elements are laid out and have their rules drawn from. An `auto` column
+// count is NS_STYLE_COLUMN_COUNT_AUTO, the lengths are in app units, and the rule width is
+// already zero when the rule style is none or hidden, which is an NS_STYLE_BORDER_STYLE_*
+// value. The width, gap and rule color are only meaningful when their mHas* flag is set;
+// otherwise they're `auto`, `normal` and the element's color. The layout, which build.rs has
+// the Rust side checked against:
+//
+//    0  mColumnCount         uint32_t
+//    4  mColumnWidth         int32_t
+//    8  mColumnGap           int32_t
+//   12  mColumnRuleWidth     int32_t
+//   16  mColumnRuleColor     uint32_t
+//   20  mColumnRuleStyle     uint8_t
+//   21  mHasColumnWidth      bool
+//   22  mHasColumnGap        bool
+//   23  mHasColumnRuleColor  bool
+//   24  (size, aligned to 4)
+struct ServoColumnInfo {
+  uint32_t mColumnCount;
+  int32_t mColumnWidth;
+  int32_t mColumnGap;
+  int32_t mColumnRuleWidth;
+  uint32_t mColumnRuleColor;  // An nscolor.
+  uint8_t mColumnRuleStyle;
+  bool mHasColumnWidth;
+  bool mHasColumnGap;
+  bool mHasColumnRuleColor;
+};
+// What hit testing reads of an element, in one go. mPointerEvents is an
+// NS_STYLE_POINTER_EVENTS_* value, which is one of the SVG ones for any element when it's set
+// to one; only SVG frames tell them apart from AUTO. mTouchAction holds NS_STYLE_TOUCH_ACTION_*
+// bits, and mUserSelect is an NS_STYLE_USER_SELECT_* value. The layout, which build.rs has the
+// Rust side checked against:
+//
+//    0  mPointerEvents       uint8_t
+//    1  mTouchAction         uint8_t
+//    2  mUserSelect          uint8_t
+//    3  (size, aligned to 1)
+struct ServoUIInfo {
+  uint8_t mPointerEvents;
+  uint8_t mTouchAction;
+  uint8_t mUserSelect;
+};
+// One side of text-overflow. mType is an NS_STYLE_TEXT_OVERFLOW_* value, and for a STRING, the
+// string is written to mString if it points at one. The layout, which build.rs has the Rust
+// side checked against:
+//
+//    0  mString              nsString*
+//    8  mType                uint8_t
+//   16  (size, aligned to 8)
+struct ServoTextOverflowSide {
+  nsString* mString;
+  uint8_t mType;
+};
+// What the text that overflows a block's line boxes is drawn as. When only one value was given,
+// mLogicalDirections is set, mLeft is clip and mRight is the value, which goes at the end of the
+// line whichever way the text runs. The layout, which build.rs has the Rust side checked
+// against:
+//
+//    0  mLeft                ServoTextOverflowSide
+//   16  mRight               ServoTextOverflowSide
+//   32  mLogicalDirections   bool
+//   40  (size, aligned to 8)
+struct ServoTextOverflow {
+  ServoTextOverflowSide mLeft;
+  ServoTextOverflowSide mRight;
+  bool mLogicalDirections;
+};
+struct SystemFontData {
+  const uint8_t* mFamily;
+  uint32_t mFamilyLength;
+  float mSize;
+  uint16_t mWeight;
+  bool mItalic;
+};
+// The style structs Servo_GetStyle* fill in, which hold what the nsStyle* struct of the same name
+// holds, and are laid out like it as far as one can be laid out like the other. Gecko constructs
+// each with its initial values first, which stay in the fields Servo doesn't compute a value for
+// yet. The keywords are the NS_STYLE_* values Gecko keeps in the nsStyle* fields, and the lengths
+// are in app units.
+//
+// mOriginalDisplay is the display the element had before it was blockified, and mContain is
+// the NS_STYLE_CONTAIN_* bits, with STRICT set only for all of the containments at once. The
+// layout, which build.rs has the Rust side checked against:
+//
+//    0  mOpacity             float
+//    4  mDisplay             uint8_t
+//    5  mOriginalDisplay     uint8_t
+//    6  mContain             uint8_t
+//    7  mAppearance          uint8_t
+//    8  mPosition            uint8_t
+//    9  mFloats              uint8_t
+//   10  mBreakType           uint8_t
+//   11  mOverflowX           uint8_t
+//   12  mOverflowY           uint8_t
+//   13  mResize              uint8_t
+//   14  mOrient              uint8_t
+//   15  mMixBlendMode        uint8_t
+//   16  mIsolation           uint8_t
+//   17  mTouchAction         uint8_t
+//   18  mScrollBehavior      uint8_t
+//   19  mScrollSnapTypeX     uint8_t
+//   20  mScrollSnapTypeY     uint8_t
+//   21  mBackfaceVisibility  uint8_t
+//   22  mTransformStyle      uint8_t
+//   23  mTransformBox        uint8_t
+//   24  (size, aligned to 4)
+struct nsStyleDisplay_FFI {
+  float mOpacity;
+  uint8_t mDisplay;
+  uint8_t mOriginalDisplay;
+  uint8_t mContain;
+  uint8_t mAppearance;
+  uint8_t mPosition;
+  uint8_t mFloats;
+  uint8_t mBreakType;
+  uint8_t mOverflowX;
+  uint8_t mOverflowY;
+  uint8_t mResize;
+  uint8_t mOrient;
+  uint8_t mMixBlendMode;
+  uint8_t mIsolation;
+  uint8_t mTouchAction;
+  uint8_t mScrollBehavior;
+  uint8_t mScrollSnapTypeX;
+  uint8_t mScrollSnapTypeY;
+  uint8_t mBackfaceVisibility;
+  uint8_t mTransformStyle;
+  uint8_t mTransformBox;
+};
+// The sides go top, right, bottom, left, as in an nsStyleSides. The layout, which build.rs has
+// the Rust side checked against:
+//
+//    0  mMargin              ServoLengthOrPercentageOrAuto[4]
+//   64  (size, aligned to 4)
+struct nsStyleMargin_FFI {
+  ServoLengthOrPercentageOrAuto mMargin[4];
+};
+// The sides go top, right, bottom, left, and are never auto. The layout, which build.rs has the
+// Rust side checked against:
+//
+//    0  mPadding             ServoLengthOrPercentageOrAuto[4]
+//   64  (size, aligned to 4)
+struct nsStylePadding_FFI {
+  ServoLengthOrPercentageOrAuto mPadding[4];
+};
+// The offsets go top, right, bottom, left. mBoxSizing is a StyleBoxSizing, a `content` flex basis
+// sets mFlexBasisIsContent and leaves mFlexBasis with the Auto unit, and mZIndex is only
+// meaningful when mHasZIndex is set; otherwise it's auto. The layout, which build.rs has the
+// Rust side checked against:
+//
+//    0  mOffset              ServoLengthOrPercentageOrAuto[4]
+//   64  mWidth               ServoLengthOrPercentageOrAuto
+//   80  mMinWidth            ServoLengthOrPercentageOrAuto
+//   96  mMaxWidth            ServoLengthOrPercentageOrAuto
+//  112  mHeight              ServoLengthOrPercentageOrAuto
+//  128  mMinHeight           ServoLengthOrPercentageOrAuto
+//  144  mMaxHeight           ServoLengthOrPercentageOrAuto
+//  160  mFlexBasis           ServoLengthOrPercentageOrAuto
+//  176  mGridAutoFlow        uint8_t
+//  177  mBoxSizing           uint8_t
+//  178  mAlignContent        uint16_t
+//  180  mAlignItems          uint8_t
+//  181  mAlignSelf           uint8_t
+//  182  mJustifyContent      uint16_t
+//  184  mJustifyItems        uint8_t
+//  185  mJustifySelf         uint8_t
+//  186  mFlexDirection       uint8_t
+//  187  mFlexWrap            uint8_t
+//  188  mObjectFit           uint8_t
+//  189  mFlexBasisIsContent  bool
+//  190  mHasZIndex           bool
+//  192  mOrder               int32_t
+//  196  mFlexGrow            float
+//  200  mFlexShrink          float
+//  204  mZIndex              int32_t
+//  208  mGridColumnGap       int32_t
+//  212  mGridRowGap          int32_t
+//  216  (size, aligned to 4)
+struct nsStylePosition_FFI {
+  ServoLengthOrPercentageOrAuto mOffset[4];
+  ServoLengthOrPercentageOrAuto mWidth;
+  ServoLengthOrPercentageOrAuto mMinWidth;
+  ServoLengthOrPercentageOrAuto mMaxWidth;
+  ServoLengthOrPercentageOrAuto mHeight;
+  ServoLengthOrPercentageOrAuto mMinHeight;
+  ServoLengthOrPercentageOrAuto mMaxHeight;
+  ServoLengthOrPercentageOrAuto mFlexBasis;
+  uint8_t mGridAutoFlow;
+  uint8_t mBoxSizing;
+  uint16_t mAlignContent;
+  uint8_t mAlignItems;
+  uint8_t mAlignSelf;
+  uint16_t mJustifyContent;
+  uint8_t mJustifyItems;
+  uint8_t mJustifySelf;
+  uint8_t mFlexDirection;
+  uint8_t mFlexWrap;
+  uint8_t mObjectFit;
+  bool mFlexBasisIsContent;
+  bool mHasZIndex;
+  int32_t mOrder;
+  float mFlexGrow;
+  float mFlexShrink;
+  int32_t mZIndex;
+  int32_t mGridColumnGap;
+  int32_t mGridRowGap;
+};
+// The layout, which build.rs has the Rust side checked against:
+//
+//    0  mDirection           uint8_t
+//    1  mVisible             uint8_t
+//    2  mPointerEvents       uint8_t
+//    3  mWritingMode         uint8_t
+//    4  mTextOrientation     uint8_t
+//    5  mImageRendering      uint8_t
+//    6  mColorAdjust         uint8_t
+//    7  mImageOrientation    uint8_t
+//    8  (size, aligned to 1)
+struct nsStyleVisibility_FFI {
+  uint8_t mDirection;
+  uint8_t mVisible;
+  uint8_t mPointerEvents;
+  uint8_t mWritingMode;
+  uint8_t mTextOrientation;
+  uint8_t mImageRendering;
+  uint8_t mColorAdjust;
+  uint8_t mImageOrientation;
+};
+// What Servo caches about a node the first time it asks Gecko_GetNodeInfo, until Gecko next
+// calls in. mParent and mNextSibling are null when there's no such node, mState is the
+// element's event state, or 0 for other nodes, and mFlags are the node's flags. The layout,
+// which build.rs has the Rust side checked against:
+//
+//    0  mParent              RawGeckoNode*
+//    8  mNextSibling         RawGeckoNode*
+//   16  mState               uint64_t
+//   24  mFlags               uint32_t
+//   28  mIsElement           bool
+//   32  (size, aligned to 8)
+struct NodeInfoFFI {
+  RawGeckoNode* mParent;
+  RawGeckoNode* mNextSibling;
+  uint64_t mState;
+  uint32_t mFlags;
+  bool mIsElement;
+};
+// An attribute as Gecko_SnapshotAttributes copies it. mValueAtom is the value when Gecko
+// has it as an atom, and null otherwise, with mValue and mValueLength the value in UTF-8.
+// Servo takes its own references to the atoms and copies the string, so they only have to
+// last as long as the call. The layout, which build.rs has the Rust side checked against:
+//
+//    0  mNamespace           nsIAtom*
+//    8  mName                nsIAtom*
+//   16  mValueAtom           nsIAtom*
+//   24  mValue               const uint8_t*
+//   32  mValueLength         uint32_t
+//   40  (size, aligned to 8)
+struct ServoAttrSnapshot {
+  nsIAtom* mNamespace;
+  nsIAtom* mName;
+  nsIAtom* mValueAtom;
+  const uint8_t* mValue;
+  uint32_t mValueLength;
+};
+
+extern "C" {
+
+uint32_t Gecko_ChildrenCount(RawGeckoNode* node);
+bool Gecko_NodeIsElement(RawGeckoNode* node);
+bool Gecko_NodeIsDocument(RawGeckoNode* node);
+bool Gecko_IsInDocument(RawGeckoNode* node);
+RawGeckoDocument* Gecko_GetOwnerDocument(RawGeckoNode* node);
+uint64_t Gecko_GetDocumentState(RawGeckoDocument* document);
+bool Gecko_IsChromeDocument(RawGeckoDocument* document);
+bool Gecko_IsSignificantChild(RawGeckoNode* node, bool text_is_significant);
+uint32_t Gecko_GetNodeFlags(RawGeckoNode* node);
+void Gecko_SetNodeFlags(RawGeckoNode* node, uint32_t flags);
+void Gecko_UnsetNodeFlags(RawGeckoNode* node, uint32_t flags);
+// Fills |out| with what the style traversal asks about |node| most, in one call.
+void Gecko_GetNodeInfo(RawGeckoNode* node, NodeInfoFFI* out);
+RawGeckoNode* Gecko_GetParentNode(RawGeckoNode* node);
+RawGeckoNode* Gecko_GetFirstChild(RawGeckoNode* node);
+RawGeckoNode* Gecko_GetLastChild(RawGeckoNode* node);
+uint32_t Gecko_GetChildren(RawGeckoNode* node, RawGeckoNode** children, uint32_t capacity);
+RawGeckoNode* Gecko_GetPrevSibling(RawGeckoNode* node);
+RawGeckoNode* Gecko_GetNextSibling(RawGeckoNode* node);
+RawGeckoElement* Gecko_GetParentElement(RawGeckoElement* element);
+RawGeckoElement* Gecko_GetFirstChildElement(RawGeckoElement* element);
+RawGeckoElement* Gecko_GetLastChildElement(RawGeckoElement* element);
+RawGeckoElement* Gecko_GetPrevSiblingElement(RawGeckoElement* element);
+RawGeckoElement* Gecko_GetNextSiblingElement(RawGeckoElement* element);
+int32_t Gecko_GetChildIndexHint(RawGeckoElement* element);
+RawGeckoElement* Gecko_GetDocumentElement(RawGeckoDocument* document);
+uint8_t Gecko_GetDocumentCompatMode(RawGeckoDocument* document);
+uint64_t Gecko_ElementState(RawGeckoElement* element);
+bool Gecko_IsHTMLElementInHTMLDocument(RawGeckoElement* element);
+bool Gecko_IsInQuirksModeDocument(RawGeckoElement* element);
+bool Gecko_IsLink(RawGeckoElement* element);
+bool Gecko_IsTextNode(RawGeckoNode* node);
+bool Gecko_IsNativeAnonymous(RawGeckoElement* element);
+bool Gecko_IsVisitedLink(RawGeckoElement* element);
+bool Gecko_IsUnvisitedLink(RawGeckoElement* element);
+bool Gecko_IsRootElement(RawGeckoElement* element);
+// Whether |element| matches |pseudo_class|, one of the -moz- pseudo-classes of the UA sheets
+// that only Gecko can evaluate. The atom has the colon, as in ":-moz-browser-frame". Called
+// from the style threads.
+bool Gecko_MatchesMozPseudoClass(RawGeckoElement* element, nsIAtom* pseudo_class);
+ServoNodeData* Gecko_GetNodeData(RawGeckoNode* node);
+void Gecko_SetNodeData(RawGeckoNode* node, ServoNodeData* data);
+void Servo_DropNodeData(ServoNodeData* data);
+// Drop the data of a subtree's nodes, right away or on another thread, for when it's
+// removed from the DOM.
+void Servo_DropNodeDataRecursive(RawGeckoNode* root);
+void Servo_DropNodeDataRecursiveLater(RawGeckoNode* root);
+// Throws away the styles of |node| and its descendants, for when their frames are rebuilt or
+// the node moves to another document, and marks them for restyling. With |keep_for_diffing|,
+// the next restyle computes the change hint against the old styles before freeing them.
+void Servo_ClearNodeStyleData(RawGeckoNode* node, bool keep_for_diffing);
+void Servo_FlushDroppedNodeData();
+bool Gecko_IsMainThread();
+// Has the main thread call |release_fn| with |ptr| soon, to free something that was dropped on
+// another thread but may only be freed on the main one. Servo calls this from any thread.
+void Gecko_DispatchRelease(void* ptr, ServoReleaseFn release_fn);
+// Debug builds crash here if a style dropped anything only the main thread may free on a thread
+// that wasn't meant to free styles, since the last call. Other builds don't check.
+void Servo_AssertNoOffThreadDrops();
+RawServoStyleSheetStrong Servo_StylesheetFromUTF8Bytes(const uint8_t* bytes, uint32_t length,
+                                                       const uint8_t* base, uint32_t base_length,
+                                                       StyleSheetOrigin origin, uint8_t compat_mode,
+                                                       void* error_context, RawGeckoURLExtraData* extra_data,
+                                                       bool* out_was_lossy);
+RawServoStyleSheetStrong Servo_StylesheetFromUTF16Bytes(const uint16_t* bytes, uint32_t length,
+                                                        const uint8_t* base, uint32_t base_length,
+                                                        StyleSheetOrigin origin,
+                                                        uint8_t compat_mode, void* error_context,
+                                                        RawGeckoURLExtraData* extra_data);
+void Servo_StyleSheet_FromUTF8BytesAsync(const uint8_t* bytes, uint32_t length, const uint8_t* base,
+                                         uint32_t base_length, StyleSheetOrigin origin,
+                                         uint8_t compat_mode, RawGeckoURLExtraData* extra_data,
+                                         void* callback_context, RawServoStyleSet* set);
+bool Servo_StyleSheet_CancelAsyncParse(void* callback_context);
+void Gecko_StyleSheetLoadComplete(void* callback_context, RawServoStyleSheetStrong sheet);
+void Gecko_AnnotateCrashReport(const uint8_t* key, uint32_t key_length, const uint8_t* value,
+                               uint32_t value_length);
+void Gecko_Log(uint8_t level, const char* module, const char* message, uint32_t msg_len);
+bool Gecko_GetFontMetrics(const nsStyleFont* font, float* out_x_height, float* out_ch_width);
+float Gecko_GetBaseFontSize(uint8_t generic_id, nsIAtom* language);
+bool Gecko_GetLookAndFeelColor(int32_t color_id, bool use_standins, uint32_t* out_color);
+bool Gecko_GetSystemFont(int32_t font_id, SystemFontData* out);
+void Gecko_ReportCSSError(void* sheet_context, const char* message, uint32_t line, uint32_t column);
+// Reports a rule dropped for the selector |selector|, whose first invalid token is at byte
+// |offset|, for Gecko to describe in its own words. |error_kind| is a ServoCSSErrorKind, and
+// |line| and |column| are where the rule starts.
+void Gecko_ReportCSSErrorWithDetails(void* sheet_context, uint8_t error_kind, const uint8_t* selector,
+                                     uint32_t len, uint32_t offset, uint32_t line, uint32_t column);
+void* Gecko_LoadStyleSheet(void* parent_sheet_context, RawGeckoURLExtraData* extra_data,
+                           RawServoImportRule* import_rule, const uint8_t* url, uint32_t url_len,
+                           const uint8_t* media, uint32_t media_len);
+void* Gecko_LoadImage(const uint8_t* url, uint32_t url_len, RawGeckoURLExtraData* extra_data);
+void Gecko_ReleaseImageRequest(void* request);
+void Gecko_AddRefURLExtraData(RawGeckoURLExtraData* extra_data);
+void Gecko_ReleaseURLExtraData(RawGeckoURLExtraData* extra_data);
+bool Servo_StyleSheet_SetImportedSheet(RawServoImportRule* import_rule, RawServoStyleSheet* sheet);
+void Servo_AddRefImportRule(RawServoImportRule* import_rule);
+void Servo_ReleaseImportRule(RawServoImportRule* import_rule);
+void Servo_AddRefStyleSheet(RawServoStyleSheet* sheet);
+void Servo_ReleaseStyleSheet(RawServoStyleSheet* sheet);
+void Servo_AppendStyleSheet(RawServoStyleSheet* sheet, RawServoStyleSet* set);
+void Servo_PrependStyleSheet(RawServoStyleSheet* sheet, RawServoStyleSet* set);
+void Servo_StyleSet_AppendStyleSheetToOrigin(RawServoStyleSet* set, RawServoStyleSheet* sheet,
+                                             StyleSheetOrigin origin);
+uint32_t Servo_StyleSet_SheetCount(RawServoStyleSet* set, StyleSheetOrigin origin);
+// Null if |index| is out of range.
+RawServoStyleSheetStrong Servo_StyleSet_SheetAt(RawServoStyleSet* set, StyleSheetOrigin origin,
+                                                uint32_t index);
+void Servo_RemoveStyleSheet(RawServoStyleSheet* sheet, RawServoStyleSet* set);
+void Servo_StyleSet_InsertStyleSheetBefore(RawServoStyleSet* set, RawServoStyleSheet* sheet,
+                                           RawServoStyleSheet* reference);
+void Servo_StyleSet_ReplaceStyleSheets(RawServoStyleSet* set, RawServoStyleSheet* const* sheets,
+                                       uint32_t count);
+void Servo_StyleSet_NoteStyleSheetsChanged(RawServoStyleSet* set, StyleSheetOrigin origin);
+void Servo_StyleSet_InvalidateForSheet(RawGeckoDocument* doc, RawServoStyleSet* set,
+                                       RawServoStyleSheet* sheet);
+void Servo_StyleSet_RuleChanged(RawServoStyleSet* set, RawServoStyleRule* rule);
+void Servo_StyleSet_NoteThemeChanged(RawServoStyleSet* set);
+void Servo_StyleSet_SetAuthorStyleDisabled(RawServoStyleSet* set, bool disabled);
+bool Servo_StyleSet_GetAuthorStyleDisabled(RawServoStyleSet* set);
+bool Servo_StyleSet_SetMedium(RawServoStyleSet* set, nsIAtom* medium);
+nsIAtom* Servo_StyleSet_GetMedium(RawServoStyleSet* set);
+bool Servo_Test_Panic();
+uint32_t Servo_Test_TakeChildFFICallCount();
+int32_t Servo_Test_GetStrongRefCount();
+uint32_t Servo_StyleSet_GetRebuildCount(RawServoStyleSet* set, StyleSheetOrigin origin);
+uint32_t Servo_StyleSet_GetAnonymousBoxCascadeCount(RawServoStyleSet* set);
+size_t Servo_StyleSheet_SizeOfIncludingThis(MallocSizeOfFn malloc_size_of,
+                                            RawServoStyleSheet* sheet);
+size_t Servo_StyleSet_SizeOf(MallocSizeOfFn malloc_size_of, RawServoStyleSet* set);
+size_t Servo_ComputedValues_SizeOf(MallocSizeOfFn malloc_size_of, ServoComputedValues* values);
+void Servo_StyleSheet_UpdateFromUTF8Bytes(RawServoStyleSheet* sheet, const uint8_t* bytes,
+                                          uint32_t length, void* error_context,
+                                          RawGeckoURLExtraData* extra_data);
+bool Servo_StyleSheetHasRules(RawServoStyleSheet* sheet);
+RawServoStyleSheetStrong Servo_StyleSheet_Clone(RawServoStyleSheet* sheet);
+RawServoSharedStyleSheetStrong Servo_StyleSheet_IntoShared(RawServoStyleSheet* sheet);
+void Servo_SharedStyleSheet_AddRef(RawServoSharedStyleSheet* shared);
+void Servo_SharedStyleSheet_Release(RawServoSharedStyleSheet* shared);
+size_t Servo_SharedStyleSheet_SizeOfRuleMaps(RawServoSharedStyleSheet* shared);
+void Servo_StyleSet_AppendSharedStyleSheet(RawServoStyleSet* set, RawServoSharedStyleSheet* shared);
+void Servo_StyleSet_AppendScopedStyleSheet(RawServoStyleSet* set, RawServoStyleSheet* sheet,
+                                           RawGeckoElement* scope_root);
+void Servo_StyleSet_RemoveScopedStyleSheets(RawServoStyleSet* set, RawGeckoElement* scope_root);
+RawServoCssRulesStrong Servo_StyleSheet_GetRules(RawServoStyleSheet* sheet);
+void Servo_CssRules_AddRef(RawServoCssRules* rules);
+void Servo_CssRules_Release(RawServoCssRules* rules);
+uint32_t Servo_CssRules_GetCount(RawServoCssRules* rules);
+uint16_t Servo_CssRules_GetTypeAt(RawServoCssRules* rules, uint32_t index);
+RawServoStyleRuleStrong Servo_CssRules_GetStyleRuleAt(RawServoCssRules* rules, uint32_t index);
+RawServoCounterStyleRuleStrong Servo_CssRules_GetCounterStyleRuleAt(RawServoCssRules* rules,
+                                                                    uint32_t index);
+RawServoCssRulesStrong Servo_CssRules_GetNestedRulesAt(RawServoCssRules* rules, uint32_t index);
+bool Servo_CssRules_GetUnsupportedRuleTextAt(RawServoCssRules* rules, uint32_t index, nsString* result);
+nsresult Servo_CssRules_InsertRule(RawServoCssRules* rules, RawServoStyleSheet* sheet,
+                                   const uint8_t* rule, uint32_t rule_length, uint32_t index,
+                                   bool nested, void* error_context);
+nsresult Servo_CssRules_DeleteRule(RawServoCssRules* rules, uint32_t index);
+void Servo_StyleRule_AddRef(RawServoStyleRule* rule);
+void Servo_StyleRule_Release(RawServoStyleRule* rule);
+void Servo_StyleRule_GetSelectorText(RawServoStyleRule* rule, nsString* result);
+uint32_t Servo_StyleRule_GetSelectorCount(RawServoStyleRule* rule);
+void Servo_StyleRule_GetSelectorTextAt(RawServoStyleRule* rule, uint32_t index, nsString* result);
+uint64_t Servo_StyleRule_GetSpecificityAt(RawServoStyleRule* rule, uint32_t index);
+bool Servo_StyleRule_SelectorMatchesElement(RawServoStyleRule* rule, RawGeckoElement* element,
+                                            uint32_t index);
+RawServoDeclarationBlockStrong Servo_StyleRule_GetDeclarations(RawServoStyleRule* rule);
+void Servo_StyleRule_GetCssText(RawServoStyleRule* rule, nsString* result);
+void Servo_StyleRule_GetSourceLocation(RawServoStyleRule* rule, uint32_t* out_line,
+                                       uint32_t* out_column);
+void Servo_GetMatchingRules(RawGeckoElement* element, nsIAtom* pseudo_tag, RawServoStyleSet* set,
+                            RawGeckoRuleList* out);
+void Gecko_RuleListAppend(RawGeckoRuleList* list, RawServoStyleSheet* sheet,
+                          RawServoStyleRuleStrong rule, uint32_t selector_index,
+                          uint32_t specificity);
+void Gecko_RuleListAppendDeclarations(RawGeckoRuleList* list,
+                                      RawServoDeclarationBlockStrong declarations,
+                                      bool is_style_attribute);
+void Servo_StyleSet_ResolvePageStyle(RawServoStyleSet* set, nsIAtom* page_name, uint8_t page_flags,
+                                     RawServoDeclarationBlockStrong* out_block,
+                                     ServoPageSize* out_size);
+bool Servo_StyleSet_GetKeyframesForName(RawServoStyleSet* set, nsIAtom* name,
+                                        RawGeckoKeyframeList* keyframes);
+bool Servo_ParseEasing(const uint8_t* value, uint32_t length, ServoTimingFunction* out);
+double Servo_EasingEvaluateAt(const ServoTimingFunction* timing, double progress, bool before_flag);
+RawServoCounterStyleRule* Servo_StyleSet_GetCounterStyleRule(RawServoStyleSet* set, nsIAtom* name);
+void Servo_CounterStyleRule_AddRef(RawServoCounterStyleRule* rule);
+void Servo_CounterStyleRule_Release(RawServoCounterStyleRule* rule);
+nsIAtom* Servo_CounterStyleRule_GetName(RawServoCounterStyleRule* rule);
+uint8_t Servo_CounterStyleRule_GetSystem(RawServoCounterStyleRule* rule,
+                                         int32_t* out_first_symbol_value, nsIAtom** out_extended);
+uint32_t Servo_CounterStyleRule_GetSymbolCount(RawServoCounterStyleRule* rule);
+bool Servo_CounterStyleRule_GetSymbolAt(RawServoCounterStyleRule* rule, uint32_t index,
+                                        nsString* result);
+uint32_t Servo_CounterStyleRule_GetAdditiveSymbolCount(RawServoCounterStyleRule* rule);
+bool Servo_CounterStyleRule_GetAdditiveSymbolAt(RawServoCounterStyleRule* rule, uint32_t index,
+                                                uint32_t* out_weight, nsString* result);
+bool Servo_CounterStyleRule_GetNegative(RawServoCounterStyleRule* rule, nsString* out_before,
+                                        nsString* out_after);
+bool Servo_CounterStyleRule_GetPrefix(RawServoCounterStyleRule* rule, nsString* result);
+bool Servo_CounterStyleRule_GetSuffix(RawServoCounterStyleRule* rule, nsString* result);
+uint32_t Servo_CounterStyleRule_GetRangeCount(RawServoCounterStyleRule* rule);
+bool Servo_CounterStyleRule_GetRangeAt(RawServoCounterStyleRule* rule, uint32_t index,
+                                       int32_t* out_lower, int32_t* out_upper);
+bool Servo_CounterStyleRule_GetPad(RawServoCounterStyleRule* rule, uint32_t* out_width,
+                                   nsString* result);
+nsIAtom* Servo_CounterStyleRule_GetFallback(RawServoCounterStyleRule* rule);
+uint8_t Servo_CounterStyleRule_GetSpeakAs(RawServoCounterStyleRule* rule,
+                                          nsIAtom** out_counter_style);
+void Gecko_AppendKeyframe(RawGeckoKeyframeList* keyframes, float offset,
+                          const ServoTimingFunction* timing_function,
+                          RawServoDeclarationBlockStrong declarations);
+void Servo_StyleSet_GetFontFaceRules(RawServoStyleSet* set, RawGeckoFontFaceRuleList* rules);
+RawGeckoFontFaceRule* Gecko_AppendFontFaceRule(RawGeckoFontFaceRuleList* rules,
+                                               RawServoStyleSheet* sheet, const uint8_t* family,
+                                               uint32_t family_length, uint16_t weight,
+                                               uint8_t style, int16_t stretch);
+void Gecko_FontFaceRule_AppendURLSource(RawGeckoFontFaceRule* rule, const uint8_t* url,
+                                        uint32_t url_length);
+void Gecko_FontFaceRule_AppendFormatHint(RawGeckoFontFaceRule* rule, const uint8_t* hint,
+                                         uint32_t hint_length);
+void Gecko_FontFaceRule_AppendLocalSource(RawGeckoFontFaceRule* rule, const uint8_t* name,
+                                          uint32_t name_length);
+void Gecko_FontFaceRule_AppendUnicodeRange(RawGeckoFontFaceRule* rule, uint32_t start,
+                                           uint32_t end);
+RawServoMediaListStrong Servo_MediaList_Parse(const uint8_t* bytes, uint32_t length);
+void Servo_AddRefMediaList(RawServoMediaList* list);
+void Servo_ReleaseMediaList(RawServoMediaList* list);
+void Servo_StyleSheet_SetMediaList(RawServoStyleSheet* sheet, RawServoMediaList* list);
+bool Servo_StyleSet_MediaFeatureValuesChanged(RawServoStyleSet* set, float viewport_width,
+                                              float viewport_height, float device_pixel_ratio);
+void Servo_StyleSet_SetDevice(RawServoStyleSet* set, float viewport_width_px,
+                              float viewport_height_px, float device_pixel_ratio,
+                              float root_font_size_px);
+void Servo_StyleSheet_SetDisabled(RawServoStyleSheet* sheet, bool disabled);
+bool Servo_StyleSheet_GetDisabled(RawServoStyleSheet* sheet);
+uint8_t Servo_StyleSheet_GetOrigin(RawServoStyleSheet* sheet);
+bool Servo_StyleSheet_SetTitle(RawServoStyleSheet* sheet, const uint8_t* title, uint32_t length);
+void Servo_StyleSheet_GetTitle(RawServoStyleSheet* sheet, nsString* result);
+void Servo_StyleSheet_GetDebugInfo(RawServoStyleSheet* sheet, nsString* result);
+void Servo_Initialize();
+void Servo_Shutdown();
+void Servo_InitializeThreadPool(uint32_t num_threads);
+void Servo_SetDeterministicTraversal(bool enabled);
+void Servo_SetRefCountLogging(bool enabled);
+void Servo_DumpLiveObjects();
+void Servo_SetTraversalStatisticsEnabled(bool enabled);
+void Servo_GetTraversalStatistics(ServoTraversalStatistics* out);
+RawServoStyleSet* Servo_InitStyleSet();
+void Servo_DropStyleSet(RawServoStyleSet* set);
+RawServoPerDocumentData* Servo_PerDocumentData_Create(RawGeckoDocument* document);
+void Servo_StyleSet_Shutdown(RawServoStyleSet* set);
+void Servo_PerDocumentData_Drop(RawServoPerDocumentData* data);
+RawServoStyleSet* Servo_PerDocumentData_GetStyleSet(RawServoPerDocumentData* data);
+const char* Gecko_GetAttrAsUTF8(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name,
+                                uint32_t* length);
+uint32_t Gecko_GetAttrsAsUTF8(RawGeckoElement* element, nsIAtom* name, const char** values,
+                              uint32_t* lengths, uint32_t capacity);
+const uint16_t* Gecko_GetAtomAsUTF16(nsIAtom* atom, uint32_t* length);
+void Gecko_AddRefAtom(nsIAtom* atom);
+void Gecko_ReleaseAtom(nsIAtom* atom);
+bool Gecko_AtomEqualsUTF8(nsIAtom* atom, const char* str, uint32_t length);
+nsIAtom* Gecko_Atomize(const char* string, uint32_t length);
+// Ignores ASCII case only.
+bool Gecko_AtomEqualsUTF8IgnoreCase(nsIAtom* atom, const char* str, uint32_t length);
+// Whether |atom| is one of Gecko's static atoms, which live as long as the process and
+// ignore AddRef and Release.
+bool Gecko_AtomIsStatic(nsIAtom* atom);
+bool Gecko_HasAttr(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name);
+bool Gecko_AttrEquals(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                      bool ignoreCase);
+bool Gecko_AttrDashEquals(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                          bool ignoreCase);
+bool Gecko_AttrIncludes(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                        bool ignoreCase);
+bool Gecko_AttrHasSubstring(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                            bool ignoreCase);
+bool Gecko_AttrHasPrefix(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                         bool ignoreCase);
+bool Gecko_AttrHasSuffix(RawGeckoElement* element, nsIAtom* ns, nsIAtom* name, nsIAtom* str,
+                         bool ignoreCase);
+nsIAtom* Gecko_LocalName(RawGeckoElement* element);
+nsIAtom* Gecko_Namespace(RawGeckoElement* element);
+nsIAtom* Gecko_GetElementId(RawGeckoElement* element);
+nsIAtom* Gecko_GetLanguage(RawGeckoElement* element);
+uint32_t Gecko_ClassOrClassList(RawGeckoElement* element, nsIAtom** class_, nsIAtom*** classList);
+RawServoDeclarationBlock* Gecko_GetStyleAttrDeclarationBlock(RawGeckoElement* element);
+RawServoDeclarationBlock* Gecko_GetHTMLPresentationAttrDeclarationBlock(RawGeckoElement* element);
+RawServoDeclarationBlock* Gecko_GetSMILOverrideDeclarationBlock(RawGeckoElement* element);
+RawServoDeclarationBlock* Gecko_GetAnimationEffectDeclarationBlock(RawGeckoElement* element,
+                                                                   uint8_t cascade_level);
+ServoElementSnapshot* Gecko_CreateElementSnapshot(RawGeckoElement* element);
+void Gecko_DropElementSnapshot(ServoElementSnapshot* snapshot);
+uint64_t Gecko_SnapshotState(ServoElementSnapshot* snapshot);
+bool Gecko_SnapshotHasAttrs(ServoElementSnapshot* snapshot);
+const char* Gecko_SnapshotGetAttrAsUTF8(ServoElementSnapshot* snapshot, nsIAtom* ns,
+                                        nsIAtom* name, uint32_t* length);
+nsIAtom* Gecko_SnapshotGetElementId(ServoElementSnapshot* snapshot);
+uint32_t Gecko_SnapshotClassOrClassList(ServoElementSnapshot* snapshot, nsIAtom** class_,
+                                        nsIAtom*** classList);
+// Copies every attribute of |element| into |snapshot|, which is Servo's, in one call: Gecko
+// asks Servo_AttributeSnapshot_SetLength for room for all of them and fills them in.
+void Gecko_SnapshotAttributes(RawGeckoElement* element, RawServoAttributeSnapshot* snapshot);
+RawServoDeclarationBlockStrong Servo_ParseStyleAttribute(const uint8_t* bytes, uint32_t length);
+void Servo_AddRefDeclarationBlock(RawServoDeclarationBlock* declarations);
+void Servo_ReleaseDeclarationBlock(RawServoDeclarationBlock* declarations);
+void Gecko_Utf8SliceToString(nsString* string, const uint8_t* bytes, uint32_t length);
+void Servo_DeclarationBlock_GetPropertyValue(RawServoDeclarationBlock* declarations,
+                                             const uint8_t* property, uint32_t property_length,
+                                             nsString* value);
+bool Servo_DeclarationBlock_GetPropertyIsImportant(RawServoDeclarationBlock* declarations,
+                                                   const uint8_t* property,
+                                                   uint32_t property_length);
+bool Servo_DeclarationBlock_SetProperty(RawServoDeclarationBlock* declarations,
+                                        const uint8_t* property, uint32_t property_length,
+                                        const uint8_t* value, uint32_t value_length,
+                                        bool is_important);
+void Servo_DeclarationBlock_RemoveProperty(RawServoDeclarationBlock* declarations,
+                                           const uint8_t* property, uint32_t property_length);
+uint32_t Servo_DeclarationBlock_Count(RawServoDeclarationBlock* declarations);
+bool Servo_DeclarationBlock_GetNthProperty(RawServoDeclarationBlock* declarations, uint32_t index,
+                                           nsString* result);
+void Servo_DeclarationBlock_GetCssText(RawServoDeclarationBlock* declarations, nsString* result);
+bool Servo_DeclarationBlock_HasImportant(RawServoDeclarationBlock* declarations);
+uint32_t Servo_DeclarationBlock_GetPropertyIds(RawServoDeclarationBlock* declarations,
+                                               nsCSSProperty* out_ids, uint32_t capacity);
+void Servo_DeclarationBlock_SerializeOneValue(RawServoDeclarationBlock* declarations,
+                                              nsCSSProperty property, nsString* result);
+void Servo_DeclarationBlock_SetIdentStringValue(RawServoDeclarationBlock* declarations,
+                                                nsCSSProperty property, nsIAtom* value);
+bool Servo_ParseProperty(nsCSSProperty property, const uint8_t* value, uint32_t value_length,
+                         const uint8_t* base, uint32_t base_length,
+                         RawServoDeclarationBlockStrong* out_block);
+bool Servo_Property_IsShorthand(nsCSSProperty property);
+void Gecko_PropertyList_Append(RawGeckoPropertyList* list, nsCSSProperty property);
+void Servo_Property_GetLonghands(nsCSSProperty property, RawGeckoPropertyList* out);
+void Servo_Property_SetEnabled(nsCSSProperty property, bool enabled_for_content, bool enabled_for_ua);
+bool Servo_CSSSupports(const uint8_t* property, uint32_t property_length, const uint8_t* value,
+                       uint32_t value_length);
+bool Servo_CSSSupportsCondition(const uint8_t* condition, uint32_t length);
+RawServoSelectorList* Servo_SelectorList_Parse(const uint8_t* bytes, uint32_t length,
+                                               nsIAtom* default_namespace);
+// Like Servo_SelectorList_Parse with no default namespace, for querySelector(), but fills in
+// |out_error| (if it isn't null) with what's wrong with the list when it's invalid.
+RawServoSelectorList* Servo_SelectorList_ParseDetailed(const uint8_t* bytes, uint32_t length,
+                                                       ServoSelectorParseError* out_error);
+void Servo_SelectorList_Drop(RawServoSelectorList* list);
+bool Servo_SelectorList_Matches(RawGeckoElement* element, RawServoSelectorList* list);
+void Gecko_ContentList_AppendElement(RawGeckoContentList* list, RawGeckoElement* element);
+RawGeckoElement* Servo_SelectorList_QueryFirst(RawGeckoNode* root, RawServoSelectorList* list);
+void Servo_SelectorList_QueryAll(RawGeckoNode* root, RawServoSelectorList* list,
+                                 RawGeckoContentList* out);
+RawServoAnimationValueStrong Servo_AnimationValue_FromDeclaration(RawServoDeclarationBlock* declarations,
+                                                                  const uint8_t* property,
+                                                                  uint32_t property_length);
+void Servo_AnimationValue_AddRef(RawServoAnimationValue* value);
+void Servo_AnimationValue_Release(RawServoAnimationValue* value);
+RawServoAnimationValueStrong Servo_AnimationValues_Interpolate(RawServoAnimationValue* from,
+                                                               RawServoAnimationValue* to,
+                                                               double progress);
+void Servo_AnimationValue_Serialize(RawServoAnimationValue* value, const uint8_t* property,
+                                    uint32_t property_length, nsString* result);
+bool Servo_AnimationValue_DeepEqual(RawServoAnimationValue* a, RawServoAnimationValue* b);
+void Gecko_StartTransition(RawGeckoElement* element, nsCSSProperty property,
+                           RawServoAnimationValue* from, RawServoAnimationValue* to,
+                           float duration_ms, float delay_ms,
+                           const ServoTimingFunction* timing_function);
+ServoComputedValuesStrong Servo_GetComputedValues(RawGeckoElement* element, RawServoStyleSet* set);
+ServoComputedValuesStrong Servo_GetComputedValuesForTextNode(RawGeckoNode* node);
+ServoComputedValuesStrong Servo_ComputedValues_Inherit(ServoComputedValues* parent);
+ServoComputedValuesStrong Servo_ComputedValues_GetInitialValues(RawServoStyleSet* set);
+ServoComputedValuesStrong Servo_StyleSet_GetComputedValuesForAnonymousBox(RawServoStyleSet* set,
+                                                                          ServoComputedValues* parentStyleOrNull,
+                                                                          nsIAtom* pseudoTag);
+ServoComputedValuesStrong Servo_GetComputedValuesForAnonymousBox(ServoComputedValues* parentStyleOrNull,
+                                                                 nsIAtom* pseudoTag);
+ServoComputedValuesStrong Servo_GetComputedValuesForPseudo(RawGeckoElement* element,
+                                                           nsIAtom* pseudo_tag);
+ServoComputedValuesStrong Servo_GetComputedValuesForPseudoElement(ServoComputedValues* parent_style,
+                                                                  RawGeckoElement* match_element,
+                                                                  nsIAtom* pseudo_tag,
+                                                                  bool is_probe);
+ServoComputedValuesStrong Servo_ResolveStyleLazily(RawGeckoElement* element, nsIAtom* pseudo_tag,
+                                                   RawServoStyleSet* set);
+ServoComputedValuesStrong Servo_ResolveStyleForNAC(RawGeckoElement* element, ServoComputedValues* originating_style,
+                                                   nsIAtom* pseudo_tag, RawServoStyleSet* set);
+ServoComputedValuesStrong Servo_ResolveStyleWithAddedDeclarations(RawGeckoElement* element,
+                                                                  RawServoStyleSet* set,
+                                                                  RawServoDeclarationBlock* extra_block,
+                                                                  uint8_t level);
+ServoComputedValuesStrong Servo_ComputedValues_GetVisitedStyle(ServoComputedValues* values);
+size_t Servo_ComputedValues_GetRuleNode(ServoComputedValues* values);
+ServoStyleDisplay Servo_GetComputedDisplay(ServoComputedValues* values);
+ServoStylePosition Servo_GetComputedPosition(ServoComputedValues* values);
+// float and clear are always physical: the logical values resolve against the direction.
+ServoStyleFloat Servo_GetComputedFloat(ServoComputedValues* values);
+ServoStyleClear Servo_GetComputedClear(ServoComputedValues* values);
+// The bits are: 1 << 0 transform, 1 << 1 opacity, 1 << 2 scroll-position, 1 << 3 contents,
+// 1 << 4 a property that creates a stacking context, 1 << 5 any other property.
+uint64_t Servo_GetComputedWillChangeBits(ServoComputedValues* values);
+// The bits are: 1 << 0 size, 1 << 1 layout, 1 << 2 style, 1 << 3 paint.
+uint8_t Servo_GetComputedContain(ServoComputedValues* values);
+ServoStyleIsolation Servo_GetComputedIsolation(ServoComputedValues* values);
+ServoLengthOrPercentageOrAuto Servo_GetComputedWidth(ServoComputedValues* values);
+ServoLengthOrPercentageOrAuto Servo_GetComputedHeight(ServoComputedValues* values);
+// The NS_STYLE_OVERFLOW_* values, once the axes have been made to agree.
+uint8_t Servo_GetComputedOverflowX(ServoComputedValues* values);
+uint8_t Servo_GetComputedOverflowY(ServoComputedValues* values);
+void Servo_GetScrollFrameInfo(ServoComputedValues* values, ServoScrollFrameInfo* info);
+bool Servo_GetComputedScrollSnapCoordinate(ServoComputedValues* values, uint32_t index,
+                                           ServoLengthOrPercentageOrAuto* x,
+                                           ServoLengthOrPercentageOrAuto* y);
+void Servo_GetComputedTextInfo(ServoComputedValues* values, ServoTextStyleFFI* out);
+ServoTransformOperation* Gecko_TransformList_SetLength(RawGeckoTransformList* list,
+                                                       uint32_t length);
+bool Servo_GetComputedTransform(ServoComputedValues* values, RawGeckoTransformList* out_list);
+ServoShadowItem* Gecko_ShadowArray_SetLength(RawGeckoShadowArray* array, uint32_t length);
+bool Servo_GetComputedTextShadow(ServoComputedValues* values, RawGeckoShadowArray* out_array);
+void Servo_GetComputedBorderInfo(ServoComputedValues* values, ServoBorderInfo* out);
+ServoGradientStop* Gecko_GradientStopArray_SetLength(RawGeckoGradientStopArray* array, uint32_t length);
+// Fills |out_image| with the image of the background layer at |layer_index|, counting from the
+// top one. Returns false, leaving it alone, past the last layer.
+bool Servo_GetComputedImageAt(ServoComputedValues* values, uint32_t layer_index, ServoImageInfo* out_image);
+// The number of background layers, which is the number of images of background-image.
+uint32_t Servo_GetComputedBackgroundLayerCount(ServoComputedValues* values);
+// Fills |out| with the background layer at |index|, counting from the top one, whose
+// properties other than the image repeat their values if they have fewer than there are
+// layers. Returns false, leaving |out| alone, past the last layer.
+bool Servo_GetComputedBackgroundLayerAt(ServoComputedValues* values, uint32_t index, ServoBackgroundLayer* out);
+ServoFontFeature* Gecko_FontFeatureArray_SetLength(RawGeckoFontFeatureArray* array, uint32_t length);
+bool Servo_GetComputedFontFeatureSettings(ServoComputedValues* values,
+                                          RawGeckoFontFeatureArray* out_array);
+void Gecko_ContentItemList_Append(RawGeckoContentItemList* list, const ServoContentItem* item);
+ServoContentKind Servo_GetComputedContent(ServoComputedValues* values, RawGeckoContentItemList* out_items);
+void Servo_GetComputedFlexInfo(ServoComputedValues* values, ServoFlexInfo* out);
+void Servo_GetComputedColumnInfo(ServoComputedValues* values, ServoColumnInfo* out);
+uint8_t Servo_GetComputedDirection(ServoComputedValues* values);
+void Servo_GetComputedTextOverflow(ServoComputedValues* values, ServoTextOverflow* out);
+ServoCursorImage* Gecko_CursorImageArray_SetLength(RawGeckoCursorImageArray* array, uint32_t length);
+// Writes the NS_STYLE_CURSOR_* keyword of the cursor to |out_keyword|, and fills |out_images|
+// with the images to try before falling back to it, in order.
+void Servo_GetComputedCursor(ServoComputedValues* values, uint8_t* out_keyword,
+                             RawGeckoCursorImageArray* out_images);
+void Servo_GetComputedUIInfo(ServoComputedValues* values, ServoUIInfo* out);
+ServoCounterItem* Gecko_CounterItemArray_SetLength(RawGeckoCounterItemArray* array, uint32_t length);
+// Fills |out_list| with the entries of counter-reset if |which| is 0, counter-increment if it's
+// 1 and counter-set if it's 2, in order. The increment of list-item that display: list-item
+// implies comes last, unless counter-increment names list-item itself. Any other |which| gets
+// an empty list.
+void Servo_GetComputedCounterData(ServoComputedValues* values, uint8_t which,
+                                  RawGeckoCounterItemArray* out_list);
+// Fills |out| with the initial values of the nsStyle* struct it stands for.
+void Gecko_Construct_nsStyleDisplay(nsStyleDisplay_FFI* out);
+void Servo_GetStyleDisplay(ServoComputedValues* values, nsStyleDisplay_FFI* out);
+void Gecko_Construct_nsStyleMargin(nsStyleMargin_FFI* out);
+void Servo_GetStyleMargin(ServoComputedValues* values, nsStyleMargin_FFI* out);
+void Gecko_Construct_nsStylePadding(nsStylePadding_FFI* out);
+void Servo_GetStylePadding(ServoComputedValues* values, nsStylePadding_FFI* out);
+void Gecko_Construct_nsStylePosition(nsStylePosition_FFI* out);
+void Servo_GetStylePosition(ServoComputedValues* values, nsStylePosition_FFI* out);
+void Gecko_Construct_nsStyleVisibility(nsStyleVisibility_FFI* out);
+void Servo_GetStyleVisibility(ServoComputedValues* values, nsStyleVisibility_FFI* out);
+bool Servo_GetComputedPropertyValue(ServoComputedValues* values, nsCSSProperty property,
+                                    nsString* value);
+bool Servo_GetCustomPropertyValue(ServoComputedValues* values, const uint8_t* name,
+                                  uint32_t name_length, nsString* value);
+uint32_t Servo_GetCustomPropertiesCount(ServoComputedValues* values);
+bool Servo_ComputedValues_EqualStructs(ServoComputedValues* a, ServoComputedValues* b,
+                                       uint8_t struct_id);
+bool Servo_ComputedValues_Equals(ServoComputedValues* a, ServoComputedValues* b);
+void Servo_AddRefComputedValues(ServoComputedValues* arg1);
+void Servo_ReleaseComputedValues(ServoComputedValues* arg1);
+void Servo_NoteExplicitHints(RawGeckoElement* element, ServoRestyleHint restyle_hint,
+                             nsChangeHint change_hint);
+void Servo_RestyleDocument(RawGeckoDocument* doc, RawServoStyleSet* set);
+void Servo_RestyleForAnimations(RawGeckoDocument* doc, RawServoStyleSet* set);
+void Servo_PerDocumentData_RestyleDocument(RawServoPerDocumentData* data);
+void Servo_RestyleSubtree(RawGeckoNode* node, RawServoStyleSet* set);
+nsChangeHint Servo_ComputedValues_CalcDifference(ServoComputedValues* old,
+                                                 ServoComputedValues* new);
+nsChangeHint Servo_TakeChangeHint(RawGeckoElement* element);
+nsChangeHint Servo_GetDescendantChangeHints(RawGeckoElement* element);
+ServoRestyleHint Servo_ComputeRestyleHint(RawGeckoElement* element, ServoElementSnapshot* snapshot,
+                                          RawServoStyleSet* set);
+bool Servo_ComputeDocumentStateDependentRestyle(RawServoStyleSet* set, uint64_t changed_bits);
+bool Servo_StyleSet_MightHaveAttributeDependency(RawServoStyleSet* set, nsIAtom* name);
+bool Servo_StyleSet_HasStateDependency(RawServoStyleSet* set, uint64_t state_bits);
+bool Servo_StyleSet_HasAttributeDependency(RawServoStyleSet* set, nsIAtom* attr_atom);
+bool Servo_StyleSet_HasDocumentStateDependency(RawServoStyleSet* set, uint64_t bits);
+ServoRestyleHint Servo_PerDocumentData_ComputeRestyleHint(RawGeckoElement* element,
+                                                          ServoElementSnapshot* snapshot,
+                                                          RawServoPerDocumentData* data);
+void Servo_NoteElementSnapshot(RawServoPerDocumentData* data, RawGeckoElement* element,
+                               uint8_t snapshot_flags);
+void Servo_ForgetElementSnapshot(RawServoPerDocumentData* data, RawGeckoElement* element);
+// Makes room for |length| attributes in |snapshot|, for Gecko_SnapshotAttributes to fill in,
+// dropping any it had. The entries are zeroed.
+ServoAttrSnapshot* Servo_AttributeSnapshot_SetLength(RawServoAttributeSnapshot* snapshot, uint32_t length);
+
+} // extern "C"
+
+#endif // mozilla_ServoBindings_h
@@ -0,0 +1,829 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The functions Gecko and Servo call each other through.
+//!
+//! The types they pass around are in `structs`, which tools/regen_bindings.sh
+//! generates from Gecko's ServoBindings.h, and in `structs_ffi` for the structs
+//! whose fields both sides use. The functions are kept here by hand, so that
+//! regenerating can't drop the ones added since, and tests/check_bindings.rs
+//! checks them against ServoBindings.h next to this file, the copy of Gecko's
+//! declarations that has to be kept in sync with Gecko's own.
+
+pub use self::structs::*;
+pub use self::structs_ffi::*;
+
+mod structs;
+mod structs_ffi;
+
+extern "C" {
+    pub fn Gecko_ChildrenCount(node: *mut RawGeckoNode) -> u32;
+    pub fn Gecko_NodeIsElement(node: *mut RawGeckoNode) -> bool;
+    pub fn Gecko_NodeIsDocument(node: *mut RawGeckoNode) -> bool;
+    pub fn Gecko_IsInDocument(node: *mut RawGeckoNode) -> bool;
+    pub fn Gecko_GetOwnerDocument(node: *mut RawGeckoNode)
+     -> *mut RawGeckoDocument;
+    pub fn Gecko_GetDocumentState(document: *mut RawGeckoDocument) -> u64;
+    pub fn Gecko_IsChromeDocument(document: *mut RawGeckoDocument) -> bool;
+    pub fn Gecko_IsSignificantChild(node: *mut RawGeckoNode,
+                                    text_is_significant: bool) -> bool;
+    pub fn Gecko_GetNodeFlags(node: *mut RawGeckoNode) -> u32;
+    pub fn Gecko_SetNodeFlags(node: *mut RawGeckoNode, flags: u32);
+    pub fn Gecko_UnsetNodeFlags(node: *mut RawGeckoNode, flags: u32);
+    pub fn Gecko_GetNodeInfo(node: *mut RawGeckoNode, out: *mut NodeInfoFFI);
+    pub fn Gecko_GetParentNode(node: *mut RawGeckoNode) -> *mut RawGeckoNode;
+    pub fn Gecko_GetFirstChild(node: *mut RawGeckoNode) -> *mut RawGeckoNode;
+    pub fn Gecko_GetLastChild(node: *mut RawGeckoNode) -> *mut RawGeckoNode;
+    pub fn Gecko_GetChildren(node: *mut RawGeckoNode,
+                             children: *mut *mut RawGeckoNode, capacity: u32)
+     -> u32;
+    pub fn Gecko_GetPrevSibling(node: *mut RawGeckoNode) -> *mut RawGeckoNode;
+    pub fn Gecko_GetNextSibling(node: *mut RawGeckoNode) -> *mut RawGeckoNode;
+    pub fn Gecko_GetParentElement(element: *mut RawGeckoElement)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetFirstChildElement(element: *mut RawGeckoElement)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetLastChildElement(element: *mut RawGeckoElement)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetPrevSiblingElement(element: *mut RawGeckoElement)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetNextSiblingElement(element: *mut RawGeckoElement)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetChildIndexHint(element: *mut RawGeckoElement) -> i32;
+    pub fn Gecko_GetDocumentElement(document: *mut RawGeckoDocument)
+     -> *mut RawGeckoElement;
+    pub fn Gecko_GetDocumentCompatMode(document: *mut RawGeckoDocument)
+     -> u8;
+    pub fn Gecko_ElementState(element: *mut RawGeckoElement) -> u64;
+    pub fn Gecko_IsHTMLElementInHTMLDocument(element: *mut RawGeckoElement)
+     -> bool;
+    pub fn Gecko_IsInQuirksModeDocument(element: *mut RawGeckoElement)
+     -> bool;
+    pub fn Gecko_IsLink(element: *mut RawGeckoElement) -> bool;
+    pub fn Gecko_IsTextNode(node: *mut RawGeckoNode) -> bool;
+    pub fn Gecko_IsNativeAnonymous(element: *mut RawGeckoElement) -> bool;
+    pub fn Gecko_IsVisitedLink(element: *mut RawGeckoElement) -> bool;
+    pub fn Gecko_IsUnvisitedLink(element: *mut RawGeckoElement) -> bool;
+    pub fn Gecko_IsRootElement(element: *mut RawGeckoElement) -> bool;
+    pub fn Gecko_MatchesMozPseudoClass(element: *mut RawGeckoElement,
+                                       pseudo_class: *mut nsIAtom) -> bool;
+    pub fn Gecko_GetNodeData(node: *mut RawGeckoNode) -> *mut ServoNodeData;
+    pub fn Gecko_SetNodeData(node: *mut RawGeckoNode,
+                             data: *mut ServoNodeData);
+    pub fn Servo_DropNodeData(data: *mut ServoNodeData);
+    pub fn Servo_DropNodeDataRecursive(root: *mut RawGeckoNode);
+    pub fn Servo_DropNodeDataRecursiveLater(root: *mut RawGeckoNode);
+    pub fn Servo_ClearNodeStyleData(node: *mut RawGeckoNode, keep_for_diffing: bool);
+    pub fn Servo_FlushDroppedNodeData();
+    pub fn Gecko_IsMainThread() -> bool;
+    pub fn Gecko_DispatchRelease(ptr: *mut ::std::os::raw::c_void,
+                                 release_fn: ServoReleaseFn);
+    pub fn Servo_AssertNoOffThreadDrops();
+    pub fn Servo_StylesheetFromUTF8Bytes(bytes: *const u8, length: u32,
+                                         base: *const u8, base_length: u32,
+                                         origin: StyleSheetOrigin,
+                                         compat_mode: u8,
+                                         error_context:
+                                             *mut ::std::os::raw::c_void,
+                                         extra_data: *mut RawGeckoURLExtraData,
+                                         out_was_lossy: *mut bool)
+     -> RawServoStyleSheetStrong;
+    pub fn Servo_StylesheetFromUTF16Bytes(bytes: *const u16, length: u32,
+                                          base: *const u8, base_length: u32,
+                                          origin: StyleSheetOrigin,
+                                          compat_mode: u8,
+                                          error_context:
+                                              *mut ::std::os::raw::c_void,
+                                          extra_data: *mut RawGeckoURLExtraData)
+     -> RawServoStyleSheetStrong;
+    pub fn Servo_StyleSheet_FromUTF8BytesAsync(bytes: *const u8, length: u32,
+                                               base: *const u8,
+                                               base_length: u32,
+                                               origin: StyleSheetOrigin,
+                                               compat_mode: u8,
+                                               extra_data: *mut RawGeckoURLExtraData,
+                                               callback_context:
+                                                   *mut ::std::os::raw::c_void,
+                                               set: *mut RawServoStyleSet);
+    pub fn Servo_StyleSheet_CancelAsyncParse(callback_context:
+                                                 *mut ::std::os::raw::c_void)
+     -> bool;
+    pub fn Gecko_StyleSheetLoadComplete(callback_context:
+                                            *mut ::std::os::raw::c_void,
+                                        sheet: RawServoStyleSheetStrong);
+    pub fn Gecko_AnnotateCrashReport(key: *const u8, key_length: u32,
+                                     value: *const u8, value_length: u32);
+    pub fn Gecko_Log(level: u8, module: *const ::std::os::raw::c_char,
+                     message: *const ::std::os::raw::c_char, msg_len: u32);
+    pub fn Gecko_GetFontMetrics(font: *const nsStyleFont, out_x_height: *mut f32,
+                                out_ch_width: *mut f32) -> bool;
+    pub fn Gecko_GetBaseFontSize(generic_id: u8, language: *mut nsIAtom) -> f32;
+    pub fn Gecko_GetLookAndFeelColor(color_id: i32, use_standins: bool,
+                                     out_color: *mut u32) -> bool;
+    pub fn Gecko_GetSystemFont(font_id: i32, out: *mut SystemFontData) -> bool;
+    pub fn Gecko_ReportCSSError(sheet_context: *mut ::std::os::raw::c_void,
+                                message: *const ::std::os::raw::c_char,
+                                line: u32, column: u32);
+    pub fn Gecko_ReportCSSErrorWithDetails(sheet_context:
+                                               *mut ::std::os::raw::c_void,
+                                           error_kind: u8,
+                                           selector: *const u8, len: u32,
+                                           offset: u32, line: u32,
+                                           column: u32);
+    pub fn Gecko_LoadStyleSheet(parent_sheet_context:
+                                    *mut ::std::os::raw::c_void,
+                                extra_data: *mut RawGeckoURLExtraData,
+                                import_rule: *mut RawServoImportRule,
+                                url: *const u8, url_len: u32,
+                                media: *const u8, media_len: u32)
+     -> *mut ::std::os::raw::c_void;
+    pub fn Gecko_LoadImage(url: *const u8, url_len: u32,
+                           extra_data: *mut RawGeckoURLExtraData)
+     -> *mut ::std::os::raw::c_void;
+    pub fn Gecko_ReleaseImageRequest(request: *mut ::std::os::raw::c_void);
+    pub fn Gecko_AddRefURLExtraData(extra_data: *mut RawGeckoURLExtraData);
+    pub fn Gecko_ReleaseURLExtraData(extra_data: *mut RawGeckoURLExtraData);
+    pub fn Servo_StyleSheet_SetImportedSheet(import_rule: *mut RawServoImportRule,
+                                             sheet: *mut RawServoStyleSheet)
+     -> bool;
+    pub fn Servo_AddRefImportRule(import_rule: *mut RawServoImportRule);
+    pub fn Servo_ReleaseImportRule(import_rule: *mut RawServoImportRule);
+    pub fn Servo_AddRefStyleSheet(sheet: *mut RawServoStyleSheet);
+    pub fn Servo_ReleaseStyleSheet(sheet: *mut RawServoStyleSheet);
+    pub fn Servo_AppendStyleSheet(sheet: *mut RawServoStyleSheet,
+                                  set: *mut RawServoStyleSet);
+    pub fn Servo_PrependStyleSheet(sheet: *mut RawServoStyleSheet,
+                                   set: *mut RawServoStyleSet);
+    pub fn Servo_StyleSet_AppendStyleSheetToOrigin(set: *mut RawServoStyleSet,
+                                                   sheet: *mut RawServoStyleSheet,
+                                                   origin: StyleSheetOrigin);
+    pub fn Servo_StyleSet_SheetCount(set: *mut RawServoStyleSet,
+                                     origin: StyleSheetOrigin) -> u32;
+    pub fn Servo_StyleSet_SheetAt(set: *mut RawServoStyleSet,
+                                  origin: StyleSheetOrigin, index: u32)
+     -> RawServoStyleSheetStrong;
+    pub fn Servo_RemoveStyleSheet(sheet: *mut RawServoStyleSheet,
+                                  set: *mut RawServoStyleSet);
+    pub fn Servo_StyleSet_InsertStyleSheetBefore(set: *mut RawServoStyleSet,
+                                                 sheet: *mut RawServoStyleSheet,
+                                                 reference: *mut RawServoStyleSheet);
+    pub fn Servo_StyleSet_ReplaceStyleSheets(set: *mut RawServoStyleSet,
+                                             sheets: *const *mut RawServoStyleSheet,
+                                             count: u32);
+    pub fn Servo_StyleSet_NoteStyleSheetsChanged(set: *mut RawServoStyleSet,
+                                                 origin: StyleSheetOrigin);
+    pub fn Servo_StyleSet_InvalidateForSheet(doc: *mut RawGeckoDocument,
+                                             set: *mut RawServoStyleSet,
+                                             sheet: *mut RawServoStyleSheet);
+    pub fn Servo_StyleSet_RuleChanged(set: *mut RawServoStyleSet, rule: *mut RawServoStyleRule);
+    pub fn Servo_StyleSet_NoteThemeChanged(set: *mut RawServoStyleSet);
+    pub fn Servo_StyleSet_SetAuthorStyleDisabled(set: *mut RawServoStyleSet,
+                                                 disabled: bool);
+    pub fn Servo_StyleSet_GetAuthorStyleDisabled(set: *mut RawServoStyleSet)
+     -> bool;
+    pub fn Servo_StyleSet_SetMedium(set: *mut RawServoStyleSet,
+                                    medium: *mut nsIAtom) -> bool;
+    pub fn Servo_StyleSet_GetMedium(set: *mut RawServoStyleSet)
+     -> *mut nsIAtom;
+    pub fn Servo_Test_Panic() -> bool;
+    pub fn Servo_Test_TakeChildFFICallCount() -> u32;
+    pub fn Servo_Test_GetStrongRefCount() -> i32;
+    pub fn Servo_StyleSet_GetRebuildCount(set: *mut RawServoStyleSet,
+                                          origin: StyleSheetOrigin) -> u32;
+    pub fn Servo_StyleSet_GetAnonymousBoxCascadeCount(set: *mut RawServoStyleSet)
+     -> u32;
+    pub fn Servo_StyleSheet_SizeOfIncludingThis(malloc_size_of: MallocSizeOfFn,
+                                                sheet: *mut RawServoStyleSheet)
+     -> usize;
+    pub fn Servo_StyleSet_SizeOf(malloc_size_of: MallocSizeOfFn,
+                                 set: *mut RawServoStyleSet) -> usize;
+    pub fn Servo_ComputedValues_SizeOf(malloc_size_of: MallocSizeOfFn,
+                                       values: *mut ServoComputedValues)
+     -> usize;
+    pub fn Servo_StyleSheet_UpdateFromUTF8Bytes(sheet: *mut RawServoStyleSheet,
+                                                bytes: *const u8, length: u32,
+                                                error_context:
+                                                    *mut ::std::os::raw::c_void,
+                                                extra_data:
+                                                    *mut RawGeckoURLExtraData);
+    pub fn Servo_StyleSheetHasRules(sheet: *mut RawServoStyleSheet) -> bool;
+    pub fn Servo_StyleSheet_Clone(sheet: *mut RawServoStyleSheet)
+     -> RawServoStyleSheetStrong;
+    pub fn Servo_StyleSheet_IntoShared(sheet: *mut RawServoStyleSheet)
+     -> RawServoSharedStyleSheetStrong;
+    pub fn Servo_SharedStyleSheet_AddRef(shared: *mut RawServoSharedStyleSheet);
+    pub fn Servo_SharedStyleSheet_Release(shared:
+                                              *mut RawServoSharedStyleSheet);
+    pub fn Servo_SharedStyleSheet_SizeOfRuleMaps(shared:
+                                                     *mut RawServoSharedStyleSheet)
+     -> usize;
+    pub fn Servo_StyleSet_AppendSharedStyleSheet(set: *mut RawServoStyleSet,
+                                                 shared:
+                                                     *mut RawServoSharedStyleSheet);
+    pub fn Servo_StyleSet_AppendScopedStyleSheet(set: *mut RawServoStyleSet,
+                                                 sheet: *mut RawServoStyleSheet,
+                                                 scope_root: *mut RawGeckoElement);
+    pub fn Servo_StyleSet_RemoveScopedStyleSheets(set: *mut RawServoStyleSet,
+                                                  scope_root: *mut RawGeckoElement);
+    pub fn Servo_StyleSheet_GetRules(sheet: *mut RawServoStyleSheet)
+     -> RawServoCssRulesStrong;
+    pub fn Servo_CssRules_AddRef(rules: *mut RawServoCssRules);
+    pub fn Servo_CssRules_Release(rules: *mut RawServoCssRules);
+    pub fn Servo_CssRules_GetCount(rules: *mut RawServoCssRules) -> u32;
+    pub fn Servo_CssRules_GetTypeAt(rules: *mut RawServoCssRules, index: u32)
+     -> u16;
+    pub fn Servo_CssRules_GetStyleRuleAt(rules: *mut RawServoCssRules,
+                                         index: u32) -> RawServoStyleRuleStrong;
+    pub fn Servo_CssRules_GetCounterStyleRuleAt(rules: *mut RawServoCssRules,
+                                                index: u32)
+     -> RawServoCounterStyleRuleStrong;
+    pub fn Servo_CssRules_GetNestedRulesAt(rules: *mut RawServoCssRules,
+                                           index: u32)
+     -> RawServoCssRulesStrong;
+    pub fn Servo_CssRules_GetUnsupportedRuleTextAt(rules:
+                                                       *mut RawServoCssRules,
+                                                   index: u32,
+                                                   result: *mut nsString)
+     -> bool;
+    pub fn Servo_CssRules_InsertRule(rules: *mut RawServoCssRules,
+                                     sheet: *mut RawServoStyleSheet,
+                                     rule: *const u8, rule_length: u32,
+                                     index: u32, nested: bool,
+                                     error_context:
+                                         *mut ::std::os::raw::c_void) -> nsresult;
+    pub fn Servo_CssRules_DeleteRule(rules: *mut RawServoCssRules,
+                                     index: u32) -> nsresult;
+    pub fn Servo_StyleRule_AddRef(rule: *mut RawServoStyleRule);
+    pub fn Servo_StyleRule_Release(rule: *mut RawServoStyleRule);
+    pub fn Servo_StyleRule_GetSelectorText(rule: *mut RawServoStyleRule,
+                                           result: *mut nsString);
+    pub fn Servo_StyleRule_GetSelectorCount(rule: *mut RawServoStyleRule)
+     -> u32;
+    pub fn Servo_StyleRule_GetSelectorTextAt(rule: *mut RawServoStyleRule,
+                                             index: u32, result: *mut nsString);
+    pub fn Servo_StyleRule_GetSpecificityAt(rule: *mut RawServoStyleRule,
+                                            index: u32) -> u64;
+    pub fn Servo_StyleRule_SelectorMatchesElement(rule:
+                                                      *mut RawServoStyleRule,
+                                                  element:
+                                                      *mut RawGeckoElement,
+                                                  index: u32) -> bool;
+    pub fn Servo_StyleRule_GetDeclarations(rule: *mut RawServoStyleRule)
+     -> RawServoDeclarationBlockStrong;
+    pub fn Servo_StyleRule_GetCssText(rule: *mut RawServoStyleRule,
+                                      result: *mut nsString);
+    pub fn Servo_StyleRule_GetSourceLocation(rule: *mut RawServoStyleRule,
+                                             out_line: *mut u32,
+                                             out_column: *mut u32);
+    pub fn Servo_GetMatchingRules(element: *mut RawGeckoElement,
+                                  pseudo_tag: *mut nsIAtom,
+                                  set: *mut RawServoStyleSet,
+                                  out: *mut RawGeckoRuleList);
+    pub fn Gecko_RuleListAppend(list: *mut RawGeckoRuleList,
+                                sheet: *mut RawServoStyleSheet,
+                                rule: RawServoStyleRuleStrong,
+                                selector_index: u32, specificity: u32);
+    pub fn Gecko_RuleListAppendDeclarations(list: *mut RawGeckoRuleList,
+                                            declarations:
+                                                RawServoDeclarationBlockStrong,
+                                            is_style_attribute: bool);
+    pub fn Servo_StyleSet_ResolvePageStyle(set: *mut RawServoStyleSet,
+                                           page_name: *mut nsIAtom,
+                                           page_flags: u8,
+                                           out_block:
+                                               *mut RawServoDeclarationBlockStrong,
+                                           out_size: *mut ServoPageSize);
+    pub fn Servo_StyleSet_GetKeyframesForName(set: *mut RawServoStyleSet,
+                                              name: *mut nsIAtom,
+                                              keyframes: *mut RawGeckoKeyframeList)
+     -> bool;
+    pub fn Servo_ParseEasing(value: *const u8, length: u32,
+                             out: *mut ServoTimingFunction) -> bool;
+    pub fn Servo_EasingEvaluateAt(timing: *const ServoTimingFunction,
+                                  progress: f64, before_flag: bool) -> f64;
+    pub fn Servo_StyleSet_GetCounterStyleRule(set: *mut RawServoStyleSet,
+                                              name: *mut nsIAtom)
+     -> *mut RawServoCounterStyleRule;
+    pub fn Servo_CounterStyleRule_AddRef(rule: *mut RawServoCounterStyleRule);
+    pub fn Servo_CounterStyleRule_Release(rule: *mut RawServoCounterStyleRule);
+    pub fn Servo_CounterStyleRule_GetName(rule: *mut RawServoCounterStyleRule)
+     -> *mut nsIAtom;
+    pub fn Servo_CounterStyleRule_GetSystem(rule: *mut RawServoCounterStyleRule,
+                                            out_first_symbol_value: *mut i32,
+                                            out_extended: *mut *mut nsIAtom)
+     -> u8;
+    pub fn Servo_CounterStyleRule_GetSymbolCount(rule:
+                                                     *mut RawServoCounterStyleRule)
+     -> u32;
+    pub fn Servo_CounterStyleRule_GetSymbolAt(rule: *mut RawServoCounterStyleRule,
+                                              index: u32,
+                                              result: *mut nsString) -> bool;
+    pub fn Servo_CounterStyleRule_GetAdditiveSymbolCount(rule:
+                                                             *mut RawServoCounterStyleRule)
+     -> u32;
+    pub fn Servo_CounterStyleRule_GetAdditiveSymbolAt(rule:
+                                                          *mut RawServoCounterStyleRule,
+                                                      index: u32,
+                                                      out_weight: *mut u32,
+                                                      result: *mut nsString)
+     -> bool;
+    pub fn Servo_CounterStyleRule_GetNegative(rule: *mut RawServoCounterStyleRule,
+                                              out_before: *mut nsString,
+                                              out_after: *mut nsString)
+     -> bool;
+    pub fn Servo_CounterStyleRule_GetPrefix(rule: *mut RawServoCounterStyleRule,
+                                            result: *mut nsString) -> bool;
+    pub fn Servo_CounterStyleRule_GetSuffix(rule: *mut RawServoCounterStyleRule,
+                                            result: *mut nsString) -> bool;
+    pub fn Servo_CounterStyleRule_GetRangeCount(rule:
+                                                    *mut RawServoCounterStyleRule)
+     -> u32;
+    pub fn Servo_CounterStyleRule_GetRangeAt(rule: *mut RawServoCounterStyleRule,
+                                             index: u32, out_lower: *mut i32,
+                                             out_upper: *mut i32) -> bool;
+    pub fn Servo_CounterStyleRule_GetPad(rule: *mut RawServoCounterStyleRule,
+                                         out_width: *mut u32,
+                                         result: *mut nsString) -> bool;
+    pub fn Servo_CounterStyleRule_GetFallback(rule:
+                                                  *mut RawServoCounterStyleRule)
+     -> *mut nsIAtom;
+    pub fn Servo_CounterStyleRule_GetSpeakAs(rule: *mut RawServoCounterStyleRule,
+                                             out_counter_style:
+                                                 *mut *mut nsIAtom) -> u8;
+    pub fn Gecko_AppendKeyframe(keyframes: *mut RawGeckoKeyframeList,
+                                offset: f32,
+                                timing_function: *const ServoTimingFunction,
+                                declarations: RawServoDeclarationBlockStrong);
+    pub fn Servo_StyleSet_GetFontFaceRules(set: *mut RawServoStyleSet,
+                                           rules: *mut RawGeckoFontFaceRuleList);
+    pub fn Gecko_AppendFontFaceRule(rules: *mut RawGeckoFontFaceRuleList,
+                                    sheet: *mut RawServoStyleSheet,
+                                    family: *const u8, family_length: u32,
+                                    weight: u16, style: u8, stretch: i16)
+     -> *mut RawGeckoFontFaceRule;
+    pub fn Gecko_FontFaceRule_AppendURLSource(rule: *mut RawGeckoFontFaceRule,
+                                              url: *const u8,
+                                              url_length: u32);
+    pub fn Gecko_FontFaceRule_AppendFormatHint(rule: *mut RawGeckoFontFaceRule,
+                                               hint: *const u8,
+                                               hint_length: u32);
+    pub fn Gecko_FontFaceRule_AppendLocalSource(rule: *mut RawGeckoFontFaceRule,
+                                                name: *const u8,
+                                                name_length: u32);
+    pub fn Gecko_FontFaceRule_AppendUnicodeRange(rule: *mut RawGeckoFontFaceRule,
+                                                 start: u32, end: u32);
+    pub fn Servo_MediaList_Parse(bytes: *const u8, length: u32)
+     -> RawServoMediaListStrong;
+    pub fn Servo_AddRefMediaList(list: *mut RawServoMediaList);
+    pub fn Servo_ReleaseMediaList(list: *mut RawServoMediaList);
+    pub fn Servo_StyleSheet_SetMediaList(sheet: *mut RawServoStyleSheet,
+                                         list: *mut RawServoMediaList);
+    pub fn Servo_StyleSet_MediaFeatureValuesChanged(set: *mut RawServoStyleSet,
+                                                    viewport_width: f32,
+                                                    viewport_height: f32,
+                                                    device_pixel_ratio: f32)
+     -> bool;
+    pub fn Servo_StyleSet_SetDevice(set: *mut RawServoStyleSet,
+                                    viewport_width_px: f32,
+                                    viewport_height_px: f32,
+                                    device_pixel_ratio: f32,
+                                    root_font_size_px: f32);
+    pub fn Servo_StyleSheet_SetDisabled(sheet: *mut RawServoStyleSheet,
+                                        disabled: bool);
+    pub fn Servo_StyleSheet_GetDisabled(sheet: *mut RawServoStyleSheet) -> bool;
+    pub fn Servo_StyleSheet_GetOrigin(sheet: *mut RawServoStyleSheet) -> u8;
+    pub fn Servo_StyleSheet_SetTitle(sheet: *mut RawServoStyleSheet,
+                                     title: *const u8, length: u32) -> bool;
+    pub fn Servo_StyleSheet_GetTitle(sheet: *mut RawServoStyleSheet,
+                                     result: *mut nsString);
+    pub fn Servo_StyleSheet_GetDebugInfo(sheet: *mut RawServoStyleSheet,
+                                         result: *mut nsString);
+    pub fn Servo_Initialize();
+    pub fn Servo_Shutdown();
+    pub fn Servo_InitializeThreadPool(num_threads: u32);
+    pub fn Servo_SetDeterministicTraversal(enabled: bool);
+    pub fn Servo_SetRefCountLogging(enabled: bool);
+    pub fn Servo_DumpLiveObjects();
+    pub fn Servo_SetTraversalStatisticsEnabled(enabled: bool);
+    pub fn Servo_GetTraversalStatistics(out: *mut ServoTraversalStatistics);
+    pub fn Servo_InitStyleSet() -> *mut RawServoStyleSet;
+    pub fn Servo_DropStyleSet(set: *mut RawServoStyleSet);
+    pub fn Servo_PerDocumentData_Create(document: *mut RawGeckoDocument)
+     -> *mut RawServoPerDocumentData;
+    pub fn Servo_StyleSet_Shutdown(set: *mut RawServoStyleSet);
+    pub fn Servo_PerDocumentData_Drop(data: *mut RawServoPerDocumentData);
+    pub fn Servo_PerDocumentData_GetStyleSet(data: *mut RawServoPerDocumentData)
+     -> *mut RawServoStyleSet;
+    pub fn Gecko_GetAttrAsUTF8(element: *mut RawGeckoElement, ns: *mut nsIAtom,
+                               name: *mut nsIAtom, length: *mut u32)
+     -> *const ::std::os::raw::c_char;
+    pub fn Gecko_GetAttrsAsUTF8(element: *mut RawGeckoElement,
+                                name: *mut nsIAtom,
+                                values: *mut *const ::std::os::raw::c_char,
+                                lengths: *mut u32, capacity: u32) -> u32;
+    pub fn Gecko_GetAtomAsUTF16(atom: *mut nsIAtom, length: *mut u32)
+     -> *const u16;
+    pub fn Gecko_AddRefAtom(atom: *mut nsIAtom);
+    pub fn Gecko_ReleaseAtom(atom: *mut nsIAtom);
+    pub fn Gecko_AtomEqualsUTF8(atom: *mut nsIAtom,
+                                str: *const ::std::os::raw::c_char,
+                                length: u32) -> bool;
+    pub fn Gecko_Atomize(string: *const ::std::os::raw::c_char, length: u32)
+     -> *mut nsIAtom;
+    pub fn Gecko_AtomEqualsUTF8IgnoreCase(atom: *mut nsIAtom,
+                                          str: *const ::std::os::raw::c_char,
+                                          length: u32) -> bool;
+    pub fn Gecko_AtomIsStatic(atom: *mut nsIAtom) -> bool;
+    pub fn Gecko_HasAttr(element: *mut RawGeckoElement, ns: *mut nsIAtom,
+                         name: *mut nsIAtom) -> bool;
+    pub fn Gecko_AttrEquals(element: *mut RawGeckoElement, ns: *mut nsIAtom,
+                            name: *mut nsIAtom, str: *mut nsIAtom,
+                            ignoreCase: bool) -> bool;
+    pub fn Gecko_AttrDashEquals(element: *mut RawGeckoElement,
+                                ns: *mut nsIAtom, name: *mut nsIAtom,
+                                str: *mut nsIAtom, ignoreCase: bool) -> bool;
+    pub fn Gecko_AttrIncludes(element: *mut RawGeckoElement, ns: *mut nsIAtom,
+                              name: *mut nsIAtom, str: *mut nsIAtom,
+                              ignoreCase: bool) -> bool;
+    pub fn Gecko_AttrHasSubstring(element: *mut RawGeckoElement,
+                                  ns: *mut nsIAtom, name: *mut nsIAtom,
+                                  str: *mut nsIAtom, ignoreCase: bool) -> bool;
+    pub fn Gecko_AttrHasPrefix(element: *mut RawGeckoElement,
+                               ns: *mut nsIAtom, name: *mut nsIAtom,
+                               str: *mut nsIAtom, ignoreCase: bool) -> bool;
+    pub fn Gecko_AttrHasSuffix(element: *mut RawGeckoElement,
+                               ns: *mut nsIAtom, name: *mut nsIAtom,
+                               str: *mut nsIAtom, ignoreCase: bool) -> bool;
+    pub fn Gecko_LocalName(element: *mut RawGeckoElement) -> *mut nsIAtom;
+    pub fn Gecko_Namespace(element: *mut RawGeckoElement) -> *mut nsIAtom;
+    pub fn Gecko_GetElementId(element: *mut RawGeckoElement) -> *mut nsIAtom;
+    pub fn Gecko_GetLanguage(element: *mut RawGeckoElement) -> *mut nsIAtom;
+    pub fn Gecko_ClassOrClassList(element: *mut RawGeckoElement,
+                                  class_: *mut *mut nsIAtom,
+                                  classList: *mut *mut *mut nsIAtom) -> u32;
+    pub fn Gecko_GetStyleAttrDeclarationBlock(element: *mut RawGeckoElement)
+     -> *mut RawServoDeclarationBlock;
+    pub fn Gecko_GetHTMLPresentationAttrDeclarationBlock(element:
+                                                             *mut RawGeckoElement)
+     -> *mut RawServoDeclarationBlock;
+    pub fn Gecko_GetSMILOverrideDeclarationBlock(element: *mut RawGeckoElement)
+     -> *mut RawServoDeclarationBlock;
+    pub fn Gecko_GetAnimationEffectDeclarationBlock(element:
+                                                        *mut RawGeckoElement,
+                                                    cascade_level: u8)
+     -> *mut RawServoDeclarationBlock;
+    pub fn Gecko_CreateElementSnapshot(element: *mut RawGeckoElement)
+     -> *mut ServoElementSnapshot;
+    pub fn Gecko_DropElementSnapshot(snapshot: *mut ServoElementSnapshot);
+    pub fn Gecko_SnapshotState(snapshot: *mut ServoElementSnapshot) -> u64;
+    pub fn Gecko_SnapshotHasAttrs(snapshot: *mut ServoElementSnapshot)
+     -> bool;
+    pub fn Gecko_SnapshotGetAttrAsUTF8(snapshot: *mut ServoElementSnapshot,
+                                       ns: *mut nsIAtom, name: *mut nsIAtom,
+                                       length: *mut u32)
+     -> *const ::std::os::raw::c_char;
+    pub fn Gecko_SnapshotGetElementId(snapshot: *mut ServoElementSnapshot)
+     -> *mut nsIAtom;
+    pub fn Gecko_SnapshotClassOrClassList(snapshot: *mut ServoElementSnapshot,
+                                          class_: *mut *mut nsIAtom,
+                                          classList: *mut *mut *mut nsIAtom)
+     -> u32;
+    pub fn Gecko_SnapshotAttributes(element: *mut RawGeckoElement,
+                                    snapshot: *mut RawServoAttributeSnapshot);
+    pub fn Servo_ParseStyleAttribute(bytes: *const u8, length: u32)
+     -> RawServoDeclarationBlockStrong;
+    pub fn Servo_AddRefDeclarationBlock(declarations:
+                                            *mut RawServoDeclarationBlock);
+    pub fn Servo_ReleaseDeclarationBlock(declarations:
+                                             *mut RawServoDeclarationBlock);
+    pub fn Gecko_Utf8SliceToString(string: *mut nsString, bytes: *const u8,
+                                   length: u32);
+    pub fn Servo_DeclarationBlock_GetPropertyValue(declarations:
+                                                       *mut RawServoDeclarationBlock,
+                                                   property: *const u8,
+                                                   property_length: u32,
+                                                   value: *mut nsString);
+    pub fn Servo_DeclarationBlock_GetPropertyIsImportant(declarations:
+                                                             *mut RawServoDeclarationBlock,
+                                                         property: *const u8,
+                                                         property_length: u32)
+     -> bool;
+    pub fn Servo_DeclarationBlock_SetProperty(declarations:
+                                                  *mut RawServoDeclarationBlock,
+                                              property: *const u8,
+                                              property_length: u32,
+                                              value: *const u8,
+                                              value_length: u32,
+                                              is_important: bool) -> bool;
+    pub fn Servo_DeclarationBlock_RemoveProperty(declarations:
+                                                     *mut RawServoDeclarationBlock,
+                                                 property: *const u8,
+                                                 property_length: u32);
+    pub fn Servo_DeclarationBlock_Count(declarations:
+                                            *mut RawServoDeclarationBlock)
+     -> u32;
+    pub fn Servo_DeclarationBlock_GetNthProperty(declarations:
+                                                     *mut RawServoDeclarationBlock,
+                                                 index: u32,
+                                                 result: *mut nsString)
+     -> bool;
+    pub fn Servo_DeclarationBlock_GetCssText(declarations:
+                                                 *mut RawServoDeclarationBlock,
+                                             result: *mut nsString);
+    pub fn Servo_DeclarationBlock_HasImportant(declarations:
+                                                   *mut RawServoDeclarationBlock)
+     -> bool;
+    pub fn Servo_DeclarationBlock_GetPropertyIds(declarations:
+                                                     *mut RawServoDeclarationBlock,
+                                                 out_ids: *mut nsCSSProperty,
+                                                 capacity: u32) -> u32;
+    pub fn Servo_DeclarationBlock_SerializeOneValue(declarations:
+                                                        *mut RawServoDeclarationBlock,
+                                                    property: nsCSSProperty,
+                                                    result: *mut nsString);
+    pub fn Servo_DeclarationBlock_SetIdentStringValue(declarations:
+                                                          *mut RawServoDeclarationBlock,
+                                                      property: nsCSSProperty,
+                                                      value: *mut nsIAtom);
+    pub fn Servo_ParseProperty(property: nsCSSProperty, value: *const u8,
+                               value_length: u32, base: *const u8,
+                               base_length: u32,
+                               out_block: *mut RawServoDeclarationBlockStrong)
+     -> bool;
+    pub fn Servo_Property_IsShorthand(property: nsCSSProperty) -> bool;
+    pub fn Gecko_PropertyList_Append(list: *mut RawGeckoPropertyList,
+                                     property: nsCSSProperty);
+    pub fn Servo_Property_GetLonghands(property: nsCSSProperty,
+                                       out: *mut RawGeckoPropertyList);
+    pub fn Servo_Property_SetEnabled(property: nsCSSProperty,
+                                     enabled_for_content: bool,
+                                     enabled_for_ua: bool);
+    pub fn Servo_CSSSupports(property: *const u8, property_length: u32,
+                             value: *const u8, value_length: u32) -> bool;
+    pub fn Servo_CSSSupportsCondition(condition: *const u8, length: u32)
+     -> bool;
+    pub fn Servo_SelectorList_Parse(bytes: *const u8, length: u32,
+                                    default_namespace: *mut nsIAtom)
+     -> *mut RawServoSelectorList;
+    pub fn Servo_SelectorList_ParseDetailed(bytes: *const u8, length: u32,
+                                            out_error:
+                                                *mut ServoSelectorParseError)
+     -> *mut RawServoSelectorList;
+    pub fn Servo_SelectorList_Drop(list: *mut RawServoSelectorList);
+    pub fn Servo_SelectorList_Matches(element: *mut RawGeckoElement,
+                                      list: *mut RawServoSelectorList) -> bool;
+    pub fn Gecko_ContentList_AppendElement(list: *mut RawGeckoContentList,
+                                           element: *mut RawGeckoElement);
+    pub fn Servo_SelectorList_QueryFirst(root: *mut RawGeckoNode,
+                                         list: *mut RawServoSelectorList)
+     -> *mut RawGeckoElement;
+    pub fn Servo_SelectorList_QueryAll(root: *mut RawGeckoNode,
+                                       list: *mut RawServoSelectorList,
+                                       out: *mut RawGeckoContentList);
+    pub fn Servo_AnimationValue_FromDeclaration(declarations:
+                                                    *mut RawServoDeclarationBlock,
+                                                property: *const u8,
+                                                property_length: u32)
+     -> RawServoAnimationValueStrong;
+    pub fn Servo_AnimationValue_AddRef(value: *mut RawServoAnimationValue);
+    pub fn Servo_AnimationValue_Release(value: *mut RawServoAnimationValue);
+    pub fn Servo_AnimationValues_Interpolate(from: *mut RawServoAnimationValue,
+                                             to: *mut RawServoAnimationValue,
+                                             progress: f64)
+     -> RawServoAnimationValueStrong;
+    pub fn Servo_AnimationValue_Serialize(value: *mut RawServoAnimationValue,
+                                          property: *const u8,
+                                          property_length: u32,
+                                          result: *mut nsString);
+    pub fn Servo_AnimationValue_DeepEqual(a: *mut RawServoAnimationValue,
+                                          b: *mut RawServoAnimationValue)
+     -> bool;
+    pub fn Gecko_StartTransition(element: *mut RawGeckoElement,
+                                 property: nsCSSProperty,
+                                 from: *mut RawServoAnimationValue,
+                                 to: *mut RawServoAnimationValue,
+                                 duration_ms: f32, delay_ms: f32,
+                                 timing_function: *const ServoTimingFunction);
+    pub fn Servo_GetComputedValues(element: *mut RawGeckoElement,
+                                   set: *mut RawServoStyleSet)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_GetComputedValuesForTextNode(node: *mut RawGeckoNode)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ComputedValues_Inherit(parent: *mut ServoComputedValues)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ComputedValues_GetInitialValues(set: *mut RawServoStyleSet)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_StyleSet_GetComputedValuesForAnonymousBox(set:
+                                                               *mut RawServoStyleSet,
+                                                           parentStyleOrNull:
+                                                               *mut ServoComputedValues,
+                                                           pseudoTag:
+                                                               *mut nsIAtom)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_GetComputedValuesForAnonymousBox(parentStyleOrNull:
+                                                      *mut ServoComputedValues,
+                                                  pseudoTag: *mut nsIAtom)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_GetComputedValuesForPseudo(element: *mut RawGeckoElement,
+                                            pseudo_tag: *mut nsIAtom)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_GetComputedValuesForPseudoElement(parent_style:
+                                                       *mut ServoComputedValues,
+                                                   match_element:
+                                                       *mut RawGeckoElement,
+                                                   pseudo_tag: *mut nsIAtom,
+                                                   is_probe: bool)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ResolveStyleLazily(element: *mut RawGeckoElement,
+                                    pseudo_tag: *mut nsIAtom,
+                                    set: *mut RawServoStyleSet)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ResolveStyleForNAC(element: *mut RawGeckoElement,
+                                    originating_style: *mut ServoComputedValues,
+                                    pseudo_tag: *mut nsIAtom,
+                                    set: *mut RawServoStyleSet)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ResolveStyleWithAddedDeclarations(element:
+                                                       *mut RawGeckoElement,
+                                                   set: *mut RawServoStyleSet,
+                                                   extra_block:
+                                                       *mut RawServoDeclarationBlock,
+                                                   level: u8)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ComputedValues_GetVisitedStyle(values:
+                                                    *mut ServoComputedValues)
+     -> ServoComputedValuesStrong;
+    pub fn Servo_ComputedValues_GetRuleNode(values: *mut ServoComputedValues)
+     -> usize;
+    pub fn Servo_GetComputedDisplay(values: *mut ServoComputedValues)
+     -> ServoStyleDisplay;
+    pub fn Servo_GetComputedPosition(values: *mut ServoComputedValues)
+     -> ServoStylePosition;
+    pub fn Servo_GetComputedFloat(values: *mut ServoComputedValues)
+     -> ServoStyleFloat;
+    pub fn Servo_GetComputedClear(values: *mut ServoComputedValues)
+     -> ServoStyleClear;
+    pub fn Servo_GetComputedWillChangeBits(values: *mut ServoComputedValues)
+     -> u64;
+    pub fn Servo_GetComputedContain(values: *mut ServoComputedValues) -> u8;
+    pub fn Servo_GetComputedIsolation(values: *mut ServoComputedValues)
+     -> ServoStyleIsolation;
+    pub fn Servo_GetComputedWidth(values: *mut ServoComputedValues)
+     -> ServoLengthOrPercentageOrAuto;
+    pub fn Servo_GetComputedHeight(values: *mut ServoComputedValues)
+     -> ServoLengthOrPercentageOrAuto;
+    pub fn Servo_GetComputedOverflowX(values: *mut ServoComputedValues) -> u8;
+    pub fn Servo_GetComputedOverflowY(values: *mut ServoComputedValues) -> u8;
+    pub fn Servo_GetScrollFrameInfo(values: *mut ServoComputedValues,
+                                    info: *mut ServoScrollFrameInfo);
+    pub fn Servo_GetComputedScrollSnapCoordinate(values: *mut ServoComputedValues,
+                                                 index: u32,
+                                                 x: *mut ServoLengthOrPercentageOrAuto,
+                                                 y: *mut ServoLengthOrPercentageOrAuto)
+     -> bool;
+    pub fn Servo_GetComputedTextInfo(values: *mut ServoComputedValues,
+                                     out: *mut ServoTextStyleFFI);
+    pub fn Gecko_TransformList_SetLength(list: *mut RawGeckoTransformList,
+                                         length: u32)
+     -> *mut ServoTransformOperation;
+    pub fn Servo_GetComputedTransform(values: *mut ServoComputedValues,
+                                      out_list: *mut RawGeckoTransformList)
+     -> bool;
+    pub fn Gecko_ShadowArray_SetLength(array: *mut RawGeckoShadowArray,
+                                       length: u32)
+     -> *mut ServoShadowItem;
+    pub fn Servo_GetComputedTextShadow(values: *mut ServoComputedValues,
+                                       out_array: *mut RawGeckoShadowArray)
+     -> bool;
+    pub fn Servo_GetComputedBorderInfo(values: *mut ServoComputedValues,
+                                       out: *mut ServoBorderInfo);
+    pub fn Gecko_GradientStopArray_SetLength(array: *mut RawGeckoGradientStopArray,
+                                             length: u32)
+     -> *mut ServoGradientStop;
+    pub fn Servo_GetComputedImageAt(values: *mut ServoComputedValues,
+                                    layer_index: u32,
+                                    out_image: *mut ServoImageInfo)
+     -> bool;
+    pub fn Servo_GetComputedBackgroundLayerCount(values: *mut ServoComputedValues)
+     -> u32;
+    pub fn Servo_GetComputedBackgroundLayerAt(values: *mut ServoComputedValues,
+                                              index: u32,
+                                              out: *mut ServoBackgroundLayer)
+     -> bool;
+    pub fn Gecko_FontFeatureArray_SetLength(array: *mut RawGeckoFontFeatureArray,
+                                            length: u32)
+     -> *mut ServoFontFeature;
+    pub fn Servo_GetComputedFontFeatureSettings(values: *mut ServoComputedValues,
+                                                out_array: *mut RawGeckoFontFeatureArray)
+     -> bool;
+    pub fn Gecko_ContentItemList_Append(list: *mut RawGeckoContentItemList,
+                                        item: *const ServoContentItem);
+    pub fn Servo_GetComputedContent(values: *mut ServoComputedValues,
+                                    out_items: *mut RawGeckoContentItemList)
+     -> ServoContentKind;
+    pub fn Servo_GetComputedFlexInfo(values: *mut ServoComputedValues,
+                                     out: *mut ServoFlexInfo);
+    pub fn Servo_GetComputedColumnInfo(values: *mut ServoComputedValues,
+                                       out: *mut ServoColumnInfo);
+    pub fn Servo_GetComputedDirection(values: *mut ServoComputedValues) -> u8;
+    pub fn Servo_GetComputedTextOverflow(values: *mut ServoComputedValues,
+                                         out: *mut ServoTextOverflow);
+    pub fn Gecko_CursorImageArray_SetLength(array: *mut RawGeckoCursorImageArray,
+                                            length: u32)
+     -> *mut ServoCursorImage;
+    pub fn Servo_GetComputedCursor(values: *mut ServoComputedValues,
+                                   out_keyword: *mut u8,
+                                   out_images: *mut RawGeckoCursorImageArray);
+    pub fn Servo_GetComputedUIInfo(values: *mut ServoComputedValues,
+                                   out: *mut ServoUIInfo);
+    pub fn Gecko_CounterItemArray_SetLength(array: *mut RawGeckoCounterItemArray,
+                                            length: u32)
+     -> *mut ServoCounterItem;
+    pub fn Servo_GetComputedCounterData(values: *mut ServoComputedValues,
+                                        which: u8,
+                                        out_list: *mut RawGeckoCounterItemArray);
+    pub fn Gecko_Construct_nsStyleDisplay(out: *mut nsStyleDisplay_FFI);
+    pub fn Servo_GetStyleDisplay(values: *mut ServoComputedValues,
+                                 out: *mut nsStyleDisplay_FFI);
+    pub fn Gecko_Construct_nsStyleMargin(out: *mut nsStyleMargin_FFI);
+    pub fn Servo_GetStyleMargin(values: *mut ServoComputedValues,
+                                out: *mut nsStyleMargin_FFI);
+    pub fn Gecko_Construct_nsStylePadding(out: *mut nsStylePadding_FFI);
+    pub fn Servo_GetStylePadding(values: *mut ServoComputedValues,
+                                 out: *mut nsStylePadding_FFI);
+    pub fn Gecko_Construct_nsStylePosition(out: *mut nsStylePosition_FFI);
+    pub fn Servo_GetStylePosition(values: *mut ServoComputedValues,
+                                  out: *mut nsStylePosition_FFI);
+    pub fn Gecko_Construct_nsStyleVisibility(out: *mut nsStyleVisibility_FFI);
+    pub fn Servo_GetStyleVisibility(values: *mut ServoComputedValues,
+                                    out: *mut nsStyleVisibility_FFI);
+    pub fn Servo_GetComputedPropertyValue(values: *mut ServoComputedValues,
+                                          property: nsCSSProperty,
+                                          value: *mut nsString)
+     -> bool;
+    pub fn Servo_GetCustomPropertyValue(values: *mut ServoComputedValues,
+                                        name: *const u8, name_length: u32,
+                                        value: *mut nsString)
+     -> bool;
+    pub fn Servo_GetCustomPropertiesCount(values: *mut ServoComputedValues)
+     -> u32;
+    pub fn Servo_ComputedValues_EqualStructs(a: *mut ServoComputedValues,
+                                             b: *mut ServoComputedValues,
+                                             struct_id: u8) -> bool;
+    pub fn Servo_ComputedValues_Equals(a: *mut ServoComputedValues,
+                                       b: *mut ServoComputedValues) -> bool;
+    pub fn Servo_AddRefComputedValues(arg1: *mut ServoComputedValues);
+    pub fn Servo_ReleaseComputedValues(arg1: *mut ServoComputedValues);
+    pub fn Servo_NoteExplicitHints(element: *mut RawGeckoElement,
+                                   restyle_hint: ServoRestyleHint,
+                                   change_hint: nsChangeHint);
+    pub fn Servo_RestyleDocument(doc: *mut RawGeckoDocument,
+                                 set: *mut RawServoStyleSet);
+    pub fn Servo_RestyleForAnimations(doc: *mut RawGeckoDocument,
+                                      set: *mut RawServoStyleSet);
+    pub fn Servo_PerDocumentData_RestyleDocument(data:
+                                                     *mut RawServoPerDocumentData);
+    pub fn Servo_RestyleSubtree(node: *mut RawGeckoNode,
+                                set: *mut RawServoStyleSet);
+    pub fn Servo_ComputedValues_CalcDifference(old:
+                                                   *mut ServoComputedValues,
+                                               new:
+                                                   *mut ServoComputedValues)
+     -> nsChangeHint;
+    pub fn Servo_TakeChangeHint(element: *mut RawGeckoElement)
+     -> nsChangeHint;
+    pub fn Servo_GetDescendantChangeHints(element: *mut RawGeckoElement)
+     -> nsChangeHint;
+    pub fn Servo_ComputeRestyleHint(element: *mut RawGeckoElement,
+                                    snapshot: *mut ServoElementSnapshot,
+                                    set: *mut RawServoStyleSet)
+     -> ServoRestyleHint;
+    pub fn Servo_ComputeDocumentStateDependentRestyle(set:
+                                                          *mut RawServoStyleSet,
+                                                      changed_bits: u64)
+     -> bool;
+    pub fn Servo_StyleSet_MightHaveAttributeDependency(set: *mut RawServoStyleSet,
+                                                       name: *mut nsIAtom)
+     -> bool;
+    pub fn Servo_StyleSet_HasStateDependency(set: *mut RawServoStyleSet,
+                                             state_bits: u64) -> bool;
+    pub fn Servo_StyleSet_HasAttributeDependency(set: *mut RawServoStyleSet,
+                                                 attr_atom: *mut nsIAtom)
+     -> bool;
+    pub fn Servo_StyleSet_HasDocumentStateDependency(set: *mut RawServoStyleSet,
+                                                     bits: u64) -> bool;
+    pub fn Servo_PerDocumentData_ComputeRestyleHint(element: *mut RawGeckoElement,
+                                                    snapshot: *mut ServoElementSnapshot,
+                                                    data: *mut RawServoPerDocumentData)
+     -> ServoRestyleHint;
+    pub fn Servo_NoteElementSnapshot(data: *mut RawServoPerDocumentData,
+                                     element: *mut RawGeckoElement,
+                                     snapshot_flags: u8);
+    pub fn Servo_ForgetElementSnapshot(data: *mut RawServoPerDocumentData,
+                                       element: *mut RawGeckoElement);
+    pub fn Servo_AttributeSnapshot_SetLength(snapshot:
+                                                 *mut RawServoAttributeSnapshot,
+                                             length: u32)
+     -> *mut ServoAttrSnapshot;
+}
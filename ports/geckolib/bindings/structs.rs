@@ -0,0 +1,178 @@
+/* automatically generated by rust-bindgen */
+
+pub enum nsIAtom { }
+pub enum nsINode { }
+pub type RawGeckoNode = nsINode;
+pub enum Element { }
+pub type RawGeckoElement = Element;
+pub enum nsIDocument { }
+pub type RawGeckoDocument = nsIDocument;
+pub enum ServoNodeData { }
+pub enum ServoComputedValues { }
+pub enum RawServoStyleSheet { }
+pub enum RawServoStyleSet { }
+pub enum RawServoPerDocumentData { }
+pub enum RawServoDeclarationBlock { }
+pub enum RawServoMediaList { }
+pub enum RawServoImportRule { }
+pub enum RawServoAnimationValue { }
+pub enum RawServoCssRules { }
+pub enum RawServoStyleRule { }
+pub enum RawServoCounterStyleRule { }
+pub enum RawServoAttributeSnapshot { }
+pub enum RawServoSelectorList { }
+pub enum RawServoSharedStyleSheet { }
+pub enum nsString { }
+pub enum ServoElementSnapshot { }
+pub enum nsStyleFont { }
+pub enum RawGeckoKeyframeList { }
+pub enum RawGeckoFontFaceRuleList { }
+pub enum RawGeckoFontFaceRule { }
+pub enum RawGeckoContentList { }
+pub enum RawGeckoPropertyList { }
+pub enum RawGeckoRuleList { }
+pub enum RawGeckoTransformList { }
+pub enum RawGeckoShadowArray { }
+pub enum RawGeckoFontFeatureArray { }
+pub enum RawGeckoContentItemList { }
+pub enum RawGeckoGradientStopArray { }
+pub enum RawGeckoCursorImageArray { }
+pub enum RawGeckoCounterItemArray { }
+pub enum RawGeckoURLExtraData { }
+pub type ServoRestyleHint = ::wrapper::ServoRestyleHint;
+pub type nsChangeHint = u32;
+pub type nsCSSProperty = ::gecko_style_structs::nsCSSProperty;
+pub type MallocSizeOfFn = ::style::malloc_size_of::MallocSizeOfFn;
+pub type ServoReleaseFn = Option<unsafe extern "C" fn(ptr: *mut ::std::os::raw::c_void)>;
+pub type ServoComputedValuesStrong = ::ownership::Strong<ServoComputedValues>;
+pub type RawServoStyleSheetStrong = ::ownership::Strong<RawServoStyleSheet>;
+pub type RawServoDeclarationBlockStrong = ::ownership::Strong<RawServoDeclarationBlock>;
+pub type RawServoMediaListStrong = ::ownership::Strong<RawServoMediaList>;
+pub type RawServoImportRuleStrong = ::ownership::Strong<RawServoImportRule>;
+pub type RawServoAnimationValueStrong = ::ownership::Strong<RawServoAnimationValue>;
+pub type RawServoCssRulesStrong = ::ownership::Strong<RawServoCssRules>;
+pub type RawServoStyleRuleStrong = ::ownership::Strong<RawServoStyleRule>;
+pub type RawServoCounterStyleRuleStrong = ::ownership::Strong<RawServoCounterStyleRule>;
+pub type RawServoSharedStyleSheetStrong = ::ownership::Strong<RawServoSharedStyleSheet>;
+pub type nsresult = u32;
+#[derive(Copy, Clone, Debug)]
+#[repr(u32)]
+pub enum StyleSheetOrigin { UserAgent = 0, User = 1, Author = 2, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStyleDisplay {
+    Inline = 0,
+    Block = 1,
+    InlineBlock = 2,
+    Table = 3,
+    InlineTable = 4,
+    TableRowGroup = 5,
+    TableHeaderGroup = 6,
+    TableFooterGroup = 7,
+    TableRow = 8,
+    TableColumnGroup = 9,
+    TableColumn = 10,
+    TableCell = 11,
+    TableCaption = 12,
+    ListItem = 13,
+    Flex = 14,
+    None = 15,
+    InlineFlex = 16,
+    Contents = 17,
+}
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStylePosition { Static = 0, Absolute = 1, Relative = 2, Fixed = 3, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStyleFloat { None = 0, Left = 1, Right = 2, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStyleClear { None = 0, Left = 1, Right = 2, Both = 3, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStyleIsolation { Auto = 0, Isolate = 1, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoLengthUnit { Length = 0, Percent = 1, Auto = 2, Calc = 3, None = 4, Keyword = 5, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoTextSpacingKind { Normal = 0, Length = 1, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoTabSizeKind { Number = 0, Length = 1, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoTimingFunctionType { CubicBezier = 0, Steps = 1, Linear = 2, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStepPosition { Start = 0, End = 1, JumpNone = 2, JumpBoth = 3, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoPageSizeKind { Auto = 0, Portrait = 1, Landscape = 2, Explicit = 3, }
+#[derive(Copy, Clone, Debug)]
+#[repr(u8)]
+pub enum ServoStyleStructId {
+    Margin = 0,
+    Padding = 1,
+    Border = 2,
+    Outline = 3,
+    PositionOffsets = 4,
+    Box = 5,
+    InheritedBox = 6,
+    List = 7,
+    Counters = 8,
+    Background = 9,
+    Color = 10,
+    Font = 11,
+    InheritedText = 12,
+    Text = 13,
+    Table = 14,
+    InheritedTable = 15,
+    Pointing = 16,
+    Column = 17,
+    Effects = 18,
+    Animation = 19,
+    Flex = 20,
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoTransformFunction {
+    Matrix = 0,
+    Skew = 1,
+    Translate = 2,
+    Scale = 3,
+    Rotate = 4,
+    Perspective = 5,
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoContentKind { Normal = 0, None = 1, Items = 2, }
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoContentItemType {
+    String = 0,
+    Image = 1,
+    Attr = 2,
+    Counter = 3,
+    Counters = 4,
+    OpenQuote = 5,
+    CloseQuote = 6,
+    NoOpenQuote = 7,
+    NoCloseQuote = 8,
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoBorderImageKind { None = 0, Url = 1, Gradient = 2, }
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoBorderImageUnit { Auto = 0, Number = 1, Percent = 2, Coord = 3, Calc = 4, }
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoBorderImageRepeat { Stretch = 0, Repeat = 1, Round = 2, Space = 3, }
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoImageKind { None = 0, Url = 1, LinearGradient = 2, RadialGradient = 3, }
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum ServoCSSErrorKind { InvalidSelector = 0, }
@@ -0,0 +1,468 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The structs Gecko and Servo both read or fill in, rather than only pass pointers to.
+//!
+//! If the two sides disagree about one of these, nothing fails to build: fields just get
+//! written over each other. So each is declared through `ffi_struct!`, and build.rs works
+//! out what its layout should be from its declaration in ServoBindings.h, down to the offset
+//! of every field, and generates a test checking it against the Rust one. Sharing a new
+//! struct with Gecko means declaring it in the header and adding an `ffi_struct!` here.
+//!
+//! tools/regen_bindings.sh leaves these out of `structs`, so that regenerating
+//! doesn't define them twice.
+
+#![allow(non_snake_case)]
+
+use super::structs::*;
+
+/// What the generated layout tests need to know about a struct, beyond what the compiler
+/// does.
+pub trait FfiStruct {
+    /// The struct's fields, in the order they are declared.
+    fn field_names() -> &'static [&'static str];
+}
+
+macro_rules! ffi_struct {
+    ($(#[$attr:meta])* pub struct $name:ident { $(pub $field:ident: $ty:ty,)* }) => {
+        #[repr(C)]
+        $(#[$attr])*
+        pub struct $name {
+            $(pub $field: $ty,)*
+        }
+
+        impl FfiStruct for $name {
+            fn field_names() -> &'static [&'static str] {
+                static NAMES: &'static [&'static str] = &[$(stringify!($field)),*];
+                NAMES
+            }
+        }
+    }
+}
+
+/// Checks the size and alignment of a struct, and the name and offset of each of its
+/// fields, against what they are in ServoBindings.h.
+#[cfg(test)]
+macro_rules! check_layout {
+    ($name:ident, size: $size:expr, align: $align:expr, fields: [$($field:ident: $offset:expr),*]) => {{
+        let value: $name = unsafe { ::std::mem::zeroed() };
+        let base = &value as *const _ as usize;
+        assert_eq!(<$name as FfiStruct>::field_names(), &[$(stringify!($field)),*],
+                   "{} has different fields in Rust", stringify!($name));
+        assert_eq!(::std::mem::size_of::<$name>(), $size, "size of {}", stringify!($name));
+        assert_eq!(::std::mem::align_of::<$name>(), $align, "alignment of {}", stringify!($name));
+        $(
+            assert_eq!(&value.$field as *const _ as usize - base, $offset,
+                       "offset of {}::{}", stringify!($name), stringify!($field));
+        )*
+    }}
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoLengthOrPercentageOrAuto {
+        pub mUnit: ServoLengthUnit,
+        pub mLength: i32,
+        pub mPercent: f32,
+        pub mHasPercent: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoScrollFrameInfo {
+        pub mOverflowX: u8,
+        pub mOverflowY: u8,
+        pub mResize: u8,
+        pub mScrollSnapTypeX: u8,
+        pub mScrollSnapTypeY: u8,
+        pub mScrollSnapPointsX: ServoLengthOrPercentageOrAuto,
+        pub mScrollSnapPointsY: ServoLengthOrPercentageOrAuto,
+        pub mScrollSnapDestinationX: ServoLengthOrPercentageOrAuto,
+        pub mScrollSnapDestinationY: ServoLengthOrPercentageOrAuto,
+        pub mScrollSnapCoordinateCount: u32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTextStyleFFI {
+        pub mWhiteSpace: u8,
+        pub mTextTransform: u8,
+        pub mWordBreak: u8,
+        pub mOverflowWrap: u8,
+        pub mTextAlign: u8,
+        pub mLetterSpacingKind: ServoTextSpacingKind,
+        pub mWordSpacingKind: ServoTextSpacingKind,
+        pub mTabSizeKind: ServoTabSizeKind,
+        pub mLetterSpacing: i32,
+        pub mWordSpacing: i32,
+        pub mTabSizeLength: i32,
+        pub mTabSizeNumber: f32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTimingFunction {
+        pub mType: ServoTimingFunctionType,
+        pub mX1: f32,
+        pub mY1: f32,
+        pub mX2: f32,
+        pub mY2: f32,
+        pub mSteps: u32,
+        pub mStepPosition: ServoStepPosition,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoPageSize {
+        pub mKind: ServoPageSizeKind,
+        pub mWidth: i32,
+        pub mHeight: i32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTraversalStatistics {
+        pub mElementsTraversed: u32,
+        pub mElementsStyled: u32,
+        pub mStylesShared: u32,
+        pub mSelectorsMatched: u32,
+        pub mDeclarationsCascaded: u32,
+        pub mNodeQueries: u32,
+        pub mNodeFFICalls: u32,
+        pub mTraversalTimeMs: f64,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTransformOperation {
+        pub mFunction: ServoTransformFunction,
+        pub mValues: [f32; 16],
+        pub mPercentages: [f32; 2],
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoContentItem {
+        pub mType: ServoContentItemType,
+        pub mString: *const u8,
+        pub mStringLength: u32,
+        pub mSeparator: *const u8,
+        pub mSeparatorLength: u32,
+        pub mCounterStyle: *const u8,
+        pub mCounterStyleLength: u32,
+        pub mCounterName: *mut nsIAtom,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct ServoShadowItem {
+        pub mXOffset: i32,
+        pub mYOffset: i32,
+        pub mRadius: i32,
+        pub mSpread: i32,
+        pub mColor: u32,
+        pub mHasColor: bool,
+        pub mInset: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct ServoFontFeature {
+        pub mTag: u32,
+        pub mValue: u32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct ServoBorderImageSide {
+        pub mUnit: ServoBorderImageUnit,
+        pub mFactor: f32,
+        pub mCoord: i32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoBorderInfo {
+        pub mBoxShadow: *mut RawGeckoShadowArray,
+        pub mWidths: [i32; 4],
+        pub mColors: [u32; 4],
+        pub mStyles: [u8; 4],
+        pub mHasColors: [bool; 4],
+        pub mImageKind: ServoBorderImageKind,
+        pub mImageFill: bool,
+        pub mImageRepeatH: ServoBorderImageRepeat,
+        pub mImageRepeatV: ServoBorderImageRepeat,
+        pub mHasBoxShadow: bool,
+        pub mImageSlice: [ServoBorderImageSide; 4],
+        pub mImageWidth: [ServoBorderImageSide; 4],
+        pub mImageOutset: [ServoBorderImageSide; 4],
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoGradientStop {
+        pub mPosition: ServoLengthOrPercentageOrAuto,
+        pub mColor: u32,
+        pub mHasColor: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoImageInfo {
+        pub mStops: *mut RawGeckoGradientStopArray,
+        pub mURL: *mut nsString,
+        pub mKind: ServoImageKind,
+        pub mShape: u8,
+        pub mSize: u8,
+        pub mHasAngle: bool,
+        pub mAngle: f32,
+        pub mPositionX: ServoLengthOrPercentageOrAuto,
+        pub mPositionY: ServoLengthOrPercentageOrAuto,
+        pub mRadiusX: ServoLengthOrPercentageOrAuto,
+        pub mRadiusY: ServoLengthOrPercentageOrAuto,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoBackgroundLayer {
+        pub mPositionX: ServoLengthOrPercentageOrAuto,
+        pub mPositionY: ServoLengthOrPercentageOrAuto,
+        pub mWidth: ServoLengthOrPercentageOrAuto,
+        pub mHeight: ServoLengthOrPercentageOrAuto,
+        pub mHasExplicitSize: bool,
+        pub mSize: u8,
+        pub mRepeat: u8,
+        pub mAttachment: u8,
+        pub mClip: u8,
+        pub mOrigin: u8,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoCursorImage {
+        pub mRequest: *mut ::std::os::raw::c_void,
+        pub mHaveHotspot: bool,
+        pub mHotspotX: f32,
+        pub mHotspotY: f32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoCounterItem {
+        pub mName: *mut nsIAtom,
+        pub mValue: i32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoSelectorParseError {
+        pub mKind: ServoCSSErrorKind,
+        pub mSelectorStart: u32,
+        pub mSelectorLength: u32,
+        pub mOffset: u32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoFlexInfo {
+        pub mFlexDirection: u8,
+        pub mFlexWrap: u8,
+        pub mJustifyContent: u8,
+        pub mAlignItems: u8,
+        pub mAlignContent: u8,
+        pub mAlignSelf: u8,
+        pub mFlexBasisIsContent: bool,
+        pub mOrder: i32,
+        pub mFlexGrow: f32,
+        pub mFlexShrink: f32,
+        pub mFlexBasis: ServoLengthOrPercentageOrAuto,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoColumnInfo {
+        pub mColumnCount: u32,
+        pub mColumnWidth: i32,
+        pub mColumnGap: i32,
+        pub mColumnRuleWidth: i32,
+        pub mColumnRuleColor: u32,
+        pub mColumnRuleStyle: u8,
+        pub mHasColumnWidth: bool,
+        pub mHasColumnGap: bool,
+        pub mHasColumnRuleColor: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoUIInfo {
+        pub mPointerEvents: u8,
+        pub mTouchAction: u8,
+        pub mUserSelect: u8,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTextOverflowSide {
+        pub mString: *mut nsString,
+        pub mType: u8,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoTextOverflow {
+        pub mLeft: ServoTextOverflowSide,
+        pub mRight: ServoTextOverflowSide,
+        pub mLogicalDirections: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct SystemFontData {
+        pub mFamily: *const u8,
+        pub mFamilyLength: u32,
+        pub mSize: f32,
+        pub mWeight: u16,
+        pub mItalic: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct nsStyleDisplay_FFI {
+        pub mOpacity: f32,
+        pub mDisplay: u8,
+        pub mOriginalDisplay: u8,
+        pub mContain: u8,
+        pub mAppearance: u8,
+        pub mPosition: u8,
+        pub mFloats: u8,
+        pub mBreakType: u8,
+        pub mOverflowX: u8,
+        pub mOverflowY: u8,
+        pub mResize: u8,
+        pub mOrient: u8,
+        pub mMixBlendMode: u8,
+        pub mIsolation: u8,
+        pub mTouchAction: u8,
+        pub mScrollBehavior: u8,
+        pub mScrollSnapTypeX: u8,
+        pub mScrollSnapTypeY: u8,
+        pub mBackfaceVisibility: u8,
+        pub mTransformStyle: u8,
+        pub mTransformBox: u8,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct nsStyleMargin_FFI {
+        pub mMargin: [ServoLengthOrPercentageOrAuto; 4],
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct nsStylePadding_FFI {
+        pub mPadding: [ServoLengthOrPercentageOrAuto; 4],
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct nsStylePosition_FFI {
+        pub mOffset: [ServoLengthOrPercentageOrAuto; 4],
+        pub mWidth: ServoLengthOrPercentageOrAuto,
+        pub mMinWidth: ServoLengthOrPercentageOrAuto,
+        pub mMaxWidth: ServoLengthOrPercentageOrAuto,
+        pub mHeight: ServoLengthOrPercentageOrAuto,
+        pub mMinHeight: ServoLengthOrPercentageOrAuto,
+        pub mMaxHeight: ServoLengthOrPercentageOrAuto,
+        pub mFlexBasis: ServoLengthOrPercentageOrAuto,
+        pub mGridAutoFlow: u8,
+        pub mBoxSizing: u8,
+        pub mAlignContent: u16,
+        pub mAlignItems: u8,
+        pub mAlignSelf: u8,
+        pub mJustifyContent: u16,
+        pub mJustifyItems: u8,
+        pub mJustifySelf: u8,
+        pub mFlexDirection: u8,
+        pub mFlexWrap: u8,
+        pub mObjectFit: u8,
+        pub mFlexBasisIsContent: bool,
+        pub mHasZIndex: bool,
+        pub mOrder: i32,
+        pub mFlexGrow: f32,
+        pub mFlexShrink: f32,
+        pub mZIndex: i32,
+        pub mGridColumnGap: i32,
+        pub mGridRowGap: i32,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct nsStyleVisibility_FFI {
+        pub mDirection: u8,
+        pub mVisible: u8,
+        pub mPointerEvents: u8,
+        pub mWritingMode: u8,
+        pub mTextOrientation: u8,
+        pub mImageRendering: u8,
+        pub mColorAdjust: u8,
+        pub mImageOrientation: u8,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct NodeInfoFFI {
+        pub mParent: *mut RawGeckoNode,
+        pub mNextSibling: *mut RawGeckoNode,
+        pub mState: u64,
+        pub mFlags: u32,
+        pub mIsElement: bool,
+    }
+}
+
+ffi_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct ServoAttrSnapshot {
+        pub mNamespace: *mut nsIAtom,
+        pub mName: *mut nsIAtom,
+        pub mValueAtom: *mut nsIAtom,
+        pub mValue: *const u8,
+        pub mValueLength: u32,
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    include!(concat!(env!("OUT_DIR"), "/layout_tests.rs"));
+}
@@ -2,9 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::Path;
 use std::process::{Command, Stdio, exit};
 
@@ -63,7 +64,8 @@ try:
 
     geckolib_template = Template(filename=os.environ['GECKOLIB_TEMPLATE'], input_encoding='utf8')
     output = geckolib_template.render(STYLE_STRUCTS = style_template.module.STYLE_STRUCTS,
-                                      LONGHANDS = style_template.module.LONGHANDS)
+                                      LONGHANDS = style_template.module.LONGHANDS,
+                                      SHORTHANDS = style_template.module.SHORTHANDS)
     print(output.encode('utf8'))
 except:
     sys.stderr.write(exceptions.text_error_template().render().encode('utf8'))
@@ -77,4 +79,203 @@ except:
     }
     let out = env::var("OUT_DIR").unwrap();
     File::create(&Path::new(&out).join("properties.rs")).unwrap().write_all(&result.stdout).unwrap();
+
+    let header = read_file(&geckolib_dir.join("bindings").join("ServoBindings.h"));
+    let structs = read_file(&geckolib_dir.join("bindings").join("structs_ffi.rs"));
+    let tests = match layout_tests(&header, &structs, &env::var("TARGET").unwrap()) {
+        Ok(tests) => tests,
+        Err(error) => {
+            writeln!(&mut ::std::io::stderr(), "ServoBindings.h: {}", error).unwrap();
+            exit(1)
+        }
+    };
+    File::create(&Path::new(&out).join("layout_tests.rs")).unwrap().write_all(tests.as_bytes()).unwrap();
+}
+
+fn read_file(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+    contents
+}
+
+/// The size and alignment of a C type.
+#[derive(Clone, Copy)]
+struct Layout {
+    size: usize,
+    align: usize,
+}
+
+/// The layout of a struct, as a C compiler for the target would lay it out.
+struct StructLayout {
+    layout: Layout,
+    fields: Vec<(String, usize)>,
+}
+
+/// What the types in ServoBindings.h are laid out as on the target.
+struct CTypes {
+    pointer: Layout,
+    /// Numbers are aligned to their size, except for the 64-bit ones, which 32-bit x86 only
+    /// aligns to 4 bytes outside Windows.
+    align_64: usize,
+    aliases: HashMap<String, String>,
+    enums: HashMap<String, String>,
+    structs: HashMap<String, StructLayout>,
+}
+
+impl CTypes {
+    fn new(target: &str) -> CTypes {
+        let arch = target.split('-').next().unwrap();
+        let is_64_bit = ["x86_64", "aarch64", "powerpc64", "mips64", "sparc64"].iter().any(|a| arch.starts_with(a));
+        let pointer_size = if is_64_bit { 8 } else { 4 };
+        let x86 = arch == "i386" || arch == "i586" || arch == "i686";
+        CTypes {
+            pointer: Layout { size: pointer_size, align: pointer_size },
+            align_64: if x86 && !target.contains("windows") { 4 } else { 8 },
+            aliases: HashMap::new(),
+            enums: HashMap::new(),
+            structs: HashMap::new(),
+        }
+    }
+
+    fn layout(&self, name: &str) -> Result<Layout, String> {
+        let primitive = |size| Layout { size: size, align: size };
+        Ok(match name {
+            "bool" | "char" | "uint8_t" | "int8_t" => primitive(1),
+            "uint16_t" | "int16_t" => primitive(2),
+            "uint32_t" | "int32_t" | "float" => primitive(4),
+            "uint64_t" | "int64_t" | "double" => Layout { size: 8, align: self.align_64 },
+            "size_t" | "intptr_t" => self.pointer,
+            _ => {
+                if let Some(target) = self.aliases.get(name) {
+                    return self.layout(target)
+                }
+                if let Some(underlying) = self.enums.get(name) {
+                    return self.layout(underlying)
+                }
+                match self.structs.get(name) {
+                    Some(layout) => layout.layout,
+                    None => return Err(format!("don't know the layout of {}", name)),
+                }
+            }
+        })
+    }
+
+    /// Works out the layout of a struct from its fields, which are separated by semicolons.
+    fn add_struct(&mut self, name: &str, body: &str) -> Result<(), String> {
+        let mut offset = 0;
+        let mut align = 1;
+        let mut fields = vec![];
+        for field in body.split(';').map(|f| f.trim()).filter(|f| !f.is_empty()) {
+            let (declaration, count) = match field.find('[') {
+                Some(open) => {
+                    let count = field[open + 1..].trim_right_matches(']').trim();
+                    (&field[..open], try!(count.parse::<usize>().map_err(|_| format!("bad array in {}", field))))
+                }
+                None => (field, 1),
+            };
+            let mut words = declaration.split_whitespace().filter(|w| *w != "const").collect::<Vec<_>>();
+            let field_name = try!(words.pop().ok_or(format!("bad field in {}", name)));
+            let is_pointer = field_name.starts_with('*') || words.iter().any(|w| w.ends_with('*'));
+            let layout = if is_pointer {
+                self.pointer
+            } else {
+                try!(self.layout(&words.join(" ")))
+            };
+            offset = (offset + layout.align - 1) / layout.align * layout.align;
+            fields.push((field_name.trim_left_matches('*').to_owned(), offset));
+            offset += layout.size * count;
+            if layout.align > align {
+                align = layout.align;
+            }
+        }
+        let size = (offset + align - 1) / align * align;
+        self.structs.insert(name.to_owned(), StructLayout {
+            layout: Layout { size: size, align: align },
+            fields: fields,
+        });
+        Ok(())
+    }
+}
+
+// The same as in tests/check_bindings.rs, which the build script can't share code with.
+fn strip_c_comments(source: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = source;
+    loop {
+        let line = rest.find("//");
+        let block = rest.find("/*");
+        let (start, end_marker) = match (line, block) {
+            (Some(l), Some(b)) if l < b => (l, "\n"),
+            (Some(l), None) => (l, "\n"),
+            (_, Some(b)) => (b, "*/"),
+            (None, None) => break,
+        };
+        stripped.push_str(&rest[..start]);
+        stripped.push(if end_marker == "\n" { '\n' } else { ' ' });
+        rest = &rest[start..];
+        rest = match rest[2..].find(end_marker) {
+            Some(end) => &rest[2 + end + end_marker.len()..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped.lines()
+            .filter(|line| !line.trim_left().starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n")
+}
+
+/// Generates a test for each struct in structs_ffi.rs, checking it's laid out as the struct
+/// of the same name in ServoBindings.h.
+fn layout_tests(header: &str, structs: &str, target: &str) -> Result<String, String> {
+    let mut types = CTypes::new(target);
+    let header = strip_c_comments(header);
+    for statement in header.split(|c| c == ';' || c == '}') {
+        let statement = statement.trim();
+        let mut words = statement.split_whitespace().collect::<Vec<_>>();
+        if words.len() == 3 && words[0] == "typedef" && !words[2].contains('(') {
+            types.aliases.insert(words[2].to_owned(), words[1].to_owned());
+        }
+        if words.get(1) == Some(&"class") {
+            words.remove(1);
+        }
+        if words.len() >= 4 && words[0] == "enum" && words[2] == ":" {
+            types.enums.insert(words[1].to_owned(), words[3].trim_right_matches('{').to_owned());
+        }
+    }
+    // Struct bodies hold no braces of their own, so each ends at the first one after it.
+    let mut rest = &*header;
+    while let Some(start) = rest.find("struct ") {
+        let after = &rest[start + "struct ".len()..];
+        let name_end = after.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(after.len());
+        let name = &after[..name_end];
+        let declaration = after[name_end..].trim_left();
+        let is_word_start = start == 0 || !rest[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        if is_word_start && declaration.starts_with('{') {
+            let end = try!(declaration.find('}').ok_or(format!("unterminated struct {}", name)));
+            try!(types.add_struct(name, &declaration[1..end]));
+            rest = &declaration[end..];
+        } else {
+            rest = &after[name_end..];
+        }
+    }
+
+    let mut tests = String::new();
+    for line in structs.lines() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.len() != 4 || words[0] != "pub" || words[1] != "struct" || words[2].starts_with('$') {
+            continue
+        }
+        let name = words[2];
+        let layout = try!(types.structs.get(name).ok_or(format!("{} is shared with Gecko in structs_ffi.rs, \
+                                                                 but isn't declared in the header", name)));
+        let fields = layout.fields.iter()
+                                  .map(|&(ref field, offset)| format!("{}: {}", field, offset))
+                                  .collect::<Vec<_>>();
+        tests.push_str(&format!("#[test]\nfn layout_of_{name}() {{\n    \
+                                 check_layout!({name}, size: {size}, align: {align}, fields: [{fields}]);\n}}\n",
+                                name = name, size = layout.layout.size, align = layout.layout.align,
+                                fields = fields.join(", ")));
+    }
+    Ok(tests)
 }
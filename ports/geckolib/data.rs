@@ -2,72 +2,842 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use bindings::RawServoStyleSet;
+use app_units::Au;
+use atoms::atom_to_gecko;
+use bindings::{RawGeckoDocument, RawServoPerDocumentData, RawServoStyleSet, ServoTraversalStatistics, nsIAtom};
 use euclid::Size2D;
 use euclid::size::TypedSize2D;
+use ffi::annotate_crash_report;
+use glue::GeckoSheetCssom;
+use heapsize::HeapSizeOf;
+use node_data_dropper::NodeDataDropper;
 use num_cpus;
-use selector_impl::{Stylist, Stylesheet, SharedStyleContext};
+use ownership;
+use platform_values::PlatformValues;
+use properties::{EXPERIMENTAL_PREFS, GeckoComputedValues};
+use selector_impl::{PseudoElement, SharedStyleContext, SharedStylesheet, Stylist, Stylesheet};
+use sheet_parser::SheetParser;
 use std::cmp;
 use std::collections::HashMap;
+use std::env;
+use std::mem;
+use std::ops::Range;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use string_cache::Atom;
 use style::animation::Animation;
 use style::dom::OpaqueNode;
+use style::error_reporting::StdoutErrorReporter;
+use style::malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use style::media_queries::{Device, MediaType};
 use style::parallel::WorkQueueData;
+use style::parser::QuirksMode;
+use style::properties::{ComputedValues, cascade};
+use style::rule_tree::RuleTree;
+use style::shared_lock::SharedRwLock;
+use style::stylesheets::{CSSRuleIteratorExt, Origin};
+use traversal::DETERMINISTIC_TRAVERSAL;
+use url::Url;
+use util::arc_ptr_eq;
+use util::cache::LRUCache;
 use util::geometry::ViewportPx;
+use util::prefs::{self, PrefValue};
 use util::thread_state;
 use util::workqueue::WorkQueue;
+use wrapper::ElementSnapshotTable;
+
+/// The number of threads to style with, as set by Servo_InitializeThreadPool. Zero
+/// means we pick one based on the number of CPUs.
+pub static NUM_THREADS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The state every style set shares, which lives from Servo_Initialize to Servo_Shutdown.
+/// It's only handed out shared, so what changes is behind locks.
+pub struct GlobalStyleData {
+    /// The parallel traversal's work queue, or None if we style sequentially. Restyles hold
+    /// the lock while the workers run.
+    pub work_queue: Mutex<Option<WorkQueue<SharedStyleContext, WorkQueueData>>>,
+
+    /// The base URL of stylesheets and declarations Gecko doesn't give us a real one for.
+    pub dummy_url: Url,
+
+    /// What the CSSOM rule lists made for each sheet share, by the address of the sheet.
+    pub sheet_cssom: Mutex<HashMap<usize, Weak<GeckoSheetCssom>>>,
+
+    /// The sheets Servo_StyleSheet_IntoShared froze, by the address of the sheet, so that
+    /// style sets of every document share the same rule maps for them.
+    pub shared_sheets: Mutex<HashMap<usize, Weak<SharedStylesheet>>>,
+
+    /// What the last traversal did, if statistics were enabled for it.
+    pub traversal_statistics: Mutex<ServoTraversalStatistics>,
+
+    /// The thread Servo_StyleSheet_FromUTF8BytesAsync parses sheets on.
+    pub sheet_parser: SheetParser,
+
+    /// The thread Servo_DropNodeDataRecursiveLater has node data freed on.
+    pub node_data_dropper: NodeDataDropper,
+
+    /// The lock the declaration blocks Gecko holds are behind. Restyles hold it for reading
+    /// while they run, and the CSSOM takes it for writing to change blocks or rules, so that
+    /// what a restyle reads can't change under it.
+    pub shared_lock: SharedRwLock,
+}
+
+static mut GLOBAL_STYLE_DATA: *mut GlobalStyleData = 0 as *mut GlobalStyleData;
+
+impl GlobalStyleData {
+    /// Builds the global state. The traversal uses `STYLO_THREADS` threads if that's set in
+    /// the environment, and the number passed to Servo_InitializeThreadPool otherwise.
+    /// Setting `STYLO_DETERMINISTIC` turns on the deterministic traversal from the start, and
+    /// setting `STYLO_LOG_REFCOUNTS` turns on the refcount log.
+    pub fn initialize() {
+        if env::var("STYLO_DETERMINISTIC").is_ok() {
+            DETERMINISTIC_TRAVERSAL.store(true, Ordering::Relaxed);
+        }
+        ownership::initialize_refcount_logging();
+        for pref in EXPERIMENTAL_PREFS {
+            prefs::set_pref(pref, PrefValue::Boolean(true));
+        }
+        let num_threads = match env::var("STYLO_THREADS").ok().and_then(|n| n.parse().ok()) {
+            Some(n) => n,
+            None => NUM_THREADS.load(Ordering::Relaxed),
+        };
+        let num_threads = match num_threads {
+            0 => cmp::max(num_cpus::get() * 3 / 4, 1),
+            n => n,
+        };
+        let work_queue = if num_threads > 1 {
+            Some(WorkQueue::new("StyleWorker", thread_state::LAYOUT, num_threads))
+        } else {
+            None
+        };
+        let data = Box::new(GlobalStyleData {
+            work_queue: Mutex::new(work_queue),
+            dummy_url: Url::parse("about:none").unwrap(),
+            sheet_cssom: Mutex::new(HashMap::new()),
+            shared_sheets: Mutex::new(HashMap::new()),
+            traversal_statistics: Mutex::new(ServoTraversalStatistics {
+                mElementsTraversed: 0,
+                mElementsStyled: 0,
+                mStylesShared: 0,
+                mSelectorsMatched: 0,
+                mDeclarationsCascaded: 0,
+                mNodeQueries: 0,
+                mNodeFFICalls: 0,
+                mTraversalTimeMs: 0.0,
+            }),
+            sheet_parser: SheetParser::new(),
+            node_data_dropper: NodeDataDropper::new(),
+            shared_lock: SharedRwLock::new(),
+        });
+        unsafe {
+            debug_assert!(GLOBAL_STYLE_DATA.is_null(), "Servo_Initialize called twice");
+            GLOBAL_STYLE_DATA = Box::into_raw(data);
+        }
+    }
+
+    /// Stops the style worker threads, the sheet parser and the node data dropper, and frees
+    /// the global state. Gecko isn't called back for the sheets that were still being parsed.
+    pub fn shutdown() {
+        unsafe {
+            if GLOBAL_STYLE_DATA.is_null() {
+                return;
+            }
+            let mut data = Box::from_raw(GLOBAL_STYLE_DATA);
+            GLOBAL_STYLE_DATA = 0 as *mut GlobalStyleData;
+            if let Some(ref mut work_queue) = *data.work_queue.get_mut().unwrap() {
+                work_queue.shutdown();
+            }
+            data.sheet_parser.shutdown();
+            data.node_data_dropper.shutdown();
+        }
+    }
+
+    /// The global state. Like everything but the traversal itself, this may only be used on
+    /// the main thread, between Servo_Initialize and Servo_Shutdown.
+    pub fn get() -> &'static GlobalStyleData {
+        unsafe {
+            assert!(!GLOBAL_STYLE_DATA.is_null(), "Servo_Initialize hasn't been called");
+            &*GLOBAL_STYLE_DATA
+        }
+    }
+
+    /// Like |get|, for what may run after Servo_Shutdown, like dropping a style set Gecko
+    /// leaked until then.
+    pub fn get_if_initialized() -> Option<&'static GlobalStyleData> {
+        unsafe { GLOBAL_STYLE_DATA.as_ref() }
+    }
+}
+
+/// How many anonymous box styles a style set keeps. Table frame construction asks for a
+/// handful of them over and over, with the same parent style.
+const ANONYMOUS_BOX_STYLE_CACHE_SIZE: usize = 32;
+
+/// The style of an anonymous box, along with the parent style it was computed from, which
+/// is kept alive so that its address can't be reused for another style.
+type AnonymousBoxStyle = (Option<Arc<GeckoComputedValues>>, Arc<GeckoComputedValues>);
 
 pub struct PerDocumentStyleData {
+    /// The document this data was made for by Servo_PerDocumentData_Create, or null if it
+    /// was made by Servo_InitStyleSet and is only told about documents as it's used.
+    pub document: *mut RawGeckoDocument,
 
     /// Rule processor.
     pub stylist: Stylist,
 
-    /// List of stylesheets, mirrored from Gecko.
+    /// List of stylesheets, mirrored from Gecko. The sheets of each origin are kept together,
+    /// user agent sheets first and author sheets last, each group in the order Gecko gave.
     pub stylesheets: Vec<Arc<Stylesheet>>,
 
-    /// Whether the stylesheets list above has changed since the last restyle.
-    pub stylesheets_changed: bool,
+    /// The origins whose stylesheets changed since the last flush, and whose rules need
+    /// to be rebuilt.
+    pub dirty_origins: Vec<Origin>,
+
+    /// The sheets that were disabled the last time the stylist was rebuilt, so that
+    /// toggling a sheet's disabled flag can be noticed on the next flush.
+    disabled_stylesheets: Vec<Arc<Stylesheet>>,
+
+    /// The rules generation of each sheet the last time the stylist was rebuilt, so that
+    /// changes made to a sheet through the CSSOM can be noticed on the next flush.
+    rules_generations: Vec<(Arc<Stylesheet>, usize)>,
+
+    /// How many times the rules of each origin have been rebuilt, for testing.
+    rebuild_counts: [u32; 3],
+
+    /// How many unsupported rules the sheets had the last time the stylist was rebuilt.
+    unsupported_rule_count: usize,
+
+    /// A bloom filter of the Gecko atoms for the attributes the stylist's selectors depend
+    /// on, so that Servo_StyleSet_HasAttributeDependency needn't hash the atom. Rebuilt with
+    /// the stylist.
+    attribute_bloom: u64,
+
+    /// Whether the rules changed since the last flush in a way the list of sheets doesn't
+    /// show, like author style being turned off.
+    unlisted_rule_changes: bool,
+
+    /// Whether every element the rule changes since the last flush can restyle has been
+    /// marked dirty already, by Servo_StyleSet_InvalidateForSheet, so that the flush needn't
+    /// have the whole document restyled.
+    rule_changes_invalidated: bool,
+
+    /// The size of the viewport, which vw, vh, vmin and vmax are relative to.
+    pub viewport_size: Size2D<Au>,
+
+    /// The medium the document is styled for, as Gecko names it: `screen`, unless print
+    /// preview or devtools' media emulation asked for another, like `print`.
+    medium: Atom,
+
+    /// The font size rem is relative to on the root element.
+    pub root_font_size: Au,
+
+    /// The style of an element that no rule applies to and that has no parent, built
+    /// the first time it's asked for and thrown away when the root font size changes.
+    initial_values: Option<Arc<GeckoComputedValues>>,
+
+    /// The styles of anonymous boxes computed since the rules or the device last changed,
+    /// by the address of their parent style and their pseudo-element.
+    anonymous_box_styles: LRUCache<(usize, PseudoElement), AnonymousBoxStyle>,
+
+    /// How many anonymous box styles were cascaded rather than found in the cache, for
+    /// testing.
+    anonymous_box_cascades: u32,
+
+    /// Whether the device or the platform's theme changed in a way that can change computed
+    /// values since the last restyle, so that the next one can't reuse any cached styles.
+    pub device_changed: bool,
+
+    /// The compatibility mode of the document, which decides whether the quirks mode
+    /// user agent sheet applies.
+    pub quirks_mode: QuirksMode,
+
+    /// What the style threads look up instead of asking Gecko about the platform, which only
+    /// answers on the main thread. Restyles fetch what's missing before the traversal.
+    pub platform_values: Arc<PlatformValues>,
+
+    /// Ticked on every restyle, so that bloom filters left behind in thread-local
+    /// storage by a previous traversal are never reused.
+    pub generation: u32,
+
+    /// Ticked whenever the rules or the device change, so that the styles the traversal
+    /// left on nodes before can be told apart from current ones.
+    pub style_generation: usize,
+
+    /// The rule tree elements' matched rules go into, so that elements that matched the same
+    /// rules share their style. It's only used if `STYLO_RULE_TREE` is set in the environment.
+    pub rule_tree: Option<Arc<RuleTree>>,
+
+    /// The traversals running from the style set, which shutting it down waits for.
+    traversals: Arc<RunningTraversals>,
+
+    /// Whether the set was shut down, after which it's only waiting to be dropped.
+    shut_down: bool,
+
+    /// The snapshots of the elements Gecko said were about to change since the last restyle,
+    /// which the next one computes restyle hints from and then throws away.
+    pub snapshots: ElementSnapshotTable,
 
     // FIXME(bholley): Hook these up to something.
     pub new_animations_sender: Sender<Animation>,
     pub new_animations_receiver: Receiver<Animation>,
     pub running_animations: Arc<RwLock<HashMap<OpaqueNode, Vec<Animation>>>>,
     pub expired_animations: Arc<RwLock<HashMap<OpaqueNode, Vec<Animation>>>>,
-
-    // FIXME(bholley): This shouldn't be per-document.
-    pub work_queue: WorkQueue<SharedStyleContext, WorkQueueData>,
 }
 
 impl PerDocumentStyleData {
-    pub fn new() -> PerDocumentStyleData {
+    pub fn new(document: *mut RawGeckoDocument) -> PerDocumentStyleData {
         // FIXME(bholley): Real window size.
         let window_size: TypedSize2D<ViewportPx, f32> = Size2D::typed(800.0, 600.0);
         let device = Device::new(MediaType::Screen, window_size);
 
         let (new_anims_sender, new_anims_receiver) = channel();
-        let num_threads = cmp::max(num_cpus::get() * 3 / 4, 1);
 
         PerDocumentStyleData {
+            document: document,
             stylist: Stylist::new(device),
             stylesheets: Vec::new(),
-            stylesheets_changed: true,
+            dirty_origins: Vec::new(),
+            disabled_stylesheets: Vec::new(),
+            rules_generations: Vec::new(),
+            rebuild_counts: [0; 3],
+            unsupported_rule_count: 0,
+            attribute_bloom: 0,
+            unlisted_rule_changes: false,
+            rule_changes_invalidated: false,
+            viewport_size: Size2D::new(Au::from_f32_px(window_size.width.get()),
+                                       Au::from_f32_px(window_size.height.get())),
+            medium: Atom::from("screen"),
+            root_font_size: Au::from_px(16),
+            initial_values: None,
+            anonymous_box_styles: LRUCache::new(ANONYMOUS_BOX_STYLE_CACHE_SIZE),
+            anonymous_box_cascades: 0,
+            device_changed: false,
+            quirks_mode: QuirksMode::NoQuirks,
+            platform_values: Arc::new(PlatformValues::new()),
+            generation: 0,
+            style_generation: 0,
+            rule_tree: env::var("STYLO_RULE_TREE").ok().map(|_| Arc::new(RuleTree::new())),
+            traversals: Arc::new(RunningTraversals {
+                count: Mutex::new(0),
+                finished: Condvar::new(),
+            }),
+            shut_down: false,
+            snapshots: ElementSnapshotTable::new(),
             new_animations_sender: new_anims_sender,
             new_animations_receiver: new_anims_receiver,
             running_animations: Arc::new(RwLock::new(HashMap::new())),
             expired_animations: Arc::new(RwLock::new(HashMap::new())),
-            work_queue: WorkQueue::new("StyleWorker", thread_state::LAYOUT, num_threads),
         }
     }
 
+    /// Gets at the style set |data| stands for, which mustn't be null.
     pub fn borrow_mut_from_raw<'a>(data: *mut RawServoStyleSet) -> &'a mut Self {
+        debug_assert!(!data.is_null());
+        unsafe { &mut *(data as *mut PerDocumentStyleData) }
+    }
+
+    /// Gets at the per-document data |data| stands for, which mustn't be null.
+    pub fn borrow_mut_from_raw_document_data<'a>(data: *mut RawServoPerDocumentData) -> &'a mut Self {
+        debug_assert!(!data.is_null());
         unsafe { &mut *(data as *mut PerDocumentStyleData) }
     }
+
+    /// Switches to a viewport of the given size, in CSS pixels. Returns whether that
+    /// changed which sheets or @media rules apply.
+    pub fn set_viewport_size(&mut self, viewport_size: TypedSize2D<ViewportPx, f32>) -> bool {
+        let new_size = Size2D::new(Au::from_f32_px(viewport_size.width.get()),
+                                   Au::from_f32_px(viewport_size.height.get()));
+        if new_size == self.viewport_size {
+            return false;
+        }
+        self.viewport_size = new_size;
+        self.anonymous_box_styles.evict_all();
+        // Only sheets using viewport units can compute to something different.
+        if self.stylesheets.iter().any(|sheet| sheet.dirty_on_viewport_size_change()) {
+            self.device_changed = true;
+            self.note_styles_stale();
+        }
+        let device = Device::new(self.media_type(), viewport_size);
+        self.stylist.set_device(device, &self.stylesheets)
+    }
+
+    fn media_type(&self) -> MediaType {
+        match &*self.medium {
+            "screen" => MediaType::Screen,
+            "print" => MediaType::Print,
+            _ => MediaType::Unknown,
+        }
+    }
+
+    pub fn medium(&self) -> &Atom {
+        &self.medium
+    }
+
+    /// Switches to styling for `medium`. Returns whether that changed which sheets or @media
+    /// rules apply, in which case the next restyle rebuilds the rules.
+    pub fn set_medium(&mut self, medium: Atom) -> bool {
+        if medium == self.medium {
+            return false;
+        }
+        self.medium = medium;
+        let viewport_size = Size2D::typed(self.viewport_size.width.to_f32_px(), self.viewport_size.height.to_f32_px());
+        let device = Device::new(self.media_type(), viewport_size);
+        self.stylist.set_device(device, &self.stylesheets)
+    }
+
+    pub fn author_style_disabled(&self) -> bool {
+        self.stylist.author_style_disabled()
+    }
+
+    /// Turns author style, that of the author sheets and style attributes, off or back on,
+    /// like Gecko's "No Style" mode. The next restyle rebuilds the author rules, dropping
+    /// them while author style is off.
+    pub fn set_author_style_disabled(&mut self, disabled: bool) {
+        if disabled != self.stylist.author_style_disabled() {
+            self.stylist.set_author_style_disabled(disabled);
+            self.note_stylesheets_changed(Origin::Author);
+        }
+    }
+
+    pub fn set_root_font_size(&mut self, root_font_size: Au) {
+        if root_font_size != self.root_font_size {
+            self.root_font_size = root_font_size;
+            self.device_changed = true;
+            self.note_styles_stale();
+            self.initial_values = None;
+            self.anonymous_box_styles.evict_all();
+        }
+    }
+
+    /// Notes that the colors or fonts of the platform's theme changed, which system color
+    /// keywords and `font: menu` and friends compute to. Which styles used them isn't
+    /// tracked, so the next restyle recomputes everything.
+    pub fn note_theme_changed(&mut self) {
+        self.device_changed = true;
+        self.platform_values = Arc::new(PlatformValues::new());
+        self.note_styles_stale();
+        self.anonymous_box_styles.evict_all();
+    }
+
+    /// The style of an element that no rule applies to and that has no parent, for the
+    /// current device.
+    pub fn initial_values(&mut self) -> Arc<GeckoComputedValues> {
+        if self.initial_values.is_none() {
+            let (mut style, _) = cascade(self.viewport_size, &[], false, None, None, Box::new(StdoutErrorReporter));
+            style.set_root_font_size(self.root_font_size);
+            self.initial_values = Some(Arc::new(style));
+        }
+        self.initial_values.clone().unwrap()
+    }
+
+    /// The style of the |pseudo| anonymous box inside |parent|, with the user agent rules for
+    /// it, from the cache if it was computed since the rules or the device last changed.
+    /// Like the rest of the style set, this mustn't be used while a traversal runs.
+    pub fn anonymous_box_style(&mut self, parent: Option<&Arc<GeckoComputedValues>>, pseudo: PseudoElement)
+                               -> Arc<GeckoComputedValues> {
+        let key = (parent.map_or(0, |parent| &**parent as *const GeckoComputedValues as usize), pseudo);
+        if let Some((_, style)) = self.anonymous_box_styles.find(&key) {
+            return style;
+        }
+        self.anonymous_box_cascades += 1;
+        let declarations = self.stylist.anonymous_box_declarations(&pseudo);
+        let (style, _) = cascade(self.viewport_size, &declarations, false, parent.map(|parent| &**parent), None,
+                                 Box::new(StdoutErrorReporter));
+        let style = Arc::new(style);
+        self.anonymous_box_styles.insert(key, (parent.cloned(), style.clone()));
+        style
+    }
+
+    pub fn anonymous_box_cascade_count(&self) -> u32 {
+        self.anonymous_box_cascades
+    }
+
+    pub fn set_quirks_mode(&mut self, quirks_mode: QuirksMode) {
+        if quirks_mode != self.quirks_mode {
+            self.quirks_mode = quirks_mode;
+            self.stylist.set_quirks_mode(quirks_mode == QuirksMode::Quirks);
+            // The quirks mode sheet is a user agent sheet, and the ids and classes of the
+            // other origins' selectors are only lowercased in quirks mode.
+            for &origin in &[Origin::UserAgent, Origin::User, Origin::Author] {
+                self.note_stylesheets_changed(origin);
+            }
+        }
+    }
+
+    /// Marks the rules from stylesheets of the given origin as needing to be rebuilt, for a
+    /// change that doesn't show in the list of sheets.
+    pub fn note_stylesheets_changed(&mut self, origin: Origin) {
+        self.unlisted_rule_changes = true;
+        self.mark_origin_dirty(origin);
+    }
+
+    fn mark_origin_dirty(&mut self, origin: Origin) {
+        if !self.dirty_origins.contains(&origin) {
+            self.dirty_origins.push(origin);
+        }
+        self.rule_changes_invalidated = false;
+        self.anonymous_box_styles.evict_all();
+    }
+
+    /// Whether |sheet| having been added since the last flush is the only change to the rules,
+    /// which is the one Servo_StyleSet_InvalidateForSheet can work out the affected elements of.
+    pub fn only_added_stylesheet(&self, sheet: &Arc<Stylesheet>) -> bool {
+        if self.unlisted_rule_changes || self.stylist.is_device_dirty() ||
+           self.rules_generations.iter().any(|&(ref x, _)| arc_ptr_eq(x, sheet)) {
+            return false;
+        }
+        let others: Vec<&Arc<Stylesheet>> = self.stylesheets.iter().filter(|x| !arc_ptr_eq(x, sheet)).collect();
+        others.len() + 1 == self.stylesheets.len() && others.len() == self.rules_generations.len() &&
+            others.iter().zip(&self.rules_generations).all(|(x, &(ref old, generation))| {
+                arc_ptr_eq(x, old) && x.rules_generation() == generation &&
+                    x.disabled() == self.disabled_stylesheets.iter().any(|d| arc_ptr_eq(d, x))
+            })
+    }
+
+    /// Notes that the elements the rule changes since the last flush can restyle have all been
+    /// marked dirty, so that the flush leaves the rest of the document alone. Any other change
+    /// to the rules before the flush takes this back.
+    pub fn note_rule_changes_invalidated(&mut self) {
+        self.rule_changes_invalidated = true;
+    }
+
+    /// The rules generation |sheet| had the last time the stylist was rebuilt, or None if it
+    /// wasn't in the set then.
+    pub fn recorded_rules_generation(&self, sheet: &Arc<Stylesheet>) -> Option<usize> {
+        self.rules_generations.iter().find(|&&(ref x, _)| arc_ptr_eq(x, sheet)).map(|&(_, generation)| generation)
+    }
+
+    /// Notes that the rule nodes got the declarations the CSSOM gave the rules of |sheet| since
+    /// the last flush, so that the flush rebuilds the stylist but only counts the other changes
+    /// to the rules as changing the styles elements may have.
+    pub fn note_declarations_replaced(&mut self, sheet: &Arc<Stylesheet>) {
+        let others_invalidated = self.rule_changes_invalidated || self.rules_unchanged_except(sheet);
+        let generation = sheet.rules_generation();
+        for &mut (ref x, ref mut recorded) in &mut self.rules_generations {
+            if arc_ptr_eq(x, sheet) {
+                *recorded = generation;
+            }
+        }
+        self.note_stylesheets_changed(sheet.origin);
+        if others_invalidated {
+            self.note_rule_changes_invalidated();
+        }
+    }
+
+    /// Whether the rules are the same as at the last flush, but for those of |sheet|.
+    fn rules_unchanged_except(&self, sheet: &Arc<Stylesheet>) -> bool {
+        if self.unlisted_rule_changes || self.stylist.is_device_dirty() || !self.dirty_origins.is_empty() {
+            return false;
+        }
+        self.stylesheets.len() == self.rules_generations.len() &&
+            self.stylesheets.iter().zip(&self.rules_generations).all(|(x, &(ref old, generation))| {
+                arc_ptr_eq(x, old) && (arc_ptr_eq(x, sheet) || x.rules_generation() == generation) &&
+                    x.disabled() == self.disabled_stylesheets.iter().any(|d| arc_ptr_eq(d, x))
+            })
+    }
+
+    /// Where the sheets of |origin| are in the list.
+    fn origin_range(&self, origin: Origin) -> Range<usize> {
+        let start = self.stylesheets.iter().take_while(|sheet| {
+            origin_index(sheet.origin) < origin_index(origin)
+        }).count();
+        let count = self.stylesheets[start..].iter().take_while(|sheet| sheet.origin == origin).count();
+        start..start + count
+    }
+
+    /// The sheets of |origin|, in the order they cascade in.
+    pub fn origin_stylesheets(&self, origin: Origin) -> &[Arc<Stylesheet>] {
+        &self.stylesheets[self.origin_range(origin)]
+    }
+
+    /// The sheets whose rules can apply, which leaves out the author sheets while author
+    /// style is disabled.
+    pub fn enabled_stylesheets(&self) -> &[Arc<Stylesheet>] {
+        if self.stylist.author_style_disabled() {
+            &self.stylesheets[..self.origin_range(Origin::Author).start]
+        } else {
+            &self.stylesheets
+        }
+    }
+
+    /// Adds a sheet after the others of its origin, moving it there if it was already in the set.
+    pub fn append_stylesheet(&mut self, sheet: &Arc<Stylesheet>) {
+        self.remove_stylesheet(sheet);
+        let index = self.origin_range(sheet.origin).end;
+        self.stylesheets.insert(index, sheet.clone());
+    }
+
+    /// Like append_stylesheet, for a sheet Gecko means to add to |origin|. A sheet's origin
+    /// is decided when it's parsed, so one parsed for another origin is added to its own.
+    pub fn append_stylesheet_to_origin(&mut self, sheet: &Arc<Stylesheet>, origin: Origin) {
+        if sheet.origin != origin {
+            warn!("Adding a {:?} sheet to the {:?} origin; adding it to its own", sheet.origin, origin);
+        }
+        self.append_stylesheet(sheet);
+    }
+
+    /// Like append_stylesheet, for a frozen sheet whose rule maps the stylist can take from
+    /// `shared` rather than build.
+    pub fn append_shared_stylesheet(&mut self, shared: &Arc<SharedStylesheet>) {
+        self.stylist.add_shared_stylesheet(shared.clone());
+        self.append_stylesheet(&shared.sheet);
+    }
+
+    /// Like append_stylesheet, for an author sheet that only applies to the descendants of
+    /// the element whose node is `scope`. Its rules cascade after those of the author sheets
+    /// that apply everywhere, as those of XBL bindings do in Gecko.
+    pub fn append_scoped_stylesheet(&mut self, sheet: &Arc<Stylesheet>, scope: OpaqueNode) {
+        if sheet.origin != Origin::Author {
+            warn!("Only author sheets can be scoped; applying the sheet everywhere");
+        } else {
+            self.stylist.set_stylesheet_scope(sheet, scope);
+        }
+        self.append_stylesheet(sheet);
+    }
+
+    /// Removes the sheets scoped to the element whose node is `scope`, which is leaving the
+    /// document.
+    pub fn remove_scoped_stylesheets(&mut self, scope: OpaqueNode) {
+        for sheet in self.stylist.forget_scope(scope) {
+            self.remove_stylesheet(&sheet);
+        }
+    }
+
+    /// Adds a sheet before the others of its origin, moving it there if it was already in the set.
+    pub fn prepend_stylesheet(&mut self, sheet: &Arc<Stylesheet>) {
+        self.remove_stylesheet(sheet);
+        let index = self.origin_range(sheet.origin).start;
+        self.stylesheets.insert(index, sheet.clone());
+    }
+
+    /// Adds a sheet right before `reference`, moving it there if it was already in the set.
+    /// The sheet is appended to its origin if `reference` isn't in the set or is of another
+    /// origin.
+    pub fn insert_stylesheet_before(&mut self, sheet: &Arc<Stylesheet>, reference: &Arc<Stylesheet>) {
+        self.remove_stylesheet(sheet);
+        match self.stylesheets.iter().position(|x| arc_ptr_eq(x, reference)) {
+            Some(index) if reference.origin == sheet.origin => self.stylesheets.insert(index, sheet.clone()),
+            _ => {
+                warn!("Inserting a stylesheet before one that isn't among those of its origin");
+                let index = self.origin_range(sheet.origin).end;
+                self.stylesheets.insert(index, sheet.clone());
+            }
+        }
+    }
+
+    pub fn remove_stylesheet(&mut self, sheet: &Arc<Stylesheet>) {
+        self.stylesheets.retain(|x| !arc_ptr_eq(x, sheet));
+        self.mark_origin_dirty(sheet.origin);
+    }
+
+    /// Swaps the whole list of sheets for a new one, in document order. Only the origins
+    /// whose sheets changed get their rules rebuilt.
+    pub fn replace_stylesheets(&mut self, mut sheets: Vec<Arc<Stylesheet>>) {
+        // The sort is stable, so each origin's sheets stay in document order.
+        sheets.sort_by_key(|sheet| origin_index(sheet.origin));
+        let old_sheets = mem::replace(&mut self.stylesheets, sheets);
+        for &origin in &[Origin::UserAgent, Origin::User, Origin::Author] {
+            let unchanged = {
+                let old: Vec<&Arc<Stylesheet>> = old_sheets.iter().filter(|sheet| sheet.origin == origin).collect();
+                let new = self.origin_stylesheets(origin);
+                old.len() == new.len() && old.iter().zip(new).all(|(a, b)| arc_ptr_eq(a, b))
+            };
+            if !unchanged {
+                self.mark_origin_dirty(origin);
+            }
+        }
+    }
+
+    /// Rebuilds the stylist's rule maps for the origins whose stylesheets changed since
+    /// the last flush. Everything is rebuilt if the device changed. Returns whether anything
+    /// was rebuilt, in which case any element's style may have changed, unless the elements
+    /// the changes affect were all marked dirty already.
+    pub fn flush_stylesheets(&mut self) -> bool {
+        let toggled: Vec<Origin> = self.stylesheets.iter().filter(|sheet| {
+            sheet.disabled() != self.disabled_stylesheets.iter().any(|x| arc_ptr_eq(x, sheet))
+        }).map(|sheet| sheet.origin).collect();
+        for origin in toggled {
+            self.mark_origin_dirty(origin);
+        }
+        self.disabled_stylesheets = self.stylesheets.iter().filter(|sheet| sheet.disabled())
+                                                    .cloned().collect();
+
+        // Sheets with no recorded generation were added since, and are dirty already.
+        let generations: Vec<usize> = self.stylesheets.iter().map(|sheet| sheet.rules_generation()).collect();
+        let mutated: Vec<Origin> = self.stylesheets.iter().zip(generations.iter()).filter(|&(sheet, &generation)| {
+            self.rules_generations.iter().any(|&(ref x, old)| arc_ptr_eq(x, sheet) && old != generation)
+        }).map(|(sheet, _)| sheet.origin).collect();
+        for origin in mutated {
+            self.mark_origin_dirty(origin);
+        }
+        self.rules_generations = self.stylesheets.iter().cloned().zip(generations.into_iter()).collect();
+
+        let rebuilt = if self.stylist.is_device_dirty() {
+            vec![Origin::UserAgent, Origin::User, Origin::Author]
+        } else {
+            self.dirty_origins.clone()
+        };
+        let updated = self.stylist.update_origins(&self.stylesheets, &self.dirty_origins);
+        let invalidated = mem::replace(&mut self.rule_changes_invalidated, false);
+        if updated {
+            self.anonymous_box_styles.evict_all();
+            // The styles of the elements that weren't marked dirty are still right.
+            if !invalidated {
+                self.note_styles_stale();
+            }
+            for origin in rebuilt {
+                self.rebuild_counts[origin_index(origin)] += 1;
+            }
+            let mut attribute_bloom = 0;
+            self.stylist.each_attribute_dependency(|name| {
+                attribute_bloom |= attribute_bloom_bits(atom_to_gecko(name))
+            });
+            self.attribute_bloom = attribute_bloom;
+            self.note_unsupported_rules();
+        }
+        self.dirty_origins.clear();
+        self.unlisted_rule_changes = false;
+        updated && !invalidated
+    }
+
+    /// Notes that the styles nodes were given until now may be out of date.
+    fn note_styles_stale(&mut self) {
+        self.style_generation = self.style_generation.wrapping_add(1);
+    }
+
+    pub fn rebuild_count(&self, origin: Origin) -> u32 {
+        self.rebuild_counts[origin_index(origin)]
+    }
+
+    /// Counts the unsupported rules of the sheets, whether they apply or not, and tells the
+    /// crash reporter how many there are and what they're called, so that crash reports show
+    /// which at-rules pages use that Servo doesn't implement yet. The annotation is for the
+    /// document whose rules were rebuilt last, and is left alone while no document has any.
+    fn note_unsupported_rules(&mut self) {
+        let mut count = 0;
+        let mut names: Vec<String> = vec![];
+        for sheet in &self.stylesheets {
+            for rule in sheet.rules().unsupported() {
+                count += 1;
+                if !names.contains(&rule.name) {
+                    names.push(rule.name.clone());
+                }
+            }
+        }
+        if count != 0 || self.unsupported_rule_count != 0 {
+            let annotation = if names.is_empty() {
+                count.to_string()
+            } else {
+                format!("{} ({})", count, names.join(", "))
+            };
+            annotate_crash_report("ServoUnsupportedCSSRules", &annotation);
+        }
+        self.unsupported_rule_count = count;
+    }
+
+    /// How many unsupported rules the sheets had the last time the rules were rebuilt.
+    pub fn unsupported_rule_count(&self) -> usize {
+        self.unsupported_rule_count
+    }
+
+    /// Whether the styles might depend on the attribute named by the Gecko atom `name`. False
+    /// positives are possible, but only if the styles depend on some attribute.
+    pub fn might_depend_on_attribute(&self, name: *mut nsIAtom) -> bool {
+        let bits = attribute_bloom_bits(name);
+        self.attribute_bloom & bits == bits
+    }
+
+    /// Notes that a traversal from the set started, until the returned value is dropped.
+    pub fn register_traversal(&self) -> TraversalRegistration {
+        *self.traversals.count.lock().unwrap() += 1;
+        TraversalRegistration(self.traversals.clone())
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Gets the set ready to be dropped: cancels the sheet parses started for it, so that
+    /// Gecko is never called back for them once this returns, waits for the traversals
+    /// running from it to finish, and frees its sheets and the styles it keeps. Restyling the
+    /// set does nothing afterwards, and neither do parses started for it. This mustn't be
+    /// called from one of its traversals, or from a callback made during one, which would
+    /// wait for itself.
+    pub fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        self.shut_down = true;
+        // The sheet parser is gone, and Gecko won't be called back, after Servo_Shutdown.
+        if let Some(global_data) = GlobalStyleData::get_if_initialized() {
+            global_data.sheet_parser.cancel_for_set(self as *const PerDocumentStyleData as usize);
+        }
+        {
+            let mut count = self.traversals.count.lock().unwrap();
+            while *count > 0 {
+                count = self.traversals.finished.wait(count).unwrap();
+            }
+        }
+        self.stylesheets.clear();
+        self.disabled_stylesheets.clear();
+        self.rules_generations.clear();
+        self.anonymous_box_styles.evict_all();
+        self.initial_values = None;
+        self.rule_tree = None;
+    }
 }
 
 impl Drop for PerDocumentStyleData {
     fn drop(&mut self) {
-        self.work_queue.shutdown();
+        self.shutdown();
+    }
+}
+
+/// How many traversals are running from a style set.
+struct RunningTraversals {
+    count: Mutex<usize>,
+    finished: Condvar,
+}
+
+/// Counts a traversal as running from a style set for as long as it's alive.
+pub struct TraversalRegistration(Arc<RunningTraversals>);
+
+impl Drop for TraversalRegistration {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.0.finished.notify_all();
+        }
+    }
+}
+
+/// The two bits of the attribute bloom filter an atom sets. Atoms are at least 8-byte
+/// aligned, so the low bits of their address carry nothing.
+fn attribute_bloom_bits(atom: *mut nsIAtom) -> u64 {
+    let address = atom as usize;
+    (1 << ((address >> 3) & 63)) | (1 << ((address >> 9) & 63))
+}
+
+impl MallocSizeOf for PerDocumentStyleData {
+    /// Measures what the style set owns. That doesn't include its sheets, which Gecko
+    /// measures through Servo_StyleSheet_SizeOfIncludingThis.
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        // The selector maps are private to the selectors crate, so the stylist can only
+        // be measured with heapsize.
+        self.stylist.heap_size_of_children() +
+            ops.vec_buffer_size_of(&self.stylesheets) +
+            ops.vec_buffer_size_of(&self.dirty_origins) +
+            ops.vec_buffer_size_of(&self.disabled_stylesheets) +
+            ops.vec_buffer_size_of(&self.rules_generations) +
+            self.initial_values.malloc_size_of_children(ops)
+    }
+}
+
+fn origin_index(origin: Origin) -> usize {
+    match origin {
+        Origin::UserAgent => 0,
+        Origin::User => 1,
+        Origin::Author => 2,
     }
 }
@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use style::element_state as servo;
+
+// Keep these in sync with the NS_EVENT_STATE_* constants in dom/events/EventStates.h.
+bitflags! {
+    #[doc = "Gecko's event-based element states, as returned by Gecko_ElementState."]
+    flags ElementState: u64 {
+        const NS_EVENT_STATE_ACTIVE = 1 << 0,
+        const NS_EVENT_STATE_FOCUS = 1 << 1,
+        const NS_EVENT_STATE_HOVER = 1 << 2,
+        const NS_EVENT_STATE_DRAGOVER = 1 << 3,
+        const NS_EVENT_STATE_URLTARGET = 1 << 4,
+        const NS_EVENT_STATE_CHECKED = 1 << 5,
+        const NS_EVENT_STATE_ENABLED = 1 << 6,
+        const NS_EVENT_STATE_DISABLED = 1 << 7,
+        const NS_EVENT_STATE_INDETERMINATE = 1 << 8,
+        const NS_EVENT_STATE_DEFAULT = 1 << 14,
+        const NS_EVENT_STATE_REQUIRED = 1 << 15,
+        const NS_EVENT_STATE_OPTIONAL = 1 << 16,
+        const NS_EVENT_STATE_VISITED = 1 << 17,
+        const NS_EVENT_STATE_UNVISITED = 1 << 18,
+        const NS_EVENT_STATE_VALID = 1 << 19,
+        const NS_EVENT_STATE_INVALID = 1 << 20,
+        const NS_EVENT_STATE_INRANGE = 1 << 21,
+        const NS_EVENT_STATE_OUTOFRANGE = 1 << 22,
+        const NS_EVENT_STATE_MOZ_READONLY = 1 << 23,
+        const NS_EVENT_STATE_MOZ_READWRITE = 1 << 24,
+        const NS_EVENT_STATE_LTR = 1 << 39,
+        const NS_EVENT_STATE_RTL = 1 << 40,
+        const NS_EVENT_STATE_FOCUS_WITHIN = 1 << 43,
+        const NS_EVENT_STATE_PLACEHOLDERSHOWN = 1 << 44,
+    }
+}
+
+// Keep these in sync with the NS_DOCUMENT_STATE_* constants in dom/base/nsIDocument.h.
+bitflags! {
+    #[doc = "Gecko's document-wide states, as returned by Gecko_GetDocumentState."]
+    flags DocumentState: u64 {
+        const NS_DOCUMENT_STATE_RTL_LOCALE = 1 << 0,
+        const NS_DOCUMENT_STATE_WINDOW_INACTIVE = 1 << 1,
+        const NS_DOCUMENT_STATE_LWTHEME = 1 << 2,
+        const NS_DOCUMENT_STATE_LWTHEME_BRIGHTTEXT = 1 << 3,
+        const NS_DOCUMENT_STATE_LWTHEME_DARKTEXT = 1 << 4,
+    }
+}
+
+impl ElementState {
+    /// Converts the bits that have a Servo equivalent into Servo's ElementState, which
+    /// is what the style system uses to track state changes for restyle hints.
+    pub fn to_servo(&self) -> servo::ElementState {
+        let mut state = servo::ElementState::empty();
+        if self.contains(NS_EVENT_STATE_ACTIVE) { state.insert(servo::IN_ACTIVE_STATE) }
+        if self.contains(NS_EVENT_STATE_FOCUS) { state.insert(servo::IN_FOCUS_STATE) }
+        if self.contains(NS_EVENT_STATE_HOVER) { state.insert(servo::IN_HOVER_STATE) }
+        if self.contains(NS_EVENT_STATE_ENABLED) { state.insert(servo::IN_ENABLED_STATE) }
+        if self.contains(NS_EVENT_STATE_DISABLED) { state.insert(servo::IN_DISABLED_STATE) }
+        if self.contains(NS_EVENT_STATE_CHECKED) { state.insert(servo::IN_CHECKED_STATE) }
+        if self.contains(NS_EVENT_STATE_INDETERMINATE) { state.insert(servo::IN_INDETERMINATE_STATE) }
+        if self.contains(NS_EVENT_STATE_FOCUS_WITHIN) { state.insert(servo::IN_FOCUS_WITHIN_STATE) }
+        if self.contains(NS_EVENT_STATE_PLACEHOLDERSHOWN) { state.insert(servo::IN_PLACEHOLDER_SHOWN_STATE) }
+        state
+    }
+}
@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Keeps panics from unwinding out of the functions Gecko calls, which is undefined
+//! behavior, and defines what those functions do with the null pointers Gecko
+//! shouldn't pass them.
+//!
+//! Refcounting functions (AddRef, Release and Drop) are no-ops on null, like their
+//! XPCOM counterparts. Every other function asserts in debug builds, naming itself and
+//! the argument, and returns early with a harmless value in release builds.
+//!
+//! Gecko may only call them between Servo_Initialize and Servo_Shutdown, which debug
+//! builds check too.
+
+use backtrace::Backtrace;
+use bindings::Gecko_AnnotateCrashReport;
+use std::any::Any;
+use std::panic::{self, AssertRecoverSafe};
+use std::sync::atomic::{ATOMIC_BOOL_INIT, AtomicBool, Ordering};
+
+/// Defines a function for Gecko to call. If its body panics, the panic is logged and
+/// reported to the crash reporter, and the function evaluates the `on_panic` expression
+/// instead: a value Gecko can cope with, like null or false, or `abort()` where carrying
+/// on would leave things in a broken state.
+///
+/// ```ignore
+/// servo_function! {
+///     fn Servo_Foo_GetCount(foo: *mut RawServoFoo) -> u32 [on_panic: 0] {
+///         ...
+///     }
+/// }
+/// ```
+///
+/// Functions Gecko may call before Servo_Initialize say so with `before_initialize`, as in
+/// `[on_panic: abort(), before_initialize]`.
+macro_rules! servo_function {
+    (
+        @define [$($check:tt)*]
+        $(#[$attr:meta])*
+        fn $name:ident($($arg:ident: $arg_type:ty),*) -> $ret:ty [on_panic: $on_panic:expr] $body:block
+    ) => {
+        $(#[$attr])*
+        #[no_mangle]
+        pub extern "C" fn $name($($arg: $arg_type),*) -> $ret {
+            // For return_if_null!.
+            const FUNCTION_NAME: &'static str = stringify!($name);
+            ::ffi::catch_panics(FUNCTION_NAME, move || -> $ret { $($check)* $body }, || $on_panic)
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        fn $name:ident($($arg:ident: $arg_type:ty),*) -> $ret:ty
+            [on_panic: $on_panic:expr, before_initialize] $body:block
+    ) => {
+        servo_function! {
+            @define []
+            $(#[$attr])*
+            fn $name($($arg: $arg_type),*) -> $ret [on_panic: $on_panic] $body
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        fn $name:ident($($arg:ident: $arg_type:ty),*) -> $ret:ty [on_panic: $on_panic:expr] $body:block
+    ) => {
+        servo_function! {
+            @define [::ffi::debug_assert_initialized(FUNCTION_NAME);]
+            $(#[$attr])*
+            fn $name($($arg: $arg_type),*) -> $ret [on_panic: $on_panic] $body
+        }
+    };
+}
+
+/// Returns `$on_null` from the `servo_function!` it's used in if any of the given
+/// arguments is null. Debug builds assert first, since it means Gecko broke the contract.
+///
+/// ```ignore
+/// return_if_null!(raw_sheet, raw_data; ());
+/// ```
+macro_rules! return_if_null {
+    ($($arg:ident),+; $on_null:expr) => {{
+        $(
+            if $arg.is_null() {
+                debug_assert!(false, "{} was passed a null {}", FUNCTION_NAME, stringify!($arg));
+                return $on_null;
+            }
+        )+
+    }}
+}
+
+static INITIALIZED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Records whether Gecko is between its calls to Servo_Initialize and Servo_Shutdown.
+pub fn set_initialized(initialized: bool) {
+    INITIALIZED.store(initialized, Ordering::SeqCst);
+}
+
+/// Asserts in debug builds that Gecko called `name` between Servo_Initialize and
+/// Servo_Shutdown.
+pub fn debug_assert_initialized(name: &'static str) {
+    debug_assert!(INITIALIZED.load(Ordering::SeqCst),
+                  "{} was called outside of Servo_Initialize and Servo_Shutdown", name);
+}
+
+/// Runs `f`, or `on_panic` if `f` panics. `name` is the function Gecko called.
+pub fn catch_panics<R, F, P>(name: &'static str, f: F, on_panic: P) -> R
+                             where F: FnOnce() -> R, P: FnOnce() -> R {
+    match panic::recover(AssertRecoverSafe::new(f)) {
+        Ok(result) => result,
+        Err(_) => {
+            error!("{} panicked", name);
+            annotate_crash_report("ServoPanicFunction", name);
+            on_panic()
+        }
+    }
+}
+
+/// Has every panic report its message and location to the crash reporter before the
+/// default handler prints them. Panics before Servo_Initialize only get printed.
+pub fn install_panic_handler() {
+    let default_handler = panic::take_handler();
+    panic::set_handler(move |info| {
+        let message = panic_message(info.payload());
+        let report = match info.location() {
+            Some(location) => format!("{} at {}:{}", message, location.file(), location.line()),
+            None => message.to_owned(),
+        };
+        // The default handler only prints its backtrace, so we take our own for Gecko.
+        let report = format!("{}\n{:?}", report, Backtrace::new());
+        annotate_crash_report("ServoPanicMessage", &report);
+        default_handler(info);
+    });
+}
+
+/// Puts the default panic handler back, freeing ours.
+pub fn uninstall_panic_handler() {
+    let _ = panic::take_handler();
+}
+
+fn panic_message(payload: &(Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<Any>"
+    }
+}
+
+/// Has the crash reporter send `value` along as `key` with the report of any crash from now
+/// on, until it's given another value for `key`.
+pub fn annotate_crash_report(key: &str, value: &str) {
+    unsafe {
+        Gecko_AnnotateCrashReport(key.as_ptr(), key.len() as u32, value.as_ptr(), value.len() as u32);
+    }
+}
@@ -5,28 +5,165 @@
 #![allow(unsafe_code)]
 
 use app_units::Au;
-use bindings::{RawGeckoDocument, RawGeckoElement};
-use bindings::{RawServoStyleSet, RawServoStyleSheet, ServoComputedValues, ServoNodeData};
-use bindings::{nsIAtom};
-use data::PerDocumentStyleData;
-use euclid::Size2D;
-use properties::GeckoComputedValues;
-use selector_impl::{SharedStyleContext, Stylesheet};
+use atoms::{atom_from_gecko, atom_to_gecko, drop_atom_caches, namespace_from_gecko};
+use bindings::{Gecko_AppendFontFaceRule, Gecko_FontFaceRule_AppendFormatHint, Gecko_FontFaceRule_AppendLocalSource};
+use bindings::{Gecko_AppendKeyframe, Gecko_ContentList_AppendElement, Gecko_GetDocumentCompatMode};
+use bindings::{Gecko_GetHTMLPresentationAttrDeclarationBlock, Gecko_GetStyleAttrDeclarationBlock};
+use bindings::{Gecko_LoadStyleSheet, Gecko_PropertyList_Append, Gecko_ReportCSSError, RawGeckoContentList};
+use bindings::{Gecko_RuleListAppend, Gecko_RuleListAppendDeclarations, RawGeckoPropertyList, RawGeckoRuleList};
+use bindings::{Gecko_FontFaceRule_AppendURLSource, Gecko_FontFaceRule_AppendUnicodeRange};
+use bindings::{Gecko_Utf8SliceToString, RawGeckoFontFaceRuleList, RawGeckoKeyframeList};
+use bindings::{RawGeckoDocument, RawGeckoElement, RawGeckoNode, RawGeckoURLExtraData};
+use bindings::{RawServoAnimationValue, RawServoCssRules, RawServoDeclarationBlock, RawServoImportRule};
+use bindings::{RawServoCounterStyleRule, RawServoCounterStyleRuleStrong, RawServoPerDocumentData};
+use bindings::{RawServoMediaList, RawServoSelectorList, RawServoStyleRule, RawServoStyleSet, RawServoStyleSheet};
+use bindings::{RawServoSharedStyleSheet, RawServoSharedStyleSheetStrong};
+use bindings::{RawServoAttributeSnapshot, ServoAttrSnapshot, ServoComputedValues, ServoElementSnapshot, ServoNodeData};
+use bindings::ServoRestyleHint;
+use bindings::{RawGeckoTransformList, ServoTraversalStatistics, ServoTransformFunction, ServoTransformOperation};
+use bindings::{Gecko_ContentItemList_Append, Gecko_TransformList_SetLength, RawGeckoContentItemList};
+use bindings::{ServoContentItem, ServoContentItemType, ServoContentKind};
+use bindings::{Gecko_ShadowArray_SetLength, RawGeckoShadowArray, ServoShadowItem};
+use bindings::{Gecko_FontFeatureArray_SetLength, RawGeckoFontFeatureArray, ServoFontFeature};
+use bindings::{ServoBorderImageKind, ServoBorderImageRepeat, ServoBorderImageSide, ServoBorderImageUnit};
+use bindings::{Gecko_GradientStopArray_SetLength, ServoBorderInfo, ServoGradientStop, ServoImageInfo, ServoImageKind};
+use bindings::{Gecko_CursorImageArray_SetLength, RawGeckoCursorImageArray, ServoBackgroundLayer, ServoCursorImage};
+use bindings::{Gecko_CounterItemArray_SetLength, RawGeckoCounterItemArray, ServoCounterItem};
+use bindings::{RawServoAnimationValueStrong, RawServoCssRulesStrong, RawServoDeclarationBlockStrong};
+use bindings::{RawServoMediaListStrong, RawServoStyleRuleStrong, RawServoStyleSheetStrong, ServoComputedValuesStrong};
+use bindings::{ServoLengthOrPercentageOrAuto, ServoLengthUnit, ServoPageSize, ServoPageSizeKind};
+use bindings::{ServoScrollFrameInfo, ServoTabSizeKind, ServoTextSpacingKind, ServoTextStyleFFI};
+use bindings::{ServoColumnInfo, ServoFlexInfo, ServoTextOverflow, ServoTextOverflowSide, ServoUIInfo};
+use bindings::{ServoStyleClear, ServoStyleDisplay, ServoStyleFloat, ServoStyleIsolation, ServoStylePosition};
+use bindings::{ServoStepPosition, ServoTimingFunction, ServoTimingFunctionType};
+use bindings::{Gecko_Construct_nsStyleDisplay, Gecko_Construct_nsStyleMargin, Gecko_Construct_nsStylePadding};
+use bindings::{Gecko_Construct_nsStylePosition, Gecko_Construct_nsStyleVisibility, nsStyleDisplay_FFI};
+use bindings::{nsStyleMargin_FFI, nsStylePadding_FFI, nsStylePosition_FFI, nsStyleVisibility_FFI};
+use bindings::{Gecko_ReportCSSErrorWithDetails, ServoCSSErrorKind, ServoSelectorParseError};
+use bindings::{StyleSheetOrigin, nsCSSProperty, nsChangeHint, nsIAtom, nsString, nsresult};
+use cssparser::{Color, Parser, SourcePosition, ToCss};
+use data::{GlobalStyleData, NUM_THREADS, PerDocumentStyleData};
+use element_state::{NS_EVENT_STATE_FOCUS, NS_EVENT_STATE_FOCUS_WITHIN};
+use euclid::{Point2D, Size2D};
+use ffi;
+use gecko_style_structs;
+use image_request;
+use logging;
+use main_thread;
+use node_data_dropper;
+use ownership::{self, Borrowed, HasArcFFI, Strong};
+use platform_values;
+use properties::{GeckoComputedValues, longhand_name_from_gecko, longhand_to_gecko, shorthand_name_from_gecko};
+use properties::{convert_rgba_to_nscolor, style_struct_id_from_gecko};
+use restyle_damage::{GeckoRestyleDamage, will_change_bits};
+use selectors::Element;
+use selectors::bloom::BloomFilter;
+use selectors::matching::{matches, matches_compound_selector};
+use selectors::parser::{CompoundSelector, ParserContext as SelectorParserContext, Selector, SimpleSelector};
+use selector_impl::{GeckoSelectorImpl, ImportRule, PseudoElement, SharedStyleContext, SharedStylesheet};
+use selector_impl::{NonTSPseudoClass, PrivateStyleData, Stylesheet, Stylist};
+use std::borrow::Cow;
+use std::ffi::CString;
 use std::marker::PhantomData;
-use std::mem::{forget, transmute};
+use std::mem;
+use std::os::raw::c_void;
+use std::process::abort;
+use std::i32;
+use std::io::{self, Write};
 use std::ptr;
 use std::slice;
 use std::str::from_utf8_unchecked;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use string_cache::Atom;
+use style::animation::AnimationValue;
 use style::context::{ReflowGoal, StylistWrapper};
-use style::dom::{TDocument, TElement, TNode};
-use style::error_reporting::StdoutErrorReporter;
+use style::counter_style::{CounterStyleRule, SpeakAs, Symbol, System, find_counter_style_rule};
+use style::custom_properties;
+use style::dom::{TDocument, TElement, TNode, TRestyleDamage};
+use style::error_reporting::{ParseErrorDetails, ParseErrorReporter, StdoutErrorReporter};
+use style::font_face::{Source, effective_font_face_rules};
+use style::keyframes::find_keyframes_rule;
+use style::malloc_size_of::{MallocSizeOf, MallocSizeOfFn, MallocSizeOfOps};
+use style::matching::MatchMethods;
+use style::media_queries::{Device, MediaQueryList, parse_media_query_list};
+use style::page::{Page, PageOrientation, PageSize, resolve_page_style};
 use style::parallel;
-use style::stylesheets::Origin;
-use traversal::RecalcStyleOnly;
+use style::parser::{ParserContext, QuirksMode};
+use style::properties::longhands::_x_lang;
+use style::properties::longhands::align_content::computed_value::T as AlignContent;
+use style::properties::longhands::align_items::computed_value::T as AlignItems;
+use style::properties::longhands::align_self::computed_value::T as AlignSelf;
+use style::properties::longhands::backface_visibility::computed_value::T as BackfaceVisibility;
+use style::properties::longhands::box_sizing::computed_value::T as BoxSizing;
+use style::properties::longhands::clear::computed_value::T as Clear;
+use style::properties::longhands::cursor::computed_value::Keyword as CursorKeyword;
+use style::properties::longhands::direction::computed_value::T as Direction;
+use style::properties::longhands::display::computed_value::T as Display;
+use style::properties::longhands::flex_basis::computed_value::T as FlexBasis;
+use style::properties::longhands::flex_direction::computed_value::T as FlexDirection;
+use style::properties::longhands::flex_wrap::computed_value::T as FlexWrap;
+use style::properties::longhands::float::computed_value::T as Float;
+use style::properties::longhands::font_feature_settings::computed_value::T as FontFeatureSettings;
+use style::properties::longhands::font_stretch::computed_value::T as FontStretch;
+use style::properties::longhands::font_style::computed_value::T as FontStyle;
+use style::properties::longhands::image_rendering::computed_value::T as ImageRendering;
+use style::properties::longhands::isolation::computed_value::T as Isolation;
+use style::properties::longhands::justify_content::computed_value::T as JustifyContent;
+use style::properties::longhands::mix_blend_mode::computed_value::T as MixBlendMode;
+use style::properties::longhands::overflow_x::computed_value::T as Overflow;
+use style::properties::longhands::pointer_events::computed_value::T as PointerEvents;
+use style::properties::longhands::position::computed_value::T as Position;
+use style::properties::longhands::resize::computed_value::T as Resize;
+use style::properties::longhands::scroll_snap_type_x::computed_value::T as SnapTypeX;
+use style::properties::longhands::scroll_snap_type_y::computed_value::T as SnapTypeY;
+use style::properties::longhands::text_orientation::computed_value::T as TextOrientation;
+use style::properties::longhands::text_overflow::computed_value::Side as TextOverflowSide;
+use style::properties::longhands::touch_action::computed_value as touch_action;
+use style::properties::longhands::transform::computed_value::ComputedOperation;
+use style::properties::longhands::transform_style::computed_value::T as TransformStyle;
+use style::properties::longhands::transition_property;
+use style::properties::longhands::transition_property::computed_value::TransitionProperty;
+use style::properties::longhands::transition_timing_function;
+use style::properties::longhands::transition_timing_function::computed_value::{StepPosition, TransitionTimingFunction};
+use style::properties::longhands::user_select::computed_value::T as UserSelect;
+use style::properties::longhands::visibility::computed_value::T as Visibility;
+use style::properties::longhands::writing_mode::computed_value::T as WritingModeKeyword;
+use style::properties::longhands::z_index::computed_value::T as ZIndex;
+use style::properties::style_struct_traits::{TBackground, TBorder, TBox, TColumn, TEffects, TFlex, TFont};
+use style::properties::style_struct_traits::{TInheritedBox, TInheritedText, TMargin, TPadding, TPointing};
+use style::properties::style_struct_traits::TPositionOffsets;
+use style::properties::{ComputedValues, DeclaredValue, PropertyDeclaration, PropertyDeclarationBlock, Shorthand};
+use style::properties::PropertyDeclarationName;
+use style::properties::{cascade, inherit_from, parse_one_declaration, parse_property_value, parse_style_attribute};
+use style::properties::restrict_declarations;
+use style::properties::{serialize_shorthand, set_property_enabled};
+use style::restyle_hints::{RESTYLE_SELF, RestyleHint};
+use style::selector_impl::{SelectorImplExt, parse_selector_list_for_matching, serialize_selector};
+use style::selector_impl::{ascii_lowercase_ids_and_classes, ascii_lowercase_selector_list, find_invalid_selector};
+use style::selector_impl::serialize_selector_list;
+use style::selector_matching::{CascadeLevel, DeclarationBlock};
+use style::sequential;
+use style::shared_lock::{Locked, SharedRwLockReadGuard};
+use style::stylesheets::{CSSRule, Origin, RulesMutateError, SourceLocation, StyleRule, StylesheetLoader};
+use style::stylesheets::{add_namespace_prefixes, decode_utf8_lossy, delete_rule, insert_rule};
+use style::supports::{supports_condition, supports_declaration};
+use style::traversal_stats;
+use style::url_value::UrlExtraData;
+use style::values::computed::{CalcLengthOrPercentage, ColorStop, LengthOrPercentage, LengthOrPercentageOrAuto};
+use style::values::computed::{ExtremumLength, LengthOrPercentageOrNone, MaxLength, MinLength, MozLength};
+use style::values::specified::BorderStyle;
+use style_traits::cursor::Cursor;
+use traversal::{DETERMINISTIC_TRAVERSAL, RecalcStyleOnly, drop_local_context, is_deterministic};
+use traversal::{StandaloneStyleContext, propagate_rule_change_damage, restyle_for_animations};
+use traversal::restyle_for_rule_changes;
 use url::Url;
+use url_extra_data::{url_extra_data_from_gecko, url_extra_data_to_gecko};
 use util::arc_ptr_eq;
-use wrapper::{GeckoDocument, GeckoElement, GeckoNode, NonOpaqueStyleData};
+use wrapper::{DomToken, GeckoDocument, GeckoElement, GeckoElementSnapshot, GeckoNode, NodeData};
+use wrapper::{NonOpaqueStyleData, SnapshotFlags, clear_node_info_cache, in_quirks_mode_document};
+use wrapper::set_document_quirks_mode;
 
 /*
  * For Gecko->Servo function calls, we need to redeclare the same signature that was declared in
@@ -36,172 +173,5818 @@ use wrapper::{GeckoDocument, GeckoElement, GeckoNode, NonOpaqueStyleData};
  * depend on but good enough for our purposes.
  */
 
-#[no_mangle]
-pub extern "C" fn Servo_RestyleDocument(doc: *mut RawGeckoDocument, raw_data: *mut RawServoStyleSet) -> () {
-    let document = unsafe { GeckoDocument::from_raw(doc) };
-    let node = match document.root_node() {
-        Some(x) => x,
-        None => return,
-    };
+fn restyle_subtree(node: GeckoNode, raw_data: *mut RawServoStyleSet) {
     let data = unsafe { &mut *(raw_data as *mut PerDocumentStyleData) };
+    if data.is_shut_down() {
+        return;
+    }
+    let _traversal = data.register_traversal();
+    let global_data = GlobalStyleData::get();
+    // Held until the traversal is done, so that no style attribute or rule changes while
+    // the worker threads read them.
+    let guard = global_data.shared_lock.read();
 
-    let _needs_dirtying = data.stylist.update(&data.stylesheets, data.stylesheets_changed);
-    data.stylesheets_changed = false;
+    let rules_changed = data.flush_stylesheets();
+    data.generation = data.generation.wrapping_add(1);
+    node.fetch_document_state();
+    set_document_quirks_mode(data.quirks_mode);
+    // Gecko only tells this thread about the platform, so the style threads get what they
+    // can ask for ahead of time.
+    Arc::make_mut(&mut data.platform_values).fetch();
+
+    // The traversal skips the subtrees Gecko didn't mark as needing a restyle, which is only
+    // right if the rules and the device stayed the same. Nodes that were never styled need
+    // everything under them styled.
+    if rules_changed || data.device_changed || node.borrow_data().map_or(true, |data| data.style.is_none()) {
+        node.dirty_self();
+        node.dirty_descendants();
+    }
 
     let shared_style_context = SharedStyleContext {
-        viewport_size: Size2D::new(Au(0), Au(0)),
-        screen_size_changed: false,
-        generation: 0,
+        viewport_size: data.viewport_size,
+        screen_size_changed: data.device_changed,
+        generation: data.generation,
+        style_generation: data.style_generation,
         goal: ReflowGoal::ForScriptQuery,
         stylist: StylistWrapper(&data.stylist),
         new_animations_sender: Mutex::new(data.new_animations_sender.clone()),
         running_animations: data.running_animations.clone(),
         expired_animations: data.expired_animations.clone(),
         error_reporter: Box::new(StdoutErrorReporter),
+        rule_tree: data.rule_tree.clone(),
+        guard: guard,
+    };
+
+    // The elements whose rule nodes got new declarations are cascaded again first, and the
+    // ones that turn out to need matching are marked dirty for the traversal.
+    let start = Instant::now();
+    let mut rule_change_restyled = vec![];
+    let rule_changes = match data.rule_tree {
+        Some(ref rule_tree) if rule_tree.has_replacements() => {
+            let context = StandaloneStyleContext::new(&shared_style_context);
+            restyle_for_rule_changes(node, node.opaque(), rule_tree, &context,
+                                     false, &mut rule_change_restyled);
+            if node.parent_node().map_or(true, |parent| !parent.is_element()) {
+                rule_tree.forget_replacements();
+            }
+            true
+        }
+        _ => false,
     };
 
+    let mut fonts_remeasured = false;
     if node.is_dirty() || node.has_dirty_descendants() {
-        parallel::traverse_dom::<GeckoNode, RecalcStyleOnly>(node, &shared_style_context, &mut data.work_queue);
+        {
+            let mut work_queue = global_data.work_queue.lock().unwrap();
+            if is_deterministic() {
+                drop_local_context();
+                sequential::traverse_dom::<GeckoNode, RecalcStyleOnly>(node, &shared_style_context);
+            } else {
+                match *work_queue {
+                    Some(ref mut work_queue) => {
+                        platform_values::start_traversal(data.platform_values.clone());
+                        parallel::traverse_dom::<GeckoNode, RecalcStyleOnly>(node, &shared_style_context,
+                                                                             work_queue);
+                        platform_values::end_traversal();
+                    }
+                    None => {
+                        sequential::traverse_dom::<GeckoNode, RecalcStyleOnly>(node, &shared_style_context);
+                    }
+                }
+            }
+            if let Some(ref work_queue) = *work_queue {
+                work_queue.run_on_each_worker(clear_node_info_cache);
+            }
+        }
+        if let Some(ref rule_tree) = data.rule_tree {
+            rule_tree.gc();
+        }
+        // The worker threads left the image loads of the styles they computed to this thread,
+        // and freed the styles the traversal replaced.
+        image_request::start_loads_requested_elsewhere();
+        image_request::release_requests_dropped_elsewhere();
+        fonts_remeasured = Arc::make_mut(&mut data.platform_values).measure_fonts_requested_elsewhere();
+    } else if !rule_changes {
+        clear_node_info_cache();
+        data.device_changed = false;
+        return;
+    }
+    propagate_rule_change_damage(node.opaque(), &rule_change_restyled);
+    if traversal_stats::enabled() {
+        let statistics = collect_traversal_statistics(global_data, start.elapsed());
+        *global_data.traversal_statistics.lock().unwrap() = statistics;
+    }
+    clear_node_info_cache();
+    // The styles computed with stand-ins for the fonts the style threads found unmeasured,
+    // which turned out to have metrics or sizes of their own, are left for the next restyle.
+    data.device_changed = fonts_remeasured;
+    if fonts_remeasured {
+        node.dirty_self();
+    }
+}
+
+/// Gathers what every thread counted during the traversal that just finished.
+fn collect_traversal_statistics(global_data: &GlobalStyleData, elapsed: Duration) -> ServoTraversalStatistics {
+    traversal_stats::collect_thread_statistics();
+    if let Some(ref work_queue) = *global_data.work_queue.lock().unwrap() {
+        work_queue.run_on_each_worker(traversal_stats::collect_thread_statistics);
+    }
+    let stats = traversal_stats::take_collected_statistics();
+    ServoTraversalStatistics {
+        mElementsTraversed: stats.elements_traversed,
+        mElementsStyled: stats.elements_styled,
+        mStylesShared: stats.styles_shared,
+        mSelectorsMatched: stats.selectors_matched,
+        mDeclarationsCascaded: stats.declarations_cascaded,
+        mNodeQueries: stats.node_queries,
+        mNodeFFICalls: stats.node_ffi_calls,
+        mTraversalTimeMs: elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1_000_000.0,
+    }
+}
+
+servo_function! {
+    /// Sets up the state every style set shares: the traversal's thread pool, the panic
+    /// handler that reports to the crash reporter, and the logger that sends log records to
+    /// Gecko_Log. Gecko calls this once at startup, before any other Servo_* function but
+    /// Servo_InitializeThreadPool.
+    fn Servo_Initialize() -> () [on_panic: abort(), before_initialize] {
+        logging::install();
+        ffi::install_panic_handler();
+        image_request::set_main_thread();
+        main_thread::record_main_thread();
+        GlobalStyleData::initialize();
+        ffi::set_initialized(true);
+    }
+}
+
+servo_function! {
+    /// Frees everything Servo_Initialize set up, along with the atoms handed out since, so
+    /// that leak checkers don't see any of it. No Servo_* function may be called afterwards,
+    /// and every style set, sheet and style must have been released already.
+    fn Servo_Shutdown() -> () [on_panic: abort()] {
+        ffi::set_initialized(false);
+        GlobalStyleData::shutdown();
+        image_request::release_requests_dropped_elsewhere();
+        platform_values::forget_languages();
+        unsafe { drop_atom_caches() };
+        ffi::uninstall_panic_handler();
+        logging::uninstall();
+    }
+}
+
+servo_function! {
+    /// Sets the number of threads the traversal will use. Zero picks one based on the number
+    /// of CPUs, and one styles sequentially on the calling thread. It only takes effect if
+    /// called before Servo_Initialize, and the `STYLO_THREADS` environment variable wins.
+    ///
+    /// Only Gecko_* functions that are safe to call off the main thread may be used during
+    /// the traversal. Anything else needs to be gathered on the main thread beforehand.
+    fn Servo_InitializeThreadPool(num_threads: u32) -> () [on_panic: abort(), before_initialize] {
+        NUM_THREADS.store(num_threads as usize, Ordering::Relaxed);
+    }
+}
+
+servo_function! {
+    /// Turns on or off the deterministic traversal, for reproducing bugs that depend on
+    /// timing. While it's on, restyles run on the calling thread and visit children in
+    /// document order, each traversal starts with empty style sharing and declaration
+    /// caches, and those caches hash with fixed keys, so that the same document comes out
+    /// with the same styles, shared the same way, every time. Setting `STYLO_DETERMINISTIC`
+    /// in the environment turns it on from Servo_Initialize.
+    fn Servo_SetDeterministicTraversal(enabled: bool) -> () [on_panic: (), before_initialize] {
+        DETERMINISTIC_TRAVERSAL.store(enabled, Ordering::Relaxed);
+    }
+}
+
+servo_function! {
+    /// Turns on or off the refcount log, which remembers every object Servo gives Gecko a
+    /// reference to until Gecko releases them all, with the stack of each AddRef and Release,
+    /// for Servo_DumpLiveObjects. It starts over empty either way. Setting
+    /// `STYLO_LOG_REFCOUNTS` in the environment turns it on from Servo_Initialize.
+    fn Servo_SetRefCountLogging(enabled: bool) -> () [on_panic: (), before_initialize] {
+        ownership::set_refcount_logging(enabled);
+    }
+}
+
+servo_function! {
+    /// Prints every object the refcount log says Gecko still holds references to, with their
+    /// stacks, to stderr. Gecko calls this at shutdown to find what leaked.
+    fn Servo_DumpLiveObjects() -> () [on_panic: (), before_initialize] {
+        let _ = write!(io::stderr(), "{}", ownership::describe_live_objects());
+    }
+}
+
+servo_function! {
+    /// Turns on or off counting what each traversal does, for Servo_GetTraversalStatistics.
+    /// Counting is off to begin with, and costs next to nothing while it's off.
+    fn Servo_SetTraversalStatisticsEnabled(enabled: bool) -> () [on_panic: ()] {
+        traversal_stats::set_enabled(enabled);
+    }
+}
+
+servo_function! {
+    /// Copies what the last traversal counted into |out|: all zeroes if no traversal ran
+    /// while counting was on. Traversals that had nothing to restyle don't count.
+    fn Servo_GetTraversalStatistics(out: *mut ServoTraversalStatistics) -> () [on_panic: ()] {
+        return_if_null!(out; ());
+        unsafe { *out = *GlobalStyleData::get().traversal_statistics.lock().unwrap() };
+    }
+}
+
+/// Restyles the whole of |doc|, which |raw_data| holds the styles of.
+fn restyle_document(doc: *mut RawGeckoDocument, raw_data: *mut RawServoStyleSet) {
+    // The snapshots are of the elements before the changes this restyle picks up, so none
+    // of them are any use afterwards, whether or not there was anything to restyle.
+    let snapshots = PerDocumentStyleData::borrow_mut_from_raw(raw_data).snapshots.take();
+    let token = unsafe { DomToken::new() };
+    let document = unsafe { GeckoDocument::from_raw(&token, doc) };
+    let node = match document.root_node() {
+        Some(x) => x,
+        None => {
+            // There's nothing to style, as during document.open() or once an XML document's
+            // root is removed, so whatever was marked dirty has to be forgotten.
+            let document_node = document.as_node();
+            unsafe {
+                document_node.set_dirty(false);
+                document_node.set_dirty_descendants(false);
+            }
+            return;
+        }
+    };
+    // Subtree restyles keep using the mode of the last full document restyle.
+    let compat_mode = unsafe { Gecko_GetDocumentCompatMode(doc) };
+    let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+    data.set_quirks_mode(quirks_mode_from_gecko(compat_mode));
+    snapshots.each(|element, snapshot| {
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        element.note_restyle_hint(compute_restyle_hint(element, snapshot, data));
+    });
+    restyle_subtree(node, raw_data);
+}
+
+servo_function! {
+    /// Kept until Gecko moves to Servo_PerDocumentData_RestyleDocument.
+    fn Servo_RestyleDocument(doc: *mut RawGeckoDocument, raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(doc, raw_data; ());
+        restyle_document(doc, raw_data);
+    }
+}
+
+servo_function! {
+    /// Restyles the whole of the document |data| was made for.
+    fn Servo_PerDocumentData_RestyleDocument(data: *mut RawServoPerDocumentData) -> () [on_panic: abort()] {
+        return_if_null!(data; ());
+        let doc = PerDocumentStyleData::borrow_mut_from_raw_document_data(data).document;
+        return_if_null!(doc; ());
+        restyle_document(doc, data as *mut RawServoStyleSet);
+    }
+}
+
+servo_function! {
+    fn Servo_RestyleSubtree(node: *mut RawGeckoNode, raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(node, raw_data; ());
+        let token = unsafe { DomToken::new() };
+        let mut node = unsafe { GeckoNode::from_raw(&token, node) };
+
+        // Subtrees that aren't in a document are never styled.
+        if !node.is_in_document() {
+            return;
+        }
+
+        // The root of the traversal inherits from its parent's existing computed values,
+        // so we can only start from a node whose parent has already been styled. If this
+        // node hasn't been styled yet, walk up to the nearest ancestor that has. Reaching
+        // the document element makes this equivalent to Servo_RestyleDocument.
+        while node.borrow_data().is_none() {
+            match node.style_parent() {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+
+        // Gecko asks for the whole subtree, whatever its restyle bits say.
+        node.dirty_self();
+        node.dirty_descendants();
+        restyle_subtree(node, raw_data);
+    }
+}
+
+servo_function! {
+    /// Re-cascades the elements of |doc| Gecko flagged with NODE_HAS_ANIMATIONS_FOR_SERVO,
+    /// with the values Gecko_GetSMILOverrideDeclarationBlock and
+    /// Gecko_GetAnimationEffectDeclarationBlock give for the current animation frame, and
+    /// without matching selectors. Servo_TakeChangeHint then only reports what the animated
+    /// properties changed. Gecko has to run Servo_RestyleDocument afterwards for the elements
+    /// this leaves dirty, which it does when an animation changes an inherited property or an
+    /// element's rules aren't known.
+    fn Servo_RestyleForAnimations(doc: *mut RawGeckoDocument,
+                                  raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(doc, raw_data; ());
+        let token = unsafe { DomToken::new() };
+        let document = unsafe { GeckoDocument::from_raw(&token, doc) };
+        let node = match document.root_node() {
+            Some(node) => node,
+            None => return,
+        };
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        if data.is_shut_down() || (!node.has_animations() && !node.has_animated_descendants()) {
+            return;
+        }
+        let _traversal = data.register_traversal();
+
+        let start = Instant::now();
+        let guard = GlobalStyleData::get().shared_lock.read();
+        restyle_for_animations(node, node.opaque(), data.rule_tree.as_ref().map(|rule_tree| &**rule_tree),
+                               data.viewport_size, &guard);
+        if traversal_stats::enabled() {
+            let global_data = GlobalStyleData::get();
+            let statistics = collect_traversal_statistics(global_data, start.elapsed());
+            *global_data.traversal_statistics.lock().unwrap() = statistics;
+        }
+    }
+}
+
+/// Computes which elements need to be restyled because of a change to |element|'s state or
+/// attributes, given a snapshot of them from before the change. The ancestors that need it
+/// are marked for restyling rather than hinted at.
+fn compute_restyle_hint(element: GeckoElement, snapshot: &GeckoElementSnapshot,
+                        data: &mut PerDocumentStyleData) -> RestyleHint {
+    element.as_node().fetch_document_state();
+    set_document_quirks_mode(data.quirks_mode);
+
+    // The dependency sets are only rebuilt along with the rest of the stylist, so flush
+    // any pending stylesheet changes first to pick up selectors from every sheet.
+    data.flush_stylesheets();
+
+    let state = element.get_state();
+    // Selectors like :focus-within make the ancestors depend on the element's state too. The
+    // hint is only for the element and what comes after it, so they're marked here.
+    for (ancestor, hint) in data.stylist.compute_ancestor_restyle_hints(&element, snapshot, state) {
+        ancestor.note_restyle_hint(hint);
+    }
+    data.stylist.compute_restyle_hint(&element, snapshot, state)
+}
+
+/// Like compute_restyle_hint, for a snapshot Gecko took and hands us itself.
+fn compute_restyle_hint_for_raw(element: *mut RawGeckoElement, snapshot: *mut ServoElementSnapshot,
+                                data: &mut PerDocumentStyleData) -> ServoRestyleHint {
+    let token = unsafe { DomToken::new() };
+    let element = unsafe { GeckoElement::from_raw(&token, element) };
+    let snapshot = unsafe { GeckoElementSnapshot::from_raw(snapshot) };
+    ServoRestyleHint::from_servo(compute_restyle_hint(element, &snapshot, data))
+}
+
+servo_function! {
+    /// Kept until Gecko moves to Servo_PerDocumentData_ComputeRestyleHint.
+    fn Servo_ComputeRestyleHint(element: *mut RawGeckoElement,
+                                snapshot: *mut ServoElementSnapshot,
+                                raw_data: *mut RawServoStyleSet) -> ServoRestyleHint [on_panic: abort()] {
+        return_if_null!(element, snapshot, raw_data; ServoRestyleHint::empty());
+        compute_restyle_hint_for_raw(element, snapshot, PerDocumentStyleData::borrow_mut_from_raw(raw_data))
+    }
+}
+
+servo_function! {
+    /// Whether any selector in the style set depends on the document state bits in
+    /// |changed_bits|, in which case Gecko has to restyle the whole document when they
+    /// change.
+    fn Servo_ComputeDocumentStateDependentRestyle(raw_data: *mut RawServoStyleSet,
+                                                  changed_bits: u64) -> bool [on_panic: true] {
+        return_if_null!(raw_data; true);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+        data.stylist.depends_on_document_state(changed_bits)
+    }
+}
+
+servo_function! {
+    /// Whether any selector in the style set depends on the attribute with local name
+    /// |name|. If none does, Gecko needn't snapshot the element before changing it.
+    fn Servo_StyleSet_MightHaveAttributeDependency(raw_data: *mut RawServoStyleSet,
+                                                   name: *mut nsIAtom) -> bool [on_panic: true] {
+        return_if_null!(raw_data, name; true);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+        data.stylist.might_have_attribute_dependency(&unsafe { atom_from_gecko(name) })
+    }
+}
+
+servo_function! {
+    /// Whether any selector in the style set depends on one of the Gecko event states in
+    /// |state_bits|. If none does, a change to them needs no restyle at all. A change to
+    /// the focus state counts as one to the focus-within state too, since focusing an
+    /// element changes that of its ancestors.
+    fn Servo_StyleSet_HasStateDependency(raw_data: *mut RawServoStyleSet, state_bits: u64) -> bool [on_panic: true] {
+        return_if_null!(raw_data; true);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+        let mut states = state_bits;
+        if states & NS_EVENT_STATE_FOCUS.bits() != 0 {
+            states |= NS_EVENT_STATE_FOCUS_WITHIN.bits();
+        }
+        data.stylist.depends_on_element_state(states)
+    }
+}
+
+servo_function! {
+    /// Like Servo_StyleSet_MightHaveAttributeDependency, but only checks a bloom filter of
+    /// the attribute atoms, so it can answer yes for an attribute nothing depends on.
+    fn Servo_StyleSet_HasAttributeDependency(raw_data: *mut RawServoStyleSet,
+                                             attr_atom: *mut nsIAtom) -> bool [on_panic: true] {
+        return_if_null!(raw_data, attr_atom; true);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+        data.might_depend_on_attribute(attr_atom)
+    }
+}
+
+servo_function! {
+    /// Whether any selector in the style set depends on the document state bits in |bits|.
+    fn Servo_StyleSet_HasDocumentStateDependency(raw_data: *mut RawServoStyleSet, bits: u64) -> bool [on_panic: true] {
+        return_if_null!(raw_data; true);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+        data.stylist.depends_on_document_state(bits)
+    }
+}
+
+servo_function! {
+    /// The restyle hint for |element| from what changed since |snapshot| was taken. The
+    /// ancestors that selectors like :focus-within make depend on the change are marked for
+    /// restyling here.
+    fn Servo_PerDocumentData_ComputeRestyleHint(element: *mut RawGeckoElement,
+                                                snapshot: *mut ServoElementSnapshot,
+                                                data: *mut RawServoPerDocumentData)
+                                                -> ServoRestyleHint [on_panic: abort()] {
+        return_if_null!(element, snapshot, data; ServoRestyleHint::empty());
+        compute_restyle_hint_for_raw(element, snapshot, PerDocumentStyleData::borrow_mut_from_raw_document_data(data))
+    }
+}
+
+servo_function! {
+    /// Snapshots |element| before Gecko changes the things |snapshot_flags| says, whose bits
+    /// are those of wrapper::SnapshotFlags, for the next restyle of the document to compute
+    /// its restyle hint from. Noting an element more than once before then keeps the values
+    /// from before the first change.
+    fn Servo_NoteElementSnapshot(data: *mut RawServoPerDocumentData, element: *mut RawGeckoElement,
+                                 snapshot_flags: u8) -> () [on_panic: ()] {
+        return_if_null!(data, element; ());
+        let flags = SnapshotFlags::from_bits_truncate(snapshot_flags);
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        // An element that hasn't been styled yet will be whatever changes.
+        if flags.is_empty() || element.as_node().borrow_data().is_none() {
+            return;
+        }
+        PerDocumentStyleData::borrow_mut_from_raw_document_data(data).snapshots.note(element, flags);
+    }
+}
+
+servo_function! {
+    /// Drops the snapshot Servo_NoteElementSnapshot took of |element|, if there's one. Gecko
+    /// calls this when the element is unbound from the document, since the next restyle
+    /// mustn't look at it.
+    fn Servo_ForgetElementSnapshot(data: *mut RawServoPerDocumentData,
+                                   element: *mut RawGeckoElement) -> () [on_panic: ()] {
+        return_if_null!(data, element; ());
+        PerDocumentStyleData::borrow_mut_from_raw_document_data(data).snapshots.forget(element);
+    }
+}
+
+servo_function! {
+    /// Makes room for |length| attributes in |snapshot|, which wrapper::AttributeSnapshot
+    /// made for Gecko_SnapshotAttributes to fill in, and returns the first of them.
+    fn Servo_AttributeSnapshot_SetLength(snapshot: *mut RawServoAttributeSnapshot,
+                                         length: u32) -> *mut ServoAttrSnapshot [on_panic: abort()] {
+        return_if_null!(snapshot; ptr::null_mut());
+        let attributes = unsafe { &mut *(snapshot as *mut Vec<ServoAttrSnapshot>) };
+        attributes.clear();
+        attributes.resize(length as usize, unsafe { mem::zeroed() });
+        attributes.as_mut_ptr()
+    }
+}
+
+servo_function! {
+    /// Computes the nsChangeHint bits for going from the |old| style to the |new| one.
+    fn Servo_ComputedValues_CalcDifference(old: *mut ServoComputedValues,
+                                           new: *mut ServoComputedValues) -> nsChangeHint [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(old, new; 0);
+        Helpers::with(old, |old| {
+            Helpers::with(new, |new| GeckoRestyleDamage::compute(Some(old), new).bits())
+        })
+    }
+}
+
+servo_function! {
+    /// Whether |a| and |b| share their |struct_id| struct, a ServoStyleStructId. It's a
+    /// cheap check to make before comparing the structs' values.
+    fn Servo_ComputedValues_EqualStructs(a: *mut ServoComputedValues,
+                                         b: *mut ServoComputedValues,
+                                         struct_id: u8) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(a, b; false);
+        let id = match style_struct_id_from_gecko(struct_id) {
+            Some(id) => id,
+            None => {
+                debug_assert!(false, "Unknown style struct {}", struct_id);
+                return false;
+            }
+        };
+        Helpers::with(a, |a| Helpers::with(b, |b| a.same_struct(b, id)))
+    }
+}
+
+servo_function! {
+    /// Whether |a| and |b| compute to the same values.
+    fn Servo_ComputedValues_Equals(a: *mut ServoComputedValues, b: *mut ServoComputedValues) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(a, b; false);
+        Helpers::with(a, |a| Helpers::with(b, |b| arc_ptr_eq(a, b) || a.deep_equals(b)))
+    }
+}
+
+servo_function! {
+    /// Returns the nsChangeHint bits the last restyle computed for |element|, and clears them.
+    fn Servo_TakeChangeHint(element: *mut RawGeckoElement) -> nsChangeHint [on_panic: 0] {
+        return_if_null!(element; 0);
+        let token = unsafe { DomToken::new() };
+        let node = unsafe { GeckoElement::from_raw(&token, element).as_node() };
+        let mut damage = node.restyle_damage();
+        node.set_restyle_damage(GeckoRestyleDamage::empty());
+        if let Some(data) = unsafe { node.get_node_data().as_ref() } {
+            damage = damage | data.explicit_damage.get();
+            data.explicit_damage.set(GeckoRestyleDamage::empty());
+        }
+        damage.bits()
+    }
+}
+
+servo_function! {
+    /// Returns the nsChangeHint bits the last restyle that went under |element| computed for
+    /// its descendants, OR'd together, so that Gecko can skip the subtrees with nothing to
+    /// do. Unlike Servo_TakeChangeHint, this doesn't clear them.
+    fn Servo_GetDescendantChangeHints(element: *mut RawGeckoElement) -> nsChangeHint [on_panic: 0] {
+        return_if_null!(element; 0);
+        let token = unsafe { DomToken::new() };
+        let node = unsafe { GeckoElement::from_raw(&token, element).as_node() };
+        unsafe { node.get_node_data().as_ref() }.map_or(0, |data| {
+            data.descendant_damage.load(Ordering::Relaxed) as nsChangeHint
+        })
+    }
+}
+
+servo_function! {
+    /// Asks for |element| to be restyled as |restyle_hint| says, and for Gecko to be handed
+    /// |change_hint| by Servo_TakeChangeHint after the restyle. Marks the elements to restyle
+    /// and their ancestors, so that the next Servo_RestyleDocument finds them.
+    fn Servo_NoteExplicitHints(element: *mut RawGeckoElement,
+                               restyle_hint: ServoRestyleHint,
+                               change_hint: nsChangeHint) -> () [on_panic: ()] {
+        return_if_null!(element; ());
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        element.note_restyle_hint(restyle_hint.to_servo());
+        let node = element.as_node();
+        if let Some(data) = unsafe { node.get_node_data().as_ref() } {
+            data.explicit_damage.set(data.explicit_damage.get() | GeckoRestyleDamage::from_bits_truncate(change_hint));
+        }
+    }
+}
+
+servo_function! {
+    fn Servo_DropNodeData(data: *mut ServoNodeData) -> () [on_panic: abort()] {
+        if data.is_null() {
+            return;
+        }
+        unsafe {
+            let _ = Box::<NodeData>::from_raw(data as NonOpaqueStyleData);
+        }
+    }
+}
+
+/// Detaches the data of every node in the subtree |root| is the root of from its node.
+fn take_subtree_data(root: GeckoNode) -> Vec<NonOpaqueStyleData> {
+    let mut data = vec![];
+    let mut subtrees = vec![root];
+    while let Some(node) = subtrees.pop() {
+        let node_data = unsafe { node.take_node_data() };
+        if !node_data.is_null() {
+            data.push(node_data);
+        }
+        let mut child = node.first_child();
+        while let Some(node) = child {
+            subtrees.push(node);
+            child = node.next_sibling();
+        }
+    }
+    data
+}
+
+servo_function! {
+    /// Drops the data of |root| and of every node under it, for when Gecko takes the subtree
+    /// out of the DOM, and leaves their data pointers null.
+    fn Servo_DropNodeDataRecursive(root: *mut RawGeckoNode) -> () [on_panic: abort()] {
+        return_if_null!(root; ());
+        let token = unsafe { DomToken::new() };
+        let root = unsafe { GeckoNode::from_raw(&token, root) };
+        node_data_dropper::drop_now(take_subtree_data(root));
+    }
+}
+
+servo_function! {
+    /// Like Servo_DropNodeDataRecursive, but only detaches the data from the nodes, and has
+    /// it freed on another thread. Servo_FlushDroppedNodeData waits for that to finish.
+    fn Servo_DropNodeDataRecursiveLater(root: *mut RawGeckoNode) -> () [on_panic: abort()] {
+        return_if_null!(root; ());
+        let token = unsafe { DomToken::new() };
+        let root = unsafe { GeckoNode::from_raw(&token, root) };
+        GlobalStyleData::get().node_data_dropper.drop_later(take_subtree_data(root));
     }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_DropNodeData(data: *mut ServoNodeData) -> () {
-    unsafe {
-        let _ = Box::<NonOpaqueStyleData>::from_raw(data as *mut NonOpaqueStyleData);
+servo_function! {
+    /// Throws away the styles of |node| and of every node under it, when Gecko rebuilds their
+    /// frames or adopts the node into another document, whose rule tree the old rule nodes
+    /// aren't part of. The nodes keep their data, but are left to be matched from scratch.
+    /// With |keep_for_diffing|, their old styles wait in the previous style slot for the
+    /// next traversal to compute the change hint against; otherwise they're dropped now.
+    fn Servo_ClearNodeStyleData(node: *mut RawGeckoNode, keep_for_diffing: bool) -> () [on_panic: abort()] {
+        return_if_null!(node; ());
+        let token = unsafe { DomToken::new() };
+        let root = unsafe { GeckoNode::from_raw(&token, node) };
+        let mut subtrees = vec![root];
+        while let Some(node) = subtrees.pop() {
+            if let Some(data) = unsafe { node.get_node_data().as_ref() } {
+                let style_data = mem::replace(&mut *data.style_data.borrow_mut(), PrivateStyleData::new());
+                *data.previous_style.borrow_mut() = if keep_for_diffing { style_data.style } else { None };
+                *data.style_before_restyle.borrow_mut() = None;
+                *data.nac_parent_style.borrow_mut() = None;
+                data.style_generation.set(0);
+            }
+            let mut child = node.first_child();
+            while let Some(node) = child {
+                subtrees.push(node);
+                child = node.next_sibling();
+            }
+        }
+        root.dirty_self();
+        root.dirty_descendants();
+        let mut ancestor = root.parent_node();
+        while let Some(node) = ancestor {
+            unsafe { node.set_dirty_descendants(true) };
+            ancestor = node.parent_node();
+        }
     }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_StylesheetFromUTF8Bytes(bytes: *const u8,
-                                                length: u32) -> *mut RawServoStyleSheet {
+servo_function! {
+    /// Waits until the data Servo_DropNodeDataRecursiveLater queued has been freed, along
+    /// with the image requests the styles in it held.
+    fn Servo_FlushDroppedNodeData() -> () [on_panic: abort()] {
+        GlobalStyleData::get().node_data_dropper.flush();
+        image_request::release_requests_dropped_elsewhere();
+    }
+}
+
+servo_function! {
+    /// Panics, and so crashes, if anything only the main thread may free was dropped on another
+    /// thread that wasn't meant to free styles since the last call, naming the threads it was
+    /// dropped on. Only debug builds keep track, so it never fails in the others.
+    fn Servo_AssertNoOffThreadDrops() -> () [on_panic: abort()] {
+        let threads = main_thread::take_off_thread_drops();
+        assert!(threads.is_empty(), "Dropped off the main thread, on {}", threads.join(", "));
+    }
+}
+
+fn origin_from_gecko(origin: StyleSheetOrigin) -> Origin {
+    match origin {
+        StyleSheetOrigin::UserAgent => Origin::UserAgent,
+        StyleSheetOrigin::User => Origin::User,
+        StyleSheetOrigin::Author => Origin::Author,
+    }
+}
+
+fn origin_to_gecko(origin: Origin) -> StyleSheetOrigin {
+    match origin {
+        Origin::UserAgent => StyleSheetOrigin::UserAgent,
+        Origin::User => StyleSheetOrigin::User,
+        Origin::Author => StyleSheetOrigin::Author,
+    }
+}
+
+// Keep these in sync with nsCompatibility in dom/base/nsCompatibility.h.
+pub const COMPATIBILITY_FULL_STANDARDS: u8 = 1;
+pub const COMPATIBILITY_ALMOST_STANDARDS: u8 = 2;
+pub const COMPATIBILITY_NAV_QUIRKS: u8 = 3;
+
+fn quirks_mode_from_gecko(compat_mode: u8) -> QuirksMode {
+    match compat_mode {
+        COMPATIBILITY_NAV_QUIRKS => QuirksMode::Quirks,
+        COMPATIBILITY_ALMOST_STANDARDS => QuirksMode::LimitedQuirks,
+        COMPATIBILITY_FULL_STANDARDS => QuirksMode::NoQuirks,
+        _ => {
+            warn!("Unknown document compatibility mode {}", compat_mode);
+            QuirksMode::NoQuirks
+        }
+    }
+}
+
+/// The UTF-8 string Gecko passed as |bytes| and |length|. Empty Gecko strings may
+/// hand over a null pointer, so null is taken as the empty string.
+unsafe fn str_from_raw<'a>(bytes: *const u8, length: u32) -> &'a str {
+    if bytes.is_null() {
+        ""
+    } else {
+        from_utf8_unchecked(slice::from_raw_parts(bytes, length as usize))
+    }
+}
+
+unsafe fn base_url_from_raw(base: *const u8, base_length: u32) -> Url {
+    // A null or unparseable base URL leaves relative URLs unresolvable, which is
+    // what we did before Gecko started passing one in.
+    Url::parse(str_from_raw(base, base_length)).unwrap_or_else(|_| GlobalStyleData::get().dummy_url.clone())
+}
+
+/// Forwards parse errors to Gecko, which associates them with the document that
+/// owns `context`. A null context drops them.
+struct GeckoErrorReporter {
+    context: *mut c_void,
+}
+
+// The context is only ever handed back to Gecko.
+unsafe impl Send for GeckoErrorReporter {}
+unsafe impl Sync for GeckoErrorReporter {}
+
+impl ParseErrorReporter for GeckoErrorReporter {
+    fn report_error(&self, input: &mut Parser, position: SourcePosition, message: &str) {
+        if self.context.is_null() {
+            return;
+        }
+        let location = input.source_location(position);
+        let message = CString::new(message.replace("\0", "\u{FFFD}")).unwrap();
+        unsafe {
+            Gecko_ReportCSSError(self.context, message.as_ptr(), location.line as u32, location.column as u32);
+        }
+    }
+
+    fn report_error_with_details(&self, input: &mut Parser, position: SourcePosition, _message: &str,
+                                 details: &ParseErrorDetails) {
+        if self.context.is_null() {
+            return;
+        }
+        let location = input.source_location(position);
+        match *details {
+            ParseErrorDetails::InvalidSelector(invalid) => unsafe {
+                Gecko_ReportCSSErrorWithDetails(self.context, ServoCSSErrorKind::InvalidSelector as u8,
+                                                invalid.selector.as_ptr(), invalid.selector.len() as u32,
+                                                invalid.offset as u32, location.line as u32,
+                                                location.column as u32);
+            },
+        }
+    }
+
+    fn clone(&self) -> Box<ParseErrorReporter + Send + Sync> {
+        Box::new(GeckoErrorReporter { context: self.context })
+    }
+}
+
+/// Asks Gecko to load the sheets `@import` rules refer to, on behalf of the sheet that owns
+/// `context` and with its `extra_data`. Gecko hands each loaded sheet back through
+/// Servo_StyleSheet_SetImportedSheet.
+struct GeckoStylesheetLoader {
+    context: *mut c_void,
+    extra_data: UrlExtraData,
+}
 
-    let input = unsafe { from_utf8_unchecked(slice::from_raw_parts(bytes, length as usize)) };
+impl StylesheetLoader<GeckoSelectorImpl> for GeckoStylesheetLoader {
+    fn request_stylesheet(&self, import: &Arc<ImportRule>, media: &str) {
+        let url = import.url.serialize();
+        // Gecko takes its own reference to the rule if it starts a load.
+        let raw_import: *mut RawServoImportRule = Borrowed::from_arc(import).as_ptr();
+        let load = unsafe {
+            Gecko_LoadStyleSheet(self.context, url_extra_data_to_gecko(&self.extra_data), raw_import,
+                                 url.as_ptr(), url.len() as u32, media.as_ptr(), media.len() as u32)
+        };
+        if load.is_null() {
+            debug!("Gecko didn't start a load for @import {}", url);
+        }
+    }
+
+    fn url_extra_data(&self) -> UrlExtraData {
+        self.extra_data.clone()
+    }
+}
+
+fn stylesheet_from_str(input: &str, url: Url, origin: StyleSheetOrigin, compat_mode: u8,
+                       error_context: *mut c_void, extra_data: *mut RawGeckoURLExtraData)
+                       -> RawServoStyleSheetStrong {
+    let error_reporter = Box::new(GeckoErrorReporter { context: error_context });
+    let loader = GeckoStylesheetLoader {
+        context: error_context,
+        extra_data: unsafe { url_extra_data_from_gecko(extra_data) },
+    };
+    let sheet = Arc::new(Stylesheet::from_str_with_loader(input, url, origin_from_gecko(origin),
+                                                          error_reporter, Some(&loader),
+                                                          quirks_mode_from_gecko(compat_mode)));
+    Strong::from_arc(sheet)
+}
+
+servo_function! {
+    fn Servo_StylesheetFromUTF8Bytes(bytes: *const u8,
+                                     length: u32,
+                                     base: *const u8,
+                                     base_length: u32,
+                                     origin: StyleSheetOrigin,
+                                     compat_mode: u8,
+                                     error_context: *mut c_void,
+                                     extra_data: *mut RawGeckoURLExtraData,
+                                     out_was_lossy: *mut bool) -> RawServoStyleSheetStrong [on_panic: abort()] {
+        // Gecko's converter sometimes hands over legacy-encoded sheets it mislabeled, so the
+        // bytes may not be valid UTF-8. Invalid sequences become U+FFFD, and |out_was_lossy|,
+        // if not null, tells Gecko whether there were any so it can warn about them. The url
+        // values of the sheet hold on to |extra_data|, which may be null, and hand it back to
+        // Gecko_LoadImage and Gecko_LoadStyleSheet.
+        let bytes: &[u8] = if bytes.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(bytes, length as usize) }
+        };
+        let (input, was_lossy) = decode_utf8_lossy(bytes);
+        if !out_was_lossy.is_null() {
+            unsafe { *out_was_lossy = was_lossy };
+        }
+        let url = unsafe { base_url_from_raw(base, base_length) };
+        stylesheet_from_str(&input, url, origin, compat_mode, error_context, extra_data)
+    }
+}
+
+servo_function! {
+    /// Replaces the rules of |raw_sheet| with the ones parsed from |bytes|, for a <style> element
+    /// whose text changed. The sheet keeps its identity and media list, and style sets holding it
+    /// rebuild their rules on their next flush. Rule lists the CSSOM made for the old rules are
+    /// detached from the sheet. Shared sheets are left alone, since other documents use them too.
+    /// The new rules are parsed against the sheet's URL and in its compatibility mode, and their
+    /// url values carry |extra_data|, which the sheet keeps for rules parsed later, or the sheet's
+    /// own extra data if it's null. As with Servo_StylesheetFromUTF8Bytes, parse errors are
+    /// reported to |error_context| and @import rules loaded with it.
+    fn Servo_StyleSheet_UpdateFromUTF8Bytes(raw_sheet: *mut RawServoStyleSheet,
+                                            bytes: *const u8,
+                                            length: u32,
+                                            error_context: *mut c_void,
+                                            extra_data: *mut RawGeckoURLExtraData) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; ());
+        let input = unsafe { str_from_raw(bytes, length) };
+        Helpers::with(raw_sheet, |sheet| {
+            if sheet.is_frozen() {
+                warn!("Not replacing the rules of a shared sheet");
+                return;
+            }
+            let loader = GeckoStylesheetLoader {
+                context: error_context,
+                extra_data: if extra_data.is_null() {
+                    sheet.url_extra_data()
+                } else {
+                    unsafe { url_extra_data_from_gecko(extra_data) }
+                },
+            };
+            // Gecko only changes rules on the main thread, and restyles only read them with
+            // the locks held, so they see either all of the old rules or all of the new ones.
+            let _guard = GlobalStyleData::get().shared_lock.write();
+            let old = sheet.reparse(input, Box::new(GeckoErrorReporter { context: error_context }), Some(&loader));
+            GeckoSheetCssom::detach(sheet, old);
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_StylesheetFromUTF16Bytes(bytes: *const u16,
+                                      length: u32,
+                                      base: *const u8,
+                                      base_length: u32,
+                                      origin: StyleSheetOrigin,
+                                      compat_mode: u8,
+                                      error_context: *mut c_void,
+                                      extra_data: *mut RawGeckoURLExtraData)
+                                      -> RawServoStyleSheetStrong [on_panic: abort()] {
+
+        // Lone surrogates are replaced with U+FFFD, matching Gecko's own UTF-16 to UTF-8
+        // conversion.
+        let input = if bytes.is_null() {
+            String::new()
+        } else {
+            unsafe { String::from_utf16_lossy(slice::from_raw_parts(bytes, length as usize)) }
+        };
+        let url = unsafe { base_url_from_raw(base, base_length) };
+        stylesheet_from_str(&input, url, origin, compat_mode, error_context, extra_data)
+    }
+}
+
+servo_function! {
+    /// Like Servo_StylesheetFromUTF8Bytes, but parses a copy of |bytes| on Servo's sheet parser
+    /// thread and returns straight away. Once the sheet is parsed, Gecko_StyleSheetLoadComplete
+    /// is called with it and |callback_context| on that thread, unless the parse was cancelled
+    /// with Servo_StyleSheet_CancelAsyncParse first, or by shutting down or dropping |set|, the
+    /// style set the sheet is for, if it isn't null. Nothing is parsed for a set that was shut
+    /// down already.
+    fn Servo_StyleSheet_FromUTF8BytesAsync(bytes: *const u8,
+                                           length: u32,
+                                           base: *const u8,
+                                           base_length: u32,
+                                           origin: StyleSheetOrigin,
+                                           compat_mode: u8,
+                                           extra_data: *mut RawGeckoURLExtraData,
+                                           callback_context: *mut c_void,
+                                           set: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(callback_context; ());
+        if !set.is_null() && PerDocumentStyleData::borrow_mut_from_raw(set).is_shut_down() {
+            return;
+        }
+        let input = unsafe { str_from_raw(bytes, length) }.to_owned();
+        let url = unsafe { base_url_from_raw(base, base_length) };
+        let extra_data = unsafe { url_extra_data_from_gecko(extra_data) };
+        GlobalStyleData::get().sheet_parser.parse(input, url, origin_from_gecko(origin),
+                                                  quirks_mode_from_gecko(compat_mode), extra_data,
+                                                  callback_context, set as usize);
+    }
+}
 
-    // FIXME(heycam): Pass in the real base URL and sheet origin to use.
-    let url = Url::parse("about:none").unwrap();
-    let sheet = Arc::new(Stylesheet::from_str(input, url, Origin::Author, Box::new(StdoutErrorReporter)));
-    unsafe {
-        transmute(sheet)
+servo_function! {
+    /// Cancels the parse Servo_StyleSheet_FromUTF8BytesAsync started with |callback_context|.
+    /// Returns true if Gecko_StyleSheetLoadComplete won't be called for it, and false if it
+    /// already has been. It waits for a callback that's running to return, so it mustn't be
+    /// called from one.
+    fn Servo_StyleSheet_CancelAsyncParse(callback_context: *mut c_void) -> bool [on_panic: false] {
+        return_if_null!(callback_context; false);
+        GlobalStyleData::get().sheet_parser.cancel(callback_context)
     }
 }
 
-struct ArcHelpers<GeckoType, ServoType> {
+pub struct ArcHelpers<GeckoType, ServoType> {
     phantom1: PhantomData<GeckoType>,
     phantom2: PhantomData<ServoType>,
 }
 
-impl<GeckoType, ServoType> ArcHelpers<GeckoType, ServoType> {
-    fn with<F, Output>(raw: *mut GeckoType, cb: F) -> Output
+impl<GeckoType, ServoType> ArcHelpers<GeckoType, ServoType> where ServoType: HasArcFFI<FFIType=GeckoType> {
+    /// Calls |cb| with the Arc |raw| stands for, which mustn't be null.
+    pub fn with<F, Output>(raw: *mut GeckoType, cb: F) -> Output
                        where F: FnOnce(&Arc<ServoType>) -> Output {
-        let owned = unsafe { Self::into(raw) };
-        let result = cb(&owned);
-        forget(owned);
-        result
+        let borrowed = unsafe { Borrowed::from_raw(raw) };
+        cb(borrowed.as_arc())
     }
 
-    unsafe fn into(ptr: *mut GeckoType) -> Arc<ServoType> {
-        transmute(ptr)
+    /// Like |with|, for arguments where null is allowed and means there's no value.
+    pub fn maybe_with<F, Output>(raw: *mut GeckoType, cb: F) -> Option<Output>
+                                 where F: FnOnce(&Arc<ServoType>) -> Output {
+        if raw.is_null() {
+            None
+        } else {
+            Some(Self::with(raw, cb))
+        }
     }
+}
 
-    unsafe fn addref(ptr: *mut GeckoType) {
-        Self::with(ptr, |arc| forget(arc.clone()));
+unsafe impl HasArcFFI for Stylesheet {
+    type FFIType = RawServoStyleSheet;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoStyleSheet"
     }
+}
 
-    unsafe fn release(ptr: *mut GeckoType) {
-        let _ = Self::into(ptr);
+unsafe impl HasArcFFI for ImportRule {
+    type FFIType = RawServoImportRule;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoImportRule"
     }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_AppendStyleSheet(raw_sheet: *mut RawServoStyleSheet,
-                                         raw_data: *mut RawServoStyleSet) {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
-    Helpers::with(raw_sheet, |sheet| {
-        data.stylesheets.retain(|x| !arc_ptr_eq(x, sheet));
-        data.stylesheets.push(sheet.clone());
-        data.stylesheets_changed = true;
-    });
+unsafe impl HasArcFFI for SharedStylesheet {
+    type FFIType = RawServoSharedStyleSheet;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoSharedStyleSheet"
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_PrependStyleSheet(raw_sheet: *mut RawServoStyleSheet,
-                                          raw_data: *mut RawServoStyleSet) {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
-    Helpers::with(raw_sheet, |sheet| {
-        data.stylesheets.retain(|x| !arc_ptr_eq(x, sheet));
-        data.stylesheets.insert(0, sheet.clone());
-        data.stylesheets_changed = true;
-    })
+unsafe impl HasArcFFI for CounterStyleRule {
+    type FFIType = RawServoCounterStyleRule;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoCounterStyleRule"
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_RemoveStyleSheet(raw_sheet: *mut RawServoStyleSheet,
-                                         raw_data: *mut RawServoStyleSet) {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
-    Helpers::with(raw_sheet, |sheet| {
-        data.stylesheets.retain(|x| !arc_ptr_eq(x, sheet));
-        data.stylesheets_changed = true;
-    });
+unsafe impl HasArcFFI for MediaQueryList {
+    type FFIType = RawServoMediaList;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoMediaList"
+    }
+}
+
+unsafe impl HasArcFFI for GeckoCssRules {
+    type FFIType = RawServoCssRules;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoCssRules"
+    }
+}
+
+unsafe impl HasArcFFI for GeckoStyleRule {
+    type FFIType = RawServoStyleRule;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoStyleRule"
+    }
+}
+
+unsafe impl HasArcFFI for GeckoDeclarationBlock {
+    type FFIType = RawServoDeclarationBlock;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoDeclarationBlock"
+    }
+}
+
+unsafe impl HasArcFFI for AnimationValue {
+    type FFIType = RawServoAnimationValue;
+
+    fn ffi_type_name() -> &'static str {
+        "RawServoAnimationValue"
+    }
+}
+
+unsafe impl HasArcFFI for GeckoComputedValues {
+    type FFIType = ServoComputedValues;
+
+    fn ffi_type_name() -> &'static str {
+        "ServoComputedValues"
+    }
+}
+
+servo_function! {
+    fn Servo_AppendStyleSheet(raw_sheet: *mut RawServoStyleSheet,
+                              raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            data.append_stylesheet(sheet);
+        });
+    }
+}
+
+servo_function! {
+    /// Adds |raw_sheet| after the other sheets of |origin|, as the stylesheet service does for
+    /// the user sheets of extensions and accessibility tools. Only that origin's rules are
+    /// rebuilt on the next restyle. The sheet must have been parsed for |origin|.
+    fn Servo_StyleSet_AppendStyleSheetToOrigin(raw_data: *mut RawServoStyleSet,
+                                               raw_sheet: *mut RawServoStyleSheet,
+                                               origin: StyleSheetOrigin) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_data, raw_sheet; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            data.append_stylesheet_to_origin(sheet, origin_from_gecko(origin));
+        });
+    }
+}
+
+servo_function! {
+    /// How many sheets of |origin| the style set has.
+    fn Servo_StyleSet_SheetCount(raw_data: *mut RawServoStyleSet,
+                                 origin: StyleSheetOrigin) -> u32 [on_panic: 0] {
+        return_if_null!(raw_data; 0);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.origin_stylesheets(origin_from_gecko(origin)).len() as u32
+    }
+}
+
+servo_function! {
+    /// The |index|th sheet of |origin| in cascade order, or null if there are no more.
+    fn Servo_StyleSet_SheetAt(raw_data: *mut RawServoStyleSet, origin: StyleSheetOrigin,
+                              index: u32) -> RawServoStyleSheetStrong [on_panic: Strong::null()] {
+        return_if_null!(raw_data; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        match data.origin_stylesheets(origin_from_gecko(origin)).get(index as usize) {
+            Some(sheet) => Strong::from_arc(sheet.clone()),
+            None => Strong::null(),
+        }
+    }
+}
+
+servo_function! {
+    /// Like Servo_AppendStyleSheet, for a sheet Servo_StyleSheet_IntoShared froze. The style set
+    /// uses the rule maps the shared sheet comes with, if any, rather than building its own.
+    fn Servo_StyleSet_AppendSharedStyleSheet(raw_data: *mut RawServoStyleSet,
+                                             shared: *mut RawServoSharedStyleSheet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoSharedStyleSheet, SharedStylesheet>;
+        return_if_null!(raw_data, shared; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(shared, |shared| {
+            data.append_shared_stylesheet(shared);
+        });
+    }
+}
+
+servo_function! {
+    fn Servo_PrependStyleSheet(raw_sheet: *mut RawServoStyleSheet,
+                               raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            data.prepend_stylesheet(sheet);
+        })
+    }
+}
+
+servo_function! {
+    /// Like Servo_AppendStyleSheet, for an author sheet that only applies to the descendants
+    /// of |scope_root|, like the sheets of an XBL binding. Gecko has to call
+    /// Servo_StyleSet_RemoveScopedStyleSheets before |scope_root| leaves the document.
+    fn Servo_StyleSet_AppendScopedStyleSheet(raw_data: *mut RawServoStyleSet,
+                                             raw_sheet: *mut RawServoStyleSheet,
+                                             scope_root: *mut RawGeckoElement) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_data, raw_sheet, scope_root; ());
+        let token = unsafe { DomToken::new() };
+        let scope = unsafe { GeckoElement::from_raw(&token, scope_root) }.as_node().opaque();
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            data.append_scoped_stylesheet(sheet, scope);
+        });
+    }
+}
+
+servo_function! {
+    /// Removes the sheets Servo_StyleSet_AppendScopedStyleSheet scoped to |scope_root|.
+    fn Servo_StyleSet_RemoveScopedStyleSheets(raw_data: *mut RawServoStyleSet,
+                                              scope_root: *mut RawGeckoElement) -> () [on_panic: abort()] {
+        return_if_null!(raw_data, scope_root; ());
+        let token = unsafe { DomToken::new() };
+        let scope = unsafe { GeckoElement::from_raw(&token, scope_root) }.as_node().opaque();
+        PerDocumentStyleData::borrow_mut_from_raw(raw_data).remove_scoped_stylesheets(scope);
+    }
+}
+
+servo_function! {
+    fn Servo_RemoveStyleSheet(raw_sheet: *mut RawServoStyleSheet,
+                              raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            data.remove_stylesheet(sheet);
+        });
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSet_InsertStyleSheetBefore(raw_data: *mut RawServoStyleSet,
+                                             raw_sheet: *mut RawServoStyleSheet,
+                                             raw_reference: *mut RawServoStyleSheet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_data, raw_sheet, raw_reference; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Helpers::with(raw_sheet, |sheet| {
+            Helpers::with(raw_reference, |reference| {
+                data.insert_stylesheet_before(sheet, reference);
+            })
+        });
+    }
+}
+
+servo_function! {
+    /// Replaces all the sheets in the style set with the `count` sheets in `raw_sheets`, which
+    /// must be in document order. The set ends up holding its own reference to each of them.
+    fn Servo_StyleSet_ReplaceStyleSheets(raw_data: *mut RawServoStyleSet,
+                                         raw_sheets: *const *mut RawServoStyleSheet,
+                                         count: u32) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let raw_sheets: &[*mut RawServoStyleSheet] = if count == 0 {
+            &[]
+        } else {
+            return_if_null!(raw_sheets; ());
+            unsafe { slice::from_raw_parts(raw_sheets, count as usize) }
+        };
+        let mut sheets: Vec<Arc<Stylesheet>> = Vec::with_capacity(raw_sheets.len());
+        for &raw_sheet in raw_sheets {
+            return_if_null!(raw_sheet; ());
+            let sheet = Helpers::with(raw_sheet, |sheet| sheet.clone());
+            // A sheet listed twice only counts at its last position, like Servo_AppendStyleSheet.
+            sheets.retain(|x| !arc_ptr_eq(x, &sheet));
+            sheets.push(sheet);
+        }
+        data.replace_stylesheets(sheets);
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_StyleSheetHasRules(raw_sheet: *mut RawServoStyleSheet) -> bool {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    Helpers::with(raw_sheet, |sheet| !sheet.rules.is_empty())
+servo_function! {
+    /// Tells the style set about a new viewport size. Returns whether that changed which sheets
+    /// or @media rules apply, in which case the next restyle rebuilds the rules and Gecko should
+    /// schedule one.
+    fn Servo_StyleSet_MediaFeatureValuesChanged(raw_data: *mut RawServoStyleSet,
+                                                viewport_width: f32,
+                                                viewport_height: f32,
+                                                _device_pixel_ratio: f32) -> bool [on_panic: abort()] {
+        return_if_null!(raw_data; false);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        // FIXME: Pass the device pixel ratio along once Servo supports resolution media queries.
+        data.set_viewport_size(Size2D::typed(viewport_width, viewport_height))
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_AddRefStyleSheet(sheet: *mut RawServoStyleSheet) -> () {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    unsafe { Helpers::addref(sheet) };
+servo_function! {
+    /// Tells the style set about the device it styles for: the viewport size in CSS pixels, and
+    /// the font size rem is relative to on the root element. The next restyle recomputes whatever
+    /// depends on them.
+    fn Servo_StyleSet_SetDevice(raw_data: *mut RawServoStyleSet,
+                                viewport_width_px: f32,
+                                viewport_height_px: f32,
+                                _device_pixel_ratio: f32,
+                                root_font_size_px: f32) -> () [on_panic: abort()] {
+        return_if_null!(raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        // FIXME: Pass the device pixel ratio along once Servo supports resolution media queries.
+        data.set_viewport_size(Size2D::typed(viewport_width_px, viewport_height_px));
+        data.set_root_font_size(Au::from_f32_px(root_font_size_px));
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_ReleaseStyleSheet(sheet: *mut RawServoStyleSheet) -> () {
-    type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
-    unsafe { Helpers::release(sheet) };
+servo_function! {
+    /// Tells the style set that the stylesheets of the given origin changed without going
+    /// through Servo_AppendStyleSheet and friends, so their rules get rebuilt on the next restyle.
+    fn Servo_StyleSet_NoteStyleSheetsChanged(raw_data: *mut RawServoStyleSet,
+                                             origin: StyleSheetOrigin) -> () [on_panic: abort()] {
+        return_if_null!(raw_data; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.note_stylesheets_changed(origin_from_gecko(origin));
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_GetComputedValues(element: *mut RawGeckoElement)
-     -> *mut ServoComputedValues {
-    let node = unsafe { GeckoElement::from_raw(element).as_node() };
-    let arc_cv = node.borrow_data().map(|data| data.style.clone());
-    arc_cv.map_or(ptr::null_mut(), |arc| unsafe { transmute(arc) })
+/// Which elements a sheet that was just added can change the style of.
+enum SheetInvalidation {
+    /// Those a selector of one of its style rules matches.
+    Selectors(Vec<Selector<GeckoSelectorImpl>>),
+    /// Any of them.
+    Document,
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_GetComputedValuesForAnonymousBox(_parentStyleOrNull: *mut ServoComputedValues,
-                                                         _pseudoTag: *mut nsIAtom)
-     -> *mut ServoComputedValues {
-    unimplemented!();
+/// Whether the elements |selector| can match are easy to pick out from the others: those
+/// whose id, class or tag its rightmost compound names. Selectors like `*` or `[lang]` may
+/// match most of the document, and `:root` changes what everything inherits, so it's
+/// quicker to restyle everything for those.
+fn is_scopable_selector(selector: &Selector<GeckoSelectorImpl>) -> bool {
+    let simple_selectors = &selector.compound_selectors.simple_selectors;
+    simple_selectors.iter().any(|simple| match *simple {
+        SimpleSelector::ID(_) | SimpleSelector::Class(_) | SimpleSelector::LocalName(_) => true,
+        _ => false,
+    }) && !simple_selectors.iter().any(|simple| match *simple {
+        SimpleSelector::Root => true,
+        _ => false,
+    })
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_AddRefComputedValues(ptr: *mut ServoComputedValues) -> () {
-    type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
-    unsafe { Helpers::addref(ptr) };
+fn sheet_invalidation(sheet: &Stylesheet, device: &Device) -> SheetInvalidation {
+    let mut selectors = vec![];
+    for rule in sheet.effective_rules(device) {
+        match *rule {
+            CSSRule::Style(ref style_rule) => {
+                for selector in &style_rule.selectors {
+                    if !is_scopable_selector(selector) {
+                        return SheetInvalidation::Document;
+                    }
+                    selectors.push(selector.clone());
+                }
+            }
+            // Keyframes start the animations of whichever elements name them, and @viewport
+            // changes what lengths are relative to.
+            CSSRule::Keyframes(_) | CSSRule::Viewport(_) => return SheetInvalidation::Document,
+            // Gecko takes @font-face, @counter-style and @page rules from the style set itself,
+            // and they don't change any computed value. Unsupported rules apply nothing. The
+            // iterator goes into the others.
+            _ => {}
+        }
+    }
+    SheetInvalidation::Selectors(selectors)
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_ReleaseComputedValues(ptr: *mut ServoComputedValues) -> () {
-    type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
-    unsafe { Helpers::release(ptr) };
+/// |selectors| as they have to be matched against the elements of the document being styled,
+/// whose ids and classes are lowercased in quirks mode.
+fn selectors_for_document(selectors: &[Selector<GeckoSelectorImpl>]) -> Cow<[Selector<GeckoSelectorImpl>]> {
+    if in_quirks_mode_document() {
+        Cow::Owned(ascii_lowercase_selector_list(selectors))
+    } else {
+        Cow::Borrowed(selectors)
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_InitStyleSet() -> *mut RawServoStyleSet {
-    let data = Box::new(PerDocumentStyleData::new());
-    Box::into_raw(data) as *mut RawServoStyleSet
+/// Like |selectors_for_document|, for a single compound selector and those it's combined with.
+fn compound_selector_for_document(selector: &Arc<CompoundSelector<GeckoSelectorImpl>>)
+                                  -> Arc<CompoundSelector<GeckoSelectorImpl>> {
+    if in_quirks_mode_document() {
+        ascii_lowercase_ids_and_classes(selector)
+    } else {
+        selector.clone()
+    }
+}
+
+/// Marks the elements from |node| down that one of |selectors| matches to be restyled, along
+/// with their descendants. Like the traversal, it keeps the ancestors of the element being
+/// matched in |bloom_filter|, which rules most descendant combinators out without walking up
+/// the tree.
+fn invalidate_matching_elements(node: GeckoNode, selectors: &[Selector<GeckoSelectorImpl>],
+                                bloom_filter: &mut BloomFilter) {
+    if let Some(element) = node.as_element() {
+        let matched = selectors.iter().any(|selector| {
+            matches_compound_selector(&*selector.compound_selectors, &element, Some(&*bloom_filter), &mut false)
+        });
+        if matched {
+            element.note_restyle_hint(RESTYLE_SELF);
+            return;
+        }
+    }
+    node.insert_into_bloom_filter(bloom_filter);
+    for child in node.children() {
+        invalidate_matching_elements(child, selectors, bloom_filter);
+    }
+    node.remove_from_bloom_filter(bloom_filter);
+}
+
+servo_function! {
+    /// Marks the elements of |doc| that |raw_sheet| can change the style of to be restyled,
+    /// for Gecko to call right after adding a sheet that finished loading to a document that
+    /// was styled already, like an async or imported one. The next restyle then leaves the rest
+    /// of the document alone, instead of restyling all of it as it does for other changes to
+    /// the rules.
+    ///
+    /// Everything is marked if the sheet has rules that could match most of the document, like
+    /// `*` or `:root` ones, or if the rules changed in other ways since the last restyle too.
+    fn Servo_StyleSet_InvalidateForSheet(doc: *mut RawGeckoDocument, raw_data: *mut RawServoStyleSet,
+                                         raw_sheet: *mut RawServoStyleSheet) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(doc, raw_data, raw_sheet; ());
+        let token = unsafe { DomToken::new() };
+        let document = unsafe { GeckoDocument::from_raw(&token, doc) };
+        let root = match document.root_node() {
+            Some(root) => root,
+            None => return,
+        };
+        // The next restyle styles everything if nothing has been styled yet.
+        if root.borrow_data().map_or(true, |data| data.style.is_none()) {
+            return;
+        }
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let sheet = Helpers::with(raw_sheet, |sheet| sheet.clone());
+        if !data.only_added_stylesheet(&sheet) {
+            return;
+        }
+        let applies = !sheet.disabled() && sheet.is_effective_for_device(&data.stylist.device) &&
+                      data.enabled_stylesheets().iter().any(|x| arc_ptr_eq(x, &sheet));
+        let invalidation = if applies {
+            sheet_invalidation(&sheet, &data.stylist.device)
+        } else {
+            SheetInvalidation::Selectors(vec![])
+        };
+        match invalidation {
+            SheetInvalidation::Selectors(ref selectors) if !selectors.is_empty() => {
+                root.fetch_document_state();
+                set_document_quirks_mode(data.quirks_mode);
+                invalidate_matching_elements(root, &selectors_for_document(selectors), &mut BloomFilter::new());
+            }
+            SheetInvalidation::Selectors(_) => {}
+            SheetInvalidation::Document => {
+                root.dirty_self();
+                root.dirty_descendants();
+            }
+        }
+        data.note_rule_changes_invalidated();
+        // The elements left alone keep the styles the old rules gave them, which the caches of
+        // styles to share can't tell from new ones.
+        drop_local_context();
+        if let Some(ref work_queue) = *GlobalStyleData::get().work_queue.lock().unwrap() {
+            work_queue.run_on_each_worker(drop_local_context);
+        }
+    }
+}
+
+servo_function! {
+    /// Tells the style set that the CSSOM changed the declarations of |rule|, for Gecko to call
+    /// right after the change. If the rule's sheet is in the set, and the changes made to the
+    /// sheet's declarations since the last restyle are the only ones made to its rules, the next
+    /// restyle just cascades again the elements whose rule nodes have the declarations that were
+    /// replaced, without matching any selectors, and leaves the rest of the document alone.
+    /// Otherwise, or without a rule tree, it restyles the whole document as for other changes
+    /// to the rules.
+    fn Servo_StyleSet_RuleChanged(raw_data: *mut RawServoStyleSet, rule: *mut RawServoStyleRule)
+                                  -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleRule, GeckoStyleRule>;
+        return_if_null!(raw_data, rule; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let rule_tree = match data.rule_tree {
+            Some(ref rule_tree) => rule_tree.clone(),
+            None => return,
+        };
+        let cssom = Helpers::with(rule, |rule| rule.rules.cssom.clone());
+        let sheet = cssom.sheet();
+        let recorded = match data.recorded_rules_generation(&sheet) {
+            Some(generation) => generation,
+            None => return,
+        };
+        {
+            let changes = cssom.declarations_changes.lock().unwrap();
+            let changes: Vec<&DeclarationsChange> = changes.iter().filter(|change| change.generation > recorded)
+                                                           .collect();
+            let covered = changes.len() == sheet.rules_generation().wrapping_sub(recorded) &&
+                          changes.iter().all(|change| change.in_rule_nodes);
+            if !covered {
+                return;
+            }
+            for change in changes {
+                for &(ref old, ref new) in &change.replacements {
+                    rule_tree.replace_declarations(old, new);
+                }
+            }
+        }
+        data.note_declarations_replaced(&sheet);
+        // The styles the caches hold were cascaded from the declarations that were replaced.
+        drop_local_context();
+        if let Some(ref work_queue) = *GlobalStyleData::get().work_queue.lock().unwrap() {
+            work_queue.run_on_each_worker(drop_local_context);
+        }
+    }
+}
+
+servo_function! {
+    /// Tells the style set that the platform's theme changed, so that the next restyle
+    /// recomputes everything that used a system color or font.
+    fn Servo_StyleSet_NoteThemeChanged(raw_data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(raw_data; ());
+        PerDocumentStyleData::borrow_mut_from_raw(raw_data).note_theme_changed();
+    }
+}
+
+servo_function! {
+    /// Turns the author sheets and style attributes off or back on, for Gecko's "No Style"
+    /// mode. User agent and user sheets still apply. The next restyle recomputes every style.
+    fn Servo_StyleSet_SetAuthorStyleDisabled(raw_data: *mut RawServoStyleSet,
+                                             disabled: bool) -> () [on_panic: abort()] {
+        return_if_null!(raw_data; ());
+        PerDocumentStyleData::borrow_mut_from_raw(raw_data).set_author_style_disabled(disabled);
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSet_GetAuthorStyleDisabled(raw_data: *mut RawServoStyleSet) -> bool [on_panic: false] {
+        return_if_null!(raw_data; false);
+        PerDocumentStyleData::borrow_mut_from_raw(raw_data).author_style_disabled()
+    }
+}
+
+servo_function! {
+    /// Styles the document for the medium `medium` names, like `print` for print preview,
+    /// instead of the screen. Returns whether that changed which sheets or @media rules
+    /// apply, in which case the next restyle rebuilds the rules and Gecko should schedule one.
+    fn Servo_StyleSet_SetMedium(raw_data: *mut RawServoStyleSet, medium: *mut nsIAtom) -> bool [on_panic: abort()] {
+        return_if_null!(raw_data, medium; false);
+        let medium = unsafe { atom_from_gecko(medium) };
+        PerDocumentStyleData::borrow_mut_from_raw(raw_data).set_medium(medium)
+    }
+}
+
+servo_function! {
+    /// The medium the document is styled for. The atom stays alive until Servo_Shutdown.
+    fn Servo_StyleSet_GetMedium(raw_data: *mut RawServoStyleSet) -> *mut nsIAtom [on_panic: ptr::null_mut()] {
+        return_if_null!(raw_data; ptr::null_mut());
+        atom_to_gecko(PerDocumentStyleData::borrow_mut_from_raw(raw_data).medium())
+    }
+}
+
+servo_function! {
+    /// Panics, so that tests can check the panic doesn't reach Gecko and that this returns
+    /// false instead.
+    #[cfg(debug_assertions)]
+    fn Servo_Test_Panic() -> bool [on_panic: false] {
+        panic!("Servo_Test_Panic")
+    }
+}
+
+servo_function! {
+    /// Returns how many times Servo called into Gecko to get at the children of a node since
+    /// the last call, so that tests can count the calls a restyle makes.
+    #[cfg(debug_assertions)]
+    fn Servo_Test_TakeChildFFICallCount() -> u32 [on_panic: 0] {
+        ::wrapper::CHILD_FFI_CALLS.swap(0, Ordering::Relaxed) as u32
+    }
+}
+
+servo_function! {
+    /// Returns how many references Servo has given Gecko that Gecko hasn't released yet, so
+    /// that tests can check the count goes back to zero once they drop what they made.
+    #[cfg(debug_assertions)]
+    fn Servo_Test_GetStrongRefCount() -> i32 [on_panic: 0] {
+        ownership::strong_ref_count() as i32
+    }
+}
+
+servo_function! {
+    /// Returns how many times the rules of the given origin have been rebuilt.
+    #[cfg(debug_assertions)]
+    fn Servo_StyleSet_GetRebuildCount(raw_data: *mut RawServoStyleSet,
+                                      origin: StyleSheetOrigin) -> u32 [on_panic: 0] {
+        return_if_null!(raw_data; 0);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.rebuild_count(origin_from_gecko(origin))
+    }
+}
+
+servo_function! {
+    /// Returns how many times the style set had to cascade an anonymous box style because it
+    /// hadn't kept one for the same parent style and pseudo-element.
+    #[cfg(debug_assertions)]
+    fn Servo_StyleSet_GetAnonymousBoxCascadeCount(raw_data: *mut RawServoStyleSet) -> u32 [on_panic: 0] {
+        return_if_null!(raw_data; 0);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.anonymous_box_cascade_count()
+    }
+}
+
+servo_function! {
+    /// Measures |raw_sheet|, its rules, and the sheets its @import rules loaded, with Gecko's
+    /// moz_malloc_size_of. Sheets imported more than once are only counted once.
+    fn Servo_StyleSheet_SizeOfIncludingThis(malloc_size_of: MallocSizeOfFn,
+                                            raw_sheet: *mut RawServoStyleSheet) -> usize [on_panic: 0] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; 0);
+        let mut ops = MallocSizeOfOps::new(malloc_size_of);
+        Helpers::with(raw_sheet, |sheet| sheet.malloc_size_of_children(&mut ops))
+    }
+}
+
+servo_function! {
+    /// Measures |raw_data| and everything it owns but its sheets, which Gecko measures with
+    /// Servo_StyleSheet_SizeOfIncludingThis.
+    fn Servo_StyleSet_SizeOf(malloc_size_of: MallocSizeOfFn, raw_data: *mut RawServoStyleSet) -> usize [on_panic: 0] {
+        return_if_null!(raw_data; 0);
+        let ops = &mut MallocSizeOfOps::new(malloc_size_of);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        unsafe { ops.malloc_size_of(data as *const PerDocumentStyleData) + data.malloc_size_of_children(ops) }
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSheetHasRules(raw_sheet: *mut RawServoStyleSheet) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; false);
+        Helpers::with(raw_sheet, |sheet| !sheet.read_rules().is_empty())
+    }
+}
+
+servo_function! {
+    /// Returns a new sheet with the rules of |sheet|, for Gecko to use in another document.
+    /// The two share their rules until either of them is changed through the CSSOM or
+    /// reparsed, at which point that one gets its own copy, so the other is left alone.
+    fn Servo_StyleSheet_Clone(sheet: *mut RawServoStyleSheet) -> RawServoStyleSheetStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(sheet; Strong::null());
+        Strong::from_arc(Helpers::with(sheet, |sheet| Arc::new((**sheet).clone())))
+    }
+}
+
+/// The shared sheet made from |sheet|, which is made now if there isn't one yet.
+fn shared_stylesheet(sheet: &Arc<Stylesheet>) -> Arc<SharedStylesheet> {
+    let mut registry = GlobalStyleData::get().shared_sheets.lock().unwrap();
+    let key = &**sheet as *const Stylesheet as usize;
+    if let Some(shared) = registry.get(&key).and_then(|shared| shared.upgrade()) {
+        return shared;
+    }
+    let dead: Vec<usize> = registry.iter().filter(|&(_, shared)| shared.upgrade().is_none())
+                                   .map(|(&key, _)| key).collect();
+    for key in dead {
+        registry.remove(&key);
+    }
+    let shared = Arc::new(SharedStylesheet::new(sheet.clone()));
+    registry.insert(key, Arc::downgrade(&shared));
+    shared
+}
+
+servo_function! {
+    /// Freezes |sheet| so that the style sets of every document can share it, through
+    /// Servo_StyleSet_AppendSharedStyleSheet. For user agent sheets whose rules apply whatever
+    /// the device, the rule maps are built once too, and shared by all of those style sets.
+    /// Freezing a sheet again returns the same shared sheet. Afterwards, the CSSOM can't
+    /// change the sheet, and it can't be reparsed or disabled.
+    fn Servo_StyleSheet_IntoShared(sheet: *mut RawServoStyleSheet)
+                                   -> RawServoSharedStyleSheetStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(sheet; Strong::null());
+        Strong::from_arc(Helpers::with(sheet, shared_stylesheet))
+    }
+}
+
+servo_function! {
+    fn Servo_SharedStyleSheet_AddRef(shared: *mut RawServoSharedStyleSheet) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<SharedStylesheet>(shared) };
+    }
+}
+
+servo_function! {
+    fn Servo_SharedStyleSheet_Release(shared: *mut RawServoSharedStyleSheet) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<SharedStylesheet>(shared) };
+    }
+}
+
+servo_function! {
+    /// Measures the rule maps the style sets using |shared| share, which Servo_StyleSet_SizeOf
+    /// leaves out. The sheet is measured with Servo_StyleSheet_SizeOfIncludingThis.
+    fn Servo_SharedStyleSheet_SizeOfRuleMaps(shared: *mut RawServoSharedStyleSheet) -> usize [on_panic: 0] {
+        type Helpers = ArcHelpers<RawServoSharedStyleSheet, SharedStylesheet>;
+        return_if_null!(shared; 0);
+        Helpers::with(shared, |shared| shared.heap_size_of_rule_maps())
+    }
+}
+
+// Keep these in sync with the rule type constants in dom/webidl/CSSRule.webidl.
+const UNKNOWN_RULE: u16 = 0;
+const STYLE_RULE: u16 = 1;
+const IMPORT_RULE: u16 = 3;
+const MEDIA_RULE: u16 = 4;
+const FONT_FACE_RULE: u16 = 5;
+const PAGE_RULE: u16 = 6;
+const KEYFRAMES_RULE: u16 = 7;
+const NAMESPACE_RULE: u16 = 10;
+const COUNTER_STYLE_RULE: u16 = 11;
+const SUPPORTS_RULE: u16 = 12;
+const VIEWPORT_RULE: u16 = 15;
+
+/// The rules the CSSOM exposes out of |rules|. @charset rules aren't part of it, but
+/// unsupported rules are, as rules of unknown type, so that the sheet serializes with them.
+fn cssom_rules(rules: &[CSSRule<GeckoSelectorImpl>]) -> Vec<&CSSRule<GeckoSelectorImpl>> {
+    rules.iter().filter(|rule| match **rule {
+        CSSRule::Charset(_) => false,
+        _ => true,
+    }).collect()
+}
+
+/// What the CSSOM rule lists made for a sheet share. When the sheet is reparsed they're
+/// detached: they move to a copy of the sheet with the rules they were made for, which
+/// they can still read and change, but which no style set holds.
+pub struct GeckoSheetCssom {
+    sheet: RwLock<Arc<Stylesheet>>,
+    /// The latest changes made to the declarations of the sheet's style rules through the
+    /// CSSOM, oldest first, for Servo_StyleSet_RuleChanged to find in the rule tree.
+    declarations_changes: Mutex<Vec<DeclarationsChange>>,
+}
+
+/// How many changes to its declarations a sheet remembers.
+const MAX_DECLARATIONS_CHANGES: usize = 32;
+
+/// A change the CSSOM made to the declarations of a style rule.
+struct DeclarationsChange {
+    /// The rules generation of the sheet right after the change.
+    generation: usize,
+    /// The lists of declarations the rule had, normal then important, each with the one it
+    /// has now.
+    replacements: Vec<(Arc<Vec<PropertyDeclaration>>, Arc<Vec<PropertyDeclaration>>)>,
+    /// Whether the rule nodes of the elements the rule applies to are all the rule's styles
+    /// went into. They aren't when it styles pseudo-elements or depends on links being
+    /// visited, which keep styles of their own, or when one of its lists was empty and isn't
+    /// anymore, since rule nodes are only made for the lists that aren't empty.
+    in_rule_nodes: bool,
+}
+
+impl GeckoSheetCssom {
+    /// The state the rule lists made for |sheet| share, if any are still around.
+    fn for_sheet(sheet: &Arc<Stylesheet>) -> Arc<GeckoSheetCssom> {
+        let mut registry = GlobalStyleData::get().sheet_cssom.lock().unwrap();
+        let key = &**sheet as *const Stylesheet as usize;
+        if let Some(cssom) = registry.get(&key).and_then(|cssom| cssom.upgrade()) {
+            return cssom;
+        }
+        let dead: Vec<usize> = registry.iter().filter(|&(_, cssom)| cssom.upgrade().is_none())
+                                       .map(|(&key, _)| key).collect();
+        for key in dead {
+            registry.remove(&key);
+        }
+        let cssom = Arc::new(GeckoSheetCssom {
+            sheet: RwLock::new(sheet.clone()),
+            declarations_changes: Mutex::new(vec![]),
+        });
+        registry.insert(key, Arc::downgrade(&cssom));
+        cssom
+    }
+
+    /// Moves the rule lists made for |sheet| to |detached|, which holds the rules they
+    /// were made for.
+    fn detach(sheet: &Arc<Stylesheet>, detached: Stylesheet) {
+        let key = &**sheet as *const Stylesheet as usize;
+        let cssom = GlobalStyleData::get().sheet_cssom.lock().unwrap().remove(&key);
+        let cssom = cssom.and_then(|cssom| cssom.upgrade());
+        if let Some(cssom) = cssom {
+            *cssom.sheet.write().unwrap() = Arc::new(detached);
+            // Their generations count the changes to the sheet they were made on.
+            cssom.declarations_changes.lock().unwrap().clear();
+        }
+    }
+
+    fn sheet(&self) -> Arc<Stylesheet> {
+        self.sheet.read().unwrap().clone()
+    }
+
+    fn note_declarations_change(&self, change: DeclarationsChange) {
+        let mut changes = self.declarations_changes.lock().unwrap();
+        if changes.len() == MAX_DECLARATIONS_CHANGES {
+            changes.remove(0);
+        }
+        changes.push(change);
+    }
+}
+
+/// A CSSOM rule list: the rules of a sheet, or the ones nested inside one of its
+/// @media or @supports rules. Holds the sheet alive.
+pub struct GeckoCssRules {
+    cssom: Arc<GeckoSheetCssom>,
+    /// The CSSOM indices of the grouping rules to go through to get to the list,
+    /// outermost first.
+    path: Vec<usize>,
+}
+
+impl GeckoCssRules {
+    /// Calls |cb| with the rules in the list, with the rules of the sheet locked for
+    /// reading. Returns None if the grouping rule the list lived in is gone.
+    fn with_rules<F, Output>(&self, cb: F) -> Option<Output>
+                             where F: FnOnce(&[&CSSRule<GeckoSelectorImpl>]) -> Output {
+        let sheet = self.cssom.sheet();
+        let sheet_rules = sheet.read_rules();
+        let mut rules = cssom_rules(&sheet_rules);
+        for &index in &self.path {
+            let rule = match rules.get(index) {
+                Some(&rule) => rule,
+                None => return None,
+            };
+            rules = match *rule {
+                CSSRule::Media(ref media) => cssom_rules(&media.rules),
+                CSSRule::Supports(ref supports) => cssom_rules(&supports.rules),
+                _ => return None,
+            };
+        }
+        Some(cb(&rules))
+    }
+
+    fn with_rule<F, Output>(&self, index: usize, cb: F) -> Option<Output>
+                            where F: FnOnce(&CSSRule<GeckoSelectorImpl>) -> Output {
+        self.with_rules(|rules| rules.get(index).map(|rule| cb(*rule))).and_then(|result| result)
+    }
+}
+
+/// Finds the list |path| leads to from the rules of a sheet, as for |GeckoCssRules|.
+fn nested_rules_mut<'a>(rules: &'a mut Vec<CSSRule<GeckoSelectorImpl>>, path: &[usize])
+                        -> Option<&'a mut Vec<CSSRule<GeckoSelectorImpl>>> {
+    let mut rules = rules;
+    for &index in path {
+        let current = rules;
+        let rule = current.iter_mut().filter(|rule| match **rule {
+            CSSRule::Charset(_) => false,
+            _ => true,
+        }).nth(index);
+        rules = match rule {
+            Some(&mut CSSRule::Media(ref mut media)) => &mut media.rules,
+            Some(&mut CSSRule::Supports(ref mut supports)) => &mut supports.rules,
+            _ => return None,
+        };
+    }
+    Some(rules)
+}
+
+/// Whether the elements |selector| matches only get its declarations through their rule
+/// nodes: it doesn't style a pseudo-element, and doesn't depend on whether links are visited,
+/// which gives its declarations a block of their own.
+fn styles_only_rule_nodes(selector: &Selector<GeckoSelectorImpl>) -> bool {
+    fn depends_on_visitedness(simple: &SimpleSelector<GeckoSelectorImpl>) -> bool {
+        match *simple {
+            SimpleSelector::NonTSPseudoClass(NonTSPseudoClass::Link) |
+            SimpleSelector::NonTSPseudoClass(NonTSPseudoClass::Visited) => true,
+            SimpleSelector::Negation(ref selectors) => selectors.iter().any(depends_on_visitedness),
+            _ => false,
+        }
+    }
+    if selector.pseudo_element.is_some() {
+        return false;
+    }
+    let mut compound = &selector.compound_selectors;
+    loop {
+        if compound.simple_selectors.iter().any(depends_on_visitedness) {
+            return false;
+        }
+        compound = match compound.next {
+            Some((ref next, _)) => next,
+            None => return true,
+        };
+    }
+}
+
+/// A style rule in a CSSOM rule list.
+pub struct GeckoStyleRule {
+    rules: Arc<GeckoCssRules>,
+    index: usize,
+}
+
+impl GeckoStyleRule {
+    /// Gives the rule |declarations|, which a block made for it was changed to, and notes the
+    /// change for Servo_StyleSet_RuleChanged. Rules of frozen sheets keep the ones they had.
+    /// Gecko only changes rules on the main thread, with the shared lock held for writing.
+    fn set_declarations(&self, declarations: &PropertyDeclarationBlock) {
+        let sheet = self.rules.cssom.sheet();
+        let index = self.index;
+        let result = sheet.try_with_rules_mut(|sheet_rules| {
+            let rule = nested_rules_mut(sheet_rules, &self.rules.path).and_then(|list| {
+                list.iter_mut().filter(|rule| match **rule {
+                    CSSRule::Charset(_) => false,
+                    _ => true,
+                }).nth(index)
+            });
+            match rule {
+                Some(&mut CSSRule::Style(ref mut rule)) => {
+                    let old = mem::replace(&mut rule.declarations, declarations.clone());
+                    let lists_matched = (!old.normal.is_empty() || declarations.normal.is_empty()) &&
+                                        (!old.important.is_empty() || declarations.important.is_empty());
+                    let in_rule_nodes = lists_matched && rule.selectors.iter().all(styles_only_rule_nodes);
+                    Some((vec![(old.normal, declarations.normal.clone()),
+                               (old.important, declarations.important.clone())],
+                          in_rule_nodes))
+                }
+                _ => None,
+            }
+        });
+        if let Ok(Some((replacements, in_rule_nodes))) = result {
+            self.rules.cssom.note_declarations_change(DeclarationsChange {
+                generation: sheet.rules_generation(),
+                replacements: replacements,
+                in_rule_nodes: in_rule_nodes,
+            });
+        }
+    }
+
+    fn with<F, Output>(raw: *mut RawServoStyleRule, default: Output, cb: F) -> Output
+                       where F: FnOnce(&StyleRule<GeckoSelectorImpl>) -> Output {
+        type Helpers = ArcHelpers<RawServoStyleRule, GeckoStyleRule>;
+        Helpers::with(raw, |style_rule| {
+            style_rule.rules.with_rule(style_rule.index, |rule| match *rule {
+                CSSRule::Style(ref rule) => Some(cb(rule)),
+                _ => None,
+            }).and_then(|result| result)
+        }).unwrap_or(default)
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSheet_GetRules(sheet: *mut RawServoStyleSheet) -> RawServoCssRulesStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(sheet; Strong::null());
+        let rules = Arc::new(GeckoCssRules {
+            cssom: Helpers::with(sheet, GeckoSheetCssom::for_sheet),
+            path: vec![],
+        });
+        Strong::from_arc(rules)
+    }
+}
+
+servo_function! {
+    fn Servo_CssRules_AddRef(rules: *mut RawServoCssRules) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<GeckoCssRules>(rules) };
+    }
+}
+
+servo_function! {
+    fn Servo_CssRules_Release(rules: *mut RawServoCssRules) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<GeckoCssRules>(rules) };
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssrulelist-length
+    fn Servo_CssRules_GetCount(rules: *mut RawServoCssRules) -> u32 [on_panic: 0] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; 0);
+        Helpers::with(rules, |rules| rules.with_rules(|rules| rules.len() as u32).unwrap_or(0))
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssrule-type
+    fn Servo_CssRules_GetTypeAt(rules: *mut RawServoCssRules, index: u32) -> u16 [on_panic: 0] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; UNKNOWN_RULE);
+        Helpers::with(rules, |rules| {
+            rules.with_rule(index as usize, |rule| match *rule {
+                CSSRule::Style(_) => STYLE_RULE,
+                CSSRule::Import(_) => IMPORT_RULE,
+                CSSRule::Media(_) => MEDIA_RULE,
+                CSSRule::FontFace(_) => FONT_FACE_RULE,
+                CSSRule::Keyframes(_) => KEYFRAMES_RULE,
+                CSSRule::Page(_) => PAGE_RULE,
+                CSSRule::CounterStyle(_) => COUNTER_STYLE_RULE,
+                CSSRule::Namespace(..) => NAMESPACE_RULE,
+                CSSRule::Supports(_) => SUPPORTS_RULE,
+                CSSRule::Viewport(_) => VIEWPORT_RULE,
+                CSSRule::Charset(_) | CSSRule::Unsupported(_) => UNKNOWN_RULE,
+            }).unwrap_or(UNKNOWN_RULE)
+        })
+    }
+}
+
+servo_function! {
+    /// Returns the style rule at |index|, or null if there's no rule there or it's some
+    /// other kind of rule.
+    fn Servo_CssRules_GetStyleRuleAt(rules: *mut RawServoCssRules, index: u32)
+                                                    -> RawServoStyleRuleStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; Strong::null());
+        Helpers::with(rules, |rules| {
+            let is_style_rule = rules.with_rule(index as usize, |rule| match *rule {
+                CSSRule::Style(_) => true,
+                _ => false,
+            }).unwrap_or(false);
+            if !is_style_rule {
+                return Strong::null()
+            }
+            let style_rule = Arc::new(GeckoStyleRule { rules: rules.clone(), index: index as usize });
+            Strong::from_arc(style_rule)
+        })
+    }
+}
+
+servo_function! {
+    /// Returns the @counter-style rule at |index|, or null if there's no rule there or it's
+    /// some other kind of rule.
+    fn Servo_CssRules_GetCounterStyleRuleAt(rules: *mut RawServoCssRules, index: u32)
+                                            -> RawServoCounterStyleRuleStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; Strong::null());
+        Helpers::with(rules, |rules| {
+            rules.with_rule(index as usize, |rule| match *rule {
+                CSSRule::CounterStyle(ref rule) => Strong::from_arc(rule.clone()),
+                _ => Strong::null(),
+            }).unwrap_or_else(Strong::null)
+        })
+    }
+}
+
+servo_function! {
+    /// Writes out the text of the unsupported rule at |index|, as it was in the sheet, for
+    /// its cssText. Returns false, leaving |result| alone, if there's no such rule there.
+    fn Servo_CssRules_GetUnsupportedRuleTextAt(rules: *mut RawServoCssRules, index: u32,
+                                               result: *mut nsString) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules, result; false);
+        let text = Helpers::with(rules, |rules| {
+            rules.with_rule(index as usize, |rule| match *rule {
+                CSSRule::Unsupported(ref rule) => Some(rule.to_css_string()),
+                _ => None,
+            }).and_then(|text| text)
+        });
+        match text {
+            Some(text) => {
+                write_to_string(result, &text);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+servo_function! {
+    /// Returns the rules nested inside the @media or @supports rule at |index|, or null if
+    /// there's no such rule there.
+    fn Servo_CssRules_GetNestedRulesAt(rules: *mut RawServoCssRules, index: u32)
+                                                      -> RawServoCssRulesStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; Strong::null());
+        Helpers::with(rules, |rules| {
+            let is_grouping_rule = rules.with_rule(index as usize, |rule| match *rule {
+                CSSRule::Media(_) | CSSRule::Supports(_) => true,
+                _ => false,
+            }).unwrap_or(false);
+            if !is_grouping_rule {
+                return Strong::null()
+            }
+            let mut path = rules.path.clone();
+            path.push(index as usize);
+            let nested = Arc::new(GeckoCssRules { cssom: rules.cssom.clone(), path: path });
+            Strong::from_arc(nested)
+        })
+    }
+}
+
+// Keep these in sync with the DOM exception codes in xpcom/base/ErrorList.h.
+const NS_OK: nsresult = 0;
+const NS_ERROR_FAILURE: nsresult = 0x80004005;
+const NS_ERROR_DOM_INDEX_SIZE_ERR: nsresult = 0x80530001;
+const NS_ERROR_DOM_HIERARCHY_REQUEST_ERR: nsresult = 0x80530003;
+const NS_ERROR_DOM_NO_MODIFICATION_ALLOWED_ERR: nsresult = 0x80530007;
+const NS_ERROR_DOM_INVALID_STATE_ERR: nsresult = 0x8053000B;
+const NS_ERROR_DOM_SYNTAX_ERR: nsresult = 0x8053000C;
+
+fn rules_mutate_result_to_gecko(result: Option<Result<(), RulesMutateError>>) -> nsresult {
+    match result {
+        Some(Ok(())) => NS_OK,
+        Some(Err(RulesMutateError::Syntax)) => NS_ERROR_DOM_SYNTAX_ERR,
+        Some(Err(RulesMutateError::IndexSize)) => NS_ERROR_DOM_INDEX_SIZE_ERR,
+        Some(Err(RulesMutateError::HierarchyRequest)) => NS_ERROR_DOM_HIERARCHY_REQUEST_ERR,
+        Some(Err(RulesMutateError::NoModificationAllowed)) => NS_ERROR_DOM_NO_MODIFICATION_ALLOWED_ERR,
+        // The list is gone if the grouping rule it was in was removed.
+        Some(Err(RulesMutateError::InvalidState)) | None => NS_ERROR_DOM_INVALID_STATE_ERR,
+    }
+}
+
+servo_function! {
+    /// Parses |rule| and inserts it in the list before |index|, for insertRule. |nested|
+    /// says whether the list is that of a grouping rule rather than of |sheet| itself.
+    /// Style sets holding the sheet notice the change on their next flush. Shared sheets
+    /// refuse with NoModificationAllowedError, as does Servo_CssRules_DeleteRule. The rule
+    /// is parsed against the sheet's URL and in its compatibility mode, and, as with
+    /// Servo_StylesheetFromUTF8Bytes, errors are reported to |error_context| and an @import
+    /// rule loaded with it.
+    // https://drafts.csswg.org/cssom/#dom-cssstylesheet-insertrule
+    fn Servo_CssRules_InsertRule(rules: *mut RawServoCssRules,
+                                 sheet: *mut RawServoStyleSheet,
+                                 rule: *const u8,
+                                 rule_length: u32,
+                                 index: u32,
+                                 nested: bool,
+                                 error_context: *mut c_void) -> nsresult [on_panic: NS_ERROR_FAILURE] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        type SheetHelpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(rules, sheet; NS_ERROR_FAILURE);
+        let rule = unsafe { str_from_raw(rule, rule_length) };
+        let (origin, base_url, quirks_mode, url_extra_data) = SheetHelpers::with(sheet, |sheet| {
+            (sheet.origin, sheet.base_url.clone(), sheet.quirks_mode, sheet.url_extra_data())
+        });
+        let loader = GeckoStylesheetLoader { context: error_context, extra_data: url_extra_data.clone() };
+        let result = Helpers::with(rules, |rules| {
+            let error_reporter = Box::new(GeckoErrorReporter { context: error_context });
+            let mut context = ParserContext::new(origin, &base_url, error_reporter);
+            context.quirks_mode = quirks_mode;
+            context.url_extra_data = url_extra_data;
+            // Gecko only changes rules on the main thread, where nothing else can be reading
+            // them without holding the locks.
+            let _guard = GlobalStyleData::get().shared_lock.write();
+            let result = rules.cssom.sheet().try_with_rules_mut(|sheet_rules| {
+                add_namespace_prefixes(&mut context, sheet_rules);
+                nested_rules_mut(sheet_rules, &rules.path).map(|list| {
+                    insert_rule(list, index as usize, nested, rule, context, Some(&loader))
+                })
+            });
+            result.unwrap_or_else(|error| Some(Err(error)))
+        });
+        rules_mutate_result_to_gecko(result)
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssstylesheet-deleterule
+    fn Servo_CssRules_DeleteRule(rules: *mut RawServoCssRules, index: u32) -> nsresult [on_panic: NS_ERROR_FAILURE] {
+        type Helpers = ArcHelpers<RawServoCssRules, GeckoCssRules>;
+        return_if_null!(rules; NS_ERROR_FAILURE);
+        let result = Helpers::with(rules, |rules| {
+            let _guard = GlobalStyleData::get().shared_lock.write();
+            let result = rules.cssom.sheet().try_with_rules_mut(|sheet_rules| {
+                nested_rules_mut(sheet_rules, &rules.path).map(|list| delete_rule(list, index as usize))
+            });
+            result.unwrap_or_else(|error| Some(Err(error)))
+        });
+        rules_mutate_result_to_gecko(result)
+    }
+}
+
+servo_function! {
+    fn Servo_StyleRule_AddRef(rule: *mut RawServoStyleRule) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<GeckoStyleRule>(rule) };
+    }
+}
+
+servo_function! {
+    fn Servo_StyleRule_Release(rule: *mut RawServoStyleRule) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<GeckoStyleRule>(rule) };
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssstylerule-selectortext
+    fn Servo_StyleRule_GetSelectorText(rule: *mut RawServoStyleRule, result: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(rule, result; ());
+        let text = GeckoStyleRule::with(rule, String::new(), |rule| {
+            let mut text = String::new();
+            let _ = serialize_selector_list(&rule.selectors, &mut text);
+            text
+        });
+        write_to_string(result, &text);
+    }
+}
+
+/// Converts the specificity of a selector to the format of nsCSSSelector::CalcWeight, which
+/// counts ids from bit 16, classes, attributes and pseudo-classes from bit 8, and types and
+/// pseudo-elements from bit 0.
+fn specificity_to_gecko(specificity: u32) -> u64 {
+    let ids = (specificity >> 20) as u64;
+    let classes = ((specificity >> 10) & 0x3ff) as u64;
+    let types = (specificity & 0x3ff) as u64;
+    (ids << 16) + (classes << 8) + types
+}
+
+servo_function! {
+    /// The number of selectors in the rule's selector list, or 0 if the rule is gone.
+    fn Servo_StyleRule_GetSelectorCount(rule: *mut RawServoStyleRule) -> u32 [on_panic: 0] {
+        return_if_null!(rule; 0);
+        GeckoStyleRule::with(rule, 0, |rule| rule.selectors.len() as u32)
+    }
+}
+
+servo_function! {
+    /// Writes out the |index|th selector of the rule, serialized like selectorText does, or
+    /// nothing if there's no such selector.
+    fn Servo_StyleRule_GetSelectorTextAt(rule: *mut RawServoStyleRule, index: u32,
+                                         result: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(rule, result; ());
+        let text = GeckoStyleRule::with(rule, String::new(), |rule| {
+            let mut text = String::new();
+            if let Some(selector) = rule.selectors.get(index as usize) {
+                let _ = serialize_selector(selector, &mut text);
+            }
+            text
+        });
+        write_to_string(result, &text);
+    }
+}
+
+servo_function! {
+    /// The specificity of the |index|th selector of the rule, in the format of
+    /// nsCSSSelector::CalcWeight, or 0 if there's no such selector.
+    fn Servo_StyleRule_GetSpecificityAt(rule: *mut RawServoStyleRule, index: u32) -> u64 [on_panic: 0] {
+        return_if_null!(rule; 0);
+        GeckoStyleRule::with(rule, 0, |rule| {
+            rule.selectors.get(index as usize).map_or(0, |selector| specificity_to_gecko(selector.specificity))
+        })
+    }
+}
+
+servo_function! {
+    /// Whether the |index|th selector of the rule matches |element|. Selectors with a
+    /// pseudo-element never match the element itself.
+    fn Servo_StyleRule_SelectorMatchesElement(rule: *mut RawServoStyleRule,
+                                              element: *mut RawGeckoElement,
+                                              index: u32) -> bool [on_panic: false] {
+        return_if_null!(rule, element; false);
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        element.as_node().fetch_document_state();
+        GeckoStyleRule::with(rule, false, |rule| match rule.selectors.get(index as usize) {
+            Some(selector) => {
+                selector.pseudo_element.is_none() &&
+                matches_compound_selector(&*compound_selector_for_document(&selector.compound_selectors),
+                                          &element, None, &mut false)
+            }
+            None => false,
+        })
+    }
+}
+
+servo_function! {
+    /// Returns a declaration block with the declarations of the rule, or null if the rule is
+    /// gone. Changes made through the block go back to the rule, but blocks got for the rule
+    /// earlier don't see them.
+    fn Servo_StyleRule_GetDeclarations(rule: *mut RawServoStyleRule)
+                                                      -> RawServoDeclarationBlockStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoStyleRule, GeckoStyleRule>;
+        return_if_null!(rule; Strong::null());
+        let declarations = GeckoStyleRule::with(rule, None, |rule| Some(rule.declarations.clone()));
+        match declarations {
+            Some(declarations) => Helpers::with(rule, |style_rule| {
+                Strong::from_arc(GeckoDeclarationBlock::for_rule(declarations, style_rule.clone()))
+            }),
+            None => Strong::null(),
+        }
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssrule-csstext
+    fn Servo_StyleRule_GetCssText(rule: *mut RawServoStyleRule, result: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(rule, result; ());
+        let css_text = GeckoStyleRule::with(rule, String::new(), |rule| rule.to_css_string());
+        write_to_string(result, &css_text);
+    }
+}
+
+servo_function! {
+    /// Writes out where the rule's selectors start in the source of its sheet, counting
+    /// lines and columns from 1. Rules added with `insertRule` report line 0, column 0.
+    fn Servo_StyleRule_GetSourceLocation(rule: *mut RawServoStyleRule,
+                                         out_line: *mut u32, out_column: *mut u32) -> () [on_panic: ()] {
+        return_if_null!(rule, out_line, out_column; ());
+        let location = GeckoStyleRule::with(rule, SourceLocation::default(), |rule| rule.source_location);
+        unsafe {
+            *out_line = location.line;
+            *out_column = location.column;
+        }
+    }
+}
+
+/// A style rule that matched an element, for Servo_GetMatchingRules.
+struct MatchedRule {
+    sheet: Arc<Stylesheet>,
+    /// Where the rule is, as for |GeckoCssRules| and |GeckoStyleRule|.
+    path: Vec<usize>,
+    index: usize,
+    /// The selector of the rule that matched, or the most specific one if several did.
+    selector_index: usize,
+    specificity: u32,
+    source_order: usize,
+}
+
+/// Matches the style rules in |rules| against |element|, going into the @media and
+/// @supports rules that apply and the sheets of the @import rules that do.
+fn collect_matching_rules(sheet: &Arc<Stylesheet>,
+                          rules: &[&CSSRule<GeckoSelectorImpl>],
+                          path: &mut Vec<usize>,
+                          element: &GeckoElement,
+                          pseudo: Option<&PseudoElement>,
+                          device: &Device,
+                          matched: &mut Vec<MatchedRule>) {
+    for (index, rule) in rules.iter().enumerate() {
+        match **rule {
+            CSSRule::Style(ref style_rule) => {
+                let best = style_rule.selectors.iter().enumerate().filter(|&(_, selector)| {
+                    selector.pseudo_element.as_ref() == pseudo &&
+                    matches_compound_selector(&*compound_selector_for_document(&selector.compound_selectors),
+                                              element, None, &mut false)
+                }).max_by_key(|&(_, selector)| selector.specificity);
+                if let Some((selector_index, selector)) = best {
+                    let source_order = matched.len();
+                    matched.push(MatchedRule {
+                        sheet: sheet.clone(),
+                        path: path.clone(),
+                        index: index,
+                        selector_index: selector_index,
+                        specificity: selector.specificity,
+                        source_order: source_order,
+                    });
+                }
+            }
+            CSSRule::Media(ref media) if media.evaluate(device) => {
+                path.push(index);
+                collect_matching_rules(sheet, &cssom_rules(&media.rules), path, element, pseudo, device, matched);
+                path.pop();
+            }
+            CSSRule::Supports(ref supports) if supports.enabled => {
+                path.push(index);
+                collect_matching_rules(sheet, &cssom_rules(&supports.rules), path, element, pseudo, device,
+                                       matched);
+                path.pop();
+            }
+            CSSRule::Import(ref import) if import.evaluate(device) => {
+                if let Some(imported) = import.stylesheet() {
+                    let imported_rules = imported.read_rules();
+                    collect_matching_rules(&imported, &cssom_rules(&imported_rules), &mut vec![], element, pseudo,
+                                           device, matched);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+servo_function! {
+    /// Appends the style rules that match |element|, or its |pseudo_tag| pseudo-element if
+    /// that isn't null, to |out|, from the one that loses the cascade to the one that wins.
+    /// Rules are ordered by the level their normal declarations cascade at. The element's
+    /// presentational hints and style attribute go where they cascade too, as declaration
+    /// blocks of their own.
+    ///
+    /// Matching is done again rather than read from the node data, so the list reflects the
+    /// element's current state even if it hasn't been restyled since it changed. Each rule
+    /// and block hands Gecko a strong reference, which it must release.
+    fn Servo_GetMatchingRules(element: *mut RawGeckoElement,
+                              pseudo_tag: *mut nsIAtom,
+                              raw_data: *mut RawServoStyleSet,
+                              out: *mut RawGeckoRuleList) -> () [on_panic: ()] {
+        return_if_null!(element, raw_data, out; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+
+        let pseudo = if pseudo_tag.is_null() {
+            None
+        } else {
+            match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+                Some(pseudo) => Some(pseudo),
+                None => return,
+            }
+        };
+
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        element.as_node().fetch_document_state();
+        set_document_quirks_mode(data.quirks_mode);
+
+        let mut matched = vec![];
+        for sheet in data.enabled_stylesheets() {
+            if sheet.disabled() || !sheet.is_effective_for_device(&data.stylist.device) {
+                continue;
+            }
+            let sheet_rules = sheet.read_rules();
+            collect_matching_rules(sheet, &cssom_rules(&sheet_rules), &mut vec![], &element, pseudo.as_ref(),
+                                   &data.stylist.device, &mut matched);
+        }
+        let origin_order = |origin: Origin| match origin {
+            Origin::UserAgent => 0,
+            Origin::User => 1,
+            Origin::Author => 2,
+        };
+        matched.sort_by_key(|rule| (origin_order(rule.sheet.origin), rule.specificity, rule.source_order));
+
+        let append_rule = |rule: &MatchedRule| {
+            let rules = Arc::new(GeckoCssRules {
+                cssom: GeckoSheetCssom::for_sheet(&rule.sheet),
+                path: rule.path.clone(),
+            });
+            let style_rule = Arc::new(GeckoStyleRule { rules: rules, index: rule.index });
+            let raw_sheet: *mut RawServoStyleSheet = Borrowed::from_arc(&rule.sheet).as_ptr();
+            unsafe {
+                Gecko_RuleListAppend(out, raw_sheet, Strong::from_arc(style_rule),
+                                     rule.selector_index as u32, rule.specificity);
+            }
+        };
+        let origin_rules = |origin: Origin| matched.iter().filter(move |rule| rule.sheet.origin == origin);
+
+        // The element's own blocks don't apply to its pseudo-elements.
+        let append_block = |block: *mut RawServoDeclarationBlock, is_style_attribute| {
+            if pseudo.is_none() && !block.is_null() {
+                let block = unsafe { Borrowed::from_raw(block) }.to_strong::<GeckoDeclarationBlock>();
+                unsafe { Gecko_RuleListAppendDeclarations(out, block, is_style_attribute) };
+            }
+        };
+
+        for rule in origin_rules(Origin::UserAgent) {
+            append_rule(rule);
+        }
+        append_block(unsafe { Gecko_GetHTMLPresentationAttrDeclarationBlock(element.as_raw()) }, false);
+        for rule in origin_rules(Origin::User).chain(origin_rules(Origin::Author)) {
+            append_rule(rule);
+        }
+        append_block(unsafe { Gecko_GetStyleAttrDeclarationBlock(element.as_raw()) }, true);
+    }
+}
+
+/// A timing function in the form Gecko takes it.
+pub fn timing_function_to_ffi(timing_function: TransitionTimingFunction) -> ServoTimingFunction {
+    match timing_function {
+        TransitionTimingFunction::Linear => ServoTimingFunction {
+            mType: ServoTimingFunctionType::Linear,
+            mX1: 0.,
+            mY1: 0.,
+            mX2: 0.,
+            mY2: 0.,
+            mSteps: 0,
+            mStepPosition: ServoStepPosition::End,
+        },
+        TransitionTimingFunction::CubicBezier(p1, p2) => ServoTimingFunction {
+            mType: ServoTimingFunctionType::CubicBezier,
+            mX1: p1.x,
+            mY1: p1.y,
+            mX2: p2.x,
+            mY2: p2.y,
+            mSteps: 0,
+            mStepPosition: ServoStepPosition::End,
+        },
+        TransitionTimingFunction::Steps(steps, position) => ServoTimingFunction {
+            mType: ServoTimingFunctionType::Steps,
+            mX1: 0.,
+            mY1: 0.,
+            mX2: 0.,
+            mY2: 0.,
+            mSteps: steps,
+            mStepPosition: match position {
+                StepPosition::Start => ServoStepPosition::Start,
+                StepPosition::End => ServoStepPosition::End,
+                StepPosition::JumpNone => ServoStepPosition::JumpNone,
+                StepPosition::JumpBoth => ServoStepPosition::JumpBoth,
+            },
+        },
+    }
+}
+
+/// The timing function Gecko handed back, undoing timing_function_to_ffi.
+fn timing_function_from_ffi(timing_function: &ServoTimingFunction) -> TransitionTimingFunction {
+    match timing_function.mType {
+        ServoTimingFunctionType::Linear => TransitionTimingFunction::Linear,
+        ServoTimingFunctionType::CubicBezier => {
+            TransitionTimingFunction::CubicBezier(Point2D::new(timing_function.mX1, timing_function.mY1),
+                                                  Point2D::new(timing_function.mX2, timing_function.mY2))
+        }
+        ServoTimingFunctionType::Steps => {
+            let position = match timing_function.mStepPosition {
+                ServoStepPosition::Start => StepPosition::Start,
+                ServoStepPosition::End => StepPosition::End,
+                ServoStepPosition::JumpNone => StepPosition::JumpNone,
+                ServoStepPosition::JumpBoth => StepPosition::JumpBoth,
+            };
+            TransitionTimingFunction::Steps(timing_function.mSteps, position)
+        }
+    }
+}
+
+/// How close to the input progress Servo_EasingEvaluateAt finds the point on a curve. The
+/// compositor compares against that to the millionth, so this is well below it.
+const EASING_EPSILON: f64 = 1e-7;
+
+servo_function! {
+    /// Parses |value| as a single timing function, like an item of transition-timing-function
+    /// or animation-timing-function, into |out|. Returns false if it's not one.
+    fn Servo_ParseEasing(value: *const u8, length: u32, out: *mut ServoTimingFunction) -> bool [on_panic: false] {
+        return_if_null!(out; false);
+        let value = unsafe { str_from_raw(value, length) };
+        let mut parser = Parser::new(value);
+        match transition_timing_function::parse_one(&mut parser) {
+            Ok(timing_function) if parser.is_exhausted() => {
+                unsafe { *out = timing_function_to_ffi(timing_function) };
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+servo_function! {
+    /// The output progress of |timing| for the input |progress|, which may be outside [0, 1]
+    /// for animations whose keyframes overshoot. |before_flag| says whether the animation is
+    /// in its before phase, which decides which side of a jump a step lands on.
+    fn Servo_EasingEvaluateAt(timing: *const ServoTimingFunction,
+                              progress: f64,
+                              before_flag: bool) -> f64 [on_panic: progress] {
+        return_if_null!(timing; progress);
+        let timing_function = timing_function_from_ffi(unsafe { &*timing });
+        timing_function.calculate_output(progress, before_flag, EASING_EPSILON)
+    }
+}
+
+servo_function! {
+    /// Appends the keyframes of the @keyframes rule that defines the animation called |name|
+    /// to |keyframes|, sorted by offset, and returns whether there was such a rule. Each
+    /// keyframe hands Gecko a strong reference to a declaration block, which it must release.
+    fn Servo_StyleSet_GetKeyframesForName(raw_data: *mut RawServoStyleSet,
+                                          name: *mut nsIAtom,
+                                          keyframes: *mut RawGeckoKeyframeList) -> bool [on_panic: false] {
+        return_if_null!(raw_data, name, keyframes; false);
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let name = unsafe { atom_from_gecko(name) };
+        let rule = match find_keyframes_rule(data.enabled_stylesheets(), &data.stylist.device, &name) {
+            Some(rule) => rule,
+            None => return false,
+        };
+        for step in rule.steps() {
+            let timing_function = step.timing_function.map(timing_function_to_ffi);
+            let block = GeckoDeclarationBlock::new(PropertyDeclarationBlock {
+                important: Arc::new(vec![]),
+                normal: step.declarations,
+                shorthands: vec![],
+            });
+            unsafe {
+                Gecko_AppendKeyframe(keyframes, step.offset,
+                                     timing_function.as_ref().map_or(ptr::null(), |f| f as *const _),
+                                     Strong::from_arc(block));
+            }
+        }
+        true
+    }
+}
+
+servo_function! {
+    /// Returns the @counter-style rule that defines the counter style called |name|, or null
+    /// if no rule does. The rule is only borrowed from the sheet it's in: Gecko must take a
+    /// reference of its own to keep it past the next change to the style set's sheets.
+    fn Servo_StyleSet_GetCounterStyleRule(raw_data: *mut RawServoStyleSet, name: *mut nsIAtom)
+                                          -> *mut RawServoCounterStyleRule [on_panic: ptr::null_mut()] {
+        return_if_null!(raw_data, name; ptr::null_mut());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let name = unsafe { atom_from_gecko(name) };
+        find_counter_style_rule(data.enabled_stylesheets(), &data.stylist.device, &name)
+            .map_or(ptr::null_mut(), |rule| Borrowed::from_arc(rule).as_ptr())
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_AddRef(rule: *mut RawServoCounterStyleRule) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<CounterStyleRule>(rule) };
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_Release(rule: *mut RawServoCounterStyleRule) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<CounterStyleRule>(rule) };
+    }
+}
+
+/// Runs |cb| with the @counter-style rule |rule| points to, or returns |default| if it's null.
+fn with_counter_style_rule<F, Output>(rule: *mut RawServoCounterStyleRule, default: Output, cb: F) -> Output
+                                      where F: FnOnce(&CounterStyleRule) -> Output {
+    ArcHelpers::<RawServoCounterStyleRule, CounterStyleRule>::maybe_with(rule, |rule| cb(&**rule)).unwrap_or(default)
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetName(rule: *mut RawServoCounterStyleRule) -> *mut nsIAtom [on_panic: ptr::null_mut()] {
+        with_counter_style_rule(rule, ptr::null_mut(), |rule| atom_to_gecko(&rule.name))
+    }
+}
+
+// Keep these in sync with NS_STYLE_COUNTER_SYSTEM_* in layout/style/nsStyleConsts.h.
+const COUNTER_SYSTEM_CYCLIC: u8 = 0;
+const COUNTER_SYSTEM_NUMERIC: u8 = 1;
+const COUNTER_SYSTEM_ALPHABETIC: u8 = 2;
+const COUNTER_SYSTEM_SYMBOLIC: u8 = 3;
+const COUNTER_SYSTEM_ADDITIVE: u8 = 4;
+const COUNTER_SYSTEM_FIXED: u8 = 5;
+const COUNTER_SYSTEM_EXTENDS: u8 = 6;
+
+servo_function! {
+    /// Returns the rule's system. For `fixed`, |out_first_symbol_value| gets the value of the
+    /// first symbol, and for `extends`, |out_extended| gets the name of the extended style.
+    fn Servo_CounterStyleRule_GetSystem(rule: *mut RawServoCounterStyleRule,
+                                        out_first_symbol_value: *mut i32,
+                                        out_extended: *mut *mut nsIAtom) -> u8 [on_panic: COUNTER_SYSTEM_SYMBOLIC] {
+        return_if_null!(out_first_symbol_value, out_extended; COUNTER_SYSTEM_SYMBOLIC);
+        with_counter_style_rule(rule, COUNTER_SYSTEM_SYMBOLIC, |rule| match rule.system {
+            System::Cyclic => COUNTER_SYSTEM_CYCLIC,
+            System::Numeric => COUNTER_SYSTEM_NUMERIC,
+            System::Alphabetic => COUNTER_SYSTEM_ALPHABETIC,
+            System::Symbolic => COUNTER_SYSTEM_SYMBOLIC,
+            System::Additive => COUNTER_SYSTEM_ADDITIVE,
+            System::Fixed(first_symbol_value) => {
+                unsafe { *out_first_symbol_value = first_symbol_value };
+                COUNTER_SYSTEM_FIXED
+            }
+            System::Extends(ref extended) => {
+                unsafe { *out_extended = atom_to_gecko(extended) };
+                COUNTER_SYSTEM_EXTENDS
+            }
+        })
+    }
+}
+
+/// Writes |symbol| to |result| if there is one, and returns whether there was.
+fn write_symbol(symbol: Option<&Symbol>, result: *mut nsString) -> bool {
+    match symbol {
+        Some(symbol) if !result.is_null() => {
+            write_to_string(result, symbol.text());
+            true
+        }
+        _ => false,
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetSymbolCount(rule: *mut RawServoCounterStyleRule) -> u32 [on_panic: 0] {
+        with_counter_style_rule(rule, 0, |rule| rule.symbols.len() as u32)
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetSymbolAt(rule: *mut RawServoCounterStyleRule, index: u32,
+                                          result: *mut nsString) -> bool [on_panic: false] {
+        with_counter_style_rule(rule, false, |rule| write_symbol(rule.symbols.get(index as usize), result))
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetAdditiveSymbolCount(rule: *mut RawServoCounterStyleRule) -> u32 [on_panic: 0] {
+        with_counter_style_rule(rule, 0, |rule| rule.additive_symbols.len() as u32)
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetAdditiveSymbolAt(rule: *mut RawServoCounterStyleRule, index: u32,
+                                                  out_weight: *mut u32,
+                                                  result: *mut nsString) -> bool [on_panic: false] {
+        return_if_null!(out_weight; false);
+        with_counter_style_rule(rule, false, |rule| match rule.additive_symbols.get(index as usize) {
+            Some(&(weight, ref symbol)) => {
+                unsafe { *out_weight = weight };
+                write_symbol(Some(symbol), result)
+            }
+            None => false,
+        })
+    }
+}
+
+servo_function! {
+    /// Returns whether the rule gives `negative`. |out_after| is left alone if it gives only
+    /// the symbol before.
+    fn Servo_CounterStyleRule_GetNegative(rule: *mut RawServoCounterStyleRule,
+                                          out_before: *mut nsString,
+                                          out_after: *mut nsString) -> bool [on_panic: false] {
+        with_counter_style_rule(rule, false, |rule| match rule.negative {
+            Some((ref before, ref after)) => {
+                write_symbol(after.as_ref(), out_after);
+                write_symbol(Some(before), out_before)
+            }
+            None => false,
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetPrefix(rule: *mut RawServoCounterStyleRule,
+                                        result: *mut nsString) -> bool [on_panic: false] {
+        with_counter_style_rule(rule, false, |rule| write_symbol(rule.prefix.as_ref(), result))
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetSuffix(rule: *mut RawServoCounterStyleRule,
+                                        result: *mut nsString) -> bool [on_panic: false] {
+        with_counter_style_rule(rule, false, |rule| write_symbol(rule.suffix.as_ref(), result))
+    }
+}
+
+servo_function! {
+    /// Returns how many ranges the rule gives, which is 0 for `auto`.
+    fn Servo_CounterStyleRule_GetRangeCount(rule: *mut RawServoCounterStyleRule) -> u32 [on_panic: 0] {
+        with_counter_style_rule(rule, 0, |rule| rule.range.len() as u32)
+    }
+}
+
+servo_function! {
+    /// Gets the bounds of the range at |index|. Infinite bounds are i32::MIN and i32::MAX.
+    fn Servo_CounterStyleRule_GetRangeAt(rule: *mut RawServoCounterStyleRule, index: u32,
+                                         out_lower: *mut i32, out_upper: *mut i32) -> bool [on_panic: false] {
+        return_if_null!(out_lower, out_upper; false);
+        with_counter_style_rule(rule, false, |rule| match rule.range.get(index as usize) {
+            Some(&(lower, upper)) => {
+                unsafe {
+                    *out_lower = lower.unwrap_or(i32::MIN);
+                    *out_upper = upper.unwrap_or(i32::MAX);
+                }
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_CounterStyleRule_GetPad(rule: *mut RawServoCounterStyleRule, out_width: *mut u32,
+                                     result: *mut nsString) -> bool [on_panic: false] {
+        return_if_null!(out_width; false);
+        with_counter_style_rule(rule, false, |rule| match rule.pad {
+            Some((width, ref symbol)) => {
+                unsafe { *out_width = width };
+                write_symbol(Some(symbol), result)
+            }
+            None => false,
+        })
+    }
+}
+
+servo_function! {
+    /// Returns the name of the fallback style, or null if the rule doesn't give one.
+    fn Servo_CounterStyleRule_GetFallback(rule: *mut RawServoCounterStyleRule)
+                                          -> *mut nsIAtom [on_panic: ptr::null_mut()] {
+        with_counter_style_rule(rule, ptr::null_mut(), |rule| {
+            rule.fallback.as_ref().map_or(ptr::null_mut(), atom_to_gecko)
+        })
+    }
+}
+
+// Keep these in sync with ServoCounterSpeakAs in ServoBindings.h.
+const COUNTER_SPEAK_AS_AUTO: u8 = 0;
+const COUNTER_SPEAK_AS_BULLETS: u8 = 1;
+const COUNTER_SPEAK_AS_NUMBERS: u8 = 2;
+const COUNTER_SPEAK_AS_WORDS: u8 = 3;
+const COUNTER_SPEAK_AS_SPELL_OUT: u8 = 4;
+const COUNTER_SPEAK_AS_COUNTER_STYLE: u8 = 5;
+
+servo_function! {
+    /// Returns the rule's `speak-as`, which is `auto` if it doesn't give one. If it names a
+    /// counter style, |out_counter_style| gets the name.
+    fn Servo_CounterStyleRule_GetSpeakAs(rule: *mut RawServoCounterStyleRule,
+                                         out_counter_style: *mut *mut nsIAtom) -> u8 [on_panic: COUNTER_SPEAK_AS_AUTO] {
+        return_if_null!(out_counter_style; COUNTER_SPEAK_AS_AUTO);
+        with_counter_style_rule(rule, COUNTER_SPEAK_AS_AUTO, |rule| match rule.speak_as {
+            None | Some(SpeakAs::Auto) => COUNTER_SPEAK_AS_AUTO,
+            Some(SpeakAs::Bullets) => COUNTER_SPEAK_AS_BULLETS,
+            Some(SpeakAs::Numbers) => COUNTER_SPEAK_AS_NUMBERS,
+            Some(SpeakAs::Words) => COUNTER_SPEAK_AS_WORDS,
+            Some(SpeakAs::SpellOut) => COUNTER_SPEAK_AS_SPELL_OUT,
+            Some(SpeakAs::CounterStyle(ref name)) => {
+                unsafe { *out_counter_style = atom_to_gecko(name) };
+                COUNTER_SPEAK_AS_COUNTER_STYLE
+            }
+        })
+    }
+}
+
+// Keep these in sync with ServoPageFlags in ServoBindings.h.
+const PAGE_FIRST: u8 = 1 << 0;
+const PAGE_LEFT: u8 = 1 << 1;
+const PAGE_BLANK: u8 = 1 << 2;
+
+servo_function! {
+    /// Cascades the @page rules that apply to a page for printing. The page is named
+    /// |page_name|, or unnamed if it's null, and |page_flags| tells whether it's the first
+    /// page, a left page and a blank page. |out_block| gets the winning margin declarations
+    /// and |out_size| the winning size, in app units if it's explicit.
+    fn Servo_StyleSet_ResolvePageStyle(raw_data: *mut RawServoStyleSet,
+                                       page_name: *mut nsIAtom,
+                                       page_flags: u8,
+                                       out_block: *mut RawServoDeclarationBlockStrong,
+                                       out_size: *mut ServoPageSize) -> () [on_panic: ()] {
+        return_if_null!(raw_data, out_block, out_size; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let page = Page {
+            name: if page_name.is_null() { None } else { Some(unsafe { atom_from_gecko(page_name) }) },
+            first: page_flags & PAGE_FIRST != 0,
+            left: page_flags & PAGE_LEFT != 0,
+            blank: page_flags & PAGE_BLANK != 0,
+        };
+        let style = resolve_page_style(data.enabled_stylesheets(), &data.stylist.device, &page);
+
+        // Declarations are stored in reverse order.
+        let mut declarations = style.declarations;
+        declarations.reverse();
+        let block = GeckoDeclarationBlock::new(PropertyDeclarationBlock {
+            important: Arc::new(vec![]),
+            normal: Arc::new(declarations),
+            shorthands: vec![],
+        });
+        let (kind, width, height) = match style.size {
+            PageSize::Auto => (ServoPageSizeKind::Auto, Au(0), Au(0)),
+            PageSize::Orientation(PageOrientation::Portrait) => (ServoPageSizeKind::Portrait, Au(0), Au(0)),
+            PageSize::Orientation(PageOrientation::Landscape) => (ServoPageSizeKind::Landscape, Au(0), Au(0)),
+            PageSize::Explicit(width, height) => (ServoPageSizeKind::Explicit, width, height),
+        };
+        unsafe {
+            *out_block = Strong::from_arc(block);
+            *out_size = ServoPageSize { mKind: kind, mWidth: width.0, mHeight: height.0 };
+        }
+    }
+}
+
+// Keep these in sync with NS_FONT_STYLE_* and NS_FONT_STRETCH_* in gfx/src/nsFont.h.
+const NS_FONT_STYLE_NORMAL: u8 = 0;
+const NS_FONT_STYLE_ITALIC: u8 = 1;
+const NS_FONT_STYLE_OBLIQUE: u8 = 2;
+
+fn font_style_to_gecko(style: FontStyle) -> u8 {
+    match style {
+        FontStyle::normal => NS_FONT_STYLE_NORMAL,
+        FontStyle::italic => NS_FONT_STYLE_ITALIC,
+        FontStyle::oblique => NS_FONT_STYLE_OBLIQUE,
+    }
+}
+
+fn font_stretch_to_gecko(stretch: FontStretch) -> i16 {
+    match stretch {
+        FontStretch::ultra_condensed => -4,
+        FontStretch::extra_condensed => -3,
+        FontStretch::condensed => -2,
+        FontStretch::semi_condensed => -1,
+        FontStretch::normal => 0,
+        FontStretch::semi_expanded => 1,
+        FontStretch::expanded => 2,
+        FontStretch::extra_expanded => 3,
+        FontStretch::ultra_expanded => 4,
+    }
+}
+
+servo_function! {
+    /// Appends every @font-face rule that applies to the document to |rules|, in cascade
+    /// order, along with the sheet it came from. Gecko takes its own reference to the sheet
+    /// if it needs one.
+    fn Servo_StyleSet_GetFontFaceRules(raw_data: *mut RawServoStyleSet,
+                                       rules: *mut RawGeckoFontFaceRuleList) -> () [on_panic: ()] {
+        return_if_null!(raw_data, rules; ());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        for (sheet, font_face) in effective_font_face_rules(data.enabled_stylesheets(), &data.stylist.device) {
+            let raw_sheet: *mut RawServoStyleSheet = Borrowed::from_arc(&sheet).as_ptr();
+            let family = font_face.family.name();
+            let rule = unsafe {
+                Gecko_AppendFontFaceRule(rules, raw_sheet, family.as_ptr(), family.len() as u32,
+                                         font_face.weight as u16, font_style_to_gecko(font_face.style),
+                                         font_stretch_to_gecko(font_face.stretch))
+            };
+
+            for source in &font_face.sources {
+                match *source {
+                    Source::Url(ref url_source) => {
+                        let url = url_source.url.serialize();
+                        unsafe { Gecko_FontFaceRule_AppendURLSource(rule, url.as_ptr(), url.len() as u32) };
+                        for hint in &url_source.format_hints {
+                            unsafe { Gecko_FontFaceRule_AppendFormatHint(rule, hint.as_ptr(), hint.len() as u32) };
+                        }
+                    }
+                    Source::Local(ref name) => {
+                        let name = name.name();
+                        unsafe { Gecko_FontFaceRule_AppendLocalSource(rule, name.as_ptr(), name.len() as u32) };
+                    }
+                }
+            }
+            for range in &font_face.unicode_range {
+                unsafe { Gecko_FontFaceRule_AppendUnicodeRange(rule, range.start, range.end) };
+            }
+        }
+    }
+}
+
+servo_function! {
+    /// Disables or re-enables a sheet. Style sets holding it notice the change on their next
+    /// restyle. Shared sheets are left alone, since other documents use them too.
+    fn Servo_StyleSheet_SetDisabled(raw_sheet: *mut RawServoStyleSheet, disabled: bool) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; ());
+        Helpers::with(raw_sheet, |sheet| {
+            if sheet.is_frozen() {
+                warn!("Not disabling or enabling a shared sheet");
+                return;
+            }
+            sheet.set_disabled(disabled)
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSheet_GetDisabled(raw_sheet: *mut RawServoStyleSheet) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; false);
+        Helpers::with(raw_sheet, |sheet| sheet.disabled())
+    }
+}
+
+servo_function! {
+    /// Returns one of the `StyleSheetOrigin` values.
+    fn Servo_StyleSheet_GetOrigin(raw_sheet: *mut RawServoStyleSheet) -> u8 [on_panic: 0] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; 0);
+        Helpers::with(raw_sheet, |sheet| origin_to_gecko(sheet.origin) as u8)
+    }
+}
+
+servo_function! {
+    /// Returns false, leaving the title alone, if the sheet is shared.
+    fn Servo_StyleSheet_SetTitle(raw_sheet: *mut RawServoStyleSheet,
+                                 title: *const u8, length: u32) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, title; false);
+        let title = unsafe { str_from_raw(title, length) };
+        Helpers::with(raw_sheet, |sheet| sheet.set_title(title).is_ok())
+    }
+}
+
+servo_function! {
+    fn Servo_StyleSheet_GetTitle(raw_sheet: *mut RawServoStyleSheet, result: *mut nsString) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, result; ());
+        let title = Helpers::with(raw_sheet, |sheet| sheet.title());
+        write_to_string(result, &title);
+    }
+}
+
+servo_function! {
+    /// Describes the sheet's origin, whether it's disabled or shared, its title and its media
+    /// list, for logging.
+    fn Servo_StyleSheet_GetDebugInfo(raw_sheet: *mut RawServoStyleSheet,
+                                     result: *mut nsString) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet, result; ());
+        let info = Helpers::with(raw_sheet, |sheet| sheet.debug_info());
+        write_to_string(result, &info);
+    }
+}
+
+servo_function! {
+    fn Servo_MediaList_Parse(bytes: *const u8, length: u32) -> RawServoMediaListStrong [on_panic: abort()] {
+        let value = unsafe { str_from_raw(bytes, length) };
+        let list = Arc::new(parse_media_query_list(&mut Parser::new(value)));
+        Strong::from_arc(list)
+    }
+}
+
+servo_function! {
+    fn Servo_AddRefMediaList(list: *mut RawServoMediaList) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<MediaQueryList>(list) };
+    }
+}
+
+servo_function! {
+    fn Servo_ReleaseMediaList(list: *mut RawServoMediaList) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<MediaQueryList>(list) };
+    }
+}
+
+servo_function! {
+    /// Sets the media list a sheet applies to, or clears it if `raw_list` is null. Gecko has to
+    /// tell the style sets holding the sheet through Servo_StyleSet_NoteStyleSheetsChanged.
+    fn Servo_StyleSheet_SetMediaList(raw_sheet: *mut RawServoStyleSheet,
+                                     raw_list: *mut RawServoMediaList) -> () [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoStyleSheet, Stylesheet>;
+        return_if_null!(raw_sheet; ());
+        let media = ArcHelpers::<RawServoMediaList, MediaQueryList>::maybe_with(raw_list, |list| (**list).clone());
+        Helpers::with(raw_sheet, |sheet| {
+            // Sheets are only mutated on the main thread, and the lock keeps restyles, which
+            // hold it for reading, from running meanwhile.
+            let _guard = GlobalStyleData::get().shared_lock.write();
+            sheet.set_media(media);
+        })
+    }
+}
+
+servo_function! {
+    /// Attaches a sheet Gecko loaded for an `@import` rule to it. Refuses, returning false, if the
+    /// sheet imports the rule itself, directly or not, since the cycle would never end, or if the
+    /// sheet holding the rule is frozen. Gecko has to tell the style sets holding the parent sheet
+    /// through Servo_StyleSet_NoteStyleSheetsChanged.
+    fn Servo_StyleSheet_SetImportedSheet(raw_import: *mut RawServoImportRule,
+                                         raw_sheet: *mut RawServoStyleSheet) -> bool [on_panic: abort()] {
+        type Helpers = ArcHelpers<RawServoImportRule, ImportRule>;
+        return_if_null!(raw_import, raw_sheet; false);
+        let sheet = ArcHelpers::<RawServoStyleSheet, Stylesheet>::with(raw_sheet, |sheet| sheet.clone());
+        Helpers::with(raw_import, |import| {
+            if sheet.contains_import_rule(import) {
+                warn!("Ignoring @import {} since it would import itself", import.url.serialize());
+                return false;
+            }
+            // Like sheets, import rules are only mutated on the main thread, with the lock
+            // held for writing.
+            let _guard = GlobalStyleData::get().shared_lock.write();
+            match import.set_stylesheet(sheet) {
+                Ok(()) => true,
+                Err(_) => {
+                    warn!("Ignoring the sheet for @import {} since its parent is frozen", import.url.serialize());
+                    false
+                }
+            }
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_AddRefImportRule(import: *mut RawServoImportRule) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<ImportRule>(import) };
+    }
+}
+
+servo_function! {
+    fn Servo_ReleaseImportRule(import: *mut RawServoImportRule) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<ImportRule>(import) };
+    }
+}
+
+servo_function! {
+    fn Servo_AddRefStyleSheet(sheet: *mut RawServoStyleSheet) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<Stylesheet>(sheet) };
+    }
+}
+
+servo_function! {
+    fn Servo_ReleaseStyleSheet(sheet: *mut RawServoStyleSheet) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<Stylesheet>(sheet) };
+    }
+}
+
+/// A parsed style attribute. Gecko caches one of these on the element and only
+/// reparses it when the attribute changes.
+pub struct GeckoDeclarationBlock {
+    /// Behind the lock in GlobalStyleData, since restyles read the blocks elements have
+    /// while the CSSOM may want to change them.
+    pub declarations: Locked<Option<PropertyDeclarationBlock>>,
+    /// The style rule the block gives the declarations of, which changes to it go to.
+    rule: Option<Arc<GeckoStyleRule>>,
+}
+
+impl GeckoDeclarationBlock {
+    pub fn new(declarations: PropertyDeclarationBlock) -> Arc<GeckoDeclarationBlock> {
+        let shared_lock = &GlobalStyleData::get().shared_lock;
+        Arc::new(GeckoDeclarationBlock {
+            declarations: Locked::new(Some(declarations), shared_lock),
+            rule: None,
+        })
+    }
+
+    fn for_rule(declarations: PropertyDeclarationBlock, rule: Arc<GeckoStyleRule>) -> Arc<GeckoDeclarationBlock> {
+        let shared_lock = &GlobalStyleData::get().shared_lock;
+        Arc::new(GeckoDeclarationBlock {
+            declarations: Locked::new(Some(declarations), shared_lock),
+            rule: Some(rule),
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_ParseStyleAttribute(bytes: *const u8, length: u32)
+         -> RawServoDeclarationBlockStrong [on_panic: abort()] {
+        let value = unsafe { str_from_raw(bytes, length) };
+        // FIXME(bholley): Real base URL.
+        let base_url = &GlobalStyleData::get().dummy_url;
+        let declarations = parse_style_attribute(value, base_url, Box::new(StdoutErrorReporter));
+        Strong::from_arc(GeckoDeclarationBlock::new(declarations))
+    }
+}
+
+servo_function! {
+    fn Servo_AddRefDeclarationBlock(declarations: *mut RawServoDeclarationBlock) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<GeckoDeclarationBlock>(declarations) };
+    }
+}
+
+servo_function! {
+    fn Servo_ReleaseDeclarationBlock(declarations: *mut RawServoDeclarationBlock) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<GeckoDeclarationBlock>(declarations) };
+    }
+}
+
+fn with_declarations<F, Output>(raw: *mut RawServoDeclarationBlock, cb: F) -> Output
+                                where F: FnOnce(&PropertyDeclarationBlock) -> Output {
+    type Helpers = ArcHelpers<RawServoDeclarationBlock, GeckoDeclarationBlock>;
+    Helpers::with(raw, |block| {
+        let guard = block.declarations.shared_lock().read();
+        match *block.declarations.read_with(&guard) {
+            Some(ref declarations) => cb(declarations),
+            None => cb(&PropertyDeclarationBlock {
+                important: Arc::new(vec![]),
+                normal: Arc::new(vec![]),
+                shorthands: vec![],
+            }),
+        }
+    })
+}
+
+fn with_declarations_mut<F, Output>(raw: *mut RawServoDeclarationBlock, cb: F) -> Output
+                                    where F: FnOnce(&mut PropertyDeclarationBlock) -> Output {
+    type Helpers = ArcHelpers<RawServoDeclarationBlock, GeckoDeclarationBlock>;
+    Helpers::with(raw, |block| {
+        let mut guard = block.declarations.shared_lock().write();
+        let declarations = block.declarations.write_with(&mut guard);
+        if declarations.is_none() {
+            *declarations = Some(PropertyDeclarationBlock {
+                important: Arc::new(vec![]),
+                normal: Arc::new(vec![]),
+                shorthands: vec![],
+            });
+        }
+        let result = cb(declarations.as_mut().unwrap());
+        if let Some(ref rule) = block.rule {
+            rule.set_declarations(declarations.as_ref().unwrap());
+        }
+        result
+    })
+}
+
+fn find_declaration<'a>(declarations: &'a PropertyDeclarationBlock, property: &str)
+                        -> Option<&'a PropertyDeclaration> {
+    declarations.important.iter().chain(declarations.normal.iter()).find(|d| d.matches(property))
+}
+
+fn remove_declaration(declarations: &mut PropertyDeclarationBlock, property: &str) {
+    declarations.forget_written_shorthands(property);
+    for list in &mut [&mut declarations.normal, &mut declarations.important] {
+        if list.iter().any(|d| d.matches(property)) {
+            Arc::make_mut(list).retain(|d| !d.matches(property));
+        }
+    }
+}
+
+/// The value of |property| in the declarations, or the empty string if they don't set it. A
+/// shorthand has one if they set all of its longhands with the same importance.
+fn property_value(declarations: &PropertyDeclarationBlock, property: &str) -> String {
+    if let Some(shorthand) = Shorthand::from_name(property) {
+        for list in &[&declarations.important, &declarations.normal] {
+            let longhands = shorthand.longhands().iter().map(|longhand| {
+                list.iter().find(|d| d.matches(longhand))
+            }).collect::<Option<Vec<_>>>();
+            if let Some(longhands) = longhands {
+                return serialize_shorthand(shorthand, &longhands)
+            }
+        }
+        return String::new()
+    }
+    find_declaration(declarations, property).map_or(String::new(), |d| d.value())
+}
+
+fn write_to_string(string: *mut nsString, value: &str) {
+    unsafe { Gecko_Utf8SliceToString(string, value.as_ptr(), value.len() as u32) };
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertyvalue
+    fn Servo_DeclarationBlock_GetPropertyValue(declarations: *mut RawServoDeclarationBlock,
+                                               property: *const u8,
+                                               property_length: u32,
+                                               value: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(declarations, value; ());
+        let property = unsafe { str_from_raw(property, property_length) };
+        let result = with_declarations(declarations, |declarations| property_value(declarations, property));
+        write_to_string(value, &result);
+    }
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-getpropertypriority
+    fn Servo_DeclarationBlock_GetPropertyIsImportant(declarations: *mut RawServoDeclarationBlock,
+                                                     property: *const u8,
+                                                     property_length: u32) -> bool [on_panic: false] {
+        return_if_null!(declarations; false);
+        let property = unsafe { str_from_raw(property, property_length) };
+        with_declarations(declarations, |declarations| {
+            let is_important = |name: &str| declarations.important.iter().any(|d| d.matches(name));
+            // A shorthand is only important if all of its longhands are.
+            match Shorthand::from_name(property) {
+                Some(shorthand) => shorthand.longhands().iter().all(|longhand| is_important(longhand)),
+                None => is_important(property),
+            }
+        })
+    }
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-setproperty
+    fn Servo_DeclarationBlock_SetProperty(declarations: *mut RawServoDeclarationBlock,
+                                          property: *const u8,
+                                          property_length: u32,
+                                          value: *const u8,
+                                          value_length: u32,
+                                          is_important: bool) -> bool [on_panic: false] {
+        return_if_null!(declarations; false);
+        let property = unsafe { str_from_raw(property, property_length) };
+        let value = unsafe { str_from_raw(value, value_length) };
+
+        // FIXME(bholley): Real base URL.
+        let base_url = &GlobalStyleData::get().dummy_url;
+        let parsed = match parse_one_declaration(property, value, base_url, Box::new(StdoutErrorReporter)) {
+            Ok(ref parsed) if parsed.is_empty() => return false,
+            Ok(parsed) => parsed,
+            Err(()) => return false,
+        };
+
+        with_declarations_mut(declarations, |declarations| {
+            for declaration in parsed {
+                let name = declaration.name();
+                if let PropertyDeclarationName::Longhand(longhand) = name {
+                    declarations.forget_written_shorthands(longhand);
+                }
+                {
+                    // A property lives in at most one of the two lists.
+                    let other = if is_important { &mut declarations.normal } else { &mut declarations.important };
+                    if other.iter().any(|d| d.name() == name) {
+                        Arc::make_mut(other).retain(|d| d.name() != name);
+                    }
+                }
+
+                let list = Arc::make_mut(if is_important {
+                    &mut declarations.important
+                } else {
+                    &mut declarations.normal
+                });
+                match list.iter().position(|d| d.name() == name) {
+                    Some(index) => list[index] = declaration,
+                    // Declarations are stored in reverse order.
+                    None => list.insert(0, declaration),
+                }
+            }
+            if let Some(shorthand) = Shorthand::from_name(property) {
+                declarations.shorthands.push((shorthand, is_important));
+            }
+        });
+        true
+    }
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-removeproperty
+    fn Servo_DeclarationBlock_RemoveProperty(declarations: *mut RawServoDeclarationBlock,
+                                             property: *const u8,
+                                             property_length: u32) -> () [on_panic: ()] {
+        return_if_null!(declarations; ());
+        let property = unsafe { str_from_raw(property, property_length) };
+        with_declarations_mut(declarations, |declarations| {
+            match Shorthand::from_name(property) {
+                Some(shorthand) => {
+                    for longhand in shorthand.longhands() {
+                        remove_declaration(declarations, longhand);
+                    }
+                }
+                None => remove_declaration(declarations, property),
+            }
+        });
+    }
+}
+
+servo_function! {
+    /// Sets `property` to the identifier `value` in the declarations, for presentational
+    /// hints Gecko maps from attributes without going through the parser. Only `-x-lang`,
+    /// which the lang attributes map to, is supported so far.
+    fn Servo_DeclarationBlock_SetIdentStringValue(declarations: *mut RawServoDeclarationBlock,
+                                                  property: nsCSSProperty,
+                                                  value: *mut nsIAtom) -> () [on_panic: ()] {
+        return_if_null!(declarations, value; ());
+        let (name, declaration) = match property {
+            nsCSSProperty::eCSSProperty__x_lang => {
+                platform_values::note_language(value);
+                let language = unsafe { atom_from_gecko(value) };
+                ("-x-lang", PropertyDeclaration::XLang(DeclaredValue::Value(_x_lang::SpecifiedValue(Some(language)))))
+            }
+            _ => {
+                warn!("Servo_DeclarationBlock_SetIdentStringValue doesn't support {:?}", property);
+                return
+            }
+        };
+        with_declarations_mut(declarations, |declarations| {
+            // Internal properties all have the same PropertyDeclarationName, so go by matches().
+            let list = Arc::make_mut(&mut declarations.normal);
+            match list.iter().position(|d| d.matches(name)) {
+                Some(index) => list[index] = declaration,
+                // Declarations are stored in reverse order.
+                None => list.insert(0, declaration),
+            }
+        });
+    }
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-length
+    fn Servo_DeclarationBlock_Count(declarations: *mut RawServoDeclarationBlock) -> u32 [on_panic: 0] {
+        return_if_null!(declarations; 0);
+        with_declarations(declarations, |declarations| {
+            (declarations.normal.len() + declarations.important.len()) as u32
+        })
+    }
+}
+
+servo_function! {
+    // https://dev.w3.org/csswg/cssom/#dom-cssstyledeclaration-item
+    fn Servo_DeclarationBlock_GetNthProperty(declarations: *mut RawServoDeclarationBlock,
+                                             index: u32,
+                                             result: *mut nsString) -> bool [on_panic: false] {
+        return_if_null!(declarations, result; false);
+        let name = with_declarations(declarations, |declarations| {
+            // Declarations are stored in reverse order.
+            declarations.normal.iter().rev()
+                        .chain(declarations.important.iter().rev())
+                        .nth(index as usize)
+                        .map(|d| d.name().to_string())
+        });
+        match name {
+            Some(name) => {
+                write_to_string(result, &name);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+servo_function! {
+    // https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-csstext
+    fn Servo_DeclarationBlock_GetCssText(declarations: *mut RawServoDeclarationBlock,
+                                         result: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(declarations, result; ());
+        let css_text = with_declarations(declarations, |declarations| declarations.to_css_string());
+        write_to_string(result, &css_text);
+    }
+}
+
+servo_function! {
+    /// Whether any of the declarations is important, which Gecko asks before serializing a
+    /// style attribute.
+    fn Servo_DeclarationBlock_HasImportant(declarations: *mut RawServoDeclarationBlock) -> bool [on_panic: false] {
+        return_if_null!(declarations; false);
+        with_declarations(declarations, |declarations| declarations.has_important())
+    }
+}
+
+servo_function! {
+    /// Puts the properties the declarations set in |out_ids|, in the order GetNthProperty gives
+    /// them in, for Gecko to go through without asking for them one at a time. Custom properties
+    /// are all eCSSPropertyExtra_variable, and internal ones are left out. Returns how many
+    /// there are, even if that's more than the |capacity| of |out_ids|, so that Gecko can ask
+    /// again with room for all of them.
+    fn Servo_DeclarationBlock_GetPropertyIds(declarations: *mut RawServoDeclarationBlock,
+                                             out_ids: *mut nsCSSProperty,
+                                             capacity: u32) -> u32 [on_panic: 0] {
+        return_if_null!(declarations; 0);
+        let ids = with_declarations(declarations, |declarations| {
+            // Declarations are stored in reverse order.
+            declarations.normal.iter().rev().chain(declarations.important.iter().rev()).filter_map(|d| {
+                match d.name() {
+                    PropertyDeclarationName::Longhand(name) => longhand_to_gecko(name),
+                    PropertyDeclarationName::Custom(_) => Some(nsCSSProperty::eCSSPropertyExtra_variable),
+                    PropertyDeclarationName::Internal => None,
+                }
+            }).collect::<Vec<_>>()
+        });
+        if !out_ids.is_null() {
+            for (index, &id) in ids.iter().take(capacity as usize).enumerate() {
+                unsafe { *out_ids.offset(index as isize) = id };
+            }
+        }
+        ids.len() as u32
+    }
+}
+
+servo_function! {
+    /// Writes the value the declarations give |property| to |result|, like GetPropertyValue but
+    /// for a property Gecko has the id of. Longhands come out as they were specified, and
+    /// shorthands are put together from their longhands, as GetCssText writes them.
+    fn Servo_DeclarationBlock_SerializeOneValue(declarations: *mut RawServoDeclarationBlock,
+                                                property: nsCSSProperty,
+                                                result: *mut nsString) -> () [on_panic: ()] {
+        return_if_null!(declarations, result; ());
+        let value = match property_name_from_gecko(property) {
+            Some(name) => with_declarations(declarations, |declarations| property_value(declarations, name)),
+            None => String::new(),
+        };
+        write_to_string(result, &value);
+    }
+}
+
+/// The name of |property|, which may be a shorthand.
+fn property_name_from_gecko(property: nsCSSProperty) -> Option<&'static str> {
+    longhand_name_from_gecko(property).or_else(|| shorthand_name_from_gecko(property))
+}
+
+servo_function! {
+    /// Parses |value| on its own as the value of |property|, for Web Animations keyframes and
+    /// devtools, and puts a new declaration block holding it in |out_block|. Shorthands are
+    /// expanded into their longhands. Fails if the value is invalid or anything follows it.
+    fn Servo_ParseProperty(property: nsCSSProperty,
+                           value: *const u8,
+                           value_length: u32,
+                           base: *const u8,
+                           base_length: u32,
+                           out_block: *mut RawServoDeclarationBlockStrong) -> bool [on_panic: false] {
+        return_if_null!(out_block; false);
+        let name = match property_name_from_gecko(property) {
+            Some(name) => name,
+            None => return false,
+        };
+        let value = unsafe { str_from_raw(value, value_length) };
+        let base_url = unsafe { base_url_from_raw(base, base_length) };
+        let mut parsed = match parse_property_value(name, value, &base_url, Box::new(StdoutErrorReporter)) {
+            Ok(ref parsed) if parsed.is_empty() => return false,
+            Ok(parsed) => parsed,
+            Err(()) => return false,
+        };
+
+        // Declarations are stored in reverse order.
+        parsed.reverse();
+        let declarations = PropertyDeclarationBlock {
+            important: Arc::new(vec![]),
+            normal: Arc::new(parsed),
+            shorthands: vec![],
+        };
+        unsafe { *out_block = Strong::from_arc(GeckoDeclarationBlock::new(declarations)) };
+        true
+    }
+}
+
+servo_function! {
+    fn Servo_Property_IsShorthand(property: nsCSSProperty) -> bool [on_panic: false] {
+        shorthand_name_from_gecko(property).is_some()
+    }
+}
+
+servo_function! {
+    /// Appends the longhands |property| expands into to |out|, if it's a shorthand.
+    fn Servo_Property_GetLonghands(property: nsCSSProperty, out: *mut RawGeckoPropertyList) -> () [on_panic: ()] {
+        return_if_null!(out; ());
+        let shorthand = match shorthand_name_from_gecko(property).and_then(Shorthand::from_name) {
+            Some(shorthand) => shorthand,
+            None => return,
+        };
+        for longhand in shorthand.longhands() {
+            if let Some(longhand) = longhand_to_gecko(longhand) {
+                unsafe { Gecko_PropertyList_Append(out, longhand) };
+            }
+        }
+    }
+}
+
+servo_function! {
+    /// Makes |property| available to UA sheets or not, and to the others or not, for Gecko to
+    /// call at startup and whenever one of the prefs that enable properties changes. Sheets
+    /// and declarations that were parsed before keep using |property| or not using it as
+    /// they did, until they're parsed again.
+    fn Servo_Property_SetEnabled(property: nsCSSProperty, enabled_for_content: bool,
+                                 enabled_for_ua: bool) -> () [on_panic: ()] {
+        if let Some(name) = property_name_from_gecko(property) {
+            set_property_enabled(name, enabled_for_content, enabled_for_ua);
+        }
+    }
+}
+
+servo_function! {
+    /// The two-argument form of CSS.supports(): whether |property: value| would parse.
+    fn Servo_CSSSupports(property: *const u8, property_length: u32,
+                         value: *const u8, value_length: u32) -> bool [on_panic: false] {
+        let property = unsafe { str_from_raw(property, property_length) };
+        let value = unsafe { str_from_raw(value, value_length) };
+        // There's no sheet to resolve urls against.
+        let base_url = &GlobalStyleData::get().dummy_url;
+        let context = ParserContext::new(Origin::Author, base_url, Box::new(StdoutErrorReporter));
+        supports_declaration(property, value, &context)
+    }
+}
+
+servo_function! {
+    /// The one-argument form of CSS.supports(), which takes an @supports condition.
+    fn Servo_CSSSupportsCondition(condition: *const u8, length: u32) -> bool [on_panic: false] {
+        let condition = unsafe { str_from_raw(condition, length) };
+        let base_url = &GlobalStyleData::get().dummy_url;
+        let context = ParserContext::new(Origin::Author, base_url, Box::new(StdoutErrorReporter));
+        supports_condition(condition, &context)
+    }
+}
+
+/// A selector list Gecko parsed for Element.matches() and the like, which it owns.
+pub type GeckoSelectorList = Vec<Selector<GeckoSelectorImpl>>;
+
+servo_function! {
+    /// Parses |bytes| as a selector list for Element.matches() and the like. Type selectors
+    /// without a namespace prefix match elements in |default_namespace|, or in any namespace if
+    /// it's null. Returns null if the list is invalid, which includes using pseudo-elements, so
+    /// Gecko can throw a SyntaxError.
+    fn Servo_SelectorList_Parse(bytes: *const u8, length: u32, default_namespace: *mut nsIAtom)
+                                -> *mut RawServoSelectorList [on_panic: ptr::null_mut()] {
+        let input = unsafe { str_from_raw(bytes, length) };
+        let default_namespace = if default_namespace.is_null() {
+            None
+        } else {
+            Some(unsafe { namespace_from_gecko(default_namespace) }.clone())
+        };
+        match parse_selector_list_for_matching::<GeckoSelectorImpl>(input, default_namespace) {
+            Ok(selectors) => Box::into_raw(Box::new(selectors)) as *mut RawServoSelectorList,
+            Err(()) => ptr::null_mut(),
+        }
+    }
+}
+
+servo_function! {
+    /// Like Servo_SelectorList_Parse with no default namespace, for querySelector(), but
+    /// fills in |out_error| with which selector makes the list invalid, and where in it, for
+    /// the SyntaxError Gecko throws.
+    fn Servo_SelectorList_ParseDetailed(bytes: *const u8, length: u32, out_error: *mut ServoSelectorParseError)
+                                        -> *mut RawServoSelectorList [on_panic: ptr::null_mut()] {
+        let input = unsafe { str_from_raw(bytes, length) };
+        if let Ok(selectors) = parse_selector_list_for_matching::<GeckoSelectorImpl>(input, None) {
+            return Box::into_raw(Box::new(selectors)) as *mut RawServoSelectorList
+        }
+        if !out_error.is_null() {
+            let context = SelectorParserContext::new();
+            let (start, length, offset) = match find_invalid_selector::<GeckoSelectorImpl>(&context, input, true) {
+                Some(invalid) => (invalid.start, invalid.selector.len(), invalid.offset),
+                // Each selector parsing on its own when the list doesn't shouldn't happen, but
+                // if it does, the whole list is at fault.
+                None => (0, input.len(), 0),
+            };
+            unsafe {
+                *out_error = ServoSelectorParseError {
+                    mKind: ServoCSSErrorKind::InvalidSelector,
+                    mSelectorStart: start as u32,
+                    mSelectorLength: length as u32,
+                    mOffset: offset as u32,
+                };
+            }
+        }
+        ptr::null_mut()
+    }
+}
+
+servo_function! {
+    fn Servo_SelectorList_Drop(list: *mut RawServoSelectorList) -> () [on_panic: abort()] {
+        if !list.is_null() {
+            let _ = unsafe { Box::from_raw(list as *mut GeckoSelectorList) };
+        }
+    }
+}
+
+servo_function! {
+    /// Whether |element| matches any of the selectors in |list|, for Element.matches(). Links
+    /// match as unvisited, so that their visitedness can't be found out through it.
+    fn Servo_SelectorList_Matches(element: *mut RawGeckoElement,
+                                  list: *mut RawServoSelectorList) -> bool [on_panic: false] {
+        return_if_null!(element, list; false);
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        let selectors = unsafe { &*(list as *const GeckoSelectorList) };
+        element.as_node().fetch_document_state();
+        // There's no bloom filter outside of a traversal, so descendant combinators walk up
+        // the tree.
+        matches(&selectors_for_document(selectors), &element, None)
+    }
+}
+
+/// How a query tests the elements it walks over.
+enum QueryTest<'a> {
+    /// The list is a lone #id, which only needs the id of each element.
+    Id(&'a Atom),
+    /// The list is a lone .class, which only needs the classes of each element.
+    Class(&'a Atom),
+    Selectors(&'a [Selector<GeckoSelectorImpl>]),
+}
+
+impl<'a> QueryTest<'a> {
+    fn new(selectors: &'a [Selector<GeckoSelectorImpl>]) -> QueryTest<'a> {
+        if selectors.len() == 1 {
+            let compound = &selectors[0].compound_selectors;
+            if compound.next.is_none() && compound.simple_selectors.len() == 1 {
+                match compound.simple_selectors[0] {
+                    SimpleSelector::ID(ref id) => return QueryTest::Id(id),
+                    SimpleSelector::Class(ref class) => return QueryTest::Class(class),
+                    _ => {}
+                }
+            }
+        }
+        QueryTest::Selectors(selectors)
+    }
+
+    fn matches(&self, element: &GeckoElement) -> bool {
+        match *self {
+            QueryTest::Id(id) => element.get_id().as_ref() == Some(id),
+            QueryTest::Class(class) => element.has_class(class),
+            QueryTest::Selectors(selectors) => matches(selectors, element, None),
+        }
+    }
+}
+
+/// Calls |f| with each element under |root|, not counting |root| itself, in document order,
+/// until it returns false.
+fn each_descendant_element<'a, F>(root: GeckoNode<'a>, mut f: F) where F: FnMut(GeckoElement<'a>) -> bool {
+    let mut current = root.first_child();
+    while let Some(node) = current {
+        if let Some(element) = node.as_element() {
+            if !f(element) {
+                return;
+            }
+        }
+        current = node.first_child().or_else(|| {
+            // The next sibling of the node or of its closest ancestor that has one, without
+            // leaving the subtree.
+            let mut node = node;
+            loop {
+                if let Some(sibling) = node.next_sibling() {
+                    return Some(sibling);
+                }
+                match node.parent_node() {
+                    Some(parent) if parent.opaque() != root.opaque() => node = parent,
+                    _ => return None,
+                }
+            }
+        });
+    }
+}
+
+servo_function! {
+    /// Returns the first element under |root| in document order that matches |list|, or null if
+    /// there's none, for querySelector(). Combinators can match ancestors of |root|, but |root|
+    /// itself is never returned.
+    fn Servo_SelectorList_QueryFirst(root: *mut RawGeckoNode,
+                                     list: *mut RawServoSelectorList)
+                                     -> *mut RawGeckoElement [on_panic: ptr::null_mut()] {
+        return_if_null!(root, list; ptr::null_mut());
+        let token = unsafe { DomToken::new() };
+        let root = unsafe { GeckoNode::from_raw(&token, root) };
+        root.fetch_document_state();
+        let selectors = selectors_for_document(unsafe { &*(list as *const GeckoSelectorList) });
+        let test = QueryTest::new(&selectors);
+        let mut result = ptr::null_mut();
+        each_descendant_element(root, |element| {
+            if test.matches(&element) {
+                result = element.as_raw();
+                return false;
+            }
+            true
+        });
+        result
+    }
+}
+
+servo_function! {
+    /// Appends every element under |root| that matches |list| to |out|, in document order, for
+    /// querySelectorAll(). Like Servo_SelectorList_QueryFirst, |root| itself is left out.
+    fn Servo_SelectorList_QueryAll(root: *mut RawGeckoNode,
+                                   list: *mut RawServoSelectorList,
+                                   out: *mut RawGeckoContentList) -> () [on_panic: ()] {
+        return_if_null!(root, list, out; ());
+        let token = unsafe { DomToken::new() };
+        let root = unsafe { GeckoNode::from_raw(&token, root) };
+        root.fetch_document_state();
+        let selectors = selectors_for_document(unsafe { &*(list as *const GeckoSelectorList) });
+        let test = QueryTest::new(&selectors);
+        each_descendant_element(root, |element| {
+            if test.matches(&element) {
+                unsafe { Gecko_ContentList_AppendElement(out, element.as_raw()) };
+            }
+            true
+        });
+    }
+}
+
+fn transition_property_from_name(name: &str) -> Option<TransitionProperty> {
+    match transition_property::parse_one(&mut Parser::new(name)) {
+        Ok(TransitionProperty::All) | Err(()) => None,
+        Ok(property) => Some(property),
+    }
+}
+
+servo_function! {
+    /// Computes the value |property| gets from |declarations|. Returns null if the property
+    /// can't be animated or the block doesn't set it.
+    fn Servo_AnimationValue_FromDeclaration(declarations: *mut RawServoDeclarationBlock,
+                                            property: *const u8,
+                                            property_length: u32)
+         -> RawServoAnimationValueStrong [on_panic: Strong::null()] {
+        return_if_null!(declarations; Strong::null());
+        let property = unsafe { str_from_raw(property, property_length) };
+        let property = match transition_property_from_name(property) {
+            Some(property) => property,
+            None => return Strong::null(),
+        };
+        // FIXME: Viewport-relative units need the style set's viewport size.
+        let value = with_declarations(declarations, |declarations| {
+            AnimationValue::from_declarations(property, declarations.normal.clone(), Size2D::new(Au(0), Au(0)))
+        });
+        value.map_or(Strong::null(), |value| Strong::from_arc(Arc::new(value)))
+    }
+}
+
+servo_function! {
+    fn Servo_AnimationValue_AddRef(value: *mut RawServoAnimationValue) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<AnimationValue>(value) };
+    }
+}
+
+servo_function! {
+    fn Servo_AnimationValue_Release(value: *mut RawServoAnimationValue) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<AnimationValue>(value) };
+    }
+}
+
+servo_function! {
+    /// Returns the value |progress| of the way from |from| to |to|, or null if they're values
+    /// of different properties.
+    fn Servo_AnimationValues_Interpolate(from: *mut RawServoAnimationValue,
+                                         to: *mut RawServoAnimationValue,
+                                         progress: f64)
+         -> RawServoAnimationValueStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<RawServoAnimationValue, AnimationValue>;
+        return_if_null!(from, to; Strong::null());
+        let value = Helpers::with(from, |from| Helpers::with(to, |to| from.interpolate(to, progress)));
+        value.map_or(Strong::null(), |value| Strong::from_arc(Arc::new(value)))
+    }
+}
+
+servo_function! {
+    fn Servo_AnimationValue_Serialize(value: *mut RawServoAnimationValue,
+                                      property: *const u8,
+                                      property_length: u32,
+                                      result: *mut nsString) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<RawServoAnimationValue, AnimationValue>;
+        return_if_null!(value, result; ());
+        let property = unsafe { str_from_raw(property, property_length) };
+        let serialization = Helpers::with(value, |value| {
+            debug_assert!(transition_property_from_name(property) == Some(value.property()),
+                          "Serializing an animation value as the wrong property");
+            value.serialize()
+        });
+        write_to_string(result, &serialization);
+    }
+}
+
+servo_function! {
+    fn Servo_AnimationValue_DeepEqual(a: *mut RawServoAnimationValue,
+                                      b: *mut RawServoAnimationValue) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<RawServoAnimationValue, AnimationValue>;
+        return_if_null!(a, b; false);
+        Helpers::with(a, |a| Helpers::with(b, |b| **a == **b))
+    }
+}
+
+servo_function! {
+    /// Returns the style of |element|, addrefed. That's the one the traversal gave it unless
+    /// the element was marked dirty since or the rules or the device changed, in which case
+    /// the style is resolved without touching the tree, like Servo_ResolveStyleLazily does.
+    /// Returns null if the element isn't in a document: styles left on an element after it
+    /// was taken out of its document are stale, and Gecko resolves those elements with
+    /// Servo_ResolveStyleLazily instead.
+    fn Servo_GetComputedValues(element: *mut RawGeckoElement, raw_data: *mut RawServoStyleSet)
+         -> ServoComputedValuesStrong [on_panic: Strong::null()] {
+        return_if_null!(element, raw_data; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        if !element.as_node().is_in_document() {
+            return Strong::null();
+        }
+        let start = Instant::now();
+        let guard = GlobalStyleData::get().shared_lock.read();
+        let style = current_or_resolved_style(data, element, &guard);
+        // The statistics then count what resolving the style cascaded, which is nothing when
+        // the element's style was up to date.
+        if traversal_stats::enabled() {
+            let global_data = GlobalStyleData::get();
+            let statistics = collect_traversal_statistics(global_data, start.elapsed());
+            *global_data.traversal_statistics.lock().unwrap() = statistics;
+        }
+        Strong::from_arc(style)
+    }
+}
+
+servo_function! {
+    /// Returns the style the traversal gave |node|, which must be a text node. It inherits
+    /// everything it can from the parent element's style.
+    fn Servo_GetComputedValuesForTextNode(node: *mut RawGeckoNode)
+         -> ServoComputedValuesStrong [on_panic: Strong::null()] {
+        return_if_null!(node; Strong::null());
+        let token = unsafe { DomToken::new() };
+        let node = unsafe { GeckoNode::from_raw(&token, node) };
+        debug_assert!(node.is_text_node());
+        let arc_cv = node.borrow_data().and_then(|data| data.style.clone());
+        arc_cv.map_or(Strong::null(), Strong::from_arc)
+    }
+}
+
+servo_function! {
+    /// Returns a style for a child of |parent| that no rule applies to: it takes its parent's
+    /// value for every inherited property and the initial value for every other, and shares
+    /// its style structs with them. A null |parent| is treated as the initial values. The
+    /// returned style is addrefed.
+    fn Servo_ComputedValues_Inherit(parent: *mut ServoComputedValues)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        let style = Helpers::maybe_with(parent, |parent| inherit_from(&**parent))
+                            .unwrap_or_else(|| inherit_from(GeckoComputedValues::initial_values()));
+        Strong::from_arc(Arc::new(style))
+    }
+}
+
+servo_function! {
+    /// Returns the style to paint |values|' element with if its relevant link is visited, or
+    /// null if the element isn't a link or inside one.
+    fn Servo_ComputedValues_GetVisitedStyle(values: *mut ServoComputedValues)
+         -> ServoComputedValuesStrong [on_panic: Strong::null()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; Strong::null());
+        Helpers::with(values, |values| {
+            values.visited_style.clone().map_or(Strong::null(), Strong::from_arc)
+        })
+    }
+}
+
+servo_function! {
+    /// Returns an id for the rule node |values| were cascaded for, equal for styles cascaded
+    /// from the same rules while that node is alive, or zero if the rule tree is off.
+    fn Servo_ComputedValues_GetRuleNode(values: *mut ServoComputedValues) -> usize [on_panic: 0] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        Helpers::with(values, |values| values.rule_node_id)
+    }
+}
+
+servo_function! {
+    /// Returns the style of an element that no rule applies to and that has no parent, for
+    /// the device the style set was last told about. It's what the initial containing block
+    /// and the canvas inherit from. The returned style is addrefed.
+    fn Servo_ComputedValues_GetInitialValues(raw_data: *mut RawServoStyleSet)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        return_if_null!(raw_data; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        Strong::from_arc(data.initial_values())
+    }
+}
+
+servo_function! {
+    /// Returns the style of the |pseudo_tag| anonymous box inside |parent_style_or_null|,
+    /// addrefed. The style set keeps the last few of these until its rules or device change,
+    /// so asking again with the same parent style doesn't cascade again.
+    fn Servo_StyleSet_GetComputedValuesForAnonymousBox(raw_data: *mut RawServoStyleSet,
+                                                       parent_style_or_null: *mut ServoComputedValues,
+                                                       pseudo_tag: *mut nsIAtom)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(raw_data, pseudo_tag; Strong::null());
+        let pseudo = match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+            Some(pseudo) => pseudo,
+            None => return Strong::null(),
+        };
+        debug_assert!(pseudo.is_anon_box(), "::{} isn't an anonymous box", pseudo.as_str());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        let parent = Helpers::maybe_with(parent_style_or_null, |parent| parent.clone());
+        Strong::from_arc(data.anonymous_box_style(parent.as_ref(), pseudo))
+    }
+}
+
+servo_function! {
+    /// Kept until Gecko moves to Servo_StyleSet_GetComputedValuesForAnonymousBox.
+    fn Servo_GetComputedValuesForAnonymousBox(parent_style_or_null: *mut ServoComputedValues,
+                                              _pseudo_tag: *mut nsIAtom)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+
+        // FIXME: Match the anonymous box's rules from the user agent sheets.
+        let cascade_from = |parent: Option<&GeckoComputedValues>| -> ServoComputedValuesStrong {
+            let (style, _) = cascade(Size2D::new(Au(0), Au(0)), &[], false, parent, None,
+                                     Box::new(StdoutErrorReporter));
+            Strong::from_arc(Arc::new(style))
+        };
+        Helpers::maybe_with(parent_style_or_null, |parent| cascade_from(Some(&**parent)))
+                .unwrap_or_else(|| cascade_from(None))
+    }
+}
+
+servo_function! {
+    /// Returns the style the last traversal gave the |pseudo_tag| pseudo-element of
+    /// |element|, addrefed, or null if no rule applied to it. Only ::before, ::after,
+    /// ::first-line and ::first-letter are styled during the traversal, so any other
+    /// pseudo-element gets null.
+    fn Servo_GetComputedValuesForPseudo(element: *mut RawGeckoElement,
+                                        pseudo_tag: *mut nsIAtom)
+         -> ServoComputedValuesStrong [on_panic: Strong::null()] {
+        return_if_null!(element, pseudo_tag; Strong::null());
+        let pseudo = match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+            Some(pseudo) => pseudo,
+            None => return Strong::null(),
+        };
+        let token = unsafe { DomToken::new() };
+        let node = unsafe { GeckoElement::from_raw(&token, element).as_node() };
+        let style = node.borrow_data().and_then(|data| data.per_pseudo.get(&pseudo).cloned());
+        style.map_or(Strong::null(), Strong::from_arc)
+    }
+}
+
+/// Whether |style| keeps the |pseudo| pseudo-element from generating a box, which ::before
+/// and ::after only do when their content is something other than none or normal.
+fn generates_no_box(pseudo: &PseudoElement, style: &GeckoComputedValues) -> bool {
+    use style::properties::longhands::content::computed_value::T as Content;
+    match *pseudo {
+        PseudoElement::Before | PseudoElement::After => match style.get_box().clone_content() {
+            Content::normal | Content::none => true,
+            Content::Content(_) => false,
+        },
+        _ => false,
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedValuesForPseudoElement(parent_style: *mut ServoComputedValues,
+                                               match_element: *mut RawGeckoElement,
+                                               pseudo_tag: *mut nsIAtom,
+                                               is_probe: bool)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(parent_style, match_element; Strong::null());
+
+        let pseudo = match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+            Some(pseudo) => pseudo,
+            None => return Strong::null(),
+        };
+
+        let token = unsafe { DomToken::new() };
+        let node = unsafe { GeckoElement::from_raw(&token, match_element).as_node() };
+        let existing = node.borrow_data().and_then(|data| data.per_pseudo.get(&pseudo).cloned());
+        if let Some(style) = existing {
+            // A ::before or ::after whose content is none or normal doesn't generate a box, so
+            // there's no frame to make for it.
+            if is_probe && generates_no_box(&pseudo, &style) {
+                return Strong::null();
+            }
+            return Strong::from_arc(style);
+        }
+
+        // Eagerly-cascaded pseudo-elements only get a style during the traversal when
+        // some rule matched them, so there's nothing to generate a frame for.
+        if is_probe {
+            return Strong::null();
+        }
+
+        Helpers::with(parent_style, |parent| {
+            let (style, _) = cascade(Size2D::new(Au(0), Au(0)), &[], false, Some(&**parent), None,
+                                     Box::new(StdoutErrorReporter));
+            Strong::from_arc(Arc::new(style))
+        })
+    }
+}
+
+servo_function! {
+    /// Resolves the style of |element|, or of its |pseudo_tag| pseudo-element if that is
+    /// non-null, even if the traversal never reached it (for example because it's inside a
+    /// display:none subtree). This is what getComputedStyle uses.
+    ///
+    /// Starting from the nearest ancestor whose style is up to date (or from the root if none
+    /// is), every element on the way down is matched and cascaded. The resulting styles are
+    /// not stored in the tree, so repeated calls without a restyle in between always give the
+    /// same answer. The returned style is addrefed.
+    fn Servo_ResolveStyleLazily(element: *mut RawGeckoElement,
+                                pseudo_tag: *mut nsIAtom,
+                                raw_data: *mut RawServoStyleSet)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        return_if_null!(element, raw_data; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+
+        let pseudo = if pseudo_tag.is_null() {
+            None
+        } else {
+            match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+                Some(pseudo) => Some(pseudo),
+                None => return Strong::null(),
+            }
+        };
+
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+
+        let guard = GlobalStyleData::get().shared_lock.read();
+        let style = current_or_resolved_style(data, element, &guard);
+        let style = match pseudo {
+            Some(pseudo) => current_or_resolved_pseudo_style(data, element, &style, pseudo, &guard),
+            None => style,
+        };
+
+        Strong::from_arc(style)
+    }
+}
+
+servo_function! {
+    /// Styles the native anonymous |element|, or its |pseudo_tag| pseudo-element if that is
+    /// non-null, as a child of |originating_style|: the style of whatever Gecko made the
+    /// content up for, which needn't be the element's parent. Only user agent rules and
+    /// animations apply to it.
+    ///
+    /// Unlike the lazy resolution, the style is stored on the element, along with the style
+    /// it inherited from, so that its change hint compares against what it had the last time
+    /// it was styled this way, and later lazy resolutions start from the same parent. The
+    /// element's text children inherit from it. The returned style is addrefed.
+    fn Servo_ResolveStyleForNAC(element: *mut RawGeckoElement,
+                                originating_style: *mut ServoComputedValues,
+                                pseudo_tag: *mut nsIAtom,
+                                raw_data: *mut RawServoStyleSet)
+         -> ServoComputedValuesStrong [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(element, originating_style, raw_data; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+
+        let pseudo = if pseudo_tag.is_null() {
+            None
+        } else {
+            match unsafe { PseudoElement::from_atom(pseudo_tag) } {
+                Some(pseudo) => Some(pseudo),
+                None => return Strong::null(),
+            }
+        };
+
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        let originating = Helpers::with(originating_style, |style| style.clone());
+        let guard = GlobalStyleData::get().shared_lock.read();
+        let style = resolve_style(&data.stylist, data.viewport_size, element, pseudo, Some(&originating), &[],
+                                  &guard);
+
+        // A pseudo-element's style isn't the element's, so there is nothing to store.
+        if pseudo.is_some() {
+            return Strong::from_arc(style);
+        }
+
+        let node = element.as_node();
+        node.initialize_data();
+        let node_data = unsafe { &*node.get_node_data() };
+        {
+            let mut style_data = node_data.style_data.borrow_mut();
+            let damage = GeckoRestyleDamage::compute(style_data.style.as_ref(), &*style);
+            node_data.restyle_damage.set(node_data.restyle_damage.get() | damage);
+            style_data.style = Some(style.clone());
+        }
+        *node_data.nac_parent_style.borrow_mut() = Some(originating);
+        node_data.style_generation.set(data.style_generation);
+        unsafe { node.set_dirty(false) };
+
+        for kid in node.children().filter(|kid| kid.is_text_node()) {
+            kid.initialize_data();
+            if let Some(mut kid_data) = kid.mutate_data() {
+                kid_data.style = Some(Arc::new(inherit_from(&*style)));
+            }
+        }
+
+        Strong::from_arc(style)
+    }
+}
+
+// Keep these in sync with ServoExtraDeclarationsLevel in ServoBindings.h.
+pub const EXTRA_DECLARATIONS_AUTHOR: u8 = 0;
+pub const EXTRA_DECLARATIONS_AUTHOR_IMPORTANT: u8 = 1;
+pub const EXTRA_DECLARATIONS_STYLE_ATTRIBUTE: u8 = 2;
+
+servo_function! {
+    /// Returns the style |element| would get if |extra_block| were cascaded at |level|, for
+    /// previewing edits to its declarations. At the author and style attribute levels the
+    /// block's normal declarations go after the element's other declarations at that level,
+    /// and its important ones after the matching important level; at the important author
+    /// level all of them go after the important author declarations. The element inherits
+    /// from its parent's current style. Nothing is stored in the tree, and the returned
+    /// style is addrefed. Returns null if |level| isn't one of those three.
+    fn Servo_ResolveStyleWithAddedDeclarations(element: *mut RawGeckoElement,
+                                               raw_data: *mut RawServoStyleSet,
+                                               extra_block: *mut RawServoDeclarationBlock,
+                                               level: u8)
+         -> ServoComputedValuesStrong [on_panic: Strong::null()] {
+        return_if_null!(element, raw_data, extra_block; Strong::null());
+        let data = PerDocumentStyleData::borrow_mut_from_raw(raw_data);
+        data.flush_stylesheets();
+
+        let (extra, extra_important) = with_declarations(extra_block, |block| {
+            (DeclarationBlock::from_declarations(block.normal.clone()),
+             DeclarationBlock::from_declarations(block.important.clone()))
+        });
+        let extra_blocks = match level {
+            EXTRA_DECLARATIONS_AUTHOR => [(CascadeLevel::AuthorNormal, extra),
+                                          (CascadeLevel::AuthorImportant, extra_important)],
+            EXTRA_DECLARATIONS_AUTHOR_IMPORTANT => [(CascadeLevel::AuthorImportant, extra),
+                                                    (CascadeLevel::AuthorImportant, extra_important)],
+            EXTRA_DECLARATIONS_STYLE_ATTRIBUTE => [(CascadeLevel::StyleAttributeNormal, extra),
+                                                   (CascadeLevel::StyleAttributeImportant, extra_important)],
+            _ => return Strong::null(),
+        };
+
+        let token = unsafe { DomToken::new() };
+        let element = unsafe { GeckoElement::from_raw(&token, element) };
+        let parent = element.as_node().style_parent().and_then(|parent| parent.as_element());
+        let guard = GlobalStyleData::get().shared_lock.read();
+        let parent_style = parent.map(|parent| current_or_resolved_style(data, parent, &guard));
+        Strong::from_arc(resolve_style(&data.stylist, data.viewport_size, element, None, parent_style.as_ref(),
+                                       &extra_blocks, &guard))
+    }
+}
+
+/// The style the traversal gave |element|, unless the element was marked dirty since or
+/// the style is from before the rules or the device last changed.
+fn up_to_date_style(data: &PerDocumentStyleData, element: GeckoElement) -> Option<Arc<GeckoComputedValues>> {
+    let node = element.as_node();
+    if node.is_dirty() {
+        return None;
+    }
+    match unsafe { node.get_node_data().as_ref() } {
+        Some(node_data) if node_data.style_generation.get() == data.style_generation => {
+            node_data.style_data.borrow().style.clone()
+        }
+        _ => None,
+    }
+}
+
+/// The style |element| has in the tree, or the one it would get if it hasn't been styled
+/// or its style is out of date.
+fn current_or_resolved_style(data: &PerDocumentStyleData, element: GeckoElement, guard: &SharedRwLockReadGuard)
+                             -> Arc<GeckoComputedValues> {
+    // Find the elements we need to resolve, from |element| up to the nearest ancestor
+    // whose style is up to date.
+    let mut unstyled = vec![];
+    let mut style = None;
+    let mut current = Some(element);
+    while let Some(el) = current {
+        if let Some(existing) = up_to_date_style(data, el) {
+            style = Some(existing);
+            break;
+        }
+        unstyled.push(el);
+        // Native anonymous content styled by Servo_ResolveStyleForNAC inherits from the style
+        // it was given then, not from its parent.
+        let nac_parent_style = unsafe { el.as_node().get_node_data().as_ref() }
+                                   .and_then(|node_data| node_data.nac_parent_style.borrow().clone());
+        if nac_parent_style.is_some() {
+            style = nac_parent_style;
+            break;
+        }
+        current = el.as_node().style_parent().and_then(|parent| parent.as_element());
+    }
+
+    for el in unstyled.into_iter().rev() {
+        style = Some(resolve_style(&data.stylist, data.viewport_size, el, None, style.as_ref(), &[], guard));
+    }
+    style.expect("Should have resolved a style for the element")
+}
+
+/// The style of the |pseudo| pseudo-element of |element|, whose own style is |style|: the one
+/// the traversal gave it if that's up to date, or the one it would get otherwise.
+fn current_or_resolved_pseudo_style(data: &PerDocumentStyleData,
+                                    element: GeckoElement,
+                                    style: &Arc<GeckoComputedValues>,
+                                    pseudo: PseudoElement,
+                                    guard: &SharedRwLockReadGuard)
+                                    -> Arc<GeckoComputedValues> {
+    if up_to_date_style(data, element).is_some() {
+        let existing = element.as_node().borrow_data()
+                              .and_then(|node_data| node_data.per_pseudo.get(&pseudo).cloned());
+        if let Some(existing) = existing {
+            return existing;
+        }
+    }
+    // ::first-letter inherits from ::first-line, whether or not any rule applies to it.
+    let parent_style = match GeckoSelectorImpl::pseudo_element_inherits_from(&pseudo) {
+        Some(parent_pseudo) => current_or_resolved_pseudo_style(data, element, style, parent_pseudo, guard),
+        None => style.clone(),
+    };
+    resolve_style(&data.stylist, data.viewport_size, element, Some(pseudo), Some(&parent_style), &[], guard)
+}
+
+/// Matches and cascades a single element or pseudo-element without touching the tree,
+/// cascading |extra_blocks| as well. |guard| holds the lock of the element's declaration
+/// blocks.
+fn resolve_style(stylist: &Stylist,
+                 viewport_size: Size2D<Au>,
+                 element: GeckoElement,
+                 pseudo: Option<PseudoElement>,
+                 parent_style: Option<&Arc<GeckoComputedValues>>,
+                 extra_blocks: &[(CascadeLevel, DeclarationBlock)],
+                 guard: &SharedRwLockReadGuard)
+                 -> Arc<GeckoComputedValues> {
+    element.as_node().fetch_document_state();
+
+    // Style attributes don't apply to pseudo-elements.
+    let style_attribute = match pseudo {
+        Some(_) => None,
+        None => element.style_attribute(guard).as_ref(),
+    };
+
+    let restriction = pseudo.as_ref().and_then(|pseudo| {
+        GeckoSelectorImpl::pseudo_element_property_restriction(pseudo)
+    });
+
+    let mut declarations: Vec<DeclarationBlock> = vec![];
+    stylist.push_applicable_declarations_with_extra_blocks(&element, guard, None, style_attribute, pseudo,
+                                                           extra_blocks, &mut declarations);
+    if let Some(restriction) = restriction {
+        declarations = restrict_declarations(&declarations, restriction);
+    }
+    traversal_stats::record(|stats| {
+        for block in &declarations {
+            stats.selectors_matched += 1;
+            stats.declarations_cascaded += block.declarations.len() as u32;
+        }
+        stats.elements_styled += 1;
+    });
+
+    let (mut style, _) = cascade(viewport_size, &declarations, false,
+                                 parent_style.map(|style| &**style), None,
+                                 Box::new(StdoutErrorReporter));
+    if style.get_box().content_reads_attributes() {
+        let content = style.get_box().clone_content().with_attributes_resolved(|name| {
+            element.get_attr(&ns!(), &Atom::from(name)).map(|value| value.to_owned())
+        });
+        style.mutate_box().set_content(content);
+    }
+    Arc::new(style)
+}
+
+servo_function! {
+    fn Servo_GetComputedDisplay(values: *mut ServoComputedValues) -> ServoStyleDisplay [on_panic: abort()] {
+        use style::properties::longhands::display::computed_value::T as display;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; ServoStyleDisplay::Inline);
+        Helpers::with(values, |values| {
+            match values.get_box().clone_display() {
+                display::inline => ServoStyleDisplay::Inline,
+                display::block => ServoStyleDisplay::Block,
+                display::inline_block => ServoStyleDisplay::InlineBlock,
+                display::table => ServoStyleDisplay::Table,
+                display::inline_table => ServoStyleDisplay::InlineTable,
+                display::table_row_group => ServoStyleDisplay::TableRowGroup,
+                display::table_header_group => ServoStyleDisplay::TableHeaderGroup,
+                display::table_footer_group => ServoStyleDisplay::TableFooterGroup,
+                display::table_row => ServoStyleDisplay::TableRow,
+                display::table_column_group => ServoStyleDisplay::TableColumnGroup,
+                display::table_column => ServoStyleDisplay::TableColumn,
+                display::table_cell => ServoStyleDisplay::TableCell,
+                display::table_caption => ServoStyleDisplay::TableCaption,
+                display::list_item => ServoStyleDisplay::ListItem,
+                display::flex => ServoStyleDisplay::Flex,
+                display::none => ServoStyleDisplay::None,
+                display::inline_flex => ServoStyleDisplay::InlineFlex,
+                display::contents => ServoStyleDisplay::Contents,
+            }
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedPosition(values: *mut ServoComputedValues) -> ServoStylePosition [on_panic: abort()] {
+        use style::properties::longhands::position::computed_value::T as position;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; ServoStylePosition::Static);
+        Helpers::with(values, |values| {
+            match values.get_box().clone_position() {
+                position::static_ => ServoStylePosition::Static,
+                position::absolute => ServoStylePosition::Absolute,
+                position::relative => ServoStylePosition::Relative,
+                position::fixed => ServoStylePosition::Fixed,
+            }
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedFloat(values: *mut ServoComputedValues) -> ServoStyleFloat [on_panic: abort()] {
+        use style::properties::longhands::float::computed_value::T as float;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; ServoStyleFloat::None);
+        Helpers::with(values, |values| {
+            match values.get_box().clone_float() {
+                float::none => ServoStyleFloat::None,
+                float::left => ServoStyleFloat::Left,
+                float::right => ServoStyleFloat::Right,
+            }
+        })
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedClear(values: *mut ServoComputedValues) -> ServoStyleClear [on_panic: abort()] {
+        use style::properties::longhands::clear::computed_value::T as clear;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; ServoStyleClear::None);
+        Helpers::with(values, |values| {
+            match values.get_box().clone_clear() {
+                clear::none => ServoStyleClear::None,
+                clear::left => ServoStyleClear::Left,
+                clear::right => ServoStyleClear::Right,
+                clear::both => ServoStyleClear::Both,
+            }
+        })
+    }
+}
+
+servo_function! {
+    /// What the computed `will-change` announces, as bits Gecko decides ahead of time which
+    /// layers and stacking contexts to make with.
+    fn Servo_GetComputedWillChangeBits(values: *mut ServoComputedValues) -> u64 [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        Helpers::with(values, |values| will_change_bits(&values.get_box().clone_will_change()))
+    }
+}
+
+servo_function! {
+    /// The kinds of containment the computed `contain` turns on, as bits.
+    fn Servo_GetComputedContain(values: *mut ServoComputedValues) -> u8 [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        Helpers::with(values, |values| values.get_box().clone_contain().0)
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedIsolation(values: *mut ServoComputedValues) -> ServoStyleIsolation [on_panic: abort()] {
+        use style::properties::longhands::isolation::computed_value::T as isolation;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; ServoStyleIsolation::Auto);
+        Helpers::with(values, |values| {
+            match values.get_effects().clone_isolation() {
+                isolation::auto => ServoStyleIsolation::Auto,
+                isolation::isolate => ServoStyleIsolation::Isolate,
+            }
+        })
+    }
+}
+
+/// Keeps both parts of a calc(), and whether it had a percentage at all, which Gecko's calc
+/// coordinates need to tell `calc(10px + 0%)` from `calc(10px)`.
+fn length_or_percentage_or_auto_to_ffi(value: LengthOrPercentageOrAuto) -> ServoLengthOrPercentageOrAuto {
+    let (unit, length, percent, has_percent) = match value {
+        LengthOrPercentageOrAuto::Length(au) => (ServoLengthUnit::Length, au, 0., false),
+        LengthOrPercentageOrAuto::Percentage(percent) => (ServoLengthUnit::Percent, Au(0), percent, true),
+        LengthOrPercentageOrAuto::Auto => (ServoLengthUnit::Auto, Au(0), 0., false),
+        LengthOrPercentageOrAuto::Calc(calc) => {
+            (ServoLengthUnit::Calc, calc.length(), calc.percentage(), calc.percentage.is_some())
+        }
+    };
+    ServoLengthOrPercentageOrAuto {
+        mUnit: unit,
+        mLength: length.0,
+        mPercent: percent,
+        mHasPercent: has_percent,
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedWidth(values: *mut ServoComputedValues) -> ServoLengthOrPercentageOrAuto [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto));
+        Helpers::with(values, |values| moz_length_to_ffi(values.get_box().clone_width()))
+    }
+}
+
+servo_function! {
+    fn Servo_GetComputedHeight(values: *mut ServoComputedValues) -> ServoLengthOrPercentageOrAuto [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto));
+        Helpers::with(values, |values| moz_length_to_ffi(values.get_box().clone_height()))
+    }
+}
+
+fn overflow_to_gecko(overflow: Overflow) -> u8 {
+    (match overflow {
+        Overflow::visible => gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE,
+        Overflow::hidden => gecko_style_structs::NS_STYLE_OVERFLOW_HIDDEN,
+        Overflow::scroll => gecko_style_structs::NS_STYLE_OVERFLOW_SCROLL,
+        Overflow::auto => gecko_style_structs::NS_STYLE_OVERFLOW_AUTO,
+    }) as u8
+}
+
+fn length_or_percentage_to_ffi(value: LengthOrPercentage) -> ServoLengthOrPercentageOrAuto {
+    length_or_percentage_or_auto_to_ffi(match value {
+        LengthOrPercentage::Length(au) => LengthOrPercentageOrAuto::Length(au),
+        LengthOrPercentage::Percentage(percent) => LengthOrPercentageOrAuto::Percentage(percent),
+        LengthOrPercentage::Calc(calc) => LengthOrPercentageOrAuto::Calc(calc),
+    })
+}
+
+servo_function! {
+    /// The computed `overflow-x`, which isn't `visible` whenever `overflow-y` isn't.
+    fn Servo_GetComputedOverflowX(values: *mut ServoComputedValues) -> u8 [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8);
+        Helpers::with(values, |values| overflow_to_gecko(values.get_box().clone_overflow_x()))
+    }
+}
+
+servo_function! {
+    /// The computed `overflow-y`, which isn't `visible` whenever `overflow-x` isn't.
+    fn Servo_GetComputedOverflowY(values: *mut ServoComputedValues) -> u8 [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8);
+        Helpers::with(values, |values| overflow_to_gecko(values.get_box().clone_overflow_y().0))
+    }
+}
+
+fn resize_to_gecko(resize: Resize) -> u8 {
+    (match resize {
+        Resize::none => gecko_style_structs::NS_STYLE_RESIZE_NONE,
+        Resize::both => gecko_style_structs::NS_STYLE_RESIZE_BOTH,
+        Resize::horizontal => gecko_style_structs::NS_STYLE_RESIZE_HORIZONTAL,
+        Resize::vertical => gecko_style_structs::NS_STYLE_RESIZE_VERTICAL,
+    }) as u8
+}
+
+fn scroll_snap_type_x_to_gecko(snap_type: SnapTypeX) -> u8 {
+    (match snap_type {
+        SnapTypeX::none => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_NONE,
+        SnapTypeX::mandatory => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_MANDATORY,
+        SnapTypeX::proximity => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_PROXIMITY,
+    }) as u8
+}
+
+fn scroll_snap_type_y_to_gecko(snap_type: SnapTypeY) -> u8 {
+    (match snap_type {
+        SnapTypeY::none => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_NONE,
+        SnapTypeY::mandatory => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_MANDATORY,
+        SnapTypeY::proximity => gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_PROXIMITY,
+    }) as u8
+}
+
+servo_function! {
+    /// Fills |info| with everything Gecko needs to build the scroll frame of an element with
+    /// |values|. The snap coordinates are only counted; Servo_GetComputedScrollSnapCoordinate
+    /// gives each of them.
+    fn Servo_GetScrollFrameInfo(values: *mut ServoComputedValues, info: *mut ServoScrollFrameInfo)
+                                -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, info; ());
+        let snap_points = |interval: Option<LengthOrPercentage>| match interval {
+            Some(interval) => length_or_percentage_to_ffi(interval),
+            None => length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto),
+        };
+        let scroll_frame_info = Helpers::with(values, |values| {
+            let box_ = values.get_box();
+            let destination = box_.clone_scroll_snap_destination();
+            ServoScrollFrameInfo {
+                mOverflowX: overflow_to_gecko(box_.clone_overflow_x()),
+                mOverflowY: overflow_to_gecko(box_.clone_overflow_y().0),
+                mResize: resize_to_gecko(box_.clone_resize()),
+                mScrollSnapTypeX: scroll_snap_type_x_to_gecko(box_.clone_scroll_snap_type_x()),
+                mScrollSnapTypeY: scroll_snap_type_y_to_gecko(box_.clone_scroll_snap_type_y()),
+                mScrollSnapPointsX: snap_points(box_.clone_scroll_snap_points_x().0),
+                mScrollSnapPointsY: snap_points(box_.clone_scroll_snap_points_y().0),
+                mScrollSnapDestinationX: length_or_percentage_to_ffi(destination.horizontal),
+                mScrollSnapDestinationY: length_or_percentage_to_ffi(destination.vertical),
+                mScrollSnapCoordinateCount: box_.clone_scroll_snap_coordinate().0.len() as u32,
+            }
+        });
+        unsafe { *info = scroll_frame_info };
+    }
+}
+
+servo_function! {
+    /// Sets |x| and |y| to the |index|th position of the computed `scroll-snap-coordinate`.
+    /// Returns false, leaving them alone, if there is no such position.
+    fn Servo_GetComputedScrollSnapCoordinate(values: *mut ServoComputedValues, index: u32,
+                                             x: *mut ServoLengthOrPercentageOrAuto,
+                                             y: *mut ServoLengthOrPercentageOrAuto) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, x, y; false);
+        let coordinate = Helpers::with(values, |values| {
+            values.get_box().clone_scroll_snap_coordinate().0.get(index as usize).cloned()
+        });
+        match coordinate {
+            Some(position) => {
+                unsafe {
+                    *x = length_or_percentage_to_ffi(position.horizontal);
+                    *y = length_or_percentage_to_ffi(position.vertical);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+servo_function! {
+    /// Fills |out| with the inherited text properties Gecko's line layout reads, so that
+    /// building a text run doesn't take a call per property.
+    fn Servo_GetComputedTextInfo(values: *mut ServoComputedValues, out: *mut ServoTextStyleFFI)
+                                 -> () [on_panic: ()] {
+        use style::properties::longhands::overflow_wrap::computed_value::T as OverflowWrap;
+        use style::properties::longhands::tab_size::computed_value::T as TabSize;
+        use style::properties::longhands::text_align::computed_value::T as TextAlign;
+        use style::properties::longhands::text_transform::computed_value::T as TextTransform;
+        use style::properties::longhands::white_space::computed_value::T as WhiteSpace;
+        use style::properties::longhands::word_break::computed_value::T as WordBreak;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        let spacing = |spacing: Option<Au>| match spacing {
+            Some(length) => (ServoTextSpacingKind::Length, length.0),
+            None => (ServoTextSpacingKind::Normal, 0),
+        };
+        let text_style = Helpers::with(values, |values| {
+            let text = values.get_inheritedtext();
+            let (letter_spacing_kind, letter_spacing) = spacing(text.clone_letter_spacing().0);
+            let (word_spacing_kind, word_spacing) = spacing(text.clone_word_spacing().0);
+            let (tab_size_kind, tab_size_number, tab_size_length) = match text.clone_tab_size() {
+                TabSize::Number(number) => (ServoTabSizeKind::Number, number, 0),
+                TabSize::Length(length) => (ServoTabSizeKind::Length, 0., length.0),
+            };
+            ServoTextStyleFFI {
+                mWhiteSpace: (match text.clone_white_space() {
+                    WhiteSpace::normal => gecko_style_structs::NS_STYLE_WHITESPACE_NORMAL,
+                    WhiteSpace::pre => gecko_style_structs::NS_STYLE_WHITESPACE_PRE,
+                    WhiteSpace::nowrap => gecko_style_structs::NS_STYLE_WHITESPACE_NOWRAP,
+                    WhiteSpace::pre_wrap => gecko_style_structs::NS_STYLE_WHITESPACE_PRE_WRAP,
+                    WhiteSpace::pre_line => gecko_style_structs::NS_STYLE_WHITESPACE_PRE_LINE,
+                }) as u8,
+                mTextTransform: (match text.clone_text_transform() {
+                    TextTransform::none => gecko_style_structs::NS_STYLE_TEXT_TRANSFORM_NONE,
+                    TextTransform::capitalize => gecko_style_structs::NS_STYLE_TEXT_TRANSFORM_CAPITALIZE,
+                    TextTransform::uppercase => gecko_style_structs::NS_STYLE_TEXT_TRANSFORM_UPPERCASE,
+                    TextTransform::lowercase => gecko_style_structs::NS_STYLE_TEXT_TRANSFORM_LOWERCASE,
+                }) as u8,
+                mWordBreak: (match text.clone_word_break() {
+                    WordBreak::normal => gecko_style_structs::NS_STYLE_WORDBREAK_NORMAL,
+                    WordBreak::break_all => gecko_style_structs::NS_STYLE_WORDBREAK_BREAK_ALL,
+                }) as u8,
+                mOverflowWrap: (match text.clone_overflow_wrap() {
+                    OverflowWrap::normal => gecko_style_structs::NS_STYLE_WORDWRAP_NORMAL,
+                    OverflowWrap::break_word => gecko_style_structs::NS_STYLE_WORDWRAP_BREAK_WORD,
+                }) as u8,
+                mTextAlign: (match text.clone_text_align() {
+                    TextAlign::start => gecko_style_structs::NS_STYLE_TEXT_ALIGN_DEFAULT,
+                    TextAlign::end => gecko_style_structs::NS_STYLE_TEXT_ALIGN_END,
+                    TextAlign::left => gecko_style_structs::NS_STYLE_TEXT_ALIGN_LEFT,
+                    TextAlign::right => gecko_style_structs::NS_STYLE_TEXT_ALIGN_RIGHT,
+                    TextAlign::center => gecko_style_structs::NS_STYLE_TEXT_ALIGN_CENTER,
+                    TextAlign::justify => gecko_style_structs::NS_STYLE_TEXT_ALIGN_JUSTIFY,
+                    TextAlign::servo_center => gecko_style_structs::NS_STYLE_TEXT_ALIGN_MOZ_CENTER,
+                    TextAlign::servo_left => gecko_style_structs::NS_STYLE_TEXT_ALIGN_MOZ_LEFT,
+                    TextAlign::servo_right => gecko_style_structs::NS_STYLE_TEXT_ALIGN_MOZ_RIGHT,
+                }) as u8,
+                mLetterSpacingKind: letter_spacing_kind,
+                mWordSpacingKind: word_spacing_kind,
+                mTabSizeKind: tab_size_kind,
+                mLetterSpacing: letter_spacing,
+                mWordSpacing: word_spacing,
+                mTabSizeLength: tab_size_length,
+                mTabSizeNumber: tab_size_number,
+            }
+        });
+        unsafe { *out = text_style };
+    }
+}
+
+/// A transform operation as Gecko's compositor reads it. mValues holds the matrix in the
+/// order matrix3d() lists it, or the function's arguments, with lengths in px and angles in
+/// radians. The parts of a translation that are relative to the reference box stay apart
+/// in mPercentages, since Gecko only knows the box once the element is laid out.
+fn transform_operation_to_ffi(operation: &ComputedOperation) -> ServoTransformOperation {
+    let mut values = [0.; 16];
+    let mut percentages = [0.; 2];
+    let function = match *operation {
+        ComputedOperation::Matrix(m) => {
+            values = [m.m11, m.m12, m.m13, m.m14, m.m21, m.m22, m.m23, m.m24,
+                      m.m31, m.m32, m.m33, m.m34, m.m41, m.m42, m.m43, m.m44];
+            ServoTransformFunction::Matrix
+        }
+        ComputedOperation::Skew(ax, ay) => {
+            values[0] = ax.radians();
+            values[1] = ay.radians();
+            ServoTransformFunction::Skew
+        }
+        ComputedOperation::Translate(tx, ty, tz) => {
+            for (i, length) in [tx, ty].iter().enumerate() {
+                let calc = CalcLengthOrPercentage::from(*length);
+                values[i] = calc.length().to_f32_px();
+                percentages[i] = calc.percentage();
+            }
+            values[2] = tz.to_f32_px();
+            ServoTransformFunction::Translate
+        }
+        ComputedOperation::Scale(sx, sy, sz) => {
+            values[0] = sx;
+            values[1] = sy;
+            values[2] = sz;
+            ServoTransformFunction::Scale
+        }
+        ComputedOperation::Rotate(ax, ay, az, theta) => {
+            values[0] = ax;
+            values[1] = ay;
+            values[2] = az;
+            values[3] = theta.radians();
+            ServoTransformFunction::Rotate
+        }
+        ComputedOperation::Perspective(d) => {
+            values[0] = d.to_f32_px();
+            ServoTransformFunction::Perspective
+        }
+    };
+    ServoTransformOperation {
+        mFunction: function,
+        mValues: values,
+        mPercentages: percentages,
+    }
+}
+
+servo_function! {
+    /// Fills |out_list|, which Gecko sizes to fit, with the operations of the computed
+    /// transform. Returns false, leaving the list alone, for `transform: none`.
+    fn Servo_GetComputedTransform(values: *mut ServoComputedValues,
+                                  out_list: *mut RawGeckoTransformList) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_list; false);
+        Helpers::with(values, |values| {
+            let transform = values.get_effects().clone_transform();
+            let operations = match transform.0 {
+                Some(ref operations) => operations,
+                None => return false,
+            };
+            let list = unsafe { Gecko_TransformList_SetLength(out_list, operations.len() as u32) };
+            if list.is_null() {
+                return false
+            }
+            let list = unsafe { slice::from_raw_parts_mut(list, operations.len()) };
+            for (slot, operation) in list.iter_mut().zip(operations) {
+                *slot = transform_operation_to_ffi(operation);
+            }
+            true
+        })
+    }
+}
+
+/// The nscolor of a shadow or border color, and whether it has one of its own rather than
+/// the element's.
+fn color_to_ffi(color: &Color) -> (u32, bool) {
+    match *color {
+        Color::RGBA(ref rgba) => (convert_rgba_to_nscolor(rgba), true),
+        Color::CurrentColor => (0, false),
+    }
+}
+
+/// Has Gecko size |array| to fit |shadows|, and fills it with them. Returns false, leaving
+/// the array alone, if there are none.
+fn fill_shadow_array(array: *mut RawGeckoShadowArray, shadows: &[ServoShadowItem]) -> bool {
+    if shadows.is_empty() {
+        return false
+    }
+    let items = unsafe { Gecko_ShadowArray_SetLength(array, shadows.len() as u32) };
+    if items.is_null() {
+        return false
+    }
+    let items = unsafe { slice::from_raw_parts_mut(items, shadows.len()) };
+    items.copy_from_slice(shadows);
+    true
+}
+
+servo_function! {
+    /// Fills |out_array|, which Gecko sizes to fit, with the shadows of the computed
+    /// text-shadow. Shadows without a color of their own come out with mHasColor unset,
+    /// for Gecko to draw in the element's color. Returns false, leaving the array alone,
+    /// for `text-shadow: none`.
+    fn Servo_GetComputedTextShadow(values: *mut ServoComputedValues,
+                                   out_array: *mut RawGeckoShadowArray) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_array; false);
+        Helpers::with(values, |values| {
+            let shadows = values.get_inheritedtext().clone_text_shadow().0.iter().map(|shadow| {
+                let (color, has_color) = color_to_ffi(&shadow.color);
+                ServoShadowItem {
+                    mXOffset: shadow.offset_x.0,
+                    mYOffset: shadow.offset_y.0,
+                    mRadius: shadow.blur_radius.0,
+                    mSpread: 0,
+                    mColor: color,
+                    mHasColor: has_color,
+                    mInset: false,
+                }
+            }).collect::<Vec<_>>();
+            fill_shadow_array(out_array, &shadows)
+        })
+    }
+}
+
+servo_function! {
+    /// Fills |out_array|, which Gecko sizes to fit, with the features of the computed
+    /// font-feature-settings, their tags packed big-endian like HB_TAG does. Returns false,
+    /// leaving the array alone, for `font-feature-settings: normal`.
+    fn Servo_GetComputedFontFeatureSettings(values: *mut ServoComputedValues,
+                                            out_array: *mut RawGeckoFontFeatureArray) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_array; false);
+        Helpers::with(values, |values| {
+            let features = match values.get_font().clone_font_feature_settings() {
+                FontFeatureSettings::Normal => return false,
+                FontFeatureSettings::Tag(features) => features,
+            };
+            let items = unsafe { Gecko_FontFeatureArray_SetLength(out_array, features.len() as u32) };
+            if items.is_null() {
+                return false
+            }
+            let items = unsafe { slice::from_raw_parts_mut(items, features.len()) };
+            for (item, feature) in items.iter_mut().zip(&features) {
+                *item = ServoFontFeature {
+                    // The parser only takes tags of four ASCII characters.
+                    mTag: feature.tag.bytes().fold(0, |tag, byte| tag << 8 | byte as u32),
+                    mValue: feature.value,
+                };
+            }
+            true
+        })
+    }
+}
+
+fn border_style_to_gecko(style: BorderStyle) -> u8 {
+    (match style {
+        BorderStyle::none => gecko_style_structs::NS_STYLE_BORDER_STYLE_NONE,
+        BorderStyle::hidden => gecko_style_structs::NS_STYLE_BORDER_STYLE_HIDDEN,
+        BorderStyle::solid => gecko_style_structs::NS_STYLE_BORDER_STYLE_SOLID,
+        BorderStyle::double => gecko_style_structs::NS_STYLE_BORDER_STYLE_DOUBLE,
+        BorderStyle::dotted => gecko_style_structs::NS_STYLE_BORDER_STYLE_DOTTED,
+        BorderStyle::dashed => gecko_style_structs::NS_STYLE_BORDER_STYLE_DASHED,
+        BorderStyle::groove => gecko_style_structs::NS_STYLE_BORDER_STYLE_GROOVE,
+        BorderStyle::ridge => gecko_style_structs::NS_STYLE_BORDER_STYLE_RIDGE,
+        BorderStyle::inset => gecko_style_structs::NS_STYLE_BORDER_STYLE_INSET,
+        BorderStyle::outset => gecko_style_structs::NS_STYLE_BORDER_STYLE_OUTSET,
+    }) as u8
+}
+
+fn border_image_side(unit: ServoBorderImageUnit, factor: f32, coord: Au) -> ServoBorderImageSide {
+    ServoBorderImageSide {
+        mUnit: unit,
+        mFactor: factor,
+        mCoord: coord.0,
+    }
+}
+
+servo_function! {
+    /// Fills |out| with what Gecko draws the borders from: the width, style and color of each
+    /// side, the border image, and the box-shadow if Gecko set out's mBoxShadow to an array
+    /// for it.
+    fn Servo_GetComputedBorderInfo(values: *mut ServoComputedValues, out: *mut ServoBorderInfo)
+                                   -> () [on_panic: ()] {
+        use style::properties::longhands::border_image_outset::computed_value::Side as OutsetSide;
+        use style::properties::longhands::border_image_repeat::RepeatKeyword;
+        use style::properties::longhands::border_image_slice::SliceOffset;
+        use style::properties::longhands::border_image_width::computed_value::Side as WidthSide;
+        use style::values::computed::Image;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        let repeat = |keyword: RepeatKeyword| match keyword {
+            RepeatKeyword::Stretch => ServoBorderImageRepeat::Stretch,
+            RepeatKeyword::Repeat => ServoBorderImageRepeat::Repeat,
+            RepeatKeyword::Round => ServoBorderImageRepeat::Round,
+            RepeatKeyword::Space => ServoBorderImageRepeat::Space,
+        };
+        let box_shadow = unsafe { (*out).mBoxShadow };
+        let info = Helpers::with(values, |values| {
+            let border = values.get_border();
+            let widths = [border.clone_border_top_width(), border.clone_border_right_width(),
+                          border.clone_border_bottom_width(), border.clone_border_left_width()];
+            let styles = [border.clone_border_top_style(), border.clone_border_right_style(),
+                          border.clone_border_bottom_style(), border.clone_border_left_style()];
+            let colors = [border.clone_border_top_color(), border.clone_border_right_color(),
+                          border.clone_border_bottom_color(), border.clone_border_left_color()];
+            let slice = border.clone_border_image_slice();
+            let image_width = border.clone_border_image_width();
+            let outset = border.clone_border_image_outset();
+            let image_repeat = border.clone_border_image_repeat();
+
+            let has_box_shadow = !box_shadow.is_null() && {
+                let shadows = values.get_effects().clone_box_shadow().0.iter().map(|shadow| {
+                    let (color, has_color) = color_to_ffi(&shadow.color);
+                    ServoShadowItem {
+                        mXOffset: shadow.offset_x.0,
+                        mYOffset: shadow.offset_y.0,
+                        mRadius: shadow.blur_radius.0,
+                        mSpread: shadow.spread_radius.0,
+                        mColor: color,
+                        mHasColor: has_color,
+                        mInset: shadow.inset,
+                    }
+                }).collect::<Vec<_>>();
+                fill_shadow_array(box_shadow, &shadows)
+            };
+
+            let mut info = ServoBorderInfo {
+                mBoxShadow: box_shadow,
+                mWidths: [0; 4],
+                mColors: [0; 4],
+                mStyles: [0; 4],
+                mHasColors: [false; 4],
+                mImageKind: match border.clone_border_image_source().0 {
+                    None => ServoBorderImageKind::None,
+                    Some(Image::Url(_)) => ServoBorderImageKind::Url,
+                    Some(Image::LinearGradient(_)) |
+                    Some(Image::RadialGradient(_)) => ServoBorderImageKind::Gradient,
+                },
+                mImageFill: slice.fill,
+                mImageRepeatH: repeat(image_repeat.0),
+                mImageRepeatV: repeat(image_repeat.1),
+                mHasBoxShadow: has_box_shadow,
+                mImageSlice: [border_image_side(ServoBorderImageUnit::Auto, 0., Au(0)); 4],
+                mImageWidth: [border_image_side(ServoBorderImageUnit::Auto, 0., Au(0)); 4],
+                mImageOutset: [border_image_side(ServoBorderImageUnit::Auto, 0., Au(0)); 4],
+            };
+            for i in 0..4 {
+                info.mWidths[i] = widths[i].0;
+                info.mStyles[i] = border_style_to_gecko(styles[i]);
+                let (color, has_color) = color_to_ffi(&colors[i]);
+                info.mColors[i] = color;
+                info.mHasColors[i] = has_color;
+                info.mImageSlice[i] = match slice.offsets[i] {
+                    SliceOffset::Number(number) => border_image_side(ServoBorderImageUnit::Number, number, Au(0)),
+                    SliceOffset::Percentage(percentage) => {
+                        border_image_side(ServoBorderImageUnit::Percent, percentage.0, Au(0))
+                    }
+                };
+                info.mImageWidth[i] = match image_width.0[i] {
+                    WidthSide::LengthOrPercentage(LengthOrPercentage::Length(length)) => {
+                        border_image_side(ServoBorderImageUnit::Coord, 0., length)
+                    }
+                    WidthSide::LengthOrPercentage(LengthOrPercentage::Percentage(percentage)) => {
+                        border_image_side(ServoBorderImageUnit::Percent, percentage, Au(0))
+                    }
+                    WidthSide::LengthOrPercentage(LengthOrPercentage::Calc(calc)) => {
+                        border_image_side(ServoBorderImageUnit::Calc, calc.percentage(), calc.length())
+                    }
+                    WidthSide::Number(number) => border_image_side(ServoBorderImageUnit::Number, number, Au(0)),
+                    WidthSide::Auto => border_image_side(ServoBorderImageUnit::Auto, 0., Au(0)),
+                };
+                info.mImageOutset[i] = match outset.0[i] {
+                    OutsetSide::Length(length) => border_image_side(ServoBorderImageUnit::Coord, 0., length),
+                    OutsetSide::Number(number) => border_image_side(ServoBorderImageUnit::Number, number, Au(0)),
+                };
+            }
+            info
+        });
+        unsafe { *out = info };
+    }
+}
+
+fn gradient_stops_to_ffi(stops: &[ColorStop]) -> Vec<ServoGradientStop> {
+    stops.iter().map(|stop| {
+        let (color, has_color) = color_to_ffi(&stop.color);
+        ServoGradientStop {
+            mPosition: length_or_percentage_to_ffi(stop.position),
+            mColor: color,
+            mHasColor: has_color,
+        }
+    }).collect()
+}
+
+servo_function! {
+    /// Fills |out_image| with the image of the background layer at |layer_index|, the top one
+    /// being the first. Gecko sets out_image's mURL and mStops to the string and array it wants
+    /// a url and a gradient's stops in, or leaves them null. Returns false, leaving |out_image|
+    /// alone, past the last layer.
+    fn Servo_GetComputedImageAt(values: *mut ServoComputedValues, layer_index: u32,
+                                out_image: *mut ServoImageInfo) -> bool [on_panic: false] {
+        use style::values::computed::{GradientSize, Image};
+        use style::values::specified::{AngleOrCorner, GradientShape, HorizontalDirection, SizeKeyword};
+        use style::values::specified::VerticalDirection;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_image; false);
+        Helpers::with(values, |values| {
+            let images = values.get_background().clone_background_image();
+            let image = match images.0.get(layer_index as usize) {
+                Some(image) => image,
+                None => return false,
+            };
+            let out_image = unsafe { &mut *out_image };
+            let zero = length_or_percentage_to_ffi(LengthOrPercentage::zero());
+            let (stops, url_string) = (out_image.mStops, out_image.mURL);
+            *out_image = ServoImageInfo {
+                mStops: stops,
+                mURL: url_string,
+                mKind: ServoImageKind::None,
+                mShape: gecko_style_structs::NS_STYLE_GRADIENT_SHAPE_LINEAR as u8,
+                mSize: gecko_style_structs::NS_STYLE_GRADIENT_SIZE_FARTHEST_CORNER as u8,
+                mHasAngle: false,
+                mAngle: 0.,
+                mPositionX: zero,
+                mPositionY: zero,
+                mRadiusX: zero,
+                mRadiusY: zero,
+            };
+            let gradient_stops = match *image {
+                None => return true,
+                Some(Image::Url(ref url)) => {
+                    out_image.mKind = ServoImageKind::Url;
+                    if !url_string.is_null() {
+                        write_to_string(url_string, &url.url().serialize());
+                    }
+                    return true
+                }
+                Some(Image::LinearGradient(ref gradient)) => {
+                    out_image.mKind = ServoImageKind::LinearGradient;
+                    match gradient.angle_or_corner {
+                        AngleOrCorner::Angle(angle) => {
+                            out_image.mHasAngle = true;
+                            out_image.mAngle = angle.radians();
+                        }
+                        AngleOrCorner::Corner(horizontal, vertical) => {
+                            let edge = |is_far| LengthOrPercentage::Percentage(if is_far { 1. } else { 0. });
+                            out_image.mPositionX = length_or_percentage_to_ffi(edge(horizontal ==
+                                                                                    HorizontalDirection::Right));
+                            out_image.mPositionY = length_or_percentage_to_ffi(edge(vertical ==
+                                                                                    VerticalDirection::Bottom));
+                        }
+                    }
+                    &gradient.stops
+                }
+                Some(Image::RadialGradient(ref gradient)) => {
+                    out_image.mKind = ServoImageKind::RadialGradient;
+                    out_image.mShape = (match gradient.shape {
+                        GradientShape::Circle => gecko_style_structs::NS_STYLE_GRADIENT_SHAPE_CIRCULAR,
+                        GradientShape::Ellipse => gecko_style_structs::NS_STYLE_GRADIENT_SHAPE_ELLIPTICAL,
+                    }) as u8;
+                    out_image.mSize = (match gradient.size {
+                        GradientSize::Keyword(SizeKeyword::ClosestSide) => {
+                            gecko_style_structs::NS_STYLE_GRADIENT_SIZE_CLOSEST_SIDE
+                        }
+                        GradientSize::Keyword(SizeKeyword::ClosestCorner) => {
+                            gecko_style_structs::NS_STYLE_GRADIENT_SIZE_CLOSEST_CORNER
+                        }
+                        GradientSize::Keyword(SizeKeyword::FarthestSide) => {
+                            gecko_style_structs::NS_STYLE_GRADIENT_SIZE_FARTHEST_SIDE
+                        }
+                        GradientSize::Keyword(SizeKeyword::FarthestCorner) => {
+                            gecko_style_structs::NS_STYLE_GRADIENT_SIZE_FARTHEST_CORNER
+                        }
+                        GradientSize::Explicit(width, height) => {
+                            out_image.mRadiusX = length_or_percentage_to_ffi(width);
+                            out_image.mRadiusY = length_or_percentage_to_ffi(height);
+                            gecko_style_structs::NS_STYLE_GRADIENT_SIZE_EXPLICIT_SIZE
+                        }
+                    }) as u8;
+                    out_image.mPositionX = length_or_percentage_to_ffi(gradient.position.horizontal);
+                    out_image.mPositionY = length_or_percentage_to_ffi(gradient.position.vertical);
+                    &gradient.stops
+                }
+            };
+            if !stops.is_null() {
+                let ffi_stops = gradient_stops_to_ffi(gradient_stops);
+                let items = unsafe { Gecko_GradientStopArray_SetLength(stops, ffi_stops.len() as u32) };
+                if !items.is_null() {
+                    unsafe { slice::from_raw_parts_mut(items, ffi_stops.len()) }.copy_from_slice(&ffi_stops);
+                }
+            }
+            true
+        })
+    }
+}
+
+servo_function! {
+    /// The number of background layers, one for each image of background-image.
+    fn Servo_GetComputedBackgroundLayerCount(values: *mut ServoComputedValues) -> u32 [on_panic: abort()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 1);
+        Helpers::with(values, |values| values.get_background().clone_background_image().0.len() as u32)
+    }
+}
+
+servo_function! {
+    /// Fills |out| with the background layer at |index|, the top one being the first, taking
+    /// the value of each layered longhand but background-image for it from the start of its
+    /// list again if it has fewer. Returns false, leaving |out| alone, past the last layer.
+    fn Servo_GetComputedBackgroundLayerAt(values: *mut ServoComputedValues, index: u32,
+                                          out: *mut ServoBackgroundLayer) -> bool [on_panic: false] {
+        use style::properties::longhands::background_attachment::computed_value::SingleComputedValue as Attachment;
+        use style::properties::longhands::background_clip::computed_value::SingleComputedValue as Clip;
+        use style::properties::longhands::background_origin::computed_value::SingleComputedValue as Origin;
+        use style::properties::longhands::background_repeat::computed_value::SingleComputedValue as Repeat;
+        use style::properties::longhands::background_size::computed_value::SingleComputedValue as Size;
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; false);
+        Helpers::with(values, |values| {
+            let background = values.get_background();
+            let index = index as usize;
+            if index >= background.clone_background_image().0.len() {
+                return false
+            }
+            let position = *background.clone_background_position().layer(index);
+            let auto = length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto);
+            let (has_explicit_size, size, width, height) = match *background.clone_background_size().layer(index) {
+                Size::Explicit(ref size) => {
+                    (true, 0, length_or_percentage_or_auto_to_ffi(size.width),
+                     length_or_percentage_or_auto_to_ffi(size.height))
+                }
+                Size::Contain => (false, gecko_style_structs::NS_STYLE_IMAGELAYER_SIZE_CONTAIN, auto, auto),
+                Size::Cover => (false, gecko_style_structs::NS_STYLE_IMAGELAYER_SIZE_COVER, auto, auto),
+            };
+            let repeat = match *background.clone_background_repeat().layer(index) {
+                Repeat::no_repeat => gecko_style_structs::NS_STYLE_IMAGELAYER_REPEAT_NO_REPEAT,
+                Repeat::repeat_x => gecko_style_structs::NS_STYLE_IMAGELAYER_REPEAT_REPEAT_X,
+                Repeat::repeat_y => gecko_style_structs::NS_STYLE_IMAGELAYER_REPEAT_REPEAT_Y,
+                Repeat::repeat => gecko_style_structs::NS_STYLE_IMAGELAYER_REPEAT_REPEAT,
+            };
+            let attachment = match *background.clone_background_attachment().layer(index) {
+                Attachment::scroll => gecko_style_structs::NS_STYLE_IMAGELAYER_ATTACHMENT_SCROLL,
+                Attachment::fixed => gecko_style_structs::NS_STYLE_IMAGELAYER_ATTACHMENT_FIXED,
+            };
+            let clip = match *background.clone_background_clip().layer(index) {
+                Clip::border_box => gecko_style_structs::NS_STYLE_IMAGELAYER_CLIP_BORDER,
+                Clip::padding_box => gecko_style_structs::NS_STYLE_IMAGELAYER_CLIP_PADDING,
+                Clip::content_box => gecko_style_structs::NS_STYLE_IMAGELAYER_CLIP_CONTENT,
+            };
+            let origin = match *background.clone_background_origin().layer(index) {
+                Origin::padding_box => gecko_style_structs::NS_STYLE_IMAGELAYER_ORIGIN_PADDING,
+                Origin::border_box => gecko_style_structs::NS_STYLE_IMAGELAYER_ORIGIN_BORDER,
+                Origin::content_box => gecko_style_structs::NS_STYLE_IMAGELAYER_ORIGIN_CONTENT,
+            };
+            unsafe {
+                *out = ServoBackgroundLayer {
+                    mPositionX: length_or_percentage_to_ffi(position.horizontal),
+                    mPositionY: length_or_percentage_to_ffi(position.vertical),
+                    mWidth: width,
+                    mHeight: height,
+                    mHasExplicitSize: has_explicit_size,
+                    mSize: size as u8,
+                    mRepeat: repeat as u8,
+                    mAttachment: attachment as u8,
+                    mClip: clip as u8,
+                    mOrigin: origin as u8,
+                };
+            }
+            true
+        })
+    }
+}
+
+servo_function! {
+    /// Tells whether the computed `content` is `normal`, `none` or a list of items, and
+    /// appends each of the items to |out_items| in the last case. Counters are left for
+    /// Gecko's counter manager to evaluate, and images for it to load.
+    fn Servo_GetComputedContent(values: *mut ServoComputedValues,
+                                out_items: *mut RawGeckoContentItemList) -> ServoContentKind [on_panic: abort()] {
+        use style::properties::longhands::content::computed_value::{ContentItem, T as Content};
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_items; ServoContentKind::Normal);
+        Helpers::with(values, |values| {
+            let items = match values.get_box().clone_content() {
+                Content::normal => return ServoContentKind::Normal,
+                Content::none => return ServoContentKind::None,
+                Content::Content(items) => items,
+            };
+            for item in &items {
+                let (item_type, string, separator, counter_style) = match *item {
+                    ContentItem::String(ref s) => (ServoContentItemType::String, s.clone(), None, None),
+                    ContentItem::Url(ref url) => (ServoContentItemType::Image, url.serialize(), None, None),
+                    ContentItem::Attr(ref name) => (ServoContentItemType::Attr, name.clone(), None, None),
+                    ContentItem::Counter(ref name, style) => {
+                        (ServoContentItemType::Counter, name.clone(), None, Some(style.to_css_string()))
+                    }
+                    ContentItem::Counters(ref name, ref separator, style) => {
+                        (ServoContentItemType::Counters, name.clone(), Some(separator), Some(style.to_css_string()))
+                    }
+                    ContentItem::OpenQuote => (ServoContentItemType::OpenQuote, String::new(), None, None),
+                    ContentItem::CloseQuote => (ServoContentItemType::CloseQuote, String::new(), None, None),
+                    ContentItem::NoOpenQuote => (ServoContentItemType::NoOpenQuote, String::new(), None, None),
+                    ContentItem::NoCloseQuote => (ServoContentItemType::NoCloseQuote, String::new(), None, None),
+                };
+                let counter_name = match *item {
+                    ContentItem::Counter(ref name, _) |
+                    ContentItem::Counters(ref name, _, _) => atom_to_gecko(&Atom::from(&**name)),
+                    _ => ptr::null_mut(),
+                };
+                let separator = separator.map_or("", |s| &**s);
+                let counter_style = counter_style.as_ref().map_or("", |s| &**s);
+                let ffi_item = ServoContentItem {
+                    mType: item_type,
+                    mString: string.as_ptr(),
+                    mStringLength: string.len() as u32,
+                    mSeparator: separator.as_ptr(),
+                    mSeparatorLength: separator.len() as u32,
+                    mCounterStyle: counter_style.as_ptr(),
+                    mCounterStyleLength: counter_style.len() as u32,
+                    mCounterName: counter_name,
+                };
+                unsafe { Gecko_ContentItemList_Append(out_items, &ffi_item) };
+            }
+            ServoContentKind::Items
+        })
+    }
+}
+
+fn flex_direction_to_gecko(direction: FlexDirection) -> u8 {
+    (match direction {
+        FlexDirection::row => gecko_style_structs::NS_STYLE_FLEX_DIRECTION_ROW,
+        FlexDirection::row_reverse => gecko_style_structs::NS_STYLE_FLEX_DIRECTION_ROW_REVERSE,
+        FlexDirection::column => gecko_style_structs::NS_STYLE_FLEX_DIRECTION_COLUMN,
+        FlexDirection::column_reverse => gecko_style_structs::NS_STYLE_FLEX_DIRECTION_COLUMN_REVERSE,
+    }) as u8
+}
+
+fn flex_wrap_to_gecko(wrap: FlexWrap) -> u8 {
+    (match wrap {
+        FlexWrap::nowrap => gecko_style_structs::NS_STYLE_FLEX_WRAP_NOWRAP,
+        FlexWrap::wrap => gecko_style_structs::NS_STYLE_FLEX_WRAP_WRAP,
+        FlexWrap::wrap_reverse => gecko_style_structs::NS_STYLE_FLEX_WRAP_WRAP_REVERSE,
+    }) as u8
+}
+
+fn justify_content_to_gecko(justify_content: JustifyContent) -> u8 {
+    (match justify_content {
+        JustifyContent::flex_start => gecko_style_structs::NS_STYLE_ALIGN_FLEX_START,
+        JustifyContent::flex_end => gecko_style_structs::NS_STYLE_ALIGN_FLEX_END,
+        JustifyContent::center => gecko_style_structs::NS_STYLE_ALIGN_CENTER,
+        JustifyContent::space_between => gecko_style_structs::NS_STYLE_ALIGN_SPACE_BETWEEN,
+        JustifyContent::space_around => gecko_style_structs::NS_STYLE_ALIGN_SPACE_AROUND,
+    }) as u8
+}
+
+fn align_items_to_gecko(align_items: AlignItems) -> u8 {
+    (match align_items {
+        AlignItems::stretch => gecko_style_structs::NS_STYLE_ALIGN_STRETCH,
+        AlignItems::flex_start => gecko_style_structs::NS_STYLE_ALIGN_FLEX_START,
+        AlignItems::flex_end => gecko_style_structs::NS_STYLE_ALIGN_FLEX_END,
+        AlignItems::center => gecko_style_structs::NS_STYLE_ALIGN_CENTER,
+        AlignItems::baseline => gecko_style_structs::NS_STYLE_ALIGN_BASELINE,
+    }) as u8
+}
+
+fn align_content_to_gecko(align_content: AlignContent) -> u8 {
+    (match align_content {
+        AlignContent::stretch => gecko_style_structs::NS_STYLE_ALIGN_STRETCH,
+        AlignContent::flex_start => gecko_style_structs::NS_STYLE_ALIGN_FLEX_START,
+        AlignContent::flex_end => gecko_style_structs::NS_STYLE_ALIGN_FLEX_END,
+        AlignContent::center => gecko_style_structs::NS_STYLE_ALIGN_CENTER,
+        AlignContent::space_between => gecko_style_structs::NS_STYLE_ALIGN_SPACE_BETWEEN,
+        AlignContent::space_around => gecko_style_structs::NS_STYLE_ALIGN_SPACE_AROUND,
+    }) as u8
+}
+
+fn align_self_to_gecko(align_self: AlignSelf) -> u8 {
+    (match align_self {
+        AlignSelf::auto => gecko_style_structs::NS_STYLE_ALIGN_AUTO,
+        AlignSelf::stretch => gecko_style_structs::NS_STYLE_ALIGN_STRETCH,
+        AlignSelf::flex_start => gecko_style_structs::NS_STYLE_ALIGN_FLEX_START,
+        AlignSelf::flex_end => gecko_style_structs::NS_STYLE_ALIGN_FLEX_END,
+        AlignSelf::center => gecko_style_structs::NS_STYLE_ALIGN_CENTER,
+        AlignSelf::baseline => gecko_style_structs::NS_STYLE_ALIGN_BASELINE,
+    }) as u8
+}
+
+/// The flex basis, with the Auto unit for `content` as well as for `auto`.
+fn flex_basis_to_ffi(basis: FlexBasis) -> ServoLengthOrPercentageOrAuto {
+    length_or_percentage_or_auto_to_ffi(match basis {
+        FlexBasis::Content => LengthOrPercentageOrAuto::Auto,
+        FlexBasis::Specified(basis) => basis,
+    })
+}
+
+servo_function! {
+    /// Fills |out| with what Gecko lays out flex containers and flex items from.
+    fn Servo_GetComputedFlexInfo(values: *mut ServoComputedValues, out: *mut ServoFlexInfo) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        let info = Helpers::with(values, |values| {
+            let flex = values.get_flex();
+            let basis = flex.clone_flex_basis();
+            ServoFlexInfo {
+                mFlexDirection: flex_direction_to_gecko(flex.clone_flex_direction()),
+                mFlexWrap: flex_wrap_to_gecko(flex.clone_flex_wrap()),
+                mJustifyContent: justify_content_to_gecko(flex.clone_justify_content()),
+                mAlignItems: align_items_to_gecko(flex.clone_align_items()),
+                mAlignContent: align_content_to_gecko(flex.clone_align_content()),
+                mAlignSelf: align_self_to_gecko(flex.clone_align_self()),
+                mFlexBasisIsContent: basis == FlexBasis::Content,
+                mOrder: flex.clone_order().0,
+                mFlexGrow: flex.clone_flex_grow().0,
+                mFlexShrink: flex.clone_flex_shrink().0,
+                mFlexBasis: flex_basis_to_ffi(basis),
+            }
+        });
+        unsafe { *out = info };
+    }
+}
+
+servo_function! {
+    /// Fills |out| with what Gecko lays out multi-column elements and draws their column
+    /// rules from.
+    fn Servo_GetComputedColumnInfo(values: *mut ServoComputedValues, out: *mut ServoColumnInfo)
+                                   -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        let info = Helpers::with(values, |values| {
+            let column = values.get_column();
+            let count = column.clone_column_count().0;
+            let (width, gap) = (column.clone_column_width().0, column.clone_column_gap().0);
+            let (rule_color, has_rule_color) = color_to_ffi(&column.clone_column_rule_color());
+            ServoColumnInfo {
+                mColumnCount: count.unwrap_or(gecko_style_structs::NS_STYLE_COLUMN_COUNT_AUTO),
+                mColumnWidth: width.unwrap_or(Au(0)).0,
+                mColumnGap: gap.unwrap_or(Au(0)).0,
+                mColumnRuleWidth: column.clone_column_rule_width().0,
+                mColumnRuleColor: rule_color,
+                mColumnRuleStyle: border_style_to_gecko(column.clone_column_rule_style()),
+                mHasColumnWidth: width.is_some(),
+                mHasColumnGap: gap.is_some(),
+                mHasColumnRuleColor: has_rule_color,
+            }
+        });
+        unsafe { *out = info };
+    }
+}
+
+fn direction_to_gecko(direction: Direction) -> u8 {
+    (match direction {
+        Direction::ltr => gecko_style_structs::NS_STYLE_DIRECTION_LTR,
+        Direction::rtl => gecko_style_structs::NS_STYLE_DIRECTION_RTL,
+    }) as u8
+}
+
+servo_function! {
+    /// The NS_STYLE_DIRECTION_* value the text of the element runs in.
+    fn Servo_GetComputedDirection(values: *mut ServoComputedValues) -> u8 [on_panic: 0] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        Helpers::with(values, |values| direction_to_gecko(values.get_inheritedbox().clone_direction()))
+    }
+}
+
+/// Fills in one side of text-overflow, writing a string to the nsString Gecko left in |side|,
+/// if there is one.
+fn text_overflow_side_to_ffi(value: &TextOverflowSide, side: &mut ServoTextOverflowSide) {
+    side.mType = (match *value {
+        TextOverflowSide::Clip => gecko_style_structs::NS_STYLE_TEXT_OVERFLOW_CLIP,
+        TextOverflowSide::Ellipsis => gecko_style_structs::NS_STYLE_TEXT_OVERFLOW_ELLIPSIS,
+        TextOverflowSide::String(ref string) => {
+            if !side.mString.is_null() {
+                write_to_string(side.mString, string);
+            }
+            gecko_style_structs::NS_STYLE_TEXT_OVERFLOW_STRING
+        }
+    }) as u8;
+}
+
+servo_function! {
+    /// Fills |out| with the element's text-overflow. Gecko sets the mString of both sides to the
+    /// strings it wants a `<string>` value in, or leaves them null.
+    fn Servo_GetComputedTextOverflow(values: *mut ServoComputedValues, out: *mut ServoTextOverflow)
+                                     -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        Helpers::with(values, |values| {
+            let text_overflow = values.get_inheritedtext().clone_text_overflow();
+            let out = unsafe { &mut *out };
+            match text_overflow.second {
+                Some(ref second) => {
+                    out.mLogicalDirections = false;
+                    text_overflow_side_to_ffi(&text_overflow.first, &mut out.mLeft);
+                    text_overflow_side_to_ffi(second, &mut out.mRight);
+                }
+                None => {
+                    out.mLogicalDirections = true;
+                    text_overflow_side_to_ffi(&TextOverflowSide::Clip, &mut out.mLeft);
+                    text_overflow_side_to_ffi(&text_overflow.first, &mut out.mRight);
+                }
+            }
+        });
+    }
+}
+
+/// The NS_STYLE_CURSOR_* value for a cursor keyword.
+fn cursor_to_gecko(keyword: CursorKeyword) -> u8 {
+    use gecko_style_structs::*;
+    (match keyword {
+        CursorKeyword::AutoCursor => NS_STYLE_CURSOR_AUTO,
+        CursorKeyword::SpecifiedCursor(cursor) => match cursor {
+            Cursor::NoCursor => NS_STYLE_CURSOR_NONE,
+            Cursor::DefaultCursor => NS_STYLE_CURSOR_DEFAULT,
+            Cursor::PointerCursor => NS_STYLE_CURSOR_POINTER,
+            Cursor::ContextMenuCursor => NS_STYLE_CURSOR_CONTEXT_MENU,
+            Cursor::HelpCursor => NS_STYLE_CURSOR_HELP,
+            Cursor::ProgressCursor => NS_STYLE_CURSOR_SPINNING,
+            Cursor::WaitCursor => NS_STYLE_CURSOR_WAIT,
+            Cursor::CellCursor => NS_STYLE_CURSOR_CELL,
+            Cursor::CrosshairCursor => NS_STYLE_CURSOR_CROSSHAIR,
+            Cursor::TextCursor => NS_STYLE_CURSOR_TEXT,
+            Cursor::VerticalTextCursor => NS_STYLE_CURSOR_VERTICAL_TEXT,
+            Cursor::AliasCursor => NS_STYLE_CURSOR_ALIAS,
+            Cursor::CopyCursor => NS_STYLE_CURSOR_COPY,
+            Cursor::MoveCursor => NS_STYLE_CURSOR_MOVE,
+            Cursor::NoDropCursor => NS_STYLE_CURSOR_NO_DROP,
+            Cursor::NotAllowedCursor => NS_STYLE_CURSOR_NOT_ALLOWED,
+            Cursor::GrabCursor => NS_STYLE_CURSOR_GRAB,
+            Cursor::GrabbingCursor => NS_STYLE_CURSOR_GRABBING,
+            Cursor::EResizeCursor => NS_STYLE_CURSOR_E_RESIZE,
+            Cursor::NResizeCursor => NS_STYLE_CURSOR_N_RESIZE,
+            Cursor::NeResizeCursor => NS_STYLE_CURSOR_NE_RESIZE,
+            Cursor::NwResizeCursor => NS_STYLE_CURSOR_NW_RESIZE,
+            Cursor::SResizeCursor => NS_STYLE_CURSOR_S_RESIZE,
+            Cursor::SeResizeCursor => NS_STYLE_CURSOR_SE_RESIZE,
+            Cursor::SwResizeCursor => NS_STYLE_CURSOR_SW_RESIZE,
+            Cursor::WResizeCursor => NS_STYLE_CURSOR_W_RESIZE,
+            Cursor::EwResizeCursor => NS_STYLE_CURSOR_EW_RESIZE,
+            Cursor::NsResizeCursor => NS_STYLE_CURSOR_NS_RESIZE,
+            Cursor::NeswResizeCursor => NS_STYLE_CURSOR_NESW_RESIZE,
+            Cursor::NwseResizeCursor => NS_STYLE_CURSOR_NWSE_RESIZE,
+            Cursor::ColResizeCursor => NS_STYLE_CURSOR_COL_RESIZE,
+            Cursor::RowResizeCursor => NS_STYLE_CURSOR_ROW_RESIZE,
+            Cursor::AllScrollCursor => NS_STYLE_CURSOR_ALL_SCROLL,
+            Cursor::ZoomInCursor => NS_STYLE_CURSOR_ZOOM_IN,
+            Cursor::ZoomOutCursor => NS_STYLE_CURSOR_ZOOM_OUT,
+        },
+    }) as u8
+}
+
+servo_function! {
+    /// Writes the keyword of the element's cursor to |out_keyword|, and fills |out_images|,
+    /// which Gecko sizes to fit, with the images to try before it. Each image carries the
+    /// request computing the style started for it, which stays Servo's, so Gecko takes its
+    /// own reference if it keeps one.
+    fn Servo_GetComputedCursor(values: *mut ServoComputedValues, out_keyword: *mut u8,
+                               out_images: *mut RawGeckoCursorImageArray) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_keyword, out_images; ());
+        Helpers::with(values, |values| {
+            let cursor = values.get_pointing().clone_cursor();
+            unsafe { *out_keyword = cursor_to_gecko(cursor.keyword) };
+            let items = unsafe { Gecko_CursorImageArray_SetLength(out_images, cursor.images.len() as u32) };
+            if items.is_null() {
+                return
+            }
+            let items = unsafe { slice::from_raw_parts_mut(items, cursor.images.len()) };
+            for (item, image) in items.iter_mut().zip(&cursor.images) {
+                let (x, y) = image.hotspot.unwrap_or((0., 0.));
+                *item = ServoCursorImage {
+                    mRequest: image.url.image_request().map_or(ptr::null_mut(), |request| {
+                        request.as_ptr() as *mut c_void
+                    }),
+                    mHaveHotspot: image.hotspot.is_some(),
+                    mHotspotX: x,
+                    mHotspotY: y,
+                };
+            }
+        });
+    }
+}
+
+fn pointer_events_to_gecko(pointer_events: PointerEvents) -> u8 {
+    use gecko_style_structs::*;
+    (match pointer_events {
+        PointerEvents::auto => NS_STYLE_POINTER_EVENTS_AUTO,
+        PointerEvents::none => NS_STYLE_POINTER_EVENTS_NONE,
+        PointerEvents::visiblePainted => NS_STYLE_POINTER_EVENTS_VISIBLEPAINTED,
+        PointerEvents::visibleFill => NS_STYLE_POINTER_EVENTS_VISIBLEFILL,
+        PointerEvents::visibleStroke => NS_STYLE_POINTER_EVENTS_VISIBLESTROKE,
+        PointerEvents::visible => NS_STYLE_POINTER_EVENTS_VISIBLE,
+        PointerEvents::painted => NS_STYLE_POINTER_EVENTS_PAINTED,
+        PointerEvents::fill => NS_STYLE_POINTER_EVENTS_FILL,
+        PointerEvents::stroke => NS_STYLE_POINTER_EVENTS_STROKE,
+        PointerEvents::all => NS_STYLE_POINTER_EVENTS_ALL,
+    }) as u8
+}
+
+// Keep these in sync with NS_STYLE_TOUCH_ACTION_* in layout/style/nsStyleConsts.h.
+pub const TOUCH_ACTION_NONE: u8 = 1 << 0;
+pub const TOUCH_ACTION_AUTO: u8 = 1 << 1;
+pub const TOUCH_ACTION_PAN_X: u8 = 1 << 2;
+pub const TOUCH_ACTION_PAN_Y: u8 = 1 << 3;
+pub const TOUCH_ACTION_MANIPULATION: u8 = 1 << 4;
+
+fn touch_action_to_gecko(touch_action: touch_action::T) -> u8 {
+    let mut bits = 0;
+    for &(servo, gecko) in &[(touch_action::NONE, TOUCH_ACTION_NONE), (touch_action::AUTO, TOUCH_ACTION_AUTO),
+                             (touch_action::PAN_X, TOUCH_ACTION_PAN_X), (touch_action::PAN_Y, TOUCH_ACTION_PAN_Y),
+                             (touch_action::MANIPULATION, TOUCH_ACTION_MANIPULATION)] {
+        if touch_action.0 & servo != 0 {
+            bits |= gecko;
+        }
+    }
+    bits
+}
+
+fn user_select_to_gecko(user_select: UserSelect) -> u8 {
+    use gecko_style_structs::*;
+    (match user_select {
+        UserSelect::auto => NS_STYLE_USER_SELECT_AUTO,
+        UserSelect::text => NS_STYLE_USER_SELECT_TEXT,
+        UserSelect::none => NS_STYLE_USER_SELECT_NONE,
+        UserSelect::element => NS_STYLE_USER_SELECT_ELEMENT,
+        UserSelect::elements => NS_STYLE_USER_SELECT_ELEMENTS,
+        UserSelect::all => NS_STYLE_USER_SELECT_ALL,
+        UserSelect::toggle => NS_STYLE_USER_SELECT_TOGGLE,
+        UserSelect::tri_state => NS_STYLE_USER_SELECT_TRI_STATE,
+        UserSelect::_moz_all => NS_STYLE_USER_SELECT_MOZ_ALL,
+        UserSelect::_moz_none => NS_STYLE_USER_SELECT_MOZ_NONE,
+        UserSelect::_moz_text => NS_STYLE_USER_SELECT_MOZ_TEXT,
+    }) as u8
+}
+
+servo_function! {
+    /// Fills |out| with the element's pointer-events, touch-action and user-select, which hit
+    /// testing reads together.
+    fn Servo_GetComputedUIInfo(values: *mut ServoComputedValues, out: *mut ServoUIInfo) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out; ());
+        let info = Helpers::with(values, |values| {
+            ServoUIInfo {
+                mPointerEvents: pointer_events_to_gecko(values.get_pointing().clone_pointer_events()),
+                mTouchAction: touch_action_to_gecko(values.get_box().clone_touch_action()),
+                mUserSelect: user_select_to_gecko(values.get_box().clone_user_select()),
+            }
+        });
+        unsafe { *out = info };
+    }
+}
+
+servo_function! {
+    /// Fills |out_list|, which Gecko sizes to fit, with the counters counter-reset (|which| 0),
+    /// counter-increment (1) or counter-set (2) names and their values, in order. A list item
+    /// increments list-item by one on top of what counter-increment says, unless that names
+    /// list-item itself. The names are the atoms Servo_GetComputedContent hands out for
+    /// counter() and counters().
+    fn Servo_GetComputedCounterData(values: *mut ServoComputedValues, which: u8,
+                                    out_list: *mut RawGeckoCounterItemArray) -> () [on_panic: ()] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, out_list; ());
+        Helpers::with(values, |values| {
+            let counters = values.get_counters();
+            let mut list = match which {
+                0 => counters.clone_counter_reset().0,
+                1 => counters.clone_counter_increment().0,
+                2 => counters.clone_counter_set().0,
+                _ => Vec::new(),
+            };
+            if which == 1 && values.get_box().clone_display() == Display::list_item &&
+               !list.iter().any(|&(ref name, _)| name == "list-item") {
+                list.push(("list-item".to_owned(), 1));
+            }
+            let items = unsafe { Gecko_CounterItemArray_SetLength(out_list, list.len() as u32) };
+            if items.is_null() {
+                return
+            }
+            let items = unsafe { slice::from_raw_parts_mut(items, list.len()) };
+            for (item, &(ref name, value)) in items.iter_mut().zip(&list) {
+                *item = ServoCounterItem {
+                    mName: atom_to_gecko(&Atom::from(&**name)),
+                    mValue: value,
+                };
+            }
+        });
+    }
+}
+
+/// Defines a Servo_GetStyle* function, which fills in the nsStyle*_FFI struct |out| from
+/// |values|. Gecko constructs |out| with its initial values first, and then each of the listed
+/// fields is set to the value it's given, so everything Servo doesn't compute yet keeps the value
+/// Gecko starts from.
+macro_rules! style_struct_getter {
+    (
+        $(#[$attr:meta])*
+        fn $name:ident($values:ident) -> $ffi:ident [construct: $construct:ident] {
+            $($field:ident: $value:expr,)*
+        }
+    ) => {
+        servo_function! {
+            $(#[$attr])*
+            fn $name(values: *mut ServoComputedValues, out: *mut $ffi) -> () [on_panic: ()] {
+                type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+                return_if_null!(values, out; ());
+                unsafe { $construct(out) };
+                Helpers::with(values, |$values| {
+                    let out = unsafe { &mut *out };
+                    $(out.$field = $value;)*
+                });
+            }
+        }
+    }
+}
+
+fn display_to_gecko(display: Display) -> u8 {
+    (match display {
+        Display::inline => gecko_style_structs::NS_STYLE_DISPLAY_INLINE,
+        Display::block => gecko_style_structs::NS_STYLE_DISPLAY_BLOCK,
+        Display::inline_block => gecko_style_structs::NS_STYLE_DISPLAY_INLINE_BLOCK,
+        Display::table => gecko_style_structs::NS_STYLE_DISPLAY_TABLE,
+        Display::inline_table => gecko_style_structs::NS_STYLE_DISPLAY_INLINE_TABLE,
+        Display::table_row_group => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_ROW_GROUP,
+        Display::table_header_group => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_HEADER_GROUP,
+        Display::table_footer_group => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_FOOTER_GROUP,
+        Display::table_row => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_ROW,
+        Display::table_column_group => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_COLUMN_GROUP,
+        Display::table_column => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_COLUMN,
+        Display::table_cell => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_CELL,
+        Display::table_caption => gecko_style_structs::NS_STYLE_DISPLAY_TABLE_CAPTION,
+        Display::list_item => gecko_style_structs::NS_STYLE_DISPLAY_LIST_ITEM,
+        Display::flex => gecko_style_structs::NS_STYLE_DISPLAY_FLEX,
+        Display::none => gecko_style_structs::NS_STYLE_DISPLAY_NONE,
+        Display::inline_flex => gecko_style_structs::NS_STYLE_DISPLAY_INLINE_FLEX,
+    }) as u8
+}
+
+/// Gecko has no size containment of its own, only `strict`, which it sets alongside the bits of
+/// the containments that `strict` stands for.
+fn contain_to_gecko(contain: u8) -> u8 {
+    use style::properties::longhands::contain::computed_value::{LAYOUT, PAINT, STRICT, STYLE};
+    let mut bits = gecko_style_structs::NS_STYLE_CONTAIN_NONE;
+    if contain == STRICT {
+        bits |= gecko_style_structs::NS_STYLE_CONTAIN_STRICT;
+    }
+    if contain & LAYOUT != 0 {
+        bits |= gecko_style_structs::NS_STYLE_CONTAIN_LAYOUT;
+    }
+    if contain & STYLE != 0 {
+        bits |= gecko_style_structs::NS_STYLE_CONTAIN_STYLE;
+    }
+    if contain & PAINT != 0 {
+        bits |= gecko_style_structs::NS_STYLE_CONTAIN_PAINT;
+    }
+    bits as u8
+}
+
+fn clear_to_gecko(clear: Clear) -> u8 {
+    (match clear {
+        Clear::none => gecko_style_structs::NS_STYLE_CLEAR_NONE,
+        Clear::left => gecko_style_structs::NS_STYLE_CLEAR_LEFT,
+        Clear::right => gecko_style_structs::NS_STYLE_CLEAR_RIGHT,
+        Clear::both => gecko_style_structs::NS_STYLE_CLEAR_BOTH,
+    }) as u8
+}
+
+fn mix_blend_mode_to_gecko(mode: MixBlendMode) -> u8 {
+    (match mode {
+        MixBlendMode::normal => gecko_style_structs::NS_STYLE_BLEND_NORMAL,
+        MixBlendMode::multiply => gecko_style_structs::NS_STYLE_BLEND_MULTIPLY,
+        MixBlendMode::screen => gecko_style_structs::NS_STYLE_BLEND_SCREEN,
+        MixBlendMode::overlay => gecko_style_structs::NS_STYLE_BLEND_OVERLAY,
+        MixBlendMode::darken => gecko_style_structs::NS_STYLE_BLEND_DARKEN,
+        MixBlendMode::lighten => gecko_style_structs::NS_STYLE_BLEND_LIGHTEN,
+        MixBlendMode::color_dodge => gecko_style_structs::NS_STYLE_BLEND_COLOR_DODGE,
+        MixBlendMode::color_burn => gecko_style_structs::NS_STYLE_BLEND_COLOR_BURN,
+        MixBlendMode::hard_light => gecko_style_structs::NS_STYLE_BLEND_HARD_LIGHT,
+        MixBlendMode::soft_light => gecko_style_structs::NS_STYLE_BLEND_SOFT_LIGHT,
+        MixBlendMode::difference => gecko_style_structs::NS_STYLE_BLEND_DIFFERENCE,
+        MixBlendMode::exclusion => gecko_style_structs::NS_STYLE_BLEND_EXCLUSION,
+        MixBlendMode::hue => gecko_style_structs::NS_STYLE_BLEND_HUE,
+        MixBlendMode::saturation => gecko_style_structs::NS_STYLE_BLEND_SATURATION,
+        MixBlendMode::color => gecko_style_structs::NS_STYLE_BLEND_COLOR,
+        MixBlendMode::luminosity => gecko_style_structs::NS_STYLE_BLEND_LUMINOSITY,
+    }) as u8
+}
+
+/// `none` has a unit of its own, which only max-width and max-height use.
+fn length_or_percentage_or_none_to_ffi(value: LengthOrPercentageOrNone) -> ServoLengthOrPercentageOrAuto {
+    length_or_percentage_or_auto_to_ffi(match value {
+        LengthOrPercentageOrNone::Length(au) => LengthOrPercentageOrAuto::Length(au),
+        LengthOrPercentageOrNone::Percentage(percent) => LengthOrPercentageOrAuto::Percentage(percent),
+        LengthOrPercentageOrNone::Calc(calc) => LengthOrPercentageOrAuto::Calc(calc),
+        LengthOrPercentageOrNone::None => {
+            return ServoLengthOrPercentageOrAuto {
+                mUnit: ServoLengthUnit::None,
+                ..length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto)
+            }
+        }
+    })
+}
+
+/// The intrinsic size keywords go to Gecko as the NS_STYLE_WIDTH_* keyword, with the Keyword
+/// unit telling them apart from lengths.
+fn extremum_length_to_ffi(keyword: ExtremumLength) -> ServoLengthOrPercentageOrAuto {
+    ServoLengthOrPercentageOrAuto {
+        mUnit: ServoLengthUnit::Keyword,
+        mLength: (match keyword {
+            ExtremumLength::MaxContent => gecko_style_structs::NS_STYLE_WIDTH_MAX_CONTENT,
+            ExtremumLength::MinContent => gecko_style_structs::NS_STYLE_WIDTH_MIN_CONTENT,
+            ExtremumLength::FitContent => gecko_style_structs::NS_STYLE_WIDTH_FIT_CONTENT,
+            ExtremumLength::FillAvailable => gecko_style_structs::NS_STYLE_WIDTH_AVAILABLE,
+        }) as i32,
+        ..length_or_percentage_or_auto_to_ffi(LengthOrPercentageOrAuto::Auto)
+    }
+}
+
+fn moz_length_to_ffi(value: MozLength) -> ServoLengthOrPercentageOrAuto {
+    match value {
+        MozLength::LengthOrPercentageOrAuto(length) => length_or_percentage_or_auto_to_ffi(length),
+        MozLength::ExtremumLength(keyword) => extremum_length_to_ffi(keyword),
+    }
+}
+
+fn min_length_to_ffi(value: MinLength) -> ServoLengthOrPercentageOrAuto {
+    match value {
+        MinLength::LengthOrPercentage(length) => length_or_percentage_to_ffi(length),
+        MinLength::ExtremumLength(keyword) => extremum_length_to_ffi(keyword),
+    }
+}
+
+fn max_length_to_ffi(value: MaxLength) -> ServoLengthOrPercentageOrAuto {
+    match value {
+        MaxLength::LengthOrPercentageOrNone(length) => length_or_percentage_or_none_to_ffi(length),
+        MaxLength::ExtremumLength(keyword) => extremum_length_to_ffi(keyword),
+    }
+}
+
+style_struct_getter! {
+    fn Servo_GetStyleDisplay(values) -> nsStyleDisplay_FFI [construct: Gecko_Construct_nsStyleDisplay] {
+        mOpacity: values.get_effects().clone_opacity(),
+        mDisplay: display_to_gecko(values.get_box().clone_display()),
+        mOriginalDisplay: display_to_gecko(values.get_box().clone__servo_display_for_hypothetical_box()),
+        mContain: contain_to_gecko(values.get_box().clone_contain().0),
+        mTouchAction: touch_action_to_gecko(values.get_box().clone_touch_action()),
+        mPosition: (match values.get_box().clone_position() {
+            Position::static_ => gecko_style_structs::NS_STYLE_POSITION_STATIC,
+            Position::absolute => gecko_style_structs::NS_STYLE_POSITION_ABSOLUTE,
+            Position::relative => gecko_style_structs::NS_STYLE_POSITION_RELATIVE,
+            Position::fixed => gecko_style_structs::NS_STYLE_POSITION_FIXED,
+        }) as u8,
+        mFloats: (match values.get_box().clone_float() {
+            Float::none => gecko_style_structs::NS_STYLE_FLOAT_NONE,
+            Float::left => gecko_style_structs::NS_STYLE_FLOAT_LEFT,
+            Float::right => gecko_style_structs::NS_STYLE_FLOAT_RIGHT,
+        }) as u8,
+        mBreakType: clear_to_gecko(values.get_box().clone_clear()),
+        mOverflowX: overflow_to_gecko(values.get_box().clone_overflow_x()),
+        mOverflowY: overflow_to_gecko(values.get_box().clone_overflow_y().0),
+        mResize: resize_to_gecko(values.get_box().clone_resize()),
+        mMixBlendMode: mix_blend_mode_to_gecko(values.get_effects().clone_mix_blend_mode()),
+        mIsolation: (match values.get_effects().clone_isolation() {
+            Isolation::auto => gecko_style_structs::NS_STYLE_ISOLATION_AUTO,
+            Isolation::isolate => gecko_style_structs::NS_STYLE_ISOLATION_ISOLATE,
+        }) as u8,
+        mScrollSnapTypeX: scroll_snap_type_x_to_gecko(values.get_box().clone_scroll_snap_type_x()),
+        mScrollSnapTypeY: scroll_snap_type_y_to_gecko(values.get_box().clone_scroll_snap_type_y()),
+        mBackfaceVisibility: (match values.get_effects().clone_backface_visibility() {
+            BackfaceVisibility::visible => gecko_style_structs::NS_STYLE_BACKFACE_VISIBILITY_VISIBLE,
+            BackfaceVisibility::hidden => gecko_style_structs::NS_STYLE_BACKFACE_VISIBILITY_HIDDEN,
+        }) as u8,
+        // Gecko has no `auto` transform style, which flattens like `flat` does.
+        mTransformStyle: (match values.get_effects().clone_transform_style() {
+            TransformStyle::auto | TransformStyle::flat => gecko_style_structs::NS_STYLE_TRANSFORM_STYLE_FLAT,
+            TransformStyle::preserve_3d => gecko_style_structs::NS_STYLE_TRANSFORM_STYLE_PRESERVE_3D,
+        }) as u8,
+    }
+}
+
+style_struct_getter! {
+    fn Servo_GetStyleMargin(values) -> nsStyleMargin_FFI [construct: Gecko_Construct_nsStyleMargin] {
+        mMargin: [
+            length_or_percentage_or_auto_to_ffi(values.get_margin().clone_margin_top()),
+            length_or_percentage_or_auto_to_ffi(values.get_margin().clone_margin_right()),
+            length_or_percentage_or_auto_to_ffi(values.get_margin().clone_margin_bottom()),
+            length_or_percentage_or_auto_to_ffi(values.get_margin().clone_margin_left()),
+        ],
+    }
+}
+
+style_struct_getter! {
+    fn Servo_GetStylePadding(values) -> nsStylePadding_FFI [construct: Gecko_Construct_nsStylePadding] {
+        mPadding: [
+            length_or_percentage_to_ffi(values.get_padding().clone_padding_top()),
+            length_or_percentage_to_ffi(values.get_padding().clone_padding_right()),
+            length_or_percentage_to_ffi(values.get_padding().clone_padding_bottom()),
+            length_or_percentage_to_ffi(values.get_padding().clone_padding_left()),
+        ],
+    }
+}
+
+style_struct_getter! {
+    fn Servo_GetStylePosition(values) -> nsStylePosition_FFI [construct: Gecko_Construct_nsStylePosition] {
+        mOffset: [
+            length_or_percentage_or_auto_to_ffi(values.get_positionoffsets().clone_top()),
+            length_or_percentage_or_auto_to_ffi(values.get_positionoffsets().clone_right()),
+            length_or_percentage_or_auto_to_ffi(values.get_positionoffsets().clone_bottom()),
+            length_or_percentage_or_auto_to_ffi(values.get_positionoffsets().clone_left()),
+        ],
+        mWidth: moz_length_to_ffi(values.get_box().clone_width()),
+        mMinWidth: min_length_to_ffi(values.get_box().clone_min_width()),
+        mMaxWidth: max_length_to_ffi(values.get_box().clone_max_width()),
+        mHeight: moz_length_to_ffi(values.get_box().clone_height()),
+        mMinHeight: min_length_to_ffi(values.get_box().clone_min_height()),
+        mMaxHeight: max_length_to_ffi(values.get_box().clone_max_height()),
+        mFlexBasis: flex_basis_to_ffi(values.get_flex().clone_flex_basis()),
+        mBoxSizing: (match values.get_box().clone_box_sizing() {
+            BoxSizing::content_box => gecko_style_structs::StyleBoxSizing::Content,
+            BoxSizing::border_box => gecko_style_structs::StyleBoxSizing::Border,
+        }) as u8,
+        mAlignContent: align_content_to_gecko(values.get_flex().clone_align_content()) as u16,
+        mAlignItems: align_items_to_gecko(values.get_flex().clone_align_items()),
+        mAlignSelf: align_self_to_gecko(values.get_flex().clone_align_self()),
+        mJustifyContent: justify_content_to_gecko(values.get_flex().clone_justify_content()) as u16,
+        mFlexDirection: flex_direction_to_gecko(values.get_flex().clone_flex_direction()),
+        mFlexWrap: flex_wrap_to_gecko(values.get_flex().clone_flex_wrap()),
+        mFlexBasisIsContent: values.get_flex().clone_flex_basis() == FlexBasis::Content,
+        mHasZIndex: values.get_box().clone_z_index() != ZIndex::Auto,
+        mOrder: values.get_flex().clone_order().0,
+        mFlexGrow: values.get_flex().clone_flex_grow().0,
+        mFlexShrink: values.get_flex().clone_flex_shrink().0,
+        mZIndex: values.get_box().clone_z_index().number_or_zero(),
+    }
+}
+
+style_struct_getter! {
+    fn Servo_GetStyleVisibility(values) -> nsStyleVisibility_FFI [construct: Gecko_Construct_nsStyleVisibility] {
+        mDirection: direction_to_gecko(values.get_inheritedbox().clone_direction()),
+        mVisible: (match values.get_inheritedbox().clone_visibility() {
+            Visibility::visible => gecko_style_structs::NS_STYLE_VISIBILITY_VISIBLE,
+            Visibility::hidden => gecko_style_structs::NS_STYLE_VISIBILITY_HIDDEN,
+        }) as u8,
+        mPointerEvents: pointer_events_to_gecko(values.get_pointing().clone_pointer_events()),
+        mWritingMode: (match values.get_inheritedbox().clone_writing_mode() {
+            WritingModeKeyword::horizontal_tb => gecko_style_structs::NS_STYLE_WRITING_MODE_HORIZONTAL_TB,
+            WritingModeKeyword::vertical_rl => gecko_style_structs::NS_STYLE_WRITING_MODE_VERTICAL_RL,
+            WritingModeKeyword::vertical_lr => gecko_style_structs::NS_STYLE_WRITING_MODE_VERTICAL_LR,
+        }) as u8,
+        // Gecko only has the one sideways orientation.
+        mTextOrientation: (match values.get_inheritedbox().clone_text_orientation() {
+            TextOrientation::sideways |
+            TextOrientation::sideways_left |
+            TextOrientation::sideways_right => gecko_style_structs::NS_STYLE_TEXT_ORIENTATION_SIDEWAYS,
+        }) as u8,
+        // Gecko has no pixelated rendering, and crisp-edges is the nearest it has.
+        mImageRendering: (match values.get_effects().clone_image_rendering() {
+            ImageRendering::Auto => gecko_style_structs::NS_STYLE_IMAGE_RENDERING_AUTO,
+            ImageRendering::CrispEdges | ImageRendering::Pixelated => {
+                gecko_style_structs::NS_STYLE_IMAGE_RENDERING_CRISPEDGES
+            }
+        }) as u8,
+    }
+}
+
+servo_function! {
+    /// Writes out the resolved value of `property`, as `getComputedStyle` reports it. Returns
+    /// false, leaving `value` alone, if Servo can't serialize the property yet.
+    fn Servo_GetComputedPropertyValue(values: *mut ServoComputedValues, property: nsCSSProperty,
+                                      value: *mut nsString) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, value; false);
+        let name = match longhand_name_from_gecko(property) {
+            Some(name) => name,
+            None => return false,
+        };
+        match Helpers::with(values, |values| values.resolved_value_to_string(name)) {
+            Ok(resolved_value) => {
+                write_to_string(value, &resolved_value);
+                true
+            }
+            Err(()) => false,
+        }
+    }
+}
+
+servo_function! {
+    /// Writes out the computed value of the custom property called `name`, which includes
+    /// its leading `--`. Returns false if the property has its initial value.
+    fn Servo_GetCustomPropertyValue(values: *mut ServoComputedValues,
+                                    name: *const u8, name_length: u32,
+                                    value: *mut nsString) -> bool [on_panic: false] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values, value; false);
+        let name = unsafe { str_from_raw(name, name_length) };
+        let name = match custom_properties::parse_name(name) {
+            Ok(name) => Atom::from(name),
+            Err(()) => return false,
+        };
+        let computed_value = Helpers::with(values, |values| {
+            values.custom_properties().and_then(|map| map.get(&name).map(|value| value.to_css_string()))
+        });
+        match computed_value {
+            Some(computed_value) => {
+                write_to_string(value, &computed_value);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+servo_function! {
+    fn Servo_GetCustomPropertiesCount(values: *mut ServoComputedValues) -> u32 [on_panic: 0] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        Helpers::with(values, |values| values.custom_properties().map_or(0, |map| map.len() as u32))
+    }
+}
+
+servo_function! {
+    /// Measures |values| and the style structs it points to. Style structs it shares with
+    /// other styles are counted by each of them.
+    fn Servo_ComputedValues_SizeOf(malloc_size_of: MallocSizeOfFn, values: *mut ServoComputedValues)
+         -> usize [on_panic: 0] {
+        type Helpers = ArcHelpers<ServoComputedValues, GeckoComputedValues>;
+        return_if_null!(values; 0);
+        let mut ops = MallocSizeOfOps::new(malloc_size_of);
+        Helpers::with(values, |values| values.malloc_size_of_children(&mut ops))
+    }
+}
+
+servo_function! {
+    fn Servo_AddRefComputedValues(ptr: *mut ServoComputedValues) -> () [on_panic: abort()] {
+        unsafe { ownership::addref::<GeckoComputedValues>(ptr) };
+    }
+}
+
+servo_function! {
+    fn Servo_ReleaseComputedValues(ptr: *mut ServoComputedValues) -> () [on_panic: abort()] {
+        unsafe { ownership::release::<GeckoComputedValues>(ptr) };
+    }
+}
+
+servo_function! {
+    /// Makes the data Servo keeps for |document|: its stylesheets and stylist, what changed
+    /// since the last restyle, and the device it's styled for.
+    fn Servo_PerDocumentData_Create(document: *mut RawGeckoDocument)
+                                    -> *mut RawServoPerDocumentData [on_panic: abort()] {
+        return_if_null!(document; ptr::null_mut());
+        let mut data = Box::new(PerDocumentStyleData::new(document));
+        let compat_mode = unsafe { Gecko_GetDocumentCompatMode(document) };
+        data.set_quirks_mode(quirks_mode_from_gecko(compat_mode));
+        Box::into_raw(data) as *mut RawServoPerDocumentData
+    }
+}
+
+servo_function! {
+    /// Shuts the style set down ahead of dropping it, which Gecko can call early in the
+    /// teardown of its document so that the waiting it takes happens then: the sheet parses
+    /// started for the set are cancelled, and the traversals running from it finished, once
+    /// this returns. Restyling the set does nothing afterwards. Dropping a set shuts it down
+    /// first if it wasn't already.
+    fn Servo_StyleSet_Shutdown(set: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        return_if_null!(set; ());
+        PerDocumentStyleData::borrow_mut_from_raw(set).shutdown();
+    }
+}
+
+servo_function! {
+    fn Servo_PerDocumentData_Drop(data: *mut RawServoPerDocumentData) -> () [on_panic: abort()] {
+        if data.is_null() {
+            return;
+        }
+        unsafe {
+            let _ = Box::<PerDocumentStyleData>::from_raw(data as *mut PerDocumentStyleData);
+        }
+    }
+}
+
+servo_function! {
+    /// The style set of |data|, for the functions that still take one. It lives as long as
+    /// |data| does, and mustn't be passed to Servo_DropStyleSet.
+    fn Servo_PerDocumentData_GetStyleSet(data: *mut RawServoPerDocumentData)
+                                         -> *mut RawServoStyleSet [on_panic: ptr::null_mut()] {
+        return_if_null!(data; ptr::null_mut());
+        data as *mut RawServoStyleSet
+    }
+}
+
+servo_function! {
+    /// Kept until Gecko moves to Servo_PerDocumentData_Create.
+    fn Servo_InitStyleSet() -> *mut RawServoStyleSet [on_panic: abort()] {
+        let data = Box::new(PerDocumentStyleData::new(ptr::null_mut()));
+        Box::into_raw(data) as *mut RawServoStyleSet
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Servo_DropStyleSet(data: *mut RawServoStyleSet) -> () {
-    unsafe {
-        let _ = Box::<PerDocumentStyleData>::from_raw(data as *mut PerDocumentStyleData);
+servo_function! {
+    /// Kept until Gecko moves to Servo_PerDocumentData_Drop.
+    fn Servo_DropStyleSet(data: *mut RawServoStyleSet) -> () [on_panic: abort()] {
+        Servo_PerDocumentData_Drop(data as *mut RawServoPerDocumentData);
     }
 }
@@ -0,0 +1,147 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Has Gecko load the images computed styles refer to.
+
+#![allow(unsafe_code)]
+
+use bindings::{Gecko_LoadImage, Gecko_ReleaseImageRequest};
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_void;
+use std::sync::{Arc, Mutex};
+use style::url_value::{ImageRequest, UrlExtraData};
+use url::Url;
+use url_extra_data::url_extra_data_to_gecko;
+
+thread_local!(static IS_MAIN_THREAD: Cell<bool> = Cell::new(false));
+
+lazy_static! {
+    /// The requests whose last style was freed off the main thread, by address, which only the
+    /// main thread may release.
+    static ref RELEASES_FOR_MAIN_THREAD: Mutex<Vec<usize>> = Mutex::new(vec![]);
+    /// The loads styles computed off the main thread asked for, with the data of the sheet
+    /// each url came from, which only the main thread may start.
+    static ref LOADS_FOR_MAIN_THREAD: Mutex<Vec<(String, UrlExtraData, Arc<Mutex<DeferredLoad>>)>> =
+        Mutex::new(vec![]);
+}
+
+fn is_main_thread() -> bool {
+    IS_MAIN_THREAD.with(|is_main_thread| is_main_thread.get())
+}
+
+/// Marks the calling thread as Gecko's main thread, the one requests are started and released
+/// on.
+pub fn set_main_thread() {
+    IS_MAIN_THREAD.with(|is_main_thread| is_main_thread.set(true));
+}
+
+/// Starts the loads styles computed on other threads asked for, for the styles still holding
+/// on to them. Only the main thread may call this.
+pub fn start_loads_requested_elsewhere() {
+    debug_assert!(is_main_thread());
+    let loads = mem::replace(&mut *LOADS_FOR_MAIN_THREAD.lock().unwrap(), vec![]);
+    for (url, extra_data, load) in loads {
+        let mut load = load.lock().unwrap();
+        let queued = match *load {
+            DeferredLoad::Queued => true,
+            DeferredLoad::Started(_) | DeferredLoad::Abandoned => false,
+        };
+        if queued {
+            *load = DeferredLoad::Started(start_load(&url, &extra_data) as usize);
+        }
+    }
+}
+
+/// Releases the requests whose last style was freed on another thread. Only the main thread
+/// may call this.
+pub fn release_requests_dropped_elsewhere() {
+    debug_assert!(is_main_thread());
+    let requests = mem::replace(&mut *RELEASES_FOR_MAIN_THREAD.lock().unwrap(), vec![]);
+    for request in requests {
+        unsafe { Gecko_ReleaseImageRequest(request as *mut c_void) }
+    }
+}
+
+/// How far along the load a style computed off the main thread asked for is.
+enum DeferredLoad {
+    /// The main thread hasn't gotten to it yet.
+    Queued,
+    /// The main thread started it, and this is the request, or null if Gecko wouldn't load
+    /// the image.
+    Started(usize),
+    /// Every style holding the request was dropped, so there's nothing to start or release.
+    Abandoned,
+}
+
+/// A strong reference to an imgRequestProxy, or to the one the main thread will start.
+enum GeckoImageRequest {
+    Started(*mut c_void),
+    Deferred(Arc<Mutex<DeferredLoad>>),
+}
+
+// Gecko only ever touches the request on the main thread, and we merely hold on to it.
+unsafe impl Send for GeckoImageRequest {}
+unsafe impl Sync for GeckoImageRequest {}
+
+impl ImageRequest for GeckoImageRequest {
+    fn as_ptr(&self) -> usize {
+        match *self {
+            GeckoImageRequest::Started(request) => request as usize,
+            GeckoImageRequest::Deferred(ref load) => match *load.lock().unwrap() {
+                DeferredLoad::Started(request) => request,
+                DeferredLoad::Queued | DeferredLoad::Abandoned => 0,
+            },
+        }
+    }
+}
+
+impl Drop for GeckoImageRequest {
+    fn drop(&mut self) {
+        let request = match *self {
+            GeckoImageRequest::Started(request) => request,
+            GeckoImageRequest::Deferred(ref load) => {
+                match mem::replace(&mut *load.lock().unwrap(), DeferredLoad::Abandoned) {
+                    DeferredLoad::Started(request) if request != 0 => request as *mut c_void,
+                    _ => return,
+                }
+            }
+        };
+        // Styles can be freed off the main thread, by the traversal and by the node data
+        // dropper, and the last of them to go may hold the request.
+        if is_main_thread() {
+            unsafe { Gecko_ReleaseImageRequest(request) }
+        } else {
+            RELEASES_FOR_MAIN_THREAD.lock().unwrap().push(request as usize);
+        }
+    }
+}
+
+fn start_load(url: &str, extra_data: &UrlExtraData) -> *mut c_void {
+    let request = unsafe {
+        Gecko_LoadImage(url.as_ptr(), url.len() as u32, url_extra_data_to_gecko(extra_data))
+    };
+    if request.is_null() {
+        debug!("Gecko didn't start a load for image {}", url);
+    }
+    request
+}
+
+/// Starts loading `url` on behalf of the sheet `extra_data` came from, or returns None if
+/// Gecko won't load it. Gecko's image loader only runs on the main thread, so the style
+/// threads of the traversal leave the load to start_loads_requested_elsewhere, and the
+/// request stays null until then.
+pub fn load_image(url: &Url, extra_data: &UrlExtraData) -> Option<Box<ImageRequest>> {
+    let url = url.serialize();
+    if !is_main_thread() {
+        let load = Arc::new(Mutex::new(DeferredLoad::Queued));
+        LOADS_FOR_MAIN_THREAD.lock().unwrap().push((url, extra_data.clone(), load.clone()));
+        return Some(Box::new(GeckoImageRequest::Deferred(load)))
+    }
+    let request = start_load(&url, extra_data);
+    if request.is_null() {
+        return None
+    }
+    Some(Box::new(GeckoImageRequest::Started(request)))
+}
@@ -6,12 +6,15 @@
 #![feature(box_syntax)]
 #![feature(ptr_as_ref)]
 #![feature(custom_derive)]
+#![feature(panic_handler)]
 #![feature(plugin)]
+#![feature(recover)]
 
 #![plugin(heapsize_plugin)]
 #![plugin(plugins)]
 
 extern crate app_units;
+extern crate backtrace;
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
@@ -29,18 +32,37 @@ extern crate smallvec;
 #[macro_use(atom, ns)]
 extern crate string_cache;
 extern crate style;
+extern crate style_traits;
 extern crate url;
 extern crate util;
 
 #[allow(dead_code, non_camel_case_types)]
 mod bindings;
+mod atoms;
 mod data;
+mod element_state;
+#[macro_use]
+mod ffi;
 #[allow(dead_code, non_camel_case_types, non_snake_case, non_upper_case_globals)]
 mod gecko_style_structs;
 #[allow(non_snake_case)]
 pub mod glue;
+mod image_request;
+mod logging;
+mod look_and_feel;
+mod main_thread;
+#[cfg(test)]
+#[allow(dead_code)]
+mod mock_gecko;
+mod node_data_dropper;
+mod ownership;
+mod platform_values;
+mod restyle_damage;
 mod selector_impl;
+mod sheet_parser;
+mod transitions;
 mod traversal;
+mod url_extra_data;
 mod wrapper;
 
 // Generated from the properties.mako.rs template by build.rs
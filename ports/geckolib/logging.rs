@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Sends the `log` crate's records to Gecko's logging, so that they show up with
+//! `MOZ_LOG`.
+
+#![allow(unsafe_code)]
+
+use bindings::Gecko_Log;
+use log::{self, Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+use std::env;
+use std::io::{self, Write};
+use std::os::raw::c_char;
+
+/// Forwards every record to Gecko_Log, which decides whether it's wanted. Gecko can
+/// be called from any thread, style workers included.
+struct GeckoLogger;
+
+// Keep these in sync with mozilla::LogLevel in xpcom/base/Logging.h.
+fn gecko_log_level(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Error => 1,
+        LogLevel::Warn => 2,
+        LogLevel::Info => 3,
+        LogLevel::Debug => 4,
+        LogLevel::Trace => 5,
+    }
+}
+
+impl Log for GeckoLogger {
+    fn enabled(&self, _metadata: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        // Gecko wants the module name NUL-terminated, so it goes in the same buffer as
+        // the message to only allocate once.
+        let module = record.location().module_path();
+        let buffer = format!("{}\0{}", module, record.args());
+        let message = &buffer[module.len() + 1..];
+        unsafe {
+            Gecko_Log(gecko_log_level(record.level()), buffer.as_ptr() as *const c_char,
+                      message.as_ptr() as *const c_char, message.len() as u32);
+        }
+    }
+}
+
+/// Prints records to stderr, filtered by the level `RUST_LOG` names, like env_logger
+/// does. Module filters aren't supported.
+struct StderrLogger {
+    level: LogLevelFilter,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(io::stderr(), "{}:{}: {}", record.level(), record.location().module_path(),
+                             record.args());
+        }
+    }
+}
+
+/// Installs the logger, which forwards to Gecko unless `RUST_LOG` is set. That's for
+/// running without Gecko's logging, as standalone tests do.
+pub fn install() {
+    let result = match env::var("RUST_LOG").ok().and_then(|level| level.parse().ok()) {
+        Some(level) => log::set_logger(|max_level| {
+            max_level.set(level);
+            Box::new(StderrLogger { level: level })
+        }),
+        None => log::set_logger(|max_level| {
+            max_level.set(LogLevelFilter::Trace);
+            Box::new(GeckoLogger)
+        }),
+    };
+    if result.is_err() {
+        warn!("A logger was already installed, so Servo logging won't reach Gecko");
+    }
+}
+
+/// Removes the logger, freeing it.
+pub fn uninstall() {
+    let _ = log::shutdown_logger();
+}
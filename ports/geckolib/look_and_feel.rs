@@ -0,0 +1,102 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Looks up system colors and fonts in Gecko's LookAndFeel.
+
+#![allow(unsafe_code)]
+
+use app_units::Au;
+use bindings::{Gecko_GetLookAndFeelColor, Gecko_GetSystemFont, SystemFontData};
+use cssparser::RGBA;
+use properties::convert_nscolor_to_rgba;
+use std::ptr;
+use std::slice;
+use std::str;
+use string_cache::Atom;
+use style::properties::longhands::font_family::computed_value::{FontFamily, T as FontFamilyList};
+use style::properties::longhands::font_style::computed_value::T as FontStyle;
+use style::properties::longhands::font_weight::computed_value::T as FontWeight;
+use style::system_values::{SystemColor, SystemFont, SystemFontValues};
+use wrapper::use_standin_system_colors;
+
+/// LookAndFeel::eColorID_activeborder. The other CSS2 system colors follow it in the order
+/// SystemColor lists them in.
+const FIRST_CSS2_COLOR_ID: i32 = 30;
+
+/// LookAndFeel::eFont_Caption, which the other CSS2 system fonts follow in the order
+/// SystemFont lists them in.
+const FIRST_CSS2_FONT_ID: i32 = 1;
+
+/// Every system color, in the order SystemColor lists them in.
+pub const SYSTEM_COLORS: [SystemColor; 28] = [
+    SystemColor::ActiveBorder, SystemColor::ActiveCaption, SystemColor::AppWorkspace, SystemColor::Background,
+    SystemColor::ButtonFace, SystemColor::ButtonHighlight, SystemColor::ButtonShadow, SystemColor::ButtonText,
+    SystemColor::CaptionText, SystemColor::GrayText, SystemColor::Highlight, SystemColor::HighlightText,
+    SystemColor::InactiveBorder, SystemColor::InactiveCaption, SystemColor::InactiveCaptionText,
+    SystemColor::InfoBackground, SystemColor::InfoText, SystemColor::Menu, SystemColor::MenuText,
+    SystemColor::Scrollbar, SystemColor::ThreeDDarkShadow, SystemColor::ThreeDFace, SystemColor::ThreeDHighlight,
+    SystemColor::ThreeDLightShadow, SystemColor::ThreeDShadow, SystemColor::Window, SystemColor::WindowFrame,
+    SystemColor::WindowText,
+];
+
+/// Every system font, in the order SystemFont lists them in.
+pub const SYSTEM_FONTS: [SystemFont; 6] = [
+    SystemFont::Caption, SystemFont::Icon, SystemFont::Menu, SystemFont::MessageBox, SystemFont::SmallCaption,
+    SystemFont::StatusBar,
+];
+
+/// The color Gecko gives `color`, or its stand-in if Gecko doesn't know it or the document
+/// being styled mustn't see it. Only the main thread may call this.
+pub fn system_color(color: SystemColor) -> RGBA {
+    let use_standins = use_standin_system_colors();
+    let mut value = 0;
+    let found = unsafe {
+        Gecko_GetLookAndFeelColor(FIRST_CSS2_COLOR_ID + color as i32, use_standins, &mut value)
+    };
+    if !found {
+        return color.standin()
+    }
+    convert_nscolor_to_rgba(value)
+}
+
+/// What Gecko says `font` is, if it knows it. Only the main thread may call this.
+pub fn system_font(font: SystemFont) -> Option<SystemFontValues> {
+    let mut data = SystemFontData {
+        mFamily: ptr::null(),
+        mFamilyLength: 0,
+        mSize: 0.,
+        mWeight: 400,
+        mItalic: false,
+    };
+    if !unsafe { Gecko_GetSystemFont(FIRST_CSS2_FONT_ID + font as i32, &mut data) } || data.mFamily.is_null() {
+        return None
+    }
+    // The family name stays Gecko's until the next call.
+    let family = unsafe { slice::from_raw_parts(data.mFamily, data.mFamilyLength as usize) };
+    let family = match str::from_utf8(family) {
+        Ok(family) => FontFamily::from_atom(Atom::from(family)),
+        Err(_) => return None,
+    };
+    Some(SystemFontValues {
+        family: FontFamilyList(vec![family]),
+        size: Au::from_f32_px(data.mSize),
+        weight: font_weight(data.mWeight),
+        style: if data.mItalic { FontStyle::italic } else { FontStyle::normal },
+    })
+}
+
+/// The closest keyword weight to a numeric one.
+fn font_weight(weight: u16) -> FontWeight {
+    match (weight as u32 + 50) / 100 {
+        0 | 1 => FontWeight::Weight100,
+        2 => FontWeight::Weight200,
+        3 => FontWeight::Weight300,
+        4 => FontWeight::Weight400,
+        5 => FontWeight::Weight500,
+        6 => FontWeight::Weight600,
+        7 => FontWeight::Weight700,
+        8 => FontWeight::Weight800,
+        _ => FontWeight::Weight900,
+    }
+}
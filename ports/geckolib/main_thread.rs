@@ -0,0 +1,140 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Keeps what only Gecko's main thread may free from being freed anywhere else.
+//!
+//! Styles are reference counted atomically, so the last reference to one can go on whichever
+//! thread happens to drop it: a traversal worker, or the node data dropper. What a style holds
+//! that has to be released on the main thread is wrapped in a `MainThreadOwned`, which debug
+//! builds check the thread of when it's dropped, through Gecko_IsMainThread. The threads that
+//! are meant to free styles opt into `release_elsewhere_from_this_thread`, and have Gecko
+//! release what they drop on the main thread instead, through Gecko_DispatchRelease. Image
+//! requests have a queue of their own, see image_request.rs.
+
+#![allow(unsafe_code)]
+
+use bindings::{Gecko_DispatchRelease, Gecko_IsMainThread};
+use heapsize::HeapSizeOf;
+use std::cell::Cell;
+use std::fmt;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_void;
+use std::sync::Mutex;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, AtomicBool, Ordering};
+use std::thread;
+
+/// Whether Servo_Initialize has run, and so whether Gecko_IsMainThread can tell the threads
+/// apart. Nothing is checked before that.
+static MAIN_THREAD_RECORDED: AtomicBool = ATOMIC_BOOL_INIT;
+
+thread_local!(static RELEASES_ELSEWHERE: Cell<bool> = Cell::new(false));
+
+lazy_static! {
+    /// The names of the threads something was wrongly dropped on since the last call to
+    /// take_off_thread_drops, once for each thing.
+    static ref OFF_THREAD_DROPS: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+/// Records that the calling thread, which Servo_Initialize runs on, is Gecko's main thread.
+pub fn record_main_thread() {
+    debug_assert!(is_main_thread(), "Servo_Initialize was called off the main thread");
+    MAIN_THREAD_RECORDED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_main_thread() -> bool {
+    unsafe { Gecko_IsMainThread() }
+}
+
+/// Whether the calling thread is somewhere other than the main thread, as far as can be told.
+fn is_off_main_thread() -> bool {
+    MAIN_THREAD_RECORDED.load(Ordering::SeqCst) && !is_main_thread()
+}
+
+/// Has whatever the calling thread drops from now on that only the main thread may free go
+/// there to be freed, rather than be checked. Only threads whose job it is to free styles
+/// should call this.
+pub fn release_elsewhere_from_this_thread() {
+    RELEASES_ELSEWHERE.with(|releases_elsewhere| releases_elsewhere.set(true));
+}
+
+/// Drops |value| right away on the main thread, and has Gecko drop it there later otherwise.
+pub fn release_on_main_thread<T: Send + 'static>(value: T) {
+    if !is_off_main_thread() {
+        return drop(value)
+    }
+    unsafe { Gecko_DispatchRelease(Box::into_raw(Box::new(value)) as *mut c_void, Some(release_boxed::<T>)) }
+}
+
+unsafe extern "C" fn release_boxed<T>(value: *mut c_void) {
+    drop(Box::from_raw(value as *mut T))
+}
+
+/// Forgets the drops made off the main thread so far, and returns the name of the thread each
+/// was made on.
+pub fn take_off_thread_drops() -> Vec<String> {
+    mem::replace(&mut *OFF_THREAD_DROPS.lock().unwrap(), vec![])
+}
+
+/// A value that only the main thread may drop.
+pub struct MainThreadOwned<T: Send + 'static>(Option<T>);
+
+impl<T: Send + 'static> MainThreadOwned<T> {
+    pub fn new(value: T) -> MainThreadOwned<T> {
+        MainThreadOwned(Some(value))
+    }
+}
+
+impl<T: Send + 'static> Drop for MainThreadOwned<T> {
+    fn drop(&mut self) {
+        let value = match self.0.take() {
+            Some(value) => value,
+            None => return,
+        };
+        if !is_off_main_thread() {
+            return drop(value)
+        }
+        if RELEASES_ELSEWHERE.with(|releases_elsewhere| releases_elsewhere.get()) {
+            return release_on_main_thread(value)
+        }
+        // It's freed here all the same, as it would be without the check.
+        if cfg!(debug_assertions) {
+            let thread = thread::current().name().unwrap_or("<unnamed>").to_owned();
+            error!("A value only the main thread may free was dropped on thread {}", thread);
+            OFF_THREAD_DROPS.lock().unwrap().push(thread);
+        }
+    }
+}
+
+impl<T: Send + 'static> Deref for MainThreadOwned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl<T: Send + 'static> DerefMut for MainThreadOwned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl<T: Clone + Send + 'static> Clone for MainThreadOwned<T> {
+    fn clone(&self) -> MainThreadOwned<T> {
+        MainThreadOwned::new((**self).clone())
+    }
+}
+
+impl<T: fmt::Debug + Send + 'static> fmt::Debug for MainThreadOwned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: HeapSizeOf + Send + 'static> HeapSizeOf for MainThreadOwned<T> {
+    fn heap_size_of_children(&self) -> usize {
+        (**self).heap_size_of_children()
+    }
+}
@@ -0,0 +1,1072 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The mock's definitions of the functions bindings/mod.rs declares Gecko to have, with the
+//! same signatures, in the same order.
+
+#![allow(non_snake_case)]
+
+use bindings::{RawGeckoContentItemList, RawGeckoContentList, RawGeckoDocument, RawGeckoElement};
+use bindings::{RawGeckoFontFeatureArray, RawGeckoGradientStopArray, ServoFontFeature, ServoGradientStop};
+use bindings::{RawGeckoCounterItemArray, RawGeckoCursorImageArray, ServoCounterItem, ServoCursorImage};
+use bindings::RawGeckoURLExtraData;
+use bindings::{RawGeckoFontFaceRule, RawGeckoFontFaceRuleList, RawGeckoKeyframeList, RawGeckoNode};
+use bindings::{RawGeckoPropertyList, RawGeckoRuleList, RawGeckoTransformList, RawServoAnimationValue};
+use bindings::{RawGeckoShadowArray, RawServoDeclarationBlock, RawServoDeclarationBlockStrong, RawServoImportRule};
+use bindings::{RawServoStyleRuleStrong, RawServoStyleSheet, RawServoStyleSheetStrong};
+use bindings::{ServoContentItem, ServoElementSnapshot, ServoNodeData, ServoShadowItem, ServoTimingFunction};
+use bindings::{ServoLengthOrPercentageOrAuto, ServoLengthUnit, ServoTransformFunction, ServoTransformOperation};
+use bindings::{NodeInfoFFI, RawServoAttributeSnapshot, ServoAttrSnapshot, ServoCSSErrorKind, ServoReleaseFn};
+use bindings::SystemFontData;
+use bindings::{nsCSSProperty, nsIAtom, nsString, nsStyleFont};
+use bindings::{nsStyleDisplay_FFI, nsStyleMargin_FFI, nsStylePadding_FFI, nsStylePosition_FFI};
+use bindings::nsStyleVisibility_FFI;
+use element_state::{NS_EVENT_STATE_UNVISITED, NS_EVENT_STATE_VISITED};
+use gecko_style_structs;
+use glue::{COMPATIBILITY_NAV_QUIRKS, Servo_AttributeSnapshot_SetLength};
+use ownership::Borrowed;
+use selector_impl::ImportRule;
+use std::ascii::AsciiExt;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::slice;
+use std::str;
+use std::sync::atomic::Ordering;
+use super::{ATTR_VALUE_COPIES, CRASH_ANNOTATIONS, DISPATCHED_RELEASES, HTML_NAMESPACE, IS_MAIN_THREAD};
+use super::{MAIN_THREAD_ONLY_CALLS_ELSEWHERE, PARENT_LOOKUPS, SIBLING_LOOKUPS};
+use super::{MockContentItem, MockContentItemList, MockContentList};
+use super::{MockFontFaceRule, MockFontFaceRuleList, MockFontFaceSource, MockFontFeatureArray, MockKeyframe};
+use super::{MockCounterItemArray, MockCursorImageArray, MockGradientStopArray, MockKeyframeList};
+use super::{MockNode, MockPropertyList, MockRule, MockRuleList, MockShadowArray, MockSheetContext, MockSnapshot};
+use super::{MockTransformList, MockTransition, MockUrlExtraData, NodeKind, XML_NAMESPACE, add_ref_atom, atom};
+use super::{atom_is_static, atom_str, atom_utf16, atomize, release_atom};
+use super::borrowed_block;
+
+unsafe fn mock<'a, T>(node: *mut T) -> &'a MockNode {
+    &*(node as *mut MockNode)
+}
+
+fn raw<T>(node: *mut MockNode) -> *mut T {
+    node as *mut T
+}
+
+unsafe fn string_from_raw<'a>(bytes: *const u8, length: u32) -> &'a str {
+    str::from_utf8(slice::from_raw_parts(bytes, length as usize)).unwrap()
+}
+
+unsafe fn string_from_option(string: *const u8, length: u32) -> String {
+    if string.is_null() {
+        String::new()
+    } else {
+        string_from_raw(string, length).to_owned()
+    }
+}
+
+/// Follows |step| from |node| until it reaches an element.
+unsafe fn element_from<F>(mut node: *mut MockNode, step: F) -> *mut MockNode where F: Fn(&MockNode) -> *mut MockNode {
+    while let Some(current) = node.as_ref() {
+        if current.is_element() {
+            return node;
+        }
+        node = step(current);
+    }
+    ptr::null_mut()
+}
+
+fn first_child(node: &MockNode) -> *mut MockNode {
+    node.inner.read().unwrap().first_child
+}
+
+fn last_child(node: &MockNode) -> *mut MockNode {
+    node.inner.read().unwrap().last_child
+}
+
+fn prev_sibling(node: &MockNode) -> *mut MockNode {
+    node.inner.read().unwrap().prev_sibling
+}
+
+fn next_sibling(node: &MockNode) -> *mut MockNode {
+    node.inner.read().unwrap().next_sibling
+}
+
+fn children(node: &MockNode) -> Vec<*mut MockNode> {
+    let mut children = vec![];
+    let mut child = first_child(node);
+    while let Some(node) = unsafe { child.as_ref() } {
+        children.push(child);
+        child = next_sibling(node);
+    }
+    children
+}
+
+fn owner_document(node: &MockNode) -> &MockNode {
+    unsafe { &*node.inner.read().unwrap().document }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ChildrenCount(node: *mut RawGeckoNode) -> u32 {
+    children(mock(node)).len() as u32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_NodeIsElement(node: *mut RawGeckoNode) -> bool {
+    mock(node).is_element()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_NodeIsDocument(node: *mut RawGeckoNode) -> bool {
+    match mock(node).inner.read().unwrap().kind {
+        NodeKind::Document(_) => true,
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsInDocument(node: *mut RawGeckoNode) -> bool {
+    let mut node = node as *mut MockNode;
+    while let Some(current) = node.as_ref() {
+        if Gecko_NodeIsDocument(raw(node)) {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetOwnerDocument(node: *mut RawGeckoNode) -> *mut RawGeckoDocument {
+    raw(mock(node).inner.read().unwrap().document)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetDocumentState(document: *mut RawGeckoDocument) -> u64 {
+    mock(document).with_document(|document| document.state)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsChromeDocument(document: *mut RawGeckoDocument) -> bool {
+    mock(document).with_document(|document| document.is_chrome)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsSignificantChild(node: *mut RawGeckoNode, text_is_significant: bool) -> bool {
+    match mock(node).inner.read().unwrap().kind {
+        NodeKind::Element(_) => true,
+        NodeKind::Text(ref text) if text_is_significant => !text.is_empty(),
+        NodeKind::Text(ref text) => !text.chars().all(|c| " \t\n\r\x0C".contains(c)),
+        NodeKind::Document(_) | NodeKind::Comment => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetNodeFlags(node: *mut RawGeckoNode) -> u32 {
+    mock(node).flags()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SetNodeFlags(node: *mut RawGeckoNode, flags: u32) {
+    mock(node).set_flags(flags)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_UnsetNodeFlags(node: *mut RawGeckoNode, flags: u32) {
+    mock(node).unset_flags(flags)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetNodeInfo(node: *mut RawGeckoNode, out: *mut NodeInfoFFI) {
+    let is_element = Gecko_NodeIsElement(node);
+    *out = NodeInfoFFI {
+        mParent: Gecko_GetParentNode(node),
+        mNextSibling: Gecko_GetNextSibling(node),
+        mState: if is_element { Gecko_ElementState(node as *mut RawGeckoElement) } else { 0 },
+        mFlags: Gecko_GetNodeFlags(node),
+        mIsElement: is_element,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetParentNode(node: *mut RawGeckoNode) -> *mut RawGeckoNode {
+    PARENT_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+    raw(mock(node).parent())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetFirstChild(node: *mut RawGeckoNode) -> *mut RawGeckoNode {
+    raw(first_child(mock(node)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetLastChild(node: *mut RawGeckoNode) -> *mut RawGeckoNode {
+    raw(last_child(mock(node)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetChildren(node: *mut RawGeckoNode, children_out: *mut *mut RawGeckoNode,
+                                           capacity: u32) -> u32 {
+    let children = children(mock(node));
+    for (index, &child) in children.iter().take(capacity as usize).enumerate() {
+        *children_out.offset(index as isize) = raw(child);
+    }
+    children.len() as u32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetPrevSibling(node: *mut RawGeckoNode) -> *mut RawGeckoNode {
+    raw(prev_sibling(mock(node)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetNextSibling(node: *mut RawGeckoNode) -> *mut RawGeckoNode {
+    raw(next_sibling(mock(node)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetParentElement(element: *mut RawGeckoElement) -> *mut RawGeckoElement {
+    PARENT_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+    let parent = mock(element).parent();
+    match parent.as_ref() {
+        Some(node) if node.is_element() => raw(parent),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetFirstChildElement(element: *mut RawGeckoElement) -> *mut RawGeckoElement {
+    raw(element_from(first_child(mock(element)), next_sibling))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetLastChildElement(element: *mut RawGeckoElement) -> *mut RawGeckoElement {
+    raw(element_from(last_child(mock(element)), prev_sibling))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetPrevSiblingElement(element: *mut RawGeckoElement) -> *mut RawGeckoElement {
+    SIBLING_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+    raw(element_from(prev_sibling(mock(element)), prev_sibling))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetNextSiblingElement(element: *mut RawGeckoElement) -> *mut RawGeckoElement {
+    SIBLING_LOOKUPS.fetch_add(1, Ordering::SeqCst);
+    raw(element_from(next_sibling(mock(element)), next_sibling))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetChildIndexHint(element: *mut RawGeckoElement) -> i32 {
+    let mut index = 0;
+    let mut sibling = element_from(prev_sibling(mock(element)), prev_sibling);
+    while let Some(node) = sibling.as_ref() {
+        index += 1;
+        sibling = element_from(prev_sibling(node), prev_sibling);
+    }
+    index
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetDocumentElement(document: *mut RawGeckoDocument) -> *mut RawGeckoElement {
+    raw(element_from(first_child(mock(document)), next_sibling))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetDocumentCompatMode(document: *mut RawGeckoDocument) -> u8 {
+    mock(document).with_document(|document| document.compat_mode)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ElementState(element: *mut RawGeckoElement) -> u64 {
+    mock(element).with_element(|element| element.state)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsHTMLElementInHTMLDocument(element: *mut RawGeckoElement) -> bool {
+    let node = mock(element);
+    node.with_element(|element| element.namespace == atom(HTML_NAMESPACE)) &&
+        owner_document(node).with_document(|document| document.is_html)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsInQuirksModeDocument(element: *mut RawGeckoElement) -> bool {
+    owner_document(mock(element)).with_document(|document| document.compat_mode == COMPATIBILITY_NAV_QUIRKS)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsLink(element: *mut RawGeckoElement) -> bool {
+    Gecko_IsVisitedLink(element) || Gecko_IsUnvisitedLink(element)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsTextNode(node: *mut RawGeckoNode) -> bool {
+    match mock(node).inner.read().unwrap().kind {
+        NodeKind::Text(_) => true,
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsNativeAnonymous(element: *mut RawGeckoElement) -> bool {
+    mock(element).with_element(|element| element.is_native_anonymous)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsVisitedLink(element: *mut RawGeckoElement) -> bool {
+    Gecko_ElementState(element) & NS_EVENT_STATE_VISITED.bits() != 0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsUnvisitedLink(element: *mut RawGeckoElement) -> bool {
+    Gecko_ElementState(element) & NS_EVENT_STATE_UNVISITED.bits() != 0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsRootElement(element: *mut RawGeckoElement) -> bool {
+    let parent = mock(element).parent();
+    !parent.is_null() && Gecko_NodeIsDocument(raw(parent))
+}
+
+/// Only knows :-moz-browser-frame, which matches <iframe mozbrowser>.
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_MatchesMozPseudoClass(element: *mut RawGeckoElement,
+                                                     pseudo_class: *mut nsIAtom) -> bool {
+    match atom_str(pseudo_class) {
+        ":-moz-browser-frame" => mock(element).with_element(|element| {
+            element.local_name == atom("iframe") && element.find_attr(atom(""), atom("mozbrowser")).is_some()
+        }),
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetNodeData(node: *mut RawGeckoNode) -> *mut ServoNodeData {
+    mock(node).data.load(Ordering::SeqCst)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SetNodeData(node: *mut RawGeckoNode, data: *mut ServoNodeData) {
+    mock(node).data.store(data, Ordering::SeqCst)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_IsMainThread() -> bool {
+    IS_MAIN_THREAD.with(|is_main_thread| is_main_thread.get())
+}
+
+/// Notes that Servo called |name|, which Gecko only lets its main thread call, if it was
+/// called on another thread.
+fn main_thread_only(name: &'static str) {
+    if !IS_MAIN_THREAD.with(|is_main_thread| is_main_thread.get()) {
+        MAIN_THREAD_ONLY_CALLS_ELSEWHERE.lock().unwrap().push(name);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_DispatchRelease(ptr: *mut c_void, release_fn: ServoReleaseFn) {
+    DISPATCHED_RELEASES.lock().unwrap().push((ptr as usize, release_fn.unwrap()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_StyleSheetLoadComplete(callback_context: *mut c_void,
+                                                      sheet: RawServoStyleSheetStrong) {
+    let context = &*(callback_context as *const MockSheetContext);
+    *context.loaded.lock().unwrap() = sheet.into_arc();
+    context.loaded_condvar.notify_all();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AnnotateCrashReport(key: *const u8, key_length: u32,
+                                                   value: *const u8, value_length: u32) {
+    let (key, value) = (string_from_raw(key, key_length), string_from_raw(value, value_length));
+    CRASH_ANNOTATIONS.lock().unwrap().insert(key.to_owned(), value.to_owned());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Log(_level: u8, _module: *const c_char, _message: *const c_char, _msg_len: u32) {
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetFontMetrics(_font: *const nsStyleFont, _out_x_height: *mut f32,
+                                              _out_ch_width: *mut f32) -> bool {
+    main_thread_only("Gecko_GetFontMetrics");
+    false
+}
+
+/// The mock's font.size.variable and font.size.fixed prefs: 16px, but 13px for monospace
+/// fonts, except in Japanese, whose monospace fonts are as big as the others.
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetBaseFontSize(generic_id: u8, language: *mut nsIAtom) -> f32 {
+    main_thread_only("Gecko_GetBaseFontSize");
+    const GENERIC_ID_MONOSPACE: u8 = 8;
+    if generic_id == GENERIC_ID_MONOSPACE && (language.is_null() || atom_str(language) != "ja") {
+        13.
+    } else {
+        16.
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetLookAndFeelColor(_color_id: i32, _use_standins: bool,
+                                                   _out_color: *mut u32) -> bool {
+    main_thread_only("Gecko_GetLookAndFeelColor");
+    false
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetSystemFont(_font_id: i32, _out: *mut SystemFontData) -> bool {
+    main_thread_only("Gecko_GetSystemFont");
+    false
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ReportCSSError(sheet_context: *mut c_void, message: *const c_char,
+                                              line: u32, column: u32) {
+    let context = &*(sheet_context as *const MockSheetContext);
+    let message = ::std::ffi::CStr::from_ptr(message).to_string_lossy();
+    context.errors.lock().unwrap().push(format!("{}:{}: {}", line, column, message));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ReportCSSErrorWithDetails(sheet_context: *mut c_void, error_kind: u8,
+                                                         selector: *const u8, len: u32, offset: u32,
+                                                         line: u32, column: u32) {
+    assert_eq!(error_kind, ServoCSSErrorKind::InvalidSelector as u8);
+    let context = &*(sheet_context as *const MockSheetContext);
+    let selector = string_from_raw(selector, len);
+    context.errors.lock().unwrap().push(format!("{}:{}: Invalid selector '{}' at '{}'", line, column, selector,
+                                                &selector[offset as usize..]));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_LoadStyleSheet(parent_sheet_context: *mut c_void,
+                                              extra_data: *mut RawGeckoURLExtraData,
+                                              import_rule: *mut RawServoImportRule,
+                                              url: *const u8, url_len: u32,
+                                              media: *const u8, media_len: u32) -> *mut c_void {
+    if parent_sheet_context.is_null() {
+        return ptr::null_mut();
+    }
+    if !extra_data.is_null() {
+        let loads = &MockUrlExtraData::from_raw(extra_data).loads;
+        loads.lock().unwrap().push(string_from_raw(url, url_len).to_owned());
+    }
+    let context = &*(parent_sheet_context as *const MockSheetContext);
+    let import_rule = Borrowed::from_raw(import_rule).as_arc::<ImportRule>().clone();
+    context.imports.lock().unwrap().push((string_from_raw(url, url_len).to_owned(),
+                                          string_from_raw(media, media_len).to_owned()));
+    let mut import_rules = context.import_rules.lock().unwrap();
+    import_rules.push(import_rule);
+    Borrowed::from_arc(import_rules.last().unwrap()).as_ptr() as *mut c_void
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_LoadImage(url: *const u8, url_len: u32,
+                                         extra_data: *mut RawGeckoURLExtraData) -> *mut c_void {
+    main_thread_only("Gecko_LoadImage");
+    if extra_data.is_null() {
+        return ptr::null_mut();
+    }
+    let url = string_from_raw(url, url_len).to_owned();
+    MockUrlExtraData::from_raw(extra_data).loads.lock().unwrap().push(url.clone());
+    Box::into_raw(Box::new(url)) as *mut c_void
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ReleaseImageRequest(request: *mut c_void) {
+    main_thread_only("Gecko_ReleaseImageRequest");
+    let _ = Box::from_raw(request as *mut String);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AddRefURLExtraData(extra_data: *mut RawGeckoURLExtraData) {
+    MockUrlExtraData::add_ref(extra_data)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ReleaseURLExtraData(extra_data: *mut RawGeckoURLExtraData) {
+    MockUrlExtraData::release(extra_data)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_RuleListAppend(list: *mut RawGeckoRuleList, sheet: *mut RawServoStyleSheet,
+                                              rule: RawServoStyleRuleStrong, selector_index: u32,
+                                              specificity: u32) {
+    (*(list as *mut MockRuleList)).rules.push(MockRule::Style {
+        sheet: sheet,
+        rule: rule.into_arc().unwrap(),
+        selector_index: selector_index,
+        specificity: specificity,
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_RuleListAppendDeclarations(list: *mut RawGeckoRuleList,
+                                                          declarations: RawServoDeclarationBlockStrong,
+                                                          is_style_attribute: bool) {
+    (*(list as *mut MockRuleList)).rules.push(MockRule::Declarations {
+        declarations: declarations.into_arc().unwrap(),
+        is_style_attribute: is_style_attribute,
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AppendKeyframe(keyframes: *mut RawGeckoKeyframeList, offset: f32,
+                                              timing_function: *const ServoTimingFunction,
+                                              declarations: RawServoDeclarationBlockStrong) {
+    (*(keyframes as *mut MockKeyframeList)).keyframes.push(MockKeyframe {
+        offset: offset,
+        timing_function: *timing_function,
+        declarations: declarations.into_arc().unwrap(),
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AppendFontFaceRule(rules: *mut RawGeckoFontFaceRuleList,
+                                                  sheet: *mut RawServoStyleSheet,
+                                                  family: *const u8, family_length: u32,
+                                                  weight: u16, style: u8, stretch: i16)
+                                                  -> *mut RawGeckoFontFaceRule {
+    let mut rule = Box::new(MockFontFaceRule {
+        sheet: sheet,
+        family: string_from_raw(family, family_length).to_owned(),
+        weight: weight,
+        style: style,
+        stretch: stretch,
+        sources: vec![],
+        unicode_ranges: vec![],
+    });
+    let raw_rule = &mut *rule as *mut MockFontFaceRule as *mut RawGeckoFontFaceRule;
+    (*(rules as *mut MockFontFaceRuleList)).rules.push(rule);
+    raw_rule
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_FontFaceRule_AppendURLSource(rule: *mut RawGeckoFontFaceRule,
+                                                            url: *const u8, url_length: u32) {
+    let url = string_from_raw(url, url_length).to_owned();
+    MockFontFaceRule::from_raw(rule).sources.push(MockFontFaceSource::Url(url, vec![]));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_FontFaceRule_AppendFormatHint(rule: *mut RawGeckoFontFaceRule,
+                                                             hint: *const u8, hint_length: u32) {
+    match MockFontFaceRule::from_raw(rule).sources.last_mut() {
+        Some(&mut MockFontFaceSource::Url(_, ref mut hints)) => {
+            hints.push(string_from_raw(hint, hint_length).to_owned())
+        }
+        _ => panic!("Format hint without a url() source to go with it"),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_FontFaceRule_AppendLocalSource(rule: *mut RawGeckoFontFaceRule,
+                                                              name: *const u8, name_length: u32) {
+    let name = string_from_raw(name, name_length).to_owned();
+    MockFontFaceRule::from_raw(rule).sources.push(MockFontFaceSource::Local(name));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_FontFaceRule_AppendUnicodeRange(rule: *mut RawGeckoFontFaceRule,
+                                                               start: u32, end: u32) {
+    MockFontFaceRule::from_raw(rule).unicode_ranges.push((start, end));
+}
+
+/// Calls |f| with the value of each attribute of |element| named |name|, in namespace
+/// |namespace| or, if that's null, any namespace, until it returns true.
+unsafe fn any_attr<F>(element: *mut RawGeckoElement, namespace: *mut nsIAtom, name: *mut nsIAtom, f: F) -> bool
+                      where F: Fn(&str) -> bool {
+    mock(element).with_element(|element| {
+        element.attrs.iter().any(|attr| {
+            (namespace.is_null() || attr.namespace == namespace) && attr.name == name && f(&attr.value)
+        })
+    })
+}
+
+/// Compares attribute values for the Gecko_Attr* functions, which lowercase both sides when
+/// asked to ignore case.
+unsafe fn attr_matches<F>(element: *mut RawGeckoElement, namespace: *mut nsIAtom, name: *mut nsIAtom,
+                          string: *mut nsIAtom, ignore_case: bool, test: F) -> bool
+                          where F: Fn(&str, &str) -> bool {
+    let string = atom_str(string);
+    any_attr(element, namespace, name, |value| {
+        if ignore_case {
+            test(&value.to_ascii_lowercase(), &string.to_ascii_lowercase())
+        } else {
+            test(value, string)
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetAttrAsUTF8(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                             length: *mut u32) -> *const c_char {
+    ATTR_VALUE_COPIES.fetch_add(1, Ordering::SeqCst);
+    mock(element).with_element(|element| {
+        match element.find_attr(ns, name) {
+            Some(attr) => {
+                *length = attr.value.len() as u32;
+                attr.value.as_ptr() as *const c_char
+            }
+            None => ptr::null(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetAttrsAsUTF8(element: *mut RawGeckoElement, name: *mut nsIAtom,
+                                              values: *mut *const c_char, lengths: *mut u32,
+                                              capacity: u32) -> u32 {
+    ATTR_VALUE_COPIES.fetch_add(1, Ordering::SeqCst);
+    mock(element).with_element(|element| {
+        let matching: Vec<_> = element.attrs.iter().filter(|attr| attr.name == name).collect();
+        for (index, attr) in matching.iter().take(capacity as usize).enumerate() {
+            *values.offset(index as isize) = attr.value.as_ptr() as *const c_char;
+            *lengths.offset(index as isize) = attr.value.len() as u32;
+        }
+        matching.len() as u32
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetAtomAsUTF16(atom: *mut nsIAtom, length: *mut u32) -> *const u16 {
+    let chars = atom_utf16(atom);
+    *length = chars.len() as u32;
+    chars.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AddRefAtom(atom: *mut nsIAtom) {
+    add_ref_atom(atom)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ReleaseAtom(atom: *mut nsIAtom) {
+    release_atom(atom)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AtomEqualsUTF8(atom: *mut nsIAtom, string: *const c_char, length: u32) -> bool {
+    atom_str(atom) == string_from_raw(string as *const u8, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AtomEqualsUTF8IgnoreCase(atom: *mut nsIAtom, string: *const c_char,
+                                                        length: u32) -> bool {
+    atom_str(atom).eq_ignore_ascii_case(string_from_raw(string as *const u8, length))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AtomIsStatic(atom: *mut nsIAtom) -> bool {
+    atom_is_static(atom)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Atomize(string: *const c_char, length: u32) -> *mut nsIAtom {
+    let atom = atomize(string_from_raw(string as *const u8, length));
+    if !atom_is_static(atom) {
+        add_ref_atom(atom);
+    }
+    atom
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_HasAttr(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom) -> bool {
+    any_attr(element, ns, name, |_| true)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrEquals(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                          str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| value == string)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrDashEquals(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                              str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| {
+        value == string || (value.starts_with(string) && value[string.len()..].starts_with('-'))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrIncludes(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                            str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| {
+        value.split_whitespace().any(|word| word == string)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrHasSubstring(element: *mut RawGeckoElement, ns: *mut nsIAtom,
+                                                name: *mut nsIAtom, str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| !string.is_empty() && value.contains(string))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrHasPrefix(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                             str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| !string.is_empty() && value.starts_with(string))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_AttrHasSuffix(element: *mut RawGeckoElement, ns: *mut nsIAtom, name: *mut nsIAtom,
+                                             str: *mut nsIAtom, ignoreCase: bool) -> bool {
+    attr_matches(element, ns, name, str, ignoreCase, |value, string| !string.is_empty() && value.ends_with(string))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_LocalName(element: *mut RawGeckoElement) -> *mut nsIAtom {
+    mock(element).with_element(|element| element.local_name)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Namespace(element: *mut RawGeckoElement) -> *mut nsIAtom {
+    mock(element).with_element(|element| element.namespace)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetElementId(element: *mut RawGeckoElement) -> *mut nsIAtom {
+    mock(element).with_element(|element| element.id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetLanguage(element: *mut RawGeckoElement) -> *mut nsIAtom {
+    // xml:lang wins over lang, and the closest ancestor that has either wins over the
+    // document's language.
+    let (xml, lang, none) = (atom(XML_NAMESPACE), atom("lang"), atom(""));
+    let mut node = element as *mut MockNode;
+    while let Some(current) = node.as_ref() {
+        if !current.is_element() {
+            break;
+        }
+        let language = current.with_element(|element| {
+            element.find_attr(xml, lang).or_else(|| element.find_attr(none, lang)).map(|attr| atom(&attr.value))
+        });
+        if let Some(language) = language {
+            return language;
+        }
+        node = current.parent();
+    }
+    owner_document(mock(element)).with_document(|document| document.language)
+}
+
+/// Hands back |classes| as Gecko_ClassOrClassList does: a lone class on its own, and
+/// otherwise a pointer to all of them.
+unsafe fn class_or_class_list(classes: &[*mut nsIAtom], class_: *mut *mut nsIAtom,
+                              class_list: *mut *mut *mut nsIAtom) -> u32 {
+    match classes.len() {
+        0 => {}
+        1 => *class_ = classes[0],
+        _ => *class_list = classes.as_ptr() as *mut *mut nsIAtom,
+    }
+    classes.len() as u32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ClassOrClassList(element: *mut RawGeckoElement, class_: *mut *mut nsIAtom,
+                                                classList: *mut *mut *mut nsIAtom) -> u32 {
+    mock(element).with_element(|element| class_or_class_list(&element.classes, class_, classList))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetStyleAttrDeclarationBlock(element: *mut RawGeckoElement)
+                                                            -> *mut RawServoDeclarationBlock {
+    mock(element).with_element(|element| borrowed_block(&element.style))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetHTMLPresentationAttrDeclarationBlock(element: *mut RawGeckoElement)
+                                                                       -> *mut RawServoDeclarationBlock {
+    mock(element).with_element(|element| borrowed_block(&element.presentational_hints))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetSMILOverrideDeclarationBlock(element: *mut RawGeckoElement)
+                                                               -> *mut RawServoDeclarationBlock {
+    mock(element).with_element(|element| borrowed_block(&element.smil_override))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GetAnimationEffectDeclarationBlock(element: *mut RawGeckoElement, cascade_level: u8)
+                                                                  -> *mut RawServoDeclarationBlock {
+    mock(element).with_element(|element| borrowed_block(&element.animation_effects[cascade_level as usize]))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_CreateElementSnapshot(element: *mut RawGeckoElement) -> *mut ServoElementSnapshot {
+    let snapshot = mock(element).with_element(|element| MockSnapshot {
+        state: element.state,
+        attrs: element.attrs.clone(),
+        id: element.id,
+        classes: element.classes.clone(),
+    });
+    Box::into_raw(Box::new(snapshot)) as *mut ServoElementSnapshot
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_DropElementSnapshot(snapshot: *mut ServoElementSnapshot) {
+    let _ = Box::from_raw(snapshot as *mut MockSnapshot);
+}
+
+unsafe fn mock_snapshot<'a>(snapshot: *mut ServoElementSnapshot) -> &'a MockSnapshot {
+    &*(snapshot as *mut MockSnapshot)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotState(snapshot: *mut ServoElementSnapshot) -> u64 {
+    mock_snapshot(snapshot).state
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotHasAttrs(_snapshot: *mut ServoElementSnapshot) -> bool {
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotGetAttrAsUTF8(snapshot: *mut ServoElementSnapshot, ns: *mut nsIAtom,
+                                                     name: *mut nsIAtom, length: *mut u32) -> *const c_char {
+    match mock_snapshot(snapshot).attrs.iter().find(|attr| attr.namespace == ns && attr.name == name) {
+        Some(attr) => {
+            *length = attr.value.len() as u32;
+            attr.value.as_ptr() as *const c_char
+        }
+        None => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotGetElementId(snapshot: *mut ServoElementSnapshot) -> *mut nsIAtom {
+    mock_snapshot(snapshot).id
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotClassOrClassList(snapshot: *mut ServoElementSnapshot,
+                                                        class_: *mut *mut nsIAtom,
+                                                        classList: *mut *mut *mut nsIAtom) -> u32 {
+    class_or_class_list(&mock_snapshot(snapshot).classes, class_, classList)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_SnapshotAttributes(element: *mut RawGeckoElement,
+                                                  snapshot: *mut RawServoAttributeSnapshot) {
+    // Gecko keeps ids as atoms, and the other values as strings.
+    mock(element).with_element(|element| {
+        let attrs = Servo_AttributeSnapshot_SetLength(snapshot, element.attrs.len() as u32);
+        for (i, attr) in element.attrs.iter().enumerate() {
+            let is_id = attr.namespace == atom("") && attr.name == atom("id") && !element.id.is_null();
+            *attrs.offset(i as isize) = ServoAttrSnapshot {
+                mNamespace: attr.namespace,
+                mName: attr.name,
+                mValueAtom: if is_id { element.id } else { ptr::null_mut() },
+                mValue: attr.value.as_ptr(),
+                mValueLength: attr.value.len() as u32,
+            };
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Utf8SliceToString(string: *mut nsString, bytes: *const u8, length: u32) {
+    *(string as *mut String) = string_from_option(bytes, length);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_PropertyList_Append(list: *mut RawGeckoPropertyList, property: nsCSSProperty) {
+    (*(list as *mut MockPropertyList)).properties.push(property);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ContentList_AppendElement(list: *mut RawGeckoContentList,
+                                                         element: *mut RawGeckoElement) {
+    (*(list as *mut MockContentList)).elements.push(element);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_StartTransition(element: *mut RawGeckoElement, property: nsCSSProperty,
+                                               _from: *mut RawServoAnimationValue, _to: *mut RawServoAnimationValue,
+                                               duration_ms: f32, delay_ms: f32,
+                                               _timing_function: *const ServoTimingFunction) {
+    mock(element).with_element_mut(|element| {
+        element.transitions.push(MockTransition { property: property, duration_ms: duration_ms, delay_ms: delay_ms })
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_TransformList_SetLength(list: *mut RawGeckoTransformList, length: u32)
+                                                       -> *mut ServoTransformOperation {
+    let operations = &mut (*(list as *mut MockTransformList)).operations;
+    operations.resize(length as usize, ServoTransformOperation {
+        mFunction: ServoTransformFunction::Matrix,
+        mValues: [0.; 16],
+        mPercentages: [0.; 2],
+    });
+    operations.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ShadowArray_SetLength(array: *mut RawGeckoShadowArray, length: u32)
+                                                     -> *mut ServoShadowItem {
+    let shadows = &mut (*(array as *mut MockShadowArray)).shadows;
+    shadows.resize(length as usize, ServoShadowItem {
+        mXOffset: 0,
+        mYOffset: 0,
+        mRadius: 0,
+        mSpread: 0,
+        mColor: 0,
+        mHasColor: false,
+        mInset: false,
+    });
+    shadows.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_GradientStopArray_SetLength(array: *mut RawGeckoGradientStopArray, length: u32)
+                                                           -> *mut ServoGradientStop {
+    let stops = &mut (*(array as *mut MockGradientStopArray)).stops;
+    stops.resize(length as usize, ServoGradientStop {
+        mPosition: ServoLengthOrPercentageOrAuto {
+            mUnit: ServoLengthUnit::Auto,
+            mLength: 0,
+            mPercent: 0.,
+            mHasPercent: false,
+        },
+        mColor: 0,
+        mHasColor: false,
+    });
+    stops.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_CursorImageArray_SetLength(array: *mut RawGeckoCursorImageArray, length: u32)
+                                                          -> *mut ServoCursorImage {
+    let images = &mut (*(array as *mut MockCursorImageArray)).images;
+    images.resize(length as usize, ServoCursorImage {
+        mRequest: ptr::null_mut(),
+        mHaveHotspot: false,
+        mHotspotX: 0.,
+        mHotspotY: 0.,
+    });
+    images.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_CounterItemArray_SetLength(array: *mut RawGeckoCounterItemArray, length: u32)
+                                                          -> *mut ServoCounterItem {
+    let items = &mut (*(array as *mut MockCounterItemArray)).items;
+    items.resize(length as usize, ServoCounterItem { mName: ptr::null_mut(), mValue: 0 });
+    items.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_FontFeatureArray_SetLength(array: *mut RawGeckoFontFeatureArray, length: u32)
+                                                          -> *mut ServoFontFeature {
+    let features = &mut (*(array as *mut MockFontFeatureArray)).features;
+    features.resize(length as usize, ServoFontFeature { mTag: 0, mValue: 0 });
+    features.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_ContentItemList_Append(list: *mut RawGeckoContentItemList,
+                                                      item: *const ServoContentItem) {
+    let item = &*item;
+    (*(list as *mut MockContentItemList)).items.push(MockContentItem {
+        item_type: item.mType,
+        string: string_from_option(item.mString, item.mStringLength),
+        separator: string_from_option(item.mSeparator, item.mSeparatorLength),
+        counter_style: string_from_option(item.mCounterStyle, item.mCounterStyleLength),
+        counter_name: item.mCounterName,
+    });
+}
+
+/// A zero length, or the keyword `unit` stands for if it isn't Length.
+fn initial_length(unit: ServoLengthUnit) -> ServoLengthOrPercentageOrAuto {
+    ServoLengthOrPercentageOrAuto { mUnit: unit, mLength: 0, mPercent: 0., mHasPercent: false }
+}
+
+// The values nsStyleDisplay's constructor starts from. Gecko's NS_THEME_NONE and
+// NS_STYLE_TOUCH_ACTION_AUTO are 0 and 2.
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Construct_nsStyleDisplay(out: *mut nsStyleDisplay_FFI) {
+    *out = nsStyleDisplay_FFI {
+        mOpacity: 1.,
+        mDisplay: gecko_style_structs::NS_STYLE_DISPLAY_INLINE as u8,
+        mOriginalDisplay: gecko_style_structs::NS_STYLE_DISPLAY_INLINE as u8,
+        mContain: gecko_style_structs::NS_STYLE_CONTAIN_NONE as u8,
+        mAppearance: 0,
+        mPosition: gecko_style_structs::NS_STYLE_POSITION_STATIC as u8,
+        mFloats: gecko_style_structs::NS_STYLE_FLOAT_NONE as u8,
+        mBreakType: gecko_style_structs::NS_STYLE_CLEAR_NONE as u8,
+        mOverflowX: gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8,
+        mOverflowY: gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8,
+        mResize: gecko_style_structs::NS_STYLE_RESIZE_NONE as u8,
+        mOrient: gecko_style_structs::NS_STYLE_ORIENT_INLINE as u8,
+        mMixBlendMode: gecko_style_structs::NS_STYLE_BLEND_NORMAL as u8,
+        mIsolation: gecko_style_structs::NS_STYLE_ISOLATION_AUTO as u8,
+        mTouchAction: 2,
+        mScrollBehavior: gecko_style_structs::NS_STYLE_SCROLL_BEHAVIOR_AUTO as u8,
+        mScrollSnapTypeX: gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_NONE as u8,
+        mScrollSnapTypeY: gecko_style_structs::NS_STYLE_SCROLL_SNAP_TYPE_NONE as u8,
+        mBackfaceVisibility: gecko_style_structs::NS_STYLE_BACKFACE_VISIBILITY_VISIBLE as u8,
+        mTransformStyle: gecko_style_structs::NS_STYLE_TRANSFORM_STYLE_FLAT as u8,
+        mTransformBox: gecko_style_structs::NS_STYLE_TRANSFORM_BOX_BORDER_BOX as u8,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Construct_nsStyleMargin(out: *mut nsStyleMargin_FFI) {
+    *out = nsStyleMargin_FFI { mMargin: [initial_length(ServoLengthUnit::Length); 4] };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Construct_nsStylePadding(out: *mut nsStylePadding_FFI) {
+    *out = nsStylePadding_FFI { mPadding: [initial_length(ServoLengthUnit::Length); 4] };
+}
+
+// Gecko's NS_STYLE_GRID_AUTO_FLOW_ROW is 1.
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Construct_nsStylePosition(out: *mut nsStylePosition_FFI) {
+    *out = nsStylePosition_FFI {
+        mOffset: [initial_length(ServoLengthUnit::Auto); 4],
+        mWidth: initial_length(ServoLengthUnit::Auto),
+        mMinWidth: initial_length(ServoLengthUnit::Length),
+        mMaxWidth: initial_length(ServoLengthUnit::None),
+        mHeight: initial_length(ServoLengthUnit::Auto),
+        mMinHeight: initial_length(ServoLengthUnit::Length),
+        mMaxHeight: initial_length(ServoLengthUnit::None),
+        mFlexBasis: initial_length(ServoLengthUnit::Auto),
+        mGridAutoFlow: 1,
+        mBoxSizing: gecko_style_structs::StyleBoxSizing::Content as u8,
+        mAlignContent: gecko_style_structs::NS_STYLE_ALIGN_NORMAL as u16,
+        mAlignItems: gecko_style_structs::NS_STYLE_ALIGN_NORMAL as u8,
+        mAlignSelf: gecko_style_structs::NS_STYLE_ALIGN_AUTO as u8,
+        mJustifyContent: gecko_style_structs::NS_STYLE_ALIGN_NORMAL as u16,
+        mJustifyItems: gecko_style_structs::NS_STYLE_ALIGN_AUTO as u8,
+        mJustifySelf: gecko_style_structs::NS_STYLE_ALIGN_AUTO as u8,
+        mFlexDirection: gecko_style_structs::NS_STYLE_FLEX_DIRECTION_ROW as u8,
+        mFlexWrap: gecko_style_structs::NS_STYLE_FLEX_WRAP_NOWRAP as u8,
+        mObjectFit: gecko_style_structs::NS_STYLE_OBJECT_FIT_FILL as u8,
+        mFlexBasisIsContent: false,
+        mHasZIndex: false,
+        mOrder: 0,
+        mFlexGrow: 0.,
+        mFlexShrink: 1.,
+        mZIndex: 0,
+        mGridColumnGap: 0,
+        mGridRowGap: 0,
+    };
+}
+
+// Gecko's NS_STYLE_COLOR_ADJUST_ECONOMY is 0.
+#[no_mangle]
+pub unsafe extern "C" fn Gecko_Construct_nsStyleVisibility(out: *mut nsStyleVisibility_FFI) {
+    *out = nsStyleVisibility_FFI {
+        mDirection: gecko_style_structs::NS_STYLE_DIRECTION_LTR as u8,
+        mVisible: gecko_style_structs::NS_STYLE_VISIBILITY_VISIBLE as u8,
+        mPointerEvents: gecko_style_structs::NS_STYLE_POINTER_EVENTS_AUTO as u8,
+        mWritingMode: gecko_style_structs::NS_STYLE_WRITING_MODE_HORIZONTAL_TB as u8,
+        mTextOrientation: gecko_style_structs::NS_STYLE_TEXT_ORIENTATION_MIXED as u8,
+        mImageRendering: gecko_style_structs::NS_STYLE_IMAGE_RENDERING_AUTO as u8,
+        mColorAdjust: 0,
+        mImageOrientation: 0,
+    };
+}
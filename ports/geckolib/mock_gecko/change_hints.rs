@@ -0,0 +1,197 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Checks the change hint compute_damage gives for a change to each group of properties
+//! it tells apart, through Servo_ComputedValues_CalcDifference.
+//!
+//! Each case only sets properties of one style struct, so the others are shared with the
+//! parent's style or the initial one on both sides, and the hint is only that group's.
+
+use glue::Servo_ComputedValues_CalcDifference;
+use ownership::Borrowed;
+use restyle_damage::{GeckoRestyleDamage, NEUTRAL_CHANGE, RECONSTRUCT_FRAME, REPAINT_FRAME};
+use restyle_damage::{UPDATE_OPACITY_LAYER, UPDATE_OVERFLOW, UPDATE_TRANSFORM_LAYER};
+use super::{MockDocument, element, main_thread};
+
+/// The change hint Gecko gets for an element whose style attribute goes from |old| to |new|.
+pub fn change_hint(old: &str, new: &str) -> GeckoRestyleDamage {
+    let doc = MockDocument::new(element("html").child(element("div").id("old").style(old))
+                                               .child(element("div").id("new").style(new)));
+    doc.restyle();
+    let (old, new) = (doc.computed_values("old"), doc.computed_values("new"));
+    let hint = Servo_ComputedValues_CalcDifference(Borrowed::from_arc(&old).as_ptr(),
+                                                   Borrowed::from_arc(&new).as_ptr());
+    GeckoRestyleDamage::from_bits(hint).expect("Unknown change hint")
+}
+
+fn assert_change_hints(changes: &[(&str, &str)], expected: GeckoRestyleDamage) {
+    for &(old, new) in changes {
+        assert_eq!(change_hint(old, new), expected, "{} to {}", old, new);
+    }
+}
+
+#[test]
+fn test_the_same_style_needs_nothing() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div").style("width: 10px")));
+    doc.restyle();
+    let style = doc.computed_values("div");
+    let style = Borrowed::from_arc(&style).as_ptr();
+    assert_eq!(Servo_ComputedValues_CalcDifference(style, style), 0);
+}
+
+#[test]
+fn test_changing_how_the_box_is_built_reconstructs_the_frame() {
+    let _guard = main_thread();
+    assert_change_hints(&[("display: block", "display: inline"),
+                          ("display: block", "display: none"),
+                          ("float: none", "float: left"),
+                          ("overflow-x: visible", "overflow-x: hidden"),
+                          ("overflow-y: visible", "overflow-y: scroll"),
+                          ("contain: none", "contain: paint"),
+                          ("will-change: auto", "will-change: transform"),
+                          ("will-change: auto", "will-change: opacity"),
+                          ("content: normal", "content: \"x\""),
+                          ("transform: none", "transform: rotate(45deg)"),
+                          ("transform-style: flat", "transform-style: preserve-3d"),
+                          ("position: static", "position: absolute"),
+                          ("position: fixed", "position: relative")],
+                        RECONSTRUCT_FRAME);
+}
+
+#[test]
+fn test_changing_the_rest_of_the_box_reflows() {
+    let _guard = main_thread();
+    assert_change_hints(&[("width: 10px", "width: 20px"),
+                          ("height: auto", "height: 50%"),
+                          ("min-width: 0", "min-width: 10px"),
+                          ("max-height: none", "max-height: 10px"),
+                          ("box-sizing: content-box", "box-sizing: border-box"),
+                          ("clear: none", "clear: both"),
+                          ("resize: none", "resize: both"),
+                          ("will-change: auto", "will-change: scroll-position"),
+                          ("position: static", "position: relative")],
+                        GeckoRestyleDamage::reflow() | REPAINT_FRAME);
+}
+
+#[test]
+fn test_changing_what_only_hit_testing_and_selection_read_is_neutral() {
+    let _guard = main_thread();
+    assert_change_hints(&[("touch-action: auto", "touch-action: none"),
+                          ("user-select: auto", "user-select: none"),
+                          ("pointer-events: auto", "pointer-events: painted")],
+                        NEUTRAL_CHANGE);
+}
+
+#[test]
+fn test_changing_counters_reconstructs_the_frame() {
+    let _guard = main_thread();
+    assert_change_hints(&[("counter-reset: none", "counter-reset: item"),
+                          ("counter-increment: item 1", "counter-increment: item 2")],
+                        RECONSTRUCT_FRAME);
+}
+
+#[test]
+fn test_changing_the_geometry_of_the_box_or_its_text_reflows() {
+    let _guard = main_thread();
+    assert_change_hints(&[("margin-top: 0", "margin-top: 10px"),
+                          ("margin-left: auto", "margin-left: 5%"),
+                          ("padding-bottom: 0", "padding-bottom: 1px"),
+                          ("border-top-width: 1px; border-top-style: solid",
+                           "border-top-width: 2px; border-top-style: solid"),
+                          ("border-left-style: none", "border-left-style: dashed"),
+                          ("border-right-color: black", "border-right-color: green"),
+                          ("font-size: 10px", "font-size: 12px"),
+                          ("font-weight: normal", "font-weight: bold"),
+                          ("font-style: normal", "font-style: italic"),
+                          ("font-family: serif", "font-family: monospace"),
+                          ("letter-spacing: normal", "letter-spacing: 1px"),
+                          ("word-spacing: normal", "word-spacing: 2px"),
+                          ("white-space: normal", "white-space: pre"),
+                          ("text-transform: none", "text-transform: uppercase"),
+                          ("text-align: left", "text-align: center"),
+                          ("text-overflow: clip", "text-overflow: ellipsis"),
+                          ("column-count: auto", "column-count: 2"),
+                          ("column-gap: normal", "column-gap: 1em"),
+                          ("order: 0", "order: 1"),
+                          ("flex-grow: 0", "flex-grow: 1"),
+                          ("flex-basis: auto", "flex-basis: 10px"),
+                          ("justify-content: flex-start", "justify-content: center")],
+                        GeckoRestyleDamage::reflow() | REPAINT_FRAME);
+}
+
+#[test]
+fn test_changing_the_background_or_the_paint_of_svg_shapes_repaints() {
+    let _guard = main_thread();
+    assert_change_hints(&[("background-repeat: repeat", "background-repeat: no-repeat"),
+                          ("background-position: 0 0", "background-position: 10px 0"),
+                          ("background-attachment: scroll", "background-attachment: fixed"),
+                          ("fill: black", "fill: green"),
+                          ("stroke: none", "stroke: red")],
+                        REPAINT_FRAME);
+}
+
+#[test]
+fn test_changing_the_visibility_or_how_the_box_blends_in_repaints() {
+    let _guard = main_thread();
+    assert_change_hints(&[("visibility: visible", "visibility: hidden"),
+                          ("mix-blend-mode: normal", "mix-blend-mode: multiply"),
+                          ("isolation: auto", "isolation: isolate"),
+                          ("image-rendering: auto", "image-rendering: pixelated")],
+                        REPAINT_FRAME);
+}
+
+#[test]
+fn test_changing_the_outline_repaints_and_updates_the_overflow() {
+    let _guard = main_thread();
+    assert_change_hints(&[("outline: 2px solid", "outline: 2px none"),
+                          ("outline-width: 1px; outline-style: solid", "outline-width: 3px; outline-style: solid"),
+                          ("outline-color: black", "outline-color: green"),
+                          ("outline-offset: 0", "outline-offset: 2px")],
+                        REPAINT_FRAME | UPDATE_OVERFLOW);
+}
+
+#[test]
+fn test_changing_the_opacity_only_updates_the_layer() {
+    let _guard = main_thread();
+    assert_change_hints(&[("opacity: 1", "opacity: 0.5"),
+                          ("opacity: 0.5", "opacity: 0")],
+                        UPDATE_OPACITY_LAYER);
+}
+
+#[test]
+fn test_changing_the_transform_of_a_transformed_box_updates_the_layer_and_the_overflow() {
+    let _guard = main_thread();
+    assert_change_hints(&[("transform: rotate(45deg)", "transform: rotate(90deg)"),
+                          ("transform: scale(2)", "transform: translate(10px, 0)")],
+                        UPDATE_TRANSFORM_LAYER | UPDATE_OVERFLOW);
+}
+
+#[test]
+fn test_changing_how_the_box_draws_outside_itself_repaints_and_updates_the_overflow() {
+    let _guard = main_thread();
+    assert_change_hints(&[("box-shadow: none", "box-shadow: 1px 1px black"),
+                          ("transform: scale(2); backface-visibility: visible",
+                           "transform: scale(2); backface-visibility: hidden")],
+                        REPAINT_FRAME | UPDATE_OVERFLOW);
+}
+
+#[test]
+fn test_changing_transitions_needs_nothing() {
+    let _guard = main_thread();
+    assert_change_hints(&[("transition-duration: 1s", "transition-duration: 2s"),
+                          ("transition-property: all", "transition-property: opacity")],
+                        GeckoRestyleDamage::empty());
+}
+
+#[test]
+fn test_changing_the_direction_reconstructs_the_frame_and_reflows() {
+    let _guard = main_thread();
+    for &(old, new) in &[("direction: ltr", "direction: rtl"),
+                         ("writing-mode: horizontal-tb", "writing-mode: vertical-rl"),
+                         ("unicode-bidi: normal", "unicode-bidi: embed")] {
+        let hint = change_hint(old, new);
+        assert_eq!(hint, RECONSTRUCT_FRAME | GeckoRestyleDamage::reflow() | REPAINT_FRAME, "{} to {}", old, new);
+    }
+}
@@ -0,0 +1,1195 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A stand-in for Gecko, so that the glue can be tested on its own.
+//!
+//! `callbacks` defines every Gecko_* function Servo calls, over a tree of `MockNode`s that
+//! tests build with `element()`, `text()` and friends and hand to a `MockDocument`. The
+//! Servo_* functions are then called on it the way Gecko calls them. Only test builds have
+//! any of this: the real library links against Gecko's own functions, and pays nothing for
+//! the mock.
+//!
+//! Servo's state is global, so tests that use the mock have to hold `main_thread()` while
+//! they run.
+
+#![allow(unsafe_code)]
+
+use bindings::{RawGeckoContentItemList, RawGeckoContentList, RawGeckoDocument, RawGeckoElement};
+use bindings::{RawGeckoFontFaceRule, RawGeckoFontFaceRuleList, RawGeckoKeyframeList, RawGeckoNode};
+use bindings::{RawGeckoPropertyList, RawGeckoRuleList, RawGeckoTransformList, RawServoDeclarationBlock};
+use bindings::{RawGeckoShadowArray, RawServoPerDocumentData, RawServoStyleSet, RawServoStyleSheet};
+use bindings::{RawGeckoFontFeatureArray, ServoContentItemType, ServoFontFeature, ServoShadowItem};
+use bindings::{RawGeckoGradientStopArray, RawGeckoURLExtraData, ServoGradientStop};
+use bindings::{RawGeckoCounterItemArray, RawGeckoCursorImageArray, ServoCounterItem, ServoCursorImage};
+use bindings::{ServoElementSnapshot, ServoNodeData, ServoTimingFunction, ServoTransformOperation};
+use bindings::{StyleSheetOrigin, nsCSSProperty, nsIAtom, nsString};
+use glue::{COMPATIBILITY_FULL_STANDARDS, GeckoDeclarationBlock, GeckoStyleRule};
+use glue::{Servo_AppendStyleSheet, Servo_DeclarationBlock_RemoveProperty, Servo_DeclarationBlock_SetIdentStringValue};
+use glue::{Servo_ClearNodeStyleData, Servo_DropNodeData, Servo_GetComputedPropertyValue};
+use glue::{Servo_GetComputedValues, Servo_Initialize, Servo_InitializeThreadPool, Servo_ParseStyleAttribute};
+use glue::{Servo_PerDocumentData_Create, Servo_PerDocumentData_Drop, Servo_PerDocumentData_GetStyleSet};
+use glue::{Servo_PerDocumentData_RestyleDocument, Servo_ReleaseStyleSheet, Servo_StylesheetFromUTF8Bytes};
+use ownership::{Borrowed, Strong};
+use properties::GeckoComputedValues;
+use selector_impl::{ImportRule, Stylesheet};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, ONCE_INIT, Once, RwLock};
+use style::selector_matching::CascadeLevel;
+use wrapper::{ANIMATION_EFFECT_LEVEL_ANIMATIONS, ANIMATION_EFFECT_LEVEL_TRANSITIONS};
+use wrapper::{NODE_HAS_ANIMATED_DESCENDANTS_FOR_SERVO, NODE_HAS_ANIMATIONS_FOR_SERVO};
+use wrapper::{NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO, NODE_IS_DIRTY_FOR_SERVO};
+
+mod callbacks;
+mod change_hints;
+mod round_trip;
+mod tests;
+
+pub const HTML_NAMESPACE: &'static str = "http://www.w3.org/1999/xhtml";
+pub const SVG_NAMESPACE: &'static str = "http://www.w3.org/2000/svg";
+pub const XML_NAMESPACE: &'static str = "http://www.w3.org/XML/1998/namespace";
+
+lazy_static! {
+    static ref MAIN_THREAD: Mutex<()> = Mutex::new(());
+    static ref ATOMS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+    static ref CRASH_ANNOTATIONS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    /// What Gecko_DispatchRelease was handed, by address, for run_dispatched_releases.
+    static ref DISPATCHED_RELEASES: Mutex<Vec<(usize, unsafe extern "C" fn(*mut c_void))>> =
+        Mutex::new(vec![]);
+    /// The callbacks only Gecko's main thread may call that Servo called on another thread.
+    static ref MAIN_THREAD_ONLY_CALLS_ELSEWHERE: Mutex<Vec<&'static str>> = Mutex::new(vec![]);
+}
+
+// Whether the thread is a test's, which Gecko_IsMainThread then takes for the main thread,
+// rather than one Servo or the test started.
+thread_local!(static IS_MAIN_THREAD: Cell<bool> = Cell::new(false));
+
+static INITIALIZE: Once = ONCE_INIT;
+
+/// The system allocator, counting what each thread allocates so that tests can see what
+/// Servo allocates for them.
+struct CountingAllocator;
+
+// How many allocations the thread made since it started.
+thread_local!(static ALLOCATIONS: Cell<usize> = Cell::new(0));
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Allocations made while the thread is being torn down go uncounted.
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// How many allocations the calling thread makes while running `f`, not counting those of
+/// the threads it starts: deterministic traversals keep Servo on the calling thread.
+pub fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|allocations| allocations.get());
+    f();
+    ALLOCATIONS.with(|allocations| allocations.get()) - before
+}
+
+// How many times Servo asked for the parent of a node, through any of the callbacks that
+// give it.
+static PARENT_LOOKUPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// How many times Servo asks for the parent of a node while running `f`, on any thread.
+pub fn count_parent_lookups<F: FnOnce()>(f: F) -> usize {
+    let before = PARENT_LOOKUPS.load(Ordering::SeqCst);
+    f();
+    PARENT_LOOKUPS.load(Ordering::SeqCst) - before
+}
+
+// How many times Servo asked for the previous or next sibling element of an element.
+static SIBLING_LOOKUPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// How many times Servo asks for a sibling element of an element while running `f`, on any
+/// thread.
+pub fn count_sibling_lookups<F: FnOnce()>(f: F) -> usize {
+    let before = SIBLING_LOOKUPS.load(Ordering::SeqCst);
+    f();
+    SIBLING_LOOKUPS.load(Ordering::SeqCst) - before
+}
+
+// How many times Servo had Gecko copy an attribute's value out to it.
+static ATTR_VALUE_COPIES: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// How many times Servo asks for the values of attributes while running `f`, on any thread.
+pub fn count_attr_value_copies<F: FnOnce()>(f: F) -> usize {
+    let before = ATTR_VALUE_COPIES.load(Ordering::SeqCst);
+    f();
+    ATTR_VALUE_COPIES.load(Ordering::SeqCst) - before
+}
+
+/// Makes the calling test the only one talking to Servo until the guard is dropped, and sets
+/// Servo up the first time. Servo is never shut down, since other tests may still want it.
+pub fn main_thread() -> MutexGuard<'static, ()> {
+    // A test that failed while holding the lock can't have left the mock in a state the
+    // next one cares about, since each builds its own document.
+    let guard = MAIN_THREAD.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    IS_MAIN_THREAD.with(|is_main_thread| is_main_thread.set(true));
+    INITIALIZE.call_once(|| {
+        // More than one thread, so that restyles run in parallel on any machine.
+        Servo_InitializeThreadPool(4);
+        Servo_Initialize();
+    });
+    guard
+}
+
+/// The names of the callbacks only Gecko's main thread may call that Servo called on another
+/// thread since the last call.
+pub fn take_main_thread_only_calls_elsewhere() -> Vec<&'static str> {
+    mem::replace(&mut *MAIN_THREAD_ONLY_CALLS_ELSEWHERE.lock().unwrap(), vec![])
+}
+
+/// Releases what Servo handed Gecko_DispatchRelease so far, the way the main thread's event
+/// loop would.
+pub fn run_dispatched_releases() {
+    let releases = mem::replace(&mut *DISPATCHED_RELEASES.lock().unwrap(), vec![]);
+    for (ptr, release_fn) in releases {
+        unsafe { release_fn(ptr as *mut c_void) }
+    }
+}
+
+/// A Gecko atom: the string, in both of the encodings Servo asks for, and the references
+/// Servo holds to it.
+pub struct MockAtom {
+    utf8: String,
+    utf16: Vec<u16>,
+    refcount: AtomicUsize,
+    is_static: bool,
+}
+
+fn intern(string: &str, is_static: bool) -> *mut nsIAtom {
+    let mut atoms = ATOMS.lock().unwrap();
+    let atom = *atoms.entry(string.to_owned()).or_insert_with(|| {
+        let atom = Box::new(MockAtom {
+            utf8: string.to_owned(),
+            utf16: string.encode_utf16().collect(),
+            refcount: AtomicUsize::new(0),
+            is_static: is_static,
+        });
+        Box::into_raw(atom) as usize
+    }) as *mut MockAtom;
+    assert!(unsafe { (*atom).is_static } == is_static, "{:?} was already made the other kind of atom", string);
+    atom as *mut nsIAtom
+}
+
+/// Returns the dynamic atom for |string|, which is the same pointer every time. Atoms are
+/// never freed, but the references Servo takes to them are counted, see `atom_refcount`.
+pub fn atom(string: &str) -> *mut nsIAtom {
+    intern(string, false)
+}
+
+/// Returns the static atom for |string|. Servo mustn't refcount these at all.
+pub fn static_atom(string: &str) -> *mut nsIAtom {
+    intern(string, true)
+}
+
+/// Returns the atom for |string| of whichever kind it was made, or a new dynamic one, the
+/// way Gecko_Atomize does.
+fn atomize(string: &str) -> *mut nsIAtom {
+    let existing = ATOMS.lock().unwrap().get(string).map(|&atom| atom as *mut nsIAtom);
+    existing.unwrap_or_else(|| atom(string))
+}
+
+/// How many references Servo holds to an atom `atom()` returned.
+pub fn atom_refcount(atom: *mut nsIAtom) -> usize {
+    unsafe { (*(atom as *mut MockAtom)).refcount.load(Ordering::SeqCst) }
+}
+
+unsafe fn mock_atom<'a>(atom: *mut nsIAtom) -> &'a MockAtom {
+    &*(atom as *mut MockAtom)
+}
+
+/// The string of an atom `atom()` returned.
+pub unsafe fn atom_str<'a>(atom: *mut nsIAtom) -> &'a str {
+    &(*(atom as *mut MockAtom)).utf8
+}
+
+unsafe fn atom_utf16<'a>(atom: *mut nsIAtom) -> &'a [u16] {
+    &(*(atom as *mut MockAtom)).utf16
+}
+
+unsafe fn atom_is_static(atom: *mut nsIAtom) -> bool {
+    mock_atom(atom).is_static
+}
+
+unsafe fn add_ref_atom(atom: *mut nsIAtom) {
+    let atom = mock_atom(atom);
+    assert!(!atom.is_static, "Static atom {:?} was AddRefed", atom.utf8);
+    atom.refcount.fetch_add(1, Ordering::SeqCst);
+}
+
+unsafe fn release_atom(atom: *mut nsIAtom) {
+    let atom = mock_atom(atom);
+    assert!(!atom.is_static, "Static atom {:?} was released", atom.utf8);
+    assert!(atom.refcount.fetch_sub(1, Ordering::SeqCst) > 0, "Atom {:?} was released too often", atom.utf8);
+}
+
+/// The value Gecko_AnnotateCrashReport was last given for |key|.
+pub fn crash_annotation(key: &str) -> Option<String> {
+    CRASH_ANNOTATIONS.lock().unwrap().get(key).cloned()
+}
+
+/// A node of the mock tree. Its address is the RawGeckoNode Servo is handed, and the
+/// RawGeckoElement or RawGeckoDocument too, as in Gecko.
+pub struct MockNode {
+    /// The nsINode flags Servo sets and reads, including its restyle bits.
+    flags: AtomicUsize,
+    data: AtomicPtr<ServoNodeData>,
+    inner: RwLock<NodeInner>,
+}
+
+// The traversal reads the tree from several threads, but tests only change it while no
+// traversal runs, as Gecko does.
+unsafe impl Send for MockNode {}
+unsafe impl Sync for MockNode {}
+
+struct NodeInner {
+    parent: *mut MockNode,
+    first_child: *mut MockNode,
+    last_child: *mut MockNode,
+    prev_sibling: *mut MockNode,
+    next_sibling: *mut MockNode,
+    document: *mut MockNode,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    Document(DocumentData),
+    Element(ElementData),
+    Text(String),
+    Comment,
+}
+
+struct DocumentData {
+    state: u64,
+    is_chrome: bool,
+    compat_mode: u8,
+    is_html: bool,
+    /// The language of content without a lang attribute, or null.
+    language: *mut nsIAtom,
+}
+
+#[derive(Clone)]
+struct MockAttr {
+    namespace: *mut nsIAtom,
+    name: *mut nsIAtom,
+    value: String,
+}
+
+struct ElementData {
+    local_name: *mut nsIAtom,
+    namespace: *mut nsIAtom,
+    attrs: Vec<MockAttr>,
+    /// Kept up to date with the id, class and style attributes, like Gecko does.
+    id: *mut nsIAtom,
+    classes: Vec<*mut nsIAtom>,
+    style: Option<Arc<GeckoDeclarationBlock>>,
+    presentational_hints: Option<Arc<GeckoDeclarationBlock>>,
+    /// The values of the element's SMIL animations, and of its animations and transitions by
+    /// the level Gecko_GetAnimationEffectDeclarationBlock takes, as Gecko composed them.
+    smil_override: Option<Arc<GeckoDeclarationBlock>>,
+    animation_effects: [Option<Arc<GeckoDeclarationBlock>>; 2],
+    state: u64,
+    is_native_anonymous: bool,
+    transitions: Vec<MockTransition>,
+}
+
+/// A transition Servo asked the mock to start with Gecko_StartTransition.
+#[derive(Clone, Copy, Debug)]
+pub struct MockTransition {
+    pub property: nsCSSProperty,
+    pub duration_ms: f32,
+    pub delay_ms: f32,
+}
+
+fn parse_declarations(css: &str) -> Arc<GeckoDeclarationBlock> {
+    Servo_ParseStyleAttribute(css.as_ptr(), css.len() as u32).into_arc().unwrap()
+}
+
+impl ElementData {
+    fn find_attr(&self, namespace: *mut nsIAtom, name: *mut nsIAtom) -> Option<&MockAttr> {
+        self.attrs.iter().find(|attr| attr.namespace == namespace && attr.name == name)
+    }
+
+    fn set_attr(&mut self, namespace: *mut nsIAtom, name: *mut nsIAtom, value: &str) {
+        match self.attrs.iter().position(|attr| attr.namespace == namespace && attr.name == name) {
+            Some(index) => self.attrs[index].value = value.to_owned(),
+            None => self.attrs.push(MockAttr { namespace: namespace, name: name, value: value.to_owned() }),
+        }
+        self.attribute_changed(namespace, name, Some(value));
+    }
+
+    fn remove_attr(&mut self, namespace: *mut nsIAtom, name: *mut nsIAtom) {
+        self.attrs.retain(|attr| attr.namespace != namespace || attr.name != name);
+        self.attribute_changed(namespace, name, None);
+    }
+
+    fn attribute_changed(&mut self, namespace: *mut nsIAtom, name: *mut nsIAtom, value: Option<&str>) {
+        if namespace != atom("") {
+            return;
+        }
+        if name == atom("id") {
+            self.id = match value {
+                Some(value) if !value.is_empty() => atom(value),
+                _ => ptr::null_mut(),
+            };
+        } else if name == atom("class") {
+            self.classes = value.map_or(vec![], |value| value.split_whitespace().map(atom).collect());
+        } else if name == atom("style") {
+            self.style = value.map(parse_declarations);
+        } else if name == atom("lang") {
+            // Gecko maps lang into the presentational hints as -x-lang, for the font code.
+            match value {
+                Some(value) => {
+                    if self.presentational_hints.is_none() {
+                        self.presentational_hints = Some(parse_declarations(""));
+                    }
+                    Servo_DeclarationBlock_SetIdentStringValue(borrowed_block(&self.presentational_hints),
+                                                               nsCSSProperty::eCSSProperty__x_lang, atom(value));
+                }
+                None if self.presentational_hints.is_some() => {
+                    let property = "-x-lang";
+                    Servo_DeclarationBlock_RemoveProperty(borrowed_block(&self.presentational_hints),
+                                                          property.as_ptr(), property.len() as u32);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+impl MockNode {
+    fn new(kind: NodeKind, document: *mut MockNode) -> *mut MockNode {
+        Box::into_raw(Box::new(MockNode {
+            flags: AtomicUsize::new(NODE_IS_DIRTY_FOR_SERVO as usize),
+            data: AtomicPtr::new(ptr::null_mut()),
+            inner: RwLock::new(NodeInner {
+                parent: ptr::null_mut(),
+                first_child: ptr::null_mut(),
+                last_child: ptr::null_mut(),
+                prev_sibling: ptr::null_mut(),
+                next_sibling: ptr::null_mut(),
+                document: document,
+                kind: kind,
+            }),
+        }))
+    }
+
+    fn flags(&self) -> u32 {
+        self.flags.load(Ordering::SeqCst) as u32
+    }
+
+    fn set_flags(&self, flags: u32) {
+        self.flags.fetch_or(flags as usize, Ordering::SeqCst);
+    }
+
+    fn unset_flags(&self, flags: u32) {
+        self.flags.fetch_and(!(flags as usize), Ordering::SeqCst);
+    }
+
+    fn is_element(&self) -> bool {
+        match self.inner.read().unwrap().kind {
+            NodeKind::Element(_) => true,
+            _ => false,
+        }
+    }
+
+    fn with_element<F, R>(&self, f: F) -> R where F: FnOnce(&ElementData) -> R {
+        match self.inner.read().unwrap().kind {
+            NodeKind::Element(ref element) => f(element),
+            _ => panic!("Not an element"),
+        }
+    }
+
+    fn with_element_mut<F, R>(&self, f: F) -> R where F: FnOnce(&mut ElementData) -> R {
+        match self.inner.write().unwrap().kind {
+            NodeKind::Element(ref mut element) => f(element),
+            _ => panic!("Not an element"),
+        }
+    }
+
+    fn with_document<F, R>(&self, f: F) -> R where F: FnOnce(&DocumentData) -> R {
+        match self.inner.read().unwrap().kind {
+            NodeKind::Document(ref document) => f(document),
+            _ => panic!("Not a document"),
+        }
+    }
+
+    fn with_document_mut<F, R>(&self, f: F) -> R where F: FnOnce(&mut DocumentData) -> R {
+        match self.inner.write().unwrap().kind {
+            NodeKind::Document(ref mut document) => f(document),
+            _ => panic!("Not a document"),
+        }
+    }
+
+    fn parent(&self) -> *mut MockNode {
+        self.inner.read().unwrap().parent
+    }
+
+    /// The nodes Servo should restyle need their ancestors marked, so that the traversal
+    /// finds them.
+    fn mark_ancestors_dirty(&self) {
+        let mut ancestor = self.parent();
+        while let Some(node) = unsafe { ancestor.as_ref() } {
+            node.set_flags(NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO);
+            ancestor = node.parent();
+        }
+    }
+}
+
+unsafe fn append_child(parent: *mut MockNode, child: *mut MockNode) {
+    let last_child = (*parent).inner.read().unwrap().last_child;
+    {
+        let mut child = (*child).inner.write().unwrap();
+        child.parent = parent;
+        child.prev_sibling = last_child;
+    }
+    match last_child.as_ref() {
+        Some(last_child) => last_child.inner.write().unwrap().next_sibling = child,
+        None => (*parent).inner.write().unwrap().first_child = child,
+    }
+    (*parent).inner.write().unwrap().last_child = child;
+}
+
+unsafe fn remove_child(child: *mut MockNode) {
+    let (parent, prev_sibling, next_sibling) = {
+        let mut child = (*child).inner.write().unwrap();
+        let siblings = (child.parent, child.prev_sibling, child.next_sibling);
+        child.parent = ptr::null_mut();
+        child.prev_sibling = ptr::null_mut();
+        child.next_sibling = ptr::null_mut();
+        siblings
+    };
+    match prev_sibling.as_ref() {
+        Some(prev_sibling) => prev_sibling.inner.write().unwrap().next_sibling = next_sibling,
+        None => (*parent).inner.write().unwrap().first_child = next_sibling,
+    }
+    match next_sibling.as_ref() {
+        Some(next_sibling) => next_sibling.inner.write().unwrap().prev_sibling = prev_sibling,
+        None => (*parent).inner.write().unwrap().last_child = prev_sibling,
+    }
+}
+
+/// Describes a node to create, and the children to give it.
+pub struct MockNodeBuilder {
+    kind: BuilderKind,
+    attrs: Vec<(String, String, String)>,
+    state: u64,
+    is_native_anonymous: bool,
+    presentational_hints: Option<String>,
+    children: Vec<MockNodeBuilder>,
+}
+
+enum BuilderKind {
+    Element(String, String),
+    Text(String),
+    Comment,
+}
+
+fn builder(kind: BuilderKind) -> MockNodeBuilder {
+    MockNodeBuilder {
+        kind: kind,
+        attrs: vec![],
+        state: 0,
+        is_native_anonymous: false,
+        presentational_hints: None,
+        children: vec![],
+    }
+}
+
+/// An HTML element.
+pub fn element(local_name: &str) -> MockNodeBuilder {
+    element_ns(HTML_NAMESPACE, local_name)
+}
+
+pub fn element_ns(namespace: &str, local_name: &str) -> MockNodeBuilder {
+    builder(BuilderKind::Element(namespace.to_owned(), local_name.to_owned()))
+}
+
+pub fn text(text: &str) -> MockNodeBuilder {
+    builder(BuilderKind::Text(text.to_owned()))
+}
+
+pub fn comment() -> MockNodeBuilder {
+    builder(BuilderKind::Comment)
+}
+
+impl MockNodeBuilder {
+    pub fn attr(self, name: &str, value: &str) -> MockNodeBuilder {
+        self.attr_ns("", name, value)
+    }
+
+    pub fn attr_ns(mut self, namespace: &str, name: &str, value: &str) -> MockNodeBuilder {
+        self.attrs.push((namespace.to_owned(), name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn id(self, id: &str) -> MockNodeBuilder {
+        self.attr("id", id)
+    }
+
+    pub fn class(self, class: &str) -> MockNodeBuilder {
+        self.attr("class", class)
+    }
+
+    pub fn style(self, style: &str) -> MockNodeBuilder {
+        self.attr("style", style)
+    }
+
+    /// The NS_EVENT_STATE_* bits of the element.
+    pub fn state(mut self, state: u64) -> MockNodeBuilder {
+        self.state = state;
+        self
+    }
+
+    pub fn native_anonymous(mut self) -> MockNodeBuilder {
+        self.is_native_anonymous = true;
+        self
+    }
+
+    /// The declarations Gecko would map the element's presentation attributes to.
+    pub fn presentational_hints(mut self, css: &str) -> MockNodeBuilder {
+        self.presentational_hints = Some(css.to_owned());
+        self
+    }
+
+    pub fn child(mut self, child: MockNodeBuilder) -> MockNodeBuilder {
+        self.children.push(child);
+        self
+    }
+}
+
+/// A document, along with the per-document data Servo keeps for it. Every node made for it
+/// lives as long as it does, and its ServoNodeData is dropped along with it.
+pub struct MockDocument {
+    document: *mut MockNode,
+    nodes: RefCell<Vec<*mut MockNode>>,
+    data: *mut RawServoPerDocumentData,
+}
+
+impl MockDocument {
+    /// A standards mode HTML document with |root| as its document element.
+    pub fn new(root: MockNodeBuilder) -> MockDocument {
+        MockDocument::with_compat_mode(root, COMPATIBILITY_FULL_STANDARDS)
+    }
+
+    /// A document in the given nsCompatibility mode.
+    pub fn with_compat_mode(root: MockNodeBuilder, compat_mode: u8) -> MockDocument {
+        let document = MockNode::new(NodeKind::Document(DocumentData {
+            state: 0,
+            is_chrome: false,
+            compat_mode: compat_mode,
+            is_html: true,
+            language: ptr::null_mut(),
+        }), ptr::null_mut());
+        unsafe { (*document).inner.write().unwrap().document = document };
+        let mut doc = MockDocument {
+            document: document,
+            nodes: RefCell::new(vec![document]),
+            data: ptr::null_mut(),
+        };
+        doc.append(document, root);
+        doc.data = Servo_PerDocumentData_Create(doc.raw_document());
+        doc
+    }
+
+    fn create(&self, builder: MockNodeBuilder) -> *mut MockNode {
+        let MockNodeBuilder { kind, attrs, state, is_native_anonymous, presentational_hints, children } = builder;
+        let kind = match kind {
+            BuilderKind::Element(namespace, local_name) => {
+                let mut element = ElementData {
+                    local_name: atom(&local_name),
+                    namespace: atom(&namespace),
+                    attrs: vec![],
+                    id: ptr::null_mut(),
+                    classes: vec![],
+                    style: None,
+                    presentational_hints: presentational_hints.map(|css| parse_declarations(&css)),
+                    smil_override: None,
+                    animation_effects: [None, None],
+                    state: state,
+                    is_native_anonymous: is_native_anonymous,
+                    transitions: vec![],
+                };
+                for (namespace, name, value) in attrs {
+                    element.set_attr(atom(&namespace), atom(&name), &value);
+                }
+                NodeKind::Element(element)
+            }
+            BuilderKind::Text(text) => NodeKind::Text(text),
+            BuilderKind::Comment => NodeKind::Comment,
+        };
+        let node = MockNode::new(kind, self.document);
+        self.nodes.borrow_mut().push(node);
+        if !children.is_empty() {
+            unsafe { (*node).set_flags(NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO) };
+        }
+        for child in children {
+            let child = self.create(child);
+            unsafe { append_child(node, child) };
+        }
+        node
+    }
+
+    fn append(&self, parent: *mut MockNode, child: MockNodeBuilder) -> *mut MockNode {
+        let child = self.create(child);
+        unsafe {
+            append_child(parent, child);
+            (*child).mark_ancestors_dirty();
+        }
+        child
+    }
+
+    /// Appends |child| to the element with id |parent_id|, marked for restyling.
+    pub fn append_child(&self, parent_id: &str, child: MockNodeBuilder) -> *mut RawGeckoNode {
+        let parent = self.find(parent_id);
+        self.append(parent, child) as *mut RawGeckoNode
+    }
+
+    fn find(&self, id: &str) -> *mut MockNode {
+        let id = atom(id);
+        *self.nodes.borrow().iter().find(|&&node| unsafe {
+            (*node).is_element() && (*node).with_element(|element| element.id == id)
+        }).unwrap_or_else(|| panic!("No element has id {}", unsafe { atom_str(id) }))
+    }
+
+    /// The element with the given id.
+    pub fn element(&self, id: &str) -> *mut RawGeckoElement {
+        self.find(id) as *mut RawGeckoElement
+    }
+
+    pub fn node(&self, id: &str) -> *mut RawGeckoNode {
+        self.find(id) as *mut RawGeckoNode
+    }
+
+    pub fn raw_document(&self) -> *mut RawGeckoDocument {
+        self.document as *mut RawGeckoDocument
+    }
+
+    pub fn document_node(&self) -> *mut RawGeckoNode {
+        self.document as *mut RawGeckoNode
+    }
+
+    pub fn data(&self) -> *mut RawServoPerDocumentData {
+        self.data
+    }
+
+    pub fn style_set(&self) -> *mut RawServoStyleSet {
+        Servo_PerDocumentData_GetStyleSet(self.data)
+    }
+
+    /// Parses |css| and adds it to the document's style set.
+    pub fn add_sheet(&self, css: &str, origin: StyleSheetOrigin) {
+        let sheet = self.parse_sheet(css, origin, &MockSheetContext::new());
+        Servo_AppendStyleSheet(sheet, self.style_set());
+        Servo_ReleaseStyleSheet(sheet);
+    }
+
+    /// Parses |css| in the document's mode, with |context| collecting what the parse asks
+    /// Gecko for. The caller owns the returned reference.
+    pub fn parse_sheet(&self, css: &str, origin: StyleSheetOrigin, context: &MockSheetContext)
+                       -> *mut RawServoStyleSheet {
+        let base = "http://www.example.com/";
+        let compat_mode = unsafe { (*self.document).with_document(|document| document.compat_mode) };
+        let sheet = Servo_StylesheetFromUTF8Bytes(css.as_ptr(), css.len() as u32, base.as_ptr(), base.len() as u32,
+                                                  origin, compat_mode, context.as_raw(), context.extra_data,
+                                                  ptr::null_mut());
+        unsafe { into_raw(sheet) }
+    }
+
+    /// Restyles whatever the restyle bits say needs it.
+    pub fn restyle(&self) {
+        Servo_PerDocumentData_RestyleDocument(self.data);
+    }
+
+    /// The style of the element with id |id|.
+    pub fn computed_values(&self, id: &str) -> Arc<GeckoComputedValues> {
+        Servo_GetComputedValues(self.element(id), self.style_set()).into_arc::<GeckoComputedValues>().expect("No style")
+    }
+
+    /// The resolved value of |property| on the element with id |id|, as getComputedStyle
+    /// would report it.
+    pub fn computed_value(&self, id: &str, property: nsCSSProperty) -> String {
+        let style = self.computed_values(id);
+        let mut value = String::new();
+        assert!(Servo_GetComputedPropertyValue(Borrowed::from_arc(&style).as_ptr(), property, ns_string(&mut value)));
+        value
+    }
+
+    /// Every element in the document, in document order.
+    pub fn elements(&self) -> Vec<*mut RawGeckoElement> {
+        self.nodes.borrow().iter().filter(|&&node| unsafe { (*node).is_element() })
+                                  .map(|&node| node as *mut RawGeckoElement).collect()
+    }
+
+    /// How many nodes the document has, counting itself.
+    pub fn node_count(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    /// The nsINode flags of the element with id |id|.
+    pub fn flags(&self, id: &str) -> u32 {
+        unsafe { (*self.find(id)).flags() }
+    }
+
+    pub fn document_flags(&self) -> u32 {
+        unsafe { (*self.document).flags() }
+    }
+
+    /// Sets an attribute without telling Servo, like Gecko does after taking a snapshot.
+    /// A snapshot of the element with id |id| as it is now, to be dropped with
+    /// Gecko_DropElementSnapshot.
+    pub fn snapshot(&self, id: &str) -> *mut ServoElementSnapshot {
+        unsafe { callbacks::Gecko_CreateElementSnapshot(self.element(id)) }
+    }
+
+    pub fn set_attr(&self, id: &str, name: &str, value: &str) {
+        unsafe { (*self.find(id)).with_element_mut(|element| element.set_attr(atom(""), atom(name), value)) }
+    }
+
+    pub fn remove_attr(&self, id: &str, name: &str) {
+        unsafe { (*self.find(id)).with_element_mut(|element| element.remove_attr(atom(""), atom(name))) }
+    }
+
+    pub fn set_state(&self, id: &str, state: u64) {
+        unsafe { (*self.find(id)).with_element_mut(|element| element.state = state) }
+    }
+
+    /// Moves the element with id |id| out of |from|, and appends it to the element with id
+    /// |parent_id|, the way Gecko adopts a node into another document: it throws away the
+    /// styles of the subtree, which came from |from|'s rule tree, before inserting it.
+    pub fn adopt(&self, from: &MockDocument, id: &str, parent_id: &str) -> *mut RawGeckoNode {
+        let node = from.find(id);
+        unsafe { remove_child(node) };
+        let mut subtree = vec![];
+        let mut pending = vec![node];
+        while let Some(node) = pending.pop() {
+            subtree.push(node);
+            let inner = unsafe { (*node).inner.read().unwrap() };
+            let mut child = inner.first_child;
+            while let Some(node) = unsafe { child.as_ref() } {
+                pending.push(child);
+                child = node.inner.read().unwrap().next_sibling;
+            }
+        }
+        from.nodes.borrow_mut().retain(|node| !subtree.contains(node));
+        for &node in &subtree {
+            unsafe { (*node).inner.write().unwrap().document = self.document };
+            self.nodes.borrow_mut().push(node);
+        }
+        Servo_ClearNodeStyleData(node as *mut RawGeckoNode, false);
+        unsafe {
+            append_child(self.find(parent_id), node);
+            (*node).mark_ancestors_dirty();
+        }
+        node as *mut RawGeckoNode
+    }
+
+    /// Takes the element with id |id| out of the tree, as removing it from the DOM does. It
+    /// stays the document's, so it can still be found by its id.
+    pub fn remove(&self, id: &str) {
+        unsafe { remove_child(self.find(id)) }
+    }
+
+    /// Marks the element with id |id| for restyling, as Gecko does with the hint Servo
+    /// computed for a change.
+    pub fn mark_dirty(&self, id: &str) {
+        let node = self.find(id);
+        unsafe {
+            (*node).set_flags(NODE_IS_DIRTY_FOR_SERVO);
+            (*node).mark_ancestors_dirty();
+        }
+    }
+
+    /// Clears the bit that marks the element with id |id| for restyling, leaving its
+    /// ancestors' bits alone.
+    pub fn unmark_dirty(&self, id: &str) {
+        unsafe { (*self.find(id)).unset_flags(NODE_IS_DIRTY_FOR_SERVO) }
+    }
+
+    /// Animates the declarations |css| on the element with id |id| at |level|, one of
+    /// CascadeLevel::animations(), and flags it and its ancestors the way Gecko does.
+    pub fn animate(&self, id: &str, level: CascadeLevel, css: &str) {
+        let node = self.find(id);
+        let block = Some(parse_declarations(css));
+        unsafe {
+            (*node).with_element_mut(|element| match level {
+                CascadeLevel::SMILOverride => element.smil_override = block,
+                CascadeLevel::Animations => {
+                    element.animation_effects[ANIMATION_EFFECT_LEVEL_ANIMATIONS as usize] = block
+                }
+                CascadeLevel::Transitions => {
+                    element.animation_effects[ANIMATION_EFFECT_LEVEL_TRANSITIONS as usize] = block
+                }
+                _ => panic!("Nothing animates at {:?}", level),
+            });
+            (*node).set_flags(NODE_HAS_ANIMATIONS_FOR_SERVO);
+            let mut ancestor = (*node).parent();
+            while let Some(node) = ancestor.as_ref() {
+                node.set_flags(NODE_HAS_ANIMATED_DESCENDANTS_FOR_SERVO);
+                ancestor = node.parent();
+            }
+        }
+    }
+
+    /// The transitions Servo started on the element with id |id|.
+    pub fn transitions(&self, id: &str) -> Vec<MockTransition> {
+        unsafe { (*self.find(id)).with_element(|element| element.transitions.clone()) }
+    }
+
+    pub fn set_document_state(&self, state: u64) {
+        unsafe { (*self.document).with_document_mut(|document| document.state = state) }
+    }
+
+    pub fn set_chrome(&self, is_chrome: bool) {
+        unsafe { (*self.document).with_document_mut(|document| document.is_chrome = is_chrome) }
+    }
+
+    /// Whether the document is an HTML one rather than an XML one.
+    pub fn set_html(&self, is_html: bool) {
+        unsafe { (*self.document).with_document_mut(|document| document.is_html = is_html) }
+    }
+
+    /// The language of content no lang attribute applies to, as from a Content-Language
+    /// header.
+    pub fn set_language(&self, language: &str) {
+        unsafe { (*self.document).with_document_mut(|document| document.language = atom(language)) }
+    }
+}
+
+impl Drop for MockDocument {
+    fn drop(&mut self) {
+        Servo_PerDocumentData_Drop(self.data);
+        for &node in self.nodes.borrow().iter() {
+            unsafe {
+                Servo_DropNodeData((*node).data.swap(ptr::null_mut(), Ordering::SeqCst));
+                let _ = Box::from_raw(node);
+            }
+        }
+    }
+}
+
+/// Takes the reference |strong| owns, the way Gecko keeps the ones Servo returns.
+pub unsafe fn into_raw<T>(strong: Strong<T>) -> *mut T {
+    let raw = mem::transmute_copy::<Strong<T>, *mut T>(&strong);
+    mem::forget(strong);
+    raw
+}
+
+/// Lends |string| to a Servo_* function that writes into an nsString.
+pub fn ns_string(string: &mut String) -> *mut nsString {
+    string as *mut String as *mut nsString
+}
+
+/// What Gecko would be asked to do on behalf of a sheet as it's parsed: the errors to
+/// report, the @import loads to start, and the sheet to hand over once an asynchronous
+/// parse is done. Its URL data, which the parse is given, records the loads of both the
+/// imports and the images.
+pub struct MockSheetContext {
+    pub errors: Mutex<Vec<String>>,
+    pub imports: Mutex<Vec<(String, String)>>,
+    pub extra_data: *mut RawGeckoURLExtraData,
+    import_rules: Mutex<Vec<Arc<ImportRule>>>,
+    loaded: Mutex<Option<Arc<Stylesheet>>>,
+    loaded_condvar: Condvar,
+}
+
+impl MockSheetContext {
+    pub fn new() -> MockSheetContext {
+        MockSheetContext {
+            errors: Mutex::new(vec![]),
+            imports: Mutex::new(vec![]),
+            extra_data: MockUrlExtraData::new(),
+            import_rules: Mutex::new(vec![]),
+            loaded: Mutex::new(None),
+            loaded_condvar: Condvar::new(),
+        }
+    }
+
+    pub fn as_raw(&self) -> *mut ::std::os::raw::c_void {
+        self as *const MockSheetContext as *mut ::std::os::raw::c_void
+    }
+
+    /// Waits for Gecko_StyleSheetLoadComplete to be called with this context.
+    pub fn wait_for_load(&self) -> Arc<Stylesheet> {
+        let mut loaded = self.loaded.lock().unwrap();
+        while loaded.is_none() {
+            loaded = self.loaded_condvar.wait(loaded).unwrap();
+        }
+        loaded.take().unwrap()
+    }
+
+    /// The sheet Gecko_StyleSheetLoadComplete was called with since this was last asked,
+    /// if it was.
+    pub fn take_loaded(&self) -> Option<Arc<Stylesheet>> {
+        self.loaded.lock().unwrap().take()
+    }
+
+    /// The urls loaded on behalf of the sheet, in order.
+    pub fn loads(&self) -> Vec<String> {
+        unsafe { MockUrlExtraData::from_raw(self.extra_data) }.loads.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockSheetContext {
+    fn drop(&mut self) {
+        unsafe { MockUrlExtraData::release(self.extra_data) }
+    }
+}
+
+/// The URL data Gecko keeps for a sheet, refcounted like Gecko's URLExtraData.
+pub struct MockUrlExtraData {
+    refcount: AtomicUsize,
+    /// The urls of the images and sheets loaded with it, in order.
+    pub loads: Mutex<Vec<String>>,
+}
+
+impl MockUrlExtraData {
+    /// Makes URL data that the caller owns the only reference to.
+    pub fn new() -> *mut RawGeckoURLExtraData {
+        let data = MockUrlExtraData {
+            refcount: AtomicUsize::new(1),
+            loads: Mutex::new(vec![]),
+        };
+        Box::into_raw(Box::new(data)) as *mut RawGeckoURLExtraData
+    }
+
+    pub unsafe fn from_raw<'a>(data: *mut RawGeckoURLExtraData) -> &'a MockUrlExtraData {
+        &*(data as *const MockUrlExtraData)
+    }
+
+    /// How many references to |data| there are.
+    pub unsafe fn refcount(data: *mut RawGeckoURLExtraData) -> usize {
+        MockUrlExtraData::from_raw(data).refcount.load(Ordering::SeqCst)
+    }
+
+    pub unsafe fn add_ref(data: *mut RawGeckoURLExtraData) {
+        MockUrlExtraData::from_raw(data).refcount.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub unsafe fn release(data: *mut RawGeckoURLExtraData) {
+        if MockUrlExtraData::from_raw(data).refcount.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let _ = Box::from_raw(data as *mut MockUrlExtraData);
+        }
+    }
+}
+
+/// What Gecko keeps for an element when it's about to change, for
+/// Servo_PerDocumentData_ComputeRestyleHint to compare the element against.
+pub struct MockSnapshot {
+    state: u64,
+    attrs: Vec<MockAttr>,
+    id: *mut nsIAtom,
+    classes: Vec<*mut nsIAtom>,
+}
+
+/// An entry of the list Servo_GetMatchingRules fills in.
+pub enum MockRule {
+    Style {
+        sheet: *mut RawServoStyleSheet,
+        rule: Arc<GeckoStyleRule>,
+        selector_index: u32,
+        specificity: u32,
+    },
+    Declarations {
+        declarations: Arc<GeckoDeclarationBlock>,
+        is_style_attribute: bool,
+    },
+}
+
+#[derive(Default)]
+pub struct MockRuleList {
+    pub rules: Vec<MockRule>,
+}
+
+impl MockRuleList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoRuleList {
+        self as *mut MockRuleList as *mut RawGeckoRuleList
+    }
+}
+
+pub struct MockKeyframe {
+    pub offset: f32,
+    pub timing_function: ServoTimingFunction,
+    pub declarations: Arc<GeckoDeclarationBlock>,
+}
+
+#[derive(Default)]
+pub struct MockKeyframeList {
+    pub keyframes: Vec<MockKeyframe>,
+}
+
+impl MockKeyframeList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoKeyframeList {
+        self as *mut MockKeyframeList as *mut RawGeckoKeyframeList
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MockFontFaceSource {
+    /// A url() source, and the format() hints that go with it.
+    Url(String, Vec<String>),
+    Local(String),
+}
+
+pub struct MockFontFaceRule {
+    pub sheet: *mut RawServoStyleSheet,
+    pub family: String,
+    pub weight: u16,
+    pub style: u8,
+    pub stretch: i16,
+    pub sources: Vec<MockFontFaceSource>,
+    pub unicode_ranges: Vec<(u32, u32)>,
+}
+
+impl MockFontFaceRule {
+    fn from_raw<'a>(rule: *mut RawGeckoFontFaceRule) -> &'a mut MockFontFaceRule {
+        unsafe { &mut *(rule as *mut MockFontFaceRule) }
+    }
+}
+
+#[derive(Default)]
+pub struct MockFontFaceRuleList {
+    pub rules: Vec<Box<MockFontFaceRule>>,
+}
+
+impl MockFontFaceRuleList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoFontFaceRuleList {
+        self as *mut MockFontFaceRuleList as *mut RawGeckoFontFaceRuleList
+    }
+}
+
+#[derive(Default)]
+pub struct MockPropertyList {
+    pub properties: Vec<nsCSSProperty>,
+}
+
+impl MockPropertyList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoPropertyList {
+        self as *mut MockPropertyList as *mut RawGeckoPropertyList
+    }
+}
+
+#[derive(Default)]
+pub struct MockContentList {
+    pub elements: Vec<*mut RawGeckoElement>,
+}
+
+impl MockContentList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoContentList {
+        self as *mut MockContentList as *mut RawGeckoContentList
+    }
+}
+
+#[derive(Default)]
+pub struct MockTransformList {
+    pub operations: Vec<ServoTransformOperation>,
+}
+
+impl MockTransformList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoTransformList {
+        self as *mut MockTransformList as *mut RawGeckoTransformList
+    }
+}
+
+#[derive(Default)]
+pub struct MockShadowArray {
+    pub shadows: Vec<ServoShadowItem>,
+}
+
+impl MockShadowArray {
+    pub fn as_raw(&mut self) -> *mut RawGeckoShadowArray {
+        self as *mut MockShadowArray as *mut RawGeckoShadowArray
+    }
+}
+
+#[derive(Default)]
+pub struct MockGradientStopArray {
+    pub stops: Vec<ServoGradientStop>,
+}
+
+impl MockGradientStopArray {
+    pub fn as_raw(&mut self) -> *mut RawGeckoGradientStopArray {
+        self as *mut MockGradientStopArray as *mut RawGeckoGradientStopArray
+    }
+}
+
+#[derive(Default)]
+pub struct MockCursorImageArray {
+    pub images: Vec<ServoCursorImage>,
+}
+
+impl MockCursorImageArray {
+    pub fn as_raw(&mut self) -> *mut RawGeckoCursorImageArray {
+        self as *mut MockCursorImageArray as *mut RawGeckoCursorImageArray
+    }
+}
+
+#[derive(Default)]
+pub struct MockCounterItemArray {
+    pub items: Vec<ServoCounterItem>,
+}
+
+impl MockCounterItemArray {
+    pub fn as_raw(&mut self) -> *mut RawGeckoCounterItemArray {
+        self as *mut MockCounterItemArray as *mut RawGeckoCounterItemArray
+    }
+}
+
+#[derive(Default)]
+pub struct MockFontFeatureArray {
+    pub features: Vec<ServoFontFeature>,
+}
+
+impl MockFontFeatureArray {
+    pub fn as_raw(&mut self) -> *mut RawGeckoFontFeatureArray {
+        self as *mut MockFontFeatureArray as *mut RawGeckoFontFeatureArray
+    }
+}
+
+/// A ServoContentItem, with the strings it points to copied out.
+#[derive(Debug)]
+pub struct MockContentItem {
+    pub item_type: ServoContentItemType,
+    pub string: String,
+    pub separator: String,
+    pub counter_style: String,
+    pub counter_name: *mut nsIAtom,
+}
+
+#[derive(Default)]
+pub struct MockContentItemList {
+    pub items: Vec<MockContentItem>,
+}
+
+impl MockContentItemList {
+    pub fn as_raw(&mut self) -> *mut RawGeckoContentItemList {
+        self as *mut MockContentItemList as *mut RawGeckoContentItemList
+    }
+}
+
+/// The pointer to |block| that Gecko lends Servo, or null.
+fn borrowed_block(block: &Option<Arc<GeckoDeclarationBlock>>) -> *mut RawServoDeclarationBlock {
+    block.as_ref().map_or(ptr::null_mut(), |block| Borrowed::from_arc(block).as_ptr())
+}
@@ -0,0 +1,426 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Checks that what Servo serializes parses back into the same thing.
+//!
+//! Declarations go through Servo_ParseStyleAttribute and Servo_DeclarationBlock_GetCssText,
+//! and selector lists through Servo_SelectorList_Parse and serialize_selector_list, twice:
+//! serializing what the first serialization parses into has to give it back unchanged.
+//!
+//! The inputs are generated from templates of the grammar of the properties and selectors
+//! that are supported, with a generator of our own so that a failure can be reproduced from
+//! its seed. Each test runs SERVO_ROUND_TRIP_ITERATIONS of them, 200 unless it's set, from
+//! SERVO_ROUND_TRIP_SEED, or a fixed seed so that a run on CI is the same as the last one.
+//! Inputs that have failed before are kept in round_trip_corpus.txt, and always run first:
+//! once what a failure found is fixed, its input goes there.
+
+use glue::{GeckoDeclarationBlock, GeckoSelectorList, Servo_DeclarationBlock_GetCssText, Servo_ParseStyleAttribute};
+use glue::{Servo_SelectorList_Drop, Servo_SelectorList_Parse};
+use ownership::Borrowed;
+use std::env;
+use std::ptr;
+use style::selector_impl::serialize_selector_list;
+use super::{main_thread, ns_string};
+
+const CORPUS: &'static str = include_str!("round_trip_corpus.txt");
+
+const DEFAULT_ITERATIONS: usize = 200;
+const DEFAULT_SEED: u64 = 0x5eed_c55_5e1a1;
+
+/// A xorshift generator, which is all that picking inputs needs.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // Xorshift gets stuck on zero.
+        Rng(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.below(choices.len())]
+    }
+}
+
+fn env_var<T: ::std::str::FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| panic!("{} should be a number, not {:?}", name, value)),
+        Err(_) => default,
+    }
+}
+
+/// The templates of the values of each property the generator declares. Each `{name}` in one
+/// is replaced with what `generate` makes for `name`.
+const PROPERTIES: &'static [(&'static str, &'static [&'static str])] = &[
+    ("color", &["{color}"]),
+    ("background-color", &["{color}"]),
+    ("opacity", &["{number}"]),
+    ("z-index", &["auto", "{integer}"]),
+    ("width", &["{length-percentage}", "auto", "-moz-fit-content"]),
+    ("min-height", &["{length-percentage}", "-moz-min-content"]),
+    ("max-width", &["{length-percentage}", "none", "-moz-max-content"]),
+    ("box-sizing", &["content-box", "border-box"]),
+    ("margin", &["{margin}", "{margin} {margin}", "{margin} {margin} {margin}",
+                 "{margin} {margin} {margin} {margin}"]),
+    ("margin-top", &["{margin}"]),
+    ("margin-left", &["{margin}"]),
+    ("padding", &["{length-percentage}", "{length-percentage} {length-percentage}"]),
+    ("padding-bottom", &["{length-percentage}"]),
+    ("border", &["{border-side}"]),
+    ("border-top", &["{border-side}"]),
+    ("border-left-width", &["{border-width}"]),
+    ("border-right-style", &["{border-style}"]),
+    ("border-bottom-color", &["{color}"]),
+    ("border-radius", &["{length-percentage}", "{length-percentage} {length-percentage}",
+                        "{length-percentage} / {length-percentage} {length-percentage}"]),
+    ("border-top-left-radius", &["{length-percentage}", "{length-percentage} {length-percentage}"]),
+    ("outline", &["{color}", "{border-style} {border-width}", "{border-width} {color} {border-style}"]),
+    ("outline-color", &["{color}", "invert"]),
+    ("list-style", &["{list-style-type}", "{list-style-position}", "{list-style-type} {list-style-position}",
+                     "none {list-style-position}", "none none"]),
+    ("list-style-type", &["{list-style-type}"]),
+    ("overflow", &["{overflow}"]),
+    ("overflow-y", &["{overflow}"]),
+    ("font", &["{font-size} {font-families}", "{font-style} {font-size}/{line-height} {font-families}",
+               "{font-weight} {font-style} {font-size} {font-families}",
+               "{font-stretch} {font-weight} {font-size}/{line-height} {font-families}"]),
+    ("font-family", &["{font-families}"]),
+    ("font-size", &["{font-size}"]),
+    ("font-style", &["{font-style}"]),
+    ("font-weight", &["{font-weight}"]),
+    ("line-height", &["{line-height}"]),
+    ("transition", &["{transition}", "{transition}, {transition}", "none"]),
+    ("transition-duration", &["{time}", "{time}, {time}"]),
+    ("transition-timing-function", &["{timing-function}"]),
+];
+
+/// Makes something for a `{name}` in a template.
+fn generate(rng: &mut Rng, name: &str) -> String {
+    match name {
+        "number" => {
+            let whole = rng.below(200) as i32 - 50;
+            match rng.below(4) {
+                0 => format!("{}.{}", whole, rng.below(100)),
+                1 => rng.pick(&["0", "-0", "0.5", "1e3", "68.23333", "0.001", ".25"]).to_owned(),
+                _ => whole.to_string(),
+            }
+        }
+        "integer" => (rng.below(2000) as i32 - 1000).to_string(),
+        "length" => {
+            let unit = rng.pick(&["px", "px", "px", "em", "rem", "ex", "ch", "vw", "vh", "vmin", "vmax",
+                                  "pt", "pc", "in", "cm", "mm", "q"]);
+            format!("{}{}", generate(rng, "number"), unit)
+        }
+        "percentage" => format!("{}%", generate(rng, "number")),
+        "length-percentage" => match rng.below(6) {
+            0 => generate(rng, "calc"),
+            1 => generate(rng, "percentage"),
+            2 => "0".to_owned(),
+            _ => generate(rng, "length"),
+        },
+        "calc" => {
+            let mut terms = vec![];
+            for _ in 0..1 + rng.below(3) {
+                terms.push(match rng.below(4) {
+                    0 => generate(rng, "percentage"),
+                    1 => format!("{} * {}", rng.below(5), generate(rng, "length")),
+                    _ => generate(rng, "length"),
+                });
+            }
+            let mut calc = format!("calc({}", terms[0]);
+            for term in &terms[1..] {
+                calc.push_str(rng.pick(&[" + ", " - "]));
+                calc.push_str(term);
+            }
+            calc.push_str(")");
+            calc
+        }
+        "margin" => if rng.one_in(5) { "auto".to_owned() } else { generate(rng, "length-percentage") },
+        "color" => match rng.below(4) {
+            0 => format!("rgb({}, {}, {})", rng.below(256), rng.below(256), rng.below(256)),
+            1 => format!("#{:03x}", rng.below(0x1000)),
+            2 => format!("#{:06X}", rng.below(0x1000000)),
+            _ => rng.pick(&["red", "Blue", "transparent", "currentColor", "rebeccapurple"]).to_owned(),
+        },
+        "border-width" => match rng.below(3) {
+            0 => rng.pick(&["thin", "medium", "thick"]).to_owned(),
+            _ => generate(rng, "length"),
+        },
+        "border-style" => rng.pick(&["none", "hidden", "solid", "dashed", "dotted", "double", "groove",
+                                     "ridge", "inset", "outset"]).to_owned(),
+        "border-side" => {
+            let mut parts = vec![];
+            if rng.one_in(2) {
+                parts.push(generate(rng, "border-width"));
+            }
+            if rng.one_in(2) {
+                parts.push(generate(rng, "border-style"));
+            }
+            if parts.is_empty() || rng.one_in(2) {
+                parts.push(generate(rng, "color"));
+            }
+            let first = rng.below(parts.len());
+            parts.swap(0, first);
+            parts.join(" ")
+        }
+        "list-style-type" => rng.pick(&["disc", "circle", "square", "decimal", "lower-roman", "none"]).to_owned(),
+        "list-style-position" => rng.pick(&["inside", "outside"]).to_owned(),
+        "overflow" => rng.pick(&["visible", "hidden", "scroll", "auto"]).to_owned(),
+        "font-size" => match rng.below(3) {
+            0 => rng.pick(&["medium", "x-small", "XX-Large", "smaller", "larger"]).to_owned(),
+            1 => generate(rng, "percentage"),
+            _ => generate(rng, "length"),
+        },
+        "font-style" => rng.pick(&["normal", "italic", "oblique"]).to_owned(),
+        "font-weight" => rng.pick(&["normal", "bold", "bolder", "lighter", "100", "400", "700", "900"]).to_owned(),
+        "font-stretch" => rng.pick(&["normal", "condensed", "ultra-expanded"]).to_owned(),
+        "line-height" => match rng.below(3) {
+            0 => "normal".to_owned(),
+            1 => generate(rng, "number"),
+            _ => generate(rng, "length-percentage"),
+        },
+        "font-family" => match rng.below(3) {
+            0 => rng.pick(&["serif", "sans-serif", "cursive", "fantasy", "monospace"]).to_owned(),
+            1 => rng.pick(&["Foo", "Foo Bar", "a1", "-moz-x", "Ünïcode", "\\31 a", "bold", "Times  New"]).to_owned(),
+            _ => rng.pick(&["\"a,b\"", "'1a'", "\"a  b\"", "\" a\"", "\"serif\"", "'Serif Pro'", "\"inherit\"",
+                            "\"q\\\"uote\"", "'back\\\\slash'", "\"\"", "\"Foo\""]).to_owned(),
+        },
+        "font-families" => {
+            let mut families = generate(rng, "font-family");
+            while rng.one_in(2) {
+                families.push_str(", ");
+                families.push_str(&generate(rng, "font-family"));
+            }
+            families
+        }
+        "time" => format!("{}{}", generate(rng, "number"), rng.pick(&["s", "ms"])),
+        "timing-function" => match rng.below(4) {
+            0 => format!("steps({}{})", 1 + rng.below(5), rng.pick(&["", ", start", ", end"])),
+            1 => format!("cubic-bezier(0.{}, {}, 1, {})", rng.below(10), generate(rng, "number"), rng.below(3)),
+            _ => rng.pick(&["ease", "linear", "ease-in", "ease-out", "ease-in-out", "step-start"]).to_owned(),
+        },
+        "transition" => {
+            let mut parts = vec![rng.pick(&["all", "opacity", "width", "color", "margin-top"]).to_owned(),
+                                 generate(rng, "time")];
+            if rng.one_in(2) {
+                parts.push(generate(rng, "timing-function"));
+            }
+            if rng.one_in(2) {
+                parts.push(generate(rng, "time"));
+            }
+            parts.join(" ")
+        }
+        _ => panic!("Nothing generates {{{}}}", name),
+    }
+}
+
+/// Replaces each `{name}` in |template| with what `generate` makes for it.
+fn expand(rng: &mut Rng, template: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').unwrap();
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&generate(rng, &rest[start + 1..end]));
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn generate_declarations(rng: &mut Rng) -> String {
+    let mut declarations = vec![];
+    for _ in 0..1 + rng.below(4) {
+        let &(name, templates) = &PROPERTIES[rng.below(PROPERTIES.len())];
+        let value = if rng.one_in(8) {
+            rng.pick(&["inherit", "initial", "unset"]).to_owned()
+        } else {
+            let template = rng.pick(templates);
+            expand(rng, template)
+        };
+        let important = if rng.one_in(6) { " !important" } else { "" };
+        declarations.push(format!("{}: {}{}", name, value, important));
+    }
+    declarations.join("; ")
+}
+
+fn generate_identifier(rng: &mut Rng) -> String {
+    rng.pick(&["a", "Foo", "x1", "-x", "_y", "é", "\\31 a", "a\\.b", "a\\ b"]).to_owned()
+}
+
+fn generate_compound_selector(rng: &mut Rng) -> String {
+    let mut compound = match rng.below(4) {
+        0 => rng.pick(&["div", "*", "SVG", "*|p", "|b"]).to_owned(),
+        _ => String::new(),
+    };
+    for _ in 0..rng.below(4) {
+        compound.push_str(&generate_simple_selector(rng));
+    }
+    if compound.is_empty() {
+        compound.push_str(&generate_simple_selector(rng));
+    }
+    compound
+}
+
+fn generate_simple_selector(rng: &mut Rng) -> String {
+    match rng.below(7) {
+        0 => format!("#{}", generate_identifier(rng)),
+        1 => format!(".{}", generate_identifier(rng)),
+        2 => {
+            let name = if rng.one_in(4) { format!("*|{}", generate_identifier(rng)) } else { generate_identifier(rng) };
+            if rng.one_in(3) {
+                return format!("[{}]", name)
+            }
+            let operator = rng.pick(&["=", "~=", "|=", "^=", "$=", "*="]);
+            let value = match rng.below(3) {
+                0 => generate_identifier(rng),
+                _ => rng.pick(&["\"b\\\"c\"", "'b\"c'", "\"\"", "'a b'", "\"\\\\\"", "'\\62'"]).to_owned(),
+            };
+            format!("[{}{}{}]", name, operator, value)
+        }
+        3 => {
+            let function = rng.pick(&["nth-child", "nth-last-child", "nth-of-type", "nth-last-of-type"]);
+            let argument = match rng.below(3) {
+                0 => rng.pick(&["odd", "even", "n", "-n+3", "+5", "2n+0", "-2n-1", "0n-0"]).to_owned(),
+                _ => format!("{}n{:+}", rng.below(7) as i32 - 3, rng.below(11) as i32 - 5),
+            };
+            format!(":{}({})", function, argument)
+        }
+        4 => format!(":not({})", match rng.below(3) {
+            0 => rng.pick(&["*", "div", "*|*"]).to_owned(),
+            _ => generate_simple_selector_but_negation(rng),
+        }),
+        _ => format!(":{}", rng.pick(&["hover", "focus", "active", "link", "visited", "any-link", "first-child",
+                                      "last-child", "only-child", "root", "empty", "first-of-type", "checked",
+                                      "-moz-read-only", "-moz-lwtheme"])),
+    }
+}
+
+fn generate_simple_selector_but_negation(rng: &mut Rng) -> String {
+    loop {
+        let simple = generate_simple_selector(rng);
+        if !simple.starts_with(":not(") {
+            return simple
+        }
+    }
+}
+
+fn generate_selectors(rng: &mut Rng) -> String {
+    let mut selectors = vec![];
+    for _ in 0..1 + rng.below(3) {
+        let mut selector = generate_compound_selector(rng);
+        for _ in 0..rng.below(3) {
+            selector.push_str(rng.pick(&[" ", " > ", "+", " ~ "]));
+            selector.push_str(&generate_compound_selector(rng));
+        }
+        selectors.push(selector);
+    }
+    selectors.join(", ")
+}
+
+fn serialize_declarations(css: &str) -> String {
+    let block = Servo_ParseStyleAttribute(css.as_ptr(), css.len() as u32).into_arc::<GeckoDeclarationBlock>().unwrap();
+    let mut text = String::new();
+    Servo_DeclarationBlock_GetCssText(Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(&block).as_ptr(),
+                                      ns_string(&mut text));
+    text
+}
+
+/// The serialization of what |selectors| parses into, or None if it's invalid.
+fn serialize_selectors(selectors: &str) -> Option<String> {
+    let list = Servo_SelectorList_Parse(selectors.as_ptr(), selectors.len() as u32, ptr::null_mut());
+    if list.is_null() {
+        return None
+    }
+    let mut text = String::new();
+    serialize_selector_list(unsafe { &*(list as *const GeckoSelectorList) }, &mut text).unwrap();
+    Servo_SelectorList_Drop(list);
+    Some(text)
+}
+
+fn check_declarations(css: &str) -> Result<(), String> {
+    let serialized = serialize_declarations(css);
+    let reserialized = serialize_declarations(&serialized);
+    if reserialized == serialized {
+        return Ok(())
+    }
+    Err(format!("{:?} serialized to {:?}, which serialized to {:?}", css, serialized, reserialized))
+}
+
+fn check_selectors(selectors: &str) -> Result<(), String> {
+    let serialized = match serialize_selectors(selectors) {
+        Some(serialized) => serialized,
+        None => return Ok(()),
+    };
+    match serialize_selectors(&serialized) {
+        Some(ref reserialized) if *reserialized == serialized => Ok(()),
+        reserialized => Err(format!("{:?} serialized to {:?}, which serialized to {:?}",
+                                    selectors, serialized, reserialized)),
+    }
+}
+
+/// The inputs of the given kind in the corpus.
+fn corpus(kind: &str) -> Vec<&'static str> {
+    CORPUS.lines().filter(|line| !line.is_empty() && !line.starts_with('#')).filter_map(|line| {
+        let separator = line.find(": ").expect("Corpus lines start with the kind of input they are");
+        if &line[..separator] == kind { Some(&line[separator + 2..]) } else { None }
+    }).collect()
+}
+
+fn run(kind: &str, generate: fn(&mut Rng) -> String, check: fn(&str) -> Result<(), String>) {
+    let _guard = main_thread();
+    let mut failures = vec![];
+    for input in corpus(kind) {
+        if let Err(failure) = check(input) {
+            failures.push(format!("{} (from the corpus)", failure));
+        }
+    }
+    let seed = env_var("SERVO_ROUND_TRIP_SEED", DEFAULT_SEED);
+    let mut rng = Rng::new(seed);
+    for _ in 0..env_var("SERVO_ROUND_TRIP_ITERATIONS", DEFAULT_ITERATIONS) {
+        let input = generate(&mut rng);
+        if let Err(failure) = check(&input) {
+            failures.push(failure);
+        }
+    }
+    assert!(failures.is_empty(), "Some {} didn't round-trip with SERVO_ROUND_TRIP_SEED={}:\n{}",
+            kind, seed, failures.join("\n"));
+}
+
+#[test]
+fn test_declarations_round_trip() {
+    run("declarations", generate_declarations, check_declarations);
+}
+
+#[test]
+fn test_selectors_round_trip() {
+    run("selectors", generate_selectors, check_selectors);
+}
+
+#[test]
+fn test_the_generator_only_makes_what_it_has_templates_for() {
+    let mut rng = Rng::new(DEFAULT_SEED);
+    for &(_, templates) in PROPERTIES {
+        for template in templates {
+            assert!(!expand(&mut rng, template).contains('{'));
+        }
+    }
+    assert!(corpus("declarations").len() > 0);
+    assert!(corpus("selectors").len() > 0);
+}
@@ -0,0 +1,3453 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use app_units::Au;
+use atoms::{GeckoAtom, WeakAtom, atom_from_gecko, local_name_from_gecko};
+use bindings::{Gecko_DropElementSnapshot, Gecko_GetFirstChild, Gecko_GetNodeData, RawServoSelectorList};
+use bindings::StyleSheetOrigin;
+use bindings::{ServoLengthUnit, ServoScrollFrameInfo, ServoTraversalStatistics, nsCSSProperty};
+use bindings::{ServoBorderImageKind, ServoBorderImageRepeat, ServoBorderImageSide, ServoBorderImageUnit};
+use bindings::{ServoBackgroundLayer, ServoBorderInfo, ServoImageInfo, ServoImageKind, ServoLengthOrPercentageOrAuto};
+use bindings::{ServoCSSErrorKind, ServoColumnInfo, ServoFlexInfo, ServoSelectorParseError};
+use bindings::{ServoFontFeature, ServoShadowItem, ServoTabSizeKind, ServoTextSpacingKind, ServoTextStyleFFI};
+use bindings::{RawServoStyleSet, ServoTextOverflow, ServoUIInfo, nsIAtom, nsStyleVisibility_FFI};
+use bindings::{ServoStepPosition, ServoTimingFunction, ServoTimingFunctionType};
+use bindings::{ServoStyleClear, ServoStyleDisplay, ServoStyleFloat, ServoStylePosition, ServoStyleStructId};
+use bindings::{Gecko_Construct_nsStyleDisplay, Gecko_Construct_nsStylePosition, Gecko_Construct_nsStyleVisibility};
+use data::PerDocumentStyleData;
+use element_state::{NS_EVENT_STATE_FOCUS, NS_EVENT_STATE_FOCUS_WITHIN, NS_EVENT_STATE_HOVER};
+use element_state::{NS_EVENT_STATE_PLACEHOLDERSHOWN, NS_EVENT_STATE_UNVISITED, NS_EVENT_STATE_VISITED};
+use element_state::{NS_DOCUMENT_STATE_LWTHEME, NS_DOCUMENT_STATE_WINDOW_INACTIVE};
+use element_state::{NS_EVENT_STATE_ACTIVE, NS_EVENT_STATE_DRAGOVER, NS_EVENT_STATE_VALID};
+use element_state::{NS_EVENT_STATE_LTR, NS_EVENT_STATE_RTL};
+use gecko_style_structs::{NS_STYLE_ALIGN_AUTO, NS_STYLE_ALIGN_BASELINE, NS_STYLE_ALIGN_CENTER, NS_STYLE_ALIGN_FLEX_END};
+use gecko_style_structs::{NS_STYLE_COLUMN_COUNT_AUTO, NS_STYLE_FLEX_DIRECTION_COLUMN_REVERSE, NS_STYLE_FLEX_WRAP_WRAP};
+use gecko_style_structs::{NS_STYLE_BORDER_STYLE_DASHED, NS_STYLE_BORDER_STYLE_NONE, NS_STYLE_BORDER_STYLE_SOLID};
+use gecko_style_structs::{NS_STYLE_GRADIENT_SHAPE_CIRCULAR, NS_STYLE_GRADIENT_SIZE_EXPLICIT_SIZE};
+use gecko_style_structs::{NS_STYLE_IMAGELAYER_ATTACHMENT_FIXED, NS_STYLE_IMAGELAYER_ATTACHMENT_SCROLL};
+use gecko_style_structs::{NS_STYLE_IMAGELAYER_CLIP_BORDER, NS_STYLE_IMAGELAYER_CLIP_CONTENT};
+use gecko_style_structs::{NS_STYLE_IMAGELAYER_ORIGIN_CONTENT, NS_STYLE_IMAGELAYER_ORIGIN_PADDING};
+use gecko_style_structs::{NS_STYLE_IMAGELAYER_REPEAT_NO_REPEAT, NS_STYLE_IMAGELAYER_REPEAT_REPEAT_X};
+use gecko_style_structs::NS_STYLE_IMAGELAYER_SIZE_COVER;
+use gecko_style_structs::{NS_STYLE_OVERFLOW_AUTO, NS_STYLE_OVERFLOW_SCROLL, NS_STYLE_RESIZE_BOTH};
+use gecko_style_structs::{NS_STYLE_SCROLL_SNAP_TYPE_NONE, NS_STYLE_SCROLL_SNAP_TYPE_PROXIMITY};
+use gecko_style_structs::{NS_STYLE_DIRECTION_LTR, NS_STYLE_DIRECTION_RTL, NS_STYLE_TEXT_ALIGN_MOZ_CENTER};
+use gecko_style_structs::{NS_STYLE_TEXT_OVERFLOW_CLIP, NS_STYLE_TEXT_OVERFLOW_ELLIPSIS, NS_STYLE_TEXT_OVERFLOW_STRING};
+use gecko_style_structs::NS_STYLE_TEXT_TRANSFORM_UPPERCASE;
+use gecko_style_structs::{NS_STYLE_CLEAR_BOTH, NS_STYLE_DISPLAY_BLOCK, NS_STYLE_DISPLAY_INLINE, NS_STYLE_FLOAT_LEFT};
+use gecko_style_structs::{NS_STYLE_POINTER_EVENTS_NONE, NS_STYLE_POSITION_ABSOLUTE, NS_STYLE_VISIBILITY_HIDDEN};
+use gecko_style_structs::{NS_STYLE_POINTER_EVENTS_AUTO, NS_STYLE_POINTER_EVENTS_VISIBLESTROKE};
+use gecko_style_structs::{NS_STYLE_USER_SELECT_AUTO, NS_STYLE_USER_SELECT_NONE};
+use gecko_style_structs::{NS_STYLE_WIDTH_FIT_CONTENT, StyleBoxSizing};
+use gecko_style_structs::{NS_STYLE_CURSOR_AUTO, NS_STYLE_CURSOR_POINTER, NS_STYLE_CURSOR_TEXT};
+use gecko_style_structs::{NS_STYLE_WHITESPACE_PRE_WRAP, NS_STYLE_WORDBREAK_BREAK_ALL, NS_STYLE_WORDWRAP_BREAK_WORD};
+use glue::{COMPATIBILITY_NAV_QUIRKS, GeckoDeclarationBlock, GeckoStyleRule};
+use glue::{Servo_DeclarationBlock_GetCssText, Servo_GetComputedValuesForTextNode, Servo_GetComputedPropertyValue};
+use glue::{Servo_DeclarationBlock_GetPropertyIds, Servo_DeclarationBlock_HasImportant, Servo_ParseStyleAttribute};
+use glue::Servo_DeclarationBlock_SerializeOneValue;
+use glue::{Servo_GetComputedContent, Servo_GetComputedCounterData, Servo_GetComputedCursor, Servo_GetComputedUIInfo};
+use glue::{TOUCH_ACTION_AUTO, TOUCH_ACTION_PAN_Y};
+use glue::{EXTRA_DECLARATIONS_AUTHOR, Servo_ResolveStyleLazily, Servo_ResolveStyleWithAddedDeclarations};
+use glue::Servo_RestyleSubtree;
+use glue::{Servo_EasingEvaluateAt, Servo_ParseEasing};
+use glue::{Servo_ClearNodeStyleData, Servo_DropNodeDataRecursive, Servo_DropNodeDataRecursiveLater};
+use glue::Servo_FlushDroppedNodeData;
+use glue::Servo_AssertNoOffThreadDrops;
+use glue::{Servo_GetMatchingRules, Servo_PerDocumentData_ComputeRestyleHint, Servo_SelectorList_Drop};
+use glue::{Servo_SelectorList_Matches, Servo_SelectorList_Parse, Servo_SelectorList_ParseDetailed};
+use glue::{Servo_SelectorList_QueryAll, Servo_StyleSet_NoteStyleSheetsChanged};
+use glue::{Servo_SelectorList_QueryFirst, Servo_StyleRule_GetSelectorText, Servo_StyleSet_MightHaveAttributeDependency};
+use glue::{Servo_StyleSet_HasAttributeDependency, Servo_StyleSet_HasDocumentStateDependency};
+use glue::Servo_StyleSet_HasStateDependency;
+use glue::{Servo_GetComputedOverflowX, Servo_GetComputedOverflowY, Servo_GetComputedScrollSnapCoordinate};
+use glue::Servo_Property_SetEnabled;
+use glue::{Servo_GetComputedTextInfo, Servo_GetScrollFrameInfo, Servo_TakeChangeHint, Servo_Test_GetStrongRefCount};
+use glue::Servo_Test_TakeChildFFICallCount;
+use glue::{Servo_ForgetElementSnapshot, Servo_NoteElementSnapshot, Servo_NoteExplicitHints};
+use glue::{Servo_GetComputedValues, Servo_GetTraversalStatistics, Servo_SetDeterministicTraversal};
+use glue::{Servo_GetComputedValuesForPseudoElement, Servo_GetDescendantChangeHints};
+use glue::Servo_SetTraversalStatisticsEnabled;
+use glue::{Servo_StyleSet_GetAuthorStyleDisabled, Servo_StyleSet_GetMedium, Servo_StyleSet_SetAuthorStyleDisabled};
+use glue::{Servo_StyleSet_InvalidateForSheet, Servo_StyleSet_SetMedium, Servo_StyleSet_SizeOf};
+use glue::{Servo_GetComputedBorderInfo, Servo_GetComputedColumnInfo, Servo_GetComputedFlexInfo};
+use glue::{Servo_GetComputedBackgroundLayerAt, Servo_GetComputedBackgroundLayerCount};
+use glue::{Servo_GetComputedDirection, Servo_GetComputedImageAt, Servo_GetComputedTextOverflow};
+use glue::{Servo_GetComputedClear, Servo_GetComputedFloat, Servo_GetComputedValuesForAnonymousBox};
+use glue::{Servo_GetComputedDisplay, Servo_GetComputedHeight, Servo_GetComputedPosition, Servo_GetComputedWidth};
+use glue::{Servo_GetStyleDisplay, Servo_GetStyleMargin, Servo_GetStylePadding, Servo_GetStylePosition};
+use glue::Servo_GetStyleVisibility;
+use glue::{Servo_AppendStyleSheet, Servo_GetComputedTextShadow, Servo_ReleaseStyleSheet, Servo_RemoveStyleSheet};
+use glue::{Servo_GetComputedFontFeatureSettings, Servo_StyleSheet_Clone, Servo_StyleSheet_UpdateFromUTF8Bytes};
+use glue::{Servo_StyleSet_GetRebuildCount, Servo_StyleSet_ReplaceStyleSheets, Servo_StyleSet_SheetAt};
+use glue::{Servo_StyleSet_AppendStyleSheetToOrigin, Servo_StyleSet_SheetCount};
+use glue::{Servo_ComputedValues_GetVisitedStyle, Servo_StyleSet_GetAnonymousBoxCascadeCount};
+use glue::Servo_StyleSet_GetComputedValuesForAnonymousBox;
+use glue::{Servo_CssRules_GetStyleRuleAt, Servo_CssRules_Release, Servo_StyleSheet_GetRules};
+use glue::{Servo_CssRules_GetNestedRulesAt, Servo_CssRules_GetTypeAt, Servo_CssRules_GetUnsupportedRuleTextAt};
+use glue::{Servo_DeclarationBlock_SetProperty, Servo_ReleaseDeclarationBlock, Servo_StyleRule_GetDeclarations};
+use glue::{Servo_StyleRule_Release, Servo_StyleSet_RuleChanged, Servo_StyleSheetHasRules};
+use glue::{COMPATIBILITY_FULL_STANDARDS, Servo_StyleSet_Shutdown, Servo_StyleSheet_CancelAsyncParse};
+use glue::{Servo_RestyleForAnimations, Servo_StyleSheet_FromUTF8BytesAsync};
+use main_thread::{MainThreadOwned, release_elsewhere_from_this_thread, take_off_thread_drops};
+use ownership::{self, Borrowed};
+use properties::GeckoComputedValues;
+use restyle_damage::{GeckoRestyleDamage, NEED_REFLOW, RECOMPUTE_POSITION, RECONSTRUCT_FRAME, REPAINT_FRAME};
+use restyle_damage::{NEUTRAL_CHANGE, SYNC_FRAME_VIEW, UPDATE_CURSOR};
+use selector_impl::PseudoElement;
+use std::ascii::AsciiExt;
+use std::collections::HashSet;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use style::dom::TElement;
+use style::properties::PropertyDeclarationBlock;
+use style::restyle_hints::{RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS, RESTYLE_SELF, RestyleHint};
+use style::rule_tree::RuleTree;
+use style::scratch;
+use style::selector_matching::CascadeLevel;
+use super::{MockContentList, MockDocument, MockFontFeatureArray, MockGradientStopArray, MockRule, MockRuleList};
+use super::{MockContentItemList, MockCounterItemArray, MockCursorImageArray};
+use super::MockShadowArray;
+use super::{MockSheetContext, MockUrlExtraData};
+use super::callbacks::{Gecko_GetDocumentElement, Gecko_GetParentElement, Gecko_GetStyleAttrDeclarationBlock};
+use super::change_hints::change_hint;
+use super::{SVG_NAMESPACE, atom, comment, element, element_ns, into_raw};
+use super::{atom_refcount, count_allocations, count_attr_value_copies, count_parent_lookups, count_sibling_lookups};
+use super::{main_thread, ns_string, run_dispatched_releases, take_main_thread_only_calls_elsewhere};
+use super::{static_atom, text};
+use util::arc_ptr_eq;
+use wrapper::{NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO, NODE_IS_DIRTY_FOR_SERVO, SNAPSHOT_ATTRIBUTES, SNAPSHOT_STATE};
+use wrapper::{DomToken, GeckoElement, NODE_DESCENDANTS_NEED_FRAMES, NodeData};
+use wrapper::{SERVO_RESTYLE_DESCENDANTS, SERVO_RESTYLE_LATER_SIBLINGS, SERVO_RESTYLE_SELF, ServoRestyleHint};
+
+fn with_selectors<F, R>(selectors: &str, f: F) -> R where F: FnOnce(*mut RawServoSelectorList) -> R {
+    let list = Servo_SelectorList_Parse(selectors.as_ptr(), selectors.len() as u32, ptr::null_mut());
+    assert!(!list.is_null(), "Invalid selectors: {}", selectors);
+    let result = f(list);
+    Servo_SelectorList_Drop(list);
+    result
+}
+
+fn matches(doc: &MockDocument, id: &str, selectors: &str) -> bool {
+    with_selectors(selectors, |list| Servo_SelectorList_Matches(doc.element(id), list))
+}
+
+fn sample_document() -> MockDocument {
+    MockDocument::new(element("html").id("html").child(
+        element("body").id("body").class("page")
+            .child(element("div").id("a").class("note warning").attr("data-x", "foo-bar baz").attr("lang", "fr")
+                .child(element("p").id("p1").child(text("Some text")))
+                .child(element("p").id("p2").child(text(""))))
+            .child(element("a").id("link").attr("href", "/").state(NS_EVENT_STATE_UNVISITED.bits()))
+            .child(element("input").id("hover").state(NS_EVENT_STATE_HOVER.bits()))))
+}
+
+#[test]
+fn test_selectors_match_through_the_callbacks() {
+    let _guard = main_thread();
+    let doc = sample_document();
+
+    assert!(matches(&doc, "a", "div"));
+    assert!(!matches(&doc, "a", "p"));
+    assert!(matches(&doc, "a", ".note.warning"));
+    assert!(matches(&doc, "a", "#a"));
+    assert!(matches(&doc, "html", ":root"));
+    assert!(!matches(&doc, "body", ":root"));
+
+    assert!(matches(&doc, "a", "[data-x]"));
+    assert!(matches(&doc, "a", "[data-x|=foo]"));
+    assert!(matches(&doc, "a", "[data-x~=baz]"));
+    assert!(matches(&doc, "a", "[data-x^=foo]"));
+    assert!(matches(&doc, "a", "[data-x$=baz]"));
+    assert!(matches(&doc, "a", "[data-x*=bar]"));
+    assert!(matches(&doc, "a", "[data-x=\"FOO-BAR BAZ\" i]"));
+    assert!(!matches(&doc, "a", "[data-x=foo]"));
+
+    assert!(matches(&doc, "p1", "body > div > p"));
+    assert!(matches(&doc, "p1", ".page p"));
+    assert!(!matches(&doc, "p1", "body > p"));
+    assert!(matches(&doc, "p2", "p + p"));
+    assert!(matches(&doc, "hover", "div ~ input"));
+    assert!(matches(&doc, "p1", "p:first-child"));
+    assert!(matches(&doc, "p2", "p:nth-child(2)"));
+    assert!(matches(&doc, "p2", "p:last-of-type"));
+    assert!(matches(&doc, "p2", "p:empty"));
+    assert!(!matches(&doc, "p1", "p:empty"));
+
+    assert!(matches(&doc, "p1", ":lang(fr)"));
+    assert!(!matches(&doc, "body", ":lang(fr)"));
+    assert!(matches(&doc, "link", "a:link"));
+    assert!(!matches(&doc, "link", "a:visited"));
+    assert!(matches(&doc, "hover", "input:hover"));
+    assert!(!matches(&doc, "link", ":hover"));
+}
+
+#[test]
+fn test_empty_and_only_whitespace_elements_are_told_apart_by_their_significant_children() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("div").id("root")
+        .child(element("p").id("childless"))
+        .child(element("p").id("comment").child(comment()))
+        .child(element("p").id("empty-text").child(text("")).child(comment()))
+        .child(element("p").id("whitespace").child(text(" \n\t")))
+        .child(element("p").id("text").child(comment()).child(text("x")))
+        .child(element("p").id("element").child(element("span"))));
+
+    for &(id, empty, only_whitespace) in &[("childless", true, true), ("comment", true, true),
+                                           ("empty-text", true, true), ("whitespace", false, true),
+                                           ("text", false, false), ("element", false, false)] {
+        assert_eq!(matches(&doc, id, "p:empty"), empty, "{}", id);
+        assert_eq!(matches(&doc, id, "p:-moz-only-whitespace"), only_whitespace, "{}", id);
+    }
+}
+
+#[test]
+fn test_only_the_document_element_is_the_root() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element_ns(SVG_NAMESPACE, "svg").id("svg")
+        .child(element_ns(SVG_NAMESPACE, "foreignObject").id("object")
+            .child(element("html").id("html"))));
+    assert!(matches(&doc, "svg", ":root"));
+    assert!(!matches(&doc, "html", ":root"));
+    assert!(!matches(&doc, "object", ":root"));
+    assert!(matches(&doc, "html", ":root html"));
+}
+
+#[test]
+fn test_of_type_selectors_only_count_siblings_with_the_same_name() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("div").id("root")
+        .child(text("Some text"))
+        .child(element("p").id("p1"))
+        .child(element("span").id("span1"))
+        .child(comment())
+        .child(element("p").id("p2"))
+        .child(element("em").id("em"))
+        .child(element("span").id("span2"))
+        .child(text(" "))
+        .child(element("p").id("p3")));
+
+    assert!(matches(&doc, "p1", "p:first-of-type"));
+    assert!(matches(&doc, "p2", "p:nth-of-type(2)"));
+    assert!(matches(&doc, "p2", "p:nth-last-of-type(2)"));
+    assert!(matches(&doc, "p3", "p:last-of-type"));
+    assert!(!matches(&doc, "p3", "p:only-of-type"));
+    assert!(matches(&doc, "span1", "span:first-of-type"));
+    assert!(matches(&doc, "span2", "span:nth-of-type(2):last-of-type"));
+    assert!(!matches(&doc, "span1", "span:nth-of-type(2)"));
+    assert!(matches(&doc, "em", "em:only-of-type"));
+    assert!(!matches(&doc, "em", "em:first-child"));
+}
+
+#[test]
+fn test_only_html_elements_in_html_documents_match_names_case_insensitively() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("div").id("div")
+        .child(element_ns(SVG_NAMESPACE, "foreignObject").id("object")));
+    assert!(matches(&doc, "div", "DIV"));
+    assert!(matches(&doc, "object", "foreignObject"));
+    assert!(!matches(&doc, "object", "foreignobject"));
+
+    doc.set_html(false);
+    assert!(!matches(&doc, "div", "DIV"));
+    assert!(matches(&doc, "div", "div"));
+    assert!(matches(&doc, "object", "foreignObject"));
+}
+
+#[test]
+fn test_dir_matches_the_direction_gecko_resolved_and_right_to_left_wins_when_it_reports_both() {
+    let _guard = main_thread();
+    let (ltr, rtl) = (NS_EVENT_STATE_LTR.bits(), NS_EVENT_STATE_RTL.bits());
+    let doc = MockDocument::new(element("html").state(ltr)
+        .child(element("p").id("ltr").state(ltr))
+        .child(element("p").id("rtl").state(rtl))
+        .child(element("p").id("both").state(ltr | rtl))
+        .child(element("p").id("neither")));
+    for &(id, is_ltr, is_rtl) in &[("ltr", true, false), ("rtl", false, true),
+                                   ("both", false, true), ("neither", false, false)] {
+        assert_eq!(matches(&doc, id, "p:dir(ltr)"), is_ltr, "{}", id);
+        assert_eq!(matches(&doc, id, "p:-moz-dir(ltr)"), is_ltr, "{}", id);
+        assert_eq!(matches(&doc, id, "p:dir(rtl)"), is_rtl, "{}", id);
+        assert_eq!(matches(&doc, id, "p:-moz-dir(rtl)"), is_rtl, "{}", id);
+        assert!(!matches(&doc, id, "p:dir(auto)"), "{}", id);
+    }
+
+    doc.add_sheet("p:dir(ltr) { float: left } p:dir(rtl) { float: right }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert_eq!(doc.computed_value("both", nsCSSProperty::eCSSProperty_float), "right");
+    assert_eq!(doc.computed_value("ltr", nsCSSProperty::eCSSProperty_float), "left");
+
+    // Gaining the other bit as well changes which rules match.
+    assert_eq!(restyle_hint(&doc, "ltr", || doc.set_state("ltr", ltr | rtl)), RESTYLE_SELF);
+    doc.mark_dirty("ltr");
+    doc.restyle();
+    assert_eq!(doc.computed_value("ltr", nsCSSProperty::eCSSProperty_float), "right");
+}
+
+#[test]
+fn test_state_pseudo_classes_match_any_of_their_gecko_state_bits_and_ignore_the_rest() {
+    use element_state::*;
+    use selector_impl::Direction;
+    use selector_impl::NonTSPseudoClass::*;
+
+    // Bits Gecko has that none of our pseudo-classes look at, which an element may well have,
+    // are dropped when the state is read rather than making it unreadable.
+    let unknown = !ElementState::all().bits();
+    let with_unknown = |state: ElementState| ElementState::from_bits_truncate(state.bits() | unknown);
+    for &(ref pseudo_class, flag) in &[(Active, NS_EVENT_STATE_ACTIVE),
+                                       (Focus, NS_EVENT_STATE_FOCUS),
+                                       (Hover, NS_EVENT_STATE_HOVER),
+                                       (MozDragOver, NS_EVENT_STATE_DRAGOVER),
+                                       (Target, NS_EVENT_STATE_URLTARGET),
+                                       (Checked, NS_EVENT_STATE_CHECKED),
+                                       (Enabled, NS_EVENT_STATE_ENABLED),
+                                       (Disabled, NS_EVENT_STATE_DISABLED),
+                                       (Indeterminate, NS_EVENT_STATE_INDETERMINATE),
+                                       (Default, NS_EVENT_STATE_DEFAULT),
+                                       (Required, NS_EVENT_STATE_REQUIRED),
+                                       (Optional, NS_EVENT_STATE_OPTIONAL),
+                                       (Link, NS_EVENT_STATE_UNVISITED),
+                                       (Visited, NS_EVENT_STATE_VISITED),
+                                       (AnyLink, NS_EVENT_STATE_UNVISITED),
+                                       (AnyLink, NS_EVENT_STATE_VISITED),
+                                       (Valid, NS_EVENT_STATE_VALID),
+                                       (Invalid, NS_EVENT_STATE_INVALID),
+                                       (InRange, NS_EVENT_STATE_INRANGE),
+                                       (OutOfRange, NS_EVENT_STATE_OUTOFRANGE),
+                                       (ReadOnly, NS_EVENT_STATE_MOZ_READONLY),
+                                       (ReadWrite, NS_EVENT_STATE_MOZ_READWRITE),
+                                       (Dir(Direction::Ltr), NS_EVENT_STATE_LTR),
+                                       (Dir(Direction::Rtl), NS_EVENT_STATE_RTL),
+                                       (FocusWithin, NS_EVENT_STATE_FOCUS_WITHIN),
+                                       (PlaceholderShown, NS_EVENT_STATE_PLACEHOLDERSHOWN)] {
+        assert!(pseudo_class.matches_gecko_state(flag), "{:?}", pseudo_class);
+        assert!(pseudo_class.matches_gecko_state(with_unknown(flag)), "{:?}", pseudo_class);
+        assert!(!pseudo_class.matches_gecko_state(with_unknown(ElementState::empty())), "{:?}", pseudo_class);
+        assert!(!pseudo_class.matches_gecko_state(ElementState::empty()), "{:?}", pseudo_class);
+        // None of the other states are enough.
+        assert!(!pseudo_class.matches_gecko_state(ElementState::all() - pseudo_class.gecko_state_flag()),
+                "{:?}", pseudo_class);
+    }
+
+    // These don't depend on the state of the element at all.
+    for pseudo_class in &[MozOnlyWhitespace, MozNativeAnonymous, Dir(Direction::Other("auto".to_owned())),
+                          MozWindowInactive, MozLocaleDir(Direction::Rtl)] {
+        assert!(!pseudo_class.matches_gecko_state(ElementState::all()), "{:?}", pseudo_class);
+    }
+}
+
+#[test]
+fn test_documents_without_a_lang_attribute_use_their_own_language() {
+    let _guard = main_thread();
+    let doc = sample_document();
+    assert!(!matches(&doc, "body", ":lang(en)"));
+    doc.set_language("en-GB");
+    assert!(matches(&doc, "body", ":lang(en)"));
+    assert!(matches(&doc, "p1", ":lang(fr)"));
+}
+
+#[test]
+fn test_quirks_mode_documents_match_classes_case_insensitively() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("div").id("a").class("Note"));
+    assert!(!matches(&doc, "a", ".note"));
+    let doc = MockDocument::with_compat_mode(element("div").id("a").class("Note"), COMPATIBILITY_NAV_QUIRKS);
+    assert!(matches(&doc, "a", ".note"));
+}
+
+#[test]
+fn test_quirks_mode_documents_restyle_with_ids_and_classes_matching_case_insensitively() {
+    let _guard = main_thread();
+    let tree = || element("html").child(element("p").id("Intro").class("Note"))
+                                 .child(element("p").id("other").class("note"));
+    for &(compat_mode, in_quirks_mode) in &[(COMPATIBILITY_FULL_STANDARDS, false), (COMPATIBILITY_NAV_QUIRKS, true)] {
+        let doc = MockDocument::with_compat_mode(tree(), compat_mode);
+        doc.add_sheet("#INTRO { float: left } .NOTE { clear: both } p:not(.nOtE) { float: right }",
+                      StyleSheetOrigin::Author);
+        doc.restyle();
+        let (float, clear) = if in_quirks_mode { ("left", "both") } else { ("right", "none") };
+        assert_eq!(doc.computed_value("Intro", nsCSSProperty::eCSSProperty_float), float);
+        assert_eq!(doc.computed_value("Intro", nsCSSProperty::eCSSProperty_clear), clear);
+        assert_eq!(doc.computed_value("other", nsCSSProperty::eCSSProperty_clear), clear);
+        assert_eq!(matches(&doc, "Intro", "#iNtRo"), in_quirks_mode);
+    }
+}
+
+#[test]
+fn test_queries_find_descendants_in_document_order() {
+    let _guard = main_thread();
+    let doc = sample_document();
+
+    let first = with_selectors("p", |list| Servo_SelectorList_QueryFirst(doc.document_node(), list));
+    assert_eq!(first, doc.element("p1"));
+    let first = with_selectors("div", |list| Servo_SelectorList_QueryFirst(doc.node("a"), list));
+    assert!(first.is_null(), "The root of a query isn't one of the results");
+
+    let mut results = MockContentList::default();
+    with_selectors("#link, div p", |list| Servo_SelectorList_QueryAll(doc.document_node(), list, results.as_raw()));
+    assert_eq!(results.elements, [doc.element("p1"), doc.element("p2"), doc.element("link")]);
+}
+
+/// What Servo_SelectorList_ParseDetailed says is wrong with |selectors|: the selector at
+/// fault, and the text from its first invalid token on. None if the list is valid.
+fn selector_error(selectors: &str) -> Option<(String, String)> {
+    let mut error: ServoSelectorParseError = unsafe { mem::zeroed() };
+    let list = Servo_SelectorList_ParseDetailed(selectors.as_ptr(), selectors.len() as u32, &mut error);
+    if !list.is_null() {
+        Servo_SelectorList_Drop(list);
+        return None
+    }
+    assert_eq!(error.mKind, ServoCSSErrorKind::InvalidSelector);
+    let start = error.mSelectorStart as usize;
+    let selector = &selectors[start..start + error.mSelectorLength as usize];
+    Some((selector.to_owned(), selector[error.mOffset as usize..].to_owned()))
+}
+
+#[test]
+fn test_invalid_selector_lists_say_where_they_go_wrong() {
+    let _guard = main_thread();
+    assert_eq!(selector_error("p, div > a"), None);
+    assert_eq!(selector_error("p, div:hovr > a"), Some(("div:hovr > a".to_owned(), ":hovr > a".to_owned())));
+    assert_eq!(selector_error("p::before"), Some(("p::before".to_owned(), "::before".to_owned())));
+    assert!(Servo_SelectorList_ParseDetailed("p:".as_ptr(), 2, ptr::null_mut()).is_null());
+}
+
+#[test]
+fn test_rules_dropped_for_their_selectors_are_reported_in_detail() {
+    let _guard = main_thread();
+    let doc = sample_document();
+    let context = MockSheetContext::new();
+    let sheet = doc.parse_sheet("p { color: red }\n  a, b:hovr { color: red }\ndiv { color: red }",
+                                StyleSheetOrigin::Author, &context);
+    assert_eq!(*context.errors.lock().unwrap(), ["2:3: Invalid selector 'b:hovr' at ':hovr'"]);
+    Servo_ReleaseStyleSheet(sheet);
+}
+
+#[test]
+fn test_disabled_properties_are_dropped_from_the_sheets_that_cannot_use_them() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div")).child(element("p").id("p")));
+    let float = nsCSSProperty::eCSSProperty_float;
+    Servo_Property_SetEnabled(float, false, true);
+    let context = MockSheetContext::new();
+    let author = doc.parse_sheet("div { float: left; color: red }", StyleSheetOrigin::Author, &context);
+    let user_agent = doc.parse_sheet("p { float: right }", StyleSheetOrigin::UserAgent, &MockSheetContext::new());
+    {
+        let errors = context.errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Unsupported property declaration: 'float: left"), "{}", errors[0]);
+    }
+    Servo_AppendStyleSheet(user_agent, doc.style_set());
+    Servo_AppendStyleSheet(author, doc.style_set());
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", float), "none");
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+    assert_eq!(doc.computed_value("p", float), "right");
+
+    // Sheets parsed before the property was enabled go on without it.
+    Servo_Property_SetEnabled(float, true, true);
+    doc.mark_dirty("div");
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", float), "none");
+    let reparsed = doc.parse_sheet("div { float: left; color: red }", StyleSheetOrigin::Author, &context);
+    Servo_StyleSet_ReplaceStyleSheets(doc.style_set(), [user_agent, reparsed].as_ptr(), 2);
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", float), "left");
+    assert_eq!(context.errors.lock().unwrap().len(), 1);
+
+    // And those parsed before it was disabled keep it.
+    Servo_Property_SetEnabled(float, false, false);
+    doc.mark_dirty("div");
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", float), "left");
+    Servo_Property_SetEnabled(float, true, true);
+    for &sheet in &[author, user_agent, reparsed] {
+        Servo_ReleaseStyleSheet(sheet);
+    }
+}
+
+#[test]
+fn test_url_values_are_loaded_with_the_url_data_of_their_sheet() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div")).child(element("p")).child(element("span")));
+    let (first, second) = (MockSheetContext::new(), MockSheetContext::new());
+    let one = doc.parse_sheet("@import url(one.css); div { background-image: url(one.png) }",
+                              StyleSheetOrigin::Author, &first);
+    let two = doc.parse_sheet("@import url(two.css); p { background-image: url(two.png) }",
+                              StyleSheetOrigin::Author, &second);
+    // A copy of a sheet has its URL data until its rules are replaced with ones that have other data.
+    let three = unsafe { into_raw(Servo_StyleSheet_Clone(one)) };
+    assert!(unsafe { MockUrlExtraData::refcount(first.extra_data) } > 1);
+    let css = "span { background-image: url(http://www.example.com/three.png) }";
+    Servo_StyleSheet_UpdateFromUTF8Bytes(three, css.as_ptr(), css.len() as u32, second.as_raw(), second.extra_data);
+    for &sheet in &[one, two, three] {
+        Servo_AppendStyleSheet(sheet, doc.style_set());
+        Servo_ReleaseStyleSheet(sheet);
+    }
+    doc.restyle();
+
+    let sorted = |mut loads: Vec<String>| { loads.sort(); loads };
+    assert_eq!(sorted(first.loads()), ["http://www.example.com/one.css", "http://www.example.com/one.png"]);
+    assert_eq!(sorted(second.loads()), ["http://www.example.com/three.png", "http://www.example.com/two.css",
+                                        "http://www.example.com/two.png"]);
+}
+
+#[test]
+fn test_replaced_rules_are_parsed_like_those_the_sheet_was_made_with() {
+    let _guard = main_thread();
+    let doc = MockDocument::with_compat_mode(element("html").child(element("div").id("div")), COMPATIBILITY_NAV_QUIRKS);
+    let (context, update) = (MockSheetContext::new(), MockSheetContext::new());
+    let sheet = doc.parse_sheet("div { color: red }", StyleSheetOrigin::Author, &context);
+    let css = "@import url(one.css) print; div { color: 00ff00; background-image: url(two.png) } p { color: }";
+    Servo_StyleSheet_UpdateFromUTF8Bytes(sheet, css.as_ptr(), css.len() as u32, update.as_raw(), ptr::null_mut());
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    Servo_ReleaseStyleSheet(sheet);
+    doc.restyle();
+
+    // The quirks of the document the sheet was made for still apply, relative urls resolve
+    // against its URL, and with no extra data of their own the new rules keep the sheet's.
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(0, 255, 0)");
+    let mut loads = context.loads();
+    loads.sort();
+    assert_eq!(loads, ["http://www.example.com/one.css", "http://www.example.com/two.png"]);
+    assert_eq!(*update.imports.lock().unwrap(), [("http://www.example.com/one.css".to_owned(), "print".to_owned())]);
+    assert_eq!(update.errors.lock().unwrap().len(), 1);
+}
+
+/// The NS_STYLE_CURSOR_* keyword of |id|'s cursor, and the url and hotspot of each of its images.
+fn cursor_of(doc: &MockDocument, id: &str) -> (u32, Vec<(String, Option<(f32, f32)>)>) {
+    let mut keyword = 0;
+    let mut images = MockCursorImageArray::default();
+    let style = doc.computed_values(id);
+    Servo_GetComputedCursor(Borrowed::from_arc(&style).as_ptr(), &mut keyword, images.as_raw());
+    (keyword as u32, images.images.iter().map(|image| {
+        assert!(!image.mRequest.is_null());
+        // The mock's requests are the url they were started for.
+        let url = unsafe { (*(image.mRequest as *const String)).clone() };
+        (url, if image.mHaveHotspot { Some((image.mHotspotX, image.mHotspotY)) } else { None })
+    }).collect())
+}
+
+#[test]
+fn test_cursor_images_are_loaded_and_handed_over_with_their_hotspots() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("images")).child(element("p").id("bad")));
+    let context = MockSheetContext::new();
+    let sheet = doc.parse_sheet("#images { cursor: url(a.png) 4 6, url(b.png), pointer } \
+                                 #bad { cursor: text; cursor: url(c.png), bogus, pointer }",
+                                StyleSheetOrigin::Author, &context);
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    Servo_ReleaseStyleSheet(sheet);
+    doc.restyle();
+
+    assert_eq!(cursor_of(&doc, "images"),
+               (NS_STYLE_CURSOR_POINTER, vec![("http://www.example.com/a.png".to_owned(), Some((4., 6.))),
+                                              ("http://www.example.com/b.png".to_owned(), None)]));
+    // A bad entry throws out the whole list, so its images aren't loaded either.
+    assert_eq!(cursor_of(&doc, "bad"), (NS_STYLE_CURSOR_TEXT, vec![]));
+    assert_eq!(cursor_of(&doc, "html"), (NS_STYLE_CURSOR_AUTO, vec![]));
+    let mut loads = context.loads();
+    loads.sort();
+    assert_eq!(loads, ["http://www.example.com/a.png", "http://www.example.com/b.png"]);
+}
+
+#[test]
+fn test_images_the_style_threads_ask_for_are_loaded_on_the_main_thread_after_the_traversal() {
+    let _guard = main_thread();
+    let doc = big_document();
+    let context = MockSheetContext::new();
+    let sheet = doc.parse_sheet("span { cursor: url(a.png), auto } div { background-image: url(b.png) }",
+                                StyleSheetOrigin::Author, &context);
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    Servo_ReleaseStyleSheet(sheet);
+    take_main_thread_only_calls_elsewhere();
+    Servo_SetDeterministicTraversal(false);
+    doc.restyle();
+
+    assert_eq!(take_main_thread_only_calls_elsewhere(), Vec::<&str>::new());
+    // Each url is loaded once, and the styles computed elsewhere hand over the request.
+    let mut loads = context.loads();
+    loads.sort();
+    assert_eq!(loads, ["http://www.example.com/a.png", "http://www.example.com/b.png"]);
+    assert_eq!(cursor_of(&doc, "first"),
+               (NS_STYLE_CURSOR_AUTO, vec![("http://www.example.com/a.png".to_owned(), None)]));
+}
+
+#[test]
+fn test_the_style_threads_look_up_what_gecko_says_about_the_platform_ahead_of_the_traversal() {
+    let _guard = main_thread();
+    let mut body = element("body");
+    for i in 0..100 {
+        let mut div = if i % 2 == 0 { element("div") } else { element("div").attr("lang", "ja") };
+        for j in 0..30 {
+            div = div.child(match (i, j) {
+                (0, 0) => element("span").id("mono"),
+                (1, 0) => element("span").id("ja"),
+                (2, 0) => element("span").id("caption"),
+                _ => element("span"),
+            });
+        }
+        body = body.child(div);
+    }
+    let doc = MockDocument::new(element("html").child(body));
+    doc.add_sheet("span { font-family: monospace; font-size: medium; color: GrayText; width: 2ex } \
+                   #caption { font: caption }", StyleSheetOrigin::Author);
+    take_main_thread_only_calls_elsewhere();
+    Servo_SetDeterministicTraversal(false);
+    doc.restyle();
+
+    assert_eq!(take_main_thread_only_calls_elsewhere(), Vec::<&str>::new());
+    assert_eq!(doc.computed_value("mono", nsCSSProperty::eCSSProperty_font_size), "13px");
+    assert_eq!(doc.computed_value("ja", nsCSSProperty::eCSSProperty_font_size), "16px");
+    assert_eq!(doc.computed_value("mono", nsCSSProperty::eCSSProperty_color), "rgb(109, 109, 109)");
+    // Without metrics, ex is half an em.
+    assert_eq!(doc.computed_value("ja", nsCSSProperty::eCSSProperty_width), "16px");
+}
+
+/// The counters and values Servo_GetComputedCounterData gives |id| for |which|.
+fn counters_of(doc: &MockDocument, id: &str, which: u8) -> Vec<(*mut nsIAtom, i32)> {
+    let mut list = MockCounterItemArray::default();
+    let style = doc.computed_values(id);
+    Servo_GetComputedCounterData(Borrowed::from_arc(&style).as_ptr(), which, list.as_raw());
+    list.items.iter().map(|item| (item.mName, item.mValue)).collect()
+}
+
+#[test]
+fn test_counter_lists_imply_the_list_item_increment_and_share_atoms_with_content() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("ol").id("list")
+                                    .child(element("li").id("implied"))
+                                    .child(element("li").id("explicit"))
+                                    .child(element("li").id("none")));
+    doc.add_sheet("#list { counter-reset: section 2 list-item; counter-set: page } \
+                   li { display: list-item; counter-increment: section } \
+                   #explicit { counter-increment: list-item 3 section -1 } \
+                   #none { counter-increment: none; content: counter(section) counters(list-item, '.') }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(counters_of(&doc, "list", 0), [(atom("section"), 2), (atom("list-item"), 0)]);
+    assert!(counters_of(&doc, "list", 1).is_empty());
+    assert_eq!(counters_of(&doc, "list", 2), [(atom("page"), 0)]);
+    assert!(counters_of(&doc, "list", 3).is_empty());
+    assert_eq!(counters_of(&doc, "implied", 1), [(atom("section"), 1), (atom("list-item"), 1)]);
+    assert_eq!(counters_of(&doc, "explicit", 1), [(atom("list-item"), 3), (atom("section"), -1)]);
+    assert_eq!(counters_of(&doc, "none", 1), [(atom("list-item"), 1)]);
+
+    let mut items = MockContentItemList::default();
+    let style = doc.computed_values("none");
+    Servo_GetComputedContent(Borrowed::from_arc(&style).as_ptr(), items.as_raw());
+    let names = items.items.iter().map(|item| item.counter_name).collect::<Vec<_>>();
+    assert_eq!(names, [atom("section"), atom("list-item")]);
+}
+
+#[test]
+fn test_probing_before_and_after_finds_nothing_when_their_content_is_none_or_normal() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("p").id("content"))
+                                               .child(element("p").id("none"))
+                                               .child(element("p").id("normal")));
+    doc.add_sheet("p::before, p::after { color: red } \
+                   #content::before, #content::after { content: \"x\" } \
+                   #none::before, #none::after { content: none }", StyleSheetOrigin::Author);
+    doc.restyle();
+    let pseudo_style = |id: &str, pseudo: PseudoElement, is_probe: bool| {
+        let parent = doc.computed_values(id);
+        Servo_GetComputedValuesForPseudoElement(Borrowed::from_arc(&parent).as_ptr(), doc.element(id), pseudo.atom(),
+                                                is_probe).into_arc::<GeckoComputedValues>()
+    };
+    for pseudo in vec![PseudoElement::Before, PseudoElement::After] {
+        assert!(pseudo_style("content", pseudo.clone(), true).is_some(), "{:?}", pseudo);
+        for &id in &["none", "normal"] {
+            assert!(pseudo_style(id, pseudo.clone(), true).is_none(), "{} {:?}", id, pseudo);
+            // What isn't a probe still gets the style.
+            assert!(pseudo_style(id, pseudo.clone(), false).is_some(), "{} {:?}", id, pseudo);
+        }
+    }
+}
+
+fn parse_style_attribute(css: &str) -> Arc<GeckoDeclarationBlock> {
+    Servo_ParseStyleAttribute(css.as_ptr(), css.len() as u32).into_arc::<GeckoDeclarationBlock>().unwrap()
+}
+
+fn declarations_of(block: &Arc<GeckoDeclarationBlock>) -> PropertyDeclarationBlock {
+    let guard = block.declarations.shared_lock().read();
+    block.declarations.read_with(&guard).clone().unwrap()
+}
+
+fn css_text(block: &Arc<GeckoDeclarationBlock>) -> String {
+    let mut text = String::new();
+    Servo_DeclarationBlock_GetCssText(Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(block).as_ptr(),
+                                      ns_string(&mut text));
+    text
+}
+
+#[test]
+fn test_important_style_attributes_beat_author_rules_and_are_only_parsed_when_they_change() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a").style("color: red !important"))
+                                               .child(element("div").id("b").style("color: red")));
+    doc.add_sheet("div { color: blue !important } #a, #b { color: green }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+    assert_eq!(doc.computed_value("b", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 255)");
+
+    // Restyling uses the block Gecko keeps for the attribute rather than parsing it again.
+    let block = unsafe { Gecko_GetStyleAttrDeclarationBlock(doc.element("a")) };
+    doc.mark_dirty("a");
+    doc.restyle();
+    assert_eq!(unsafe { Gecko_GetStyleAttrDeclarationBlock(doc.element("a")) }, block);
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+
+    doc.set_attr("a", "style", "color: green");
+    doc.mark_dirty("a");
+    doc.restyle();
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 255)");
+}
+
+#[test]
+fn test_style_attributes_parse_back_into_the_same_declarations() {
+    let _guard = main_thread();
+    for css in &["color: red", "margin: 1px 2px; color: blue !important", "text-decoration: underline; opacity: 0.5",
+                 "border-top: 1px solid red; border-right: 1px solid red; border-bottom: 1px solid red; \
+                  border-left: 1px solid red",
+                 "--gap: 2px; margin: var(--gap); float: left !important"] {
+        let block = parse_style_attribute(css);
+        let text = css_text(&block);
+        let reparsed = parse_style_attribute(&text);
+        assert_eq!(declarations_of(&reparsed), declarations_of(&block));
+        assert_eq!(css_text(&reparsed), text);
+    }
+    // The sides were written one at a time, so they come back that way rather than as
+    // border-width, border-style and border-color.
+    let sides = "border-top: red solid 1px; border-right: red solid 1px; border-bottom: red solid 1px; \
+                 border-left: red solid 1px;";
+    assert_eq!(css_text(&parse_style_attribute(sides)), sides);
+}
+
+#[test]
+fn test_declaration_blocks_list_their_properties_and_serialize_them_one_at_a_time() {
+    let _guard = main_thread();
+    let block = parse_style_attribute("margin: 1px 2px; --gap: 2px; color: blue !important");
+    let raw = Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(&block).as_ptr();
+    assert!(Servo_DeclarationBlock_HasImportant(raw));
+    assert!(!Servo_DeclarationBlock_HasImportant(
+        Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(&parse_style_attribute("color: blue")).as_ptr()));
+
+    let expected = [nsCSSProperty::eCSSProperty_margin_top, nsCSSProperty::eCSSProperty_margin_right,
+                    nsCSSProperty::eCSSProperty_margin_bottom, nsCSSProperty::eCSSProperty_margin_left,
+                    nsCSSProperty::eCSSPropertyExtra_variable, nsCSSProperty::eCSSProperty_color];
+    let mut ids = [nsCSSProperty::eCSSProperty_UNKNOWN; 8];
+    assert_eq!(Servo_DeclarationBlock_GetPropertyIds(raw, ids.as_mut_ptr(), 8), 6);
+    assert_eq!(ids[..6].iter().map(|&id| id as i32).collect::<Vec<_>>(),
+               expected.iter().map(|&id| id as i32).collect::<Vec<_>>());
+    // Only as many as fit are written, but all of them are counted.
+    let mut ids = [nsCSSProperty::eCSSProperty_UNKNOWN; 2];
+    assert_eq!(Servo_DeclarationBlock_GetPropertyIds(raw, ids.as_mut_ptr(), 2), 6);
+    assert_eq!(ids[1] as i32, nsCSSProperty::eCSSProperty_margin_right as i32);
+
+    let value = |property| {
+        let mut value = String::new();
+        Servo_DeclarationBlock_SerializeOneValue(raw, property, ns_string(&mut value));
+        value
+    };
+    assert_eq!(value(nsCSSProperty::eCSSProperty_margin), "1px 2px 1px 2px");
+    assert_eq!(value(nsCSSProperty::eCSSProperty_margin_left), "2px");
+    assert_eq!(value(nsCSSProperty::eCSSProperty_color), "blue");
+    assert_eq!(value(nsCSSProperty::eCSSProperty_float), "");
+}
+
+#[test]
+fn test_shorthands_only_serialize_when_their_longhands_agree_on_importance() {
+    let _guard = main_thread();
+    let value = |css, property| {
+        let block = parse_style_attribute(css);
+        let raw = Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(&block).as_ptr();
+        let mut value = String::new();
+        Servo_DeclarationBlock_SerializeOneValue(raw, property, ns_string(&mut value));
+        value
+    };
+    let margin = nsCSSProperty::eCSSProperty_margin;
+    assert_eq!(value("margin: 1px 2px; margin-left: 3px !important", margin), "");
+    assert_eq!(value("margin: 1px 2px; margin-left: 3px !important", nsCSSProperty::eCSSProperty_margin_left),
+               "3px");
+    assert_eq!(value("margin: 1px 2px !important", margin), "1px 2px 1px 2px");
+    assert_eq!(value("margin: 1px 2px; margin-left: 3px", margin), "1px 2px 1px 3px");
+}
+
+#[test]
+fn test_gecko_matches_the_moz_pseudo_classes_of_ua_sheets_for_us() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html")
+        .child(element("iframe").id("browser").attr("mozbrowser", ""))
+        .child(element("iframe").id("iframe"))
+        .child(element("div").id("anonymous").native_anonymous())
+        .child(element("div").id("dragged-over").state(NS_EVENT_STATE_DRAGOVER.bits())));
+    let css = ":-moz-browser-frame { color: green } :-MOZ-native-anonymous { color: blue } \
+               :-moz-drag-over { color: red }";
+    doc.add_sheet(css, StyleSheetOrigin::UserAgent);
+    doc.restyle();
+    assert_eq!(doc.computed_value("browser", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    assert_eq!(doc.computed_value("iframe", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 0)");
+    assert_eq!(doc.computed_value("anonymous", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 255)");
+    assert_eq!(doc.computed_value("dragged-over", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+
+    let sheet = doc.parse_sheet(":-MOZ-Browser-Frame { color: green }", StyleSheetOrigin::UserAgent,
+                                &MockSheetContext::new());
+    let rules = unsafe { into_raw(Servo_StyleSheet_GetRules(sheet)) };
+    let rule = unsafe { into_raw(Servo_CssRules_GetStyleRuleAt(rules, 0)) };
+    let mut text = String::new();
+    Servo_StyleRule_GetSelectorText(rule, ns_string(&mut text));
+    assert_eq!(text, ":-moz-browser-frame");
+    Servo_StyleRule_Release(rule);
+    Servo_CssRules_Release(rules);
+    Servo_ReleaseStyleSheet(sheet);
+
+    // Pages can only use the ones Servo knows.
+    for css in &[":-moz-browser-frame { color: green }", ":-moz-made-up { color: green }"] {
+        let sheet = doc.parse_sheet(css, StyleSheetOrigin::Author, &MockSheetContext::new());
+        assert!(!Servo_StyleSheetHasRules(sheet), "{}", css);
+        Servo_ReleaseStyleSheet(sheet);
+    }
+    let sheet = doc.parse_sheet(":-moz-drag-over { color: green }", StyleSheetOrigin::Author,
+                                &MockSheetContext::new());
+    assert!(Servo_StyleSheetHasRules(sheet));
+    Servo_ReleaseStyleSheet(sheet);
+}
+
+#[test]
+fn test_every_pseudo_element_round_trips_through_its_atom_and_selector() {
+    let _guard = main_thread();
+    let doc = sample_document();
+    let mut names = HashSet::new();
+    for pseudo in PseudoElement::all() {
+        let name = pseudo.as_str();
+        assert!(names.insert(name), "::{} is in the table twice", name);
+        assert_eq!(PseudoElement::from_slice(&name.to_ascii_uppercase()).as_ref(), Some(pseudo));
+
+        let atom = pseudo.atom();
+        assert!(unsafe { WeakAtom::new(atom) }.eq_str(&format!(":{}", name)));
+        assert_eq!(unsafe { PseudoElement::from_atom(atom) }.as_ref(), Some(pseudo));
+
+        let css = format!("::{} {{ color: red }}", name);
+        let sheet = doc.parse_sheet(&css, StyleSheetOrigin::UserAgent, &MockSheetContext::new());
+        let rules = unsafe { into_raw(Servo_StyleSheet_GetRules(sheet)) };
+        let rule = unsafe { into_raw(Servo_CssRules_GetStyleRuleAt(rules, 0)) };
+        let mut text = String::new();
+        Servo_StyleRule_GetSelectorText(rule, ns_string(&mut text));
+        assert_eq!(text, format!("::{}", name));
+        Servo_StyleRule_Release(rule);
+        Servo_CssRules_Release(rules);
+        Servo_ReleaseStyleSheet(sheet);
+
+        // Author sheets can't reach into the boxes only Gecko's own sheets style.
+        let sheet = doc.parse_sheet(&css, StyleSheetOrigin::Author, &MockSheetContext::new());
+        assert_eq!(Servo_StyleSheetHasRules(sheet), !pseudo.is_ua_sheet_only(), "::{}", name);
+        Servo_ReleaseStyleSheet(sheet);
+    }
+    let eager: Vec<_> = PseudoElement::all().iter().filter(|pseudo| pseudo.is_eager()).collect();
+    assert_eq!(eager, [&PseudoElement::Before, &PseudoElement::After,
+                       &PseudoElement::FirstLine, &PseudoElement::FirstLetter]);
+    assert!(PseudoElement::MozAnonymousBlock.is_anon_box() && !PseudoElement::MozProgressBar.is_anon_box());
+    assert!(PseudoElement::from_slice("-moz-frobnicator").is_none());
+}
+
+fn describe(rule: &MockRule) -> String {
+    let mut text = String::new();
+    match *rule {
+        MockRule::Style { ref rule, .. } => {
+            Servo_StyleRule_GetSelectorText(Borrowed::<_>::from_arc::<GeckoStyleRule>(rule).as_ptr(),
+                                            ns_string(&mut text));
+        }
+        MockRule::Declarations { ref declarations, is_style_attribute } => {
+            Servo_DeclarationBlock_GetCssText(Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(declarations).as_ptr(),
+                                              ns_string(&mut text));
+            if is_style_attribute {
+                text = format!("style=\"{}\"", text);
+            }
+        }
+    }
+    text
+}
+
+#[test]
+fn test_matching_rules_come_in_cascade_order() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("div").id("a").class("note").style("float: left").presentational_hints("position: relative")));
+    doc.add_sheet("#a { float: right } .note { float: none } p { float: left }", StyleSheetOrigin::Author);
+    doc.add_sheet("div { display: block }", StyleSheetOrigin::UserAgent);
+
+    let mut list = MockRuleList::default();
+    Servo_GetMatchingRules(doc.element("a"), ptr::null_mut(), doc.style_set(), list.as_raw());
+    let rules: Vec<_> = list.rules.iter().map(describe).collect();
+    assert_eq!(rules, ["div", "position: relative;", ".note", "#a", "style=\"float: left;\""]);
+}
+
+#[test]
+fn test_author_rules_beat_presentation_attributes_which_beat_user_agent_rules() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("table").child(element("td").id("ruled").class("wide").presentational_hints("width: 50%"))
+                        .child(element("td").id("unruled").presentational_hints("width: 50%"))
+                        .child(element("td").id("plain"))));
+    doc.add_sheet("td { width: 10px }", StyleSheetOrigin::UserAgent);
+    doc.add_sheet(".wide { width: 200px }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert_eq!(doc.computed_value("ruled", nsCSSProperty::eCSSProperty_width), "200px");
+    assert_eq!(doc.computed_value("unruled", nsCSSProperty::eCSSProperty_width), "50%");
+    assert_eq!(doc.computed_value("plain", nsCSSProperty::eCSSProperty_width), "10px");
+}
+
+#[test]
+fn test_origins_cascade_in_order_with_importance_reversing_it() {
+    let _guard = main_thread();
+    // What float the user agent, user and author sheets ask for, and which of them wins.
+    let cases = [
+        (("left", ""), ("right", ""), ("none", ""), "none"),
+        (("left", ""), ("right", ""), ("", ""), "right"),
+        (("left", ""), ("right", " !important"), ("none", ""), "right"),
+        (("left", ""), ("right", ""), ("none", " !important"), "none"),
+        (("left", ""), ("right", " !important"), ("none", " !important"), "right"),
+        (("left", " !important"), ("right", ""), ("none", ""), "left"),
+        (("left", " !important"), ("right", " !important"), ("none", " !important"), "left"),
+        (("left", " !important"), ("", ""), ("none", ""), "left"),
+    ];
+    for &(user_agent, user, author, expected) in &cases {
+        let doc = MockDocument::new(element("html").child(element("div").id("a")));
+        // Added out of cascade order, to check the style set doesn't go by the order sheets came in.
+        for &((value, importance), origin) in &[(author, StyleSheetOrigin::Author), (user, StyleSheetOrigin::User),
+                                                (user_agent, StyleSheetOrigin::UserAgent)] {
+            if !value.is_empty() {
+                let css = format!("div {{ float: {}{} }}", value, importance);
+                let sheet = doc.parse_sheet(&css, origin, &MockSheetContext::new());
+                Servo_StyleSet_AppendStyleSheetToOrigin(doc.style_set(), sheet, origin);
+                Servo_ReleaseStyleSheet(sheet);
+            }
+        }
+        doc.restyle();
+        assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_float), expected,
+                   "user agent: {:?}, user: {:?}, author: {:?}", user_agent, user, author);
+    }
+}
+
+#[test]
+fn test_style_sets_list_each_origins_sheets_and_only_rebuild_the_ones_that_changed() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a")));
+    let set = doc.style_set();
+    let parse = |css: &str, origin| doc.parse_sheet(css, origin, &MockSheetContext::new());
+    let (author, first_user) = (parse("div { float: none }", StyleSheetOrigin::Author),
+                                parse("div { float: left }", StyleSheetOrigin::User));
+    let second_user = parse("div { float: right }", StyleSheetOrigin::User);
+    Servo_AppendStyleSheet(author, set);
+    Servo_StyleSet_AppendStyleSheetToOrigin(set, first_user, StyleSheetOrigin::User);
+    Servo_StyleSet_AppendStyleSheetToOrigin(set, second_user, StyleSheetOrigin::User);
+    doc.restyle();
+
+    let sheet_at = |origin, index| unsafe { into_raw(Servo_StyleSet_SheetAt(set, origin, index)) };
+    assert_eq!(Servo_StyleSet_SheetCount(set, StyleSheetOrigin::UserAgent), 0);
+    assert_eq!(Servo_StyleSet_SheetCount(set, StyleSheetOrigin::User), 2);
+    assert_eq!(Servo_StyleSet_SheetCount(set, StyleSheetOrigin::Author), 1);
+    for &(origin, index, expected) in &[(StyleSheetOrigin::User, 0, first_user),
+                                        (StyleSheetOrigin::User, 1, second_user),
+                                        (StyleSheetOrigin::Author, 0, author)] {
+        let sheet = sheet_at(origin, index);
+        assert_eq!(sheet, expected);
+        Servo_ReleaseStyleSheet(sheet);
+    }
+    assert!(sheet_at(StyleSheetOrigin::User, 2).is_null());
+    assert!(sheet_at(StyleSheetOrigin::UserAgent, 0).is_null());
+
+    // Removing a user sheet leaves the author rules alone.
+    let rebuilds = |origin| Servo_StyleSet_GetRebuildCount(set, origin);
+    let (user_rebuilds, author_rebuilds) = (rebuilds(StyleSheetOrigin::User), rebuilds(StyleSheetOrigin::Author));
+    Servo_RemoveStyleSheet(second_user, set);
+    doc.restyle();
+    assert_eq!(rebuilds(StyleSheetOrigin::User), user_rebuilds + 1);
+    assert_eq!(rebuilds(StyleSheetOrigin::Author), author_rebuilds);
+    assert_eq!(Servo_StyleSet_SheetCount(set, StyleSheetOrigin::User), 1);
+
+    // So does replacing the sheets with the same author sheets and other user ones.
+    Servo_StyleSet_ReplaceStyleSheets(set, [second_user, author].as_ptr(), 2);
+    doc.restyle();
+    assert_eq!(rebuilds(StyleSheetOrigin::User), user_rebuilds + 2);
+    assert_eq!(rebuilds(StyleSheetOrigin::Author), author_rebuilds);
+    let sheet = sheet_at(StyleSheetOrigin::User, 0);
+    assert_eq!(sheet, second_user);
+    Servo_ReleaseStyleSheet(sheet);
+
+    for &sheet in &[author, first_user, second_user] {
+        Servo_ReleaseStyleSheet(sheet);
+    }
+}
+
+#[test]
+fn test_only_the_rules_of_the_origins_whose_sheets_changed_are_rebuilt() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a")));
+    let set = doc.style_set();
+    doc.add_sheet("div { display: inline; float: left }", StyleSheetOrigin::UserAgent);
+    doc.add_sheet("div { float: right }", StyleSheetOrigin::User);
+    doc.restyle();
+    let rebuilds = || (Servo_StyleSet_GetRebuildCount(set, StyleSheetOrigin::UserAgent),
+                       Servo_StyleSet_GetRebuildCount(set, StyleSheetOrigin::User),
+                       Servo_StyleSet_GetRebuildCount(set, StyleSheetOrigin::Author));
+    let (ua, user, author) = rebuilds();
+
+    // However many author sheets come and go before the restyle, the author rules are rebuilt
+    // once, and the user agent and user ones not at all.
+    doc.add_sheet("#a { visibility: hidden }", StyleSheetOrigin::Author);
+    let sheet = doc.parse_sheet("div { float: none }", StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_AppendStyleSheet(sheet, set);
+    Servo_RemoveStyleSheet(sheet, set);
+    Servo_ReleaseStyleSheet(sheet);
+    doc.restyle();
+    assert_eq!(rebuilds(), (ua, user, author + 1));
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_display), "inline");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_float), "right");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_visibility), "hidden");
+
+    // Nothing is rebuilt when no sheet changed.
+    doc.mark_dirty("a");
+    doc.restyle();
+    assert_eq!(rebuilds(), (ua, user, author + 1));
+
+    Servo_StyleSet_NoteStyleSheetsChanged(set, StyleSheetOrigin::User);
+    doc.restyle();
+    assert_eq!(rebuilds(), (ua, user + 1, author + 1));
+}
+
+#[test]
+fn test_late_sheets_only_restyle_the_elements_their_selectors_match() {
+    let _guard = main_thread();
+    Servo_SetTraversalStatisticsEnabled(true);
+    let styled = |doc: &MockDocument| {
+        doc.restyle();
+        let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+        Servo_GetTraversalStatistics(&mut statistics);
+        statistics.mElementsStyled
+    };
+    for &(css, expected) in &[(".late { color: red }", 2), ("div .late { color: red }", 1),
+                              (":root { color: red }", 5), ("* { color: red }", 5)] {
+        let doc = MockDocument::new(element("html").id("html")
+            .child(element("div").id("div").child(element("p").id("first").class("late"))
+                                           .child(element("p").id("second")))
+            .child(element("span").id("last").class("late")));
+        doc.add_sheet("#second { color: blue }", StyleSheetOrigin::Author);
+        assert_eq!(styled(&doc), 5);
+        let second = doc.computed_values("second");
+
+        let sheet = doc.parse_sheet(css, StyleSheetOrigin::Author, &MockSheetContext::new());
+        Servo_AppendStyleSheet(sheet, doc.style_set());
+        Servo_StyleSet_InvalidateForSheet(doc.raw_document(), doc.style_set(), sheet);
+        Servo_ReleaseStyleSheet(sheet);
+        assert_eq!(styled(&doc), expected);
+        assert_eq!(doc.computed_value("first", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+        let last = doc.computed_value("last", nsCSSProperty::eCSSProperty_color);
+        assert_eq!(last == "rgb(255, 0, 0)", !css.starts_with("div"));
+        if expected < 5 {
+            assert!(arc_ptr_eq(&doc.computed_values("second"), &second));
+        }
+    }
+
+    // Other changes to the rules since the last restyle still restyle everything.
+    let doc = MockDocument::new(element("html").child(element("p").id("p").class("late")));
+    doc.add_sheet("p { color: blue }", StyleSheetOrigin::Author);
+    assert_eq!(styled(&doc), 2);
+    let sheet = doc.parse_sheet(".late { color: red }", StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_StyleSet_SetAuthorStyleDisabled(doc.style_set(), true);
+    Servo_StyleSet_SetAuthorStyleDisabled(doc.style_set(), false);
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    Servo_StyleSet_InvalidateForSheet(doc.raw_document(), doc.style_set(), sheet);
+    Servo_ReleaseStyleSheet(sheet);
+    assert_eq!(styled(&doc), 2);
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+    Servo_SetTraversalStatisticsEnabled(false);
+}
+
+#[test]
+fn test_computed_values_are_handed_out_without_cascading_until_they_are_out_of_date() {
+    let _guard = main_thread();
+    Servo_SetTraversalStatisticsEnabled(true);
+    let statistics = || {
+        let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+        Servo_GetTraversalStatistics(&mut statistics);
+        statistics
+    };
+    let doc = MockDocument::new(element("html").child(element("div").id("div").child(element("p").id("p"))));
+    doc.add_sheet("div { float: left } p { display: block }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert_eq!(statistics().mElementsStyled, 3);
+
+    let first = doc.computed_values("p");
+    assert_eq!((statistics().mElementsStyled, statistics().mDeclarationsCascaded), (0, 0));
+    let second = doc.computed_values("p");
+    assert_eq!((statistics().mElementsStyled, statistics().mDeclarationsCascaded), (0, 0));
+    assert!(arc_ptr_eq(&first, &second));
+
+    // Once the rules change, the style is resolved again, along with its ancestors' up to the root.
+    doc.add_sheet("div { float: right }", StyleSheetOrigin::Author);
+    let third = doc.computed_values("p");
+    assert!(!arc_ptr_eq(&first, &third));
+    assert_eq!(statistics().mElementsStyled, 3);
+    assert!(statistics().mDeclarationsCascaded > 0);
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_float), "right");
+    Servo_SetTraversalStatisticsEnabled(false);
+}
+
+#[test]
+fn test_changing_a_rules_declarations_only_cascades_the_elements_it_applies_to() {
+    let _guard = main_thread();
+    Servo_SetTraversalStatisticsEnabled(true);
+    let doc = MockDocument::new(element("html").id("html")
+        .child(element("div").id("first").class("a").child(element("span").id("child")))
+        .child(element("div").id("second").class("b"))
+        .child(element("div").id("third").class("a"))
+        .child(element("div").id("fourth").class("c")));
+    PerDocumentStyleData::borrow_mut_from_raw(doc.style_set()).rule_tree = Some(Arc::new(RuleTree::new()));
+    let sheet = doc.parse_sheet(".b { color: blue } .a { color: red } .c { color: blue }",
+                                StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    doc.restyle();
+    let unchanged: Vec<_> = ["html", "second", "fourth"].iter().map(|id| doc.computed_values(id)).collect();
+
+    let rules = unsafe { into_raw(Servo_StyleSheet_GetRules(sheet)) };
+    let rule = unsafe { into_raw(Servo_CssRules_GetStyleRuleAt(rules, 1)) };
+    let declarations = unsafe { into_raw(Servo_StyleRule_GetDeclarations(rule)) };
+    let (property, value) = ("color", "green");
+    assert!(Servo_DeclarationBlock_SetProperty(declarations, property.as_ptr(), property.len() as u32,
+                                               value.as_ptr(), value.len() as u32, false));
+    Servo_StyleSet_RuleChanged(doc.style_set(), rule);
+    doc.restyle();
+
+    // The two .a elements, and the child that inherits the color, without any matching.
+    let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+    Servo_GetTraversalStatistics(&mut statistics);
+    assert_eq!(statistics.mElementsStyled, 3);
+    assert_eq!(statistics.mSelectorsMatched, 0);
+    for id in &["first", "child", "third"] {
+        assert_eq!(doc.computed_value(id, nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    }
+    for (id, style) in ["html", "second", "fourth"].iter().zip(&unchanged) {
+        assert!(arc_ptr_eq(&doc.computed_values(id), style));
+    }
+
+    // The rules the stylist was rebuilt with have the new declarations too.
+    doc.append_child("html", element("div").id("fifth").class("a"));
+    doc.restyle();
+    assert_eq!(doc.computed_value("fifth", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+
+    Servo_ReleaseDeclarationBlock(declarations);
+    Servo_StyleRule_Release(rule);
+    Servo_CssRules_Release(rules);
+    Servo_ReleaseStyleSheet(sheet);
+    Servo_SetTraversalStatisticsEnabled(false);
+}
+
+#[test]
+fn test_unsupported_rules_apply_nothing_but_keep_their_text() {
+    use super::crash_annotation;
+
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html")
+        .child(element("p").id("p"))
+        .child(element("div").id("div")));
+    let css = "p { color: red } @frobnicate {} @media screen { @frobnicate one; div { color: blue } } \
+               @supports (display: block) { @-vendor-thing x { y: z } p { color: green } }";
+    let sheet = doc.parse_sheet(css, StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    doc.restyle();
+
+    // The rules around and after the unsupported ones still apply.
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 255)");
+    assert_eq!(PerDocumentStyleData::borrow_mut_from_raw(doc.style_set()).unsupported_rule_count(), 3);
+    assert_eq!(crash_annotation("ServoUnsupportedCSSRules").as_ref().map(|s| &**s),
+               Some("3 (frobnicate, -vendor-thing)"));
+
+    // The CSSOM has them as rules of unknown type, with the text they were written with.
+    let text_at = |rules, index| {
+        let mut text = String::new();
+        if Servo_CssRules_GetUnsupportedRuleTextAt(rules, index, ns_string(&mut text)) {
+            Some(text)
+        } else {
+            None
+        }
+    };
+    let rules = unsafe { into_raw(Servo_StyleSheet_GetRules(sheet)) };
+    assert_eq!(Servo_CssRules_GetTypeAt(rules, 1), 0);
+    assert_eq!(text_at(rules, 0), None);
+    assert_eq!(text_at(rules, 1).as_ref().map(|s| &**s), Some("@frobnicate {}"));
+    let media_rules = unsafe { into_raw(Servo_CssRules_GetNestedRulesAt(rules, 2)) };
+    assert_eq!(text_at(media_rules, 0).as_ref().map(|s| &**s), Some("@frobnicate one;"));
+    let supports_rules = unsafe { into_raw(Servo_CssRules_GetNestedRulesAt(rules, 3)) };
+    assert_eq!(text_at(supports_rules, 0).as_ref().map(|s| &**s), Some("@-vendor-thing x { y: z }"));
+
+    Servo_CssRules_Release(supports_rules);
+    Servo_CssRules_Release(media_rules);
+    Servo_CssRules_Release(rules);
+    Servo_ReleaseStyleSheet(sheet);
+}
+
+#[test]
+fn test_animated_values_cascade_at_their_levels() {
+    let _guard = main_thread();
+    Servo_SetTraversalStatisticsEnabled(true);
+    let doc = MockDocument::new(element("html").id("html")
+        .child(element("div").id("a"))
+        .child(element("div").id("b")));
+    PerDocumentStyleData::borrow_mut_from_raw(doc.style_set()).rule_tree = Some(Arc::new(RuleTree::new()));
+    let user_agent_sheet = doc.parse_sheet("#a { margin-right: 7px !important }", StyleSheetOrigin::UserAgent,
+                                           &MockSheetContext::new());
+    let sheet = doc.parse_sheet("#a { color: red; width: 1px; height: 1px; margin-left: 1px; margin-right: 1px; \
+                                      padding-left: 1px; padding-right: 1px } \
+                                 #a { height: 2px !important; margin-left: 2px !important; \
+                                      padding-top: 2px !important } \
+                                 #b { width: 1px; height: 2px !important }",
+                                StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_AppendStyleSheet(user_agent_sheet, doc.style_set());
+    Servo_AppendStyleSheet(sheet, doc.style_set());
+    doc.animate("a", CascadeLevel::SMILOverride, "padding-left: 5px; padding-right: 5px; padding-top: 5px");
+    doc.animate("a", CascadeLevel::Animations, "color: green; width: 3px; height: 3px; padding-left: 6px");
+    doc.animate("a", CascadeLevel::Transitions, "width: 4px; margin-left: 4px; margin-right: 4px");
+    doc.restyle();
+
+    let expected = [
+        // Animations win over normal declarations, and lose to important ones.
+        (nsCSSProperty::eCSSProperty_color, "rgb(0, 128, 0)"),
+        (nsCSSProperty::eCSSProperty_height, "2px"),
+        // Transitions win over animations and important author declarations, but not over
+        // important user agent ones.
+        (nsCSSProperty::eCSSProperty_width, "4px"),
+        (nsCSSProperty::eCSSProperty_margin_left, "4px"),
+        (nsCSSProperty::eCSSProperty_margin_right, "7px"),
+        // SMIL overrides normal declarations, and loses to animations and important ones.
+        (nsCSSProperty::eCSSProperty_padding_right, "5px"),
+        (nsCSSProperty::eCSSProperty_padding_left, "6px"),
+        (nsCSSProperty::eCSSProperty_padding_top, "2px"),
+    ];
+    for &(property, value) in &expected {
+        assert_eq!(doc.computed_value("a", property), value);
+    }
+
+    // The animation-only restyle swaps the values for newer ones, and puts those of an element
+    // that had none where they go, without matching again.
+    doc.animate("a", CascadeLevel::Transitions, "width: 8px");
+    doc.animate("b", CascadeLevel::Animations, "height: 3px");
+    doc.animate("b", CascadeLevel::Transitions, "width: 4px");
+    Servo_RestyleForAnimations(doc.raw_document(), doc.style_set());
+    let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+    Servo_GetTraversalStatistics(&mut statistics);
+    assert_eq!(statistics.mElementsStyled, 2);
+    assert_eq!(statistics.mSelectorsMatched, 0);
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_width), "8px");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_margin_left), "2px");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    assert_eq!(doc.computed_value("b", nsCSSProperty::eCSSProperty_height), "2px");
+    assert_eq!(doc.computed_value("b", nsCSSProperty::eCSSProperty_width), "4px");
+    for id in &["a", "b"] {
+        assert_eq!(doc.flags(id) & NODE_IS_DIRTY_FOR_SERVO, 0);
+    }
+
+    Servo_ReleaseStyleSheet(user_agent_sheet);
+    Servo_ReleaseStyleSheet(sheet);
+    Servo_SetTraversalStatisticsEnabled(false);
+}
+
+#[test]
+fn test_no_sheet_parsed_for_a_style_set_is_delivered_once_it_is_dropped() {
+    let _guard = main_thread();
+    let mut css = String::new();
+    for i in 0..200 {
+        css.push_str(&format!(".rule{} {{ color: red; margin: {}px }}\n", i, i));
+    }
+    let base = "http://www.example.com/";
+    let parse = |context: &MockSheetContext, set: *mut RawServoStyleSet| {
+        Servo_StyleSheet_FromUTF8BytesAsync(css.as_ptr(), css.len() as u32, base.as_ptr(), base.len() as u32,
+                                            StyleSheetOrigin::Author, COMPATIBILITY_FULL_STANDARDS,
+                                            context.extra_data, context.as_raw(), set);
+    };
+    // Parses that get the parser thread to the end of the queue.
+    let drain = || {
+        let context = MockSheetContext::new();
+        parse(&context, ptr::null_mut());
+        context.wait_for_load();
+    };
+
+    // Dropping the set right after starting the parse races with it. Gecko may get the sheet
+    // before the drop returns, but never afterwards.
+    let contexts: Vec<MockSheetContext> = (0..50).map(|_| MockSheetContext::new()).collect();
+    for (i, context) in contexts.iter().enumerate() {
+        let doc = MockDocument::new(element("html"));
+        parse(context, doc.style_set());
+        if i % 2 == 0 {
+            Servo_StyleSet_Shutdown(doc.style_set());
+            let _ = context.take_loaded();
+        }
+        drop(doc);
+        let _ = context.take_loaded();
+    }
+    drain();
+    for context in &contexts {
+        assert!(context.take_loaded().is_none());
+        assert!(!Servo_StyleSheet_CancelAsyncParse(context.as_raw()));
+    }
+
+    // Nothing is parsed for a set that was shut down, nor restyled.
+    let doc = MockDocument::new(element("html").id("html"));
+    Servo_StyleSet_Shutdown(doc.style_set());
+    let context = MockSheetContext::new();
+    parse(&context, doc.style_set());
+    drain();
+    assert!(context.take_loaded().is_none());
+    doc.restyle();
+    assert!(doc.flags("html") & NODE_IS_DIRTY_FOR_SERVO != 0);
+
+    // Parses for other sets go on.
+    let other = MockDocument::new(element("html"));
+    let context = MockSheetContext::new();
+    parse(&context, other.style_set());
+    drop(doc);
+    let sheet = context.wait_for_load();
+    assert_eq!(sheet.read_rules().len(), 200);
+}
+
+#[test]
+fn test_author_style_can_be_disabled_and_enabled_again() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div").style("color: blue"))
+                                               .child(element("p").id("p")));
+    doc.add_sheet("div { display: block; color: red }", StyleSheetOrigin::UserAgent);
+    doc.add_sheet("p { color: green }", StyleSheetOrigin::User);
+    doc.add_sheet("div { display: inline; margin-top: 5px } p { color: black }", StyleSheetOrigin::Author);
+    doc.restyle();
+    let set = doc.style_set();
+    let author = || (doc.computed_value("div", nsCSSProperty::eCSSProperty_display),
+                     doc.computed_value("div", nsCSSProperty::eCSSProperty_color),
+                     doc.computed_value("div", nsCSSProperty::eCSSProperty_margin_top),
+                     doc.computed_value("p", nsCSSProperty::eCSSProperty_color));
+    let styled = ("inline".to_owned(), "rgb(0, 0, 255)".to_owned(), "5px".to_owned(), "rgb(0, 0, 0)".to_owned());
+    let unstyled = ("block".to_owned(), "rgb(255, 0, 0)".to_owned(), "0px".to_owned(), "rgb(0, 128, 0)".to_owned());
+    assert_eq!(author(), styled);
+    assert!(!Servo_StyleSet_GetAuthorStyleDisabled(set));
+
+    // Only the heapsize-measured stylist matters here.
+    unsafe extern "C" fn no_malloc_size_of(_: *const c_void) -> usize { 0 }
+    let rebuilds = || (Servo_StyleSet_GetRebuildCount(set, StyleSheetOrigin::UserAgent),
+                       Servo_StyleSet_GetRebuildCount(set, StyleSheetOrigin::Author));
+    let (ua_rebuilds, author_rebuilds) = rebuilds();
+    let mut sizes = vec![];
+    for cycle in 1..3 {
+        // Neither the style attribute nor the author sheet applies, but the others still do.
+        Servo_StyleSet_SetAuthorStyleDisabled(set, true);
+        assert!(Servo_StyleSet_GetAuthorStyleDisabled(set));
+        doc.restyle();
+        assert_eq!(author(), unstyled);
+        let disabled_size = Servo_StyleSet_SizeOf(no_malloc_size_of, set);
+
+        Servo_StyleSet_SetAuthorStyleDisabled(set, false);
+        doc.restyle();
+        assert_eq!(author(), styled);
+        let enabled_size = Servo_StyleSet_SizeOf(no_malloc_size_of, set);
+        assert!(disabled_size < enabled_size, "{} {}", disabled_size, enabled_size);
+        sizes.push((disabled_size, enabled_size));
+
+        // Only the author rules were rebuilt.
+        assert_eq!(rebuilds(), (ua_rebuilds, author_rebuilds + 2 * cycle));
+    }
+    // Going back and forth doesn't leave anything behind.
+    assert_eq!(sizes[0], sizes[1]);
+
+    // Setting it to what it is is a no-op.
+    Servo_StyleSet_SetAuthorStyleDisabled(set, false);
+    doc.restyle();
+    assert_eq!(rebuilds(), (ua_rebuilds, author_rebuilds + 4));
+}
+
+#[test]
+fn test_the_medium_can_be_switched_for_print_preview() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div")));
+    doc.add_sheet("div { color: red } @media print { div { color: blue } }", StyleSheetOrigin::Author);
+    doc.restyle();
+    let set = doc.style_set();
+    assert_eq!(Servo_StyleSet_GetMedium(set), atom("screen"));
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+
+    assert!(Servo_StyleSet_SetMedium(set, atom("print")));
+    assert_eq!(Servo_StyleSet_GetMedium(set), atom("print"));
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 255)");
+    assert!(!Servo_StyleSet_SetMedium(set, atom("print")));
+
+    // Nothing is for an unknown medium but rules for all of them.
+    assert!(Servo_StyleSet_SetMedium(set, atom("tv")));
+    doc.restyle();
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+
+    // The sheet has no rules for the screen in particular, so going back to it changes nothing.
+    assert!(!Servo_StyleSet_SetMedium(set, atom("screen")));
+    assert_eq!(Servo_StyleSet_GetMedium(set), atom("screen"));
+}
+
+#[test]
+fn test_restyle_styles_every_element_and_clears_the_restyle_bits() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body")
+            .child(element("div").id("a").style("float: left").child(text("Some text")))
+            .child(element("span").id("b"))));
+    doc.add_sheet("div { display: block; position: absolute } body span { display: inline-block }",
+                  StyleSheetOrigin::Author);
+    assert!(doc.flags("a") & NODE_IS_DIRTY_FOR_SERVO != 0);
+    assert!(doc.flags("html") & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+
+    doc.restyle();
+    for id in &["html", "body", "a", "b"] {
+        assert_eq!(doc.flags(id) & (NODE_IS_DIRTY_FOR_SERVO | NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO), 0);
+    }
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_display), "block");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_float), "left");
+    assert_eq!(doc.computed_value("b", nsCSSProperty::eCSSProperty_display), "inline-block");
+    assert_eq!(doc.computed_value("body", nsCSSProperty::eCSSProperty_display), "inline");
+
+    // Text gets a style of its own, which doesn't inherit the reset properties.
+    let text = unsafe { Gecko_GetFirstChild(doc.node("a")) };
+    let style = Servo_GetComputedValuesForTextNode(text).into_arc::<GeckoComputedValues>().unwrap();
+    let mut position = String::new();
+    assert!(Servo_GetComputedPropertyValue(Borrowed::from_arc(&style).as_ptr(), nsCSSProperty::eCSSProperty_position,
+                                           ns_string(&mut position)));
+    assert_eq!(position, "static");
+
+    let span = doc.append_child("a", element("span").id("c"));
+    assert!(doc.flags("c") & NODE_IS_DIRTY_FOR_SERVO != 0);
+    assert!(doc.flags("a") & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+    assert!(!span.is_null());
+    doc.restyle();
+    assert_eq!(doc.computed_value("c", nsCSSProperty::eCSSProperty_display), "inline-block");
+}
+
+#[test]
+fn test_display_is_fixed_up_before_gecko_sees_it() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("span").id("float"))
+            .child(element("div").id("flex").child(element("span").id("item")))
+            .child(element("img").id("img"))
+            .child(element("span").id("contents"))));
+    doc.add_sheet("html { display: contents } #float { float: left } #flex { display: flex } \
+                   #item { display: inline-table } img, #contents { display: contents }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let display = |id| doc.computed_value(id, nsCSSProperty::eCSSProperty_display);
+    assert_eq!(display("html"), "block");
+    assert_eq!(display("float"), "block");
+    assert_eq!(display("item"), "table");
+    // Replaced elements can't do without their box.
+    assert_eq!(display("img"), "none");
+    assert_eq!(display("contents"), "contents");
+}
+
+#[test]
+fn test_floats_and_clears_compute_to_physical_sides() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("div").id("rtl").child(element("span").id("start")).child(element("span").id("end")))
+            .child(element("span").id("ltr").child(element("span").id("inherits")))
+            .child(element("span").id("fixed"))
+            .child(element("span").id("absolute"))));
+    doc.add_sheet("#rtl { direction: rtl } span { float: inline-start; clear: inline-end } \
+                   #end { float: inline-end; clear: inline-start } \
+                   #fixed { float: right; position: fixed } \
+                   #absolute { float: left } #absolute { position: absolute } \
+                   #inherits { float: inherit; position: absolute }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let sides = |id| {
+        let style = doc.computed_values(id);
+        let values = Borrowed::from_arc(&style).as_ptr();
+        (Servo_GetComputedFloat(values) as u8, Servo_GetComputedClear(values) as u8,
+         doc.computed_value(id, nsCSSProperty::eCSSProperty_float),
+         doc.computed_value(id, nsCSSProperty::eCSSProperty_clear))
+    };
+    let left = (ServoStyleFloat::Left as u8, "left".to_owned());
+    let right = (ServoStyleFloat::Right as u8, "right".to_owned());
+    let none = (ServoStyleFloat::None as u8, "none".to_owned());
+    let expect = |float: &(u8, String), clear: ServoStyleClear, clear_name: &str| {
+        (float.0, clear as u8, float.1.clone(), clear_name.to_owned())
+    };
+    assert_eq!(sides("start"), expect(&right, ServoStyleClear::Left, "left"));
+    assert_eq!(sides("end"), expect(&left, ServoStyleClear::Right, "right"));
+    assert_eq!(sides("ltr"), expect(&left, ServoStyleClear::Right, "right"));
+    // Absolutely positioned boxes don't float, whichever way the float got there.
+    assert_eq!(sides("fixed"), expect(&none, ServoStyleClear::Right, "right"));
+    assert_eq!(sides("absolute"), expect(&none, ServoStyleClear::Right, "right"));
+    assert_eq!(sides("inherits"), expect(&none, ServoStyleClear::Right, "right"));
+}
+
+#[test]
+fn test_typed_getters_return_what_the_sheet_declared() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("box")).child(element("span").id("plain")));
+    doc.add_sheet("#box { display: flex; position: relative; float: right; width: 25%; height: 10px }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let getters = |style: &Arc<GeckoComputedValues>| {
+        let values = Borrowed::from_arc(style).as_ptr();
+        (Servo_GetComputedDisplay(values) as u8, Servo_GetComputedPosition(values) as u8,
+         Servo_GetComputedFloat(values) as u8, Servo_GetComputedWidth(values), Servo_GetComputedHeight(values))
+    };
+    let (display, position, float, width, height) = getters(&doc.computed_values("box"));
+    assert_eq!((display, position, float),
+               (ServoStyleDisplay::Flex as u8, ServoStylePosition::Relative as u8, ServoStyleFloat::Right as u8));
+    assert_percent(width, 0.25);
+    assert_length(height, 10);
+
+    let (display, position, float, width, height) = getters(&doc.computed_values("plain"));
+    assert_eq!((display, position, float),
+               (ServoStyleDisplay::Inline as u8, ServoStylePosition::Static as u8, ServoStyleFloat::None as u8));
+    assert_eq!((width.mUnit as u8, height.mUnit as u8), (ServoLengthUnit::Auto as u8, ServoLengthUnit::Auto as u8));
+
+    // Anonymous boxes inherit none of these, so they get the initial values whatever their parent has.
+    let parent = doc.computed_values("box");
+    let anonymous = Servo_GetComputedValuesForAnonymousBox(Borrowed::from_arc(&parent).as_ptr(),
+                                                           atom(":-moz-anonymous-block"));
+    let (display, position, float, width, height) = getters(&anonymous.into_arc::<GeckoComputedValues>().unwrap());
+    assert_eq!((display, position, float),
+               (ServoStyleDisplay::Inline as u8, ServoStylePosition::Static as u8, ServoStyleFloat::None as u8));
+    assert_eq!((width.mUnit as u8, height.mUnit as u8), (ServoLengthUnit::Auto as u8, ServoLengthUnit::Auto as u8));
+}
+
+#[test]
+fn test_anonymous_boxes_get_their_ua_rules_and_are_cascaded_once_per_parent() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html"));
+    doc.add_sheet("::-moz-anonymous-block { display: block; position: relative } \
+                   ::-moz-anonymous-block { position: absolute }",
+                  StyleSheetOrigin::UserAgent);
+    doc.add_sheet("::-moz-anonymous-block { float: left }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let parent = doc.computed_values("html");
+    let anonymous_box = || {
+        Servo_StyleSet_GetComputedValuesForAnonymousBox(doc.style_set(), Borrowed::from_arc(&parent).as_ptr(),
+                                                        atom(":-moz-anonymous-block"))
+            .into_arc::<GeckoComputedValues>().unwrap()
+    };
+    let first = anonymous_box();
+    assert_eq!(Servo_StyleSet_GetAnonymousBoxCascadeCount(doc.style_set()), 1);
+    let values = Borrowed::from_arc(&first).as_ptr();
+    assert_eq!(Servo_GetComputedDisplay(values) as u8, ServoStyleDisplay::Block as u8);
+    assert_eq!(Servo_GetComputedPosition(values) as u8, ServoStylePosition::Absolute as u8);
+    // Only the user agent's rules style anonymous boxes.
+    assert_eq!(Servo_GetComputedFloat(values) as u8, ServoStyleFloat::None as u8);
+
+    // A second identical call doesn't cascade again.
+    let second = anonymous_box();
+    assert!(arc_ptr_eq(&first, &second));
+    assert_eq!(Servo_StyleSet_GetAnonymousBoxCascadeCount(doc.style_set()), 1);
+}
+
+fn visited_style_of(style: &Arc<GeckoComputedValues>) -> Option<Arc<GeckoComputedValues>> {
+    Servo_ComputedValues_GetVisitedStyle(Borrowed::from_arc(style).as_ptr()).into_arc::<GeckoComputedValues>()
+}
+
+fn resolved_value(style: &Arc<GeckoComputedValues>, property: nsCSSProperty) -> String {
+    let mut value = String::new();
+    assert!(Servo_GetComputedPropertyValue(Borrowed::from_arc(style).as_ptr(), property, ns_string(&mut value)));
+    value
+}
+
+#[test]
+fn test_added_declarations_only_show_in_the_style_they_are_resolved_into() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("parent").child(element("p").id("p"))));
+    doc.add_sheet("#parent { visibility: hidden } #p { color: red }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let block = parse_style_attribute("color: green !important");
+    let raw_block = Borrowed::<_>::from_arc::<GeckoDeclarationBlock>(&block).as_ptr();
+    let preview = Servo_ResolveStyleWithAddedDeclarations(doc.element("p"), doc.style_set(), raw_block,
+                                                          EXTRA_DECLARATIONS_AUTHOR);
+    let preview = preview.into_arc::<GeckoComputedValues>().expect("No preview style");
+    assert_eq!(resolved_value(&preview, nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    assert_eq!(resolved_value(&preview, nsCSSProperty::eCSSProperty_visibility), "hidden");
+
+    // The style the traversal gave the element is the one it keeps.
+    let cached = doc.computed_values("p");
+    assert!(!arc_ptr_eq(&preview, &cached));
+    assert_eq!(resolved_value(&cached, nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+}
+
+#[test]
+fn test_links_and_what_they_contain_get_visited_styles_with_only_the_visited_colors() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body")
+            .child(element("a").id("link").attr("href", "/").state(NS_EVENT_STATE_UNVISITED.bits())
+                .child(element("span").id("inner").child(text("Link text"))))
+            .child(element("a").id("visited").attr("href", "/").state(NS_EVENT_STATE_VISITED.bits()))
+            .child(element("p").id("outside"))));
+    doc.add_sheet("a { fill: blue; outline-color: blue } \
+                   a:link { border-top-color: yellow } \
+                   a:visited { fill: purple; stroke: purple; display: block } \
+                   a:visited span { outline-color: green }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    // The style itself leaves out the :visited rules, whether the link is visited or not.
+    for id in &["link", "visited"] {
+        let style = doc.computed_values(id);
+        assert_eq!(resolved_value(&style, nsCSSProperty::eCSSProperty_fill), "rgb(0, 0, 255)");
+        assert_eq!(resolved_value(&style, nsCSSProperty::eCSSProperty_stroke), "none");
+        assert_eq!(resolved_value(&style, nsCSSProperty::eCSSProperty_border_top_color), "rgb(255, 255, 0)");
+        assert_eq!(resolved_value(&style, nsCSSProperty::eCSSProperty_display), "inline");
+
+        // The visited one leaves out the :link rules, and whatever isn't a color.
+        let visited = visited_style_of(&style).expect("Links get a visited style");
+        assert_eq!(resolved_value(&visited, nsCSSProperty::eCSSProperty_fill), "rgb(128, 0, 128)");
+        assert_eq!(resolved_value(&visited, nsCSSProperty::eCSSProperty_stroke), "rgb(128, 0, 128)");
+        assert_eq!(resolved_value(&visited, nsCSSProperty::eCSSProperty_outline_color), "rgb(0, 0, 255)");
+        assert!(resolved_value(&visited, nsCSSProperty::eCSSProperty_border_top_color) != "rgb(255, 255, 0)");
+        assert_eq!(resolved_value(&visited, nsCSSProperty::eCSSProperty_display), "inline");
+    }
+
+    // What's inside the link inherits its visited colors, and matches the rules for it being
+    // visited.
+    let inner = doc.computed_values("inner");
+    assert_eq!(resolved_value(&inner, nsCSSProperty::eCSSProperty_fill), "rgb(0, 0, 255)");
+    let inner_visited = visited_style_of(&inner).expect("Elements inside links get a visited style");
+    assert_eq!(resolved_value(&inner_visited, nsCSSProperty::eCSSProperty_fill), "rgb(128, 0, 128)");
+    assert_eq!(resolved_value(&inner_visited, nsCSSProperty::eCSSProperty_outline_color), "rgb(0, 128, 0)");
+    let text = unsafe { Gecko_GetFirstChild(doc.node("inner")) };
+    let text_style = Servo_GetComputedValuesForTextNode(text).into_arc::<GeckoComputedValues>().unwrap();
+    let text_visited = visited_style_of(&text_style).expect("Text inside links gets a visited style");
+    assert_eq!(resolved_value(&text_visited, nsCSSProperty::eCSSProperty_fill), "rgb(128, 0, 128)");
+
+    // Nothing outside the links pays for them.
+    for id in &["html", "body", "outside"] {
+        assert!(visited_style_of(&doc.computed_values(id)).is_none());
+    }
+}
+
+#[test]
+fn test_links_only_get_visited_styles_when_rules_tell_visited_links_apart() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("a").id("link").attr("href", "/").state(NS_EVENT_STATE_UNVISITED.bits())
+            .child(element("span").id("inner"))));
+    doc.add_sheet("a { fill: blue }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert!(visited_style_of(&doc.computed_values("link")).is_none());
+    assert!(visited_style_of(&doc.computed_values("inner")).is_none());
+}
+
+#[test]
+fn test_elements_inside_links_still_share_styles_with_their_visited_ones() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("a").attr("href", "/").state(NS_EVENT_STATE_UNVISITED.bits())
+            .child(element("span").class("word"))
+            .child(element("span").class("word"))));
+    doc.add_sheet("a:visited { fill: purple } .word { fill: blue }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let styles = doc.elements().into_iter().map(|element| {
+        Servo_GetComputedValues(element, doc.style_set()).into_arc::<GeckoComputedValues>().unwrap()
+    }).collect::<Vec<_>>();
+    assert_eq!(styles.len(), 3);
+    // The visited style was there before the style was shared, rather than copied into it after.
+    assert!(arc_ptr_eq(&styles[1], &styles[2]));
+    let visited = visited_style_of(&styles[1]).expect("Elements inside links get a visited style");
+    assert_eq!(resolved_value(&visited, nsCSSProperty::eCSSProperty_fill), "rgb(0, 0, 255)");
+}
+
+#[test]
+fn test_restyles_hand_gecko_the_change_hints() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a")));
+    doc.add_sheet("div { display: block } .inline { display: inline }", StyleSheetOrigin::Author);
+    doc.restyle();
+    Servo_TakeChangeHint(doc.element("a"));
+
+    doc.set_attr("a", "class", "inline");
+    doc.mark_dirty("a");
+    doc.restyle();
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_display), "inline");
+    assert!(Servo_TakeChangeHint(doc.element("a")) & RECONSTRUCT_FRAME.bits() != 0);
+    assert_eq!(Servo_TakeChangeHint(doc.element("a")), 0);
+}
+
+#[test]
+fn test_restyles_start_transitions() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a")));
+    doc.add_sheet("#a { transition-property: transform; transition-duration: 2s; transition-delay: 0.5s; \
+                        transform: translateX(0px) } \
+                   #a.moved { transform: translateX(10px) }", StyleSheetOrigin::Author);
+    doc.restyle();
+    assert!(doc.transitions("a").is_empty());
+
+    doc.set_attr("a", "class", "moved");
+    doc.mark_dirty("a");
+    doc.restyle();
+    let transitions = doc.transitions("a");
+    assert_eq!(transitions.len(), 1);
+    assert_eq!(transitions[0].property as u32, nsCSSProperty::eCSSProperty_transform as u32);
+    assert_eq!((transitions[0].duration_ms, transitions[0].delay_ms), (2000., 500.));
+}
+
+#[test]
+fn test_easings_parse_into_the_struct_gecko_evaluates_them_through() {
+    let _guard = main_thread();
+    let parse = |css: &str| {
+        let mut timing = unsafe { mem::zeroed::<ServoTimingFunction>() };
+        if Servo_ParseEasing(css.as_ptr(), css.len() as u32, &mut timing) { Some(timing) } else { None }
+    };
+
+    let steps = parse("steps(2, jump-both)").unwrap();
+    assert_eq!(steps.mType as u8, ServoTimingFunctionType::Steps as u8);
+    assert_eq!((steps.mSteps, steps.mStepPosition as u8), (2, ServoStepPosition::JumpBoth as u8));
+    assert_eq!(Servo_EasingEvaluateAt(&steps, 0.75, false), 2. / 3.);
+
+    let ease = parse("ease").unwrap();
+    assert_eq!(ease.mType as u8, ServoTimingFunctionType::CubicBezier as u8);
+    assert_eq!((ease.mX1, ease.mY1, ease.mX2, ease.mY2), (0.25, 0.1, 0.25, 1.));
+    assert!((Servo_EasingEvaluateAt(&ease, 0.5, false) - 0.8024034).abs() < 1e-6);
+
+    let step_start = parse("step-start").unwrap();
+    assert_eq!(Servo_EasingEvaluateAt(&step_start, 0., true), 0.);
+    assert_eq!(Servo_EasingEvaluateAt(&step_start, 0., false), 1.);
+
+    assert_eq!(parse("linear").unwrap().mType as u8, ServoTimingFunctionType::Linear as u8);
+    assert!(parse("linear 1s").is_none());
+    assert!(parse("steps(1, jump-none)").is_none());
+}
+
+#[test]
+fn test_scroll_frames_get_their_overflow_and_snapping() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("a")));
+    doc.add_sheet("#a { overflow-x: scroll; resize: both; scroll-snap-type-y: proximity; \
+                        scroll-snap-points-y: repeat(100px); scroll-snap-coordinate: 0 0, 10px 50% } \
+                   #a.clipped { overflow: hidden }", StyleSheetOrigin::Author);
+    doc.restyle();
+    Servo_TakeChangeHint(doc.element("a"));
+
+    let style = doc.computed_values("a");
+    let values = Borrowed::from_arc(&style).as_ptr();
+    assert_eq!(Servo_GetComputedOverflowX(values), NS_STYLE_OVERFLOW_SCROLL as u8);
+    assert_eq!(Servo_GetComputedOverflowY(values), NS_STYLE_OVERFLOW_AUTO as u8);
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_overflow_y), "auto");
+
+    let mut info: ServoScrollFrameInfo = unsafe { mem::zeroed() };
+    Servo_GetScrollFrameInfo(values, &mut info);
+    assert_eq!((info.mOverflowX, info.mOverflowY), (NS_STYLE_OVERFLOW_SCROLL as u8, NS_STYLE_OVERFLOW_AUTO as u8));
+    assert_eq!(info.mResize, NS_STYLE_RESIZE_BOTH as u8);
+    assert_eq!((info.mScrollSnapTypeX, info.mScrollSnapTypeY),
+               (NS_STYLE_SCROLL_SNAP_TYPE_NONE as u8, NS_STYLE_SCROLL_SNAP_TYPE_PROXIMITY as u8));
+    assert_eq!(info.mScrollSnapPointsX.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert_eq!(info.mScrollSnapPointsY.mUnit as u8, ServoLengthUnit::Length as u8);
+    assert_eq!(info.mScrollSnapPointsY.mLength, Au::from_px(100).0);
+    assert_eq!(info.mScrollSnapCoordinateCount, 2);
+
+    let (mut x, mut y) = (info.mScrollSnapDestinationX, info.mScrollSnapDestinationY);
+    assert!(Servo_GetComputedScrollSnapCoordinate(values, 1, &mut x, &mut y));
+    assert_eq!(x.mLength, Au::from_px(10).0);
+    assert_eq!(y.mUnit as u8, ServoLengthUnit::Percent as u8);
+    assert_eq!(y.mPercent, 0.5);
+    assert!(!Servo_GetComputedScrollSnapCoordinate(values, 2, &mut x, &mut y));
+
+    // Losing the scroll frame means building the frame again.
+    doc.set_attr("a", "class", "clipped");
+    doc.mark_dirty("a");
+    doc.restyle();
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_overflow_x), "hidden");
+    assert!(Servo_TakeChangeHint(doc.element("a")) & RECONSTRUCT_FRAME.bits() != 0);
+}
+
+#[test]
+fn test_line_layout_gets_its_text_properties_in_one_call() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("p").id("p").child(element("span").id("span"))));
+    doc.add_sheet("#p { white-space: pre-wrap; text-transform: uppercase; word-break: break-all; \
+                        overflow-wrap: break-word; text-align: -servo-center; letter-spacing: 2px; \
+                        tab-size: 4 } \
+                   #span { word-spacing: 3px; letter-spacing: normal; tab-size: 16px }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let style = doc.computed_values("p");
+    let mut text: ServoTextStyleFFI = unsafe { mem::zeroed() };
+    Servo_GetComputedTextInfo(Borrowed::from_arc(&style).as_ptr(), &mut text);
+    assert_eq!(text.mWhiteSpace, NS_STYLE_WHITESPACE_PRE_WRAP as u8);
+    assert_eq!(text.mTextTransform, NS_STYLE_TEXT_TRANSFORM_UPPERCASE as u8);
+    assert_eq!(text.mWordBreak, NS_STYLE_WORDBREAK_BREAK_ALL as u8);
+    assert_eq!(text.mOverflowWrap, NS_STYLE_WORDWRAP_BREAK_WORD as u8);
+    assert_eq!(text.mTextAlign, NS_STYLE_TEXT_ALIGN_MOZ_CENTER as u8);
+    assert_eq!(text.mLetterSpacingKind as u8, ServoTextSpacingKind::Length as u8);
+    assert_eq!(text.mLetterSpacing, Au::from_px(2).0);
+    assert_eq!(text.mWordSpacingKind as u8, ServoTextSpacingKind::Normal as u8);
+    assert_eq!(text.mTabSizeKind as u8, ServoTabSizeKind::Number as u8);
+    assert_eq!(text.mTabSizeNumber, 4.);
+
+    // The span inherits what it doesn't set.
+    let style = doc.computed_values("span");
+    Servo_GetComputedTextInfo(Borrowed::from_arc(&style).as_ptr(), &mut text);
+    assert_eq!(text.mWhiteSpace, NS_STYLE_WHITESPACE_PRE_WRAP as u8);
+    assert_eq!(text.mLetterSpacingKind as u8, ServoTextSpacingKind::Normal as u8);
+    assert_eq!(text.mWordSpacingKind as u8, ServoTextSpacingKind::Length as u8);
+    assert_eq!(text.mWordSpacing, Au::from_px(3).0);
+    assert_eq!(text.mTabSizeKind as u8, ServoTabSizeKind::Length as u8);
+    assert_eq!(text.mTabSizeLength, Au::from_px(16).0);
+    assert_eq!(doc.computed_value("span", nsCSSProperty::eCSSProperty__moz_tab_size), "16px");
+}
+
+#[test]
+fn test_text_shadows_are_handed_to_gecko_with_their_colors() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("p").id("p").child(element("span").id("span")))
+                                               .child(element("div").id("plain"))
+                                               .child(element("div").id("current")));
+    doc.add_sheet("#p { text-shadow: 1px 2px 3px rgba(255, 0, 0, 0.5), -4px 0 blue; \
+                        text-decoration: underline dotted red } \
+                   #current { text-shadow: 0 0 2px; color: green }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let shadow = |x: i32, y: i32, radius: i32, color: u32, has_color: bool| ServoShadowItem {
+        mXOffset: Au::from_px(x).0,
+        mYOffset: Au::from_px(y).0,
+        mRadius: Au::from_px(radius).0,
+        mSpread: 0,
+        mColor: color,
+        mHasColor: has_color,
+        mInset: false,
+    };
+    let mut array = MockShadowArray::default();
+    let style = doc.computed_values("p");
+    assert!(Servo_GetComputedTextShadow(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert_eq!(array.shadows, vec![shadow(1, 2, 3, 0x800000FF, true), shadow(-4, 0, 0, 0xFFFF0000, true)]);
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_text_decoration_line), "underline");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_text_decoration_style), "dotted");
+
+    // The span inherits the shadows, but not the decoration.
+    let mut array = MockShadowArray::default();
+    let style = doc.computed_values("span");
+    assert!(Servo_GetComputedTextShadow(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert_eq!(array.shadows.len(), 2);
+    assert_eq!(doc.computed_value("span", nsCSSProperty::eCSSProperty_text_decoration_line), "none");
+
+    // Without a shadow, the array is left alone.
+    let mut array = MockShadowArray { shadows: vec![shadow(1, 1, 1, 0, false)] };
+    let style = doc.computed_values("plain");
+    assert!(!Servo_GetComputedTextShadow(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert_eq!(array.shadows.len(), 1);
+
+    // A shadow without a color of its own takes the element's.
+    let style = doc.computed_values("current");
+    assert!(Servo_GetComputedTextShadow(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert_eq!(array.shadows, vec![shadow(0, 0, 2, 0, false)]);
+}
+
+#[test]
+fn test_font_longhands_round_trip_through_the_gecko_struct() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("p").id("p").child(element("span").id("span")))
+                                               .child(element("div").id("plain")));
+    doc.add_sheet("#p { font: italic small-caps bold condensed 20px/2 monospace; font-size-adjust: 0.5 } \
+                   #span { font-family: Foo, serif; font-stretch: ultra-expanded }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_style), "italic");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_variant), "small-caps");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_weight), "700");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_stretch), "condensed");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_size), "20px");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_size_adjust), "0.5");
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_family), "monospace");
+
+    // Everything but what the span sets is inherited.
+    assert_eq!(doc.computed_value("span", nsCSSProperty::eCSSProperty_font_style), "italic");
+    assert_eq!(doc.computed_value("span", nsCSSProperty::eCSSProperty_font_stretch), "ultra-expanded");
+    assert_eq!(doc.computed_value("span", nsCSSProperty::eCSSProperty_font_family), "Foo, serif");
+
+    assert_eq!(doc.computed_value("plain", nsCSSProperty::eCSSProperty_font_style), "normal");
+    assert_eq!(doc.computed_value("plain", nsCSSProperty::eCSSProperty_font_weight), "400");
+    assert_eq!(doc.computed_value("plain", nsCSSProperty::eCSSProperty_font_size_adjust), "none");
+}
+
+#[test]
+fn test_keyword_font_sizes_depend_on_the_generic_family_and_language() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("serif"))
+                                               .child(element("div").id("mono"))
+                                               .child(element("div").id("ja").attr("lang", "ja"))
+                                               .child(element("div").id("large"))
+                                               .child(element("div").id("em")));
+    doc.add_sheet("div { font-size: medium; font-family: monospace } \
+                   #serif { font-family: serif } \
+                   #large { font-size: x-large } \
+                   #em { font-size: 2em }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(doc.computed_value("serif", nsCSSProperty::eCSSProperty_font_size), "16px");
+    assert_eq!(doc.computed_value("mono", nsCSSProperty::eCSSProperty_font_size), "13px");
+    assert_eq!(doc.computed_value("ja", nsCSSProperty::eCSSProperty_font_size), "16px");
+    assert_eq!(doc.computed_value("ja", nsCSSProperty::eCSSProperty__x_lang), "ja");
+    assert_eq!(doc.computed_value("large", nsCSSProperty::eCSSProperty_font_size), "19.5px");
+    // Relative sizes don't go by the family, but by the parent's size.
+    assert_eq!(doc.computed_value("em", nsCSSProperty::eCSSProperty_font_size), "32px");
+
+    // Dropping the lang attribute drops what it maps to.
+    doc.remove_attr("ja", "lang");
+    doc.mark_dirty("ja");
+    doc.restyle();
+    assert_eq!(doc.computed_value("ja", nsCSSProperty::eCSSProperty_font_size), "13px");
+}
+
+#[test]
+fn test_font_feature_settings_are_handed_to_gecko_as_tags() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("p").id("p"))
+                                               .child(element("div").id("plain")));
+    doc.add_sheet("#p { font-feature-settings: \"liga\" off, \"swsh\" 2, \"smcp\" }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let feature = |tag: &[u8; 4], value: u32| ServoFontFeature {
+        mTag: (tag[0] as u32) << 24 | (tag[1] as u32) << 16 | (tag[2] as u32) << 8 | tag[3] as u32,
+        mValue: value,
+    };
+    let mut array = MockFontFeatureArray::default();
+    let style = doc.computed_values("p");
+    assert!(Servo_GetComputedFontFeatureSettings(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert_eq!(array.features, vec![feature(b"liga", 0), feature(b"swsh", 2), feature(b"smcp", 1)]);
+    assert_eq!(array.features[0].mTag, 0x6C696761);
+    assert_eq!(doc.computed_value("p", nsCSSProperty::eCSSProperty_font_feature_settings),
+               "\"liga\" off, \"swsh\" 2, \"smcp\"");
+
+    let mut array = MockFontFeatureArray::default();
+    let style = doc.computed_values("plain");
+    assert!(!Servo_GetComputedFontFeatureSettings(Borrowed::from_arc(&style).as_ptr(), array.as_raw()));
+    assert!(array.features.is_empty());
+    assert_eq!(doc.computed_value("plain", nsCSSProperty::eCSSProperty_font_feature_settings), "normal");
+}
+
+#[test]
+fn test_borders_are_handed_to_gecko_side_by_side() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div"))
+                                               .child(element("div").id("plain")));
+    doc.add_sheet("#div { border: 2px solid red; border-left-style: none; border-bottom: 1px dashed; \
+                          border-image: url(a.png) 10 20% fill / 2 auto / 3px round; \
+                          box-shadow: inset 1px 2px 3px 4px blue, 5px 5px }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let side = |unit: ServoBorderImageUnit, factor: f32, coord: i32| ServoBorderImageSide {
+        mUnit: unit,
+        mFactor: factor,
+        mCoord: coord,
+    };
+    let mut array = MockShadowArray::default();
+    let mut info: ServoBorderInfo = unsafe { mem::zeroed() };
+    info.mBoxShadow = array.as_raw();
+    let style = doc.computed_values("div");
+    Servo_GetComputedBorderInfo(Borrowed::from_arc(&style).as_ptr(), &mut info);
+    // The left border has no style, so it has no width either.
+    assert_eq!(info.mWidths, [Au::from_px(2).0, Au::from_px(2).0, Au::from_px(1).0, 0]);
+    assert_eq!(info.mStyles, [NS_STYLE_BORDER_STYLE_SOLID as u8, NS_STYLE_BORDER_STYLE_SOLID as u8,
+                              NS_STYLE_BORDER_STYLE_DASHED as u8, NS_STYLE_BORDER_STYLE_NONE as u8]);
+    assert_eq!(info.mColors, [0xFF0000FF, 0xFF0000FF, 0, 0xFF0000FF]);
+    assert_eq!(info.mHasColors, [true, true, false, true]);
+    assert_eq!(info.mImageKind, ServoBorderImageKind::Url);
+    assert!(info.mImageFill);
+    let (number, percent) = (ServoBorderImageUnit::Number, ServoBorderImageUnit::Percent);
+    assert_eq!(info.mImageSlice, [side(number, 10., 0), side(percent, 0.2, 0),
+                                  side(number, 10., 0), side(percent, 0.2, 0)]);
+    let auto = side(ServoBorderImageUnit::Auto, 0., 0);
+    assert_eq!(info.mImageWidth, [side(number, 2., 0), auto, side(number, 2., 0), auto]);
+    assert_eq!(info.mImageOutset, [side(ServoBorderImageUnit::Coord, 0., Au::from_px(3).0); 4]);
+    assert_eq!(info.mImageRepeatH, ServoBorderImageRepeat::Round);
+    assert_eq!(info.mImageRepeatV, ServoBorderImageRepeat::Round);
+    assert!(info.mHasBoxShadow);
+    assert_eq!(array.shadows, vec![ServoShadowItem {
+        mXOffset: Au::from_px(1).0,
+        mYOffset: Au::from_px(2).0,
+        mRadius: Au::from_px(3).0,
+        mSpread: Au::from_px(4).0,
+        mColor: 0xFFFF0000,
+        mHasColor: true,
+        mInset: true,
+    }, ServoShadowItem {
+        mXOffset: Au::from_px(5).0,
+        mYOffset: Au::from_px(5).0,
+        mRadius: 0,
+        mSpread: 0,
+        mColor: 0,
+        mHasColor: false,
+        mInset: false,
+    }]);
+
+    // Without an array for them, the shadows are left out.
+    let mut info: ServoBorderInfo = unsafe { mem::zeroed() };
+    Servo_GetComputedBorderInfo(Borrowed::from_arc(&style).as_ptr(), &mut info);
+    assert!(!info.mHasBoxShadow);
+
+    let mut array = MockShadowArray::default();
+    let mut info: ServoBorderInfo = unsafe { mem::zeroed() };
+    info.mBoxShadow = array.as_raw();
+    let style = doc.computed_values("plain");
+    Servo_GetComputedBorderInfo(Borrowed::from_arc(&style).as_ptr(), &mut info);
+    assert_eq!(info.mWidths, [0; 4]);
+    assert_eq!(info.mStyles, [NS_STYLE_BORDER_STYLE_NONE as u8; 4]);
+    assert_eq!(info.mHasColors, [false; 4]);
+    assert_eq!(info.mImageKind, ServoBorderImageKind::None);
+    assert_eq!(info.mImageSlice, [side(percent, 1., 0); 4]);
+    assert_eq!(info.mImageWidth, [side(number, 1., 0); 4]);
+    assert_eq!(info.mImageOutset, [side(number, 0., 0); 4]);
+    assert_eq!(info.mImageRepeatH, ServoBorderImageRepeat::Stretch);
+    assert!(!info.mHasBoxShadow);
+    assert!(array.shadows.is_empty());
+}
+
+#[test]
+fn test_flexbox_properties_are_handed_to_gecko() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("container"))
+                                               .child(element("div").id("item")));
+    doc.add_sheet("#container { display: inline-flex; flex-flow: column-reverse wrap; justify-content: center; \
+                                align-items: baseline } \
+                   #item { flex: 2 content; order: -1; align-self: flex-end }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let flex_info = |id: &str| {
+        let mut info: ServoFlexInfo = unsafe { mem::zeroed() };
+        Servo_GetComputedFlexInfo(Borrowed::from_arc(&doc.computed_values(id)).as_ptr(), &mut info);
+        info
+    };
+    let container = flex_info("container");
+    assert_eq!(container.mFlexDirection, NS_STYLE_FLEX_DIRECTION_COLUMN_REVERSE as u8);
+    assert_eq!(container.mFlexWrap, NS_STYLE_FLEX_WRAP_WRAP as u8);
+    assert_eq!(container.mJustifyContent, NS_STYLE_ALIGN_CENTER as u8);
+    assert_eq!(container.mAlignItems, NS_STYLE_ALIGN_BASELINE as u8);
+    assert_eq!(container.mAlignSelf, NS_STYLE_ALIGN_AUTO as u8);
+    assert_eq!((container.mFlexGrow, container.mFlexShrink), (0., 1.));
+    assert_eq!(container.mFlexBasis.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert!(!container.mFlexBasisIsContent);
+
+    let item = flex_info("item");
+    assert_eq!(item.mAlignSelf, NS_STYLE_ALIGN_FLEX_END as u8);
+    assert_eq!((item.mFlexGrow, item.mFlexShrink, item.mOrder), (2., 1., -1));
+    assert_eq!(item.mFlexBasis.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert!(item.mFlexBasisIsContent);
+}
+
+#[test]
+fn test_column_rules_are_handed_to_gecko() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("ruled"))
+                                               .child(element("div").id("unstyled")));
+    doc.add_sheet("#ruled { columns: 3; column-gap: 10px; column-rule: 2px dashed red } \
+                   #unstyled { column-width: 100px; column-rule-width: 2px }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let column_info = |id: &str| {
+        let mut info: ServoColumnInfo = unsafe { mem::zeroed() };
+        Servo_GetComputedColumnInfo(Borrowed::from_arc(&doc.computed_values(id)).as_ptr(), &mut info);
+        info
+    };
+    let ruled = column_info("ruled");
+    assert_eq!(ruled.mColumnCount, 3);
+    assert!(!ruled.mHasColumnWidth);
+    assert_eq!((ruled.mColumnGap, ruled.mHasColumnGap), (Au::from_px(10).0, true));
+    assert_eq!(ruled.mColumnRuleWidth, Au::from_px(2).0);
+    assert_eq!(ruled.mColumnRuleStyle, NS_STYLE_BORDER_STYLE_DASHED as u8);
+    assert_eq!((ruled.mColumnRuleColor, ruled.mHasColumnRuleColor), (0xFF0000FF, true));
+
+    // A rule that has no style has no width either, and its color is the element's.
+    let unstyled = column_info("unstyled");
+    assert_eq!(unstyled.mColumnCount, NS_STYLE_COLUMN_COUNT_AUTO);
+    assert_eq!((unstyled.mColumnWidth, unstyled.mHasColumnWidth), (Au::from_px(100).0, true));
+    assert!(!unstyled.mHasColumnGap);
+    assert_eq!(unstyled.mColumnRuleWidth, 0);
+    assert_eq!(unstyled.mColumnRuleStyle, NS_STYLE_BORDER_STYLE_NONE as u8);
+    assert!(!unstyled.mHasColumnRuleColor);
+}
+
+/// The background layer at |index| of |id|'s style, with its url and stops, or None past the last layer.
+fn image_at(doc: &MockDocument, id: &str, index: u32)
+            -> Option<(ServoImageInfo, String, MockGradientStopArray)> {
+    let mut url = String::new();
+    let mut stops = MockGradientStopArray::default();
+    let mut info: ServoImageInfo = unsafe { mem::zeroed() };
+    info.mStops = stops.as_raw();
+    info.mURL = ns_string(&mut url);
+    let style = doc.computed_values(id);
+    if Servo_GetComputedImageAt(Borrowed::from_arc(&style).as_ptr(), index, &mut info) {
+        Some((info, url, stops))
+    } else {
+        None
+    }
+}
+
+fn assert_length(value: ServoLengthOrPercentageOrAuto, px: i32) {
+    assert_eq!(value.mUnit as u8, ServoLengthUnit::Length as u8);
+    assert_eq!(value.mLength, Au::from_px(px).0);
+}
+
+fn assert_percent(value: ServoLengthOrPercentageOrAuto, percent: f32) {
+    assert_eq!(value.mUnit as u8, ServoLengthUnit::Percent as u8);
+    assert_eq!(value.mPercent, percent);
+}
+
+#[test]
+fn test_background_layers_hand_gecko_urls_and_gradients() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div"))
+                                               .child(element("div").id("plain")));
+    doc.add_sheet("#div { background-image: url(a.png), -moz-linear-gradient(left top, red 10px, currentColor), \
+                          radial-gradient(circle 5px at 25% 75%, blue 0px, green, red 40px) }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let (info, url, _) = image_at(&doc, "div", 0).unwrap();
+    assert_eq!(info.mKind, ServoImageKind::Url);
+    assert_eq!(url, "http://www.example.com/a.png");
+
+    // The legacy gradient starts at the top left, so it goes to the bottom right.
+    let (info, _, stops) = image_at(&doc, "div", 1).unwrap();
+    assert_eq!(info.mKind, ServoImageKind::LinearGradient);
+    assert!(!info.mHasAngle);
+    assert_percent(info.mPositionX, 1.);
+    assert_percent(info.mPositionY, 1.);
+    assert_eq!(stops.stops.len(), 2);
+    assert_length(stops.stops[0].mPosition, 10);
+    assert_eq!((stops.stops[0].mColor, stops.stops[0].mHasColor), (0xFF0000FF, true));
+    assert_percent(stops.stops[1].mPosition, 1.);
+    assert!(!stops.stops[1].mHasColor);
+
+    let (info, _, stops) = image_at(&doc, "div", 2).unwrap();
+    assert_eq!(info.mKind, ServoImageKind::RadialGradient);
+    assert_eq!(info.mShape, NS_STYLE_GRADIENT_SHAPE_CIRCULAR as u8);
+    assert_eq!(info.mSize, NS_STYLE_GRADIENT_SIZE_EXPLICIT_SIZE as u8);
+    assert_length(info.mRadiusX, 5);
+    assert_length(info.mRadiusY, 5);
+    assert_percent(info.mPositionX, 0.25);
+    assert_percent(info.mPositionY, 0.75);
+    // The green stop is halfway between the ones around it.
+    let positions = stops.stops.iter().map(|stop| stop.mPosition).collect::<Vec<_>>();
+    assert_length(positions[0], 0);
+    assert_length(positions[1], 20);
+    assert_length(positions[2], 40);
+
+    assert!(image_at(&doc, "div", 3).is_none());
+
+    let (info, url, stops) = image_at(&doc, "plain", 0).unwrap();
+    assert_eq!(info.mKind, ServoImageKind::None);
+    assert!(url.is_empty());
+    assert!(stops.stops.is_empty());
+    assert!(image_at(&doc, "plain", 1).is_none());
+}
+
+/// The background layer at |index| of |id|'s style, or None past the last layer.
+fn background_layer_at(doc: &MockDocument, id: &str, index: u32) -> Option<ServoBackgroundLayer> {
+    let mut layer: ServoBackgroundLayer = unsafe { mem::zeroed() };
+    let style = doc.computed_values(id);
+    if Servo_GetComputedBackgroundLayerAt(Borrowed::from_arc(&style).as_ptr(), index, &mut layer) {
+        Some(layer)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_background_layers_repeat_shorter_lists_up_to_the_image_count() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("div"))
+                                               .child(element("div").id("plain")));
+    doc.add_sheet("#div { background: url(a.png) 10px 20% / cover repeat-x fixed content-box, \
+                                      url(b.png) no-repeat, url(c.png) red; \
+                          background-clip: border-box, content-box }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let style = doc.computed_values("div");
+    assert_eq!(Servo_GetComputedBackgroundLayerCount(Borrowed::from_arc(&style).as_ptr()), 3);
+
+    let top = background_layer_at(&doc, "div", 0).unwrap();
+    assert_length(top.mPositionX, 10);
+    assert_percent(top.mPositionY, 0.2);
+    assert!(!top.mHasExplicitSize);
+    assert_eq!(top.mSize, NS_STYLE_IMAGELAYER_SIZE_COVER as u8);
+    assert_eq!(top.mRepeat, NS_STYLE_IMAGELAYER_REPEAT_REPEAT_X as u8);
+    assert_eq!(top.mAttachment, NS_STYLE_IMAGELAYER_ATTACHMENT_FIXED as u8);
+    assert_eq!(top.mOrigin, NS_STYLE_IMAGELAYER_ORIGIN_CONTENT as u8);
+    assert_eq!(top.mClip, NS_STYLE_IMAGELAYER_CLIP_BORDER as u8);
+
+    let middle = background_layer_at(&doc, "div", 1).unwrap();
+    assert_percent(middle.mPositionX, 0.);
+    assert!(middle.mHasExplicitSize);
+    assert_eq!(middle.mWidth.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert_eq!(middle.mHeight.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert_eq!(middle.mRepeat, NS_STYLE_IMAGELAYER_REPEAT_NO_REPEAT as u8);
+    assert_eq!(middle.mAttachment, NS_STYLE_IMAGELAYER_ATTACHMENT_SCROLL as u8);
+    assert_eq!(middle.mOrigin, NS_STYLE_IMAGELAYER_ORIGIN_PADDING as u8);
+    assert_eq!(middle.mClip, NS_STYLE_IMAGELAYER_CLIP_CONTENT as u8);
+
+    // background-clip has two values for the three images, so the bottom layer takes the first.
+    let bottom = background_layer_at(&doc, "div", 2).unwrap();
+    assert_eq!(bottom.mClip, NS_STYLE_IMAGELAYER_CLIP_BORDER as u8);
+    assert!(background_layer_at(&doc, "div", 3).is_none());
+
+    let style = doc.computed_values("plain");
+    assert_eq!(Servo_GetComputedBackgroundLayerCount(Borrowed::from_arc(&style).as_ptr()), 1);
+    let plain = background_layer_at(&doc, "plain", 0).unwrap();
+    assert_eq!(plain.mClip, NS_STYLE_IMAGELAYER_CLIP_BORDER as u8);
+    assert_eq!(plain.mOrigin, NS_STYLE_IMAGELAYER_ORIGIN_PADDING as u8);
+    assert!(background_layer_at(&doc, "plain", 1).is_none());
+}
+
+#[test]
+fn test_ua_sheets_can_size_boxes_by_their_contents() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html")
+        .child(element("fieldset").id("fieldset"))
+        .child(element("div").id("div")));
+    doc.add_sheet("fieldset { display: block; min-width: -moz-min-content; width: -moz-fit-content; \
+                              max-height: -moz-available; color: red }",
+                  StyleSheetOrigin::UserAgent);
+    doc.add_sheet("div { box-sizing: border-box; max-width: -moz-max-content }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    // None of the rule is dropped for the keywords in it.
+    for &(property, value) in &[(nsCSSProperty::eCSSProperty_display, "block"),
+                                (nsCSSProperty::eCSSProperty_min_width, "-moz-min-content"),
+                                (nsCSSProperty::eCSSProperty_width, "-moz-fit-content"),
+                                (nsCSSProperty::eCSSProperty_max_height, "-moz-available"),
+                                (nsCSSProperty::eCSSProperty_color, "rgb(255, 0, 0)")] {
+        assert_eq!(doc.computed_value("fieldset", property), value);
+    }
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_max_width), "-moz-max-content");
+    assert_eq!(doc.computed_value("div", nsCSSProperty::eCSSProperty_box_sizing), "border-box");
+    assert_eq!(doc.computed_value("html", nsCSSProperty::eCSSProperty_box_sizing), "content-box");
+}
+
+#[test]
+fn test_going_between_static_and_relative_positioning_only_reflows() {
+    let _guard = main_thread();
+    for &(old, new) in &[("position: static", "position: relative"), ("position: relative", "position: static")] {
+        let hint = change_hint(old, new);
+        assert!(hint.contains(NEED_REFLOW), "{} to {}: {:?}", old, new, hint);
+        assert!(!hint.contains(RECONSTRUCT_FRAME), "{} to {}: {:?}", old, new, hint);
+    }
+}
+
+#[test]
+fn test_changing_sizes_or_how_theyre_measured_reflows() {
+    let _guard = main_thread();
+    for &(old, new) in &[("width: 10px", "width: -moz-fit-content"),
+                         ("width: -moz-fit-content", "width: -moz-available"),
+                         ("min-height: 0", "min-height: -moz-min-content"),
+                         ("max-width: none", "max-width: 50%"),
+                         ("box-sizing: content-box", "box-sizing: border-box")] {
+        let hint = change_hint(old, new);
+        assert!(hint.contains(GeckoRestyleDamage::reflow()), "{} to {}: {:?}", old, new, hint);
+        assert!(!hint.contains(RECONSTRUCT_FRAME), "{} to {}: {:?}", old, new, hint);
+    }
+}
+
+#[test]
+fn test_going_from_or_to_absolute_or_fixed_positioning_reconstructs_the_frame() {
+    let _guard = main_thread();
+    let positions = ["static", "relative", "absolute", "fixed"];
+    for &old in &positions {
+        for &new in &positions {
+            if old == new || (old != "absolute" && old != "fixed" && new != "absolute" && new != "fixed") {
+                continue;
+            }
+            let hint = change_hint(&format!("position: {}", old), &format!("position: {}", new));
+            assert!(hint.contains(RECONSTRUCT_FRAME), "{} to {}: {:?}", old, new, hint);
+        }
+    }
+}
+
+#[test]
+fn test_giving_a_border_a_style_reflows() {
+    let _guard = main_thread();
+    assert!(change_hint("border: 2px none", "border: 2px solid").contains(NEED_REFLOW));
+    assert!(change_hint("outline: 2px solid", "outline: 2px none").contains(REPAINT_FRAME));
+}
+
+#[test]
+fn test_changing_between_z_indices_only_repaints() {
+    let _guard = main_thread();
+    assert_eq!(change_hint("position: relative; z-index: 1", "position: relative; z-index: 2"), REPAINT_FRAME);
+    assert_eq!(change_hint("position: relative; z-index: 0", "position: relative; z-index: -1"), REPAINT_FRAME);
+}
+
+#[test]
+fn test_changing_what_hit_testing_reads_repaints_nothing() {
+    let _guard = main_thread();
+    for &(old, new) in &[("pointer-events: auto", "pointer-events: none"),
+                         ("pointer-events: visibleFill", "pointer-events: stroke"),
+                         ("touch-action: auto", "touch-action: pan-x pan-y"),
+                         ("user-select: auto", "-moz-user-select: none"),
+                         ("pointer-events: none; touch-action: none", "pointer-events: all; user-select: all")] {
+        assert_eq!(change_hint(old, new), NEUTRAL_CHANGE, "{} to {}", old, new);
+    }
+    let hint = change_hint("cursor: auto; pointer-events: none", "cursor: pointer; pointer-events: auto");
+    assert_eq!(hint, REPAINT_FRAME | UPDATE_CURSOR | NEUTRAL_CHANGE);
+}
+
+/// The pointer-events, touch-action and user-select Gecko gets for |id|.
+fn ui_info_of(doc: &MockDocument, id: &str) -> (u8, u8, u8) {
+    let mut info: ServoUIInfo = unsafe { mem::zeroed() };
+    Servo_GetComputedUIInfo(Borrowed::from_arc(&doc.computed_values(id)).as_ptr(), &mut info);
+    (info.mPointerEvents, info.mTouchAction, info.mUserSelect)
+}
+
+#[test]
+fn test_pointer_events_is_inherited_and_touch_action_and_user_select_are_not() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("div").id("grandparent").child(
+            element("div").id("parent").child(
+                element("p").id("child")))));
+    doc.add_sheet("#grandparent { pointer-events: visibleStroke; touch-action: pan-y; -moz-user-select: none } \
+                   #parent { touch-action: inherit; user-select: inherit }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let visible_stroke = NS_STYLE_POINTER_EVENTS_VISIBLESTROKE as u8;
+    let (none, auto) = (NS_STYLE_USER_SELECT_NONE as u8, NS_STYLE_USER_SELECT_AUTO as u8);
+    assert_eq!(ui_info_of(&doc, "html"), (NS_STYLE_POINTER_EVENTS_AUTO as u8, TOUCH_ACTION_AUTO, auto));
+    assert_eq!(ui_info_of(&doc, "grandparent"), (visible_stroke, TOUCH_ACTION_PAN_Y, none));
+    assert_eq!(ui_info_of(&doc, "parent"), (visible_stroke, TOUCH_ACTION_PAN_Y, none));
+    assert_eq!(ui_info_of(&doc, "child"), (visible_stroke, TOUCH_ACTION_AUTO, auto));
+
+    // The SVG values are handed to Gecko as they are on any element, for its SVG frames.
+    let mut visibility: nsStyleVisibility_FFI = unsafe { mem::zeroed() };
+    Servo_GetStyleVisibility(Borrowed::from_arc(&doc.computed_values("child")).as_ptr(), &mut visibility);
+    assert_eq!(visibility.mPointerEvents, visible_stroke);
+}
+
+#[test]
+fn test_changing_z_index_from_or_to_auto_updates_the_stacking_context_without_reflowing() {
+    let _guard = main_thread();
+    for &(old, new) in &[("auto", "1"), ("0", "auto")] {
+        let hint = change_hint(&format!("position: absolute; z-index: {}", old),
+                               &format!("position: absolute; z-index: {}", new));
+        assert_eq!(hint, REPAINT_FRAME | SYNC_FRAME_VIEW, "z-index: {} to {}", old, new);
+    }
+}
+
+#[test]
+fn test_moving_relatively_positioned_elements_only_recomputes_their_position() {
+    let _guard = main_thread();
+    assert_eq!(change_hint("position: relative; top: 1px", "position: relative; top: 2px"), RECOMPUTE_POSITION);
+    assert_eq!(change_hint("position: relative; left: 1px", "position: relative; left: 10%"), RECOMPUTE_POSITION);
+}
+
+#[test]
+fn test_moving_absolutely_positioned_elements_reflows_them() {
+    let _guard = main_thread();
+    let hint = change_hint("position: absolute; top: 1px", "position: absolute; top: 2px");
+    assert!(hint.contains(NEED_REFLOW), "{:?}", hint);
+    assert!(!hint.contains(RECONSTRUCT_FRAME), "{:?}", hint);
+}
+
+#[test]
+fn test_offsets_of_statically_positioned_elements_change_nothing() {
+    let _guard = main_thread();
+    assert_eq!(change_hint("top: 1px", "top: 2px"), GeckoRestyleDamage::empty());
+}
+
+#[test]
+fn test_reordering_flex_items_only_reflows_and_becoming_a_flex_container_reconstructs() {
+    let _guard = main_thread();
+    let hint = change_hint("order: 1", "order: 2");
+    assert!(hint.contains(NEED_REFLOW), "{:?}", hint);
+    assert!(!hint.contains(RECONSTRUCT_FRAME), "{:?}", hint);
+    assert!(change_hint("display: block", "display: flex").contains(RECONSTRUCT_FRAME));
+}
+
+#[test]
+fn test_changing_the_direction_or_unicode_bidi_reconstructs_the_frame() {
+    let _guard = main_thread();
+    assert!(change_hint("direction: ltr", "direction: rtl").contains(RECONSTRUCT_FRAME));
+    assert!(change_hint("unicode-bidi: normal", "unicode-bidi: isolate").contains(RECONSTRUCT_FRAME));
+    assert!(!change_hint("visibility: visible", "visibility: hidden").contains(RECONSTRUCT_FRAME));
+}
+
+#[test]
+fn test_direction_is_handed_to_gecko_and_inherited() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("rtl").child(element("p").id("child")))
+                                               .child(element("div").id("ltr")));
+    doc.add_sheet("#rtl { direction: rtl; unicode-bidi: plaintext }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let direction = |id: &str| Servo_GetComputedDirection(Borrowed::from_arc(&doc.computed_values(id)).as_ptr());
+    assert_eq!(direction("rtl"), NS_STYLE_DIRECTION_RTL as u8);
+    assert_eq!(direction("child"), NS_STYLE_DIRECTION_RTL as u8);
+    assert_eq!(direction("ltr"), NS_STYLE_DIRECTION_LTR as u8);
+    assert_eq!(doc.computed_value("rtl", nsCSSProperty::eCSSProperty_unicode_bidi), "plaintext");
+    assert_eq!(doc.computed_value("child", nsCSSProperty::eCSSProperty_unicode_bidi), "normal");
+}
+
+#[test]
+fn test_logical_margins_and_offsets_resolve_to_their_physical_sides() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("rtl"))
+                                               .child(element("div").id("vertical")));
+    doc.add_sheet("div { margin-inline-start: 4px; inset-block-start: 1px; top: 2px } \
+                   #rtl { direction: rtl } #vertical { writing-mode: vertical-lr }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(doc.computed_value("rtl", nsCSSProperty::eCSSProperty_margin_right), "4px");
+    assert_eq!(doc.computed_value("rtl", nsCSSProperty::eCSSProperty_margin_inline_start), "4px");
+    assert_eq!(doc.computed_value("rtl", nsCSSProperty::eCSSProperty_margin_left), "0px");
+    assert_eq!(doc.computed_value("rtl", nsCSSProperty::eCSSProperty_offset_block_start), "2px");
+    assert_eq!(doc.computed_value("vertical", nsCSSProperty::eCSSProperty_margin_top), "4px");
+    assert_eq!(doc.computed_value("vertical", nsCSSProperty::eCSSProperty_left), "1px");
+    assert_eq!(doc.computed_value("vertical", nsCSSProperty::eCSSProperty_offset_block_start), "1px");
+}
+
+#[test]
+fn test_text_overflow_is_handed_to_gecko_with_its_strings() {
+    let _guard = main_thread();
+    let two = "text-overflow: \"\u{2026}\" \"\u{1F600}\"";
+    let doc = MockDocument::new(element("html").child(element("div").id("one").style("text-overflow: ellipsis"))
+                                               .child(element("div").id("two").style(two)));
+    doc.restyle();
+
+    let text_overflow = |id: &str| {
+        let (mut left, mut right) = (String::new(), String::new());
+        let mut info: ServoTextOverflow = unsafe { mem::zeroed() };
+        info.mLeft.mString = ns_string(&mut left);
+        info.mRight.mString = ns_string(&mut right);
+        Servo_GetComputedTextOverflow(Borrowed::from_arc(&doc.computed_values(id)).as_ptr(), &mut info);
+        (info, left, right)
+    };
+    // A single value goes at the end of the line, whichever side that's on.
+    let (one, left, right) = text_overflow("one");
+    assert!(one.mLogicalDirections);
+    assert_eq!(one.mLeft.mType, NS_STYLE_TEXT_OVERFLOW_CLIP as u8);
+    assert_eq!(one.mRight.mType, NS_STYLE_TEXT_OVERFLOW_ELLIPSIS as u8);
+    assert_eq!((left, right), (String::new(), String::new()));
+
+    let (two, left, right) = text_overflow("two");
+    assert!(!two.mLogicalDirections);
+    assert_eq!((two.mLeft.mType, two.mRight.mType),
+               (NS_STYLE_TEXT_OVERFLOW_STRING as u8, NS_STYLE_TEXT_OVERFLOW_STRING as u8));
+    assert_eq!((&*left, &*right), ("\u{2026}", "\u{1F600}"));
+}
+
+#[test]
+fn test_style_structs_are_filled_in_over_gecko_initial_values() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("div").id("styled"))
+                                               .child(element("span").id("unstyled")));
+    doc.add_sheet("#styled { display: block; position: absolute; float: left; clear: both; opacity: 0.5; \
+                             margin: 1px 2px 3px 4px; padding: 50%; max-width: 10px; z-index: 3; \
+                             box-sizing: border-box; visibility: hidden; pointer-events: none; direction: rtl; \
+                             touch-action: pan-y; height: -moz-fit-content }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    // Every byte starts out set, so that a field nobody writes shows up.
+    fn filled<T>(fill: &Fn(*mut T)) -> T {
+        let mut out: T = unsafe { mem::uninitialized() };
+        unsafe { ptr::write_bytes(&mut out, 0xFF, 1) };
+        fill(&mut out);
+        out
+    }
+    let (styled, unstyled) = (doc.computed_values("styled"), doc.computed_values("unstyled"));
+    let raw = |values: &Arc<GeckoComputedValues>| Borrowed::from_arc(values).as_ptr();
+    let initial_display = filled(&|out| unsafe { Gecko_Construct_nsStyleDisplay(out) });
+    let initial_position = filled(&|out| unsafe { Gecko_Construct_nsStylePosition(out) });
+    let initial_visibility = filled(&|out| unsafe { Gecko_Construct_nsStyleVisibility(out) });
+
+    let display = filled(&|out| Servo_GetStyleDisplay(raw(&styled), out));
+    assert_eq!(display.mOpacity, 0.5);
+    assert_eq!(display.mDisplay, NS_STYLE_DISPLAY_BLOCK as u8);
+    assert_eq!(display.mPosition, NS_STYLE_POSITION_ABSOLUTE as u8);
+    assert_eq!(display.mFloats, NS_STYLE_FLOAT_LEFT as u8);
+    assert_eq!(display.mBreakType, NS_STYLE_CLEAR_BOTH as u8);
+    assert_eq!(display.mTouchAction, TOUCH_ACTION_PAN_Y);
+    // What Servo doesn't compute stays the way Gecko constructed it.
+    assert_eq!((display.mAppearance, display.mOrient, display.mTransformBox),
+               (initial_display.mAppearance, initial_display.mOrient, initial_display.mTransformBox));
+    let display = filled(&|out| Servo_GetStyleDisplay(raw(&unstyled), out));
+    assert_eq!((display.mOpacity, display.mDisplay), (1., NS_STYLE_DISPLAY_INLINE as u8));
+
+    let margin = filled(&|out| Servo_GetStyleMargin(raw(&styled), out));
+    for (side, &px) in margin.mMargin.iter().zip(&[1, 2, 3, 4]) {
+        assert_length(*side, px);
+    }
+    let padding = filled(&|out| Servo_GetStylePadding(raw(&styled), out));
+    for side in padding.mPadding.iter() {
+        assert_percent(*side, 0.5);
+    }
+
+    let position = filled(&|out| Servo_GetStylePosition(raw(&styled), out));
+    assert_length(position.mMaxWidth, 10);
+    assert_eq!(position.mMaxHeight.mUnit as u8, ServoLengthUnit::None as u8);
+    assert_eq!(position.mWidth.mUnit as u8, ServoLengthUnit::Auto as u8);
+    assert_eq!((position.mHeight.mUnit as u8, position.mHeight.mLength),
+               (ServoLengthUnit::Keyword as u8, NS_STYLE_WIDTH_FIT_CONTENT as i32));
+    assert_eq!(position.mBoxSizing, StyleBoxSizing::Border as u8);
+    assert_eq!((position.mZIndex, position.mHasZIndex), (3, true));
+    assert_eq!((position.mGridAutoFlow, position.mJustifyItems, position.mObjectFit),
+               (initial_position.mGridAutoFlow, initial_position.mJustifyItems, initial_position.mObjectFit));
+    let position = filled(&|out| Servo_GetStylePosition(raw(&unstyled), out));
+    assert_eq!((position.mZIndex, position.mHasZIndex), (0, false));
+    assert_eq!(position.mMaxWidth.mUnit as u8, ServoLengthUnit::None as u8);
+
+    let visibility = filled(&|out| Servo_GetStyleVisibility(raw(&styled), out));
+    assert_eq!(visibility.mDirection, NS_STYLE_DIRECTION_RTL as u8);
+    assert_eq!(visibility.mVisible, NS_STYLE_VISIBILITY_HIDDEN as u8);
+    assert_eq!(visibility.mPointerEvents, NS_STYLE_POINTER_EVENTS_NONE as u8);
+    assert_eq!((visibility.mColorAdjust, visibility.mImageOrientation),
+               (initial_visibility.mColorAdjust, initial_visibility.mImageOrientation));
+}
+
+/// A document with 50,000 elements or so: 500 divs in the body, with 99 spans in each.
+fn big_document() -> MockDocument {
+    let mut body = element("body").id("body");
+    for i in 0..500 {
+        let mut div = element("div");
+        for j in 0..99 {
+            div = div.child(if i == 0 && j == 0 { element("span").id("first") } else { element("span") });
+        }
+        body = body.child(div);
+    }
+    MockDocument::new(element("html").id("html").child(body))
+}
+
+/// Styles a fresh copy of a document with plenty of style sharing, and reports what each
+/// element came out with, which earlier element it shares its style with, if any, and what
+/// the traversal counted.
+fn style_for_determinism() -> (Vec<String>, (u32, u32, u32, u32, u32)) {
+    let mut body = element("body");
+    for i in 0..40 {
+        let mut div = element("div").class(if i % 3 == 0 { "a" } else { "b" });
+        for j in 0..12 {
+            div = div.child(element("span").class(if j % 4 == 0 { "c" } else { "d" }).child(text("x")));
+        }
+        body = body.child(div);
+    }
+    let doc = MockDocument::new(element("html").child(body));
+    doc.add_sheet("div:nth-child(2n) { float: left } .a span { white-space: pre } .c { display: block } \
+                   span:first-child { float: right } .b .d:last-child { resize: both; overflow: auto }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+    Servo_GetTraversalStatistics(&mut statistics);
+    let styles = doc.elements().into_iter().map(|element| {
+        Servo_GetComputedValues(element, doc.style_set()).into_arc::<GeckoComputedValues>().unwrap()
+    }).collect::<Vec<_>>();
+    let summaries = styles.iter().enumerate().map(|(index, style)| {
+        let values = Borrowed::from_arc(style).as_ptr();
+        let value = |property: nsCSSProperty| {
+            let mut value = String::new();
+            assert!(Servo_GetComputedPropertyValue(values, property, ns_string(&mut value)));
+            value
+        };
+        let shares_with = styles[..index].iter().position(|earlier| arc_ptr_eq(earlier, style));
+        format!("{} {} {} {} {:?}", value(nsCSSProperty::eCSSProperty_display),
+                value(nsCSSProperty::eCSSProperty_float), value(nsCSSProperty::eCSSProperty_white_space),
+                value(nsCSSProperty::eCSSProperty_resize), shares_with)
+    }).collect();
+    (summaries, (statistics.mElementsTraversed, statistics.mElementsStyled, statistics.mStylesShared,
+                 statistics.mSelectorsMatched, statistics.mDeclarationsCascaded))
+}
+
+#[test]
+fn test_deterministic_traversals_style_the_same_document_the_same_way() {
+    let _guard = main_thread();
+    Servo_SetDeterministicTraversal(true);
+    Servo_SetTraversalStatisticsEnabled(true);
+    let first = style_for_determinism();
+    let second = style_for_determinism();
+    Servo_SetTraversalStatisticsEnabled(false);
+    Servo_SetDeterministicTraversal(false);
+
+    assert_eq!(first, second);
+    let (summaries, (traversed, styled, shared, _, _)) = first;
+    assert_eq!((traversed as usize, styled as usize), (summaries.len(), summaries.len()));
+    assert!(shared > 0, "Nothing shared a style");
+}
+
+#[test]
+fn test_the_traversal_asks_gecko_about_each_node_once() {
+    let _guard = main_thread();
+    Servo_SetDeterministicTraversal(true);
+    Servo_SetTraversalStatisticsEnabled(true);
+    let doc = big_document();
+    doc.add_sheet("html div span { color: red } body > div > span { float: left } span + span { clear: both }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+    let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+    Servo_GetTraversalStatistics(&mut statistics);
+    Servo_SetTraversalStatisticsEnabled(false);
+    Servo_SetDeterministicTraversal(false);
+
+    // Matching walks up past the same few ancestors from every span, and the spans' own
+    // answers are fetched once for everything that's asked about them.
+    assert!(statistics.mNodeFFICalls * 3 <= statistics.mNodeQueries);
+    assert!(statistics.mNodeFFICalls as usize <= 2 * doc.node_count());
+    assert_eq!(doc.computed_value("first", nsCSSProperty::eCSSProperty_float), "left");
+}
+
+#[test]
+fn test_descendant_selectors_the_bloom_filter_rules_out_dont_walk_up_a_deep_tree() {
+    let _guard = main_thread();
+    let mut tree = element("span").id("deepest");
+    for _ in 0..30 {
+        tree = element("div").child(tree);
+    }
+    let doc = MockDocument::new(element("html").child(element("body").child(tree)));
+    let selectors = ".missing div, .missing span";
+    doc.add_sheet(&format!("{} {{ float: left }}", selectors), StyleSheetOrigin::Author);
+
+    Servo_SetDeterministicTraversal(true);
+    let traversal = count_parent_lookups(|| doc.restyle());
+    Servo_SetDeterministicTraversal(false);
+    assert_eq!(doc.computed_value("deepest", nsCSSProperty::eCSSProperty_float), "none");
+    // Each node's parent is asked for once, whatever the selectors are.
+    assert!(traversal <= doc.node_count(), "{} lookups for {} nodes", traversal, doc.node_count());
+
+    // Matching outside of a traversal has no filter to ask, so every div and the span walk
+    // up to the root looking for .missing.
+    let elements = doc.elements();
+    let matching = count_parent_lookups(|| {
+        for &element in &elements {
+            assert!(!with_selectors(selectors, |list| Servo_SelectorList_Matches(element, list)));
+        }
+    });
+    assert!(matching > 10 * traversal, "{} lookups without the filter, {} with it", matching, traversal);
+}
+
+#[test]
+fn test_restyling_a_long_list_with_nth_selectors_looks_up_each_sibling_about_once() {
+    let _guard = main_thread();
+    let mut list = element("ol");
+    for index in 0..5000 {
+        list = list.child(match index {
+            1 => element("li").id("second"),
+            2 => element("li").id("third"),
+            4997 => element("li").id("third-from-last"),
+            _ => element("li"),
+        });
+    }
+    let doc = MockDocument::new(element("html").child(element("body").child(list)));
+    doc.add_sheet("li:nth-child(2n) { color: lime } li:not(:nth-last-of-type(3)) { float: left }",
+                  StyleSheetOrigin::Author);
+
+    Servo_SetDeterministicTraversal(true);
+    let lookups = count_sibling_lookups(|| doc.restyle());
+    Servo_SetDeterministicTraversal(false);
+    assert_eq!(doc.computed_value("second", nsCSSProperty::eCSSProperty_color), "rgb(0, 255, 0)");
+    assert_eq!(doc.computed_value("third", nsCSSProperty::eCSSProperty_color), "rgb(0, 0, 0)");
+    assert_eq!(doc.computed_value("third", nsCSSProperty::eCSSProperty_float), "left");
+    assert_eq!(doc.computed_value("third-from-last", nsCSSProperty::eCSSProperty_float), "none");
+    // Gecko knows the index among all the children, and the nth-index cache counts those of
+    // the same type from the end once. Walking the siblings from each item would take
+    // 12.5 million lookups for each selector.
+    assert!(lookups <= 2 * 5000, "{} lookups for 5000 siblings", lookups);
+}
+
+#[test]
+fn test_the_parallel_traversal_fetches_the_children_of_each_element_in_batches() {
+    let _guard = main_thread();
+    let mut body = element("body");
+    for _ in 0..40 {
+        body = body.child(element("div"));
+    }
+    let doc = MockDocument::new(element("html").child(body));
+    Servo_Test_TakeChildFFICallCount();
+    doc.restyle();
+    let calls = Servo_Test_TakeChildFFICallCount() as usize;
+
+    // With batches, each element gets its children with one call, and only the body's
+    // children past the first batch are found through their siblings. Without them, each
+    // element at least asks for its first child.
+    let elements = doc.elements().len();
+    if cfg!(feature = "batched_children") {
+        assert!(calls <= elements + (40 - 16), "{} calls for {} elements", calls, elements);
+    } else {
+        assert!(calls >= elements, "{} calls for {} elements", calls, elements);
+    }
+}
+
+#[test]
+fn test_matching_reuses_its_buffers_from_one_element_to_the_next() {
+    let _guard = main_thread();
+    Servo_SetDeterministicTraversal(true);
+    // Nothing with a ::before shares its style, so every span is matched and cascaded.
+    let allocations = |reuse| {
+        scratch::set_reuse_enabled(reuse);
+        let doc = big_document();
+        doc.add_sheet("span::before { content: \"\" } span { color: red }", StyleSheetOrigin::Author);
+        let allocations = count_allocations(|| doc.restyle());
+        assert_eq!(doc.computed_value("first", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+        allocations
+    };
+    let without_reuse = allocations(false);
+    let with_reuse = allocations(true);
+    scratch::set_reuse_enabled(true);
+    Servo_SetDeterministicTraversal(false);
+
+    assert!(with_reuse < without_reuse);
+}
+
+/// The color and float of every element of a document whose rules look at the parents and
+/// siblings of the elements they match.
+fn style_with_ancestors(doc: &MockDocument) -> Vec<(String, String)> {
+    doc.restyle();
+    doc.elements().into_iter().map(|element| {
+        let style = Servo_GetComputedValues(element, doc.style_set()).into_arc::<GeckoComputedValues>().unwrap();
+        let values = Borrowed::from_arc(&style).as_ptr();
+        let value = |property: nsCSSProperty| {
+            let mut value = String::new();
+            assert!(Servo_GetComputedPropertyValue(values, property, ns_string(&mut value)));
+            value
+        };
+        (value(nsCSSProperty::eCSSProperty_color), value(nsCSSProperty::eCSSProperty_float))
+    }).collect()
+}
+
+#[test]
+fn test_parallel_traversals_answer_from_their_own_node_caches() {
+    let _guard = main_thread();
+    // The workers all cache what they're told about the body and the divs, and each has to
+    // notice when those change between traversals.
+    let styles = |deterministic| {
+        Servo_SetDeterministicTraversal(deterministic);
+        let doc = big_document();
+        doc.add_sheet("body > div > span { color: red } span > p { color: green } span + span { float: left } \
+                       div > span:hover { float: right }", StyleSheetOrigin::Author);
+        let first = style_with_ancestors(&doc);
+        doc.append_child("first", element("p").id("inner"));
+        doc.set_state("first", NS_EVENT_STATE_HOVER.bits());
+        doc.mark_dirty("first");
+        let second = style_with_ancestors(&doc);
+        assert_eq!(doc.computed_value("first", nsCSSProperty::eCSSProperty_float), "right");
+        Servo_SetDeterministicTraversal(false);
+        assert_eq!(doc.computed_value("inner", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+        (first, second)
+    };
+    assert!(styles(true) == styles(false));
+}
+
+/// Which of the inherited style structs of each element of a freshly styled document are the
+/// very ones of its parent's style. Each traversal allocates styles of its own, but the
+/// structs the cascade leaves alone are shared with the parent whichever thread did it.
+fn structs_shared_with_parents() -> Vec<Vec<bool>> {
+    let mut body = element("body");
+    for i in 0..40 {
+        let mut div = element("div").class(if i % 3 == 0 { "a" } else { "b" });
+        for j in 0..12 {
+            div = div.child(element("span").class(if j % 4 == 0 { "c" } else { "d" }).child(text("x")));
+        }
+        body = body.child(div);
+    }
+    let doc = MockDocument::new(element("html").child(body));
+    doc.add_sheet("div:nth-child(2n) { visibility: hidden } .a span { white-space: pre } .c { cursor: pointer } \
+                   .b .d:last-child { font-style: italic }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+
+    let style_of = |element| {
+        Servo_GetComputedValues(element, doc.style_set()).into_arc::<GeckoComputedValues>().unwrap()
+    };
+    doc.elements().into_iter().skip(1).map(|element| {
+        let style = style_of(element);
+        let parent_style = style_of(unsafe { Gecko_GetParentElement(element) });
+        [ServoStyleStructId::InheritedBox, ServoStyleStructId::Font, ServoStyleStructId::InheritedText,
+         ServoStyleStructId::Pointing].iter().map(|&id| style.same_struct(&parent_style, id)).collect()
+    }).collect()
+}
+
+#[test]
+fn test_parallel_traversals_share_the_same_style_structs_as_sequential_ones() {
+    let _guard = main_thread();
+    Servo_SetDeterministicTraversal(true);
+    let sequential = structs_shared_with_parents();
+    Servo_SetDeterministicTraversal(false);
+    let parallel = structs_shared_with_parents();
+
+    assert!(sequential.iter().any(|structs| structs.iter().any(|&shared| shared)));
+    assert!(sequential.iter().any(|structs| structs.iter().any(|&shared| !shared)));
+    assert_eq!(sequential, parallel);
+}
+
+#[test]
+fn test_the_damage_under_an_element_is_gathered_on_the_way_up() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body")
+            .child(element("div").id("outer").child(element("span").id("inner")))
+            .child(element("p").id("other"))));
+    doc.add_sheet(".block { display: block }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    doc.set_attr("inner", "class", "block");
+    doc.mark_dirty("inner");
+    doc.restyle();
+    for id in &["html", "body", "outer"] {
+        assert!(Servo_GetDescendantChangeHints(doc.element(id)) & RECONSTRUCT_FRAME.bits() != 0);
+        assert!(doc.flags(id) & NODE_DESCENDANTS_NEED_FRAMES != 0);
+    }
+    for id in &["inner", "other"] {
+        assert_eq!(Servo_GetDescendantChangeHints(doc.element(id)), 0);
+        assert_eq!(doc.flags(id) & NODE_DESCENDANTS_NEED_FRAMES, 0);
+    }
+    // Unlike the element's own change hint, the hints of its descendants are left for asking
+    // again.
+    assert!(Servo_TakeChangeHint(doc.element("inner")) & RECONSTRUCT_FRAME.bits() != 0);
+    assert!(Servo_GetDescendantChangeHints(doc.element("outer")) & RECONSTRUCT_FRAME.bits() != 0);
+}
+
+#[test]
+fn test_removed_subtrees_drop_their_node_data_in_one_call() {
+    let _guard = main_thread();
+    for &later in &[false, true] {
+        let doc = big_document();
+        // The id keeps the style of #first from being shared, so only its node holds it.
+        doc.add_sheet("span { float: left } #first { float: right }", StyleSheetOrigin::Author);
+        doc.restyle();
+        let style = doc.computed_values("first");
+        assert_eq!(Arc::strong_count(&style), 2);
+
+        if later {
+            Servo_DropNodeDataRecursiveLater(doc.node("body"));
+        } else {
+            Servo_DropNodeDataRecursive(doc.node("body"));
+        }
+        for id in &["body", "first"] {
+            assert!(unsafe { Gecko_GetNodeData(doc.node(id)) }.is_null());
+        }
+        assert!(!unsafe { Gecko_GetNodeData(doc.node("html")) }.is_null());
+
+        Servo_FlushDroppedNodeData();
+        assert_eq!(Arc::strong_count(&style), 1);
+    }
+    Servo_AssertNoOffThreadDrops();
+}
+
+/// The data Servo keeps on the node with id |id|.
+fn node_data<'a>(doc: &'a MockDocument, id: &str) -> &'a NodeData {
+    unsafe { (Gecko_GetNodeData(doc.node(id)) as *const NodeData).as_ref().expect("No node data") }
+}
+
+#[test]
+fn test_cleared_styles_are_kept_for_the_next_change_hint_or_dropped() {
+    let _guard = main_thread();
+    for &keep_for_diffing in &[true, false] {
+        let doc = MockDocument::new(element("html").id("html")
+            .child(element("div").id("a").child(element("span").id("b"))));
+        doc.add_sheet("div { color: red } .green { color: green }", StyleSheetOrigin::Author);
+        doc.restyle();
+        for id in &["a", "b"] {
+            Servo_TakeChangeHint(doc.element(id));
+        }
+        let style = doc.computed_values("a");
+        assert_eq!(Arc::strong_count(&style), 2);
+
+        doc.set_attr("a", "class", "green");
+        Servo_ClearNodeStyleData(doc.node("a"), keep_for_diffing);
+        assert!(node_data(&doc, "a").style_data.borrow().style.is_none());
+        assert!(node_data(&doc, "b").style_data.borrow().style.is_none());
+        assert_eq!(Arc::strong_count(&style), if keep_for_diffing { 2 } else { 1 });
+        for id in &["a", "b"] {
+            assert!(doc.flags(id) & NODE_IS_DIRTY_FOR_SERVO != 0);
+        }
+        assert!(doc.flags("html") & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+
+        doc.restyle();
+        for id in &["a", "b"] {
+            assert_eq!(doc.computed_value(id, nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+            let hint = Servo_TakeChangeHint(doc.element(id));
+            if keep_for_diffing {
+                // Only the color changed, which is a repaint.
+                assert!(hint != 0 && hint & RECONSTRUCT_FRAME.bits() == 0);
+            } else {
+                assert!(hint & RECONSTRUCT_FRAME.bits() != 0);
+            }
+            assert!(node_data(&doc, id).previous_style.borrow().is_none());
+        }
+        assert_eq!(Arc::strong_count(&style), 1);
+    }
+}
+
+#[test]
+fn test_traversals_match_nodes_left_with_only_their_previous_style() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(element("div").id("a")));
+    doc.add_sheet("div { color: red } .green { color: green }", StyleSheetOrigin::Author);
+    doc.restyle();
+    Servo_TakeChangeHint(doc.element("a"));
+
+    // The previous style alone gets the node matched, even without its restyle bit.
+    doc.set_attr("a", "class", "green");
+    Servo_ClearNodeStyleData(doc.node("a"), true);
+    doc.unmark_dirty("a");
+    doc.restyle();
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    assert!(node_data(&doc, "a").previous_style.borrow().is_none());
+    assert_eq!(Servo_TakeChangeHint(doc.element("a")) & RECONSTRUCT_FRAME.bits(), 0);
+}
+
+#[test]
+fn test_restyling_a_subtree_inherits_from_the_styles_its_parent_already_has() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body").child(element("div").id("outer").child(element("div").id("inner")))
+                                  .child(element("p").id("sibling"))));
+    doc.add_sheet("body { visibility: hidden } div { float: left } .moved { float: right }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+    let styles: Vec<_> = ["html", "body", "outer", "sibling"].iter().map(|&id| doc.computed_values(id)).collect();
+
+    doc.set_attr("inner", "class", "moved");
+    Servo_RestyleSubtree(doc.node("inner"), doc.style_set());
+    assert_eq!(doc.computed_value("inner", nsCSSProperty::eCSSProperty_float), "right");
+    assert_eq!(doc.computed_value("inner", nsCSSProperty::eCSSProperty_visibility), "hidden");
+    for (&id, style) in ["html", "body", "outer", "sibling"].iter().zip(&styles) {
+        assert!(arc_ptr_eq(&doc.computed_values(id), style), "{} was restyled", id);
+    }
+
+    // A node that was never styled is restyled from its nearest styled ancestor.
+    let added = doc.append_child("inner", element("div").id("added").class("moved").child(element("div").id("deep")));
+    assert!(unsafe { Gecko_GetNodeData(added) }.is_null());
+    Servo_RestyleSubtree(doc.node("deep"), doc.style_set());
+    assert_eq!(doc.computed_value("added", nsCSSProperty::eCSSProperty_float), "right");
+    assert_eq!(doc.computed_value("deep", nsCSSProperty::eCSSProperty_float), "left");
+    assert_eq!(doc.computed_value("deep", nsCSSProperty::eCSSProperty_visibility), "hidden");
+    for (&id, style) in ["html", "body", "outer", "sibling"].iter().zip(&styles) {
+        assert!(arc_ptr_eq(&doc.computed_values(id), style), "{} was restyled", id);
+    }
+
+    // Starting from the document element restyles everything, as a document restyle would.
+    doc.set_attr("sibling", "class", "moved");
+    Servo_RestyleSubtree(doc.node("html"), doc.style_set());
+    assert_eq!(doc.computed_value("sibling", nsCSSProperty::eCSSProperty_float), "right");
+}
+
+#[test]
+fn test_documents_without_a_root_restyle_nothing_and_forget_what_was_dirty() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(element("body").id("body")));
+    doc.add_sheet("body { float: left }", StyleSheetOrigin::Author);
+    doc.restyle();
+    doc.mark_dirty("body");
+    doc.remove("html");
+    assert!(unsafe { Gecko_GetDocumentElement(doc.raw_document()) }.is_null());
+    assert!(doc.document_flags() & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+
+    doc.restyle();
+    assert_eq!(doc.document_flags() & (NODE_IS_DIRTY_FOR_SERVO | NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO), 0);
+
+    // Nor is the subtree that was taken out restyled, or its stale styles handed out.
+    Servo_RestyleSubtree(doc.node("body"), doc.style_set());
+    assert!(doc.flags("body") & NODE_IS_DIRTY_FOR_SERVO != 0);
+    for &id in &["html", "body"] {
+        assert!(Servo_GetComputedValues(doc.element(id), doc.style_set()).into_arc::<GeckoComputedValues>().is_none(),
+                "{} has computed values", id);
+    }
+}
+
+#[test]
+fn test_elements_of_a_document_without_a_root_resolve_from_the_initial_values() {
+    let _guard = main_thread();
+    // As during document.open(), the document lost its root before it was ever styled.
+    let doc = MockDocument::new(element("html").id("html").child(element("body").id("body")));
+    doc.add_sheet("html { visibility: hidden } body { float: left }", StyleSheetOrigin::Author);
+    doc.remove("html");
+    doc.restyle();
+    assert!(unsafe { Gecko_GetNodeData(doc.node("body")) }.is_null());
+
+    let resolved = |id: &str| {
+        Servo_ResolveStyleLazily(doc.element(id), ptr::null_mut(), doc.style_set())
+            .into_arc::<GeckoComputedValues>().expect("No resolved style")
+    };
+    let body = resolved("body");
+    assert_eq!(resolved_value(&body, nsCSSProperty::eCSSProperty_float), "left");
+    assert_eq!(resolved_value(&body, nsCSSProperty::eCSSProperty_visibility), "hidden");
+    let html = resolved("html");
+    assert_eq!(resolved_value(&html, nsCSSProperty::eCSSProperty_visibility), "hidden");
+    assert_eq!(resolved_value(&html, nsCSSProperty::eCSSProperty_float), "none");
+}
+
+#[test]
+fn test_adopted_nodes_drop_the_rule_nodes_of_their_old_document() {
+    let _guard = main_thread();
+    let old_doc = MockDocument::new(element("html").id("html")
+        .child(element("div").id("moved").class("x").child(element("span").id("inner"))));
+    let new_doc = MockDocument::new(element("html").id("target"));
+    let mut rule_trees = vec![];
+    for &(doc, css) in &[(&old_doc, ".x { color: red }"), (&new_doc, ".x { color: green }")] {
+        let rule_tree = Arc::new(RuleTree::new());
+        PerDocumentStyleData::borrow_mut_from_raw(doc.style_set()).rule_tree = Some(rule_tree.clone());
+        doc.add_sheet(css, StyleSheetOrigin::Author);
+        doc.restyle();
+        rule_trees.push(rule_tree);
+    }
+    assert_eq!(old_doc.computed_value("inner", nsCSSProperty::eCSSProperty_color), "rgb(255, 0, 0)");
+
+    new_doc.adopt(&old_doc, "moved", "target");
+    for id in &["moved", "inner"] {
+        let data = node_data(&new_doc, id).style_data.borrow();
+        assert!(data.style.is_none() && data.rule_node.is_none());
+    }
+    // Nothing in the new document points into the old one's rule tree once it's gone.
+    drop(old_doc);
+    new_doc.restyle();
+    for id in &["moved", "inner"] {
+        assert_eq!(new_doc.computed_value(id, nsCSSProperty::eCSSProperty_color), "rgb(0, 128, 0)");
+    }
+    let data = node_data(&new_doc, "moved").style_data.borrow();
+    let mut rule_node = data.rule_node.clone().expect("No rule node");
+    loop {
+        let parent = rule_node.parent().cloned();
+        match parent {
+            Some(parent) => rule_node = parent,
+            None => break,
+        }
+    }
+    assert!(arc_ptr_eq(&rule_node, rule_trees[1].root()));
+}
+
+/// Adds one to the count it shares when dropped.
+struct CountsDrops(Arc<AtomicUsize>);
+
+impl Drop for CountsDrops {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_main_thread_values_dropped_elsewhere_are_sent_back_or_caught() {
+    let _guard = main_thread();
+    take_off_thread_drops();
+    let drops = Arc::new(AtomicUsize::new(0));
+
+    drop(MainThreadOwned::new(CountsDrops(drops.clone())));
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    // The threads meant to free styles have the main thread free it for them.
+    let value = MainThreadOwned::new(CountsDrops(drops.clone()));
+    thread::spawn(move || {
+        release_elsewhere_from_this_thread();
+        drop(value);
+    }).join().unwrap();
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+    run_dispatched_releases();
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+    Servo_AssertNoOffThreadDrops();
+
+    // Any other thread frees it itself, and debug builds remember it did.
+    let value = MainThreadOwned::new(CountsDrops(drops.clone()));
+    thread::Builder::new().name("Stray".to_owned()).spawn(move || drop(value)).unwrap().join().unwrap();
+    assert_eq!(drops.load(Ordering::SeqCst), 3);
+    let expected: &[&str] = if cfg!(debug_assertions) { &["Stray"] } else { &[] };
+    assert_eq!(take_off_thread_drops(), expected);
+}
+
+fn restyle_hint<F>(doc: &MockDocument, id: &str, change: F) -> RestyleHint where F: FnOnce() {
+    let snapshot = doc.snapshot(id);
+    change();
+    let hint = Servo_PerDocumentData_ComputeRestyleHint(doc.element(id), snapshot, doc.data());
+    unsafe { Gecko_DropElementSnapshot(snapshot) };
+    hint.to_servo()
+}
+
+#[test]
+fn test_snapshots_give_restyle_hints_for_what_changed() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("div").id("a").class("note").child(element("p")))
+            .child(element("p"))));
+    doc.add_sheet(".warning { float: left } .note p { float: right } div:hover { float: none } \
+                   [data-x] + p { float: left }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(restyle_hint(&doc, "a", || doc.remove_attr("a", "class")), RESTYLE_DESCENDANTS);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "class", "warning")), RESTYLE_SELF);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "title", "Hello")), RestyleHint::empty());
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "data-x", "")), RESTYLE_LATER_SIBLINGS);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_state("a", NS_EVENT_STATE_HOVER.bits())), RESTYLE_SELF);
+
+    assert!(Servo_StyleSet_MightHaveAttributeDependency(doc.style_set(), atom("data-x")));
+    assert!(!Servo_StyleSet_MightHaveAttributeDependency(doc.style_set(), atom("title")));
+}
+
+#[test]
+fn test_restyles_match_attribute_selectors_without_copying_the_values_out() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("body")
+        .child(element("input").id("input").attr("type", "TEXT").attr("title", "a b-c"))
+        .child(element("a").id("a").attr("href", "https://example.com/page.html").attr("hreflang", "en-US"))));
+    doc.add_sheet("[type=text] { float: left } \
+                   [title~=b-c] { color: lime } \
+                   [hreflang|=en] { float: right } \
+                   [href^='https:'][href*=example][href$='.html'] { color: lime } \
+                   :not([disabled]) { visibility: hidden }",
+                  StyleSheetOrigin::Author);
+
+    let copies = count_attr_value_copies(|| doc.restyle());
+    // type is one of the attributes HTML compares values of case-insensitively.
+    assert_eq!(doc.computed_value("input", nsCSSProperty::eCSSProperty_float), "left");
+    assert_eq!(doc.computed_value("input", nsCSSProperty::eCSSProperty_color), "rgb(0, 255, 0)");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_float), "right");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_color), "rgb(0, 255, 0)");
+    assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_visibility), "hidden");
+    assert_eq!(copies, 0);
+}
+
+#[test]
+fn test_attribute_selectors_give_restyle_hints_from_every_origin() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("div").id("a").child(element("span")))
+            .child(element("p"))));
+    doc.add_sheet("[hidden] { display: none }", StyleSheetOrigin::UserAgent);
+    doc.add_sheet("[lang] ~ p { float: left }", StyleSheetOrigin::User);
+    doc.add_sheet("[title] span { float: right }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "hidden", "")), RESTYLE_SELF);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "lang", "en")), RESTYLE_LATER_SIBLINGS);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "title", "Hello")), RESTYLE_DESCENDANTS);
+    assert_eq!(restyle_hint(&doc, "a", || doc.set_attr("a", "data-x", "")), RestyleHint::empty());
+}
+
+#[test]
+fn test_restyle_hints_keep_their_bits_across_the_ffi() {
+    assert_eq!(ServoRestyleHint::from_servo(RESTYLE_SELF), SERVO_RESTYLE_SELF);
+    assert_eq!(ServoRestyleHint::from_servo(RESTYLE_DESCENDANTS), SERVO_RESTYLE_DESCENDANTS);
+    assert_eq!(ServoRestyleHint::from_servo(RESTYLE_LATER_SIBLINGS), SERVO_RESTYLE_LATER_SIBLINGS);
+    assert_eq!(ServoRestyleHint::all().to_servo(), RestyleHint::all());
+    // Gecko reads the hints as plain bits.
+    assert_eq!(ServoRestyleHint::all().bits(), RestyleHint::all().bits());
+    assert_eq!(mem::size_of::<ServoRestyleHint>(), 1);
+}
+
+#[test]
+fn test_explicit_hints_dirty_what_they_cover() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body")
+            .child(element("div").id("a").child(element("span").id("child")))
+            .child(element("p").id("sibling"))));
+    doc.restyle();
+    let dirty = |id| doc.flags(id) & NODE_IS_DIRTY_FOR_SERVO != 0;
+
+    Servo_NoteExplicitHints(doc.element("a"), SERVO_RESTYLE_LATER_SIBLINGS, 0);
+    assert!(!dirty("a") && !dirty("child"));
+    assert!(dirty("sibling"));
+    assert!(doc.flags("body") & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+    doc.restyle();
+
+    Servo_NoteExplicitHints(doc.element("a"), SERVO_RESTYLE_DESCENDANTS, 0);
+    assert!(!dirty("a") && !dirty("sibling"));
+    assert!(dirty("child"));
+    doc.restyle();
+
+    Servo_NoteExplicitHints(doc.element("a"), SERVO_RESTYLE_SELF, 0);
+    assert!(dirty("a"));
+    assert!(!dirty("sibling"));
+    assert!(doc.flags("html") & NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO != 0);
+    doc.restyle();
+
+    Servo_NoteExplicitHints(doc.element("a"), ServoRestyleHint::empty(), 0);
+    for id in &["html", "body", "a", "child", "sibling"] {
+        assert_eq!(doc.flags(id) & (NODE_IS_DIRTY_FOR_SERVO | NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO), 0);
+    }
+}
+
+#[test]
+fn test_dependency_predicates_cover_every_origin_and_follow_new_sheets() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(element("body")));
+    doc.add_sheet("input:valid { float: left }", StyleSheetOrigin::UserAgent);
+    doc.add_sheet("p:not(:hover) { float: right }", StyleSheetOrigin::Author);
+
+    let set = doc.style_set();
+    assert!(Servo_StyleSet_HasStateDependency(set, NS_EVENT_STATE_VALID.bits()));
+    assert!(Servo_StyleSet_HasStateDependency(set, NS_EVENT_STATE_HOVER.bits() | NS_EVENT_STATE_ACTIVE.bits()));
+    assert!(!Servo_StyleSet_HasStateDependency(set, NS_EVENT_STATE_ACTIVE.bits()));
+    assert!(!Servo_StyleSet_HasStateDependency(set, NS_EVENT_STATE_FOCUS.bits()));
+    assert!(!Servo_StyleSet_HasAttributeDependency(set, atom("data-x")));
+    assert!(!Servo_StyleSet_HasDocumentStateDependency(set, NS_DOCUMENT_STATE_WINDOW_INACTIVE.bits()));
+
+    doc.add_sheet("[data-x] { float: none } div:focus-within { float: left } \
+                   :-moz-window-inactive p { float: right }", StyleSheetOrigin::Author);
+    // Focusing an element flips :focus-within on its ancestors.
+    assert!(Servo_StyleSet_HasStateDependency(set, NS_EVENT_STATE_FOCUS.bits()));
+    assert!(Servo_StyleSet_HasAttributeDependency(set, atom("data-x")));
+    assert!(Servo_StyleSet_HasDocumentStateDependency(set, NS_DOCUMENT_STATE_WINDOW_INACTIVE.bits()));
+    assert!(!Servo_StyleSet_HasDocumentStateDependency(set, NS_DOCUMENT_STATE_LWTHEME.bits()));
+}
+
+#[test]
+fn test_noted_snapshots_are_restyled_from_on_the_next_restyle() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("div").id("a").class("note").child(element("p").id("p")))
+            .child(element("div").id("b").class("note").child(element("p").id("q")))));
+    doc.add_sheet(".note p { float: right } div:hover { float: left }", StyleSheetOrigin::Author);
+    doc.restyle();
+    let float = |id: &str| doc.computed_value(id, nsCSSProperty::eCSSProperty_float);
+
+    // The class went from `note` to `x` to `y`, so the snapshot has to be of the element
+    // before the first change for `.note p` to stop matching.
+    Servo_NoteElementSnapshot(doc.data(), doc.element("a"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_attr("a", "class", "x");
+    Servo_NoteElementSnapshot(doc.data(), doc.element("a"), (SNAPSHOT_STATE | SNAPSHOT_ATTRIBUTES).bits());
+    doc.set_attr("a", "class", "y");
+    doc.set_state("a", NS_EVENT_STATE_HOVER.bits());
+    // Elements that leave the document before the restyle take their snapshots with them.
+    Servo_NoteElementSnapshot(doc.data(), doc.element("b"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_attr("b", "class", "x");
+    Servo_ForgetElementSnapshot(doc.data(), doc.element("b"));
+    doc.restyle();
+    assert_eq!((float("a"), float("p")), ("left".to_owned(), "none".to_owned()));
+    assert_eq!(float("q"), "right");
+
+    // A snapshot only counts the changes it was taken for.
+    Servo_NoteElementSnapshot(doc.data(), doc.element("a"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_state("a", 0);
+    doc.restyle();
+    assert_eq!(float("a"), "left");
+}
+
+/// The restyle hint the next restyle would compute for the snapshot Servo_NoteElementSnapshot
+/// took of the element with id |id|, which is dropped along with the others.
+fn noted_restyle_hint(doc: &MockDocument, id: &str) -> RestyleHint {
+    let data = PerDocumentStyleData::borrow_mut_from_raw_document_data(doc.data());
+    data.flush_stylesheets();
+    let token = unsafe { DomToken::new() };
+    let snapshots = data.snapshots.take();
+    let stylist = &data.stylist;
+    let mut hint = None;
+    snapshots.each(|element, snapshot| {
+        if element == doc.element(id) {
+            let element = unsafe { GeckoElement::from_raw(&token, element) };
+            hint = Some(stylist.compute_restyle_hint(&element, snapshot, element.get_state()));
+        }
+    });
+    hint.expect("The element has no snapshot")
+}
+
+#[test]
+fn test_noted_snapshots_match_attribute_selectors_against_the_attributes_they_copied() {
+    let _guard = main_thread();
+    let doc = MockDocument::new(element("html").child(
+        element("body")
+            .child(element("button").id("toggle").attr("aria-expanded", "false").child(element("span")))
+            .child(element("div").id("menu").attr("data-open", "").child(element("p")))));
+    doc.add_sheet("[aria-expanded=\"true\"] { float: left } [data-open] p { float: right } \
+                   #renamed { float: none }", StyleSheetOrigin::Author);
+    doc.restyle();
+
+    // Only the button's own style depends on aria-expanded, whatever the other attributes do.
+    Servo_NoteElementSnapshot(doc.data(), doc.element("toggle"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_attr("toggle", "aria-expanded", "true");
+    doc.set_attr("toggle", "title", "Menu");
+    assert_eq!(noted_restyle_hint(&doc, "toggle"), RESTYLE_SELF);
+
+    Servo_NoteElementSnapshot(doc.data(), doc.element("toggle"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_attr("toggle", "title", "Close");
+    assert_eq!(noted_restyle_hint(&doc, "toggle"), RestyleHint::empty());
+
+    // The old values are the ones from before the first change noted, and the id is among them.
+    Servo_NoteElementSnapshot(doc.data(), doc.element("menu"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.remove_attr("menu", "data-open");
+    Servo_NoteElementSnapshot(doc.data(), doc.element("menu"), SNAPSHOT_ATTRIBUTES.bits());
+    doc.set_attr("menu", "data-open", "");
+    doc.set_attr("menu", "id", "renamed");
+    assert_eq!(noted_restyle_hint(&doc, "renamed"), RESTYLE_SELF);
+}
+
+#[test]
+fn test_focus_changes_restyle_the_ancestors_whose_focus_within_rules_they_flip() {
+    let _guard = main_thread();
+    Servo_SetTraversalStatisticsEnabled(true);
+    let doc = MockDocument::new(element("html").id("html").child(
+        element("body").id("body")
+            .child(element("div").id("outer").class("box").child(
+                element("section").id("mid").child(
+                    element("form").id("inner").class("box").child(element("input").id("field")))))
+            .child(element("p").id("other"))));
+    doc.add_sheet(".box:focus-within { float: left } :placeholder-shown { float: right }",
+                  StyleSheetOrigin::Author);
+    doc.restyle();
+    let float = |id: &str| doc.computed_value(id, nsCSSProperty::eCSSProperty_float);
+    let unchanged = ["html", "body", "other"].iter().map(|&id| doc.computed_values(id)).collect::<Vec<_>>();
+
+    // Gecko updates the focus-within state of the ancestors along with the focus of the
+    // element, but only snapshots the element itself.
+    let set_focus = |focused: bool| {
+        Servo_NoteElementSnapshot(doc.data(), doc.element("field"), SNAPSHOT_STATE.bits());
+        let within = if focused { NS_EVENT_STATE_FOCUS_WITHIN.bits() } else { 0 };
+        for &id in &["html", "body", "outer", "mid", "inner"] {
+            doc.set_state(id, within);
+        }
+        doc.set_state("field", if focused { (NS_EVENT_STATE_FOCUS | NS_EVENT_STATE_FOCUS_WITHIN).bits() } else { 0 });
+        doc.restyle();
+        let mut statistics: ServoTraversalStatistics = unsafe { mem::zeroed() };
+        Servo_GetTraversalStatistics(&mut statistics);
+        statistics.mElementsStyled
+    };
+    for &focused in &[true, false] {
+        // #outer and everything below it, as restyling an element restyles its descendants.
+        assert_eq!(set_focus(focused), 4);
+        let expected = if focused { "left" } else { "none" };
+        assert_eq!((float("outer"), float("mid"), float("inner")),
+                   (expected.to_owned(), "none".to_owned(), expected.to_owned()));
+        for (&id, values) in ["html", "body", "other"].iter().zip(&unchanged) {
+            assert!(arc_ptr_eq(&doc.computed_values(id), values), "{} was restyled", id);
+        }
+    }
+    Servo_SetTraversalStatisticsEnabled(false);
+
+    assert_eq!(restyle_hint(&doc, "field", || doc.set_state("field", NS_EVENT_STATE_PLACEHOLDERSHOWN.bits())),
+               RESTYLE_SELF);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_panics_are_reported_to_the_crash_reporter() {
+    use glue::Servo_Test_Panic;
+    use super::crash_annotation;
+
+    let _guard = main_thread();
+    assert!(!Servo_Test_Panic());
+    assert_eq!(crash_annotation("ServoPanicFunction").as_ref().map(|s| &**s), Some("Servo_Test_Panic"));
+    let message = crash_annotation("ServoPanicMessage").unwrap();
+    assert!(message.starts_with("Servo_Test_Panic at "));
+    // Followed by the backtrace of the panic.
+    assert!(message.lines().count() > 1, "{}", message);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_gecko_gives_back_every_reference_it_was_handed() {
+    let _guard = main_thread();
+    let references = Servo_Test_GetStrongRefCount();
+    {
+        let doc = sample_document();
+        doc.add_sheet("div { float: left } #a { float: right }", StyleSheetOrigin::Author);
+        doc.restyle();
+        assert_eq!(doc.computed_value("a", nsCSSProperty::eCSSProperty_float), "right");
+        let mut list = MockRuleList::default();
+        Servo_GetMatchingRules(doc.element("a"), ptr::null_mut(), doc.style_set(), list.as_raw());
+        assert_eq!(list.rules.len(), 2);
+    }
+    assert_eq!(Servo_Test_GetStrongRefCount(), references);
+}
+
+#[test]
+fn test_the_refcount_log_reports_what_gecko_never_released() {
+    use glue::{Servo_AddRefStyleSheet, Servo_SetRefCountLogging};
+
+    let _guard = main_thread();
+    let doc = sample_document();
+    Servo_SetRefCountLogging(true);
+    let leaked = doc.parse_sheet("div { float: left }", StyleSheetOrigin::Author, &MockSheetContext::new());
+    let released = doc.parse_sheet("div { float: right }", StyleSheetOrigin::Author, &MockSheetContext::new());
+    Servo_AddRefStyleSheet(released);
+    Servo_ReleaseStyleSheet(released);
+    Servo_ReleaseStyleSheet(released);
+
+    let counts = ownership::live_object_counts();
+    let sheets: Vec<_> = counts.iter().filter(|&&(type_name, _)| type_name == "RawServoStyleSheet").collect();
+    assert_eq!(sheets, [&("RawServoStyleSheet", 1)]);
+    let report = ownership::describe_live_objects();
+    assert!(report.contains("1 live RawServoStyleSheet\n"));
+    assert!(report.contains(&format!("RawServoStyleSheet {:#x} (1 held by Gecko)\n", leaked as usize)));
+    assert!(report.contains("  Created (1 held by Gecko) at\n"));
+
+    Servo_SetRefCountLogging(false);
+    assert!(ownership::live_object_counts().is_empty());
+    Servo_ReleaseStyleSheet(leaked);
+}
+
+#[test]
+fn test_atoms_converted_from_gecko_are_cached_per_thread_with_a_bound() {
+    let _guard = main_thread();
+    // More atoms than a thread keeps, so that the first ones are evicted before they come up
+    // again.
+    let names: Vec<_> = (0..4096).map(|index| format!("test-cached-class-{}", index)).collect();
+    for _ in 0..2 {
+        for name in &names {
+            assert_eq!(&*unsafe { atom_from_gecko(atom(name)) }, &**name);
+        }
+    }
+    // The thread's cache holds a reference to each atom it keeps, and only to those.
+    let references: usize = names.iter().map(|name| atom_refcount(atom(name))).sum();
+    assert!(references > 0 && references < names.len(), "{}", references);
+
+    // Element names are kept for good, since node data borrows them.
+    let name = atom("test-cached-element");
+    let first = unsafe { local_name_from_gecko(name) } as *const _;
+    assert_eq!(unsafe { local_name_from_gecko(name) } as *const _, first);
+    assert_eq!(unsafe { &**first }, "test-cached-element");
+}
+
+#[test]
+fn test_gecko_atoms_refcount_dynamic_atoms_and_leave_static_ones_alone() {
+    let dynamic = atom("test-dynamic-atom");
+    let first = unsafe { GeckoAtom::from_raw(dynamic) };
+    assert!(!first.is_static());
+    assert_eq!(atom_refcount(dynamic), 1);
+    let second = first.clone();
+    assert_eq!(atom_refcount(dynamic), 2);
+    assert_eq!(first, second);
+    assert_eq!(first.as_ptr(), dynamic);
+    drop(second);
+    assert_eq!(atom_refcount(dynamic), 1);
+    let atomized = GeckoAtom::from_str("test-dynamic-atom");
+    assert_eq!(atomized, first);
+    assert_eq!(atom_refcount(dynamic), 2);
+    drop((first, atomized));
+    assert_eq!(atom_refcount(dynamic), 0);
+
+    // The mock panics if a static atom is ever refcounted.
+    let static_ = static_atom("test-static-atom");
+    let first = unsafe { GeckoAtom::from_raw(static_) };
+    assert!(first.is_static());
+    assert_eq!(first.as_ptr(), static_);
+    let second = first.clone();
+    assert_eq!(GeckoAtom::from_str("test-static-atom"), second);
+    assert_eq!(atom_refcount(static_), 0);
+
+    let set: HashSet<_> = vec![first.clone(), second, unsafe { GeckoAtom::from_raw(dynamic) }].into_iter().collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&first));
+    assert_eq!(first.to_string(), "test-static-atom");
+
+    let weak = first.as_weak();
+    assert_eq!(weak, unsafe { WeakAtom::new(static_) });
+    assert!(weak.eq_str("test-static-atom"));
+    assert!(!weak.eq_str("TEST-static-atom"));
+    assert!(weak.eq_str_ignore_ascii_case("TEST-Static-Atom"));
+    assert!(!weak.eq_str_ignore_ascii_case("test-static"));
+    assert_eq!(weak.upgrade(), first);
+    drop(set);
+    assert_eq!(atom_refcount(dynamic), 0);
+}
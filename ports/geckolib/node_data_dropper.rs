@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Frees the data of nodes Gecko took out of the DOM on a thread of its own, so that tearing
+//! down a big subtree doesn't hold up Gecko's main thread.
+//!
+//! What the data holds is safe to free there: the styles are reference counted atomically,
+//! the image requests they hold are released on the main thread once the last style
+//! referring to them is gone (see image_request.rs), and so is whatever else only the main
+//! thread may free, which the thread has Gecko release there (see main_thread.rs).
+
+#![allow(unsafe_code)]
+
+use ffi;
+use main_thread;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+use wrapper::{NodeData, NonOpaqueStyleData};
+
+enum Job {
+    /// The data of nodes Gecko no longer points to, by address so that it can cross threads.
+    Drop(Vec<usize>),
+    /// Everything queued before has been freed once this is answered.
+    Flush(Sender<()>),
+}
+
+pub struct NodeDataDropper {
+    /// Where the data to free is queued, or None once the dropper has shut down.
+    jobs: Option<Sender<Job>>,
+
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NodeDataDropper {
+    pub fn new() -> NodeDataDropper {
+        let (sender, receiver) = channel::<Job>();
+        let thread = thread::Builder::new().name("StyleNodeDataDropper".to_owned()).spawn(move || {
+            main_thread::release_elsewhere_from_this_thread();
+            for job in receiver {
+                match job {
+                    Job::Drop(data) => {
+                        ffi::catch_panics("StyleNodeDataDropper", move || {
+                            for data in data {
+                                unsafe { drop(Box::from_raw(data as NonOpaqueStyleData)) };
+                            }
+                        }, || ());
+                    }
+                    Job::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        }).unwrap();
+        NodeDataDropper {
+            jobs: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues |data| to be freed. Nothing may point to any of it anymore.
+    pub fn drop_later(&self, data: Vec<NonOpaqueStyleData>) {
+        if data.is_empty() {
+            return;
+        }
+        let data = data.into_iter().map(|data| data as usize).collect();
+        self.jobs.as_ref().expect("The node data dropper has shut down").send(Job::Drop(data)).unwrap();
+    }
+
+    /// Waits until everything queued so far has been freed.
+    pub fn flush(&self) {
+        let (done, finished) = channel();
+        self.jobs.as_ref().expect("The node data dropper has shut down").send(Job::Flush(done)).unwrap();
+        let _ = finished.recv();
+    }
+
+    /// Frees whatever is still queued and waits for the dropper thread to stop.
+    pub fn shutdown(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Frees |data| right away.
+pub fn drop_now(data: Vec<NonOpaqueStyleData>) {
+    for data in data {
+        unsafe { drop(Box::<NodeData>::from_raw(data)) };
+    }
+}
@@ -0,0 +1,284 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Typed pointers for the Servo objects Gecko holds in Arcs.
+//!
+//! Gecko knows an `Arc<Stylesheet>` as a `*mut RawServoStyleSheet`, and whether a given
+//! pointer carries a reference of its own used to be something each glue function had to
+//! get right by hand. A `Strong` owns a reference, and is what functions handing one to
+//! Gecko return. A `Borrowed` is a pointer Gecko lends us for the length of a call. These
+//! are the only places that turn Arcs into pointers and back.
+//!
+//! Debug builds count the references Gecko holds, and print every one that changes hands
+//! if `STYLO_LOG_REFCOUNTS` is set.
+//!
+//! Any build can also keep a log of the objects Gecko holds references to, for tracking
+//! down leaks: Servo_SetRefCountLogging turns it on, as does `STYLO_LOG_REFCOUNTS`, and
+//! Servo_DumpLiveObjects prints every object Gecko still holds, with the stacks that gave
+//! it the references and took them back. While it's off, the only cost is checking a flag.
+
+#![allow(unsafe_code)]
+
+use backtrace::Backtrace;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::mem::{forget, transmute_copy};
+use std::ptr;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, ATOMIC_ISIZE_INIT, AtomicBool, AtomicIsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Ties a Servo type to the opaque type Gecko knows it by.
+///
+/// Implementing this promises that every non-null `*mut FFIType` is an `Arc<Self>`.
+pub unsafe trait HasArcFFI: Sized {
+    type FFIType;
+
+    /// The name Gecko knows FFIType by, for the refcount log.
+    fn ffi_type_name() -> &'static str;
+}
+
+/// How many references Servo has given Gecko through a `Strong` that Gecko hasn't given
+/// back, for testing.
+static STRONG_REFS: AtomicIsize = ATOMIC_ISIZE_INIT;
+
+pub fn strong_ref_count() -> isize {
+    STRONG_REFS.load(Ordering::Relaxed)
+}
+
+lazy_static! {
+    static ref LOG_REFCOUNTS: bool = env::var("STYLO_LOG_REFCOUNTS").is_ok();
+}
+
+fn note_strong_ref<ServoType: HasArcFFI>(ptr: *mut ServoType::FFIType, delta: isize, what: &str) {
+    if ptr.is_null() {
+        return;
+    }
+    if REFCOUNT_LOGGING.load(Ordering::Relaxed) {
+        log_refcount_change(ServoType::ffi_type_name(), ptr as usize, delta);
+    }
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let count = STRONG_REFS.fetch_add(delta, Ordering::Relaxed) + delta;
+    if *LOG_REFCOUNTS {
+        let _ = writeln!(io::stderr(), "{} {:p} ({} held by Gecko)", what, ptr, count);
+    }
+}
+
+/// Whether every reference given to Gecko or taken back goes in LIVE_OBJECTS.
+static REFCOUNT_LOGGING: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Something that happened to one of the references Gecko holds to an object.
+struct RefCountEvent {
+    /// "Created" for the first reference Gecko got while logging was on, and "AddRef" or
+    /// "Release" after that.
+    what: &'static str,
+    /// How many references Gecko held afterwards.
+    count: isize,
+    backtrace: Backtrace,
+}
+
+/// An object Gecko holds references to, and everything that happened to them.
+struct LiveObject {
+    count: isize,
+    events: Vec<RefCountEvent>,
+}
+
+lazy_static! {
+    /// The objects Gecko holds references to, by type name and then address. An object
+    /// leaves once Gecko has released every reference it was given while logging was on.
+    /// Releases happen on the style worker threads too, hence the lock.
+    static ref LIVE_OBJECTS: Mutex<HashMap<&'static str, HashMap<usize, LiveObject>>> = Mutex::new(HashMap::new());
+}
+
+/// Turns the refcount log on or off. Either way, it starts over empty.
+pub fn set_refcount_logging(enabled: bool) {
+    let mut live_objects = LIVE_OBJECTS.lock().unwrap();
+    REFCOUNT_LOGGING.store(enabled, Ordering::Relaxed);
+    live_objects.clear();
+}
+
+/// Turns on the refcount log if `STYLO_LOG_REFCOUNTS` is set.
+pub fn initialize_refcount_logging() {
+    if env::var("STYLO_LOG_REFCOUNTS").is_ok() {
+        set_refcount_logging(true);
+    }
+}
+
+fn log_refcount_change(type_name: &'static str, address: usize, delta: isize) {
+    // Walking the stack takes a while, so do it before taking the lock.
+    let backtrace = Backtrace::new();
+    let mut live_objects = LIVE_OBJECTS.lock().unwrap();
+    // The log may have been turned off while we walked.
+    if !REFCOUNT_LOGGING.load(Ordering::Relaxed) {
+        return;
+    }
+    let objects = live_objects.entry(type_name).or_insert_with(HashMap::new);
+    if !objects.contains_key(&address) {
+        // Releasing a reference Gecko got before the log was on doesn't tell us anything.
+        if delta < 0 {
+            return;
+        }
+        objects.insert(address, LiveObject { count: 0, events: vec![] });
+    }
+    let count = {
+        let object = objects.get_mut(&address).unwrap();
+        object.count += delta;
+        let what = match (delta > 0, object.events.is_empty()) {
+            (true, true) => "Created",
+            (true, false) => "AddRef",
+            (false, _) => "Release",
+        };
+        object.events.push(RefCountEvent { what: what, count: object.count, backtrace: backtrace });
+        object.count
+    };
+    if count <= 0 {
+        objects.remove(&address);
+    }
+}
+
+/// How many objects of each type Gecko still holds references to, by the name Gecko knows
+/// the type by. Types with none left aren't listed.
+pub fn live_object_counts() -> Vec<(&'static str, usize)> {
+    let live_objects = LIVE_OBJECTS.lock().unwrap();
+    let mut counts: Vec<_> = live_objects.iter()
+                                         .filter(|&(_, objects)| !objects.is_empty())
+                                         .map(|(&type_name, objects)| (type_name, objects.len()))
+                                         .collect();
+    counts.sort();
+    counts
+}
+
+/// Describes every object Gecko still holds references to, along with the stack of each
+/// time a reference was given to Gecko or taken back.
+pub fn describe_live_objects() -> String {
+    let live_objects = LIVE_OBJECTS.lock().unwrap();
+    let mut type_names: Vec<_> = live_objects.keys().cloned().collect();
+    type_names.sort();
+    let mut description = String::new();
+    for type_name in type_names {
+        let objects = &live_objects[&type_name];
+        if objects.is_empty() {
+            continue;
+        }
+        let _ = writeln!(description, "{} live {}", objects.len(), type_name);
+        let mut addresses: Vec<_> = objects.keys().cloned().collect();
+        addresses.sort();
+        for address in addresses {
+            let object = &objects[&address];
+            let _ = writeln!(description, "{} {:#x} ({} held by Gecko)", type_name, address, object.count);
+            for event in &object.events {
+                let _ = writeln!(description, "  {} ({} held by Gecko) at", event.what, event.count);
+                let _ = writeln!(description, "{:?}", event.backtrace);
+            }
+        }
+    }
+    description
+}
+
+/// A reference to a Servo object, owned by whoever holds this. Gecko declares the same
+/// struct around the pointer, so both sides agree on how it's passed.
+///
+/// Dropping one leaks the reference: it has to end up with Gecko, or go back to being an
+/// Arc through `into_arc`.
+///
+/// FIXME: This should be #[repr(transparent)], so it's passed exactly like the bare
+/// pointer, once the compiler has it.
+#[repr(C)]
+#[must_use]
+pub struct Strong<GeckoType> {
+    ptr: *mut GeckoType,
+}
+
+impl<GeckoType> Strong<GeckoType> {
+    pub fn from_arc<ServoType>(arc: Arc<ServoType>) -> Strong<GeckoType>
+                               where ServoType: HasArcFFI<FFIType=GeckoType> {
+        // transmute can't tell that an Arc<ServoType> is the size of a pointer.
+        let ptr = unsafe { transmute_copy::<Arc<ServoType>, *mut GeckoType>(&arc) };
+        forget(arc);
+        note_strong_ref::<ServoType>(ptr, 1, "Gave");
+        Strong { ptr: ptr }
+    }
+
+    pub fn null() -> Strong<GeckoType> {
+        Strong { ptr: ptr::null_mut() }
+    }
+
+    /// Takes back a reference Gecko already owns, like the one it gives up in a Release
+    /// function.
+    pub unsafe fn from_raw(ptr: *mut GeckoType) -> Strong<GeckoType> {
+        Strong { ptr: ptr }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// Turns the reference back into an Arc, or returns None if it's null.
+    pub fn into_arc<ServoType>(self) -> Option<Arc<ServoType>>
+                               where ServoType: HasArcFFI<FFIType=GeckoType> {
+        if self.ptr.is_null() {
+            return None;
+        }
+        note_strong_ref::<ServoType>(self.ptr, -1, "Took back");
+        Some(unsafe { transmute_copy::<*mut GeckoType, Arc<ServoType>>(&self.ptr) })
+    }
+}
+
+/// A pointer to a Servo object that Gecko lends for the length of a call, or that we lend
+/// Gecko. It doesn't own a reference.
+pub struct Borrowed<'a, GeckoType: 'a> {
+    ptr: *mut GeckoType,
+    _marker: PhantomData<&'a GeckoType>,
+}
+
+impl<'a, GeckoType> Borrowed<'a, GeckoType> {
+    /// The caller has to keep the object alive for 'a, which holds for the arguments of a
+    /// call from Gecko.
+    pub unsafe fn from_raw(ptr: *mut GeckoType) -> Borrowed<'a, GeckoType> {
+        Borrowed { ptr: ptr, _marker: PhantomData }
+    }
+
+    pub fn from_arc<ServoType>(arc: &'a Arc<ServoType>) -> Borrowed<'a, GeckoType>
+                               where ServoType: HasArcFFI<FFIType=GeckoType> {
+        let ptr = unsafe { transmute_copy::<Arc<ServoType>, *mut GeckoType>(arc) };
+        Borrowed { ptr: ptr, _marker: PhantomData }
+    }
+
+    pub fn as_ptr(&self) -> *mut GeckoType {
+        self.ptr
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// The Arc the pointer stands for, which mustn't be null.
+    pub fn as_arc<ServoType>(&self) -> &Arc<ServoType> where ServoType: HasArcFFI<FFIType=GeckoType> {
+        debug_assert!(!self.ptr.is_null());
+        unsafe { &*(&self.ptr as *const *mut GeckoType as *const Arc<ServoType>) }
+    }
+
+    /// A new reference to the object, for Gecko to own.
+    pub fn to_strong<ServoType>(&self) -> Strong<GeckoType> where ServoType: HasArcFFI<FFIType=GeckoType> {
+        let arc: Arc<ServoType> = self.as_arc::<ServoType>().clone();
+        Strong::from_arc(arc)
+    }
+}
+
+/// Adds a reference to |ptr| for Gecko. Does nothing if it's null.
+pub unsafe fn addref<ServoType>(ptr: *mut ServoType::FFIType) where ServoType: HasArcFFI {
+    let borrowed = Borrowed::from_raw(ptr);
+    if !borrowed.is_null() {
+        forget(borrowed.to_strong::<ServoType>());
+    }
+}
+
+/// Drops one of Gecko's references to |ptr|. Does nothing if it's null.
+pub unsafe fn release<ServoType>(ptr: *mut ServoType::FFIType) where ServoType: HasArcFFI {
+    let _ = Strong::from_raw(ptr).into_arc::<ServoType>();
+}
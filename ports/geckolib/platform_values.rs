@@ -0,0 +1,216 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! What the cascade asks Gecko about the platform: the system colors and fonts, the sizes
+//! font-size keywords are based on, and the metrics of fonts. Gecko only answers on the main
+//! thread, so before a traversal starts there, the style set fetches what its style threads
+//! can ask for into its `PlatformValues`, and they look it up there instead.
+//!
+//! Which fonts need measuring is only known once their styles are computed. A font the style
+//! threads find no metrics for is taken for one that hasn't loaded, the way Gecko would answer
+//! for it, and measured on the main thread once the traversal is done. Should that find
+//! metrics after all, the next restyle computes the styles again.
+
+#![allow(unsafe_code)]
+
+use app_units::Au;
+use atoms::GeckoAtom;
+use bindings::{Gecko_GetBaseFontSize, nsIAtom};
+use cssparser::RGBA;
+use look_and_feel::{self, SYSTEM_COLORS, SYSTEM_FONTS};
+use main_thread::is_main_thread;
+use properties::{FontMetricsKey, GENERIC_FONT_IDS, GeckoFont};
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use std::sync::{Arc, Mutex, RwLock};
+use style::system_values::{SystemColor, SystemFont, SystemFontValues};
+use style::values::FONT_MEDIUM_PX;
+use style::values::computed::FontMetrics;
+use wrapper::use_standin_system_colors;
+
+lazy_static! {
+    /// The languages Gecko mapped lang attributes to so far, which the base font sizes are
+    /// fetched for along with those for no language.
+    static ref LANGUAGES: Mutex<Vec<GeckoAtom>> = Mutex::new(vec![]);
+    /// What the style threads of the running traversal look values up in.
+    static ref TRAVERSAL_VALUES: RwLock<Option<Arc<PlatformValues>>> = RwLock::new(None);
+    /// The fonts the style threads found no base size or metrics for, which only the main
+    /// thread may measure.
+    static ref FONTS_FOR_MAIN_THREAD: Mutex<Vec<GeckoFont>> = Mutex::new(vec![]);
+}
+
+/// Notes that fonts can be in `language`, so that the base font sizes of the traversals from
+/// now on are fetched for it. Only the main thread may call this.
+pub fn note_language(language: *mut nsIAtom) {
+    let mut languages = LANGUAGES.lock().unwrap();
+    if !languages.iter().any(|known| known.as_ptr() == language) {
+        languages.push(unsafe { GeckoAtom::from_raw(language) });
+    }
+}
+
+/// Drops the references to the languages noted so far, for Servo_Shutdown.
+pub fn forget_languages() {
+    drop(mem::replace(&mut *LANGUAGES.lock().unwrap(), vec![]));
+}
+
+/// What the style threads of a style set's traversals look up instead of asking Gecko.
+#[derive(Clone)]
+pub struct PlatformValues {
+    /// The system colors, by SystemColor, and whether they're the stand-ins documents that
+    /// mustn't see the real ones get.
+    system_colors: Option<(bool, Vec<RGBA>)>,
+
+    /// The system fonts, by SystemFont.
+    system_fonts: Option<Vec<Option<SystemFontValues>>>,
+
+    /// The sizes font-size keywords are based on, by generic family and the address of the
+    /// language, which is zero for none. Gecko's atoms are unique, and the languages are
+    /// kept alive in LANGUAGES.
+    base_font_sizes: HashMap<(u8, usize), Au>,
+
+    /// How many of the languages the base font sizes were fetched for.
+    languages_fetched: usize,
+
+    /// The metrics of the fonts measured so far. Fonts that hadn't loaded are left out, to be
+    /// measured again the next time they're asked about.
+    font_metrics: HashMap<FontMetricsKey, FontMetrics>,
+}
+
+impl PlatformValues {
+    pub fn new() -> PlatformValues {
+        PlatformValues {
+            system_colors: None,
+            system_fonts: None,
+            base_font_sizes: HashMap::new(),
+            languages_fetched: 0,
+            font_metrics: HashMap::new(),
+        }
+    }
+
+    /// Fetches what the style threads can ask for that isn't known yet. Only the main thread
+    /// may call this, once the document state was fetched for the traversal.
+    pub fn fetch(&mut self) {
+        let use_standins = use_standin_system_colors();
+        if self.system_colors.as_ref().map_or(true, |&(standins, _)| standins != use_standins) {
+            let colors = SYSTEM_COLORS.iter().map(|&color| look_and_feel::system_color(color)).collect();
+            self.system_colors = Some((use_standins, colors));
+        }
+        if self.system_fonts.is_none() {
+            self.system_fonts = Some(SYSTEM_FONTS.iter().map(|&font| look_and_feel::system_font(font)).collect());
+        }
+        if self.base_font_sizes.is_empty() {
+            self.fetch_base_font_sizes(ptr::null_mut());
+        }
+        let languages = LANGUAGES.lock().unwrap();
+        for language in &languages[self.languages_fetched..] {
+            self.fetch_base_font_sizes(language.as_ptr());
+        }
+        self.languages_fetched = languages.len();
+    }
+
+    fn fetch_base_font_sizes(&mut self, language: *mut nsIAtom) {
+        for &generic in &GENERIC_FONT_IDS {
+            let size = unsafe { Gecko_GetBaseFontSize(generic, language) };
+            self.base_font_sizes.insert((generic, language as usize), Au::from_f32_px(size));
+        }
+    }
+
+    /// The base font size for `generic` in a language whose sizes weren't fetched: the one for
+    /// no language, which Gecko takes for the document's.
+    fn fallback_base_font_size(&self, generic: u8) -> Au {
+        self.base_font_sizes.get(&(generic, 0)).cloned().unwrap_or(Au::from_px(FONT_MEDIUM_PX))
+    }
+
+    /// Measures the fonts the style threads of the traversal that just ended found nothing
+    /// for. Returns whether any came out different from what the style threads went with
+    /// instead. Only the main thread may call this.
+    pub fn measure_fonts_requested_elsewhere(&mut self) -> bool {
+        let fonts = mem::replace(&mut *FONTS_FOR_MAIN_THREAD.lock().unwrap(), vec![]);
+        let mut changed = false;
+        for font in fonts {
+            let (generic, language) = font.base_font_size_key();
+            if !self.base_font_sizes.contains_key(&(generic, language as usize)) {
+                if !language.is_null() {
+                    note_language(language);
+                }
+                let fallback = self.fallback_base_font_size(generic);
+                let size = Au::from_f32_px(unsafe { Gecko_GetBaseFontSize(generic, language) });
+                self.base_font_sizes.insert((generic, language as usize), size);
+                changed |= size != fallback;
+            }
+            let key = font.metrics_key();
+            if !self.font_metrics.contains_key(&key) {
+                if let Some(metrics) = font.gecko_font_metrics() {
+                    self.font_metrics.insert(key, metrics);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Has the style threads look values up in `values` until `end_traversal` is called. Only the
+/// main thread may call this, before it starts the traversal.
+pub fn start_traversal(values: Arc<PlatformValues>) {
+    *TRAVERSAL_VALUES.write().unwrap() = Some(values);
+}
+
+/// Takes back the values `start_traversal` handed the style threads, once the traversal is
+/// done.
+pub fn end_traversal() {
+    *TRAVERSAL_VALUES.write().unwrap() = None;
+}
+
+/// Looks something up in the values of the running traversal.
+fn look_up<T, F>(f: F) -> Option<T> where F: FnOnce(&PlatformValues) -> Option<T> {
+    TRAVERSAL_VALUES.read().unwrap().as_ref().and_then(|values| f(values))
+}
+
+/// What look_and_feel::system_color gives `color`.
+pub fn system_color(color: SystemColor) -> RGBA {
+    if is_main_thread() {
+        return look_and_feel::system_color(color)
+    }
+    look_up(|values| values.system_colors.as_ref().map(|&(_, ref colors)| colors[color as usize]))
+        .unwrap_or_else(|| color.standin())
+}
+
+/// What look_and_feel::system_font gives `font`.
+pub fn system_font(font: SystemFont) -> Option<SystemFontValues> {
+    if is_main_thread() {
+        return look_and_feel::system_font(font)
+    }
+    look_up(|values| values.system_fonts.as_ref().and_then(|fonts| fonts[font as usize].clone()))
+}
+
+/// The size font-size keywords are based on for the generic family and language of `font`.
+pub fn base_font_size(font: &GeckoFont) -> Au {
+    let (generic, language) = font.base_font_size_key();
+    if is_main_thread() {
+        return Au::from_f32_px(unsafe { Gecko_GetBaseFontSize(generic, language) })
+    }
+    look_up(|values| match values.base_font_sizes.get(&(generic, language as usize)) {
+        Some(&size) => Some(size),
+        None => {
+            FONTS_FOR_MAIN_THREAD.lock().unwrap().push(font.clone());
+            Some(values.fallback_base_font_size(generic))
+        }
+    }).unwrap_or(Au::from_px(FONT_MEDIUM_PX))
+}
+
+/// The metrics of `font`, or None if it hasn't loaded yet.
+pub fn font_metrics(font: &GeckoFont) -> Option<FontMetrics> {
+    if is_main_thread() {
+        return font.gecko_font_metrics()
+    }
+    look_up(|values| {
+        let metrics = values.font_metrics.get(&font.metrics_key()).cloned();
+        if metrics.is_none() {
+            FONTS_FOR_MAIN_THREAD.lock().unwrap().push(font.clone());
+        }
+        metrics
+    })
+}
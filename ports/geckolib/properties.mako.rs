@@ -3,20 +3,134 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
+use atoms::{GeckoAtom, atom_from_gecko, atom_to_gecko};
+use bindings::{self, Gecko_GetFontMetrics, ServoStyleStructId};
+use cssparser::{RGBA, ToCss};
+use gecko_style_structs;
+use gecko_style_structs::nsCSSProperty;
 % for style_struct in STYLE_STRUCTS:
 %if style_struct.gecko_name:
 use gecko_style_structs::${style_struct.gecko_name};
 % endif
 % endfor
 use heapsize::HeapSizeOf;
+use image_request;
+use main_thread::MainThreadOwned;
+use platform_values;
 use std::fmt::{self, Debug};
-use std::mem::zeroed;
+use std::mem::{self, zeroed};
+use std::ptr;
 use std::sync::Arc;
 use style::custom_properties::ComputedValuesMap;
 use style::logical_geometry::WritingMode;
-use style::properties::{CascadePropertyFn, ServoComputedValues, ComputedValues};
+use style::malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use style::properties::{CascadePropertyFn, ComputedValues, PropertyDeclaration, ServoComputedValues};
 use style::properties::longhands;
+use style::properties::physical_longhand_name;
 use style::properties::style_struct_traits::*;
+use style::system_values::{SystemColor, SystemFont, SystemFontValues};
+use style::url_value::{ImageRequest, UrlExtraData};
+use style::values::computed::FontMetrics;
+use url::Url;
+use util::arc_ptr_eq;
+
+/// The nscolor for `rgba`. An nscolor has red in its lowest byte and alpha in its highest.
+pub fn convert_rgba_to_nscolor(rgba: &RGBA) -> u32 {
+    let channel = |value: f32, shift: u32| ((value.max(0.).min(1.) * 255.).round() as u32) << shift;
+    channel(rgba.red, 0) | channel(rgba.green, 8) | channel(rgba.blue, 16) | channel(rgba.alpha, 24)
+}
+
+/// The color an nscolor stands for.
+pub fn convert_nscolor_to_rgba(color: u32) -> RGBA {
+    let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.;
+    RGBA {
+        red: channel(0),
+        green: channel(8),
+        blue: channel(16),
+        alpha: channel(24),
+    }
+}
+
+<%!
+def to_rust_ident(name):
+    name = name.replace("-", "_")
+    if name in ["static", "super", "box", "move"]:  # Rust keywords
+        name += "_"
+    return name
+
+DISPLAY_VALUES = ("inline block inline-block table inline-table table-row-group table-header-group "
+                  "table-footer-group table-row table-column-group table-column table-cell "
+                  "table-caption list-item flex none inline-flex contents")
+
+OVERFLOW_VALUES = "visible hidden scroll auto"
+
+# Longhands Gecko only knows under another name.
+GECKO_PROPERTY_IDENTS = {
+    "overflow-wrap": "word_wrap",
+    "column-width": "_moz_column_width",
+    "column-count": "_moz_column_count",
+    "column-gap": "_moz_column_gap",
+    "column-rule-width": "_moz_column_rule_width",
+    "column-rule-style": "_moz_column_rule_style",
+    "column-rule-color": "_moz_column_rule_color",
+    "tab-size": "_moz_tab_size",
+    "inset-block-start": "offset_block_start",
+    "inset-block-end": "offset_block_end",
+    "inset-inline-start": "offset_inline_start",
+    "inset-inline-end": "offset_inline_end",
+}
+
+def gecko_property_ident(longhand):
+    if longhand.name.startswith("-servo-") or longhand.name in ["text-justify", "font-variation-settings",
+                                                                 "counter-set"]:
+        return None
+    return GECKO_PROPERTY_IDENTS.get(longhand.name, longhand.name.replace("-", "_"))
+
+# Shorthands Gecko only knows under another name.
+GECKO_SHORTHAND_IDENTS = {
+    "columns": "_moz_columns",
+    "column-rule": "_moz_column_rule",
+}
+
+# Longhands kept in the Rust side of a style struct, because Gecko's struct has no place for
+# their computed values yet. Gecko asks for them through Servo_GetComputed* and Servo_GetStyle*
+# instead.
+RUST_SIDE_LONGHANDS = {
+    "Margin": ["margin-top", "margin-right", "margin-bottom", "margin-left"],
+    "Padding": ["padding-top", "padding-right", "padding-bottom", "padding-left"],
+    "PositionOffsets": ["top", "right", "bottom", "left"],
+    "Box": ["content", "will-change", "contain", "scroll-snap-points-x", "scroll-snap-points-y",
+            "scroll-snap-destination", "scroll-snap-coordinate", "z-index", "-servo-display-for-hypothetical-box",
+            "clear", "width", "height", "min-width", "max-width", "min-height", "max-height", "box-sizing",
+            "touch-action", "user-select"],
+    "Animation": ["transition-property", "transition-duration", "transition-delay",
+                  "transition-timing-function"],
+    "Background": ["background-image", "background-position", "background-repeat", "background-attachment",
+                   "background-clip", "background-origin", "background-size"],
+    "Border": ["border-image-source", "border-image-slice", "border-image-width", "border-image-outset",
+               "border-image-repeat"],
+    "Effects": ["transform", "isolation", "box-shadow", "opacity", "mix-blend-mode", "backface-visibility",
+                "transform-style", "image-rendering"],
+    "InheritedText": ["letter-spacing", "word-spacing", "tab-size", "text-shadow", "text-emphasis-style",
+                      "text-emphasis-color", "text-emphasis-position", "text-overflow"],
+    "InheritedBox": ["direction", "writing-mode", "text-orientation", "visibility"],
+    "Pointing": ["pointer-events", "cursor", "fill", "stroke"],
+    "Font": ["font-family", "font-feature-settings", "font-variation-settings", "font-language-override",
+             "-servo-system-font"],
+    "Column": ["column-width", "column-count", "column-gap", "column-rule-width", "column-rule-style",
+               "column-rule-color"],
+    "Counters": ["counter-increment", "counter-reset", "counter-set"],
+    "Flex": ["flex-direction", "flex-wrap", "justify-content", "align-items", "align-content", "align-self",
+             "flex-grow", "flex-shrink", "flex-basis", "order"],
+}
+
+def gecko_shorthand_ident(shorthand):
+    # Gecko has word-wrap as an alias of the overflow-wrap longhand, and no logical shorthands
+    # for the start and end of an axis.
+    if shorthand.name == "word-wrap" or shorthand.name.endswith(("-block", "-inline")):
+        return None
+    return GECKO_SHORTHAND_IDENTS.get(shorthand.name, shorthand.name.replace("-", "_"))
+%>
 
 #[derive(Clone)]
 pub struct GeckoComputedValues {
@@ -28,6 +142,15 @@ pub struct GeckoComputedValues {
     shareable: bool,
     pub writing_mode: WritingMode,
     pub root_font_size: Au,
+
+    /// The style to use if the relevant link turns out to be visited, for elements that
+    /// are links or live inside one. Only the colors Gecko allows :visited rules to
+    /// change should be read from it.
+    pub visited_style: Option<Arc<GeckoComputedValues>>,
+
+    /// The id of the rule node the values were cascaded for, for
+    /// Servo_ComputedValues_GetRuleNode, or zero if they weren't cascaded with the rule tree.
+    pub rule_node_id: usize,
 }
 
 impl ComputedValues for GeckoComputedValues {
@@ -55,6 +178,8 @@ impl ComputedValues for GeckoComputedValues {
             % for style_struct in STYLE_STRUCTS:
             ${style_struct.ident}: ${style_struct.ident},
             % endfor
+            visited_style: None,
+            rule_node_id: 0,
         }
     }
 
@@ -82,17 +207,60 @@ impl ComputedValues for GeckoComputedValues {
     fn custom_properties(&self) -> Option<Arc<ComputedValuesMap>> { self.custom_properties.as_ref().map(|x| x.clone())}
     fn root_font_size(&self) -> Au { self.root_font_size }
     fn set_root_font_size(&mut self, s: Au) { self.root_font_size = s; }
+    fn writing_mode(&self) -> WritingMode { self.writing_mode }
     fn set_writing_mode(&mut self, mode: WritingMode) { self.writing_mode = mode; }
 
     #[inline]
-    fn is_multicol(&self) -> bool { unimplemented!() }
+    fn is_multicol(&self) -> bool {
+        let column = self.get_column();
+        column.clone_column_count().0.is_some() || column.clone_column_width().0.is_some()
+    }
+
+    fn set_rule_node_id(&mut self, id: usize) { self.rule_node_id = id; }
+
+    fn visited_style(&self) -> Option<&Arc<Self>> { self.visited_style.as_ref() }
+    fn set_visited_style(&mut self, style: Arc<Self>) { self.visited_style = Some(style); }
+
+    fn system_color(color: SystemColor) -> RGBA { platform_values::system_color(color) }
+    fn system_font(font: SystemFont) -> Option<SystemFontValues> { platform_values::system_font(font) }
+    fn medium_font_size(font: &GeckoFont) -> Au { platform_values::base_font_size(font) }
+
+    fn load_image(url: &Url, extra_data: &UrlExtraData) -> Option<Box<ImageRequest>> {
+        image_request::load_image(url, extra_data)
+    }
+}
+
+impl MallocSizeOf for GeckoComputedValues {
+    fn malloc_size_of_children(&self, ops: &mut MallocSizeOfOps) -> usize {
+        let mut size = 0;
+        % for style_struct in STYLE_STRUCTS:
+        size += self.${style_struct.ident}.malloc_size_of_children(ops);
+        % endfor
+        size + self.custom_properties.malloc_size_of_children(ops) + self.visited_style.malloc_size_of_children(ops)
+    }
+}
+
+% for style_struct in STYLE_STRUCTS:
+// What the Gecko style structs point to belongs to Gecko, which measures it itself.
+impl MallocSizeOf for Gecko${style_struct.name} {
+    fn malloc_size_of_children(&self, _: &mut MallocSizeOfOps) -> usize { 0 }
 }
+% endfor
 
 <%def name="declare_style_struct(style_struct)">
 #[derive(Clone, HeapSizeOf, Debug)]
 % if style_struct.gecko_name:
 pub struct Gecko${style_struct.name} {
-    gecko: ${style_struct.gecko_name},
+    gecko: MainThreadOwned<${style_struct.gecko_name}>,
+    % for name in RUST_SIDE_LONGHANDS.get(style_struct.name, []):
+    ${to_rust_ident(name)}: longhands::${to_rust_ident(name)}::computed_value::T,
+    % endfor
+}
+% elif style_struct.name in RUST_SIDE_LONGHANDS:
+pub struct Gecko${style_struct.name} {
+    % for name in RUST_SIDE_LONGHANDS[style_struct.name]:
+    ${to_rust_ident(name)}: longhands::${to_rust_ident(name)}::computed_value::T,
+    % endfor
 }
 % else:
 pub struct Gecko${style_struct.name};
@@ -104,8 +272,19 @@ impl Gecko${style_struct.name} {
     #[allow(dead_code, unused_variables)]
     fn initial() -> Self {
 % if style_struct.gecko_name:
-        let result = Gecko${style_struct.name} { gecko: unsafe { zeroed() } };
+        let result = Gecko${style_struct.name} {
+            gecko: MainThreadOwned::new(unsafe { zeroed() }),
+            % for name in RUST_SIDE_LONGHANDS.get(style_struct.name, []):
+            ${to_rust_ident(name)}: longhands::${to_rust_ident(name)}::get_initial_value(),
+            % endfor
+        };
         panic!("Need to invoke Gecko placement new");
+% elif style_struct.name in RUST_SIDE_LONGHANDS:
+        Gecko${style_struct.name} {
+            % for name in RUST_SIDE_LONGHANDS[style_struct.name]:
+            ${to_rust_ident(name)}: longhands::${to_rust_ident(name)}::get_initial_value(),
+            % endfor
+        }
 % else:
         Gecko${style_struct.name}
 % endif
@@ -174,27 +353,734 @@ ${caller.body()}
 <% MANUAL_STYLE_STRUCTS.append(style_struct_name) %>
 </%def>
 
-// Proof-of-concept for a style struct with some manually-implemented methods. We add
-// the manually-implemented methods to skip_longhands and skip_additionals, and the
-// infrastructure auto-generates everything not in those lists. This allows us to
-// iteratively implement more and more methods.
+
+<%! SERIALIZABLE_LONGHANDS = [] %>
+<%def name="impl_keyword(ident, gecko_ffi_name, values, gecko_constant_prefix)">
+<% keyword_values = values.split() %>
+<% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        use style::properties::longhands::${ident}::computed_value::T as Keyword;
+        // FIXME(bholley): Align binary representations and ditch |match| for cast + static_asserts
+        self.gecko.${gecko_ffi_name} = match v {
+            % for value in keyword_values:
+            Keyword::${to_rust_ident(value)} =>
+                gecko_style_structs::${gecko_constant_prefix}_${value.upper().replace("-", "_")} as u8,
+            % endfor
+        };
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        self.gecko.${gecko_ffi_name} = other.gecko.${gecko_ffi_name};
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        use style::properties::longhands::${ident}::computed_value::T as Keyword;
+        match self.gecko.${gecko_ffi_name} as u32 {
+            % for value in keyword_values:
+            gecko_style_structs::${gecko_constant_prefix}_${value.upper().replace("-", "_")} =>
+                Keyword::${to_rust_ident(value)},
+            % endfor
+            x => panic!("Found unexpected value in style struct for ${ident} property: {}", x),
+        }
+    }
+</%def>
+
+<%def name="impl_rust_side_longhands(style_struct_name, skip=[])">
+    % for ident in [to_rust_ident(name) for name in RUST_SIDE_LONGHANDS[style_struct_name] if name not in skip]:
+    <% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        self.${ident} = v;
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        self.${ident} = other.${ident}.clone();
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        self.${ident}.clone()
+    }
+    % endfor
+</%def>
+
+// Gecko keeps the border and outline styles in the low bits of a byte, and flags a currentColor
+// color in its high ones, so neither goes through impl_keyword.
+<% BORDER_STYLE_VALUES = "none hidden solid double dotted dashed groove ridge inset outset" %>
+<%def name="impl_border_style(ident, gecko_ffi_name)">
+<% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        use style::values::specified::BorderStyle;
+        let style = match v {
+            % for value in BORDER_STYLE_VALUES.split():
+            BorderStyle::${value} => gecko_style_structs::NS_STYLE_BORDER_STYLE_${value.upper()},
+            % endfor
+        };
+        let mask = gecko_style_structs::BORDER_STYLE_MASK;
+        self.gecko.${gecko_ffi_name} = ((self.gecko.${gecko_ffi_name} as u32 & !mask) | style) as u8;
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        let mask = gecko_style_structs::BORDER_STYLE_MASK as u8;
+        self.gecko.${gecko_ffi_name} &= !mask;
+        self.gecko.${gecko_ffi_name} |= other.gecko.${gecko_ffi_name} & mask;
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        use style::values::specified::BorderStyle;
+        match self.gecko.${gecko_ffi_name} as u32 & gecko_style_structs::BORDER_STYLE_MASK {
+            % for value in BORDER_STYLE_VALUES.split():
+            gecko_style_structs::NS_STYLE_BORDER_STYLE_${value.upper()} => BorderStyle::${value},
+            % endfor
+            x => panic!("Found unexpected value in style struct for ${ident} property: {}", x),
+        }
+    }
+</%def>
+
+<%def name="impl_flagged_color(ident, gecko_ffi_name, gecko_flags_ffi_name, flag)">
+<% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        use cssparser::Color;
+        let flag = gecko_style_structs::${flag} as u8;
+        match v {
+            Color::RGBA(ref rgba) => {
+                self.gecko.${gecko_ffi_name} = convert_rgba_to_nscolor(rgba);
+                self.gecko.${gecko_flags_ffi_name} &= !flag;
+            }
+            Color::CurrentColor => {
+                self.gecko.${gecko_ffi_name} = 0;
+                self.gecko.${gecko_flags_ffi_name} |= flag;
+            }
+        }
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        let flag = gecko_style_structs::${flag} as u8;
+        self.gecko.${gecko_ffi_name} = other.gecko.${gecko_ffi_name};
+        self.gecko.${gecko_flags_ffi_name} &= !flag;
+        self.gecko.${gecko_flags_ffi_name} |= other.gecko.${gecko_flags_ffi_name} & flag;
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        use cssparser::Color;
+        if self.gecko.${gecko_flags_ffi_name} as u32 & gecko_style_structs::${flag} != 0 {
+            Color::CurrentColor
+        } else {
+            Color::RGBA(convert_nscolor_to_rgba(self.gecko.${gecko_ffi_name}))
+        }
+    }
+</%def>
+
+<%self:impl_trait style_struct_name="Color"
+                  skip_longhands="${['color']}"
+                  skip_additionals="${['clone_color']}">
+<% SERIALIZABLE_LONGHANDS.append("color") %>
+    fn set_color(&mut self, v: longhands::color::computed_value::T) {
+        self.gecko.mColor = convert_rgba_to_nscolor(&v);
+    }
+    fn copy_color_from(&mut self, other: &Self) {
+        self.gecko.mColor = other.gecko.mColor;
+    }
+    fn clone_color(&self) -> longhands::color::computed_value::T {
+        convert_nscolor_to_rgba(self.gecko.mColor)
+    }
+</%self:impl_trait>
+
+<%self:impl_trait style_struct_name="Background"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Background']}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Background']]}">
+    ${impl_rust_side_longhands('Background')}
+</%self:impl_trait>
+
+<% BORDER_SIDES = ["top", "right", "bottom", "left"] %>
+<% BORDER_SIDE_LONGHANDS = ["border-%s-%s" % (side, prop) for side in BORDER_SIDES
+                            for prop in ["color", "style", "width"]] %>
+<% BORDER_SKIPPED_ADDITIONALS = ["border_%s_is_none_or_hidden_and_has_nonzero_width" % side
+                                 for side in BORDER_SIDES] + \
+                                ["clone_" + to_rust_ident(name)
+                                 for name in BORDER_SIDE_LONGHANDS + RUST_SIDE_LONGHANDS["Border"]] %>
 <%self:impl_trait style_struct_name="Border"
-                  skip_longhands="${['border-left-color', 'border-left-style']}"
-                  skip_additionals="${['border_bottom_is_none_or_hidden_and_has_nonzero_width']}">
-    fn set_border_left_color(&mut self, _: longhands::border_left_color::computed_value::T) {
-        unimplemented!()
+                  skip_longhands="${BORDER_SIDE_LONGHANDS + RUST_SIDE_LONGHANDS['Border']}"
+                  skip_additionals="${BORDER_SKIPPED_ADDITIONALS}">
+    % for i, side in enumerate(BORDER_SIDES):
+    ${impl_border_style("border_%s_style" % side, "mBorderStyle[%s]" % i)}
+    ${impl_flagged_color("border_%s_color" % side, "mBorderColor[%s]" % i, "mBorderStyle[%s]" % i,
+                         "BORDER_COLOR_FOREGROUND")}
+    // The cascade has already made the width zero if the style is none or hidden, so Gecko's
+    // specified and computed widths are the same.
+    <% SERIALIZABLE_LONGHANDS.append("border_%s_width" % side) %>
+    fn set_border_${side}_width(&mut self, v: longhands::border_${side}_width::computed_value::T) {
+        self.gecko.mBorder.${side} = v.0;
+        self.gecko.mComputedBorder.${side} = v.0;
     }
-    fn copy_border_left_color_from(&mut self, _: &Self) {
-        unimplemented!()
+    fn copy_border_${side}_width_from(&mut self, other: &Self) {
+        self.gecko.mBorder.${side} = other.gecko.mBorder.${side};
+        self.gecko.mComputedBorder.${side} = other.gecko.mComputedBorder.${side};
     }
-    fn set_border_left_style(&mut self, _: longhands::border_left_style::computed_value::T) {
-        unimplemented!()
+    fn clone_border_${side}_width(&self) -> longhands::border_${side}_width::computed_value::T {
+        Au(self.gecko.mComputedBorder.${side})
     }
-    fn copy_border_left_style_from(&mut self, _: &Self) {
-        unimplemented!()
+    fn border_${side}_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
+        self.clone_border_${side}_style().none_or_hidden() && self.gecko.mComputedBorder.${side} != 0
     }
-    fn border_bottom_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
-        unimplemented!()
+    % endfor
+    ${impl_rust_side_longhands('Border')}
+</%self:impl_trait>
+
+<% OUTLINE_LONGHANDS = ["outline-color", "outline-style", "outline-width", "outline-offset"] %>
+<% OUTLINE_SKIPPED_ADDITIONALS = ["outline_is_none_or_hidden_and_has_nonzero_width"] + \
+                                 ["clone_" + to_rust_ident(name) for name in OUTLINE_LONGHANDS] %>
+<%self:impl_trait style_struct_name="Outline"
+                  skip_longhands="${OUTLINE_LONGHANDS}"
+                  skip_additionals="${OUTLINE_SKIPPED_ADDITIONALS}">
+    ${impl_border_style("outline_style", "mOutlineStyle")}
+    // Gecko's initial outline color is currentColor, which `invert` computes to as well.
+    ${impl_flagged_color("outline_color", "mOutlineColor", "mOutlineStyle", "OUTLINE_COLOR_INITIAL")}
+    // Like the border widths, the outline width has been made zero for a none style already,
+    // so it's handed to Gecko as its cached computed width too.
+    <% SERIALIZABLE_LONGHANDS.append("outline_width") %>
+    fn set_outline_width(&mut self, v: longhands::outline_width::computed_value::T) {
+        self.gecko.mOutlineWidth.mUnit = gecko_style_structs::nsStyleUnit::eStyleUnit_Coord;
+        // An nscoord lives in the union's mInt, at its start.
+        unsafe { *(&mut self.gecko.mOutlineWidth.mValue as *mut _ as *mut i32) = v.0 };
+        self.gecko.mCachedOutlineWidth = v.0;
+        self.gecko.mHasCachedOutline = true;
+    }
+    fn copy_outline_width_from(&mut self, other: &Self) {
+        self.gecko.mOutlineWidth.mUnit = other.gecko.mOutlineWidth.mUnit;
+        self.gecko.mOutlineWidth.mValue = other.gecko.mOutlineWidth.mValue;
+        self.gecko.mCachedOutlineWidth = other.gecko.mCachedOutlineWidth;
+        self.gecko.mHasCachedOutline = other.gecko.mHasCachedOutline;
+    }
+    fn clone_outline_width(&self) -> longhands::outline_width::computed_value::T {
+        Au(self.gecko.mCachedOutlineWidth)
+    }
+    <% SERIALIZABLE_LONGHANDS.append("outline_offset") %>
+    fn set_outline_offset(&mut self, v: longhands::outline_offset::computed_value::T) {
+        self.gecko.mOutlineOffset = v.0;
+    }
+    fn copy_outline_offset_from(&mut self, other: &Self) {
+        self.gecko.mOutlineOffset = other.gecko.mOutlineOffset;
+    }
+    fn clone_outline_offset(&self) -> longhands::outline_offset::computed_value::T {
+        Au(self.gecko.mOutlineOffset)
+    }
+    fn outline_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
+        self.clone_outline_style().none_or_hidden() && self.gecko.mCachedOutlineWidth != 0
+    }
+</%self:impl_trait>
+
+% for name in ["Margin", "Padding"]:
+<%self:impl_trait style_struct_name="${name}"
+                  skip_longhands="${RUST_SIDE_LONGHANDS[name]}"
+                  skip_additionals="${['clone_' + to_rust_ident(longhand) for longhand in RUST_SIDE_LONGHANDS[name]]}">
+    ${impl_rust_side_longhands(name)}
+</%self:impl_trait>
+% endfor
+
+<%self:impl_trait style_struct_name="PositionOffsets"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['PositionOffsets']}"
+                  skip_additionals="${['clone_' + name for name in RUST_SIDE_LONGHANDS['PositionOffsets']]}">
+    ${impl_rust_side_longhands('PositionOffsets')}
+</%self:impl_trait>
+
+<% BOX_KEYWORD_LONGHANDS = ['display', 'position', 'float', 'overflow-x', 'overflow-y', 'resize',
+                             'scroll-snap-type-x', 'scroll-snap-type-y'] %>
+<% BOX_SKIPPED_ADDITIONALS = ['is_floated', 'content_reads_attributes', 'overflow_x_is_visible',
+                              'overflow_y_is_visible'] + \
+                             ['clone_' + to_rust_ident(name)
+                              for name in BOX_KEYWORD_LONGHANDS + RUST_SIDE_LONGHANDS["Box"]] %>
+<%self:impl_trait style_struct_name="Box"
+                  skip_longhands="${BOX_KEYWORD_LONGHANDS + RUST_SIDE_LONGHANDS['Box']}"
+                  skip_additionals="${BOX_SKIPPED_ADDITIONALS}">
+    ${impl_keyword('display', 'mDisplay', DISPLAY_VALUES, 'NS_STYLE_DISPLAY')}
+    ${impl_keyword('position', 'mPosition', 'static absolute relative fixed', 'NS_STYLE_POSITION')}
+    ${impl_keyword('float', 'mFloats', 'none left right', 'NS_STYLE_FLOAT')}
+    ${impl_keyword('overflow_x', 'mOverflowX', OVERFLOW_VALUES, 'NS_STYLE_OVERFLOW')}
+    ${impl_keyword('resize', 'mResize', 'none both horizontal vertical', 'NS_STYLE_RESIZE')}
+    % for axis in ["x", "y"]:
+    ${impl_keyword('scroll_snap_type_' + axis, 'mScrollSnapType' + axis.upper(), 'none mandatory proximity',
+                   'NS_STYLE_SCROLL_SNAP_TYPE')}
+    % endfor
+    // overflow-y's computed value wraps overflow-x's, so it can't go through impl_keyword.
+    <% SERIALIZABLE_LONGHANDS.append('overflow_y') %>
+    fn set_overflow_y(&mut self, v: longhands::overflow_y::computed_value::T) {
+        use style::properties::longhands::overflow_x::computed_value::T as Keyword;
+        self.gecko.mOverflowY = match v.0 {
+            % for value in OVERFLOW_VALUES.split():
+            Keyword::${value} => gecko_style_structs::NS_STYLE_OVERFLOW_${value.upper()} as u8,
+            % endfor
+        };
+    }
+    fn copy_overflow_y_from(&mut self, other: &Self) {
+        self.gecko.mOverflowY = other.gecko.mOverflowY;
+    }
+    fn clone_overflow_y(&self) -> longhands::overflow_y::computed_value::T {
+        use style::properties::longhands::overflow_x::computed_value::T as Keyword;
+        longhands::overflow_y::computed_value::T(match self.gecko.mOverflowY as u32 {
+            % for value in OVERFLOW_VALUES.split():
+            gecko_style_structs::NS_STYLE_OVERFLOW_${value.upper()} => Keyword::${value},
+            % endfor
+            x => panic!("Found unexpected value in style struct for overflow_y property: {}", x),
+        })
+    }
+    fn overflow_x_is_visible(&self) -> bool {
+        self.gecko.mOverflowX == gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8
+    }
+    fn overflow_y_is_visible(&self) -> bool {
+        self.gecko.mOverflowY == gecko_style_structs::NS_STYLE_OVERFLOW_VISIBLE as u8
+    }
+    ${impl_rust_side_longhands('Box')}
+    fn content_reads_attributes(&self) -> bool {
+        !self.content.attribute_names().is_empty()
+    }
+    fn is_floated(&self) -> bool {
+        self.gecko.mFloats != gecko_style_structs::NS_STYLE_FLOAT_NONE as u8
+    }
+</%self:impl_trait>
+
+impl GeckoBox {
+    /// Whether the two structs agree on every longhand but those named in |except|. Only
+    /// the longhands the struct can hold so far are compared; setting any other panics.
+    pub fn same_values_except(&self, other: &Self, except: &[&str]) -> bool {
+        % for name in BOX_KEYWORD_LONGHANDS + RUST_SIDE_LONGHANDS["Box"]:
+        (except.contains(&"${name}") || self.clone_${to_rust_ident(name)}() == other.clone_${to_rust_ident(name)}()) &&
+        % endfor
+        true
+    }
+}
+
+// FIXME: Back InheritedBox by nsStyleVisibility, which is where Gecko's frames look for the
+// direction and writing mode. Until then Gecko asks for them through Servo_GetComputed* and
+// Servo_GetStyleVisibility.
+<% INHERITED_BOX_SKIPPED_ADDITIONALS = ['clone_' + to_rust_ident(name)
+                                      for name in RUST_SIDE_LONGHANDS['InheritedBox']] %>
+<%self:impl_trait style_struct_name="InheritedBox"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['InheritedBox']}"
+                  skip_additionals="${INHERITED_BOX_SKIPPED_ADDITIONALS}">
+    ${impl_rust_side_longhands('InheritedBox')}
+</%self:impl_trait>
+
+<% INHERITED_TEXT_KEYWORD_LONGHANDS = ['white-space', 'text-transform', 'word-break', 'overflow-wrap'] %>
+<% TEXT_ALIGN_VALUES = [("start", "DEFAULT"), ("end", "END"), ("left", "LEFT"), ("right", "RIGHT"),
+                        ("center", "CENTER"), ("justify", "JUSTIFY"), ("servo-center", "MOZ_CENTER"),
+                        ("servo-left", "MOZ_LEFT"), ("servo-right", "MOZ_RIGHT")] %>
+<% INHERITED_TEXT_LONGHANDS = INHERITED_TEXT_KEYWORD_LONGHANDS + ['text-align'] + \
+                               RUST_SIDE_LONGHANDS['InheritedText'] %>
+<%self:impl_trait style_struct_name="InheritedText"
+                  skip_longhands="${INHERITED_TEXT_LONGHANDS}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in INHERITED_TEXT_LONGHANDS]}">
+    ${impl_keyword('white_space', 'mWhiteSpace', 'normal pre nowrap pre-wrap pre-line', 'NS_STYLE_WHITESPACE')}
+    ${impl_keyword('text_transform', 'mTextTransform', 'none capitalize uppercase lowercase',
+                   'NS_STYLE_TEXT_TRANSFORM')}
+    ${impl_keyword('word_break', 'mWordBreak', 'normal break-all', 'NS_STYLE_WORDBREAK')}
+    ${impl_keyword('overflow_wrap', 'mWordWrap', 'normal break-word', 'NS_STYLE_WORDWRAP')}
+    // The -servo- values are Gecko's -moz- ones, so text-align can't go through impl_keyword.
+    <% SERIALIZABLE_LONGHANDS.append('text_align') %>
+    fn set_text_align(&mut self, v: longhands::text_align::computed_value::T) {
+        use style::properties::longhands::text_align::computed_value::T as Keyword;
+        self.gecko.mTextAlign = match v {
+            % for value, gecko_value in TEXT_ALIGN_VALUES:
+            Keyword::${to_rust_ident(value)} => gecko_style_structs::NS_STYLE_TEXT_ALIGN_${gecko_value} as u8,
+            % endfor
+        };
+    }
+    fn copy_text_align_from(&mut self, other: &Self) {
+        self.gecko.mTextAlign = other.gecko.mTextAlign;
+    }
+    fn clone_text_align(&self) -> longhands::text_align::computed_value::T {
+        use style::properties::longhands::text_align::computed_value::T as Keyword;
+        match self.gecko.mTextAlign as u32 {
+            % for value, gecko_value in TEXT_ALIGN_VALUES:
+            gecko_style_structs::NS_STYLE_TEXT_ALIGN_${gecko_value} => Keyword::${to_rust_ident(value)},
+            % endfor
+            x => panic!("Found unexpected value in style struct for text_align property: {}", x),
+        }
+    }
+    ${impl_rust_side_longhands('InheritedText')}
+</%self:impl_trait>
+
+<% TEXT_DECORATION_LINES = ["underline", "overline", "line-through"] %>
+<% TEXT_DECORATION_LONGHANDS = ["text-decoration-line", "text-decoration-style", "text-decoration-color"] %>
+<% TEXT_DECORATION_STYLE_VALUES = "solid double dotted dashed wavy" %>
+<% UNICODE_BIDI_VALUES = [("normal", "NORMAL"), ("embed", "EMBED"), ("isolate", "ISOLATE"),
+                           ("bidi-override", "OVERRIDE"), ("isolate-override", "ISOLATE_OVERRIDE"),
+                           ("plaintext", "PLAINTEXT")] %>
+<% TEXT_SKIPPED_ADDITIONALS = ['has_' + to_rust_ident(line) for line in TEXT_DECORATION_LINES] + \
+                              ['clone_' + to_rust_ident(name) for name in TEXT_DECORATION_LONGHANDS] + \
+                              ['clone_unicode_bidi'] %>
+<%self:impl_trait style_struct_name="Text"
+                  skip_longhands="${TEXT_DECORATION_LONGHANDS + ['unicode-bidi']}"
+                  skip_additionals="${TEXT_SKIPPED_ADDITIONALS}">
+    // Gecko calls bidi-override plain OVERRIDE, so unicode-bidi can't go through impl_keyword.
+    <% SERIALIZABLE_LONGHANDS.append('unicode_bidi') %>
+    fn set_unicode_bidi(&mut self, v: longhands::unicode_bidi::computed_value::T) {
+        use style::properties::longhands::unicode_bidi::computed_value::T as Keyword;
+        self.gecko.mUnicodeBidi = match v {
+            % for value, gecko_value in UNICODE_BIDI_VALUES:
+            Keyword::${to_rust_ident(value)} => gecko_style_structs::NS_STYLE_UNICODE_BIDI_${gecko_value} as u8,
+            % endfor
+        };
+    }
+    fn copy_unicode_bidi_from(&mut self, other: &Self) {
+        self.gecko.mUnicodeBidi = other.gecko.mUnicodeBidi;
+    }
+    fn clone_unicode_bidi(&self) -> longhands::unicode_bidi::computed_value::T {
+        use style::properties::longhands::unicode_bidi::computed_value::T as Keyword;
+        match self.gecko.mUnicodeBidi as u32 {
+            % for value, gecko_value in UNICODE_BIDI_VALUES:
+            gecko_style_structs::NS_STYLE_UNICODE_BIDI_${gecko_value} => Keyword::${to_rust_ident(value)},
+            % endfor
+            x => panic!("Found unexpected value in style struct for unicode_bidi property: {}", x),
+        }
+    }
+    <% SERIALIZABLE_LONGHANDS.append('text_decoration_line') %>
+    fn set_text_decoration_line(&mut self, v: longhands::text_decoration_line::computed_value::T) {
+        let mut bits = 0;
+        % for line in TEXT_DECORATION_LINES:
+        if v.${to_rust_ident(line)} {
+            bits |= gecko_style_structs::NS_STYLE_TEXT_DECORATION_LINE_${line.upper().replace("-", "_")};
+        }
+        % endfor
+        self.gecko.mTextDecorationLine = bits as u8;
+    }
+    fn copy_text_decoration_line_from(&mut self, other: &Self) {
+        self.gecko.mTextDecorationLine = other.gecko.mTextDecorationLine;
+    }
+    fn clone_text_decoration_line(&self) -> longhands::text_decoration_line::computed_value::T {
+        longhands::text_decoration_line::computed_value::T {
+            % for line in TEXT_DECORATION_LINES:
+            ${to_rust_ident(line)}: self.has_${to_rust_ident(line)}(),
+            % endfor
+        }
+    }
+    % for line in TEXT_DECORATION_LINES:
+    fn has_${to_rust_ident(line)}(&self) -> bool {
+        let bit = gecko_style_structs::NS_STYLE_TEXT_DECORATION_LINE_${line.upper().replace("-", "_")};
+        self.gecko.mTextDecorationLine as u32 & bit != 0
+    }
+    % endfor
+    // Gecko keeps the style in the low bits of mTextDecorationStyle, and flags a currentColor
+    // text-decoration-color in its high ones, so neither can go through impl_keyword.
+    <% SERIALIZABLE_LONGHANDS.append('text_decoration_style') %>
+    fn set_text_decoration_style(&mut self, v: longhands::text_decoration_style::computed_value::T) {
+        use style::properties::longhands::text_decoration_style::computed_value::T as Keyword;
+        let style = match v {
+            % for value in TEXT_DECORATION_STYLE_VALUES.split():
+            Keyword::${value} => gecko_style_structs::NS_STYLE_TEXT_DECORATION_STYLE_${value.upper()},
+            % endfor
+        };
+        let mask = gecko_style_structs::BORDER_STYLE_MASK;
+        self.gecko.mTextDecorationStyle = ((self.gecko.mTextDecorationStyle as u32 & !mask) | style) as u8;
+    }
+    fn copy_text_decoration_style_from(&mut self, other: &Self) {
+        let mask = gecko_style_structs::BORDER_STYLE_MASK as u8;
+        self.gecko.mTextDecorationStyle = (self.gecko.mTextDecorationStyle & !mask) |
+                                          (other.gecko.mTextDecorationStyle & mask);
+    }
+    fn clone_text_decoration_style(&self) -> longhands::text_decoration_style::computed_value::T {
+        use style::properties::longhands::text_decoration_style::computed_value::T as Keyword;
+        match self.gecko.mTextDecorationStyle as u32 & gecko_style_structs::BORDER_STYLE_MASK {
+            % for value in TEXT_DECORATION_STYLE_VALUES.split():
+            gecko_style_structs::NS_STYLE_TEXT_DECORATION_STYLE_${value.upper()} => Keyword::${value},
+            % endfor
+            x => panic!("Found unexpected value in style struct for text_decoration_style property: {}", x),
+        }
+    }
+    <% SERIALIZABLE_LONGHANDS.append('text_decoration_color') %>
+    fn set_text_decoration_color(&mut self, v: longhands::text_decoration_color::computed_value::T) {
+        use cssparser::Color;
+        let foreground = gecko_style_structs::BORDER_COLOR_FOREGROUND as u8;
+        match v {
+            Color::RGBA(ref rgba) => {
+                self.gecko.mTextDecorationColor = convert_rgba_to_nscolor(rgba);
+                self.gecko.mTextDecorationStyle &= !foreground;
+            }
+            Color::CurrentColor => {
+                self.gecko.mTextDecorationColor = 0;
+                self.gecko.mTextDecorationStyle |= foreground;
+            }
+        }
+    }
+    fn copy_text_decoration_color_from(&mut self, other: &Self) {
+        let foreground = gecko_style_structs::BORDER_COLOR_FOREGROUND as u8;
+        self.gecko.mTextDecorationColor = other.gecko.mTextDecorationColor;
+        self.gecko.mTextDecorationStyle = (self.gecko.mTextDecorationStyle & !foreground) |
+                                          (other.gecko.mTextDecorationStyle & foreground);
+    }
+    fn clone_text_decoration_color(&self) -> longhands::text_decoration_color::computed_value::T {
+        use cssparser::Color;
+        if self.gecko.mTextDecorationStyle as u32 & gecko_style_structs::BORDER_COLOR_FOREGROUND != 0 {
+            Color::CurrentColor
+        } else {
+            Color::RGBA(convert_nscolor_to_rgba(self.gecko.mTextDecorationColor))
+        }
+    }
+</%self:impl_trait>
+
+// The font keywords are stored as gfxFontConstants.h's NS_FONT_* values, which the generated
+// structs don't have, so they're spelled out here.
+<%def name="impl_font_keyword(ident, gecko_ffi_name, gecko_values)">
+<% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        use style::properties::longhands::${ident}::computed_value::T as Keyword;
+        self.gecko.mFont.${gecko_ffi_name} = match v {
+            % for value, gecko_value in gecko_values:
+            Keyword::${to_rust_ident(value)} => ${gecko_value},
+            % endfor
+        };
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        self.gecko.mFont.${gecko_ffi_name} = other.gecko.mFont.${gecko_ffi_name};
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        use style::properties::longhands::${ident}::computed_value::T as Keyword;
+        match self.gecko.mFont.${gecko_ffi_name} {
+            % for value, gecko_value in gecko_values:
+            ${gecko_value} => Keyword::${to_rust_ident(value)},
+            % endfor
+            x => panic!("Found unexpected value in style struct for ${ident} property: {}", x),
+        }
+    }
+</%def>
+
+// nsStyleFont's mGenericID, which picks the default font and the base size for keywords.
+const GENERIC_ID_MOZ_VARIABLE: u8 = 0;
+const GENERIC_ID_SERIF: u8 = 2;
+const GENERIC_ID_SANS_SERIF: u8 = 4;
+const GENERIC_ID_MONOSPACE: u8 = 8;
+const GENERIC_ID_CURSIVE: u8 = 16;
+const GENERIC_ID_FANTASY: u8 = 32;
+
+/// Every generic family mGenericID can be.
+pub const GENERIC_FONT_IDS: [u8; 6] = [GENERIC_ID_MOZ_VARIABLE, GENERIC_ID_SERIF, GENERIC_ID_SANS_SERIF,
+                                       GENERIC_ID_MONOSPACE, GENERIC_ID_CURSIVE, GENERIC_ID_FANTASY];
+
+/// The generic family Gecko falls back to for `families`: the first generic in the list,
+/// or the user's default font if there's none.
+fn generic_font_id(families: &longhands::font_family::computed_value::T) -> u8 {
+    use style::properties::longhands::font_family::computed_value::FontFamily;
+    for family in &families.0 {
+        match *family {
+            FontFamily::FamilyName(_) => {}
+            FontFamily::Serif => return GENERIC_ID_SERIF,
+            FontFamily::SansSerif => return GENERIC_ID_SANS_SERIF,
+            FontFamily::Monospace => return GENERIC_ID_MONOSPACE,
+            FontFamily::Cursive => return GENERIC_ID_CURSIVE,
+            FontFamily::Fantasy => return GENERIC_ID_FANTASY,
+        }
+    }
+    GENERIC_ID_MOZ_VARIABLE
+}
+
+impl GeckoFont {
+    /// Makes `language` the struct's language, handing its reference to mLanguage, which
+    /// Gecko drops along with the struct.
+    fn set_language(&mut self, language: Option<GeckoAtom>) {
+        let language: *mut gecko_style_structs::nsIAtom =
+            language.map_or(ptr::null_mut(), |language| language.into_raw() as *mut _);
+        let old = mem::replace(&mut self.gecko.mLanguage.mRawPtr, language);
+        if !old.is_null() {
+            unsafe { drop(GeckoAtom::from_addrefed(old as *mut bindings::nsIAtom)) };
+        }
+    }
+
+    fn language(&self) -> Option<GeckoAtom> {
+        let language = self.gecko.mLanguage.mRawPtr as *mut bindings::nsIAtom;
+        if language.is_null() {
+            None
+        } else {
+            Some(unsafe { GeckoAtom::from_raw(language) })
+        }
+    }
+
+    /// The generic family and the language Gecko_GetBaseFontSize gives the base size of
+    /// font-size keywords for.
+    pub fn base_font_size_key(&self) -> (u8, *mut bindings::nsIAtom) {
+        (self.gecko.mGenericID, self.gecko.mLanguage.mRawPtr as *mut bindings::nsIAtom)
+    }
+
+    /// What Gecko_GetFontMetrics finds the font by.
+    pub fn metrics_key(&self) -> FontMetricsKey {
+        FontMetricsKey {
+            families: self.font_family.clone(),
+            style: self.gecko.mFont.style,
+            weight: self.gecko.mFont.weight,
+            stretch: self.gecko.mFont.stretch,
+            size: self.gecko.mFont.size,
+            language: self.gecko.mLanguage.mRawPtr as usize,
+        }
+    }
+
+    /// Has Gecko measure the font. Only the main thread may call this.
+    pub fn gecko_font_metrics(&self) -> Option<FontMetrics> {
+        let mut x_height = 0.0;
+        let mut zero_advance = 0.0;
+        let found = unsafe {
+            Gecko_GetFontMetrics(&*self.gecko as *const _ as *const bindings::nsStyleFont,
+                                 &mut x_height, &mut zero_advance)
+        };
+        if found {
+            Some(FontMetrics {
+                x_height: Au::from_f32_px(x_height),
+                zero_advance_measure: Au::from_f32_px(zero_advance),
+            })
+        } else {
+            // The font hasn't loaded yet.
+            None
+        }
+    }
+}
+
+/// The parts of a font struct Gecko looks the font up by when it measures it. Gecko's atoms
+/// are unique, so the language goes by address.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FontMetricsKey {
+    families: longhands::font_family::computed_value::T,
+    style: u8,
+    weight: u16,
+    stretch: i16,
+    size: i32,
+    language: usize,
+}
+
+<% FONT_LONGHANDS = [x.name for x in next(s for s in STYLE_STRUCTS if s.name == "Font").longhands] %>
+<% FONT_CLONES = ["clone_" + to_rust_ident(name) for name in FONT_LONGHANDS] %>
+<%self:impl_trait style_struct_name="Font"
+                  skip_longhands="${FONT_LONGHANDS}"
+                  skip_additionals="${['font_metrics', 'compute_font_hash'] + FONT_CLONES}">
+    ${impl_rust_side_longhands('Font', skip=['font-family'])}
+
+    <% SERIALIZABLE_LONGHANDS.append("font_family") %>
+    fn set_font_family(&mut self, v: longhands::font_family::computed_value::T) {
+        self.gecko.mGenericID = generic_font_id(&v);
+        self.font_family = v;
+    }
+    fn copy_font_family_from(&mut self, other: &Self) {
+        self.gecko.mGenericID = other.gecko.mGenericID;
+        self.font_family = other.font_family.clone();
+    }
+    fn clone_font_family(&self) -> longhands::font_family::computed_value::T {
+        self.font_family.clone()
+    }
+
+    ${impl_font_keyword("font_style", "style", [("normal", 0), ("italic", 1), ("oblique", 2)])}
+    ${impl_font_keyword("font_variant", "variantCaps", [("normal", 0), ("small-caps", 1)])}
+    ${impl_font_keyword("font_stretch", "stretch",
+                        [("ultra-condensed", -4), ("extra-condensed", -3), ("condensed", -2),
+                         ("semi-condensed", -1), ("normal", 0), ("semi-expanded", 1), ("expanded", 2),
+                         ("extra-expanded", 3), ("ultra-expanded", 4)])}
+
+    <% SERIALIZABLE_LONGHANDS.append("font_weight") %>
+    fn set_font_weight(&mut self, v: longhands::font_weight::computed_value::T) {
+        self.gecko.mFont.weight = v as u16;
+    }
+    fn copy_font_weight_from(&mut self, other: &Self) {
+        self.gecko.mFont.weight = other.gecko.mFont.weight;
+    }
+    fn clone_font_weight(&self) -> longhands::font_weight::computed_value::T {
+        use style::properties::longhands::font_weight::computed_value::T;
+        match self.gecko.mFont.weight {
+            % for weight in range(100, 901, 100):
+            ${weight} => T::Weight${weight},
+            % endfor
+            x => panic!("Found unexpected value in style struct for font_weight property: {}", x),
+        }
+    }
+
+    // Gecko keeps the size twice: mSize is what font-size computed to, and mFont.size what
+    // gets used once the minimum font size and text zoom are applied, which we don't do yet.
+    <% SERIALIZABLE_LONGHANDS.append("font_size") %>
+    fn set_font_size(&mut self, v: longhands::font_size::computed_value::T) {
+        self.gecko.mSize = v.0;
+        self.gecko.mFont.size = v.0;
+    }
+    fn copy_font_size_from(&mut self, other: &Self) {
+        self.gecko.mSize = other.gecko.mSize;
+        self.gecko.mFont.size = other.gecko.mFont.size;
+    }
+    fn clone_font_size(&self) -> longhands::font_size::computed_value::T {
+        Au(self.gecko.mSize)
+    }
+
+    <% SERIALIZABLE_LONGHANDS.append("font_size_adjust") %>
+    fn set_font_size_adjust(&mut self, v: longhands::font_size_adjust::computed_value::T) {
+        // Gecko has a negative sizeAdjust for none.
+        self.gecko.mFont.sizeAdjust = v.0.unwrap_or(-1.0);
+    }
+    fn copy_font_size_adjust_from(&mut self, other: &Self) {
+        self.gecko.mFont.sizeAdjust = other.gecko.mFont.sizeAdjust;
+    }
+    fn clone_font_size_adjust(&self) -> longhands::font_size_adjust::computed_value::T {
+        use style::properties::longhands::font_size_adjust::computed_value::T;
+        T(if self.gecko.mFont.sizeAdjust < 0.0 { None } else { Some(self.gecko.mFont.sizeAdjust) })
+    }
+
+    <% SERIALIZABLE_LONGHANDS.append("_x_lang") %>
+    fn set__x_lang(&mut self, v: longhands::_x_lang::computed_value::T) {
+        self.gecko.mExplicitLanguage = v.0.is_some();
+        self.set_language(v.0.map(|language| unsafe { GeckoAtom::from_raw(atom_to_gecko(&language)) }));
+    }
+    fn copy__x_lang_from(&mut self, other: &Self) {
+        self.gecko.mExplicitLanguage = other.gecko.mExplicitLanguage;
+        self.set_language(other.language());
+    }
+    fn clone__x_lang(&self) -> longhands::_x_lang::computed_value::T {
+        use style::properties::longhands::_x_lang::computed_value::T;
+        T(self.language().map(|language| unsafe { atom_from_gecko(language.as_ptr()) }))
+    }
+
+    // Gecko hashes the nsFont itself when it looks up a font group.
+    fn compute_font_hash(&mut self) {}
+
+    fn font_metrics(&self) -> Option<FontMetrics> {
+        platform_values::font_metrics(self)
+    }
+</%self:impl_trait>
+
+<%self:impl_trait style_struct_name="Pointing"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Pointing']}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Pointing']]}">
+    ${impl_rust_side_longhands('Pointing')}
+</%self:impl_trait>
+
+<%self:impl_trait style_struct_name="Effects"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Effects']}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Effects']]}">
+    ${impl_rust_side_longhands('Effects')}
+</%self:impl_trait>
+
+<% COLUMN_SKIPPED_ADDITIONALS = ['column_rule_is_none_or_hidden_and_has_nonzero_width'] + \
+                                ['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Column']] %>
+<%self:impl_trait style_struct_name="Column"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Column']}"
+                  skip_additionals="${COLUMN_SKIPPED_ADDITIONALS}">
+    ${impl_rust_side_longhands('Column')}
+    fn column_rule_is_none_or_hidden_and_has_nonzero_width(&self) -> bool {
+        self.column_rule_style.none_or_hidden() && self.column_rule_width != Au(0)
+    }
+</%self:impl_trait>
+
+<%self:impl_trait style_struct_name="Counters"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Counters']}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Counters']]}">
+    ${impl_rust_side_longhands('Counters')}
+</%self:impl_trait>
+
+<%self:impl_trait style_struct_name="Flex"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Flex']}"
+                  skip_additionals="${['clone_' + to_rust_ident(name) for name in RUST_SIDE_LONGHANDS['Flex']]}">
+    ${impl_rust_side_longhands('Flex')}
+</%self:impl_trait>
+
+<% TRANSITION_LONGHANDS = [to_rust_ident(name) for name in RUST_SIDE_LONGHANDS["Animation"]] %>
+<%self:impl_trait style_struct_name="Animation"
+                  skip_longhands="${RUST_SIDE_LONGHANDS['Animation']}"
+                  skip_additionals="${['transition_count'] + ['clone_' + ident for ident in TRANSITION_LONGHANDS]}">
+    % for ident in TRANSITION_LONGHANDS:
+    <% SERIALIZABLE_LONGHANDS.append(ident) %>
+    fn set_${ident}(&mut self, v: longhands::${ident}::computed_value::T) {
+        self.${ident} = v;
+    }
+    fn copy_${ident}_from(&mut self, other: &Self) {
+        self.${ident} = other.${ident}.clone();
+    }
+    fn clone_${ident}(&self) -> longhands::${ident}::computed_value::T {
+        self.${ident}.clone()
+    }
+    % endfor
+    fn transition_count(&self) -> usize {
+        self.transition_property.0.len()
     }
 </%self:impl_trait>
 
@@ -205,3 +1091,185 @@ ${impl_style_struct(style_struct)}
 <%self:raw_impl_trait style_struct="${style_struct}"></%self:raw_impl_trait>
 % endif
 % endfor
+
+/// The name of the longhand Gecko calls `property`, if Servo implements it.
+pub fn longhand_name_from_gecko(property: nsCSSProperty) -> Option<<&'static str> {
+    match property {
+        % for longhand in LONGHANDS:
+        % if gecko_property_ident(longhand):
+        nsCSSProperty::eCSSProperty_${gecko_property_ident(longhand)} => Some("${longhand.name}"),
+        % endif
+        % endfor
+        _ => None,
+    }
+}
+
+pub fn shorthand_name_from_gecko(property: nsCSSProperty) -> Option<<&'static str> {
+    match property {
+        % for shorthand in SHORTHANDS:
+        % if gecko_shorthand_ident(shorthand):
+        nsCSSProperty::eCSSProperty_${gecko_shorthand_ident(shorthand)} => Some("${shorthand.name}"),
+        % endif
+        % endfor
+        _ => None,
+    }
+}
+
+<%
+    # Gecko always supports flexbox, multi-column layout and writing modes, which Servo keeps
+    # behind prefs.
+    EXPERIMENTAL_PREFS = ["layout.flex.enabled", "layout.inline-flex.enabled", "layout.contents.enabled",
+                          "layout.writing-mode.enabled", "layout.text-orientation.enabled"]
+    for property in LONGHANDS + SHORTHANDS:
+        sub_properties = getattr(property, "sub_properties", [property])
+        if property.experimental and all(p.style_struct.name in ["Column", "Flex"] for p in sub_properties):
+            if property.experimental not in EXPERIMENTAL_PREFS:
+                EXPERIMENTAL_PREFS.append(property.experimental)
+%>
+/// The prefs that enable the experimental properties and display values Gecko supports.
+/// Gecko doesn't read Servo's prefs, so GlobalStyleData::initialize turns these on.
+pub static EXPERIMENTAL_PREFS: &'static [&'static str] = &[
+    % for pref in EXPERIMENTAL_PREFS:
+    "${pref}",
+    % endfor
+];
+
+/// The style struct Gecko means by `id`, one of the values of ServoStyleStructId.
+pub fn style_struct_id_from_gecko(id: u8) -> Option<<ServoStyleStructId> {
+    % for style_struct in STYLE_STRUCTS:
+    if id == ServoStyleStructId::${style_struct.name} as u8 {
+        return Some(ServoStyleStructId::${style_struct.name});
+    }
+    % endfor
+    None
+}
+
+/// The Gecko property for the longhand called `name`, if Gecko has it.
+pub fn longhand_to_gecko(name: &str) -> Option<<nsCSSProperty> {
+    match name {
+        % for longhand in LONGHANDS:
+        % if gecko_property_ident(longhand):
+        "${longhand.name}" => Some(nsCSSProperty::eCSSProperty_${gecko_property_ident(longhand)}),
+        % endif
+        % endfor
+        _ => None,
+    }
+}
+
+impl GeckoComputedValues {
+    /// The resolved value of the longhand called `name`, as `getComputedStyle` reports it.
+    /// Only the longhands that can be read back out of Gecko's style structs are supported.
+    pub fn resolved_value_to_string(&self, name: &str) -> Result<String, ()> {
+        if let Some(physical) = physical_longhand_name(name, self.writing_mode) {
+            return self.resolved_value_to_string(physical)
+        }
+        match name {
+            % for longhand in LONGHANDS:
+            % if longhand.ident in SERIALIZABLE_LONGHANDS:
+            "${longhand.name}" => {
+                Ok(self.get_${longhand.style_struct.name.lower()}().clone_${longhand.ident}().to_css_string())
+            }
+            % endif
+            % endfor
+            _ => Err(()),
+        }
+    }
+
+    /// Whether the `id` struct of these values and `other` is the same allocation, which it
+    /// is when they inherited it from the same parent, or got it from the same cached style,
+    /// and the cascade didn't change it.
+    pub fn same_struct(&self, other: &GeckoComputedValues, id: ServoStyleStructId) -> bool {
+        match id {
+            % for style_struct in STYLE_STRUCTS:
+            ServoStyleStructId::${style_struct.name} => {
+                arc_ptr_eq(&self.${style_struct.ident}, &other.${style_struct.ident})
+            }
+            % endfor
+        }
+    }
+
+    /// These values, with the structs holding the longhands `declarations` set taken from
+    /// `animated`, which was cascaded from the same rules plus `declarations`. The other
+    /// structs stay shared with these values, so that restyle damage is only computed for
+    /// what the declarations touch.
+    ///
+    /// The font struct decides what em lengths in every other struct come to, so
+    /// declarations touching it take every struct from `animated`.
+    pub fn with_structs_from(&self, animated: &GeckoComputedValues,
+                             declarations: &[PropertyDeclaration]) -> GeckoComputedValues {
+        let mut values = self.clone();
+        values.writing_mode = animated.writing_mode;
+        values.root_font_size = animated.root_font_size;
+        for declaration in declarations {
+            match *declaration {
+                % for style_struct in STYLE_STRUCTS:
+                % for longhand in style_struct.longhands:
+                PropertyDeclaration::${longhand.camel_case}(..) => {
+                    % if style_struct.name == "Font":
+                    return animated.clone();
+                    % else:
+                    values.${style_struct.ident} = animated.${style_struct.ident}.clone();
+                    % endif
+                }
+                % endfor
+                % endfor
+                // The physical longhands they set are in the same struct.
+                % for longhand in [l for l in LONGHANDS if l.logical_side]:
+                PropertyDeclaration::${longhand.camel_case}(..) => {
+                    values.${longhand.style_struct.ident} = animated.${longhand.style_struct.ident}.clone();
+                }
+                % endfor
+                PropertyDeclaration::Custom(..) => {
+                    values.custom_properties = animated.custom_properties.clone();
+                }
+            }
+        }
+        values
+    }
+
+    /// Whether any of the structs children inherit from differs between these values and
+    /// `other`, going by whether it's the same allocation.
+    pub fn inherited_structs_differ(&self, other: &GeckoComputedValues) -> bool {
+        % for style_struct in STYLE_STRUCTS:
+        % if style_struct.inherited:
+        if !arc_ptr_eq(&self.${style_struct.ident}, &other.${style_struct.ident}) {
+            return true;
+        }
+        % endif
+        % endfor
+        false
+    }
+
+    /// Whether these values and `other` compute to the same thing.
+    ///
+    /// FIXME: Structs are compared by value only when Servo can read every one of their
+    /// longhands back out of Gecko's struct. Other structs are only equal if they're shared,
+    /// so this may say different values differ, but never that they're the same.
+    pub fn deep_equals(&self, other: &GeckoComputedValues) -> bool {
+        % for style_struct in STYLE_STRUCTS:
+        <% longhands = style_struct.longhands %>
+        % if longhands and all(longhand.ident in SERIALIZABLE_LONGHANDS for longhand in longhands):
+        if !arc_ptr_eq(&self.${style_struct.ident}, &other.${style_struct.ident}) {
+            let (a, b) = (&self.${style_struct.ident}, &other.${style_struct.ident});
+            % for longhand in longhands:
+            if a.clone_${longhand.ident}() != b.clone_${longhand.ident}() {
+                return false;
+            }
+            % endfor
+        }
+        % else:
+        if !arc_ptr_eq(&self.${style_struct.ident}, &other.${style_struct.ident}) {
+            return false;
+        }
+        % endif
+        % endfor
+        self.custom_properties == other.custom_properties &&
+            self.writing_mode == other.writing_mode &&
+            self.root_font_size == other.root_font_size &&
+            match (&self.visited_style, &other.visited_style) {
+                (&Some(ref a), &Some(ref b)) => arc_ptr_eq(a, b) || a.deep_equals(b),
+                (&None, &None) => true,
+                _ => false,
+            }
+    }
+}
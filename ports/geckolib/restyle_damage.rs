@@ -0,0 +1,309 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use properties::GeckoComputedValues;
+use std::ascii::AsciiExt;
+use std::sync::Arc;
+use style::dom::TRestyleDamage;
+use style::properties::longhands::position::computed_value::T as Position;
+use style::properties::longhands::transform::computed_value::T as Transform;
+use style::properties::longhands::transform_style::computed_value::T as TransformStyle;
+use style::properties::longhands::will_change::computed_value::T as WillChange;
+use style::properties::longhands::z_index::computed_value::T as ZIndex;
+use style::properties::style_struct_traits::{TBox, TEffects, TInheritedBox, TPointing, TPositionOffsets, TText};
+use style::properties::{ComputedValues, is_supported_property};
+
+// Keep these in sync with nsChangeHint in layout/base/nsChangeHint.h.
+bitflags! {
+    #[doc = "The work Gecko has to do on an element's frames after a restyle."]
+    flags GeckoRestyleDamage: u32 {
+        #[doc = "Repaint the frame."]
+        const REPAINT_FRAME = 1 << 0,
+        #[doc = "Reflow the frame."]
+        const NEED_REFLOW = 1 << 1,
+        #[doc = "The frame's intrinsic sizes, and those of its ancestors, are out of date."]
+        const CLEAR_ANCESTOR_INTRINSICS = 1 << 2,
+        #[doc = "The intrinsic sizes of the frame's descendants are out of date."]
+        const CLEAR_DESCENDANT_INTRINSICS = 1 << 3,
+        #[doc = "Reflow the frame's descendants too, not just the frame."]
+        const NEED_DIRTY_REFLOW = 1 << 4,
+        #[doc = "Update the frame's view, which is what keeps its z-index and whether it's auto."]
+        const SYNC_FRAME_VIEW = 1 << 5,
+        #[doc = "Update the mouse cursor."]
+        const UPDATE_CURSOR = 1 << 6,
+        #[doc = "Update SVG filters, clip paths and masks."]
+        const UPDATE_EFFECTS = 1 << 7,
+        #[doc = "Update the opacity of the frame's layer without repainting."]
+        const UPDATE_OPACITY_LAYER = 1 << 8,
+        #[doc = "Update the transform of the frame's layer without repainting."]
+        const UPDATE_TRANSFORM_LAYER = 1 << 9,
+        #[doc = "Throw away the frame and build a new one."]
+        const RECONSTRUCT_FRAME = 1 << 10,
+        #[doc = "Recompute the frame's visual overflow."]
+        const UPDATE_OVERFLOW = 1 << 11,
+        #[doc = "Move the relatively positioned frame to its new offsets, without reflowing it."]
+        const RECOMPUTE_POSITION = 1 << 16,
+        #[doc = "Nothing about the frame has to change, but something that reads the style has to"]
+        #[doc = "see it did, such as hit testing."]
+        const NEUTRAL_CHANGE = 1 << 21,
+    }
+}
+
+// Keep these in sync with the will-change bits described in ServoBindings.h.
+/// `will-change: transform`.
+pub const WILL_CHANGE_TRANSFORM: u64 = 1 << 0;
+/// `will-change: opacity`.
+pub const WILL_CHANGE_OPACITY: u64 = 1 << 1;
+/// `will-change: scroll-position`.
+pub const WILL_CHANGE_SCROLL_POSITION: u64 = 1 << 2;
+/// `will-change: contents`.
+pub const WILL_CHANGE_CONTENTS: u64 = 1 << 3;
+/// A property that makes the element a stacking context when it isn't at its initial value.
+pub const WILL_CHANGE_STACKING_CONTEXT: u64 = 1 << 4;
+/// Any other property.
+pub const WILL_CHANGE_OTHER_PROPERTY: u64 = 1 << 5;
+
+/// The properties that make an element a stacking context when they're set to something
+/// other than their initial value, so that Gecko makes it one ahead of time when they're
+/// expected to change.
+const STACKING_CONTEXT_PROPERTIES: &'static [&'static str] = &[
+    "transform", "opacity", "perspective", "filter", "mix-blend-mode", "isolation", "mask",
+    "clip-path", "position", "z-index",
+];
+
+/// The will-change bits for `will_change`. Identifiers that are neither keywords nor
+/// property names have no effect.
+pub fn will_change_bits(will_change: &WillChange) -> u64 {
+    let features = match *will_change {
+        WillChange::Auto => return 0,
+        WillChange::AnimateableFeatures(ref features) => features,
+    };
+    features.iter().fold(0, |bits, feature| {
+        let is = |name: &str| feature.eq_ignore_ascii_case(name);
+        let mut bit = if is("scroll-position") {
+            WILL_CHANGE_SCROLL_POSITION
+        } else if is("contents") {
+            WILL_CHANGE_CONTENTS
+        } else if is("transform") {
+            WILL_CHANGE_TRANSFORM
+        } else if is("opacity") {
+            WILL_CHANGE_OPACITY
+        } else if is_supported_property(feature) && !feature.starts_with("--") {
+            WILL_CHANGE_OTHER_PROPERTY
+        } else {
+            0
+        };
+        if STACKING_CONTEXT_PROPERTIES.iter().any(|name| is(name)) {
+            bit |= WILL_CHANGE_STACKING_CONTEXT;
+        }
+        bits | bit
+    })
+}
+
+impl TRestyleDamage for GeckoRestyleDamage {
+    type ConcreteComputedValues = GeckoComputedValues;
+    fn compute(old: Option<&Arc<GeckoComputedValues>>, new: &GeckoComputedValues) -> GeckoRestyleDamage {
+        match old {
+            None => GeckoRestyleDamage::rebuild_and_reflow(),
+            Some(old) => compute_damage(&**old, new),
+        }
+    }
+
+    fn rebuild_and_reflow() -> GeckoRestyleDamage {
+        RECONSTRUCT_FRAME
+    }
+}
+
+impl GeckoRestyleDamage {
+    /// Everything Gecko needs to do to reflow a frame.
+    pub fn reflow() -> GeckoRestyleDamage {
+        NEED_REFLOW | CLEAR_ANCESTOR_INTRINSICS | CLEAR_DESCENDANT_INTRINSICS | NEED_DIRTY_REFLOW
+    }
+}
+
+fn is_out_of_flow(position: Position) -> bool {
+    position == Position::absolute || position == Position::fixed
+}
+
+fn same_struct<T>(old: &T, new: &T) -> bool {
+    old as *const T == new as *const T
+}
+
+// Style structs that weren't touched by the cascade are shared with the old style (or the
+// parent's, for inherited structs), so checking whether they're the same struct is both
+// a cheap fast path and, for struct types that don't expose their values to Servo yet,
+// the only comparison we can make.
+macro_rules! add_if_struct_changed(
+    ($old:ident, $new:ident, $damage:ident, [ $($effect:ident),* ], [ $($struct_getter:ident),* ]) => ({
+        $(
+            if !same_struct($old.$struct_getter(), $new.$struct_getter()) {
+                $damage.insert($($effect)|*);
+            }
+        )*
+    });
+);
+
+pub fn compute_damage(old: &GeckoComputedValues, new: &GeckoComputedValues) -> GeckoRestyleDamage {
+    let mut damage = GeckoRestyleDamage::empty();
+
+    if !same_struct(old.get_box(), new.get_box()) {
+        let (old_box, new_box) = (old.get_box(), new.get_box());
+        // Gecko builds the frames of generated content from `content` when it constructs the
+        // pseudo-element's frame, and whether it builds one at all depends on it.
+        //
+        // Becoming a stacking context, or a containing block for fixed-position descendants
+        // (which will-change: transform and paint containment also make the element), changes
+        // which frames go where, so the frame has to be built again.
+        //
+        // Whether the element gets a scroll frame at all depends on its overflow.
+        //
+        // Absolutely and fixed positioned frames hang off their containing block rather than
+        // their parent, so going from or to those positions moves the frame. Going between
+        // static and relative positioning only changes where the frame ends up.
+        //
+        // Everything else in the struct, the sizes and their intrinsic size keywords and
+        // box-sizing among it, changes how the frame is laid out.
+        let will_change_changes = will_change_bits(&old_box.clone_will_change()) ^
+                                  will_change_bits(&new_box.clone_will_change());
+        let (old_position, new_position) = (old_box.clone_position(), new_box.clone_position());
+        if old_box.clone_display() != new_box.clone_display() ||
+           old_position != new_position && (is_out_of_flow(old_position) || is_out_of_flow(new_position)) ||
+           old_box.clone_float() != new_box.clone_float() ||
+           old_box.clone_content() != new_box.clone_content() ||
+           old_box.clone_contain() != new_box.clone_contain() ||
+           old_box.clone_overflow_x() != new_box.clone_overflow_x() ||
+           old_box.clone_overflow_y() != new_box.clone_overflow_y() ||
+           will_change_changes & (WILL_CHANGE_STACKING_CONTEXT | WILL_CHANGE_TRANSFORM) != 0 {
+            damage.insert(RECONSTRUCT_FRAME);
+        } else if old_position != new_position ||
+                  !old_box.same_values_except(new_box, &["position", "z-index", "touch-action", "user-select"]) {
+            damage.insert(GeckoRestyleDamage::reflow() | REPAINT_FRAME);
+        }
+
+        // Only hit testing and selection read touch-action and user-select.
+        if old_box.clone_touch_action() != new_box.clone_touch_action() ||
+           old_box.clone_user_select() != new_box.clone_user_select() {
+            damage.insert(NEUTRAL_CHANGE);
+        }
+
+        // Changing z-index only changes the order the frame is painted in. Whether it's auto
+        // decides whether a positioned frame is a stacking context, which its view keeps.
+        let (old_z_index, new_z_index) = (old_box.clone_z_index(), new_box.clone_z_index());
+        if old_z_index != new_z_index {
+            damage.insert(REPAINT_FRAME);
+            if (old_z_index == ZIndex::Auto) != (new_z_index == ZIndex::Auto) {
+                damage.insert(SYNC_FRAME_VIEW);
+            }
+        }
+    }
+
+    // The offsets move relatively positioned frames without changing their size, and only
+    // apply to positioned frames.
+    if !same_struct(old.get_positionoffsets(), new.get_positionoffsets()) {
+        let (old_offsets, new_offsets) = (old.get_positionoffsets(), new.get_positionoffsets());
+        if old_offsets.clone_top() != new_offsets.clone_top() ||
+           old_offsets.clone_right() != new_offsets.clone_right() ||
+           old_offsets.clone_bottom() != new_offsets.clone_bottom() ||
+           old_offsets.clone_left() != new_offsets.clone_left() {
+            match new.get_box().clone_position() {
+                Position::static_ => {}
+                Position::relative => damage.insert(RECOMPUTE_POSITION),
+                Position::absolute | Position::fixed => damage.insert(GeckoRestyleDamage::reflow()),
+            }
+        }
+    }
+
+    add_if_struct_changed!(old, new, damage, [ RECONSTRUCT_FRAME ], [ get_counters ]);
+
+    // Gecko resolves the bidi levels of the text when it builds the frames, so changing the
+    // direction or unicode-bidi has to build them again.
+    if old.writing_mode != new.writing_mode ||
+       !same_struct(old.get_text(), new.get_text()) &&
+       old.get_text().clone_unicode_bidi() != new.get_text().clone_unicode_bidi() {
+        damage.insert(RECONSTRUCT_FRAME);
+    }
+
+    // Changing `order` moves a flex item among its siblings, which the flex container only
+    // does when it's reflowed, so the frames can stay as they are.
+    add_if_struct_changed!(old, new, damage,
+                           [ NEED_REFLOW, CLEAR_ANCESTOR_INTRINSICS, CLEAR_DESCENDANT_INTRINSICS,
+                             NEED_DIRTY_REFLOW, REPAINT_FRAME ], [
+        get_margin, get_padding, get_border,
+        get_list, get_font, get_inheritedtext, get_text,
+        get_table, get_inheritedtable, get_column, get_flex
+    ]);
+
+    // Visibility only changes whether the frame is drawn. The rest of the struct changes how
+    // its text is laid out.
+    if !same_struct(old.get_inheritedbox(), new.get_inheritedbox()) {
+        let (old_inheritedbox, new_inheritedbox) = (old.get_inheritedbox(), new.get_inheritedbox());
+        if old_inheritedbox.clone_direction() != new_inheritedbox.clone_direction() ||
+           old_inheritedbox.clone_writing_mode() != new_inheritedbox.clone_writing_mode() ||
+           old_inheritedbox.clone_text_orientation() != new_inheritedbox.clone_text_orientation() {
+            damage.insert(GeckoRestyleDamage::reflow() | REPAINT_FRAME);
+        }
+        if old_inheritedbox.clone_visibility() != new_inheritedbox.clone_visibility() {
+            damage.insert(REPAINT_FRAME);
+        }
+    }
+
+    add_if_struct_changed!(old, new, damage, [ REPAINT_FRAME ], [ get_color, get_background ]);
+    add_if_struct_changed!(old, new, damage, [ REPAINT_FRAME, UPDATE_OVERFLOW ], [ get_outline ]);
+
+    // pointer-events only decides what hit testing finds, so nothing is drawn differently.
+    // The paint of SVG shapes only changes how they're drawn.
+    if !same_struct(old.get_pointing(), new.get_pointing()) {
+        let (old_pointing, new_pointing) = (old.get_pointing(), new.get_pointing());
+        if old_pointing.clone_cursor() != new_pointing.clone_cursor() {
+            damage.insert(REPAINT_FRAME | UPDATE_CURSOR);
+        }
+        if old_pointing.clone_fill() != new_pointing.clone_fill() ||
+           old_pointing.clone_stroke() != new_pointing.clone_stroke() {
+            damage.insert(REPAINT_FRAME);
+        }
+        if old_pointing.clone_pointer_events() != new_pointing.clone_pointer_events() {
+            damage.insert(NEUTRAL_CHANGE);
+        }
+    }
+
+    // Changing the opacity or the transform only updates the frame's layer, unless the element
+    // starts or stops being transformed, which makes it a containing block for fixed-position
+    // descendants and gives it a stacking context. Transforms and box shadows move what the
+    // frame draws outside of it.
+    if !same_struct(old.get_effects(), new.get_effects()) {
+        let (old_effects, new_effects) = (old.get_effects(), new.get_effects());
+        let (old_transform, new_transform) = (old_effects.clone_transform(), new_effects.clone_transform());
+        let (old_transform_style, new_transform_style) = (old_effects.clone_transform_style(),
+                                                          new_effects.clone_transform_style());
+        let is_transformed = |transform: &Transform, transform_style: TransformStyle| {
+            transform.0.is_some() || transform_style == TransformStyle::preserve_3d
+        };
+        if is_transformed(&old_transform, old_transform_style) != is_transformed(&new_transform, new_transform_style) {
+            damage.insert(RECONSTRUCT_FRAME);
+        } else {
+            if old_transform != new_transform {
+                damage.insert(UPDATE_TRANSFORM_LAYER | UPDATE_OVERFLOW);
+            }
+            if old_transform_style != new_transform_style ||
+               old_effects.clone_backface_visibility() != new_effects.clone_backface_visibility() {
+                damage.insert(REPAINT_FRAME | UPDATE_OVERFLOW);
+            }
+        }
+        if old_effects.clone_opacity() != new_effects.clone_opacity() {
+            damage.insert(UPDATE_OPACITY_LAYER);
+        }
+        if old_effects.clone_box_shadow() != new_effects.clone_box_shadow() {
+            damage.insert(REPAINT_FRAME | UPDATE_OVERFLOW);
+        }
+        if old_effects.clone_isolation() != new_effects.clone_isolation() ||
+           old_effects.clone_mix_blend_mode() != new_effects.clone_mix_blend_mode() ||
+           old_effects.clone_image_rendering() != new_effects.clone_image_rendering() {
+            damage.insert(REPAINT_FRAME);
+        }
+    }
+
+    // Transitions don't change anything about the frame by themselves.
+
+    damage
+}
@@ -2,91 +2,180 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use atoms::{atom_from_gecko, atom_to_gecko};
+use bindings::nsIAtom;
+use cssparser::{Parser, serialize_identifier, serialize_string};
+use element_state;
 use properties::GeckoComputedValues;
-use selectors::parser::{ParserContext, SelectorImpl};
+use selectors::parser::{AttrSelector, CaseSensitivity, ParserContext, SelectorImpl, SimpleSelector};
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::fmt;
+use string_cache::Atom;
 use style;
 use style::element_state::ElementState;
-use style::selector_impl::SelectorImplExt;
+use style::nth_index::NthIndexKind;
+use style::properties::PropertyRestriction;
+use style::selector_impl::{SelectorImplExt, parse_lang_ranges, serialize_nth};
 
 pub type Stylist = style::selector_matching::Stylist<GeckoSelectorImpl>;
 pub type Stylesheet = style::stylesheets::Stylesheet<GeckoSelectorImpl>;
+pub type ImportRule = style::stylesheets::ImportRule<GeckoSelectorImpl>;
+pub type SharedStylesheet = style::selector_matching::SharedStylesheet<GeckoSelectorImpl>;
 pub type SharedStyleContext = style::context::SharedStyleContext<GeckoSelectorImpl>;
 pub type PrivateStyleData = style::data::PrivateStyleData<GeckoSelectorImpl, GeckoComputedValues>;
 
+#[derive(Clone, HeapSizeOf)]
 pub struct GeckoSelectorImpl;
 
-#[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
-pub enum PseudoElement {
-    Before,
-    After,
-    FirstLine,
-    // TODO: Probably a few more are missing here
+bitflags! {
+    #[doc = "What kind of pseudo-element an entry of the pseudo-element table is."]
+    flags PseudoElementFlags: u8 {
+        #[doc = "None of the flags: a pseudo-element Gecko asks for the style of when it needs it."]
+        const PSEUDO_LAZY = 0,
+        #[doc = "The traversal cascades the pseudo-element along with its element."]
+        const PSEUDO_EAGER = 1 << 0,
+        #[doc = "One of the boxes Gecko makes for its frames, from nsCSSAnonBoxList.h."]
+        const PSEUDO_ANON_BOX = 1 << 1,
+        #[doc = "Only user agent sheets may have selectors with the pseudo-element."]
+        const PSEUDO_UA_SHEET_ONLY = 1 << 2,
+    }
+}
+
+/// Defines PseudoElement from the table of the pseudo-elements Gecko knows, with the name of
+/// each, without the leading colons, and its flags.
+macro_rules! pseudo_elements {
+    ($($variant:ident => $name:expr, $flags:expr;)*) => {
+        #[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
+        pub enum PseudoElement {
+            $($variant,)*
+        }
+
+        /// Every pseudo-element, in the order of the table.
+        static PSEUDO_ELEMENTS: &'static [PseudoElement] = &[$(PseudoElement::$variant,)*];
+
+        impl PseudoElement {
+            /// The name of the pseudo-element, without the leading colons.
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $(PseudoElement::$variant => $name,)*
+                }
+            }
+
+            pub fn flags(&self) -> PseudoElementFlags {
+                match *self {
+                    $(PseudoElement::$variant => $flags,)*
+                }
+            }
+        }
+    }
+}
+
+// Adding a pseudo-element is adding a line here. The eager ones cascade in this order, so
+// ::first-line comes before the ::first-letter that inherits from it.
+pseudo_elements! {
+    Before => "before", PSEUDO_EAGER;
+    After => "after", PSEUDO_EAGER;
+    FirstLine => "first-line", PSEUDO_EAGER;
+    FirstLetter => "first-letter", PSEUDO_EAGER;
+
+    // https://mxr.mozilla.org/mozilla-central/source/layout/style/nsCSSPseudoElementList.h
+    Backdrop => "backdrop", PSEUDO_LAZY;
+    MozSelection => "-moz-selection", PSEUDO_LAZY;
+    MozFocusInner => "-moz-focus-inner", PSEUDO_LAZY;
+    MozFocusOuter => "-moz-focus-outer", PSEUDO_LAZY;
+    MozListBullet => "-moz-list-bullet", PSEUDO_LAZY;
+    MozListNumber => "-moz-list-number", PSEUDO_LAZY;
+    MozMathAnonymous => "-moz-math-anonymous", PSEUDO_UA_SHEET_ONLY;
+    MozNumberWrapper => "-moz-number-wrapper", PSEUDO_UA_SHEET_ONLY;
+    MozNumberText => "-moz-number-text", PSEUDO_UA_SHEET_ONLY;
+    MozNumberSpinBox => "-moz-number-spin-box", PSEUDO_UA_SHEET_ONLY;
+    MozNumberSpinUp => "-moz-number-spin-up", PSEUDO_UA_SHEET_ONLY;
+    MozNumberSpinDown => "-moz-number-spin-down", PSEUDO_UA_SHEET_ONLY;
+    MozProgressBar => "-moz-progress-bar", PSEUDO_LAZY;
+    MozRangeTrack => "-moz-range-track", PSEUDO_LAZY;
+    MozRangeProgress => "-moz-range-progress", PSEUDO_LAZY;
+    MozRangeThumb => "-moz-range-thumb", PSEUDO_LAZY;
+    MozMeterBar => "-moz-meter-bar", PSEUDO_LAZY;
+    MozPlaceholder => "-moz-placeholder", PSEUDO_LAZY;
+    Placeholder => "placeholder", PSEUDO_LAZY;
+    MozColorSwatch => "-moz-color-swatch", PSEUDO_LAZY;
 
     // https://mxr.mozilla.org/mozilla-central/source/layout/style/nsCSSAnonBoxList.h
-    MozNonElement,
-    MozAnonymousBlock,
-    MozAnonymousPositionedBlock,
-    MozMathMLAnonymousBlock,
-    MozXULAnonymousBlock,
-
-    MozHorizontalFramesetBorder,
-    MozVerticalFramesetBorder,
-    MozLineFrame,
-    MozButtonContent,
-    MozButtonLabel,
-    MozCellContent,
-    MozDropdownList,
-    MozFieldsetContent,
-    MozFramesetBlank,
-    MozDisplayComboboxControlFrame,
-
-    MozHTMLCanvasContent,
-    MozInlineTable,
-    MozTable,
-    MozTableCell,
-    MozTableColumnGroup,
-    MozTableColumn,
-    MozTableOuter,
-    MozTableRowGroup,
-    MozTableRow,
-
-    MozCanvas,
-    MozPageBreak,
-    MozPage,
-    MozPageContent,
-    MozPageSequence,
-    MozScrolledContent,
-    MozScrolledCanvas,
-    MozScrolledPageSequence,
-    MozColumnContent,
-    MozViewport,
-    MozViewportScroll,
-    MozAnonymousFlexItem,
-    MozAnonymousGridItem,
-
-    MozRuby,
-    MozRubyBase,
-    MozRubyBaseContainer,
-    MozRubyText,
-    MozRubyTextContainer,
-
-    MozTreeColumn,
-    MozTreeRow,
-    MozTreeSeparator,
-    MozTreeCell,
-    MozTreeIndentation,
-    MozTreeLine,
-    MozTreeTwisty,
-    MozTreeImage,
-    MozTreeCellText,
-    MozTreeCheckbox,
-    MozTreeProgressMeter,
-    MozTreeDropFeedback,
-
-    MozSVGMarkerAnonChild,
-    MozSVGOuterSVGAnonChild,
-    MozSVGForeignContent,
-    MozSVGText,
+    MozNonElement => "-moz-non-element", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozAnonymousBlock => "-moz-anonymous-block", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozAnonymousPositionedBlock => "-moz-anonymous-positioned-block", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozMathMLAnonymousBlock => "-moz-mathml-anonymous-block", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozXULAnonymousBlock => "-moz-xul-anonymous-block", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+
+    MozHorizontalFramesetBorder => "-moz-hframeset-border", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozVerticalFramesetBorder => "-moz-vframeset-border", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozLineFrame => "-moz-line-frame", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozButtonContent => "-moz-button-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozButtonLabel => "-moz-buttonlabel", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozCellContent => "-moz-cell-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozDropdownList => "-moz-dropdown-list", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozFieldsetContent => "-moz-fieldset-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozFramesetBlank => "-moz-frameset-blank", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozDisplayComboboxControlFrame => "-moz-display-comboboxcontrol-frame", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+
+    MozHTMLCanvasContent => "-moz-html-canvas-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozInlineTable => "-moz-inline-table", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTable => "-moz-table", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableCell => "-moz-table-cell", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableColumnGroup => "-moz-table-column-group", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableColumn => "-moz-table-column", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableOuter => "-moz-table-outer", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableRowGroup => "-moz-table-row-group", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozTableRow => "-moz-table-row", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+
+    MozCanvas => "-moz-canvas", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozPageBreak => "-moz-pagebreak", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozPage => "-moz-page", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozPageContent => "-moz-pagecontent", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozPageSequence => "-moz-page-sequence", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozScrolledContent => "-moz-scrolled-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozScrolledCanvas => "-moz-scrolled-canvas", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozScrolledPageSequence => "-moz-scrolled-page-sequence", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozColumnContent => "-moz-column-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozViewport => "-moz-viewport", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozViewportScroll => "-moz-viewport-scroll", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozAnonymousFlexItem => "-moz-anonymous-flex-item", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozAnonymousGridItem => "-moz-anonymous-grid-item", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+
+    MozRuby => "-moz-ruby", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozRubyBase => "-moz-ruby-base", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozRubyBaseContainer => "-moz-ruby-base-container", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozRubyText => "-moz-ruby-text", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozRubyTextContainer => "-moz-ruby-text-container", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+
+    // Chrome sheets style XUL trees with these, so they aren't kept to user agent sheets.
+    MozTreeColumn => "-moz-tree-column", PSEUDO_ANON_BOX;
+    MozTreeRow => "-moz-tree-row", PSEUDO_ANON_BOX;
+    MozTreeSeparator => "-moz-tree-separator", PSEUDO_ANON_BOX;
+    MozTreeCell => "-moz-tree-cell", PSEUDO_ANON_BOX;
+    MozTreeIndentation => "-moz-tree-indentation", PSEUDO_ANON_BOX;
+    MozTreeLine => "-moz-tree-line", PSEUDO_ANON_BOX;
+    MozTreeTwisty => "-moz-tree-twisty", PSEUDO_ANON_BOX;
+    MozTreeImage => "-moz-tree-image", PSEUDO_ANON_BOX;
+    MozTreeCellText => "-moz-tree-cell-text", PSEUDO_ANON_BOX;
+    MozTreeCheckbox => "-moz-tree-checkbox", PSEUDO_ANON_BOX;
+    MozTreeProgressMeter => "-moz-tree-progressmeter", PSEUDO_ANON_BOX;
+    MozTreeDropFeedback => "-moz-tree-drop-feedback", PSEUDO_ANON_BOX;
+
+    MozSVGMarkerAnonChild => "-moz-svg-marker-anon-child", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozSVGOuterSVGAnonChild => "-moz-svg-outer-svg-anon-child", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozSVGForeignContent => "-moz-svg-foreign-content", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+    MozSVGText => "-moz-svg-text", PSEUDO_ANON_BOX | PSEUDO_UA_SHEET_ONLY;
+}
+
+lazy_static! {
+    /// The pseudo-elements by their names, without the leading colons.
+    static ref PSEUDO_ELEMENTS_BY_NAME: HashMap<&'static str, PseudoElement> =
+        PSEUDO_ELEMENTS.iter().map(|pseudo| (pseudo.as_str(), pseudo.clone())).collect();
+    /// The names of Gecko's atoms for the pseudo-elements, by their place in the table.
+    static ref PSEUDO_ELEMENT_ATOMS: Vec<Atom> =
+        PSEUDO_ELEMENTS.iter().map(|pseudo| Atom::from(&*format!(":{}", pseudo.as_str()))).collect();
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
@@ -96,11 +185,113 @@ pub enum NonTSPseudoClass {
     Visited,
     Active,
     Focus,
+    FocusWithin,
     Hover,
+    Target,
     Enabled,
     Disabled,
     Checked,
     Indeterminate,
+    Default,
+    Required,
+    Optional,
+    Valid,
+    Invalid,
+    InRange,
+    OutOfRange,
+    ReadOnly,
+    ReadWrite,
+    PlaceholderShown,
+    MozDragOver,
+    /// Matches the anonymous content Gecko makes for elements like form controls.
+    MozNativeAnonymous,
+    /// Matches elements with no children but comments, processing instructions and
+    /// whitespace text.
+    MozOnlyWhitespace,
+    /// Matches elements whose content language is in one of the language ranges.
+    Lang(Vec<String>),
+    /// Matches elements whose directionality, as Gecko resolved it, is the given one.
+    Dir(Direction),
+
+    // These depend on the state of the document rather than that of the element.
+    MozWindowInactive,
+    MozLWTheme,
+    MozLWThemeBrightText,
+    MozLWThemeDarkText,
+    /// Matches every element if the direction of the user interface's locale is the given
+    /// one.
+    MozLocaleDir(Direction),
+
+    /// An `:nth-child()`, `:nth-last-child()`, `:nth-of-type()` or `:nth-last-of-type()`
+    /// selector with the given `a` and `b`, which the stylist has us match through the
+    /// nth-index cache of the thread instead of letting the selectors crate walk the
+    /// siblings. It's never parsed.
+    NthIndex(i32, i32, NthIndexKind),
+    /// An attribute selector, which the stylist has us match by having Gecko compare the
+    /// attribute against the interned value instead of copying the attribute's value out for
+    /// the selectors crate to test. It's never parsed either.
+    Attr(AttrSelector, AttrOperator),
+
+    /// Any other -moz- pseudo-class of Gecko's own sheets, like :-moz-browser-frame, which
+    /// Gecko matches for us. The atom is Gecko's for it, with the colon, as in
+    /// ":-moz-browser-frame".
+    MozCustom(Atom),
+}
+
+/// What an attribute selector tests the attribute for, with the value it's compared
+/// against.
+#[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
+pub enum AttrOperator {
+    Exists,
+    Equal(Atom, CaseSensitivity),
+    Includes(Atom),
+    DashMatch(Atom),
+    PrefixMatch(Atom),
+    SubstringMatch(Atom),
+    SuffixMatch(Atom),
+}
+
+impl AttrOperator {
+    /// The operator as it's written between the attribute's name and the value.
+    fn as_str(&self) -> &'static str {
+        match *self {
+            AttrOperator::Exists => "",
+            AttrOperator::Equal(..) => "=",
+            AttrOperator::Includes(_) => "~=",
+            AttrOperator::DashMatch(_) => "|=",
+            AttrOperator::PrefixMatch(_) => "^=",
+            AttrOperator::SubstringMatch(_) => "*=",
+            AttrOperator::SuffixMatch(_) => "$=",
+        }
+    }
+}
+
+/// The argument of :dir().
+#[derive(Clone, Debug, PartialEq, Eq, HeapSizeOf, Hash)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    /// Any other identifier, lowercased. It's valid, but never matches.
+    Other(String),
+}
+
+impl Direction {
+    fn parse(input: &mut Parser) -> Result<Direction, ()> {
+        let ident = try!(input.expect_ident());
+        Ok(match_ignore_ascii_case! { &*ident,
+            "ltr" => Direction::Ltr,
+            "rtl" => Direction::Rtl,
+            _ => Direction::Other(ident.to_ascii_lowercase())
+        })
+    }
+
+    fn as_str(&self) -> &str {
+        match *self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+            Direction::Other(ref ident) => ident,
+        }
+    }
 }
 
 impl NonTSPseudoClass {
@@ -115,18 +306,189 @@ impl NonTSPseudoClass {
             Disabled => IN_DISABLED_STATE,
             Checked => IN_CHECKED_STATE,
             Indeterminate => IN_INDETERMINATE_STATE,
+            FocusWithin => IN_FOCUS_WITHIN_STATE,
+            PlaceholderShown => IN_PLACEHOLDER_SHOWN_STATE,
 
+            // FIXME: These have no Servo equivalent, so changes to them aren't picked
+            // up by restyle hints yet.
             AnyLink |
             Link |
-            Visited => ElementState::empty(),
+            Visited |
+            Target |
+            Default |
+            Required |
+            Optional |
+            Valid |
+            Invalid |
+            InRange |
+            OutOfRange |
+            ReadOnly |
+            ReadWrite |
+            MozDragOver |
+            Dir(_) => ElementState::empty(),
+
+            // These depend on the children or the ancestors of the element rather than its
+            // state, or never change.
+            MozOnlyWhitespace |
+            MozNativeAnonymous |
+            NthIndex(..) |
+            Attr(..) |
+            Lang(_) => ElementState::empty(),
+
+            // Only Gecko knows what these depend on.
+            MozCustom(_) => ElementState::empty(),
+
+            MozWindowInactive |
+            MozLWTheme |
+            MozLWThemeBrightText |
+            MozLWThemeDarkText |
+            MozLocaleDir(_) => ElementState::empty(),
         }
     }
+
+    /// The Gecko event states that make an element match this pseudo-class. Having any
+    /// one of them is enough.
+    pub fn gecko_state_flag(&self) -> element_state::ElementState {
+        use element_state::*;
+        use self::NonTSPseudoClass::*;
+        match *self {
+            AnyLink => NS_EVENT_STATE_VISITED | NS_EVENT_STATE_UNVISITED,
+            Link => NS_EVENT_STATE_UNVISITED,
+            Visited => NS_EVENT_STATE_VISITED,
+            Active => NS_EVENT_STATE_ACTIVE,
+            Focus => NS_EVENT_STATE_FOCUS,
+            FocusWithin => NS_EVENT_STATE_FOCUS_WITHIN,
+            Hover => NS_EVENT_STATE_HOVER,
+            Target => NS_EVENT_STATE_URLTARGET,
+            Enabled => NS_EVENT_STATE_ENABLED,
+            Disabled => NS_EVENT_STATE_DISABLED,
+            Checked => NS_EVENT_STATE_CHECKED,
+            Indeterminate => NS_EVENT_STATE_INDETERMINATE,
+            Default => NS_EVENT_STATE_DEFAULT,
+            Required => NS_EVENT_STATE_REQUIRED,
+            Optional => NS_EVENT_STATE_OPTIONAL,
+            Valid => NS_EVENT_STATE_VALID,
+            Invalid => NS_EVENT_STATE_INVALID,
+            InRange => NS_EVENT_STATE_INRANGE,
+            OutOfRange => NS_EVENT_STATE_OUTOFRANGE,
+            ReadOnly => NS_EVENT_STATE_MOZ_READONLY,
+            ReadWrite => NS_EVENT_STATE_MOZ_READWRITE,
+            PlaceholderShown => NS_EVENT_STATE_PLACEHOLDERSHOWN,
+            MozDragOver => NS_EVENT_STATE_DRAGOVER,
+            Dir(Direction::Ltr) => NS_EVENT_STATE_LTR,
+            Dir(Direction::Rtl) => NS_EVENT_STATE_RTL,
+            Dir(Direction::Other(_)) |
+            MozNativeAnonymous |
+            MozOnlyWhitespace |
+            NthIndex(..) |
+            Attr(..) |
+            Lang(_) |
+            MozWindowInactive |
+            MozLWTheme |
+            MozLWThemeBrightText |
+            MozLWThemeDarkText |
+            MozLocaleDir(_) |
+            MozCustom(_) => element_state::ElementState::empty(),
+        }
+    }
+
+    /// The Gecko document states this pseudo-class depends on.
+    pub fn document_state_flag(&self) -> element_state::DocumentState {
+        use element_state::*;
+        use self::NonTSPseudoClass::*;
+        match *self {
+            MozWindowInactive => NS_DOCUMENT_STATE_WINDOW_INACTIVE,
+            MozLWTheme => NS_DOCUMENT_STATE_LWTHEME,
+            MozLWThemeBrightText => NS_DOCUMENT_STATE_LWTHEME_BRIGHTTEXT,
+            MozLWThemeDarkText => NS_DOCUMENT_STATE_LWTHEME_DARKTEXT,
+            MozLocaleDir(Direction::Ltr) |
+            MozLocaleDir(Direction::Rtl) => NS_DOCUMENT_STATE_RTL_LOCALE,
+            _ => DocumentState::empty(),
+        }
+    }
+
+    /// Whether elements of a document in the given Gecko document state match this
+    /// pseudo-class, for the pseudo-classes that depend on it.
+    pub fn matches_document_state(&self, state: element_state::DocumentState) -> bool {
+        use element_state::*;
+        use self::NonTSPseudoClass::*;
+        match *self {
+            MozLocaleDir(Direction::Ltr) => !state.contains(NS_DOCUMENT_STATE_RTL_LOCALE),
+            MozLocaleDir(Direction::Rtl) => state.contains(NS_DOCUMENT_STATE_RTL_LOCALE),
+            MozLocaleDir(Direction::Other(_)) => false,
+            _ => state.intersects(self.document_state_flag()),
+        }
+    }
+
+    /// The name of the pseudo-class, without the leading colon.
+    pub fn as_str(&self) -> &str {
+        use self::NonTSPseudoClass::*;
+        match *self {
+            AnyLink => "any-link",
+            Link => "link",
+            Visited => "visited",
+            Active => "active",
+            Focus => "focus",
+            FocusWithin => "focus-within",
+            Hover => "hover",
+            Target => "target",
+            Enabled => "enabled",
+            Disabled => "disabled",
+            Checked => "checked",
+            Indeterminate => "indeterminate",
+            Default => "default",
+            Required => "required",
+            Optional => "optional",
+            Valid => "valid",
+            Invalid => "invalid",
+            InRange => "in-range",
+            OutOfRange => "out-of-range",
+            ReadOnly => "read-only",
+            ReadWrite => "read-write",
+            PlaceholderShown => "placeholder-shown",
+            MozDragOver => "-moz-drag-over",
+            MozNativeAnonymous => "-moz-native-anonymous",
+            MozOnlyWhitespace => "-moz-only-whitespace",
+            Lang(_) => "lang",
+            Dir(_) => "dir",
+            MozWindowInactive => "-moz-window-inactive",
+            MozLWTheme => "-moz-lwtheme",
+            MozLWThemeBrightText => "-moz-lwtheme-brighttext",
+            MozLWThemeDarkText => "-moz-lwtheme-darktext",
+            MozLocaleDir(_) => "-moz-locale-dir",
+            NthIndex(_, _, kind) => kind.pseudo_class_name(),
+            // Attribute selectors have no name. Like the nth ones, they only end up in the
+            // stylist's copies of selectors, which aren't serialized but for debugging.
+            Attr(..) => "",
+            MozCustom(ref atom) => &atom[1..],
+        }
+    }
+
+    /// Parses a pseudo-class that takes arguments, with |input| at the start of them.
+    ///
+    /// FIXME: The selectors crate only parses its own functional pseudo-classes, so it
+    /// doesn't call this yet.
+    pub fn parse_functional(_context: &ParserContext, name: &str, input: &mut Parser)
+                            -> Result<NonTSPseudoClass, ()> {
+        match_ignore_ascii_case! { name,
+            "lang" => Ok(NonTSPseudoClass::Lang(try!(parse_lang_ranges(input)))),
+            "dir" => Ok(NonTSPseudoClass::Dir(try!(Direction::parse(input)))),
+            "-moz-dir" => Ok(NonTSPseudoClass::Dir(try!(Direction::parse(input)))),
+            "-moz-locale-dir" => Ok(NonTSPseudoClass::MozLocaleDir(try!(Direction::parse(input)))),
+            _ => Err(())
+        }
+    }
+
+    /// Whether an element in the given Gecko event state matches this pseudo-class.
+    pub fn matches_gecko_state(&self, state: element_state::ElementState) -> bool {
+        state.intersects(self.gecko_state_flag())
+    }
 }
 
 impl SelectorImpl for GeckoSelectorImpl {
     type PseudoElement = PseudoElement;
     type NonTSPseudoClass = NonTSPseudoClass;
-    fn parse_non_ts_pseudo_class(_context: &ParserContext,
+    fn parse_non_ts_pseudo_class(context: &ParserContext,
                                  name: &str) -> Result<NonTSPseudoClass, ()> {
         use self::NonTSPseudoClass::*;
         let pseudo_class = match_ignore_ascii_case! { name,
@@ -135,94 +497,91 @@ impl SelectorImpl for GeckoSelectorImpl {
             "visited" => Visited,
             "active" => Active,
             "focus" => Focus,
+            "focus-within" => FocusWithin,
             "hover" => Hover,
+            "target" => Target,
             "enabled" => Enabled,
             "disabled" => Disabled,
             "checked" => Checked,
             "indeterminate" => Indeterminate,
-            _ => return Err(())
+            "default" => Default,
+            "required" => Required,
+            "optional" => Optional,
+            "valid" => Valid,
+            "invalid" => Invalid,
+            "in-range" => InRange,
+            "out-of-range" => OutOfRange,
+            "read-only" => ReadOnly,
+            "-moz-read-only" => ReadOnly,
+            "read-write" => ReadWrite,
+            "-moz-read-write" => ReadWrite,
+            "placeholder-shown" => PlaceholderShown,
+            "-moz-drag-over" => MozDragOver,
+            "-moz-native-anonymous" => MozNativeAnonymous,
+            "-moz-only-whitespace" => MozOnlyWhitespace,
+            "-moz-window-inactive" => MozWindowInactive,
+            "-moz-lwtheme" => MozLWTheme,
+            "-moz-lwtheme-brighttext" => MozLWThemeBrightText,
+            "-moz-lwtheme-darktext" => MozLWThemeDarkText,
+            _ => {
+                // Gecko's own sheets use many more -moz- pseudo-classes, which are left to it to
+                // match. Anywhere else, ones we don't know are invalid.
+                if context.in_user_agent_stylesheet && name.len() > 5 &&
+                   name.as_bytes()[..5].eq_ignore_ascii_case(b"-moz-") {
+                    return Ok(MozCustom(Atom::from(&*format!(":{}", name.to_ascii_lowercase()))))
+                }
+                return Err(())
+            }
         };
 
         Ok(pseudo_class)
     }
 
-    fn parse_pseudo_element(_context: &ParserContext,
+    fn parse_pseudo_element(context: &ParserContext,
                             name: &str) -> Result<PseudoElement, ()> {
-        use self::PseudoElement::*;
-        let pseudo_element = match_ignore_ascii_case! { name,
-            "before" => Before,
-            "after" => After,
-            "first-line" => FirstLine,
-
-            "-moz-non-element" => MozNonElement,
-
-            "-moz-anonymous-block" => MozAnonymousBlock,
-            "-moz-anonymous-positioned-block" => MozAnonymousPositionedBlock,
-            "-moz-mathml-anonymous-block" => MozMathMLAnonymousBlock,
-            "-moz-xul-anonymous-block" => MozXULAnonymousBlock,
-
-            "-moz-hframeset-border" => MozHorizontalFramesetBorder,
-            "-moz-vframeset-border" => MozVerticalFramesetBorder,
-
-            "-moz-line-frame" => MozLineFrame,
-
-            "-moz-button-content" => MozButtonContent,
-            "-moz-buttonlabel" => MozButtonLabel,
-            "-moz-cell-content" => MozCellContent,
-            "-moz-dropdown-list" => MozDropdownList,
-            "-moz-fieldset-content" => MozFieldsetContent,
-            "-moz-frameset-blank" => MozFramesetBlank,
-            "-moz-display-comboboxcontrol-frame" => MozDisplayComboboxControlFrame,
-            "-moz-html-canvas-content" => MozHTMLCanvasContent,
-
-            "-moz-inline-table" => MozInlineTable,
-            "-moz-table" => MozTable,
-            "-moz-table-cell" => MozTableCell,
-            "-moz-table-column-group" => MozTableColumnGroup,
-            "-moz-table-column" => MozTableColumn,
-            "-moz-table-outer" => MozTableOuter,
-            "-moz-table-row-group" => MozTableRowGroup,
-            "-moz-table-row" => MozTableRow,
-
-            "-moz-canvas" => MozCanvas,
-            "-moz-pagebreak" => MozPageBreak,
-            "-moz-page" => MozPage,
-            "-moz-pagecontent" => MozPageContent,
-            "-moz-page-sequence" => MozPageSequence,
-            "-moz-scrolled-content" => MozScrolledContent,
-            "-moz-scrolled-canvas" => MozScrolledCanvas,
-            "-moz-scrolled-page-sequence" => MozScrolledPageSequence,
-            "-moz-column-content" => MozColumnContent,
-            "-moz-viewport" => MozViewport,
-            "-moz-viewport-scroll" => MozViewportScroll,
-            "-moz-anonymous-flex-item" => MozAnonymousFlexItem,
-            "-moz-anonymous-grid-item" => MozAnonymousGridItem,
-            "-moz-ruby" => MozRuby,
-            "-moz-ruby-base" => MozRubyBase,
-            "-moz-ruby-base-container" => MozRubyBaseContainer,
-            "-moz-ruby-text" => MozRubyText,
-            "-moz-ruby-text-container" => MozRubyTextContainer,
-            "-moz-tree-column" => MozTreeColumn,
-            "-moz-tree-row" => MozTreeRow,
-            "-moz-tree-separator" => MozTreeSeparator,
-            "-moz-tree-cell" => MozTreeCell,
-            "-moz-tree-indentation" => MozTreeIndentation,
-            "-moz-tree-line" => MozTreeLine,
-            "-moz-tree-twisty" => MozTreeTwisty,
-            "-moz-tree-image" => MozTreeImage,
-            "-moz-tree-cell-text" => MozTreeCellText,
-            "-moz-tree-checkbox" => MozTreeCheckbox,
-            "-moz-tree-progressmeter" => MozTreeProgressMeter,
-            "-moz-tree-drop-feedback" => MozTreeDropFeedback,
-            "-moz-svg-marker-anon-child" => MozSVGMarkerAnonChild,
-            "-moz-svg-outer-svg-anon-child" => MozSVGOuterSVGAnonChild,
-            "-moz-svg-foreign-content" => MozSVGForeignContent,
-            "-moz-svg-text" => MozSVGText,
-
-            _ => return Err(())
-        };
+        let pseudo = try!(PseudoElement::from_slice(name).ok_or(()));
+        if pseudo.is_ua_sheet_only() && !context.in_user_agent_stylesheet {
+            return Err(());
+        }
+        Ok(pseudo)
+    }
+}
+
+impl PseudoElement {
+    /// Every pseudo-element Gecko knows.
+    pub fn all() -> &'static [PseudoElement] {
+        PSEUDO_ELEMENTS
+    }
+
+    /// Maps a pseudo-element name, without the leading colons, to a `PseudoElement`.
+    pub fn from_slice(name: &str) -> Option<PseudoElement> {
+        PSEUDO_ELEMENTS.iter().find(|pseudo| pseudo.as_str().eq_ignore_ascii_case(name)).cloned()
+    }
 
-        Ok(pseudo_element)
+    /// Maps the pseudo-tag atoms Gecko hands us, like ":before" and ":-moz-anonymous-block",
+    /// to a `PseudoElement`.
+    pub unsafe fn from_atom(atom: *mut nsIAtom) -> Option<PseudoElement> {
+        if atom.is_null() {
+            return None;
+        }
+        PSEUDO_ELEMENTS_BY_NAME.get(atom_from_gecko(atom).trim_left_matches(':')).cloned()
+    }
+
+    /// Gecko's atom for the pseudo-element, which stays alive until Servo_Shutdown.
+    pub fn atom(&self) -> *mut nsIAtom {
+        atom_to_gecko(&PSEUDO_ELEMENT_ATOMS[self.clone() as usize])
+    }
+
+    pub fn is_eager(&self) -> bool {
+        self.flags().contains(PSEUDO_EAGER)
+    }
+
+    pub fn is_anon_box(&self) -> bool {
+        self.flags().contains(PSEUDO_ANON_BOX)
+    }
+
+    pub fn is_ua_sheet_only(&self) -> bool {
+        self.flags().contains(PSEUDO_UA_SHEET_ONLY)
     }
 }
 
@@ -230,9 +589,11 @@ impl SelectorImplExt for GeckoSelectorImpl {
     #[inline]
     fn each_eagerly_cascaded_pseudo_element<F>(mut fun: F)
         where F: FnMut(PseudoElement) {
-        fun(PseudoElement::Before);
-        fun(PseudoElement::After);
-        // TODO: probably a lot more are missing here
+        // Gecko builds the frames for these from the styles the traversal leaves in the
+        // node data, without asking for them lazily.
+        for pseudo in PSEUDO_ELEMENTS.iter().filter(|pseudo| pseudo.is_eager()) {
+            fun(pseudo.clone());
+        }
     }
 
     #[inline]
@@ -240,6 +601,25 @@ impl SelectorImplExt for GeckoSelectorImpl {
         pc.state_flag()
     }
 
+    #[inline]
+    fn pseudo_class_document_state_flag(pc: &NonTSPseudoClass) -> u64 {
+        pc.document_state_flag().bits()
+    }
+
+    #[inline]
+    fn pseudo_class_element_state_bits(pc: &NonTSPseudoClass) -> u64 {
+        pc.gecko_state_flag().bits()
+    }
+
+    #[inline]
+    fn pseudo_class_link_visitedness(pc: &NonTSPseudoClass) -> Option<bool> {
+        match *pc {
+            NonTSPseudoClass::Link => Some(false),
+            NonTSPseudoClass::Visited => Some(true),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn get_user_or_user_agent_stylesheets() -> &'static [Stylesheet] {
         &[]
@@ -249,4 +629,116 @@ impl SelectorImplExt for GeckoSelectorImpl {
     fn get_quirks_mode_stylesheet() -> Option<&'static Stylesheet> {
         None
     }
+
+    #[inline]
+    fn pseudo_class_name(pc: &NonTSPseudoClass) -> &str {
+        pc.as_str()
+    }
+
+    fn serialize_pseudo_class<W>(pc: &NonTSPseudoClass, dest: &mut W) -> fmt::Result where W: fmt::Write {
+        try!(dest.write_str(pc.as_str()));
+        match *pc {
+            NonTSPseudoClass::Lang(ref ranges) => {
+                try!(dest.write_str("("));
+                for (i, range) in ranges.iter().enumerate() {
+                    if i != 0 {
+                        try!(dest.write_str(", "));
+                    }
+                    try!(serialize_string(range, dest));
+                }
+                dest.write_str(")")
+            }
+            NonTSPseudoClass::Dir(ref direction) |
+            NonTSPseudoClass::MozLocaleDir(ref direction) => {
+                try!(dest.write_str("("));
+                try!(serialize_identifier(direction.as_str(), dest));
+                dest.write_str(")")
+            }
+            NonTSPseudoClass::NthIndex(a, b, _) => serialize_nth("(", a, b, dest),
+            NonTSPseudoClass::Attr(ref attr, ref operator) => {
+                try!(dest.write_str("["));
+                try!(serialize_identifier(&attr.name, dest));
+                try!(dest.write_str(operator.as_str()));
+                match *operator {
+                    AttrOperator::Exists => {}
+                    AttrOperator::Equal(ref value, case_sensitivity) => {
+                        try!(serialize_string(value, dest));
+                        if case_sensitivity == CaseSensitivity::CaseInsensitive {
+                            try!(dest.write_str(" i"));
+                        }
+                    }
+                    AttrOperator::Includes(ref value) |
+                    AttrOperator::DashMatch(ref value) |
+                    AttrOperator::PrefixMatch(ref value) |
+                    AttrOperator::SubstringMatch(ref value) |
+                    AttrOperator::SuffixMatch(ref value) => try!(serialize_string(value, dest)),
+                }
+                dest.write_str("]")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn pseudo_element_name(pseudo: &PseudoElement) -> &'static str {
+        pseudo.as_str()
+    }
+
+    fn pseudo_element_property_restriction(pseudo: &PseudoElement) -> Option<PropertyRestriction> {
+        match *pseudo {
+            PseudoElement::FirstLetter => Some(PropertyRestriction::FirstLetter),
+            PseudoElement::FirstLine => Some(PropertyRestriction::FirstLine),
+            _ => None,
+        }
+    }
+
+    /// The first letter of a block is in its first line, so ::first-letter sits inside
+    /// ::first-line in the fictional tag sequence and inherits from it.
+    ///
+    /// https://drafts.csswg.org/css-pseudo/#first-text-inheritance
+    fn pseudo_element_inherits_from(pseudo: &PseudoElement) -> Option<PseudoElement> {
+        match *pseudo {
+            PseudoElement::FirstLetter => Some(PseudoElement::FirstLine),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn pseudo_element_is_anonymous_box(pseudo: &PseudoElement) -> bool {
+        pseudo.is_anon_box()
+    }
+
+    #[inline]
+    fn lowercases_ids_and_classes_in_quirks_mode() -> bool {
+        true
+    }
+
+    #[inline]
+    fn nth_index_pseudo_class(a: i32, b: i32, kind: NthIndexKind) -> Option<NonTSPseudoClass> {
+        Some(NonTSPseudoClass::NthIndex(a, b, kind))
+    }
+
+    fn attr_pseudo_class(selector: &SimpleSelector<GeckoSelectorImpl>) -> Option<NonTSPseudoClass> {
+        let (attr, operator) = match *selector {
+            SimpleSelector::AttrExists(ref attr) => (attr, AttrOperator::Exists),
+            SimpleSelector::AttrEqual(ref attr, ref value, case_sensitivity) => {
+                (attr, AttrOperator::Equal(Atom::from(&**value), case_sensitivity))
+            }
+            SimpleSelector::AttrIncludes(ref attr, ref value) => (attr, AttrOperator::Includes(Atom::from(&**value))),
+            SimpleSelector::AttrDashMatch(ref attr, ref value, _) => {
+                (attr, AttrOperator::DashMatch(Atom::from(&**value)))
+            }
+            SimpleSelector::AttrPrefixMatch(ref attr, ref value) => {
+                (attr, AttrOperator::PrefixMatch(Atom::from(&**value)))
+            }
+            SimpleSelector::AttrSubstringMatch(ref attr, ref value) => {
+                (attr, AttrOperator::SubstringMatch(Atom::from(&**value)))
+            }
+            SimpleSelector::AttrSuffixMatch(ref attr, ref value) => {
+                (attr, AttrOperator::SuffixMatch(Atom::from(&**value)))
+            }
+            _ => return None,
+        };
+        Some(NonTSPseudoClass::Attr(attr.clone(), operator))
+    }
 }
@@ -0,0 +1,139 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Parses stylesheets on a thread of their own, so that big sheets don't hold up Gecko's
+//! main thread. Gecko is called back on that thread once a sheet is parsed, and bounces the
+//! sheet to the main thread itself.
+
+#![allow(unsafe_code)]
+
+use bindings::Gecko_StyleSheetLoadComplete;
+use ffi;
+use ownership::Strong;
+use selector_impl::{GeckoSelectorImpl, ImportRule, Stylesheet};
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use style::error_reporting::StdoutErrorReporter;
+use style::parser::QuirksMode;
+use style::stylesheets::{Origin, StylesheetLoader};
+use style::url_value::UrlExtraData;
+use url::Url;
+
+struct ParseJob {
+    input: String,
+    url: Url,
+    origin: Origin,
+    quirks_mode: QuirksMode,
+    extra_data: UrlExtraData,
+    /// The context Gecko passed, by address so that it can cross threads.
+    context: usize,
+}
+
+/// Gives the url values of a sheet parsed off the main thread their extra data, without
+/// loading the sheets its `@import` rules refer to.
+struct ExtraDataOnlyLoader(UrlExtraData);
+
+impl StylesheetLoader<GeckoSelectorImpl> for ExtraDataOnlyLoader {
+    fn request_stylesheet(&self, _import: &Arc<ImportRule>, _media: &str) {}
+
+    fn url_extra_data(&self) -> UrlExtraData {
+        self.0.clone()
+    }
+}
+
+pub struct SheetParser {
+    /// Where parses are queued, or None once the parser has shut down.
+    jobs: Option<Sender<ParseJob>>,
+
+    /// The contexts of the parses Gecko hasn't been called back for and hasn't cancelled,
+    /// with the address of the style set each is for, or zero. The parser thread holds the
+    /// lock while it calls Gecko back, so a cancel either comes first or waits for the
+    /// callback to return.
+    pending: Arc<Mutex<HashMap<usize, usize>>>,
+
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SheetParser {
+    pub fn new() -> SheetParser {
+        let (sender, receiver) = channel::<ParseJob>();
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let thread_pending = pending.clone();
+        let thread = thread::Builder::new().name("StyleSheetParser".to_owned()).spawn(move || {
+            for job in receiver {
+                if !thread_pending.lock().unwrap().contains_key(&job.context) {
+                    continue;
+                }
+                let ParseJob { input, url, origin, quirks_mode, extra_data, context } = job;
+                // FIXME: Gecko's error reporting and @import loads are main thread only, so
+                // errors go to stdout, and the sheet's @import rules are never loaded.
+                let sheet = ffi::catch_panics("StyleSheetParser", move || {
+                    let loader = ExtraDataOnlyLoader(extra_data);
+                    let sheet = Stylesheet::from_str_with_loader(&input, url, origin, Box::new(StdoutErrorReporter),
+                                                                  Some(&loader), quirks_mode);
+                    Strong::from_arc(Arc::new(sheet))
+                }, Strong::null);
+                let mut pending = thread_pending.lock().unwrap();
+                if pending.remove(&context).is_some() {
+                    unsafe { Gecko_StyleSheetLoadComplete(context as *mut c_void, sheet) };
+                } else {
+                    let _ = sheet.into_arc::<Stylesheet>();
+                }
+            }
+        }).unwrap();
+        SheetParser {
+            jobs: Some(sender),
+            pending: pending,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues |input| to be parsed for the style set at address |set|, or for none if it's
+    /// zero. Gecko gets the sheet through Gecko_StyleSheetLoadComplete, with |context|, unless
+    /// the parse is cancelled first. The sheet is null if parsing it panicked. |context|
+    /// mustn't be used for another parse until then.
+    pub fn parse(&self, input: String, url: Url, origin: Origin, quirks_mode: QuirksMode, extra_data: UrlExtraData,
+                 context: *mut c_void, set: usize) {
+        let context = context as usize;
+        let previous = self.pending.lock().unwrap().insert(context, set);
+        assert!(previous.is_none(), "Parsing two sheets with the same context at once");
+        self.jobs.as_ref().expect("The sheet parser has shut down").send(ParseJob {
+            input: input,
+            url: url,
+            origin: origin,
+            quirks_mode: quirks_mode,
+            extra_data: extra_data,
+            context: context,
+        }).unwrap();
+    }
+
+    /// Cancels the parse Gecko passed |context| for. Returns true if Gecko won't be called
+    /// back for it, and false if it already has been.
+    pub fn cancel(&self, context: *mut c_void) -> bool {
+        self.pending.lock().unwrap().remove(&(context as usize)).is_some()
+    }
+
+    /// Cancels the parses for the style set at address |set|. Once this returns, Gecko won't
+    /// be called back for any of them.
+    pub fn cancel_for_set(&self, set: usize) {
+        let mut pending = self.pending.lock().unwrap();
+        let cancelled: Vec<usize> = pending.iter().filter(|&(_, &parse_set)| parse_set == set)
+                                           .map(|(&context, _)| context).collect();
+        for context in cancelled {
+            pending.remove(&context);
+        }
+    }
+
+    /// Cancels every parse that hasn't finished and waits for the parser thread to stop.
+    pub fn shutdown(&mut self) {
+        self.pending.lock().unwrap().clear();
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
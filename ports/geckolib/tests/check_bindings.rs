@@ -0,0 +1,559 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Checks the functions in bindings/mod.rs against the C++ declarations in
+//! bindings/ServoBindings.h, so that a binding that changed on one side only
+//! fails here rather than at runtime in Gecko.
+//!
+//! Both are parsed just far enough for the declarations they contain: types are
+//! compared by name once aliases are resolved, and pointers by whether what they
+//! point to may be mutated.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+const BINDINGS: &'static str = include_str!("../bindings/mod.rs");
+const STRUCTS: &'static str = include_str!("../bindings/structs.rs");
+const STRUCTS_FFI: &'static str = include_str!("../bindings/structs_ffi.rs");
+const HEADER: &'static str = include_str!("../bindings/ServoBindings.h");
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Type {
+    Named(String),
+    Pointer { mutable: bool, pointee: Box<Type> },
+}
+
+impl Type {
+    fn named(name: &str) -> Type {
+        Type::Named(name.to_owned())
+    }
+
+    fn pointer(mutable: bool, pointee: Type) -> Type {
+        Type::Pointer { mutable: mutable, pointee: Box::new(pointee) }
+    }
+
+    /// Whether the two types are the same but for what their pointers allow.
+    fn same_but_for_mutability(&self, other: &Type) -> bool {
+        match (self, other) {
+            (&Type::Named(ref a), &Type::Named(ref b)) => a == b,
+            (&Type::Pointer { pointee: ref a, .. }, &Type::Pointer { pointee: ref b, .. }) =>
+                a.same_but_for_mutability(b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Named(ref name) => write!(f, "{}", name),
+            Type::Pointer { mutable: true, ref pointee } => write!(f, "*mut {}", pointee),
+            Type::Pointer { mutable: false, ref pointee } => write!(f, "*const {}", pointee),
+        }
+    }
+}
+
+const PRIMITIVES: &'static [&'static str] = &[
+    "void", "bool", "c_char", "u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64",
+    "usize", "isize",
+];
+
+const C_PRIMITIVES: &'static [(&'static str, &'static str)] = &[
+    ("void", "void"), ("bool", "bool"), ("char", "c_char"),
+    ("uint8_t", "u8"), ("uint16_t", "u16"), ("uint32_t", "u32"), ("uint64_t", "u64"),
+    ("int8_t", "i8"), ("int16_t", "i16"), ("int32_t", "i32"), ("int64_t", "i64"),
+    ("float", "f32"), ("double", "f64"), ("size_t", "usize"), ("intptr_t", "isize"),
+];
+
+#[derive(Debug)]
+struct Function {
+    name: String,
+    arguments: Vec<Type>,
+    result: Type,
+}
+
+/// The declarations one side of the bindings makes.
+#[derive(Debug, Default)]
+struct Declarations {
+    /// The types declared, including the targets of the aliases.
+    types: HashSet<String>,
+    aliases: HashMap<String, Type>,
+    functions: Vec<Function>,
+}
+
+impl Declarations {
+    /// Follows aliases until `ty` is in terms of declared or primitive types.
+    fn resolve(&self, ty: &Type) -> Type {
+        match *ty {
+            Type::Named(ref name) => {
+                let mut name = name.clone();
+                let mut seen = HashSet::new();
+                loop {
+                    let target = match self.aliases.get(&name) {
+                        Some(&Type::Named(ref target)) if !seen.contains(target) => target.clone(),
+                        Some(target @ &Type::Pointer { .. }) => return self.resolve(target),
+                        _ => return Type::Named(name),
+                    };
+                    seen.insert(name);
+                    name = target;
+                }
+            }
+            Type::Pointer { mutable, ref pointee } => Type::pointer(mutable, self.resolve(pointee)),
+        }
+    }
+
+    /// The names in `ty` that neither side could know about.
+    fn unknown_names(&self, ty: &Type, unknown: &mut Vec<String>) {
+        match *ty {
+            Type::Named(ref name) => {
+                if !PRIMITIVES.contains(&&**name) && !self.types.contains(name) {
+                    unknown.push(name.clone());
+                }
+            }
+            Type::Pointer { ref pointee, .. } => self.unknown_names(pointee, unknown),
+        }
+    }
+}
+
+/// The last segment of a Rust path, with the C types Rust spells differently
+/// brought to their primitive names.
+fn rust_type(text: &str) -> Type {
+    let text = text.trim();
+    if text.starts_with("*const ") {
+        return Type::pointer(false, rust_type(&text["*const ".len()..]))
+    }
+    if text.starts_with("*mut ") {
+        return Type::pointer(true, rust_type(&text["*mut ".len()..]))
+    }
+    match text.rsplit("::").next().unwrap() {
+        "c_void" | "()" => Type::named("void"),
+        name => Type::named(name),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn strip_rust_comments(source: &str) -> String {
+    source.lines()
+          .map(|line| match line.find("//") {
+              Some(start) => &line[..start],
+              None => line,
+          })
+          .collect::<Vec<_>>()
+          .join("\n")
+}
+
+fn parse_rust_function(declaration: &str) -> Result<Function, String> {
+    let declaration = collapse_whitespace(declaration);
+    let rest = match declaration.find("fn ") {
+        Some(start) => &declaration[start + "fn ".len()..],
+        None => return Err(format!("not a function: {}", declaration)),
+    };
+    let (open, close) = match (rest.find('('), rest.rfind(')')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return Err(format!("no arguments in: {}", declaration)),
+    };
+    let mut arguments = vec![];
+    for argument in rest[open + 1..close].split(',').filter(|a| !a.trim().is_empty()) {
+        match argument.find(':') {
+            Some(colon) => arguments.push(rust_type(&argument[colon + 1..])),
+            None => return Err(format!("unnamed argument in: {}", declaration)),
+        }
+    }
+    let after = rest[close + 1..].trim();
+    let result = if after.starts_with("->") {
+        rust_type(&after["->".len()..])
+    } else {
+        Type::named("void")
+    };
+    Ok(Function { name: rest[..open].trim().to_owned(), arguments: arguments, result: result })
+}
+
+/// Reads the functions out of the `extern "C"` blocks in `bindings`, and the
+/// types out of `structs`.
+fn parse_rust(bindings: &str, structs: &str) -> Result<Declarations, String> {
+    let mut declarations = Declarations::default();
+
+    // Bindgen puts each declaration on a line of its own.
+    for line in strip_rust_comments(structs).lines() {
+        let line = line.trim();
+        let mut words = line.split(|c: char| c == ' ' || c == '{' || c == '=').filter(|w| !w.is_empty());
+        if words.next() != Some("pub") {
+            continue
+        }
+        match words.next() {
+            Some("enum") | Some("struct") => {
+                if let Some(name) = words.next() {
+                    declarations.types.insert(name.to_owned());
+                }
+            }
+            Some("type") => {
+                let equals = try!(line.find('=').ok_or(format!("alias without a target: {}", line)));
+                let name = line["pub type ".len()..equals].trim().to_owned();
+                let target = line[equals + 1..].trim().trim_right_matches(';');
+                declarations.types.insert(name.clone());
+                // Aliases for instantiated generics, like the Strong types, stand for
+                // a type of their own on the C++ side.
+                if !target.contains('<') {
+                    let target = rust_type(target);
+                    if let Type::Named(ref target) = target {
+                        declarations.types.insert(target.clone());
+                    }
+                    if target != Type::Named(name.clone()) {
+                        declarations.aliases.insert(name, target);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let bindings = strip_rust_comments(bindings);
+    let mut rest = &*bindings;
+    while let Some(start) = rest.find("extern \"C\" {") {
+        let block = &rest[start + "extern \"C\" {".len()..];
+        let end = try!(block.find("\n}").ok_or("unterminated extern block".to_owned()));
+        for declaration in block[..end].split(';').filter(|d| !d.trim().is_empty()) {
+            declarations.functions.push(try!(parse_rust_function(declaration)));
+        }
+        rest = &block[end..];
+    }
+    Ok(declarations)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Literal(String),
+    Punctuation(char),
+}
+
+fn strip_c_comments(source: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = source;
+    loop {
+        let line = rest.find("//");
+        let block = rest.find("/*");
+        let (start, end_marker) = match (line, block) {
+            (Some(l), Some(b)) if l < b => (l, "\n"),
+            (Some(l), None) => (l, "\n"),
+            (_, Some(b)) => (b, "*/"),
+            (None, None) => break,
+        };
+        stripped.push_str(&rest[..start]);
+        stripped.push(if end_marker == "\n" { '\n' } else { ' ' });
+        rest = &rest[start..];
+        rest = match rest[2..].find(end_marker) {
+            Some(end) => &rest[2 + end + end_marker.len()..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped.lines()
+            .filter(|line| !line.trim_left().starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n")
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let mut word = c.to_string();
+            while let Some(&next) = chars.peek() {
+                if !next.is_alphanumeric() && next != '_' {
+                    break
+                }
+                word.push(next);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else if c == '"' {
+            let literal = chars.by_ref().take_while(|&c| c != '"').collect();
+            tokens.push(Token::Literal(literal));
+        } else {
+            tokens.push(Token::Punctuation(c));
+        }
+    }
+    tokens
+}
+
+/// Splits the source into the statements at the top level, looking through
+/// `extern "C" { ... }`.
+fn c_statements(source: &str) -> Vec<Vec<Token>> {
+    let tokens = tokenize(&strip_c_comments(source));
+    let mut statements = vec![];
+    let mut statement = vec![];
+    let mut depth = 0;
+    let mut extern_blocks = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        match (&tokens[i], tokens.get(i + 1), tokens.get(i + 2)) {
+            (&Token::Word(ref extern_), Some(&Token::Literal(_)), Some(&Token::Punctuation('{')))
+                if extern_ == "extern" && depth == 0 && statement.is_empty() => {
+                extern_blocks += 1;
+                i += 3;
+                continue
+            }
+            (&Token::Punctuation('}'), _, _) if depth == 0 && extern_blocks > 0 => {
+                extern_blocks -= 1;
+            }
+            (&Token::Punctuation(';'), _, _) if depth == 0 => {
+                statements.push(statement);
+                statement = vec![];
+            }
+            (token, _, _) => {
+                match *token {
+                    Token::Punctuation('{') | Token::Punctuation('(') => depth += 1,
+                    Token::Punctuation('}') | Token::Punctuation(')') => depth -= 1,
+                    _ => {}
+                }
+                statement.push(token.clone());
+            }
+        }
+        i += 1;
+    }
+    statements
+}
+
+/// The type spelled by `tokens`, where each `const` applies to what precedes the
+/// next `*`, as in `const T*` and `T* const*`.
+fn c_type(tokens: &[Token]) -> Result<Type, String> {
+    let mut ty = None;
+    let mut pending_const = false;
+    for token in tokens {
+        match *token {
+            Token::Word(ref word) if word == "const" => pending_const = true,
+            Token::Word(ref word) if word == "struct" || word == "class" || word == "enum" => {}
+            Token::Word(ref word) if ty.is_none() => {
+                let name = C_PRIMITIVES.iter()
+                                       .find(|&&(c, _)| c == word)
+                                       .map_or(&**word, |&(_, rust)| rust);
+                ty = Some(Type::named(name));
+            }
+            Token::Punctuation('*') => {
+                let pointee = try!(ty.take().ok_or(format!("pointer to nothing in {:?}", tokens)));
+                ty = Some(Type::pointer(!pending_const, pointee));
+                pending_const = false;
+            }
+            _ => return Err(format!("unexpected {:?} in type {:?}", token, tokens)),
+        }
+    }
+    ty.ok_or(format!("no type in {:?}", tokens))
+}
+
+fn word(token: Option<&Token>) -> Option<&str> {
+    match token {
+        Some(&Token::Word(ref word)) => Some(word),
+        _ => None,
+    }
+}
+
+/// Splits a declarator into its type and, if it has one, its name.
+fn c_declarator(tokens: &[Token]) -> Result<(Type, Option<String>), String> {
+    let named = tokens.len() > 1 && match word(tokens.last()) {
+        Some(last) => last != "const" && C_PRIMITIVES.iter().all(|&(c, _)| c != last) &&
+                      tokens[..tokens.len() - 1].iter().any(|t| match *t {
+                          Token::Word(ref w) => w != "const" && w != "struct",
+                          _ => false,
+                      }),
+        None => false,
+    };
+    if named {
+        let name = word(tokens.last()).unwrap().to_owned();
+        Ok((try!(c_type(&tokens[..tokens.len() - 1])), Some(name)))
+    } else {
+        Ok((try!(c_type(tokens)), None))
+    }
+}
+
+fn parse_c(source: &str) -> Result<Declarations, String> {
+    let mut declarations = Declarations::default();
+    for statement in c_statements(source) {
+        match word(statement.first()) {
+            Some("struct") | Some("class") | Some("enum") => {
+                let name = match word(statement.get(1)) {
+                    Some("class") | Some("struct") => word(statement.get(2)),
+                    name => name,
+                };
+                let name = try!(name.ok_or(format!("unnamed type in {:?}", statement)));
+                declarations.types.insert(name.to_owned());
+            }
+            Some("typedef") => {
+                let declarator = &statement[1..];
+                if declarator.contains(&Token::Punctuation('(')) {
+                    // A function pointer, `typedef R (*Name)(...)`.
+                    let name = declarator.windows(2)
+                                         .find(|w| w[0] == Token::Punctuation('*'))
+                                         .and_then(|w| word(Some(&w[1])));
+                    let name = try!(name.ok_or(format!("unnamed function pointer in {:?}", statement)));
+                    declarations.types.insert(name.to_owned());
+                } else {
+                    let (target, name) = try!(c_declarator(declarator));
+                    let name = try!(name.ok_or(format!("unnamed typedef in {:?}", statement)));
+                    declarations.types.insert(name.clone());
+                    declarations.aliases.insert(name, target);
+                }
+            }
+            _ => {
+                let open = try!(statement.iter()
+                                         .position(|t| *t == Token::Punctuation('('))
+                                         .ok_or(format!("not a declaration: {:?}", statement)));
+                let (result, name) = try!(c_declarator(&statement[..open]));
+                let name = try!(name.ok_or(format!("unnamed function in {:?}", statement)));
+                let close = statement.len() - 1;
+                if statement[close] != Token::Punctuation(')') {
+                    return Err(format!("{} doesn't end in its arguments", name))
+                }
+                let mut arguments = vec![];
+                let list = &statement[open + 1..close];
+                if list.len() != 1 || word(list.first()) != Some("void") {
+                    for argument in list.split(|t| *t == Token::Punctuation(',')).filter(|a| !a.is_empty()) {
+                        arguments.push(try!(c_declarator(argument)).0);
+                    }
+                }
+                declarations.functions.push(Function { name: name, arguments: arguments, result: result });
+            }
+        }
+    }
+    Ok(declarations)
+}
+
+/// Everything that differs between the Rust and the C++ declarations.
+fn compare(rust: &Declarations, c: &Declarations) -> Vec<String> {
+    let mut errors = vec![];
+    let c_functions: HashMap<&str, &Function> = c.functions.iter().map(|f| (&*f.name, f)).collect();
+    let rust_functions: HashSet<&str> = rust.functions.iter().map(|f| &*f.name).collect();
+
+    for function in &c.functions {
+        if !rust_functions.contains(&*function.name) {
+            errors.push(format!("{} is declared in ServoBindings.h but not in bindings/mod.rs", function.name));
+        }
+    }
+
+    for function in &rust.functions {
+        let c_function = match c_functions.get(&*function.name) {
+            Some(c_function) => c_function,
+            None => {
+                errors.push(format!("{} is declared in bindings/mod.rs but not in ServoBindings.h",
+                                    function.name));
+                continue
+            }
+        };
+        if function.arguments.len() != c_function.arguments.len() {
+            errors.push(format!("{} takes {} arguments in bindings/mod.rs but {} in ServoBindings.h",
+                                function.name, function.arguments.len(), c_function.arguments.len()));
+            continue
+        }
+
+        let mut pairs: Vec<(String, &Type, &Type)> =
+            function.arguments.iter().zip(&c_function.arguments).enumerate()
+                    .map(|(i, (r, c))| (format!("argument {}", i + 1), r, c))
+                    .collect();
+        pairs.push(("the result".to_owned(), &function.result, &c_function.result));
+
+        for (what, ours, theirs) in pairs {
+            let (rust_type, c_type) = (rust.resolve(ours), c.resolve(theirs));
+            let mut unknown = vec![];
+            rust.unknown_names(&rust_type, &mut unknown);
+            c.unknown_names(&c_type, &mut unknown);
+            if !unknown.is_empty() {
+                errors.push(format!("{} of {} uses undeclared types: {}", what, function.name, unknown.join(", ")));
+            } else if rust_type.same_but_for_mutability(&c_type) && rust_type != c_type {
+                errors.push(format!("{} of {} is {} in bindings/mod.rs but {} in ServoBindings.h, \
+                                     which differ in what they let be mutated",
+                                    what, function.name, rust_type, c_type));
+            } else if rust_type != c_type {
+                errors.push(format!("{} of {} is {} in bindings/mod.rs but {} in ServoBindings.h",
+                                    what, function.name, rust_type, c_type));
+            }
+        }
+    }
+    errors
+}
+
+fn check(bindings: &str, structs: &str, header: &str) -> Vec<String> {
+    match (parse_rust(bindings, structs), parse_c(header)) {
+        (Ok(rust), Ok(c)) => compare(&rust, &c),
+        (Err(error), _) => vec![format!("couldn't parse the Rust bindings: {}", error)],
+        (_, Err(error)) => vec![format!("couldn't parse ServoBindings.h: {}", error)],
+    }
+}
+
+#[test]
+fn bindings_match_servo_bindings_header() {
+    let errors = check(BINDINGS, &format!("{}\n{}", STRUCTS, STRUCTS_FFI), HEADER);
+    assert!(errors.is_empty(), "bindings/mod.rs and ServoBindings.h disagree:\n{}", errors.join("\n"));
+}
+
+const SNIPPET_STRUCTS: &'static str = "
+pub enum RawServoStyleSheet { }
+pub enum nsINode { }
+pub type RawGeckoNode = nsINode;
+";
+
+const SNIPPET_HEADER: &'static str = "
+struct RawServoStyleSheet;
+struct nsINode;
+typedef nsINode RawGeckoNode;
+extern \"C\" {
+/* Comments don't count. */
+bool Servo_StyleSheetHasRules(RawServoStyleSheet* sheet);
+void Servo_StyleSet_ReplaceStyleSheets(RawServoStyleSheet* const* sheets, uint32_t length);
+uint32_t Gecko_ChildrenCount(const RawGeckoNode* node);
+} // extern \"C\"
+";
+
+fn check_snippet(functions: &str) -> Vec<String> {
+    check(&format!("extern \"C\" {{\n{}\n}}\n", functions), SNIPPET_STRUCTS, SNIPPET_HEADER)
+}
+
+#[test]
+fn matching_snippets_check_out() {
+    let errors = check_snippet("
+    pub fn Servo_StyleSheetHasRules(sheet: *mut RawServoStyleSheet) -> bool;
+    pub fn Servo_StyleSet_ReplaceStyleSheets(sheets: *const *mut RawServoStyleSheet, length: u32);
+    pub fn Gecko_ChildrenCount(node: *const nsINode) -> u32;");
+    assert_eq!(errors, Vec::<String>::new());
+}
+
+#[test]
+fn const_and_mut_pointers_are_told_apart() {
+    let errors = check_snippet("
+    pub fn Servo_StyleSheetHasRules(sheet: *const RawServoStyleSheet) -> bool;
+    pub fn Servo_StyleSet_ReplaceStyleSheets(sheets: *mut *mut RawServoStyleSheet, length: u32);
+    pub fn Gecko_ChildrenCount(node: *const RawGeckoNode) -> u32;");
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors[0].contains("Servo_StyleSheetHasRules") && errors[0].contains("mutated"));
+    assert!(errors[1].contains("Servo_StyleSet_ReplaceStyleSheets") && errors[1].contains("mutated"));
+}
+
+#[test]
+fn missing_functions_and_arguments_are_reported() {
+    let errors = check_snippet("
+    pub fn Servo_StyleSheetHasRules(sheet: *mut RawServoStyleSheet, extra: u32) -> bool;
+    pub fn Servo_RustOnly();
+    pub fn Gecko_ChildrenCount(node: *const RawGeckoNode) -> u8;");
+    assert_eq!(errors.len(), 4, "{:?}", errors);
+    assert!(errors[0].contains("Servo_StyleSet_ReplaceStyleSheets is declared in ServoBindings.h"));
+    assert!(errors[1].contains("Servo_StyleSheetHasRules takes 2 arguments"));
+    assert!(errors[2].contains("Servo_RustOnly is declared in bindings/mod.rs"));
+    assert!(errors[3].contains("the result of Gecko_ChildrenCount is u8"));
+}
+
+#[test]
+fn undeclared_types_are_reported() {
+    let errors = check_snippet("
+    pub fn Servo_StyleSheetHasRules(sheet: *mut RawServoStyleSheet) -> bool;
+    pub fn Servo_StyleSet_ReplaceStyleSheets(sheets: *const *mut RawServoStyleSheet, length: u32);
+    pub fn Gecko_ChildrenCount(node: *const nsIContent) -> u32;");
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].contains("undeclared types: nsIContent"));
+}
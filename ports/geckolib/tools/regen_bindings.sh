@@ -34,7 +34,26 @@ fi
 ./rust-bindgen/target/debug/bindgen                \
   -x c++ -std=gnu++0x                              \
   "-I$DIST_INCLUDE"                                \
-  -o ../bindings.rs                                \
+  -o bindings.rs.tmp                               \
   -no-type-renaming                                \
   "$DIST_INCLUDE/mozilla/ServoBindings.h"          \
-  -match "ServoBindings.h"                         \
+  -match "ServoBindings.h"
+
+# Only the types are kept. The functions in ../bindings/mod.rs are kept by hand,
+# and ../tests/check_bindings.rs checks them against ../bindings/ServoBindings.h,
+# so new declarations need adding to both.
+#
+# The structs whose fields both sides use are defined in ../bindings/structs_ffi.rs
+# instead, which has their layouts checked against ServoBindings.h, so bindgen's
+# definitions of them and its layout tests for them are dropped too. Attributes are
+# held back until it's known whether what they're on is kept.
+FFI_STRUCTS="$(sed -n 's/^    pub struct \([A-Za-z0-9_]*\) {$/\1/p' ../bindings/structs_ffi.rs | paste -sd '|' -)"
+awk -v ffi="^(pub struct |fn bindgen_test_layout_)($FFI_STRUCTS)[ (]" '
+  /^extern "C" \{$/ { skip = 1 }
+  drop { if (/^\}$/) drop = 0; next }
+  !skip && /^#\[/ { held = held $0 "\n"; next }
+  !skip && $0 ~ ffi { held = ""; if ($0 !~ /\}$/) drop = 1; next }
+  !skip { printf "%s", held; held = ""; print }
+  skip && /^\}$/ { skip = 0; held = "" }
+' bindings.rs.tmp > ../bindings/structs.rs
+rm bindings.rs.tmp
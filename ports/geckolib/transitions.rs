@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Tells Gecko which transitions a restyle starts. Gecko runs them itself.
+
+#![allow(unsafe_code)]
+
+use app_units::Au;
+use bindings::{Gecko_StartTransition, RawGeckoElement, RawServoAnimationValue};
+use cssparser::ToCss;
+use data::GlobalStyleData;
+use euclid::size::Size2D;
+use glue::timing_function_to_ffi;
+use ownership::Borrowed;
+use properties::{GeckoComputedValues, longhand_to_gecko};
+use std::sync::Arc;
+use style::animation::{AnimationValue, single_transitions};
+use style::properties::ComputedValues;
+use style::properties::longhands::display::computed_value::T as Display;
+use style::properties::style_struct_traits::{TAnimation, TBox};
+
+/// Calls Gecko_StartTransition for each longhand `transition-property` names on the element
+/// whose value went from `old_style` to `new_style` in a way that can be interpolated.
+///
+/// `from` is the value in `old_style`, which doesn't include the progress of any transition
+/// Gecko was already running on the property. To reverse a transition rather than start it
+/// over, Gecko can check whether `to` is the value the running one started from with
+/// Servo_AnimationValue_DeepEqual, and if so, start from where the running one is and
+/// shorten the new one by how far it got.
+///
+/// FIXME: Only the longhands Servo can read back out of Gecko's style structs transition.
+pub fn start_transitions(element: *mut RawGeckoElement,
+                         old_style: &GeckoComputedValues,
+                         new_style: &GeckoComputedValues,
+                         viewport_size: Size2D<Au>) {
+    // Nothing transitions into or out of display: none.
+    if old_style.get_box().clone_display() == Display::none ||
+       new_style.get_box().clone_display() == Display::none {
+        return
+    }
+
+    let animation = new_style.get_animation();
+    let transitions = single_transitions(&animation.clone_transition_property(),
+                                         &animation.clone_transition_duration(),
+                                         &animation.clone_transition_delay(),
+                                         &animation.clone_transition_timing_function());
+    let base_url = &GlobalStyleData::get().dummy_url;
+    for transition in transitions {
+        let name = transition.property.to_css_string();
+        let gecko_property = match longhand_to_gecko(&name) {
+            Some(gecko_property) => gecko_property,
+            None => continue,
+        };
+        let (from, to) = match (old_style.resolved_value_to_string(&name),
+                                new_style.resolved_value_to_string(&name)) {
+            (Ok(ref from), Ok(ref to)) if from != to => (from.clone(), to.clone()),
+            _ => continue,
+        };
+        let (from, to) = match (AnimationValue::from_css(transition.property, &from, base_url, viewport_size),
+                                AnimationValue::from_css(transition.property, &to, base_url, viewport_size)) {
+            (Some(from), Some(to)) => (Arc::new(from), Arc::new(to)),
+            _ => continue,
+        };
+        if !from.can_interpolate(&to) {
+            continue
+        }
+
+        debug!("Starting a transition of {} from {} to {}", name, from.serialize(), to.serialize());
+        let timing_function = timing_function_to_ffi(transition.timing_function);
+        unsafe {
+            Gecko_StartTransition(element,
+                                  gecko_property,
+                                  Borrowed::<RawServoAnimationValue>::from_arc(&from).as_ptr(),
+                                  Borrowed::<RawServoAnimationValue>::from_arc(&to).as_ptr(),
+                                  transition.duration.seconds() * 1000.,
+                                  transition.delay.seconds() * 1000.,
+                                  &timing_function);
+        }
+    }
+}
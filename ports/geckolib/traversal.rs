@@ -2,20 +2,52 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use app_units::Au;
+use euclid::size::Size2D;
 use properties::GeckoComputedValues;
+use restyle_damage::{GeckoRestyleDamage, RECONSTRUCT_FRAME};
 use selector_impl::{GeckoSelectorImpl, SharedStyleContext};
+use selectors::matching::DeclarationBlock;
 use std::cell::RefCell;
 use std::mem;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, AtomicBool, Ordering};
 use style::context::{LocalStyleContext, StyleContext};
-use style::dom::OpaqueNode;
-use style::matching::{ApplicableDeclarationsCache, StyleSharingCandidateCache};
-use style::traversal::{DomTraversalContext, recalc_style_at};
-use wrapper::GeckoNode;
+use style::dom::{OpaqueNode, TElement, TNode, TRestyleDamage};
+use style::error_reporting::StdoutErrorReporter;
+use style::matching::{ApplicableDeclarationsCache, MatchMethods, StyleSharingCandidateCache};
+use style::properties::{ComputedValues, PropertyDeclaration, cascade, inherit_from};
+use style::properties::style_struct_traits::TBox;
+use style::restyle_hints::RESTYLE_SELF;
+use style::rule_tree::{RuleNodeStyleCache, RuleTree};
+use style::scratch::ScratchArena;
+use style::selector_impl::ElementExt;
+use style::selector_matching::CascadeLevel;
+use style::shared_lock::SharedRwLockReadGuard;
+use style::traversal::{DomTraversalContext, pop_thread_local_bloom_filter, recalc_style_at};
+use style::traversal_stats;
+use transitions::start_transitions;
+use wrapper::{GeckoElement, GeckoNode, with_relevant_link};
+
+/// Whether traversals run in the deterministic debugging mode Servo_SetDeterministicTraversal
+/// turns on: on the calling thread, in document order, starting from empty caches that hash
+/// the same way every time.
+pub static DETERMINISTIC_TRAVERSAL: AtomicBool = ATOMIC_BOOL_INIT;
+
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC_TRAVERSAL.load(Ordering::Relaxed)
+}
 
 thread_local!(static LOCAL_CONTEXT_KEY:
                 RefCell<Option<Rc<LocalStyleContext<GeckoComputedValues>>>> = RefCell::new(None));
 
+/// Forgets the calling thread's caches, so that the next traversal on it can't share styles
+/// or reuse cascades from an earlier one.
+pub fn drop_local_context() {
+    LOCAL_CONTEXT_KEY.with(|r| *r.borrow_mut() = None);
+}
+
 // Keep this implementation in sync with the one in components/layout/context.rs.
 fn create_or_get_local_context(shared: &SharedStyleContext)
                                -> Rc<LocalStyleContext<GeckoComputedValues>> {
@@ -24,12 +56,20 @@ fn create_or_get_local_context(shared: &SharedStyleContext)
         if let Some(context) = r.clone() {
             if shared.screen_size_changed {
                 context.applicable_declarations_cache.borrow_mut().evict_all();
+                context.rule_node_style_cache.borrow_mut().evict_all();
             }
             context
         } else {
+            let applicable_declarations_cache = if is_deterministic() {
+                ApplicableDeclarationsCache::with_hash_keys(0, 0)
+            } else {
+                ApplicableDeclarationsCache::new()
+            };
             let context = Rc::new(LocalStyleContext {
-                applicable_declarations_cache: RefCell::new(ApplicableDeclarationsCache::new()),
+                applicable_declarations_cache: RefCell::new(applicable_declarations_cache),
                 style_sharing_candidate_cache: RefCell::new(StyleSharingCandidateCache::new()),
+                rule_node_style_cache: RefCell::new(RuleNodeStyleCache::new()),
+                scratch: RefCell::new(ScratchArena::new()),
             });
             *r = Some(context.clone());
             context
@@ -80,7 +120,385 @@ impl<'lc, 'ln> DomTraversalContext<GeckoNode<'ln>> for RecalcStyleOnly<'lc> {
         }
     }
 
-    fn process_preorder(&self, node: GeckoNode<'ln>) { recalc_style_at(&self.context, self.root, node); }
-    fn process_postorder(&self, _: GeckoNode<'ln>) {}
+    /// The top-down step: styles the node once its parent is styled. Text nodes are left
+    /// to the bottom-up step.
+    fn process_preorder(&self, node: GeckoNode<'ln>) {
+        // Nodes Servo_ClearNodeStyleData left with just their previous style need matching
+        // from scratch, whatever their restyle bits say.
+        let has_previous_style = unsafe { node.get_node_data().as_ref() }
+            .map_or(false, |data| data.previous_style.borrow().is_some());
+        if has_previous_style && !node.is_dirty() {
+            unsafe { node.set_dirty(true) };
+        }
+        let dirty = node.is_dirty();
+        let style_before_restyle = if dirty && !node.is_text_node() {
+            node.borrow_data().and_then(|data| data.style.clone())
+        } else {
+            None
+        };
+        with_relevant_link(relevant_link(&self.context, self.root, node),
+                           || recalc_style_at(&self.context, self.root, node));
+        if let Some(data) = unsafe { node.get_node_data().as_ref() } {
+            data.descendant_damage.store(0, Ordering::Relaxed);
+            data.style_generation.set(self.context.shared.style_generation);
+            *data.style_before_restyle.borrow_mut() = style_before_restyle;
+        }
+    }
+
+    /// The bottom-up step, once everything under the node is done: styles text nodes, starts
+    /// the transitions of elements that had a style before, gathers the damage of the node's
+    /// subtree into its parent, and clears the node's dirty bits.
+    #[allow(unsafe_code)]
+    fn process_postorder(&self, node: GeckoNode<'ln>) {
+        if node.is_text_node() && node.is_dirty() {
+            inherit_text_style(self.root, node);
+        }
+
+        // What changed since the styles Servo_ClearNodeStyleData kept, now that the node has
+        // new ones, rather than the frame reconstruction a node styled for the first time gets.
+        let previous_style = unsafe { node.get_node_data().as_ref() }
+            .and_then(|data| data.previous_style.borrow_mut().take());
+        if let Some(previous_style) = previous_style {
+            if let Some(new_style) = node.borrow_data().and_then(|data| data.style.clone()) {
+                node.set_restyle_damage(GeckoRestyleDamage::compute(Some(&previous_style), &new_style));
+            }
+        }
+
+        if let Some(element) = node.as_element() {
+            let style_before_restyle = unsafe { node.get_node_data().as_ref() }
+                .and_then(|data| data.style_before_restyle.borrow_mut().take());
+            // Elements styled for the first time have nothing to transition from.
+            if let Some(old_style) = style_before_restyle {
+                if let Some(new_style) = node.borrow_data().and_then(|data| data.style.clone()) {
+                    start_transitions(element.as_raw(), &old_style, &new_style, self.context.shared.viewport_size);
+                }
+            }
+        }
+
+        // Servo does this during flow construction. We have no flows, so pop the node
+        // off the ancestor bloom filter directly.
+        pop_thread_local_bloom_filter(&self.context, self.root, node);
+
+        let descendant_damage = unsafe { node.get_node_data().as_ref() }.map_or(GeckoRestyleDamage::empty(), |data| {
+            GeckoRestyleDamage::from_bits_truncate(data.descendant_damage.load(Ordering::Relaxed) as u32)
+        });
+        if descendant_damage.contains(RECONSTRUCT_FRAME) {
+            unsafe { node.set_descendants_need_frames(true) };
+        }
+        // The parent of the traversal root isn't part of the traversal, so its count of its
+        // descendants' damage wasn't reset.
+        if node.opaque() != self.root {
+            let parent_data = node.layout_parent_node(self.root)
+                                  .and_then(|parent| unsafe { parent.get_node_data().as_ref() });
+            if let Some(parent_data) = parent_data {
+                let subtree_damage = node.restyle_damage() | descendant_damage;
+                parent_data.descendant_damage.fetch_or(subtree_damage.bits() as usize, Ordering::Relaxed);
+            }
+        }
+
+        // Everything under the node is done too, so it's clean.
+        unsafe {
+            node.set_dirty(false);
+            node.set_dirty_descendants(false);
+        }
+    }
+    fn should_traverse_child(&self, node: GeckoNode<'ln>) -> bool {
+        // Native anonymous content that Gecko styles itself, with Servo_ResolveStyleForNAC,
+        // doesn't inherit from the parent the traversal would give it.
+        if let Some(node_data) = unsafe { node.get_node_data().as_ref() } {
+            if node_data.nac_parent_style.borrow().is_some() {
+                return false;
+            }
+        }
+        node.is_dirty() || node.has_dirty_descendants()
+    }
+}
+
+/// Gives a text node a style that only inherits from its parent's, rather than the copy of
+/// the parent's style the shared traversal leaves it with, which would also carry over the
+/// parent's non-inherited properties. Inside links, it takes the parent's visited style too.
+fn inherit_text_style<'ln>(root: OpaqueNode, node: GeckoNode<'ln>) {
+    let parent_style = node.layout_parent_node(root)
+                           .and_then(|parent| parent.borrow_data().and_then(|data| data.style.clone()));
+    let style = match parent_style {
+        Some(ref parent_style) => {
+            let mut style = inherit_from(&**parent_style);
+            if let Some(ref visited_style) = parent_style.visited_style {
+                style.visited_style = Some(visited_style.clone());
+            }
+            style
+        }
+        None => inherit_from(GeckoComputedValues::initial_values()),
+    };
+    if let Some(mut data) = node.mutate_data() {
+        data.style = Some(Arc::new(style));
+    }
+}
+
+/// The relevant link of |node|, when some rules tell visited links apart: the nearest link
+/// among it and its ancestors. Only links and the elements whose parent has a visited style,
+/// which are those inside links, look for one, so the rest of the tree pays nothing for it.
+fn relevant_link<'ln>(context: &StandaloneStyleContext, root: OpaqueNode, node: GeckoNode<'ln>)
+                      -> Option<GeckoElement<'ln>> {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return None,
+    };
+    let stylist = unsafe { &*context.shared.stylist.0 };
+    if !stylist.has_link_dependent_rules() {
+        return None;
+    }
+    if element.is_link() {
+        return Some(element);
+    }
+    let mut ancestor = match node.layout_parent_node(root) {
+        Some(parent) => parent,
+        None => return None,
+    };
+    let in_link = ancestor.borrow_data().map_or(false, |data| {
+        data.style.as_ref().map_or(false, |style| style.visited_style.is_some())
+    });
+    if !in_link {
+        return None;
+    }
+    loop {
+        if let Some(element) = ancestor.as_element() {
+            if element.is_link() {
+                return Some(element);
+            }
+        }
+        ancestor = match ancestor.layout_parent_node(root) {
+            Some(parent) => parent,
+            None => return None,
+        };
+    }
 }
 
+/// The animation-only restyle: re-cascades the elements under `node` that Gecko flagged as
+/// having animations, with the current values of their animations in place of the ones they
+/// were last cascaded with, and without matching any selectors. It only goes into the
+/// subtrees Gecko flagged as having animated elements, so a frame costs as much as the
+/// animated elements and their ancestors. Returns the damage of `node` and its descendants.
+///
+/// Elements whose matched blocks aren't known, because of there being no rule tree or
+/// because they shared a sibling's style, are left for the next full restyle instead, as
+/// are the descendants of elements whose animations change inherited properties. `guard`
+/// holds the lock of the blocks with the animation values.
+pub fn restyle_for_animations<'ln>(node: GeckoNode<'ln>, root: OpaqueNode, rule_tree: Option<&RuleTree>,
+                                   viewport_size: Size2D<Au>, guard: &SharedRwLockReadGuard)
+                                   -> GeckoRestyleDamage {
+    traversal_stats::record(|stats| stats.elements_traversed += 1);
+    let damage = if node.has_animations() {
+        cascade_animations(node, root, rule_tree, viewport_size, guard)
+    } else {
+        GeckoRestyleDamage::empty()
+    };
+    if !node.has_animated_descendants() {
+        return damage;
+    }
+
+    let mut descendant_damage = GeckoRestyleDamage::empty();
+    for child in node.children() {
+        if child.has_animations() || child.has_animated_descendants() {
+            let child_damage = restyle_for_animations(child, root, rule_tree, viewport_size, guard);
+            descendant_damage = descendant_damage | child_damage;
+        }
+    }
+    if let Some(data) = unsafe { node.get_node_data().as_ref() } {
+        data.descendant_damage.store(descendant_damage.bits() as usize, Ordering::Relaxed);
+    }
+    if descendant_damage.contains(RECONSTRUCT_FRAME) {
+        unsafe { node.set_descendants_need_frames(true) };
+    }
+    damage | descendant_damage
+}
+
+/// Re-cascades an element for `restyle_for_animations`. The new style only differs from the
+/// old one in the structs holding animated properties, which keeps the damage to those.
+fn cascade_animations<'ln>(node: GeckoNode<'ln>, root: OpaqueNode, rule_tree: Option<&RuleTree>,
+                           viewport_size: Size2D<Au>, guard: &SharedRwLockReadGuard) -> GeckoRestyleDamage {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return GeckoRestyleDamage::empty(),
+    };
+    let matched = node.borrow_data().and_then(|data| match (&data.style, &data.rule_node) {
+        (&Some(ref style), &Some(ref rule_node)) => Some((style.clone(), rule_node.clone())),
+        _ => None,
+    });
+    let (old_style, rule_node, rule_tree) = match (matched, rule_tree) {
+        (Some((style, rule_node)), Some(rule_tree)) => (style, rule_node, rule_tree),
+        _ => {
+            element.note_restyle_hint(RESTYLE_SELF);
+            return GeckoRestyleDamage::empty();
+        }
+    };
+
+    // The node's blocks at the animation levels are swapped for the current values.
+    let animation_declarations: Vec<(CascadeLevel, Vec<DeclarationBlock<Vec<PropertyDeclaration>>>)> =
+        CascadeLevel::animations().iter().map(|&level| {
+            let mut blocks = vec![];
+            element.get_animation_declarations(level, guard, &mut blocks);
+            (level, blocks)
+        }).collect();
+    let new_rule_node = rule_tree.replace_levels(&rule_node, &animation_declarations);
+    let declarations = new_rule_node.path();
+
+    let parent_style = node.layout_parent_node(root)
+                           .and_then(|parent| parent.borrow_data().and_then(|data| data.style.clone()));
+    let (animated_style, _) = cascade(viewport_size,
+                                      &declarations,
+                                      false,
+                                      parent_style.as_ref().map(|style| &**style),
+                                      None,
+                                      Box::new(StdoutErrorReporter));
+
+    // The properties of animations that just ended go back to their other values too.
+    let mut animated: Vec<PropertyDeclaration> = vec![];
+    for (level, block) in rule_node.path_with_levels() {
+        if level.map_or(false, |level| level.is_animation()) {
+            animated.extend(block.declarations.iter().cloned());
+        }
+    }
+    for &(_, ref blocks) in &animation_declarations {
+        for block in blocks {
+            animated.extend(block.declarations.iter().cloned());
+        }
+    }
+    let mut new_style = old_style.with_structs_from(&animated_style, &animated);
+    new_style.set_rule_node_id(new_rule_node.id());
+    if new_style.inherited_structs_differ(&old_style) {
+        element.note_restyle_hint(RESTYLE_SELF);
+    }
+
+    traversal_stats::record(|stats| {
+        stats.elements_styled += 1;
+        stats.declarations_cascaded += declarations.iter()
+                                                   .map(|block| block.declarations.len() as u32)
+                                                   .fold(0, |sum, count| sum + count);
+    });
+
+    let damage = GeckoRestyleDamage::compute(Some(&old_style), &new_style);
+    if let Some(mut data) = node.mutate_data() {
+        data.style = Some(Arc::new(new_style));
+        data.rule_node = Some(new_rule_node);
+    }
+    node.set_restyle_damage(node.restyle_damage() | damage);
+    damage
+}
+
+/// The restyle for declarations the CSSOM replaced: cascades again the elements under `node`
+/// whose rule nodes have declarations in them that `rule_tree` replaced, and the descendants
+/// of those whose inherited properties changed, without matching any selectors. It goes
+/// before the traversal, which picks up the elements Gecko marked dirty, and the elements
+/// whose styles didn't only come from their rule nodes, like those sharing a sibling's
+/// style, which it marks dirty. Native anonymous content Gecko styles itself is left alone.
+/// The elements it cascades are added to `restyled`, with their damage, for
+/// `propagate_rule_change_damage` to pass up once the traversal is done.
+pub fn restyle_for_rule_changes<'ln>(node: GeckoNode<'ln>, root: OpaqueNode, rule_tree: &RuleTree,
+                                     context: &StandaloneStyleContext, parent_changed: bool,
+                                     restyled: &mut Vec<(GeckoNode<'ln>, GeckoRestyleDamage)>) {
+    match unsafe { node.get_node_data().as_ref() } {
+        Some(node_data) if node_data.nac_parent_style.borrow().is_none() => {}
+        _ => return,
+    }
+    if node.borrow_data().map_or(true, |data| data.style.is_none()) {
+        return;
+    }
+    if node.as_element().is_none() {
+        if parent_changed && node.is_text_node() && !node.is_dirty() {
+            inherit_text_style(root, node);
+        }
+        return;
+    }
+    traversal_stats::record(|stats| stats.elements_traversed += 1);
+    let changed = if node.is_dirty() {
+        false
+    } else {
+        cascade_for_rule_changes(node, root, rule_tree, context, parent_changed, restyled)
+    };
+    for child in node.children() {
+        restyle_for_rule_changes(child, root, rule_tree, context, changed, restyled);
+    }
+}
+
+/// Cascades an element for `restyle_for_rule_changes` if it needs it. Returns whether its
+/// children need cascading too.
+fn cascade_for_rule_changes<'ln>(node: GeckoNode<'ln>, root: OpaqueNode, rule_tree: &RuleTree,
+                                 context: &StandaloneStyleContext, parent_changed: bool,
+                                 restyled: &mut Vec<(GeckoNode<'ln>, GeckoRestyleDamage)>) -> bool {
+    let element = node.as_element().unwrap();
+    let styled = node.borrow_data().map(|data| {
+        (data.style.clone().unwrap(), data.rule_node.clone(), data.per_pseudo.is_empty())
+    });
+    let (old_style, rule_node, pseudos_unstyled) = match styled {
+        Some(styled) => styled,
+        None => return false,
+    };
+    let rule_node = match rule_node {
+        Some(rule_node) => rule_node,
+        // It shares a sibling's style, which doesn't tell which blocks it came from.
+        None => {
+            element.note_restyle_hint(RESTYLE_SELF);
+            return false;
+        }
+    };
+    let refreshed = rule_tree.refresh(&rule_node);
+    if refreshed.is_none() && !parent_changed {
+        return false;
+    }
+    // Pseudo-elements and visited styles are cascaded from blocks of their own.
+    if !pseudos_unstyled || old_style.visited_style.is_some() {
+        element.note_restyle_hint(RESTYLE_SELF);
+        return false;
+    }
+
+    let shared = context.shared;
+    let local = context.local_context();
+    let damage = unsafe {
+        node.cascade_rule_node(shared,
+                               node.layout_parent_node(root),
+                               refreshed.unwrap_or(rule_node),
+                               &mut local.applicable_declarations_cache.borrow_mut(),
+                               &mut local.rule_node_style_cache.borrow_mut(),
+                               &shared.new_animations_sender)
+    };
+    let (new_style, rule_node) = node.borrow_data().map(|data| {
+        (data.style.clone().unwrap(), data.rule_node.clone().unwrap())
+    }).unwrap();
+    traversal_stats::record(|stats| {
+        stats.elements_styled += 1;
+        stats.declarations_cascaded += rule_node.path().iter()
+                                                .map(|block| block.declarations.len() as u32)
+                                                .fold(0, |sum, count| sum + count);
+    });
+    start_transitions(element.as_raw(), &old_style, &new_style, shared.viewport_size);
+    node.set_restyle_damage(node.restyle_damage() | damage);
+    restyled.push((node, damage));
+    new_style.inherited_structs_differ(&old_style) ||
+        new_style.get_box().clone_display() != old_style.get_box().clone_display()
+}
+
+/// Adds the damage of the elements `restyle_for_rule_changes` cascaded to what their ancestors
+/// up to `root` count for their descendants. The traversal resets those counts for the
+/// elements it goes through, so this comes after it.
+pub fn propagate_rule_change_damage<'ln>(root: OpaqueNode, restyled: &[(GeckoNode<'ln>, GeckoRestyleDamage)]) {
+    for &(node, damage) in restyled {
+        if damage.is_empty() {
+            continue;
+        }
+        let mut current = node;
+        while current.opaque() != root {
+            let parent = match current.layout_parent_node(root) {
+                Some(parent) => parent,
+                None => break,
+            };
+            if let Some(parent_data) = unsafe { parent.get_node_data().as_ref() } {
+                parent_data.descendant_damage.fetch_or(damage.bits() as usize, Ordering::Relaxed);
+            }
+            if damage.contains(RECONSTRUCT_FRAME) {
+                unsafe { parent.set_descendants_need_frames(true) };
+            }
+            current = parent;
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The URL data Gecko keeps for a sheet, which the url values parsed from it carry so that
+//! loads started for them get the sheet's principal and referrer.
+
+#![allow(unsafe_code)]
+
+use bindings::{Gecko_AddRefURLExtraData, Gecko_ReleaseURLExtraData, RawGeckoURLExtraData};
+use style::url_value::{UrlExtraData, UrlExtraDataRef};
+
+/// A strong reference to a URLExtraData.
+struct GeckoUrlExtraData(*mut RawGeckoURLExtraData);
+
+// URLExtraData is refcounted atomically, and never changes once made.
+unsafe impl Send for GeckoUrlExtraData {}
+unsafe impl Sync for GeckoUrlExtraData {}
+
+impl UrlExtraDataRef for GeckoUrlExtraData {
+    fn as_ptr(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Drop for GeckoUrlExtraData {
+    fn drop(&mut self) {
+        unsafe { Gecko_ReleaseURLExtraData(self.0) }
+    }
+}
+
+/// Takes a reference to `data`, for the values of a sheet to carry. Null stands for a sheet
+/// without any.
+pub unsafe fn url_extra_data_from_gecko(data: *mut RawGeckoURLExtraData) -> UrlExtraData {
+    if data.is_null() {
+        return UrlExtraData::none()
+    }
+    Gecko_AddRefURLExtraData(data);
+    UrlExtraData::new(Box::new(GeckoUrlExtraData(data)))
+}
+
+/// The URLExtraData `extra_data` holds on to, or null if it's empty. The pointer is only good
+/// for as long as `extra_data` is alive.
+pub fn url_extra_data_to_gecko(extra_data: &UrlExtraData) -> *mut RawGeckoURLExtraData {
+    extra_data.as_ptr() as *mut RawGeckoURLExtraData
+}
@@ -4,47 +4,368 @@
 
 #![allow(unsafe_code)]
 
-use bindings::{Gecko_ChildrenCount};
-use bindings::{Gecko_ElementState, Gecko_GetAttrAsUTF8, Gecko_GetDocumentElement};
-use bindings::{Gecko_GetFirstChild, Gecko_GetFirstChildElement};
-use bindings::{Gecko_GetLastChild, Gecko_GetLastChildElement};
-use bindings::{Gecko_GetNextSibling, Gecko_GetNextSiblingElement};
-use bindings::{Gecko_GetNodeData};
-use bindings::{Gecko_GetParentElement, Gecko_GetParentNode};
+use atoms::{GeckoAtom, atom_from_gecko, atom_to_gecko, local_name_from_gecko, namespace_from_gecko};
+use bindings::{Gecko_AttrDashEquals, Gecko_AttrEquals, Gecko_AttrHasPrefix, Gecko_AttrHasSubstring};
+use bindings::{Gecko_AttrHasSuffix, Gecko_AttrIncludes, Gecko_ChildrenCount, Gecko_ClassOrClassList};
+use bindings::{Gecko_CreateElementSnapshot, Gecko_DropElementSnapshot};
+use bindings::{Gecko_GetAnimationEffectDeclarationBlock, Gecko_GetAttrAsUTF8, Gecko_GetDocumentCompatMode};
+use bindings::{Gecko_GetAttrsAsUTF8, Gecko_GetChildIndexHint, Gecko_GetDocumentElement, Gecko_GetDocumentState};
+use bindings::{Gecko_GetElementId, Gecko_GetFirstChild, Gecko_GetFirstChildElement};
+use bindings::Gecko_GetHTMLPresentationAttrDeclarationBlock;
+use bindings::{Gecko_GetLanguage, Gecko_GetLastChild, Gecko_GetLastChildElement};
+use bindings::{Gecko_GetNextSibling, Gecko_GetNextSiblingElement, Gecko_GetOwnerDocument};
+use bindings::{Gecko_GetNodeData, Gecko_GetNodeFlags, Gecko_SetNodeFlags, Gecko_UnsetNodeFlags};
+use bindings::Gecko_GetNodeInfo;
 use bindings::{Gecko_GetPrevSibling, Gecko_GetPrevSiblingElement};
-use bindings::{Gecko_IsHTMLElementInHTMLDocument, Gecko_IsLink, Gecko_IsRootElement, Gecko_IsTextNode};
-use bindings::{Gecko_IsUnvisitedLink, Gecko_IsVisitedLink};
-#[allow(unused_imports)] // Used in commented-out code.
-use bindings::{Gecko_LocalName, Gecko_Namespace, Gecko_NodeIsElement, Gecko_SetNodeData};
-use bindings::{RawGeckoDocument, RawGeckoElement, RawGeckoNode};
-use bindings::{ServoNodeData};
+use bindings::{Gecko_GetSMILOverrideDeclarationBlock, Gecko_GetStyleAttrDeclarationBlock, Gecko_HasAttr};
+use bindings::{Gecko_IsChromeDocument, Gecko_IsHTMLElementInHTMLDocument, Gecko_IsInDocument};
+use bindings::Gecko_IsNativeAnonymous;
+use bindings::{Gecko_IsRootElement, Gecko_MatchesMozPseudoClass};
+use bindings::{Gecko_IsSignificantChild, Gecko_IsTextNode, Gecko_NodeIsDocument};
+use bindings::{Gecko_LocalName, Gecko_Namespace, Gecko_SetNodeData};
+use bindings::{Gecko_SnapshotClassOrClassList, Gecko_SnapshotGetAttrAsUTF8, Gecko_SnapshotGetElementId};
+use bindings::{Gecko_SnapshotAttributes, Gecko_SnapshotHasAttrs, Gecko_SnapshotState};
+use bindings::{NodeInfoFFI, RawGeckoDocument, RawGeckoElement, RawGeckoNode, RawServoDeclarationBlock};
+use bindings::{RawServoAttributeSnapshot, ServoAttrSnapshot, ServoElementSnapshot, ServoNodeData, nsIAtom};
+use element_state::{self, NS_EVENT_STATE_LTR, NS_EVENT_STATE_RTL};
+use glue::{ArcHelpers, COMPATIBILITY_NAV_QUIRKS, GeckoDeclarationBlock};
 use libc::uintptr_t;
 use properties::GeckoComputedValues;
-use selector_impl::{GeckoSelectorImpl, NonTSPseudoClass, PrivateStyleData};
+use restyle_damage::GeckoRestyleDamage;
+use selector_impl::{AttrOperator, Direction, GeckoSelectorImpl, NonTSPseudoClass, PrivateStyleData};
 use selectors::Element;
 use selectors::matching::DeclarationBlock;
-use selectors::parser::{AttrSelector, NamespaceConstraint};
+use selectors::parser::{AttrSelector, CaseSensitivity, NamespaceConstraint};
 use smallvec::VecLike;
-use std::cell::{Ref, RefCell, RefMut};
+use std::ascii::AsciiExt;
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::cmp;
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::ops::BitOr;
+use std::mem;
+use std::ptr;
 use std::slice;
 use std::str::from_utf8_unchecked;
 use std::sync::Arc;
+use std::sync::atomic::{ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT, AtomicBool, AtomicUsize, Ordering};
 use string_cache::{Atom, Namespace};
-use style::dom::{OpaqueNode, TDocument, TElement, TNode, TRestyleDamage, UnsafeNode};
+use style::dom::{OpaqueNode, TDocument, TElement, TNode, UnsafeNode};
 use style::element_state::ElementState;
-#[allow(unused_imports)] // Used in commented-out code.
-use style::error_reporting::StdoutErrorReporter;
+use style::nth_index::{NthIndexCache, NthIndexElement, NthIndexKind, matches_nth};
+use style::parser::QuirksMode;
 use style::properties::{PropertyDeclaration, PropertyDeclarationBlock};
-#[allow(unused_imports)] // Used in commented-out code.
-use style::properties::{parse_style_attribute};
-use style::restyle_hints::ElementSnapshot;
-use style::selector_impl::ElementExt;
-#[allow(unused_imports)] // Used in commented-out code.
-use url::Url;
+use style::restyle_hints::{ElementSnapshot, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS, RESTYLE_SELF, RestyleHint};
+use style::restyle_hints::ServoElementSnapshot as StyleElementSnapshot;
+use style::selector_impl::{ElementExt, attr_value_ignores_case, lang_matches};
+use style::selector_matching::CascadeLevel;
+use style::shared_lock::SharedRwLockReadGuard;
+use style::traversal_stats;
+
+/// What we store in each Gecko node's ServoNodeData.
+pub struct NodeData {
+    pub style_data: RefCell<PrivateStyleData>,
+
+    /// The damage from the last restyle of this node, until Gecko takes it with
+    /// Servo_TakeChangeHint.
+    pub restyle_damage: Cell<GeckoRestyleDamage>,
+
+    /// The change hints Gecko asked for with Servo_NoteExplicitHints, which the next restyle
+    /// doesn't overwrite. Servo_TakeChangeHint hands them back along with the damage.
+    pub explicit_damage: Cell<GeckoRestyleDamage>,
+
+    /// The damage of the node's descendants from the last restyle that went into them, OR'd
+    /// together. Each child folds its own in at the end of its bottom-up step, which can run
+    /// on a different thread than its siblings', so it's atomic.
+    pub descendant_damage: AtomicUsize,
+
+    /// The local name and namespace of the element, which never change, so that matching
+    /// doesn't need to ask Gecko for them every time. None for other nodes.
+    pub element_names: Option<(&'static Atom, &'static Namespace)>,
+
+    /// The style generation of the document when the traversal last styled the node, which
+    /// tells whether its style is still current.
+    pub style_generation: Cell<usize>,
+
+    /// The style the element had before the restyle in progress, from its top-down step,
+    /// where the restyle replaces it, to its bottom-up one, which starts the transitions
+    /// going from one to the other.
+    pub style_before_restyle: RefCell<Option<Arc<GeckoComputedValues>>>,
+
+    /// For native anonymous content styled with Servo_ResolveStyleForNAC, the style it
+    /// inherited from, which needn't be its parent's. Restyling it again starts from this.
+    pub nac_parent_style: RefCell<Option<Arc<GeckoComputedValues>>>,
+
+    /// The style Servo_ClearNodeStyleData threw away when asked to keep it for diffing. The
+    /// next traversal matches the node from scratch, computes its damage against this
+    /// instead of reconstructing its frame, and then frees it.
+    pub previous_style: RefCell<Option<Arc<GeckoComputedValues>>>,
+}
+
+// Keep these in sync with NODE_IS_DIRTY_FOR_SERVO and NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO in
+// dom/base/nsINode.h.
+/// The node needs to be restyled.
+pub const NODE_IS_DIRTY_FOR_SERVO: u32 = 1 << 14;
+/// Some of the node's descendants need to be restyled.
+pub const NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO: u32 = 1 << 15;
+// Keep this in sync with NODE_DESCENDANTS_NEED_FRAMES in dom/base/nsINode.h.
+/// The frames of some of the node's descendants have to be rebuilt.
+pub const NODE_DESCENDANTS_NEED_FRAMES: u32 = 1 << 16;
+// Keep these in sync with NODE_HAS_ANIMATIONS_FOR_SERVO and
+// NODE_HAS_ANIMATED_DESCENDANTS_FOR_SERVO in dom/base/nsINode.h. Gecko sets them, the same
+// way as the dirty bits, for as long as the animations run.
+/// The element has animations or transitions, or properties SMIL animates, whose values
+/// Servo_RestyleForAnimations cascades.
+pub const NODE_HAS_ANIMATIONS_FOR_SERVO: u32 = 1 << 17;
+/// Some of the node's descendants have animations.
+pub const NODE_HAS_ANIMATED_DESCENDANTS_FOR_SERVO: u32 = 1 << 18;
+
+// Keep these in sync with EffectCompositor::CascadeLevel in dom/animation/EffectCompositor.h.
+/// The level of Gecko_GetAnimationEffectDeclarationBlock's block of animation values.
+pub const ANIMATION_EFFECT_LEVEL_ANIMATIONS: u8 = 0;
+/// The level of Gecko_GetAnimationEffectDeclarationBlock's block of transition values.
+pub const ANIMATION_EFFECT_LEVEL_TRANSITIONS: u8 = 1;
+
+pub type NonOpaqueStyleData = *mut NodeData;
+
+static NO_STYLE_ATTRIBUTE: Option<PropertyDeclarationBlock> = None;
+
+thread_local!(static RELEVANT_LINK: Cell<usize> = Cell::new(0));
+
+/// Runs |f| with |link| as the relevant link of the element being styled: the nearest link
+/// among it and its ancestors, and the only one :visited matches, so that the rules for it
+/// being visited go into the element's visited style.
+pub fn with_relevant_link<F, R>(link: Option<GeckoElement>, f: F) -> R where F: FnOnce() -> R {
+    let link = link.map_or(0, |link| link.element as usize);
+    let previous = RELEVANT_LINK.with(|relevant_link| {
+        let previous = relevant_link.get();
+        relevant_link.set(link);
+        previous
+    });
+    let result = f();
+    RELEVANT_LINK.with(|relevant_link| relevant_link.set(previous));
+    result
+}
+
+fn is_relevant_link(element: &GeckoElement) -> bool {
+    RELEVANT_LINK.with(|relevant_link| relevant_link.get() == element.element as usize)
+}
+
+/// How many children Gecko_GetChildren fetches at once. Past that, the rest are found
+/// through their sibling pointers.
+#[cfg(feature = "batched_children")]
+const CHILDREN_BATCH_SIZE: usize = 16;
+
+/// How many times we called into Gecko to get at the children of a node, in debug builds,
+/// so that tests can measure what batching saves.
+pub static CHILD_FFI_CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+#[inline]
+fn count_child_ffi_call() {
+    if cfg!(debug_assertions) {
+        CHILD_FFI_CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Bumped whenever Gecko calls in, since the DOM may have changed since the last call, so
+/// that each thread knows to empty its nth-index cache.
+static DOM_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
 
-pub type NonOpaqueStyleData = *mut RefCell<PrivateStyleData>;
+/// The state of the document being styled, which the document state pseudo-classes match
+/// against. Fetched from Gecko whenever it calls in to match selectors.
+static DOCUMENT_STATE: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn document_state() -> element_state::DocumentState {
+    element_state::DocumentState::from_bits_truncate(DOCUMENT_STATE.load(Ordering::Relaxed) as u64)
+}
+
+/// Whether the document being styled is a chrome document, which unlike content documents
+/// may see the real system colors rather than their stand-ins. Fetched along with the
+/// document state.
+static IN_CHROME_DOCUMENT: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Whether system colors have to be given their stand-ins, so that content can't use them
+/// to fingerprint users.
+pub fn use_standin_system_colors() -> bool {
+    !IN_CHROME_DOCUMENT.load(Ordering::Relaxed)
+}
+
+/// Whether the document being styled is in quirks mode, where ids and classes match ASCII
+/// case-insensitively. Fetched along with the document state, unless a restyle gives the
+/// mode of its document's style data instead.
+static IN_QUIRKS_MODE_DOCUMENT: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Matches ids and classes as they are in |quirks_mode| until the document state is next
+/// fetched, for restyles, whose rules are those of that mode.
+pub fn set_document_quirks_mode(quirks_mode: QuirksMode) {
+    IN_QUIRKS_MODE_DOCUMENT.store(quirks_mode == QuirksMode::Quirks, Ordering::Relaxed);
+}
+
+pub fn in_quirks_mode_document() -> bool {
+    IN_QUIRKS_MODE_DOCUMENT.load(Ordering::Relaxed)
+}
+
+thread_local!(static NTH_INDEX_CACHE: RefCell<(usize, NthIndexCache)> = RefCell::new((0, NthIndexCache::new())));
+
+/// How many nodes a thread's node info cache has room for before it first grows.
+const NODE_INFO_CACHE_INITIAL_CAPACITY: usize = 256;
+
+/// What Gecko_GetNodeInfo told this thread about the nodes it asked about, in an
+/// open-addressing table keyed by node pointer, with linear probing. Node pointers are never
+/// null, so a null key marks an empty slot.
+///
+/// The answers hold until Gecko calls in again, so the cache is emptied the first time it's
+/// used after that, and the traversal empties the cache of every thread it ran on once it's
+/// done, so that none keeps its memory in between.
+struct NodeInfoCache {
+    generation: usize,
+    len: usize,
+    slots: Vec<(usize, NodeInfoFFI)>,
+}
+
+fn empty_node_info() -> NodeInfoFFI {
+    NodeInfoFFI {
+        mParent: ptr::null_mut(),
+        mNextSibling: ptr::null_mut(),
+        mState: 0,
+        mFlags: 0,
+        mIsElement: false,
+    }
+}
+
+impl NodeInfoCache {
+    fn new() -> NodeInfoCache {
+        NodeInfoCache {
+            generation: 0,
+            len: 0,
+            slots: vec![],
+        }
+    }
+
+    /// Empties the cache if Gecko called in since it was last used.
+    fn start_generation(&mut self) {
+        let generation = DOM_GENERATION.load(Ordering::Relaxed);
+        if self.generation != generation {
+            self.clear();
+            self.generation = generation;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots = vec![];
+        self.len = 0;
+    }
+
+    /// The index of the slot |node| is in, or of the empty one it would go in. There's always
+    /// an empty one, since the table never gets more than three quarters full.
+    fn slot_index(&self, node: usize) -> usize {
+        let mask = self.slots.len() - 1;
+        // Nodes are at least 8-byte aligned, so the low bits of their address say nothing.
+        let mut index = (node >> 3).wrapping_mul(0x9E3779B9) & mask;
+        loop {
+            let key = self.slots[index].0;
+            if key == node || key == 0 {
+                return index
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    fn get(&self, node: usize) -> Option<NodeInfoFFI> {
+        if self.slots.is_empty() {
+            return None
+        }
+        let (key, info) = self.slots[self.slot_index(node)];
+        if key == node {
+            Some(info)
+        } else {
+            None
+        }
+    }
+
+    fn insert(&mut self, node: usize, info: NodeInfoFFI) {
+        if (self.len + 1) * 4 > self.slots.len() * 3 {
+            let capacity = cmp::max(self.slots.len() * 2, NODE_INFO_CACHE_INITIAL_CAPACITY);
+            let old_slots = mem::replace(&mut self.slots, vec![(0, empty_node_info()); capacity]);
+            for (key, info) in old_slots {
+                if key != 0 {
+                    let index = self.slot_index(key);
+                    self.slots[index] = (key, info);
+                }
+            }
+        }
+        let index = self.slot_index(node);
+        if self.slots[index].0 == 0 {
+            self.len += 1;
+        }
+        self.slots[index] = (node, info);
+    }
+}
+
+thread_local!(static NODE_INFO_CACHE: RefCell<NodeInfoCache> = RefCell::new(NodeInfoCache::new()));
+
+/// The flags the traversal sets and clears itself. Another thread may have changed them since
+/// this one cached the node's flags, so they're always asked for.
+const TRAVERSAL_FLAGS: u32 = NODE_IS_DIRTY_FOR_SERVO | NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO |
+                             NODE_DESCENDANTS_NEED_FRAMES;
+
+#[inline]
+fn count_node_query() {
+    traversal_stats::record(|stats| stats.node_queries += 1);
+}
+
+#[inline]
+fn count_node_ffi_call() {
+    traversal_stats::record(|stats| stats.node_ffi_calls += 1);
+}
+
+/// What Gecko says about |node|, from the calling thread's cache if it was asked before.
+fn node_info(node: *mut RawGeckoNode) -> NodeInfoFFI {
+    count_node_query();
+    NODE_INFO_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.start_generation();
+        if let Some(info) = cache.get(node as usize) {
+            return info
+        }
+        count_node_ffi_call();
+        let mut info = empty_node_info();
+        unsafe { Gecko_GetNodeInfo(node, &mut info) };
+        cache.insert(node as usize, info);
+        info
+    })
+}
+
+/// What the calling thread's cache has on |node|, without asking Gecko if that's nothing.
+fn cached_node_info(node: *mut RawGeckoNode) -> Option<NodeInfoFFI> {
+    count_node_query();
+    NODE_INFO_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.start_generation();
+        cache.get(node as usize)
+    })
+}
+
+/// Empties the calling thread's node info cache. The traversal runs it on every thread it
+/// ran on, once it's done.
+pub fn clear_node_info_cache() {
+    NODE_INFO_CACHE.with(|cache| cache.borrow_mut().clear());
+}
+
+/// Stands for Gecko leaving the DOM alone while Servo looks at it, from when Gecko calls a
+/// Servo_* function until it returns. The wrappers made from the pointers Gecko hands us
+/// borrow it, so none of them can be kept around past the call.
+pub struct DomToken {
+    _private: (),
+}
+
+impl DomToken {
+    /// The token mustn't outlive the Servo_* function that makes it, which is what keeping it
+    /// on the stack of that function is for.
+    pub unsafe fn new() -> DomToken {
+        DOM_GENERATION.fetch_add(1, Ordering::Relaxed);
+        DomToken { _private: () }
+    }
+}
 
 // Important: We don't currently refcount the DOM, because the wrapper lifetime
 // magic guarantees that our LayoutFoo references won't outlive the root, and
@@ -58,50 +379,185 @@ pub struct GeckoNode<'ln> {
 }
 
 impl<'ln> GeckoNode<'ln> {
-    unsafe fn from_raw(n: *mut RawGeckoNode) -> GeckoNode<'ln> {
+    /// Wraps |n|, which Gecko handed us and mustn't be null, for as long as the token lives.
+    pub unsafe fn from_raw(_token: &'ln DomToken, n: *mut RawGeckoNode) -> GeckoNode<'ln> {
+        assert!(!n.is_null());
+        GeckoNode::wrap(n)
+    }
+
+    /// Wraps |n|, which mustn't be null, for as long as the node it came from.
+    unsafe fn wrap(n: *mut RawGeckoNode) -> GeckoNode<'ln> {
+        debug_assert!(!n.is_null());
         GeckoNode {
             node: n,
             chain: PhantomData,
         }
     }
 
-    unsafe fn from_ref(n: &RawGeckoNode) -> GeckoNode<'ln> {
-        GeckoNode::from_raw(n as *const RawGeckoNode as *mut RawGeckoNode)
+    /// Asks Gecko for the state of the document this node is in, for the document state
+    /// pseudo-classes to match against until the next time this is called, and for whether
+    /// it's a chrome document or in quirks mode.
+    pub fn fetch_document_state(&self) {
+        let document = unsafe { Gecko_GetOwnerDocument(self.node) };
+        let state = unsafe { Gecko_GetDocumentState(document) };
+        DOCUMENT_STATE.store(state as usize, Ordering::Relaxed);
+        IN_CHROME_DOCUMENT.store(unsafe { Gecko_IsChromeDocument(document) }, Ordering::Relaxed);
+        let in_quirks_mode = unsafe { Gecko_GetDocumentCompatMode(document) } == COMPATIBILITY_NAV_QUIRKS;
+        IN_QUIRKS_MODE_DOCUMENT.store(in_quirks_mode, Ordering::Relaxed);
+    }
+
+    /// Wraps |n|, or returns None if it's null.
+    unsafe fn wrap_opt(n: *mut RawGeckoNode) -> Option<GeckoNode<'ln>> {
+        if n.is_null() {
+            None
+        } else {
+            Some(GeckoNode::wrap(n))
+        }
+    }
+
+    fn dump_indent(self, indent: u32) {
+        let mut line = String::new();
+        for _ in 0..indent {
+            line.push_str("  ");
+        }
+        line.push_str(&self.debug_str());
+        println!("{}", line);
+        for kid in self.children() {
+            kid.dump_indent(indent + 1);
+        }
+    }
+
+    fn debug_str(self) -> String {
+        match self.as_element() {
+            Some(element) => {
+                format!("<{}> {:#x} dirty={} dirty_descendants={}", element.get_local_name(),
+                        self.debug_id(), self.is_dirty(), self.has_dirty_descendants())
+            }
+            None if self.is_text_node() => format!("#text {:#x}", self.debug_id()),
+            None => format!("#node {:#x}", self.debug_id()),
+        }
+    }
+
+    fn has_flags(&self, flags: u32) -> bool {
+        if flags & TRAVERSAL_FLAGS != 0 {
+            return unsafe { Gecko_GetNodeFlags(self.node) & flags != 0 }
+        }
+        node_info(self.node).mFlags & flags != 0
     }
 
-    fn get_node_data(&self) -> NonOpaqueStyleData {
+    /// Sets or clears restyle bits. During the parallel traversal, only the thread styling a
+    /// node touches its bits, so Gecko doesn't need to update them atomically.
+    unsafe fn set_flags(&self, flags: u32, value: bool) {
+        if value {
+            Gecko_SetNodeFlags(self.node, flags);
+        } else {
+            Gecko_UnsetNodeFlags(self.node, flags);
+        }
+    }
+
+    pub fn get_node_data(&self) -> NonOpaqueStyleData {
         unsafe {
             Gecko_GetNodeData(self.node) as NonOpaqueStyleData
         }
     }
+
+    /// Detaches the node's data from it, leaving its data pointer null. Whatever had
+    /// borrowed the data must be done with it.
+    pub unsafe fn take_node_data(&self) -> NonOpaqueStyleData {
+        let data = self.get_node_data();
+        if !data.is_null() {
+            Gecko_SetNodeData(self.node, ptr::null_mut());
+        }
+        data
+    }
+
+    pub fn descendants_need_frames(&self) -> bool {
+        self.has_flags(NODE_DESCENDANTS_NEED_FRAMES)
+    }
+
+    /// Only the thread doing the node's bottom-up step may call this, as for the dirty bits.
+    pub unsafe fn set_descendants_need_frames(&self, value: bool) {
+        self.set_flags(NODE_DESCENDANTS_NEED_FRAMES, value)
+    }
+
+    pub fn has_animations(&self) -> bool {
+        self.has_flags(NODE_HAS_ANIMATIONS_FOR_SERVO)
+    }
+
+    pub fn has_animated_descendants(&self) -> bool {
+        self.has_flags(NODE_HAS_ANIMATED_DESCENDANTS_FOR_SERVO)
+    }
+
+    /// Whether the node is in a document, rather than in a subtree that was taken out of
+    /// one or never put in.
+    pub fn is_in_document(&self) -> bool {
+        unsafe { Gecko_IsInDocument(self.node) }
+    }
+
+    /// The node the style of this one inherits from: its parent, if that's an element of the
+    /// same document. While Gecko adopts a subtree into another document, the subtree's root
+    /// can briefly have a parent in the old one, which it mustn't inherit from.
+    pub fn style_parent(&self) -> Option<GeckoNode<'ln>> {
+        let parent = match self.parent_node() {
+            Some(parent) if parent.is_element() => parent,
+            _ => return None,
+        };
+        let same_document = unsafe { Gecko_GetOwnerDocument(parent.node) == Gecko_GetOwnerDocument(self.node) };
+        if same_document {
+            Some(parent)
+        } else {
+            None
+        }
+    }
 }
 
+/// A node that can be handed from one thread of the traversal to another, which is the only
+/// way a Gecko node gets to a style worker thread.
+///
+/// Gecko's nodes can't be used off the main thread in general, which is why the wrappers
+/// aren't Send or Sync. While styling, though, the main thread waits for the traversal and
+/// Gecko leaves the tree alone, and the workers only call the Gecko_* functions that just
+/// read the node. What only the main thread may ask Gecko, like the state of the document,
+/// is fetched before the traversal starts.
 #[derive(Clone, Copy)]
-pub struct DummyRestyleDamage;
-impl TRestyleDamage for DummyRestyleDamage {
-    type ConcreteComputedValues = GeckoComputedValues;
-    fn compute(_: Option<&Arc<GeckoComputedValues>>, _: &GeckoComputedValues) -> Self { DummyRestyleDamage }
-    fn rebuild_and_reflow() -> Self { DummyRestyleDamage }
-}
-impl BitOr for DummyRestyleDamage {
-    type Output = Self;
-    fn bitor(self, _: Self) -> Self { DummyRestyleDamage }
-}
+pub struct SendNode(*mut RawGeckoNode);
 
+unsafe impl Send for SendNode {}
+unsafe impl Sync for SendNode {}
 
+impl SendNode {
+    /// Unsafe because the node must only be used by the threads of a traversal of its tree,
+    /// and only until the traversal ends.
+    pub unsafe fn new(node: GeckoNode) -> SendNode {
+        SendNode(node.node)
+    }
+
+    /// The node, for the thread it was sent to.
+    pub unsafe fn node<'ln>(&self) -> GeckoNode<'ln> {
+        GeckoNode::wrap(self.0)
+    }
+
+    fn to_unsafe(&self) -> UnsafeNode {
+        (self.0 as usize, 0)
+    }
+
+    unsafe fn from_unsafe(n: &UnsafeNode) -> SendNode {
+        SendNode(n.0 as *mut RawGeckoNode)
+    }
+}
 
 impl<'ln> TNode for GeckoNode<'ln> {
     type ConcreteDocument = GeckoDocument<'ln>;
     type ConcreteElement = GeckoElement<'ln>;
-    type ConcreteRestyleDamage = DummyRestyleDamage;
+    type ConcreteRestyleDamage = GeckoRestyleDamage;
     type ConcreteComputedValues = GeckoComputedValues;
 
     fn to_unsafe(&self) -> UnsafeNode {
-        (self.node as usize, 0)
+        unsafe { SendNode::new(*self) }.to_unsafe()
     }
 
     unsafe fn from_unsafe(n: &UnsafeNode) -> Self {
-        GeckoNode::from_raw(n.0 as *mut RawGeckoNode)
+        SendNode::from_unsafe(n).node()
     }
 
     fn is_text_node(&self) -> bool {
@@ -111,13 +567,37 @@ impl<'ln> TNode for GeckoNode<'ln> {
     }
 
     fn is_element(&self) -> bool {
-        unsafe {
-            Gecko_NodeIsElement(self.node)
+        node_info(self.node).mIsElement
+    }
+
+    /// Fetches the first CHILDREN_BATCH_SIZE children with a single call, when built against
+    /// a Gecko that has Gecko_GetChildren.
+    #[cfg(feature = "batched_children")]
+    fn each_child<F>(&self, mut f: F) where F: FnMut(GeckoNode<'ln>) {
+        use bindings::Gecko_GetChildren;
+
+        let mut batch = [ptr::null_mut(); CHILDREN_BATCH_SIZE];
+        count_child_ffi_call();
+        let count = unsafe {
+            Gecko_GetChildren(self.node, batch.as_mut_ptr(), CHILDREN_BATCH_SIZE as u32)
+        } as usize;
+        let mut last = None;
+        for &child in &batch[..cmp::min(count, CHILDREN_BATCH_SIZE)] {
+            let child = unsafe { GeckoNode::wrap(child) };
+            f(child);
+            last = Some(child);
+        }
+        if count > CHILDREN_BATCH_SIZE {
+            let mut next = last.and_then(|last| last.next_sibling());
+            while let Some(child) = next {
+                f(child);
+                next = child.next_sibling();
+            }
         }
     }
 
     fn dump(self) {
-        unimplemented!()
+        self.dump_indent(0);
     }
 
     fn opaque(&self) -> OpaqueNode {
@@ -128,22 +608,33 @@ impl<'ln> TNode for GeckoNode<'ln> {
     fn initialize_data(self) {
         unsafe {
             if self.get_node_data().is_null() {
-                let ptr: NonOpaqueStyleData = Box::into_raw(box RefCell::new(PrivateStyleData::new()));
+                let ptr: NonOpaqueStyleData = Box::into_raw(box NodeData {
+                    style_data: RefCell::new(PrivateStyleData::new()),
+                    restyle_damage: Cell::new(GeckoRestyleDamage::empty()),
+                    explicit_damage: Cell::new(GeckoRestyleDamage::empty()),
+                    descendant_damage: AtomicUsize::new(0),
+                    element_names: self.as_element().map(|element| element.fetch_names()),
+                    style_generation: Cell::new(0),
+                    style_before_restyle: RefCell::new(None),
+                    nac_parent_style: RefCell::new(None),
+                    previous_style: RefCell::new(None),
+                });
                 Gecko_SetNodeData(self.node, ptr as *mut ServoNodeData);
             }
         }
     }
 
-    fn layout_parent_node(self, reflow_root: OpaqueNode) -> Option<GeckoNode<'ln>> {
-        if self.opaque() == reflow_root {
-            None
-        } else {
-            self.parent_node()
-        }
+    fn layout_parent_node(self, _reflow_root: OpaqueNode) -> Option<GeckoNode<'ln>> {
+        // Unlike Servo, we don't stop at the traversal root. A traversal may start
+        // at any styled element in the tree (see Servo_RestyleSubtree), and the root
+        // of that traversal still needs to inherit from its parent's computed values.
+        // The document element's parent is the document itself, which never gets
+        // styled, so we stop there, as we do at parents in other documents.
+        self.style_parent()
     }
 
     fn debug_id(self) -> usize {
-        unimplemented!()
+        self.node as usize
     }
 
     fn children_count(&self) -> u32 {
@@ -154,14 +645,21 @@ impl<'ln> TNode for GeckoNode<'ln> {
 
     fn as_element(&self) -> Option<GeckoElement<'ln>> {
         if self.is_element() {
-            unsafe { Some(GeckoElement::from_raw(self.node as *mut RawGeckoElement)) }
+            unsafe { Some(GeckoElement::wrap(self.node as *mut RawGeckoElement)) }
         } else {
             None
         }
     }
 
     fn as_document(&self) -> Option<GeckoDocument<'ln>> {
-        unimplemented!()
+        if unsafe { Gecko_NodeIsDocument(self.node) } {
+            Some(GeckoDocument {
+                document: self.node as *mut RawGeckoDocument,
+                chain: PhantomData,
+            })
+        } else {
+            None
+        }
     }
 
     fn has_changed(&self) -> bool {
@@ -170,25 +668,23 @@ impl<'ln> TNode for GeckoNode<'ln> {
     }
 
     unsafe fn set_changed(&self, _value: bool) {
-        unimplemented!()
+        // has_changed always returns true, so there's nothing to keep track of.
     }
 
     fn is_dirty(&self) -> bool {
-        // FIXME(bholley)
-        true
+        self.has_flags(NODE_IS_DIRTY_FOR_SERVO)
     }
 
-    unsafe fn set_dirty(&self, _value: bool) {
-        unimplemented!()
+    unsafe fn set_dirty(&self, value: bool) {
+        self.set_flags(NODE_IS_DIRTY_FOR_SERVO, value)
     }
 
     fn has_dirty_descendants(&self) -> bool {
-        // FIXME(bholley)
-        true
+        self.has_flags(NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO)
     }
 
-    unsafe fn set_dirty_descendants(&self, _value: bool) {
-        unimplemented!()
+    unsafe fn set_dirty_descendants(&self, value: bool) {
+        self.set_flags(NODE_HAS_DIRTY_DESCENDANTS_FOR_SERVO, value)
     }
 
     fn can_be_fragmented(&self) -> bool {
@@ -204,54 +700,75 @@ impl<'ln> TNode for GeckoNode<'ln> {
 
     #[inline(always)]
     unsafe fn borrow_data_unchecked(&self) -> Option<*const PrivateStyleData> {
-        self.get_node_data().as_ref().map(|d| d.as_unsafe_cell().get() as *const PrivateStyleData)
+        self.get_node_data().as_ref().map(|d| d.style_data.as_unsafe_cell().get() as *const PrivateStyleData)
     }
 
     #[inline(always)]
     fn borrow_data(&self) -> Option<Ref<PrivateStyleData>> {
         unsafe {
-            self.get_node_data().as_ref().map(|d| d.borrow())
+            self.get_node_data().as_ref().map(|d| d.style_data.borrow())
         }
     }
 
     #[inline(always)]
     fn mutate_data(&self) -> Option<RefMut<PrivateStyleData>> {
         unsafe {
-            self.get_node_data().as_ref().map(|d| d.borrow_mut())
+            self.get_node_data().as_ref().map(|d| d.style_data.borrow_mut())
         }
     }
 
-    fn restyle_damage(self) -> Self::ConcreteRestyleDamage { DummyRestyleDamage }
+    fn restyle_damage(self) -> Self::ConcreteRestyleDamage {
+        unsafe {
+            self.get_node_data().as_ref().map_or(GeckoRestyleDamage::empty(), |d| d.restyle_damage.get())
+        }
+    }
 
-    fn set_restyle_damage(self, _: Self::ConcreteRestyleDamage) {}
+    fn set_restyle_damage(self, damage: Self::ConcreteRestyleDamage) {
+        unsafe {
+            if let Some(d) = self.get_node_data().as_ref() {
+                d.restyle_damage.set(damage);
+            }
+        }
+    }
 
     fn parent_node(&self) -> Option<GeckoNode<'ln>> {
         unsafe {
-            Gecko_GetParentNode(self.node).as_ref().map(|n| GeckoNode::from_ref(n))
+            GeckoNode::wrap_opt(node_info(self.node).mParent)
         }
     }
 
     fn first_child(&self) -> Option<GeckoNode<'ln>> {
+        count_child_ffi_call();
         unsafe {
-            Gecko_GetFirstChild(self.node).as_ref().map(|n| GeckoNode::from_ref(n))
+            GeckoNode::wrap_opt(Gecko_GetFirstChild(self.node))
         }
     }
 
     fn last_child(&self) -> Option<GeckoNode<'ln>> {
         unsafe {
-            Gecko_GetLastChild(self.node).as_ref().map(|n| GeckoNode::from_ref(n))
+            GeckoNode::wrap_opt(Gecko_GetLastChild(self.node))
         }
     }
 
     fn prev_sibling(&self) -> Option<GeckoNode<'ln>> {
         unsafe {
-            Gecko_GetPrevSibling(self.node).as_ref().map(|n| GeckoNode::from_ref(n))
+            GeckoNode::wrap_opt(Gecko_GetPrevSibling(self.node))
         }
     }
 
+    /// Asks Gecko on its own when the node isn't cached, since the traversal usually walks
+    /// the siblings before it looks at any of them.
     fn next_sibling(&self) -> Option<GeckoNode<'ln>> {
+        let next = match cached_node_info(self.node) {
+            Some(info) => info.mNextSibling,
+            None => {
+                count_child_ffi_call();
+                count_node_ffi_call();
+                unsafe { Gecko_GetNextSibling(self.node) }
+            }
+        };
         unsafe {
-            Gecko_GetNextSibling(self.node).as_ref().map(|n| GeckoNode::from_ref(n))
+            GeckoNode::wrap_opt(next)
         }
     }
 }
@@ -263,7 +780,9 @@ pub struct GeckoDocument<'ld> {
 }
 
 impl<'ld> GeckoDocument<'ld> {
-    pub unsafe fn from_raw(doc: *mut RawGeckoDocument) -> GeckoDocument<'ld> {
+    /// Wraps |doc|, which Gecko handed us and mustn't be null, for as long as the token lives.
+    pub unsafe fn from_raw(_token: &'ld DomToken, doc: *mut RawGeckoDocument) -> GeckoDocument<'ld> {
+        assert!(!doc.is_null());
         GeckoDocument {
             document: doc,
             chain: PhantomData,
@@ -276,20 +795,19 @@ impl<'ld> TDocument for GeckoDocument<'ld> {
     type ConcreteElement = GeckoElement<'ld>;
 
     fn as_node(&self) -> GeckoNode<'ld> {
-        unsafe { GeckoNode::from_raw(self.document as *mut RawGeckoNode) }
+        unsafe { GeckoNode::wrap(self.document as *mut RawGeckoNode) }
     }
 
     fn root_node(&self) -> Option<GeckoNode<'ld>> {
         unsafe {
-            Gecko_GetDocumentElement(self.document).as_ref().map(|el| GeckoElement::from_ref(el).as_node())
+            GeckoElement::wrap_opt(Gecko_GetDocumentElement(self.document)).map(|el| el.as_node())
         }
     }
 
-    fn drain_modified_elements(&self) -> Vec<(GeckoElement<'ld>, ElementSnapshot)> {
-        unimplemented!()
-        /*
-        let elements =  unsafe { self.document.drain_modified_elements() };
-        elements.into_iter().map(|(el, snapshot)| (ServoLayoutElement::from_layout_js(el), snapshot)).collect()*/
+    fn drain_modified_elements(&self) -> Vec<(GeckoElement<'ld>, StyleElementSnapshot)> {
+        // The snapshots Gecko has us take are kept in the per-document data, which the restyle
+        // computes hints from, so the document never has any of its own.
+        vec![]
     }
 }
 
@@ -300,15 +818,147 @@ pub struct GeckoElement<'le> {
 }
 
 impl<'le> GeckoElement<'le> {
-    pub unsafe fn from_raw(el: *mut RawGeckoElement) -> GeckoElement<'le> {
+    /// Wraps |el|, which Gecko handed us and mustn't be null, for as long as the token lives.
+    pub unsafe fn from_raw(_token: &'le DomToken, el: *mut RawGeckoElement) -> GeckoElement<'le> {
+        assert!(!el.is_null());
+        GeckoElement::wrap(el)
+    }
+
+    /// Wraps |el|, which mustn't be null, for as long as the node it came from.
+    unsafe fn wrap(el: *mut RawGeckoElement) -> GeckoElement<'le> {
+        debug_assert!(!el.is_null());
         GeckoElement {
             element: el,
             chain: PhantomData,
         }
     }
 
-    unsafe fn from_ref(el: &RawGeckoElement) -> GeckoElement<'le> {
-        GeckoElement::from_raw(el as *const RawGeckoElement as *mut RawGeckoElement)
+    /// Wraps |el|, or returns None if it's null.
+    unsafe fn wrap_opt(el: *mut RawGeckoElement) -> Option<GeckoElement<'le>> {
+        if el.is_null() {
+            None
+        } else {
+            Some(GeckoElement::wrap(el))
+        }
+    }
+
+    pub fn as_raw(&self) -> *mut RawGeckoElement {
+        self.element
+    }
+
+    /// Whether the element has a child that keeps it from matching :empty, when
+    /// |text_is_significant|, or :-moz-only-whitespace otherwise. Elements always count,
+    /// and comments and processing instructions never do. Text counts unless it's empty,
+    /// or, when text isn't significant, all whitespace.
+    fn has_significant_child(&self, text_is_significant: bool) -> bool {
+        let mut child = self.as_node().first_child();
+        while let Some(node) = child {
+            if unsafe { Gecko_IsSignificantChild(node.node, text_is_significant) } {
+                return true;
+            }
+            child = node.next_sibling();
+        }
+        false
+    }
+
+    /// Whether the content language of the element, from the nearest lang or xml:lang
+    /// attribute or else from the document, is in any of |ranges|.
+    fn matches_lang(&self, ranges: &[String]) -> bool {
+        let language = unsafe { Gecko_GetLanguage(self.element) };
+        if language.is_null() {
+            return false;
+        }
+        let language = unsafe { atom_from_gecko(language) };
+        ranges.iter().any(|range| lang_matches(range, &language))
+    }
+
+    /// Whether Gecko resolved the directionality of the element to |direction|. An element
+    /// only has one, so should Gecko ever report both, right-to-left wins.
+    fn has_direction(&self, direction: &Direction) -> bool {
+        let state = self.get_gecko_state();
+        let rtl = state.contains(NS_EVENT_STATE_RTL);
+        match *direction {
+            Direction::Ltr => !rtl && state.contains(NS_EVENT_STATE_LTR),
+            Direction::Rtl => rtl,
+            Direction::Other(_) => false,
+        }
+    }
+
+    /// Asks Gecko for the element's local name and namespace.
+    fn fetch_names(&self) -> (&'static Atom, &'static Namespace) {
+        unsafe {
+            (local_name_from_gecko(Gecko_LocalName(self.element)), namespace_from_gecko(Gecko_Namespace(self.element)))
+        }
+    }
+
+    /// The element's local name and namespace, from its node data once it has some.
+    fn names(&self) -> (&'static Atom, &'static Namespace) {
+        let data = self.as_node().get_node_data();
+        if !data.is_null() {
+            // The names are set when the node data is made, and never change after.
+            if let Some(names) = unsafe { (*data).element_names } {
+                return names;
+            }
+        }
+        self.fetch_names()
+    }
+
+    /// The element's full Gecko event state. Bits we don't know about are ignored.
+    fn get_gecko_state(&self) -> element_state::ElementState {
+        element_state::ElementState::from_bits_truncate(node_info(self.element as *mut RawGeckoNode).mState)
+    }
+
+    /// Whether the index of the element among the siblings |kind| counts is |a|n+|b| for
+    /// some n, looking it up in the nth-index cache of the current thread.
+    fn matches_nth_index(&self, a: i32, b: i32, kind: NthIndexKind) -> bool {
+        NTH_INDEX_CACHE.with(|cell| {
+            let mut cache = cell.borrow_mut();
+            let generation = DOM_GENERATION.load(Ordering::Relaxed);
+            if cache.0 != generation {
+                cache.1.clear();
+                cache.0 = generation;
+            }
+            matches_nth(a, b, cache.1.index(self, kind))
+        })
+    }
+
+    /// Whether the element's |attr| attribute passes the test of |operator|, which Gecko
+    /// makes against the interned value without copying the attribute's value out.
+    fn matches_attr_operator(&self, attr: &AttrSelector, operator: &AttrOperator) -> bool {
+        let is_html = self.is_html_element_in_html_document();
+        let name = atom_to_gecko(if is_html { &attr.lower_name } else { &attr.name });
+        let ns = match attr.namespace {
+            NamespaceConstraint::Specific(ref ns) => atom_to_gecko(&ns.0),
+            // Gecko takes a null namespace to mean any namespace.
+            NamespaceConstraint::Any => ptr::null_mut(),
+        };
+        // Only = carries the `i` flag; the other operators go by HTML's list alone.
+        let ignore_case = attr_value_ignores_case(attr, CaseSensitivity::CaseSensitive, is_html);
+        let el = self.element;
+        unsafe {
+            match *operator {
+                AttrOperator::Exists => Gecko_HasAttr(el, ns, name),
+                AttrOperator::Equal(ref value, case_sensitivity) => {
+                    let ignore_case = attr_value_ignores_case(attr, case_sensitivity, is_html);
+                    Gecko_AttrEquals(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+                AttrOperator::Includes(ref value) => {
+                    Gecko_AttrIncludes(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+                AttrOperator::DashMatch(ref value) => {
+                    Gecko_AttrDashEquals(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+                AttrOperator::PrefixMatch(ref value) => {
+                    Gecko_AttrHasPrefix(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+                AttrOperator::SubstringMatch(ref value) => {
+                    Gecko_AttrHasSubstring(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+                AttrOperator::SuffixMatch(ref value) => {
+                    Gecko_AttrHasSuffix(el, ns, name, atom_to_gecko(value), ignore_case)
+                }
+            }
+        }
     }
 }
 
@@ -317,46 +967,109 @@ impl<'le> TElement for GeckoElement<'le> {
     type ConcreteDocument = GeckoDocument<'le>;
 
     fn as_node(&self) -> Self::ConcreteNode {
-        unsafe { GeckoNode::from_raw(self.element as *mut RawGeckoNode) }
+        unsafe { GeckoNode::wrap(self.element as *mut RawGeckoNode) }
     }
 
-    fn style_attribute(&self) -> &Option<PropertyDeclarationBlock> {
-        panic!("Requires signature modification - only implemented in stylo branch");
-        /*
-        // FIXME(bholley): We should do what Servo does here. Gecko needs to
-        // call into the Servo CSS parser and then cache the resulting block
-        // in the nsAttrValue. That will allow us to borrow it from here.
-        let attr = self.get_attr(&ns!(), &atom!("style"));
-        // FIXME(bholley): Real base URL and error reporter.
-        let base_url = Url::parse("http://www.example.org").unwrap();
-        attr.map(|v| parse_style_attribute(&v, &base_url, Box::new(StdoutErrorReporter)))
-        */
+    fn style_attribute<'a>(&'a self, guard: &'a SharedRwLockReadGuard) -> &'a Option<PropertyDeclarationBlock> {
+        type Helpers = ArcHelpers<RawServoDeclarationBlock, GeckoDeclarationBlock>;
+        let declarations = unsafe { Gecko_GetStyleAttrDeclarationBlock(self.element) };
+        if declarations.is_null() {
+            &NO_STYLE_ATTRIBUTE
+        } else {
+            // The block is kept alive by the element's attribute, which outlives
+            // this borrow.
+            Helpers::with(declarations, |block| unsafe {
+                &*(block.declarations.read_with(guard) as *const Option<PropertyDeclarationBlock>)
+            })
+        }
     }
 
     fn get_state(&self) -> ElementState {
-        unsafe {
-            ElementState::from_bits_truncate(Gecko_ElementState(self.element))
+        self.get_gecko_state().to_servo()
+    }
+
+    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, guard: &SharedRwLockReadGuard, hints: &mut V)
+        where V: VecLike<DeclarationBlock<Vec<PropertyDeclaration>>>
+    {
+        type Helpers = ArcHelpers<RawServoDeclarationBlock, GeckoDeclarationBlock>;
+        // Gecko maps the element's presentation attributes into a single block, which
+        // it's also free to fill with hints inherited from ancestors, like a table's
+        // cellpadding. Most elements don't have one.
+        let declarations = unsafe { Gecko_GetHTMLPresentationAttrDeclarationBlock(self.element) };
+        if declarations.is_null() {
+            return;
         }
+        Helpers::with(declarations, |block| {
+            if let Some(ref declarations) = *block.declarations.read_with(guard) {
+                // Presentational hints can't be !important.
+                hints.push(DeclarationBlock::from_declarations(declarations.normal.clone()));
+            }
+        });
     }
 
-    fn synthesize_presentational_hints_for_legacy_attributes<V>(&self, _hints: &mut V)
+    fn get_animation_declarations<V>(&self, level: CascadeLevel, guard: &SharedRwLockReadGuard, hints: &mut V)
         where V: VecLike<DeclarationBlock<Vec<PropertyDeclaration>>>
     {
-        // FIXME(bholley) - Need to implement this.
+        type Helpers = ArcHelpers<RawServoDeclarationBlock, GeckoDeclarationBlock>;
+        if !self.as_node().has_animations() {
+            return;
+        }
+        // Gecko composes the values of the element's effects at each level into a single
+        // block, which it keeps until the next animation frame. Most elements have none.
+        let block = unsafe {
+            match level {
+                CascadeLevel::SMILOverride => Gecko_GetSMILOverrideDeclarationBlock(self.element),
+                CascadeLevel::Animations => {
+                    Gecko_GetAnimationEffectDeclarationBlock(self.element, ANIMATION_EFFECT_LEVEL_ANIMATIONS)
+                }
+                CascadeLevel::Transitions => {
+                    Gecko_GetAnimationEffectDeclarationBlock(self.element, ANIMATION_EFFECT_LEVEL_TRANSITIONS)
+                }
+                _ => return,
+            }
+        };
+        if block.is_null() {
+            return;
+        }
+        Helpers::with(block, |block| {
+            if let Some(ref declarations) = *block.declarations.read_with(guard) {
+                hints.push(DeclarationBlock::from_declarations(declarations.normal.clone()));
+            }
+        });
+    }
+
+    fn is_native_anonymous(&self) -> bool {
+        unsafe { Gecko_IsNativeAnonymous(self.element) }
     }
 
     #[inline]
     fn get_attr<'a>(&'a self, namespace: &Namespace, name: &Atom) -> Option<&'a str> {
         unsafe {
             let mut length: u32 = 0;
-            let ptr = Gecko_GetAttrAsUTF8(self.element, namespace.0.as_ptr(), name.as_ptr(), &mut length);
+            let ptr = Gecko_GetAttrAsUTF8(self.element, atom_to_gecko(&namespace.0), atom_to_gecko(name),
+                                          &mut length);
             reinterpret_string(ptr, length)
         }
     }
 
-    #[inline]
-    fn get_attrs<'a>(&'a self, _name: &Atom) -> Vec<&'a str> {
-        unimplemented!()
+    fn get_attrs<'a>(&'a self, name: &Atom) -> Vec<&'a str> {
+        // Elements hardly ever have attributes of the same name in more than one namespace,
+        // so a small buffer is nearly always enough.
+        let mut values = vec![ptr::null(); 4];
+        let mut lengths = vec![0; 4];
+        loop {
+            let count = unsafe {
+                Gecko_GetAttrsAsUTF8(self.element, atom_to_gecko(name), values.as_mut_ptr(),
+                                     lengths.as_mut_ptr(), values.len() as u32)
+            } as usize;
+            if count <= values.len() {
+                return values[..count].iter().zip(&lengths[..count])
+                                      .filter_map(|(&value, &length)| unsafe { reinterpret_string(value, length) })
+                                      .collect();
+            }
+            values.resize(count, ptr::null());
+            lengths.resize(count, 0);
+        }
     }
 }
 
@@ -364,32 +1077,30 @@ impl<'le> ::selectors::Element for GeckoElement<'le> {
     type Impl = GeckoSelectorImpl;
 
     fn parent_element(&self) -> Option<Self> {
-        unsafe {
-            Gecko_GetParentElement(self.element).as_ref().map(|el| GeckoElement::from_ref(el))
-        }
+        self.as_node().parent_node().and_then(|parent| parent.as_element())
     }
 
     fn first_child_element(&self) -> Option<Self> {
         unsafe {
-            Gecko_GetFirstChildElement(self.element).as_ref().map(|el| GeckoElement::from_ref(el))
+            GeckoElement::wrap_opt(Gecko_GetFirstChildElement(self.element))
         }
     }
 
     fn last_child_element(&self) -> Option<Self> {
         unsafe {
-            Gecko_GetLastChildElement(self.element).as_ref().map(|el| GeckoElement::from_ref(el))
+            GeckoElement::wrap_opt(Gecko_GetLastChildElement(self.element))
         }
     }
 
     fn prev_sibling_element(&self) -> Option<Self> {
         unsafe {
-            Gecko_GetPrevSiblingElement(self.element).as_ref().map(|el| GeckoElement::from_ref(el))
+            GeckoElement::wrap_opt(Gecko_GetPrevSiblingElement(self.element))
         }
     }
 
     fn next_sibling_element(&self) -> Option<Self> {
         unsafe {
-            Gecko_GetNextSiblingElement(self.element).as_ref().map(|el| GeckoElement::from_ref(el))
+            GeckoElement::wrap_opt(Gecko_GetNextSiblingElement(self.element))
         }
     }
 
@@ -400,69 +1111,72 @@ impl<'le> ::selectors::Element for GeckoElement<'le> {
     }
 
     fn is_empty(&self) -> bool {
-        unimplemented!()
+        !self.has_significant_child(true)
     }
 
     fn get_local_name(&self) -> &Atom {
-        panic!("Requires signature modification - only implemented in stylo branch");
-        /*
-        unsafe {
-            let mut length: u32 = 0;
-            let p = Gecko_LocalName(self.element, &mut length);
-            Atom::from(String::from_utf16(slice::from_raw_parts(p, length as usize)).unwrap())
-        }
-        */
+        self.names().0
     }
 
     fn get_namespace(&self) -> &Namespace {
-        panic!("Requires signature modification - only implemented in stylo branch");
-        /*
-        unsafe {
-            let mut length: u32 = 0;
-            let p = Gecko_Namespace(self.element, &mut length);
-            Namespace(Atom::from(String::from_utf16(slice::from_raw_parts(p, length as usize)).unwrap()))
-        }
-        */
+        self.names().1
     }
 
     fn match_non_ts_pseudo_class(&self, pseudo_class: NonTSPseudoClass) -> bool {
+        // To avoid leaking history through styles, links don't match :link or :visited
+        // based on their actual visitedness. Every link matches :link, and the relevant link
+        // of the element being styled :visited too; the rules only one of them matches are
+        // then kept out of the element's style or its visited one.
         match pseudo_class {
-            // https://github.com/servo/servo/issues/8718
-            NonTSPseudoClass::AnyLink => unsafe { Gecko_IsLink(self.element) },
-            NonTSPseudoClass::Link => unsafe { Gecko_IsUnvisitedLink(self.element) },
-            NonTSPseudoClass::Visited => unsafe { Gecko_IsVisitedLink(self.element) },
-            NonTSPseudoClass::Active |
-            NonTSPseudoClass::Focus |
-            NonTSPseudoClass::Hover |
-            NonTSPseudoClass::Enabled |
-            NonTSPseudoClass::Disabled |
-            NonTSPseudoClass::Checked |
-            NonTSPseudoClass::Indeterminate => {
-                self.get_state().contains(pseudo_class.state_flag())
+            NonTSPseudoClass::Link => self.is_link(),
+            NonTSPseudoClass::Visited => self.is_link() && is_relevant_link(self),
+            NonTSPseudoClass::MozOnlyWhitespace => !self.has_significant_child(false),
+            NonTSPseudoClass::MozNativeAnonymous => self.is_native_anonymous(),
+            NonTSPseudoClass::MozCustom(ref atom) => unsafe {
+                Gecko_MatchesMozPseudoClass(self.element, atom_to_gecko(atom))
             },
+            NonTSPseudoClass::Lang(ref ranges) => self.matches_lang(ranges),
+            NonTSPseudoClass::Dir(ref direction) => self.has_direction(direction),
+            NonTSPseudoClass::NthIndex(a, b, kind) => self.matches_nth_index(a, b, kind),
+            NonTSPseudoClass::Attr(ref attr, ref operator) => self.matches_attr_operator(attr, operator),
+            NonTSPseudoClass::MozWindowInactive |
+            NonTSPseudoClass::MozLWTheme |
+            NonTSPseudoClass::MozLWThemeBrightText |
+            NonTSPseudoClass::MozLWThemeDarkText |
+            NonTSPseudoClass::MozLocaleDir(_) => pseudo_class.matches_document_state(document_state()),
+            _ => pseudo_class.matches_gecko_state(self.get_gecko_state()),
         }
     }
 
     fn get_id(&self) -> Option<Atom> {
-        // FIXME(bholley): Servo caches the id atom directly on the element to
-        // make this blazing fast. Assuming that was a measured optimization, doing
-        // the dumb thing like we do below will almost certainly be a bottleneck.
-        self.get_attr(&ns!(), &atom!("id")).map(|s| Atom::from(s))
+        unsafe {
+            let id = Gecko_GetElementId(self.element);
+            if id.is_null() {
+                None
+            } else {
+                Some(id_or_class_for_matching(atom_from_gecko(id)))
+            }
+        }
     }
 
-    fn has_class(&self, _name: &Atom) -> bool {
-        unimplemented!()
+    fn has_class(&self, name: &Atom) -> bool {
+        let mut found = false;
+        self.each_class(|class| if class == name { found = true });
+        found
     }
 
-    fn each_class<F>(&self, mut callback: F) where F: FnMut(&Atom) {
-        // FIXME(bholley): Synergize with the DOM to stop splitting strings here.
-        if let Some(classes) = self.get_attr(&ns!(), &atom!("class")) {
-            for c in classes.split(" ") {
-                callback(&Atom::from(c));
-            }
+    fn each_class<F>(&self, callback: F) where F: FnMut(&Atom) {
+        unsafe {
+            let mut class: *mut nsIAtom = ptr::null_mut();
+            let mut class_list: *mut *mut nsIAtom = ptr::null_mut();
+            let length = Gecko_ClassOrClassList(self.element, &mut class, &mut class_list);
+            each_gecko_class(length, class, class_list, callback);
         }
     }
 
+    /// Copies the attribute's value out for the test. The stylist's attribute selectors go
+    /// through NonTSPseudoClass::Attr instead, so this is only for those matched outside of
+    /// its rules.
     fn match_attr<F>(&self, attr: &AttrSelector, test: F) -> bool where F: Fn(&str) -> bool {
         // FIXME(bholley): This is copy-pasted from the servo wrapper's version.
         // We should find a way to share it.
@@ -494,6 +1208,343 @@ impl<'le> ElementExt for GeckoElement<'le> {
     }
 }
 
+impl<'le> NthIndexElement for GeckoElement<'le> {
+    fn nth_index_key(&self) -> usize {
+        self.element as usize
+    }
+
+    fn child_index_hint(&self) -> Option<i32> {
+        match unsafe { Gecko_GetChildIndexHint(self.element) } {
+            -1 => None,
+            index => Some(index + 1),
+        }
+    }
+}
+
+/// A snapshot of an element's state and attributes taken by Gecko before they changed,
+/// used to compute restyle hints. Gecko keeps the old values as atoms and a state word,
+/// so reading them back doesn't copy any strings except for attribute selectors.
+#[derive(Clone, Copy)]
+pub struct GeckoElementSnapshot<'a> {
+    snapshot: *mut ServoElementSnapshot,
+    /// Which of the old values the snapshot is compared on. The rest are taken to be the
+    /// same as the element's.
+    flags: SnapshotFlags,
+    /// The old attributes, when Servo copied them itself, which the id and attribute
+    /// selectors are matched against instead of asking Gecko one attribute at a time.
+    attributes: Option<&'a AttributeSnapshot>,
+    chain: PhantomData<&'a ()>,
+}
+
+impl<'a> GeckoElementSnapshot<'a> {
+    /// Wraps |snapshot|, which mustn't be null.
+    pub unsafe fn from_raw(snapshot: *mut ServoElementSnapshot) -> GeckoElementSnapshot<'a> {
+        GeckoElementSnapshot::with_flags(snapshot, SnapshotFlags::all(), None)
+    }
+
+    /// Wraps |snapshot|, which mustn't be null, to compare only the values that |flags| says
+    /// have changed, with the old attributes in |attributes| if Servo copied them.
+    unsafe fn with_flags(snapshot: *mut ServoElementSnapshot, flags: SnapshotFlags,
+                         attributes: Option<&'a AttributeSnapshot>) -> GeckoElementSnapshot<'a> {
+        debug_assert!(!snapshot.is_null());
+        GeckoElementSnapshot {
+            snapshot: snapshot,
+            flags: flags,
+            attributes: attributes,
+            chain: PhantomData,
+        }
+    }
+
+    fn get_attr(&self, namespace: &Namespace, name: &Atom) -> Option<&'a str> {
+        unsafe {
+            let mut length: u32 = 0;
+            let ptr = Gecko_SnapshotGetAttrAsUTF8(self.snapshot, atom_to_gecko(&namespace.0),
+                                                  atom_to_gecko(name), &mut length);
+            reinterpret_string(ptr, length)
+        }
+    }
+}
+
+impl<'a> ElementSnapshot for GeckoElementSnapshot<'a> {
+    fn state(&self) -> Option<ElementState> {
+        if !self.flags.contains(SNAPSHOT_STATE) {
+            return None;
+        }
+        let state = unsafe { Gecko_SnapshotState(self.snapshot) };
+        Some(element_state::ElementState::from_bits_truncate(state).to_servo())
+    }
+
+    fn has_attrs(&self) -> bool {
+        self.flags.contains(SNAPSHOT_ATTRIBUTES) && (self.attributes.is_some() || unsafe {
+            Gecko_SnapshotHasAttrs(self.snapshot)
+        })
+    }
+
+    fn id_attr(&self) -> Option<Atom> {
+        if let Some(attributes) = self.attributes {
+            return attributes.id().map(id_or_class_for_matching);
+        }
+        unsafe {
+            let id = Gecko_SnapshotGetElementId(self.snapshot);
+            if id.is_null() {
+                None
+            } else {
+                Some(id_or_class_for_matching(atom_from_gecko(id)))
+            }
+        }
+    }
+
+    fn has_class(&self, name: &Atom) -> bool {
+        let mut found = false;
+        self.each_class(|class| if class == name { found = true });
+        found
+    }
+
+    fn each_class<F>(&self, callback: F) where F: FnMut(&Atom) {
+        unsafe {
+            let mut class: *mut nsIAtom = ptr::null_mut();
+            let mut class_list: *mut *mut nsIAtom = ptr::null_mut();
+            let length = Gecko_SnapshotClassOrClassList(self.snapshot, &mut class, &mut class_list);
+            each_gecko_class(length, class, class_list, callback);
+        }
+    }
+
+    fn match_attr<F>(&self, attr: &AttrSelector, is_html: bool, test: F) -> bool
+                    where F: Fn(&str) -> bool {
+        let name = if is_html { &attr.lower_name } else { &attr.name };
+        if let Some(attributes) = self.attributes {
+            let namespace = match attr.namespace {
+                NamespaceConstraint::Specific(ref ns) => Some(ns),
+                NamespaceConstraint::Any => None,
+            };
+            return attributes.any(namespace, name, test);
+        }
+        match attr.namespace {
+            NamespaceConstraint::Specific(ref ns) => {
+                self.get_attr(ns, name).map_or(false, |attr| test(attr))
+            },
+            // FIXME: Like GeckoElement::get_attrs, this needs a way to
+            // enumerate attributes across namespaces.
+            NamespaceConstraint::Any => false,
+        }
+    }
+}
+
+/// An old attribute value: an atom when Gecko had the value as one, as it does for ids and
+/// short values, and a copy of the string when it didn't.
+enum AttributeValue {
+    Atom(GeckoAtom),
+    String(Box<str>),
+}
+
+struct SnapshotAttribute {
+    namespace: GeckoAtom,
+    name: GeckoAtom,
+    value: AttributeValue,
+}
+
+/// Every attribute of an element, copied by Gecko_SnapshotAttributes in one call before
+/// any of them changed. There can be thousands of these waiting for the next restyle, so
+/// the names and whatever values Gecko has atoms for are kept as atoms rather than strings.
+pub struct AttributeSnapshot {
+    attributes: Vec<SnapshotAttribute>,
+}
+
+impl AttributeSnapshot {
+    pub fn take(element: GeckoElement) -> AttributeSnapshot {
+        // Gecko fills in the array through Servo_AttributeSnapshot_SetLength, with atoms and
+        // strings that are only borrowed for the call.
+        let mut copied: Vec<ServoAttrSnapshot> = vec![];
+        unsafe {
+            Gecko_SnapshotAttributes(element.as_raw(), &mut copied as *mut _ as *mut RawServoAttributeSnapshot);
+        }
+        let attributes = copied.iter().map(|attr| unsafe {
+            let value = if attr.mValueAtom.is_null() {
+                let value = reinterpret_string(attr.mValue as *const ::libc::c_char, attr.mValueLength);
+                AttributeValue::String(value.unwrap_or("").into())
+            } else {
+                AttributeValue::Atom(GeckoAtom::from_raw(attr.mValueAtom))
+            };
+            SnapshotAttribute {
+                namespace: GeckoAtom::from_raw(attr.mNamespace),
+                name: GeckoAtom::from_raw(attr.mName),
+                value: value,
+            }
+        }).collect();
+        AttributeSnapshot {
+            attributes: attributes,
+        }
+    }
+
+    /// Whether any attribute called |name| in |namespace|, or in any namespace if that's
+    /// None, had a value that passes |test|.
+    fn any<F>(&self, namespace: Option<&Namespace>, name: &Atom, test: F) -> bool where F: Fn(&str) -> bool {
+        let namespace = namespace.map(|ns| atom_to_gecko(&ns.0));
+        let name = atom_to_gecko(name);
+        self.attributes.iter().any(|attr| {
+            if attr.name.as_ptr() != name || namespace.map_or(false, |ns| attr.namespace.as_ptr() != ns) {
+                return false;
+            }
+            match attr.value {
+                AttributeValue::Atom(ref atom) => test(&atom.to_string()),
+                AttributeValue::String(ref string) => test(string),
+            }
+        })
+    }
+
+    fn id(&self) -> Option<Atom> {
+        let (namespace, name) = (atom_to_gecko(&ns!().0), atom_to_gecko(&Atom::from("id")));
+        let id = self.attributes.iter().find(|attr| attr.name.as_ptr() == name && attr.namespace.as_ptr() == namespace);
+        id.map(|attr| match attr.value {
+            AttributeValue::Atom(ref atom) => unsafe { atom_from_gecko(atom.as_ptr()) },
+            AttributeValue::String(ref string) => Atom::from(&**string),
+        })
+    }
+}
+
+bitflags! {
+    #[doc = "What Servo_NoteElementSnapshot is told is about to change on an element."]
+    flags SnapshotFlags: u8 {
+        #[doc = "The element's event states."]
+        const SNAPSHOT_STATE = 1 << 0,
+        #[doc = "The element's attributes, its id and classes among them."]
+        const SNAPSHOT_ATTRIBUTES = 1 << 1,
+    }
+}
+
+// Keep these in sync with the ServoRestyleHint enum in ServoBindings.h.
+bitflags! {
+    #[repr(C)]
+    #[doc = "The restyle hints that cross the FFI boundary, with the same bits as"]
+    #[doc = "style::restyle_hints::RestyleHint."]
+    flags ServoRestyleHint: u8 {
+        #[doc = "Restyle the element itself."]
+        const SERVO_RESTYLE_SELF = 1 << 0,
+        #[doc = "Restyle the element's descendants."]
+        const SERVO_RESTYLE_DESCENDANTS = 1 << 1,
+        #[doc = "Restyle the element's later siblings and their descendants."]
+        const SERVO_RESTYLE_LATER_SIBLINGS = 1 << 2,
+    }
+}
+
+impl ServoRestyleHint {
+    pub fn from_servo(hint: RestyleHint) -> ServoRestyleHint {
+        let mut result = ServoRestyleHint::empty();
+        if hint.contains(RESTYLE_SELF) { result.insert(SERVO_RESTYLE_SELF) }
+        if hint.contains(RESTYLE_DESCENDANTS) { result.insert(SERVO_RESTYLE_DESCENDANTS) }
+        if hint.contains(RESTYLE_LATER_SIBLINGS) { result.insert(SERVO_RESTYLE_LATER_SIBLINGS) }
+        result
+    }
+
+    pub fn to_servo(&self) -> RestyleHint {
+        let mut hint = RestyleHint::empty();
+        if self.contains(SERVO_RESTYLE_SELF) { hint.insert(RESTYLE_SELF) }
+        if self.contains(SERVO_RESTYLE_DESCENDANTS) { hint.insert(RESTYLE_DESCENDANTS) }
+        if self.contains(SERVO_RESTYLE_LATER_SIBLINGS) { hint.insert(RESTYLE_LATER_SIBLINGS) }
+        hint
+    }
+}
+
+/// A snapshot Servo_NoteElementSnapshot took, what it was taken for, and the attributes
+/// Servo copied for it once they were about to change.
+struct NotedSnapshot {
+    snapshot: *mut ServoElementSnapshot,
+    flags: SnapshotFlags,
+    attributes: Option<AttributeSnapshot>,
+}
+
+/// The snapshots of the elements that changed since the last restyle, by element, which
+/// the next restyle computes restyle hints from. Servo takes the snapshots itself, so they
+/// go away with the table rather than being left for Gecko to drop.
+pub struct ElementSnapshotTable {
+    snapshots: HashMap<*mut RawGeckoElement, NotedSnapshot>,
+}
+
+impl ElementSnapshotTable {
+    pub fn new() -> ElementSnapshotTable {
+        ElementSnapshotTable {
+            snapshots: HashMap::new(),
+        }
+    }
+
+    /// Snapshots |element| before the changes |flags| describes. An element that was noted
+    /// since the last restyle already keeps its first snapshot, which has the values from
+    /// before all of the changes, and only has what else is changing added to it.
+    pub fn note(&mut self, element: GeckoElement, flags: SnapshotFlags) {
+        let entry = self.snapshots.entry(element.as_raw()).or_insert_with(|| {
+            NotedSnapshot {
+                snapshot: unsafe { Gecko_CreateElementSnapshot(element.as_raw()) },
+                flags: SnapshotFlags::empty(),
+                attributes: None,
+            }
+        });
+        if flags.contains(SNAPSHOT_ATTRIBUTES) && entry.attributes.is_none() {
+            entry.attributes = Some(AttributeSnapshot::take(element));
+        }
+        entry.flags.insert(flags);
+    }
+
+    /// Drops the snapshot of |element|, if there's one, as it's leaving the document and
+    /// mustn't be looked at by the next restyle.
+    pub fn forget(&mut self, element: *mut RawGeckoElement) {
+        if let Some(noted) = self.snapshots.remove(&element) {
+            unsafe { Gecko_DropElementSnapshot(noted.snapshot) };
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Takes every snapshot out of the table, leaving it empty.
+    pub fn take(&mut self) -> ElementSnapshotTable {
+        mem::replace(self, ElementSnapshotTable::new())
+    }
+
+    /// Calls |callback| with each element and its snapshot, in no particular order.
+    pub fn each<F>(&self, mut callback: F) where F: FnMut(*mut RawGeckoElement, &GeckoElementSnapshot) {
+        for (&element, noted) in &self.snapshots {
+            let snapshot = unsafe {
+                GeckoElementSnapshot::with_flags(noted.snapshot, noted.flags, noted.attributes.as_ref())
+            };
+            callback(element, &snapshot);
+        }
+    }
+}
+
+impl Drop for ElementSnapshotTable {
+    fn drop(&mut self) {
+        for (_, noted) in self.snapshots.drain() {
+            unsafe { Gecko_DropElementSnapshot(noted.snapshot) };
+        }
+    }
+}
+
+/// |atom| as an id or class to match selectors against. In quirks mode documents those match
+/// ASCII case-insensitively, so they're lowercased, as the stylist does with the selectors.
+fn id_or_class_for_matching(atom: Atom) -> Atom {
+    if in_quirks_mode_document() && atom.bytes().any(|byte| b'A' <= byte && byte <= b'Z') {
+        Atom::from(&*atom.to_ascii_lowercase())
+    } else {
+        atom
+    }
+}
+
+/// Calls |callback| with the class atoms returned by Gecko_ClassOrClassList and friends,
+/// which hand back a single atom when there's only one class to avoid an array.
+unsafe fn each_gecko_class<F>(length: u32, class: *mut nsIAtom, class_list: *mut *mut nsIAtom,
+                              mut callback: F) where F: FnMut(&Atom) {
+    match length {
+        0 => {}
+        1 => callback(&id_or_class_for_matching(atom_from_gecko(class))),
+        length => {
+            for &class in slice::from_raw_parts(class_list, length as usize) {
+                callback(&id_or_class_for_matching(atom_from_gecko(class)));
+            }
+        }
+    }
+}
+
 unsafe fn reinterpret_string<'a>(ptr: *const ::libc::c_char, length: u32) -> Option<&'a str> {
     (ptr as *const u8).as_ref().map(|p| from_utf8_unchecked(slice::from_raw_parts(p, length as usize)))
 }
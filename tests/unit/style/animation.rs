@@ -0,0 +1,267 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use app_units::Au;
+use cssparser::{Parser, ToCss};
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use style::animation::{AnimationValue, SingleTransition, single_transitions};
+use style::properties::longhands::transition_property::computed_value::TransitionProperty;
+use style::properties::longhands::transition_timing_function::computed_value::StepPosition;
+use style::properties::longhands::transition_timing_function::computed_value::TransitionTimingFunction;
+use style::properties::longhands::{transition_delay, transition_duration, transition_property};
+use style::properties::longhands::transition_timing_function;
+use style::properties::parse_style_attribute;
+use style::values::computed::Time;
+
+fn value(property: TransitionProperty, css: &str) -> AnimationValue {
+    let declarations = parse_style_attribute(css, &url!("http://localhost"), Box::new(CSSErrorReporterTest));
+    AnimationValue::from_declarations(property, declarations.normal, Size2D::new(Au(0), Au(0))).unwrap()
+}
+
+fn interpolate(property: TransitionProperty, from: &str, to: &str, progress: f64) -> String {
+    value(property, from).interpolate(&value(property, to), progress).unwrap().serialize()
+}
+
+#[test]
+fn test_length_and_percentage_interpolate_through_calc() {
+    assert_eq!(interpolate(TransitionProperty::Width, "width: 100px", "width: 50%", 0.5),
+               "calc(50px + 25%)");
+    assert_eq!(interpolate(TransitionProperty::TextIndent, "text-indent: calc(10px + 10%)",
+                           "text-indent: 30px", 0.5),
+               "calc(20px + 5%)");
+    // Each part of a calc() interpolates on its own.
+    assert_eq!(interpolate(TransitionProperty::Width, "width: calc(100% - 20px)", "width: calc(20px + 50%)", 0.5),
+               "calc(0px + 75%)");
+    assert_eq!(interpolate(TransitionProperty::Width, "width: calc(100% - 20px)", "width: 0px", 0.25),
+               "calc(-15px + 75%)");
+}
+
+#[test]
+fn test_mismatched_transform_lists_interpolate_as_matrices() {
+    assert_eq!(interpolate(TransitionProperty::Transform, "transform: translate(100px)",
+                           "transform: scale(3)", 0.5),
+               "matrix(2, 0, 0, 2, 50, 0)");
+    assert_eq!(interpolate(TransitionProperty::Transform, "transform: translate(100px)",
+                           "transform: scale(3)", 0.),
+               "matrix(1, 0, 0, 1, 100, 0)");
+}
+
+#[test]
+fn test_matching_transform_lists_interpolate_pairwise() {
+    assert_eq!(interpolate(TransitionProperty::Transform, "transform: scale(1)", "transform: scale(3, 5)", 0.5),
+               "matrix(2, 0, 0, 3, 0, 0)");
+}
+
+#[test]
+fn test_non_interpolable_values_flip_halfway() {
+    let from = "transform: translate(50%)";
+    let to = "transform: scale(2)";
+    assert_eq!(interpolate(TransitionProperty::Transform, from, to, 0.4),
+               value(TransitionProperty::Transform, from).serialize());
+    assert_eq!(interpolate(TransitionProperty::Transform, from, to, 0.5),
+               value(TransitionProperty::Transform, to).serialize());
+}
+
+#[test]
+fn test_colors_interpolate_premultiplied() {
+    assert_eq!(interpolate(TransitionProperty::Color, "color: transparent", "color: rgba(255, 0, 0, 0.5)", 0.5),
+               value(TransitionProperty::Color, "color: rgba(255, 0, 0, 0.25)").serialize());
+}
+
+#[test]
+fn test_shorter_shadow_lists_are_padded_with_transparent_shadows() {
+    let one = "text-shadow: 2px 4px 6px red";
+    let two = "text-shadow: 4px 8px 2px red, 10px 20px 0px rgba(0, 0, 255, 0.5)";
+    assert_eq!(interpolate(TransitionProperty::TextShadow, one, two, 0.5),
+               value(TransitionProperty::TextShadow,
+                     "text-shadow: 3px 6px 4px red, 5px 10px 0px rgba(0, 0, 255, 0.25)").serialize());
+    assert_eq!(interpolate(TransitionProperty::TextShadow, two, one, 0.5),
+               value(TransitionProperty::TextShadow,
+                     "text-shadow: 3px 6px 4px red, 5px 10px 0px rgba(0, 0, 255, 0.25)").serialize());
+    assert_eq!(interpolate(TransitionProperty::TextShadow, "text-shadow: none", one, 0.5),
+               value(TransitionProperty::TextShadow, "text-shadow: 1px 2px 3px rgba(255, 0, 0, 0.5)").serialize());
+
+    // A currentColor shadow can't be blended with a colored one.
+    let current = "text-shadow: 2px 2px";
+    assert_eq!(interpolate(TransitionProperty::TextShadow, current, one, 0.4),
+               value(TransitionProperty::TextShadow, current).serialize());
+}
+
+#[test]
+fn test_values_from_different_properties_do_not_interpolate() {
+    let width = value(TransitionProperty::Width, "width: 10px");
+    let height = value(TransitionProperty::Height, "height: 10px");
+    assert!(width.interpolate(&height, 0.5).is_none());
+    assert!(width == value(TransitionProperty::Width, "width: 10px; height: 20px"));
+}
+
+fn transitions(properties: Vec<TransitionProperty>, durations: Vec<f32>, delays: Vec<f32>,
+               timing_functions: Vec<TransitionTimingFunction>) -> Vec<SingleTransition> {
+    let times = |seconds: Vec<f32>| -> Vec<Time> { seconds.into_iter().map(Time).collect() };
+    single_transitions(&transition_property::computed_value::T(properties),
+                       &transition_duration::computed_value::T(times(durations)),
+                       &transition_delay::computed_value::T(times(delays)),
+                       &transition_timing_function::computed_value::T(timing_functions))
+}
+
+fn transition(property: TransitionProperty, duration: f32, delay: f32,
+              timing_function: TransitionTimingFunction) -> SingleTransition {
+    SingleTransition {
+        property: property,
+        duration: Time(duration),
+        delay: Time(delay),
+        timing_function: timing_function,
+    }
+}
+
+#[test]
+fn test_transition_lists_repeat_to_match_the_properties() {
+    let ease = transition_timing_function::get_initial_single_value();
+    let steps = TransitionTimingFunction::Steps(4, StepPosition::End);
+    let properties = vec![TransitionProperty::Width, TransitionProperty::Height, TransitionProperty::Color];
+    assert_eq!(transitions(properties, vec![1., 2.], vec![0.5], vec![ease, steps]),
+               vec![transition(TransitionProperty::Width, 1., 0.5, ease),
+                    transition(TransitionProperty::Height, 2., 0.5, steps),
+                    transition(TransitionProperty::Color, 1., 0.5, ease)]);
+}
+
+#[test]
+fn test_all_expands_and_later_mentions_win() {
+    let ease = transition_timing_function::get_initial_single_value();
+    let transitions = transitions(vec![TransitionProperty::All, TransitionProperty::Width],
+                                  vec![1., 3.], vec![0.], vec![ease]);
+    assert!(transitions.len() > 40);
+    assert!(transitions.iter().all(|transition| transition.property != TransitionProperty::All));
+    let duration_of = |property| {
+        let mentions: Vec<_> = transitions.iter().filter(|transition| transition.property == property).collect();
+        assert_eq!(mentions.len(), 1);
+        mentions[0].duration
+    };
+    assert_eq!(duration_of(TransitionProperty::Width), Time(3.));
+    assert_eq!(duration_of(TransitionProperty::Height), Time(1.));
+}
+
+#[test]
+fn test_transitions_that_take_no_time_are_left_out() {
+    let ease = transition_timing_function::get_initial_single_value();
+    let properties = vec![TransitionProperty::Width, TransitionProperty::Height, TransitionProperty::Opacity];
+    let transitions = transitions(properties, vec![0., -1., 0.], vec![0., 2., 1.], vec![ease]);
+    // A negative duration counts as zero, but a delay alone is enough.
+    assert_eq!(transitions.iter().map(|transition| transition.property).collect::<Vec<_>>(),
+               vec![TransitionProperty::Height, TransitionProperty::Opacity]);
+}
+
+#[test]
+fn test_values_from_css_tell_whether_they_interpolate() {
+    let base_url = url!("http://localhost");
+    let viewport_size = Size2D::new(Au(0), Au(0));
+    let from_css = |property, css| AnimationValue::from_css(property, css, &base_url, viewport_size);
+    let width = from_css(TransitionProperty::Width, "10px").unwrap();
+    assert!(width.can_interpolate(&from_css(TransitionProperty::Width, "50%").unwrap()));
+    assert!(!width.can_interpolate(&from_css(TransitionProperty::Height, "10px").unwrap()));
+    let translate = from_css(TransitionProperty::Transform, "translate(50%)").unwrap();
+    assert!(!translate.can_interpolate(&from_css(TransitionProperty::Transform, "scale(2)").unwrap()));
+    assert!(from_css(TransitionProperty::Width, "red").is_none());
+}
+
+#[test]
+fn test_gradients_with_the_same_structure_interpolate() {
+    let from = "background-image: none, linear-gradient(red, blue 50%)";
+    let to = "background-image: none, linear-gradient(rgba(255, 0, 0, 0.5) 50%, blue)";
+    assert_eq!(interpolate(TransitionProperty::BackgroundImage, from, to, 0.5),
+               value(TransitionProperty::BackgroundImage,
+                     "background-image: none, linear-gradient(rgba(255, 0, 0, 0.75) 25%, blue 75%)").serialize());
+    assert_eq!(interpolate(TransitionProperty::BackgroundImage,
+                           "background-image: radial-gradient(circle 10px at 0% 0%, red, blue)",
+                           "background-image: radial-gradient(circle 30px at 100% 0%, red, blue)", 0.5),
+               value(TransitionProperty::BackgroundImage,
+                     "background-image: radial-gradient(circle 20px at 50% 0%, red, blue)").serialize());
+
+    let gradient = value(TransitionProperty::BackgroundImage, "background-image: linear-gradient(red, blue)");
+    let three_stops = "background-image: linear-gradient(red, blue, red)";
+    assert!(!gradient.can_interpolate(&value(TransitionProperty::BackgroundImage, three_stops)));
+    let radial = "background-image: radial-gradient(red, blue)";
+    assert!(!gradient.can_interpolate(&value(TransitionProperty::BackgroundImage, radial)));
+    let two_layers = "background-image: linear-gradient(red, blue), none";
+    assert!(!gradient.can_interpolate(&value(TransitionProperty::BackgroundImage, two_layers)));
+    // currentColor stops only interpolate with currentColor.
+    let current = value(TransitionProperty::BackgroundImage, "background-image: linear-gradient(currentColor, blue)");
+    assert!(!gradient.can_interpolate(&current));
+    let current_to_left = "background-image: linear-gradient(to left, currentColor, red)";
+    assert!(current.can_interpolate(&value(TransitionProperty::BackgroundImage, current_to_left)));
+}
+
+fn easing(css: &str) -> Result<TransitionTimingFunction, ()> {
+    let mut parser = Parser::new(css);
+    transition_timing_function::parse_one(&mut parser).and_then(|easing| {
+        if parser.is_exhausted() { Ok(easing) } else { Err(()) }
+    })
+}
+
+fn output(css: &str, input: f64, before_flag: bool) -> f64 {
+    easing(css).unwrap().calculate_output(input, before_flag, 1e-7)
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-5, "{} isn't close enough to {}", actual, expected);
+}
+
+#[test]
+fn test_easings_parse_every_form_and_reject_bad_arguments() {
+    assert_eq!(easing("linear"), Ok(TransitionTimingFunction::Linear));
+    assert_eq!(easing("steps(3)"), Ok(TransitionTimingFunction::Steps(3, StepPosition::End)));
+    assert_eq!(easing("step-start"), Ok(TransitionTimingFunction::Steps(1, StepPosition::Start)));
+    assert_eq!(easing("steps(2, jump-start)"), easing("steps(2, start)"));
+    assert_eq!(easing("steps(2, jump-both)"), Ok(TransitionTimingFunction::Steps(2, StepPosition::JumpBoth)));
+    assert_eq!(easing("steps(2, jump-none)").unwrap().to_css_string(), "steps(2, jump-none)");
+    assert_eq!(easing("cubic-bezier(0.1, -2, 0.9, 3)").unwrap().to_css_string(), "cubic-bezier(0.1, -2, 0.9, 3)");
+
+    assert!(easing("steps(0)").is_err());
+    assert!(easing("steps(1, jump-none)").is_err());
+    assert!(easing("steps(2, middle)").is_err());
+    assert!(easing("cubic-bezier(1.5, 0, 0.5, 1)").is_err());
+    assert!(easing("cubic-bezier(0.5, 0, -0.1, 1)").is_err());
+    assert!(easing("ease ease").is_err());
+}
+
+#[test]
+fn test_curves_match_known_values_and_extrapolate_along_their_tangents() {
+    assert_close(output("ease", 0.5, false), 0.8024033875848);
+    assert_close(output("ease-in", 0.5, false), 0.3153568);
+    assert_close(output("ease-out", 0.5, false), 0.6846432);
+    assert_close(output("ease-in-out", 0.5, false), 0.5);
+    assert_close(output("ease", 0., false), 0.);
+    assert_close(output("ease", 1., false), 1.);
+    assert_eq!(output("linear", 0.3, false), 0.3);
+    assert_eq!(output("linear", -0.5, false), -0.5);
+
+    // ease leaves 0 with the slope of the line to its first control point, and comes into 1 flat.
+    assert_close(output("ease", -1., false), -0.4);
+    assert_close(output("ease", 2., false), 1.);
+    // With an x of 0 the first control point gives no slope, so the second one is used.
+    assert_close(output("cubic-bezier(0, 0, 0.5, 2)", -1., false), -4.);
+    assert_close(output("cubic-bezier(0, 0.5, 1, 0.5)", -1., false), 0.);
+}
+
+#[test]
+fn test_steps_jump_where_their_position_says_and_honor_the_before_flag() {
+    assert_eq!(output("steps(4)", 0.5, false), 0.5);
+    assert_eq!(output("steps(4)", 0.99, false), 0.75);
+    assert_eq!(output("steps(4)", 1., false), 1.);
+    assert_eq!(output("steps(4, start)", 0.1, false), 0.25);
+    assert_eq!(output("steps(4, start)", 1., false), 1.);
+    assert_eq!(output("steps(3, jump-none)", 0.5, false), 0.5);
+    assert_eq!(output("steps(3, jump-none)", 1., false), 1.);
+    assert_eq!(output("steps(2, jump-both)", 0., false), 1. / 3.);
+    assert_eq!(output("steps(2, jump-both)", 0.75, false), 2. / 3.);
+    assert_eq!(output("steps(2, jump-both)", 1., false), 1.);
+
+    // Going backwards through a jump, or before the animation starts, the earlier step holds.
+    assert_eq!(output("step-start", 0., false), 1.);
+    assert_eq!(output("step-start", 0., true), 0.);
+    assert_eq!(output("steps(4)", 0.25, true), 0.);
+    assert_eq!(output("steps(4)", 0.3, true), 0.25);
+    assert_eq!(output("step-start", -0.5, true), 0.);
+}
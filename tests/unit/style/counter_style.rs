@@ -0,0 +1,132 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use string_cache::Atom;
+use style::counter_style::{CounterStyleRule, SpeakAs, Symbol, System, find_counter_style_rule};
+use style::media_queries::{Device, MediaType};
+use style::servo::Stylesheet;
+use style::stylesheets::{CSSRule, Origin};
+
+fn stylesheet(css: &str, origin: Origin) -> Arc<Stylesheet> {
+    Arc::new(Stylesheet::from_str(css, url!("http://localhost"), origin, Box::new(CSSErrorReporterTest)))
+}
+
+fn device() -> Device {
+    Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0))
+}
+
+/// The @counter-style rules `css` parses to in an author sheet.
+fn parse(css: &str) -> Vec<Arc<CounterStyleRule>> {
+    let sheet = stylesheet(css, Origin::Author);
+    let rules = sheet.read_rules().iter().filter_map(|rule| match *rule {
+        CSSRule::CounterStyle(ref rule) => Some(rule.clone()),
+        _ => None,
+    }).collect();
+    rules
+}
+
+fn string(text: &str) -> Symbol {
+    Symbol::String(text.to_owned())
+}
+
+#[test]
+fn test_counter_style_descriptors() {
+    let rules = parse("@counter-style thumbs { \
+                           system: fixed 3; \
+                           symbols: '*' b \"c\"; \
+                           negative: '(' ')'; \
+                           prefix: '['; \
+                           suffix: ']'; \
+                           range: 1 10, 20 infinite; \
+                           pad: 3 '0'; \
+                           fallback: lower-alpha; \
+                           speak-as: bullets; \
+                       }");
+    assert_eq!(rules.len(), 1);
+    let rule = &rules[0];
+    assert_eq!(rule.name, Atom::from("thumbs"));
+    assert_eq!(rule.system, System::Fixed(3));
+    assert_eq!(rule.symbols, vec![string("*"), Symbol::Ident("b".to_owned()), string("c")]);
+    assert_eq!(rule.negative, Some((string("("), Some(string(")")))));
+    assert_eq!(rule.prefix, Some(string("[")));
+    assert_eq!(rule.suffix, Some(string("]")));
+    assert_eq!(rule.range, vec![(Some(1), Some(10)), (Some(20), None)]);
+    assert_eq!(rule.pad, Some((3, string("0"))));
+    assert_eq!(rule.fallback, Some("lower-alpha".into()));
+    assert_eq!(rule.speak_as, Some(SpeakAs::Bullets));
+}
+
+#[test]
+fn test_invalid_descriptors_are_dropped() {
+    let rules = parse("@counter-style a { \
+                           symbols: x; \
+                           range: 10 1; \
+                           pad: -1 '0'; \
+                           fallback: none; \
+                           prefix: 12; \
+                           system: fixed 2; system: bogus; \
+                       }");
+    assert_eq!(rules.len(), 1);
+    let rule = &rules[0];
+    assert_eq!(rule.system, System::Fixed(2));
+    assert_eq!(rule.range, vec![]);
+    assert_eq!(rule.pad, None);
+    assert_eq!(rule.fallback, None);
+    assert_eq!(rule.prefix, None);
+    assert_eq!(rule.speak_as, None);
+}
+
+#[test]
+fn test_systems_need_their_symbols() {
+    // Symbolic by default, which needs a symbol.
+    assert!(parse("@counter-style a { suffix: '.'; }").is_empty());
+    assert_eq!(parse("@counter-style a { symbols: x; }")[0].system, System::Symbolic);
+
+    assert!(parse("@counter-style a { system: alphabetic; symbols: x; }").is_empty());
+    assert_eq!(parse("@counter-style a { system: numeric; symbols: '0' '1'; }").len(), 1);
+
+    assert!(parse("@counter-style a { system: additive; symbols: x; }").is_empty());
+    let rules = parse("@counter-style a { system: additive; additive-symbols: 10 x, y 5, 0 z; }");
+    assert_eq!(rules[0].additive_symbols, vec![(10, Symbol::Ident("x".to_owned())),
+                                               (5, Symbol::Ident("y".to_owned())),
+                                               (0, Symbol::Ident("z".to_owned()))]);
+    // The weights have to decrease.
+    assert!(parse("@counter-style a { system: additive; additive-symbols: 1 x, 5 y; }").is_empty());
+
+    // A style that extends another can't give symbols of its own.
+    let rules = parse("@counter-style a { system: extends decimal; suffix: ')'; }");
+    assert_eq!(rules[0].system, System::Extends(Atom::from("decimal")));
+    assert!(parse("@counter-style a { system: extends decimal; symbols: x; }").is_empty());
+}
+
+#[test]
+fn test_predefined_counter_styles_are_only_definable_by_user_agent_sheets() {
+    assert!(parse("@counter-style decimal { symbols: x; }").is_empty());
+    assert!(parse("@counter-style DISC { symbols: x; }").is_empty());
+    assert!(parse("@counter-style none { symbols: x; }").is_empty());
+    assert_eq!(parse("@counter-style lower-roman { symbols: x; }").len(), 1);
+
+    let sheet = stylesheet("@counter-style decimal { system: numeric; symbols: '0' '1'; }", Origin::UserAgent);
+    assert_eq!(sheet.read_rules().len(), 1);
+}
+
+#[test]
+fn test_find_counter_style_rule_uses_cascade_order() {
+    let sheets = vec![
+        stylesheet("@counter-style a { symbols: author1; } @counter-style b { symbols: b; }", Origin::Author),
+        stylesheet("@counter-style a { symbols: user-agent; }", Origin::UserAgent),
+        stylesheet("@counter-style a { symbols: author2; }", Origin::Author),
+        stylesheet("@counter-style a { symbols: user; }", Origin::User),
+    ];
+    let device = device();
+    let rule = find_counter_style_rule(&sheets, &device, &Atom::from("a")).unwrap();
+    assert_eq!(rule.symbols, vec![Symbol::Ident("author2".to_owned())]);
+    assert!(find_counter_style_rule(&sheets, &device, &Atom::from("c")).is_none());
+
+    let rule = find_counter_style_rule(&sheets[1..2], &device, &Atom::from("a")).unwrap();
+    assert_eq!(rule.symbols, vec![Symbol::Ident("user-agent".to_owned())]);
+}
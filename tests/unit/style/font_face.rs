@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use style::font_face::{FontFaceRule, Source, UnicodeRange, effective_font_face_rules};
+use style::media_queries::{Device, MediaType};
+use style::properties::longhands::font_stretch::computed_value::T as FontStretch;
+use style::properties::longhands::font_style::computed_value::T as FontStyle;
+use style::properties::longhands::font_weight::computed_value::T as FontWeight;
+use style::servo::Stylesheet;
+use style::stylesheets::{CSSRuleIteratorExt, Origin};
+
+fn stylesheet(css: &str, origin: Origin) -> Arc<Stylesheet> {
+    Arc::new(Stylesheet::from_str(css, url!("http://localhost/fonts/"), origin, Box::new(CSSErrorReporterTest)))
+}
+
+fn font_faces(stylesheet: &Stylesheet) -> Vec<&FontFaceRule> {
+    stylesheet.rules().font_face().collect()
+}
+
+#[test]
+fn test_font_face_with_src_fallbacks() {
+    let sheet = stylesheet("@font-face {
+        font-family: Foo;
+        src: local(Foo Bold), url(foo.woff2) format('woff2'), url(foo.ttf) format('truetype', 'opentype');
+        font-weight: bold;
+        font-style: italic;
+        font-stretch: condensed;
+        unicode-range: U+0-7F, U+4??;
+    }", Origin::Author);
+    let rules = font_faces(&sheet);
+    assert_eq!(rules.len(), 1);
+    let rule = rules[0];
+    assert_eq!(rule.family.name(), "Foo");
+    assert_eq!(rule.sources.len(), 3);
+    match rule.sources[0] {
+        Source::Local(ref family) => assert_eq!(family.name(), "Foo Bold"),
+        ref source => panic!("Expected a local source, got {:?}", source),
+    }
+    match rule.sources[1] {
+        Source::Url(ref source) => {
+            assert_eq!(source.url, url!("http://localhost/fonts/foo.woff2"));
+            assert_eq!(source.format_hints, vec!["woff2".to_owned()]);
+        }
+        ref source => panic!("Expected a url source, got {:?}", source),
+    }
+    match rule.sources[2] {
+        Source::Url(ref source) => {
+            assert_eq!(source.url, url!("http://localhost/fonts/foo.ttf"));
+            assert_eq!(source.format_hints, vec!["truetype".to_owned(), "opentype".to_owned()]);
+        }
+        ref source => panic!("Expected a url source, got {:?}", source),
+    }
+    assert_eq!(rule.weight, FontWeight::Weight700);
+    assert_eq!(rule.style, FontStyle::italic);
+    assert_eq!(rule.stretch, FontStretch::condensed);
+    assert_eq!(rule.unicode_range, vec![UnicodeRange { start: 0, end: 0x7F },
+                                        UnicodeRange { start: 0x400, end: 0x4FF }]);
+}
+
+#[test]
+fn test_font_face_ignores_invalid_descriptors() {
+    let sheet = stylesheet("@font-face {
+        font-family: Foo;
+        src: url(foo.woff);
+        font-weight: bolder;
+        font-stretch: wider;
+        unicode-range: U+7F-0;
+    }", Origin::Author);
+    let rules = font_faces(&sheet);
+    assert_eq!(rules.len(), 1);
+    let rule = rules[0];
+    assert_eq!(rule.weight, FontWeight::Weight400);
+    assert_eq!(rule.stretch, FontStretch::normal);
+    assert_eq!(rule.unicode_range, vec![UnicodeRange { start: 0, end: 0x10FFFF }]);
+}
+
+#[test]
+fn test_font_face_without_src_is_dropped() {
+    let sheet = stylesheet("@font-face { font-family: Foo; font-weight: 200; }", Origin::Author);
+    assert!(font_faces(&sheet).is_empty());
+}
+
+#[test]
+fn test_effective_font_face_rules_are_in_cascade_order() {
+    let device = Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0));
+    let sheets = vec![
+        stylesheet("@font-face { font-family: A; src: url(a.woff); }", Origin::Author),
+        stylesheet("@font-face { font-family: B; src: url(b.woff); }", Origin::UserAgent),
+        stylesheet("@media print { @font-face { font-family: C; src: url(c.woff); } }
+                    @font-face { font-family: A; src: url(a2.woff); }", Origin::Author),
+    ];
+    let families: Vec<_> = effective_font_face_rules(&sheets, &device).iter().map(|&(ref sheet, ref rule)| {
+        (sheet.origin, rule.family.name().to_owned())
+    }).collect();
+    assert_eq!(families, vec![(Origin::UserAgent, "B".to_owned()),
+                              (Origin::Author, "A".to_owned()),
+                              (Origin::Author, "A".to_owned())]);
+
+    sheets[0].set_disabled(true);
+    assert_eq!(effective_font_face_rules(&sheets, &device).len(), 2);
+}
@@ -0,0 +1,77 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use style::keyframes::find_keyframes_rule;
+use style::media_queries::{Device, MediaType};
+use style::properties::{DeclaredValue, PropertyDeclaration, longhands};
+use style::servo::Stylesheet;
+use style::stylesheets::Origin;
+
+fn stylesheet(css: &str, origin: Origin) -> Arc<Stylesheet> {
+    Arc::new(Stylesheet::from_str(css, url!("http://localhost"), origin, Box::new(CSSErrorReporterTest)))
+}
+
+fn device() -> Device {
+    Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0))
+}
+
+fn display(value: longhands::display::SpecifiedValue) -> PropertyDeclaration {
+    PropertyDeclaration::Display(DeclaredValue::Value(value))
+}
+
+#[test]
+fn test_later_sheet_defines_keyframes() {
+    let sheets = vec![
+        stylesheet("@keyframes fade { from { display: block; } }", Origin::Author),
+        stylesheet("@keyframes fade { to { display: inline; } } @keyframes other { }", Origin::Author),
+    ];
+    let device = device();
+    let rule = find_keyframes_rule(&sheets, &device, &atom!("fade")).unwrap();
+    let steps = rule.steps();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].offset, 1.);
+    assert_eq!(*steps[0].declarations, vec![display(longhands::display::SpecifiedValue::inline)]);
+
+    assert!(find_keyframes_rule(&sheets, &device, &atom!("a")).is_none());
+}
+
+#[test]
+fn test_author_keyframes_beat_user_agent_and_prefixed_ones() {
+    let sheets = vec![
+        stylesheet("@keyframes fade { 50% { display: inline; } }", Origin::Author),
+        stylesheet("@keyframes fade { 50% { display: block; } }", Origin::UserAgent),
+        stylesheet("@-webkit-keyframes fade { 50% { display: none; } }", Origin::Author),
+    ];
+    let device = device();
+    let rule = find_keyframes_rule(&sheets, &device, &atom!("fade")).unwrap();
+    assert!(!rule.vendor_prefixed);
+    assert_eq!(*rule.steps()[0].declarations, vec![display(longhands::display::SpecifiedValue::inline)]);
+
+    // Prefixed rules are used when they're all there is.
+    let rule = find_keyframes_rule(&sheets[2..], &device, &atom!("fade")).unwrap();
+    assert!(rule.vendor_prefixed);
+}
+
+#[test]
+fn test_keyframes_steps_are_sorted_and_last_wins() {
+    let sheets = vec![stylesheet(r"
+        @keyframes fade {
+            to { display: none; }
+            from, 50% { display: block; animation-timing-function: linear; }
+            50% { display: inline; }
+            150% { display: block; }
+            20% { display: block !important; }
+        }", Origin::Author)];
+    let device = device();
+    let steps = find_keyframes_rule(&sheets, &device, &atom!("fade")).unwrap().steps();
+    let offsets: Vec<f32> = steps.iter().map(|step| step.offset).collect();
+    assert_eq!(offsets, vec![0., 0.2, 0.5, 1.]);
+    assert!(steps[0].timing_function.is_some());
+    assert!(steps[1].declarations.is_empty());
+    assert_eq!(steps[2].timing_function, None);
+    assert_eq!(*steps[2].declarations, vec![display(longhands::display::SpecifiedValue::inline)]);
+}
@@ -9,6 +9,7 @@
 extern crate app_units;
 extern crate cssparser;
 extern crate euclid;
+extern crate heapsize;
 extern crate msg;
 extern crate selectors;
 #[macro_use(atom, ns)] extern crate string_cache;
@@ -17,10 +18,25 @@ extern crate style_traits;
 extern crate url;
 extern crate util;
 
+#[cfg(test)] mod animation;
 #[cfg(test)] mod attr;
+#[cfg(test)] mod counter_style;
+#[cfg(test)] mod font_face;
+#[cfg(test)] mod keyframes;
 #[cfg(test)] mod logical_geometry;
+#[cfg(test)] mod malloc_size_of;
 #[cfg(test)] mod media_queries;
+#[cfg(test)] mod nth_index;
+#[cfg(test)] mod page;
+#[cfg(test)] mod properties;
+#[cfg(test)] mod restyle_hints;
+#[cfg(test)] mod rule_tree;
+#[cfg(test)] mod selector_impl;
+#[cfg(test)] mod shared_lock;
 #[cfg(test)] mod stylesheets;
+#[cfg(test)] mod supports;
+#[cfg(test)] mod traversal_stats;
+#[cfg(test)] mod url_value;
 #[cfg(test)] mod viewport;
 
 #[cfg(test)] mod writing_modes {
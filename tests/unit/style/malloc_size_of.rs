@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use euclid::size::Size2D;
+use heapsize::HeapSizeOf;
+use media_queries::CSSErrorReporterTest;
+use std::os::raw::c_void;
+use std::sync::Arc;
+use style::malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use style::media_queries::{Device, MediaType};
+use style::selector_impl::{PseudoElement, ServoSelectorImpl};
+use style::selector_matching::SharedStylesheet;
+use style::servo::{Stylesheet, Stylist};
+use style::stylesheets::Origin;
+
+/// Pretends every heap block is 16 bytes.
+unsafe extern "C" fn sixteen_bytes(_: *const c_void) -> usize {
+    16
+}
+
+fn size_of_sheet(css: &str) -> usize {
+    let sheet = Arc::new(Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                              Box::new(CSSErrorReporterTest)));
+    sheet.malloc_size_of_children(&mut MallocSizeOfOps::new(sixteen_bytes))
+}
+
+#[test]
+fn test_sheet_size_grows_with_its_rules() {
+    let one_rule = size_of_sheet("a { color: red; }");
+    let three_rules = size_of_sheet("a { color: red; } p { color: blue; } div { margin: 0 auto; }");
+    assert!(one_rule > 0);
+    assert!(three_rules > one_rule);
+}
+
+#[test]
+fn test_shared_sheet_is_measured_once() {
+    let sheet = Arc::new(Stylesheet::from_str("a { color: red; }", url!("http://localhost"), Origin::Author,
+                                              Box::new(CSSErrorReporterTest)));
+    let once = vec![sheet.clone()].malloc_size_of_children(&mut MallocSizeOfOps::new(sixteen_bytes));
+    let twice = vec![sheet.clone(), sheet].malloc_size_of_children(&mut MallocSizeOfOps::new(sixteen_bytes));
+    assert_eq!(once, twice);
+}
+
+fn user_agent_sheet(css: &str) -> Arc<Stylesheet> {
+    Arc::new(Stylesheet::from_str(css, url!("chrome:///ua.css"), Origin::UserAgent, Box::new(CSSErrorReporterTest)))
+}
+
+fn stylist_with(sheet: &Arc<Stylesheet>, shared: Option<&Arc<SharedStylesheet<ServoSelectorImpl>>>) -> Stylist {
+    let mut stylist = Stylist::new(Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0)));
+    if let Some(shared) = shared {
+        stylist.add_shared_stylesheet(shared.clone());
+    }
+    stylist.update(&[sheet.clone()], true);
+    stylist
+}
+
+#[test]
+fn test_stylists_sharing_user_agent_rules_use_less_memory() {
+    let css = "html, address, blockquote, body, div, p, ul, ol { display: block; } \
+               a:link { color: blue; } input[type=hidden] { display: none !important; } \
+               q::before { content: open-quote; } q::after { content: close-quote; }";
+
+    let independent = stylist_with(&user_agent_sheet(css), None).heap_size_of_children() +
+                      stylist_with(&user_agent_sheet(css), None).heap_size_of_children();
+
+    let sheet = user_agent_sheet(css);
+    let shared = Arc::new(SharedStylesheet::new(sheet.clone()));
+    assert!(shared.shares_rule_maps());
+    let first = stylist_with(&sheet, Some(&shared));
+    let second = stylist_with(&sheet, Some(&shared));
+    let sharing = first.heap_size_of_children() + second.heap_size_of_children() + shared.heap_size_of_rule_maps();
+
+    assert!(sharing < independent);
+    // The shared rule maps still have everything in them.
+    assert!(first.has_rules_for_pseudo(&PseudoElement::Before));
+}
+
+#[test]
+fn test_rule_maps_of_sheets_that_depend_on_the_device_are_not_shared() {
+    let shared = SharedStylesheet::new(user_agent_sheet("@media print { p { display: block; } }"));
+    assert!(!shared.shares_rule_maps());
+    assert_eq!(shared.heap_size_of_rule_maps(), 0);
+    assert!(shared.sheet.is_frozen());
+}
@@ -3,12 +3,14 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use app_units::Au;
-use cssparser::{Parser, SourcePosition};
+use cssparser::{Parser, SourcePosition, ToCss};
 use euclid::size::Size2D;
 use std::borrow::ToOwned;
+use std::sync::Arc;
 use style::error_reporting::ParseErrorReporter;
 use style::media_queries::*;
-use style::servo::Stylesheet;
+use style::selector_impl::PseudoElement;
+use style::servo::{Stylesheet, Stylist};
 use style::stylesheets::{Origin, CSSRuleIteratorExt};
 use style::values::specified;
 
@@ -429,3 +431,59 @@ fn test_matching_invalid() {
     media_query_test(&device, "@media screen and (height: 100px) { a { color: red; } }", 0);
     media_query_test(&device, "@media not print and (width: 100) { a { color: red; } }", 0);
 }
+
+#[test]
+fn test_set_device_reports_applicability_changes() {
+    let url = url!("http://localhost");
+    let sheet = Stylesheet::from_str("@media (min-width: 300px) { a { color: red; } }", url.clone(),
+                                     Origin::Author, Box::new(CSSErrorReporterTest));
+    let mut sheet_with_media = Stylesheet::from_str("a { color: blue; }", url, Origin::Author,
+                                                    Box::new(CSSErrorReporterTest));
+    sheet_with_media.set_media(Some(parse_media_query_list(&mut Parser::new("(max-width: 100px)"))));
+
+    let small = Device::new(MediaType::Screen, Size2D::typed(50.0, 50.0));
+    let medium = Device::new(MediaType::Screen, Size2D::typed(200.0, 50.0));
+    let large = Device::new(MediaType::Screen, Size2D::typed(400.0, 50.0));
+    assert!(sheet_with_media.is_effective_for_device(&small));
+    assert!(!sheet_with_media.is_effective_for_device(&medium));
+
+    let sheets = vec![Arc::new(sheet), Arc::new(sheet_with_media)];
+    let mut stylist = Stylist::new(Device::new(MediaType::Screen, Size2D::typed(50.0, 50.0)));
+    stylist.update(&sheets, true);
+
+    // The sheet-level media list stops matching.
+    assert!(stylist.set_device(medium, &sheets));
+    // Nothing changes between these two widths.
+    assert!(!stylist.set_device(Device::new(MediaType::Screen, Size2D::typed(250.0, 50.0)), &sheets));
+    // The nested @media rule starts matching.
+    assert!(stylist.set_device(large, &sheets));
+    assert!(!stylist.set_device(Device::new(MediaType::Screen, Size2D::typed(500.0, 50.0)), &sheets));
+    assert!(stylist.set_device(small, &sheets));
+}
+
+#[test]
+fn test_stylist_knows_which_pseudo_elements_have_rules() {
+    let device = || Device::new(MediaType::Screen, Size2D::typed(50.0, 50.0));
+    let stylist = Stylist::new(device());
+    assert!(!stylist.has_rules_for_pseudo(&PseudoElement::Before));
+    assert!(!stylist.has_rules_for_pseudo(&PseudoElement::After));
+
+    let sheet = Stylesheet::from_str("p::after { color: red; }", url!("http://localhost"), Origin::Author,
+                                     Box::new(CSSErrorReporterTest));
+    let mut stylist = Stylist::new(device());
+    stylist.update(&[Arc::new(sheet)], true);
+    assert!(stylist.has_rules_for_pseudo(&PseudoElement::After));
+}
+
+#[test]
+fn test_mq_serialization() {
+    fn serialized(css: &str) -> String {
+        parse_media_query_list(&mut Parser::new(css)).to_css_string()
+    }
+    assert_eq!(serialized(""), "all");
+    assert_eq!(serialized("screen, print"), "screen, print");
+    assert_eq!(serialized("ONLY screen AND (MIN-WIDTH: 100px)"), "only screen and (min-width: 100px)");
+    assert_eq!(serialized("all and (min-width: 100px) and (max-width: 200px)"),
+               "(min-width: 100px) and (max-width: 200px)");
+    assert_eq!(serialized("not all and (max-width: 10em)"), "not all and (max-width: 10em)");
+}
@@ -0,0 +1,183 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use selectors::Element;
+use selectors::parser::{AttrSelector, SimpleSelector};
+use std::cell::Cell;
+use string_cache::{Atom, Namespace};
+use style::nth_index::{NthIndexCache, NthIndexElement, NthIndexKind, matches_nth, nth_arguments};
+use style::selector_impl::{NonTSPseudoClass, ServoSelectorImpl};
+
+/// The children of a single parent, which count how often matching steps from one to the
+/// next.
+struct MockSiblings {
+    local_names: Vec<Atom>,
+    namespace: Namespace,
+    steps: Cell<usize>,
+    hints: bool,
+}
+
+impl MockSiblings {
+    fn new(local_names: &[&str]) -> MockSiblings {
+        MockSiblings {
+            local_names: local_names.iter().map(|&name| Atom::from(name)).collect(),
+            namespace: ns!(html),
+            steps: Cell::new(0),
+            hints: false,
+        }
+    }
+
+    fn repeated(local_name: &str, count: usize) -> MockSiblings {
+        MockSiblings::new(&vec![local_name; count])
+    }
+
+    fn element(&self, index: usize) -> MockSibling {
+        MockSibling { siblings: self, index: index }
+    }
+
+    fn step_to(&self, index: usize) -> Option<MockSibling> {
+        self.steps.set(self.steps.get() + 1);
+        if index < self.local_names.len() {
+            Some(self.element(index))
+        } else {
+            None
+        }
+    }
+
+    /// The indices of the siblings matching `selector`, looked up in the given order.
+    fn matching<I>(&self, selector: &SimpleSelector<ServoSelectorImpl>, order: I) -> Vec<usize>
+                   where I: Iterator<Item=usize> {
+        let (a, b, kind) = nth_arguments(selector).unwrap();
+        let mut cache = NthIndexCache::new();
+        let mut matched: Vec<_> = order.filter(|&index| {
+            matches_nth(a, b, cache.index(&self.element(index), kind))
+        }).collect();
+        matched.sort();
+        matched
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MockSibling<'a> {
+    siblings: &'a MockSiblings,
+    index: usize,
+}
+
+impl<'a> NthIndexElement for MockSibling<'a> {
+    fn nth_index_key(&self) -> usize {
+        self.index
+    }
+
+    fn child_index_hint(&self) -> Option<i32> {
+        if self.siblings.hints {
+            Some(self.index as i32 + 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Element for MockSibling<'a> {
+    type Impl = ServoSelectorImpl;
+
+    fn match_non_ts_pseudo_class(&self, _: NonTSPseudoClass) -> bool {
+        false
+    }
+    fn parent_element(&self) -> Option<Self> {
+        None
+    }
+    fn first_child_element(&self) -> Option<Self> {
+        None
+    }
+    fn last_child_element(&self) -> Option<Self> {
+        None
+    }
+    fn prev_sibling_element(&self) -> Option<Self> {
+        if self.index == 0 {
+            self.siblings.steps.set(self.siblings.steps.get() + 1);
+            return None;
+        }
+        self.siblings.step_to(self.index - 1)
+    }
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.siblings.step_to(self.index + 1)
+    }
+    fn is_html_element_in_html_document(&self) -> bool {
+        true
+    }
+    fn get_local_name(&self) -> &Atom {
+        &self.siblings.local_names[self.index]
+    }
+    fn get_namespace(&self) -> &Namespace {
+        &self.siblings.namespace
+    }
+    fn get_id(&self) -> Option<Atom> {
+        None
+    }
+    fn has_class(&self, _: &Atom) -> bool {
+        false
+    }
+    fn match_attr<F>(&self, _: &AttrSelector, _: F) -> bool where F: Fn(&str) -> bool {
+        false
+    }
+    fn is_empty(&self) -> bool {
+        true
+    }
+    fn is_root(&self) -> bool {
+        false
+    }
+    fn each_class<F>(&self, _: F) where F: FnMut(&Atom) {}
+}
+
+#[test]
+fn test_matches_nth() {
+    assert!(matches_nth(2, 0, 4));
+    assert!(!matches_nth(2, 0, 3));
+    assert!(matches_nth(0, 3, 3));
+    assert!(!matches_nth(0, 3, 6));
+    assert!(matches_nth(-1, 3, 1));
+    assert!(matches_nth(-1, 3, 3));
+    assert!(!matches_nth(-1, 3, 4));
+    assert!(!matches_nth(3, 2, 1));
+}
+
+#[test]
+fn test_nth_last_child_is_linear_over_many_siblings() {
+    let siblings = MockSiblings::repeated("li", 10000);
+    let matched = siblings.matching(&SimpleSelector::NthLastChild(0, 1), 0..10000);
+    assert_eq!(matched, vec![9999]);
+    assert!(siblings.steps.get() <= 2 * 10000, "took {} steps", siblings.steps.get());
+}
+
+#[test]
+fn test_siblings_looked_up_out_of_order() {
+    let siblings = MockSiblings::repeated("li", 1000);
+    let matched = siblings.matching(&SimpleSelector::NthChild(0, 500), (0..1000).rev());
+    assert_eq!(matched, vec![499]);
+    assert!(siblings.steps.get() <= 2 * 1000, "took {} steps", siblings.steps.get());
+}
+
+#[test]
+fn test_nth_of_type_only_counts_siblings_of_the_same_type() {
+    let siblings = MockSiblings::new(&["h1", "p", "p", "div", "p", "div"]);
+    assert_eq!(siblings.matching(&SimpleSelector::NthOfType(0, 2), 0..6), vec![2, 5]);
+    assert_eq!(siblings.matching(&SimpleSelector::NthLastOfType(0, 1), 0..6), vec![0, 4, 5]);
+    assert_eq!(siblings.matching(&SimpleSelector::NthChild(0, 2), 0..6), vec![1]);
+}
+
+#[test]
+fn test_child_index_hint_skips_walking_siblings() {
+    let mut siblings = MockSiblings::repeated("li", 100);
+    siblings.hints = true;
+    assert_eq!(siblings.matching(&SimpleSelector::NthChild(0, 7), 0..100), vec![6]);
+    assert_eq!(siblings.steps.get(), 0);
+}
+
+#[test]
+fn test_nth_arguments() {
+    let nth_last_of_type: SimpleSelector<ServoSelectorImpl> = SimpleSelector::NthLastOfType(2, 1);
+    let first_child: SimpleSelector<ServoSelectorImpl> = SimpleSelector::FirstChild;
+    assert_eq!(nth_arguments(&nth_last_of_type), Some((2, 1, NthIndexKind { of_type: true, from_end: true })));
+    assert_eq!(nth_arguments(&first_child), None);
+}
@@ -0,0 +1,129 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use app_units::Au;
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use style::media_queries::{Device, MediaType};
+use style::page::{Page, PageOrientation, PagePseudoClass, PageSelector, PageSize, PageStyle, resolve_page_style};
+use style::servo::Stylesheet;
+use style::stylesheets::{CSSRule, Origin};
+
+fn stylesheet(css: &str, origin: Origin) -> Arc<Stylesheet> {
+    Arc::new(Stylesheet::from_str(css, url!("http://localhost"), origin, Box::new(CSSErrorReporterTest)))
+}
+
+fn device() -> Device {
+    Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0))
+}
+
+fn page(name: Option<&str>, first: bool, left: bool) -> Page {
+    Page {
+        name: name.map(Into::into),
+        first: first,
+        left: left,
+        blank: false,
+    }
+}
+
+fn margin(style: &PageStyle, name: &str) -> Option<String> {
+    style.declarations.iter()
+         .find(|declaration| declaration.name().to_string() == name)
+         .map(|declaration| declaration.value())
+}
+
+#[test]
+fn test_author_page_margin_overrides_user_agent_default() {
+    let sheets = vec![
+        stylesheet("@page { margin: 5px; }", Origin::UserAgent),
+        stylesheet("@page { margin-top: 10px; }", Origin::Author),
+    ];
+    let style = resolve_page_style(&sheets, &device(), &page(None, false, false));
+    assert_eq!(margin(&style, "margin-top"), Some("10px".to_owned()));
+    assert_eq!(margin(&style, "margin-left"), Some("5px".to_owned()));
+    assert_eq!(style.declarations.len(), 4);
+    assert_eq!(style.size, PageSize::Auto);
+}
+
+#[test]
+fn test_important_user_agent_page_margin_wins() {
+    let sheets = vec![
+        stylesheet("@page { margin-top: 5px !important; }", Origin::UserAgent),
+        stylesheet("@page { margin-top: 10px !important; size: landscape !important; }", Origin::Author),
+    ];
+    let style = resolve_page_style(&sheets, &device(), &page(None, false, false));
+    assert_eq!(margin(&style, "margin-top"), Some("5px".to_owned()));
+    assert_eq!(style.size, PageSize::Orientation(PageOrientation::Landscape));
+}
+
+#[test]
+fn test_pseudo_pages_and_named_pages() {
+    let sheets = vec![stylesheet("@page :first { margin-top: 1px; } \
+                                  @page { margin-top: 2px; } \
+                                  @page :left { margin-left: 3px; } \
+                                  @page chapter { margin-top: 4px; } \
+                                  @page chapter:right { margin-left: 5px; }", Origin::Author)];
+    let device = device();
+
+    // :first is more specific than no selector, even though it comes before.
+    let style = resolve_page_style(&sheets, &device, &page(None, true, false));
+    assert_eq!(margin(&style, "margin-top"), Some("1px".to_owned()));
+    assert_eq!(margin(&style, "margin-left"), None);
+
+    let style = resolve_page_style(&sheets, &device, &page(None, false, true));
+    assert_eq!(margin(&style, "margin-top"), Some("2px".to_owned()));
+    assert_eq!(margin(&style, "margin-left"), Some("3px".to_owned()));
+
+    // A page name is more specific than any pseudo-page.
+    let style = resolve_page_style(&sheets, &device, &page(Some("chapter"), true, false));
+    assert_eq!(margin(&style, "margin-top"), Some("4px".to_owned()));
+    assert_eq!(margin(&style, "margin-left"), Some("5px".to_owned()));
+}
+
+#[test]
+fn test_page_size() {
+    let device = device();
+    let size = |css: &str| {
+        let sheets = vec![stylesheet(css, Origin::Author)];
+        resolve_page_style(&sheets, &device, &page(None, false, false)).size
+    };
+    assert_eq!(size("@page { size: 100px 200px; }"), PageSize::Explicit(Au::from_px(100), Au::from_px(200)));
+    assert_eq!(size("@page { size: 100px; }"), PageSize::Explicit(Au::from_px(100), Au::from_px(100)));
+    assert_eq!(size("@page { size: portrait; }"), PageSize::Orientation(PageOrientation::Portrait));
+    assert_eq!(size("@page { size: 4in 100px; } @page { size: auto; }"), PageSize::Auto);
+
+    let a4 = size("@page { size: A4; }");
+    let a4_landscape = size("@page { size: landscape a4; }");
+    match (a4, a4_landscape) {
+        (PageSize::Explicit(width, height), PageSize::Explicit(landscape_width, landscape_height)) => {
+            assert!(width < height);
+            assert_eq!((landscape_width, landscape_height), (height, width));
+        }
+        sizes => panic!("Expected explicit sizes but got {:?}", sizes),
+    }
+
+    // Relative lengths and negative ones are invalid, and leave the size alone.
+    assert_eq!(size("@page { size: landscape; size: 10em; size: -1px 2px; }"),
+               PageSize::Orientation(PageOrientation::Landscape));
+}
+
+#[test]
+fn test_page_rules_are_kept_in_the_sheet() {
+    let sheet = stylesheet("@page chapter:first, :left { margin: 0; color: red; } @page a :first { }",
+                           Origin::Author);
+    let rules = sheet.read_rules();
+    assert_eq!(rules.len(), 1);
+    match rules[0] {
+        CSSRule::Page(ref rule) => {
+            assert_eq!(rule.selectors, vec![
+                PageSelector { name: Some("chapter".into()), pseudo_classes: vec![PagePseudoClass::First] },
+                PageSelector { name: None, pseudo_classes: vec![PagePseudoClass::Left] },
+            ]);
+            assert_eq!(rule.declarations.normal.len(), 4);
+            assert_eq!(rule.size, None);
+        }
+        ref rule => panic!("Expected a page rule but got {:?}", rule),
+    }
+}
@@ -0,0 +1,1250 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use app_units::Au;
+use cssparser::{Color, RGBA, ToCss};
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use selectors::matching::DeclarationBlock;
+use style::properties::{ComputedValues, PropertyRestriction, ServoComputedValues, cascade, inherit_from};
+use style::properties::{is_supported_property, parse_property_value, restrict_declarations};
+use style::properties::longhands;
+use style::properties::longhands::transform::computed_value::ComputedMatrix;
+use style::properties::parse_style_attribute;
+use style::selector_matching::CascadeLevel;
+use style::stylesheets::Origin;
+use style::values::computed::{CalcLengthOrPercentage, ColorStop, Image, LengthOrPercentage, LengthOrPercentageOrAuto};
+use style::values::computed::{ExtremumLength, LengthOrPercentageOrNone, MozLength};
+
+/// What `width` or `height` computes to when |length| does.
+fn size(length: LengthOrPercentageOrAuto) -> MozLength {
+    MozLength::LengthOrPercentageOrAuto(length)
+}
+
+fn serialize_declarations(css: &str) -> String {
+    let url = url!("http://localhost");
+    parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest)).to_css_string()
+}
+
+#[test]
+fn test_serialize_declaration_block_collapses_shorthands() {
+    let css = "display: block; margin-top: 1px; margin-right: 2px; margin-bottom: 3px; margin-left: 4px";
+    assert_eq!(serialize_declarations(css), "display: block; margin: 1px 2px 3px 4px;");
+}
+
+#[test]
+fn test_serialize_declaration_block_keeps_importance_apart() {
+    let css = "margin-top: 1px !important; margin-right: 2px; margin-bottom: 3px; margin-left: 4px";
+    assert_eq!(serialize_declarations(css),
+               "margin-right: 2px; margin-bottom: 3px; margin-left: 4px; margin-top: 1px !important;");
+}
+
+#[test]
+fn test_serialize_declaration_block_gives_back_the_shorthands_as_written() {
+    // The longhands of `border-width` are all there too, but these were what was written.
+    let css = "border-top: red solid 1px; border-right: red solid 1px; \
+               border-bottom: red solid 1px; border-left: red solid 1px;";
+    assert_eq!(serialize_declarations(css), css);
+}
+
+fn cascade_declarations(css: &str, parent: Option<&ServoComputedValues>) -> ServoComputedValues {
+    let url = url!("http://localhost");
+    let block = parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest));
+    let declarations = vec![DeclarationBlock::from_declarations(block.normal.clone())];
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    let (style, _) = cascade(viewport_size, &declarations, false, parent, None, Box::new(CSSErrorReporterTest));
+    style
+}
+
+#[test]
+fn test_viewport_percentage_lengths_compute_against_viewport() {
+    let style = cascade_declarations("width: 50vw; height: 10vh", None);
+    assert_eq!(style.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(400))));
+    assert_eq!(style.get_box().height, size(LengthOrPercentageOrAuto::Length(Au::from_px(60))));
+}
+
+#[test]
+fn test_rem_on_root_computes_against_initial_font_size() {
+    let root = cascade_declarations("font-size: 2rem", None);
+    assert_eq!(root.get_font().font_size, Au::from_px(32));
+
+    // Descendants resolve rem against the root's font size.
+    let child = cascade_declarations("font-size: 10px; width: 1rem", Some(&root));
+    assert_eq!(child.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(32))));
+}
+
+#[test]
+fn test_ex_and_ch_fall_back_to_half_an_em_without_font_metrics() {
+    let style = cascade_declarations("font-size: 20px; width: 2ex; height: calc(3ch + 1px)", None);
+    assert_eq!(style.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(20))));
+    assert_eq!(style.get_box().height, size(LengthOrPercentageOrAuto::Calc(CalcLengthOrPercentage {
+        length: Some(Au::from_px(31)),
+        percentage: None,
+    })));
+}
+
+#[test]
+fn test_calc_keeps_its_length_and_percentage_apart() {
+    assert_eq!(serialize_declarations("width: calc(100% - 20px)"), "width: calc(-20px + 100%);");
+    let style = cascade_declarations("width: calc(100% - 20px)", None);
+    assert_eq!(style.get_box().width, size(LengthOrPercentageOrAuto::Calc(CalcLengthOrPercentage {
+        length: Some(Au::from_px(-20)),
+        percentage: Some(1.),
+    })));
+    assert_eq!(style.computed_value_to_string("width"), Ok("calc(-20px + 100%)".to_owned()));
+}
+
+#[test]
+fn test_degenerate_calc() {
+    // Sums of lengths alone, or of percentages alone, serialize as what they sum up to.
+    let style = cascade_declarations("width: calc(10px + 2 * 5px); height: calc(25% + 25%)", None);
+    assert_eq!(style.computed_value_to_string("width"), Ok("20px".to_owned()));
+    assert_eq!(style.computed_value_to_string("height"), Ok("50%".to_owned()));
+
+    // Nested calc() works like parentheses.
+    let style = cascade_declarations("width: calc(10px + calc(2 * 25%))", None);
+    assert_eq!(style.computed_value_to_string("width"), Ok("calc(10px + 50%)".to_owned()));
+
+    // Dividing by zero, adding numbers to lengths and lengths made of numbers alone are
+    // invalid.
+    assert_eq!(serialize_declarations("width: calc(10px / 0)"), "");
+    assert_eq!(serialize_declarations("width: calc(10px + 5)"), "");
+    assert_eq!(serialize_declarations("width: calc(5)"), "");
+    assert_eq!(serialize_declarations("width: calc(10px * 2px)"), "");
+}
+
+/// Cascades `css` the way the declarations matched by a pseudo-element `restriction` limits
+/// are cascaded.
+fn cascade_restricted(css: &str, restriction: PropertyRestriction, parent: &ServoComputedValues)
+                      -> ServoComputedValues {
+    let url = url!("http://localhost");
+    let block = parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest));
+    let declarations = restrict_declarations(&[DeclarationBlock::from_declarations(block.normal.clone())],
+                                             restriction);
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    let (style, _) = cascade(viewport_size, &declarations, false, Some(parent), None,
+                             Box::new(CSSErrorReporterTest));
+    style
+}
+
+#[test]
+fn test_first_line_ignores_the_properties_that_dont_apply_to_it() {
+    let element = cascade_declarations("margin-left: 5px", None);
+    let css = "color: blue; font-size: 20px; letter-spacing: 2px; margin-left: 10px; float: left; \
+               display: block; --custom: 1";
+    let first_line = cascade_restricted(css, PropertyRestriction::FirstLine, &element);
+    assert_eq!(first_line.get_color().color, RGBA { red: 0., green: 0., blue: 1., alpha: 1. });
+    assert_eq!(first_line.computed_value_to_string("font-size"), Ok("20px".to_owned()));
+    assert_eq!(first_line.computed_value_to_string("letter-spacing"), Ok("2px".to_owned()));
+    assert_eq!(custom_property(&first_line, "--custom"), Some("1".to_owned()));
+    // The rest are ignored rather than invalid, so they don't even inherit.
+    assert_eq!(first_line.computed_value_to_string("margin-left"), Ok("0px".to_owned()));
+    assert_eq!(first_line.computed_value_to_string("float"), Ok("none".to_owned()));
+    assert_eq!(first_line.computed_value_to_string("display"), Ok("inline".to_owned()));
+}
+
+#[test]
+fn test_first_letter_takes_box_properties_too() {
+    let element = cascade_declarations("", None);
+    let css = "color: blue; margin-left: 10px; padding-top: 1px; float: left; display: block; width: 10px";
+    let first_letter = cascade_restricted(css, PropertyRestriction::FirstLetter, &element);
+    assert_eq!(first_letter.get_color().color, RGBA { red: 0., green: 0., blue: 1., alpha: 1. });
+    assert_eq!(first_letter.computed_value_to_string("margin-left"), Ok("10px".to_owned()));
+    assert_eq!(first_letter.computed_value_to_string("padding-top"), Ok("1px".to_owned()));
+    assert_eq!(first_letter.computed_value_to_string("float"), Ok("left".to_owned()));
+    assert_eq!(first_letter.computed_value_to_string("width"), Ok("auto".to_owned()));
+}
+
+#[test]
+fn test_restricting_drops_blocks_left_empty() {
+    let url = url!("http://localhost");
+    let blocks: Vec<_> = ["color: red", "width: 10px; display: block", "width: 20px; color: blue"].iter().map(|css| {
+        let block = parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest));
+        DeclarationBlock::from_declarations(block.normal.clone())
+    }).collect();
+    let restricted = restrict_declarations(&blocks, PropertyRestriction::FirstLine);
+    assert_eq!(restricted.len(), 2);
+    assert_eq!(restricted[0].declarations.len(), 1);
+    assert_eq!(restricted[1].declarations.len(), 1);
+    assert_eq!(restricted[1].declarations[0].name().to_string(), "color");
+}
+
+#[test]
+fn test_first_letter_inherits_through_first_line() {
+    // <p>'s ::first-letter sits inside its ::first-line: <p><p::first-line><p::first-letter>.
+    let element = cascade_declarations("color: red; font-size: 10px; letter-spacing: 1px", None);
+    let first_line = cascade_restricted("color: blue; font-size: 2em", PropertyRestriction::FirstLine, &element);
+    let first_letter = cascade_restricted("font-size: 2em; letter-spacing: inherit",
+                                          PropertyRestriction::FirstLetter, &first_line);
+    assert_eq!(first_letter.get_color().color, RGBA { red: 0., green: 0., blue: 1., alpha: 1. });
+    assert_eq!(first_letter.computed_value_to_string("font-size"), Ok("40px".to_owned()));
+    assert_eq!(first_letter.computed_value_to_string("letter-spacing"), Ok("1px".to_owned()));
+}
+
+fn custom_property(style: &ServoComputedValues, name: &str) -> Option<String> {
+    style.computed_value_to_string(name).ok().map(|value| value.trim().to_owned())
+}
+
+#[test]
+fn test_custom_properties_inherit_and_substitute() {
+    let parent = cascade_declarations("--size: 10px; --unused: foo", None);
+    let child = cascade_declarations("--width: var(--size); width: var(--width)", Some(&parent));
+    assert_eq!(custom_property(&child, "--size"), Some("10px".to_owned()));
+    assert_eq!(custom_property(&child, "--unused"), Some("foo".to_owned()));
+    assert_eq!(custom_property(&child, "--width"), Some("10px".to_owned()));
+    assert_eq!(child.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(10))));
+}
+
+#[test]
+fn test_var_fallbacks_chain() {
+    let style = cascade_declarations("--a: var(--missing, var(--also-missing, 3px)); \
+                                      width: var(--a); height: var(--missing, var(--a))", None);
+    assert_eq!(custom_property(&style, "--a"), Some("3px".to_owned()));
+    assert_eq!(style.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(3))));
+    assert_eq!(style.get_box().height, size(LengthOrPercentageOrAuto::Length(Au::from_px(3))));
+}
+
+#[test]
+fn test_custom_property_cycles_are_invalid_at_computed_value_time() {
+    let parent = cascade_declarations("--a: 1px; width: 5px", None);
+    let child = cascade_declarations("--a: var(--b); --b: var(--a); --c: var(--a, 7px); width: var(--b)",
+                                     Some(&parent));
+    // Both variables in the cycle compute to their initial value, even if inherited.
+    assert_eq!(custom_property(&child, "--a"), None);
+    assert_eq!(custom_property(&child, "--b"), None);
+    // So references to them use their fallback...
+    assert_eq!(custom_property(&child, "--c"), Some("7px".to_owned()));
+    // ...or make the property compute to its initial value, since width doesn't inherit.
+    assert_eq!(child.get_box().width, size(LengthOrPercentageOrAuto::Auto));
+}
+
+#[test]
+fn test_var_in_shorthand_substitutes_into_every_longhand() {
+    let style = cascade_declarations("--sides: 1px 2px; margin: var(--sides) 3px", None);
+    assert_eq!(style.computed_value_to_string("margin-top"), Ok("1px".to_owned()));
+    assert_eq!(style.computed_value_to_string("margin-right"), Ok("2px".to_owned()));
+    assert_eq!(style.computed_value_to_string("margin-bottom"), Ok("3px".to_owned()));
+    assert_eq!(style.computed_value_to_string("margin-left"), Ok("2px".to_owned()));
+
+    // An unknown variable makes every longhand invalid at computed-value time.
+    let style = cascade_declarations("margin: var(--missing)", None);
+    assert_eq!(style.computed_value_to_string("margin-top"), Ok("0px".to_owned()));
+}
+
+fn resolved_values(css: &str, names: &[&str]) -> Vec<String> {
+    let style = cascade_declarations(css, None);
+    names.iter().map(|name| style.resolved_value_to_string(name).unwrap()).collect()
+}
+
+#[test]
+fn test_resolved_box_model_values_are_in_px() {
+    assert_eq!(resolved_values("width: 2em; margin-left: 1.5px; padding-top: 10%; display: INLINE-BLOCK",
+                               &["width", "height", "margin-left", "padding-top", "display"]),
+               vec!["32px", "auto", "1.5px", "10%", "inline-block"]);
+}
+
+#[test]
+fn test_resolved_colors_use_rgb_functions() {
+    assert_eq!(resolved_values("color: #00f; border-top-color: currentColor; outline-color: rgba(255, 0, 0, 0.5)",
+                               &["color", "border-top-color", "outline-color"]),
+               vec!["rgb(0, 0, 255)", "rgb(0, 0, 255)", "rgba(255, 0, 0, 0.5)"]);
+}
+
+#[test]
+fn test_system_colors_compute_to_their_stand_ins() {
+    assert_eq!(serialize_declarations("color: GrayText; background-color: window"),
+               "color: GrayText; background-color: window;");
+    assert_eq!(resolved_values("color: GrayText; background-color: window; border-top-color: Highlight",
+                               &["color", "background-color", "border-top-color"]),
+               vec!["rgb(109, 109, 109)", "rgb(255, 255, 255)", "rgb(51, 153, 255)"]);
+    assert_eq!(serialize_declarations("color: NotASystemColor"), "");
+}
+
+#[test]
+fn test_system_fonts_reset_the_font_longhands() {
+    assert_eq!(parse_value("font", "menu").map(|names| names.contains(&"-servo-system-font".to_owned())),
+               Ok(true));
+    assert!(parse_value("font", "menu 12px").is_err());
+    // The internal longhand can't be set directly outside of user agent sheets.
+    assert!(parse_value("-servo-system-font", "menu").is_err());
+
+    // Servo knows no system fonts, so only the declarations after the system font count.
+    assert_eq!(resolved_values("font: bold 20px serif; font: caption; font-style: italic",
+                               &["font-size", "font-weight", "font-style"]),
+               vec!["16px", "400", "italic"]);
+}
+
+#[test]
+fn test_resolved_font_values() {
+    assert_eq!(resolved_values("font: italic bold 12px serif", &["font-style", "font-weight", "font-size"]),
+               vec!["italic", "700", "12px"]);
+}
+
+#[test]
+fn test_resolved_background_values() {
+    assert_eq!(resolved_values("background: red no-repeat", &["background-color", "background-repeat"]),
+               vec!["rgb(255, 0, 0)", "no-repeat"]);
+    assert_eq!(resolved_values("", &["background-color"]), vec!["rgba(0, 0, 0, 0)"]);
+}
+
+/// Turns on the prefs the flexbox and multi-column properties are behind.
+fn enable_flexbox_and_multicol() {
+    let names = ["flex", "inline-flex", "flex-direction", "flex-wrap", "flex-flow", "flex-grow", "flex-shrink",
+                 "flex-basis", "order", "column-rule", "column-rule-width", "column-rule-style", "column-rule-color"];
+    for name in &names {
+        ::util::prefs::set_pref(&format!("layout.{}.enabled", name), ::util::prefs::PrefValue::Boolean(true));
+    }
+}
+
+#[test]
+fn test_flex_shorthand_takes_unitless_zeros_as_factors_until_it_has_both() {
+    enable_flexbox_and_multicol();
+    let flex = |value: &str| resolved_values(&format!("flex: {}", value), &["flex-grow", "flex-shrink", "flex-basis"]);
+    assert_eq!(flex("0"), vec!["0", "1", "0%"]);
+    assert_eq!(flex("0 0"), vec!["0", "0", "0%"]);
+    assert_eq!(flex("0 0 0"), vec!["0", "0", "0px"]);
+    assert_eq!(flex("0px 2"), vec!["2", "1", "0px"]);
+    assert_eq!(flex("2 content"), vec!["2", "1", "content"]);
+    assert_eq!(flex("auto"), vec!["1", "1", "auto"]);
+    assert_eq!(flex("none"), vec!["0", "0", "auto"]);
+    // Only a zero can be a basis without a unit, and the factors can't be negative.
+    assert_eq!(flex("1 2 3"), vec!["0", "1", "auto"]);
+    assert_eq!(flex("-1"), vec!["0", "1", "auto"]);
+}
+
+#[test]
+fn test_flexbox_and_column_rule_shorthands_serialize() {
+    enable_flexbox_and_multicol();
+    assert_eq!(serialize_declarations("flex: 0 0"), "flex: 0 0 0%;");
+    assert_eq!(serialize_declarations("flex-flow: wrap"), "flex-flow: wrap;");
+    assert_eq!(serialize_declarations("flex-flow: column nowrap"), "flex-flow: column;");
+    assert_eq!(serialize_declarations("column-rule: red 1px"), "column-rule: 1px red;");
+}
+
+#[test]
+fn test_shorthands_can_only_be_written_with_a_css_wide_keyword_on_its_own() {
+    assert_eq!(serialize_declarations("margin: inherit"), "margin: inherit;");
+    assert_eq!(serialize_declarations("margin: initial"), "margin: initial;");
+    assert_eq!(serialize_declarations("border: unset"), "border: unset;");
+    for css in &["margin: 1px; margin-top: initial", "font: 12px serif; font-style: inherit"] {
+        let serialized = serialize_declarations(css);
+        assert!(!serialized.starts_with("margin:") && !serialized.starts_with("font:"), "{}", serialized);
+    }
+    assert!(serialize_declarations("font: 12px serif; font-style: inherit").contains("font-style: inherit;"));
+}
+
+#[test]
+fn test_lengths_keep_their_app_units_when_parsed_back() {
+    // 68.23333px is a hair under 4094 app units.
+    assert_eq!(serialize_declarations("width: 68.23333px"), "width: 68.23333px;");
+    assert_eq!(serialize_declarations("width: 1pt"), serialize_declarations(&serialize_declarations("width: 1pt")));
+}
+
+#[test]
+fn test_shorthands_are_written_in_their_own_grammar() {
+    assert_eq!(serialize_declarations("outline: red"), "outline: red;");
+    assert_eq!(serialize_declarations("border-top: 1px"), "border-top: 1px;");
+    assert_eq!(serialize_declarations("list-style: square"), "list-style: square;");
+    assert_eq!(serialize_declarations("list-style: none inside"), "list-style: inside none;");
+    assert_eq!(serialize_declarations("list-style: outside disc"), "list-style: disc;");
+    assert_eq!(serialize_declarations("transition: opacity 1s linear, width 2s steps(2, start) 1s"),
+               "transition: opacity 1s linear 0s, width 2s steps(2, start) 1s;");
+    assert!(!serialize_declarations("transition: opacity 1s; transition-delay: 1s, 2s").starts_with("transition:"));
+    assert_eq!(serialize_declarations("overflow: auto"), "overflow: auto;");
+    assert!(!serialize_declarations("overflow: auto; overflow-y: hidden").starts_with("overflow:"));
+    assert_eq!(serialize_declarations("border-radius: 1px 2%"), "border-radius: 1px 2% 1px 2%;");
+    assert_eq!(serialize_declarations("border-radius: 1px / 2px 3px"),
+               "border-radius: 1px 1px 1px 1px / 2px 3px 2px 3px;");
+}
+
+#[test]
+fn test_flex_items_are_blockified() {
+    enable_flexbox_and_multicol();
+    let root = cascade_declarations("display: inline-flex", None);
+    assert_eq!(root.get_box().display, longhands::display::computed_value::T::flex);
+    let display = |css: &str| cascade_declarations(css, Some(&root)).resolved_value_to_string("display").unwrap();
+    assert_eq!(display("display: inline-flex"), "flex");
+    assert_eq!(display("display: inline-table"), "table");
+    assert_eq!(display("display: inline"), "block");
+    assert_eq!(display("display: none"), "none");
+}
+
+#[test]
+fn test_column_rules_without_a_style_have_no_width() {
+    enable_flexbox_and_multicol();
+    assert_eq!(resolved_values("column-rule: 2px", &["column-rule-width"]), vec!["0px"]);
+    assert_eq!(resolved_values("column-rule: 2px dotted; color: blue", &["column-rule-width", "column-rule-color"]),
+               vec!["2px", "rgb(0, 0, 255)"]);
+}
+
+#[test]
+fn test_gradients_serialize_in_their_shortest_modern_form() {
+    let background_image = |css: &str| serialize_declarations(&format!("background-image: {}", css));
+    assert_eq!(background_image("linear-gradient(to bottom, red, blue 50%)"),
+               "background-image: linear-gradient(red, blue 50%);");
+    assert_eq!(background_image("linear-gradient(to left bottom, red, blue)"),
+               "background-image: linear-gradient(to left bottom, red, blue);");
+    assert_eq!(background_image("radial-gradient(ellipse farthest-corner at center, red, blue)"),
+               "background-image: radial-gradient(red, blue);");
+    assert_eq!(background_image("radial-gradient(closest-side circle at left top, red, blue)"),
+               "background-image: radial-gradient(circle closest-side at 0% 0%, red, blue);");
+    assert_eq!(background_image("radial-gradient(10px 20% at 25% center, red, blue)"),
+               "background-image: radial-gradient(10px 20% at 25% 50%, red, blue);");
+    assert_eq!(background_image("url(a.png), none, radial-gradient(circle 10px, red, blue)"),
+               "background-image: url(\"http://localhost/a.png\"), none, radial-gradient(10px, red, blue);");
+
+    // A circle's size is a length, and a gradient needs two stops.
+    assert_eq!(background_image("radial-gradient(circle 10%, red, blue)"), "");
+    assert_eq!(background_image("radial-gradient(10px 20px 30px, red, blue)"), "");
+    assert_eq!(background_image("linear-gradient(red)"), "");
+}
+
+#[test]
+fn test_legacy_gradients_parse_as_the_modern_ones() {
+    let background_image = |css: &str| serialize_declarations(&format!("background-image: {}", css));
+    // -moz- gradients name where they start, and measure angles counterclockwise from the right.
+    assert_eq!(background_image("-moz-linear-gradient(top, red, blue)"),
+               "background-image: linear-gradient(red, blue);");
+    assert_eq!(background_image("-moz-linear-gradient(right center, red, blue)"),
+               "background-image: linear-gradient(to left, red, blue);");
+    assert_eq!(background_image("-moz-linear-gradient(left top, red, blue)"),
+               "background-image: linear-gradient(to right bottom, red, blue);");
+    assert_eq!(background_image("-moz-linear-gradient(0deg, red, blue)"),
+               "background-image: linear-gradient(to right, red, blue);");
+    assert_eq!(background_image("-moz-radial-gradient(center, circle cover, red, blue)"),
+               "background-image: radial-gradient(circle, red, blue);");
+    assert_eq!(background_image("-moz-radial-gradient(left top, contain, red, blue)"),
+               "background-image: radial-gradient(closest-side at 0% 0%, red, blue);");
+    assert_eq!(background_image("-moz-radial-gradient(left, 45deg, red, blue)"), "");
+    assert_eq!(background_image("-moz-radial-gradient(circle 10px, red, blue)"), "");
+}
+
+fn computed_stops(css: &str) -> Vec<ColorStop> {
+    let style = cascade_declarations(&format!("background-image: {}", css), None);
+    match style.get_background().background_image.0[0] {
+        Some(Image::LinearGradient(ref gradient)) => gradient.stops.clone(),
+        Some(Image::RadialGradient(ref gradient)) => gradient.stops.clone(),
+        _ => panic!("{} isn't a gradient", css),
+    }
+}
+
+fn computed_stop_positions(css: &str) -> Vec<LengthOrPercentage> {
+    computed_stops(css).iter().map(|stop| stop.position).collect()
+}
+
+#[test]
+fn test_gradient_stop_positions_are_filled_in_when_computed() {
+    let percentage = LengthOrPercentage::Percentage;
+    let length = |px| LengthOrPercentage::Length(Au::from_px(px));
+    assert_eq!(computed_stop_positions("linear-gradient(red, green, blue)"),
+               vec![percentage(0.), percentage(0.5), percentage(1.)]);
+    // Stops can't come before the ones preceding them.
+    assert_eq!(computed_stop_positions("linear-gradient(red 20%, green, blue 10%, yellow)"),
+               vec![percentage(0.2), percentage(0.2), percentage(0.2), percentage(1.)]);
+    assert_eq!(computed_stop_positions("radial-gradient(red 10px, green, blue, yellow 40px, black 2em)"),
+               vec![length(10), length(20), length(30), length(40), length(40)]);
+    // Stops between a length and a percentage end up in calc().
+    assert_eq!(computed_stop_positions("linear-gradient(red 10px, green, blue 50%)"),
+               vec![length(10),
+                    LengthOrPercentage::Calc(CalcLengthOrPercentage {
+                        length: Some(Au::from_px(5)),
+                        percentage: Some(0.25),
+                    }),
+                    percentage(0.5)]);
+
+    // currentColor is kept for whichever element's color the gradient ends up painted with.
+    let stops = computed_stops("linear-gradient(currentColor, blue 30px)");
+    assert_eq!(stops[0].color, Color::CurrentColor);
+    assert!(stops[0].implied_position && !stops[1].implied_position);
+
+    let style = cascade_declarations("background-image: linear-gradient(red, blue 30%)", None);
+    assert_eq!(style.computed_value_to_string("background-image"),
+               Ok("linear-gradient(rgb(255, 0, 0), rgb(0, 0, 255) 30%)".to_owned()));
+}
+
+const TRANSFORMS: &'static [&'static str] = &[
+    "matrix(1, 2, 3, 4, 5, 6)",
+    "matrix3d(1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0.5, 10, 20, 30, 1)",
+    "translate(10px, 50%)",
+    "translateX(calc(10px + 50%))",
+    "translateY(2em)",
+    "translateZ(5px)",
+    "translate3d(1px, 2%, 3px)",
+    "scale(2, 3)",
+    "scale3d(1, 2, 3)",
+    "rotate(0.5rad)",
+    "rotate3d(1, 1, 0, 0.5rad)",
+    "skew(0.5rad, 0.25rad)",
+    "perspective(100px)",
+    "translateX(10px) rotate(0.5rad) scale(2, 2)",
+];
+
+#[test]
+fn test_specified_transforms_round_trip() {
+    assert_eq!(serialize_declarations("transform: none"), "transform: none;");
+    for transform in TRANSFORMS {
+        let css = format!("transform: {}", transform);
+        assert_eq!(serialize_declarations(&css), format!("{};", css));
+    }
+}
+
+fn computed_transform(transform: &str) -> longhands::transform::computed_value::T {
+    cascade_declarations(&format!("transform: {}", transform), None).get_effects().transform.clone()
+}
+
+fn matrix_entries(matrix: ComputedMatrix) -> [f32; 16] {
+    [matrix.m11, matrix.m12, matrix.m13, matrix.m14, matrix.m21, matrix.m22, matrix.m23, matrix.m24,
+     matrix.m31, matrix.m32, matrix.m33, matrix.m34, matrix.m41, matrix.m42, matrix.m43, matrix.m44]
+}
+
+#[test]
+fn test_computed_transforms_serialize_as_matrices() {
+    assert_eq!(computed_transform("none").to_css_string(), "none");
+    // The rightmost operation applies first.
+    assert_eq!(computed_transform("translateX(10px) scale(2)").to_css_string(), "matrix(2, 0, 0, 2, 10, 0)");
+    assert_eq!(computed_transform("scale(2) translateX(10px)").to_css_string(), "matrix(2, 0, 0, 2, 20, 0)");
+    assert_eq!(computed_transform("translateX(2em)").to_css_string(), "matrix(1, 0, 0, 1, 32, 0)");
+    assert_eq!(computed_transform("translate3d(1px, 2px, 3px)").to_css_string(),
+               "matrix3d(1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 2, 3, 1)");
+    assert_eq!(computed_transform("perspective(100px)").to_css_string(),
+               "matrix3d(1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, -0.01, 0, 0, 0, 1)");
+}
+
+#[test]
+fn test_computed_translations_keep_their_percentages() {
+    let transform = computed_transform("translate(50%, calc(10px + 10%))");
+    let matrix = transform.to_matrix(Size2D::new(Au::from_px(200), Au::from_px(100))).unwrap();
+    assert_eq!((matrix.m41, matrix.m42), (100., 20.));
+    let matrix = transform.to_matrix(Size2D::new(Au::from_px(20), Au::from_px(300))).unwrap();
+    assert_eq!((matrix.m41, matrix.m42), (10., 40.));
+    // Without a box, the percentages resolve against nothing.
+    assert_eq!(transform.to_css_string(), "matrix(1, 0, 0, 1, 0, 10)");
+}
+
+#[test]
+fn test_computed_transforms_round_trip() {
+    let reference_box = Size2D::new(Au(0), Au(0));
+    for transform in TRANSFORMS {
+        let computed = computed_transform(transform);
+        let reparsed = computed_transform(&computed.to_css_string());
+        let (a, b) = (matrix_entries(computed.to_matrix(reference_box).unwrap()),
+                      matrix_entries(reparsed.to_matrix(reference_box).unwrap()));
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-5, "{} doesn't round-trip: {:?} vs {:?}", transform, a, b);
+        }
+    }
+}
+
+#[test]
+fn test_content_items_round_trip() {
+    for content in &["normal", "none", "\"a\" attr(data-label) \"b\"", "url(\"http://localhost/a.png\")",
+                     "counter(item, decimal)", "counters(item, \".\", lower-alpha)",
+                     "open-quote close-quote no-open-quote no-close-quote"] {
+        let style = cascade_declarations(&format!("content: {}", content), None);
+        assert_eq!(style.resolved_value_to_string("content").as_ref().map(|s| &**s), Ok(*content));
+    }
+    assert_eq!(resolved_values("content: counter(item)", &["content"]), vec!["counter(item, decimal)"]);
+    assert_eq!(resolved_values("content: url(a.png)", &["content"]), vec!["url(\"http://localhost/a.png\")"]);
+    assert!(parse_value("content", "attr(\"quoted\")").is_err());
+    assert!(parse_value("content", "none \"a\"").is_err());
+}
+
+#[test]
+fn test_content_attributes_resolve_to_strings() {
+    use style::properties::longhands::content::computed_value::{ContentItem, T as Content};
+    let content = cascade_declarations("content: attr(title) \" \" attr(lang) attr(missing)", None)
+                      .get_box().content.clone();
+    assert_eq!(content.attribute_names(), vec!["title", "lang", "missing"]);
+    let resolved = content.with_attributes_resolved(|name| match name {
+        "title" => Some("Hello".to_owned()),
+        "lang" => Some("en".to_owned()),
+        _ => None,
+    });
+    assert_eq!(resolved, Content::Content(vec![ContentItem::String("Hello".to_owned()),
+                                               ContentItem::String(" ".to_owned()),
+                                               ContentItem::String("en".to_owned()),
+                                               ContentItem::String("".to_owned())]));
+    assert!(resolved.attribute_names().is_empty());
+    assert_eq!(Content::none.with_attributes_resolved(|_| None), Content::none);
+}
+
+#[test]
+fn test_will_change_values() {
+    assert_eq!(resolved_values("", &["will-change"]), vec!["auto"]);
+    assert_eq!(resolved_values("will-change: AUTO", &["will-change"]), vec!["auto"]);
+    assert_eq!(resolved_values("will-change: transform, scroll-position,contents, --my-thing", &["will-change"]),
+               vec!["transform, scroll-position, contents, --my-thing"]);
+    assert_eq!(resolved_values("will-change: not-a-property", &["will-change"]), vec!["not-a-property"]);
+    for invalid in &["none", "all", "transform, auto", "will-change", "inherit, opacity", "transform opacity", ""] {
+        assert!(parse_value("will-change", invalid).is_err(), "{} should be invalid", invalid);
+    }
+}
+
+#[test]
+fn test_contain_values() {
+    use style::properties::longhands::contain::computed_value::{CONTENT, LAYOUT, PAINT, SIZE, STRICT, STYLE};
+    for &(specified, bits, serialization) in &[("none", 0, "none"),
+                                               ("strict", STRICT, "strict"),
+                                               ("content", CONTENT, "content"),
+                                               ("size", SIZE, "size"),
+                                               ("paint layout", LAYOUT | PAINT, "layout paint"),
+                                               ("style size", SIZE | STYLE, "size style"),
+                                               ("size layout style paint", STRICT, "strict"),
+                                               ("paint style layout", CONTENT, "content")] {
+        let style = cascade_declarations(&format!("contain: {}", specified), None);
+        assert_eq!(style.get_box().contain.0, bits, "contain: {}", specified);
+        assert_eq!(style.resolved_value_to_string("contain"), Ok(serialization.to_owned()));
+    }
+    for invalid in &["size size", "none size", "strict paint", "content layout", "auto", ""] {
+        assert!(parse_value("contain", invalid).is_err(), "{} should be invalid", invalid);
+    }
+}
+
+#[test]
+fn test_touch_action_values() {
+    use style::properties::longhands::touch_action::computed_value::{AUTO, MANIPULATION, NONE, PAN_X, PAN_Y};
+    for &(specified, bits, serialization) in &[("auto", AUTO, "auto"),
+                                               ("none", NONE, "none"),
+                                               ("manipulation", MANIPULATION, "manipulation"),
+                                               ("pan-x", PAN_X, "pan-x"),
+                                               ("PAN-Y", PAN_Y, "pan-y"),
+                                               ("pan-y pan-x", PAN_X | PAN_Y, "pan-x pan-y")] {
+        let style = cascade_declarations(&format!("touch-action: {}", specified), None);
+        assert_eq!(style.get_box().touch_action.0, bits, "touch-action: {}", specified);
+        assert_eq!(style.resolved_value_to_string("touch-action"), Ok(serialization.to_owned()));
+    }
+    for invalid in &["pan-x pan-x", "auto pan-x", "pan-y none", "manipulation pan-x", "pan-z", ""] {
+        assert!(parse_value("touch-action", invalid).is_err(), "{} should be invalid", invalid);
+    }
+}
+
+#[test]
+fn test_sizes_take_the_intrinsic_size_keywords() {
+    let css = "width: -MOZ-FIT-CONTENT; min-height: -moz-min-content; max-width: -moz-available; \
+               max-height: -moz-max-content; color: red";
+    assert_eq!(serialize_declarations(css),
+               "width: -moz-fit-content; min-height: -moz-min-content; max-width: -moz-available; \
+                max-height: -moz-max-content; color: red;");
+    let style = cascade_declarations(css, None);
+    assert_eq!(style.get_box().width, MozLength::ExtremumLength(ExtremumLength::FitContent));
+    assert_eq!(style.resolved_value_to_string("min-height"), Ok("-moz-min-content".to_owned()));
+    // Servo's layout sizes the box as it would by the initial values.
+    assert_eq!(style.content_inline_size(), LengthOrPercentageOrAuto::Auto);
+    assert_eq!(style.min_block_size(), LengthOrPercentage::Length(Au(0)));
+    assert_eq!(style.max_inline_size(), LengthOrPercentageOrNone::None);
+    for invalid in &["fit-content", "-moz-fit-content 10px", "-moz-none"] {
+        assert!(parse_value("width", invalid).is_err(), "{} should be invalid", invalid);
+    }
+}
+
+#[test]
+fn test_pointer_events_takes_the_svg_values_on_any_element() {
+    assert_eq!(resolved_values("", &["pointer-events"]), vec!["auto"]);
+    for value in &["visiblePainted", "visibleFill", "visibleStroke", "visible", "painted", "fill", "stroke", "all"] {
+        assert_eq!(resolved_values(&format!("pointer-events: {}", value.to_uppercase()), &["pointer-events"]),
+                   vec![value.to_string()]);
+    }
+    assert!(parse_value("pointer-events", "visible-painted").is_err());
+}
+
+#[test]
+fn test_user_select_is_also_parsed_under_its_prefixed_name() {
+    assert_eq!(resolved_values("", &["user-select"]), vec!["auto"]);
+    assert_eq!(serialize_declarations("-moz-user-select: -moz-none"), "user-select: -moz-none;");
+    assert_eq!(serialize_declarations("-MOZ-USER-SELECT: tri-state"), "user-select: tri-state;");
+    assert_eq!(parse_value("-moz-user-select", "all"), Ok(vec!["user-select".to_owned()]));
+    assert!(is_supported_property("-moz-user-select"));
+}
+
+#[test]
+fn test_isolation_values() {
+    assert_eq!(resolved_values("", &["isolation"]), vec!["auto"]);
+    assert_eq!(resolved_values("isolation: Isolate", &["isolation"]), vec!["isolate"]);
+    assert!(parse_value("isolation", "isolated").is_err());
+}
+
+#[test]
+fn test_overflow_axes_agree_at_computed_value_time() {
+    let names = &["overflow-x", "overflow-y"];
+    assert_eq!(resolved_values("", names), vec!["visible", "visible"]);
+    assert_eq!(resolved_values("overflow: hidden", names), vec!["hidden", "hidden"]);
+    assert_eq!(resolved_values("overflow-x: scroll", names), vec!["scroll", "auto"]);
+    assert_eq!(resolved_values("overflow-y: hidden", names), vec!["auto", "hidden"]);
+    assert_eq!(resolved_values("overflow-x: hidden; overflow-y: scroll", names), vec!["hidden", "scroll"]);
+}
+
+#[test]
+fn test_scroll_snap_values() {
+    let names = &["resize", "scroll-snap-type-x", "scroll-snap-type-y", "scroll-snap-points-x",
+                  "scroll-snap-points-y", "scroll-snap-destination", "scroll-snap-coordinate"];
+    assert_eq!(resolved_values("", names), vec!["none", "none", "none", "none", "none", "0px 0px", "none"]);
+    assert_eq!(resolved_values("resize: both; scroll-snap-type: mandatory; scroll-snap-points-x: repeat(2em); \
+                                scroll-snap-points-y: REPEAT(50%); scroll-snap-destination: center top; \
+                                scroll-snap-coordinate: 10px 20px, left bottom", names),
+               vec!["both", "mandatory", "mandatory", "repeat(32px)", "repeat(50%)", "50% 0%",
+                    "10px 20px, 0% 100%"]);
+    assert_eq!(parse_value("scroll-snap-type", "proximity"),
+               Ok(vec!["scroll-snap-type-x".to_owned(), "scroll-snap-type-y".to_owned()]));
+    for &(name, invalid) in &[("scroll-snap-points-x", "repeat(-1px)"), ("scroll-snap-points-x", "10px"),
+                              ("scroll-snap-points-y", "repeat()"), ("scroll-snap-coordinate", "none, 0 0"),
+                              ("scroll-snap-type", "mandatory proximity"), ("resize", "auto")] {
+        assert!(parse_value(name, invalid).is_err(), "{}: {} should be invalid", name, invalid);
+    }
+}
+
+#[test]
+fn test_tab_size_values() {
+    assert_eq!(resolved_values("", &["tab-size"]), vec!["8"]);
+    assert_eq!(resolved_values("tab-size: 4", &["tab-size"]), vec!["4"]);
+    assert_eq!(resolved_values("font-size: 10px; tab-size: 2em", &["tab-size"]), vec!["20px"]);
+    assert_eq!(resolved_values("tab-size: 0", &["tab-size"]), vec!["0"]);
+    for invalid in &["-1", "-2px", "auto", "10%"] {
+        assert!(parse_value("tab-size", invalid).is_err(), "tab-size: {} should be invalid", invalid);
+    }
+}
+
+#[test]
+fn test_text_decoration_serializes_without_its_initial_values() {
+    assert_eq!(serialize_declarations("text-decoration: underline"), "text-decoration: underline;");
+    assert_eq!(serialize_declarations("text-decoration: red dotted underline"),
+               "text-decoration: underline dotted red;");
+    assert_eq!(serialize_declarations("text-decoration: red"), "text-decoration: red;");
+    assert_eq!(serialize_declarations("text-decoration: none"), "text-decoration: none;");
+    assert_eq!(serialize_declarations("text-decoration-line: overline; text-decoration-style: solid; \
+                                       text-decoration-color: currentcolor"),
+               "text-decoration: overline;");
+    assert_eq!(parse_value("text-decoration", "underline overline wavy"),
+               Ok(vec!["text-decoration-line".to_owned(), "text-decoration-style".to_owned(),
+                       "text-decoration-color".to_owned()]));
+    assert!(parse_value("text-decoration", "solid dotted").is_err());
+}
+
+#[test]
+fn test_text_decoration_color_is_resolved_when_used() {
+    let style = cascade_declarations("color: blue; text-decoration: underline", None);
+    assert_eq!(style.computed_value_to_string("text-decoration-color"), Ok("currentColor".to_owned()));
+    assert_eq!(resolved_values("color: blue; text-decoration: underline", &["text-decoration-color"]),
+               vec!["rgb(0, 0, 255)"]);
+    let blue = RGBA { red: 0., green: 0., blue: 1., alpha: 1. };
+    assert_eq!(style.get_inheritedtext()._servo_text_decorations_in_effect.underline, Some(blue));
+
+    // A child with a color of its own keeps the color the decoration was drawn in.
+    let child = cascade_declarations("color: red; display: inline", Some(&style));
+    assert_eq!(child.get_inheritedtext()._servo_text_decorations_in_effect.underline, Some(blue));
+}
+
+#[test]
+fn test_text_emphasis_values() {
+    assert_eq!(resolved_values("", &["text-emphasis-style", "text-emphasis-position"]), vec!["none", "over right"]);
+    assert_eq!(resolved_values("text-emphasis: open", &["text-emphasis-style"]), vec!["open circle"]);
+    assert_eq!(resolved_values("writing-mode: vertical-rl; text-emphasis-style: filled", &["text-emphasis-style"]),
+               vec!["filled sesame"]);
+    assert_eq!(resolved_values("text-emphasis-style: triangle; text-emphasis-position: left under",
+                               &["text-emphasis-style", "text-emphasis-position"]),
+               vec!["filled triangle", "under left"]);
+    assert_eq!(resolved_values("color: red; text-emphasis: '*'", &["text-emphasis-style", "text-emphasis-color"]),
+               vec!["\"*\"", "rgb(255, 0, 0)"]);
+    assert_eq!(serialize_declarations("text-emphasis: double-circle red"), "text-emphasis: double-circle red;");
+    assert_eq!(serialize_declarations("text-emphasis: none"), "text-emphasis: none;");
+    for &(name, invalid) in &[("text-emphasis-style", "filled open"), ("text-emphasis-style", "dot circle"),
+                              ("text-emphasis-position", "over"), ("text-emphasis-position", "over under")] {
+        assert!(parse_value(name, invalid).is_err(), "{}: {} should be invalid", name, invalid);
+    }
+}
+
+#[test]
+fn test_border_serializes_as_a_shorthand_only_when_the_sides_agree() {
+    assert_eq!(serialize_declarations("border: 1px solid red"), "border: 1px solid red;");
+    assert_eq!(serialize_declarations("border: solid"), "border: solid;");
+    assert_eq!(serialize_declarations("border: none"), "border: none;");
+    for css in &["border: 1px solid red; border-left-color: blue", "border: 1px solid; border-top-width: 2px",
+                 "border: 1px solid; border-image-slice: 10"] {
+        let serialized = serialize_declarations(css);
+        assert!(!serialized.starts_with("border:") && !serialized.contains(" border:"), "{}: {}", css, serialized);
+    }
+    // Being a reset of the border image, `border` shows up in what it expands to.
+    assert!(parse_value("border", "1px solid").unwrap().contains(&"border-image-repeat".to_owned()));
+}
+
+#[test]
+fn test_border_image_values() {
+    assert_eq!(serialize_declarations("border-image: 10 20% fill / 2 round"),
+               "border-image: 10 20% fill / 2 round;");
+    assert_eq!(serialize_declarations("border-image: fill 1 2 3 4 / 1 / 3px space repeat"),
+               "border-image: 1 2 3 4 fill / 1 / 3px space repeat;");
+    assert_eq!(serialize_declarations("border-image: none"), "border-image: none;");
+    assert_eq!(serialize_declarations("border-image-repeat: round round"), "border-image-repeat: round;");
+    assert_eq!(serialize_declarations("border-image-width: auto 1 10%"), "border-image-width: auto 1 10%;");
+    for &(name, invalid) in &[("border-image-slice", "-1"), ("border-image-slice", "fill 1 fill"),
+                              ("border-image-width", "-1px"), ("border-image-outset", "10%"),
+                              ("border-image-repeat", "round round round"), ("border-image", "10 / / ")] {
+        assert!(parse_value(name, invalid).is_err(), "{}: {} should be invalid", name, invalid);
+    }
+}
+
+#[test]
+fn test_outline_color_invert_computes_to_currentcolor() {
+    assert_eq!(serialize_declarations("outline-color: invert"), "outline-color: invert;");
+    let style = cascade_declarations("color: red; outline-color: invert", None);
+    assert_eq!(style.get_outline().outline_color, Color::CurrentColor);
+    assert_eq!(cascade_declarations("outline-offset: 2px", None).get_outline().outline_offset, Au::from_px(2));
+    assert!(parse_value("outline", "1px hidden").is_err());
+}
+
+#[test]
+fn test_box_shadow_values() {
+    assert_eq!(serialize_declarations("box-shadow: red 1px 2px 3px 4px inset"),
+               "box-shadow: inset 1px 2px 3px 4px red;");
+    assert!(parse_value("box-shadow", "1px 2px -3px").is_err());
+    assert!(parse_value("box-shadow", "1px 2px 3px -4px").is_ok());
+}
+
+#[test]
+fn test_em_lengths_use_the_element_font_size_whatever_the_order() {
+    let parent = cascade_declarations("font-size: 10px", None);
+    let cached = cascade_declarations("font-size: 30px", Some(&parent));
+    let declarations = |css: &str| {
+        let url = url!("http://localhost");
+        let block = parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest));
+        vec![DeclarationBlock::from_declarations(block.normal.clone())]
+    };
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    for css in &["letter-spacing: 1em; font-size: 20px", "font-size: 20px; letter-spacing: 1em"] {
+        let style = cascade_declarations(css, Some(&parent));
+        assert_eq!(style.resolved_value_to_string("letter-spacing"), Ok("20px".to_owned()));
+
+        // Computing from a cached style has the same early pass.
+        let (style, _) = cascade(viewport_size, &declarations(css), false, Some(&parent), Some(&cached),
+                                 Box::new(CSSErrorReporterTest));
+        assert_eq!(style.resolved_value_to_string("letter-spacing"), Ok("20px".to_owned()));
+    }
+}
+
+#[test]
+fn test_keyword_font_sizes_scale_the_medium_size() {
+    assert_eq!(cascade_declarations("font-size: medium", None).get_font().font_size, Au::from_px(16));
+    assert_eq!(cascade_declarations("font-size: x-large", None).get_font().font_size, Au::from_px(24));
+    assert_eq!(cascade_declarations("font-size: xx-large", None).get_font().font_size, Au::from_px(32));
+
+    // Keywords don't depend on the parent's size, unlike larger and smaller.
+    let parent = cascade_declarations("font-size: 20px", None);
+    let child = cascade_declarations("font-size: xx-large; width: 1em", Some(&parent));
+    assert_eq!(child.get_font().font_size, Au::from_px(32));
+    assert_eq!(child.get_box().width, size(LengthOrPercentageOrAuto::Length(Au::from_px(32))));
+    assert_eq!(cascade_declarations("font-size: larger", Some(&parent)).get_font().font_size, Au::from_px(24));
+    assert_eq!(serialize_declarations("font-size: X-Large"), "font-size: x-large;");
+}
+
+#[test]
+fn test_font_shorthand_serializes_only_what_it_can_set() {
+    assert_eq!(serialize_declarations("font: italic bold condensed 12px/30px Foo, serif"),
+               "font: italic bold condensed 12px/30px Foo, serif;");
+    assert_eq!(serialize_declarations("font: normal 12px serif"), "font: 12px serif;");
+    assert_eq!(serialize_declarations("font: caption"), "font: caption;");
+    assert_eq!(parse_value("font", "12px serif").unwrap().len(), 12);
+    assert!(parse_value("font", "italic italic 12px serif").is_err());
+
+    // The shorthand resets font-feature-settings, but can't set it to anything else.
+    let serialized = serialize_declarations("font: 12px serif; font-feature-settings: \"liga\"");
+    assert!(!serialized.starts_with("font:"), "{}", serialized);
+    assert!(serialized.contains("font-feature-settings: \"liga\";"), "{}", serialized);
+}
+
+#[test]
+fn test_font_family_names_are_quoted_unless_identifiers_read_back_the_same() {
+    assert_eq!(serialize_declarations("font-family: Foo  Bar, 'Baz', serif"), "font-family: Foo Bar, Baz, serif;");
+    assert_eq!(serialize_declarations("font-family: 'a,b', '1a', 'a  b', ' a'"),
+               "font-family: \"a,b\", \"1a\", \"a  b\", \" a\";");
+    // Unquoted, these would be a generic family or the whole declaration's keyword.
+    assert_eq!(serialize_declarations("font-family: 'serif', 'Monospace Pro', 'inherit'"),
+               "font-family: \"serif\", \"Monospace Pro\", \"inherit\";");
+    assert_eq!(serialize_declarations("font: 12px 'sans-serif'"), "font: 12px \"sans-serif\";");
+}
+
+#[test]
+fn test_font_feature_settings_values() {
+    assert_eq!(serialize_declarations("font-feature-settings: \"liga\" on, 'swsh' 2, \"smcp\" off"),
+               "font-feature-settings: \"liga\", \"swsh\" 2, \"smcp\" off;");
+    assert_eq!(serialize_declarations("font-feature-settings: normal"), "font-feature-settings: normal;");
+    assert!(parse_value("font-feature-settings", "\"lig\"").is_err());
+    assert!(parse_value("font-feature-settings", "\"liga\" -1").is_err());
+    assert!(parse_value("font-feature-settings", "liga").is_err());
+    assert_eq!(resolved_values("font-size-adjust: 0.5; font-language-override: \"TRK\"",
+                               &["font-size-adjust", "font-language-override", "font-variation-settings"]),
+               vec!["0.5", "\"TRK\"", "normal"]);
+}
+
+#[test]
+fn test_text_overflow_values() {
+    assert_eq!(serialize_declarations("text-overflow: \"\u{2026}\" clip"), "text-overflow: \"\u{2026}\" clip;");
+    assert_eq!(serialize_declarations("text-overflow: ELLIPSIS"), "text-overflow: ellipsis;");
+    assert!(parse_value("text-overflow", "clip ellipsis clip").is_err());
+    assert!(parse_value("text-overflow", "fade").is_err());
+
+    // A single value is for the end of the line, whichever side that's on.
+    let style = cascade_declarations("text-overflow: ellipsis", None);
+    let text_overflow = &style.get_inheritedtext().text_overflow;
+    assert_eq!(text_overflow.line_end(true), text_overflow.line_end(false));
+    let style = cascade_declarations("text-overflow: clip \"-\"", None);
+    let text_overflow = &style.get_inheritedtext().text_overflow;
+    assert_eq!(*text_overflow.line_end(true), longhands::text_overflow::computed_value::Side::Clip);
+    assert_eq!(*text_overflow.line_end(false),
+               longhands::text_overflow::computed_value::Side::String("-".to_owned()));
+}
+
+#[test]
+fn test_direction_is_inherited_and_unicode_bidi_is_not() {
+    let parent = cascade_declarations("direction: rtl; unicode-bidi: bidi-override", None);
+    assert!(!parent.writing_mode.is_bidi_ltr());
+    let child = cascade_declarations("", Some(&parent));
+    assert!(!child.writing_mode.is_bidi_ltr());
+    assert_eq!(child.resolved_value_to_string("direction"), Ok("rtl".to_owned()));
+    assert_eq!(child.resolved_value_to_string("unicode-bidi"), Ok("normal".to_owned()));
+}
+
+fn enable_writing_modes() {
+    for name in &["writing-mode", "text-orientation"] {
+        ::util::prefs::set_pref(&format!("layout.{}.enabled", name), ::util::prefs::PrefValue::Boolean(true));
+    }
+}
+
+fn physical_sides(css: &str, pattern: &str) -> Vec<String> {
+    let names = ["top", "right", "bottom", "left"].iter().map(|side| pattern.replace("%s", side)).collect::<Vec<_>>();
+    resolved_values(css, &names.iter().map(|name| &**name).collect::<Vec<_>>())
+}
+
+#[test]
+fn test_logical_properties_map_onto_the_physical_sides() {
+    enable_writing_modes();
+    let logical = "margin-inline-start: 1px; padding-block-end: 2px; inset-inline-end: 3px";
+    // (writing mode, the sides margin-inline-start, padding-block-end and inset-inline-end set)
+    let cases = [("", ["0px 0px 0px 1px", "0px 0px 2px 0px", "auto 3px auto auto"]),
+                 ("direction: rtl", ["0px 1px 0px 0px", "0px 0px 2px 0px", "auto auto auto 3px"]),
+                 ("writing-mode: vertical-rl", ["1px 0px 0px 0px", "0px 0px 0px 2px", "auto auto 3px auto"]),
+                 ("writing-mode: vertical-lr; direction: rtl",
+                  ["0px 0px 1px 0px", "0px 2px 0px 0px", "3px auto auto auto"])];
+    for &(mode, ref expected) in &cases {
+        let css = format!("{}; {}", mode, logical);
+        assert_eq!(physical_sides(&css, "margin-%s").join(" "), expected[0]);
+        assert_eq!(physical_sides(&css, "padding-%s").join(" "), expected[1]);
+        assert_eq!(physical_sides(&css, "%s").join(" "), expected[2]);
+    }
+}
+
+#[test]
+fn test_logical_and_physical_properties_cascade_in_source_order() {
+    enable_writing_modes();
+    assert_eq!(resolved_values("margin-left: 1px; margin-inline-start: 2px", &["margin-left"]), vec!["2px"]);
+    assert_eq!(resolved_values("margin-inline-start: 2px; margin-left: 1px", &["margin-left"]), vec!["1px"]);
+    assert_eq!(resolved_values("margin-inline-start: 2px; direction: rtl; margin-left: 1px",
+                               &["margin-left", "margin-right"]),
+               vec!["1px", "2px"]);
+    assert_eq!(resolved_values("padding: 1px; padding-block: 2px 3px", &["padding-top", "padding-bottom"]),
+               vec!["2px", "3px"]);
+}
+
+#[test]
+fn test_logical_properties_resolve_to_their_physical_sides() {
+    enable_writing_modes();
+    assert_eq!(resolved_values("writing-mode: vertical-rl; margin-top: 1em; padding-right: 10%",
+                               &["margin-inline-start", "padding-block-start", "inset-block-end"]),
+               vec!["16px", "10%", "auto"]);
+}
+
+#[test]
+fn test_logical_shorthands() {
+    assert_eq!(serialize_declarations("margin-inline: 1px"), "margin-inline: 1px;");
+    assert_eq!(serialize_declarations("margin-inline: 1px 2px"), "margin-inline: 1px 2px;");
+    assert_eq!(serialize_declarations("padding-block-start: 1px; padding-block-end: 1px"), "padding-block: 1px;");
+    assert_eq!(serialize_declarations("inset-block: auto 10%"), "inset-block: auto 10%;");
+    assert!(parse_value("margin-inline", "1px 2px 3px").is_err());
+}
+
+#[test]
+fn test_unknown_properties_have_no_resolved_value() {
+    let style = cascade_declarations("", None);
+    assert_eq!(style.resolved_value_to_string("not-a-property"), Err(()));
+}
+
+#[test]
+fn test_inherit_from_shares_inherited_structs_and_resets_the_rest() {
+    let parent = cascade_declarations("color: red; --x: 1; width: 10px", None);
+    let child: ServoComputedValues = inherit_from(&parent);
+    assert_eq!(&*child.get_color() as *const _, &*parent.get_color() as *const _);
+    assert_eq!(&*child.get_box() as *const _, &*ServoComputedValues::initial_values().get_box() as *const _);
+    assert_eq!(child.get_box().width, size(LengthOrPercentageOrAuto::Auto));
+    assert_eq!(child.computed_value_to_string("--x"), parent.computed_value_to_string("--x"));
+}
+
+#[test]
+fn test_cascade_shares_untouched_inherited_structs_with_the_parent() {
+    let parent = cascade_declarations("font-family: serif; color: red", None);
+    let child = cascade_declarations("margin-top: 1px", Some(&parent));
+    assert_eq!(&*child.get_font() as *const _, &*parent.get_font() as *const _);
+    assert_eq!(&*child.get_color() as *const _, &*parent.get_color() as *const _);
+
+    // Setting a property of an inherited struct gives the child a struct of its own.
+    let child = cascade_declarations("font-family: sans-serif", Some(&parent));
+    assert!(&*child.get_font() as *const _ != &*parent.get_font() as *const _);
+    assert_eq!(&*child.get_color() as *const _, &*parent.get_color() as *const _);
+}
+
+/// The names of the declarations `value` parses into.
+fn parse_value(name: &str, value: &str) -> Result<Vec<String>, ()> {
+    let url = url!("http://localhost");
+    parse_property_value(name, value, &url, Box::new(CSSErrorReporterTest)).map(|declarations| {
+        declarations.iter().map(|declaration| declaration.name().to_string()).collect()
+    })
+}
+
+#[test]
+fn test_parse_property_value_expands_shorthands() {
+    assert_eq!(parse_value("color", "red"), Ok(vec!["color".to_owned()]));
+    assert_eq!(parse_value("margin", "1px 2px"),
+               Ok(vec!["margin-top".to_owned(), "margin-right".to_owned(),
+                       "margin-bottom".to_owned(), "margin-left".to_owned()]));
+}
+
+#[test]
+fn test_parse_property_value_rejects_trailing_input() {
+    assert!(parse_value("color", "red blue").is_err());
+    assert!(parse_value("color", "red !important").is_err());
+    assert!(parse_value("color", "red;").is_err());
+    assert!(parse_value("margin", "1px, 2px").is_err());
+}
+
+#[test]
+fn test_parse_property_value_never_applies_quirks() {
+    // Hashless hex colors are only allowed in quirks mode.
+    assert!(parse_value("color", "ff0000").is_err());
+}
+
+#[test]
+fn test_cursor_keeps_its_images_in_order_with_their_hotspots() {
+    let css = "cursor: url(\"http://localhost/a.png\") 4 6.5, url(\"http://localhost/b.png\"), pointer;";
+    assert_eq!(serialize_declarations(css), css);
+}
+
+#[test]
+fn test_cursor_with_a_bad_entry_is_dropped_whole() {
+    for css in &["url(a.png) 4, pointer", "url(a.png), bogus, pointer", "url(a.png) 1 2 3, auto",
+                 "url(a.png)", "pointer, url(a.png)", "url(a.png) pointer"] {
+        assert!(parse_value("cursor", css).is_err(), "{} parsed", css);
+    }
+    assert_eq!(serialize_declarations("cursor: text; cursor: url(a.png), bogus, pointer"), "cursor: text;");
+}
+
+#[test]
+fn test_cascade_level_order() {
+    let level = CascadeLevel::for_rules;
+    // Normal declarations go from user agent to author, important ones the other way round.
+    assert!(level(Origin::UserAgent, false) < level(Origin::User, false));
+    assert!(level(Origin::User, false) < level(Origin::Author, false));
+    assert!(level(Origin::Author, false) < level(Origin::Author, true));
+    assert!(level(Origin::Author, true) < level(Origin::User, true));
+    assert!(level(Origin::User, true) < level(Origin::UserAgent, true));
+
+    // Style attributes come right after the author rules of the same importance.
+    assert!(level(Origin::Author, false) < CascadeLevel::StyleAttributeNormal);
+    assert!(CascadeLevel::StyleAttributeNormal < level(Origin::Author, true));
+    assert!(level(Origin::Author, true) < CascadeLevel::StyleAttributeImportant);
+    assert!(CascadeLevel::StyleAttributeImportant < level(Origin::User, true));
+
+    // Animations win over every normal declaration and lose to every important one.
+    assert!(CascadeLevel::StyleAttributeNormal < CascadeLevel::Animations);
+    assert!(CascadeLevel::Animations < level(Origin::Author, true));
+
+    // SMIL overrides every normal declaration too, but loses to animations.
+    assert!(CascadeLevel::StyleAttributeNormal < CascadeLevel::SMILOverride);
+    assert!(CascadeLevel::SMILOverride < CascadeLevel::Animations);
+
+    // Transitions win over everything but important user agent declarations.
+    assert!(level(Origin::User, true) < CascadeLevel::Transitions);
+    assert!(CascadeLevel::Transitions < level(Origin::UserAgent, true));
+
+    // Presentational hints lose to everything but normal user agent rules.
+    assert!(level(Origin::UserAgent, false) < CascadeLevel::PresentationalHints);
+    assert!(CascadeLevel::PresentationalHints < level(Origin::User, false));
+
+    let mut levels = CascadeLevel::all().to_vec();
+    levels.sort();
+    assert_eq!(levels, CascadeLevel::all());
+}
+
+#[test]
+fn test_later_cascade_levels_win_whatever_the_specificity() {
+    let url = url!("http://localhost");
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    let levels = CascadeLevel::all();
+    for loser in 0..levels.len() {
+        for winner in loser + 1..levels.len() {
+            // The stylist pushes declarations in cascade order, so the winner goes last even
+            // though the loser has the more specific selector and comes later in its sheet.
+            let declarations = [(loser, !0, 1), (winner, 0, 0)].iter().map(|&(index, specificity, source_order)| {
+                let css = format!("color: rgb({}, 0, 0)", index);
+                let block = parse_style_attribute(&css, &url, Box::new(CSSErrorReporterTest));
+                DeclarationBlock {
+                    declarations: block.normal.clone(),
+                    source_order: source_order,
+                    specificity: specificity,
+                }
+            }).collect::<Vec<_>>();
+            let (style, _) = cascade(viewport_size, &declarations, false, None, None,
+                                     Box::new(CSSErrorReporterTest));
+            assert_eq!((style.get_color().color.red * 255.).round() as usize, winner,
+                       "{:?} should win over {:?}", levels[winner], levels[loser]);
+        }
+    }
+}
+
+/// Each display value, and what it computes to on a box that has to be block-level.
+const BLOCKIFICATION_TABLE: &'static [(&'static str, &'static str)] = &[
+    ("inline", "block"),
+    ("block", "block"),
+    ("inline-block", "block"),
+    ("list-item", "list-item"),
+    ("table", "table"),
+    ("inline-table", "table"),
+    ("table-row-group", "block"),
+    ("table-header-group", "block"),
+    ("table-footer-group", "block"),
+    ("table-row", "block"),
+    ("table-column-group", "block"),
+    ("table-column", "block"),
+    ("table-cell", "block"),
+    ("table-caption", "block"),
+    ("flex", "flex"),
+    ("inline-flex", "flex"),
+    ("none", "none"),
+    ("contents", "contents"),
+];
+
+#[test]
+fn test_display_is_blockified_for_the_root_floats_and_absolutely_positioned_boxes() {
+    enable_flexbox_and_multicol();
+    ::util::prefs::set_pref("layout.contents.enabled", ::util::prefs::PrefValue::Boolean(true));
+    let parent = cascade_declarations("display: block", None);
+    for &(display, blockified) in BLOCKIFICATION_TABLE {
+        for position in &["static", "relative", "absolute", "fixed"] {
+            for float in &["none", "left", "right"] {
+                for &is_root in &[false, true] {
+                    let css = format!("display: {}; position: {}; float: {}", display, position, float);
+                    let style = cascade_declarations(&css, if is_root { None } else { Some(&parent) });
+                    let positioned = *position == "absolute" || *position == "fixed";
+                    let expected = if is_root && display == "contents" {
+                        "block"
+                    } else if is_root || positioned || *float != "none" {
+                        blockified
+                    } else {
+                        display
+                    };
+                    assert!(style.resolved_value_to_string("display") == Ok(expected.to_owned()),
+                            "`{}` on {} should compute to display: {}", css,
+                            if is_root { "the root" } else { "a child" }, expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_flex_items_are_blockified_whatever_the_cached_style() {
+    enable_flexbox_and_multicol();
+    let root = cascade_declarations("display: block", None);
+    let flex_container = cascade_declarations("display: inline-flex", Some(&root));
+    let block_container = cascade_declarations("display: block", Some(&root));
+    let url = url!("http://localhost");
+    let block = parse_style_attribute("display: inline-table", &url, Box::new(CSSErrorReporterTest));
+    let declarations = vec![DeclarationBlock::from_declarations(block.normal.clone())];
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    let cascade_under = |parent: &ServoComputedValues, cached: Option<&ServoComputedValues>| {
+        cascade(viewport_size, &declarations, false, Some(parent), cached, Box::new(CSSErrorReporterTest)).0
+    };
+
+    let flex_item = cascade_under(&flex_container, None);
+    assert_eq!(flex_item.resolved_value_to_string("display"), Ok("table".to_owned()));
+    let not_a_flex_item = cascade_under(&block_container, Some(&flex_item));
+    assert_eq!(not_a_flex_item.resolved_value_to_string("display"), Ok("inline-table".to_owned()));
+    let flex_item = cascade_under(&flex_container, Some(&not_a_flex_item));
+    assert_eq!(flex_item.resolved_value_to_string("display"), Ok("table".to_owned()));
+
+    // The root's style isn't cached, since its specified display is gone.
+    let (_, cacheable) = cascade::<ServoComputedValues>(viewport_size, &declarations, false, None, None,
+                                                        Box::new(CSSErrorReporterTest));
+    assert!(!cacheable);
+}
+
+#[test]
+fn test_counter_lists_serialize_with_their_default_values() {
+    assert_eq!(serialize_declarations("counter-reset: none"), "counter-reset: none;");
+    assert_eq!(serialize_declarations("counter-increment: a b -2"), "counter-increment: a 1 b -2;");
+    assert_eq!(serialize_declarations("counter-reset: a b 3"), "counter-reset: a 0 b 3;");
+    assert_eq!(serialize_declarations("counter-set: list-item 4 a"), "counter-set: list-item 4 a 0;");
+    let style = cascade_declarations("", None);
+    assert_eq!(style.resolved_value_to_string("counter-increment"), Ok("none".to_owned()));
+}
+
+#[test]
+fn test_a_later_counter_list_replaces_the_earlier_one_whole() {
+    let url = url!("http://localhost");
+    let declarations = ["counter-reset: a 1 b 2; counter-increment: a", "counter-reset: c"].iter().map(|css| {
+        DeclarationBlock::from_declarations(parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest)).normal)
+    }).collect::<Vec<_>>();
+    let viewport_size = Size2D::new(Au::from_px(800), Au::from_px(600));
+    let (style, _) = cascade::<ServoComputedValues>(viewport_size, &declarations, false, None, None,
+                                                    Box::new(CSSErrorReporterTest));
+    assert_eq!(style.get_counters().counter_reset.0, vec![("c".to_owned(), 0)]);
+    assert_eq!(style.get_counters().counter_increment.0, vec![("a".to_owned(), 1)]);
+}
+
+#[test]
+fn test_background_shorthand_takes_a_layer_for_each_comma() {
+    assert_eq!(serialize_declarations("background: blue"), "background: blue;");
+    assert_eq!(serialize_declarations("background: none"), "background: none;");
+    assert_eq!(serialize_declarations("background: url(a.png) left top / contain no-repeat, content-box green"),
+               "background: url(\"http://localhost/a.png\") 0% 0% / contain no-repeat, content-box green;");
+    assert_eq!(serialize_declarations("background: url(a.png) 10px 20px / 5px no-repeat fixed content-box \
+                                       padding-box red"),
+               "background: url(\"http://localhost/a.png\") 10px 20px / 5px auto no-repeat fixed \
+                content-box padding-box red;");
+    assert_eq!(serialize_declarations("background: url(a.png), red"),
+               "background: url(\"http://localhost/a.png\"), red;");
+    // Only the bottom layer has a color.
+    assert_eq!(serialize_declarations("background: red, url(a.png)"), "");
+    assert_eq!(serialize_declarations("background: url(a.png), , none"), "");
+}
+
+#[test]
+fn test_background_is_only_serialized_when_every_longhand_has_a_value_for_each_image() {
+    assert_eq!(serialize_declarations("background: url(a.png), url(b.png); background-repeat: repeat-x, no-repeat"),
+               "background: url(\"http://localhost/a.png\") repeat-x, url(\"http://localhost/b.png\") no-repeat;");
+    assert_eq!(serialize_declarations("background: url(a.png), url(b.png); background-repeat: repeat-x"),
+               "background-color: initial; background-position: 0% 0%, 0% 0%; \
+                background-attachment: scroll, scroll; \
+                background-image: url(\"http://localhost/a.png\"), url(\"http://localhost/b.png\"); \
+                background-size: auto auto, auto auto; background-origin: padding-box, padding-box; \
+                background-clip: border-box, border-box; background-repeat: repeat-x;");
+    assert_eq!(serialize_declarations("background: url(a.png); background-clip: content-box, padding-box"),
+               "background-color: initial; background-position: initial; background-repeat: initial; \
+                background-attachment: initial; background-image: url(\"http://localhost/a.png\"); \
+                background-size: initial; background-origin: initial; \
+                background-clip: content-box, padding-box;");
+    assert_eq!(serialize_declarations("background: url(a.png); background-position: inherit"), "\
+               background-color: initial; background-repeat: initial; background-attachment: initial; \
+               background-image: url(\"http://localhost/a.png\"); background-size: initial; \
+               background-origin: initial; background-clip: initial; background-position: inherit;");
+}
+
+#[test]
+fn test_shorter_background_lists_repeat_over_the_images() {
+    use style::properties::longhands::background_repeat::computed_value::SingleComputedValue as Repeat;
+
+    let style = cascade_declarations("background-image: url(a.png), url(b.png), url(c.png); \
+                                      background-repeat: repeat-x, no-repeat", None);
+    let repeat = &style.get_background().background_repeat;
+    assert_eq!((0..3).map(|index| *repeat.layer(index)).collect::<Vec<_>>(),
+               vec![Repeat::repeat_x, Repeat::no_repeat, Repeat::repeat_x]);
+    assert_eq!(style.resolved_value_to_string("background-repeat"), Ok("repeat-x, no-repeat".to_owned()));
+    assert_eq!(style.get_background().background_clip.layer(2).to_css_string(), "border-box");
+}
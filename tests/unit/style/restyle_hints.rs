@@ -0,0 +1,98 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use string_cache::Atom;
+use style::element_state::{ElementState, IN_ACTIVE_STATE, IN_CHECKED_STATE, IN_FOCUS_STATE, IN_HOVER_STATE};
+use style::restyle_hints::{DependencySet, RESTYLE_DESCENDANTS, RESTYLE_LATER_SIBLINGS, RESTYLE_SELF};
+use style::restyle_hints::RestyleHint;
+use style::selector_impl::{ServoSelectorImpl, parse_selector_list_for_matching};
+use style::stylesheets::Origin;
+
+fn dependency_set(selectors: &[(&str, Origin)]) -> DependencySet<ServoSelectorImpl> {
+    let mut dependencies = DependencySet::new();
+    for &(selector_list, origin) in selectors {
+        for selector in parse_selector_list_for_matching::<ServoSelectorImpl>(selector_list, None).unwrap() {
+            dependencies.note_selector(selector.compound_selectors.clone(), origin);
+        }
+    }
+    dependencies
+}
+
+#[test]
+fn test_attribute_dependencies_are_partitioned_by_combinator() {
+    let dependencies = dependency_set(&[("div.note > p, #main a, [data-x] + span, :not(.hidden)", Origin::Author)]);
+
+    assert!(dependencies.might_have_attribute_dependency(&atom!("class")));
+    assert!(dependencies.might_have_attribute_dependency(&atom!("id")));
+    assert!(dependencies.might_have_attribute_dependency(&Atom::from("data-x")));
+    assert!(!dependencies.might_have_attribute_dependency(&atom!("title")));
+
+    assert_eq!(dependencies.attribute_dependency_hint(&Atom::from("data-x")), RESTYLE_LATER_SIBLINGS);
+    assert_eq!(dependencies.attribute_dependency_hint(&atom!("id")), RESTYLE_DESCENDANTS);
+    assert_eq!(dependencies.attribute_dependency_hint(&atom!("class")), RESTYLE_SELF | RESTYLE_DESCENDANTS);
+    assert_eq!(dependencies.attribute_dependency_hint(&atom!("title")), RestyleHint::empty());
+}
+
+#[test]
+fn test_class_dependencies_only_cover_the_classes_selectors_mention() {
+    let dependencies = dependency_set(&[("div.note > p, .warning, :not(.hidden)", Origin::Author)]);
+    assert_eq!(dependencies.class_dependency_hint(&Atom::from("note")), RESTYLE_DESCENDANTS);
+    assert_eq!(dependencies.class_dependency_hint(&Atom::from("warning")), RESTYLE_SELF);
+    assert_eq!(dependencies.class_dependency_hint(&Atom::from("hidden")), RESTYLE_SELF);
+    assert_eq!(dependencies.class_dependency_hint(&Atom::from("other")), RestyleHint::empty());
+}
+
+#[test]
+fn test_state_dependencies_are_partitioned_by_combinator() {
+    let dependencies = dependency_set(&[("a:hover, li:focus ~ li", Origin::Author)]);
+    assert_eq!(dependencies.state_dependency_hint(IN_HOVER_STATE), RESTYLE_SELF);
+    assert_eq!(dependencies.state_dependency_hint(IN_FOCUS_STATE), RESTYLE_LATER_SIBLINGS);
+    assert_eq!(dependencies.state_dependency_hint(IN_HOVER_STATE | IN_FOCUS_STATE),
+               RESTYLE_SELF | RESTYLE_LATER_SIBLINGS);
+
+    let dependencies = dependency_set(&[("p", Origin::Author)]);
+    assert_eq!(dependencies.state_dependency_hint(IN_HOVER_STATE), RestyleHint::empty());
+}
+
+#[test]
+fn test_content_attributes_restyle_the_element() {
+    let mut dependencies = dependency_set(&[("div + [data-x]", Origin::Author)]);
+    dependencies.note_content_attribute(Atom::from("data-label"), Origin::Author);
+    dependencies.note_content_attribute(Atom::from("data-x"), Origin::User);
+    assert!(dependencies.might_have_attribute_dependency(&Atom::from("data-label")));
+    assert_eq!(dependencies.attribute_dependency_hint(&Atom::from("data-label")), RESTYLE_SELF);
+    assert_eq!(dependencies.attribute_dependency_hint(&Atom::from("data-x")), RESTYLE_SELF);
+
+    dependencies.clear_origin(Origin::Author);
+    assert!(!dependencies.might_have_attribute_dependency(&Atom::from("data-label")));
+    assert_eq!(dependencies.attribute_dependency_hint(&Atom::from("data-x")), RESTYLE_SELF);
+}
+
+#[test]
+fn test_clearing_an_origin_forgets_its_dependencies() {
+    let mut dependencies = dependency_set(&[("[type]", Origin::UserAgent), (".note, a:hover", Origin::Author)]);
+    dependencies.clear_origin(Origin::Author);
+    assert!(dependencies.might_have_attribute_dependency(&atom!("type")));
+    assert!(!dependencies.might_have_attribute_dependency(&atom!("class")));
+    assert_eq!(dependencies.class_dependency_hint(&Atom::from("note")), RestyleHint::empty());
+    assert_eq!(dependencies.state_dependency_hint(IN_HOVER_STATE), RestyleHint::empty());
+}
+
+#[test]
+fn test_element_state_mask_is_the_union_of_the_remaining_origins() {
+    let mut dependencies = dependency_set(&[("input:checked", Origin::UserAgent),
+                                            ("a:hover, p:not(:focus)", Origin::Author)]);
+    let bits = |state: ElementState| state.bits() as u64;
+    assert!(dependencies.depends_on_element_state(bits(IN_CHECKED_STATE)));
+    assert!(dependencies.depends_on_element_state(bits(IN_FOCUS_STATE)));
+    assert!(dependencies.depends_on_element_state(bits(IN_ACTIVE_STATE | IN_HOVER_STATE)));
+    assert!(!dependencies.depends_on_element_state(bits(IN_ACTIVE_STATE)));
+
+    dependencies.clear_origin(Origin::Author);
+    assert!(dependencies.depends_on_element_state(bits(IN_CHECKED_STATE)));
+    assert!(!dependencies.depends_on_element_state(bits(IN_HOVER_STATE | IN_FOCUS_STATE)));
+
+    dependencies.clear();
+    assert!(!dependencies.depends_on_element_state(bits(IN_CHECKED_STATE)));
+}
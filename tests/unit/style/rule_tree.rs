@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::sync::Arc;
+use style::properties::{ComputedValues, ServoComputedValues};
+use style::rule_tree::{RuleNodeStyleCache, RuleTree};
+use style::selector_matching::{CascadeLevel, DeclarationBlock};
+use util::arc_ptr_eq;
+
+fn block() -> DeclarationBlock {
+    DeclarationBlock::from_declarations(Arc::new(vec![]))
+}
+
+#[test]
+fn test_same_rules_share_a_node() {
+    let tree = RuleTree::new();
+    let (a, b) = (block(), block());
+    let first = tree.insert_ordered_rules(&[a.clone(), b.clone()]);
+    let second = tree.insert_ordered_rules(&[a.clone(), b.clone()]);
+    assert_eq!(first.id(), second.id());
+    assert!(first.id() != tree.insert_ordered_rules(&[b.clone(), a.clone()]).id());
+    assert_eq!(first.parent().unwrap().id(), tree.insert_ordered_rules(&[a.clone()]).id());
+    assert_eq!(tree.insert_ordered_rules(&[]).id(), tree.root().id());
+}
+
+#[test]
+fn test_gc_forgets_unused_nodes() {
+    let tree = RuleTree::new();
+    let (a, b) = (block(), block());
+    let kept = tree.insert_ordered_rules(&[a.clone()]);
+    let dropped = tree.insert_ordered_rules(&[b.clone(), a.clone()]);
+    assert_eq!(tree.node_count(), 4);
+
+    drop(dropped);
+    tree.gc();
+    assert_eq!(tree.node_count(), 2);
+    assert_eq!(tree.insert_ordered_rules(&[a.clone()]).id(), kept.id());
+}
+
+#[test]
+fn test_replacing_rules_keeps_the_rest_of_the_path() {
+    let tree = RuleTree::new();
+    let (a, b, c, d) = (block(), block(), block(), block());
+    let node = tree.insert_ordered_rules(&[a.clone(), b.clone(), c.clone()]);
+    assert_eq!(node.path().len(), 3);
+    assert!(arc_ptr_eq(&node.path()[1].declarations, &b.declarations));
+
+    let replaced = tree.replace_rules(&node, 1..2, &[d.clone()]);
+    assert_eq!(replaced.id(), tree.insert_ordered_rules(&[a.clone(), d.clone(), c.clone()]).id());
+    let inserted = tree.replace_rules(&node, 2..2, &[d.clone()]);
+    assert_eq!(inserted.id(), tree.insert_ordered_rules(&[a.clone(), b.clone(), d.clone(), c.clone()]).id());
+    assert_eq!(tree.replace_rules(&node, 1..2, &[b.clone()]).id(), node.id());
+}
+
+#[test]
+fn test_replacing_levels_puts_the_new_blocks_where_the_level_goes() {
+    let tree = RuleTree::new();
+    let (author, important, transition, smil, animation) = (block(), block(), block(), block(), block());
+    let levels = [(CascadeLevel::AuthorNormal, 0..1), (CascadeLevel::AuthorImportant, 1..2),
+                  (CascadeLevel::Transitions, 2..3)];
+    let node = tree.insert_ordered_rules_at_levels(&[author.clone(), important.clone(), transition.clone()],
+                                                   &levels);
+    assert_eq!(node.level(), Some(CascadeLevel::Transitions));
+
+    // The transition goes, and the animations go between the normal and important blocks.
+    let replaced = tree.replace_levels(&node, &[(CascadeLevel::SMILOverride, vec![smil.clone()]),
+                                                (CascadeLevel::Animations, vec![animation.clone()]),
+                                                (CascadeLevel::Transitions, vec![])]);
+    let path = replaced.path_with_levels();
+    let expected = [(CascadeLevel::AuthorNormal, &author), (CascadeLevel::SMILOverride, &smil),
+                    (CascadeLevel::Animations, &animation), (CascadeLevel::AuthorImportant, &important)];
+    assert_eq!(path.len(), expected.len());
+    for (&(level, ref block), &(expected_level, expected_block)) in path.iter().zip(&expected) {
+        assert_eq!(level, Some(expected_level));
+        assert!(arc_ptr_eq(&block.declarations, &expected_block.declarations));
+    }
+    assert_eq!(replaced.parent().unwrap().parent().unwrap().parent().unwrap().id(),
+               node.parent().unwrap().parent().unwrap().id());
+
+    // Putting the blocks back leads to the same node.
+    let restored = tree.replace_levels(&replaced, &[(CascadeLevel::SMILOverride, vec![]),
+                                                    (CascadeLevel::Animations, vec![]),
+                                                    (CascadeLevel::Transitions, vec![transition.clone()])]);
+    assert_eq!(restored.id(), node.id());
+}
+
+#[test]
+fn test_replaced_declarations_make_the_nodes_with_them_stale() {
+    let tree = RuleTree::new();
+    let (a, b, c) = (block(), block(), block());
+    let stale = tree.insert_ordered_rules(&[a.clone(), b.clone(), c.clone()]);
+    let current = tree.insert_ordered_rules(&[a.clone(), c.clone()]);
+    assert!(tree.refresh(&stale).is_none());
+
+    let new_b = Arc::new(vec![]);
+    tree.replace_declarations(&b.declarations, &new_b);
+    assert!(tree.has_replacements());
+    assert!(tree.refresh(&current).is_none());
+    let refreshed = tree.refresh(&stale).unwrap();
+    let path = refreshed.path();
+    assert!(arc_ptr_eq(&path[0].declarations, &a.declarations));
+    assert!(arc_ptr_eq(&path[1].declarations, &new_b));
+    assert!(arc_ptr_eq(&path[2].declarations, &c.declarations));
+    assert!(tree.refresh(&refreshed).is_none());
+
+    // Changing them again takes the nodes with the first ones straight to the latest.
+    let newer_b = Arc::new(vec![]);
+    tree.replace_declarations(&new_b, &newer_b);
+    assert!(arc_ptr_eq(&tree.refresh(&stale).unwrap().path()[1].declarations, &newer_b));
+    assert!(arc_ptr_eq(&tree.refresh(&refreshed).unwrap().path()[1].declarations, &newer_b));
+
+    tree.forget_replacements();
+    assert!(!tree.has_replacements());
+}
+
+#[test]
+fn test_style_cache_is_keyed_by_parent_style() {
+    let tree = RuleTree::new();
+    let node = tree.insert_ordered_rules(&[block()]);
+    let parent = Arc::new(ServoComputedValues::initial_values().clone());
+    let other_parent = Arc::new(ServoComputedValues::initial_values().clone());
+    let style = Arc::new(ServoComputedValues::initial_values().clone());
+
+    let mut cache = RuleNodeStyleCache::new();
+    cache.insert(&node, Some(&parent), true, style.clone());
+    assert!(arc_ptr_eq(&cache.find(&node, Some(&parent), true).unwrap(), &style));
+    assert!(cache.find(&node, Some(&other_parent), true).is_none());
+    assert!(cache.find(&node, Some(&parent), false).is_none());
+    assert!(cache.find(&node, None, true).is_none());
+
+    cache.evict_all();
+    assert!(cache.find(&node, Some(&parent), true).is_none());
+}
@@ -0,0 +1,331 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::Parser;
+use selectors::Element;
+use selectors::matching::matches;
+use selectors::parser::{AttrSelector, ParserContext, SimpleSelector};
+use string_cache::{Atom, Namespace};
+use style::selector_impl::{NonTSPseudoClass, ServoSelectorImpl, lang_matches, parse_lang_ranges};
+use style::selector_impl::{find_invalid_selector, matches_attr_equal, parse_selector_list_for_matching};
+
+struct MockNode {
+    local_name: Atom,
+    namespace: Namespace,
+    id: Option<Atom>,
+    classes: Vec<Atom>,
+    attrs: Vec<(Atom, String)>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// A tree of elements, in document order.
+struct MockTree {
+    nodes: Vec<MockNode>,
+}
+
+impl MockTree {
+    fn new() -> MockTree {
+        MockTree { nodes: vec![] }
+    }
+
+    /// Appends an element to the children of `parent`, and returns its index.
+    fn add(&mut self, parent: Option<usize>, local_name: &str, id: Option<&str>, classes: &[&str],
+           attrs: &[(&str, &str)]) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(MockNode {
+            local_name: Atom::from(local_name),
+            namespace: ns!(html),
+            id: id.map(Atom::from),
+            classes: classes.iter().map(|&class| Atom::from(class)).collect(),
+            attrs: attrs.iter().map(|&(name, value)| (Atom::from(name), value.to_owned())).collect(),
+            parent: parent,
+            children: vec![],
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    /// Moves the element at `index` into `namespace`.
+    fn set_namespace(&mut self, index: usize, namespace: Namespace) {
+        self.nodes[index].namespace = namespace;
+    }
+
+    fn element(&self, index: usize) -> MockElement {
+        MockElement { tree: self, index: index }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MockElement<'a> {
+    tree: &'a MockTree,
+    index: usize,
+}
+
+impl<'a> MockElement<'a> {
+    fn node(&self) -> &'a MockNode {
+        &self.tree.nodes[self.index]
+    }
+
+    fn sibling(&self, offset: isize) -> Option<MockElement<'a>> {
+        let siblings = &self.tree.nodes[self.node().parent.unwrap_or(self.index)].children;
+        let position = match siblings.iter().position(|&child| child == self.index) {
+            Some(position) => position as isize + offset,
+            None => return None,
+        };
+        if position < 0 {
+            return None;
+        }
+        siblings.get(position as usize).map(|&index| self.tree.element(index))
+    }
+}
+
+impl<'a> Element for MockElement<'a> {
+    type Impl = ServoSelectorImpl;
+
+    fn match_non_ts_pseudo_class(&self, _: NonTSPseudoClass) -> bool {
+        false
+    }
+    fn parent_element(&self) -> Option<Self> {
+        self.node().parent.map(|index| self.tree.element(index))
+    }
+    fn first_child_element(&self) -> Option<Self> {
+        self.node().children.first().map(|&index| self.tree.element(index))
+    }
+    fn last_child_element(&self) -> Option<Self> {
+        self.node().children.last().map(|&index| self.tree.element(index))
+    }
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.sibling(-1)
+    }
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.sibling(1)
+    }
+    fn is_html_element_in_html_document(&self) -> bool {
+        self.node().namespace == ns!(html)
+    }
+    fn get_local_name(&self) -> &Atom {
+        &self.node().local_name
+    }
+    fn get_namespace(&self) -> &Namespace {
+        &self.node().namespace
+    }
+    fn get_id(&self) -> Option<Atom> {
+        self.node().id.clone()
+    }
+    fn has_class(&self, name: &Atom) -> bool {
+        self.node().classes.contains(name)
+    }
+    fn match_attr<F>(&self, attr: &AttrSelector, test: F) -> bool where F: Fn(&str) -> bool {
+        self.node().attrs.iter().any(|&(ref name, ref value)| *name == attr.lower_name && test(value))
+    }
+    fn is_empty(&self) -> bool {
+        self.node().children.is_empty()
+    }
+    fn is_root(&self) -> bool {
+        self.node().parent.is_none()
+    }
+    fn each_class<F>(&self, mut callback: F) where F: FnMut(&Atom) {
+        for class in &self.node().classes {
+            callback(class);
+        }
+    }
+}
+
+/// <html><body><p id="first" class="note"></p><a href="/" lang="en-US"></a><p class="warning"></p></body></html>
+fn mock_document() -> MockTree {
+    let mut tree = MockTree::new();
+    let html = tree.add(None, "html", None, &[], &[]);
+    let body = tree.add(Some(html), "body", None, &[], &[]);
+    tree.add(Some(body), "p", Some("first"), &["note"], &[]);
+    tree.add(Some(body), "a", None, &[], &[("href", "/"), ("lang", "en-US")]);
+    tree.add(Some(body), "p", None, &["warning"], &[]);
+    tree
+}
+
+fn matching(tree: &MockTree, selectors: &str) -> Vec<usize> {
+    let selectors = parse_selector_list_for_matching::<ServoSelectorImpl>(selectors, None).unwrap();
+    (0..tree.nodes.len()).filter(|&index| matches(&selectors, &tree.element(index), None)).collect()
+}
+
+#[test]
+fn test_negation() {
+    let tree = mock_document();
+    assert_eq!(matching(&tree, "p:not(.note)"), vec![4]);
+    assert_eq!(matching(&tree, "body > :not(p)"), vec![3]);
+}
+
+#[test]
+fn test_attribute_selectors() {
+    let tree = mock_document();
+    assert_eq!(matching(&tree, "[href]"), vec![3]);
+    assert_eq!(matching(&tree, "a[href=\"/\"]"), vec![3]);
+    assert_eq!(matching(&tree, "[lang|=en]"), vec![3]);
+    assert_eq!(matching(&tree, "[href=\"/other\"]"), vec![]);
+}
+
+#[test]
+fn test_sibling_combinators() {
+    let tree = mock_document();
+    assert_eq!(matching(&tree, "#first + a"), vec![3]);
+    assert_eq!(matching(&tree, "#first ~ p"), vec![4]);
+    assert_eq!(matching(&tree, "a + #first"), vec![]);
+}
+
+#[test]
+fn test_matching_rejects_invalid_lists() {
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("p::before", None).is_err());
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("p, a::after", None).is_err());
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("p {", None).is_err());
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("p, a", None).is_ok());
+}
+
+/// The invalid selector found in `selectors`, where it starts, and the text from its first
+/// invalid token on.
+fn invalid_selector(selectors: &str, for_matching: bool) -> Option<(&str, usize, &str)> {
+    find_invalid_selector::<ServoSelectorImpl>(&ParserContext::new(), selectors, for_matching)
+        .map(|invalid| (invalid.selector, invalid.start, &invalid.selector[invalid.offset..]))
+}
+
+#[test]
+fn test_invalid_selectors_are_found_along_with_their_first_invalid_token() {
+    assert_eq!(invalid_selector("p, a > .note", false), None);
+    assert_eq!(invalid_selector("p,  div:hovr > a, b::nope", false), Some(("div:hovr > a", 4, ":hovr > a")));
+    assert_eq!(invalid_selector("123", false), Some(("123", 0, "123")));
+    assert_eq!(invalid_selector("p > > a", false), Some(("p > > a", 0, "> > a")));
+    assert_eq!(invalid_selector("a, , b", false), Some(("", 3, "")));
+    assert_eq!(invalid_selector("p, a::after", false), None);
+    assert_eq!(invalid_selector("p, a::after", true), Some(("a::after", 3, "::after")));
+}
+
+#[test]
+fn test_default_namespace() {
+    let tree = mock_document();
+    let selectors = parse_selector_list_for_matching::<ServoSelectorImpl>("p", Some(ns!(svg))).unwrap();
+    assert!(!matches(&selectors, &tree.element(2), None));
+    let selectors = parse_selector_list_for_matching::<ServoSelectorImpl>("p", Some(ns!(html))).unwrap();
+    assert!(matches(&selectors, &tree.element(2), None));
+}
+
+/// <html><body><a></a><svg><a></a><circle class="note"></circle></svg></body></html>, with the
+/// svg element and its children in the SVG namespace.
+fn mixed_document() -> MockTree {
+    let mut tree = MockTree::new();
+    let html = tree.add(None, "html", None, &[], &[]);
+    let body = tree.add(Some(html), "body", None, &[], &[]);
+    tree.add(Some(body), "a", None, &[], &[]);
+    let svg = tree.add(Some(body), "svg", None, &[], &[]);
+    let svg_a = tree.add(Some(svg), "a", None, &[], &[]);
+    let circle = tree.add(Some(svg), "circle", None, &["note"], &[]);
+    for &index in &[svg, svg_a, circle] {
+        tree.set_namespace(index, ns!(svg));
+    }
+    tree
+}
+
+#[test]
+fn test_mixed_html_and_svg_tree() {
+    let tree = mixed_document();
+    assert_eq!(matching(&tree, "a"), vec![2, 4]);
+    assert_eq!(matching(&tree, "svg > a"), vec![4]);
+    assert_eq!(matching(&tree, "body > a"), vec![2]);
+    assert_eq!(matching(&tree, "svg .note"), vec![5]);
+
+    let selectors = parse_selector_list_for_matching::<ServoSelectorImpl>("a", Some(ns!(svg))).unwrap();
+    let svg_links: Vec<_> = (0..tree.nodes.len()).filter(|&index| matches(&selectors, &tree.element(index), None))
+                                                    .collect();
+    assert_eq!(svg_links, vec![4]);
+}
+
+/// The elements `selector`, a single attribute equality selector, matches through
+/// `matches_attr_equal`.
+fn matching_attr_equal(tree: &MockTree, selector: &str) -> Vec<usize> {
+    let selectors = parse_selector_list_for_matching::<ServoSelectorImpl>(selector, None).unwrap();
+    let (attr, value, case_sensitivity) = match selectors[0].compound_selectors.simple_selectors[0] {
+        SimpleSelector::AttrEqual(ref attr, ref value, case_sensitivity) => (attr, value, case_sensitivity),
+        ref other => panic!("not an attribute equality selector: {:?}", other),
+    };
+    (0..tree.nodes.len()).filter(|&index| {
+        matches_attr_equal(&tree.element(index), attr, value, case_sensitivity)
+    }).collect()
+}
+
+/// <html><body><input type="text"><svg><text type="text"></text></svg></body></html>, with
+/// the svg element and its child in the SVG namespace.
+fn form_document() -> MockTree {
+    let mut tree = MockTree::new();
+    let html = tree.add(None, "html", None, &[], &[]);
+    let body = tree.add(Some(html), "body", None, &[], &[]);
+    tree.add(Some(body), "input", None, &[], &[("type", "text"), ("name", "text")]);
+    let svg = tree.add(Some(body), "svg", None, &[], &[]);
+    let text = tree.add(Some(svg), "text", None, &[], &[("type", "text")]);
+    for &index in &[svg, text] {
+        tree.set_namespace(index, ns!(svg));
+    }
+    tree
+}
+
+#[test]
+fn test_html_attributes_compare_case_insensitively_on_html_elements() {
+    let tree = form_document();
+    assert_eq!(matching_attr_equal(&tree, "[type=TEXT]"), vec![2]);
+    assert_eq!(matching_attr_equal(&tree, "[type=text]"), vec![2, 4]);
+    // Attributes HTML doesn't list stay case-sensitive...
+    assert_eq!(matching_attr_equal(&tree, "[name=TEXT]"), vec![]);
+    // ...unless the selector says otherwise, which goes for any element.
+    assert_eq!(matching_attr_equal(&tree, "[name=TEXT i]"), vec![2]);
+    assert_eq!(matching_attr_equal(&tree, "[type=TEXT i]"), vec![2, 4]);
+}
+
+#[test]
+fn test_unknown_attribute_flags_make_the_selector_invalid() {
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("[type=text I]", None).is_ok());
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("[type=text s]", None).is_err());
+    assert!(parse_selector_list_for_matching::<ServoSelectorImpl>("[type=text x]", None).is_err());
+}
+
+#[test]
+fn test_lang_matches_subtag_prefixes() {
+    assert!(lang_matches("en", "en"));
+    assert!(lang_matches("en", "en-US"));
+    assert!(lang_matches("en-US", "en-US"));
+    assert!(!lang_matches("en-US", "en"));
+    assert!(!lang_matches("en", "eng"));
+    assert!(!lang_matches("fr", "en-FR"));
+}
+
+#[test]
+fn test_lang_matches_ignores_ascii_case() {
+    assert!(lang_matches("EN", "en-us"));
+    assert!(lang_matches("en-us", "EN-US"));
+    assert!(lang_matches("zh-hant", "zh-Hant-TW"));
+}
+
+#[test]
+fn test_lang_matches_extended_ranges() {
+    // Subtags the range leaves out are skipped, but not past the start of an extension.
+    assert!(lang_matches("de-DE", "de-Latn-DE"));
+    assert!(!lang_matches("de-DE", "de-x-DE"));
+    assert!(lang_matches("*-CH", "fr-CH"));
+    assert!(lang_matches("de-*-DE", "de-Latn-DE"));
+    assert!(!lang_matches("de-CH", "de-DE"));
+}
+
+#[test]
+fn test_lang_matches_nothing_with_an_empty_range() {
+    assert!(!lang_matches("", "en"));
+    assert!(!lang_matches("", ""));
+    assert!(!lang_matches("en", ""));
+}
+
+#[test]
+fn test_parse_lang_ranges() {
+    assert_eq!(parse_lang_ranges(&mut Parser::new("fr")), Ok(vec!["fr".to_owned()]));
+    assert_eq!(parse_lang_ranges(&mut Parser::new("en-US, \"de\", \"\"")),
+               Ok(vec!["en-US".to_owned(), "de".to_owned(), "".to_owned()]));
+    assert!(parse_lang_ranges(&mut Parser::new("")).is_err());
+    assert!(parse_lang_ranges(&mut Parser::new("12")).is_err());
+}
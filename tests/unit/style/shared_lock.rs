@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::ToCss;
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use style::properties::{PropertyDeclarationBlock, parse_style_attribute};
+use style::shared_lock::{Locked, SharedRwLock};
+
+fn declarations(css: &str) -> PropertyDeclarationBlock {
+    let url = url!("http://localhost");
+    parse_style_attribute(css, &url, Box::new(CSSErrorReporterTest))
+}
+
+#[test]
+fn test_readers_never_see_a_block_halfway_through_a_change() {
+    let narrow = declarations("margin-top: 1px; margin-right: 1px; margin-bottom: 1px; margin-left: 1px");
+    let wide = declarations("margin-top: 2em; margin-right: 2em; margin-bottom: 2em; margin-left: 2em");
+    let (narrow_css, wide_css) = (narrow.to_css_string(), wide.to_css_string());
+
+    let lock = SharedRwLock::new();
+    let locked = Arc::new(Locked::new(narrow.clone(), &lock));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..2).map(|_| {
+        let (lock, locked, done) = (lock.clone(), locked.clone(), done.clone());
+        let (narrow_css, wide_css) = (narrow_css.clone(), wide_css.clone());
+        thread::spawn(move || {
+            let mut reads = 0;
+            while !done.load(Ordering::SeqCst) || reads == 0 {
+                let guard = lock.read();
+                let css = locked.read_with(&guard).to_css_string();
+                assert!(css == narrow_css || css == wide_css, "Torn read: {}", css);
+                reads += 1;
+            }
+        })
+    }).collect();
+
+    // Change one declaration at a time, giving the readers every chance to look in between.
+    for i in 0..500 {
+        let source = if i % 2 == 0 { &wide } else { &narrow };
+        let mut guard = lock.write();
+        let block = locked.write_with(&mut guard);
+        for (index, declaration) in source.normal.iter().enumerate() {
+            Arc::make_mut(&mut block.normal)[index] = declaration.clone();
+            thread::yield_now();
+        }
+    }
+    done.store(true, Ordering::SeqCst);
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(locked.read_with(&lock.read()).to_css_string(), narrow_css);
+}
+
+#[test]
+#[should_panic]
+fn test_a_guard_of_another_lock_is_refused() {
+    let locked = Locked::new(declarations("color: red"), &SharedRwLock::new());
+    let other = SharedRwLock::new();
+    locked.read_with(&other.read());
+}
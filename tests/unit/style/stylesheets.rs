@@ -2,17 +2,28 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use cssparser::{self, Parser, SourcePosition};
+use cssparser::{self, Parser, SourcePosition, ToCss};
+use euclid::size::Size2D;
 use media_queries::CSSErrorReporterTest;
 use selectors::parser::*;
-use std::borrow::ToOwned;
+use std::borrow::{Cow, ToOwned};
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use string_cache::Atom;
-use style::properties::{PropertyDeclaration, PropertyDeclarationBlock, DeclaredValue, longhands};
-use style::stylesheets::{CSSRule, StyleRule, Origin};
-use style::error_reporting::ParseErrorReporter;
-use style::servo::Stylesheet;
+use style::media_queries::{Device, MediaType, parse_media_query_list};
+use style::parser::{ParserContext, QuirksMode};
+use style::properties::{PropertyDeclaration, PropertyDeclarationBlock, DeclaredValue, Shorthand, longhands};
+use style::selector_impl::{PseudoElement, ServoSelectorImpl, serialize_selector};
+use style::stylesheets::{CSSRule, CSSRuleIteratorExt, ImportRule, StyleRule, Origin, StylesheetLoader};
+use style::stylesheets::{RulesMutateError, SourceLocation, add_namespace_prefixes, decode_utf8_lossy};
+use style::stylesheets::{delete_rule, insert_rule};
+use style::dom::OpaqueNode;
+use style::error_reporting::{ParseErrorDetails, ParseErrorReporter};
+use style::servo::{Stylesheet, Stylist};
+use style::url_value::{UrlExtraData, UrlExtraDataRef};
+use style::values::specified::Image;
+use util::arc_ptr_eq;
 
 #[test]
 fn test_parse_stylesheet() {
@@ -28,9 +39,16 @@ fn test_parse_stylesheet() {
                                           Box::new(CSSErrorReporterTest));
     assert_eq!(stylesheet, Stylesheet {
         origin: Origin::UserAgent,
-        media: None,
-        dirty_on_viewport_size_change: false,
-        rules: vec![
+        media: RwLock::new(None),
+        dirty_on_viewport_size_change: AtomicBool::new(false),
+        disabled: AtomicBool::new(false),
+        rules_generation: AtomicUsize::new(0),
+        frozen: AtomicBool::new(false),
+        title: RwLock::new(String::new()),
+        url_extra_data: RwLock::new(UrlExtraData::none()),
+        base_url: url!("about::test"),
+        quirks_mode: QuirksMode::NoQuirks,
+        rules: RwLock::new(Arc::new(vec![
             CSSRule::Namespace(None, ns!(html)),
             CSSRule::Style(StyleRule {
                 selectors: vec![
@@ -60,6 +78,11 @@ fn test_parse_stylesheet() {
                         PropertyDeclaration::Display(DeclaredValue::Value(
                             longhands::display::SpecifiedValue::none)),
                     ]),
+                    shorthands: vec![],
+                },
+                source_location: SourceLocation {
+                    line: 4,
+                    column: 9,
                 },
             }),
             CSSRule::Style(StyleRule {
@@ -99,6 +122,11 @@ fn test_parse_stylesheet() {
                             longhands::display::SpecifiedValue::block)),
                     ]),
                     important: Arc::new(vec![]),
+                    shorthands: vec![],
+                },
+                source_location: SourceLocation {
+                    line: 5,
+                    column: 9,
                 },
             }),
             CSSRule::Style(StyleRule {
@@ -138,9 +166,14 @@ fn test_parse_stylesheet() {
                         )),
                     ]),
                     important: Arc::new(vec![]),
+                    shorthands: vec![(Shorthand::Background, false)],
+                },
+                source_location: SourceLocation {
+                    line: 6,
+                    column: 9,
                 },
             }),
-        ],
+        ])),
     });
 }
 
@@ -217,3 +250,582 @@ fn test_report_error_stylesheet() {
     assert_eq!(4, error.line);
     assert_eq!(9, error.column);
 }
+
+/// Keeps the line of each style rule dropped for its selectors, the selector that's
+/// invalid, and the text from its first invalid token on.
+struct InvalidSelectorReporterTest {
+    pub selectors: Arc<Mutex<Vec<(usize, String, String)>>>,
+}
+
+impl ParseErrorReporter for InvalidSelectorReporterTest {
+    fn report_error(&self, _input: &mut Parser, _position: SourcePosition, _message: &str) {}
+
+    fn report_error_with_details(&self, input: &mut Parser, position: SourcePosition, _message: &str,
+                                 details: &ParseErrorDetails) {
+        match *details {
+            ParseErrorDetails::InvalidSelector(invalid) => {
+                let line = input.source_location(position).line;
+                self.selectors.lock().unwrap().push((line, invalid.selector.to_owned(),
+                                                     invalid.selector[invalid.offset..].to_owned()));
+            }
+        }
+    }
+
+    fn clone(&self) -> Box<ParseErrorReporter + Send + Sync> {
+        Box::new(InvalidSelectorReporterTest { selectors: self.selectors.clone() })
+    }
+}
+
+#[test]
+fn test_invalid_selectors_are_reported_and_only_drop_their_rule() {
+    let css = "p { color: red; }\n\
+               a, b:hovr > c { color: red; }\n\
+               @media screen { d::fake { color: red; } e { color: red; } }\n\
+               f { color: red; } g { color: red; }";
+    let selectors = Arc::new(Mutex::new(vec![]));
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(InvalidSelectorReporterTest { selectors: selectors.clone() }));
+    assert_eq!(*selectors.lock().unwrap(), vec![(2, "b:hovr > c".to_owned(), ":hovr > c".to_owned()),
+                                                (3, "d::fake".to_owned(), "::fake".to_owned())]);
+    assert_eq!(serialized_style_rules_of(&stylesheet),
+               vec!["p { color: red; }", "e { color: red; }", "f { color: red; }", "g { color: red; }"]);
+}
+
+struct ImportLoaderTest {
+    pub requests: Mutex<Vec<(String, String)>>,
+}
+
+impl StylesheetLoader<ServoSelectorImpl> for ImportLoaderTest {
+    fn request_stylesheet(&self, import: &Arc<ImportRule<ServoSelectorImpl>>, media: &str) {
+        self.requests.lock().unwrap().push((import.url.serialize(), media.to_owned()));
+    }
+}
+
+#[test]
+fn test_import_requests_load() {
+    let css = r#"
+        @import url(a.css);
+        @import "b.css" screen and (min-width: 100px);
+        p { color: red; }
+        @import url(too-late.css);
+    "#;
+    let loader = ImportLoaderTest { requests: Mutex::new(vec![]) };
+    let stylesheet = Stylesheet::from_str_with_loader(css, url!("http://localhost/style/"), Origin::Author,
+                                                      Box::new(CSSErrorReporterTest), Some(&loader),
+                                                      QuirksMode::NoQuirks);
+    assert_eq!(*loader.requests.lock().unwrap(), vec![
+        ("http://localhost/style/a.css".to_owned(), "".to_owned()),
+        ("http://localhost/style/b.css".to_owned(), "screen and (min-width: 100px)".to_owned()),
+    ]);
+    assert_eq!(stylesheet.rules().import().count(), 2);
+}
+
+struct ExtraDataTest(usize);
+
+impl UrlExtraDataRef for ExtraDataTest {
+    fn as_ptr(&self) -> usize {
+        self.0
+    }
+}
+
+struct SheetContextLoaderTest(UrlExtraData);
+
+impl SheetContextLoaderTest {
+    fn new(address: usize) -> SheetContextLoaderTest {
+        SheetContextLoaderTest(UrlExtraData::new(Box::new(ExtraDataTest(address))))
+    }
+}
+
+impl StylesheetLoader<ServoSelectorImpl> for SheetContextLoaderTest {
+    fn request_stylesheet(&self, _import: &Arc<ImportRule<ServoSelectorImpl>>, _media: &str) {}
+
+    fn url_extra_data(&self) -> UrlExtraData {
+        self.0.clone()
+    }
+}
+
+/// The extra data of the url of the first background image of the first style rule of |stylesheet|.
+fn first_url_extra_data(stylesheet: &Stylesheet) -> UrlExtraData {
+    let rule = stylesheet.rules().style().next().unwrap();
+    match rule.declarations.normal[0] {
+        PropertyDeclaration::BackgroundImage(DeclaredValue::Value(
+            longhands::background_image::SpecifiedValue(ref images))) => {
+            assert_eq!(images.len(), 1);
+            match images[0] {
+                Some(Image::Url(ref url)) => {
+                    assert_eq!(url.url().serialize(), "http://localhost/style/a.png");
+                    url.extra_data().clone()
+                }
+                ref image => panic!("unexpected image {:?}", image),
+            }
+        }
+        ref declaration => panic!("unexpected declaration {:?}", declaration),
+    }
+}
+
+#[test]
+fn test_url_values_carry_the_loaders_extra_data() {
+    let css = "p { background-image: url(a.png) }";
+    let loader = SheetContextLoaderTest::new(42);
+    let stylesheet = Stylesheet::from_str_with_loader(css, url!("http://localhost/style/"), Origin::Author,
+                                                      Box::new(CSSErrorReporterTest), Some(&loader),
+                                                      QuirksMode::NoQuirks);
+    assert_eq!(first_url_extra_data(&stylesheet).as_ptr(), 42);
+    assert_eq!(stylesheet.url_extra_data(), loader.0);
+    assert_eq!(stylesheet.clone().url_extra_data().as_ptr(), 42);
+}
+
+#[test]
+fn test_reparsed_sheets_keep_their_extra_data_unless_given_new_data() {
+    let css = "p { background-image: url(a.png) }";
+    let stylesheet = Stylesheet::from_str_with_loader(css, url!("http://localhost/style/"), Origin::Author,
+                                                      Box::new(CSSErrorReporterTest),
+                                                      Some(&SheetContextLoaderTest::new(1)), QuirksMode::NoQuirks);
+    let old = stylesheet.reparse(css, Box::new(CSSErrorReporterTest), None);
+    assert_eq!(first_url_extra_data(&stylesheet).as_ptr(), 1);
+    assert_eq!(old.url_extra_data().as_ptr(), 1);
+
+    let old = stylesheet.reparse(css, Box::new(CSSErrorReporterTest), Some(&SheetContextLoaderTest::new(2)));
+    assert_eq!(first_url_extra_data(&stylesheet).as_ptr(), 2);
+    assert_eq!(stylesheet.url_extra_data().as_ptr(), 2);
+    assert_eq!(first_url_extra_data(&old).as_ptr(), 1);
+    assert_eq!(old.url_extra_data().as_ptr(), 1);
+
+    stylesheet.set_url_extra_data(UrlExtraData::new(Box::new(ExtraDataTest(3))));
+    stylesheet.reparse(css, Box::new(CSSErrorReporterTest), None);
+    assert_eq!(first_url_extra_data(&stylesheet).as_ptr(), 3);
+}
+
+fn import_rule_for(stylesheet: Option<Arc<Stylesheet>>) -> Arc<ImportRule<ServoSelectorImpl>> {
+    Arc::new(ImportRule::new(url!("http://localhost/imported.css"), parse_media_query_list(&mut Parser::new("")),
+                             stylesheet))
+}
+
+#[test]
+fn test_imported_rules_cascade_at_import_position() {
+    let child = Stylesheet::from_str("a { color: red; }", url!("http://localhost/imported.css"), Origin::Author,
+                                     Box::new(CSSErrorReporterTest));
+    let import = import_rule_for(Some(Arc::new(child)));
+    let mut parent = Stylesheet::from_str("p { color: red; }", url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    Arc::make_mut(parent.rules.get_mut().unwrap()).insert(0, CSSRule::Import(import.clone()));
+
+    let names: Vec<_> = parent.rules().style().map(|rule| {
+        match rule.selectors[0].compound_selectors.simple_selectors[0] {
+            SimpleSelector::LocalName(ref name) => name.name.clone(),
+            _ => panic!("Unexpected selector"),
+        }
+    }).collect();
+    assert_eq!(names, vec![atom!("a"), atom!("p")]);
+
+    assert!(parent.contains_import_rule(&import));
+    assert!(!parent.contains_import_rule(&import_rule_for(None)));
+}
+
+#[test]
+fn test_namespace_prefixes_resolve_to_namespace_urls() {
+    let css = r"
+        @namespace url(http://www.w3.org/2000/svg);
+        @namespace xlink url(http://www.w3.org/1999/xlink);
+        rect[xlink|href] { color: red; }
+        [width] { color: red; }
+    ";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    let selectors: Vec<_> = stylesheet.rules().style().map(|rule| {
+        rule.selectors[0].compound_selectors.simple_selectors.clone()
+    }).collect();
+
+    // Element selectors pick up the default namespace, so this only matches SVG rects.
+    assert_eq!(selectors[0].len(), 3);
+    assert_eq!(selectors[0][0], SimpleSelector::Namespace(ns!(svg)));
+    match selectors[0][2] {
+        SimpleSelector::AttrExists(ref attr) => {
+            assert_eq!(attr.namespace, NamespaceConstraint::Specific(ns!(xlink)))
+        }
+        ref other => panic!("Unexpected selector {:?}", other),
+    }
+
+    // Unprefixed attribute selectors are in no namespace, regardless of the default.
+    assert_eq!(selectors[1].len(), 2);
+    assert_eq!(selectors[1][0], SimpleSelector::Namespace(ns!(svg)));
+    match selectors[1][1] {
+        SimpleSelector::AttrExists(ref attr) => assert_eq!(attr.namespace, NamespaceConstraint::Specific(ns!())),
+        ref other => panic!("Unexpected selector {:?}", other),
+    }
+}
+
+fn serialized_style_rules_of(stylesheet: &Stylesheet) -> Vec<String> {
+    stylesheet.rules().style().map(|rule| rule.to_css_string()).collect()
+}
+
+fn serialized_style_rules(css: &str) -> Vec<String> {
+    serialized_style_rules_of(&Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                                    Box::new(CSSErrorReporterTest)))
+}
+
+/// The style rules of a sheet parsed from bytes that should be UTF-8, and whether
+/// decoding them replaced anything.
+fn serialized_style_rules_of_bytes(bytes: &[u8]) -> (Vec<String>, bool) {
+    let (css, was_lossy) = decode_utf8_lossy(bytes);
+    (serialized_style_rules(&css), was_lossy)
+}
+
+#[test]
+fn test_valid_utf8_is_decoded_without_copying() {
+    let bytes = "p::before { content: \"\u{2192}\"; }".as_bytes();
+    match decode_utf8_lossy(bytes) {
+        (Cow::Borrowed(css), false) => assert_eq!(css.as_bytes(), bytes),
+        other => panic!("Unexpected decoding {:?}", other),
+    }
+}
+
+#[test]
+fn test_truncated_utf8_sequences_are_replaced() {
+    // A sheet cut off in the middle of a four byte sequence.
+    let (rules, was_lossy) = serialized_style_rules_of_bytes(b"i { color: green; } \xF0\x9F\x98");
+    assert!(was_lossy);
+    assert_eq!(rules, vec!["i { color: green; }"]);
+
+    // A three byte sequence missing its last byte, followed by more sheet.
+    let (css, was_lossy) = decode_utf8_lossy(b"p::before { content: \"\xE2\x86\"; }");
+    assert!(was_lossy);
+    assert_eq!(css, "p::before { content: \"\u{FFFD}\"; }");
+}
+
+#[test]
+fn test_overlong_utf8_encodings_do_not_produce_rules() {
+    // The overlong encoding of `{` doesn't open a block, so the rule swallows the next
+    // one's and is dropped along with it.
+    let (rules, was_lossy) =
+        serialized_style_rules_of_bytes(b"a \xC1\xBB color: red; } b { color: blue; } i { color: green; }");
+    assert!(was_lossy);
+    assert_eq!(rules, vec!["i { color: green; }"]);
+
+    // Nor does the overlong encoding of `.` make a class selector.
+    let (css, _) = decode_utf8_lossy(b"\xC0\xAEwarning { color: red; }");
+    assert_eq!(css, "\u{FFFD}\u{FFFD}warning { color: red; }");
+}
+
+#[test]
+fn test_style_rules_serialize_like_cssom() {
+    let css = r"
+        a>b.c#d~* { color: red }
+        input[type=checkbox i]:checked+label::after, ::before { display: block !important; color: red; }
+        [*|lang|=en],:not(.x):nth-child(odd) li:nth-last-of-type(-n+3) {}
+        @media screen { p::selection { color: red; } }
+    ";
+    let serialized = serialized_style_rules(css);
+    assert_eq!(serialized, vec![
+        "a > b.c#d ~ * { color: red; }",
+        "input[type=\"checkbox\" i]:checked + label::after, ::before { color: red; display: block !important; }",
+        "[*|lang|=\"en\"], :not(.x):nth-child(2n+1) li:nth-last-of-type(-n+3) { }",
+        "p::selection { color: red; }",
+    ]);
+
+    // Parsing the serialization gives back the same rules.
+    assert_eq!(serialized_style_rules(&serialized.join("\n")), serialized);
+}
+
+#[test]
+fn test_selectors_serialize_one_by_one() {
+    let stylesheet = Stylesheet::from_str("a:before, [x=y]  b, *.c { }", url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    let rule = stylesheet.rules().style().next().unwrap();
+    let serialized: Vec<_> = rule.selectors.iter().map(|selector| {
+        let mut text = String::new();
+        serialize_selector(selector, &mut text).unwrap();
+        text
+    }).collect();
+    assert_eq!(serialized, vec!["a::before", "[x=\"y\"] b", ".c"]);
+}
+
+fn declaration_counts(css: &str, origin: Origin, quirks_mode: QuirksMode) -> Vec<usize> {
+    let stylesheet = Stylesheet::from_str_with_loader(css, url!("http://localhost"), origin,
+                                                      Box::new(CSSErrorReporterTest), None, quirks_mode);
+    stylesheet.rules().style().map(|rule| rule.declarations.normal.len()).collect()
+}
+
+#[test]
+fn test_hashless_hex_colors_only_parse_in_quirks_mode() {
+    let css = "p { color: ff0000; } a { color: 1f1f1f; } b { color: 123; } i { color: 12345678; }";
+    assert_eq!(declaration_counts(css, Origin::Author, QuirksMode::Quirks), vec![1, 1, 1, 0]);
+    assert_eq!(declaration_counts(css, Origin::Author, QuirksMode::LimitedQuirks), vec![0, 0, 0, 0]);
+    assert_eq!(declaration_counts(css, Origin::Author, QuirksMode::NoQuirks), vec![0, 0, 0, 0]);
+    // User agent sheets are always parsed in standards mode.
+    assert_eq!(declaration_counts(css, Origin::UserAgent, QuirksMode::Quirks), vec![0, 0, 0, 0]);
+}
+
+fn insert(stylesheet: &Stylesheet, css: &str, index: usize) -> Result<(), RulesMutateError> {
+    let url = url!("http://localhost");
+    let mut context = ParserContext::new(Origin::Author, &url, Box::new(CSSErrorReporterTest));
+    stylesheet.with_rules_mut(|rules| {
+        add_namespace_prefixes(&mut context, rules);
+        insert_rule(rules, index, false, css, context, None)
+    })
+}
+
+fn delete(stylesheet: &Stylesheet, index: usize) -> Result<(), RulesMutateError> {
+    stylesheet.with_rules_mut(|rules| delete_rule(rules, index))
+}
+
+#[test]
+fn test_insert_rule_follows_cssom_constraints() {
+    let css = r#"@charset "utf-8"; @import url(a.css); p { color: red; }"#;
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    let generation = stylesheet.rules_generation();
+
+    // CSSOM indices don't count the @charset rule.
+    assert_eq!(insert(&stylesheet, "a { color: red; }", 3), Err(RulesMutateError::IndexSize));
+    assert_eq!(insert(&stylesheet, "a { color: red; } b { color: red; }", 2), Err(RulesMutateError::Syntax));
+    assert_eq!(insert(&stylesheet, "a {", 2), Ok(()));
+    assert_eq!(insert(&stylesheet, r#"@charset "utf-8";"#, 0), Err(RulesMutateError::Syntax));
+    assert_eq!(insert(&stylesheet, "@import url(b.css);", 2), Err(RulesMutateError::HierarchyRequest));
+    assert_eq!(insert(&stylesheet, "b { color: red; }", 0), Err(RulesMutateError::HierarchyRequest));
+    assert_eq!(insert(&stylesheet, "@namespace url(http://www.w3.org/2000/svg);", 1),
+               Err(RulesMutateError::InvalidState));
+    assert_eq!(insert(&stylesheet, "@import url(b.css);", 1), Ok(()));
+
+    assert_eq!(stylesheet.rules().import().count(), 2);
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["p { color: red; }", "a { }"]);
+    assert!(stylesheet.rules_generation() != generation);
+}
+
+#[test]
+fn test_inserted_rules_use_the_namespaces_of_the_sheet() {
+    let css = "@namespace svg url(http://www.w3.org/2000/svg);";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    assert_eq!(insert(&stylesheet, "svg|rect { color: red; }", 1), Ok(()));
+    let rule = stylesheet.rules().style().next().unwrap();
+    assert_eq!(rule.selectors[0].compound_selectors.simple_selectors[0],
+               SimpleSelector::Namespace(ns!(svg)));
+    assert_eq!(insert(&stylesheet, "html|p { color: red; }", 2), Err(RulesMutateError::Syntax));
+}
+
+#[test]
+fn test_nested_rule_lists_only_take_nested_rules() {
+    let css = "@media screen { p { color: red; } }";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    let url = url!("http://localhost");
+    let insert_nested = |css: &str| {
+        stylesheet.with_rules_mut(|rules| match rules[0] {
+            CSSRule::Media(ref mut media) => {
+                let context = ParserContext::new(Origin::Author, &url, Box::new(CSSErrorReporterTest));
+                insert_rule(&mut media.rules, 1, true, css, context, None)
+            }
+            _ => panic!("Expected a @media rule"),
+        })
+    };
+    assert_eq!(insert_nested("@import url(a.css);"), Err(RulesMutateError::HierarchyRequest));
+    assert_eq!(insert_nested("a { color: red; }"), Ok(()));
+    assert_eq!(stylesheet.rules().style().count(), 2);
+}
+
+#[test]
+fn test_unknown_at_rules_are_kept_verbatim() {
+    let css = "@frobnicate {}\n@import url(a.css);\np { color: red; }\n\
+               @media screen { @import url(b.css); @-foo-bar  x, y { a: b } q { color: red; } }\n\
+               @FrobNicate  once;";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+
+    // An unknown at-rule doesn't keep an @import after it from applying, and @import is only
+    // known at the top of a sheet.
+    assert_eq!(stylesheet.rules().import().count(), 1);
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["p { color: red; }", "q { color: red; }"]);
+    let unsupported: Vec<_> = stylesheet.rules().unsupported().map(|rule| (rule.name.clone(), rule.to_css_string()))
+                                                              .collect();
+    assert_eq!(unsupported, vec![("frobnicate".to_owned(), "@frobnicate {}".to_owned()),
+                                 ("-foo-bar".to_owned(), "@-foo-bar  x, y { a: b }".to_owned()),
+                                 ("frobnicate".to_owned(), "@FrobNicate  once;".to_owned())]);
+
+    // insertRule only takes rules it knows, but doesn't mind the unknown ones already there.
+    assert_eq!(insert(&stylesheet, "@frobnicate {}", 0), Err(RulesMutateError::Syntax));
+    assert_eq!(insert(&stylesheet, "@import url(c.css);", 1), Ok(()));
+    assert_eq!(stylesheet.rules().import().count(), 2);
+}
+
+#[test]
+fn test_delete_rule() {
+    let css = "@namespace url(http://www.w3.org/1999/xhtml); p { color: red; } a { color: red; }";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    assert_eq!(delete(&stylesheet, 3), Err(RulesMutateError::IndexSize));
+    assert_eq!(delete(&stylesheet, 0), Err(RulesMutateError::InvalidState));
+    assert_eq!(delete(&stylesheet, 1), Ok(()));
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["a { color: red; }"]);
+}
+
+fn style_rule_locations(rules: &[CSSRule<ServoSelectorImpl>], locations: &mut Vec<(u32, u32)>) {
+    for rule in rules {
+        match *rule {
+            CSSRule::Style(ref rule) => locations.push((rule.source_location.line,
+                                                        rule.source_location.column)),
+            CSSRule::Media(ref rule) => style_rule_locations(&rule.rules, locations),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_rules_record_their_source_location() {
+    let css = "a { color: red; }\n@media screen {\n  p, q { color: red; }\n}\n   b { color: red; }";
+    let stylesheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    assert_eq!(insert(&stylesheet, "\n\n  i { color: red; }", 1), Ok(()));
+
+    let mut locations = vec![];
+    style_rule_locations(&stylesheet.read_rules(), &mut locations);
+    // Inserted rules didn't come from the sheet's source.
+    assert_eq!(locations, vec![(1, 1), (0, 0), (3, 3), (5, 4)]);
+}
+
+#[test]
+fn test_reparse_replaces_rules_in_place() {
+    let stylesheet = Stylesheet::from_str("a { color: red; } p { color: red; }", url!("http://localhost"),
+                                          Origin::Author, Box::new(CSSErrorReporterTest));
+    let media = parse_media_query_list(&mut Parser::new("screen"));
+    stylesheet.set_media(Some(media.clone()));
+    let generation = stylesheet.rules_generation();
+
+    let old = stylesheet.reparse("i { width: 1vw; }", Box::new(CSSErrorReporterTest), None);
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["i { width: 1vw; }"]);
+    assert_eq!(stylesheet.media(), Some(media.clone()));
+    assert!(stylesheet.dirty_on_viewport_size_change());
+    assert!(stylesheet.rules_generation() != generation);
+
+    assert_eq!(serialized_style_rules_of(&old), vec!["a { color: red; }", "p { color: red; }"]);
+    assert_eq!(old.media(), Some(media));
+    assert!(!old.dirty_on_viewport_size_change());
+}
+
+#[test]
+fn test_cloned_sheets_copy_their_rules_when_changed() {
+    let original = Stylesheet::from_str("p::after { color: red; } a { color: red; }", url!("http://localhost"),
+                                        Origin::Author, Box::new(CSSErrorReporterTest));
+    let clone = original.clone();
+    assert!(arc_ptr_eq(&*original.rules.read().unwrap(), &*clone.rules.read().unwrap()));
+    assert_eq!(clone, original);
+
+    assert_eq!(insert(&clone, "p::before { color: red; }", 0), Ok(()));
+    assert_eq!(delete(&clone, 2), Ok(()));
+    assert_eq!(serialized_style_rules_of(&original), vec!["p::after { color: red; }", "a { color: red; }"]);
+    assert_eq!(serialized_style_rules_of(&clone), vec!["p::before { color: red; }", "p::after { color: red; }"]);
+
+    // Now that the clone has rules of its own, changing them again doesn't copy them.
+    let rules = &*clone.read_rules() as *const Vec<CSSRule<ServoSelectorImpl>>;
+    assert_eq!(insert(&clone, "b { color: red; }", 2), Ok(()));
+    assert_eq!(&*clone.read_rules() as *const Vec<CSSRule<ServoSelectorImpl>>, rules);
+
+    let device = || Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0));
+    let mut original_stylist = Stylist::new(device());
+    original_stylist.update(&[Arc::new(original)], true);
+    assert!(original_stylist.has_rules_for_pseudo(&PseudoElement::After));
+    assert!(!original_stylist.has_rules_for_pseudo(&PseudoElement::Before));
+    let mut clone_stylist = Stylist::new(device());
+    clone_stylist.update(&[Arc::new(clone)], true);
+    assert!(clone_stylist.has_rules_for_pseudo(&PseudoElement::After));
+    assert!(clone_stylist.has_rules_for_pseudo(&PseudoElement::Before));
+}
+
+#[test]
+fn test_scoped_sheets_are_forgotten_with_their_scope() {
+    let sheet = || Arc::new(Stylesheet::from_str(".note::before { color: red; }", url!("http://localhost"),
+                                                 Origin::Author, Box::new(CSSErrorReporterTest)));
+    let (scoped, other_scoped, unscoped) = (sheet(), sheet(), sheet());
+    let (scope, other_scope) = (OpaqueNode(1), OpaqueNode(2));
+    let mut stylist = Stylist::new(Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0)));
+    stylist.set_stylesheet_scope(&scoped, scope);
+    stylist.set_stylesheet_scope(&other_scoped, other_scope);
+    stylist.update(&[scoped.clone(), other_scoped.clone()], true);
+    // The rules of scoped sheets still count for whether a pseudo-element needs matching.
+    assert!(stylist.has_rules_for_pseudo(&PseudoElement::Before));
+
+    let forgotten = stylist.forget_scope(scope);
+    assert_eq!(forgotten.len(), 1);
+    assert!(arc_ptr_eq(&forgotten[0], &scoped));
+    assert!(stylist.forget_scope(scope).is_empty());
+
+    // Sheets that are no longer in the list lose their scope on the next update.
+    stylist.update(&[unscoped.clone()], true);
+    assert!(stylist.forget_scope(other_scope).is_empty());
+}
+
+#[test]
+fn test_frozen_sheets_refuse_cssom_changes() {
+    let stylesheet = Stylesheet::from_str("a { color: red; }", url!("http://localhost"), Origin::UserAgent,
+                                          Box::new(CSSErrorReporterTest));
+    stylesheet.freeze();
+    assert!(stylesheet.is_frozen());
+    let generation = stylesheet.rules_generation();
+    let result = stylesheet.try_with_rules_mut(|rules| delete_rule(rules, 0));
+    assert_eq!(result, Err(RulesMutateError::NoModificationAllowed));
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["a { color: red; }"]);
+    assert_eq!(stylesheet.rules_generation(), generation);
+
+    // Copies of a frozen sheet belong to whoever made them.
+    let clone = stylesheet.clone();
+    assert!(!clone.is_frozen());
+    assert_eq!(clone.try_with_rules_mut(|rules| delete_rule(rules, 0)), Ok(Ok(())));
+    assert_eq!(serialized_style_rules_of(&stylesheet), vec!["a { color: red; }"]);
+}
+
+#[test]
+fn test_reparsing_a_cloned_sheet_leaves_the_original_alone() {
+    let original = Stylesheet::from_str("a { color: red; }", url!("http://localhost"), Origin::Author,
+                                        Box::new(CSSErrorReporterTest));
+    original.set_disabled(true);
+    let clone = original.clone();
+    assert!(clone.disabled());
+
+    clone.reparse("b { color: red; }", Box::new(CSSErrorReporterTest), None);
+    assert_eq!(serialized_style_rules_of(&original), vec!["a { color: red; }"]);
+    assert_eq!(serialized_style_rules_of(&clone), vec!["b { color: red; }"]);
+}
+
+#[test]
+fn test_sheets_describe_themselves_for_logs() {
+    let stylesheet = Stylesheet::from_str("a { color: red; }", url!("http://localhost"), Origin::Author,
+                                          Box::new(CSSErrorReporterTest));
+    assert_eq!(stylesheet.debug_info(), r#"author, enabled, title "", media """#);
+
+    stylesheet.set_media(Some(parse_media_query_list(&mut Parser::new("screen and (min-width: 400px), print"))));
+    assert_eq!(stylesheet.set_title("Dark \"mode\""), Ok(()));
+    stylesheet.set_disabled(true);
+    assert_eq!(stylesheet.title(), "Dark \"mode\"");
+    assert_eq!(stylesheet.clone().title(), "Dark \"mode\"");
+    assert_eq!(stylesheet.debug_info(),
+               r#"author, disabled, title "Dark \"mode\"", media "screen and (min-width: 400px), print""#);
+}
+
+#[test]
+fn test_frozen_sheets_keep_their_title() {
+    let stylesheet = Stylesheet::from_str("", url!("http://localhost"), Origin::UserAgent,
+                                          Box::new(CSSErrorReporterTest));
+    stylesheet.set_title("Default").unwrap();
+    stylesheet.freeze();
+    assert_eq!(stylesheet.set_title("Other"), Err(RulesMutateError::NoModificationAllowed));
+    assert_eq!(stylesheet.title(), "Default");
+    assert_eq!(stylesheet.debug_info(), r#"user-agent, enabled, frozen, title "Default", media """#);
+}
+
+#[test]
+fn test_frozen_sheets_keep_the_sheets_they_import() {
+    let parent = Stylesheet::from_str("@import url(a.css);", url!("http://localhost"), Origin::UserAgent,
+                                      Box::new(CSSErrorReporterTest));
+    let import = parent.rules().import().next().unwrap().clone();
+    let imported = || {
+        Arc::new(Stylesheet::from_str("a { color: red; }", url!("http://localhost/a.css"), Origin::UserAgent,
+                                      Box::new(CSSErrorReporterTest)))
+    };
+    let first = imported();
+    assert_eq!(import.set_stylesheet(first.clone()), Ok(()));
+    assert_eq!(parent.rules().style().count(), 1);
+
+    parent.freeze();
+    assert_eq!(import.set_stylesheet(imported()), Err(RulesMutateError::NoModificationAllowed));
+    assert!(arc_ptr_eq(&import.stylesheet().unwrap(), &first));
+}
@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use cssparser::{Parser, ToCss};
+use euclid::size::Size2D;
+use media_queries::CSSErrorReporterTest;
+use style::media_queries::{Device, MediaType};
+use style::parser::ParserContext;
+use style::servo::Stylesheet;
+use style::stylesheets::{CSSRuleIteratorExt, Origin};
+use style::supports::{SupportsCondition, supports_condition, supports_declaration};
+
+fn supports(condition: &str) -> bool {
+    let url = url!("http://localhost");
+    let context = ParserContext::new(Origin::Author, &url, Box::new(CSSErrorReporterTest));
+    supports_condition(condition, &context)
+}
+
+fn parse(condition: &str) -> Result<SupportsCondition, ()> {
+    SupportsCondition::parse(&mut Parser::new(condition))
+}
+
+#[test]
+fn test_supports_declarations() {
+    let url = url!("http://localhost");
+    let context = ParserContext::new(Origin::Author, &url, Box::new(CSSErrorReporterTest));
+    assert!(supports_declaration("color", "red", &context));
+    assert!(supports_declaration("color", "red !important", &context));
+    assert!(!supports_declaration("color", "red blue", &context));
+    assert!(!supports_declaration("colour", "red", &context));
+
+    assert!(supports("(color: red)"));
+    assert!(supports("color: red"));
+    assert!(!supports("(color: 12px)"));
+    assert!(!supports("(unknown-property: red)"));
+}
+
+#[test]
+fn test_supports_operators() {
+    assert!(supports("not (color: 12px)"));
+    assert!(!supports("not (color: red)"));
+    assert!(supports("(color: red) and (display: block)"));
+    assert!(!supports("(color: red) and (display: red)"));
+    assert!(supports("(color: 12px) or (display: block)"));
+    assert!(!supports("(color: 12px) or (display: red)"));
+    assert!(supports("((color: 12px) or (display: block)) and (not (display: red))"));
+}
+
+#[test]
+fn test_supports_general_enclosed_is_false_but_valid() {
+    assert_eq!(parse("(color: red) or selector(a > b)"),
+               Ok(SupportsCondition::Or(vec![
+                   SupportsCondition::Declaration("color".to_owned(), "red".to_owned()),
+                   SupportsCondition::FutureSyntax("selector(a > b)".to_owned()),
+               ])));
+    assert!(supports("(color: red) or selector(a > b)"));
+    assert!(!supports("(color: red) and (some future syntax)"));
+    assert!(supports("not (some future syntax)"));
+}
+
+#[test]
+fn test_supports_invalid_conditions() {
+    assert!(parse("(color: red) and (display: block) or (color: blue)").is_err());
+    assert!(parse("(color: red) (display: block)").is_err());
+    assert!(parse("not (color: red) and (display: block)").is_err());
+    assert!(parse("color").is_err());
+    assert!(!supports("(color: red) and"));
+}
+
+#[test]
+fn test_supports_condition_serialization() {
+    let condition = parse("(color:red)   and (not (display: block)) and (future syntax)").unwrap();
+    assert_eq!(condition.to_css_string(), "(color: red) and (not (display: block)) and (future syntax)");
+    assert_eq!(parse(&condition.to_css_string()), Ok(condition));
+}
+
+#[test]
+fn test_failing_supports_rules_are_kept_but_not_effective() {
+    let css = "@supports (color: red) { a { color: red } }
+               @supports (color: 12px) { b { color: red } }";
+    let sheet = Stylesheet::from_str(css, url!("http://localhost"), Origin::Author, Box::new(CSSErrorReporterTest));
+    let device = Device::new(MediaType::Screen, Size2D::typed(800.0, 600.0));
+
+    let supports_rules: Vec<_> = sheet.rules().supports().collect();
+    assert_eq!(supports_rules.len(), 2);
+    assert!(supports_rules[0].enabled);
+    assert!(!supports_rules[1].enabled);
+    assert_eq!(supports_rules[1].rules.len(), 1);
+
+    assert_eq!(sheet.rules().style().count(), 2);
+    assert_eq!(sheet.effective_rules(&device).style().count(), 1);
+}
@@ -0,0 +1,37 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::thread;
+use style::traversal_stats::{self, TraversalStatistics};
+
+fn style_one_element() {
+    traversal_stats::record(|stats| {
+        stats.elements_traversed += 1;
+        stats.elements_styled += 1;
+    });
+}
+
+// The counts are global, so everything that touches them has to be in one test.
+#[test]
+fn test_statistics_are_merged_across_threads() {
+    style_one_element();
+    traversal_stats::collect_thread_statistics();
+    assert_eq!(traversal_stats::take_collected_statistics(), TraversalStatistics::default());
+
+    traversal_stats::set_enabled(true);
+    style_one_element();
+    traversal_stats::collect_thread_statistics();
+    thread::spawn(|| {
+        style_one_element();
+        traversal_stats::record(|stats| stats.styles_shared += 1);
+        traversal_stats::collect_thread_statistics();
+    }).join().unwrap();
+    traversal_stats::set_enabled(false);
+
+    let stats = traversal_stats::take_collected_statistics();
+    assert_eq!(stats.elements_traversed, 2);
+    assert_eq!(stats.elements_styled, 2);
+    assert_eq!(stats.styles_shared, 1);
+    assert_eq!(traversal_stats::take_collected_statistics(), TraversalStatistics::default());
+}
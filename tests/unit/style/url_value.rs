@@ -0,0 +1,100 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use media_queries::CSSErrorReporterTest;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use style::properties::longhands::background_image;
+use style::properties::{DeclaredValue, PropertyDeclaration, parse_property_value};
+use style::url_value::{ComputedUrl, ImageRequest, UrlExtraData, UrlValue};
+use style::values::specified::Image;
+use util::arc_ptr_eq;
+
+/// Counts the requests a test started and how many of them were let go of.
+#[derive(Default)]
+struct Loads {
+    started: AtomicUsize,
+    released: AtomicUsize,
+}
+
+struct CountedRequest(Arc<Loads>);
+
+impl ImageRequest for CountedRequest {
+    fn as_ptr(&self) -> usize {
+        self as *const CountedRequest as usize
+    }
+}
+
+impl Drop for CountedRequest {
+    fn drop(&mut self) {
+        self.0.released.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// The url `background-image: <css>` refers to, if any.
+fn background_image(css: &str) -> Option<Arc<UrlValue>> {
+    let declarations = parse_property_value("background-image", css, &url!("http://localhost/style/"),
+                                            Box::new(CSSErrorReporterTest)).unwrap();
+    match declarations[0] {
+        PropertyDeclaration::BackgroundImage(DeclaredValue::Value(background_image::SpecifiedValue(ref images))) => {
+            match images[0] {
+                Some(Image::Url(ref url)) => Some(url.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// What computing `url` for a style amounts to, with the embedder's loads counted.
+fn compute(url: &Arc<UrlValue>, loads: &Arc<Loads>) -> ComputedUrl {
+    let request = url.image_request(|_, _| {
+        loads.started.fetch_add(1, Ordering::SeqCst);
+        Some(Box::new(CountedRequest(loads.clone())))
+    });
+    ComputedUrl::new(url.clone(), request)
+}
+
+#[test]
+fn test_url_values_resolve_against_the_sheet() {
+    let url = background_image("url(a.png)").unwrap();
+    assert_eq!(url.url().serialize(), "http://localhost/style/a.png");
+    // Style attributes and the like don't come with anything for the embedder.
+    assert!(url.extra_data().is_none());
+    assert!(background_image("none").is_none());
+}
+
+#[test]
+fn test_image_requests_last_as_long_as_the_styles_using_them() {
+    let loads = Arc::new(Loads::default());
+    let url = background_image("url(a.png)").unwrap();
+
+    // none -> url starts a load.
+    let first = compute(&url, &loads);
+    assert_eq!(loads.started.load(Ordering::SeqCst), 1);
+    assert!(first.image_request().is_some());
+
+    // Restyling while the old style is still around shares its request.
+    let second = compute(&url, &loads);
+    assert_eq!(loads.started.load(Ordering::SeqCst), 1);
+    assert!(arc_ptr_eq(first.image_request().unwrap(), second.image_request().unwrap()));
+    drop(first);
+    assert_eq!(loads.released.load(Ordering::SeqCst), 0);
+
+    // url -> none lets go of it once the last style is gone...
+    drop(second);
+    assert_eq!(loads.released.load(Ordering::SeqCst), 1);
+
+    // ...and none -> url loads it again.
+    let third = compute(&url, &loads);
+    assert_eq!(loads.started.load(Ordering::SeqCst), 2);
+    drop(third);
+    assert_eq!(loads.released.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_urls_the_embedder_does_not_load_have_no_request() {
+    let url = Arc::new(UrlValue::new(url!("http://localhost/a.png"), UrlExtraData::none()));
+    assert!(url.image_request(|_, _| None).is_none());
+}